@@ -0,0 +1,124 @@
+// Camera-relative rain and snow
+//
+// Every quad's vertex position holds a random seed in [0, 1)^3. The particle's world
+// position is a pure function of that seed, time, wind and the camera, wrapped inside a
+// box that follows the camera, so the simulation runs entirely on the GPU without any
+// per-particle state. Particles below the occlusion map height (terrain, bridges,
+// overhangs) collapse to a degenerate quad.
+
+#import bevy_pbr::mesh_view_bindings::view
+
+struct PrecipitationParams {
+    camera_position: vec3<f32>,
+    time: f32,
+    wind: vec3<f32>,
+    intensity: f32,
+    volume_size: vec3<f32>,
+    fall_speed: f32,
+    color: vec4<f32>,
+    occlusion_origin: vec2<f32>,
+    occlusion_cell_size: f32,
+    streak_length: f32,
+    particle_size: f32,
+    kind: u32,              // 0 = rain, 1 = snow
+}
+
+@group(1) @binding(0) var<uniform> params: PrecipitationParams;
+@group(1) @binding(1) var occlusion_map: texture_2d<f32>;
+
+struct Vertex {
+    @location(0) seed: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) fade: f32,
+}
+
+const KIND_SNOW: u32 = 1u;
+
+fn hash(seed: vec3<f32>) -> f32 {
+    return fract(sin(dot(seed, vec3(12.9898, 78.233, 37.719))) * 43758.5453);
+}
+
+fn occlusion_height(position: vec3<f32>) -> f32 {
+    let size = vec2<i32>(textureDimensions(occlusion_map));
+    let cell = vec2<i32>(floor((position.xz - params.occlusion_origin) / params.occlusion_cell_size));
+    if (any(cell < vec2(0)) || any(cell >= size)) {
+        return -1.0e6;
+    }
+    return textureLoad(occlusion_map, cell, 0).r;
+}
+
+fn culled() -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4(0.0, 0.0, 0.0, 1.0);
+    out.uv = vec2(0.0);
+    out.fade = 0.0;
+    return out;
+}
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    // Thin the particle budget with intensity
+    if (hash(vertex.seed) >= params.intensity) {
+        return culled();
+    }
+
+    let speed = params.fall_speed * (0.8 + 0.4 * vertex.seed.y);
+    var velocity = params.wind + vec3(0.0, -speed, 0.0);
+    var drift = velocity * params.time;
+    if (params.kind == KIND_SNOW) {
+        // Flakes flutter around their fall path
+        let phase = params.time * (1.0 + vertex.seed.x) + vertex.seed.z * 6.283;
+        drift += vec3(sin(phase), 0.0, cos(phase * 0.7)) * 0.4;
+    }
+
+    // Wrap inside the box centered on the camera
+    let unwrapped = vertex.seed * params.volume_size + drift;
+    let local = (fract((unwrapped - params.camera_position) / params.volume_size) - 0.5) * params.volume_size;
+    let center = params.camera_position + local;
+
+    if (center.y < occlusion_height(center)) {
+        return culled();
+    }
+
+    let to_camera = normalize(view.world_position - center);
+    var axis: vec3<f32>;
+    var half_length: f32;
+    if (params.kind == KIND_SNOW) {
+        axis = normalize(cross(to_camera, cross(vec3(0.0, 1.0, 0.0), to_camera)));
+        half_length = params.particle_size;
+    } else {
+        // Rain is stretched along its velocity into a streak
+        axis = normalize(velocity);
+        half_length = max(length(velocity) * params.streak_length, params.particle_size);
+    }
+    let side = normalize(cross(axis, to_camera));
+
+    let corner = vertex.uv * 2.0 - 1.0;
+    let world = center + side * corner.x * params.particle_size + axis * corner.y * half_length;
+
+    var out: VertexOutput;
+    out.clip_position = view.view_proj * vec4(world, 1.0);
+    out.uv = vertex.uv;
+    // Fade out towards the edges of the volume so wrapping is invisible
+    let edge = max(abs(local.x) / params.volume_size.x, abs(local.z) / params.volume_size.z) * 2.0;
+    out.fade = 1.0 - smoothstep(0.7, 1.0, edge);
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let centered = in.uv * 2.0 - 1.0;
+    var alpha: f32;
+    if (params.kind == KIND_SNOW) {
+        alpha = 1.0 - smoothstep(0.5, 1.0, length(centered));
+    } else {
+        // Thin streak, brightest in the middle
+        alpha = (1.0 - abs(centered.x)) * (1.0 - centered.y * centered.y);
+    }
+    return vec4(params.color.rgb, params.color.a * alpha * in.fade);
+}
@@ -1,26 +1,268 @@
-struct TerrainMaterial {
-    height_scale: f32,
+// Terrain splatting, extends the standard PBR material
+//
+// Up to eight ground layers are blended per pixel: painted splat map weights first, with the
+// height and slope rules filling whatever the painting leaves. Inside the baked biome map the rules
+// are scaled by the biome's layer affinity, with some cover growing regardless of height and slope,
+// so a desert turns sandy on ground the rules alone would grass over. Layer textures are projected
+// from above on gentle ground and triplanar on steep faces so cliffs don't stretch.
+// Snow then settles on flat ground first and only reaches the steeper slopes as coverage builds,
+// with drift noise in world space breaking up the edge so partial cover reads as patches.
+// Last, cloud shadows are traced up along the sun to the cloud layer and take the sunlight off the
+// ground under them, scrolling with the wind in step with the volumetric clouds.
+
+#import bevy_pbr::{
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::{alpha_discard, apply_pbr_lighting, main_pass_post_lighting_processing},
+    forward_io::{VertexOutput, FragmentOutput},
+    mesh_view_bindings::lights,
+}
+
+const MAX_LAYERS: u32 = 8u;
+
+struct TerrainSnow {
+    snow_color: vec4<f32>,
+    coverage: f32,
+    slope_start: f32,
+    slope_end: f32,
+    noise_scale: f32,
+}
+
+struct TerrainLayer {
+    color: vec4<f32>,
+    height_min: f32,
+    height_max: f32,
+    slope_min: f32,
+    slope_max: f32,
+    height_blend: f32,
+    slope_blend: f32,
     texture_scale: f32,
-    normal_strength: f32,
-};
+    roughness: f32,
+    detail_strength: f32,
+}
 
-@group(1) @binding(0)
-var height_map: texture_2d<f32>;
-@group(1) @binding(1)
-var height_sampler: sampler;
+struct TerrainSplat {
+    layers: array<TerrainLayer, 8>,
+    layer_count: u32,
+    splat_origin: vec2<f32>,
+    splat_size: f32,
+    biome_origin: vec2<f32>,
+    biome_size: f32,
+    biome_cover: f32,
+    triplanar_start: f32,
+    triplanar_sharpness: f32,
+}
 
-@fragment
-fn fragment(
-    @location(0) world_position: vec3<f32>,
-    @location(1) world_normal: vec3<f32>,
-    @location(2) uv: vec2<f32>,
-) -> @location(0) vec4<f32> {
-    let height = textureSample(height_map, height_sampler, uv).r;
-    let color = mix(
-        vec3<f32>(0.2, 0.5, 0.1), // grass color
-        vec3<f32>(0.6, 0.5, 0.4), // dirt color
-        smoothstep(0.3, 0.7, height)
+struct CloudShadow {
+    scroll: vec2<f32>,
+    scale: f32,
+    coverage: f32,
+    softness: f32,
+    strength: f32,
+    altitude: f32,
+}
+
+@group(1) @binding(100) var<uniform> snow: TerrainSnow;
+@group(1) @binding(101) var<uniform> splat: TerrainSplat;
+@group(1) @binding(102) var layer_textures: texture_2d_array<f32>;
+@group(1) @binding(103) var layer_sampler: sampler;
+@group(1) @binding(104) var splat_map_0: texture_2d<f32>;
+@group(1) @binding(105) var splat_sampler_0: sampler;
+@group(1) @binding(106) var splat_map_1: texture_2d<f32>;
+@group(1) @binding(107) var splat_sampler_1: sampler;
+@group(1) @binding(108) var<uniform> clouds: CloudShadow;
+@group(1) @binding(109) var cloud_texture: texture_2d<f32>;
+@group(1) @binding(110) var cloud_sampler: sampler;
+@group(1) @binding(111) var biome_map_0: texture_2d<f32>;
+@group(1) @binding(112) var biome_sampler_0: sampler;
+@group(1) @binding(113) var biome_map_1: texture_2d<f32>;
+@group(1) @binding(114) var biome_sampler_1: sampler;
+
+fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(127.1, 311.7))) * 43758.5453);
+}
+
+fn value_noise(p: vec2<f32>) -> f32 {
+    let cell = floor(p);
+    let f = fract(p);
+    let u = f * f * (3.0 - 2.0 * f);
+    return mix(
+        mix(hash(cell), hash(cell + vec2<f32>(1.0, 0.0)), u.x),
+        mix(hash(cell + vec2<f32>(0.0, 1.0)), hash(cell + vec2<f32>(1.0, 1.0)), u.x),
+        u.y,
+    );
+}
+
+fn fbm(p: vec2<f32>) -> f32 {
+    var value = 0.0;
+    var amplitude = 0.5;
+    var q = p;
+    for (var i = 0; i < 4; i++) {
+        value += value_noise(q) * amplitude;
+        q *= 2.03;
+        amplitude *= 0.5;
+    }
+    return value;
+}
+
+// Inside [min, max] with soft edges of `blend` either side
+fn band(value: f32, min_value: f32, max_value: f32, blend: f32) -> f32 {
+    let soft = max(blend, 1.0e-4);
+    return smoothstep(min_value - soft, min_value + soft, value)
+        * (1.0 - smoothstep(max_value - soft, max_value + soft, value));
+}
+
+// Mirrors `TerrainLayerUniform::rule_weight`
+fn rule_weight(layer: TerrainLayer, height: f32, slope: f32) -> f32 {
+    return band(height, layer.height_min, layer.height_max, layer.height_blend)
+        * band(slope, layer.slope_min, layer.slope_max, layer.slope_blend);
+}
+
+// Mirrors `SplatUniform::weights_in_biome`, and `SplatUniform::weights` with full affinity and no cover
+fn splat_weights(
+    height: f32,
+    slope: f32,
+    painted: array<f32, 8>,
+    affinity: array<f32, 8>,
+    cover: f32,
+) -> array<f32, 8> {
+    let count = min(splat.layer_count, MAX_LAYERS);
+    var rules: array<f32, 8>;
+    var rule_total = 0.0;
+    for (var i = 0u; i < count; i++) {
+        rules[i] = (rule_weight(splat.layers[i], height, slope) + cover) * affinity[i];
+        rule_total += rules[i];
+    }
+    if rule_total <= 1.0e-4 {
+        // Nothing matches, fall back to the first layer
+        rules[0] = 1.0;
+        rule_total = 1.0;
+    }
+
+    var painted_total = 0.0;
+    for (var i = 0u; i < count; i++) {
+        painted_total += painted[i];
+    }
+    painted_total = min(painted_total, 1.0);
+
+    var weights: array<f32, 8>;
+    var total = 0.0;
+    for (var i = 0u; i < count; i++) {
+        weights[i] = painted[i] + rules[i] / rule_total * (1.0 - painted_total);
+        total += weights[i];
+    }
+    for (var i = 0u; i < count; i++) {
+        weights[i] /= total;
+    }
+    return weights;
+}
+
+// Painted weights at a world position, zero outside the splat maps or while nothing is painted
+fn painted_weights(world: vec3<f32>) -> array<f32, 8> {
+    let uv = (world.xz - splat.splat_origin) / max(splat.splat_size, 1.0e-4);
+    // Sampled either way, texture reads have to stay in uniform control flow
+    let first = textureSample(splat_map_0, splat_sampler_0, uv);
+    let second = textureSample(splat_map_1, splat_sampler_1, uv);
+    let inside = all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0)) && splat.splat_size > 0.0;
+    let mask = select(0.0, 1.0, inside);
+    return array<f32, 8>(
+        first.r * mask, first.g * mask, first.b * mask, first.a * mask,
+        second.r * mask, second.g * mask, second.b * mask, second.a * mask,
     );
-    
-    return vec4<f32>(color, 1.0);
-} 
\ No newline at end of file
+}
+
+// Biome layer affinity at a world position, full for every layer outside the biome map or before it's baked
+fn biome_affinity(world: vec3<f32>) -> array<f32, 8> {
+    let uv = (world.xz - splat.biome_origin) / max(splat.biome_size, 1.0e-4);
+    let first = textureSample(biome_map_0, biome_sampler_0, uv);
+    let second = textureSample(biome_map_1, biome_sampler_1, uv);
+    let inside = biome_inside(world);
+    return array<f32, 8>(
+        mix(1.0, first.r, inside), mix(1.0, first.g, inside), mix(1.0, first.b, inside), mix(1.0, first.a, inside),
+        mix(1.0, second.r, inside), mix(1.0, second.g, inside), mix(1.0, second.b, inside), mix(1.0, second.a, inside),
+    );
+}
+
+// 1.0 inside the baked biome map, 0.0 outside it
+fn biome_inside(world: vec3<f32>) -> f32 {
+    let uv = (world.xz - splat.biome_origin) / max(splat.biome_size, 1.0e-4);
+    let inside = all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0)) && splat.biome_size > 0.0;
+    return select(0.0, 1.0, inside);
+}
+
+// Mirrors `CloudShadowUniform::shadow`
+fn cloud_shadow(density: f32) -> f32 {
+    let start = 1.0 - clouds.coverage;
+    let t = saturate((density - start) / max(clouds.softness, 1.0e-3));
+    return smoothstep(0.0, 1.0, t) * clouds.strength * step(1.0e-4, clouds.coverage);
+}
+
+// Share of the light a point loses to the clouds between it and the sun
+fn cloud_shade(world: vec3<f32>, normal: vec3<f32>) -> f32 {
+    let to_sun = lights.directional_lights[0].direction_to_light;
+    // Low sun stretches the shadows, clamped so they don't run off to infinity at sunset
+    let rise = max(clouds.altitude - world.y, 0.0) / max(to_sun.y, 0.1);
+    let cloud_point = world.xz + to_sun.xz * rise - clouds.scroll;
+    let density = textureSample(cloud_texture, cloud_sampler, cloud_point * clouds.scale).r;
+    // Faces turned from the sun only had sky light to begin with
+    let sunlit = saturate(dot(normal, to_sun)) * select(0.0, 1.0, lights.n_directional_lights > 0u);
+    return cloud_shadow(density) * sunlit;
+}
+
+// Layer texture projected from above, plus from the sides on faces steeper than `triplanar_start`
+fn layer_detail(index: u32, world: vec3<f32>, projection: vec3<f32>) -> f32 {
+    let scale = splat.layers[index].texture_scale;
+    let top = textureSample(layer_textures, layer_sampler, world.xz * scale, index).r;
+    let side_x = textureSample(layer_textures, layer_sampler, world.zy * scale, index).r;
+    let side_z = textureSample(layer_textures, layer_sampler, world.xy * scale, index).r;
+    return dot(projection, vec3<f32>(side_x, top, side_z));
+}
+
+@fragment
+fn fragment(in: VertexOutput, @builtin(front_facing) is_front: bool) -> FragmentOutput {
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+    let normal = normalize(in.world_normal);
+    let slope = 1.0 - saturate(normal.y);
+    let world = in.world_position.xyz;
+
+    // Side projections fade in past the triplanar start, gentle ground only reads from above
+    var projection = pow(abs(normal), vec3<f32>(splat.triplanar_sharpness));
+    let side = smoothstep(splat.triplanar_start, splat.triplanar_start + 0.1, slope);
+    projection = vec3<f32>(projection.x * side, projection.y + 1.0e-4, projection.z * side);
+    projection /= projection.x + projection.y + projection.z;
+
+    let cover = splat.biome_cover * biome_inside(world);
+    let weights = splat_weights(world.y, slope, painted_weights(world), biome_affinity(world), cover);
+    var layer_color = vec3<f32>(0.0);
+    var roughness = 0.0;
+    for (var i = 0u; i < MAX_LAYERS; i++) {
+        let layer = splat.layers[i];
+        let detail = layer_detail(i, world, projection);
+        layer_color += weights[i] * layer.color.rgb * mix(1.0, detail * 2.0, layer.detail_strength);
+        roughness += weights[i] * layer.roughness;
+    }
+    let ground = layer_color * pbr_input.material.base_color.rgb;
+    pbr_input.material.base_color = vec4<f32>(ground, pbr_input.material.base_color.a);
+    pbr_input.material.perceptual_roughness = roughness;
+
+    // Steep ground holds less snow, so it needs more coverage before it turns white
+    let holding = 1.0 - smoothstep(snow.slope_start, snow.slope_end, slope);
+    let drift = fbm(world.xz * snow.noise_scale);
+    let threshold = 1.0 - snow.coverage * holding;
+    let amount = smoothstep(threshold - 0.1, threshold + 0.1, drift + 0.05) * step(0.001, snow.coverage);
+
+    let color = mix(pbr_input.material.base_color.rgb, snow.snow_color.rgb, amount * snow.snow_color.a);
+    pbr_input.material.base_color = vec4<f32>(color, pbr_input.material.base_color.a);
+    // Snow is matte whatever the ground under it was
+    pbr_input.material.perceptual_roughness = mix(pbr_input.material.perceptual_roughness, 0.75, amount);
+    pbr_input.material.metallic = mix(pbr_input.material.metallic, 0.0, amount);
+
+    let shade = cloud_shade(world, normal);
+
+    var out: FragmentOutput;
+    out.color = apply_pbr_lighting(pbr_input);
+    out.color = vec4<f32>(out.color.rgb * (1.0 - shade), out.color.a);
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+    return out;
+}
@@ -0,0 +1,221 @@
+// Vehicle body dirt layer, extends the standard PBR material
+//
+// Mud collects below the mud line with a ragged splash edge, dust forms a thin film over
+// the whole body that is heavier toward the bottom, and wet dirt goes darker and glossier.
+// Breakup noise is in UV space so the pattern sticks to the body as it moves.
+//
+// Paint showing through the dirt reflects its surroundings: screen-space reflections traced against
+// the depth prepass where the camera has one, and the reflection probe around the vehicle where the
+// rays leave the screen or miss.
+
+#import bevy_pbr::{
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::{alpha_discard, apply_pbr_lighting, main_pass_post_lighting_processing},
+    forward_io::{VertexOutput, FragmentOutput},
+    mesh_view_bindings::view,
+}
+
+#ifdef DEPTH_PREPASS
+#import bevy_pbr::prepass_utils
+#endif
+
+struct VehicleDirt {
+    mud_color: vec4<f32>,
+    dust_color: vec4<f32>,
+    body_origin: vec3<f32>,
+    body_height: f32,
+    body_up: vec3<f32>,
+    mud: f32,
+    dust: f32,
+    mud_line: f32,
+    wetness: f32,
+    noise_scale: f32,
+}
+
+struct PaintReflection {
+    strength: f32,
+    ssr_max_distance: f32,
+    ssr_thickness: f32,
+    ssr_steps: u32,
+    probe_ready: u32,
+    screen_space: u32,
+}
+
+@group(1) @binding(100) var<uniform> dirt: VehicleDirt;
+@group(1) @binding(101) var<uniform> reflection: PaintReflection;
+@group(1) @binding(102) var probe_texture: texture_2d<f32>;
+@group(1) @binding(103) var probe_sampler: sampler;
+@group(1) @binding(104) var scene_color_texture: texture_2d<f32>;
+@group(1) @binding(105) var scene_color_sampler: sampler;
+
+fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(127.1, 311.7))) * 43758.5453);
+}
+
+fn value_noise(p: vec2<f32>) -> f32 {
+    let cell = floor(p);
+    let f = fract(p);
+    let u = f * f * (3.0 - 2.0 * f);
+    return mix(
+        mix(hash(cell), hash(cell + vec2<f32>(1.0, 0.0)), u.x),
+        mix(hash(cell + vec2<f32>(0.0, 1.0)), hash(cell + vec2<f32>(1.0, 1.0)), u.x),
+        u.y,
+    );
+}
+
+fn fbm(p: vec2<f32>) -> f32 {
+    var value = 0.0;
+    var amplitude = 0.5;
+    var q = p;
+    for (var i = 0; i < 4; i++) {
+        value += value_noise(q) * amplitude;
+        q *= 2.03;
+        amplitude *= 0.5;
+    }
+    return value;
+}
+
+// Atlas coordinates of the probe in `direction`, matches probe_atlas_uv on the CPU side.
+// Faces are +X, -X, +Y on the top row and -Y, +Z, -Z below.
+fn probe_atlas_uv(direction: vec3<f32>) -> vec2<f32> {
+    let a = abs(direction);
+    var face = 0u;
+    var forward = vec3<f32>(1.0, 0.0, 0.0);
+    var up = vec3<f32>(0.0, 1.0, 0.0);
+    if (a.x >= a.y && a.x >= a.z) {
+        face = select(1u, 0u, direction.x > 0.0);
+        forward = vec3<f32>(sign(direction.x), 0.0, 0.0);
+    } else if (a.y >= a.z) {
+        face = select(3u, 2u, direction.y > 0.0);
+        forward = vec3<f32>(0.0, sign(direction.y), 0.0);
+        up = vec3<f32>(0.0, 0.0, sign(direction.y));
+    } else {
+        face = select(5u, 4u, direction.z > 0.0);
+        forward = vec3<f32>(0.0, 0.0, sign(direction.z));
+    }
+    let right = cross(forward, up);
+    let local = vec2<f32>(dot(direction, right), dot(direction, up)) / dot(direction, forward);
+    // Half a texel in from the edges so filtering doesn't pick up the neighbouring face
+    let inset = 1.5 / f32(textureDimensions(probe_texture).x);
+    let face_uv = clamp(vec2<f32>(0.5 + 0.5 * local.x, 0.5 - 0.5 * local.y), vec2<f32>(inset), vec2<f32>(1.0 - inset));
+    let cell = vec2<f32>(f32(face % 3u), f32(face / 3u));
+    return (cell + face_uv) / vec2<f32>(3.0, 2.0);
+}
+
+#ifdef DEPTH_PREPASS
+// View space distance of a reverse-z perspective depth value
+fn linear_depth(depth: f32) -> f32 {
+    return view.projection[3][2] / max(depth, 0.000001);
+}
+
+// Marches from `origin` along `direction` against the depth prepass, returns the scene color where
+// it hits and how much to trust it in alpha
+fn trace_screen_space(origin: vec3<f32>, direction: vec3<f32>) -> vec4<f32> {
+    let steps = max(reflection.ssr_steps, 1u);
+    let step_length = reflection.ssr_max_distance / f32(steps);
+    let scene_size = vec2<f32>(textureDimensions(scene_color_texture));
+    // Half a step out so the ray doesn't hit the body it leaves
+    var distance = step_length * 0.5;
+    for (var i = 0u; i < steps; i++) {
+        let clip = view.view_proj * vec4<f32>(origin + direction * distance, 1.0);
+        if (clip.w <= 0.0) {
+            break;
+        }
+        let ndc = clip.xy / clip.w;
+        let uv = vec2<f32>(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+        if (any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0))) {
+            break;
+        }
+        let pixel = view.viewport.xy + uv * view.viewport.zw;
+        let scene_depth = linear_depth(prepass_utils::prepass_depth(vec4<f32>(pixel, 0.0, 0.0), 0u));
+        let behind = clip.w - scene_depth;
+        if (behind > 0.0 && behind < reflection.ssr_thickness) {
+            // Fade out toward the screen edges and the end of the ray, where the probe takes over
+            let edge = min(min(uv.x, 1.0 - uv.x), min(uv.y, 1.0 - uv.y));
+            let confidence = saturate(edge * 10.0) * (1.0 - f32(i) / f32(steps));
+            let color = textureSampleLevel(scene_color_texture, scene_color_sampler, pixel / scene_size, 0.0).rgb;
+            return vec4<f32>(color, confidence);
+        }
+        distance += step_length;
+    }
+    return vec4<f32>(0.0);
+}
+#endif
+
+// Light reflected off the paint toward the camera, on top of the regular lighting
+fn paint_reflection(position: vec3<f32>, normal: vec3<f32>, view_dir: vec3<f32>, base_color: vec3<f32>,
+                    metallic: f32, roughness: f32) -> vec3<f32> {
+    let reflected = reflect(-view_dir, normal);
+    var color = vec3<f32>(0.0);
+    var weight = 0.0;
+    if (reflection.probe_ready != 0u) {
+        color = textureSampleLevel(probe_texture, probe_sampler, probe_atlas_uv(reflected), 0.0).rgb;
+        weight = 1.0;
+    }
+#ifdef DEPTH_PREPASS
+    if (reflection.screen_space != 0u) {
+        let hit = trace_screen_space(position, reflected);
+        color = mix(color, hit.rgb, hit.a);
+        weight = max(weight, hit.a);
+    }
+#endif
+
+    // Schlick fresnel, dielectric clear coat for paint and the base color for chrome
+    let f0 = mix(vec3<f32>(0.04), base_color, metallic);
+    let fresnel = f0 + (1.0 - f0) * pow(1.0 - saturate(dot(normal, view_dir)), 5.0);
+    // Without blurred probe mips rough surfaces just reflect less
+    let gloss = (1.0 - roughness) * (1.0 - roughness);
+    return color * fresnel * gloss * weight * reflection.strength;
+}
+
+@fragment
+fn fragment(in: VertexOutput, @builtin(front_facing) is_front: bool) -> FragmentOutput {
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+    // Height up the body, 0 at the bottom and 1 at the roof
+    let height = dot(in.world_position.xyz - dirt.body_origin, dirt.body_up) / max(dirt.body_height, 0.01);
+
+#ifdef VERTEX_UVS
+    let noise_uv = in.uv * dirt.noise_scale;
+#else
+    let noise_uv = in.world_position.xz * 4.0;
+#endif
+    let noise = fbm(noise_uv);
+
+    // Mud: solid below the line, splattered above it
+    let splash_edge = dirt.mud_line + (noise - 0.5) * 0.15;
+    let below_line = 1.0 - smoothstep(splash_edge - 0.05, splash_edge + 0.05, height);
+    let mud = saturate(below_line * smoothstep(1.0 - dirt.mud, 1.0 - dirt.mud + 0.2, noise + 0.3));
+
+    // Dust: even film, heavier low on the body, patchy from the noise
+    let dust = saturate(dirt.dust * (0.6 + 0.4 * (1.0 - saturate(height))) * (0.7 + 0.6 * noise));
+
+    // Wet dirt is darker
+    let wet_darkening = 1.0 - dirt.wetness * 0.4;
+    var color = pbr_input.material.base_color.rgb;
+    color = mix(color, dirt.dust_color.rgb * wet_darkening, dust * dirt.dust_color.a);
+    color = mix(color, dirt.mud_color.rgb * wet_darkening, mud * dirt.mud_color.a);
+    pbr_input.material.base_color = vec4<f32>(color, pbr_input.material.base_color.a);
+
+    // Dirt kills paint gloss and metalness, water brings some of the shine back
+    let coverage = max(mud, dust);
+    let dirt_roughness = mix(0.95, 0.35, dirt.wetness);
+    pbr_input.material.perceptual_roughness = mix(pbr_input.material.perceptual_roughness, dirt_roughness, coverage);
+    pbr_input.material.metallic = mix(pbr_input.material.metallic, 0.0, coverage);
+
+    var out: FragmentOutput;
+    out.color = apply_pbr_lighting(pbr_input);
+    // Dirt has already taken the gloss off where it covers the paint
+    let reflected = paint_reflection(
+        pbr_input.world_position.xyz,
+        pbr_input.N,
+        pbr_input.V,
+        pbr_input.material.base_color.rgb,
+        pbr_input.material.metallic,
+        pbr_input.material.perceptual_roughness,
+    );
+    out.color = vec4<f32>(out.color.rgb + reflected, out.color.a);
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+    return out;
+}
@@ -0,0 +1,123 @@
+// Water surface shader for streams, puddles and mud pits
+//
+// - Two normal map samples scroll along the surface flow to animate ripples
+// - Water thickness comes from the depth prepass: thin water is clear and foamy, thick water
+//   absorbs toward the deep color
+// - Reflections use the planar reflection texture in screen space when this surface is the
+//   reflected one, otherwise a flat sky color
+
+#import bevy_pbr::{
+    forward_io::VertexOutput,
+    mesh_view_bindings::{view, globals, lights},
+}
+
+#ifdef DEPTH_PREPASS
+#import bevy_pbr::prepass_utils
+#endif
+
+struct WaterParams {
+    shallow_color: vec4<f32>,
+    deep_color: vec4<f32>,
+    foam_color: vec4<f32>,
+    sky_color: vec4<f32>,
+    flow: vec2<f32>,
+    normal_tiling: f32,
+    normal_strength: f32,
+    absorption_depth: f32,
+    foam_width: f32,
+    reflection_strength: f32,
+    distortion: f32,
+    planar_reflection: u32,
+    _padding: vec3<f32>,
+}
+
+@group(1) @binding(0) var<uniform> water: WaterParams;
+@group(1) @binding(1) var normal_map: texture_2d<f32>;
+@group(1) @binding(2) var normal_sampler: sampler;
+@group(1) @binding(3) var reflection_texture: texture_2d<f32>;
+@group(1) @binding(4) var reflection_sampler: sampler;
+
+// Still water still gets a slow drift so puddles shimmer
+const IDLE_DRIFT: vec2<f32> = vec2<f32>(0.02, 0.015);
+
+fn sample_normal(uv: vec2<f32>) -> vec4<f32> {
+    let texel = textureSample(normal_map, normal_sampler, uv);
+    return vec4<f32>(texel.xyz * 2.0 - 1.0, texel.a);
+}
+
+// View space distance of a reverse-z perspective depth value
+fn linear_depth(depth: f32) -> f32 {
+    return view.projection[3][2] / max(depth, 0.000001);
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let time = globals.time;
+    let tile = 1.0 / water.normal_tiling;
+    let drift = (water.flow + IDLE_DRIFT) * tile * time;
+
+    // Two layers at different scales crossing each other hide the tiling
+    let uv = in.world_position.xz * tile;
+    let layer_a = sample_normal(uv + drift);
+    let layer_b = sample_normal(uv * 0.63 + vec2<f32>(0.37, 0.11) - drift.yx * 0.7);
+    let tangent_normal = normalize(vec3<f32>(
+        (layer_a.x + layer_b.x) * water.normal_strength,
+        (layer_a.y + layer_b.y) * water.normal_strength,
+        1.0,
+    ));
+    // Tangent space z maps to world up on a flat surface
+    let normal = normalize(vec3<f32>(tangent_normal.x, tangent_normal.z, tangent_normal.y));
+
+    let view_dir = normalize(view.world_position.xyz - in.world_position.xyz);
+    let n_dot_v = max(dot(normal, view_dir), 0.0);
+
+    // Schlick fresnel with the reflectance of water at normal incidence
+    let fresnel = 0.02 + 0.98 * pow(1.0 - n_dot_v, 5.0);
+
+    // Thickness of the water between the surface and whatever is behind it
+    var thickness = water.absorption_depth * 4.0;
+#ifdef DEPTH_PREPASS
+    let scene_depth = prepass_utils::prepass_depth(in.position, 0u);
+    thickness = max(linear_depth(scene_depth) - linear_depth(in.position.z), 0.0);
+#endif
+
+    let absorption = 1.0 - exp(-thickness / max(water.absorption_depth, 0.001));
+    var color = mix(water.shallow_color.rgb, water.deep_color.rgb, absorption);
+    var alpha = mix(water.shallow_color.a, water.deep_color.a, absorption);
+
+    // Reflection
+    var reflection = water.sky_color.rgb;
+    if (water.planar_reflection != 0u) {
+        let screen_uv = in.position.xy / view.viewport.zw;
+        // The mirrored camera renders upside down relative to the surface
+        let reflect_uv = vec2<f32>(screen_uv.x, 1.0 - screen_uv.y) + normal.xz * water.distortion;
+        reflection = textureSample(reflection_texture, reflection_sampler, clamp(reflect_uv, vec2<f32>(0.001), vec2<f32>(0.999))).rgb;
+    } else {
+        // Brighten toward the horizon where the sky is brightest
+        let reflected = reflect(-view_dir, normal);
+        reflection = mix(water.sky_color.rgb * 1.2, water.sky_color.rgb * 0.8, saturate(reflected.y));
+    }
+    let reflectance = saturate(fresnel * water.reflection_strength);
+    color = mix(color, reflection, reflectance);
+    alpha = max(alpha, reflectance);
+
+    // Sun glint
+    if (lights.n_directional_lights > 0u) {
+        let sun = lights.directional_lights[0];
+        let half_vector = normalize(sun.direction_to_light + view_dir);
+        let glint = pow(max(dot(normal, half_vector), 0.0), 256.0) * water.reflection_strength;
+        color += sun.color.rgb * glint * 0.0005;
+        alpha = max(alpha, saturate(glint));
+    }
+
+    // Shoreline foam, broken up by the noise in the normal map alpha
+    if (water.foam_width > 0.0) {
+        let edge = 1.0 - saturate(thickness / water.foam_width);
+        let noise = (layer_a.a + layer_b.a) * 0.5 * 0.5 + 0.5;
+        let foam = smoothstep(0.35, 0.65, edge * noise + edge * 0.5) * water.foam_color.a;
+        color = mix(color, water.foam_color.rgb, foam);
+        alpha = max(alpha, foam);
+    }
+
+    return vec4<f32>(color, alpha);
+}
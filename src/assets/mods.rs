@@ -0,0 +1,433 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bevy::asset::io::file::FileAssetReader;
+use bevy::asset::io::{AssetReader, AssetReaderError, AssetSource, AssetSourceId, PathStream, Reader};
+use bevy::prelude::*;
+use bevy::tasks::futures_lite::{stream, StreamExt};
+use bevy::utils::BoxedFuture;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::schema::{check_schema_version, default_schema_version, read_schema_version, SchemaVersionError};
+
+/// Folder data packs are discovered in, next to `assets`
+pub const MODS_DIRECTORY: &str = "mods";
+/// Manifest file at the root of every data pack
+pub const MOD_MANIFEST: &str = "mod.json";
+
+/// `mod.json` describing a data pack
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModManifest {
+    #[serde(default = "default_schema_version")]
+    pub schema_version: u32,
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Packs with a higher load order are layered on top of lower ones, ties go by folder name
+    #[serde(default)]
+    pub load_order: i32,
+}
+
+/// Kind of content a data pack file is, from the folder it sits in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ModContent {
+    Vehicle,
+    Level,
+    Texture,
+    Sound,
+    Other,
+}
+
+impl ModContent {
+    pub fn of(path: &Path) -> Self {
+        match path.components().next().and_then(|component| component.as_os_str().to_str()) {
+            Some("vehicles") => Self::Vehicle,
+            Some("levels") => Self::Level,
+            Some("textures") => Self::Texture,
+            Some("sounds" | "audio") => Self::Sound,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// A data pack found in the mods folder
+#[derive(Debug, Clone)]
+pub struct DataPack {
+    /// Folder name, unique among packs
+    pub id: String,
+    pub manifest: ModManifest,
+    pub root: PathBuf,
+    /// Asset paths the pack provides, relative to its root, sorted
+    pub files: Vec<PathBuf>,
+}
+
+impl DataPack {
+    /// Number of files of each kind of content
+    pub fn content(&self) -> BTreeMap<ModContent, usize> {
+        let mut content = BTreeMap::new();
+        for file in &self.files {
+            *content.entry(ModContent::of(file)).or_default() += 1;
+        }
+        content
+    }
+}
+
+/// A file more than one data pack provides
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModConflict {
+    pub path: PathBuf,
+    /// Ids of the packs providing it, in load order
+    pub packs: Vec<String>,
+}
+
+impl ModConflict {
+    /// The pack whose file is used, the last one loaded
+    pub fn winner(&self) -> &str {
+        self.packs.last().map_or("", String::as_str)
+    }
+}
+
+/// Errors produced while reading a data pack, which is then skipped
+#[derive(Error, Debug)]
+pub enum ModLoadError {
+    #[error("{}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    #[error("{}: {source}", path.display())]
+    Manifest { path: PathBuf, source: serde_json::Error },
+    #[error("{}: {source}", path.display())]
+    Schema { path: PathBuf, source: SchemaVersionError },
+}
+
+/// Data packs layered over the base assets, in load order
+#[derive(Resource, Debug, Default)]
+pub struct DataPacks {
+    pub packs: Vec<DataPack>,
+    pub conflicts: Vec<ModConflict>,
+    /// Folders in the mods folder that couldn't be loaded
+    pub errors: Vec<ModLoadError>,
+}
+
+impl DataPacks {
+    /// Finds the data packs in `directory` and puts them in load order
+    pub fn discover(directory: &Path) -> Self {
+        let Ok(entries) = fs::read_dir(directory) else {
+            return Self::default();
+        };
+
+        let mut packs = Vec::new();
+        let mut errors = Vec::new();
+        for root in entries.flatten().map(|entry| entry.path()).filter(|path| path.is_dir()) {
+            match load_data_pack(&root) {
+                Ok(pack) => packs.push(pack),
+                Err(error) => errors.push(error),
+            }
+        }
+        packs.sort_by(|a, b| {
+            a.manifest
+                .load_order
+                .cmp(&b.manifest.load_order)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        let conflicts = find_conflicts(&packs);
+        Self { packs, conflicts, errors }
+    }
+
+    /// The pack whose copy of `path` is used, if any pack provides it
+    pub fn provider(&self, path: &Path) -> Option<&DataPack> {
+        self.packs
+            .iter()
+            .rev()
+            .find(|pack| pack.files.binary_search_by(|file| file.as_path().cmp(path)).is_ok())
+    }
+
+    /// Root folders of the packs in the order files are looked up in, the last loaded first
+    pub fn layers(&self) -> Vec<PathBuf> {
+        self.packs.iter().rev().map(|pack| pack.root.clone()).collect()
+    }
+
+    fn report(&self) {
+        for pack in &self.packs {
+            let content: Vec<String> = pack
+                .content()
+                .iter()
+                .map(|(kind, count)| format!("{count} {kind:?}"))
+                .collect();
+            info!(
+                "Data pack '{}' {} from {}: {}",
+                pack.manifest.name,
+                pack.manifest.version,
+                pack.id,
+                content.join(", ")
+            );
+        }
+        for conflict in &self.conflicts {
+            warn!(
+                "{} is in data packs {}, using the one from {}",
+                conflict.path.display(),
+                conflict.packs.join(", "),
+                conflict.winner()
+            );
+        }
+        for error in &self.errors {
+            warn!("Skipping data pack: {error}");
+        }
+    }
+}
+
+fn load_data_pack(root: &Path) -> Result<DataPack, ModLoadError> {
+    let path = root.join(MOD_MANIFEST);
+    let bytes = fs::read(&path).map_err(|source| ModLoadError::Io { path: path.clone(), source })?;
+    let version = read_schema_version(&bytes).map_err(|source| ModLoadError::Manifest { path: path.clone(), source })?;
+    check_schema_version(version).map_err(|source| ModLoadError::Schema { path: path.clone(), source })?;
+    let manifest = serde_json::from_slice(&bytes).map_err(|source| ModLoadError::Manifest { path: path.clone(), source })?;
+
+    let mut files = Vec::new();
+    collect_files(root, root, &mut files).map_err(|source| ModLoadError::Io { path: root.to_path_buf(), source })?;
+    files.retain(|file| file != Path::new(MOD_MANIFEST));
+    files.sort();
+
+    Ok(DataPack {
+        id: root.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned()),
+        manifest,
+        root: root.to_path_buf(),
+        files,
+    })
+}
+
+fn collect_files(root: &Path, directory: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            files.push(relative.to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+fn find_conflicts(packs: &[DataPack]) -> Vec<ModConflict> {
+    let mut providers: BTreeMap<&Path, Vec<String>> = BTreeMap::new();
+    for pack in packs {
+        for file in &pack.files {
+            providers.entry(file).or_default().push(pack.id.clone());
+        }
+    }
+    providers
+        .into_iter()
+        .filter(|(_, packs)| packs.len() > 1)
+        .map(|(path, packs)| ModConflict { path: path.to_path_buf(), packs })
+        .collect()
+}
+
+/// Asset reader that looks files up in the data packs before the base assets
+pub struct ModAssetReader {
+    /// Pack readers in lookup order, then the base assets
+    readers: Vec<Box<dyn AssetReader>>,
+}
+
+impl ModAssetReader {
+    pub fn new(layers: impl IntoIterator<Item = PathBuf>, base: Box<dyn AssetReader>) -> Self {
+        let mut readers: Vec<Box<dyn AssetReader>> = layers
+            .into_iter()
+            .map(|root| Box::new(FileAssetReader::new(root)) as Box<dyn AssetReader>)
+            .collect();
+        readers.push(base);
+        Self { readers }
+    }
+}
+
+impl AssetReader for ModAssetReader {
+    fn read<'a>(&'a self, path: &'a Path) -> BoxedFuture<'a, Result<Box<Reader<'a>>, AssetReaderError>> {
+        Box::pin(async move {
+            for reader in &self.readers {
+                match reader.read(path).await {
+                    Err(AssetReaderError::NotFound(_)) => continue,
+                    result => return result,
+                }
+            }
+            Err(AssetReaderError::NotFound(path.to_path_buf()))
+        })
+    }
+
+    fn read_meta<'a>(&'a self, path: &'a Path) -> BoxedFuture<'a, Result<Box<Reader<'a>>, AssetReaderError>> {
+        Box::pin(async move {
+            for reader in &self.readers {
+                match reader.read_meta(path).await {
+                    Err(AssetReaderError::NotFound(_)) => continue,
+                    result => return result,
+                }
+            }
+            Err(AssetReaderError::NotFound(path.to_path_buf()))
+        })
+    }
+
+    /// Lists the folder across every pack and the base assets, so packs can add files to it
+    fn read_directory<'a>(&'a self, path: &'a Path) -> BoxedFuture<'a, Result<Box<PathStream>, AssetReaderError>> {
+        Box::pin(async move {
+            let mut found = false;
+            let mut paths = BTreeSet::new();
+            for reader in &self.readers {
+                match reader.read_directory(path).await {
+                    Ok(mut entries) => {
+                        found = true;
+                        while let Some(entry) = entries.next().await {
+                            paths.insert(entry);
+                        }
+                    }
+                    Err(AssetReaderError::NotFound(_)) => {}
+                    Err(error) => return Err(error),
+                }
+            }
+            if !found {
+                return Err(AssetReaderError::NotFound(path.to_path_buf()));
+            }
+            let entries: Box<PathStream> = Box::new(stream::iter(paths));
+            Ok(entries)
+        })
+    }
+
+    fn is_directory<'a>(&'a self, path: &'a Path) -> BoxedFuture<'a, Result<bool, AssetReaderError>> {
+        Box::pin(async move {
+            for reader in &self.readers {
+                if let Ok(true) = reader.is_directory(path).await {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        })
+    }
+}
+
+/// Discovers the data packs in the mods folder and layers them over the base assets.
+///
+/// Has to be added before `DefaultPlugins`: asset sources are fixed once the asset plugin is
+/// built. Packs replace base files with the same path and add new ones to folder loads.
+pub struct ModPlugin {
+    /// Mods folder, relative paths are taken from the same place as `assets`
+    pub directory: PathBuf,
+}
+
+impl Default for ModPlugin {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from(MODS_DIRECTORY),
+        }
+    }
+}
+
+impl Plugin for ModPlugin {
+    fn build(&self, app: &mut App) {
+        let packs = DataPacks::discover(&FileAssetReader::get_base_path().join(&self.directory));
+        packs.report();
+
+        let layers = packs.layers();
+        let mut base = AssetSource::get_default_reader("assets".to_string());
+        app.register_asset_source(
+            AssetSourceId::Default,
+            AssetSource::build().with_reader(move || Box::new(ModAssetReader::new(layers.clone(), base()))),
+        )
+        .insert_resource(packs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::asset::AsyncReadExt;
+    use bevy::tasks::block_on;
+
+    fn write(root: &Path, path: &str, contents: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn manifest(name: &str, load_order: i32) -> String {
+        format!(r#"{{ "name": "{name}", "version": "1.0", "load_order": {load_order} }}"#)
+    }
+
+    #[test]
+    fn test_packs_are_discovered_in_load_order() {
+        let mods = tempfile::tempdir().unwrap();
+        write(mods.path(), "zz_trucks/mod.json", &manifest("Trucks", 0));
+        write(mods.path(), "zz_trucks/vehicles/hauler.vehicle.json", "{}");
+        write(mods.path(), "zz_trucks/sounds/engine.ogg", "");
+        write(mods.path(), "aa_textures/mod.json", &manifest("Textures", 5));
+        write(mods.path(), "aa_textures/textures/fire_atlas.png", "");
+        write(mods.path(), "aa_base_fixes/mod.json", &manifest("Fixes", 0));
+
+        let packs = DataPacks::discover(mods.path());
+        let ids: Vec<&str> = packs.packs.iter().map(|pack| pack.id.as_str()).collect();
+        assert_eq!(ids, ["aa_base_fixes", "zz_trucks", "aa_textures"]);
+        assert_eq!(packs.packs[1].content()[&ModContent::Vehicle], 1);
+        assert_eq!(packs.packs[1].content()[&ModContent::Sound], 1);
+        assert!(!packs.packs[1].files.contains(&PathBuf::from(MOD_MANIFEST)));
+        assert!(packs.errors.is_empty());
+    }
+
+    #[test]
+    fn test_conflicts_go_to_the_last_pack_loaded() {
+        let mods = tempfile::tempdir().unwrap();
+        write(mods.path(), "first/mod.json", &manifest("First", 0));
+        write(mods.path(), "first/sounds/engine.ogg", "first");
+        write(mods.path(), "second/mod.json", &manifest("Second", 1));
+        write(mods.path(), "second/sounds/engine.ogg", "second");
+
+        let packs = DataPacks::discover(mods.path());
+        assert_eq!(packs.conflicts.len(), 1);
+        assert_eq!(packs.conflicts[0].winner(), "second");
+        assert_eq!(packs.provider(Path::new("sounds/engine.ogg")).unwrap().id, "second");
+        assert!(packs.provider(Path::new("sounds/crash.ogg")).is_none());
+    }
+
+    #[test]
+    fn test_broken_and_newer_packs_are_skipped() {
+        let mods = tempfile::tempdir().unwrap();
+        write(mods.path(), "no_manifest/textures/mud.png", "");
+        write(mods.path(), "bad_manifest/mod.json", "{ name: ");
+        write(
+            mods.path(),
+            "from_the_future/mod.json",
+            &format!(r#"{{ "schema_version": {}, "name": "Future" }}"#, crate::assets::ASSET_SCHEMA_VERSION + 1),
+        );
+
+        let packs = DataPacks::discover(mods.path());
+        assert!(packs.packs.is_empty());
+        assert_eq!(packs.errors.len(), 3);
+        assert!(packs.errors.iter().any(|error| matches!(error, ModLoadError::Schema { .. })));
+    }
+
+    #[test]
+    fn test_reader_prefers_packs_over_base_assets() {
+        let base = tempfile::tempdir().unwrap();
+        let pack = tempfile::tempdir().unwrap();
+        write(base.path(), "sounds/engine.ogg", "base engine");
+        write(base.path(), "sounds/crash.ogg", "base crash");
+        write(pack.path(), "sounds/engine.ogg", "pack engine");
+        write(pack.path(), "sounds/horn.ogg", "pack horn");
+
+        let reader = ModAssetReader::new([pack.path().to_path_buf()], Box::new(FileAssetReader::new(base.path())));
+        let read = |path: &str| {
+            block_on(async {
+                let mut bytes = Vec::new();
+                reader.read(Path::new(path)).await.unwrap().read_to_end(&mut bytes).await.unwrap();
+                String::from_utf8(bytes).unwrap()
+            })
+        };
+        assert_eq!(read("sounds/engine.ogg"), "pack engine");
+        assert_eq!(read("sounds/crash.ogg"), "base crash");
+
+        let listed: Vec<PathBuf> = block_on(async {
+            reader.read_directory(Path::new("sounds")).await.unwrap().collect().await
+        });
+        assert_eq!(listed.len(), 3);
+    }
+}
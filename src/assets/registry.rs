@@ -0,0 +1,611 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use bevy::asset::{Asset, LoadState, UntypedHandle};
+use bevy::audio::AudioSource;
+use bevy::prelude::*;
+
+use crate::audio::StreamedAudio;
+
+/// When an asset is needed, categories load in this order
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LoadPriority {
+    /// Needed before anything shows: UI, fonts, shaders
+    #[default]
+    Critical,
+    /// Needed to start driving: vehicles and the ground
+    High,
+    /// Needed soon after: props, effects, vehicle sounds
+    Medium,
+    /// Fine to arrive in the background
+    Low,
+}
+
+/// Type of asset a category holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AssetKind {
+    Scene,
+    Image,
+    Audio,
+    /// Streamed from disk when played, only the path is kept
+    Stream,
+    Font,
+    Shader,
+    /// Made at runtime, never found on disk
+    Material,
+}
+
+impl AssetKind {
+    fn extensions(self) -> &'static [&'static str] {
+        match self {
+            AssetKind::Scene => &["glb", "gltf"],
+            AssetKind::Image => &["png", "jpg", "ktx2"],
+            AssetKind::Audio | AssetKind::Stream => &["ogg", "wav"],
+            AssetKind::Font => &["ttf", "otf"],
+            AssetKind::Shader => &["wgsl"],
+            AssetKind::Material => &[],
+        }
+    }
+}
+
+/// What an asset is for, which decides where it's found and when it loads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AssetCategory {
+    VehicleModel,
+    VehicleTexture,
+    VehicleMaterial,
+    TerrainTexture,
+    TerrainHeightmap,
+    TerrainMaterial,
+    /// Trees, rocks and other props
+    TerrainObject,
+    EngineSound,
+    EnvironmentSound,
+    ImpactSound,
+    UiSound,
+    VoiceLine,
+    Music,
+    Radio,
+    UiTexture,
+    UiIcon,
+    Font,
+    ParticleTexture,
+    /// Tire tracks, damage marks
+    DecalTexture,
+    WeatherEffect,
+    TrailMarker,
+    Skybox,
+    Shader,
+}
+
+impl AssetCategory {
+    pub const ALL: [AssetCategory; 23] = [
+        AssetCategory::VehicleModel,
+        AssetCategory::VehicleTexture,
+        AssetCategory::VehicleMaterial,
+        AssetCategory::TerrainTexture,
+        AssetCategory::TerrainHeightmap,
+        AssetCategory::TerrainMaterial,
+        AssetCategory::TerrainObject,
+        AssetCategory::EngineSound,
+        AssetCategory::EnvironmentSound,
+        AssetCategory::ImpactSound,
+        AssetCategory::UiSound,
+        AssetCategory::VoiceLine,
+        AssetCategory::Music,
+        AssetCategory::Radio,
+        AssetCategory::UiTexture,
+        AssetCategory::UiIcon,
+        AssetCategory::Font,
+        AssetCategory::ParticleTexture,
+        AssetCategory::DecalTexture,
+        AssetCategory::WeatherEffect,
+        AssetCategory::TrailMarker,
+        AssetCategory::Skybox,
+        AssetCategory::Shader,
+    ];
+
+    /// Folder under `assets/` [`GameAssets::load_all`] finds the category's files in
+    pub fn folder(self) -> Option<&'static str> {
+        Some(match self {
+            AssetCategory::VehicleModel => "models/vehicles",
+            AssetCategory::VehicleTexture => "textures/vehicles",
+            AssetCategory::TerrainTexture => "textures/terrain",
+            AssetCategory::TerrainHeightmap => "textures/heightmaps",
+            AssetCategory::TerrainObject => "models/terrain",
+            AssetCategory::EngineSound => "audio/engine",
+            AssetCategory::EnvironmentSound => "audio/environment",
+            AssetCategory::ImpactSound => "audio/impacts",
+            AssetCategory::UiSound => "audio/ui",
+            AssetCategory::VoiceLine => "audio/voice",
+            AssetCategory::Music => "audio/music",
+            AssetCategory::Radio => "audio/radio",
+            AssetCategory::UiTexture => "textures/ui",
+            AssetCategory::UiIcon => "textures/icons",
+            AssetCategory::Font => "fonts",
+            AssetCategory::ParticleTexture => "textures/particles",
+            AssetCategory::DecalTexture => "textures/decals",
+            AssetCategory::WeatherEffect => "models/weather",
+            AssetCategory::TrailMarker => "models/markers",
+            AssetCategory::Skybox => "textures/skybox",
+            AssetCategory::Shader => "shaders",
+            AssetCategory::VehicleMaterial | AssetCategory::TerrainMaterial => return None,
+        })
+    }
+
+    pub fn priority(self) -> LoadPriority {
+        match self {
+            AssetCategory::UiTexture | AssetCategory::UiIcon | AssetCategory::Font | AssetCategory::Shader => {
+                LoadPriority::Critical
+            }
+            AssetCategory::VehicleModel
+            | AssetCategory::VehicleTexture
+            | AssetCategory::VehicleMaterial
+            | AssetCategory::TerrainTexture
+            | AssetCategory::TerrainHeightmap
+            | AssetCategory::TerrainMaterial
+            | AssetCategory::Skybox => LoadPriority::High,
+            AssetCategory::TerrainObject
+            | AssetCategory::EngineSound
+            | AssetCategory::ImpactSound
+            | AssetCategory::ParticleTexture
+            | AssetCategory::DecalTexture
+            | AssetCategory::WeatherEffect
+            | AssetCategory::TrailMarker => LoadPriority::Medium,
+            AssetCategory::EnvironmentSound
+            | AssetCategory::UiSound
+            | AssetCategory::VoiceLine
+            | AssetCategory::Music
+            | AssetCategory::Radio => LoadPriority::Low,
+        }
+    }
+
+    fn kind(self) -> AssetKind {
+        match self {
+            AssetCategory::VehicleModel
+            | AssetCategory::TerrainObject
+            | AssetCategory::WeatherEffect
+            | AssetCategory::TrailMarker => AssetKind::Scene,
+            AssetCategory::VehicleTexture
+            | AssetCategory::TerrainTexture
+            | AssetCategory::TerrainHeightmap
+            | AssetCategory::UiTexture
+            | AssetCategory::UiIcon
+            | AssetCategory::ParticleTexture
+            | AssetCategory::DecalTexture
+            | AssetCategory::Skybox => AssetKind::Image,
+            AssetCategory::EngineSound
+            | AssetCategory::EnvironmentSound
+            | AssetCategory::ImpactSound
+            | AssetCategory::UiSound
+            | AssetCategory::VoiceLine => AssetKind::Audio,
+            AssetCategory::Music | AssetCategory::Radio => AssetKind::Stream,
+            AssetCategory::Font => AssetKind::Font,
+            AssetCategory::Shader => AssetKind::Shader,
+            AssetCategory::VehicleMaterial | AssetCategory::TerrainMaterial => AssetKind::Material,
+        }
+    }
+}
+
+/// One asset in the registry
+#[derive(Debug, Clone)]
+pub struct AssetEntry {
+    pub category: AssetCategory,
+    /// Name the asset is looked up by, its file name without the extension
+    pub key: String,
+    /// Path relative to the assets directory
+    pub path: String,
+    pub priority: LoadPriority,
+    /// `None` for streamed audio, which isn't loaded up front
+    handle: Option<UntypedHandle>,
+}
+
+impl AssetEntry {
+    pub fn handle(&self) -> Option<&UntypedHandle> {
+        self.handle.as_ref()
+    }
+}
+
+/// Every asset the game loads up front, by category and key. Categories are found by
+/// [`GameAssets::load_all`], assets made at runtime are added with [`GameAssets::insert`].
+#[derive(Resource, Debug, Default)]
+pub struct GameAssets {
+    entries: Vec<AssetEntry>,
+    index: HashMap<(AssetCategory, String), usize>,
+}
+
+impl GameAssets {
+    fn push(&mut self, entry: AssetEntry) {
+        match self.index.get(&(entry.category, entry.key.clone())) {
+            Some(&existing) => self.entries[existing] = entry,
+            None => {
+                self.index.insert((entry.category, entry.key.clone()), self.entries.len());
+                self.entries.push(entry);
+            }
+        }
+    }
+
+    /// Starts loading `path` into `category`, keyed by its file name without the extension
+    pub fn load<A: Asset>(&mut self, asset_server: &AssetServer, category: AssetCategory, path: &str) -> Handle<A> {
+        self.load_keyed(asset_server, category, file_key(path), path.to_string())
+    }
+
+    fn load_keyed<A: Asset>(
+        &mut self,
+        asset_server: &AssetServer,
+        category: AssetCategory,
+        key: String,
+        path: String,
+    ) -> Handle<A> {
+        let handle: Handle<A> = asset_server.load(path.clone());
+        self.push(AssetEntry {
+            category,
+            key,
+            path,
+            priority: category.priority(),
+            handle: Some(handle.clone().untyped()),
+        });
+        handle
+    }
+
+    /// Adds an asset made at runtime, like a material
+    pub fn insert<A: Asset>(&mut self, category: AssetCategory, key: impl Into<String>, handle: Handle<A>) {
+        let key = key.into();
+        self.push(AssetEntry {
+            category,
+            path: key.clone(),
+            key,
+            priority: category.priority(),
+            handle: Some(handle.untyped()),
+        });
+    }
+
+    /// Typed handle of the asset `key` in `category`, `None` when missing or of another type
+    pub fn get<A: Asset>(&self, category: AssetCategory, key: &str) -> Option<Handle<A>> {
+        let entry = &self.entries[*self.index.get(&(category, key.to_string()))?];
+        entry.handle.clone()?.try_typed::<A>().ok()
+    }
+
+    /// Typed handles of every asset in `category`
+    pub fn handles<A: Asset>(&self, category: AssetCategory) -> impl Iterator<Item = Handle<A>> + '_ {
+        self.category(category).filter_map(|entry| entry.handle.clone()?.try_typed::<A>().ok())
+    }
+
+    /// Streamed music or radio track `key` in `category`
+    pub fn stream(&self, category: AssetCategory, key: &str) -> Option<StreamedAudio> {
+        let entry = &self.entries[*self.index.get(&(category, key.to_string()))?];
+        entry.handle.is_none().then(|| StreamedAudio::new(&entry.path))
+    }
+
+    pub fn category(&self, category: AssetCategory) -> impl Iterator<Item = &AssetEntry> {
+        self.entries.iter().filter(move |entry| entry.category == category)
+    }
+
+    pub fn entries(&self) -> &[AssetEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Finds every category's files on disk and starts loading them, most needed first
+    pub fn load_all(&mut self, asset_server: &AssetServer) {
+        let mut categories = AssetCategory::ALL;
+        categories.sort_by_key(|category| category.priority());
+        for category in categories {
+            let Some(folder) = category.folder() else {
+                continue;
+            };
+            for path in asset_files(folder, category.kind().extensions()) {
+                let key = file_key(&path);
+                match category.kind() {
+                    AssetKind::Scene => {
+                        self.load_keyed::<Scene>(asset_server, category, key, format!("{path}#Scene0"));
+                    }
+                    AssetKind::Image => {
+                        self.load_keyed::<Image>(asset_server, category, key, path);
+                    }
+                    AssetKind::Audio => {
+                        self.load_keyed::<AudioSource>(asset_server, category, key, path);
+                    }
+                    AssetKind::Font => {
+                        self.load_keyed::<Font>(asset_server, category, key, path);
+                    }
+                    AssetKind::Shader => {
+                        self.load_keyed::<Shader>(asset_server, category, key, path);
+                    }
+                    AssetKind::Stream => {
+                        self.push(AssetEntry { category, key, path, priority: category.priority(), handle: None });
+                    }
+                    AssetKind::Material => {}
+                }
+            }
+        }
+    }
+
+    /// Reloads every asset from disk, for iterating on them while the game runs
+    pub fn hot_reload(&self, asset_server: &AssetServer) {
+        // Streams are read fresh on every play, materials only exist in memory
+        for entry in self.entries.iter().filter(|entry| entry.category.kind() != AssetKind::Material) {
+            if entry.handle.is_some() {
+                asset_server.reload(entry.path.clone());
+            }
+        }
+    }
+}
+
+/// File name of `path` without the extension
+fn file_key(path: &str) -> String {
+    Path::new(path).file_stem().map_or_else(|| path.to_string(), |stem| stem.to_string_lossy().into_owned())
+}
+
+/// Files under `assets/<folder>` with one of `extensions`, as sorted asset paths
+fn asset_files(folder: &str, extensions: &[&str]) -> Vec<String> {
+    let Ok(files) = std::fs::read_dir(Path::new("assets").join(folder)) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<String> = files
+        .flatten()
+        .filter_map(|file| {
+            let name = file.file_name().to_string_lossy().into_owned();
+            let extension = Path::new(&name).extension()?.to_string_lossy().to_lowercase();
+            extensions.contains(&extension.as_str()).then(|| format!("{folder}/{name}"))
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// How far the registry's assets have loaded
+#[derive(Resource, Debug, Clone, Default)]
+pub struct AssetLoadingState {
+    pub total_assets: usize,
+    pub loaded_assets: usize,
+    /// Paths of the assets that failed to load
+    pub failed_assets: Vec<String>,
+    pub loading_complete: bool,
+    /// Most urgent priority with assets still loading
+    pub current_priority: LoadPriority,
+}
+
+impl AssetLoadingState {
+    /// Get loading progress as a percentage
+    pub fn progress(&self) -> f32 {
+        if self.total_assets == 0 {
+            return 1.0;
+        }
+        self.loaded_assets as f32 / self.total_assets as f32
+    }
+}
+
+pub(super) fn load_game_assets(mut game_assets: ResMut<GameAssets>, asset_server: Res<AssetServer>) {
+    game_assets.load_all(&asset_server);
+    info!("Loading {} game assets", game_assets.len());
+}
+
+/// System to check asset loading progress
+pub fn check_asset_loading_progress(
+    asset_server: Res<AssetServer>,
+    mut loading_state: ResMut<AssetLoadingState>,
+    game_assets: Res<GameAssets>,
+) {
+    let mut state = AssetLoadingState { current_priority: LoadPriority::Low, ..default() };
+    for entry in game_assets.entries() {
+        let Some(handle) = entry.handle() else {
+            continue;
+        };
+        state.total_assets += 1;
+        match asset_server.get_load_state(handle.id()) {
+            // Assets made at runtime aren't tracked by the server, they're there from the start
+            Some(LoadState::Loaded) | None => state.loaded_assets += 1,
+            Some(LoadState::Failed) => state.failed_assets.push(entry.path.clone()),
+            _ => state.current_priority = state.current_priority.min(entry.priority),
+        }
+    }
+    state.loading_complete = state.loaded_assets + state.failed_assets.len() == state.total_assets;
+
+    if state.current_priority != loading_state.current_priority && !state.loading_complete {
+        info!("Loading {:?} priority assets", state.current_priority);
+    }
+    *loading_state = state;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::asset::io::Reader;
+    use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+    use bevy::utils::BoxedFuture;
+
+    use super::*;
+
+    /// Raw file contents, an empty file fails to load and one reading "slow" takes a while
+    #[derive(Asset, TypePath, Debug)]
+    struct TestBlob(Vec<u8>);
+
+    #[derive(Default)]
+    struct TestBlobLoader;
+
+    impl AssetLoader for TestBlobLoader {
+        type Asset = TestBlob;
+        type Settings = ();
+        type Error = std::io::Error;
+
+        fn load<'a>(
+            &'a self,
+            reader: &'a mut Reader,
+            _settings: &'a (),
+            _load_context: &'a mut LoadContext,
+        ) -> BoxedFuture<'a, Result<TestBlob, std::io::Error>> {
+            Box::pin(async move {
+                let mut bytes = Vec::new();
+                reader.read_to_end(&mut bytes).await?;
+                if bytes.is_empty() {
+                    return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "empty blob"));
+                }
+                if bytes == b"slow" {
+                    std::thread::sleep(Duration::from_millis(300));
+                }
+                Ok(TestBlob(bytes))
+            })
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &["blob"]
+        }
+    }
+
+    /// An app loading from a scratch assets directory holding `files`
+    fn loading_app(files: &[(&str, &[u8])]) -> (App, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, bytes) in files {
+            std::fs::write(dir.path().join(name), bytes).unwrap();
+        }
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            bevy::asset::AssetPlugin { file_path: dir.path().to_string_lossy().into_owned(), ..default() },
+        ))
+        .init_asset::<TestBlob>()
+        .init_asset_loader::<TestBlobLoader>()
+        .init_resource::<GameAssets>()
+        .init_resource::<AssetLoadingState>()
+        .add_systems(Update, check_asset_loading_progress);
+        (app, dir)
+    }
+
+    fn load(app: &mut App, category: AssetCategory, path: &str) {
+        let asset_server = app.world.resource::<AssetServer>().clone();
+        app.world.resource_mut::<GameAssets>().load::<TestBlob>(&asset_server, category, path);
+    }
+
+    /// Updates until every asset has loaded or failed
+    fn finish_loading(app: &mut App) -> AssetLoadingState {
+        for _ in 0..1000 {
+            app.update();
+            let state = app.world.resource::<AssetLoadingState>();
+            if state.loading_complete {
+                return state.clone();
+            }
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        panic!("assets still loading: {:?}", app.world.resource::<AssetLoadingState>());
+    }
+
+    #[test]
+    fn test_loading_state_progress() {
+        let state = AssetLoadingState { total_assets: 10, loaded_assets: 5, ..default() };
+        assert_eq!(state.progress(), 0.5);
+        assert_eq!(AssetLoadingState::default().progress(), 1.0);
+    }
+
+    #[test]
+    fn test_progress_counts_loaded_assets() {
+        let (mut app, _dir) = loading_app(&[("a.blob", b"a"), ("b.blob", b"b"), ("c.blob", b"c")]);
+        for path in ["a.blob", "b.blob", "c.blob"] {
+            load(&mut app, AssetCategory::UiTexture, path);
+        }
+
+        let state = finish_loading(&mut app);
+        assert_eq!(state.total_assets, 3);
+        assert_eq!(state.loaded_assets, 3);
+        assert!(state.failed_assets.is_empty());
+        assert_eq!(state.progress(), 1.0);
+    }
+
+    #[test]
+    fn test_current_priority_is_the_most_urgent_still_loading() {
+        let (mut app, _dir) = loading_app(&[("ui.blob", b"ui"), ("truck.blob", b"slow"), ("idle.blob", b"slow")]);
+        load(&mut app, AssetCategory::UiTexture, "ui.blob");
+        load(&mut app, AssetCategory::VehicleModel, "truck.blob");
+        load(&mut app, AssetCategory::EngineSound, "idle.blob");
+
+        // The UI texture is quick, the vehicle and its sound are still on their way
+        for _ in 0..100 {
+            app.update();
+            if app.world.resource::<AssetLoadingState>().loaded_assets > 0 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        let state = app.world.resource::<AssetLoadingState>();
+        assert_eq!(state.loaded_assets, 1);
+        assert_eq!(state.current_priority, LoadPriority::High);
+
+        let state = finish_loading(&mut app);
+        assert_eq!(state.loaded_assets, 3);
+        assert_eq!(state.current_priority, LoadPriority::Low);
+    }
+
+    #[test]
+    fn test_failed_assets_are_tracked() {
+        let (mut app, _dir) = loading_app(&[("good.blob", b"ok"), ("invalid.blob", b"")]);
+        load(&mut app, AssetCategory::VehicleModel, "good.blob");
+        load(&mut app, AssetCategory::VehicleModel, "invalid.blob");
+
+        let state = finish_loading(&mut app);
+        assert_eq!(state.loaded_assets, 1);
+        assert_eq!(state.failed_assets, vec!["invalid.blob".to_string()]);
+        assert_eq!(state.progress(), 0.5);
+    }
+
+    #[test]
+    fn test_partial_loading_completes() {
+        // Only some of the registered files exist, the missing ones fail rather than hold up loading
+        let (mut app, _dir) = loading_app(&[("loading.blob", b"ui"), ("main.blob", b"font")]);
+        load(&mut app, AssetCategory::UiTexture, "loading.blob");
+        load(&mut app, AssetCategory::Font, "main.blob");
+        load(&mut app, AssetCategory::TerrainTexture, "missing.blob");
+
+        let state = finish_loading(&mut app);
+        assert_eq!(state.total_assets, 3);
+        assert_eq!(state.loaded_assets, 2);
+        assert_eq!(state.failed_assets, vec!["missing.blob".to_string()]);
+        let assets = app.world.resource::<GameAssets>();
+        assert!(assets.get::<TestBlob>(AssetCategory::UiTexture, "loading").is_some());
+        assert!(assets.get::<TestBlob>(AssetCategory::Font, "main").is_some());
+    }
+
+    #[test]
+    fn test_runtime_assets_count_as_loaded() {
+        let (mut app, _dir) = loading_app(&[]);
+        let material = app.world.resource_mut::<Assets<TestBlob>>().add(TestBlob(vec![1]));
+        app.world.resource_mut::<GameAssets>().insert(AssetCategory::TerrainMaterial, "mud", material);
+
+        let state = finish_loading(&mut app);
+        assert_eq!(state.loaded_assets, 1);
+    }
+
+    #[test]
+    fn test_categories_load_in_priority_order() {
+        assert_eq!(AssetCategory::Font.priority(), LoadPriority::Critical);
+        assert_eq!(AssetCategory::VehicleModel.priority(), LoadPriority::High);
+        assert!(AssetCategory::VoiceLine.priority() > AssetCategory::ImpactSound.priority());
+        // Materials are made at runtime, everything else is found on disk
+        for category in AssetCategory::ALL {
+            let runtime = matches!(category, AssetCategory::VehicleMaterial | AssetCategory::TerrainMaterial);
+            assert_eq!(category.folder().is_none(), runtime, "{category:?}");
+        }
+    }
+
+    #[test]
+    fn test_registry_looks_up_by_category_and_key() {
+        let mut assets = GameAssets::default();
+        let mud = Handle::<StandardMaterial>::default();
+        assets.insert(AssetCategory::TerrainMaterial, "mud", mud.clone());
+        assets.insert(AssetCategory::VehicleMaterial, "mud", Handle::<StandardMaterial>::default());
+        assets.insert(AssetCategory::TerrainMaterial, "mud", mud.clone());
+
+        assert_eq!(assets.len(), 2);
+        assert_eq!(assets.get::<StandardMaterial>(AssetCategory::TerrainMaterial, "mud"), Some(mud));
+        assert_eq!(assets.get::<Image>(AssetCategory::TerrainMaterial, "mud"), None);
+        assert_eq!(assets.get::<StandardMaterial>(AssetCategory::TerrainMaterial, "rock"), None);
+        assert_eq!(assets.handles::<StandardMaterial>(AssetCategory::VehicleMaterial).count(), 1);
+    }
+}
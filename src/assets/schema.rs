@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Schema version of the JSON asset formats this build writes and reads.
+///
+/// Formats only ever gain fields with defaults, so files from older versions keep loading. Bump
+/// this when a change can't be read by older builds, and keep reading the older versions.
+pub const ASSET_SCHEMA_VERSION: u32 = 1;
+
+/// The `schema_version` field every JSON asset format may carry. Files written before formats
+/// had one are version 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaVersion {
+    #[serde(default = "default_schema_version")]
+    pub schema_version: u32,
+}
+
+/// Version of files written before formats carried one
+pub fn default_schema_version() -> u32 {
+    1
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SchemaVersionError {
+    #[error("written for asset schema version {found}, this build reads up to {ASSET_SCHEMA_VERSION}")]
+    TooNew { found: u32 },
+}
+
+/// Checks that a file's schema version can be read by this build
+pub fn check_schema_version(found: u32) -> Result<(), SchemaVersionError> {
+    if found > ASSET_SCHEMA_VERSION {
+        return Err(SchemaVersionError::TooNew { found });
+    }
+    Ok(())
+}
+
+/// Reads just the schema version of a JSON asset, before the rest of it might fail to parse
+pub fn read_schema_version(bytes: &[u8]) -> Result<u32, serde_json::Error> {
+    Ok(serde_json::from_slice::<SchemaVersion>(bytes)?.schema_version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unversioned_files_are_the_first_version() {
+        assert_eq!(read_schema_version(br#"{ "events": [] }"#).unwrap(), 1);
+        assert_eq!(read_schema_version(br#"{ "schema_version": 3, "events": [] }"#).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_only_newer_versions_are_rejected() {
+        assert_eq!(check_schema_version(1), Ok(()));
+        assert_eq!(check_schema_version(ASSET_SCHEMA_VERSION), Ok(()));
+        assert_eq!(
+            check_schema_version(ASSET_SCHEMA_VERSION + 1),
+            Err(SchemaVersionError::TooNew { found: ASSET_SCHEMA_VERSION + 1 })
+        );
+    }
+}
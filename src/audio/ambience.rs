@@ -0,0 +1,107 @@
+use bevy::audio::{AudioSinkPlayback, Volume};
+use bevy::prelude::*;
+
+use super::AudioSettings;
+use crate::terrain::{Biome, BiomeField, BiomeSample};
+
+/// Seconds an ambient bed takes to fade fully in or out as the biomes around the camera change
+const AMBIENT_FADE: f32 = 4.0;
+
+/// Looping ambient beds of the biomes around the camera, crossfaded by how much of each biome
+/// surrounds it. Biomes sharing a bed share one sound.
+#[derive(Resource, Default)]
+pub struct AmbientBeds {
+    playing: Vec<AmbientBed>,
+}
+
+struct AmbientBed {
+    path: String,
+    entity: Entity,
+    gain: f32,
+}
+
+/// Gain each biome's bed should be at for a biome mix, by asset path
+pub fn ambient_targets(field: &BiomeField, sample: &BiomeSample) -> Vec<(String, f32)> {
+    let mut targets: Vec<(String, f32)> = Vec::new();
+    for biome in Biome::ALL {
+        let path = &field.settings().profile(biome).ambient;
+        match targets.iter_mut().find(|(target, _)| target == path) {
+            Some((_, gain)) => *gain += sample.weight(biome),
+            None => targets.push((path.clone(), sample.weight(biome))),
+        }
+    }
+    targets
+}
+
+/// Fades the biome beds toward the mix around the active camera, starting beds as their biome
+/// comes near and stopping them once faded out
+pub(super) fn update_ambient_beds(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    settings: Res<AudioSettings>,
+    field: Option<Res<BiomeField>>,
+    asset_server: Res<AssetServer>,
+    mut beds: ResMut<AmbientBeds>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    sinks: Query<&AudioSink>,
+) {
+    let Some(field) = field else {
+        return;
+    };
+    let targets = match cameras.iter().find(|(camera, _)| camera.is_active) {
+        Some((_, transform)) => {
+            let position = transform.translation();
+            ambient_targets(&field, &field.sample(position.x, position.z))
+        }
+        // Nothing to listen from, let everything fade out
+        None => Vec::new(),
+    };
+
+    for (path, gain) in &targets {
+        if *gain > 0.0 && !beds.playing.iter().any(|bed| bed.path == *path) {
+            let entity = commands
+                .spawn(AudioBundle {
+                    source: asset_server.load(path.clone()),
+                    settings: PlaybackSettings::LOOP.with_volume(Volume::new_relative(0.0)),
+                })
+                .id();
+            beds.playing.push(AmbientBed { path: path.clone(), entity, gain: 0.0 });
+        }
+    }
+
+    let step = time.delta_seconds() / AMBIENT_FADE;
+    beds.playing.retain_mut(|bed| {
+        let target = targets.iter().find(|(path, _)| *path == bed.path).map_or(0.0, |(_, gain)| *gain);
+        bed.gain = if bed.gain < target { (bed.gain + step).min(target) } else { (bed.gain - step).max(target) };
+        if bed.gain <= 0.0 && target <= 0.0 {
+            commands.entity(bed.entity).despawn();
+            return false;
+        }
+        if let Ok(sink) = sinks.get(bed.entity) {
+            sink.set_volume(bed.gain * settings.ambient_volume * settings.master_volume);
+        }
+        true
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::Climate;
+
+    #[test]
+    fn test_beds_follow_the_biome_mix() {
+        let field = BiomeField::default();
+        let sample = BiomeSample {
+            climate: Climate { temperature: 0.5, moisture: 0.5 },
+            weights: [0.25, 0.75, 0.0],
+            biome: Biome::Forest,
+        };
+        let targets = ambient_targets(&field, &sample);
+        assert_eq!(targets.len(), 3);
+        assert!((targets.iter().map(|(_, gain)| gain).sum::<f32>() - 1.0).abs() < 1e-4);
+        let forest = &field.settings().profile(Biome::Forest).ambient;
+        let forest_gain = targets.iter().find(|(path, _)| path == forest).unwrap().1;
+        assert!((forest_gain - 0.75).abs() < 1e-4);
+    }
+}
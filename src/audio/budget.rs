@@ -0,0 +1,188 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+
+use super::streaming::StreamedAudio;
+
+const MEGABYTE: f32 = 1024.0 * 1024.0;
+
+/// Memory held by loaded audio, as of the last budget check
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AudioMemoryUsage {
+    /// Bytes of the preloaded sound effects, these are never evicted
+    pub preloaded_bytes: usize,
+    /// Bytes of clips loaded on demand and kept around for the next time they play
+    pub cached_bytes: usize,
+    pub cached_clips: usize,
+    /// Tracks playing from disk, these hold no clip in memory
+    pub streams: usize,
+}
+
+impl AudioMemoryUsage {
+    pub fn total_bytes(&self) -> usize {
+        self.preloaded_bytes + self.cached_bytes
+    }
+}
+
+struct CachedClip {
+    handle: Handle<AudioSource>,
+    bytes: usize,
+    /// Budget check the clip last played in
+    last_used: u64,
+}
+
+/// Clips loaded on demand by path, kept loaded while they fit in the audio memory budget.
+/// Once over the budget the least recently played clips that aren't playing are dropped.
+#[derive(Resource)]
+pub struct AudioClipCache {
+    /// Most bytes of audio kept loaded
+    pub budget_bytes: usize,
+    clips: HashMap<String, CachedClip>,
+    preloaded: HashSet<AssetId<AudioSource>>,
+    usage: AudioMemoryUsage,
+    tick: u64,
+}
+
+impl Default for AudioClipCache {
+    fn default() -> Self {
+        Self {
+            budget_bytes: 32 * 1024 * 1024,
+            clips: HashMap::new(),
+            preloaded: HashSet::new(),
+            usage: AudioMemoryUsage::default(),
+            tick: 0,
+        }
+    }
+}
+
+impl AudioClipCache {
+    /// Handle to the clip at `path`, loading it if it isn't cached
+    pub fn load(&mut self, path: &str, asset_server: &AssetServer) -> Handle<AudioSource> {
+        let tick = self.tick;
+        let clip = self.clips.entry(path.to_string()).or_insert_with(|| CachedClip {
+            handle: asset_server.load(path.to_string()),
+            bytes: 0,
+            last_used: tick,
+        });
+        clip.last_used = tick;
+        clip.handle.clone()
+    }
+
+    /// Keeps a preloaded sound effect out of eviction, its memory still counts towards the budget
+    pub fn preload(&mut self, handle: &Handle<AudioSource>) {
+        self.preloaded.insert(handle.id());
+    }
+
+    pub fn usage(&self) -> AudioMemoryUsage {
+        self.usage
+    }
+
+    /// Cached clips to drop, least recently played first, until the rest fit in the budget.
+    /// Clips that are playing stay even if that leaves the cache over budget.
+    fn eviction_order(&self, playing: &HashSet<AssetId<AudioSource>>) -> Vec<String> {
+        let mut candidates: Vec<(&String, &CachedClip)> = self
+            .clips
+            .iter()
+            .filter(|(_, clip)| !playing.contains(&clip.handle.id()))
+            .collect();
+        candidates.sort_by_key(|(path, clip)| (clip.last_used, *path));
+
+        let mut total = self.usage.preloaded_bytes + self.clips.values().map(|clip| clip.bytes).sum::<usize>();
+        let mut evicted = Vec::new();
+        for (path, clip) in candidates {
+            if total <= self.budget_bytes {
+                break;
+            }
+            total -= clip.bytes;
+            evicted.push(path.clone());
+        }
+        evicted
+    }
+
+    fn refresh(&mut self, sources: &Assets<AudioSource>, playing: &HashSet<AssetId<AudioSource>>, streams: usize) {
+        self.tick += 1;
+        let clip_bytes = |id: AssetId<AudioSource>| sources.get(id).map_or(0, |source| source.bytes.len());
+        for clip in self.clips.values_mut() {
+            clip.bytes = clip_bytes(clip.handle.id());
+            if playing.contains(&clip.handle.id()) {
+                clip.last_used = self.tick;
+            }
+        }
+        self.usage.preloaded_bytes = self.preloaded.iter().map(|id| clip_bytes(*id)).sum();
+
+        for path in self.eviction_order(playing) {
+            debug!("Evicting audio clip {path} to stay within the memory budget");
+            self.clips.remove(&path);
+        }
+        self.usage.cached_bytes = self.clips.values().map(|clip| clip.bytes).sum();
+        self.usage.cached_clips = self.clips.len();
+        self.usage.streams = streams;
+    }
+}
+
+/// Loads a clip through the cache when there is one, straight from the asset server otherwise
+pub fn load_clip(cache: Option<&mut AudioClipCache>, asset_server: &AssetServer, path: &str) -> Handle<AudioSource> {
+    match cache {
+        Some(cache) => cache.load(path, asset_server),
+        None => asset_server.load(path.to_string()),
+    }
+}
+
+/// Line for the audio memory readout in the debug overlay
+pub fn audio_memory_text(cache: &AudioClipCache) -> String {
+    let usage = cache.usage();
+    format!(
+        "Audio: {:.1} / {:.1} MB ({:.1} MB preloaded, {} cached clips, {} streams)",
+        usage.total_bytes() as f32 / MEGABYTE,
+        cache.budget_bytes as f32 / MEGABYTE,
+        usage.preloaded_bytes as f32 / MEGABYTE,
+        usage.cached_clips,
+        usage.streams
+    )
+}
+
+/// Measures loaded audio and evicts cached clips once over the budget
+pub(super) fn enforce_audio_budget(
+    mut cache: ResMut<AudioClipCache>,
+    sources: Res<Assets<AudioSource>>,
+    clips: Query<&Handle<AudioSource>>,
+    streams: Query<(), With<Handle<StreamedAudio>>>,
+) {
+    let playing: HashSet<AssetId<AudioSource>> = clips.iter().map(|handle| handle.id()).collect();
+    cache.refresh(&sources, &playing, streams.iter().count());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::utils::Uuid;
+
+    fn cache_with(clips: &[(&str, usize, u64)], budget_bytes: usize) -> AudioClipCache {
+        let mut cache = AudioClipCache { budget_bytes, ..default() };
+        for (index, (path, bytes, last_used)) in clips.iter().enumerate() {
+            let handle = Handle::Weak(AssetId::Uuid { uuid: Uuid::from_u128(index as u128 + 1) });
+            cache.clips.insert(path.to_string(), CachedClip { handle, bytes: *bytes, last_used: *last_used });
+        }
+        cache
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used_until_within_budget() {
+        let cache = cache_with(&[("old.ogg", 40, 1), ("recent.ogg", 40, 5), ("older.ogg", 40, 0)], 50);
+        assert_eq!(cache.eviction_order(&HashSet::new()), vec!["older.ogg", "old.ogg"]);
+
+        let roomy = cache_with(&[("old.ogg", 40, 1)], 50);
+        assert!(roomy.eviction_order(&HashSet::new()).is_empty());
+    }
+
+    #[test]
+    fn test_playing_and_preloaded_clips_are_kept() {
+        let mut cache = cache_with(&[("playing.ogg", 40, 0), ("idle.ogg", 40, 3)], 50);
+        let playing = HashSet::from([cache.clips["playing.ogg"].handle.id()]);
+        assert_eq!(cache.eviction_order(&playing), vec!["idle.ogg"]);
+
+        // Preloaded effects count against the budget but only cached clips go
+        cache.usage.preloaded_bytes = 100;
+        assert_eq!(cache.eviction_order(&HashSet::new()), vec!["playing.ogg", "idle.ogg"]);
+    }
+}
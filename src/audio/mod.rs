@@ -1,10 +1,30 @@
 use bevy::prelude::*;
 use bevy::audio::*;
 use bevy::math::Vec3;
-use crate::game::Vehicle;
-use bevy_rapier3d::prelude::CollisionEvent;
+use crate::utils::EntityPool;
+use crate::game::{
+    DebugInfo, Engine, EngineConfig, ImpactEvent, LightningStrikeEvent, ProfileGroup, PropBrokenEvent, PropKind,
+    RaceFinishedEvent, RacePositionEvent, UnderbodyScrapeEvent, Vehicle, VehicleUnlockedEvent, Wheel,
+};
 use std::collections::HashMap;
 
+mod ambience;
+mod budget;
+mod music;
+mod propagation;
+mod streaming;
+mod thunder;
+
+pub use ambience::{ambient_targets, AmbientBeds};
+pub use budget::{audio_memory_text, load_clip, AudioClipCache, AudioMemoryUsage};
+pub use music::{
+    gameplay_intensity, next_mood, stem_gain, MusicDirector, MusicLibrary, MusicMood, MusicSettings, MusicStem,
+    MusicTrack, MusicTrackError, MusicTrackLoader,
+};
+pub use propagation::{ridge_occlusion, Muffling, SoundOcclusion, SoundPropagation, SoundPropagationSettings};
+pub use streaming::{play_stream, StreamedAudio, StreamedAudioDecoder};
+pub use thunder::{thunder_sample, thunder_volume, ThunderQueue, SPEED_OF_SOUND};
+
 pub struct AudioPlugin;
 
 impl Plugin for AudioPlugin {
@@ -12,12 +32,43 @@ impl Plugin for AudioPlugin {
         app.init_resource::<AudioAssets>()
            .init_resource::<AudioSettings>()
            .init_resource::<SoundEffectPool>()
+           .init_resource::<AudioClipCache>()
+           .init_resource::<ThunderQueue>()
+           .init_resource::<MusicSettings>()
+           .init_resource::<MusicDirector>()
+           .init_resource::<AmbientBeds>()
+           .init_resource::<SoundPropagationSettings>()
+           .init_asset::<MusicTrack>()
+           .init_asset_loader::<MusicTrackLoader>()
+           .add_audio_source::<StreamedAudio>()
+           .add_event::<RadioMessageEvent>()
+           .add_event::<UnderbodyScrapeEvent>()
+           .add_event::<LightningStrikeEvent>()
+           .add_event::<ImpactEvent>()
+           .add_event::<PropBrokenEvent>()
+           .add_event::<RacePositionEvent>()
+           .add_event::<RaceFinishedEvent>()
+           .add_event::<VehicleUnlockedEvent>()
+           .add_systems(Startup, (
+                preload_sound_effects,
+                warm_sound_pool,
+                spawn_audio_overlay,
+                music::load_music_tracks,
+            ))
            .add_systems(Update, (
-                update_vehicle_sounds,
+                (propagation::update_sound_occlusion, update_vehicle_sounds).chain(),
                 handle_environment_sounds,
+                play_scrape_sounds,
+                play_prop_break_sounds,
+                play_radio_messages,
+                (music::update_music_intensity, music::play_music_stings, music::update_music).chain(),
+                (thunder::queue_thunder, thunder::play_thunder).chain(),
+                ambience::update_ambient_beds,
                 update_spatial_audio,
                 cleanup_finished_sounds,
-            ));
+                budget::enforce_audio_budget,
+                update_audio_overlay,
+            ).in_set(ProfileGroup::Audio));
     }
 }
 
@@ -29,6 +80,23 @@ pub struct AudioAssets {
     pub tire_squeal: Handle<AudioSource>,
     pub wind: Handle<AudioSource>,
     pub suspension: Handle<AudioSource>,
+    pub scrape: Handle<AudioSource>,
+    pub prop_break: Handle<AudioSource>,
+}
+
+impl AudioAssets {
+    fn handles(&self) -> [&Handle<AudioSource>; 8] {
+        [
+            &self.engine_sound,
+            &self.crash_sound,
+            &self.ambient_sound,
+            &self.tire_squeal,
+            &self.wind,
+            &self.suspension,
+            &self.scrape,
+            &self.prop_break,
+        ]
+    }
 }
 
 impl FromWorld for AudioAssets {
@@ -41,16 +109,30 @@ impl FromWorld for AudioAssets {
             tire_squeal: asset_server.load("sounds/tire_squeal.ogg"),
             wind: asset_server.load("sounds/wind.ogg"),
             suspension: asset_server.load("sounds/suspension.ogg"),
+            scrape: asset_server.load("sounds/scrape.ogg"),
+            prop_break: asset_server.load("sounds/wood_break.ogg"),
         }
     }
 }
 
+/// A voice line over the radio, shown as a subtitle when subtitles are on
+#[derive(Event, Debug, Clone)]
+pub struct RadioMessageEvent {
+    pub speaker: String,
+    pub text: String,
+    /// Asset path of the recorded line, `None` for text only messages
+    pub voice_line: Option<String>,
+    /// Seconds the subtitle stays up
+    pub duration: f32,
+}
+
 #[derive(Resource)]
 pub struct AudioSettings {
     master_volume: f32,
     engine_volume: f32,
     effects_volume: f32,
     ambient_volume: f32,
+    music_volume: f32,
     spatial_scale: f32,
     doppler_effect: bool,
 }
@@ -62,23 +144,29 @@ impl Default for AudioSettings {
             engine_volume: 0.8,
             effects_volume: 0.7,
             ambient_volume: 0.5,
+            music_volume: 0.6,
             spatial_scale: 1.0,
             doppler_effect: true,
         }
     }
 }
 
+/// Marks the recycled entities one-shot and looping sound effects play on
+#[derive(Component, Default)]
+pub struct PooledSound;
+
 #[derive(Resource)]
 struct SoundEffectPool {
     active_sounds: HashMap<Entity, ActiveSound>,
-    available_entities: Vec<Entity>,
+    entities: EntityPool<PooledSound>,
 }
 
 impl Default for SoundEffectPool {
     fn default() -> Self {
         Self {
             active_sounds: HashMap::new(),
-            available_entities: Vec::new(),
+            // Enough for a crash: impact, scrape, squeal and a few debris hits at once
+            entities: EntityPool::new(16, 48),
         }
     }
 }
@@ -97,23 +185,30 @@ enum SoundCategory {
     Ambient,
 }
 
+#[allow(clippy::too_many_arguments)]
 fn update_vehicle_sounds(
     mut commands: Commands,
-    vehicle_query: Query<(&Vehicle, &Transform, &Velocity)>,
+    vehicle_query: Query<(&Vehicle, &Engine, &Transform, &Velocity, Option<&SoundOcclusion>)>,
+    wheels: Query<&Wheel>,
+    engine_config: Option<Res<EngineConfig>>,
     audio_assets: Res<AudioAssets>,
     settings: Res<AudioSettings>,
+    propagation: Res<SoundPropagationSettings>,
     mut sound_pool: ResMut<SoundEffectPool>,
     time: Res<Time>,
 ) {
-    for (vehicle, transform, velocity) in vehicle_query.iter() {
+    let redline = engine_config.map_or_else(|| EngineConfig::default().redline_rpm, |config| config.redline_rpm);
+    for (vehicle, engine, transform, velocity, occlusion) in vehicle_query.iter() {
         let speed = velocity.linvel.length();
-        let rpm_factor = vehicle.engine.current_rpm / vehicle.engine.max_rpm;
-        
+        let rpm_factor = (engine.rpm / redline).min(1.0);
+        // Vehicles behind a ridge are heard dull and quiet
+        let muffling = Muffling::new(occlusion.map_or(0.0, |occlusion| occlusion.0), &propagation);
+
         // Engine sound modulation
-        let volume = (rpm_factor * 0.8 + 0.2) * settings.engine_volume * settings.master_volume;
+        let volume = (rpm_factor * 0.8 + 0.2) * settings.engine_volume * settings.master_volume * muffling.volume;
         let base_pitch = rpm_factor * 0.5 + 0.75;
-        let load_pitch = if vehicle.engine.throttle > 0.1 { 1.1 } else { 1.0 };
-        let final_pitch = base_pitch * load_pitch;
+        let load_pitch = if engine.throttle > 0.1 { 1.1 } else { 1.0 };
+        let final_pitch = base_pitch * load_pitch * muffling.pitch;
 
         spawn_or_update_sound(
             &mut commands,
@@ -128,14 +223,14 @@ fn update_vehicle_sounds(
         );
 
         // Tire squeal based on lateral force
-        if vehicle.wheels.iter().any(|w| w.slip_ratio.abs() > 0.2) {
+        if wheels.iter_many(vehicle.wheel_entities).any(|w| w.slip_ratio.abs() > 0.2) {
             spawn_or_update_sound(
                 &mut commands,
                 &mut sound_pool,
                 audio_assets.tire_squeal.clone(),
                 transform.translation,
-                0.4 * settings.effects_volume * settings.master_volume,
-                1.0,
+                0.4 * settings.effects_volume * settings.master_volume * muffling.volume,
+                muffling.pitch,
                 SoundCategory::Effect,
                 true,
                 None,
@@ -150,7 +245,7 @@ fn update_vehicle_sounds(
                 &mut sound_pool,
                 audio_assets.wind.clone(),
                 transform.translation,
-                wind_volume * settings.effects_volume * settings.master_volume,
+                wind_volume * settings.effects_volume * settings.master_volume * muffling.volume,
                 1.0,
                 SoundCategory::Ambient,
                 true,
@@ -162,32 +257,166 @@ fn update_vehicle_sounds(
 
 fn handle_environment_sounds(
     mut commands: Commands,
-    mut collision_events: EventReader<CollisionEvent>,
+    mut impacts: EventReader<ImpactEvent>,
     audio_assets: Res<AudioAssets>,
     settings: Res<AudioSettings>,
+    propagation: SoundPropagation,
     mut sound_pool: ResMut<SoundEffectPool>,
-    query: Query<&Transform>,
 ) {
-    for event in collision_events.iter() {
-        if let CollisionEvent::Started(entity1, entity2, _) = event {
-            // Get collision position from either entity
-            if let Ok(transform) = query.get(*entity1) {
-                let impact_velocity = 10.0; // TODO: Calculate from actual collision
-                let volume = (impact_velocity / 20.0).min(1.0) * 0.5;
-                
-                spawn_or_update_sound(
-                    &mut commands,
-                    &mut sound_pool,
-                    audio_assets.crash_sound.clone(),
-                    transform.translation,
-                    volume * settings.effects_volume * settings.master_volume,
-                    1.0,
-                    SoundCategory::Effect,
-                    false,
-                    Some(0.5),
-                );
-            }
+    for impact in impacts.read() {
+        // Louder and deeper the harder the hit
+        let muffling = propagation.muffling(impact.position);
+        let volume = (0.2 + impact.intensity * 0.8) * muffling.volume;
+        let pitch = (1.1 - impact.intensity * 0.3) * muffling.pitch;
+
+        spawn_or_update_sound(
+            &mut commands,
+            &mut sound_pool,
+            audio_assets.crash_sound.clone(),
+            impact.position,
+            volume * settings.effects_volume * settings.master_volume,
+            pitch,
+            SoundCategory::Effect,
+            false,
+            Some(0.5),
+        );
+    }
+}
+
+/// Grinding of underbody hardware dragged over the ground, harsher on rock than on dirt
+fn play_scrape_sounds(
+    mut commands: Commands,
+    mut scrapes: EventReader<UnderbodyScrapeEvent>,
+    audio_assets: Res<AudioAssets>,
+    settings: Res<AudioSettings>,
+    propagation: SoundPropagation,
+    mut sound_pool: ResMut<SoundEffectPool>,
+) {
+    for scrape in scrapes.read() {
+        let surface = if scrape.surface.is_hard() { 1.0 } else { 0.5 };
+        let muffling = propagation.muffling(scrape.position);
+        let volume = (0.15 + scrape.intensity * 0.6) * surface * muffling.volume;
+        let pitch = (0.9 + scrape.intensity * 0.3) * muffling.pitch;
+
+        spawn_or_update_sound(
+            &mut commands,
+            &mut sound_pool,
+            audio_assets.scrape.clone(),
+            scrape.position,
+            volume * settings.effects_volume * settings.master_volume,
+            pitch,
+            SoundCategory::Effect,
+            false,
+            Some(0.3),
+        );
+    }
+}
+
+/// Splintering wood of a broken prop, a sapling snaps lighter and higher than a fence or gate
+fn play_prop_break_sounds(
+    mut commands: Commands,
+    mut broken: EventReader<PropBrokenEvent>,
+    audio_assets: Res<AudioAssets>,
+    settings: Res<AudioSettings>,
+    propagation: SoundPropagation,
+    mut sound_pool: ResMut<SoundEffectPool>,
+) {
+    for prop in broken.read() {
+        let (size, pitch) = match prop.kind {
+            PropKind::Sapling => (0.6, 1.3),
+            PropKind::Fence => (0.9, 1.0),
+            PropKind::Gate => (1.0, 0.85),
+        };
+        let muffling = propagation.muffling(prop.position);
+        let volume = (0.4 + prop.intensity * 0.6) * size * muffling.volume;
+        let pitch = pitch * muffling.pitch;
+
+        spawn_or_update_sound(
+            &mut commands,
+            &mut sound_pool,
+            audio_assets.prop_break.clone(),
+            prop.position,
+            volume * settings.effects_volume * settings.master_volume,
+            pitch,
+            SoundCategory::Effect,
+            false,
+            Some(0.5),
+        );
+    }
+}
+
+/// The short sound effects stay loaded for the whole session, outside the clip cache's eviction
+fn preload_sound_effects(audio_assets: Res<AudioAssets>, mut cache: ResMut<AudioClipCache>) {
+    for handle in audio_assets.handles() {
+        cache.preload(handle);
+    }
+}
+
+/// Plays radio voice lines, they come through the cab speaker so aren't spatial.
+/// Streamed from disk like music, so long transmissions don't sit in memory.
+fn play_radio_messages(
+    mut commands: Commands,
+    mut streams: ResMut<Assets<StreamedAudio>>,
+    settings: Res<AudioSettings>,
+    mut messages: EventReader<RadioMessageEvent>,
+) {
+    for message in messages.read() {
+        let Some(voice_line) = &message.voice_line else {
+            continue;
+        };
+        play_stream(
+            &mut commands,
+            &mut streams,
+            StreamedAudio::new(voice_line),
+            PlaybackSettings::DESPAWN
+                .with_volume(Volume::new_relative(settings.effects_volume * settings.master_volume)),
+        );
+    }
+}
+
+#[derive(Component)]
+pub struct AudioOverlayText;
+
+fn spawn_audio_overlay(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 16.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.0),
+            right: Val::Px(10.0),
+            ..default()
+        }),
+        Visibility::Hidden,
+        AudioOverlayText,
+    ));
+}
+
+fn update_audio_overlay(
+    debug_info: Res<DebugInfo>,
+    cache: Res<AudioClipCache>,
+    sound_pool: Res<SoundEffectPool>,
+    mut texts: Query<(&mut Text, &mut Visibility), With<AudioOverlayText>>,
+) {
+    for (mut text, mut visibility) in texts.iter_mut() {
+        if !debug_info.show_audio_debug {
+            *visibility = Visibility::Hidden;
+            continue;
         }
+        *visibility = Visibility::Visible;
+        let pool = sound_pool.entities.metrics();
+        text.sections[0].value = format!("{}\nSound pool: {pool}", audio_memory_text(&cache));
+        text.sections[0].style.color = if cache.usage().total_bytes() > cache.budget_bytes {
+            Color::ORANGE
+        } else {
+            Color::WHITE
+        };
     }
 }
 
@@ -208,21 +437,30 @@ fn update_spatial_audio(
     }
 }
 
+fn warm_sound_pool(mut commands: Commands, mut sound_pool: ResMut<SoundEffectPool>) {
+    let warm_up = sound_pool.entities.warm_up;
+    sound_pool.entities.warm(&mut commands, warm_up);
+}
+
+/// Stops finished sounds and hands their entities back to the pool
 fn cleanup_finished_sounds(
     mut commands: Commands,
     mut sound_pool: ResMut<SoundEffectPool>,
-    time: Res<Time>,
+    time: Res<Time<Real>>,
 ) {
-    sound_pool.active_sounds.retain(|entity, sound| {
+    let pool = &mut *sound_pool;
+    pool.active_sounds.retain(|entity, sound| {
         sound.elapsed += time.delta_seconds();
-        if let Some(duration) = sound.duration {
-            if sound.elapsed >= duration {
-                commands.entity(*entity).despawn();
-                sound_pool.available_entities.push(*entity);
-                return false;
-            }
+        if sound.elapsed < sound.duration {
+            return true;
         }
-        true
+        // Dropping the sink stops playback, without the source it isn't started again
+        if pool.entities.release(*entity) {
+            commands.entity(*entity).remove::<(AudioSink, Handle<AudioSource>)>();
+        } else {
+            commands.entity(*entity).despawn();
+        }
+        false
     });
 }
 
@@ -245,11 +483,7 @@ fn spawn_or_update_sound(
     .with_volume(Volume::new_relative(volume))
     .with_speed(pitch);
 
-    let entity = if let Some(entity) = sound_pool.available_entities.pop() {
-        entity
-    } else {
-        commands.spawn_empty().id()
-    };
+    let entity = sound_pool.entities.acquire(commands);
 
     commands.entity(entity).insert(AudioBundle {
         source,
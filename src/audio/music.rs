@@ -0,0 +1,411 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    audio::{AudioSinkPlayback, Volume},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{play_stream, AudioSettings, RadioMessageEvent, StreamedAudio};
+use crate::assets::{check_schema_version, read_schema_version, SchemaVersionError};
+use crate::game::{ImpactEvent, PlayerId, RaceFinishedEvent, RacePositionEvent, Vehicle, VehicleUnlockedEvent};
+
+fn default_volume() -> f32 {
+    1.0
+}
+
+fn default_fade() -> f32 {
+    4.0
+}
+
+/// What a piece of music is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MusicMood {
+    /// Exploring and cruising
+    Calm,
+    /// Steep climbs, fast runs, crashes and close races
+    Tense,
+    /// Short one-off played over the music on a success
+    Sting,
+}
+
+/// One layer of a track, streamed in sync with the others
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MusicStem {
+    /// Audio file, relative to the assets directory
+    pub path: String,
+    /// Gameplay intensity (0.0 - 1.0) the stem fades in at, 0.0 always plays
+    #[serde(default)]
+    pub min_intensity: f32,
+    #[serde(default = "default_volume")]
+    pub volume: f32,
+}
+
+/// A stem-based music track, loaded from `*.music.json`
+#[derive(Asset, TypePath, Debug, Clone, Serialize, Deserialize)]
+pub struct MusicTrack {
+    pub name: String,
+    pub mood: MusicMood,
+    pub stems: Vec<MusicStem>,
+    #[serde(default = "default_volume")]
+    pub volume: f32,
+    /// Seconds the track takes to fade in and out
+    #[serde(default = "default_fade")]
+    pub fade: f32,
+    /// Seconds a sting lasts, the music is ducked under it for as long
+    #[serde(default)]
+    pub length: Option<f32>,
+}
+
+/// Errors produced while loading music track files
+#[derive(Debug, thiserror::Error)]
+pub enum MusicTrackError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Schema(#[from] SchemaVersionError),
+}
+
+/// Asset loader for music track files
+#[derive(Default)]
+pub struct MusicTrackLoader;
+
+impl AssetLoader for MusicTrackLoader {
+    type Asset = MusicTrack;
+    type Settings = ();
+    type Error = MusicTrackError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<MusicTrack, MusicTrackError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            check_schema_version(read_schema_version(&bytes)?)?;
+            Ok(serde_json::from_slice(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["music.json"]
+    }
+}
+
+/// Tracks to load and how the music follows the gameplay
+#[derive(Resource, Debug, Clone)]
+pub struct MusicSettings {
+    pub tracks: Vec<String>,
+    /// Intensity the music turns tense above, and calm again below
+    pub tense_above: f32,
+    pub calm_below: f32,
+    /// How fast the intensity follows the gameplay, per second
+    pub intensity_response: f32,
+    /// Damage intensity lost per second after an impact
+    pub damage_decay: f32,
+    /// Music level under radio messages and stings
+    pub radio_duck: f32,
+    pub sting_duck: f32,
+    /// How fast ducking comes and goes, per second
+    pub duck_rate: f32,
+}
+
+impl Default for MusicSettings {
+    fn default() -> Self {
+        Self {
+            tracks: vec![
+                "audio/music/calm_exploration.music.json".to_string(),
+                "audio/music/tense_climb.music.json".to_string(),
+                "audio/music/success_sting.music.json".to_string(),
+            ],
+            tense_above: 0.55,
+            calm_below: 0.35,
+            intensity_response: 0.5,
+            damage_decay: 0.2,
+            radio_duck: 0.35,
+            sting_duck: 0.4,
+            duck_rate: 4.0,
+        }
+    }
+}
+
+/// Loaded music tracks
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MusicLibrary {
+    pub tracks: Vec<Handle<MusicTrack>>,
+}
+
+/// A stem of a track that's playing, fading with its track
+#[derive(Debug, Clone)]
+struct PlayingStem {
+    track: Handle<MusicTrack>,
+    stem: usize,
+    entity: Entity,
+}
+
+/// Where the music is at: how intense the gameplay is, the mood it calls for and what's ducking
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MusicDirector {
+    /// Smoothed gameplay intensity (0.0 - 1.0)
+    pub intensity: f32,
+    pub mood: Option<MusicMood>,
+    /// Fades off after each impact to the player's vehicle
+    pub damage: f32,
+    /// Player's place in a running race and the field size
+    pub race: Option<(u32, u32)>,
+    /// Current music level from ducking (0.0 - 1.0)
+    pub duck: f32,
+    radio_remaining: f32,
+    sting_remaining: f32,
+    /// Each track's fade level
+    track_gains: Vec<(Handle<MusicTrack>, f32)>,
+    playing: Vec<PlayingStem>,
+}
+
+/// Gameplay intensity (0.0 - 1.0) from the vehicle's tilt in radians, its speed in m/s, recent damage
+/// (0.0 - 1.0) and its place in a race
+pub fn gameplay_intensity(slope: f32, speed: f32, damage: f32, race: Option<(u32, u32)>) -> f32 {
+    let slope = (slope / 0.6).clamp(0.0, 1.0);
+    let speed = (speed / 25.0).clamp(0.0, 1.0);
+    // A race is tense all the way, most of all when fighting for the lead
+    let race = race.map_or(0.0, |(position, racers)| {
+        let behind = position.saturating_sub(1) as f32 / racers.saturating_sub(1).max(1) as f32;
+        0.4 + 0.3 * (1.0 - behind.clamp(0.0, 1.0))
+    });
+    (slope * 0.6 + speed * 0.35).max(race).max(damage).clamp(0.0, 1.0)
+}
+
+/// Mood the music moves to at `intensity`, staying put between the thresholds so it doesn't flip back and forth
+pub fn next_mood(current: Option<MusicMood>, intensity: f32, settings: &MusicSettings) -> MusicMood {
+    match current {
+        Some(MusicMood::Tense) if intensity > settings.calm_below => MusicMood::Tense,
+        _ if intensity >= settings.tense_above => MusicMood::Tense,
+        _ => MusicMood::Calm,
+    }
+}
+
+/// Level of a stem fading in at `min_intensity`, fully in a little above it
+pub fn stem_gain(min_intensity: f32, intensity: f32) -> f32 {
+    if min_intensity <= 0.0 {
+        return 1.0;
+    }
+    let t = ((intensity - min_intensity) / 0.15 + 1.0).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn approach(current: f32, target: f32, step: f32) -> f32 {
+    if current < target {
+        (current + step).min(target)
+    } else {
+        (current - step).max(target)
+    }
+}
+
+pub(super) fn load_music_tracks(mut commands: Commands, settings: Res<MusicSettings>, asset_server: Res<AssetServer>) {
+    commands.insert_resource(MusicLibrary {
+        tracks: settings.tracks.iter().map(|path| asset_server.load(path.clone())).collect(),
+    });
+}
+
+/// Reads the gameplay into the music's intensity and mood
+#[allow(clippy::too_many_arguments)]
+pub(super) fn update_music_intensity(
+    time: Res<Time<Real>>,
+    settings: Res<MusicSettings>,
+    mut director: ResMut<MusicDirector>,
+    players: Query<(Entity, &GlobalTransform, &Vehicle), With<PlayerId>>,
+    mut impacts: EventReader<ImpactEvent>,
+    mut positions: EventReader<RacePositionEvent>,
+    mut finishes: EventReader<RaceFinishedEvent>,
+    mut radio: EventReader<RadioMessageEvent>,
+) {
+    let dt = time.delta_seconds();
+    let player = |vehicle: Entity| players.contains(vehicle);
+
+    director.damage = (director.damage - settings.damage_decay * dt).max(0.0);
+    for impact in impacts.read().filter(|impact| player(impact.entity1) || player(impact.entity2)) {
+        director.damage = director.damage.max(impact.intensity);
+    }
+    for race in positions.read().filter(|race| player(race.vehicle)) {
+        director.race = Some((race.position, race.racers));
+    }
+    if finishes.read().any(|race| player(race.vehicle)) {
+        director.race = None;
+    }
+    for message in radio.read() {
+        director.radio_remaining = director.radio_remaining.max(message.duration);
+    }
+    director.radio_remaining = (director.radio_remaining - dt).max(0.0);
+
+    // The most intense of the local players sets the music
+    let target = players
+        .iter()
+        .map(|(_, transform, vehicle)| {
+            let slope = transform.up().angle_between(Vec3::Y);
+            gameplay_intensity(slope, vehicle.vehicle_speed.abs(), director.damage, director.race)
+        })
+        .fold(0.0, f32::max);
+    let blend = 1.0 - (-settings.intensity_response * dt).exp();
+    director.intensity += (target - director.intensity) * blend;
+    director.mood = Some(next_mood(director.mood, director.intensity, &settings));
+}
+
+/// Starts a sting on a podium finish or a mission unlock
+#[allow(clippy::too_many_arguments)]
+pub(super) fn play_music_stings(
+    mut commands: Commands,
+    settings: Res<AudioSettings>,
+    library: Option<Res<MusicLibrary>>,
+    tracks: Res<Assets<MusicTrack>>,
+    mut streams: ResMut<Assets<StreamedAudio>>,
+    mut director: ResMut<MusicDirector>,
+    players: Query<(), With<PlayerId>>,
+    mut finishes: EventReader<RaceFinishedEvent>,
+    mut unlocks: EventReader<VehicleUnlockedEvent>,
+) {
+    let podium = finishes.read().any(|race| players.contains(race.vehicle) && race.position <= 3);
+    let unlocked = unlocks.read().count() > 0;
+    if !podium && !unlocked {
+        return;
+    }
+    let Some(sting) = library
+        .iter()
+        .flat_map(|library| library.tracks.iter())
+        .filter_map(|handle| tracks.get(handle))
+        .find(|track| track.mood == MusicMood::Sting)
+    else {
+        return;
+    };
+    for stem in &sting.stems {
+        let volume = stem.volume * sting.volume * settings.music_volume * settings.master_volume;
+        play_stream(
+            &mut commands,
+            &mut streams,
+            StreamedAudio::new(&stem.path),
+            PlaybackSettings::DESPAWN.with_volume(Volume::new_relative(volume)),
+        );
+    }
+    director.sting_remaining = sting.length.unwrap_or(3.0);
+}
+
+/// Crossfades the tracks of the current mood in and the rest out, layers stems with the intensity
+/// and ducks the music under radio messages and stings
+#[allow(clippy::too_many_arguments)]
+pub(super) fn update_music(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    settings: Res<AudioSettings>,
+    music: Res<MusicSettings>,
+    library: Option<Res<MusicLibrary>>,
+    tracks: Res<Assets<MusicTrack>>,
+    mut streams: ResMut<Assets<StreamedAudio>>,
+    mut director: ResMut<MusicDirector>,
+    sinks: Query<&AudioSink>,
+) {
+    let (Some(library), Some(mood)) = (library, director.mood) else {
+        return;
+    };
+    let dt = time.delta_seconds();
+    director.sting_remaining = (director.sting_remaining - dt).max(0.0);
+
+    let duck_target = match (director.radio_remaining > 0.0, director.sting_remaining > 0.0) {
+        (true, _) => music.radio_duck,
+        (false, true) => music.sting_duck,
+        (false, false) => 1.0,
+    };
+    director.duck = approach(director.duck, duck_target, music.duck_rate * dt);
+
+    let bed = library.tracks.iter().find(|handle| tracks.get(*handle).is_some_and(|track| track.mood == mood));
+    if let Some(bed) = bed {
+        if !director.track_gains.iter().any(|(handle, _)| handle == bed) {
+            director.track_gains.push((bed.clone(), 0.0));
+            let track = tracks.get(bed).expect("bed track is loaded");
+            for (index, stem) in track.stems.iter().enumerate() {
+                let entity = play_stream(
+                    &mut commands,
+                    &mut streams,
+                    StreamedAudio::new(&stem.path),
+                    PlaybackSettings::LOOP.with_volume(Volume::new_relative(0.0)),
+                );
+                director.playing.push(PlayingStem { track: bed.clone(), stem: index, entity });
+            }
+        }
+    }
+
+    let director = &mut *director;
+    for (handle, gain) in director.track_gains.iter_mut() {
+        let fade = tracks.get(&*handle).map_or(default_fade(), |track| track.fade).max(0.1);
+        let target = if Some(&*handle) == bed { 1.0 } else { 0.0 };
+        *gain = approach(*gain, target, dt / fade);
+    }
+    let gains = &director.track_gains;
+    let gain_of = |handle: &Handle<MusicTrack>| {
+        gains.iter().find(|(track, _)| track == handle).map_or(0.0, |(_, gain)| *gain)
+    };
+
+    director.playing.retain(|playing| {
+        let track_gain = gain_of(&playing.track);
+        if track_gain <= 0.0 && Some(&playing.track) != bed {
+            commands.entity(playing.entity).despawn();
+            return false;
+        }
+        if let (Ok(sink), Some(track)) = (sinks.get(playing.entity), tracks.get(&playing.track)) {
+            let stem = &track.stems[playing.stem];
+            let volume = track_gain * stem_gain(stem.min_intensity, director.intensity) * stem.volume * track.volume;
+            sink.set_volume(volume * director.duck * settings.music_volume * settings.master_volume);
+        }
+        true
+    });
+    director.track_gains.retain(|(handle, gain)| *gain > 0.0 || Some(handle) == bed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_music_track() {
+        let json = r#"{
+            "name": "Tense Climb",
+            "mood": "tense",
+            "fade": 2.5,
+            "stems": [
+                { "path": "audio/music/tense_climb_drone.ogg" },
+                { "path": "audio/music/tense_climb_drums.ogg", "min_intensity": 0.7, "volume": 0.8 }
+            ]
+        }"#;
+        let track: MusicTrack = serde_json::from_str(json).unwrap();
+        assert_eq!(track.mood, MusicMood::Tense);
+        assert_eq!(track.stems[0].min_intensity, 0.0);
+        assert_eq!(track.stems[1].volume, 0.8);
+        assert_eq!(track.volume, 1.0);
+        assert_eq!(track.length, None);
+    }
+
+    #[test]
+    fn test_mood_follows_intensity_with_hysteresis() {
+        let settings = MusicSettings::default();
+        let cruising = gameplay_intensity(0.05, 8.0, 0.0, None);
+        let climbing = gameplay_intensity(0.6, 5.0, 0.0, None);
+        assert_eq!(next_mood(None, cruising, &settings), MusicMood::Calm);
+        assert_eq!(next_mood(Some(MusicMood::Calm), climbing, &settings), MusicMood::Tense);
+        // Easing off a little doesn't drop straight back to calm
+        assert_eq!(next_mood(Some(MusicMood::Tense), 0.45, &settings), MusicMood::Tense);
+        assert_eq!(next_mood(Some(MusicMood::Calm), 0.45, &settings), MusicMood::Calm);
+        // Leading a race is tenser than trailing it
+        assert!(gameplay_intensity(0.0, 0.0, 0.0, Some((1, 4))) > gameplay_intensity(0.0, 0.0, 0.0, Some((4, 4))));
+    }
+
+    #[test]
+    fn test_stems_layer_in_with_intensity() {
+        assert_eq!(stem_gain(0.0, 0.0), 1.0);
+        assert_eq!(stem_gain(0.7, 0.4), 0.0);
+        assert_eq!(stem_gain(0.7, 0.7), 1.0);
+        assert!(stem_gain(0.7, 0.62) > 0.0 && stem_gain(0.7, 0.62) < 1.0);
+    }
+}
@@ -0,0 +1,172 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::game::{GameCamera, Vehicle};
+use crate::terrain::TerrainQuery;
+
+/// How sound from distant sources carries over the terrain. A ridge between a source and the listener
+/// muffles it, more the deeper the straight line between them runs under the ground. Bevy's audio has
+/// no filters, so the low-pass is approximated by turning the sound down and playing it lower.
+#[derive(Resource, Debug, Clone)]
+pub struct SoundPropagationSettings {
+    /// Sources closer than this in meters are always heard directly
+    pub min_distance: f32,
+    /// Meters between the height checks along the line to the listener
+    pub step: f32,
+    /// Most height checks on one line, further sources are checked more coarsely
+    pub max_steps: usize,
+    /// Meters the line has to run under a ridge for the sound to be fully muffled
+    pub full_occlusion_depth: f32,
+    /// Volume of a fully muffled sound, relative to an open one
+    pub occluded_volume: f32,
+    /// Playback speed of a fully muffled sound, dull and low
+    pub occluded_pitch: f32,
+    /// How fast the muffling of a moving source follows the terrain, per second
+    pub smoothing: f32,
+}
+
+impl Default for SoundPropagationSettings {
+    fn default() -> Self {
+        Self {
+            min_distance: 25.0,
+            step: 4.0,
+            max_steps: 200,
+            full_occlusion_depth: 12.0,
+            occluded_volume: 0.3,
+            occluded_pitch: 0.85,
+            smoothing: 4.0,
+        }
+    }
+}
+
+/// How much the terrain blocks the line from `emitter` to `listener`, 0.0 in the open and 1.0 behind
+/// a ridge at least `full_occlusion_depth` thick. Marches the line over the heightfield, ground that
+/// isn't loaded doesn't block.
+pub fn ridge_occlusion(
+    terrain: &TerrainQuery,
+    emitter: Vec3,
+    listener: Vec3,
+    settings: &SoundPropagationSettings,
+) -> f32 {
+    let distance = emitter.distance(listener);
+    if distance < settings.min_distance {
+        return 0.0;
+    }
+    let steps = ((distance / settings.step.max(0.1)) as usize).clamp(1, settings.max_steps.max(1));
+    // Both ends sit on or near the ground, only what's between them counts
+    let depth = (1..steps)
+        .filter_map(|step| {
+            let point = emitter.lerp(listener, step as f32 / steps as f32);
+            terrain.height(point.x, point.z).map(|height| height - point.y)
+        })
+        .fold(0.0, f32::max);
+    (depth / settings.full_occlusion_depth.max(f32::EPSILON)).clamp(0.0, 1.0)
+}
+
+/// Volume and playback speed a sound is scaled by on its way to the listener
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Muffling {
+    pub volume: f32,
+    pub pitch: f32,
+}
+
+impl Muffling {
+    pub const NONE: Self = Self { volume: 1.0, pitch: 1.0 };
+
+    pub fn new(occlusion: f32, settings: &SoundPropagationSettings) -> Self {
+        let occlusion = occlusion.clamp(0.0, 1.0);
+        Self {
+            volume: 1.0 + (settings.occluded_volume - 1.0) * occlusion,
+            pitch: 1.0 + (settings.occluded_pitch - 1.0) * occlusion,
+        }
+    }
+}
+
+/// Smoothed occlusion between a moving source and the listener, so driving behind a rock doesn't cut
+/// the sound off in one frame
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct SoundOcclusion(pub f32);
+
+/// Muffling of one-off sounds at a position, by the terrain between it and the game camera
+#[derive(SystemParam)]
+pub struct SoundPropagation<'w, 's> {
+    terrain: Option<Res<'w, TerrainQuery>>,
+    settings: Res<'w, SoundPropagationSettings>,
+    cameras: Query<'w, 's, &'static GlobalTransform, With<GameCamera>>,
+}
+
+impl<'w, 's> SoundPropagation<'w, 's> {
+    /// Occlusion between `position` and the listener, open without a camera or loaded terrain
+    pub fn occlusion(&self, position: Vec3) -> f32 {
+        let (Some(terrain), Some(listener)) = (self.terrain.as_deref(), self.cameras.iter().next()) else {
+            return 0.0;
+        };
+        ridge_occlusion(terrain, position, listener.translation(), &self.settings)
+    }
+
+    pub fn muffling(&self, position: Vec3) -> Muffling {
+        Muffling::new(self.occlusion(position), &self.settings)
+    }
+
+    pub fn settings(&self) -> &SoundPropagationSettings {
+        &self.settings
+    }
+}
+
+/// Follows the terrain between each vehicle and the listener
+pub(super) fn update_sound_occlusion(
+    mut commands: Commands,
+    time: Res<Time>,
+    propagation: SoundPropagation,
+    mut vehicles: Query<(Entity, &GlobalTransform, Option<&mut SoundOcclusion>), With<Vehicle>>,
+) {
+    let blend = (propagation.settings().smoothing * time.delta_seconds()).min(1.0);
+    for (entity, transform, occlusion) in vehicles.iter_mut() {
+        let target = propagation.occlusion(transform.translation());
+        match occlusion {
+            Some(mut occlusion) => occlusion.0 += (target - occlusion.0) * blend,
+            None => {
+                commands.entity(entity).insert(SoundOcclusion(target));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::ChunkHeights;
+
+    fn canyon() -> TerrainQuery {
+        let mut terrain = TerrainQuery::default();
+        // Flat floor with a 30 m ridge a few meters wide across x = 0
+        terrain.insert_chunk(ChunkHeights::from_fn(IVec2::ZERO, 64, |x, _| if x.abs() < 4.0 { 30.0 } else { 0.0 }));
+        terrain
+    }
+
+    #[test]
+    fn test_ridges_muffle_sound() {
+        let settings = SoundPropagationSettings::default();
+        let terrain = canyon();
+        let listener = Vec3::new(-40.0, 1.0, 0.0);
+
+        assert_eq!(ridge_occlusion(&terrain, Vec3::new(40.0, 1.0, 0.0), listener, &settings), 1.0);
+        // Same side of the ridge, or high enough to be heard over it
+        assert_eq!(ridge_occlusion(&terrain, Vec3::new(-10.0, 1.0, 20.0), listener, &settings), 0.0);
+        assert_eq!(ridge_occlusion(&terrain, Vec3::new(40.0, 70.0, 0.0), listener, &settings), 0.0);
+        let over = ridge_occlusion(&terrain, Vec3::new(40.0, 50.0, 0.0), listener, &settings);
+        assert!(over > 0.0 && over < 1.0);
+        // Close sources are never checked
+        assert_eq!(ridge_occlusion(&terrain, Vec3::new(5.0, 1.0, 0.0), Vec3::new(-5.0, 1.0, 0.0), &settings), 0.0);
+    }
+
+    #[test]
+    fn test_muffling_scales_volume_and_pitch() {
+        let settings = SoundPropagationSettings::default();
+        assert_eq!(Muffling::new(0.0, &settings), Muffling::NONE);
+        let muffled = Muffling::new(1.0, &settings);
+        assert_eq!(muffled.volume, settings.occluded_volume);
+        assert_eq!(muffled.pitch, settings.occluded_pitch);
+        assert!(Muffling::new(0.5, &settings).volume > muffled.volume);
+    }
+}
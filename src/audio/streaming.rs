@@ -0,0 +1,107 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use bevy::audio::{Decodable, Source};
+use bevy::prelude::*;
+
+/// A long track played straight from disk, decoding as it goes instead of holding the whole file
+/// in memory like an [`AudioSource`] does. Music and radio play through these.
+#[derive(Asset, TypePath, Debug, Clone, PartialEq)]
+pub struct StreamedAudio {
+    /// File on disk the track is read from
+    pub path: PathBuf,
+}
+
+impl StreamedAudio {
+    /// Streams the file at `asset_path`, relative to the assets directory
+    pub fn new(asset_path: impl AsRef<Path>) -> Self {
+        Self {
+            path: Path::new(env!("CARGO_MANIFEST_DIR")).join("assets").join(asset_path),
+        }
+    }
+}
+
+/// Decodes a streamed track a block at a time as the mixer pulls samples.
+/// A file that can't be opened or decoded plays as silence that ends right away.
+pub struct StreamedAudioDecoder {
+    decoder: Option<rodio::Decoder<BufReader<File>>>,
+}
+
+impl StreamedAudioDecoder {
+    fn open(path: &Path) -> Self {
+        let decoder = File::open(path)
+            .map_err(|error| error.to_string())
+            .and_then(|file| rodio::Decoder::new(BufReader::new(file)).map_err(|error| error.to_string()));
+        match decoder {
+            Ok(decoder) => Self { decoder: Some(decoder) },
+            Err(error) => {
+                warn!("Can't stream {}: {error}", path.display());
+                Self { decoder: None }
+            }
+        }
+    }
+}
+
+impl Iterator for StreamedAudioDecoder {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        self.decoder.as_mut()?.next()
+    }
+}
+
+impl Source for StreamedAudioDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.decoder.as_ref().map_or(Some(0), |decoder| decoder.current_frame_len())
+    }
+
+    fn channels(&self) -> u16 {
+        self.decoder.as_ref().map_or(1, |decoder| decoder.channels())
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.decoder.as_ref().map_or(44_100, |decoder| decoder.sample_rate())
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.decoder.as_ref().map_or(Some(Duration::ZERO), |decoder| decoder.total_duration())
+    }
+}
+
+impl Decodable for StreamedAudio {
+    type DecoderItem = i16;
+    type Decoder = StreamedAudioDecoder;
+
+    fn decoder(&self) -> Self::Decoder {
+        StreamedAudioDecoder::open(&self.path)
+    }
+}
+
+/// Starts streaming a track, returning the entity playing it
+pub fn play_stream(
+    commands: &mut Commands,
+    streams: &mut Assets<StreamedAudio>,
+    audio: StreamedAudio,
+    settings: PlaybackSettings,
+) -> Entity {
+    commands
+        .spawn(AudioSourceBundle {
+            source: streams.add(audio),
+            settings,
+        })
+        .id()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_file_streams_silence() {
+        let mut decoder = StreamedAudio::new("audio/music/does_not_exist.ogg").decoder();
+        assert_eq!(decoder.next(), None);
+        assert_eq!(decoder.total_duration(), Some(Duration::ZERO));
+    }
+}
@@ -0,0 +1,141 @@
+use bevy::prelude::*;
+
+use super::{
+    spawn_or_update_sound, AudioClipCache, AudioSettings, Muffling, SoundCategory, SoundEffectPool, SoundPropagation,
+};
+use crate::game::{GameCamera, LightningStrikeEvent};
+
+/// Meters per second, thunder is heard this long after the flash
+pub const SPEED_OF_SOUND: f32 = 343.0;
+/// Thunder further away than this isn't heard at all
+const AUDIBLE_DISTANCE: f32 = 4000.0;
+/// Longest thunder clip, in seconds
+const THUNDER_LENGTH: f32 = 12.0;
+/// Close cracks first and distant rumbles last, a strike picks among the ones fitting its distance
+const THUNDER_SAMPLES: [&str; 4] = [
+    "sounds/weather/thunder_crack.ogg",
+    "sounds/weather/thunder_clap.ogg",
+    "sounds/weather/thunder_roll.ogg",
+    "sounds/weather/thunder_rumble.ogg",
+];
+
+struct PendingThunder {
+    /// Seconds until the sound gets to the listener
+    delay: f32,
+    distance: f32,
+    variant: f32,
+    /// Ridges between the strike and the listener
+    muffling: Muffling,
+}
+
+/// Thunder on its way from strikes that have been seen but not heard yet
+#[derive(Resource, Default)]
+pub struct ThunderQueue {
+    pending: Vec<PendingThunder>,
+}
+
+impl ThunderQueue {
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// Volume of thunder `distance` meters away, 0.0 once out of earshot
+pub fn thunder_volume(distance: f32) -> f32 {
+    let falloff = 1.0 - (distance / AUDIBLE_DISTANCE).clamp(0.0, 1.0);
+    falloff * falloff
+}
+
+/// Sample for thunder `distance` meters away, `variant` (0.0 - 1.0) picking between the two closest fits
+pub fn thunder_sample(distance: f32, variant: f32) -> &'static str {
+    let last = THUNDER_SAMPLES.len() - 1;
+    let fit = (distance / AUDIBLE_DISTANCE * last as f32).clamp(0.0, last as f32);
+    let index = if variant < 0.5 { fit.floor() } else { fit.ceil() };
+    THUNDER_SAMPLES[index as usize]
+}
+
+/// Starts thunder on its way to the listener for each strike
+pub(super) fn queue_thunder(
+    mut strikes: EventReader<LightningStrikeEvent>,
+    cameras: Query<&GlobalTransform, With<GameCamera>>,
+    propagation: SoundPropagation,
+    mut queue: ResMut<ThunderQueue>,
+) {
+    let Some(listener) = cameras.iter().next() else {
+        strikes.clear();
+        return;
+    };
+    for strike in strikes.read() {
+        let distance = strike.position.distance(listener.translation());
+        if distance >= AUDIBLE_DISTANCE {
+            continue;
+        }
+        queue.pending.push(PendingThunder {
+            delay: distance / SPEED_OF_SOUND,
+            distance,
+            variant: strike.variant,
+            muffling: propagation.muffling(strike.position),
+        });
+    }
+}
+
+/// Plays thunder that has arrived on the ambient bus, deeper the further it has rolled and duller behind ridges
+pub(super) fn play_thunder(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    settings: Res<AudioSettings>,
+    mut cache: ResMut<AudioClipCache>,
+    mut sound_pool: ResMut<SoundEffectPool>,
+    mut queue: ResMut<ThunderQueue>,
+) {
+    let delta = time.delta_seconds();
+    let mut arrived = Vec::new();
+    queue.pending.retain_mut(|thunder| {
+        thunder.delay -= delta;
+        if thunder.delay > 0.0 {
+            return true;
+        }
+        arrived.push((thunder.distance, thunder.variant, thunder.muffling));
+        false
+    });
+
+    for (distance, variant, muffling) in arrived {
+        let source = cache.load(thunder_sample(distance, variant), &asset_server);
+        let pitch = (1.05 - (distance / AUDIBLE_DISTANCE) * 0.25 + (variant - 0.5) * 0.1) * muffling.pitch;
+        spawn_or_update_sound(
+            &mut commands,
+            &mut sound_pool,
+            source,
+            Vec3::ZERO,
+            thunder_volume(distance) * settings.ambient_volume * settings.master_volume * muffling.volume,
+            pitch,
+            SoundCategory::Ambient,
+            false,
+            Some(THUNDER_LENGTH),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thunder_fades_with_distance() {
+        assert_eq!(thunder_volume(0.0), 1.0);
+        assert!(thunder_volume(500.0) > thunder_volume(2000.0));
+        assert_eq!(thunder_volume(AUDIBLE_DISTANCE), 0.0);
+    }
+
+    #[test]
+    fn test_samples_follow_distance() {
+        assert_eq!(thunder_sample(50.0, 0.2), THUNDER_SAMPLES[0]);
+        assert_eq!(thunder_sample(50.0, 0.8), THUNDER_SAMPLES[1]);
+        assert_eq!(thunder_sample(AUDIBLE_DISTANCE * 2.0, 0.8), THUNDER_SAMPLES[3]);
+    }
+}
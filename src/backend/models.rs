@@ -39,4 +39,29 @@ pub struct HealthCheck {
     pub status: String,
     pub version: String,
     pub timestamp: chrono::DateTime<chrono::Utc>,
-} 
\ No newline at end of file
+} 
+/// A listed multiplayer session
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionInfo {
+    pub id: String,
+    pub name: String,
+    pub host: String,
+    pub level: String,
+    pub players: u32,
+    pub max_players: u32,
+}
+
+/// Create session request
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateSessionRequest {
+    pub name: String,
+    pub host: String,
+    pub level: String,
+    pub max_players: u32,
+}
+
+/// Join session request
+#[derive(Debug, Clone, Deserialize)]
+pub struct JoinSessionRequest {
+    pub player: String,
+}
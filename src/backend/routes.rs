@@ -1,6 +1,14 @@
+use std::sync::{Arc, Mutex};
+
 use warp::{Filter, Rejection, Reply};
+use warp::http::StatusCode;
 use serde_json::json;
 
+use super::models::{ApiError, CreateSessionRequest, JoinSessionRequest, SessionInfo};
+
+/// Largest crash report accepted, dumps carry logs and a backtrace
+const MAX_CRASH_REPORT_BYTES: u64 = 1024 * 1024;
+
 /// Health check handler
 pub async fn health_check() -> Result<impl Reply, Rejection> {
     Ok(warp::reply::json(&json!({
@@ -9,11 +17,106 @@ pub async fn health_check() -> Result<impl Reply, Rejection> {
     })))
 }
 
+/// Crash report handler, takes the crash dump the game wrote
+pub async fn crash_report(report: serde_json::Value) -> Result<impl Reply, Rejection> {
+    tracing::warn!(
+        version = %report["version"],
+        reason = %report["reason"],
+        "Crash report received"
+    );
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({ "status": "received" })),
+        StatusCode::ACCEPTED,
+    ))
+}
+
+/// Most players a session can be created for
+const MAX_SESSION_PLAYERS: u32 = 8;
+
+/// Open sessions, kept in memory for as long as the server runs
+#[derive(Debug, Default)]
+pub struct SessionRegistry {
+    next_id: u64,
+    sessions: Vec<SessionInfo>,
+}
+
+pub type Sessions = Arc<Mutex<SessionRegistry>>;
+
+fn error_reply(status: StatusCode, code: &str, message: &str) -> warp::reply::WithStatus<warp::reply::Json> {
+    let error = ApiError { code: code.to_string(), message: message.to_string(), details: None };
+    warp::reply::with_status(warp::reply::json(&error), status)
+}
+
+/// Session list handler
+pub async fn list_sessions(sessions: Sessions) -> Result<impl Reply, Rejection> {
+    let registry = sessions.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    Ok(warp::reply::json(&registry.sessions))
+}
+
+/// Create session handler, the host counts as the first player
+pub async fn create_session(request: CreateSessionRequest, sessions: Sessions) -> Result<impl Reply, Rejection> {
+    if request.name.trim().is_empty() || !(2..=MAX_SESSION_PLAYERS).contains(&request.max_players) {
+        return Ok(error_reply(StatusCode::BAD_REQUEST, "invalid_session", "Session needs a name and 2 - 8 players"));
+    }
+    let mut registry = sessions.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    registry.next_id += 1;
+    let session = SessionInfo {
+        id: registry.next_id.to_string(),
+        name: request.name,
+        host: request.host,
+        level: request.level,
+        players: 1,
+        max_players: request.max_players,
+    };
+    registry.sessions.push(session.clone());
+    Ok(warp::reply::with_status(warp::reply::json(&session), StatusCode::CREATED))
+}
+
+/// Join session handler
+pub async fn join_session(id: String, request: JoinSessionRequest, sessions: Sessions) -> Result<impl Reply, Rejection> {
+    let mut registry = sessions.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let Some(session) = registry.sessions.iter_mut().find(|session| session.id == id) else {
+        return Ok(error_reply(StatusCode::NOT_FOUND, "session_not_found", "No session with that id"));
+    };
+    if session.players >= session.max_players {
+        return Ok(error_reply(StatusCode::CONFLICT, "session_full", "Session is full"));
+    }
+    session.players += 1;
+    tracing::info!(session = %session.id, player = %request.player, "Player joined session");
+    Ok(warp::reply::with_status(warp::reply::json(&*session), StatusCode::OK))
+}
+
 /// Create all routes
 pub fn routes() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
     let health = warp::path("health")
         .and(warp::get())
         .and_then(health_check);
 
-    health
-} 
\ No newline at end of file
+    let crash = warp::path!("telemetry" / "crash")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(MAX_CRASH_REPORT_BYTES))
+        .and(warp::body::json())
+        .and_then(crash_report);
+
+    let sessions: Sessions = Arc::default();
+    let with_sessions = warp::any().map(move || sessions.clone());
+
+    let list = warp::path!("sessions")
+        .and(warp::get())
+        .and(with_sessions.clone())
+        .and_then(list_sessions);
+
+    let create = warp::path!("sessions")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_sessions.clone())
+        .and_then(create_session);
+
+    let join = warp::path!("sessions" / String / "join")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_sessions)
+        .and_then(join_session);
+
+    health.or(crash).or(list).or(create).or(join)
+}
@@ -19,6 +19,69 @@ async fn test_health_check() {
     assert!(body["timestamp"].is_string());
 }
 
+#[tokio::test]
+async fn test_crash_report() {
+    let api = routes::routes();
+
+    let response = request()
+        .method("POST")
+        .path("/telemetry/crash")
+        .json(&serde_json::json!({
+            "version": "0.1.0",
+            "reason": { "kind": "panic", "message": "boom", "location": null },
+        }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), 202);
+
+    let response = request()
+        .method("GET")
+        .path("/telemetry/crash")
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), 405);
+}
+
+#[tokio::test]
+async fn test_sessions() {
+    let api = routes::routes();
+
+    let response = request()
+        .method("POST")
+        .path("/sessions")
+        .json(&serde_json::json!({ "name": "Mud run", "host": "Host", "level": "default", "max_players": 2 }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), 201);
+    let session: SessionInfo = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(session.players, 1);
+
+    let response = request().method("GET").path("/sessions").reply(&api).await;
+    let sessions: Vec<SessionInfo> = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(sessions, vec![session.clone()]);
+
+    let join = |id: &str| {
+        request()
+            .method("POST")
+            .path(&format!("/sessions/{id}/join"))
+            .json(&serde_json::json!({ "player": "Guest" }))
+    };
+    let response = join(&session.id).reply(&api).await;
+    assert_eq!(response.status(), 200);
+    let joined: SessionInfo = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(joined.players, 2);
+    assert_eq!(join(&session.id).reply(&api).await.status(), 409);
+    assert_eq!(join("missing").reply(&api).await.status(), 404);
+
+    let response = request()
+        .method("POST")
+        .path("/sessions")
+        .json(&serde_json::json!({ "name": "", "host": "Host", "level": "default", "max_players": 4 }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), 400);
+}
+
 #[test]
 fn test_backend_config() {
     // Test default config
@@ -6,12 +6,11 @@ mod vehicle;
 
 pub use camera::{MainCamera, CameraFollow};
 pub use player::{Player, PlayerInput};
-pub use vehicle::{Vehicle, Suspension, Wheel};
+pub use vehicle::Wheel;
 
 // Re-export commonly used components
 pub use camera::MainCamera;
 pub use player::Player;
-pub use vehicle::{Vehicle, Suspension};
 
 #[derive(Component)]
 pub struct MainCamera {
@@ -37,31 +36,6 @@ pub struct CameraFollow {
     pub smoothness: f32,
 }
 
-#[derive(Component)]
-pub struct Vehicle {
-    pub steering_angle: f32,
-    pub throttle: f32,
-    pub brake: f32,
-    pub handbrake: bool,
-    pub gear: i32,
-    pub rpm: f32,
-    pub speed: f32,
-}
-
-impl Default for Vehicle {
-    fn default() -> Self {
-        Self {
-            steering_angle: 0.0,
-            throttle: 0.0,
-            brake: 0.0,
-            handbrake: false,
-            gear: 1,
-            rpm: 0.0,
-            speed: 0.0,
-        }
-    }
-}
-
 #[derive(Component)]
 pub struct Player {
     pub name: String,
@@ -79,27 +53,6 @@ impl Default for Player {
     }
 }
 
-#[derive(Component)]
-pub struct Suspension {
-    pub spring_strength: f32,
-    pub damping: f32,
-    pub rest_length: f32,
-    pub max_travel: f32,
-    pub current_compression: f32,
-}
-
-impl Default for Suspension {
-    fn default() -> Self {
-        Self {
-            spring_strength: 50000.0,
-            damping: 4000.0,
-            rest_length: 0.5,
-            max_travel: 0.3,
-            current_compression: 0.0,
-        }
-    }
-}
-
 /// Component for entities that can be interacted with
 #[derive(Component)]
 pub struct Interactable {
@@ -1,32 +1,7 @@
 use bevy::prelude::*;
 use bevy::math::Vec3;
-use crate::game::components::{Vehicle, Suspension, Wheel};
-
-// Helper function to create a test vehicle with default settings
-fn create_test_vehicle() -> Vehicle {
-    Vehicle {
-        mass: 1500.0,
-        max_speed: 150.0,
-        acceleration: 10.0,
-        brake_force: 20.0,
-        steering_angle: 0.0,
-        max_steering_angle: 45.0,
-        steering_speed: 2.0,
-        ..Default::default()
-    }
-}
-
-#[test]
-fn test_vehicle_default() {
-    let vehicle = Vehicle::default();
-    assert!(vehicle.mass > 0.0);
-    assert!(vehicle.max_speed > 0.0);
-    assert!(vehicle.acceleration > 0.0);
-    assert!(vehicle.brake_force > 0.0);
-    assert_eq!(vehicle.steering_angle, 0.0);
-    assert!(vehicle.max_steering_angle > 0.0);
-    assert!(vehicle.steering_speed > 0.0);
-}
+use crate::game::components::Wheel;
+use crate::game::Suspension;
 
 #[test]
 fn test_suspension_default() {
@@ -79,28 +54,6 @@ fn test_wheel_custom_configuration() {
     assert_eq!(custom_wheel.rotation_angle, 45.0);
 }
 
-#[test]
-fn test_vehicle_movement() {
-    let mut vehicle = create_test_vehicle();
-    let initial_speed = vehicle.current_speed;
-    
-    // Test acceleration
-    vehicle.accelerate(1.0, 0.016); // 16ms frame time
-    assert!(vehicle.current_speed > initial_speed);
-    assert!(vehicle.current_speed <= vehicle.max_speed);
-
-    // Test braking
-    let speed_before_brake = vehicle.current_speed;
-    vehicle.brake(1.0, 0.016);
-    assert!(vehicle.current_speed < speed_before_brake);
-
-    // Test steering
-    let initial_angle = vehicle.steering_angle;
-    vehicle.steer(1.0, 0.016); // Turn right
-    assert!(vehicle.steering_angle > initial_angle);
-    assert!(vehicle.steering_angle <= vehicle.max_steering_angle);
-}
-
 #[test]
 fn test_suspension_physics() {
     let mut suspension = Suspension::default();
@@ -125,20 +78,3 @@ fn test_suspension_physics() {
     );
     assert!(max_extension_force <= 0.0);
 }
-
-#[test]
-fn test_vehicle_suspension_integration() {
-    let mut vehicle = create_test_vehicle();
-    let mut suspension = Suspension::default();
-    
-    // Test suspension response to vehicle movement
-    vehicle.accelerate(1.0, 0.016);
-    let force = suspension.calculate_force(
-        vehicle.transform.translation.y,
-        vehicle.transform.translation.y - suspension.rest_length
-    );
-    
-    assert!(force != 0.0); // Suspension should respond to vehicle movement
-    assert!(suspension.current_compression >= 0.0);
-    assert!(suspension.current_compression <= suspension.max_compression);
-} 
\ No newline at end of file
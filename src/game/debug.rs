@@ -1,6 +1,15 @@
+use std::collections::VecDeque;
+
 use bevy::prelude::*;
 use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 use bevy::log::LogPlugin;
+use serde::{Deserialize, Serialize};
+
+mod fly_camera;
+mod profiler;
+
+pub use fly_camera::{FlyCamera, FlyCameraPlugin, FlyCameraSettings, FlyCameraState};
+pub use profiler::{FrameProfiler, GroupProfile, ProfileGroup, RenderTimer, RENDER_TIME};
 
 /// Resource for managing debug visualization states
 #[derive(Resource, Default)]
@@ -9,6 +18,40 @@ pub struct DebugInfo {
     pub show_physics_debug: bool,
     pub show_vehicle_debug: bool,
     pub show_particle_debug: bool,
+    pub show_determinism_debug: bool,
+    pub show_audio_debug: bool,
+    pub show_profiler: bool,
+    pub show_memory: bool,
+    /// Milliseconds the vehicle simulation and physics step took last frame
+    pub physics_time: f32,
+    /// Milliseconds the render app took on its last frame
+    pub render_time: f32,
+    /// Time and entity count of each profiled subsystem, in [`ProfileGroup::ALL`] order
+    pub profile: Vec<GroupProfile>,
+    /// Metrics of the most recent frames, oldest first, at most [`METRICS_HISTORY`] of them
+    pub metrics: VecDeque<FrameMetrics>,
+}
+
+/// Number of frames [`DebugInfo::metrics`] keeps
+pub const METRICS_HISTORY: usize = 300;
+
+/// Timing of one frame
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FrameMetrics {
+    /// Seconds since startup at the end of the frame
+    pub elapsed: f32,
+    pub frame_time_ms: f32,
+    pub entities: u32,
+}
+
+impl DebugInfo {
+    /// Adds a frame's metrics, dropping the oldest once the history is full
+    pub fn record(&mut self, metrics: FrameMetrics) {
+        if self.metrics.len() == METRICS_HISTORY {
+            self.metrics.pop_front();
+        }
+        self.metrics.push_back(metrics);
+    }
 }
 
 /// Plugin for managing debug features and visualization
@@ -21,10 +64,13 @@ impl Plugin for DebugPlugin {
         app.init_resource::<DebugInfo>()
            .add_plugins(LogDiagnosticsPlugin::default())
            .add_plugins(FrameTimeDiagnosticsPlugin::default())
+           .add_plugins(FlyCameraPlugin)
            .add_systems(Update, (
                toggle_debug_info,
                update_debug_display.after(toggle_debug_info)
-           ));
+           ))
+           .add_systems(Last, record_frame_metrics);
+        profiler::build(app);
 
         debug!("Debug Plugin initialized successfully");
     }
@@ -51,6 +97,22 @@ fn toggle_debug_info(
         debug_info.show_particle_debug = !debug_info.show_particle_debug;
         info!("Particle debug toggled: {}", debug_info.show_particle_debug);
     }
+    if keyboard.just_pressed(KeyCode::F7) {
+        debug_info.show_profiler = !debug_info.show_profiler;
+        info!("Profiler toggled: {}", debug_info.show_profiler);
+    }
+    if keyboard.just_pressed(KeyCode::F9) {
+        debug_info.show_memory = !debug_info.show_memory;
+        info!("Memory overlay toggled: {}", debug_info.show_memory);
+    }
+    if keyboard.just_pressed(KeyCode::F11) {
+        debug_info.show_determinism_debug = !debug_info.show_determinism_debug;
+        info!("Determinism debug toggled: {}", debug_info.show_determinism_debug);
+    }
+    if keyboard.just_pressed(KeyCode::F12) {
+        debug_info.show_audio_debug = !debug_info.show_audio_debug;
+        info!("Audio debug toggled: {}", debug_info.show_audio_debug);
+    }
 }
 
 /// System for updating debug display based on active debug flags
@@ -83,6 +145,15 @@ fn update_debug_display(
     }
 }
 
+/// System for keeping the recent frame metrics history
+fn record_frame_metrics(mut debug_info: ResMut<DebugInfo>, time: Res<Time>, entities: &bevy::ecs::entity::Entities) {
+    debug_info.record(FrameMetrics {
+        elapsed: time.elapsed_seconds(),
+        frame_time_ms: time.delta_seconds() * 1000.0,
+        entities: entities.len(),
+    });
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -94,5 +165,20 @@ mod tests {
         assert!(!debug_info.show_physics_debug);
         assert!(!debug_info.show_vehicle_debug);
         assert!(!debug_info.show_particle_debug);
+        assert!(!debug_info.show_determinism_debug);
+    }
+
+    #[test]
+    fn test_metrics_history_drops_oldest() {
+        let mut debug_info = DebugInfo::default();
+        for frame in 0..METRICS_HISTORY + 5 {
+            debug_info.record(FrameMetrics {
+                elapsed: frame as f32,
+                frame_time_ms: 16.0,
+                entities: 0,
+            });
+        }
+        assert_eq!(debug_info.metrics.len(), METRICS_HISTORY);
+        assert_eq!(debug_info.metrics.front().unwrap().elapsed, 5.0);
     }
 } 
\ No newline at end of file
@@ -0,0 +1,343 @@
+use bevy::input::mouse::MouseMotion;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_inspector_egui::bevy_egui::{EguiContext, EguiContexts};
+use bevy_inspector_egui::{bevy_inspector, egui, DefaultInspectorConfigPlugin};
+use bevy_rapier3d::prelude::*;
+
+/// Controls for the detached debug / spectator camera
+#[derive(Resource, Clone, Debug)]
+pub struct FlyCameraSettings {
+    pub toggle_key: KeyCode,
+    /// Base speed in meters per second
+    pub speed: f32,
+    /// Speed multiplier while shift is held
+    pub fast_multiplier: f32,
+    /// Speed multiplier while control is held
+    pub slow_multiplier: f32,
+    /// Radians per pixel of mouse motion while the right button is held
+    pub look_sensitivity: f32,
+    /// Time to glide back to the gameplay camera, in seconds
+    pub return_seconds: f32,
+    /// Longest pick ray in meters
+    pub pick_distance: f32,
+}
+
+impl Default for FlyCameraSettings {
+    fn default() -> Self {
+        Self {
+            toggle_key: KeyCode::F8,
+            speed: 12.0,
+            fast_multiplier: 4.0,
+            slow_multiplier: 0.25,
+            look_sensitivity: 0.003,
+            return_seconds: 0.6,
+            pick_distance: 500.0,
+        }
+    }
+}
+
+/// The detached camera, spawned while fly mode is on
+#[derive(Component, Debug, Clone)]
+pub struct FlyCamera {
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Gliding back toward the gameplay camera, seconds into the glide and the pose it started from
+    returning: Option<(f32, Transform)>,
+}
+
+/// Fly mode state and the entity picked for inspection
+#[derive(Resource, Debug, Default)]
+pub struct FlyCameraState {
+    /// Gameplay camera switched off while flying
+    pub gameplay_camera: Option<Entity>,
+    pub fly_camera: Option<Entity>,
+    pub picked: Option<Entity>,
+}
+
+impl FlyCameraState {
+    pub fn is_flying(&self) -> bool {
+        self.fly_camera.is_some()
+    }
+}
+
+/// Movement speed for the held modifiers
+pub fn fly_speed(settings: &FlyCameraSettings, fast: bool, slow: bool) -> f32 {
+    let mut speed = settings.speed;
+    if fast {
+        speed *= settings.fast_multiplier;
+    }
+    if slow {
+        speed *= settings.slow_multiplier;
+    }
+    speed
+}
+
+/// Yaw and pitch of a rotation, matching [`fly_rotation`]
+pub fn yaw_pitch(rotation: Quat) -> (f32, f32) {
+    let (yaw, pitch, _) = rotation.to_euler(EulerRot::YXZ);
+    (yaw, pitch)
+}
+
+pub fn fly_rotation(yaw: f32, pitch: f32) -> Quat {
+    Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.0)
+}
+
+/// Pose `t` (0.0 - 1.0) of the way from `from` to `to`, eased at both ends
+pub fn glide(from: &Transform, to: &Transform, t: f32) -> Transform {
+    let t = t.clamp(0.0, 1.0);
+    let eased = t * t * (3.0 - 2.0 * t);
+    Transform {
+        translation: from.translation.lerp(to.translation, eased),
+        rotation: from.rotation.slerp(to.rotation, eased),
+        scale: Vec3::ONE,
+    }
+}
+
+/// Swaps between the gameplay camera and the fly camera
+fn toggle_fly_camera(
+    mut commands: Commands,
+    keyboard: Res<Input<KeyCode>>,
+    settings: Res<FlyCameraSettings>,
+    mut state: ResMut<FlyCameraState>,
+    mut cameras: Query<(Entity, &mut Camera, &GlobalTransform), (With<Camera3d>, Without<FlyCamera>)>,
+    mut fly_cameras: Query<(&mut FlyCamera, &Transform)>,
+) {
+    if !keyboard.just_pressed(settings.toggle_key) {
+        return;
+    }
+
+    match state.fly_camera {
+        Some(entity) => {
+            // Glide back rather than cutting, the camera is removed once it arrives
+            if let Ok((mut fly, transform)) = fly_cameras.get_mut(entity) {
+                if fly.returning.is_none() {
+                    fly.returning = Some((0.0, *transform));
+                }
+            }
+        }
+        None => {
+            // The gameplay camera is the highest order 3D camera, offscreen ones like reflections sit below it
+            let Some((gameplay, mut camera, global)) = cameras
+                .iter_mut()
+                .filter(|(_, camera, _)| camera.is_active)
+                .max_by_key(|(_, camera, _)| camera.order)
+            else {
+                return;
+            };
+            let start = global.compute_transform();
+            let (yaw, pitch) = yaw_pitch(start.rotation);
+            camera.is_active = false;
+
+            let fly = commands
+                .spawn((
+                    Camera3dBundle {
+                        camera: Camera { order: camera.order, ..default() },
+                        transform: Transform::from_translation(start.translation).with_rotation(fly_rotation(yaw, pitch)),
+                        ..default()
+                    },
+                    FlyCamera { yaw, pitch, returning: None },
+                    Name::new("Fly Camera"),
+                ))
+                .id();
+            state.gameplay_camera = Some(gameplay);
+            state.fly_camera = Some(fly);
+        }
+    }
+}
+
+/// WASD to move, Q/E down and up, right mouse to look, shift and control for speed
+fn fly_camera_movement(
+    time: Res<Time<Real>>,
+    keyboard: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    settings: Res<FlyCameraSettings>,
+    mut fly_cameras: Query<(&mut FlyCamera, &mut Transform)>,
+) {
+    let look: Vec2 = mouse_motion.read().map(|motion| motion.delta).sum();
+    for (mut fly, mut transform) in fly_cameras.iter_mut() {
+        if fly.returning.is_some() {
+            continue;
+        }
+
+        if mouse_buttons.pressed(MouseButton::Right) {
+            fly.yaw -= look.x * settings.look_sensitivity;
+            fly.pitch = (fly.pitch - look.y * settings.look_sensitivity).clamp(-1.54, 1.54);
+            transform.rotation = fly_rotation(fly.yaw, fly.pitch);
+        }
+
+        let mut direction = Vec3::ZERO;
+        for (key, axis) in [
+            (KeyCode::W, transform.forward()),
+            (KeyCode::S, transform.back()),
+            (KeyCode::A, transform.left()),
+            (KeyCode::D, transform.right()),
+            (KeyCode::E, Vec3::Y),
+            (KeyCode::Q, Vec3::NEG_Y),
+        ] {
+            if keyboard.pressed(key) {
+                direction += axis;
+            }
+        }
+        let fast = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+        let slow = keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+        transform.translation += direction.normalize_or_zero() * fly_speed(&settings, fast, slow) * time.delta_seconds();
+    }
+}
+
+/// Glides the fly camera back onto the gameplay camera, then hands control back
+fn return_to_gameplay_camera(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    settings: Res<FlyCameraSettings>,
+    mut state: ResMut<FlyCameraState>,
+    mut fly_cameras: Query<(&mut FlyCamera, &mut Transform)>,
+    mut cameras: Query<(&mut Camera, &GlobalTransform), Without<FlyCamera>>,
+) {
+    let (Some(fly_entity), Some(gameplay)) = (state.fly_camera, state.gameplay_camera) else {
+        return;
+    };
+    let Ok((mut fly, mut transform)) = fly_cameras.get_mut(fly_entity) else {
+        return;
+    };
+    let Some((elapsed, from)) = fly.returning else {
+        return;
+    };
+
+    let Ok((mut camera, target)) = cameras.get_mut(gameplay) else {
+        // The gameplay camera went away while flying, just drop the fly camera
+        commands.entity(fly_entity).despawn_recursive();
+        *state = FlyCameraState { picked: state.picked, ..default() };
+        return;
+    };
+
+    let elapsed = elapsed + time.delta_seconds();
+    let t = if settings.return_seconds > 0.0 { elapsed / settings.return_seconds } else { 1.0 };
+    // The gameplay camera keeps following its target during the glide, so aim at where it is now
+    *transform = glide(&from, &target.compute_transform(), t);
+    fly.returning = Some((elapsed, from));
+
+    if t >= 1.0 {
+        camera.is_active = true;
+        commands.entity(fly_entity).despawn_recursive();
+        state.fly_camera = None;
+        state.gameplay_camera = None;
+    }
+}
+
+/// Left click picks the collider under the cursor for the inspector
+fn pick_entity(
+    mouse_buttons: Res<Input<MouseButton>>,
+    settings: Res<FlyCameraSettings>,
+    mut state: ResMut<FlyCameraState>,
+    mut egui_contexts: EguiContexts,
+    rapier_context: Option<Res<RapierContext>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    fly_cameras: Query<(&Camera, &GlobalTransform), With<FlyCamera>>,
+) {
+    if !mouse_buttons.just_pressed(MouseButton::Left) || egui_contexts.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let (Some(rapier_context), Ok(window)) = (rapier_context, windows.get_single()) else {
+        return;
+    };
+    let Some(cursor) = window.cursor_position() else {
+        return;
+    };
+    let Some((camera, transform)) = state.fly_camera.and_then(|entity| fly_cameras.get(entity).ok()) else {
+        return;
+    };
+    let Some(ray) = camera.viewport_to_world(transform, cursor) else {
+        return;
+    };
+
+    state.picked = rapier_context
+        .cast_ray(ray.origin, ray.direction, settings.pick_distance, true, QueryFilter::default())
+        .map(|(entity, _)| entity);
+}
+
+/// Inspector window for the picked entity
+fn picked_entity_inspector(world: &mut World) {
+    let Some(picked) = world.resource::<FlyCameraState>().picked else {
+        return;
+    };
+    if world.get_entity(picked).is_none() {
+        world.resource_mut::<FlyCameraState>().picked = None;
+        return;
+    }
+    let Ok(egui_context) = world
+        .query_filtered::<&mut EguiContext, With<PrimaryWindow>>()
+        .get_single(world)
+    else {
+        return;
+    };
+    let mut egui_context = egui_context.clone();
+
+    let mut open = true;
+    egui::Window::new("Picked Entity")
+        .open(&mut open)
+        .default_pos((10.0, 120.0))
+        .show(egui_context.get_mut(), |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                bevy_inspector::ui_for_entity(world, picked, ui);
+            });
+        });
+    if !open {
+        world.resource_mut::<FlyCameraState>().picked = None;
+    }
+}
+
+/// Detached fly camera for debugging and spectating, with click to inspect
+pub struct FlyCameraPlugin;
+
+impl Plugin for FlyCameraPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<DefaultInspectorConfigPlugin>() {
+            app.add_plugins(DefaultInspectorConfigPlugin);
+        }
+        app.init_resource::<FlyCameraSettings>()
+            .init_resource::<FlyCameraState>()
+            .add_systems(Update, (
+                toggle_fly_camera,
+                fly_camera_movement,
+                return_to_gameplay_camera,
+                pick_entity.run_if(|state: Res<FlyCameraState>| state.is_flying()),
+            ).chain())
+            .add_systems(Update, picked_entity_inspector);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speed_modifiers() {
+        let settings = FlyCameraSettings::default();
+        assert_eq!(fly_speed(&settings, false, false), settings.speed);
+        assert_eq!(fly_speed(&settings, true, false), settings.speed * settings.fast_multiplier);
+        assert_eq!(fly_speed(&settings, false, true), settings.speed * settings.slow_multiplier);
+    }
+
+    #[test]
+    fn test_yaw_pitch_round_trip() {
+        let rotation = fly_rotation(0.7, -0.3);
+        let (yaw, pitch) = yaw_pitch(rotation);
+        assert!((yaw - 0.7).abs() < 1e-5);
+        assert!((pitch + 0.3).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_glide_ends_on_target() {
+        let from = Transform::from_xyz(0.0, 10.0, 0.0);
+        let to = Transform::from_xyz(5.0, 2.0, -3.0).looking_at(Vec3::ZERO, Vec3::Y);
+        assert_eq!(glide(&from, &to, 0.0).translation, from.translation);
+        let end = glide(&from, &to, 1.0);
+        assert!((end.translation - to.translation).length() < 1e-5);
+        assert!(end.rotation.angle_between(to.rotation) < 1e-3);
+        // Eased: a quarter of the way in time is less than a quarter of the distance
+        let quarter = glide(&from, &to, 0.25);
+        assert!(quarter.translation.distance(from.translation) < from.translation.distance(to.translation) * 0.25);
+    }
+}
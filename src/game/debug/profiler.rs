@@ -0,0 +1,250 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use bevy::diagnostic::{Diagnostic, DiagnosticId, Diagnostics, RegisterDiagnostic};
+use bevy::prelude::*;
+use bevy::render::{Render, RenderApp, RenderSet};
+use bevy_rapier3d::prelude::*;
+
+use super::DebugInfo;
+use crate::game::{render_available, GameSet, ParticleSystem};
+use crate::terrain::TerrainChunk;
+
+/// Frame time of the render app, registered with Bevy's diagnostics
+pub const RENDER_TIME: DiagnosticId = DiagnosticId::from_u128(0x5a4b_0e1f_93c2_4d6a_b1e0_7f3c_8a21_0001);
+
+/// How quickly the overlay's numbers follow new frames, so they can be read
+const SMOOTHING: f32 = 0.1;
+
+/// Subsystems whose frame time the profiler measures. Plugins put their systems in these sets, the
+/// profiler brackets each set with markers, so a group's time is the wall time from its first system
+/// to its last and includes whatever ran in parallel with it.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProfileGroup {
+    TerrainStreaming,
+    /// Vehicle simulation and the physics step
+    VehiclePhysics,
+    ParticlesPrepare,
+    Audio,
+    Ui,
+}
+
+impl ProfileGroup {
+    pub const ALL: [Self; 5] = [
+        Self::TerrainStreaming,
+        Self::VehiclePhysics,
+        Self::ParticlesPrepare,
+        Self::Audio,
+        Self::Ui,
+    ];
+
+    pub fn name_key(self) -> &'static str {
+        match self {
+            Self::TerrainStreaming => "profiler.terrain_streaming",
+            Self::VehiclePhysics => "profiler.vehicle_physics",
+            Self::ParticlesPrepare => "profiler.particles_prepare",
+            Self::Audio => "profiler.audio",
+            Self::Ui => "profiler.ui",
+        }
+    }
+
+    /// Id of the group's time in Bevy's diagnostics
+    pub fn diagnostic(self) -> DiagnosticId {
+        DiagnosticId::from_u128(0x5a4b_0e1f_93c2_4d6a_b1e0_7f3c_8a21_0100 + self as u128)
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Time and entity count of a profile group, smoothed over recent frames
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GroupProfile {
+    pub group: ProfileGroup,
+    pub cpu_ms: f32,
+    /// Entities the group works on: terrain chunks, rigid bodies, particle systems, playing sounds and UI nodes
+    pub entities: u32,
+}
+
+/// Start and accumulated time of each group in the current frame
+#[derive(Resource, Debug, Default)]
+pub struct FrameProfiler {
+    started: [Option<Instant>; ProfileGroup::ALL.len()],
+    elapsed: [Duration; ProfileGroup::ALL.len()],
+}
+
+impl FrameProfiler {
+    pub fn begin(&mut self, group: ProfileGroup, now: Instant) {
+        self.started[group.index()] = Some(now);
+    }
+
+    /// Adds the time since the group began, a group running in several schedules adds up
+    pub fn end(&mut self, group: ProfileGroup, now: Instant) {
+        if let Some(started) = self.started[group.index()].take() {
+            self.elapsed[group.index()] += now.saturating_duration_since(started);
+        }
+    }
+
+    /// Milliseconds each group took this frame, in [`ProfileGroup::ALL`] order, and starts the next frame
+    pub fn finish_frame(&mut self) -> [f32; ProfileGroup::ALL.len()] {
+        let elapsed = std::mem::take(&mut self.elapsed);
+        self.started = Default::default();
+        elapsed.map(|duration| duration.as_secs_f32() * 1000.0)
+    }
+}
+
+/// Render app frame time, shared between the main and render worlds since rendering runs on its own
+#[derive(Resource, Debug, Clone, Default)]
+pub struct RenderTimer {
+    started: Arc<Mutex<Option<Instant>>>,
+    /// Milliseconds of the last rendered frame, as f32 bits
+    last_ms: Arc<AtomicU32>,
+}
+
+impl RenderTimer {
+    pub fn last_ms(&self) -> f32 {
+        f32::from_bits(self.last_ms.load(Ordering::Relaxed))
+    }
+}
+
+fn begin_group(group: ProfileGroup) -> impl FnMut(ResMut<FrameProfiler>) {
+    move |mut profiler: ResMut<FrameProfiler>| profiler.begin(group, Instant::now())
+}
+
+fn end_group(group: ProfileGroup) -> impl FnMut(ResMut<FrameProfiler>) {
+    move |mut profiler: ResMut<FrameProfiler>| profiler.end(group, Instant::now())
+}
+
+fn begin_render(timer: Res<RenderTimer>) {
+    *timer.started.lock().unwrap() = Some(Instant::now());
+}
+
+fn end_render(timer: Res<RenderTimer>) {
+    if let Some(started) = timer.started.lock().unwrap().take() {
+        timer.last_ms.store((started.elapsed().as_secs_f32() * 1000.0).to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Counts the entities each group works on
+fn count_entities(
+    group: ProfileGroup,
+    chunks: &Query<(), With<TerrainChunk>>,
+    bodies: &Query<(), With<RigidBody>>,
+    particles: &Query<(), With<ParticleSystem>>,
+    sounds: &Query<(), With<AudioSink>>,
+    nodes: &Query<(), With<Node>>,
+) -> u32 {
+    let count = match group {
+        ProfileGroup::TerrainStreaming => chunks.iter().count(),
+        ProfileGroup::VehiclePhysics => bodies.iter().count(),
+        ProfileGroup::ParticlesPrepare => particles.iter().count(),
+        ProfileGroup::Audio => sounds.iter().count(),
+        ProfileGroup::Ui => nodes.iter().count(),
+    };
+    count as u32
+}
+
+/// Hands the frame's group times to the diagnostics and the debug overlay
+#[allow(clippy::too_many_arguments)]
+fn publish_profile(
+    mut profiler: ResMut<FrameProfiler>,
+    timer: Res<RenderTimer>,
+    mut diagnostics: Diagnostics,
+    mut debug_info: ResMut<DebugInfo>,
+    chunks: Query<(), With<TerrainChunk>>,
+    bodies: Query<(), With<RigidBody>>,
+    particles: Query<(), With<ParticleSystem>>,
+    sounds: Query<(), With<AudioSink>>,
+    nodes: Query<(), With<Node>>,
+) {
+    let times = profiler.finish_frame();
+    let render_ms = timer.last_ms();
+    for (group, ms) in ProfileGroup::ALL.into_iter().zip(times) {
+        diagnostics.add_measurement(group.diagnostic(), || ms as f64);
+    }
+    diagnostics.add_measurement(RENDER_TIME, || render_ms as f64);
+
+    let profile = ProfileGroup::ALL
+        .into_iter()
+        .zip(times)
+        .map(|(group, ms)| {
+            let previous = debug_info.profile.iter().find(|profile| profile.group == group);
+            let previous = previous.map_or(ms, |profile| profile.cpu_ms);
+            GroupProfile {
+                group,
+                cpu_ms: previous + (ms - previous) * SMOOTHING,
+                entities: count_entities(group, &chunks, &bodies, &particles, &sounds, &nodes),
+            }
+        })
+        .collect();
+    debug_info.profile = profile;
+    debug_info.physics_time = times[ProfileGroup::VehiclePhysics.index()];
+    debug_info.render_time = render_ms;
+}
+
+pub(super) fn build(app: &mut App) {
+    app.init_resource::<FrameProfiler>()
+        .init_resource::<RenderTimer>()
+        .register_diagnostic(Diagnostic::new(RENDER_TIME, "render_time", 120).with_suffix("ms"));
+    for group in ProfileGroup::ALL {
+        app.register_diagnostic(Diagnostic::new(group.diagnostic(), group.name_key(), 120).with_suffix("ms"));
+    }
+
+    // The vehicle systems run in the simulation stage, rapier steps the world after Update
+    app.configure_sets(Update, GameSet::Simulation.in_set(ProfileGroup::VehiclePhysics))
+        .configure_sets(
+            PostUpdate,
+            (PhysicsSet::SyncBackend, PhysicsSet::StepSimulation, PhysicsSet::Writeback)
+                .in_set(ProfileGroup::VehiclePhysics),
+        );
+    for group in ProfileGroup::ALL {
+        app.add_systems(Update, (begin_group(group).before(group), end_group(group).after(group)));
+    }
+    let physics = ProfileGroup::VehiclePhysics;
+    app.add_systems(PostUpdate, (begin_group(physics).before(physics), end_group(physics).after(physics)))
+        .add_systems(Last, publish_profile);
+
+    if render_available(app) {
+        let timer = app.world.resource::<RenderTimer>().clone();
+        if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.insert_resource(timer).add_systems(Render, (
+                begin_render.in_set(RenderSet::ExtractCommands),
+                end_render.in_set(RenderSet::Cleanup),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_time_adds_up_across_schedules() {
+        let mut profiler = FrameProfiler::default();
+        let start = Instant::now();
+        let group = ProfileGroup::VehiclePhysics;
+        profiler.begin(group, start);
+        profiler.end(group, start + Duration::from_millis(2));
+        profiler.begin(group, start + Duration::from_millis(5));
+        profiler.end(group, start + Duration::from_millis(8));
+        // Ending a group that never began adds nothing
+        profiler.end(ProfileGroup::Audio, start + Duration::from_millis(8));
+
+        let times = profiler.finish_frame();
+        assert!((times[group.index()] - 5.0).abs() < 1e-3);
+        assert_eq!(times[ProfileGroup::Audio.index()], 0.0);
+        assert_eq!(profiler.finish_frame(), [0.0; ProfileGroup::ALL.len()]);
+    }
+
+    #[test]
+    fn test_groups_have_distinct_diagnostics() {
+        for (index, group) in ProfileGroup::ALL.into_iter().enumerate() {
+            assert_eq!(group.index(), index);
+            assert_ne!(group.diagnostic(), RENDER_TIME);
+        }
+        assert_ne!(ProfileGroup::Audio.diagnostic(), ProfileGroup::Ui.diagnostic());
+    }
+}
@@ -0,0 +1,156 @@
+/// Headless simulation without a window or renderer
+///
+/// [`HeadlessPlugin`] sets up the engine side: time, assets, transforms and input, with every
+/// update advancing the world by one fixed timestep so tests and servers can step it exactly.
+/// [`HeadlessSimulationPlugins`] adds the physics, vehicle and terrain simulation on top.
+/// Plugins that draw check [`render_available`] and leave out their rendering parts without one.
+use std::time::Duration;
+
+use bevy::app::{PluginGroupBuilder, ScheduleRunnerPlugin};
+use bevy::audio::AudioSource;
+use bevy::render::RenderApp;
+use bevy::time::TimeUpdateStrategy;
+use bevy_rapier3d::prelude::*;
+
+use super::plugins::WaterPlugin;
+use super::vehicle;
+use crate::terrain::{TerrainFocus, TerrainPlugin};
+
+/// Whether the app has a renderer. Only reliable once the render plugins have been added, which
+/// `DefaultPlugins` and [`HeadlessPlugin`] both do before any gameplay plugin.
+pub fn render_available(app: &App) -> bool {
+    app.get_sub_app(RenderApp).is_ok()
+}
+
+/// Marks an app running headless, with the timestep each update advances
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct Headless {
+    pub timestep: f32,
+}
+
+/// Engine setup for running without a window or renderer
+#[derive(Debug, Clone, Copy)]
+pub struct HeadlessPlugin {
+    /// Seconds each update advances the simulation
+    pub timestep: f32,
+    /// Pace updates to the timestep like a server instead of stepping as fast as possible
+    pub real_time: bool,
+}
+
+impl Default for HeadlessPlugin {
+    fn default() -> Self {
+        Self {
+            timestep: 1.0 / 60.0,
+            real_time: false,
+        }
+    }
+}
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        let timestep = Duration::from_secs_f32(self.timestep);
+        let wait = if self.real_time { timestep } else { Duration::ZERO };
+        app.add_plugins((
+            MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(wait)),
+            AssetPlugin::default(),
+            TransformPlugin,
+            HierarchyPlugin,
+            bevy::input::InputPlugin,
+        ))
+        // Gameplay code still builds meshes and materials and loads sounds, they just never reach a GPU or speaker
+        .init_asset::<Mesh>()
+        .init_asset::<StandardMaterial>()
+        .init_asset::<Image>()
+        .init_asset::<AudioSource>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(timestep))
+        .insert_resource(Headless { timestep: self.timestep })
+        .add_systems(Startup, use_fixed_physics_timestep)
+        .add_systems(PreUpdate, focus_terrain_on_vehicles);
+    }
+}
+
+/// Steps physics by exactly one timestep per update, whatever the wall clock did
+fn use_fixed_physics_timestep(headless: Res<Headless>, rapier_config: Option<ResMut<RapierConfiguration>>) {
+    if let Some(mut rapier_config) = rapier_config {
+        rapier_config.timestep_mode = TimestepMode::Fixed {
+            dt: headless.timestep,
+            substeps: 1,
+        };
+    }
+}
+
+/// Without cameras, terrain loads around the vehicles instead
+fn focus_terrain_on_vehicles(mut commands: Commands, vehicles: Query<Entity, Added<vehicle::Vehicle>>) {
+    for entity in vehicles.iter() {
+        commands.entity(entity).insert(TerrainFocus);
+    }
+}
+
+/// Physics, vehicle, water and terrain simulation, headless
+pub struct HeadlessSimulationPlugins;
+
+impl PluginGroup for HeadlessSimulationPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(HeadlessPlugin::default())
+            .add(crate::physics::PhysicsPlugin)
+            .add(vehicle::VehiclePlugin)
+            .add(vehicle::VehicleDirtPlugin)
+            .add(vehicle::TowingPlugin)
+            .add(vehicle::CargoPlugin)
+            .add(vehicle::FuelPlugin)
+            .add(vehicle::EngineThermalPlugin)
+            .add(vehicle::DriverAssistPlugin)
+            .add(vehicle::DrivetrainPlugin)
+            .add(vehicle::UnderbodyPlugin)
+            .add(vehicle::VehicleCollisionPlugin)
+            .add(vehicle::RecoveryGearPlugin)
+            .add(vehicle::VehicleSpawnerPlugin)
+            .add(WaterPlugin)
+            .add(TerrainPlugin)
+    }
+}
+
+/// Advances a headless app by `ticks` timesteps
+pub fn step(app: &mut App, ticks: usize) {
+    for _ in 0..ticks {
+        app.update();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_headless_has_no_renderer() {
+        let mut app = App::new();
+        app.add_plugins(HeadlessPlugin::default());
+        assert!(!render_available(&app));
+        assert!(app.world.contains_resource::<Assets<Mesh>>());
+    }
+
+    #[test]
+    fn test_each_update_advances_one_timestep() {
+        let mut app = App::new();
+        app.add_plugins(HeadlessPlugin {
+            timestep: 0.05,
+            real_time: false,
+        });
+        // The first update only starts the clock
+        step(&mut app, 1);
+        let start = app.world.resource::<Time>().elapsed_seconds();
+        step(&mut app, 4);
+        let elapsed = app.world.resource::<Time>().elapsed_seconds() - start;
+        assert!((elapsed - 0.2).abs() < 1e-4, "elapsed {elapsed}");
+    }
+
+    #[test]
+    fn test_vehicles_drive_the_terrain() {
+        let mut app = App::new();
+        app.add_plugins(HeadlessPlugin::default());
+        let vehicle = app.world.spawn(vehicle::Vehicle::default()).id();
+        app.update();
+        assert!(app.world.get::<TerrainFocus>(vehicle).is_some());
+    }
+}
@@ -3,7 +3,9 @@ use bevy::render::render_resource::PrimitiveTopology;
 use crate::terrain::TerrainPlugin;
 use bevy_rapier3d::prelude::*;
 use bevy::app::PluginGroupBuilder;
+use crate::physics::ForceAccumulator;
 
+pub mod assets;
 pub mod states;
 pub mod ui;
 #[cfg(test)]
@@ -20,16 +22,26 @@ mod input;
 mod vehicle;
 mod physics;
 mod camera;
+mod headless;
+mod sets;
 
 pub use plugins::*;
 pub use systems::*;
 pub use components::*;
 pub use resources::*;
 
-pub use state::GameState;
-pub use debug::DebugInfo;
+pub use state::{GameState, StateScoped};
+pub use debug::{DebugInfo, FrameMetrics, FrameProfiler, GroupProfile, ProfileGroup, RenderTimer, RENDER_TIME};
 pub use input::InputState;
-pub use vehicle::VehicleConfig;
+pub use vehicle::{
+    needle_angle, Brakes, Bumper, CargoItem, Chassis, Drivetrain, Engine, EngineConfig, JackSide, LiftKit, Part,
+    Recovered, RecoveryAction, RecoveryGear, RecoveryGearConfig, RecoveryRejectedEvent, RecoveryRejection,
+    RecoverySettings, RecoveryTool, Steering, Suspension, SuspensionState, TireType, TractionHint, TractionHintEvent,
+    TransferCase, Transmission, UnderbodyPart, UnderbodyScrapeEvent, UseRecoveryToolEvent, Vehicle, VehicleConfig,
+    Wheel, WheelHub, Winch, SPEEDOMETER_FULL_SCALE,
+};
+pub use headless::{render_available, step, Headless, HeadlessPlugin, HeadlessSimulationPlugins};
+pub use sets::{configure_game_sets, GameSet};
 
 // Constants
 pub mod constants {
@@ -46,7 +58,7 @@ pub mod constants {
 use constants::*;
 
 // Re-export commonly used components
-pub use components::{MainCamera, CameraFollow, Vehicle, Player, Suspension};
+pub use components::{MainCamera, CameraFollow, Player};
 
 /// Main plugin group for the game that sets up all core systems and resources
 pub struct GamePluginGroup;
@@ -59,6 +71,23 @@ impl PluginGroup for GamePluginGroup {
             .add(debug::DebugPlugin) 
             .add(input::InputPlugin)
             .add(vehicle::VehiclePlugin)
+            .add(vehicle::VehicleDirtPlugin)
+            .add(vehicle::PaintReflectionPlugin)
+            .add(vehicle::WheelVisualPlugin)
+            .add(vehicle::VehicleLodPlugin)
+            .add(vehicle::CockpitPlugin)
+            .add(vehicle::DriverPlugin)
+            .add(vehicle::TowingPlugin)
+            .add(vehicle::CargoPlugin)
+            .add(vehicle::FuelPlugin)
+            .add(vehicle::EngineThermalPlugin)
+            .add(vehicle::DriverAssistPlugin)
+            .add(vehicle::TractionAssistPlugin)
+            .add(vehicle::DrivetrainPlugin)
+            .add(vehicle::UnderbodyPlugin)
+            .add(vehicle::VehicleCollisionPlugin)
+            .add(vehicle::RecoveryGearPlugin)
+            .add(vehicle::VehicleSpawnerPlugin)
             .add(physics::PhysicsPlugin)
             .add(camera::CameraPlugin)
             .add(ui::UiPlugin)
@@ -112,74 +141,11 @@ pub fn init_game() -> App {
 #[derive(Component)]
 struct JeepPart;
 
-#[derive(Component)]
-struct Wheel;
-
-#[derive(Component)]
-pub struct Vehicle {
-    pub speed: f32,
-    pub acceleration: f32,
-    pub max_speed: f32,
-    pub turn_speed: f32,
-    pub ground_check_ray: Ray,
-    pub is_grounded: bool,
-}
-
-impl Default for Vehicle {
-    fn default() -> Self {
-        Self {
-            speed: 0.0,
-            acceleration: 15.0,
-            max_speed: 25.0,
-            turn_speed: 2.0,
-            ground_check_ray: Ray {
-                origin: Vec3::ZERO,
-                direction: -Vec3::Y,
-            },
-            is_grounded: false,
-        }
-    }
-}
-
 #[derive(Component)]
 pub struct Player {
     pub health: f32,
 }
 
-#[derive(Component)]
-pub struct Suspension {
-    pub spring_strength: f32,
-    pub damping: f32,
-    pub rest_length: f32,
-    pub min_length: f32,
-    pub max_length: f32,
-    pub max_force: f32,
-    pub wheel_positions: Vec<Vec3>,
-    pub wheel_radius: f32,
-    pub prev_lengths: Vec<f32>,
-}
-
-impl Default for Suspension {
-    fn default() -> Self {
-        Self {
-            spring_strength: 50000.0,
-            damping: 2000.0,
-            rest_length: 0.5,
-            min_length: 0.2,
-            max_length: 0.8,
-            max_force: 50000.0,
-            wheel_positions: vec![
-                Vec3::new(-1.0, 0.0, -1.5),  // Front left
-                Vec3::new(1.0, 0.0, -1.5),   // Front right
-                Vec3::new(-1.0, 0.0, 1.5),   // Rear left
-                Vec3::new(1.0, 0.0, 1.5),    // Rear right
-            ],
-            wheel_radius: 0.4,
-            prev_lengths: vec![0.5; 4],
-        }
-    }
-}
-
 fn setup_game(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -217,17 +183,7 @@ fn setup_game(
     let vehicle_entity = commands.spawn((
         Name::new("Jeep TJ"),
         Player { health: 100.0 },
-        Vehicle {
-            speed: 0.0,
-            max_speed: 30.0,
-            acceleration: 15.0,
-            turn_speed: 2.0,
-            is_grounded: false,
-            ground_check_ray: Ray {
-                origin: Vec3::ZERO,
-                direction: -Vec3::Y,
-            },
-        },
+        (Vehicle::default(), Engine::default(), Transmission::default(), Steering::default(), Brakes::default()),
         RigidBody::Dynamic,
         Collider::cuboid(JEEP_WIDTH/2.0, JEEP_HEIGHT/2.0, JEEP_LENGTH/2.0),
         ColliderMassProperties::Mass(1000.0),
@@ -237,10 +193,21 @@ fn setup_game(
             linear_damping: 0.2,
             angular_damping: 0.2,
         },
-        Suspension::default(),
+        vehicle::Chassis::default(),
+        ExternalForce::default(),
+        ForceAccumulator::default(),
         Transform::from_xyz(0.0, 5.0, 0.0),
         GlobalTransform::default(),
-    )).id();
+    )).with_children(|chassis| {
+        // One hub per corner, each carrying its own suspension
+        for (index, mount) in vehicle::Chassis::default().suspension_points.into_iter().enumerate() {
+            let suspension = Suspension::default();
+            let wheel = mount - Vec3::Y * suspension.rest_length;
+            chassis.spawn((WheelHub::new(index, mount), suspension, TransformBundle::from_transform(
+                Transform::from_translation(wheel),
+            )));
+        }
+    }).id();
 
     // Update camera to follow vehicle
     if let Some(mut camera) = commands.get_entity(camera_entity) {
@@ -328,16 +295,15 @@ fn create_wheel_mesh() -> Mesh {
 
 fn update_vehicle_movement(
     mut query: Query<(
-        &mut ExternalForce,
+        &mut ForceAccumulator,
         &mut Vehicle,
         &Transform,
         &Velocity,
-        &Suspension,
     )>,
     keyboard: Res<Input<KeyCode>>,
     time: Res<Time>,
 ) {
-    for (mut external_force, mut vehicle, transform, velocity, suspension) in query.iter_mut() {
+    for (mut forces, mut vehicle, transform, velocity) in query.iter_mut() {
         let forward = transform.forward();
         let right = transform.right();
         
@@ -361,43 +327,16 @@ fn update_vehicle_movement(
             turn_force += right * 12000.0;
         }
         
-        // Calculate suspension force
-        let mut suspension_force = Vec3::ZERO;
-        let ray_origin = transform.translation;
-        let ray_direction = -Vec3::Y;
-        
-        // Cast ray for ground detection
-        let max_toi = suspension.max_length;
-        let solid = true;
-        
-        // Update vehicle grounded state based on suspension
-        vehicle.is_grounded = false; // Will be set to true if ground is detected
-        
-        // Apply suspension forces
-        let ground_height = ray_origin.y; // This should be replaced with actual raycast result
-        let suspension_length = (suspension.rest_length - ground_height).clamp(
-            suspension.min_length,
-            suspension.max_length
-        );
-        
-        if suspension_length < suspension.max_length {
-            vehicle.is_grounded = true;
-            let spring_force = suspension.spring_strength * (suspension.rest_length - suspension_length);
-            let damping_force = -suspension.damping * velocity.linvel.y;
-            suspension_force = Vec3::new(0.0, spring_force + damping_force, 0.0);
-        }
-        
-        // Combine all forces
-        let total_force = drive_force + turn_force + suspension_force;
+        // Combine all forces, the wheel hubs add their suspension forces alongside
+        let total_force = drive_force + turn_force;
         
         // Apply artificial drag when moving
         let drag_force = -velocity.linvel * 0.5; // Reduced drag coefficient
         
-        // Set the final external force
-        external_force.force = total_force + drag_force;
+        forces.add_force(total_force + drag_force);
         
         // Update vehicle speed for reference
-        vehicle.speed = velocity.linvel.length();
+        vehicle.vehicle_speed = velocity.linvel.length();
     }
 }
 
@@ -423,11 +362,12 @@ fn update_camera_follow(
 
 // Add a debug system to monitor vehicle state
 fn debug_vehicle_state(
-    query: Query<(&Transform, &Vehicle, &Velocity, &ExternalForce, &Name), With<Player>>,
+    query: Query<(Entity, &Transform, &Vehicle, &Velocity, &ExternalForce, &Name), With<Player>>,
+    hubs: Query<(&WheelHub, &Parent)>,
     keyboard: Res<Input<KeyCode>>,
     time: Res<Time>,
 ) {
-    if let Ok((transform, vehicle, velocity, ext_force, name)) = query.get_single() {
+    if let Ok((entity, transform, vehicle, velocity, ext_force, name)) = query.get_single() {
         if keyboard.any_pressed([KeyCode::W, KeyCode::S, KeyCode::A, KeyCode::D]) {
             info!(
                 "Vehicle Debug Info:\n\
@@ -443,10 +383,10 @@ fn debug_vehicle_state(
                 name,
                 transform.translation,
                 transform.rotation,
-                vehicle.speed,
+                vehicle.vehicle_speed,
                 velocity.linvel,
                 ext_force.force,
-                vehicle.is_grounded,
+                hubs.iter().any(|(hub, parent)| parent.get() == entity && hub.state.ground_contact),
                 keyboard.get_pressed().collect::<Vec<_>>(),
                 time.delta_seconds()
             );
@@ -456,71 +396,6 @@ fn debug_vehicle_state(
     }
 }
 
-// Add suspension system
-fn update_suspension(
-    mut vehicle_query: Query<(&mut Suspension, &Transform, &mut ExternalForce), With<Vehicle>>,
-    rapier_context: Res<RapierContext>,
-    mut gizmos: Gizmos,
-) {
-    for (mut suspension, transform, mut ext_force) in vehicle_query.iter_mut() {
-        let mut total_force = Vec3::ZERO;
-        
-        for (i, wheel_pos) in suspension.wheel_positions.iter().enumerate() {
-            // Transform wheel position to world space
-            let world_wheel_pos = transform.transform_point(*wheel_pos);
-            let ray_dir = -transform.up();
-            
-            // Cast ray for suspension
-            let ray = Ray::new(world_wheel_pos, ray_dir);
-            if let Some((_, hit_point)) = rapier_context.cast_ray(
-                ray.origin,
-                ray.direction,
-                2.0,
-                true,
-                QueryFilter::default(),
-            ) {
-                // Calculate suspension force
-                let hit_distance = hit_point * ray.direction.length();
-                let compression = (suspension.rest_length - hit_distance).max(0.0);
-                suspension.compression[i] = compression;
-                
-                // Spring force
-                let spring_force = compression * suspension.spring_strength;
-                
-                // Damping force (using current compression)
-                let damping_force = suspension.compression[i] * suspension.damping;
-                
-                // Total force for this wheel
-                let force = (spring_force + damping_force).min(suspension.max_force);
-                let force_vec = transform.up() * force;
-                
-                suspension.wheel_forces[i] = force_vec;
-                total_force += force_vec;
-                
-                // Visualize suspension rays
-                gizmos.ray(
-                    world_wheel_pos,
-                    ray.direction * 2.0,
-                    if compression > 0.0 { Color::RED } else { Color::GREEN },
-                );
-            } else {
-                suspension.compression[i] = 0.0;
-                suspension.wheel_forces[i] = Vec3::ZERO;
-                
-                // Visualize suspension rays (no hit)
-                gizmos.ray(
-                    world_wheel_pos,
-                    ray.direction * 2.0,
-                    Color::BLUE,
-                );
-            }
-        }
-        
-        // Apply total suspension force
-        ext_force.force += total_force;
-    }
-}
-
 // System implementations
 fn setup_physics(mut commands: Commands) {
     // Initialize physics world and constraints
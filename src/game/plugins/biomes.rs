@@ -0,0 +1,272 @@
+//! What the terrain's biomes mean for the world on top of it
+//!
+//! The terrain's [`BiomeField`] sorts the ground into desert, forest and alpine by its climate maps
+//! and splats each with its own surface layers. Here every chunk that streams in is scattered with
+//! the plants of its biomes, thinning out across a boundary as one biome gives way to the next, and
+//! when the weather changes on its own the new weather is drawn from the odds of the biomes around
+//! the camera. The ambient sound beds are crossfaded by the audio plugin.
+
+use bevy::prelude::*;
+
+use super::seasons::{Season, SeasonSettings, SeasonalVegetation, VegetationKind};
+use super::weather::{Weather, WeatherManager};
+use crate::game::{configure_game_sets, GameSet};
+use crate::terrain::{
+    chunk_origin, Biome, BiomeField, BiomeSample, TerrainChunk, TerrainQuery, TerrainSeed, TerrainSettings, CHUNK_SIZE,
+};
+
+/// Relative odds of each weather in a biome
+pub fn default_weather_odds(biome: Biome) -> Vec<(Weather, f32)> {
+    match biome {
+        Biome::Desert => vec![
+            (Weather::Clear, 6.0),
+            (Weather::Cloudy, 2.0),
+            (Weather::Rain, 0.3),
+            (Weather::Storm, 0.5),
+        ],
+        Biome::Forest => vec![
+            (Weather::Clear, 3.0),
+            (Weather::Cloudy, 3.0),
+            (Weather::Rain, 2.5),
+            (Weather::Storm, 1.0),
+            (Weather::Fog, 1.5),
+            (Weather::Snow, 0.3),
+        ],
+        Biome::Alpine => vec![
+            (Weather::Clear, 2.0),
+            (Weather::Cloudy, 3.0),
+            (Weather::Rain, 0.5),
+            (Weather::Storm, 1.0),
+            (Weather::Fog, 2.0),
+            (Weather::Snow, 3.0),
+        ],
+    }
+}
+
+/// How biomes dress the streamed terrain and steer the weather
+#[derive(Resource, Debug, Clone)]
+pub struct BiomeWorldSettings {
+    /// Relative odds of each weather per biome, in biome order
+    pub weather_odds: [Vec<(Weather, f32)>; 3],
+    /// Seconds between weather rolls, none while a level's weather timeline still has changes to come
+    pub weather_interval: f32,
+    /// Side of the grid cells plants are scattered on, at most one plant per cell
+    pub scatter_cell: f32,
+    /// Slope (1 - normal.y) above which nothing is planted
+    pub scatter_max_slope: f32,
+    /// Meters around the origin left bare, vehicles spawn there
+    pub scatter_clear_radius: f32,
+}
+
+impl Default for BiomeWorldSettings {
+    fn default() -> Self {
+        Self {
+            weather_odds: Biome::ALL.map(default_weather_odds),
+            weather_interval: 300.0,
+            scatter_cell: 5.0,
+            scatter_max_slope: 0.35,
+            scatter_clear_radius: 25.0,
+        }
+    }
+}
+
+impl BiomeWorldSettings {
+    /// Weather odds of the biome mix at a point
+    pub fn weather_odds(&self, sample: &BiomeSample) -> Vec<(Weather, f32)> {
+        Weather::ALL
+            .into_iter()
+            .map(|weather| {
+                let odds = Biome::ALL.into_iter().map(|biome| {
+                    let odds = &self.weather_odds[biome.index()];
+                    let chance = odds.iter().find(|(w, _)| *w == weather).map_or(0.0, |(_, chance)| *chance);
+                    chance * sample.weight(biome)
+                });
+                (weather, odds.sum())
+            })
+            .filter(|(_, odds)| *odds > 0.0)
+            .collect()
+    }
+}
+
+/// Weather the roll (0.0 - 1.0) lands on, `None` when nothing has any odds
+pub fn pick_weather(odds: &[(Weather, f32)], roll: f32) -> Option<Weather> {
+    let total: f32 = odds.iter().map(|(_, odds)| odds.max(0.0)).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let mut remaining = roll.clamp(0.0, 1.0) * total;
+    for (weather, odds) in odds {
+        remaining -= odds.max(0.0);
+        if remaining <= 0.0 {
+            return Some(*weather);
+        }
+    }
+    odds.last().map(|(weather, _)| *weather)
+}
+
+/// Plants of each kind expected in a scatter cell of `cell_size`, from the biome mix
+fn cell_odds(field: &BiomeField, sample: &BiomeSample, cell_size: f32) -> [(VegetationKind, f32); 3] {
+    VegetationKind::ALL.map(|kind| (kind, field.vegetation_density(sample, kind) * cell_size * cell_size / 1000.0))
+}
+
+/// Same value for the same cell and salt on every machine (0.0 - 1.0)
+fn scatter_hash(cell: IVec2, salt: u32, seed: u32) -> f32 {
+    let mut h = (cell.x as u32).wrapping_mul(0x27d4_eb2d)
+        ^ (cell.y as u32).wrapping_mul(0x1656_67b1)
+        ^ salt.wrapping_mul(0x85eb_ca6b)
+        ^ seed.wrapping_mul(0x9e37_79b9);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 13;
+    (h & 0xffff) as f32 / 65535.0
+}
+
+/// Scatters the plants of its biomes over each chunk as it streams in, as children of the chunk
+/// so they unload with it
+#[allow(clippy::too_many_arguments)]
+fn scatter_biome_vegetation(
+    mut commands: Commands,
+    field: Res<BiomeField>,
+    settings: Res<BiomeWorldSettings>,
+    terrain_settings: Res<TerrainSettings>,
+    terrain: Res<TerrainQuery>,
+    seed: Option<Res<TerrainSeed>>,
+    season: Option<Res<SeasonSettings>>,
+    vegetation: Res<SeasonalVegetation>,
+    chunks: Query<(Entity, &TerrainChunk), Added<TerrainChunk>>,
+) {
+    let seed = seed.map_or(0, |seed| seed.0);
+    let season = season.map_or(Season::Summer, |settings| settings.season);
+    let cell_size = settings.scatter_cell.max(0.5);
+    let cells = (CHUNK_SIZE / cell_size).round() as i32;
+
+    for (entity, chunk) in chunks.iter() {
+        let origin = chunk_origin(chunk.coord, &terrain_settings);
+        let corner = origin.xz() - Vec2::splat(CHUNK_SIZE * 0.5);
+        let mut plants = Vec::new();
+        for z in 0..cells {
+            for x in 0..cells {
+                // Cells counted world wide, so the pattern doesn't repeat chunk to chunk
+                let cell = chunk.coord * cells + IVec2::new(x, z);
+                let jitter = Vec2::new(scatter_hash(cell, 1, seed), scatter_hash(cell, 2, seed));
+                let position = corner + (Vec2::new(x as f32, z as f32) + jitter) * cell_size;
+                if position.length() < settings.scatter_clear_radius {
+                    continue;
+                }
+
+                let sample = field.sample(position.x, position.y);
+                let mut roll = scatter_hash(cell, 0, seed);
+                let kind = cell_odds(&field, &sample, cell_size).into_iter().find_map(|(kind, odds)| {
+                    roll -= odds;
+                    (roll < 0.0).then_some(kind)
+                });
+                let Some(kind) = kind else {
+                    continue;
+                };
+                let Some(ground) = terrain.sample(position.x, position.y) else {
+                    continue;
+                };
+                if 1.0 - ground.normal.y > settings.scatter_max_slope {
+                    continue;
+                }
+
+                let scale = 0.8 + scatter_hash(cell, 3, seed) * 0.4;
+                let local = Vec3::new(position.x, ground.height, position.y) - origin;
+                plants.extend(vegetation.spawn(&mut commands, kind, season, local, scale));
+            }
+        }
+        commands.entity(entity).push_children(&plants);
+    }
+}
+
+/// Rolls the next weather from the odds of the biomes around the camera, every so often
+fn roll_biome_weather(
+    time: Res<Time>,
+    field: Res<BiomeField>,
+    settings: Res<BiomeWorldSettings>,
+    mut weather: ResMut<WeatherManager>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    mut since_roll: Local<f32>,
+) {
+    *since_roll += time.delta_seconds();
+    if *since_roll < settings.weather_interval {
+        return;
+    }
+    *since_roll = 0.0;
+    // A level's own timeline has the say until it runs out
+    if !weather.scheduled().is_empty() {
+        return;
+    }
+    let Some((_, transform)) = cameras.iter().find(|(camera, _)| camera.is_active) else {
+        return;
+    };
+    let position = transform.translation();
+    let odds = settings.weather_odds(&field.sample(position.x, position.z));
+    if let Some(next) = pick_weather(&odds, rand::random()) {
+        weather.change_weather(next);
+    }
+}
+
+/// Plugin for biome vegetation and weather
+pub struct BiomesPlugin;
+
+impl Plugin for BiomesPlugin {
+    fn build(&self, app: &mut App) {
+        configure_game_sets(app);
+        app.init_resource::<BiomeWorldSettings>()
+            .init_resource::<BiomeField>()
+            .init_resource::<TerrainQuery>()
+            .init_resource::<TerrainSettings>()
+            .init_resource::<SeasonalVegetation>()
+            .init_resource::<WeatherManager>()
+            .add_systems(Update, (scatter_biome_vegetation, roll_biome_weather).in_set(GameSet::PostSim));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::Climate;
+
+    fn sample(weights: [f32; 3]) -> BiomeSample {
+        BiomeSample {
+            climate: Climate { temperature: 0.5, moisture: 0.5 },
+            weights,
+            biome: Biome::Forest,
+        }
+    }
+
+    #[test]
+    fn test_weather_odds_follow_the_biome_mix() {
+        let settings = BiomeWorldSettings::default();
+        let odds_of = |odds: &[(Weather, f32)], weather: Weather| {
+            odds.iter().find(|(w, _)| *w == weather).map_or(0.0, |(_, odds)| *odds)
+        };
+
+        let desert = settings.weather_odds(&sample([1.0, 0.0, 0.0]));
+        assert_eq!(odds_of(&desert, Weather::Snow), 0.0);
+        assert!(desert.iter().all(|(_, odds)| *odds > 0.0));
+
+        // Halfway into the mountains snow is possible, at half the alpine odds
+        let edge = settings.weather_odds(&sample([0.0, 0.5, 0.5]));
+        assert!((odds_of(&edge, Weather::Snow) - (0.3 + 3.0) * 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_pick_weather_by_odds() {
+        let odds = [(Weather::Clear, 3.0), (Weather::Rain, 1.0)];
+        assert_eq!(pick_weather(&odds, 0.0), Some(Weather::Clear));
+        assert_eq!(pick_weather(&odds, 0.74), Some(Weather::Clear));
+        assert_eq!(pick_weather(&odds, 0.76), Some(Weather::Rain));
+        assert_eq!(pick_weather(&odds, 1.0), Some(Weather::Rain));
+        assert_eq!(pick_weather(&[], 0.5), None);
+    }
+
+    #[test]
+    fn test_scatter_is_repeatable() {
+        let cell = IVec2::new(-14, 37);
+        assert_eq!(scatter_hash(cell, 0, 7), scatter_hash(cell, 0, 7));
+        assert_ne!(scatter_hash(cell, 0, 7), scatter_hash(cell, 0, 8));
+        assert!((0.0..=1.0).contains(&scatter_hash(cell, 3, 7)));
+    }
+}
@@ -0,0 +1,102 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::assets::{check_schema_version, read_schema_version, SchemaVersionError};
+
+/// A boulder field along a spline, as written in a level file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BoulderFieldDesc {
+    pub name: String,
+    /// Control points of the Catmull-Rom spline the field follows. Only X and Z count on generated
+    /// terrain, boulders are grounded on it.
+    pub path: Vec<[f32; 3]>,
+    /// Width of the field across the spline in meters
+    pub width: f32,
+    /// Boulders per 100 square meters
+    pub density: f32,
+    /// Radius of the smallest and largest boulders in meters, small ones are the most common
+    pub min_radius: f32,
+    pub max_radius: f32,
+    /// Boulders smaller than this are loose and can be knocked around, bigger ones never move
+    #[serde(default)]
+    pub loose_below: f32,
+    /// Same seed, same field
+    #[serde(default)]
+    pub seed: u64,
+}
+
+/// Boulder fields of a level, loaded from `*.boulders.json`
+#[derive(Asset, TypePath, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LevelBoulders {
+    pub fields: Vec<BoulderFieldDesc>,
+}
+
+/// Errors produced while loading level boulder files
+#[derive(Debug, thiserror::Error)]
+pub enum LevelBouldersError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Schema(#[from] SchemaVersionError),
+}
+
+/// Asset loader for level boulder files
+#[derive(Default)]
+pub struct LevelBouldersLoader;
+
+impl AssetLoader for LevelBouldersLoader {
+    type Asset = LevelBoulders;
+    type Settings = ();
+    type Error = LevelBouldersError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<LevelBoulders, LevelBouldersError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            check_schema_version(read_schema_version(&bytes)?)?;
+            Ok(serde_json::from_slice(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["boulders.json"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_level_boulders() {
+        let json = r#"{
+            "fields": [
+                {
+                    "name": "Rock Garden",
+                    "path": [[0.0, 0.0, 0.0], [10.0, 0.0, -30.0], [25.0, 0.0, -50.0]],
+                    "width": 8.0,
+                    "density": 6.0,
+                    "min_radius": 0.3,
+                    "max_radius": 1.2,
+                    "loose_below": 0.5
+                }
+            ]
+        }"#;
+        let level: LevelBoulders = serde_json::from_str(json).unwrap();
+        let field = &level.fields[0];
+        assert_eq!(field.path.len(), 3);
+        assert_eq!(field.loose_below, 0.5);
+        assert_eq!(field.seed, 0);
+    }
+}
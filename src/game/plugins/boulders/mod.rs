@@ -0,0 +1,369 @@
+/// Boulder fields for rock-crawling sections
+///
+/// Fields come from a level's `*.boulders.json` file and follow a spline through the level.
+/// Boulders are convex hulls of a few shared rock shapes, grounded analytically on the terrain
+/// so they start out resting instead of settling under physics. Every boulder draws one of the
+/// shared meshes with the shared material, which Bevy batches into instanced draws.
+mod level;
+
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy_rapier3d::parry::math::Point;
+use bevy_rapier3d::parry::transformation::convex_hull;
+use bevy_rapier3d::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+pub use level::{BoulderFieldDesc, LevelBoulders, LevelBouldersError, LevelBouldersLoader};
+
+use super::impacts::SurfaceMaterial;
+use crate::terrain::{
+    ground_height, spline_points, terrain_noise, Drivability, DrivabilityBlocker, TerrainCarves, TerrainErosion,
+    TerrainSeed, TerrainSettings,
+};
+
+/// Rock shapes shared by all boulders
+pub const BOULDER_VARIANTS: usize = 6;
+/// Share of a boulder's radius sunk into the ground, so it sits in it rather than on it
+const EMBED: f32 = 0.15;
+
+/// Convex rock shape at radius 1, resting on its largest face at y = 0
+#[derive(Debug, Clone)]
+pub struct BoulderShape {
+    pub vertices: Vec<Vec3>,
+    pub indices: Vec<[u32; 3]>,
+}
+
+impl BoulderShape {
+    pub fn generate(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let squash = rng.gen_range(0.55..0.85);
+        let points: Vec<Point<f32>> = (0..24)
+            .map(|_| {
+                let direction = Vec3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+                let point = direction.normalize_or_zero() * rng.gen_range(0.75..1.0) * Vec3::new(1.0, squash, 1.0);
+                Point::new(point.x, point.y, point.z)
+            })
+            .collect();
+        let (hull, indices) = convex_hull(&points);
+        let vertices: Vec<Vec3> = hull.iter().map(|point| Vec3::new(point.x, point.y, point.z)).collect();
+
+        // Largest face down, the widest base the rock can rest on
+        let face_normal = |[a, b, c]: [u32; 3]| {
+            let corner = vertices[a as usize];
+            (vertices[b as usize] - corner).cross(vertices[c as usize] - corner)
+        };
+        let base = indices
+            .iter()
+            .map(|triangle| face_normal(*triangle))
+            .max_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+            .unwrap_or(Vec3::NEG_Y);
+        let rotation = Quat::from_rotation_arc(base.normalize(), Vec3::NEG_Y);
+        let mut vertices: Vec<Vec3> = vertices.into_iter().map(|vertex| rotation * vertex).collect();
+        let bottom = vertices.iter().map(|vertex| vertex.y).fold(f32::INFINITY, f32::min);
+        let center = vertices.iter().sum::<Vec3>() / vertices.len().max(1) as f32;
+        for vertex in &mut vertices {
+            *vertex -= Vec3::new(center.x, bottom, center.z);
+        }
+        Self { vertices, indices }
+    }
+
+    /// Flat shaded mesh of the hull
+    pub fn mesh(&self) -> Mesh {
+        let mut positions = Vec::with_capacity(self.indices.len() * 3);
+        let mut normals = Vec::with_capacity(self.indices.len() * 3);
+        for triangle in &self.indices {
+            let corners = triangle.map(|index| self.vertices[index as usize]);
+            let normal = (corners[1] - corners[0]).cross(corners[2] - corners[0]).normalize_or_zero();
+            for corner in corners {
+                positions.push(corner.to_array());
+                normals.push(normal.to_array());
+            }
+        }
+        let count = positions.len() as u32;
+
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        mesh.set_indices(Some(Indices::U32((0..count).collect())));
+        mesh
+    }
+
+    pub fn collider(&self) -> Collider {
+        Collider::convex_hull(&self.vertices).unwrap_or_else(|| Collider::ball(0.5))
+    }
+}
+
+/// Where one boulder of a field goes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoulderPlacement {
+    /// Scaled by the radius, the shapes are built at radius 1
+    pub transform: Transform,
+    pub radius: f32,
+    /// Index into the shared shapes
+    pub variant: usize,
+}
+
+/// A boulder of a field
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Boulder {
+    pub radius: f32,
+    /// Whether it can be knocked around, bigger boulders are fixed
+    pub loose: bool,
+}
+
+/// Marks level entities whose boulder fields have been spawned
+#[derive(Component)]
+pub struct LevelBouldersSpawned;
+
+/// Shared meshes and colliders of the boulder shapes, and the rock material
+#[derive(Resource, Default)]
+struct BoulderAssets {
+    shapes: Vec<(Handle<Mesh>, Collider)>,
+    material: Handle<StandardMaterial>,
+}
+
+/// Rests a boulder on ground of `height`(x, z): tilted to the slope, sunk until no part of its base
+/// hangs over a dip, then sunk a little more to sit in the ground
+pub fn ground_boulder(center: Vec2, radius: f32, yaw: f32, height: &impl Fn(f32, f32) -> f32) -> Transform {
+    let height_at = |point: Vec2| height(point.x, point.y);
+    let step = radius.max(0.1);
+    let gradient = Vec2::new(
+        height_at(center + Vec2::X * step) - height_at(center - Vec2::X * step),
+        height_at(center + Vec2::Y * step) - height_at(center - Vec2::Y * step),
+    ) / (2.0 * step);
+    let normal = Vec3::new(-gradient.x, 1.0, -gradient.y).normalize();
+    let ground = height_at(center);
+
+    // Drop of the ground below the tilted base around its rim
+    let hang = (0..8)
+        .map(|i| {
+            let offset = Vec2::from_angle(i as f32 * TAU / 8.0) * radius * 0.7;
+            ground + gradient.dot(offset) - height_at(center + offset)
+        })
+        .fold(0.0, f32::max);
+
+    Transform::from_xyz(center.x, ground - hang - radius * EMBED, center.y)
+        .with_rotation(Quat::from_rotation_arc(Vec3::Y, normal) * Quat::from_rotation_y(yaw))
+        .with_scale(Vec3::splat(radius))
+}
+
+/// Lays out a field's boulders on ground of `height`(x, z). Boulders that would overlap one already
+/// placed are dropped, so a crowded field comes out a little sparse rather than interpenetrating.
+pub fn place_boulders(desc: &BoulderFieldDesc, height: impl Fn(f32, f32) -> f32) -> Vec<BoulderPlacement> {
+    let path: Vec<Vec3> = desc.path.iter().map(|point| Vec3::from(*point)).collect();
+    let points: Vec<Vec2> = spline_points(&path, 1.0).iter().map(|point| point.xz()).collect();
+    let lengths: Vec<f32> = points
+        .windows(2)
+        .scan(0.0, |total, pair| {
+            *total += pair[0].distance(pair[1]);
+            Some(*total)
+        })
+        .collect();
+    let length = lengths.last().copied().unwrap_or(0.0);
+    if length <= 0.0 || desc.width <= 0.0 {
+        return Vec::new();
+    }
+
+    let count = (length * desc.width * desc.density / 100.0).round() as usize;
+    let mut rng = StdRng::seed_from_u64(desc.seed);
+    let mut placements: Vec<BoulderPlacement> = Vec::with_capacity(count);
+    for _ in 0..count * 8 {
+        if placements.len() >= count {
+            break;
+        }
+        let along = rng.gen_range(0.0..length);
+        let across = rng.gen_range(-0.5..0.5) * desc.width;
+        // Small rocks are the most common
+        let radius = desc.min_radius + (desc.max_radius - desc.min_radius).max(0.0) * rng.gen::<f32>().powi(2);
+        let variant = rng.gen_range(0..BOULDER_VARIANTS);
+        let yaw = rng.gen_range(0.0..TAU);
+
+        let segment = lengths.partition_point(|end| *end < along).min(lengths.len() - 1);
+        let start = if segment == 0 { 0.0 } else { lengths[segment - 1] };
+        let span = lengths[segment] - start;
+        let (a, b) = (points[segment], points[segment + 1]);
+        let t = if span > 0.0 { (along - start) / span } else { 0.0 };
+        let center = a.lerp(b, t) + (b - a).normalize_or_zero().perp() * across;
+
+        let overlaps = placements
+            .iter()
+            .any(|placed| placed.transform.translation.xz().distance(center) < placed.radius + radius);
+        if !overlaps {
+            let transform = ground_boulder(center, radius, yaw, &height);
+            placements.push(BoulderPlacement { transform, radius, variant });
+        }
+    }
+    placements
+}
+
+fn setup_boulder_assets(
+    mut assets: ResMut<BoulderAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    assets.shapes = (0..BOULDER_VARIANTS as u64)
+        .map(|seed| {
+            let shape = BoulderShape::generate(seed);
+            (meshes.add(shape.mesh()), shape.collider())
+        })
+        .collect();
+    assets.material = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.45, 0.42, 0.38),
+        perceptual_roughness: 0.85,
+        ..default()
+    });
+}
+
+/// Spawns the boulder fields of loaded level boulder assets as children of the level entity.
+/// Fields lie on the generated terrain, erosion and carved trails included,
+/// or flat at their first point's height without one.
+#[allow(clippy::too_many_arguments)]
+fn spawn_level_boulders(
+    mut commands: Commands,
+    levels: Query<(Entity, &Handle<LevelBoulders>), Without<LevelBouldersSpawned>>,
+    level_boulders: Res<Assets<LevelBoulders>>,
+    assets: Res<BoulderAssets>,
+    terrain: Option<Res<TerrainSettings>>,
+    seed: Option<Res<TerrainSeed>>,
+    erosion: Option<Res<TerrainErosion>>,
+    carves: Option<Res<TerrainCarves>>,
+) {
+    let no_erosion = TerrainErosion::default();
+    let erosion = erosion.as_deref().unwrap_or(&no_erosion);
+    let no_carves = TerrainCarves::default();
+    let carves = carves.as_deref().unwrap_or(&no_carves);
+    let terrain = terrain.map(|settings| {
+        let noise = terrain_noise(&settings, seed.map_or(0, |seed| seed.0));
+        (settings, noise)
+    });
+    for (level, handle) in levels.iter() {
+        let Some(boulders) = level_boulders.get(handle) else {
+            continue;
+        };
+
+        let mut children = Vec::new();
+        for field in &boulders.fields {
+            let placements = match &terrain {
+                Some((settings, noise)) => {
+                    place_boulders(field, |x, z| ground_height(noise, settings, erosion, carves, x, z))
+                }
+                None => {
+                    let flat = field.path.first().map_or(0.0, |point| point[1]);
+                    place_boulders(field, |_, _| flat)
+                }
+            };
+            for placement in placements {
+                let Some((mesh, collider)) = assets.shapes.get(placement.variant) else {
+                    continue;
+                };
+                let loose = placement.radius < field.loose_below;
+                let mut boulder = commands.spawn((
+                    PbrBundle {
+                        mesh: mesh.clone(),
+                        material: assets.material.clone(),
+                        transform: placement.transform,
+                        ..default()
+                    },
+                    collider.clone(),
+                    Friction::coefficient(0.9),
+                    SurfaceMaterial::Rock,
+                    Boulder { radius: placement.radius, loose },
+                    Name::new("Boulder"),
+                ));
+                if loose {
+                    // Asleep until something hits it, so it stays exactly where it was grounded
+                    boulder.insert((
+                        RigidBody::Dynamic,
+                        ColliderMassProperties::Density(2600.0),
+                        Sleeping { sleeping: true, ..default() },
+                    ));
+                } else {
+                    // Loose ones get pushed aside, fixed ones have to be driven around
+                    let blocker = DrivabilityBlocker {
+                        half_size: Vec2::splat(placement.radius),
+                        drivability: Drivability::Impassable,
+                    };
+                    boulder.insert((RigidBody::Fixed, blocker));
+                }
+                children.push(boulder.id());
+            }
+        }
+        commands.entity(level).push_children(&children).insert(LevelBouldersSpawned);
+    }
+}
+
+/// Plugin for level boulder fields
+pub struct BoulderPlugin;
+
+impl Plugin for BoulderPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BoulderAssets>()
+            .init_asset::<LevelBoulders>()
+            .init_asset_loader::<LevelBouldersLoader>()
+            .add_systems(Startup, setup_boulder_assets)
+            .add_systems(Update, spawn_level_boulders);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(seed: u64) -> BoulderFieldDesc {
+        BoulderFieldDesc {
+            name: "Rock Garden".to_string(),
+            path: vec![[0.0, 0.0, 0.0], [0.0, 0.0, -20.0], [5.0, 0.0, -40.0]],
+            width: 6.0,
+            density: 15.0,
+            min_radius: 0.3,
+            max_radius: 1.0,
+            loose_below: 0.5,
+            seed,
+        }
+    }
+
+    #[test]
+    fn test_shapes_rest_on_a_flat_base() {
+        for seed in 0..BOULDER_VARIANTS as u64 {
+            let shape = BoulderShape::generate(seed);
+            let bottom = shape.vertices.iter().map(|vertex| vertex.y).fold(f32::INFINITY, f32::min);
+            assert!(bottom.abs() < 1e-4);
+            // A whole face on the ground, not a point
+            assert!(shape.vertices.iter().filter(|vertex| vertex.y < 1e-3).count() >= 3);
+            assert!(shape.vertices.iter().all(|vertex| vertex.xz().length() < 1.3));
+        }
+    }
+
+    #[test]
+    fn test_boulders_stay_in_the_field_without_overlapping() {
+        let placements = place_boulders(&field(7), |_, _| 0.0);
+        assert!(placements.len() > 10);
+        for (i, a) in placements.iter().enumerate() {
+            let position = a.transform.translation;
+            assert!(a.radius >= 0.3 && a.radius <= 1.0);
+            assert!(position.x.abs() <= 5.0 + 3.0 && position.z <= 3.0 && position.z >= -43.0);
+            assert!(position.y <= 0.0, "boulder floats above flat ground");
+            for b in &placements[i + 1..] {
+                assert!(position.xz().distance(b.transform.translation.xz()) >= a.radius + b.radius - 1e-4);
+            }
+        }
+        assert_eq!(place_boulders(&field(7), |_, _| 0.0), placements);
+        assert_ne!(place_boulders(&field(8), |_, _| 0.0), placements);
+    }
+
+    #[test]
+    fn test_grounding_follows_the_slope() {
+        let ramp = |x: f32, _z: f32| x * 0.5;
+        let transform = ground_boulder(Vec2::new(4.0, 0.0), 1.0, 0.3, &ramp);
+        let normal = Vec3::new(-0.5, 1.0, 0.0).normalize();
+        assert!(transform.up().abs_diff_eq(normal, 1e-3));
+        assert!(transform.translation.y < ramp(4.0, 0.0));
+
+        // Over a dip the base sinks until its rim is in the ground
+        let bowl = |x: f32, z: f32| -(x * x + z * z) * 0.2;
+        let transform = ground_boulder(Vec2::ZERO, 1.0, 0.0, &bowl);
+        assert!(transform.translation.y <= bowl(0.7, 0.0) - EMBED + 1e-4);
+    }
+}
@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::Velocity;
+
+use super::GameCamera;
+use crate::game::vehicle::{CockpitLayout, Vehicle};
+
+/// Standard gravity, g-forces are in multiples of it
+const GRAVITY: f32 = 9.81;
+
+/// How the driver's head moves in the cockpit view
+#[derive(Resource, Debug, Clone)]
+pub struct CockpitCameraSettings {
+    /// Switches between the chase and the cockpit view
+    pub toggle_key: KeyCode,
+    /// Head travel per g of acceleration, in meters
+    pub lean_per_g: f32,
+    /// Furthest the head moves off the seat position
+    pub max_lean: f32,
+    /// Head tilt per g of sideways acceleration, and the most it tilts, in radians
+    pub roll_per_g: f32,
+    pub max_roll: f32,
+    /// How fast the head follows the g-forces, per second. Lower is softer.
+    pub head_response: f32,
+}
+
+impl Default for CockpitCameraSettings {
+    fn default() -> Self {
+        Self {
+            toggle_key: KeyCode::Tab,
+            lean_per_g: 0.06,
+            max_lean: 0.1,
+            roll_per_g: 0.04,
+            max_roll: 0.08,
+            head_response: 6.0,
+        }
+    }
+}
+
+/// Puts a game camera in its target's cab, at the driver's eyes. Holds the smoothed head movement.
+#[derive(Component, Debug, Clone, Default)]
+pub struct CockpitView {
+    /// Head offset from the eye position in chassis space
+    pub lean: Vec3,
+    /// Head tilt about the chassis' Z axis, negative tips it to the right
+    pub roll: f32,
+    /// Target's velocity last frame, to get its acceleration from
+    last_velocity: Option<Vec3>,
+}
+
+/// Head offset (in chassis space) and tilt the driver is pushed to by `acceleration`, given in chassis
+/// space in m/s². The head swings against the acceleration, out of a turn and forward under braking.
+pub fn head_lean(acceleration: Vec3, settings: &CockpitCameraSettings) -> (Vec3, f32) {
+    let g = acceleration / GRAVITY;
+    let lean = (-Vec3::new(g.x, 0.0, g.z) * settings.lean_per_g).clamp_length_max(settings.max_lean);
+    // Rolls towards the side the head swings to
+    let roll = (g.x * settings.roll_per_g).clamp(-settings.max_roll, settings.max_roll);
+    (lean, roll)
+}
+
+/// Switches every game camera between the chase and the cockpit view
+pub(super) fn toggle_cockpit_view(
+    mut commands: Commands,
+    keyboard: Option<Res<Input<KeyCode>>>,
+    settings: Res<CockpitCameraSettings>,
+    cameras: Query<(Entity, Has<CockpitView>), With<GameCamera>>,
+) {
+    if !keyboard.is_some_and(|keyboard| keyboard.just_pressed(settings.toggle_key)) {
+        return;
+    }
+    for (camera, cockpit) in cameras.iter() {
+        if cockpit {
+            commands.entity(camera).remove::<CockpitView>();
+        } else {
+            commands.entity(camera).insert(CockpitView::default());
+        }
+    }
+}
+
+/// Seats cockpit cameras at their driver's eyes, leaning the head with the g-forces
+pub(super) fn update_cockpit_camera(
+    time: Res<Time>,
+    settings: Res<CockpitCameraSettings>,
+    mut cameras: Query<(&mut Transform, &GameCamera, &mut CockpitView)>,
+    targets: Query<(&Transform, &Vehicle, Option<&Velocity>), Without<GameCamera>>,
+) {
+    let dt = time.delta_seconds();
+    for (mut transform, game_camera, mut view) in cameras.iter_mut() {
+        let Some((target, vehicle, velocity)) = game_camera.target.and_then(|target| targets.get(target).ok()) else {
+            continue;
+        };
+
+        let velocity = velocity.map_or(Vec3::ZERO, |velocity| velocity.linvel);
+        let acceleration = match view.last_velocity {
+            Some(last) if dt > 0.0 => (velocity - last) / dt,
+            _ => Vec3::ZERO,
+        };
+        view.last_velocity = Some(velocity);
+        let (lean, roll) = head_lean(target.rotation.inverse() * acceleration, &settings);
+        let blend = 1.0 - (-settings.head_response * dt).exp();
+        view.lean = view.lean.lerp(lean, blend);
+        view.roll += (roll - view.roll) * blend;
+
+        let eye = CockpitLayout::for_config(&vehicle.config).eye + view.lean;
+        transform.translation = target.transform_point(eye);
+        transform.rotation = target.rotation * Quat::from_rotation_z(view.roll);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_head_swings_against_the_g_forces() {
+        let settings = CockpitCameraSettings::default();
+        // Turning left pulls the vehicle towards -X, the head goes right
+        let (lean, roll) = head_lean(Vec3::new(-GRAVITY, 0.0, 0.0), &settings);
+        assert!(lean.x > 0.0 && roll < 0.0);
+        // Braking decelerates towards +Z, the head goes forward
+        let (lean, _) = head_lean(Vec3::new(0.0, 0.0, GRAVITY), &settings);
+        assert!(lean.z < 0.0);
+        // A hard landing doesn't throw it out of the cab
+        let (lean, _) = head_lean(Vec3::new(40.0 * GRAVITY, 0.0, 40.0 * GRAVITY), &settings);
+        assert!(lean.length() <= settings.max_lean + 1e-5);
+    }
+}
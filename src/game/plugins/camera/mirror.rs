@@ -0,0 +1,161 @@
+use bevy::{
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+    },
+};
+
+use super::GameCamera;
+use crate::game::plugins::PerformanceBudget;
+use crate::game::{GameSettings, MirrorQuality};
+
+/// Height of the mirror above the vehicle's origin, in meters
+const MIRROR_HEIGHT: f32 = 1.2;
+/// Vertical field of view of the mirror, narrower than the game camera like a real mirror
+const MIRROR_FOV: f32 = 0.5;
+
+/// Render target of the rear-view mirror and how often it redraws
+#[derive(Resource)]
+pub struct RearViewMirror {
+    pub image: Handle<Image>,
+    /// Quality the image is sized for
+    pub quality: MirrorQuality,
+    frame: u32,
+}
+
+/// Camera rendering the view behind the player's vehicle into the mirror
+#[derive(Component)]
+pub struct RearViewCamera;
+
+impl MirrorQuality {
+    /// Size of the mirror texture in pixels
+    pub fn resolution(self) -> UVec2 {
+        match self {
+            Self::Low => UVec2::new(256, 80),
+            Self::Medium => UVec2::new(384, 120),
+            Self::High => UVec2::new(512, 160),
+        }
+    }
+
+    /// Frames between mirror redraws, the image holds its last frame in between
+    pub fn refresh_interval(self) -> u32 {
+        match self {
+            Self::Low => 3,
+            Self::Medium => 2,
+            Self::High => 1,
+        }
+    }
+}
+
+/// Mirror camera on top of `vehicle`, looking out the back (vehicles face -Z)
+pub fn mirror_view(vehicle: &Transform) -> Transform {
+    let position = vehicle.translation + vehicle.up() * MIRROR_HEIGHT;
+    Transform::from_translation(position).looking_to(vehicle.back(), vehicle.up())
+}
+
+fn mirror_size(quality: MirrorQuality) -> Extent3d {
+    let resolution = quality.resolution();
+    Extent3d { width: resolution.x, height: resolution.y, depth_or_array_layers: 1 }
+}
+
+fn mirror_image(size: Extent3d) -> Image {
+    let mut image = Image {
+        data: vec![0; (size.width * size.height * 4) as usize],
+        ..default()
+    };
+    image.texture_descriptor.size = size;
+    image.texture_descriptor.dimension = TextureDimension::D2;
+    image.texture_descriptor.format = TextureFormat::Bgra8UnormSrgb;
+    image.texture_descriptor.usage =
+        TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::RENDER_ATTACHMENT;
+    image
+}
+
+pub(super) fn setup_rear_view_mirror(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let quality = MirrorQuality::default();
+    let image = images.add(mirror_image(mirror_size(quality)));
+
+    commands.spawn((
+        Camera3dBundle {
+            camera: Camera {
+                // Renders ahead of the main camera like the water reflection, which has -1
+                order: -2,
+                target: RenderTarget::Image(image.clone()),
+                is_active: false,
+                ..default()
+            },
+            projection: Projection::Perspective(PerspectiveProjection { fov: MIRROR_FOV, ..default() }),
+            ..default()
+        },
+        RearViewCamera,
+        Name::new("Rear View Camera"),
+    ));
+
+    commands.insert_resource(RearViewMirror { image, quality, frame: 0 });
+}
+
+/// Follows the first game camera's vehicle, redrawing at the rate the mirror quality allows.
+/// The performance budget lowers the quality like it does shadows, the mirror costs a whole scene render.
+pub(super) fn update_rear_view_mirror(
+    game_settings: Option<Res<GameSettings>>,
+    budget: Option<Res<PerformanceBudget>>,
+    mut mirror: ResMut<RearViewMirror>,
+    mut images: ResMut<Assets<Image>>,
+    game_cameras: Query<&GameCamera>,
+    vehicles: Query<&Transform, Without<RearViewCamera>>,
+    mut mirror_cameras: Query<(&mut Camera, &mut Transform), With<RearViewCamera>>,
+) {
+    let Ok((mut camera, mut transform)) = mirror_cameras.get_single_mut() else {
+        return;
+    };
+    let graphics = game_settings.as_ref().map(|settings| &settings.graphics);
+    let vehicle = game_cameras
+        .iter()
+        .find_map(|game_camera| game_camera.target)
+        .and_then(|target| vehicles.get(target).ok());
+    let (Some(graphics), Some(vehicle)) = (graphics.filter(|graphics| graphics.rear_view_mirror), vehicle) else {
+        camera.is_active = false;
+        return;
+    };
+
+    let quality = match budget {
+        Some(budget) => budget.mirror_quality(graphics.mirror_quality),
+        None => graphics.mirror_quality,
+    };
+    if quality != mirror.quality {
+        if let Some(image) = images.get_mut(&mirror.image) {
+            image.resize(mirror_size(quality));
+        }
+        mirror.quality = quality;
+    }
+
+    camera.is_active = mirror.frame % quality.refresh_interval() == 0;
+    mirror.frame = mirror.frame.wrapping_add(1);
+    if camera.is_active {
+        *transform = mirror_view(vehicle);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mirror_looks_out_the_back() {
+        let vehicle =
+            Transform::from_xyz(3.0, 1.0, 0.0).with_rotation(Quat::from_rotation_y(std::f32::consts::FRAC_PI_2));
+        let view = mirror_view(&vehicle);
+        assert!(view.forward().abs_diff_eq(vehicle.back(), 1e-5));
+        assert!((view.translation.y - 1.0 - MIRROR_HEIGHT).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_lower_quality_is_smaller_and_redraws_less() {
+        let low = MirrorQuality::Low;
+        let high = MirrorQuality::High;
+        assert!(low.resolution().x < high.resolution().x);
+        assert!(low.refresh_interval() > high.refresh_interval());
+        assert_eq!(high.refresh_interval(), 1);
+    }
+}
@@ -1,6 +1,15 @@
+mod cockpit;
+mod mirror;
+
 use bevy::prelude::*;
 use bevy::render::camera::Camera3d;
 
+pub use cockpit::{head_lean, CockpitCameraSettings, CockpitView};
+pub use mirror::{mirror_view, RearViewCamera, RearViewMirror};
+
+use super::split_screen::PlayerInput;
+use crate::game::{configure_game_sets, render_available, GameSet, GameSettings};
+
 /// Camera settings for controlling behavior
 #[derive(Resource)]
 pub struct CameraSettings {
@@ -11,6 +20,12 @@ pub struct CameraSettings {
     pub zoom_sensitivity: f32,
     pub min_zoom: f32,
     pub max_zoom: f32,
+    /// Largest shake rotation in radians, reached at full trauma
+    pub shake_max_angle: f32,
+    /// Trauma lost per second
+    pub shake_decay: f32,
+    /// How fast the shake wobbles
+    pub shake_frequency: f32,
 }
 
 impl Default for CameraSettings {
@@ -23,6 +38,9 @@ impl Default for CameraSettings {
             zoom_sensitivity: 0.5,
             min_zoom: 5.0,
             max_zoom: 20.0,
+            shake_max_angle: 0.06,
+            shake_decay: 1.5,
+            shake_frequency: 25.0,
         }
     }
 }
@@ -33,6 +51,8 @@ pub struct GameCamera {
     pub target: Option<Entity>,
     pub orbit_angle: Vec2, // (yaw, pitch)
     pub current_zoom: f32,
+    /// Shake amount (0.0 - 1.0), decays over time
+    pub trauma: f32,
 }
 
 impl Default for GameCamera {
@@ -41,22 +61,57 @@ impl Default for GameCamera {
             target: None,
             orbit_angle: Vec2::new(0.0, std::f32::consts::FRAC_PI_4),
             current_zoom: 10.0,
+            trauma: 0.0,
         }
     }
 }
 
+impl GameCamera {
+    /// Adds shake, e.g. from an impact near the camera's target
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+}
+
+/// Yaw and pitch shake offset in radians for `trauma` at `time` seconds
+pub fn shake_offset(settings: &CameraSettings, trauma: f32, time: f32) -> Vec2 {
+    // Squared so small knocks barely register and big crashes really shake
+    let strength = trauma * trauma * settings.shake_max_angle;
+    let t = time * settings.shake_frequency;
+    // Incommensurate frequencies so the wobble doesn't visibly repeat
+    Vec2::new(
+        (t.sin() + (t * 2.31 + 1.7).sin()) * 0.5,
+        (t * 1.13 + 0.5).sin() * 0.6 + (t * 2.87).sin() * 0.4,
+    ) * strength
+}
+
 /// Plugin for managing camera systems
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
+        configure_game_sets(app);
         app.init_resource::<CameraSettings>()
+            .init_resource::<CockpitCameraSettings>()
             .add_systems(Startup, setup_camera)
             .add_systems(Update, (
                 update_camera_position,
                 update_camera_rotation,
                 update_camera_zoom,
-            ));
+                (cockpit::toggle_cockpit_view, cockpit::update_cockpit_camera)
+                    .chain()
+                    .after(update_camera_position),
+                apply_camera_shake.after(cockpit::update_cockpit_camera),
+            ).in_set(GameSet::CameraUi));
+
+        // The mirror renders into an image
+        if render_available(app) {
+            app.add_systems(Startup, mirror::setup_rear_view_mirror)
+                .add_systems(Update, mirror::update_rear_view_mirror
+                    .after(update_camera_position)
+                    .in_set(GameSet::CameraUi)
+                    .run_if(resource_exists::<RearViewMirror>()));
+        }
     }
 }
 
@@ -72,9 +127,9 @@ fn setup_camera(mut commands: Commands) {
     ));
 }
 
-/// Updates camera position based on target and settings
+/// Updates camera position based on target and settings, cameras in the cockpit view sit in the cab instead
 fn update_camera_position(
-    mut camera_query: Query<(&mut Transform, &GameCamera), With<Camera3d>>,
+    mut camera_query: Query<(&mut Transform, &GameCamera), (With<Camera3d>, Without<CockpitView>)>,
     target_query: Query<&Transform, Without<Camera3d>>,
     settings: Res<CameraSettings>,
     time: Res<Time>,
@@ -110,14 +165,24 @@ fn update_camera_position(
     }
 }
 
+/// Camera orbit and zoom input for a camera: its target player's input, or the shared input state
+fn camera_input(game_camera: &GameCamera, players: &Query<&PlayerInput>, input: &crate::InputState) -> (Vec2, f32) {
+    game_camera
+        .target
+        .and_then(|target| players.get(target).ok())
+        .map_or((input.camera_rotate, input.camera_zoom), |player| (player.camera_rotate, player.camera_zoom))
+}
+
 /// Updates camera rotation based on input
 fn update_camera_rotation(
     mut camera_query: Query<&mut GameCamera>,
+    players: Query<&PlayerInput>,
     input: Res<crate::InputState>,
     settings: Res<CameraSettings>,
 ) {
     for mut game_camera in camera_query.iter_mut() {
-        let rotation_delta = input.camera_rotate * settings.rotation_sensitivity;
+        let (rotate, _) = camera_input(&game_camera, &players, &input);
+        let rotation_delta = rotate * settings.rotation_sensitivity;
         game_camera.orbit_angle += rotation_delta;
         
         // Clamp pitch to prevent camera flipping
@@ -129,12 +194,35 @@ fn update_camera_rotation(
 /// Updates camera zoom based on input
 fn update_camera_zoom(
     mut camera_query: Query<&mut GameCamera>,
+    players: Query<&PlayerInput>,
     input: Res<crate::InputState>,
     settings: Res<CameraSettings>,
 ) {
     for mut game_camera in camera_query.iter_mut() {
-        game_camera.current_zoom += input.camera_zoom * settings.zoom_sensitivity;
+        let (_, zoom) = camera_input(&game_camera, &players, &input);
+        game_camera.current_zoom += zoom * settings.zoom_sensitivity;
         game_camera.current_zoom = game_camera.current_zoom
             .clamp(settings.min_zoom, settings.max_zoom);
     }
-} 
\ No newline at end of file
+} 
+
+/// Rotates cameras by their shake and lets the trauma decay, runs after the look-at so it never accumulates
+fn apply_camera_shake(
+    mut camera_query: Query<(&mut Transform, &mut GameCamera), With<Camera3d>>,
+    settings: Res<CameraSettings>,
+    game_settings: Option<Res<GameSettings>>,
+    time: Res<Time>,
+) {
+    // Players sensitive to motion can turn the shake down or off
+    let intensity = game_settings.map_or(1.0, |game_settings| game_settings.accessibility.camera_shake.clamp(0.0, 1.0));
+    for (mut transform, mut game_camera) in camera_query.iter_mut() {
+        if game_camera.trauma <= 0.0 {
+            continue;
+        }
+        if game_camera.target.is_some() && intensity > 0.0 {
+            let offset = shake_offset(&settings, game_camera.trauma, time.elapsed_seconds()) * intensity;
+            transform.rotate_local(Quat::from_euler(EulerRot::YXZ, offset.x, offset.y, 0.0));
+        }
+        game_camera.trauma = (game_camera.trauma - settings.shake_decay * time.delta_seconds()).max(0.0);
+    }
+}
@@ -121,4 +121,40 @@ fn test_camera_follow() {
     let camera_transform = app.world.query::<&Transform>().iter(&app.world).next().unwrap();
     assert!(camera_transform.translation.distance(Vec3::new(10.0, 0.0, 10.0)) > 5.0,
         "Camera should follow moving target");
-} 
\ No newline at end of file
+} 
+#[test]
+fn test_trauma_is_clamped() {
+    let mut camera = GameCamera::default();
+    camera.add_trauma(0.7);
+    camera.add_trauma(0.7);
+    assert_eq!(camera.trauma, 1.0);
+}
+
+#[test]
+fn test_shake_scales_with_trauma_squared() {
+    let settings = CameraSettings::default();
+    assert_eq!(shake_offset(&settings, 0.0, 1.3), Vec2::ZERO);
+    let full = shake_offset(&settings, 1.0, 1.3);
+    let half = shake_offset(&settings, 0.5, 1.3);
+    assert!((half - full * 0.25).length() < 1e-6);
+    assert!(full.abs().max_element() <= settings.shake_max_angle);
+}
+
+#[test]
+fn test_cockpit_view_sits_in_the_cab() {
+    let mut app = setup_test_app();
+    let vehicle = crate::game::vehicle::Vehicle::default();
+    let eye = crate::game::vehicle::CockpitLayout::for_config(&vehicle.config).eye;
+    let target = app.world.spawn((Transform::from_xyz(5.0, 0.0, 5.0), vehicle)).id();
+
+    let mut cameras = app.world.query::<(Entity, &mut GameCamera)>();
+    let (camera_entity, mut camera) = cameras.single_mut(&mut app.world);
+    camera.target = Some(target);
+    app.world.entity_mut(camera_entity).insert(CockpitView::default());
+    app.update();
+
+    let camera_transform = app.world.get::<Transform>(camera_entity).unwrap();
+    assert!(camera_transform.translation.distance(Vec3::new(5.0, 0.0, 5.0) + eye) < 1e-4,
+        "Cockpit camera should sit at the driver's eyes");
+    assert!(camera_transform.forward().dot(Vec3::NEG_Z) > 0.999, "Cockpit camera should look where the vehicle does");
+}
@@ -0,0 +1,323 @@
+//! In-game text chat for multiplayer sessions.
+//!
+//! Typed lines and quick messages picked from the quick chat menu go through
+//! the [`ChatFilters`] before anything is sent, then out as [`SendChatMessage`]
+//! events for the network session to carry. Messages from peers come back in
+//! as [`ReceivedChatMessage`] events, are filtered the same way and end up in
+//! the [`ChatHistory`] the overlay draws.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::split_screen::{PlayerId, PlayerInput, QUICK_CHAT_PAGE_SIZE};
+
+/// Canned messages reachable from a gamepad, in quick chat menu order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum QuickMessage {
+    FollowMe,
+    Wait,
+    NeedRecovery,
+    OnMyWay,
+    NiceLine,
+    Thanks,
+    Sorry,
+    GoodGame,
+}
+
+impl QuickMessage {
+    /// Every quick message, `QUICK_CHAT_PAGE_SIZE` to a menu page
+    pub const ALL: [QuickMessage; 8] = [
+        QuickMessage::FollowMe,
+        QuickMessage::Wait,
+        QuickMessage::NeedRecovery,
+        QuickMessage::OnMyWay,
+        QuickMessage::NiceLine,
+        QuickMessage::Thanks,
+        QuickMessage::Sorry,
+        QuickMessage::GoodGame,
+    ];
+
+    /// Quick messages on a page of the quick chat menu
+    pub fn page(page: usize) -> &'static [QuickMessage] {
+        let start = (page * QUICK_CHAT_PAGE_SIZE).min(Self::ALL.len());
+        let end = (start + QUICK_CHAT_PAGE_SIZE).min(Self::ALL.len());
+        &Self::ALL[start..end]
+    }
+
+    /// Locale key of the message, quick messages show in each reader's language
+    pub fn text_key(self) -> &'static str {
+        match self {
+            QuickMessage::FollowMe => "chat.quick.follow_me",
+            QuickMessage::Wait => "chat.quick.wait",
+            QuickMessage::NeedRecovery => "chat.quick.need_recovery",
+            QuickMessage::OnMyWay => "chat.quick.on_my_way",
+            QuickMessage::NiceLine => "chat.quick.nice_line",
+            QuickMessage::Thanks => "chat.quick.thanks",
+            QuickMessage::Sorry => "chat.quick.sorry",
+            QuickMessage::GoodGame => "chat.quick.good_game",
+        }
+    }
+}
+
+/// What a chat message says
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ChatBody {
+    Text(String),
+    Quick(QuickMessage),
+}
+
+/// One chat line as sent over the network session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub peer: u32,
+    /// Display name of the sender
+    pub sender: String,
+    pub body: ChatBody,
+}
+
+/// A local player wrote a chat line or picked a quick message
+#[derive(Event, Debug, Clone)]
+pub struct SubmitChatEvent(pub ChatBody);
+
+/// A chat message of the local player for the network session to send to its peers
+#[derive(Event, Debug, Clone)]
+pub struct SendChatMessage(pub ChatMessage);
+
+/// A chat message that arrived from a peer over the network session
+#[derive(Event, Debug, Clone)]
+pub struct ReceivedChatMessage(pub ChatMessage);
+
+/// Who this player is in chat and how much chat is kept
+#[derive(Resource, Debug, Clone)]
+pub struct ChatSettings {
+    /// Peer id of this machine in the network session
+    pub local_peer: u32,
+    pub player_name: String,
+    /// Characters a typed line is cut to
+    pub max_length: usize,
+    /// Messages kept in the history
+    pub history_length: usize,
+}
+
+impl Default for ChatSettings {
+    fn default() -> Self {
+        Self {
+            local_peer: 0,
+            player_name: std::env::var("SANDK_PLAYER_NAME").unwrap_or_else(|_| "Player".to_string()),
+            max_length: 160,
+            history_length: 50,
+        }
+    }
+}
+
+/// A chat message as received, with when it arrived for fading it out
+#[derive(Debug, Clone)]
+pub struct ChatEntry {
+    pub message: ChatMessage,
+    /// Elapsed seconds when the message was added
+    pub received_at: f32,
+}
+
+/// Recent chat messages, oldest first
+#[derive(Resource, Debug, Clone, Default)]
+pub struct ChatHistory {
+    entries: VecDeque<ChatEntry>,
+}
+
+impl ChatHistory {
+    /// Adds a message, dropping the oldest ones past `limit`
+    pub fn push(&mut self, message: ChatMessage, received_at: f32, limit: usize) {
+        self.entries.push_back(ChatEntry { message, received_at });
+        while self.entries.len() > limit {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &ChatEntry> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Rewrites or rejects typed chat lines, quick messages never go through filters
+pub trait ChatFilter: Send + Sync + 'static {
+    /// Filtered text, `None` drops the line entirely
+    fn filter(&self, text: &str) -> Option<String>;
+}
+
+/// Masks listed words with asterisks, ignoring case
+#[derive(Debug, Clone, Default)]
+pub struct WordListFilter {
+    words: Vec<String>,
+}
+
+impl WordListFilter {
+    pub fn new<I, S>(words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let words = words.into_iter().map(|word| word.as_ref().to_lowercase());
+        Self { words: words.filter(|word| !word.is_empty()).collect() }
+    }
+}
+
+impl ChatFilter for WordListFilter {
+    fn filter(&self, text: &str) -> Option<String> {
+        let filtered = text
+            .split(' ')
+            .map(|word| {
+                let bare = word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+                if self.words.contains(&bare) {
+                    word.chars().map(|c| if c.is_alphanumeric() { '*' } else { c }).collect()
+                } else {
+                    word.to_string()
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(" ");
+        Some(filtered)
+    }
+}
+
+/// Filters every typed chat line runs through in order, outgoing and incoming
+#[derive(Resource, Default)]
+pub struct ChatFilters(Vec<Box<dyn ChatFilter>>);
+
+impl ChatFilters {
+    /// Adds a filter after the existing ones
+    pub fn add(&mut self, filter: impl ChatFilter) {
+        self.0.push(Box::new(filter));
+    }
+
+    /// Runs a body through the filters, `None` when a filter dropped it or nothing is left
+    pub fn apply(&self, body: &ChatBody, max_length: usize) -> Option<ChatBody> {
+        let ChatBody::Text(text) = body else {
+            return Some(body.clone());
+        };
+        let mut text: String = text.trim().chars().take(max_length).collect();
+        for filter in self.0.iter() {
+            text = filter.filter(&text)?;
+        }
+        (!text.trim().is_empty()).then_some(ChatBody::Text(text))
+    }
+}
+
+/// Turns quick chat picks of the first local player into chat lines
+fn submit_quick_messages(players: Query<(&PlayerId, &PlayerInput)>, mut submit: EventWriter<SubmitChatEvent>) {
+    for (player, input) in players.iter() {
+        if player.0 != 0 {
+            continue;
+        }
+        if let Some(message) = input.quick_chat_pick.and_then(|pick| QuickMessage::ALL.get(pick)) {
+            submit.send(SubmitChatEvent(ChatBody::Quick(*message)));
+        }
+    }
+}
+
+/// Filters local chat lines, adds them to the history and hands them to the session
+fn send_chat_messages(
+    time: Res<Time<Real>>,
+    settings: Res<ChatSettings>,
+    filters: Res<ChatFilters>,
+    mut history: ResMut<ChatHistory>,
+    mut submitted: EventReader<SubmitChatEvent>,
+    mut send: EventWriter<SendChatMessage>,
+) {
+    for SubmitChatEvent(body) in submitted.read() {
+        let Some(body) = filters.apply(body, settings.max_length) else {
+            continue;
+        };
+        let message = ChatMessage { peer: settings.local_peer, sender: settings.player_name.clone(), body };
+        history.push(message.clone(), time.elapsed_seconds(), settings.history_length);
+        send.send(SendChatMessage(message));
+    }
+}
+
+/// Filters peers' chat lines into the history
+fn receive_chat_messages(
+    time: Res<Time<Real>>,
+    settings: Res<ChatSettings>,
+    filters: Res<ChatFilters>,
+    mut history: ResMut<ChatHistory>,
+    mut received: EventReader<ReceivedChatMessage>,
+) {
+    for ReceivedChatMessage(message) in received.read() {
+        if message.peer == settings.local_peer {
+            continue;
+        }
+        let Some(body) = filters.apply(&message.body, settings.max_length) else {
+            continue;
+        };
+        let message = ChatMessage { body, ..message.clone() };
+        history.push(message, time.elapsed_seconds(), settings.history_length);
+    }
+}
+
+/// Plugin for in-game text and quick chat
+pub struct ChatPlugin;
+
+impl Plugin for ChatPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChatSettings>()
+            .init_resource::<ChatHistory>()
+            .init_resource::<ChatFilters>()
+            .add_event::<SubmitChatEvent>()
+            .add_event::<SendChatMessage>()
+            .add_event::<ReceivedChatMessage>()
+            .add_systems(Update, (submit_quick_messages, send_chat_messages, receive_chat_messages).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_list_masks_listed_words() {
+        let mut filters = ChatFilters::default();
+        filters.add(WordListFilter::new(["mud", "ROCK"]));
+        let filtered = filters.apply(&ChatBody::Text("  Mud, rocks and a Rock!  ".to_string()), 160);
+        assert_eq!(filtered, Some(ChatBody::Text("***, rocks and a ****!".to_string())));
+        assert_eq!(filters.apply(&ChatBody::Text("   ".to_string()), 160), None);
+        assert_eq!(filters.apply(&ChatBody::Text("abcdef".to_string()), 3), Some(ChatBody::Text("abc".to_string())));
+        let quick = ChatBody::Quick(QuickMessage::Thanks);
+        assert_eq!(filters.apply(&quick, 3), Some(quick));
+    }
+
+    #[test]
+    fn test_history_drops_oldest() {
+        let mut history = ChatHistory::default();
+        for index in 0..5 {
+            let message =
+                ChatMessage { peer: 1, sender: "Test".to_string(), body: ChatBody::Text(index.to_string()) };
+            history.push(message, index as f32, 3);
+        }
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.entries().next().unwrap().received_at, 2.0);
+    }
+
+    #[test]
+    fn test_quick_pick_is_sent_and_kept() {
+        let mut app = App::new();
+        app.init_resource::<Time>().add_plugins(ChatPlugin);
+        app.world.spawn((PlayerId(0), PlayerInput { quick_chat_pick: Some(5), ..default() }));
+        app.world.spawn((PlayerId(1), PlayerInput { quick_chat_pick: Some(0), ..default() }));
+        app.update();
+
+        let sent = app.world.resource::<Events<SendChatMessage>>();
+        let messages: Vec<&SendChatMessage> = sent.get_reader().read(sent).collect();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].0.body, ChatBody::Quick(QuickMessage::Thanks));
+        assert_eq!(app.world.resource::<ChatHistory>().len(), 1);
+    }
+}
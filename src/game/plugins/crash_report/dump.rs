@@ -0,0 +1,168 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::FrameMetrics;
+
+/// Extension of crash dump files in the crash folder
+pub const CRASH_DUMP_EXTENSION: &str = "crash.json";
+
+/// What brought the game down
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CrashReason {
+    Panic {
+        message: String,
+        /// `file:line:column` of the panic
+        location: Option<String>,
+    },
+    /// The GPU reported an error nothing handled, usually the device being lost
+    Gpu { message: String },
+}
+
+/// State of one vehicle when the game crashed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VehicleSnapshot {
+    pub name: Option<String>,
+    pub position: [f32; 3],
+    /// Speed in m/s
+    pub speed: f32,
+    pub gear: i32,
+    pub engine_rpm: f32,
+    pub throttle: f32,
+    pub brake: f32,
+    pub fuel: Option<f32>,
+    pub engine_temperature: Option<f32>,
+}
+
+/// Everything written to disk about a crash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashDump {
+    pub version: String,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub reason: CrashReason,
+    pub backtrace: String,
+    /// Most recent log lines, oldest first
+    pub logs: Vec<String>,
+    /// Most recent frame metrics from [`crate::game::DebugInfo`], oldest first
+    pub metrics: Vec<FrameMetrics>,
+    pub settings: Option<serde_json::Value>,
+    pub vehicles: Vec<VehicleSnapshot>,
+}
+
+impl CrashDump {
+    pub fn new(reason: CrashReason) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs()),
+            reason,
+            backtrace: String::new(),
+            logs: Vec::new(),
+            metrics: Vec::new(),
+            settings: None,
+            vehicles: Vec::new(),
+        }
+    }
+
+    /// One line description for the crash dialog
+    pub fn summary(&self) -> String {
+        match &self.reason {
+            CrashReason::Panic { message, location: Some(location) } => format!("{message} ({location})"),
+            CrashReason::Panic { message, location: None } => message.clone(),
+            CrashReason::Gpu { message } => format!("GPU error: {message}"),
+        }
+    }
+}
+
+/// Errors produced while reading or writing crash dumps
+#[derive(Debug, thiserror::Error)]
+pub enum CrashDumpError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// Writes `dump` into `directory`, returning the file it went to
+pub fn write_crash_dump(directory: &Path, dump: &CrashDump) -> Result<PathBuf, CrashDumpError> {
+    fs::create_dir_all(directory)?;
+    let mut path = directory.join(format!("{}.{CRASH_DUMP_EXTENSION}", dump.timestamp));
+    // Two crashes in the same second, from different threads
+    let mut count = 1;
+    while path.exists() {
+        path = directory.join(format!("{}-{count}.{CRASH_DUMP_EXTENSION}", dump.timestamp));
+        count += 1;
+    }
+    fs::write(&path, serde_json::to_vec_pretty(dump)?)?;
+    Ok(path)
+}
+
+/// Crash dumps left in `directory` by earlier runs, oldest first. Unreadable dumps are skipped.
+pub fn read_crash_dumps(directory: &Path) -> Vec<(PathBuf, CrashDump)> {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return Vec::new();
+    };
+    let mut dumps: Vec<(PathBuf, CrashDump)> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .map_or(false, |name| name.ends_with(&format!(".{CRASH_DUMP_EXTENSION}")))
+        })
+        .filter_map(|path| {
+            let dump = serde_json::from_slice(&fs::read(&path).ok()?).ok()?;
+            Some((path, dump))
+        })
+        .collect();
+    dumps.sort_by_key(|(_, dump)| dump.timestamp);
+    dumps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dumps_round_trip_through_the_crash_folder() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut first = CrashDump::new(CrashReason::Panic {
+            message: "wheel index out of range".to_string(),
+            location: Some("src/game/vehicle/wheel.rs:42:9".to_string()),
+        });
+        first.timestamp = 100;
+        first.logs.push("WARN sandk_offroad: low fuel".to_string());
+        let mut second = CrashDump::new(CrashReason::Gpu { message: "Parent device is lost".to_string() });
+        second.timestamp = 50;
+
+        write_crash_dump(directory.path(), &first).unwrap();
+        write_crash_dump(directory.path(), &second).unwrap();
+        // Same second as the first, must not overwrite it
+        write_crash_dump(directory.path(), &first).unwrap();
+        fs::write(directory.path().join("notes.txt"), "not a dump").unwrap();
+
+        let dumps = read_crash_dumps(directory.path());
+        assert_eq!(dumps.len(), 3);
+        assert_eq!(dumps[0].1.reason, second.reason);
+        assert_eq!(dumps[1].1.logs, first.logs);
+    }
+
+    #[test]
+    fn test_missing_folder_has_no_dumps() {
+        let directory = tempfile::tempdir().unwrap();
+        assert!(read_crash_dumps(&directory.path().join("crashes")).is_empty());
+    }
+
+    #[test]
+    fn test_summary_names_the_location() {
+        let dump = CrashDump::new(CrashReason::Panic {
+            message: "boom".to_string(),
+            location: Some("src/main.rs:1:1".to_string()),
+        });
+        assert_eq!(dump.summary(), "boom (src/main.rs:1:1)");
+    }
+}
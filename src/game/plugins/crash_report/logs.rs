@@ -0,0 +1,90 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::sync::{Mutex, OnceLock};
+
+use bevy::log::BoxedSubscriber;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+/// Number of log lines kept for crash dumps
+pub const RECENT_LOG_LINES: usize = 200;
+
+/// Global because the subscriber is built before the app exists and the panic hook runs outside it
+fn recent_log_buffer() -> &'static Mutex<VecDeque<String>> {
+    static RECENT: OnceLock<Mutex<VecDeque<String>>> = OnceLock::new();
+    RECENT.get_or_init(|| Mutex::new(VecDeque::with_capacity(RECENT_LOG_LINES)))
+}
+
+fn push_log_line(line: String) {
+    // A panic while logging must not take the log with it
+    let mut recent = recent_log_buffer().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if recent.len() == RECENT_LOG_LINES {
+        recent.pop_front();
+    }
+    recent.push_back(line);
+}
+
+/// The most recent log lines, oldest first
+pub fn recent_logs() -> Vec<String> {
+    match recent_log_buffer().try_lock() {
+        Ok(recent) => recent.iter().cloned().collect(),
+        // Whoever holds it may be the thread that is panicking
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Collects an event's fields into one line, message first
+#[derive(Default)]
+struct LineVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for LineVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            let _ = write!(self.fields, " {}={value:?}", field.name());
+        }
+    }
+}
+
+/// Layer copying every log event into the recent log buffer
+struct RecentLogsLayer;
+
+impl<S: Subscriber> Layer<S> for RecentLogsLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = LineVisitor::default();
+        event.record(&mut visitor);
+        let metadata = event.metadata();
+        push_log_line(format!(
+            "{} {}: {}{}",
+            metadata.level(),
+            metadata.target(),
+            visitor.message,
+            visitor.fields
+        ));
+    }
+}
+
+/// `LogPlugin::update_subscriber` hook that keeps the recent log lines for crash dumps
+pub fn capture_recent_logs(subscriber: BoxedSubscriber) -> BoxedSubscriber {
+    Box::new(subscriber.with(RecentLogsLayer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffer_keeps_the_latest_lines() {
+        for line in 0..RECENT_LOG_LINES + 10 {
+            push_log_line(format!("line {line}"));
+        }
+        let logs = recent_logs();
+        assert_eq!(logs.len(), RECENT_LOG_LINES);
+        assert_eq!(logs.last().unwrap(), &format!("line {}", RECENT_LOG_LINES + 9));
+    }
+}
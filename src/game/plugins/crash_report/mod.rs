@@ -0,0 +1,265 @@
+/// Crash reporting
+///
+/// A panic hook, and a handler for GPU errors such as a lost device, write a [`CrashDump`] to the
+/// crash folder: the panic and its backtrace, the recent log lines, the frame metrics history of
+/// [`DebugInfo`], the settings and the state of every vehicle. The hook can't reach into the world
+/// while the app is unwinding, so that state is copied out at the end of every frame.
+///
+/// On the next launch the dumps left behind are listed in [`PendingCrashReports`], and the UI
+/// offers to send them to the backend's telemetry endpoint.
+mod dump;
+mod logs;
+
+use std::backtrace::Backtrace;
+use std::fs;
+use std::panic::PanicInfo;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, Once, OnceLock};
+
+use bevy::prelude::*;
+use bevy::render::renderer::RenderDevice;
+
+use crate::game::vehicle::{Brakes, Engine, EngineTemperature, FuelTank, Transmission, Vehicle};
+use crate::game::{DebugInfo, FrameMetrics, GameSettings};
+
+pub use dump::{
+    read_crash_dumps, write_crash_dump, CrashDump, CrashDumpError, CrashReason, VehicleSnapshot, CRASH_DUMP_EXTENSION,
+};
+pub use logs::{capture_recent_logs, recent_logs, RECENT_LOG_LINES};
+
+/// Telemetry endpoint of a locally running backend
+pub const DEFAULT_TELEMETRY_URL: &str = "http://localhost:3000/telemetry/crash";
+
+/// Where crash dumps go and where reports are sent
+#[derive(Resource, Debug, Clone)]
+pub struct CrashReportSettings {
+    pub directory: PathBuf,
+    /// Telemetry endpoint taking crash dumps as JSON, `None` to never offer sending them
+    pub telemetry_url: Option<String>,
+}
+
+impl Default for CrashReportSettings {
+    fn default() -> Self {
+        Self {
+            directory: std::env::var("SANDK_CRASH_PATH")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| PathBuf::from("crashes")),
+            telemetry_url: Some(
+                std::env::var("SANDK_TELEMETRY_URL").unwrap_or_else(|_| DEFAULT_TELEMETRY_URL.to_string()),
+            ),
+        }
+    }
+}
+
+/// Crash dumps from earlier runs the player hasn't sent or dismissed yet, oldest first
+#[derive(Resource, Debug, Default)]
+pub struct PendingCrashReports {
+    pub reports: Vec<(PathBuf, CrashDump)>,
+}
+
+/// Game state copied out of the world for the panic hook
+#[derive(Debug, Default)]
+struct CrashContext {
+    /// Unset until crash reporting has started, nothing is written before then
+    directory: Option<PathBuf>,
+    settings: Option<serde_json::Value>,
+    metrics: Vec<FrameMetrics>,
+    vehicles: Vec<VehicleSnapshot>,
+    /// Set by the GPU error handler just before it panics, so the dump names the real cause
+    gpu_error: Option<String>,
+}
+
+impl CrashContext {
+    fn dump(&mut self, message: String, location: Option<String>) -> CrashDump {
+        let reason = match self.gpu_error.take() {
+            Some(message) => CrashReason::Gpu { message },
+            None => CrashReason::Panic { message, location },
+        };
+        let mut dump = CrashDump::new(reason);
+        dump.settings = self.settings.clone();
+        dump.metrics = self.metrics.clone();
+        dump.vehicles = self.vehicles.clone();
+        dump
+    }
+}
+
+fn crash_context() -> &'static Mutex<CrashContext> {
+    static CONTEXT: OnceLock<Mutex<CrashContext>> = OnceLock::new();
+    CONTEXT.get_or_init(Default::default)
+}
+
+fn panic_message(info: &PanicInfo) -> String {
+    let payload = info.payload();
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Box<dyn Any>".to_string())
+}
+
+fn write_panic_dump(info: &PanicInfo) {
+    // Whoever holds the context may be the thread that is panicking
+    let Ok(mut context) = crash_context().try_lock() else {
+        eprintln!("Crash context is locked, no crash report written");
+        return;
+    };
+    let Some(directory) = context.directory.clone() else {
+        return;
+    };
+    let mut dump = context.dump(panic_message(info), info.location().map(ToString::to_string));
+    drop(context);
+    dump.backtrace = Backtrace::force_capture().to_string();
+    dump.logs = recent_logs();
+
+    match write_crash_dump(&directory, &dump) {
+        Ok(path) => eprintln!("Crash report written to {}", path.display()),
+        Err(error) => eprintln!("Failed to write crash report: {error}"),
+    }
+}
+
+/// Writes a crash dump on any panic, then carries on with the previous hook
+fn install_panic_hook() {
+    static INSTALLED: Once = Once::new();
+    INSTALLED.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            write_panic_dump(info);
+            previous(info);
+        }));
+    });
+}
+
+fn start_crash_reporting(settings: Res<CrashReportSettings>, mut pending: ResMut<PendingCrashReports>) {
+    pending.reports = read_crash_dumps(&settings.directory);
+    if !pending.reports.is_empty() {
+        info!("Found {} crash report(s) from earlier runs", pending.reports.len());
+    }
+    crash_context().lock().unwrap_or_else(|poisoned| poisoned.into_inner()).directory =
+        Some(settings.directory.clone());
+    install_panic_hook();
+}
+
+/// Copies the state a crash dump needs out of the world
+fn snapshot_crash_context(
+    debug_info: Option<Res<DebugInfo>>,
+    settings: Option<Res<GameSettings>>,
+    vehicles: Query<(
+        &Vehicle,
+        &Engine,
+        &Transmission,
+        &Brakes,
+        &GlobalTransform,
+        Option<&Name>,
+        Option<&FuelTank>,
+        Option<&EngineTemperature>,
+    )>,
+) {
+    let mut context = crash_context().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(settings) = settings.filter(|settings| settings.is_changed()) {
+        context.settings = serde_json::to_value(&*settings).ok();
+    }
+    if let Some(debug_info) = debug_info {
+        context.metrics.clear();
+        context.metrics.extend(debug_info.metrics.iter().copied());
+    }
+    context.vehicles = vehicles
+        .iter()
+        .map(|(vehicle, engine, transmission, brakes, transform, name, fuel, temperature)| VehicleSnapshot {
+            name: name.map(|name| name.to_string()),
+            position: transform.translation().to_array(),
+            speed: vehicle.vehicle_speed,
+            gear: transmission.gear,
+            engine_rpm: engine.rpm,
+            throttle: engine.throttle,
+            brake: brakes.brake,
+            fuel: fuel.map(|fuel| fuel.level),
+            engine_temperature: temperature.map(|temperature| temperature.temperature),
+        })
+        .collect();
+}
+
+/// Sends a crash report to the telemetry endpoint in the background, deleting the dump once it's
+/// been received. A failed send leaves the dump to be offered again next launch.
+pub fn submit_crash_report(url: &str, path: PathBuf, dump: CrashDump) {
+    let url = url.to_string();
+    std::thread::spawn(move || match ureq::post(&url).send_json(&dump) {
+        Ok(_) => {
+            info!("Crash report sent");
+            dismiss_crash_report(&path);
+        }
+        Err(error) => warn!("Failed to send crash report: {error}"),
+    });
+}
+
+/// Deletes a crash dump without sending it
+pub fn dismiss_crash_report(path: &Path) {
+    if let Err(error) = fs::remove_file(path) {
+        warn!("Failed to remove crash report {}: {error}", path.display());
+    }
+}
+
+/// Plugin writing crash dumps and listing the ones earlier runs left behind
+pub struct CrashReportPlugin;
+
+impl Plugin for CrashReportPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CrashReportSettings>()
+            .init_resource::<PendingCrashReports>()
+            .add_systems(PreStartup, start_crash_reporting)
+            .add_systems(Last, snapshot_crash_context);
+    }
+
+    fn finish(&self, app: &mut App) {
+        // Only there once the renderer is up, which is after every plugin has been built
+        let Some(device) = app.world.get_resource::<RenderDevice>() else {
+            return;
+        };
+        // wgpu's default handler panics too, this only makes sure the dump says why
+        device.wgpu_device().on_uncaptured_error(Box::new(|error: wgpu::Error| {
+            crash_context().lock().unwrap_or_else(|poisoned| poisoned.into_inner()).gpu_error = Some(error.to_string());
+            panic!("GPU error: {error}");
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gpu_error_is_the_reason() {
+        let mut context = CrashContext {
+            gpu_error: Some("Parent device is lost".to_string()),
+            ..default()
+        };
+        let dump = context.dump("GPU error: Parent device is lost".to_string(), None);
+        assert_eq!(dump.reason, CrashReason::Gpu { message: "Parent device is lost".to_string() });
+        // Only once, a later panic is its own crash
+        let dump = context.dump("boom".to_string(), None);
+        assert!(matches!(dump.reason, CrashReason::Panic { .. }));
+    }
+
+    #[test]
+    fn test_vehicle_state_is_copied_out() {
+        let mut app = App::new();
+        app.add_systems(Update, snapshot_crash_context);
+        app.world.spawn((
+            Vehicle {
+                vehicle_speed: 12.0,
+                ..default()
+            },
+            Engine::default(),
+            Transmission { gear: 3 },
+            Brakes::default(),
+            GlobalTransform::from_xyz(1.0, 2.0, 3.0),
+            FuelTank::default(),
+        ));
+        app.update();
+
+        let context = crash_context().lock().unwrap();
+        assert_eq!(context.vehicles.len(), 1);
+        assert_eq!(context.vehicles[0].gear, 3);
+        assert_eq!(context.vehicles[0].position, [1.0, 2.0, 3.0]);
+        assert_eq!(context.vehicles[0].fuel, Some(72.0));
+    }
+}
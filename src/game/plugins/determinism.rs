@@ -0,0 +1,700 @@
+//! Determinism mode for replay validation and networked races.
+//!
+//! With the mode on, physics steps at a fixed rate, random streams are
+//! seeded from one session seed, player inputs are quantized to fixed point
+//! and every tick the vehicle state is hashed. Comparing those checksums
+//! against a recording or a remote peer shows the first tick where two runs
+//! stopped agreeing.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::game::vehicle::Vehicle;
+use crate::game::DebugInfo;
+use crate::terrain::TerrainSeed;
+
+use super::split_screen::{apply_player_input, read_player_input, PlayerId, PlayerInput};
+use super::traffic::Traffic;
+use super::weather::Lightning;
+use super::wildlife::WildlifeRng;
+
+/// Fixed-point scale for vehicle state, 1/1024 m (or rad, m/s) per step
+pub const STATE_FIXED_POINT_SCALE: f32 = 1024.0;
+
+/// Quantizes a value to fixed point so tiny float noise doesn't change the checksum
+pub fn to_fixed(value: f32) -> i32 {
+    (value * STATE_FIXED_POINT_SCALE).round() as i32
+}
+
+/// Quantizes an input axis in -1.0..=1.0 to i16
+pub fn quantize_axis(value: f32) -> i16 {
+    (value.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16
+}
+
+pub fn dequantize_axis(value: i16) -> f32 {
+    value as f32 / i16::MAX as f32
+}
+
+/// FNV-1a, used instead of std's hasher whose output isn't stable across builds
+#[derive(Debug, Clone, Copy)]
+pub struct StateHasher(u64);
+
+impl Default for StateHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl StateHasher {
+    pub fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    pub fn write_i32(&mut self, value: i32) {
+        self.write(&value.to_le_bytes());
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Settings for determinism mode
+#[derive(Resource, Debug, Clone)]
+pub struct DeterminismSettings {
+    pub enabled: bool,
+    /// Session seed every random stream is derived from
+    pub seed: u64,
+    /// Physics ticks per second
+    pub tick_rate: u32,
+    /// Number of recent checksums kept for the debug overlay
+    pub checksum_history: usize,
+}
+
+impl Default for DeterminismSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            seed: 0x5a4e_4b5f_0ff0_ad00,
+            tick_rate: 60,
+            checksum_history: 600,
+        }
+    }
+}
+
+impl DeterminismSettings {
+    pub fn fixed_dt(&self) -> f32 {
+        1.0 / self.tick_rate.max(1) as f32
+    }
+
+    /// Seed for one named random stream so terrain, wildlife etc. don't share a sequence
+    pub fn stream_seed(&self, stream: &str) -> u32 {
+        let mut hasher = StateHasher::default();
+        hasher.write(&self.seed.to_le_bytes());
+        hasher.write(stream.as_bytes());
+        let hash = hasher.finish();
+        (hash ^ (hash >> 32)) as u32
+    }
+}
+
+/// Quantized state of one vehicle at the end of a tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct VehicleSnapshot {
+    pub position: [i32; 3],
+    pub rotation: [i32; 4],
+    pub linear_velocity: [i32; 3],
+    pub angular_velocity: [i32; 3],
+}
+
+impl VehicleSnapshot {
+    pub fn new(transform: &Transform, velocity: Option<&Velocity>) -> Self {
+        // q and -q are the same rotation, keep w positive so both hash alike
+        let rotation = if transform.rotation.w < 0.0 {
+            -transform.rotation
+        } else {
+            transform.rotation
+        };
+        let velocity = velocity.copied().unwrap_or_default();
+        Self {
+            position: transform.translation.to_array().map(to_fixed),
+            rotation: rotation.to_array().map(to_fixed),
+            linear_velocity: velocity.linvel.to_array().map(to_fixed),
+            angular_velocity: velocity.angvel.to_array().map(to_fixed),
+        }
+    }
+
+    fn hash_into(&self, hasher: &mut StateHasher) {
+        self.position
+            .iter()
+            .chain(&self.rotation)
+            .chain(&self.linear_velocity)
+            .chain(&self.angular_velocity)
+            .for_each(|value| hasher.write_i32(*value));
+    }
+}
+
+/// Checksum of all vehicles for one tick, independent of entity order
+pub fn state_checksum(mut vehicles: Vec<(Option<usize>, VehicleSnapshot)>) -> u64 {
+    // Players first by id, then any AI or parked vehicles by their own state
+    vehicles.sort_by_key(|(player, snapshot)| (player.unwrap_or(usize::MAX), *snapshot));
+    let mut hasher = StateHasher::default();
+    for (player, snapshot) in &vehicles {
+        hasher.write_i32(player.map_or(-1, |player| player as i32));
+        snapshot.hash_into(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Quantized controls of one player for one tick
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordedInput {
+    pub tick: u64,
+    pub player: usize,
+    pub throttle: i16,
+    pub brake: i16,
+    pub steering: i16,
+    pub handbrake: bool,
+    #[serde(default)]
+    pub gear: Option<i32>,
+    #[serde(default)]
+    pub ignition: bool,
+    #[serde(default)]
+    pub shift_transfer: bool,
+    #[serde(default)]
+    pub diff_lock: bool,
+    #[serde(default)]
+    pub recover: bool,
+    #[serde(default)]
+    pub refuel: bool,
+}
+
+impl RecordedInput {
+    pub fn new(tick: u64, player: usize, input: &PlayerInput) -> Self {
+        Self {
+            tick,
+            player,
+            throttle: quantize_axis(input.throttle),
+            brake: quantize_axis(input.brake),
+            steering: quantize_axis(input.steering),
+            handbrake: input.handbrake,
+            gear: input.gear,
+            ignition: input.ignition,
+            shift_transfer: input.shift_transfer,
+            diff_lock: input.diff_lock,
+            recover: input.recover,
+            refuel: input.refuel,
+        }
+    }
+
+    /// Writes the quantized controls back, camera input is left alone
+    pub fn apply(&self, input: &mut PlayerInput) {
+        input.throttle = dequantize_axis(self.throttle);
+        input.brake = dequantize_axis(self.brake);
+        input.steering = dequantize_axis(self.steering);
+        input.handbrake = self.handbrake;
+        input.gear = self.gear;
+        input.ignition = self.ignition;
+        input.shift_transfer = self.shift_transfer;
+        input.diff_lock = self.diff_lock;
+        input.recover = self.recover;
+        input.refuel = self.refuel;
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TickChecksum {
+    pub tick: u64,
+    pub checksum: u64,
+}
+
+#[derive(Error, Debug)]
+pub enum RecordingError {
+    #[error("Could not parse recording: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("Recording was made with seed {recorded:#x}, session uses {session:#x}")]
+    SeedMismatch { recorded: u64, session: u64 },
+    #[error("Recording was made at {recorded} ticks per second, session runs at {session}")]
+    TickRateMismatch { recorded: u32, session: u32 },
+}
+
+/// Everything needed to reproduce and validate a run
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct InputRecording {
+    pub seed: u64,
+    pub tick_rate: u32,
+    /// Sorted by tick
+    pub inputs: Vec<RecordedInput>,
+    pub checksums: Vec<TickChecksum>,
+}
+
+impl InputRecording {
+    pub fn new(settings: &DeterminismSettings) -> Self {
+        Self {
+            seed: settings.seed,
+            tick_rate: settings.tick_rate,
+            ..default()
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, RecordingError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_json(&self) -> Result<String, RecordingError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn inputs_at(&self, tick: u64) -> &[RecordedInput] {
+        let start = self.inputs.partition_point(|input| input.tick < tick);
+        let end = self.inputs.partition_point(|input| input.tick <= tick);
+        &self.inputs[start..end]
+    }
+}
+
+/// Where this run stopped matching its reference
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DivergenceReport {
+    /// First tick whose checksum didn't match, with the expected and actual values
+    pub first: Option<(u64, u64, u64)>,
+    pub diverged_ticks: u64,
+    pub validated_ticks: u64,
+}
+
+impl DivergenceReport {
+    pub fn in_sync(&self) -> bool {
+        self.first.is_none()
+    }
+}
+
+/// Sent the first time the run diverges from its reference
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DesyncEvent {
+    pub tick: u64,
+    pub expected: u64,
+    pub actual: u64,
+}
+
+/// Live state of the determinism mode
+#[derive(Resource, Debug, Default)]
+pub struct DeterminismSession {
+    pub tick: u64,
+    /// What this run is doing, saved to replay or validate it later
+    pub recording: InputRecording,
+    /// Inputs being replayed instead of read from devices
+    pub playback: Option<InputRecording>,
+    /// Checksums to validate against, from a replay or a remote peer
+    pub reference: BTreeMap<u64, u64>,
+    pub history: VecDeque<TickChecksum>,
+    pub divergence: DivergenceReport,
+}
+
+impl DeterminismSession {
+    /// Starts a fresh run that records inputs and checksums
+    pub fn start_recording(&mut self, settings: &DeterminismSettings) {
+        *self = Self {
+            recording: InputRecording::new(settings),
+            ..default()
+        };
+    }
+
+    /// Replays a recording and validates this run against its checksums
+    pub fn start_playback(
+        &mut self,
+        settings: &DeterminismSettings,
+        recording: InputRecording,
+    ) -> Result<(), RecordingError> {
+        if recording.seed != settings.seed {
+            return Err(RecordingError::SeedMismatch {
+                recorded: recording.seed,
+                session: settings.seed,
+            });
+        }
+        if recording.tick_rate != settings.tick_rate {
+            return Err(RecordingError::TickRateMismatch {
+                recorded: recording.tick_rate,
+                session: settings.tick_rate,
+            });
+        }
+        self.start_recording(settings);
+        self.reference = recording
+            .checksums
+            .iter()
+            .map(|entry| (entry.tick, entry.checksum))
+            .collect();
+        self.playback = Some(recording);
+        Ok(())
+    }
+
+    /// Adds a checksum reported by a networked peer
+    pub fn add_reference(&mut self, tick: u64, checksum: u64) {
+        self.reference.insert(tick, checksum);
+        // The peer may report a tick we've already simulated
+        let actual = self
+            .recording
+            .checksums
+            .iter()
+            .rev()
+            .find(|entry| entry.tick == tick)
+            .map(|entry| entry.checksum);
+        if let Some(actual) = actual {
+            self.compare(tick, actual);
+        }
+    }
+
+    /// Validates `actual` against the reference, returns the desync if this is the first one
+    fn compare(&mut self, tick: u64, actual: u64) -> Option<DesyncEvent> {
+        let expected = *self.reference.get(&tick)?;
+        self.divergence.validated_ticks += 1;
+        if expected == actual {
+            return None;
+        }
+        self.divergence.diverged_ticks += 1;
+        if self.divergence.first.is_some() {
+            return None;
+        }
+        self.divergence.first = Some((tick, expected, actual));
+        Some(DesyncEvent { tick, expected, actual })
+    }
+}
+
+fn determinism_enabled(settings: Res<DeterminismSettings>) -> bool {
+    settings.enabled
+}
+
+/// Seeds the terrain before it's generated at startup
+fn seed_terrain(mut commands: Commands, settings: Res<DeterminismSettings>) {
+    if settings.enabled {
+        commands.insert_resource(TerrainSeed(settings.stream_seed("terrain")));
+    }
+}
+
+/// Switches physics to a fixed step and reseeds random streams when the mode changes
+fn apply_determinism_settings(
+    settings: Res<DeterminismSettings>,
+    mut session: ResMut<DeterminismSession>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    mut wildlife_rng: ResMut<WildlifeRng>,
+    mut traffic: ResMut<Traffic>,
+    mut lightning: ResMut<Lightning>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    if settings.enabled {
+        rapier_config.timestep_mode = TimestepMode::Fixed {
+            dt: settings.fixed_dt(),
+            substeps: 1,
+        };
+        *wildlife_rng = WildlifeRng::from_seed(settings.stream_seed("wildlife"));
+        traffic.rng = WildlifeRng::from_seed(settings.stream_seed("traffic"));
+        lightning.rng = WildlifeRng::from_seed(settings.stream_seed("lightning"));
+        if session.playback.is_none() {
+            session.start_recording(&settings);
+        }
+        info!("Determinism mode on, seed {:#x} at {} Hz", settings.seed, settings.tick_rate);
+    } else {
+        rapier_config.timestep_mode = RapierConfiguration::default().timestep_mode;
+    }
+}
+
+/// Quantizes live inputs, or replaces them with the recording during playback
+pub(super) fn record_player_inputs(
+    mut session: ResMut<DeterminismSession>,
+    mut players: Query<(&PlayerId, &mut PlayerInput)>,
+) {
+    let tick = session.tick;
+    for (player, mut input) in players.iter_mut() {
+        let recorded = match &session.playback {
+            Some(playback) => match playback.inputs_at(tick).iter().find(|recorded| recorded.player == player.0) {
+                Some(recorded) => *recorded,
+                // Player had no input recorded this tick
+                None => RecordedInput::new(tick, player.0, &PlayerInput::default()),
+            },
+            None => RecordedInput::new(tick, player.0, &input),
+        };
+        // Live play uses the quantized values too, so a replay drives physics identically
+        recorded.apply(&mut input);
+        session.recording.inputs.push(recorded);
+    }
+}
+
+/// Hashes vehicle state after the physics step and checks it against the reference
+pub(super) fn record_state_checksum(
+    settings: Res<DeterminismSettings>,
+    mut session: ResMut<DeterminismSession>,
+    mut desyncs: EventWriter<DesyncEvent>,
+    vehicles: Query<(&Transform, Option<&Velocity>, Option<&PlayerId>), With<Vehicle>>,
+) {
+    let snapshots = vehicles
+        .iter()
+        .map(|(transform, velocity, player)| {
+            (player.map(|player| player.0), VehicleSnapshot::new(transform, velocity))
+        })
+        .collect();
+    let entry = TickChecksum {
+        tick: session.tick,
+        checksum: state_checksum(snapshots),
+    };
+
+    session.recording.checksums.push(entry);
+    session.history.push_back(entry);
+    while session.history.len() > settings.checksum_history {
+        session.history.pop_front();
+    }
+    if let Some(desync) = session.compare(entry.tick, entry.checksum) {
+        warn!(
+            "Simulation diverged at tick {}: expected {:016x}, got {:016x}",
+            desync.tick, desync.expected, desync.actual
+        );
+        desyncs.send(desync);
+    }
+    session.tick += 1;
+}
+
+#[derive(Component)]
+pub struct DeterminismOverlayText;
+
+fn spawn_determinism_overlay(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 16.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            right: Val::Px(10.0),
+            ..default()
+        }),
+        Visibility::Hidden,
+        DeterminismOverlayText,
+    ));
+}
+
+/// Text for the divergence diagnostics in the debug overlay
+pub fn determinism_overlay_text(settings: &DeterminismSettings, session: &DeterminismSession) -> String {
+    if !settings.enabled {
+        return "Determinism: off".to_string();
+    }
+    let mode = if session.playback.is_some() { "playback" } else { "recording" };
+    let latest = session
+        .history
+        .back()
+        .map_or("-".to_string(), |entry| format!("{:016x}", entry.checksum));
+    let status = match session.divergence.first {
+        None if session.divergence.validated_ticks == 0 => "no reference".to_string(),
+        None => format!("in sync ({} ticks validated)", session.divergence.validated_ticks),
+        Some((tick, expected, actual)) => format!(
+            "DIVERGED at tick {}: expected {:016x}, got {:016x} ({} of {} ticks differ)",
+            tick,
+            expected,
+            actual,
+            session.divergence.diverged_ticks,
+            session.divergence.validated_ticks
+        ),
+    };
+    format!(
+        "Determinism: {} | seed {:#x} @ {} Hz\nTick {} checksum {}\n{}",
+        mode, settings.seed, settings.tick_rate, session.tick, latest, status
+    )
+}
+
+fn update_determinism_overlay(
+    debug_info: Res<DebugInfo>,
+    settings: Res<DeterminismSettings>,
+    session: Res<DeterminismSession>,
+    mut texts: Query<(&mut Text, &mut Visibility), With<DeterminismOverlayText>>,
+) {
+    for (mut text, mut visibility) in texts.iter_mut() {
+        if !debug_info.show_determinism_debug {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+        *visibility = Visibility::Visible;
+        text.sections[0].value = determinism_overlay_text(&settings, &session);
+        text.sections[0].style.color = if session.divergence.in_sync() {
+            Color::WHITE
+        } else {
+            Color::RED
+        };
+    }
+}
+
+/// Plugin for the deterministic simulation mode
+pub struct DeterminismPlugin;
+
+impl Plugin for DeterminismPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DeterminismSettings>()
+            .init_resource::<DeterminismSession>()
+            .init_resource::<WildlifeRng>()
+            .init_resource::<Traffic>()
+            .init_resource::<Lightning>()
+            .add_event::<DesyncEvent>()
+            .add_systems(PreStartup, seed_terrain)
+            .add_systems(Startup, spawn_determinism_overlay)
+            .add_systems(Update, (
+                apply_determinism_settings,
+                record_player_inputs
+                    .after(read_player_input)
+                    .before(apply_player_input)
+                    .run_if(determinism_enabled),
+                update_determinism_overlay,
+            ))
+            .add_systems(
+                PostUpdate,
+                record_state_checksum
+                    .after(PhysicsSet::Writeback)
+                    .run_if(determinism_enabled),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(x: f32) -> VehicleSnapshot {
+        VehicleSnapshot::new(&Transform::from_xyz(x, 1.0, 2.0), None)
+    }
+
+    #[test]
+    fn test_stream_seeds_differ_but_repeat() {
+        let settings = DeterminismSettings::default();
+        assert_eq!(settings.stream_seed("terrain"), settings.stream_seed("terrain"));
+        assert_ne!(settings.stream_seed("terrain"), settings.stream_seed("wildlife"));
+    }
+
+    /// App after determinism mode was switched on with `seed`
+    fn reseeded(seed: u64) -> App {
+        let mut app = App::new();
+        app.insert_resource(DeterminismSettings { enabled: true, seed, ..default() })
+            .init_resource::<DeterminismSession>()
+            .init_resource::<RapierConfiguration>()
+            .init_resource::<WildlifeRng>()
+            .init_resource::<Traffic>()
+            .init_resource::<Lightning>()
+            .add_systems(Update, apply_determinism_settings);
+        app.update();
+        app
+    }
+
+    #[test]
+    fn test_traffic_stream_follows_the_session_seed() {
+        let mut first = reseeded(7);
+        let mut again = reseeded(7);
+        let mut other = reseeded(8);
+        let next = |app: &mut App| app.world.resource_mut::<Traffic>().rng.next_f32();
+        let sample = next(&mut first);
+        assert_eq!(sample, next(&mut again));
+        assert_ne!(sample, next(&mut other));
+        assert_ne!(sample, first.world.resource_mut::<WildlifeRng>().next_f32());
+    }
+
+    #[test]
+    fn test_lightning_stream_follows_the_session_seed() {
+        let next = |app: &mut App| app.world.resource_mut::<Lightning>().rng.next_f32();
+        let sample = next(&mut reseeded(7));
+        assert_eq!(sample, next(&mut reseeded(7)));
+        assert_ne!(sample, next(&mut reseeded(8)));
+    }
+
+    #[test]
+    fn test_checksum_ignores_entity_order_and_float_noise() {
+        let a = state_checksum(vec![(Some(0), snapshot(1.0)), (None, snapshot(5.0))]);
+        let b = state_checksum(vec![(None, snapshot(5.0)), (Some(0), snapshot(1.0 + 1e-6))]);
+        assert_eq!(a, b);
+        let moved = state_checksum(vec![(Some(0), snapshot(1.1)), (None, snapshot(5.0))]);
+        assert_ne!(a, moved);
+    }
+
+    #[test]
+    fn test_negated_quaternion_hashes_the_same() {
+        let rotation = Quat::from_rotation_y(0.7);
+        let a = VehicleSnapshot::new(&Transform::from_rotation(rotation), None);
+        let b = VehicleSnapshot::new(&Transform::from_rotation(-rotation), None);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_input_quantization_round_trips() {
+        let input = PlayerInput {
+            throttle: 0.73,
+            steering: -0.41,
+            handbrake: true,
+            ..default()
+        };
+        let recorded = RecordedInput::new(3, 0, &input);
+        let mut replayed = PlayerInput::default();
+        recorded.apply(&mut replayed);
+        assert!((replayed.throttle - 0.73).abs() < 1e-4);
+        assert!((replayed.steering + 0.41).abs() < 1e-4);
+        assert!(replayed.handbrake);
+        // Quantizing twice changes nothing
+        assert_eq!(RecordedInput::new(3, 0, &replayed), recorded);
+    }
+
+    #[test]
+    fn test_recording_json_round_trip() {
+        let settings = DeterminismSettings::default();
+        let mut recording = InputRecording::new(&settings);
+        recording.inputs.push(RecordedInput::new(0, 0, &PlayerInput::default()));
+        recording.inputs.push(RecordedInput::new(1, 0, &PlayerInput::default()));
+        recording.inputs.push(RecordedInput::new(1, 1, &PlayerInput::default()));
+        recording.checksums.push(TickChecksum { tick: 0, checksum: 42 });
+        let loaded = InputRecording::from_json(&recording.to_json().unwrap()).unwrap();
+        assert_eq!(loaded, recording);
+        assert_eq!(loaded.inputs_at(1).len(), 2);
+        assert!(loaded.inputs_at(2).is_empty());
+    }
+
+    #[test]
+    fn test_playback_rejects_other_tick_rate() {
+        let settings = DeterminismSettings::default();
+        let recording = InputRecording {
+            tick_rate: 30,
+            ..default()
+        };
+        let mut session = DeterminismSession::default();
+        assert!(matches!(
+            session.start_playback(&settings, recording),
+            Err(RecordingError::TickRateMismatch { recorded: 30, session: 60 })
+        ));
+    }
+
+    #[test]
+    fn test_divergence_reports_first_tick_only() {
+        let mut session = DeterminismSession::default();
+        session.reference.extend([(0, 10), (1, 11), (2, 12)]);
+        assert_eq!(session.compare(0, 10), None);
+        assert_eq!(
+            session.compare(1, 99),
+            Some(DesyncEvent { tick: 1, expected: 11, actual: 99 })
+        );
+        assert_eq!(session.compare(2, 98), None);
+        // No reference for this tick, nothing to validate
+        assert_eq!(session.compare(3, 0), None);
+        assert_eq!(session.divergence.first, Some((1, 11, 99)));
+        assert_eq!(session.divergence.diverged_ticks, 2);
+        assert_eq!(session.divergence.validated_ticks, 3);
+    }
+
+    #[test]
+    fn test_late_peer_checksum_is_validated() {
+        let mut session = DeterminismSession::default();
+        session.recording.checksums.push(TickChecksum { tick: 4, checksum: 7 });
+        session.add_reference(4, 8);
+        assert_eq!(session.divergence.first, Some((4, 8, 7)));
+    }
+}
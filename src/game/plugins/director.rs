@@ -0,0 +1,556 @@
+//! Director mode for turning replays into cinematics.
+//!
+//! While a recorded run plays back, the pose of every vehicle is kept per tick
+//! in the [`ReplayTimeline`]. Director mode then pauses physics and poses the
+//! vehicles from the timeline at [`DirectorState::time`], so the replay can be
+//! scrubbed back and forth freely, and flies the game camera along the
+//! keyframed [`CameraPath`]. Exporting steps through the timeline at a fixed
+//! frame rate, one frame per update whatever the render speed, and writes each
+//! rendered frame to disk as a numbered PNG, optionally handing the sequence to
+//! ffmpeg for a video once it's done.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::render::view::screenshot::ScreenshotManager;
+use bevy::time::TimeUpdateStrategy;
+use bevy::transform::TransformSystem;
+use bevy::window::PrimaryWindow;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::game::vehicle::Vehicle;
+
+use super::camera::GameCamera;
+use super::determinism::{record_state_checksum, DeterminismSession, DeterminismSettings};
+use super::floating_origin::{OriginShiftEvent, ShiftOrigin, WorldOrigin};
+
+/// Export frame rates offered by the director
+pub const EXPORT_FRAME_RATES: [u32; 3] = [24, 30, 60];
+
+/// Where exports go and what encodes them
+#[derive(Resource, Debug, Clone)]
+pub struct DirectorSettings {
+    /// Each export gets its own numbered directory in here
+    pub export_directory: PathBuf,
+    pub frame_rate: u32,
+    /// ffmpeg binary to turn exported frames into a video, `None` leaves just the frames
+    pub ffmpeg: Option<PathBuf>,
+}
+
+impl Default for DirectorSettings {
+    fn default() -> Self {
+        Self {
+            export_directory: std::env::var("SANDK_EXPORT_PATH")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| PathBuf::from("exports")),
+            frame_rate: 30,
+            ffmpeg: std::env::var("SANDK_FFMPEG").ok().map(PathBuf::from),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum DirectorError {
+    #[error("camera path file: {0}")]
+    Io(#[from] io::Error),
+    #[error("could not parse camera path: {0}")]
+    Parse(#[from] serde_json::Error),
+}
+
+/// Where the camera is and what it looks at a moment into the replay
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CameraKeyframe {
+    /// Seconds from the start of the replay
+    pub time: f32,
+    /// In the level's coordinates, so paths stay put when the origin moves and in saved files
+    pub position: [f32; 3],
+    pub look_at: [f32; 3],
+    /// Vertical field of view in radians
+    pub fov: f32,
+}
+
+impl CameraKeyframe {
+    /// Keyframe matching a camera's current view
+    pub fn from_transform(time: f32, transform: &Transform, fov: f32, origin: &WorldOrigin) -> Self {
+        Self {
+            time,
+            position: origin.to_world(transform.translation).to_array(),
+            look_at: origin.to_world(transform.translation + transform.forward() * 10.0).to_array(),
+            fov,
+        }
+    }
+}
+
+/// Catmull-Rom spline through `p1` and `p2` at `t` (0.0 - 1.0)
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * (2.0 * p1 + (p2 - p0) * t + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2 + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+/// Camera keyframes over the replay timeline, sorted by time
+#[derive(Resource, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CameraPath {
+    pub keyframes: Vec<CameraKeyframe>,
+}
+
+impl CameraPath {
+    /// Adds a keyframe, replacing one already at the same time
+    pub fn insert(&mut self, keyframe: CameraKeyframe) {
+        let index = self.keyframes.partition_point(|existing| existing.time < keyframe.time);
+        match self.keyframes.get_mut(index) {
+            Some(existing) if (existing.time - keyframe.time).abs() < 1e-3 => *existing = keyframe,
+            _ => self.keyframes.insert(index, keyframe),
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.keyframes.len() {
+            self.keyframes.remove(index);
+        }
+    }
+
+    /// Camera transform in the level's coordinates and field of view at `time`, held at the first
+    /// and last keyframes
+    pub fn sample(&self, time: f32) -> Option<(Transform, f32)> {
+        let last = self.keyframes.len().checked_sub(1)?;
+        let next = self.keyframes.partition_point(|keyframe| keyframe.time <= time).clamp(1, last.max(1));
+        let (a, b) = (next - 1, next.min(last));
+        let (from, to) = (&self.keyframes[a], &self.keyframes[b]);
+        let span = to.time - from.time;
+        let t = if span > 0.0 { ((time - from.time) / span).clamp(0.0, 1.0) } else { 0.0 };
+
+        // Neighbours shape the curve, repeating the ends so it doesn't overshoot them
+        let before = &self.keyframes[a.saturating_sub(1)];
+        let after = &self.keyframes[(b + 1).min(last)];
+        let point = |keyframe: &CameraKeyframe| Vec3::from(keyframe.position);
+        let target = |keyframe: &CameraKeyframe| Vec3::from(keyframe.look_at);
+        let position = catmull_rom(point(before), point(from), point(to), point(after), t);
+        let look_at = catmull_rom(target(before), target(from), target(to), target(after), t);
+        let fov = from.fov + (to.fov - from.fov) * t;
+        Some((Transform::from_translation(position).looking_at(look_at, Vec3::Y), fov))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, DirectorError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_json(&self) -> Result<String, DirectorError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, DirectorError> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), DirectorError> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        Ok(fs::write(path, self.to_json()?)?)
+    }
+}
+
+/// Vehicle poses at the end of one replayed tick
+#[derive(Debug, Clone, Default)]
+pub struct TimelineFrame {
+    pub tick: u64,
+    pub vehicles: Vec<(Entity, Transform)>,
+}
+
+/// Vehicle poses of the replay, one frame per simulation tick
+#[derive(Resource, Debug, Clone, Default)]
+pub struct ReplayTimeline {
+    pub tick_rate: u32,
+    pub frames: Vec<TimelineFrame>,
+}
+
+impl ReplayTimeline {
+    /// Length of the replay in seconds
+    pub fn duration(&self) -> f32 {
+        self.frames.len().saturating_sub(1) as f32 / self.tick_rate.max(1) as f32
+    }
+
+    /// Pose of every vehicle at `time`, blended between the ticks around it
+    pub fn sample(&self, time: f32) -> Vec<(Entity, Transform)> {
+        let Some(last) = self.frames.len().checked_sub(1) else {
+            return Vec::new();
+        };
+        let position = (time * self.tick_rate.max(1) as f32).clamp(0.0, last as f32);
+        let from = &self.frames[position as usize];
+        let to = &self.frames[(position as usize + 1).min(last)];
+        let t = position.fract();
+        from.vehicles
+            .iter()
+            .map(|(entity, pose)| {
+                let pose = match to.vehicles.iter().find(|(other, _)| other == entity) {
+                    Some((_, next)) => Transform {
+                        translation: pose.translation.lerp(next.translation, t),
+                        rotation: pose.rotation.slerp(next.rotation, t),
+                        scale: pose.scale,
+                    },
+                    None => *pose,
+                };
+                (*entity, pose)
+            })
+            .collect()
+    }
+}
+
+/// Director mode state, edited by the director window
+#[derive(Resource, Debug, Clone, Default)]
+pub struct DirectorState {
+    pub active: bool,
+    /// Seconds into the replay
+    pub time: f32,
+    pub playing: bool,
+    /// Camera follows the path, otherwise the follow camera stays free for framing new keyframes
+    pub preview_path: bool,
+    /// Frames written so far and the total, while an export runs
+    pub export_progress: Option<(u32, u32)>,
+}
+
+/// Asks for the replay to be exported at the director's frame rate
+#[derive(Event, Debug, Clone, Copy)]
+pub struct StartExportEvent;
+
+/// An export wrote its last frame
+#[derive(Event, Debug, Clone)]
+pub struct ExportFinishedEvent {
+    pub directory: PathBuf,
+    pub frames: u32,
+}
+
+/// Running export
+#[derive(Resource, Debug, Clone)]
+struct DirectorExport {
+    directory: PathBuf,
+    frame_rate: u32,
+    next_frame: u32,
+    frame_count: u32,
+    /// Fixed step time advanced by before the export, restored when it ends
+    previous_step: Option<Duration>,
+}
+
+/// Frames an export of `duration` seconds at `frame_rate` writes, both ends included
+pub fn export_frame_count(duration: f32, frame_rate: u32) -> u32 {
+    (duration * frame_rate as f32).floor() as u32 + 1
+}
+
+fn director_active(state: Res<DirectorState>) -> bool {
+    state.active
+}
+
+/// Keeps vehicle poses while a replay plays back, starting over when a new one starts
+fn record_replay_timeline(
+    settings: Res<DeterminismSettings>,
+    session: Res<DeterminismSession>,
+    director: Res<DirectorState>,
+    mut timeline: ResMut<ReplayTimeline>,
+    vehicles: Query<(Entity, &Transform), With<Vehicle>>,
+) {
+    if session.playback.is_none() || director.active {
+        return;
+    }
+    if timeline.frames.last().map_or(false, |frame| frame.tick >= session.tick) {
+        timeline.frames.clear();
+    }
+    timeline.tick_rate = settings.tick_rate;
+    timeline.frames.push(TimelineFrame {
+        tick: session.tick,
+        vehicles: vehicles.iter().map(|(entity, transform)| (entity, *transform)).collect(),
+    });
+}
+
+/// Freezes physics in director mode, the timeline moves the vehicles instead
+fn pause_physics_for_director(director: Res<DirectorState>, mut rapier_config: ResMut<RapierConfiguration>) {
+    if director.is_changed() {
+        rapier_config.physics_pipeline_active = !director.active;
+    }
+}
+
+/// Plays the timeline in real time while the director has it playing
+fn advance_director_time(time: Res<Time>, timeline: Res<ReplayTimeline>, mut director: ResMut<DirectorState>) {
+    if !director.playing || director.export_progress.is_some() {
+        return;
+    }
+    director.time += time.delta_seconds();
+    if director.time >= timeline.duration() {
+        director.time = timeline.duration();
+        director.playing = false;
+    }
+}
+
+/// Starts an export into a fresh numbered directory, stepping time by exactly one frame per update
+fn start_export(
+    mut commands: Commands,
+    settings: Res<DirectorSettings>,
+    timeline: Res<ReplayTimeline>,
+    time_strategy: Res<TimeUpdateStrategy>,
+    mut director: ResMut<DirectorState>,
+    mut requests: EventReader<StartExportEvent>,
+) {
+    if requests.read().count() == 0 || director.export_progress.is_some() || timeline.frames.is_empty() {
+        return;
+    }
+    let index = (1..).find(|index| !settings.export_directory.join(format!("replay_{index:03}")).exists()).unwrap_or(1);
+    let directory = settings.export_directory.join(format!("replay_{index:03}"));
+    if let Err(error) = fs::create_dir_all(&directory) {
+        warn!("Failed to create export directory {}: {error}", directory.display());
+        return;
+    }
+
+    let frame_rate = settings.frame_rate.max(1);
+    let frame_count = export_frame_count(timeline.duration(), frame_rate);
+    info!("Exporting {frame_count} frames at {frame_rate} fps to {}", directory.display());
+    commands.insert_resource(DirectorExport {
+        directory,
+        frame_rate,
+        next_frame: 0,
+        frame_count,
+        previous_step: match *time_strategy {
+            TimeUpdateStrategy::ManualDuration(step) => Some(step),
+            _ => None,
+        },
+    });
+    commands.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(1.0 / frame_rate as f64)));
+    director.active = true;
+    director.playing = false;
+    director.preview_path = true;
+    director.export_progress = Some((0, frame_count));
+}
+
+/// Moves the timeline to the next export frame and has it written once rendered
+fn capture_export_frame(
+    mut commands: Commands,
+    settings: Res<DirectorSettings>,
+    export: Option<ResMut<DirectorExport>>,
+    mut director: ResMut<DirectorState>,
+    mut screenshots: ResMut<ScreenshotManager>,
+    windows: Query<Entity, With<PrimaryWindow>>,
+    mut finished: EventWriter<ExportFinishedEvent>,
+) {
+    let Some(mut export) = export else {
+        return;
+    };
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    if export.next_frame >= export.frame_count {
+        let previous = export.previous_step.map_or(TimeUpdateStrategy::Automatic, TimeUpdateStrategy::ManualDuration);
+        commands.insert_resource(previous);
+        commands.remove_resource::<DirectorExport>();
+        director.export_progress = None;
+        if let Some(ffmpeg) = settings.ffmpeg.clone() {
+            encode_video(ffmpeg, export.directory.clone(), export.frame_rate);
+        }
+        info!("Exported {} frames to {}", export.frame_count, export.directory.display());
+        finished.send(ExportFinishedEvent { directory: export.directory.clone(), frames: export.frame_count });
+        return;
+    }
+
+    let path = export.directory.join(format!("frame_{:05}.png", export.next_frame));
+    // Still busy writing the last frame, try the same one again next update
+    if screenshots.save_screenshot_to_disk(window, path).is_err() {
+        return;
+    }
+    director.time = export.next_frame as f32 / export.frame_rate as f32;
+    export.next_frame += 1;
+    director.export_progress = Some((export.next_frame, export.frame_count));
+}
+
+/// Turns an exported image sequence into an H.264 video next to it, in the background
+fn encode_video(ffmpeg: PathBuf, directory: PathBuf, frame_rate: u32) {
+    std::thread::spawn(move || {
+        let status = Command::new(&ffmpeg)
+            .arg("-y")
+            .args(["-framerate", &frame_rate.to_string()])
+            .arg("-i")
+            .arg(directory.join("frame_%05d.png"))
+            .args(["-c:v", "libx264", "-pix_fmt", "yuv420p"])
+            .arg(directory.join("replay.mp4"))
+            .status();
+        match status {
+            Ok(status) if status.success() => info!("Encoded {}", directory.join("replay.mp4").display()),
+            Ok(status) => warn!("ffmpeg exited with {status}"),
+            Err(error) => warn!("Failed to run {}: {error}", ffmpeg.display()),
+        }
+    });
+}
+
+/// Poses the vehicles from the timeline, after physics wrote its transforms back
+fn pose_replay_vehicles(
+    director: Res<DirectorState>,
+    timeline: Res<ReplayTimeline>,
+    mut vehicles: Query<&mut Transform, With<Vehicle>>,
+) {
+    for (entity, pose) in timeline.sample(director.time) {
+        if let Ok(mut transform) = vehicles.get_mut(entity) {
+            *transform = pose;
+        }
+    }
+}
+
+/// Flies the game camera along the camera path, overriding the follow camera
+fn pose_director_camera(
+    director: Res<DirectorState>,
+    path: Res<CameraPath>,
+    origin: Res<WorldOrigin>,
+    mut cameras: Query<(&mut Transform, &mut Projection), With<GameCamera>>,
+) {
+    if !director.preview_path {
+        return;
+    }
+    let Some((mut pose, fov)) = path.sample(director.time) else {
+        return;
+    };
+    pose.translation = origin.to_local(pose.translation);
+    for (mut transform, mut projection) in cameras.iter_mut() {
+        *transform = pose;
+        if let Projection::Perspective(perspective) = projection.as_mut() {
+            perspective.fov = fov;
+        }
+    }
+}
+
+/// Moves the recorded vehicle poses along with the world origin
+fn rebase_replay_timeline(mut shifts: EventReader<OriginShiftEvent>, mut timeline: ResMut<ReplayTimeline>) {
+    for shift in shifts.read() {
+        for frame in &mut timeline.frames {
+            for (_, pose) in &mut frame.vehicles {
+                pose.translation -= shift.translation();
+            }
+        }
+    }
+}
+
+/// Plugin for director mode and replay export
+pub struct DirectorPlugin;
+
+impl Plugin for DirectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DirectorSettings>()
+            .init_resource::<DirectorState>()
+            .init_resource::<CameraPath>()
+            .init_resource::<ReplayTimeline>()
+            .init_resource::<WorldOrigin>()
+            .add_event::<StartExportEvent>()
+            .add_event::<ExportFinishedEvent>()
+            .add_event::<OriginShiftEvent>()
+            .add_systems(
+                Update,
+                (
+                    start_export,
+                    pause_physics_for_director,
+                    advance_director_time,
+                    capture_export_frame.run_if(resource_exists::<ScreenshotManager>()),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    record_replay_timeline.after(PhysicsSet::Writeback).before(record_state_checksum),
+                    rebase_replay_timeline.after(ShiftOrigin),
+                    (pose_replay_vehicles, pose_director_camera)
+                        .run_if(director_active)
+                        .after(PhysicsSet::Writeback)
+                        .after(rebase_replay_timeline)
+                        .before(TransformSystem::TransformPropagate),
+                ),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::plugins::{FloatingOriginPlugin, FloatingOriginSettings, PlayerId};
+
+    fn keyframe(time: f32, x: f32) -> CameraKeyframe {
+        CameraKeyframe { time, position: [x, 5.0, 0.0], look_at: [x, 0.0, -10.0], fov: 0.8 }
+    }
+
+    #[test]
+    fn test_camera_path_passes_through_keyframes() {
+        let mut path = CameraPath::default();
+        assert!(path.sample(0.0).is_none());
+        path.insert(keyframe(2.0, 20.0));
+        path.insert(keyframe(0.0, 0.0));
+        path.insert(keyframe(1.0, 10.0));
+        path.insert(keyframe(1.0, 12.0));
+        assert_eq!(path.keyframes.len(), 3);
+        assert_eq!(path.keyframes[1].position[0], 12.0);
+
+        for keyframe in path.keyframes.clone() {
+            let (transform, fov) = path.sample(keyframe.time).unwrap();
+            assert!(transform.translation.distance(Vec3::from(keyframe.position)) < 1e-4);
+            assert_eq!(fov, keyframe.fov);
+        }
+        // Held before the first and after the last keyframe
+        assert!(path.sample(-1.0).unwrap().0.translation.distance(Vec3::new(0.0, 5.0, 0.0)) < 1e-4);
+        assert!(path.sample(5.0).unwrap().0.translation.distance(Vec3::new(20.0, 5.0, 0.0)) < 1e-4);
+        let between = path.sample(0.5).unwrap().0.translation.x;
+        assert!(between > 0.0 && between < 12.0);
+
+        let loaded = CameraPath::from_json(&path.to_json().unwrap()).unwrap();
+        assert_eq!(loaded, path);
+    }
+
+    #[test]
+    fn test_timeline_blends_between_ticks() {
+        let entity = Entity::from_raw(7);
+        let timeline = ReplayTimeline {
+            tick_rate: 10,
+            frames: (0..11)
+                .map(|tick| TimelineFrame {
+                    tick,
+                    vehicles: vec![(entity, Transform::from_xyz(tick as f32, 0.0, 0.0))],
+                })
+                .collect(),
+        };
+        assert_eq!(timeline.duration(), 1.0);
+        let sampled = timeline.sample(0.25);
+        assert_eq!(sampled[0].0, entity);
+        assert!((sampled[0].1.translation.x - 2.5).abs() < 1e-4);
+        assert_eq!(timeline.sample(9.0)[0].1.translation.x, 10.0);
+        assert!(ReplayTimeline::default().sample(0.0).is_empty());
+    }
+
+    #[test]
+    fn test_keyframes_and_timeline_follow_origin_shifts() {
+        let mut app = App::new();
+        app.add_plugins(FloatingOriginPlugin)
+            .init_resource::<ReplayTimeline>()
+            .add_systems(PostUpdate, rebase_replay_timeline.after(ShiftOrigin));
+        app.world.resource_mut::<FloatingOriginSettings>().threshold = 1.0;
+        let far = Transform::from_xyz(2000.0, 5.0, 0.0);
+        let vehicle = app.world.spawn((PlayerId(0), far, GlobalTransform::from(far))).id();
+        let frame = TimelineFrame { tick: 0, vehicles: vec![(vehicle, far)] };
+        app.world.resource_mut::<ReplayTimeline>().frames.push(frame);
+        let keyframe = CameraKeyframe::from_transform(0.0, &far, 0.8, app.world.resource::<WorldOrigin>());
+
+        app.world.run_schedule(PostUpdate);
+        let origin = *app.world.resource::<WorldOrigin>();
+        assert_ne!(origin, WorldOrigin::default());
+
+        // Both still land where the vehicle now is
+        let moved = app.world.get::<Transform>(vehicle).unwrap().translation;
+        assert_eq!(app.world.resource::<ReplayTimeline>().frames[0].vehicles[0].1.translation, moved);
+        assert_eq!(origin.to_local(Vec3::from(keyframe.position)), moved);
+    }
+
+    #[test]
+    fn test_export_covers_both_ends() {
+        assert_eq!(export_frame_count(0.0, 30), 1);
+        assert_eq!(export_frame_count(1.0, 30), 31);
+        assert_eq!(export_frame_count(2.5, 24), 61);
+    }
+}
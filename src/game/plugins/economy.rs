@@ -0,0 +1,531 @@
+//! In-game money
+//!
+//! Cash is earned from deliveries, points of interest and races and spent on vehicles and parts in the
+//! garage, fuel at gas stations and the tow service. The balance is kept in the [`GameProgress`] save and
+//! every change to it goes through [`earn`] or [`spend`], which record it in the [`Ledger`] and hand back
+//! the [`TransactionEvent`] announcing it. While the leaderboard is synced the ledger is audited against
+//! the balance every frame, and a run whose money doesn't add up isn't submitted.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use thiserror::Error;
+
+use super::jobs::JobCargo;
+use super::progression::{write_profile, ProgressionConfig, RaceFinishedEvent, Unlock};
+use super::session_log::SessionLogSettings;
+use super::split_screen::PlayerId;
+use super::trails::PoiReachedEvent;
+use crate::game::states::GameProgress;
+use crate::game::vehicle::{Bumper, CargoDeliveredEvent, FuelSource, LiftKit, Part, RefuelEvent, TireType};
+use crate::game::{configure_game_sets, GameSet};
+
+/// Seconds of earnings the audit looks back over
+const EARNING_WINDOW: f32 = 60.0;
+
+/// What a vehicle or part costs once it's unlocked
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceEntry {
+    pub unlock: Unlock,
+    pub price: u32,
+}
+
+impl PriceEntry {
+    fn new(unlock: Unlock, price: u32) -> Self {
+        Self { unlock, price }
+    }
+}
+
+/// Prices of the vehicles and parts that aren't free, paints and accessories come with their level
+pub fn default_prices() -> Vec<PriceEntry> {
+    vec![
+        PriceEntry::new(Unlock::Vehicle("Rock Crawler".to_string()), 12_000),
+        PriceEntry::new(Unlock::Vehicle("Ford Raptor".to_string()), 20_000),
+        PriceEntry::new(Unlock::Vehicle("Trophy Truck".to_string()), 35_000),
+        PriceEntry::new(Unlock::Part(Part::Tires(TireType::MudTerrain)), 1_200),
+        PriceEntry::new(Unlock::Part(Part::Tires(TireType::Crawler)), 2_000),
+        PriceEntry::new(Unlock::Part(Part::LiftKit(LiftKit::TwoInch)), 1_000),
+        PriceEntry::new(Unlock::Part(Part::LiftKit(LiftKit::FourInch)), 2_200),
+        PriceEntry::new(Unlock::Part(Part::Bumper(Bumper::Steel)), 800),
+        PriceEntry::new(Unlock::Part(Part::Bumper(Bumper::Winch)), 1_500),
+    ]
+}
+
+/// Rewards, prices and the limits the leaderboard audit holds the ledger to
+#[derive(Resource, Debug, Clone)]
+pub struct EconomyConfig {
+    /// Cash for delivering cargo intact
+    pub delivery_reward: u32,
+    /// Share of the delivery reward paid for damaged cargo
+    pub damaged_delivery_share: f32,
+    /// Cash for reaching a point of interest
+    pub discovery_reward: u32,
+    /// Cash for finishing a race, each place ahead of last adds the same again
+    pub race_reward: u32,
+    /// Price of a liter at the pump
+    pub fuel_price: f32,
+    pub prices: Vec<PriceEntry>,
+    /// Largest single payout a fair run can get
+    pub max_payout: u32,
+    /// Most a fair run can earn over a minute
+    pub max_earned_per_minute: u32,
+}
+
+impl Default for EconomyConfig {
+    fn default() -> Self {
+        Self {
+            delivery_reward: 300,
+            damaged_delivery_share: 0.4,
+            discovery_reward: 75,
+            race_reward: 150,
+            fuel_price: 1.8,
+            prices: default_prices(),
+            max_payout: 5_000,
+            max_earned_per_minute: 8_000,
+        }
+    }
+}
+
+impl EconomyConfig {
+    /// What `unlock` costs, free when it has no price
+    pub fn price(&self, unlock: &Unlock) -> u32 {
+        self.prices.iter().find(|entry| entry.unlock == *unlock).map_or(0, |entry| entry.price)
+    }
+
+    /// Whether the profile can use `unlock` without paying for it, unlocking it is a separate matter
+    pub fn is_owned(&self, progress: &GameProgress, unlock: &Unlock) -> bool {
+        let bought = match unlock {
+            Unlock::Vehicle(name) => progress.owned_vehicles.contains(name),
+            Unlock::Part(part) => progress.owned_parts.contains(part),
+            Unlock::Paint(_) | Unlock::Accessory(_) => false,
+        };
+        bought || self.price(unlock) == 0
+    }
+
+    /// Cash for finishing a race in `position` of `racers`
+    pub fn race_payout(&self, position: u32, racers: u32) -> u32 {
+        let ahead_of = racers.saturating_sub(position.max(1));
+        self.race_reward * (ahead_of + 1)
+    }
+
+    /// Cash for a delivery, a share of it for damaged cargo
+    pub fn delivery_payout(&self, intact: bool) -> u32 {
+        let share = if intact { 1.0 } else { self.damaged_delivery_share };
+        (self.delivery_reward as f32 * share).round() as u32
+    }
+}
+
+/// What cash changed hands for
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransactionKind {
+    Delivery,
+    Discovery,
+    Race,
+    /// A vehicle or part bought in the garage
+    Purchase(Unlock),
+    Fuel,
+    Tow,
+    /// A delivery job from a job board
+    Job,
+}
+
+impl TransactionKind {
+    /// Whether the player is paid, rather than paying
+    pub fn is_income(&self) -> bool {
+        matches!(self, Self::Delivery | Self::Discovery | Self::Race | Self::Job)
+    }
+
+    pub fn name_key(&self) -> &'static str {
+        match self {
+            Self::Delivery => "economy.delivery",
+            Self::Discovery => "economy.discovery",
+            Self::Race => "economy.race",
+            Self::Purchase(_) => "economy.purchase",
+            Self::Fuel => "economy.fuel",
+            Self::Tow => "economy.tow",
+            Self::Job => "economy.job",
+        }
+    }
+}
+
+/// Cash was earned or spent, for the UI to show
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct TransactionEvent {
+    pub kind: TransactionKind,
+    pub amount: u32,
+    /// Balance after the transaction
+    pub balance: u32,
+}
+
+/// Asks to buy an unlocked vehicle or part
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct PurchaseEvent {
+    pub unlock: Unlock,
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum EconomyError {
+    #[error("costs {price} with only {balance} to spend")]
+    InsufficientFunds { price: u32, balance: u32 },
+    #[error("{0:?} isn't unlocked yet")]
+    Locked(Unlock),
+    #[error("{0:?} is already owned")]
+    AlreadyOwned(Unlock),
+}
+
+/// Money that doesn't add up, found by the leaderboard audit
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum EconomyViolation {
+    #[error("balance is {actual} but the ledger adds up to {expected}")]
+    BalanceMismatch { expected: u64, actual: u32 },
+    #[error("a single payout of {amount} is over the limit of {max}")]
+    OversizedPayout { amount: u32, max: u32 },
+    #[error("{earned} earned within a minute is over the limit of {max}")]
+    EarningRate { earned: u32, max: u32 },
+}
+
+/// A recorded change of the balance
+#[derive(Debug, Clone, PartialEq)]
+pub struct LedgerEntry {
+    /// Seconds since startup
+    pub time: f32,
+    pub kind: TransactionKind,
+    pub amount: u32,
+}
+
+/// Every change of the balance since the game started, for the leaderboard audit
+#[derive(Resource, Debug, Default)]
+pub struct Ledger {
+    /// Balance before the first recorded change
+    opening: Option<u32>,
+    earned: u64,
+    spent: u64,
+    /// Entries of the last [`EARNING_WINDOW`] seconds
+    recent: VecDeque<LedgerEntry>,
+    violation: Option<EconomyViolation>,
+}
+
+impl Ledger {
+    /// Balance the recorded transactions add up to, `None` before the ledger knows where it started
+    pub fn expected_balance(&self) -> Option<u64> {
+        self.opening.map(|opening| (opening as u64 + self.earned).saturating_sub(self.spent))
+    }
+
+    /// First thing the audit found wrong, the run can't go to the leaderboard once set
+    pub fn violation(&self) -> Option<&EconomyViolation> {
+        self.violation.as_ref()
+    }
+
+    fn record(&mut self, time: f32, balance_before: u32, kind: TransactionKind, amount: u32) {
+        self.opening.get_or_insert(balance_before);
+        if kind.is_income() {
+            self.earned += amount as u64;
+        } else {
+            self.spent += amount as u64;
+        }
+        self.recent.push_back(LedgerEntry { time, kind, amount });
+        while self.recent.front().is_some_and(|entry| entry.time < time - EARNING_WINDOW) {
+            self.recent.pop_front();
+        }
+    }
+}
+
+/// Pays `amount` into the balance
+pub fn earn(
+    progress: &mut GameProgress,
+    ledger: &mut Ledger,
+    time: f32,
+    kind: TransactionKind,
+    amount: u32,
+) -> TransactionEvent {
+    ledger.record(time, progress.cash, kind.clone(), amount);
+    progress.cash = progress.cash.saturating_add(amount);
+    TransactionEvent { kind, amount, balance: progress.cash }
+}
+
+/// Takes `amount` from the balance, leaving it alone if there isn't enough
+pub fn spend(
+    progress: &mut GameProgress,
+    ledger: &mut Ledger,
+    time: f32,
+    kind: TransactionKind,
+    amount: u32,
+) -> Result<TransactionEvent, EconomyError> {
+    if progress.cash < amount {
+        return Err(EconomyError::InsufficientFunds { price: amount, balance: progress.cash });
+    }
+    ledger.record(time, progress.cash, kind.clone(), amount);
+    progress.cash -= amount;
+    Ok(TransactionEvent { kind, amount, balance: progress.cash })
+}
+
+/// What's wrong with the ledger of a `balance` at `time`, `None` when it adds up
+pub fn audit_ledger(ledger: &Ledger, balance: u32, config: &EconomyConfig, time: f32) -> Option<EconomyViolation> {
+    if let Some(expected) = ledger.expected_balance().filter(|expected| *expected != balance as u64) {
+        return Some(EconomyViolation::BalanceMismatch { expected, actual: balance });
+    }
+    let earnings = ledger.recent.iter().filter(|entry| entry.kind.is_income());
+    if let Some(entry) = earnings.clone().find(|entry| entry.amount > config.max_payout) {
+        return Some(EconomyViolation::OversizedPayout { amount: entry.amount, max: config.max_payout });
+    }
+    let earned: u32 = earnings.filter(|entry| entry.time >= time - EARNING_WINDOW).map(|entry| entry.amount).sum();
+    (earned > config.max_earned_per_minute)
+        .then_some(EconomyViolation::EarningRate { earned, max: config.max_earned_per_minute })
+}
+
+/// Cash for deliveries, points of interest and races. Job cargo is paid by its job instead.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn pay_rewards(
+    time: Res<Time>,
+    config: Res<EconomyConfig>,
+    progress: Option<ResMut<GameProgress>>,
+    mut ledger: ResMut<Ledger>,
+    players: Query<(), With<PlayerId>>,
+    job_cargo: Query<(), With<JobCargo>>,
+    mut deliveries: EventReader<CargoDeliveredEvent>,
+    mut discoveries: EventReader<PoiReachedEvent>,
+    mut races: EventReader<RaceFinishedEvent>,
+    mut transactions: EventWriter<TransactionEvent>,
+) {
+    let mut payouts: Vec<(TransactionKind, u32)> = Vec::new();
+    for delivery in deliveries.read().filter(|delivery| !job_cargo.contains(delivery.cargo)) {
+        payouts.push((TransactionKind::Delivery, config.delivery_payout(delivery.intact)));
+    }
+    for _ in discoveries.read().filter(|discovery| players.contains(discovery.vehicle)) {
+        payouts.push((TransactionKind::Discovery, config.discovery_reward));
+    }
+    for race in races.read().filter(|race| players.contains(race.vehicle)) {
+        payouts.push((TransactionKind::Race, config.race_payout(race.position, race.racers)));
+    }
+    let Some(mut progress) = progress else {
+        return;
+    };
+    for (kind, amount) in payouts.into_iter().filter(|(_, amount)| *amount > 0) {
+        transactions.send(earn(&mut progress, &mut ledger, time.elapsed_seconds(), kind, amount));
+    }
+}
+
+/// Buys unlocked vehicles and parts the garage asks for and saves the profile with them
+fn handle_purchases(
+    time: Res<Time>,
+    config: Res<EconomyConfig>,
+    progression: Option<Res<ProgressionConfig>>,
+    progress: Option<ResMut<GameProgress>>,
+    mut ledger: ResMut<Ledger>,
+    mut purchases: EventReader<PurchaseEvent>,
+    mut transactions: EventWriter<TransactionEvent>,
+) {
+    let (Some(mut progress), Some(progression)) = (progress, progression) else {
+        purchases.clear();
+        return;
+    };
+    for purchase in purchases.read() {
+        let unlock = purchase.unlock.clone();
+        let bought = if !progression.is_unlocked(&progress, &unlock) {
+            Err(EconomyError::Locked(unlock.clone()))
+        } else if config.is_owned(&progress, &unlock) {
+            Err(EconomyError::AlreadyOwned(unlock.clone()))
+        } else {
+            let price = config.price(&unlock);
+            let kind = TransactionKind::Purchase(unlock.clone());
+            spend(&mut progress, &mut ledger, time.elapsed_seconds(), kind, price)
+        };
+        match bought {
+            Ok(transaction) => {
+                match &unlock {
+                    Unlock::Vehicle(name) => progress.owned_vehicles.push(name.clone()),
+                    Unlock::Part(part) => progress.owned_parts.push(*part),
+                    Unlock::Paint(_) | Unlock::Accessory(_) => {}
+                }
+                transactions.send(transaction);
+                write_profile(&progression, &progress);
+            }
+            Err(error) => warn!("Couldn't buy {unlock:?}: {error}"),
+        }
+    }
+}
+
+/// Bills player vehicles for the fuel they pumped once they let go of the pump. A driver short of cash
+/// pays what they have rather than being left stranded.
+fn bill_fuel(
+    time: Res<Time>,
+    config: Res<EconomyConfig>,
+    progress: Option<ResMut<GameProgress>>,
+    mut ledger: ResMut<Ledger>,
+    players: Query<(), With<PlayerId>>,
+    mut refuels: EventReader<RefuelEvent>,
+    mut transactions: EventWriter<TransactionEvent>,
+    mut pumped: Local<HashMap<Entity, f32>>,
+) {
+    let mut pumping = Vec::new();
+    for refuel in refuels.read() {
+        if matches!(refuel.source, FuelSource::Station(_)) && players.contains(refuel.vehicle) {
+            *pumped.entry(refuel.vehicle).or_default() += refuel.liters;
+            pumping.push(refuel.vehicle);
+        }
+    }
+    let Some(mut progress) = progress else {
+        return;
+    };
+    let done: Vec<Entity> = pumped.keys().filter(|vehicle| !pumping.contains(vehicle)).copied().collect();
+    for vehicle in done {
+        let liters = pumped.remove(&vehicle).unwrap_or_default();
+        let price = ((liters * config.fuel_price).round() as u32).min(progress.cash);
+        if price > 0 {
+            let billed = spend(&mut progress, &mut ledger, time.elapsed_seconds(), TransactionKind::Fuel, price);
+            transactions.send(billed.expect("price is capped at the balance"));
+        }
+    }
+}
+
+fn leaderboard_synced(settings: Option<Res<SessionLogSettings>>) -> bool {
+    settings.is_some_and(|settings| settings.leaderboard_sync)
+}
+
+/// Holds the ledger to the balance and the earning limits, the first violation found sticks
+fn audit_economy(
+    time: Res<Time>,
+    config: Res<EconomyConfig>,
+    progress: Option<Res<GameProgress>>,
+    mut ledger: ResMut<Ledger>,
+) {
+    let Some(progress) = progress else {
+        return;
+    };
+    if ledger.violation.is_some() {
+        return;
+    }
+    // A balance nothing has touched yet is where the ledger starts
+    ledger.opening.get_or_insert(progress.cash);
+    if let Some(violation) = audit_ledger(&ledger, progress.cash, &config, time.elapsed_seconds()) {
+        warn!("Economy audit failed, this run won't go to the leaderboard: {violation}");
+        ledger.violation = Some(violation);
+    }
+}
+
+/// Plugin for earning and spending cash
+pub struct EconomyPlugin;
+
+impl Plugin for EconomyPlugin {
+    fn build(&self, app: &mut App) {
+        configure_game_sets(app);
+        app.init_resource::<EconomyConfig>()
+            .init_resource::<Ledger>()
+            .add_event::<TransactionEvent>()
+            .add_event::<PurchaseEvent>()
+            .add_event::<CargoDeliveredEvent>()
+            .add_event::<PoiReachedEvent>()
+            .add_event::<RaceFinishedEvent>()
+            .add_event::<RefuelEvent>()
+            .add_systems(Update, (
+                pay_rewards,
+                handle_purchases,
+                bill_fuel,
+                audit_economy.run_if(leaderboard_synced),
+            ).chain().in_set(GameSet::PostSim));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spending_needs_the_cash_and_the_ledger_keeps_up() {
+        let config = EconomyConfig::default();
+        let mut progress = GameProgress { cash: 100, ..default() };
+        let mut ledger = Ledger::default();
+
+        let paid = earn(&mut progress, &mut ledger, 1.0, TransactionKind::Delivery, 300);
+        assert_eq!(paid.balance, 400);
+        let spent = spend(&mut progress, &mut ledger, 2.0, TransactionKind::Tow, 250).unwrap();
+        assert_eq!(spent.balance, 150);
+        let short = spend(&mut progress, &mut ledger, 3.0, TransactionKind::Fuel, 151);
+        assert_eq!(short, Err(EconomyError::InsufficientFunds { price: 151, balance: 150 }));
+        assert_eq!(progress.cash, 150);
+        assert_eq!(ledger.expected_balance(), Some(150));
+        assert_eq!(audit_ledger(&ledger, progress.cash, &config, 3.0), None);
+
+        // Free stock parts are owned from the start, the rest once bought
+        let steel = Unlock::Part(Part::Bumper(Bumper::Steel));
+        assert!(config.is_owned(&progress, &Unlock::Part(Part::Bumper(Bumper::Stock))));
+        assert!(!config.is_owned(&progress, &steel));
+        progress.owned_parts.push(Part::Bumper(Bumper::Steel));
+        assert!(config.is_owned(&progress, &steel));
+    }
+
+    #[test]
+    fn test_audit_catches_edited_balances_and_farming() {
+        let config = EconomyConfig { max_payout: 1_000, max_earned_per_minute: 2_000, ..default() };
+        let mut progress = GameProgress::default();
+        let mut ledger = Ledger::default();
+        earn(&mut progress, &mut ledger, 0.0, TransactionKind::Race, 600);
+        assert_eq!(
+            audit_ledger(&ledger, 99_999, &config, 0.0),
+            Some(EconomyViolation::BalanceMismatch { expected: 600, actual: 99_999 })
+        );
+
+        earn(&mut progress, &mut ledger, 1.0, TransactionKind::Race, 1_500);
+        assert_eq!(
+            audit_ledger(&ledger, progress.cash, &config, 1.0),
+            Some(EconomyViolation::OversizedPayout { amount: 1_500, max: 1_000 })
+        );
+
+        let mut ledger = Ledger::default();
+        let mut progress = GameProgress::default();
+        for second in 0..4 {
+            earn(&mut progress, &mut ledger, second as f32, TransactionKind::Delivery, 600);
+        }
+        assert_eq!(
+            audit_ledger(&ledger, progress.cash, &config, 4.0),
+            Some(EconomyViolation::EarningRate { earned: 2_400, max: 2_000 })
+        );
+        // Spread out over a few minutes the same earnings are fine
+        assert_eq!(audit_ledger(&ledger, progress.cash, &config, 200.0), None);
+    }
+
+    #[test]
+    fn test_rewards_purchases_and_fuel() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins).add_plugins(EconomyPlugin);
+        app.insert_resource(ProgressionConfig { profile_path: None, ..default() })
+            .insert_resource(GameProgress { cash: 1_000, ..default() });
+        let player = app.world.spawn(PlayerId(0)).id();
+
+        app.world.send_event(RaceFinishedEvent { vehicle: player, position: 1, racers: 4 });
+        app.world.send_event(CargoDeliveredEvent {
+            cargo: Entity::PLACEHOLDER,
+            zone: Entity::PLACEHOLDER,
+            integrity: 0.5,
+            intact: false,
+        });
+        app.update();
+        // 600 for the win and 120 for the damaged delivery
+        assert_eq!(app.world.resource::<GameProgress>().cash, 1_720);
+
+        // Locked parts can't be bought, unlocked ones can once
+        let steel = Unlock::Part(Part::Bumper(Bumper::Steel));
+        app.world.send_event(PurchaseEvent { unlock: steel.clone() });
+        app.update();
+        assert_eq!(app.world.resource::<GameProgress>().cash, 1_720);
+        app.world.resource_mut::<GameProgress>().unlocked_parts.push(Part::Bumper(Bumper::Steel));
+        app.world.send_event(PurchaseEvent { unlock: steel.clone() });
+        app.world.send_event(PurchaseEvent { unlock: steel });
+        app.update();
+        let progress = app.world.resource::<GameProgress>();
+        assert_eq!(progress.cash, 920);
+        assert_eq!(progress.owned_parts, [Part::Bumper(Bumper::Steel)]);
+
+        // Fuel is billed once the pump stops
+        let station = app.world.spawn_empty().id();
+        for _ in 0..2 {
+            app.world.send_event(RefuelEvent { vehicle: player, source: FuelSource::Station(station), liters: 10.0 });
+            app.update();
+        }
+        assert_eq!(app.world.resource::<GameProgress>().cash, 920);
+        app.update();
+        assert_eq!(app.world.resource::<GameProgress>().cash, 920 - 36);
+        assert!(app.world.resource::<Ledger>().violation().is_none());
+    }
+}
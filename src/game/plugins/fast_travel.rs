@@ -0,0 +1,464 @@
+//! Tow service taking player one's vehicle to a trailhead or a point of interest already reached
+//!
+//! From the trail map the player picks a destination and pays for the tow, a base fee plus a rate
+//! per kilometer of straight-line distance. The screen fades out, the vehicle is put down at the
+//! destination once the ground under it has streamed in, and the screen fades back in. Damage stays
+//! with the vehicle, the tow isn't a repair, but the engine has cooled off on the flatbed and a
+//! nearly empty tank gets a splash of fuel so the vehicle doesn't arrive stranded.
+//!
+//! There's no towing out of a race or a challenge, nor with cargo loaded.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use super::economy::{spend, Ledger, TransactionEvent, TransactionKind};
+use super::split_screen::PlayerId;
+use super::trails::{PoiReachedEvent, PointOfInterest, Trail};
+use crate::game::resources::{self, GameMode as SessionMode};
+use crate::game::states::{GameMode, GameProgress};
+use crate::game::vehicle::{CargoItem, EngineTemperature, EngineThermalConfig, FuelTank, Vehicle, RECOVERY_LIFT};
+use crate::game::{configure_game_sets, GameSet};
+use crate::terrain::TerrainQuery;
+
+/// Trailheads closer together than this are one stop, several trails often start from the same lot
+const SAME_TRAILHEAD: f32 = 10.0;
+
+/// Prices, timing and what a tow does to the vehicle
+#[derive(Resource, Debug, Clone)]
+pub struct FastTravelConfig {
+    /// Cash every tow costs
+    pub base_fee: u32,
+    /// Cash per kilometer towed
+    pub fee_per_km: u32,
+    /// Seconds the screen takes to fade out, and again to fade back in
+    pub fade_time: f32,
+    /// Seconds the screen stays dark at the least, longer while the destination's ground loads
+    pub tow_time: f32,
+    /// Share of the tank (0.0 - 1.0) a tank below it is filled up to
+    pub fuel_reserve: f32,
+}
+
+impl Default for FastTravelConfig {
+    fn default() -> Self {
+        Self {
+            base_fee: 100,
+            fee_per_km: 40,
+            fade_time: 0.6,
+            tow_time: 1.5,
+            fuel_reserve: 0.2,
+        }
+    }
+}
+
+impl FastTravelConfig {
+    /// Price of a tow from `from` to `to`
+    pub fn fee(&self, from: Vec3, to: Vec3) -> u32 {
+        let kilometers = from.xz().distance(to.xz()) / 1000.0;
+        self.base_fee + (kilometers * self.fee_per_km as f32).ceil() as u32
+    }
+}
+
+/// What kind of place a tow goes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DestinationKind {
+    Trailhead,
+    PointOfInterest,
+}
+
+/// A place the tow service goes to
+#[derive(Debug, Clone, PartialEq)]
+pub struct TravelDestination {
+    pub name: String,
+    pub kind: DestinationKind,
+    pub position: Vec3,
+}
+
+/// Why a tow can't be booked right now
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FastTravelBlock {
+    Race,
+    Challenge,
+    Cargo,
+    /// The player can't afford it
+    Funds,
+    /// A tow is already under way
+    Towing,
+}
+
+impl FastTravelBlock {
+    /// Locale key of the reason shown on the map
+    pub fn reason_key(self) -> &'static str {
+        match self {
+            Self::Race => "travel.blocked_race",
+            Self::Challenge => "travel.blocked_challenge",
+            Self::Cargo => "travel.blocked_cargo",
+            Self::Funds => "travel.blocked_funds",
+            Self::Towing => "travel.blocked_towing",
+        }
+    }
+}
+
+/// Trailheads of `trails`, the first point of each, then the discovered points of interest, both by name
+pub fn travel_destinations<'a>(
+    trails: impl IntoIterator<Item = &'a Trail>,
+    points: impl IntoIterator<Item = &'a PointOfInterest>,
+    discovered: &[String],
+) -> Vec<TravelDestination> {
+    let mut destinations: Vec<TravelDestination> = Vec::new();
+    for trail in trails {
+        let Some(start) = trail.0.path.first().map(|point| Vec3::from(*point)) else {
+            continue;
+        };
+        let known = destinations.iter().any(|destination| destination.position.distance(start) < SAME_TRAILHEAD);
+        if !known {
+            destinations.push(TravelDestination {
+                name: trail.0.name.clone(),
+                kind: DestinationKind::Trailhead,
+                position: start,
+            });
+        }
+    }
+    destinations.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut reached: Vec<TravelDestination> = points
+        .into_iter()
+        .filter(|poi| discovered.contains(&poi.desc.name))
+        .map(|poi| TravelDestination {
+            name: poi.desc.name.clone(),
+            kind: DestinationKind::PointOfInterest,
+            position: Vec3::from(poi.desc.position),
+        })
+        .collect();
+    reached.sort_by(|a, b| a.name.cmp(&b.name));
+    destinations.extend(reached);
+    destinations
+}
+
+/// Asks for player one's vehicle to be towed to `destination`
+#[derive(Event, Debug, Clone)]
+pub struct RequestFastTravelEvent {
+    pub destination: TravelDestination,
+}
+
+/// A tow was paid for and the vehicle set down at its destination
+#[derive(Event, Debug, Clone)]
+pub struct FastTravelFinishedEvent {
+    pub vehicle: Entity,
+    pub destination: String,
+    pub fee: u32,
+}
+
+/// A tow under way
+#[derive(Debug, Clone)]
+struct Tow {
+    vehicle: Entity,
+    destination: TravelDestination,
+    fee: u32,
+    /// Seconds since the screen started fading out
+    elapsed: f32,
+    /// The vehicle has been picked up, fueled and cooled off
+    loaded: bool,
+    /// Ground height at the destination, once it's streamed in
+    ground: Option<f32>,
+}
+
+/// The tow under way, if any
+#[derive(Resource, Debug, Default)]
+pub struct FastTravel {
+    tow: Option<Tow>,
+}
+
+impl FastTravel {
+    pub fn is_towing(&self) -> bool {
+        self.tow.is_some()
+    }
+
+    /// Where the current tow is going
+    pub fn destination(&self) -> Option<&TravelDestination> {
+        self.tow.as_ref().map(|tow| &tow.destination)
+    }
+
+    /// How dark the screen is (0.0 - 1.0): fading out, dark while the vehicle is moved, fading back in
+    pub fn fade(&self, config: &FastTravelConfig) -> f32 {
+        let Some(tow) = &self.tow else {
+            return 0.0;
+        };
+        let fade_time = config.fade_time.max(f32::EPSILON);
+        let fade_in_from = if tow.ground.is_some() { fade_time + config.tow_time } else { f32::MAX };
+        if tow.elapsed < fade_time {
+            tow.elapsed / fade_time
+        } else {
+            (1.0 - (tow.elapsed - fade_in_from) / fade_time).min(1.0)
+        }
+    }
+}
+
+/// Why player one's vehicle can't be towed for `fee`, `None` when it can
+pub fn fast_travel_block(
+    fast_travel: &FastTravel,
+    mode: Option<&GameMode>,
+    session: Option<SessionMode>,
+    has_cargo: bool,
+    cash: u32,
+    fee: u32,
+) -> Option<FastTravelBlock> {
+    if fast_travel.is_towing() {
+        Some(FastTravelBlock::Towing)
+    } else if mode == Some(&GameMode::Race) || matches!(session, Some(SessionMode::Race)) {
+        Some(FastTravelBlock::Race)
+    } else if matches!(session, Some(SessionMode::Challenge)) {
+        Some(FastTravelBlock::Challenge)
+    } else if has_cargo {
+        Some(FastTravelBlock::Cargo)
+    } else if cash < fee {
+        Some(FastTravelBlock::Funds)
+    } else {
+        None
+    }
+}
+
+/// Remembers the points of interest players reach, so they can be towed back to them
+fn record_discoveries(
+    players: Query<(), With<PlayerId>>,
+    mut reached: EventReader<PoiReachedEvent>,
+    progress: Option<ResMut<GameProgress>>,
+) {
+    let Some(mut progress) = progress else {
+        reached.clear();
+        return;
+    };
+    for poi in reached.read().filter(|poi| players.contains(poi.vehicle)) {
+        if !progress.discovered_points.contains(&poi.name) {
+            progress.discovered_points.push(poi.name.clone());
+        }
+    }
+}
+
+/// Takes payment and starts the tow if nothing stands in its way
+#[allow(clippy::too_many_arguments)]
+fn start_fast_travel(
+    time: Res<Time>,
+    config: Res<FastTravelConfig>,
+    mut fast_travel: ResMut<FastTravel>,
+    mut requests: EventReader<RequestFastTravelEvent>,
+    progress: Option<ResMut<GameProgress>>,
+    mode: Option<Res<State<GameMode>>>,
+    session: Option<Res<resources::GameState>>,
+    players: Query<(Entity, &PlayerId, &GlobalTransform), With<Vehicle>>,
+    cargo: Query<&CargoItem>,
+    mut ledger: ResMut<Ledger>,
+    mut transactions: EventWriter<TransactionEvent>,
+) {
+    let Some(mut progress) = progress else {
+        requests.clear();
+        return;
+    };
+    for request in requests.read() {
+        let Some((vehicle, _, transform)) = players.iter().find(|(_, player, _)| player.0 == 0) else {
+            continue;
+        };
+        let fee = config.fee(transform.translation(), request.destination.position);
+        let has_cargo = cargo.iter().any(|item| item.in_bed_of == Some(vehicle) || item.strapped_to == Some(vehicle));
+        let mode = mode.as_deref().map(State::get);
+        let session = session.as_ref().map(|session| session.mode);
+        if let Some(block) = fast_travel_block(&fast_travel, mode, session, has_cargo, progress.cash, fee) {
+            warn!("Can't tow to {}: {block:?}", request.destination.name);
+            continue;
+        }
+        match spend(&mut progress, &mut ledger, time.elapsed_seconds(), TransactionKind::Tow, fee) {
+            Ok(transaction) => transactions.send(transaction),
+            Err(error) => {
+                warn!("Can't tow to {}: {error}", request.destination.name);
+                continue;
+            }
+        }
+        fast_travel.tow = Some(Tow {
+            vehicle,
+            destination: request.destination.clone(),
+            fee,
+            elapsed: 0.0,
+            loaded: false,
+            ground: None,
+        });
+    }
+}
+
+/// Moves the towed vehicle behind the faded out screen and holds it there until the ground under it has loaded
+#[allow(clippy::too_many_arguments)]
+fn update_fast_travel(
+    time: Res<Time>,
+    config: Res<FastTravelConfig>,
+    thermal: Option<Res<EngineThermalConfig>>,
+    terrain: Option<Res<TerrainQuery>>,
+    mut fast_travel: ResMut<FastTravel>,
+    mut vehicles: Query<(&mut Transform, Option<&mut Velocity>, Option<&mut FuelTank>, Option<&mut EngineTemperature>)>,
+    mut finished: EventWriter<FastTravelFinishedEvent>,
+) {
+    let Some(tow) = fast_travel.tow.as_mut() else {
+        return;
+    };
+    let Ok((mut transform, velocity, tank, engine)) = vehicles.get_mut(tow.vehicle) else {
+        fast_travel.tow = None;
+        return;
+    };
+
+    tow.elapsed += time.delta_seconds();
+    if tow.elapsed < config.fade_time {
+        return;
+    }
+    if !tow.loaded {
+        // Fully faded out, the vehicle goes on the flatbed
+        tow.loaded = true;
+        if let Some(mut tank) = tank {
+            let reserve = tank.capacity * config.fuel_reserve;
+            if tank.level < reserve {
+                tank.level = reserve;
+            }
+        }
+        if let (Some(mut engine), Some(thermal)) = (engine, thermal) {
+            engine.temperature = thermal.ambient;
+        }
+    }
+
+    // Without streamed terrain, the destination's own height is the ground
+    let destination = tow.destination.position;
+    if tow.ground.is_none() {
+        tow.ground = match terrain {
+            Some(terrain) => terrain.sample(destination.x, destination.z).map(|sample| sample.height),
+            None => Some(destination.y),
+        };
+    }
+    let ground = tow.ground.unwrap_or(destination.y);
+    let (yaw, _, _) = transform.rotation.to_euler(EulerRot::YXZ);
+    transform.translation = Vec3::new(destination.x, ground + RECOVERY_LIFT, destination.z);
+    transform.rotation = Quat::from_rotation_y(yaw);
+    if let Some(mut velocity) = velocity {
+        *velocity = Velocity::zero();
+    }
+
+    if fast_travel.fade(&config) <= 0.0 {
+        let Some(tow) = fast_travel.tow.take() else {
+            return;
+        };
+        finished.send(FastTravelFinishedEvent {
+            vehicle: tow.vehicle,
+            destination: tow.destination.name,
+            fee: tow.fee,
+        });
+    }
+}
+
+/// Plugin for the tow service
+pub struct FastTravelPlugin;
+
+impl Plugin for FastTravelPlugin {
+    fn build(&self, app: &mut App) {
+        configure_game_sets(app);
+        app.init_resource::<FastTravelConfig>()
+            .init_resource::<FastTravel>()
+            .init_resource::<Ledger>()
+            .add_event::<RequestFastTravelEvent>()
+            .add_event::<TransactionEvent>()
+            .add_event::<FastTravelFinishedEvent>()
+            .add_event::<PoiReachedEvent>()
+            .add_systems(Update, (
+                record_discoveries,
+                start_fast_travel,
+                update_fast_travel,
+            ).chain().in_set(GameSet::PostSim));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::plugins::trails::{PoiDesc, TrailDesc, TrailDifficulty};
+
+    fn trail(name: &str, start: [f32; 3]) -> Trail {
+        Trail(TrailDesc {
+            name: name.to_string(),
+            difficulty: TrailDifficulty::Easy,
+            path: vec![start, [start[0] + 50.0, start[1], start[2]]],
+            rain_steps: 0,
+            carve: None,
+            paved: false,
+        })
+    }
+
+    #[test]
+    fn test_destinations_are_trailheads_and_discovered_points() {
+        let trails = [trail("Ridge Climb", [30.0, 0.0, -40.0]), trail("Creek Run", [-6.0, 0.0, 4.0])];
+        let mut loop_trail = trail("Trailhead Loop", [-4.0, 0.0, 5.0]);
+        loop_trail.0.path.push([-6.0, 0.0, 4.0]);
+        let trails = [trails[0].clone(), trails[1].clone(), loop_trail];
+        let desc = |name: &str| -> PoiDesc {
+            serde_json::from_value(serde_json::json!({ "name": name, "position": [10.0, 2.0, 10.0], "message": "" }))
+                .unwrap()
+        };
+        let points = [PointOfInterest::new(desc("Lookout")), PointOfInterest::new(desc("Old Mine"))];
+
+        let destinations = travel_destinations(&trails, &points, &["Old Mine".to_string()]);
+        let names: Vec<&str> = destinations.iter().map(|destination| destination.name.as_str()).collect();
+        // The loop starts in the same lot as the creek run
+        assert_eq!(names, ["Creek Run", "Ridge Climb", "Old Mine"]);
+        assert_eq!(destinations[2].kind, DestinationKind::PointOfInterest);
+        assert_eq!(destinations[2].position, Vec3::new(10.0, 2.0, 10.0));
+    }
+
+    #[test]
+    fn test_fee_and_blocks() {
+        let config = FastTravelConfig::default();
+        assert_eq!(config.fee(Vec3::ZERO, Vec3::new(0.0, 300.0, 0.0)), config.base_fee);
+        assert_eq!(config.fee(Vec3::ZERO, Vec3::new(1500.0, 0.0, 0.0)), config.base_fee + config.fee_per_km * 3 / 2);
+
+        let idle = FastTravel::default();
+        let free_roam = Some(&GameMode::FreeRoam);
+        assert_eq!(fast_travel_block(&idle, free_roam, None, false, 500, 200), None);
+        assert_eq!(fast_travel_block(&idle, free_roam, None, false, 100, 200), Some(FastTravelBlock::Funds));
+        assert_eq!(fast_travel_block(&idle, free_roam, None, true, 500, 200), Some(FastTravelBlock::Cargo));
+        assert_eq!(fast_travel_block(&idle, Some(&GameMode::Race), None, false, 500, 200), Some(FastTravelBlock::Race));
+        let challenge = Some(SessionMode::Challenge);
+        assert_eq!(fast_travel_block(&idle, free_roam, challenge, false, 500, 200), Some(FastTravelBlock::Challenge));
+    }
+
+    #[test]
+    fn test_tow_fades_out_waits_for_ground_and_fades_in() {
+        let config = FastTravelConfig::default();
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins).add_plugins(FastTravelPlugin);
+        app.insert_resource(GameProgress { cash: 1000, ..default() });
+        let vehicle = app
+            .world
+            .spawn((
+                Vehicle::default(),
+                PlayerId(0),
+                TransformBundle::default(),
+                FuelTank { capacity: 60.0, level: 1.0 },
+            ))
+            .id();
+        let destination = TravelDestination {
+            name: "Old Mine".to_string(),
+            kind: DestinationKind::PointOfInterest,
+            position: Vec3::new(400.0, 12.0, -300.0),
+        };
+        app.world.send_event(RequestFastTravelEvent { destination });
+        app.update();
+        assert!(app.world.resource::<FastTravel>().is_towing());
+        let fee = config.fee(Vec3::ZERO, Vec3::new(400.0, 0.0, -300.0));
+        assert_eq!(app.world.resource::<GameProgress>().cash, 1000 - fee);
+
+        // Stepping the tow along by hand, MinimalPlugins' clock barely moves between updates
+        for _ in 0..2 {
+            if let Some(tow) = app.world.resource_mut::<FastTravel>().tow.as_mut() {
+                tow.elapsed += config.fade_time + config.tow_time;
+            }
+            app.update();
+        }
+        assert!(!app.world.resource::<FastTravel>().is_towing());
+        let transform = app.world.get::<Transform>(vehicle).unwrap();
+        assert_eq!(transform.translation, Vec3::new(400.0, 12.0 + RECOVERY_LIFT, -300.0));
+        let tank = app.world.get::<FuelTank>(vehicle).unwrap();
+        assert!((tank.level - 60.0 * config.fuel_reserve).abs() < 1e-4);
+        assert_eq!(app.world.resource::<Events<FastTravelFinishedEvent>>().len(), 1);
+    }
+}
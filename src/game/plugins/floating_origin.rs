@@ -0,0 +1,171 @@
+//! Floating origin for large maps
+//!
+//! f32 positions get coarse a few kilometers out, enough for wheel contacts and suspension to jitter.
+//! Once the players drive far enough from the origin, everything is shifted back toward it by whole
+//! terrain chunks, so the chunk grid stays aligned with itself. [`WorldOrigin`] keeps the total for what
+//! has to work in the level's own coordinates, like terrain generation and level data.
+//!
+//! Only root entities are moved, children follow them. Rapier takes the moved bodies as teleports and
+//! keeps their velocities. Cameras, audio emitters and listeners are entities like any other and move
+//! with the rest, state holding world positions outside of transforms follows [`OriginShiftEvent`].
+
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use bevy_rapier3d::prelude::*;
+
+use super::split_screen::PlayerId;
+use crate::terrain::{world_pos_to_chunk, TerrainFocus, CHUNK_SIZE};
+
+/// Where the local origin sits in the level, in terrain chunks
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WorldOrigin {
+    chunks: IVec2,
+}
+
+impl WorldOrigin {
+    /// Chunk of the level at the local origin
+    pub fn chunks(&self) -> IVec2 {
+        self.chunks
+    }
+
+    /// Level position of the local origin
+    pub fn offset(&self) -> Vec3 {
+        chunk_translation(self.chunks)
+    }
+
+    /// Level position of local `position`
+    pub fn to_world(&self, position: Vec3) -> Vec3 {
+        position + self.offset()
+    }
+
+    /// Local position of level `position`
+    pub fn to_local(&self, position: Vec3) -> Vec3 {
+        position - self.offset()
+    }
+}
+
+fn chunk_translation(chunks: IVec2) -> Vec3 {
+    Vec3::new(chunks.x as f32 * CHUNK_SIZE, 0.0, chunks.y as f32 * CHUNK_SIZE)
+}
+
+/// The origin moved by `chunks` and everything in the world was moved back by as much
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OriginShiftEvent {
+    pub chunks: IVec2,
+}
+
+impl OriginShiftEvent {
+    /// What was taken off every position
+    pub fn translation(&self) -> Vec3 {
+        chunk_translation(self.chunks)
+    }
+}
+
+/// When the origin moves
+#[derive(Resource, Debug, Clone)]
+pub struct FloatingOriginSettings {
+    pub enabled: bool,
+    /// Meters along X or Z the players can get from the origin before it moves under them
+    pub threshold: f32,
+}
+
+impl Default for FloatingOriginSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold: 1000.0,
+        }
+    }
+}
+
+/// Moves the origin. Systems adjusting to a shift run after this set, before transforms propagate.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShiftOrigin;
+
+/// Chunks the origin has to move by so `focus` is back near it, `None` while it's within `threshold`
+pub fn origin_shift(focus: Vec3, threshold: f32) -> Option<IVec2> {
+    let chunks = world_pos_to_chunk(focus);
+    (focus.xz().abs().max_element() > threshold && chunks != IVec2::ZERO).then_some(chunks)
+}
+
+/// Shifts the world back toward the origin once the players are far out, by their midpoint so
+/// split screen players stay as close to it as they can
+#[allow(clippy::type_complexity)]
+fn rebase_origin(
+    settings: Res<FloatingOriginSettings>,
+    mut origin: ResMut<WorldOrigin>,
+    players: Query<&GlobalTransform, With<PlayerId>>,
+    focuses: Query<&GlobalTransform, With<TerrainFocus>>,
+    mut roots: Query<&mut Transform, (Without<Parent>, Without<Node>, Without<Camera2d>)>,
+    mut shifts: EventWriter<OriginShiftEvent>,
+) {
+    if !settings.enabled {
+        return;
+    }
+    let mut focus = players.iter().map(GlobalTransform::translation).collect::<Vec<_>>();
+    if focus.is_empty() {
+        focus.extend(focuses.iter().map(GlobalTransform::translation));
+    }
+    if focus.is_empty() {
+        return;
+    }
+    let midpoint = focus.iter().sum::<Vec3>() / focus.len() as f32;
+    let Some(chunks) = origin_shift(midpoint, settings.threshold) else {
+        return;
+    };
+
+    let shift = OriginShiftEvent { chunks };
+    for mut transform in roots.iter_mut() {
+        transform.translation -= shift.translation();
+    }
+    origin.chunks += chunks;
+    info!("Moved the world origin by {chunks} chunks, now at {}", origin.chunks);
+    shifts.send(shift);
+}
+
+/// Plugin keeping the players near the origin on large maps
+pub struct FloatingOriginPlugin;
+
+impl Plugin for FloatingOriginPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FloatingOriginSettings>()
+            .init_resource::<WorldOrigin>()
+            .add_event::<OriginShiftEvent>()
+            // After Rapier wrote this step's poses back, so the next step starts from the moved ones
+            .configure_sets(
+                PostUpdate,
+                ShiftOrigin
+                    .after(PhysicsSet::Writeback)
+                    .before(TransformSystem::TransformPropagate),
+            )
+            .add_systems(PostUpdate, rebase_origin.in_set(ShiftOrigin));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shift_brings_focus_back_near_origin() {
+        assert_eq!(origin_shift(Vec3::new(900.0, 50.0, -900.0), 1000.0), None);
+        let far = Vec3::new(1250.0, 30.0, -40.0);
+        let chunks = origin_shift(far, 1000.0).unwrap();
+        assert_eq!(chunks.y, 0);
+        let moved = far - OriginShiftEvent { chunks }.translation();
+        assert!(moved.x.abs() <= CHUNK_SIZE * 0.5);
+        assert_eq!(moved.y, far.y);
+        // Below the threshold nothing moves even past a chunk boundary
+        assert_eq!(origin_shift(Vec3::new(CHUNK_SIZE * 3.0, 0.0, 0.0), CHUNK_SIZE * 4.0), None);
+    }
+
+    #[test]
+    fn test_origin_converts_between_local_and_level() {
+        let origin = WorldOrigin { chunks: IVec2::new(3, -2) };
+        let local = Vec3::new(10.0, 5.0, -4.0);
+        let world = origin.to_world(local);
+        assert_eq!(world, local + Vec3::new(3.0 * CHUNK_SIZE, 0.0, -2.0 * CHUNK_SIZE));
+        assert_eq!(origin.to_local(world), local);
+        assert_eq!(WorldOrigin::default().offset(), Vec3::ZERO);
+    }
+}
@@ -0,0 +1,180 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::assets::{check_schema_version, read_schema_version, SchemaVersionError};
+
+/// What sets a hazard off
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HazardTrigger {
+    /// A vehicle comes within `radius` meters of `center`
+    Zone { center: [f32; 3], radius: f32 },
+    /// Precipitation reaches `min_precipitation` (0.0 - 1.0), e.g. a storm
+    Weather { min_precipitation: f32 },
+    /// A fixed time after the level starts, in seconds
+    Timer { after: f32 },
+    /// A mission script calls `spawn_event` with the hazard's name
+    Script,
+}
+
+/// The hazard itself
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HazardKind {
+    /// Rocks released from `origin` that tumble down the slope under physics
+    Rockslide {
+        origin: [f32; 3],
+        /// Horizontal scatter of the release points in meters
+        spread: f32,
+        count: u32,
+        /// Largest rock radius in meters, rocks vary down to half of it
+        rock_radius: f32,
+        /// Release window in seconds, rocks come loose one after another
+        #[serde(default = "default_rockslide_duration")]
+        duration: f32,
+    },
+    /// A tree that topples across the trail
+    FallenTree {
+        /// Base of the trunk
+        base: [f32; 3],
+        height: f32,
+        radius: f32,
+        /// Direction the tree falls, in degrees around the vertical axis (0 falls toward -Z)
+        fall_direction: f32,
+    },
+    /// Raises the named water body from the level water file
+    FlashFlood {
+        water_body: String,
+        /// Rise of the surface in meters
+        rise: f32,
+        /// Time to reach full height in seconds
+        duration: f32,
+    },
+}
+
+fn default_rockslide_duration() -> f32 {
+    4.0
+}
+
+fn default_one_shot() -> bool {
+    true
+}
+
+/// A hazard event as written in a level file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HazardEventDesc {
+    pub name: String,
+    pub trigger: HazardTrigger,
+    pub hazard: HazardKind,
+    /// Only fire once per level load
+    #[serde(default = "default_one_shot")]
+    pub one_shot: bool,
+    /// Seconds before a repeating hazard can fire again
+    #[serde(default)]
+    pub cooldown: f32,
+    /// Sound played at the hazard when it starts, as an asset path
+    #[serde(default)]
+    pub sound: Option<String>,
+    /// Particle effect name handed to effect systems with [`super::HazardStartedEvent`]
+    #[serde(default)]
+    pub particles: Option<String>,
+}
+
+/// Hazard events of a level, loaded from `*.hazards.json`
+#[derive(Asset, TypePath, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LevelHazards {
+    pub events: Vec<HazardEventDesc>,
+}
+
+/// Errors produced while loading level hazard files
+#[derive(Debug, thiserror::Error)]
+pub enum LevelHazardsError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Schema(#[from] SchemaVersionError),
+}
+
+/// Asset loader for level hazard files
+#[derive(Default)]
+pub struct LevelHazardsLoader;
+
+impl AssetLoader for LevelHazardsLoader {
+    type Asset = LevelHazards;
+    type Settings = ();
+    type Error = LevelHazardsError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<LevelHazards, LevelHazardsError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            check_schema_version(read_schema_version(&bytes)?)?;
+            Ok(serde_json::from_slice(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["hazards.json"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_level_hazards() {
+        let json = r#"{
+            "events": [
+                {
+                    "name": "Ridge slide",
+                    "trigger": { "type": "zone", "center": [10.0, 0.0, 40.0], "radius": 15.0 },
+                    "hazard": { "type": "rockslide", "origin": [20.0, 12.0, 40.0], "spread": 6.0, "count": 12, "rock_radius": 0.6 },
+                    "sound": "audio/rockslide.ogg"
+                },
+                {
+                    "name": "Creek flood",
+                    "trigger": { "type": "weather", "min_precipitation": 0.9 },
+                    "hazard": { "type": "flash_flood", "water_body": "Creek", "rise": 0.6, "duration": 30.0 },
+                    "one_shot": false,
+                    "cooldown": 300.0
+                }
+            ]
+        }"#;
+        let level: LevelHazards = serde_json::from_str(json).unwrap();
+        assert_eq!(level.events.len(), 2);
+        assert!(level.events[0].one_shot);
+        assert!(matches!(level.events[0].hazard, HazardKind::Rockslide { duration, .. } if duration == 4.0));
+        assert_eq!(level.events[1].trigger, HazardTrigger::Weather { min_precipitation: 0.9 });
+        assert!(!level.events[1].one_shot);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let level = LevelHazards {
+            events: vec![HazardEventDesc {
+                name: "Deadfall".into(),
+                trigger: HazardTrigger::Timer { after: 60.0 },
+                hazard: HazardKind::FallenTree { base: [0.0; 3], height: 12.0, radius: 0.3, fall_direction: 90.0 },
+                one_shot: true,
+                cooldown: 0.0,
+                sound: None,
+                particles: Some("dust_burst".into()),
+            }],
+        };
+        let json = serde_json::to_string(&level).unwrap();
+        let parsed: LevelHazards = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.events, level.events);
+    }
+}
@@ -0,0 +1,475 @@
+/// Dynamic trail hazards: rockslides, falling trees and flash floods
+///
+/// Hazards come from a level's `*.hazards.json` file. Each event pairs a trigger (a vehicle
+/// entering a zone, the weather turning, or a timer) with a hazard that plays out under physics.
+/// Audio and particle systems hook in through [`HazardStartedEvent`] and [`HazardFinishedEvent`].
+mod level;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use std::f32::consts::TAU;
+
+pub use level::{HazardEventDesc, HazardKind, HazardTrigger, LevelHazards, LevelHazardsError, LevelHazardsLoader};
+
+use super::scripting::ScriptEvent;
+use super::water::FluidVolume;
+use super::weather::WeatherManager;
+use crate::audio::{load_clip, AudioClipCache};
+use crate::game::vehicle::Vehicle;
+
+/// Where a hazard is in its lifetime
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HazardState {
+    /// Waiting for its trigger
+    Armed,
+    /// Playing out, `elapsed` seconds since it fired
+    Active { elapsed: f32 },
+    /// Waiting to re-arm after a repeatable hazard finished
+    Cooldown { remaining: f32 },
+    /// One-shot hazard that already fired
+    Spent,
+}
+
+/// Runtime state of a level hazard event
+#[derive(Component, Debug, Clone)]
+pub struct Hazard {
+    pub desc: HazardEventDesc,
+    pub state: HazardState,
+    /// Seconds since the hazard was spawned with its level, for timer triggers
+    pub age: f32,
+    /// Rocks released so far by an active rockslide
+    released: u32,
+    /// Surface height and depth of a flooding water body before the flood
+    flood_baseline: Option<(f32, f32)>,
+}
+
+impl Hazard {
+    pub fn new(desc: HazardEventDesc) -> Self {
+        Self {
+            desc,
+            state: HazardState::Armed,
+            age: 0.0,
+            released: 0,
+            flood_baseline: None,
+        }
+    }
+}
+
+/// Rocks, trunks and other bodies spawned by a hazard
+#[derive(Component, Debug, Clone, Copy)]
+pub struct HazardDebris {
+    pub hazard: Entity,
+}
+
+/// Marks level entities whose hazards have been spawned
+#[derive(Component)]
+pub struct LevelHazardsSpawned;
+
+/// Coarse hazard type for listeners that don't need the full description
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HazardType {
+    Rockslide,
+    FallenTree,
+    FlashFlood,
+}
+
+impl From<&HazardKind> for HazardType {
+    fn from(kind: &HazardKind) -> Self {
+        match kind {
+            HazardKind::Rockslide { .. } => HazardType::Rockslide,
+            HazardKind::FallenTree { .. } => HazardType::FallenTree,
+            HazardKind::FlashFlood { .. } => HazardType::FlashFlood,
+        }
+    }
+}
+
+/// A hazard fired, for audio and particle systems
+#[derive(Event, Debug, Clone)]
+pub struct HazardStartedEvent {
+    pub hazard: Entity,
+    pub hazard_type: HazardType,
+    pub position: Vec3,
+    pub particles: Option<String>,
+}
+
+/// A hazard finished playing out
+#[derive(Event, Debug, Clone, Copy)]
+pub struct HazardFinishedEvent {
+    pub hazard: Entity,
+    pub hazard_type: HazardType,
+}
+
+/// Whether a trigger fires given the hazard's age, current precipitation and vehicle positions
+pub fn trigger_fires(trigger: &HazardTrigger, age: f32, precipitation: f32, vehicles: &[Vec3]) -> bool {
+    match trigger {
+        HazardTrigger::Zone { center, radius } => {
+            let center = Vec3::from(*center);
+            vehicles.iter().any(|position| position.distance(center) <= *radius)
+        }
+        HazardTrigger::Weather { min_precipitation } => precipitation >= *min_precipitation,
+        HazardTrigger::Timer { after } => age >= *after,
+        // Fired by `ScriptEvent`s instead
+        HazardTrigger::Script => false,
+    }
+}
+
+/// How long a hazard stays active. Floods rise, hold at the peak, then recede, each over `duration`.
+pub fn hazard_duration(kind: &HazardKind) -> f32 {
+    match kind {
+        HazardKind::Rockslide { duration, .. } => *duration,
+        HazardKind::FallenTree { .. } => 0.0,
+        HazardKind::FlashFlood { duration, .. } => duration * 3.0,
+    }
+}
+
+/// Flood level (0.0 - 1.0) `elapsed` seconds into a flash flood
+pub fn flood_level(elapsed: f32, duration: f32) -> f32 {
+    if duration <= 0.0 {
+        return 0.0;
+    }
+    let t = elapsed / duration;
+    if t < 1.0 {
+        // Flash floods come up fast and slow as they peak
+        1.0 - (1.0 - t) * (1.0 - t)
+    } else if t < 2.0 {
+        1.0
+    } else {
+        (3.0 - t).clamp(0.0, 1.0)
+    }
+}
+
+/// Release point of rock `index`, scattered on a golden angle spiral so rocks never stack
+pub fn rock_release_point(origin: Vec3, spread: f32, index: u32, count: u32) -> Vec3 {
+    let golden_angle = TAU * (1.0 - 1.0 / 1.618_034);
+    let r = spread * ((index as f32 + 0.5) / count.max(1) as f32).sqrt();
+    let angle = index as f32 * golden_angle;
+    origin + Vec3::new(angle.cos() * r, 0.0, angle.sin() * r)
+}
+
+/// Radius of rock `index`, between half and all of `max_radius`
+pub fn rock_radius(max_radius: f32, index: u32) -> f32 {
+    let variation = ((index as f32 * 12.9898).sin() * 43758.547).fract().abs();
+    max_radius * (0.5 + 0.5 * variation)
+}
+
+/// Spawns the hazard events of loaded level hazard assets as children of the level entity
+fn spawn_level_hazards(
+    mut commands: Commands,
+    levels: Query<(Entity, &Handle<LevelHazards>), Without<LevelHazardsSpawned>>,
+    level_hazards: Res<Assets<LevelHazards>>,
+) {
+    for (level, handle) in levels.iter() {
+        let Some(hazards) = level_hazards.get(handle) else {
+            continue;
+        };
+
+        let events: Vec<Entity> = hazards
+            .events
+            .iter()
+            .map(|desc| commands.spawn((Hazard::new(desc.clone()), Name::new(desc.name.clone()))).id())
+            .collect();
+        commands.entity(level).push_children(&events).insert(LevelHazardsSpawned);
+    }
+}
+
+/// Arms, fires and cools down hazards
+fn check_hazard_triggers(
+    time: Res<Time>,
+    weather: Option<Res<WeatherManager>>,
+    vehicles: Query<&GlobalTransform, With<Vehicle>>,
+    water_bodies: Query<(&Name, &GlobalTransform), With<FluidVolume>>,
+    mut hazards: Query<(Entity, &mut Hazard)>,
+    mut script_events: EventReader<ScriptEvent>,
+    mut started_events: EventWriter<HazardStartedEvent>,
+) {
+    let dt = time.delta_seconds();
+    let precipitation = weather.map_or(0.0, |weather| weather.current_state().precipitation());
+    let vehicle_positions: Vec<Vec3> = vehicles.iter().map(|t| t.translation()).collect();
+    let scripted: Vec<String> = script_events.read().map(|event| event.name.clone()).collect();
+
+    for (entity, mut hazard) in hazards.iter_mut() {
+        hazard.age += dt;
+        match hazard.state {
+            HazardState::Cooldown { remaining } => {
+                hazard.state = if remaining > dt {
+                    HazardState::Cooldown { remaining: remaining - dt }
+                } else {
+                    HazardState::Armed
+                };
+            }
+            HazardState::Armed
+                if trigger_fires(&hazard.desc.trigger, hazard.age, precipitation, &vehicle_positions)
+                    || (hazard.desc.trigger == HazardTrigger::Script && scripted.contains(&hazard.desc.name)) =>
+            {
+                let position = match &hazard.desc.hazard {
+                    HazardKind::Rockslide { origin, .. } => Vec3::from(*origin),
+                    HazardKind::FallenTree { base, .. } => Vec3::from(*base),
+                    HazardKind::FlashFlood { water_body, .. } => water_bodies
+                        .iter()
+                        .find(|(name, _)| name.as_str() == water_body)
+                        .map_or(Vec3::ZERO, |(_, t)| t.translation()),
+                };
+                hazard.state = HazardState::Active { elapsed: 0.0 };
+                hazard.released = 0;
+                started_events.send(HazardStartedEvent {
+                    hazard: entity,
+                    hazard_type: (&hazard.desc.hazard).into(),
+                    position,
+                    particles: hazard.desc.particles.clone(),
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Plays out active hazards and retires them once done
+#[allow(clippy::too_many_arguments)]
+fn run_active_hazards(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut hazards: Query<(Entity, &mut Hazard)>,
+    mut water_bodies: Query<(&Name, &mut FluidVolume, &mut Transform)>,
+    mut finished_events: EventWriter<HazardFinishedEvent>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut hazard) in hazards.iter_mut() {
+        let HazardState::Active { elapsed } = hazard.state else {
+            continue;
+        };
+        let elapsed = elapsed + dt;
+
+        match hazard.desc.hazard.clone() {
+            HazardKind::Rockslide { origin, spread, count, rock_radius: max_radius, duration } => {
+                let due = if duration > 0.0 {
+                    ((elapsed / duration * count as f32).ceil() as u32).min(count)
+                } else {
+                    count
+                };
+                for index in hazard.released..due {
+                    let radius = rock_radius(max_radius, index);
+                    commands.spawn((
+                        PbrBundle {
+                            mesh: meshes.add(shape::UVSphere { radius, sectors: 10, stacks: 6 }.into()),
+                            material: materials.add(Color::rgb(0.42, 0.38, 0.34).into()),
+                            transform: Transform::from_translation(rock_release_point(Vec3::from(origin), spread, index, count)),
+                            ..default()
+                        },
+                        RigidBody::Dynamic,
+                        Collider::ball(radius),
+                        ColliderMassProperties::Density(2600.0),
+                        Friction::coefficient(0.8),
+                        Ccd::enabled(),
+                        HazardDebris { hazard: entity },
+                        Name::new("Rockslide Rock"),
+                    ));
+                }
+                hazard.released = due;
+            }
+            HazardKind::FallenTree { base, height, radius, fall_direction } if hazard.released == 0 => {
+                // Start just off vertical and let gravity bring it down across the trail
+                let fall_axis = Quat::from_rotation_y(fall_direction.to_radians()) * Vec3::X;
+                let tilt = Quat::from_axis_angle(fall_axis, -0.08);
+                let center = Vec3::from(base) + tilt * Vec3::Y * (height * 0.5);
+                commands.spawn((
+                    PbrBundle {
+                        mesh: meshes.add(shape::Cylinder { radius, height, resolution: 12, segments: 1 }.into()),
+                        material: materials.add(Color::rgb(0.33, 0.24, 0.16).into()),
+                        transform: Transform::from_translation(center).with_rotation(tilt),
+                        ..default()
+                    },
+                    RigidBody::Dynamic,
+                    Collider::cylinder(height * 0.5, radius),
+                    ColliderMassProperties::Density(700.0),
+                    Friction::coefficient(0.9),
+                    HazardDebris { hazard: entity },
+                    Name::new("Fallen Tree"),
+                ));
+                hazard.released = 1;
+            }
+            HazardKind::FlashFlood { water_body, rise, duration } => {
+                if let Some((_, mut volume, mut transform)) = water_bodies
+                    .iter_mut()
+                    .find(|(name, _, _)| name.as_str() == water_body)
+                {
+                    let (surface, depth) =
+                        *hazard.flood_baseline.get_or_insert((transform.translation.y, volume.depth));
+                    let raised = rise * flood_level(elapsed, duration);
+                    transform.translation.y = surface + raised;
+                    volume.depth = depth + raised;
+                }
+            }
+            _ => {}
+        }
+
+        if elapsed >= hazard_duration(&hazard.desc.hazard) {
+            hazard.flood_baseline = None;
+            hazard.state = if hazard.desc.one_shot {
+                HazardState::Spent
+            } else {
+                HazardState::Cooldown { remaining: hazard.desc.cooldown }
+            };
+            finished_events.send(HazardFinishedEvent {
+                hazard: entity,
+                hazard_type: (&hazard.desc.hazard).into(),
+            });
+        } else {
+            hazard.state = HazardState::Active { elapsed };
+        }
+    }
+}
+
+/// Plays each hazard's sound at the spot it starts
+fn play_hazard_sounds(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut clips: Option<ResMut<AudioClipCache>>,
+    hazards: Query<&Hazard>,
+    mut started_events: EventReader<HazardStartedEvent>,
+) {
+    for event in started_events.read() {
+        let Some(sound) = hazards.get(event.hazard).ok().and_then(|hazard| hazard.desc.sound.as_ref()) else {
+            continue;
+        };
+        commands.spawn((
+            AudioBundle {
+                source: load_clip(clips.as_deref_mut(), &asset_server, sound),
+                settings: PlaybackSettings::DESPAWN.with_spatial(true),
+            },
+            SpatialBundle::from_transform(Transform::from_translation(event.position)),
+        ));
+    }
+}
+
+/// Plugin for level hazard events
+pub struct HazardPlugin;
+
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<LevelHazards>()
+            .init_asset_loader::<LevelHazardsLoader>()
+            .add_event::<HazardStartedEvent>()
+            .add_event::<HazardFinishedEvent>()
+            .add_event::<ScriptEvent>()
+            .add_systems(Update, (
+                spawn_level_hazards,
+                check_hazard_triggers,
+                run_active_hazards,
+                play_hazard_sounds,
+            ).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zone_trigger() {
+        let trigger = HazardTrigger::Zone { center: [0.0, 0.0, 10.0], radius: 5.0 };
+        assert!(!trigger_fires(&trigger, 0.0, 0.0, &[]));
+        assert!(!trigger_fires(&trigger, 0.0, 0.0, &[Vec3::ZERO]));
+        assert!(trigger_fires(&trigger, 0.0, 0.0, &[Vec3::ZERO, Vec3::new(0.0, 0.0, 7.0)]));
+    }
+
+    #[test]
+    fn test_weather_and_timer_triggers() {
+        let storm = HazardTrigger::Weather { min_precipitation: 0.9 };
+        assert!(!trigger_fires(&storm, 0.0, 0.6, &[]));
+        assert!(trigger_fires(&storm, 0.0, 1.0, &[]));
+
+        let timer = HazardTrigger::Timer { after: 30.0 };
+        assert!(!trigger_fires(&timer, 29.0, 0.0, &[]));
+        assert!(trigger_fires(&timer, 30.0, 0.0, &[]));
+    }
+
+    #[test]
+    fn test_flood_rises_holds_and_recedes() {
+        assert_eq!(flood_level(0.0, 10.0), 0.0);
+        assert!(flood_level(2.0, 10.0) > 0.2);
+        assert_eq!(flood_level(15.0, 10.0), 1.0);
+        assert!((flood_level(25.0, 10.0) - 0.5).abs() < 1e-5);
+        assert_eq!(flood_level(30.0, 10.0), 0.0);
+    }
+
+    #[test]
+    fn test_rocks_scatter_within_spread() {
+        let origin = Vec3::new(5.0, 10.0, 5.0);
+        let points: Vec<Vec3> = (0..20).map(|i| rock_release_point(origin, 4.0, i, 20)).collect();
+        for (i, a) in points.iter().enumerate() {
+            assert!(a.xz().distance(origin.xz()) <= 4.0);
+            assert_eq!(a.y, origin.y);
+            for b in &points[i + 1..] {
+                assert!(a.distance(*b) > 0.1);
+            }
+        }
+        for i in 0..20 {
+            let radius = rock_radius(1.0, i);
+            assert!((0.5..=1.0).contains(&radius));
+        }
+    }
+
+    #[test]
+    fn test_one_shot_hazard_is_spent() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<Assets<Mesh>>()
+            .init_resource::<Assets<StandardMaterial>>()
+            .add_event::<HazardStartedEvent>()
+            .add_event::<HazardFinishedEvent>()
+            .add_event::<ScriptEvent>()
+            .add_systems(Update, (check_hazard_triggers, run_active_hazards).chain());
+
+        let hazard = app
+            .world
+            .spawn(Hazard::new(HazardEventDesc {
+                name: "Deadfall".into(),
+                trigger: HazardTrigger::Timer { after: 0.0 },
+                hazard: HazardKind::FallenTree { base: [0.0; 3], height: 10.0, radius: 0.3, fall_direction: 0.0 },
+                one_shot: true,
+                cooldown: 0.0,
+                sound: None,
+                particles: None,
+            }))
+            .id();
+
+        app.update();
+        assert_eq!(app.world.get::<Hazard>(hazard).unwrap().state, HazardState::Spent);
+        assert_eq!(app.world.query::<&HazardDebris>().iter(&app.world).count(), 1);
+
+        app.update();
+        assert_eq!(app.world.query::<&HazardDebris>().iter(&app.world).count(), 1);
+    }
+
+    #[test]
+    fn test_script_trigger_waits_for_its_event() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .add_event::<HazardStartedEvent>()
+            .add_event::<ScriptEvent>()
+            .add_systems(Update, check_hazard_triggers);
+
+        let hazard = app
+            .world
+            .spawn(Hazard::new(HazardEventDesc {
+                name: "Creek flood".into(),
+                trigger: HazardTrigger::Script,
+                hazard: HazardKind::Rockslide { origin: [0.0; 3], spread: 1.0, count: 1, rock_radius: 0.5, duration: 1.0 },
+                one_shot: true,
+                cooldown: 0.0,
+                sound: None,
+                particles: None,
+            }))
+            .id();
+
+        app.world.send_event(ScriptEvent { name: "Other hazard".into() });
+        app.update();
+        assert_eq!(app.world.get::<Hazard>(hazard).unwrap().state, HazardState::Armed);
+
+        app.world.send_event(ScriptEvent { name: "Creek flood".into() });
+        app.update();
+        assert_eq!(app.world.get::<Hazard>(hazard).unwrap().state, HazardState::Active { elapsed: 0.0 });
+    }
+}
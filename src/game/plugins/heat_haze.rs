@@ -0,0 +1,186 @@
+use bevy::prelude::*;
+
+use crate::game::constants::{JEEP_HEIGHT, JEEP_LENGTH};
+use crate::game::vehicle::{Engine, EngineTemperature, EngineThermalConfig, Vehicle};
+
+use super::particle_system::{ParticleEffectLifecycle, ParticleEffectType, ParticleMaterial, ParticlePresets};
+
+const HOOD_HAZE: ParticleEffectType = ParticleEffectType("hood_heat_haze");
+const EXHAUST_HAZE: ParticleEffectType = ParticleEffectType("exhaust_heat_haze");
+
+/// Lifetime of a haze particle, matches the preset
+const HAZE_LIFETIME: f32 = 0.8;
+
+/// Where on a vehicle heat haze rises from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeatHazeSource {
+    Hood,
+    Exhaust,
+}
+
+/// A heat haze effect riding on a vehicle, its emission and distortion follow the engine
+#[derive(Component, Debug, Clone, Copy)]
+pub struct HeatHaze {
+    pub vehicle: Entity,
+    pub source: HeatHazeSource,
+}
+
+/// How hot engines shimmer
+#[derive(Resource, Debug, Clone)]
+pub struct HeatHazeSettings {
+    /// Hood haze starts this many °C below the overheat point and peaks at the critical temperature
+    pub hood_margin: f32,
+    /// Exhaust haze at idle, as a fraction of full throttle at redline
+    pub exhaust_idle: f32,
+    /// Exhaust haze from a cold engine, as a fraction of a warm one
+    pub exhaust_cold: f32,
+    /// Hood haze is blown away entirely at this speed in m/s
+    pub airflow_fade_speed: f32,
+    /// Particle distortion strength at full haze
+    pub max_distortion: f32,
+    /// Particles per second at full haze
+    pub max_spawn_rate: f32,
+    /// Emitter positions in the vehicle's local space
+    pub hood_offset: Vec3,
+    pub exhaust_offset: Vec3,
+}
+
+impl Default for HeatHazeSettings {
+    fn default() -> Self {
+        Self {
+            hood_margin: 10.0,
+            exhaust_idle: 0.2,
+            exhaust_cold: 0.5,
+            airflow_fade_speed: 15.0,
+            max_distortion: 0.6,
+            max_spawn_rate: 40.0,
+            hood_offset: Vec3::new(0.0, JEEP_HEIGHT * 0.25, -JEEP_LENGTH * 0.35),
+            exhaust_offset: Vec3::new(0.5, -JEEP_HEIGHT * 0.35, JEEP_LENGTH * 0.5),
+        }
+    }
+}
+
+impl HeatHazeSettings {
+    /// Hood haze (0.0 - 1.0) from the coolant temperature, at standstill
+    pub fn hood_intensity(&self, engine: &EngineTemperature, config: &EngineThermalConfig) -> f32 {
+        let start = config.overheat - self.hood_margin;
+        ((engine.temperature - start) / (config.critical - start).max(f32::EPSILON)).clamp(0.0, 1.0)
+    }
+
+    /// Exhaust haze (0.0 - 1.0) from the engine load, growing as it warms up. None from an engine that isn't turning.
+    pub fn exhaust_intensity(&self, engine: &EngineTemperature, state: &Engine, config: &EngineThermalConfig) -> f32 {
+        if state.rpm <= 0.0 {
+            return 0.0;
+        }
+        let load = state.throttle.clamp(0.0, 1.0) * (state.rpm / config.redline_rpm).clamp(0.0, 1.0);
+        let warm_up = (config.operating - config.ambient).max(f32::EPSILON);
+        let warmth = ((engine.temperature - config.ambient) / warm_up).clamp(0.0, 1.0);
+        let exhaust = self.exhaust_idle + (1.0 - self.exhaust_idle) * load;
+        exhaust * (self.exhaust_cold + (1.0 - self.exhaust_cold) * warmth)
+    }
+
+    /// Fraction of the hood haze left standing in the airflow at `speed` m/s
+    pub fn airflow_scale(&self, speed: f32) -> f32 {
+        (1.0 - speed.abs() / self.airflow_fade_speed.max(f32::EPSILON)).clamp(0.0, 1.0)
+    }
+}
+
+/// Gives every vehicle with an engine temperature a hood and an exhaust haze effect
+fn attach_heat_haze(
+    mut commands: Commands,
+    settings: Res<HeatHazeSettings>,
+    asset_server: Option<Res<AssetServer>>,
+    vehicles: Query<Entity, (With<Vehicle>, Added<EngineTemperature>)>,
+) {
+    for vehicle in vehicles.iter() {
+        let sources = [
+            (HeatHazeSource::Hood, HOOD_HAZE, settings.hood_offset),
+            (HeatHazeSource::Exhaust, EXHAUST_HAZE, settings.exhaust_offset),
+        ];
+        for (source, effect_type, offset) in sources {
+            let texture = asset_server
+                .as_ref()
+                .map(|server| server.load("textures/smoke_atlas.png"))
+                .unwrap_or_default();
+            let effect = ParticlePresets::heat_haze(&mut commands, Transform::from_translation(offset), None);
+            commands
+                .entity(effect)
+                .insert((
+                    ParticleMaterial::preset_heat_haze(texture),
+                    // Never finishes, emission follows the heat instead
+                    ParticleEffectLifecycle::new(effect_type, 0.0, HAZE_LIFETIME).keep_alive(),
+                    HeatHaze { vehicle, source },
+                ))
+                .set_parent(vehicle);
+        }
+    }
+}
+
+/// Scales each haze's emission and distortion with its engine's heat and load
+fn update_heat_haze(
+    settings: Res<HeatHazeSettings>,
+    config: Res<EngineThermalConfig>,
+    vehicles: Query<(&Vehicle, &Engine, &EngineTemperature)>,
+    mut hazes: Query<(&HeatHaze, &mut ParticleEffectLifecycle, &mut ParticleMaterial)>,
+) {
+    for (haze, mut lifecycle, mut material) in hazes.iter_mut() {
+        let Ok((vehicle, state, engine)) = vehicles.get(haze.vehicle) else {
+            continue;
+        };
+        let intensity = match haze.source {
+            HeatHazeSource::Hood => {
+                settings.hood_intensity(engine, &config) * settings.airflow_scale(vehicle.vehicle_speed)
+            }
+            HeatHazeSource::Exhaust => settings.exhaust_intensity(engine, state, &config),
+        };
+        lifecycle.base_spawn_rate = settings.max_spawn_rate * intensity;
+        material.distortion_strength = settings.max_distortion * intensity;
+    }
+}
+
+/// Plugin for heat shimmer over hot engines and exhausts
+pub struct HeatHazePlugin;
+
+impl Plugin for HeatHazePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HeatHazeSettings>()
+            .init_resource::<EngineThermalConfig>()
+            .add_systems(Update, (attach_heat_haze, update_heat_haze).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hood_haze_builds_towards_critical() {
+        let settings = HeatHazeSettings::default();
+        let config = EngineThermalConfig::default();
+        let at = |temperature| EngineTemperature { temperature, ..default() };
+
+        assert_eq!(settings.hood_intensity(&at(config.operating - 5.0), &config), 0.0);
+        let overheating = settings.hood_intensity(&at(config.overheat), &config);
+        assert!(overheating > 0.0 && overheating < 1.0);
+        assert_eq!(settings.hood_intensity(&at(config.critical), &config), 1.0);
+
+        assert_eq!(settings.airflow_scale(0.0), 1.0);
+        assert_eq!(settings.airflow_scale(-settings.airflow_fade_speed * 2.0), 0.0);
+    }
+
+    #[test]
+    fn test_exhaust_haze_follows_load() {
+        let settings = HeatHazeSettings::default();
+        let config = EngineThermalConfig::default();
+        let warm = EngineTemperature { temperature: config.operating, ..default() };
+        let idle = Engine { throttle: 0.0, rpm: 800.0 };
+        let flat_out = Engine { throttle: 1.0, rpm: config.redline_rpm };
+
+        assert_eq!(settings.exhaust_intensity(&warm, &idle, &config), settings.exhaust_idle);
+        assert_eq!(settings.exhaust_intensity(&warm, &flat_out, &config), 1.0);
+        let cold = EngineTemperature::default();
+        assert_eq!(settings.exhaust_intensity(&cold, &flat_out, &config), settings.exhaust_cold);
+        let stalled = Engine { throttle: 1.0, rpm: 0.0 };
+        assert_eq!(settings.exhaust_intensity(&warm, &stalled, &config), 0.0);
+    }
+}
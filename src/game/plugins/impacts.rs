@@ -0,0 +1,378 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_rapier3d::prelude::*;
+
+use crate::game::vehicle::{RadiatorDamageEvent, UnderbodyScrapeEvent, Vehicle};
+
+use super::camera::GameCamera;
+use super::particle_system::{ParticleEffectLifecycle, ParticleEffectType, ParticlePresets, PresetConfig};
+
+const IMPACT_SPARKS: ParticleEffectType = ParticleEffectType("impact_sparks");
+const IMPACT_DUST: ParticleEffectType = ParticleEffectType("impact_dust");
+const SCRAPE_SPARKS: ParticleEffectType = ParticleEffectType("scrape_sparks");
+const SCRAPE_DUST: ParticleEffectType = ParticleEffectType("scrape_dust");
+
+/// What a collider is made of, decides how hits on it look and sound.
+/// Colliders without one count as dirt, like the terrain.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SurfaceMaterial {
+    #[default]
+    Dirt,
+    Rock,
+    Metal,
+    Wood,
+    Ice,
+}
+
+impl SurfaceMaterial {
+    /// Hard surfaces scrape sparks off each other
+    pub fn is_hard(self) -> bool {
+        matches!(self, SurfaceMaterial::Rock | SurfaceMaterial::Metal)
+    }
+}
+
+/// A collision strong enough to notice, sent once per contact pair
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ImpactEvent {
+    pub entity1: Entity,
+    pub entity2: Entity,
+    /// Contact point in world space
+    pub position: Vec3,
+    /// Direction of the force on `entity1`
+    pub normal: Vec3,
+    /// Impulse over the physics step in N·s
+    pub impulse: f32,
+    /// Impulse mapped onto the configured range (0.0 - 1.0)
+    pub intensity: f32,
+    pub surfaces: (SurfaceMaterial, SurfaceMaterial),
+}
+
+impl ImpactEvent {
+    pub fn involves(&self, entity: Entity) -> bool {
+        self.entity1 == entity || self.entity2 == entity
+    }
+
+    pub fn throws_sparks(&self) -> bool {
+        self.surfaces.0.is_hard() && self.surfaces.1.is_hard()
+    }
+}
+
+/// Thresholds for turning contact forces into impacts and what impacts do
+#[derive(Resource, Debug, Clone)]
+pub struct ImpactSettings {
+    /// Contact force in newtons before Rapier reports a pair at all
+    pub contact_force_threshold: f32,
+    /// Impulse of the lightest impact worth reporting
+    pub min_impulse: f32,
+    /// Impulse at which an impact counts as full intensity
+    pub max_impulse: f32,
+    /// Seconds before the same pair can report another impact, a scrape is one impact
+    pub pair_cooldown: f32,
+    /// Intensity above which a frontal hit damages the radiator
+    pub damage_threshold: f32,
+    /// Radiator damage from a full intensity frontal hit
+    pub max_radiator_damage: f32,
+    /// Cameras further than this from an impact don't shake
+    pub shake_radius: f32,
+}
+
+impl Default for ImpactSettings {
+    fn default() -> Self {
+        Self {
+            contact_force_threshold: 20_000.0,
+            min_impulse: 300.0,
+            max_impulse: 15_000.0,
+            pair_cooldown: 0.25,
+            damage_threshold: 0.4,
+            max_radiator_damage: 0.3,
+            shake_radius: 30.0,
+        }
+    }
+}
+
+impl ImpactSettings {
+    /// Intensity for an impulse, `None` when too light to report
+    pub fn intensity(&self, impulse: f32) -> Option<f32> {
+        if impulse < self.min_impulse {
+            return None;
+        }
+        let range = (self.max_impulse - self.min_impulse).max(f32::EPSILON);
+        Some(((impulse - self.min_impulse) / range).clamp(0.0, 1.0))
+    }
+
+    /// Radiator damage for a frontal impact of `intensity`
+    pub fn radiator_damage(&self, intensity: f32) -> Option<f32> {
+        if intensity <= self.damage_threshold {
+            return None;
+        }
+        let over = (intensity - self.damage_threshold) / (1.0 - self.damage_threshold).max(f32::EPSILON);
+        Some(over * self.max_radiator_damage)
+    }
+}
+
+/// Last time each contact pair reported an impact
+#[derive(Default)]
+struct ImpactCooldowns {
+    last: HashMap<(Entity, Entity), f32>,
+}
+
+impl ImpactCooldowns {
+    /// Whether `a` and `b` may report an impact at `now`, marks them as reported if so
+    fn ready(&mut self, a: Entity, b: Entity, now: f32, cooldown: f32) -> bool {
+        let pair = if a < b { (a, b) } else { (b, a) };
+        match self.last.get(&pair) {
+            Some(last) if now - last < cooldown => false,
+            _ => {
+                self.last.insert(pair, now);
+                true
+            }
+        }
+    }
+
+    fn prune(&mut self, now: f32, cooldown: f32) {
+        self.last.retain(|_, last| now - *last < cooldown);
+    }
+}
+
+/// Length of the physics step the contact forces were measured over
+fn physics_step(config: &RapierConfiguration, time: &Time) -> f32 {
+    match config.timestep_mode {
+        TimestepMode::Fixed { dt, .. } | TimestepMode::Interpolated { dt, .. } => dt,
+        TimestepMode::Variable { max_dt, .. } => time.delta_seconds().min(max_dt),
+    }
+}
+
+/// Has vehicles report contact forces so crashes become impacts
+fn enable_contact_force_events(
+    mut commands: Commands,
+    settings: Res<ImpactSettings>,
+    vehicles: Query<(Entity, Option<&ActiveEvents>), Added<Vehicle>>,
+) {
+    for (entity, events) in vehicles.iter() {
+        let events = events.copied().unwrap_or_else(ActiveEvents::empty) | ActiveEvents::CONTACT_FORCE_EVENTS;
+        commands
+            .entity(entity)
+            .insert((events, ContactForceEventThreshold(settings.contact_force_threshold)));
+    }
+}
+
+/// Turns Rapier contact forces into [`ImpactEvent`]s with a contact point and surface pair
+fn process_contact_forces(
+    time: Res<Time>,
+    rapier_config: Res<RapierConfiguration>,
+    rapier_context: Res<RapierContext>,
+    settings: Res<ImpactSettings>,
+    mut cooldowns: Local<ImpactCooldowns>,
+    mut contact_forces: EventReader<ContactForceEvent>,
+    surfaces: Query<&SurfaceMaterial>,
+    transforms: Query<&GlobalTransform>,
+    mut impacts: EventWriter<ImpactEvent>,
+) {
+    let now = time.elapsed_seconds();
+    let step = physics_step(&rapier_config, &time);
+    cooldowns.prune(now, settings.pair_cooldown);
+
+    for event in contact_forces.read() {
+        let impulse = event.total_force_magnitude * step;
+        let Some(intensity) = settings.intensity(impulse) else {
+            continue;
+        };
+        if !cooldowns.ready(event.collider1, event.collider2, now, settings.pair_cooldown) {
+            continue;
+        }
+
+        // Average of the solver contacts, the midpoint between the bodies if there are none
+        let mut contact_sum = Vec3::ZERO;
+        let mut contact_count = 0;
+        if let Some(pair) = rapier_context.contact_pair(event.collider1, event.collider2) {
+            for manifold in pair.manifolds() {
+                for contact in manifold.solver_contacts() {
+                    contact_sum += contact.point();
+                    contact_count += 1;
+                }
+            }
+        }
+        let position = if contact_count > 0 {
+            contact_sum / contact_count as f32
+        } else {
+            match (transforms.get(event.collider1), transforms.get(event.collider2)) {
+                (Ok(a), Ok(b)) => (a.translation() + b.translation()) * 0.5,
+                (Ok(a), Err(_)) | (Err(_), Ok(a)) => a.translation(),
+                _ => continue,
+            }
+        };
+
+        let surface = |entity| surfaces.get(entity).copied().unwrap_or_default();
+        impacts.send(ImpactEvent {
+            entity1: event.collider1,
+            entity2: event.collider2,
+            position,
+            normal: event.total_force.try_normalize().unwrap_or(event.max_force_direction),
+            impulse,
+            intensity,
+            surfaces: (surface(event.collider1), surface(event.collider2)),
+        });
+    }
+}
+
+/// Sparks where hard surfaces meet, a puff of dust otherwise
+fn spawn_impact_particles(mut commands: Commands, mut impacts: EventReader<ImpactEvent>) {
+    for impact in impacts.read() {
+        let transform = Transform::from_translation(impact.position);
+        let config = PresetConfig {
+            intensity: 0.5 + impact.intensity * 2.0,
+            speed: 1.0 + impact.intensity * 3.0,
+            ..default()
+        };
+        let (effect, effect_type, spawn_rate, lifetime) = if impact.throws_sparks() {
+            (ParticlePresets::sparkle(&mut commands, transform, Some(config.clone())), IMPACT_SPARKS, 50.0, 0.8)
+        } else {
+            (ParticlePresets::dust_trail(&mut commands, transform, Some(config.clone())), IMPACT_DUST, 25.0, 2.0)
+        };
+        // A short burst, then the effect winds down and despawns itself
+        commands.entity(effect).insert(
+            ParticleEffectLifecycle::new(effect_type, spawn_rate * config.intensity, lifetime * config.lifetime)
+                .with_duration(0.15),
+        );
+    }
+}
+
+/// A trickle of sparks where steel drags over rock, grit off dirt
+fn spawn_scrape_particles(mut commands: Commands, mut scrapes: EventReader<UnderbodyScrapeEvent>) {
+    for scrape in scrapes.read() {
+        let transform = Transform::from_translation(scrape.position);
+        let config = PresetConfig {
+            intensity: 0.3 + scrape.intensity,
+            speed: 0.5 + scrape.intensity * 2.0,
+            ..default()
+        };
+        let (effect, effect_type, spawn_rate, lifetime) = if scrape.surface.is_hard() {
+            (ParticlePresets::sparkle(&mut commands, transform, Some(config.clone())), SCRAPE_SPARKS, 30.0, 0.5)
+        } else {
+            (ParticlePresets::dust_trail(&mut commands, transform, Some(config.clone())), SCRAPE_DUST, 15.0, 1.5)
+        };
+        commands.entity(effect).insert(
+            ParticleEffectLifecycle::new(effect_type, spawn_rate * config.intensity, lifetime * config.lifetime)
+                .with_duration(0.1),
+        );
+    }
+}
+
+/// Hard hits to the front of a vehicle damage its radiator
+fn apply_impact_damage(
+    settings: Res<ImpactSettings>,
+    mut impacts: EventReader<ImpactEvent>,
+    vehicles: Query<&GlobalTransform, With<Vehicle>>,
+    mut radiator_damage: EventWriter<RadiatorDamageEvent>,
+) {
+    for impact in impacts.read() {
+        let Some(amount) = settings.radiator_damage(impact.intensity) else {
+            continue;
+        };
+        for vehicle in [impact.entity1, impact.entity2] {
+            let Ok(transform) = vehicles.get(vehicle) else {
+                continue;
+            };
+            let to_contact = (impact.position - transform.translation()).normalize_or_zero();
+            if to_contact.dot(transform.forward()) > 0.5 {
+                radiator_damage.send(RadiatorDamageEvent { vehicle, amount });
+            }
+        }
+    }
+}
+
+/// Shakes cameras following a vehicle in the impact, and nearby cameras a little
+fn shake_cameras_on_impact(
+    settings: Res<ImpactSettings>,
+    mut impacts: EventReader<ImpactEvent>,
+    mut cameras: Query<(&mut GameCamera, &GlobalTransform)>,
+) {
+    for impact in impacts.read() {
+        for (mut camera, transform) in cameras.iter_mut() {
+            let trauma = if camera.target.is_some_and(|target| impact.involves(target)) {
+                impact.intensity
+            } else {
+                let distance = transform.translation().distance(impact.position);
+                impact.intensity * 0.5 * (1.0 - distance / settings.shake_radius).max(0.0)
+            };
+            if trauma > 0.0 {
+                camera.add_trauma(trauma);
+            }
+        }
+    }
+}
+
+/// Plugin that turns physics contacts into impacts and fans them out to effects, damage and shake
+pub struct ImpactPlugin;
+
+impl Plugin for ImpactPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ImpactSettings>()
+            .add_event::<ImpactEvent>()
+            .add_event::<UnderbodyScrapeEvent>()
+            .add_systems(Update, (
+                enable_contact_force_events,
+                process_contact_forces,
+                (
+                    spawn_impact_particles,
+                    spawn_scrape_particles,
+                    apply_impact_damage,
+                    shake_cameras_on_impact,
+                ),
+            ).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn impact(surfaces: (SurfaceMaterial, SurfaceMaterial)) -> ImpactEvent {
+        ImpactEvent {
+            entity1: Entity::from_raw(1),
+            entity2: Entity::from_raw(2),
+            position: Vec3::ZERO,
+            normal: Vec3::Y,
+            impulse: 1000.0,
+            intensity: 0.5,
+            surfaces,
+        }
+    }
+
+    #[test]
+    fn test_intensity_maps_impulse_range() {
+        let settings = ImpactSettings::default();
+        assert_eq!(settings.intensity(100.0), None);
+        assert_eq!(settings.intensity(settings.min_impulse), Some(0.0));
+        assert_eq!(settings.intensity(settings.max_impulse * 2.0), Some(1.0));
+        let mid = settings.intensity((settings.min_impulse + settings.max_impulse) * 0.5).unwrap();
+        assert!((mid - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_only_hard_hits_damage_radiator() {
+        let settings = ImpactSettings::default();
+        assert_eq!(settings.radiator_damage(settings.damage_threshold), None);
+        assert_eq!(settings.radiator_damage(1.0), Some(settings.max_radiator_damage));
+    }
+
+    #[test]
+    fn test_sparks_need_two_hard_surfaces() {
+        assert!(impact((SurfaceMaterial::Metal, SurfaceMaterial::Rock)).throws_sparks());
+        assert!(!impact((SurfaceMaterial::Metal, SurfaceMaterial::Dirt)).throws_sparks());
+        assert!(!impact((SurfaceMaterial::Wood, SurfaceMaterial::Metal)).throws_sparks());
+    }
+
+    #[test]
+    fn test_cooldown_is_per_pair_and_order_independent() {
+        let mut cooldowns = ImpactCooldowns::default();
+        let (a, b, c) = (Entity::from_raw(1), Entity::from_raw(2), Entity::from_raw(3));
+        assert!(cooldowns.ready(a, b, 0.0, 0.25));
+        assert!(!cooldowns.ready(b, a, 0.1, 0.25));
+        assert!(cooldowns.ready(a, c, 0.1, 0.25));
+        assert!(cooldowns.ready(a, b, 0.3, 0.25));
+
+        cooldowns.prune(1.0, 0.25);
+        assert!(cooldowns.last.is_empty());
+    }
+}
@@ -0,0 +1,591 @@
+//! Delivery jobs offered on job boards at points of interest
+//!
+//! Every point of interest keeps a board of a few jobs made up from the level's other points: carry a
+//! kind of cargo from here to there for a payout, some with a condition attached for a bonus, a time
+//! limit, a cap on damage to the cargo or staying off paved roads. Taking a job puts the cargo in
+//! player one's bed, switches the session to jobs mode and routes the vehicle to a drop-off zone at
+//! the destination. Delivering it there pays out through the economy, breaking the condition, losing
+//! the cargo or abandoning the job ends it without pay. Boards offer new jobs every so often.
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use super::economy::{earn, pay_rewards, Ledger, TransactionEvent, TransactionKind};
+use super::routing::{RouteToEvent, VehicleRoute};
+use super::split_screen::PlayerId;
+use super::trails::{PoiDesc, PointOfInterest, Trail};
+use crate::game::resources::{self, GameMode};
+use crate::game::states::GameProgress;
+use crate::game::vehicle::{spawn_cargo, CargoBed, CargoDeliveredEvent, CargoItem, CargoKind, DeliveryZone, Vehicle};
+use crate::game::{configure_game_sets, GameSet};
+
+/// Destinations closer than this to the board aren't worth a job
+const MIN_JOB_DISTANCE: f32 = 30.0;
+
+/// Extra the driver has to manage on top of getting the cargo there
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobCondition {
+    /// Seconds to make the delivery in
+    TimeLimit(f32),
+    /// Share of the cargo's condition it may lose on the way (0.0 - 1.0)
+    MaxDamage(f32),
+    /// No driving on paved trails until the cargo is delivered
+    AvoidPaved,
+}
+
+impl JobCondition {
+    /// Localization key of the condition's description
+    pub fn name_key(self) -> &'static str {
+        match self {
+            Self::TimeLimit(_) => "jobs.condition.time_limit",
+            Self::MaxDamage(_) => "jobs.condition.max_damage",
+            Self::AvoidPaved => "jobs.condition.avoid_paved",
+        }
+    }
+}
+
+/// A delivery offered on a job board
+#[derive(Debug, Clone, PartialEq)]
+pub struct Job {
+    /// Unique among the jobs offered this session
+    pub id: u32,
+    /// Point of interest whose board offers it
+    pub origin: String,
+    pub destination: String,
+    pub destination_position: Vec3,
+    pub cargo: CargoKind,
+    pub condition: Option<JobCondition>,
+    pub payout: u32,
+}
+
+/// How job boards make up their jobs and how jobs are held to their conditions
+#[derive(Resource, Debug, Clone)]
+pub struct JobsConfig {
+    pub jobs_per_board: usize,
+    pub base_payout: u32,
+    /// Payout per kilometer of straight-line distance to the destination
+    pub payout_per_km: u32,
+    /// Share of the payout added for a job with a condition
+    pub condition_bonus: f32,
+    /// Seconds a timed job allows per kilometer of straight-line distance
+    pub seconds_per_km: f32,
+    /// Seconds every timed job gets on top
+    pub time_slack: f32,
+    /// Share of its condition the cargo of a careful job may lose
+    pub max_damage: f32,
+    /// Meters either side of a paved trail's path that count as being on it
+    pub paved_width: f32,
+    /// Radius of the drop-off zone at the destination
+    pub drop_off_radius: f32,
+    /// Seconds before the boards offer new jobs
+    pub refresh_interval: f32,
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self {
+            jobs_per_board: 3,
+            base_payout: 150,
+            payout_per_km: 400,
+            condition_bonus: 0.5,
+            seconds_per_km: 300.0,
+            time_slack: 60.0,
+            max_damage: 0.2,
+            paved_width: 5.0,
+            drop_off_radius: 6.0,
+            refresh_interval: 600.0,
+        }
+    }
+}
+
+impl JobsConfig {
+    /// Payout of a delivery over `distance` meters, rounded to tens
+    pub fn payout(&self, distance: f32, condition: Option<JobCondition>) -> u32 {
+        let base = self.base_payout as f32 + self.payout_per_km as f32 * distance / 1000.0;
+        let bonus = if condition.is_some() { 1.0 + self.condition_bonus } else { 1.0 };
+        ((base * bonus / 10.0).round() * 10.0) as u32
+    }
+
+    /// Seconds a timed job over `distance` meters allows
+    pub fn time_limit(&self, distance: f32) -> f32 {
+        self.time_slack + self.seconds_per_km * distance / 1000.0
+    }
+}
+
+/// Xorshift, so the same board seed offers the same jobs on every machine
+struct JobRandom(u32);
+
+impl JobRandom {
+    fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+
+    fn pick(&mut self, count: usize) -> usize {
+        self.next() as usize % count.max(1)
+    }
+}
+
+/// Board seed of a point of interest for a refresh `generation`
+fn board_seed(name: &str, generation: u32) -> u32 {
+    let hash = name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193));
+    (hash ^ generation.wrapping_mul(0x9e37_79b9)) | 1
+}
+
+/// Jobs the board at `origin` offers to the other points of interest, numbered from `first_id`
+pub fn generate_jobs(
+    config: &JobsConfig,
+    origin: &PoiDesc,
+    destinations: &[&PoiDesc],
+    seed: u32,
+    first_id: u32,
+) -> Vec<Job> {
+    let start = Vec3::from(origin.position);
+    let candidates: Vec<&PoiDesc> = destinations
+        .iter()
+        .copied()
+        .filter(|poi| poi.name != origin.name && Vec3::from(poi.position).distance(start) >= MIN_JOB_DISTANCE)
+        .collect();
+    if candidates.is_empty() {
+        return Vec::new();
+    }
+
+    let mut random = JobRandom(seed | 1);
+    (0..config.jobs_per_board)
+        .map(|index| {
+            let destination = candidates[random.pick(candidates.len())];
+            let position = Vec3::from(destination.position);
+            let distance = position.distance(start);
+            let condition = match random.pick(4) {
+                0 => None,
+                1 => Some(JobCondition::TimeLimit(config.time_limit(distance))),
+                2 => Some(JobCondition::MaxDamage(config.max_damage)),
+                _ => Some(JobCondition::AvoidPaved),
+            };
+            Job {
+                id: first_id + index as u32,
+                origin: origin.name.clone(),
+                destination: destination.name.clone(),
+                destination_position: position,
+                cargo: CargoKind::ALL[random.pick(CargoKind::ALL.len())],
+                condition,
+                payout: config.payout(distance, condition),
+            }
+        })
+        .collect()
+}
+
+/// Whether `position` is within `width` meters, across the ground, of a paved trail
+pub fn on_paved_trail<'a>(trails: impl IntoIterator<Item = &'a Trail>, position: Vec3, width: f32) -> bool {
+    let point = position.xz();
+    trails.into_iter().filter(|trail| trail.0.paved).any(|trail| {
+        trail.0.path.windows(2).any(|segment| {
+            let (a, b) = (Vec3::from(segment[0]).xz(), Vec3::from(segment[1]).xz());
+            let along = b - a;
+            let t = ((point - a).dot(along) / along.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+            (a + along * t).distance(point) <= width
+        })
+    })
+}
+
+/// Why a job ended without pay
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobFailure {
+    TimeUp,
+    TooDamaged,
+    DroveOnPaved,
+    /// The cargo was destroyed or went missing
+    CargoLost,
+    Abandoned,
+}
+
+impl JobFailure {
+    /// Localization key of the reason
+    pub fn reason_key(self) -> &'static str {
+        match self {
+            Self::TimeUp => "jobs.failed.time_up",
+            Self::TooDamaged => "jobs.failed.too_damaged",
+            Self::DroveOnPaved => "jobs.failed.paved",
+            Self::CargoLost => "jobs.failed.cargo_lost",
+            Self::Abandoned => "jobs.failed.abandoned",
+        }
+    }
+}
+
+/// How a job ended
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobOutcome {
+    Delivered { payout: u32 },
+    Failed(JobFailure),
+}
+
+/// The job player one has taken
+#[derive(Debug, Clone)]
+pub struct ActiveJob {
+    pub job: Job,
+    pub vehicle: Entity,
+    pub cargo: Entity,
+    pub drop_off: Entity,
+    /// Seconds since the job was taken
+    pub elapsed: f32,
+}
+
+impl ActiveJob {
+    /// Seconds left on a timed job
+    pub fn time_left(&self) -> Option<f32> {
+        match self.job.condition {
+            Some(JobCondition::TimeLimit(limit)) => Some((limit - self.elapsed).max(0.0)),
+            _ => None,
+        }
+    }
+}
+
+/// Jobs on offer at each point of interest and the one being worked
+#[derive(Resource, Debug, Default)]
+pub struct JobBoards {
+    boards: HashMap<String, Vec<Job>>,
+    /// Seconds since the boards last offered new jobs
+    since_refresh: f32,
+    generation: u32,
+    next_id: u32,
+    active: Option<ActiveJob>,
+}
+
+impl JobBoards {
+    /// Jobs offered at the point of interest named `poi`
+    pub fn board(&self, poi: &str) -> &[Job] {
+        self.boards.get(poi).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn active(&self) -> Option<&ActiveJob> {
+        self.active.as_ref()
+    }
+}
+
+/// Marks the cargo of the job being worked
+#[derive(Component, Debug, Clone, Copy)]
+pub struct JobCargo;
+
+/// Marks the delivery zone of the job being worked
+#[derive(Component, Debug, Clone)]
+pub struct JobDropOff {
+    pub destination: String,
+}
+
+/// Takes job `job` from the board player one is at
+#[derive(Event, Debug, Clone, Copy)]
+pub struct AcceptJobEvent {
+    pub job: u32,
+}
+
+/// Gives up the job being worked
+#[derive(Event, Debug, Clone, Copy, Default)]
+pub struct AbandonJobEvent;
+
+/// A job was delivered or failed
+#[derive(Event, Debug, Clone)]
+pub struct JobFinishedEvent {
+    pub job: Job,
+    pub outcome: JobOutcome,
+}
+
+/// Point of interest whose board a vehicle at `position` can read
+pub fn board_at<'a>(points: impl IntoIterator<Item = &'a PointOfInterest>, position: Vec3) -> Option<&'a PoiDesc> {
+    points
+        .into_iter()
+        .map(|poi| &poi.desc)
+        .find(|poi| Vec3::from(poi.position).distance(position) <= poi.radius)
+}
+
+/// Fills the boards of points of interest that have none, and offers new jobs everywhere once in a while
+fn refresh_job_boards(
+    time: Res<Time>,
+    config: Res<JobsConfig>,
+    points: Query<&PointOfInterest>,
+    mut boards: ResMut<JobBoards>,
+) {
+    boards.since_refresh += time.delta_seconds();
+    if boards.since_refresh >= config.refresh_interval {
+        boards.since_refresh = 0.0;
+        boards.generation += 1;
+        boards.boards.clear();
+    }
+
+    let descs: Vec<&PoiDesc> = points.iter().map(|poi| &poi.desc).collect();
+    for origin in &descs {
+        if boards.boards.contains_key(&origin.name) {
+            continue;
+        }
+        let seed = board_seed(&origin.name, boards.generation);
+        let jobs = generate_jobs(&config, origin, &descs, seed, boards.next_id);
+        boards.next_id += jobs.len() as u32;
+        boards.boards.insert(origin.name.clone(), jobs);
+    }
+}
+
+/// Starts a job taken from the board player one is at: loads the cargo, sets up the drop-off and the route
+#[allow(clippy::too_many_arguments)]
+fn accept_jobs(
+    mut commands: Commands,
+    config: Res<JobsConfig>,
+    mut boards: ResMut<JobBoards>,
+    mut requests: EventReader<AcceptJobEvent>,
+    players: Query<(Entity, &PlayerId, &GlobalTransform, Option<&CargoBed>), With<Vehicle>>,
+    points: Query<&PointOfInterest>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut session: Option<ResMut<resources::GameState>>,
+    mut routes: EventWriter<RouteToEvent>,
+) {
+    for request in requests.read() {
+        if boards.active.is_some() {
+            continue;
+        }
+        let Some((vehicle, _, transform, bed)) = players.iter().find(|(_, player, ..)| player.0 == 0) else {
+            continue;
+        };
+        let Some(board) = board_at(&points, transform.translation()) else {
+            continue;
+        };
+        let Some(jobs) = boards.boards.get_mut(&board.name) else {
+            continue;
+        };
+        let Some(index) = jobs.iter().position(|job| job.id == request.job) else {
+            continue;
+        };
+        let job = jobs.remove(index);
+
+        // Dropped into the bed when there is one, next to the vehicle when there isn't
+        let lift = Vec3::Y * (job.cargo.half_extents().y + 0.05);
+        let position = match bed {
+            Some(bed) => transform.transform_point(bed.offset + lift),
+            None => transform.transform_point(Vec3::new(2.5, 0.5, 0.0)),
+        };
+        let transform = Transform::from_translation(position);
+        let cargo = spawn_cargo(&mut commands, &mut meshes, &mut materials, job.cargo, transform);
+        commands.entity(cargo).insert(JobCargo);
+        let drop_off = commands
+            .spawn((
+                DeliveryZone { radius: config.drop_off_radius, min_integrity: 0.0 },
+                JobDropOff { destination: job.destination.clone() },
+                TransformBundle::from_transform(Transform::from_translation(job.destination_position)),
+                Name::new(format!("Job drop-off at {}", job.destination)),
+            ))
+            .id();
+        routes.send(RouteToEvent { vehicle, destination: job.destination_position });
+        if let Some(session) = session.as_mut() {
+            session.mode = GameMode::Jobs;
+        }
+        boards.active = Some(ActiveJob { job, vehicle, cargo, drop_off, elapsed: 0.0 });
+    }
+}
+
+/// Holds the job being worked to its condition and ends it when the cargo is delivered or it can't be anymore.
+/// Delivered jobs pay out through the economy.
+#[allow(clippy::too_many_arguments)]
+fn track_active_job(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<JobsConfig>,
+    mut boards: ResMut<JobBoards>,
+    trails: Query<&Trail>,
+    vehicles: Query<&GlobalTransform, With<Vehicle>>,
+    cargo: Query<&CargoItem>,
+    mut delivered: EventReader<CargoDeliveredEvent>,
+    mut abandoned: EventReader<AbandonJobEvent>,
+    progress: Option<ResMut<GameProgress>>,
+    mut ledger: ResMut<Ledger>,
+    session: Option<ResMut<resources::GameState>>,
+    mut transactions: EventWriter<TransactionEvent>,
+    mut finished: EventWriter<JobFinishedEvent>,
+) {
+    let Some(active) = boards.active.as_mut() else {
+        delivered.clear();
+        abandoned.clear();
+        return;
+    };
+    active.elapsed += time.delta_seconds();
+
+    let delivery = delivered
+        .read()
+        .filter(|delivery| delivery.cargo == active.cargo && delivery.zone == active.drop_off)
+        .last()
+        .copied();
+    let too_damaged = |integrity: f32| {
+        matches!(active.job.condition, Some(JobCondition::MaxDamage(max)) if 1.0 - integrity > max)
+    };
+    let item = cargo.get(active.cargo).ok().filter(|item| !item.is_destroyed());
+    let outcome = if abandoned.read().count() > 0 {
+        JobOutcome::Failed(JobFailure::Abandoned)
+    } else if let Some(delivery) = delivery {
+        if too_damaged(delivery.integrity) {
+            JobOutcome::Failed(JobFailure::TooDamaged)
+        } else {
+            JobOutcome::Delivered { payout: active.job.payout }
+        }
+    } else if let (Some(item), Ok(transform)) = (item, vehicles.get(active.vehicle)) {
+        let paved = || on_paved_trail(&trails, transform.translation(), config.paved_width);
+        if active.time_left() == Some(0.0) {
+            JobOutcome::Failed(JobFailure::TimeUp)
+        } else if too_damaged(item.integrity) {
+            JobOutcome::Failed(JobFailure::TooDamaged)
+        } else if active.job.condition == Some(JobCondition::AvoidPaved) && paved() {
+            JobOutcome::Failed(JobFailure::DroveOnPaved)
+        } else {
+            return;
+        }
+    } else {
+        JobOutcome::Failed(JobFailure::CargoLost)
+    };
+
+    let Some(active) = boards.active.take() else {
+        return;
+    };
+    if let (JobOutcome::Delivered { payout }, Some(mut progress)) = (outcome, progress) {
+        let kind = TransactionKind::Job;
+        transactions.send(earn(&mut progress, &mut ledger, time.elapsed_seconds(), kind, payout));
+    }
+    for entity in [active.cargo, active.drop_off] {
+        if let Some(entity) = commands.get_entity(entity) {
+            entity.despawn_recursive();
+        }
+    }
+    if let Some(mut vehicle) = commands.get_entity(active.vehicle) {
+        vehicle.remove::<VehicleRoute>();
+    }
+    if let Some(mut session) = session.filter(|session| matches!(session.mode, GameMode::Jobs)) {
+        session.mode = GameMode::FreeRoam;
+    }
+    finished.send(JobFinishedEvent { job: active.job, outcome });
+}
+
+/// Plugin for the job boards and the delivery jobs they offer
+pub struct JobsPlugin;
+
+impl Plugin for JobsPlugin {
+    fn build(&self, app: &mut App) {
+        configure_game_sets(app);
+        app.init_resource::<JobsConfig>()
+            .init_resource::<JobBoards>()
+            .init_resource::<Ledger>()
+            .add_event::<AcceptJobEvent>()
+            .add_event::<AbandonJobEvent>()
+            .add_event::<JobFinishedEvent>()
+            .add_event::<TransactionEvent>()
+            .add_event::<CargoDeliveredEvent>()
+            .add_event::<RouteToEvent>()
+            .add_systems(Update, (
+                refresh_job_boards,
+                accept_jobs,
+                track_active_job,
+            ).chain().in_set(GameSet::PostSim).after(pay_rewards));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::plugins::trails::{TrailDesc, TrailDifficulty};
+
+    fn poi(name: &str, position: [f32; 3]) -> PoiDesc {
+        serde_json::from_value(serde_json::json!({ "name": name, "position": position, "message": "" })).unwrap()
+    }
+
+    #[test]
+    fn test_boards_offer_jobs_to_other_points() {
+        let config = JobsConfig::default();
+        let points = [
+            poi("Trailhead", [0.0; 3]),
+            poi("Ridge Lookout", [600.0, 20.0, -800.0]),
+            poi("Shed", [10.0, 0.0, 0.0]),
+        ];
+        let descs: Vec<&PoiDesc> = points.iter().collect();
+        let jobs = generate_jobs(&config, &points[0], &descs, 42, 7);
+        assert_eq!(jobs.len(), 3);
+        assert_eq!(jobs.iter().map(|job| job.id).collect::<Vec<_>>(), [7, 8, 9]);
+        // Only the lookout is far enough away to be worth it
+        assert!(jobs.iter().all(|job| job.destination == "Ridge Lookout" && job.origin == "Trailhead"));
+        assert_eq!(jobs, generate_jobs(&config, &points[0], &descs, 42, 7));
+
+        // A kilometer pays 550, a condition half again
+        assert_eq!(config.payout(1000.0, None), 550);
+        assert_eq!(config.payout(1000.0, Some(JobCondition::AvoidPaved)), 830);
+        assert_eq!(config.time_limit(1000.0), 360.0);
+    }
+
+    #[test]
+    fn test_paved_trails() {
+        let road = Trail(TrailDesc {
+            name: "County Road".into(),
+            difficulty: TrailDifficulty::Easy,
+            path: vec![[0.0, 0.0, 0.0], [100.0, 0.0, 0.0]],
+            rain_steps: 0,
+            carve: None,
+            paved: true,
+        });
+        let mut dirt = road.clone();
+        dirt.0.paved = false;
+        dirt.0.path = vec![[0.0, 0.0, 50.0], [100.0, 0.0, 50.0]];
+        let trails = [road, dirt];
+        assert!(on_paved_trail(&trails, Vec3::new(50.0, 3.0, 4.0), 5.0));
+        assert!(!on_paved_trail(&trails, Vec3::new(50.0, 0.0, 50.0), 5.0));
+        assert!(!on_paved_trail(&trails, Vec3::new(110.0, 0.0, 0.0), 5.0));
+    }
+
+    fn job_app() -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins).add_plugins(JobsPlugin);
+        app.init_resource::<GameProgress>().init_resource::<resources::GameState>();
+        app.init_resource::<Assets<Mesh>>().init_resource::<Assets<StandardMaterial>>();
+        app.world.spawn(PointOfInterest::new(poi("Trailhead", [0.0; 3])));
+        app.world.spawn(PointOfInterest::new(poi("Ridge Lookout", [600.0, 20.0, -800.0])));
+        let vehicle = app.world.spawn((Vehicle::default(), PlayerId(0), TransformBundle::default())).id();
+        app.update();
+        (app, vehicle)
+    }
+
+    fn take_job(app: &mut App, condition: Option<JobCondition>) -> ActiveJob {
+        let job = {
+            let mut boards = app.world.resource_mut::<JobBoards>();
+            let jobs = boards.boards.get_mut("Trailhead").unwrap();
+            jobs[0].condition = condition;
+            jobs[0].id
+        };
+        app.world.send_event(AcceptJobEvent { job });
+        app.update();
+        app.world.resource::<JobBoards>().active().cloned().unwrap()
+    }
+
+    #[test]
+    fn test_delivered_jobs_pay_and_failed_ones_dont() {
+        let (mut app, _) = job_app();
+        let active = take_job(&mut app, None);
+        assert!(app.world.get::<JobCargo>(active.cargo).is_some());
+        assert!(matches!(app.world.resource::<resources::GameState>().mode, GameMode::Jobs));
+
+        app.world.send_event(CargoDeliveredEvent {
+            cargo: active.cargo,
+            zone: active.drop_off,
+            integrity: 0.9,
+            intact: true,
+        });
+        app.update();
+        assert!(app.world.resource::<JobBoards>().active().is_none());
+        let paid = active.job.payout;
+        assert_eq!(app.world.resource::<GameProgress>().cash, paid);
+        assert!(app.world.get_entity(active.drop_off).is_none());
+        assert!(matches!(app.world.resource::<resources::GameState>().mode, GameMode::FreeRoam));
+
+        // Out of time before the cargo gets there
+        let active = take_job(&mut app, Some(JobCondition::TimeLimit(1.0)));
+        assert!(active.time_left().is_some());
+        app.world.resource_mut::<JobBoards>().active.as_mut().unwrap().elapsed = 2.0;
+        app.update();
+        let finished: Vec<JobFinishedEvent> = app.world.resource_mut::<Events<JobFinishedEvent>>().drain().collect();
+        assert_eq!(finished.last().unwrap().outcome, JobOutcome::Failed(JobFailure::TimeUp));
+        assert_eq!(app.world.resource::<GameProgress>().cash, paid);
+        assert!(app.world.get_entity(active.cargo).is_none());
+    }
+}
@@ -2,6 +2,8 @@ use bevy::prelude::*;
 use bevy::render::render_resource::*;
 use bevy::pbr::{CascadedShadowConfig, DirectionalLightShadowMap};
 
+use crate::game::{GameState, StateScoped};
+
 /// Plugin for managing dynamic lighting in the game
 pub struct LightingPlugin;
 
@@ -133,7 +135,7 @@ fn update_spot_lights(
     }
 }
 
-/// Helper function to create a point light with flicker effect
+/// Helper function to create a point light with flicker effect, removed when the game is left
 pub fn spawn_point_light(
     commands: &mut Commands,
     position: Vec3,
@@ -156,10 +158,11 @@ pub fn spawn_point_light(
         },
         flicker.unwrap_or_default(),
         LightTemperature::default(),
+        StateScoped(GameState::InGame),
     )).id()
 }
 
-/// Helper function to create a spot light
+/// Helper function to create a spot light, removed when the game is left
 pub fn spawn_spot_light(
     commands: &mut Commands,
     position: Vec3,
@@ -188,6 +191,7 @@ pub fn spawn_spot_light(
             ..default()
         },
         LightTemperature::default(),
+        StateScoped(GameState::InGame),
     )).id()
 }
 
@@ -4,17 +4,28 @@
 /// - 3D texture management for volumetric data
 /// - Render pipeline for volumetric effects
 /// - Dynamic updates for animated effects
+/// - Weather, time of day and elevation driven fog density
+/// - Vehicle headlight cones that scatter in the fog at night
 /// 
-/// The system uses a 128x128x64 3D texture to store volumetric data, which is:
+/// The system uses a 3D texture (128x128x64 on high quality) to store volumetric data, which is:
 /// - Updated each frame with animated noise patterns
+/// - Shaped by a ground fog layer that pools in valleys around dawn
 /// - Rendered using ray marching in the fragment shader
 /// - Blended with the scene using physically-based light scattering
+mod night_lights;
+mod volumetric_fog;
 mod volumetric_pipeline;
 mod volumetric_texture;
 
 use bevy::prelude::*;
+use crate::game::plugins::weather::{TimeManager, WeatherManager};
+use night_lights::{
+    apply_dashboard_levels, apply_headlight_levels, collect_volumetric_spot_lights,
+    update_night_lighting_levels,
+};
+use volumetric_fog::{apply_fog_conditions, update_fog_conditions};
 use volumetric_pipeline::VolumetricRenderPlugin;
-use volumetric_texture::{VolumetricTexture, update_volume_texture};
+use volumetric_texture::{VolumetricTexture, resize_volume_texture, update_volume_texture};
 
 /// Plugin that sets up the volumetric lighting system
 /// 
@@ -34,11 +45,36 @@ pub struct VolumetricLightingPlugin;
 
 impl Plugin for VolumetricLightingPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<VolumetricTexture>()
-            .add_systems(Update, update_volume_texture)
+        app.init_resource::<VolumetricFogConfig>()
+            .init_resource::<FogConditions>()
+            .init_resource::<VolumetricTexture>()
+            .add_systems(Update, (
+                // Fog only follows the weather when the weather plugin is present
+                update_fog_conditions.run_if(
+                    resource_exists::<WeatherManager>()
+                        .and_then(resource_exists::<TimeManager>()),
+                ),
+                apply_fog_conditions,
+                resize_volume_texture,
+                update_volume_texture,
+            ).chain())
+            .init_resource::<NightLightingConfig>()
+            .init_resource::<NightLightingLevels>()
+            .add_systems(Update, (
+                update_night_lighting_levels.run_if(resource_exists::<TimeManager>()),
+                apply_headlight_levels,
+                apply_dashboard_levels,
+            ).chain())
+            // Gather cones after transforms propagate so beams don't lag behind the vehicle
+            .add_systems(PostUpdate, collect_volumetric_spot_lights.after(TransformSystem::TransformPropagate))
             .add_plugins(VolumetricRenderPlugin);
     }
 }
 
 // Re-export the settings struct for configuration
-pub use volumetric_pipeline::VolumetricSettings; 
\ No newline at end of file
+pub use volumetric_pipeline::VolumetricSettings;
+pub use volumetric_fog::{FogConditions, VolumetricFogConfig, VolumetricQuality};
+pub use night_lights::{
+    spawn_dashboard_gauges, spawn_vehicle_headlights, DashboardEmissive, HeadlightMode, NightLightingConfig,
+    NightLightingLevels, VehicleHeadlight, VolumetricSpotLight, VolumetricSpotLights,
+}; 
\ No newline at end of file
@@ -0,0 +1,352 @@
+use bevy::{
+    prelude::*,
+    render::{extract_resource::ExtractResource, render_resource::ShaderType},
+};
+
+use crate::game::plugins::weather::TimeManager;
+
+/// Maximum number of spotlights that scatter light in the volumetric pass
+pub const MAX_VOLUMETRIC_SPOT_LIGHTS: usize = 4;
+
+/// How vehicle headlights respond to the time of day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HeadlightMode {
+    /// Follow the time of day, fading in at dusk and out at dawn
+    #[default]
+    Auto,
+    /// Always on at full intensity
+    On,
+    /// Always off
+    Off,
+}
+
+/// Configuration for night driving lights
+#[derive(Resource, Clone, Debug)]
+pub struct NightLightingConfig {
+    pub headlight_mode: HeadlightMode,
+    /// Sun height (0.0 - 1.0) at which lights reach full night intensity
+    pub night_sun_height: f32,
+    /// Sun height (0.0 - 1.0) above which lights are fully off in auto mode
+    pub day_sun_height: f32,
+    /// Fraction of dashboard emissive kept during the day so gauges stay readable
+    pub dashboard_day_level: f32,
+    /// Medium density used for headlight beams when there is no fog, so beams stay faintly visible
+    pub beam_haze: f32,
+}
+
+impl Default for NightLightingConfig {
+    fn default() -> Self {
+        Self {
+            headlight_mode: HeadlightMode::Auto,
+            night_sun_height: 0.35,
+            day_sun_height: 0.55,
+            dashboard_day_level: 0.15,
+            beam_haze: 0.02,
+        }
+    }
+}
+
+impl NightLightingConfig {
+    /// How dark it is for lighting purposes (0.0 = day, 1.0 = night)
+    pub fn night_factor(&self, sun_height: f32) -> f32 {
+        let span = (self.day_sun_height - self.night_sun_height).max(f32::EPSILON);
+        let t = ((self.day_sun_height - sun_height) / span).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    /// Headlight intensity multiplier for the current mode and sun height
+    pub fn headlight_level(&self, sun_height: f32) -> f32 {
+        match self.headlight_mode {
+            HeadlightMode::Auto => self.night_factor(sun_height),
+            HeadlightMode::On => 1.0,
+            HeadlightMode::Off => 0.0,
+        }
+    }
+
+    /// Dashboard emissive multiplier for the sun height
+    pub fn dashboard_level(&self, sun_height: f32) -> f32 {
+        let night = self.night_factor(sun_height);
+        self.dashboard_day_level + (1.0 - self.dashboard_day_level) * night
+    }
+}
+
+/// Current night lighting levels, derived from the [`TimeManager`]
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct NightLightingLevels {
+    pub headlights: f32,
+    pub dashboard: f32,
+}
+
+/// Vehicle headlight, attached to an entity with a [`SpotLight`]
+#[derive(Component, Clone, Debug)]
+pub struct VehicleHeadlight {
+    /// Intensity at full night in lumens
+    pub base_intensity: f32,
+    /// Whether the beam scatters light in the volumetric pass
+    pub volumetric: bool,
+}
+
+impl Default for VehicleHeadlight {
+    fn default() -> Self {
+        Self {
+            base_intensity: 60_000.0,
+            volumetric: true,
+        }
+    }
+}
+
+/// Emissive dashboard or gauge material that brightens at night
+#[derive(Component, Clone, Debug)]
+pub struct DashboardEmissive {
+    /// Emissive color at full night
+    pub base_emissive: Color,
+}
+
+impl Default for DashboardEmissive {
+    fn default() -> Self {
+        Self {
+            // Warm amber backlight, like most off-roaders' gauge clusters
+            base_emissive: Color::rgb(2.0, 1.1, 0.3),
+        }
+    }
+}
+
+/// Spotlight cone data used by the volumetric shader
+#[derive(Clone, Copy, Debug, Default, ShaderType)]
+pub struct VolumetricSpotLight {
+    pub position: Vec3,
+    pub range: f32,
+    pub direction: Vec3,
+    /// Cosine of the outer cone angle
+    pub cos_outer: f32,
+    /// Linear color premultiplied by intensity
+    pub color: Vec3,
+    /// Cosine of the inner cone angle
+    pub cos_inner: f32,
+}
+
+/// Spotlights that scatter in the volumetric pass, closest to the camera first
+#[derive(Resource, Clone, Copy, Debug, Default, ShaderType)]
+pub struct VolumetricSpotLights {
+    pub lights: [VolumetricSpotLight; MAX_VOLUMETRIC_SPOT_LIGHTS],
+    pub count: u32,
+    /// Minimum medium density for beams, see [`NightLightingConfig::beam_haze`]
+    pub haze: f32,
+}
+
+impl ExtractResource for VolumetricSpotLights {
+    type Source = VolumetricSpotLights;
+
+    fn extract_resource(source: &Self::Source) -> Self {
+        *source
+    }
+}
+
+/// System that derives light levels from the time of day
+pub fn update_night_lighting_levels(
+    time: Res<TimeManager>,
+    config: Res<NightLightingConfig>,
+    mut levels: ResMut<NightLightingLevels>,
+) {
+    let sun_height = time.sun_height();
+    let next = NightLightingLevels {
+        headlights: config.headlight_level(sun_height),
+        dashboard: config.dashboard_level(sun_height),
+    };
+    if *levels != next {
+        *levels = next;
+    }
+}
+
+/// System that scales headlight intensity with the current light level
+pub fn apply_headlight_levels(
+    levels: Res<NightLightingLevels>,
+    mut headlights: Query<(&VehicleHeadlight, &mut SpotLight)>,
+) {
+    for (headlight, mut light) in headlights.iter_mut() {
+        light.intensity = headlight.base_intensity * levels.headlights;
+    }
+}
+
+/// System that scales dashboard emissive with the current light level
+pub fn apply_dashboard_levels(
+    levels: Res<NightLightingLevels>,
+    dashboards: Query<(&DashboardEmissive, &Handle<StandardMaterial>)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !levels.is_changed() {
+        return;
+    }
+    for (dashboard, handle) in dashboards.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.emissive = dashboard.base_emissive * levels.dashboard;
+        }
+    }
+}
+
+/// System that gathers the headlight cones closest to the camera for the volumetric pass
+pub fn collect_volumetric_spot_lights(
+    config: Res<NightLightingConfig>,
+    cameras: Query<&GlobalTransform, With<Camera3d>>,
+    headlights: Query<(&VehicleHeadlight, &SpotLight, &GlobalTransform)>,
+    mut spot_lights: ResMut<VolumetricSpotLights>,
+) {
+    let camera_position = cameras.iter().next().map(|t| t.translation()).unwrap_or(Vec3::ZERO);
+
+    let mut candidates: Vec<_> = headlights
+        .iter()
+        .filter(|(headlight, light, _)| headlight.volumetric && light.intensity > 0.0)
+        .map(|(_, light, transform)| {
+            let position = transform.translation();
+            (position.distance_squared(camera_position), light, transform)
+        })
+        .collect();
+    candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut next = VolumetricSpotLights {
+        haze: config.beam_haze,
+        ..default()
+    };
+    for (slot, (_, light, transform)) in next.lights.iter_mut().zip(candidates) {
+        // Lumens to a rough radiance scale, same convention as Bevy's spot light shading
+        let intensity = light.intensity / (4.0 * std::f32::consts::PI);
+        *slot = VolumetricSpotLight {
+            position: transform.translation(),
+            range: light.range,
+            direction: transform.forward(),
+            cos_outer: light.outer_angle.cos(),
+            color: Vec3::from_slice(&light.color.as_linear_rgba_f32()[..3]) * intensity,
+            cos_inner: light.inner_angle.cos(),
+        };
+        next.count += 1;
+    }
+
+    *spot_lights = next;
+}
+
+/// Spawns a pair of volumetric headlights as children of `vehicle`
+/// `front` is the local offset of the headlight midpoint, `spacing` the distance between lamps
+pub fn spawn_vehicle_headlights(commands: &mut Commands, vehicle: Entity, front: Vec3, spacing: f32) {
+    commands.entity(vehicle).with_children(|parent| {
+        for side in [-0.5, 0.5] {
+            let position = front + Vec3::X * spacing * side;
+            parent.spawn((
+                SpotLightBundle {
+                    spot_light: SpotLight {
+                        color: Color::rgb(1.0, 0.96, 0.88),
+                        intensity: 0.0,
+                        range: 60.0,
+                        inner_angle: 0.25,
+                        outer_angle: 0.45,
+                        shadows_enabled: true,
+                        ..default()
+                    },
+                    // Aim slightly down the road ahead
+                    transform: Transform::from_translation(position)
+                        .looking_at(position + Vec3::new(0.0, -0.08, -1.0), Vec3::Y),
+                    ..default()
+                },
+                VehicleHeadlight::default(),
+            ));
+        }
+    });
+}
+
+/// Spawns an emissive gauge cluster as a child of `vehicle`, visible from the hood camera
+pub fn spawn_dashboard_gauges(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    vehicle: Entity,
+    dash_position: Vec3,
+) {
+    let gauge = meshes.add(shape::Circle::new(0.06).into());
+    let dashboard = DashboardEmissive::default();
+
+    commands.entity(vehicle).with_children(|parent| {
+        // Speedometer and tachometer side by side
+        for offset in [-0.08, 0.08] {
+            let material = materials.add(StandardMaterial {
+                base_color: Color::rgb(0.05, 0.05, 0.05),
+                emissive: dashboard.base_emissive,
+                unlit: false,
+                ..default()
+            });
+            parent.spawn((
+                PbrBundle {
+                    mesh: gauge.clone(),
+                    material,
+                    // Face the driver, tilted back like a real cluster
+                    transform: Transform::from_translation(dash_position + Vec3::X * offset)
+                        .with_rotation(Quat::from_rotation_y(std::f32::consts::PI) * Quat::from_rotation_x(-0.3)),
+                    ..default()
+                },
+                dashboard.clone(),
+            ));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_night_factor_transitions() {
+        let config = NightLightingConfig::default();
+        assert_eq!(config.night_factor(1.0), 0.0);
+        assert_eq!(config.night_factor(0.0), 1.0);
+
+        let mid = config.night_factor((config.day_sun_height + config.night_sun_height) * 0.5);
+        assert!((mid - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_headlight_modes() {
+        let mut config = NightLightingConfig::default();
+        assert_eq!(config.headlight_level(1.0), 0.0);
+
+        config.headlight_mode = HeadlightMode::On;
+        assert_eq!(config.headlight_level(1.0), 1.0);
+
+        config.headlight_mode = HeadlightMode::Off;
+        assert_eq!(config.headlight_level(0.0), 0.0);
+    }
+
+    #[test]
+    fn test_dashboard_stays_readable_by_day() {
+        let config = NightLightingConfig::default();
+        assert_eq!(config.dashboard_level(1.0), config.dashboard_day_level);
+        assert_eq!(config.dashboard_level(0.0), 1.0);
+    }
+
+    #[test]
+    fn test_closest_headlights_are_collected() {
+        let mut app = App::new();
+        app.init_resource::<NightLightingConfig>()
+            .init_resource::<VolumetricSpotLights>()
+            .add_systems(Update, collect_volumetric_spot_lights);
+
+        app.world.spawn((Camera3d::default(), GlobalTransform::IDENTITY));
+        for distance in [50.0, 5.0, 30.0, 10.0, 20.0, 40.0] {
+            app.world.spawn((
+                VehicleHeadlight::default(),
+                SpotLight { intensity: 1000.0, ..default() },
+                GlobalTransform::from_translation(Vec3::new(0.0, 0.0, -distance)),
+            ));
+        }
+        // Switched off lights never scatter
+        app.world.spawn((
+            VehicleHeadlight::default(),
+            SpotLight { intensity: 0.0, ..default() },
+            GlobalTransform::IDENTITY,
+        ));
+
+        app.update();
+
+        let spot_lights = app.world.resource::<VolumetricSpotLights>();
+        assert_eq!(spot_lights.count as usize, MAX_VOLUMETRIC_SPOT_LIGHTS);
+        assert_eq!(spot_lights.lights[0].position.z, -5.0);
+        assert_eq!(spot_lights.lights[3].position.z, -30.0);
+    }
+}
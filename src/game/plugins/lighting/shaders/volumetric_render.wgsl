@@ -73,6 +73,22 @@ struct VolumetricSettings {
     max_distance: f32, // Maximum ray march distance in world units
 }
 
+// Spotlight cone that scatters light in the volume (vehicle headlights)
+struct VolumetricSpotLight {
+    position: vec3<f32>,
+    range: f32,
+    direction: vec3<f32>,
+    cos_outer: f32,
+    color: vec3<f32>,     // Linear color premultiplied by intensity
+    cos_inner: f32,
+}
+
+struct VolumetricSpotLights {
+    lights: array<VolumetricSpotLight, 4>,
+    count: u32,
+    haze: f32,         // Minimum medium density so beams stay visible without fog
+}
+
 // Vertex shader output structure
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,  // Clip space position
@@ -88,6 +104,7 @@ struct VertexOutput {
 @group(0) @binding(4) var scene_sampler: sampler;                   // Sampler for scene texture
 @group(0) @binding(5) var depth_texture: texture_depth_2d;          // Scene depth texture
 @group(0) @binding(6) var<uniform> settings: VolumetricSettings;    // Volumetric effect settings
+@group(0) @binding(7) var<uniform> spot_lights: VolumetricSpotLights; // Headlight cones
 
 // Vertex shader that generates a full-screen triangle
 // Uses a single triangle that covers the screen, generated from vertex ID
@@ -142,6 +159,35 @@ fn get_linear_depth(uv: vec2<f32>) -> f32 {
     return (2.0 * near) / (far + near - depth * (far - near));
 }
 
+// Henyey-Greenstein phase function, g > 0 favours forward scattering
+// so beams glow brighter when looking down them
+fn henyey_greenstein(cos_theta: f32, g: f32) -> f32 {
+    let g2 = g * g;
+    let denom = 1.0 + g2 - 2.0 * g * cos_theta;
+    return (1.0 - g2) / (4.0 * 3.14159265 * denom * sqrt(denom));
+}
+
+// In-scattered light from all spotlight cones at a sample position
+fn spot_light_scattering(sample_pos: vec3<f32>, ray_dir: vec3<f32>) -> vec3<f32> {
+    var scattered = vec3<f32>(0.0);
+    for (var i = 0u; i < min(spot_lights.count, 4u); i++) {
+        let light = spot_lights.lights[i];
+        let to_sample = sample_pos - light.position;
+        let distance = length(to_sample);
+        if (distance > light.range || distance < 0.001) {
+            continue;
+        }
+
+        let light_dir = to_sample / distance;
+        let cone = smoothstep(light.cos_outer, light.cos_inner, dot(light_dir, light.direction));
+        // Same windowed inverse square falloff as the PBR spot lights
+        let window = saturate(1.0 - pow(distance / light.range, 4.0));
+        let attenuation = window * window / max(distance * distance, 0.01);
+        scattered += light.color * cone * attenuation * henyey_greenstein(dot(light_dir, -ray_dir), 0.6);
+    }
+    return scattered;
+}
+
 // Fragment shader that performs ray marching through the volume
 // Implements physically-based light transport with scattering and absorption
 @fragment
@@ -182,6 +228,13 @@ fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
         // Performance: Cache texture sample to avoid multiple lookups
         let volume_sample = textureSample(volume_texture, volume_sampler, volume_uv);
         let density = volume_sample.a * settings.density;
+
+        // Headlight beams scatter even in thin air, so they are handled before the empty space skip
+        if (spot_lights.count > 0u) {
+            let beam_density = max(density, spot_lights.haze);
+            result += spot_light_scattering(sample_pos, ray_dir) * beam_density * settings.scattering
+                * transmittance * step_size;
+        }
         
         // Performance: Skip empty space
         if (density <= 0.001) {
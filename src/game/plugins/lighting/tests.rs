@@ -2,7 +2,8 @@ use bevy::prelude::*;
 use bevy::render::render_resource::{TextureFormat, TextureUsages};
 use super::{
     volumetric_pipeline::{VolumetricSettings, VolumetricPipeline, VolumetricSettingsBuffer},
-    volumetric_texture::{VolumetricTexture, VOLUME_SIZE, update_volume_texture},
+    volumetric_texture::{VolumetricTexture, VOLUME_SIZE, update_volume_texture, resize_volume_texture},
+    volumetric_fog::{FogConditions, VolumetricFogConfig, VolumetricQuality},
     VolumetricLightingPlugin,
     examples::volumetric_demo::VolumetricDemoPlugin,
 };
@@ -76,6 +77,8 @@ fn test_volumetric_texture_update() {
     // Create a test app
     let mut app = App::new();
     app.add_plugins(MinimalPlugins);
+    app.init_resource::<VolumetricFogConfig>();
+    app.init_resource::<FogConditions>();
     app.init_resource::<VolumetricTexture>();
     
     // Add the update system
@@ -275,6 +278,12 @@ fn test_weather_cycle_transitions() {
 fn test_volumetric_texture_noise_distribution() {
     let mut app = App::new();
     app.add_plugins(MinimalPlugins);
+    app.init_resource::<VolumetricFogConfig>();
+    // Saturate the fog layer so the raw noise distribution is what ends up in the texture
+    app.insert_resource(FogConditions {
+        base_density: 1.0,
+        ..Default::default()
+    });
     app.init_resource::<VolumetricTexture>();
     app.add_systems(Update, update_volume_texture);
 
@@ -402,4 +411,25 @@ fn test_volumetric_render_pipeline_validation() {
     // Verify resources are still valid after updates
     assert!(render_app.world.contains_resource::<VolumetricPipeline>());
     assert!(render_app.world.contains_resource::<VolumetricSettingsBuffer>());
-} 
\ No newline at end of file
+} 
+
+#[test]
+fn test_volumetric_texture_follows_quality_tier() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins);
+    app.init_resource::<VolumetricFogConfig>();
+    app.init_resource::<VolumetricTexture>();
+    app.add_systems(Update, resize_volume_texture);
+
+    app.world.resource_mut::<VolumetricFogConfig>().quality = VolumetricQuality::Low;
+    app.update();
+
+    let texture = app.world.resource::<VolumetricTexture>();
+    let images = app.world.resource::<Assets<Image>>();
+    let image = images.get(&texture.texture).unwrap();
+    let expected = VolumetricQuality::Low.volume_size();
+
+    assert_eq!(texture.size, expected);
+    assert_eq!(image.texture_descriptor.size.width, expected.0);
+    assert_eq!(image.texture_descriptor.size.depth_or_array_layers, expected.2);
+}
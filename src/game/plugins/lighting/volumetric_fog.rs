@@ -0,0 +1,231 @@
+use bevy::prelude::*;
+
+use crate::game::plugins::weather::{TimeManager, TimeOfDay, WeatherManager};
+use super::volumetric_pipeline::VolumetricSettings;
+
+/// Quality tiers for the volumetric fog volume
+/// Lower tiers shrink the 3D texture to reduce CPU upload and ray marching cost
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VolumetricQuality {
+    Low,
+    Medium,
+    #[default]
+    High,
+}
+
+impl VolumetricQuality {
+    /// Get the 3D texture resolution (width, height, depth) for this tier
+    pub fn volume_size(&self) -> (u32, u32, u32) {
+        match self {
+            VolumetricQuality::Low => (64, 64, 32),
+            VolumetricQuality::Medium => (96, 96, 48),
+            VolumetricQuality::High => (128, 128, 64),
+        }
+    }
+}
+
+/// Configuration for how weather, time of day and terrain elevation drive the fog
+#[derive(Resource, Clone, Debug)]
+pub struct VolumetricFogConfig {
+    /// Quality tier controlling the volume texture resolution
+    pub quality: VolumetricQuality,
+    /// World height of the lowest valley floor, where ground fog pools
+    pub valley_floor_height: f32,
+    /// Thickness of the ground fog layer above the valley floor in meters
+    pub valley_fog_height: f32,
+    /// World height spanned by the fog volume, starting at the valley floor
+    pub volume_height: f32,
+    /// Extra density added to valleys around dawn (0.0 - 1.0)
+    pub dawn_valley_density: f32,
+    /// Visibility distance in clear weather in meters
+    pub clear_visibility: f32,
+    /// Visibility distance inside the heaviest rain squall in meters
+    pub squall_visibility: f32,
+    /// Maximum scattering boost applied for god rays through cloud openings
+    pub god_ray_strength: f32,
+}
+
+impl Default for VolumetricFogConfig {
+    fn default() -> Self {
+        Self {
+            quality: VolumetricQuality::default(),
+            valley_floor_height: -7.0, // Lowest point of the default terrain chunk
+            valley_fog_height: 6.0,
+            volume_height: 40.0,
+            dawn_valley_density: 0.6,
+            clear_visibility: 200.0,
+            squall_visibility: 25.0,
+            god_ray_strength: 0.35,
+        }
+    }
+}
+
+/// Fog conditions derived each frame from the weather, time of day and camera elevation
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct FogConditions {
+    /// Uniform density applied to the whole volume (0.0 - 1.0)
+    pub base_density: f32,
+    /// Additional density pooled near the valley floor (0.0 - 1.0)
+    pub valley_density: f32,
+    /// Distance at which the scene is fully obscured, in meters
+    pub visibility_distance: f32,
+    /// Strength of god rays through cloud openings (0.0 - 1.0)
+    pub god_rays: f32,
+    /// Height of the active camera above the valley floor
+    pub camera_elevation: f32,
+}
+
+impl FogConditions {
+    /// Density at the given height above the valley floor
+    pub fn density_at(&self, height_above_floor: f32, layer_height: f32) -> f32 {
+        let valley = self.valley_density * height_falloff(height_above_floor, layer_height);
+        (self.base_density + valley).clamp(0.0, 1.0)
+    }
+}
+
+/// Exponential falloff of ground fog with height above the valley floor
+pub fn height_falloff(height_above_floor: f32, layer_height: f32) -> f32 {
+    if layer_height <= 0.0 {
+        return 0.0;
+    }
+    (-height_above_floor.max(0.0) / layer_height).exp()
+}
+
+/// Extra valley fog for the given time of day
+/// Reason: cold air settles overnight, so fog peaks at dawn and burns off by midday
+pub fn valley_fog_factor(time_of_day: TimeOfDay) -> f32 {
+    match time_of_day {
+        TimeOfDay::Dawn => 1.0,
+        TimeOfDay::Night => 0.6,
+        TimeOfDay::Morning => 0.4,
+        TimeOfDay::Dusk => 0.3,
+        TimeOfDay::Afternoon => 0.05,
+        TimeOfDay::Noon => 0.0,
+    }
+}
+
+/// Visibility distance for the given fog, precipitation and wind
+/// Rain squalls are modelled as heavy precipitation combined with strong wind
+pub fn visibility_distance(
+    fog_density: f32,
+    precipitation: f32,
+    wind_speed: f32,
+    config: &VolumetricFogConfig,
+) -> f32 {
+    let gustiness = (wind_speed / 15.0).clamp(0.0, 1.0);
+    let squall = (precipitation * (0.5 + 0.5 * gustiness)).clamp(0.0, 1.0);
+    let visibility = config.clear_visibility
+        + (config.squall_visibility - config.clear_visibility) * squall;
+
+    // Fog further reduces visibility on top of precipitation
+    (visibility * (1.0 - fog_density.clamp(0.0, 1.0) * 0.8)).max(config.squall_visibility * 0.5)
+}
+
+/// God ray strength for the given cloud coverage and sun height
+/// Rays are strongest with broken cloud cover and a low sun
+pub fn god_ray_factor(cloud_coverage: f32, sun_height: f32) -> f32 {
+    // Peaks at 50% coverage: no openings when overcast, nothing to shaft through when clear
+    let openings = 1.0 - ((cloud_coverage - 0.5).abs() * 2.0).clamp(0.0, 1.0);
+    let low_sun = 1.0 - sun_height.clamp(0.0, 1.0);
+    openings * (0.3 + 0.7 * low_sun)
+}
+
+/// System that derives fog conditions from the weather manager, time of day and camera elevation
+pub fn update_fog_conditions(
+    weather: Res<WeatherManager>,
+    time: Res<TimeManager>,
+    config: Res<VolumetricFogConfig>,
+    cameras: Query<&GlobalTransform, With<Camera3d>>,
+    mut conditions: ResMut<FogConditions>,
+) {
+    let state = weather.current_state();
+
+    let camera_elevation = cameras
+        .iter()
+        .next()
+        .map(|transform| transform.translation().y - config.valley_floor_height)
+        .unwrap_or(0.0);
+
+    let valley_density = config.dawn_valley_density
+        * valley_fog_factor(time.time_of_day())
+        * (1.0 - state.wind_speed() / 20.0).clamp(0.0, 1.0); // Wind disperses pooled fog
+
+    *conditions = FogConditions {
+        base_density: state.fog_density() * 0.5 + state.precipitation() * 0.2,
+        valley_density,
+        visibility_distance: visibility_distance(
+            state.fog_density(),
+            state.precipitation(),
+            state.wind_speed(),
+            &config,
+        ),
+        god_rays: god_ray_factor(state.cloud_coverage(), time.sun_height()),
+        camera_elevation,
+    };
+}
+
+/// System that pushes the current fog conditions into the volumetric render settings
+pub fn apply_fog_conditions(
+    conditions: Res<FogConditions>,
+    config: Res<VolumetricFogConfig>,
+    mut settings: ResMut<VolumetricSettings>,
+) {
+    if !conditions.is_changed() {
+        return;
+    }
+
+    // Use the density the camera is actually sitting in so valley fog thins as you climb out
+    let density = conditions.density_at(conditions.camera_elevation, config.valley_fog_height);
+    let scattering = 0.6 + conditions.god_rays * config.god_ray_strength;
+
+    *settings = VolumetricSettings::new(
+        density,
+        scattering,
+        0.1 + density * 0.2,
+        conditions.visibility_distance,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quality_tiers_shrink_volume() {
+        let low = VolumetricQuality::Low.volume_size();
+        let medium = VolumetricQuality::Medium.volume_size();
+        let high = VolumetricQuality::High.volume_size();
+        assert!(low.0 < medium.0 && medium.0 < high.0);
+        assert!(low.2 < medium.2 && medium.2 < high.2);
+    }
+
+    #[test]
+    fn test_valley_fog_densest_at_dawn() {
+        assert!(valley_fog_factor(TimeOfDay::Dawn) > valley_fog_factor(TimeOfDay::Morning));
+        assert_eq!(valley_fog_factor(TimeOfDay::Noon), 0.0);
+
+        let conditions = FogConditions {
+            valley_density: 0.6,
+            ..Default::default()
+        };
+        assert!(conditions.density_at(0.0, 6.0) > conditions.density_at(12.0, 6.0));
+    }
+
+    #[test]
+    fn test_squall_reduces_visibility() {
+        let config = VolumetricFogConfig::default();
+        let clear = visibility_distance(0.0, 0.0, 2.0, &config);
+        let rain = visibility_distance(0.3, 0.6, 8.0, &config);
+        let squall = visibility_distance(0.4, 1.0, 15.0, &config);
+        assert_eq!(clear, config.clear_visibility);
+        assert!(rain < clear);
+        assert!(squall < rain);
+    }
+
+    #[test]
+    fn test_god_rays_need_cloud_openings() {
+        assert!(god_ray_factor(0.5, 0.2) > god_ray_factor(1.0, 0.2));
+        assert!(god_ray_factor(0.5, 0.2) > god_ray_factor(0.0, 0.2));
+        assert!(god_ray_factor(0.5, 0.1) > god_ray_factor(0.5, 0.9));
+    }
+}
@@ -11,9 +11,9 @@ use bevy::{
             RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, Sampler,
             SamplerBindingType, SamplerDescriptor, ShaderStages, ShaderType, TextureFormat,
             TextureSampleType, TextureViewDimension, VertexState, Buffer, BufferUsages,
-            BufferDescriptor, BufferInitDescriptor,
+            BufferDescriptor, BufferInitDescriptor, UniformBuffer,
         },
-        renderer::{RenderContext, RenderDevice},
+        renderer::{RenderContext, RenderDevice, RenderQueue},
         texture::BevyDefault,
         view::{ViewUniforms, ViewUniformOffset},
         camera::CameraRenderGraph,
@@ -21,6 +21,7 @@ use bevy::{
     },
 };
 
+use super::night_lights::VolumetricSpotLights;
 use super::volumetric_texture::VolumetricTexture;
 
 /// Resource that holds the render pipeline and bind group layout for volumetric rendering
@@ -211,6 +212,17 @@ impl FromWorld for VolumetricPipeline {
                     },
                     count: None,
                 },
+                // Spotlight cones that scatter in the volume (vehicle headlights)
+                BindGroupLayoutEntry {
+                    binding: 7,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: Some(VolumetricSpotLights::min_size()),
+                    },
+                    count: None,
+                },
             ],
         });
 
@@ -264,6 +276,23 @@ impl FromWorld for VolumetricSettingsBuffer {
     }
 }
 
+/// Resource that holds the GPU buffer containing the volumetric spotlight cones
+#[derive(Resource, Default)]
+pub struct VolumetricSpotLightsBuffer {
+    buffer: UniformBuffer<VolumetricSpotLights>,
+}
+
+/// System that uploads the extracted spotlight cones
+pub fn prepare_volumetric_spot_lights(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    spot_lights: Res<VolumetricSpotLights>,
+    mut spot_lights_buffer: ResMut<VolumetricSpotLightsBuffer>,
+) {
+    spot_lights_buffer.buffer.set(*spot_lights);
+    spot_lights_buffer.buffer.write_buffer(&render_device, &render_queue);
+}
+
 /// Node in the render graph that handles volumetric rendering
 /// This node renders a full-screen quad with the volumetric shader
 pub struct VolumetricNode {
@@ -327,6 +356,7 @@ pub fn prepare_volumetrics(
     view_uniforms: Res<ViewUniforms>,
     volumetric_texture: Res<VolumetricTexture>,
     settings_buffer: Res<VolumetricSettingsBuffer>,
+    spot_lights_buffer: Res<VolumetricSpotLightsBuffer>,
     images: Res<RenderAssets<Image>>,
     mut volumetric_settings: ResMut<VolumetricSettings>,
     views: Query<(Entity, &ViewUniformOffset, &ExtractedView)>,
@@ -350,6 +380,10 @@ pub fn prepare_volumetrics(
     );
 
     // Create bind groups for each view
+    let Some(spot_lights_binding) = spot_lights_buffer.buffer.binding() else {
+        return;
+    };
+
     for (entity, view_uniform, view) in views.iter() {
         let Some(volume_texture_view) = images.get(&volumetric_texture.texture) else {
             continue;
@@ -394,6 +428,11 @@ pub fn prepare_volumetrics(
                     binding: 6,
                     resource: settings_buffer.buffer.as_entire_binding(),
                 },
+                // Volumetric spotlights
+                BindGroupEntry {
+                    binding: 7,
+                    resource: spot_lights_binding.clone(),
+                },
             ],
         });
 
@@ -410,13 +449,21 @@ impl Plugin for VolumetricRenderPlugin {
     fn build(&self, app: &mut App) {
         // Initialize resources and add systems
         app.init_resource::<VolumetricSettings>()
-            .add_plugins(ExtractResourcePlugin::<VolumetricSettings>::default());
+            .init_resource::<VolumetricSpotLights>()
+            .add_plugins((
+                ExtractResourcePlugin::<VolumetricSettings>::default(),
+                ExtractResourcePlugin::<VolumetricSpotLights>::default(),
+            ));
 
         let render_app = app.sub_app_mut(RenderApp);
         render_app
             .init_resource::<VolumetricPipeline>()
             .init_resource::<VolumetricSettingsBuffer>()
-            .add_systems(Render, prepare_volumetrics.in_set(RenderSet::PrepareResources));
+            .init_resource::<VolumetricSpotLightsBuffer>()
+            .add_systems(Render, (
+                prepare_volumetric_spot_lights,
+                prepare_volumetrics,
+            ).chain().in_set(RenderSet::PrepareResources));
 
         // Add the volumetric node to the render graph after the main pass
         let mut render_graph = render_app.world.resource_mut::<RenderGraph>();
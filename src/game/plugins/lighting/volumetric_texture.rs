@@ -9,8 +9,11 @@ use bevy::{
     },
 };
 
-/// Size of the volumetric texture (width, height, depth)
-/// Using 128x128x64 for a good balance of quality and performance
+use super::volumetric_fog::{FogConditions, VolumetricFogConfig};
+
+/// Default size of the volumetric texture (width, height, depth)
+/// Using 128x128x64 for a good balance of quality and performance.
+/// Lower quality tiers shrink this, see `VolumetricQuality::volume_size`
 pub const VOLUME_SIZE: (u32, u32, u32) = (128, 128, 64);
 
 /// Resource that holds the volumetric texture used for fog, clouds, and other volumetric effects
@@ -21,38 +24,69 @@ pub const VOLUME_SIZE: (u32, u32, u32) = (128, 128, 64);
 pub struct VolumetricTexture {
     /// Handle to the 3D texture in Bevy's asset system
     pub texture: Handle<Image>,
+    /// Current resolution of the texture (width, height, depth)
+    pub size: (u32, u32, u32),
 }
 
 impl FromWorld for VolumetricTexture {
     fn from_world(world: &mut World) -> Self {
+        let size = world
+            .get_resource::<VolumetricFogConfig>()
+            .map(|config| config.quality.volume_size())
+            .unwrap_or(VOLUME_SIZE);
         let mut images = world.resource_mut::<Assets<Image>>();
-        
-        // Create 3D texture for volumetric data
-        let size = Extent3d {
-            width: VOLUME_SIZE.0,
-            height: VOLUME_SIZE.1,
-            depth_or_array_layers: VOLUME_SIZE.2,
-        };
+        let texture = images.add(create_volume_image(size));
+
+        Self { texture, size }
+    }
+}
+
+/// Creates an empty 3D volume image with the given resolution
+fn create_volume_image(size: (u32, u32, u32)) -> Image {
+    // Create 3D texture for volumetric data
+    let size = Extent3d {
+        width: size.0,
+        height: size.1,
+        depth_or_array_layers: size.2,
+    };
 
-        let mut volume_texture = Image::new_fill(
-            size,
-            TextureDimension::D3,
-            &[0, 0, 0, 0],
-            TextureFormat::Rgba8Unorm,
-        );
+    let mut volume_texture = Image::new_fill(
+        size,
+        TextureDimension::D3,
+        &[0, 0, 0, 0],
+        TextureFormat::Rgba8Unorm,
+    );
 
-        // Configure texture usage flags:
-        // - TEXTURE_BINDING: Allow sampling in shaders
-        // - COPY_DST: Allow updating the texture data
-        // - STORAGE_BINDING: Allow using as a storage texture in compute shaders
-        volume_texture.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING 
-            | TextureUsages::COPY_DST 
-            | TextureUsages::STORAGE_BINDING;
+    // Configure texture usage flags:
+    // - TEXTURE_BINDING: Allow sampling in shaders
+    // - COPY_DST: Allow updating the texture data
+    // - STORAGE_BINDING: Allow using as a storage texture in compute shaders
+    volume_texture.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING 
+        | TextureUsages::COPY_DST 
+        | TextureUsages::STORAGE_BINDING;
 
-        let texture = images.add(volume_texture);
+    volume_texture
+}
 
-        Self { texture }
+/// System that recreates the volume texture when the fog quality tier changes
+pub fn resize_volume_texture(
+    config: Res<VolumetricFogConfig>,
+    mut images: ResMut<Assets<Image>>,
+    mut volume_texture: ResMut<VolumetricTexture>,
+) {
+    if !config.is_changed() {
+        return;
     }
+
+    let size = config.quality.volume_size();
+    if size == volume_texture.size {
+        return;
+    }
+
+    // Replace the image in place so existing handles keep pointing at the volume
+    let handle = volume_texture.texture.clone();
+    images.insert(handle, create_volume_image(size));
+    volume_texture.size = size;
 }
 
 /// System that updates the volumetric texture data each frame
@@ -62,14 +96,18 @@ impl FromWorld for VolumetricTexture {
 /// * `images` - Asset storage for accessing the texture data
 /// * `volume_texture` - The volumetric texture resource
 /// * `time` - Time resource for animation
+/// * `conditions` - Weather and elevation driven fog density
+/// * `config` - Fog configuration describing the height span of the volume
 pub fn update_volume_texture(
     mut images: ResMut<Assets<Image>>,
     volume_texture: Res<VolumetricTexture>,
     time: Res<Time>,
+    conditions: Res<FogConditions>,
+    config: Res<VolumetricFogConfig>,
 ) {
     if let Some(texture) = images.get_mut(&volume_texture.texture) {
         let data = texture.data.as_mut_slice();
-        let size = VOLUME_SIZE;
+        let size = volume_texture.size;
         let t = time.elapsed_seconds();
 
         // Update volume texture data
@@ -92,7 +130,11 @@ pub fn update_volume_texture(
                         * (pos.y * 4.0 + t * 0.2).cos() 
                         * (pos.z * 4.0 + t * 0.15).sin();
                     
-                    let density = (noise * 0.5 + 0.5).max(0.0).min(1.0);
+                    // Shape the noise by the fog layer so density pools in the valleys
+                    let height_above_floor = pos.y * config.volume_height;
+                    let layer_density =
+                        conditions.density_at(height_above_floor, config.valley_fog_height);
+                    let density = ((noise * 0.5 + 0.5) * layer_density).max(0.0).min(1.0);
                     
                     // RGBA: RGB for color (light gray), A for density
                     data[index] = (255.0 * 0.8) as u8;     // R: Light gray
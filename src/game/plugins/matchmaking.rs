@@ -0,0 +1,239 @@
+//! Session browser backed by the matchmaking endpoint of the backend.
+//!
+//! [`MatchmakingRequest`] events list, create or join sessions over HTTP on a
+//! background thread, the outcome comes back as a [`MatchmakingResult`] and is
+//! kept in the [`SessionBrowser`]. Hosting a session makes this machine the
+//! netcode server, joining one makes it a client.
+
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::chat::ChatSettings;
+use super::netcode::{NetRole, NetcodeSettings};
+
+/// Matchmaking endpoint of a locally running backend
+pub const DEFAULT_MATCHMAKING_URL: &str = "http://localhost:3000/sessions";
+
+/// Where sessions are listed
+#[derive(Resource, Debug, Clone)]
+pub struct MatchmakingSettings {
+    pub url: String,
+}
+
+impl Default for MatchmakingSettings {
+    fn default() -> Self {
+        Self { url: std::env::var("SANDK_MATCHMAKING_URL").unwrap_or_else(|_| DEFAULT_MATCHMAKING_URL.to_string()) }
+    }
+}
+
+/// A session as listed by the matchmaking endpoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub id: String,
+    pub name: String,
+    /// Display name of the hosting player
+    pub host: String,
+    pub level: String,
+    pub players: u32,
+    pub max_players: u32,
+}
+
+impl SessionInfo {
+    pub fn is_full(&self) -> bool {
+        self.players >= self.max_players
+    }
+}
+
+/// Body of a create session call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateSessionRequest {
+    pub name: String,
+    pub host: String,
+    pub level: String,
+    pub max_players: u32,
+}
+
+/// Body of a join session call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JoinSessionRequest {
+    pub player: String,
+}
+
+#[derive(Error, Debug)]
+pub enum MatchmakingError {
+    #[error("matchmaking request: {0}")]
+    Request(#[from] Box<ureq::Error>),
+    #[error("matchmaking response: {0}")]
+    Response(#[from] std::io::Error),
+}
+
+/// Something to ask the matchmaking endpoint
+#[derive(Event, Debug, Clone)]
+pub enum MatchmakingRequest {
+    Refresh,
+    Create { name: String, level: String, max_players: u32 },
+    Join { id: String },
+}
+
+/// Answer of the matchmaking endpoint
+#[derive(Event, Debug, Clone)]
+pub enum MatchmakingResult {
+    Sessions(Vec<SessionInfo>),
+    Hosting(SessionInfo),
+    Joined(SessionInfo),
+    Failed(String),
+}
+
+/// Sessions last listed and the one this machine is in
+#[derive(Resource, Debug, Clone, Default)]
+pub struct SessionBrowser {
+    pub sessions: Vec<SessionInfo>,
+    /// A request is on its way
+    pub loading: bool,
+    pub error: Option<String>,
+    pub current: Option<SessionInfo>,
+}
+
+/// Results of requests running on background threads
+#[derive(Resource)]
+struct MatchmakingChannel {
+    sender: Mutex<Sender<MatchmakingResult>>,
+    receiver: Mutex<Receiver<MatchmakingResult>>,
+}
+
+impl Default for MatchmakingChannel {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self { sender: Mutex::new(sender), receiver: Mutex::new(receiver) }
+    }
+}
+
+/// Makes a request against the endpoint, blocking until it answers
+fn call(url: &str, request: MatchmakingRequest, player: String) -> Result<MatchmakingResult, MatchmakingError> {
+    Ok(match request {
+        MatchmakingRequest::Refresh => {
+            MatchmakingResult::Sessions(ureq::get(url).call().map_err(Box::new)?.into_json()?)
+        }
+        MatchmakingRequest::Create { name, level, max_players } => {
+            let body = CreateSessionRequest { name, host: player, level, max_players };
+            MatchmakingResult::Hosting(ureq::post(url).send_json(&body).map_err(Box::new)?.into_json()?)
+        }
+        MatchmakingRequest::Join { id } => {
+            let body = JoinSessionRequest { player };
+            let url = format!("{}/{id}/join", url.trim_end_matches('/'));
+            MatchmakingResult::Joined(ureq::post(&url).send_json(&body).map_err(Box::new)?.into_json()?)
+        }
+    })
+}
+
+/// Runs each request on its own thread so the game doesn't wait on the network
+fn send_matchmaking_requests(
+    settings: Res<MatchmakingSettings>,
+    chat: Res<ChatSettings>,
+    channel: Res<MatchmakingChannel>,
+    mut browser: ResMut<SessionBrowser>,
+    mut requests: EventReader<MatchmakingRequest>,
+) {
+    for request in requests.read() {
+        let Ok(sender) = channel.sender.lock().map(|sender| sender.clone()) else {
+            continue;
+        };
+        let url = settings.url.clone();
+        let request = request.clone();
+        let player = chat.player_name.clone();
+        browser.loading = true;
+        std::thread::spawn(move || {
+            let result = call(&url, request, player).unwrap_or_else(|error| {
+                warn!("{error}");
+                MatchmakingResult::Failed(error.to_string())
+            });
+            // The receiver only goes away with the app
+            let _ = sender.send(result);
+        });
+    }
+}
+
+/// Hands finished requests to the rest of the game
+fn receive_matchmaking_results(channel: Res<MatchmakingChannel>, mut results: EventWriter<MatchmakingResult>) {
+    let Ok(receiver) = channel.receiver.lock() else {
+        return;
+    };
+    results.send_batch(receiver.try_iter());
+}
+
+/// Keeps the browser up to date and picks the netcode role for a hosted or joined session
+fn apply_matchmaking_results(
+    mut browser: ResMut<SessionBrowser>,
+    mut netcode: ResMut<NetcodeSettings>,
+    mut results: EventReader<MatchmakingResult>,
+) {
+    for result in results.read() {
+        browser.loading = false;
+        browser.error = None;
+        match result {
+            MatchmakingResult::Sessions(sessions) => browser.sessions = sessions.clone(),
+            MatchmakingResult::Hosting(session) => {
+                netcode.role = NetRole::Server;
+                browser.current = Some(session.clone());
+            }
+            MatchmakingResult::Joined(session) => {
+                netcode.role = NetRole::Client;
+                browser.current = Some(session.clone());
+            }
+            MatchmakingResult::Failed(error) => browser.error = Some(error.clone()),
+        }
+    }
+}
+
+/// Plugin for listing, creating and joining online sessions
+pub struct MatchmakingPlugin;
+
+impl Plugin for MatchmakingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MatchmakingSettings>()
+            .init_resource::<SessionBrowser>()
+            .init_resource::<MatchmakingChannel>()
+            .add_event::<MatchmakingRequest>()
+            .add_event::<MatchmakingResult>()
+            .add_systems(
+                Update,
+                (send_matchmaking_requests, receive_matchmaking_results, apply_matchmaking_results).chain(),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_joining_makes_this_machine_a_client() {
+        let mut app = App::new();
+        app.init_resource::<NetcodeSettings>()
+            .init_resource::<SessionBrowser>()
+            .add_event::<MatchmakingResult>()
+            .add_systems(Update, apply_matchmaking_results);
+        let session = SessionInfo {
+            id: "1".to_string(),
+            name: "Mud run".to_string(),
+            host: "Host".to_string(),
+            level: "default".to_string(),
+            players: 2,
+            max_players: 2,
+        };
+        assert!(session.is_full());
+        app.world.send_event(MatchmakingResult::Sessions(vec![session.clone()]));
+        app.world.send_event(MatchmakingResult::Joined(session.clone()));
+        app.update();
+
+        assert_eq!(app.world.resource::<NetcodeSettings>().role, NetRole::Client);
+        let browser = app.world.resource::<SessionBrowser>();
+        assert_eq!(browser.sessions.len(), 1);
+        assert_eq!(browser.current, Some(session));
+        assert!(!browser.loading);
+    }
+}
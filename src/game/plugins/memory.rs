@@ -0,0 +1,297 @@
+use bevy::prelude::*;
+use bevy::render::mesh::Indices;
+use bevy::utils::HashSet;
+
+use super::particle_system::ParticleBufferManager;
+use crate::audio::AudioClipCache;
+use crate::terrain::{TerrainChunk, TerrainQuery};
+
+const MEGABYTE: usize = 1024 * 1024;
+
+/// What memory is spent on, as far as the game keeps track of it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemoryCategory {
+    Textures,
+    /// Meshes other than terrain chunks
+    Meshes,
+    Audio,
+    ParticleBuffers,
+    /// Chunk meshes and the heights kept for ground queries
+    TerrainChunks,
+}
+
+impl MemoryCategory {
+    pub const ALL: [Self; 5] = [
+        Self::Textures,
+        Self::Meshes,
+        Self::Audio,
+        Self::ParticleBuffers,
+        Self::TerrainChunks,
+    ];
+
+    pub fn name_key(self) -> &'static str {
+        match self {
+            Self::Textures => "memory.textures",
+            Self::Meshes => "memory.meshes",
+            Self::Audio => "memory.audio",
+            Self::ParticleBuffers => "memory.particle_buffers",
+            Self::TerrainChunks => "memory.terrain_chunks",
+        }
+    }
+
+    /// Whether lowering rendering quality frees memory in this category. Audio evicts its own clips.
+    pub fn scales_with_quality(self) -> bool {
+        self != Self::Audio
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// How close a category is to its budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum MemoryPressure {
+    #[default]
+    Normal,
+    /// Past the warning fraction of the budget
+    Nearing,
+    Over,
+}
+
+/// Budget per category and when to warn about it
+#[derive(Resource, Debug, Clone)]
+pub struct MemoryBudgetConfig {
+    /// Bytes per category, indexed like [`MemoryCategory::ALL`]
+    pub budgets: [usize; MemoryCategory::ALL.len()],
+    /// Share of a budget past which the category is nearing it
+    pub warning_fraction: f32,
+    /// Share of a budget a nearing category has to drop under to count as normal again,
+    /// so usage hovering at the threshold doesn't flood warnings
+    pub recovery_fraction: f32,
+    /// Seconds between counts, walking every asset each frame isn't worth it
+    pub interval: f32,
+}
+
+impl Default for MemoryBudgetConfig {
+    fn default() -> Self {
+        Self {
+            budgets: [1024 * MEGABYTE, 256 * MEGABYTE, 64 * MEGABYTE, 64 * MEGABYTE, 256 * MEGABYTE],
+            warning_fraction: 0.85,
+            recovery_fraction: 0.75,
+            interval: 1.0,
+        }
+    }
+}
+
+impl MemoryBudgetConfig {
+    pub fn budget(&self, category: MemoryCategory) -> usize {
+        self.budgets[category.index()]
+    }
+
+    /// Pressure on a category using `bytes`, given the pressure it was under before
+    pub fn pressure(&self, category: MemoryCategory, bytes: usize, previous: MemoryPressure) -> MemoryPressure {
+        let budget = self.budget(category).max(1) as f32;
+        let fraction = bytes as f32 / budget;
+        if fraction > 1.0 {
+            MemoryPressure::Over
+        } else if fraction >= self.warning_fraction {
+            MemoryPressure::Nearing
+        } else if previous != MemoryPressure::Normal && fraction >= self.recovery_fraction {
+            MemoryPressure::Nearing
+        } else {
+            MemoryPressure::Normal
+        }
+    }
+}
+
+/// Approximate bytes in use per category, as of the last count
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MemoryUsage {
+    bytes: [usize; MemoryCategory::ALL.len()],
+    pressure: [MemoryPressure; MemoryCategory::ALL.len()],
+    since_count: f32,
+}
+
+impl MemoryUsage {
+    pub fn bytes(&self, category: MemoryCategory) -> usize {
+        self.bytes[category.index()]
+    }
+
+    pub fn pressure(&self, category: MemoryCategory) -> MemoryPressure {
+        self.pressure[category.index()]
+    }
+
+    pub fn total_bytes(&self) -> usize {
+        self.bytes.iter().sum()
+    }
+
+    /// Whether any category that lower quality helps with is nearing or over its budget
+    pub fn under_pressure(&self) -> bool {
+        MemoryCategory::ALL
+            .into_iter()
+            .any(|category| category.scales_with_quality() && self.pressure(category) != MemoryPressure::Normal)
+    }
+
+    /// Takes a new count, returns the categories whose pressure changed
+    pub fn record(
+        &mut self,
+        config: &MemoryBudgetConfig,
+        bytes: [usize; MemoryCategory::ALL.len()],
+    ) -> Vec<MemoryBudgetEvent> {
+        self.bytes = bytes;
+        MemoryCategory::ALL
+            .into_iter()
+            .filter_map(|category| {
+                let used = bytes[category.index()];
+                let previous = self.pressure[category.index()];
+                let pressure = config.pressure(category, used, previous);
+                self.pressure[category.index()] = pressure;
+                (pressure != previous).then(|| MemoryBudgetEvent {
+                    category,
+                    pressure,
+                    used_bytes: used,
+                    budget_bytes: config.budget(category),
+                })
+            })
+            .collect()
+    }
+}
+
+/// A category's pressure changed: it's nearing or over its budget, or back under it
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct MemoryBudgetEvent {
+    pub category: MemoryCategory,
+    pub pressure: MemoryPressure,
+    pub used_bytes: usize,
+    pub budget_bytes: usize,
+}
+
+/// Bytes of a mesh's vertex and index data
+pub fn mesh_bytes(mesh: &Mesh) -> usize {
+    let indices = match mesh.indices() {
+        Some(Indices::U16(indices)) => indices.len() * 2,
+        Some(Indices::U32(indices)) => indices.len() * 4,
+        None => 0,
+    };
+    mesh.count_vertices() * mesh.get_vertex_size() as usize + indices
+}
+
+/// Counts memory per category every [`MemoryBudgetConfig::interval`] and reports pressure changes
+#[allow(clippy::too_many_arguments)]
+fn count_memory(
+    time: Res<Time>,
+    config: Res<MemoryBudgetConfig>,
+    mut usage: ResMut<MemoryUsage>,
+    images: Option<Res<Assets<Image>>>,
+    meshes: Option<Res<Assets<Mesh>>>,
+    audio: Option<Res<AudioClipCache>>,
+    terrain: Option<Res<TerrainQuery>>,
+    particle_buffers: Query<&ParticleBufferManager>,
+    chunks: Query<&Handle<Mesh>, With<TerrainChunk>>,
+    mut events: EventWriter<MemoryBudgetEvent>,
+) {
+    usage.since_count += time.delta_seconds();
+    if usage.since_count < config.interval {
+        return;
+    }
+    usage.since_count = 0.0;
+
+    let textures = images.map_or(0, |images| images.iter().map(|(_, image)| image.data.len()).sum());
+    let chunk_meshes: HashSet<_> = chunks.iter().map(|handle| handle.id()).collect();
+    let (terrain_meshes, other_meshes) = meshes.map_or((0, 0), |meshes| {
+        meshes.iter().fold((0, 0), |(terrain, other), (id, mesh)| {
+            if chunk_meshes.contains(&id) {
+                (terrain + mesh_bytes(mesh), other)
+            } else {
+                (terrain, other + mesh_bytes(mesh))
+            }
+        })
+    });
+
+    let mut bytes = [0; MemoryCategory::ALL.len()];
+    bytes[MemoryCategory::Textures.index()] = textures;
+    bytes[MemoryCategory::Meshes.index()] = other_meshes;
+    bytes[MemoryCategory::Audio.index()] = audio.map_or(0, |audio| audio.usage().total_bytes());
+    bytes[MemoryCategory::ParticleBuffers.index()] =
+        particle_buffers.iter().map(|buffers| buffers.size_bytes() as usize).sum();
+    bytes[MemoryCategory::TerrainChunks.index()] = terrain_meshes + terrain.map_or(0, |terrain| terrain.memory_bytes());
+
+    for event in usage.record(&config, bytes) {
+        match event.pressure {
+            MemoryPressure::Normal => info!("{:?} memory back under budget", event.category),
+            pressure => warn!(
+                "{:?} memory {:?} budget: {:.1} of {:.1} MB",
+                event.category,
+                pressure,
+                event.used_bytes as f32 / MEGABYTE as f32,
+                event.budget_bytes as f32 / MEGABYTE as f32
+            ),
+        }
+        events.send(event);
+    }
+}
+
+/// Plugin that keeps approximate memory accounts per category and warns near their budgets
+pub struct MemoryBudgetPlugin;
+
+impl Plugin for MemoryBudgetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MemoryBudgetConfig>()
+            .init_resource::<MemoryUsage>()
+            .add_event::<MemoryBudgetEvent>()
+            .add_systems(Update, count_memory);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> MemoryBudgetConfig {
+        MemoryBudgetConfig {
+            budgets: [100; MemoryCategory::ALL.len()],
+            ..default()
+        }
+    }
+
+    #[test]
+    fn test_pressure_has_hysteresis() {
+        let config = config();
+        let textures = MemoryCategory::Textures;
+        assert_eq!(config.pressure(textures, 50, MemoryPressure::Normal), MemoryPressure::Normal);
+        assert_eq!(config.pressure(textures, 90, MemoryPressure::Normal), MemoryPressure::Nearing);
+        assert_eq!(config.pressure(textures, 120, MemoryPressure::Nearing), MemoryPressure::Over);
+        // Dropping just under the warning line keeps the warning until usage is well clear of it
+        assert_eq!(config.pressure(textures, 80, MemoryPressure::Nearing), MemoryPressure::Nearing);
+        assert_eq!(config.pressure(textures, 80, MemoryPressure::Normal), MemoryPressure::Normal);
+        assert_eq!(config.pressure(textures, 70, MemoryPressure::Over), MemoryPressure::Normal);
+    }
+
+    #[test]
+    fn test_events_only_on_changes() {
+        let config = config();
+        let mut usage = MemoryUsage::default();
+        let mut bytes = [10; MemoryCategory::ALL.len()];
+        assert!(usage.record(&config, bytes).is_empty());
+
+        bytes[MemoryCategory::Meshes.index()] = 95;
+        let events = usage.record(&config, bytes);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].category, MemoryCategory::Meshes);
+        assert_eq!(events[0].pressure, MemoryPressure::Nearing);
+        assert!(usage.under_pressure());
+        assert!(usage.record(&config, bytes).is_empty());
+        assert_eq!(usage.total_bytes(), 135);
+    }
+
+    #[test]
+    fn test_audio_pressure_leaves_quality_alone() {
+        let config = config();
+        let mut usage = MemoryUsage::default();
+        let mut bytes = [0; MemoryCategory::ALL.len()];
+        bytes[MemoryCategory::Audio.index()] = 150;
+        assert_eq!(usage.record(&config, bytes)[0].pressure, MemoryPressure::Over);
+        assert!(!usage.under_pressure());
+    }
+}
@@ -1,30 +1,183 @@
 use bevy::prelude::*;
 
+mod biomes;
+mod boulders;
 mod camera;
+mod chat;
+mod crash_report;
 mod debug;
+mod determinism;
+mod director;
+mod economy;
+mod fast_travel;
+mod floating_origin;
+mod hazards;
+mod heat_haze;
+mod impacts;
 mod input;
+mod jobs;
 mod lighting;
+mod matchmaking;
+mod memory;
+mod netcode;
 mod particle_system;
+mod performance;
 mod physics;
+#[cfg(feature = "physics-fuzz")]
+mod physics_fuzz;
 mod post_process;
+mod progression;
+mod props;
+mod relevance;
+mod routing;
+mod rumble;
+mod scripting;
+mod seasons;
+mod session_log;
+mod split_screen;
+mod steering_wheel;
 mod state;
 mod ui;
 mod vehicle;
+mod voice_chat;
 mod terrain;
+mod traffic;
+mod trails;
+mod tutorial;
+mod water;
 mod weather;
+mod wildlife;
+mod world_clock;
 
-pub use camera::CameraPlugin;
+pub use biomes::{default_weather_odds, pick_weather, BiomeWorldSettings, BiomesPlugin};
+pub use boulders::{
+    ground_boulder, place_boulders, Boulder, BoulderFieldDesc, BoulderPlacement, BoulderPlugin, BoulderShape,
+    LevelBoulders, LevelBouldersError, LevelBouldersLoader, BOULDER_VARIANTS,
+};
+pub use camera::{
+    head_lean, mirror_view, CameraPlugin, CockpitCameraSettings, CockpitView, GameCamera, RearViewCamera, RearViewMirror,
+};
+pub use chat::{
+    ChatBody, ChatEntry, ChatFilter, ChatFilters, ChatHistory, ChatMessage, ChatPlugin, ChatSettings, QuickMessage,
+    ReceivedChatMessage, SendChatMessage, SubmitChatEvent, WordListFilter,
+};
+pub use crash_report::{
+    capture_recent_logs, dismiss_crash_report, read_crash_dumps, recent_logs, submit_crash_report, write_crash_dump,
+    CrashDump, CrashDumpError, CrashReason, CrashReportPlugin, CrashReportSettings, PendingCrashReports, VehicleSnapshot,
+};
 pub use debug::DebugPlugin;
+pub use determinism::{DesyncEvent, DeterminismPlugin, DeterminismSession, DeterminismSettings, InputRecording};
+pub use director::{
+    export_frame_count, CameraKeyframe, CameraPath, DirectorError, DirectorPlugin, DirectorSettings, DirectorState,
+    ExportFinishedEvent, ReplayTimeline, StartExportEvent, TimelineFrame, EXPORT_FRAME_RATES,
+};
+pub use economy::{
+    audit_ledger, default_prices, earn, spend, EconomyConfig, EconomyError, EconomyPlugin, EconomyViolation, Ledger,
+    LedgerEntry, PriceEntry, PurchaseEvent, TransactionEvent, TransactionKind,
+};
+pub use fast_travel::{
+    fast_travel_block, travel_destinations, DestinationKind, FastTravel, FastTravelBlock, FastTravelConfig,
+    FastTravelFinishedEvent, FastTravelPlugin, RequestFastTravelEvent, TravelDestination,
+};
+pub use floating_origin::{
+    origin_shift, FloatingOriginPlugin, FloatingOriginSettings, OriginShiftEvent, ShiftOrigin, WorldOrigin,
+};
+pub use hazards::{HazardPlugin, HazardStartedEvent, HazardType};
+pub use heat_haze::{HeatHaze, HeatHazePlugin, HeatHazeSettings, HeatHazeSource};
+pub use impacts::{ImpactEvent, ImpactPlugin, ImpactSettings, SurfaceMaterial};
 pub use input::InputPlugin;
-pub use lighting::LightingPlugin;
-pub use particle_system::ParticleSystemPlugin;
+pub use jobs::{
+    board_at, generate_jobs, on_paved_trail, AbandonJobEvent, AcceptJobEvent, ActiveJob, Job, JobBoards, JobCargo,
+    JobCondition, JobDropOff, JobFailure, JobFinishedEvent, JobOutcome, JobsConfig, JobsPlugin,
+};
+pub use lighting::{
+    spawn_dashboard_gauges, spawn_vehicle_headlights, DashboardEmissive, LightingPlugin, VehicleHeadlight,
+};
+pub use matchmaking::{
+    CreateSessionRequest, JoinSessionRequest, MatchmakingError, MatchmakingPlugin, MatchmakingRequest, MatchmakingResult,
+    MatchmakingSettings, SessionBrowser, SessionInfo, DEFAULT_MATCHMAKING_URL,
+};
+pub use memory::{
+    mesh_bytes, MemoryBudgetConfig, MemoryBudgetEvent, MemoryBudgetPlugin, MemoryCategory, MemoryPressure, MemoryUsage,
+};
+pub use netcode::{
+    reconcile, Correction, InputMessage, NetClock, NetRole, NetVehicleState, NetcodePlugin, NetcodeSettings, NetworkedVehicle,
+    PendingCorrection, PredictionHistory, ReceivedInputMessage, ReceivedSnapshot, SendInputMessage, SendSnapshot,
+    ServerInputQueue, SnapshotBuffer, StateError, StateSnapshot,
+};
+pub use particle_system::{ParticleSystem, ParticleSystemPlugin};
+pub use performance::{PerformanceBudget, PerformanceBudgetConfig, PerformanceBudgetPlugin, QualityLevel};
 pub use physics::PhysicsPlugin;
-pub use post_process::PostProcessPlugin;
+#[cfg(feature = "physics-fuzz")]
+pub use physics_fuzz::{
+    check_invariants, fuzz, fuzz_seeds, parse_seed, random_inputs, run_fuzz_case, specific_energy, FuzzConfig, FuzzFailure,
+    FuzzReport, Violation,
+};
+pub use post_process::{GpuPass, GpuTimer, GpuTimingResults, GpuTimings, PostProcessPlugin, SceneColor};
+#[cfg(feature = "golden-images")]
+pub use post_process::{
+    check_golden_scene, delta_e, golden_scenes, perceptual_diff, render_golden_scene, srgb_to_lab, GoldenConfig,
+    GoldenDiff, GoldenError, GoldenPattern, GoldenReport, GoldenScene, GoldenTolerance,
+};
+pub use progression::{
+    catalog_vehicles, default_unlocks, level_for_xp, level_progress, load_profile, save_profile, xp_for_level, Accessory,
+    AwardXpEvent, FittedAccessory, ItemUnlockedEvent, LevelUpEvent, Loadout, Paint, ProfileError, ProgressionConfig,
+    ProgressionPlugin, RaceFinishedEvent, RacePositionEvent, Unlock, UnlockEntry, XpSource, STARTER_VEHICLE,
+};
+pub use props::{
+    prop_pieces, DestructibleProp, LevelProps, LevelPropsError, LevelPropsLoader, LevelPropsSpawned, PooledPropDebris,
+    PropBrokenEvent, PropDebris, PropDesc, PropKind, PropPiece, PropPlugin, PropSettings,
+};
+pub use relevance::{track_relevance, Relevance, RelevanceBucket, RelevancePlugin, RelevanceSettings};
+pub use routing::{
+    steer_towards, RouteFailedEvent, RouteFinishedEvent, RouteToEvent, RoutingPlugin, RoutingSettings, VehicleRoute,
+};
+pub use rumble::{
+    engine_idle, mix_rumble, winch_pulse, Rumble, RumbleMotors, RumblePlugin, RumbleSettings, RumbleSource,
+};
+pub use scripting::{
+    LevelScript, LevelScriptError, MissionScript, ScriptCommand, ScriptEvent, ScriptMessageEvent, ScriptRuntime,
+    ScriptSettings, ScriptZone, ScriptingPlugin, VehicleUnlockedEvent,
+};
+pub use seasons::{
+    IceSheet, LevelSeason, LevelSeasonError, LevelSeasonLoader, Season, SeasonSettings, SeasonalVegetation,
+    SeasonsPlugin, Vegetation, VegetationDesc, VegetationKind,
+};
+pub use session_log::{
+    EndSessionEvent, LeaderboardEntry, LeaderboardRank, LeaderboardStatus, Medal, SaveReplayEvent, SessionEvent,
+    SessionEventKind, SessionLog, SessionLogError, SessionLogPlugin, SessionLogSettings, SessionStats, SessionSummary,
+    SubmitScoreEvent, DEFAULT_LEADERBOARD_URL,
+};
+pub use split_screen::{
+    split_viewport, GamepadLayout, KeyboardLayout, PlayerId, PlayerInput, PlayerInputDevice, SplitLayout, SplitScreenPlugin,
+    SplitScreenSettings, QUICK_CHAT_PAGES, QUICK_CHAT_PAGE_SIZE,
+};
 pub use state::StatePlugin;
+pub use steering_wheel::{ForceFeedback, PedalAxis, SteeringWheelDevice, SteeringWheelPlugin, SteeringWheelSettings};
 pub use ui::UiPlugin;
 pub use vehicle::VehiclePlugin;
+pub use voice_chat::{
+    proximity_gain, JitterBuffer, JitterFrame, ReceivedVoicePacket, SendVoicePacket, VoiceChatPlugin, VoiceEmitter, VoiceMutes,
+    VoicePacket, VoicePeer, VoiceSettings, VOICE_FRAME_SAMPLES, VOICE_SAMPLE_RATE,
+};
 pub use terrain::TerrainPlugin;
-pub use weather::WeatherPlugin;
+pub use traffic::{Traffic, TrafficPlugin, TrafficSettings, TrafficVehicle};
+pub use trails::{
+    CarveTrailEvent, CrossingStatusEvent, LevelTrails, PoiReachedEvent, PointOfInterest, Trail, TrailCondition,
+    TrailConditionChangedEvent, TrailConditions, TrailCrossing, TrailDifficulty, TrailPlugin, TrailSettings, TrailTool,
+    TrailToolError, WeatherHistory,
+};
+pub use tutorial::{Tutorial, TutorialAction, TutorialPlugin, TutorialStep};
+pub use water::{sample_fluid, FluidKind, FluidSample, FluidVolume, WaterPlugin};
+pub use weather::{
+    ForecastEntry, LevelWeather, Lightning, LightningSettings, LightningStrikeEvent, ScheduledWeather, WeatherManager,
+    WeatherOverrides, WeatherPlugin,
+};
+pub use wildlife::{NoiseEmitter, WildlifePlugin, WildlifeSettings};
+pub use world_clock::{
+    is_open, DailyCurve, DayPhaseChangedEvent, OpenHours, ScheduleChangedEvent, ScheduleOpen, WorldClock, WorldClockPlugin,
+    WorldScheduleSettings,
+};
 
 /// Main plugin group that initializes all core game systems
 pub struct GamePluginGroup;
@@ -32,18 +185,52 @@ pub struct GamePluginGroup;
 impl PluginGroup for GamePluginGroup {
     fn build(self) -> PluginGroupBuilder {
         PluginGroupBuilder::start::<Self>()
+            .add(CrashReportPlugin)
             .add(StatePlugin)
             .add(InputPlugin)
             .add(PhysicsPlugin)
+            .add(FloatingOriginPlugin)
+            .add(ImpactPlugin)
             .add(VehiclePlugin)
             .add(CameraPlugin)
+            .add(SplitScreenPlugin)
+            .add(SteeringWheelPlugin)
+            .add(RumblePlugin)
+            .add(VoiceChatPlugin)
             .add(UiPlugin)
             .add(LightingPlugin)
             .add(ParticleSystemPlugin)
+            .add(HeatHazePlugin)
             .add(PostProcessPlugin)
+            .add(MemoryBudgetPlugin)
+            .add(PerformanceBudgetPlugin)
+            .add(RelevancePlugin)
             .add(DebugPlugin)
+            .add(DeterminismPlugin)
+            .add(NetcodePlugin)
+            .add(DirectorPlugin)
+            .add(ChatPlugin)
+            .add(MatchmakingPlugin)
             .add(TerrainPlugin)
+            .add(WaterPlugin)
+            .add(HazardPlugin)
+            .add(BoulderPlugin)
+            .add(PropPlugin)
+            .add(TrailPlugin)
+            .add(RoutingPlugin)
+            .add(TrafficPlugin)
+            .add(ScriptingPlugin)
+            .add(ProgressionPlugin)
+            .add(EconomyPlugin)
+            .add(JobsPlugin)
+            .add(FastTravelPlugin)
+            .add(SessionLogPlugin)
+            .add(TutorialPlugin)
+            .add(WildlifePlugin)
             .add(WeatherPlugin)
+            .add(WorldClockPlugin)
+            .add(SeasonsPlugin)
+            .add(BiomesPlugin)
     }
 }
 
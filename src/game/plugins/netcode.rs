@@ -0,0 +1,827 @@
+//! Server-authoritative multiplayer with client prediction.
+//!
+//! Both sides run the deterministic simulation at the same fixed tick. A client
+//! drives its own vehicle from local input right away, keeps what it predicted
+//! for every tick and sends its inputs to the server. The server applies them
+//! to that client's vehicle on the same tick and sends back snapshots of every
+//! vehicle. When a snapshot disagrees with the prediction for its tick the
+//! client shifts its vehicle by the error, blended in over a few frames unless
+//! the error is too large to hide. Other players' vehicles aren't simulated on
+//! the client, they play back snapshots a short delay behind the server.
+//!
+//! Messages travel as events, the network session carries [`SendInputMessage`]
+//! and [`SendSnapshot`] to the other side and delivers them as
+//! [`ReceivedInputMessage`] and [`ReceivedSnapshot`].
+//!
+//! Every machine moves its own floating origin, so states on the wire are in
+//! the level's coordinates. Everything buffered locally is in local coordinates
+//! and follows [`OriginShiftEvent`].
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::game::vehicle::Vehicle;
+
+use super::floating_origin::{OriginShiftEvent, ShiftOrigin, WorldOrigin};
+use super::determinism::{record_player_inputs, record_state_checksum, DeterminismSession, DeterminismSettings, RecordedInput};
+use super::split_screen::{apply_player_input, read_player_input, PlayerId, PlayerInput};
+
+/// Which end of the session this machine is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NetRole {
+    /// Single machine play, nothing is predicted or sent
+    #[default]
+    Offline,
+    /// Owns the authoritative simulation
+    Server,
+    /// Predicts its own vehicle and follows the server
+    Client,
+}
+
+/// Tick rate, interpolation delay and correction tuning for online play
+#[derive(Resource, Debug, Clone)]
+pub struct NetcodeSettings {
+    pub role: NetRole,
+    /// Peer id of this machine in the network session
+    pub local_peer: u32,
+    /// Simulation ticks per second, shared by server and clients
+    pub tick_rate: u32,
+    /// Snapshots the server sends per second
+    pub snapshot_rate: u32,
+    /// Seconds other players' vehicles are shown behind the latest snapshot
+    pub interpolation_delay: f32,
+    /// Position error in meters a client lets slide before correcting its own vehicle
+    pub position_tolerance: f32,
+    /// Errors beyond this many meters snap instead of blending
+    pub snap_distance: f32,
+    /// Seconds a correction is blended in over
+    pub correction_time: f32,
+    /// Recent inputs repeated in each input message, covering lost packets
+    pub input_redundancy: usize,
+    /// Ticks of prediction kept waiting for the server to confirm them
+    pub prediction_history: usize,
+    /// Inputs further than this many ticks ahead of the server are dropped
+    pub max_input_lead: u64,
+}
+
+impl Default for NetcodeSettings {
+    fn default() -> Self {
+        Self {
+            role: NetRole::Offline,
+            local_peer: 0,
+            tick_rate: 60,
+            snapshot_rate: 20,
+            interpolation_delay: 0.1,
+            position_tolerance: 0.05,
+            snap_distance: 4.0,
+            correction_time: 0.15,
+            input_redundancy: 3,
+            prediction_history: 128,
+            max_input_lead: 30,
+        }
+    }
+}
+
+impl NetcodeSettings {
+    /// Ticks between two snapshots
+    pub fn snapshot_interval(&self) -> u64 {
+        (self.tick_rate / self.snapshot_rate.max(1)).max(1) as u64
+    }
+
+    /// Interpolation delay in ticks
+    pub fn interpolation_ticks(&self) -> f32 {
+        self.interpolation_delay * self.tick_rate as f32
+    }
+}
+
+/// A vehicle driven by a peer in the session
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkedVehicle {
+    pub owner: u32,
+}
+
+/// Physical state of one vehicle, in local coordinates unless converted for the wire
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NetVehicleState {
+    pub owner: u32,
+    pub position: [f32; 3],
+    pub rotation: [f32; 4],
+    pub linear_velocity: [f32; 3],
+    pub angular_velocity: [f32; 3],
+}
+
+impl NetVehicleState {
+    pub fn new(owner: u32, transform: &Transform, velocity: &Velocity) -> Self {
+        Self {
+            owner,
+            position: transform.translation.to_array(),
+            rotation: transform.rotation.to_array(),
+            linear_velocity: velocity.linvel.to_array(),
+            angular_velocity: velocity.angvel.to_array(),
+        }
+    }
+
+    /// This state in the level's coordinates, for peers whose origin sits elsewhere
+    pub fn to_world(&self, origin: &WorldOrigin) -> Self {
+        Self { position: origin.to_world(self.position()).to_array(), ..*self }
+    }
+
+    /// A state in the level's coordinates, in this machine's local ones
+    pub fn to_local(&self, origin: &WorldOrigin) -> Self {
+        Self { position: origin.to_local(self.position()).to_array(), ..*self }
+    }
+
+    /// This state moved along with the world by an origin shift
+    pub fn rebased(&self, shift: &OriginShiftEvent) -> Self {
+        Self { position: (self.position() - shift.translation()).to_array(), ..*self }
+    }
+
+    pub fn position(&self) -> Vec3 {
+        Vec3::from(self.position)
+    }
+
+    pub fn rotation(&self) -> Quat {
+        Quat::from_array(self.rotation).normalize()
+    }
+
+    /// This state moved by `error`
+    pub fn corrected(&self, error: &StateError) -> Self {
+        let mut transform = Transform::from_translation(self.position()).with_rotation(self.rotation());
+        let mut velocity = Velocity {
+            linvel: Vec3::from(self.linear_velocity),
+            angvel: Vec3::from(self.angular_velocity),
+        };
+        error.apply(&mut transform, &mut velocity);
+        Self::new(self.owner, &transform, &velocity)
+    }
+
+    /// State `t` of the way from `self` to `other`
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self {
+            owner: self.owner,
+            position: self.position().lerp(other.position(), t).to_array(),
+            rotation: self.rotation().slerp(other.rotation(), t).to_array(),
+            linear_velocity: Vec3::from(self.linear_velocity).lerp(Vec3::from(other.linear_velocity), t).to_array(),
+            angular_velocity: Vec3::from(self.angular_velocity).lerp(Vec3::from(other.angular_velocity), t).to_array(),
+        }
+    }
+}
+
+/// State of every vehicle at the end of a server tick, in the level's coordinates
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    pub tick: u64,
+    pub vehicles: Vec<NetVehicleState>,
+}
+
+/// A client's most recent inputs, oldest first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputMessage {
+    pub peer: u32,
+    pub inputs: Vec<RecordedInput>,
+}
+
+/// Inputs of the local player for the network session to send to the server
+#[derive(Event, Debug, Clone)]
+pub struct SendInputMessage(pub InputMessage);
+
+/// Inputs from a client that arrived at the server
+#[derive(Event, Debug, Clone)]
+pub struct ReceivedInputMessage(pub InputMessage);
+
+/// A snapshot for the network session to send to every client
+#[derive(Event, Debug, Clone)]
+pub struct SendSnapshot(pub StateSnapshot);
+
+/// A snapshot from the server that arrived at a client
+#[derive(Event, Debug, Clone)]
+pub struct ReceivedSnapshot(pub StateSnapshot);
+
+/// How to fix a predicted state the server disagreed with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Correction {
+    /// Close enough, keep predicting
+    None,
+    /// Blend the error in over the correction time
+    Smooth(StateError),
+    /// Too far off to hide, jump straight to the server's state
+    Snap(StateError),
+}
+
+/// Difference between the server's state and the predicted one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StateError {
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub linear_velocity: Vec3,
+    pub angular_velocity: Vec3,
+}
+
+impl Default for StateError {
+    fn default() -> Self {
+        Self {
+            position: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            linear_velocity: Vec3::ZERO,
+            angular_velocity: Vec3::ZERO,
+        }
+    }
+}
+
+impl StateError {
+    pub fn between(predicted: &NetVehicleState, server: &NetVehicleState) -> Self {
+        Self {
+            position: server.position() - predicted.position(),
+            rotation: server.rotation() * predicted.rotation().inverse(),
+            linear_velocity: Vec3::from(server.linear_velocity) - Vec3::from(predicted.linear_velocity),
+            angular_velocity: Vec3::from(server.angular_velocity) - Vec3::from(predicted.angular_velocity),
+        }
+    }
+
+    /// This error followed by `other`
+    pub fn then(&self, other: &Self) -> Self {
+        Self {
+            position: self.position + other.position,
+            rotation: (other.rotation * self.rotation).normalize(),
+            linear_velocity: self.linear_velocity + other.linear_velocity,
+            angular_velocity: self.angular_velocity + other.angular_velocity,
+        }
+    }
+
+    /// `fraction` of this error
+    pub fn scaled(&self, fraction: f32) -> Self {
+        Self {
+            position: self.position * fraction,
+            rotation: Quat::IDENTITY.slerp(self.rotation, fraction),
+            linear_velocity: self.linear_velocity * fraction,
+            angular_velocity: self.angular_velocity * fraction,
+        }
+    }
+
+    pub fn apply(&self, transform: &mut Transform, velocity: &mut Velocity) {
+        transform.translation += self.position;
+        transform.rotation = (self.rotation * transform.rotation).normalize();
+        velocity.linvel += self.linear_velocity;
+        velocity.angvel += self.angular_velocity;
+    }
+}
+
+/// Compares a predicted state to the server's for the same tick
+pub fn reconcile(predicted: &NetVehicleState, server: &NetVehicleState, settings: &NetcodeSettings) -> Correction {
+    let error = StateError::between(predicted, server);
+    let distance = error.position.length();
+    if distance > settings.snap_distance {
+        Correction::Snap(error)
+    } else if distance > settings.position_tolerance {
+        Correction::Smooth(error)
+    } else {
+        Correction::None
+    }
+}
+
+/// What a client predicted for its own vehicle, by tick
+#[derive(Component, Debug, Clone, Default)]
+pub struct PredictionHistory {
+    pub states: VecDeque<(u64, NetVehicleState)>,
+}
+
+impl PredictionHistory {
+    pub fn push(&mut self, tick: u64, state: NetVehicleState, capacity: usize) {
+        self.states.push_back((tick, state));
+        while self.states.len() > capacity {
+            self.states.pop_front();
+        }
+    }
+
+    /// Takes the prediction for `tick`, dropping it and everything older since the server has moved past them
+    pub fn confirm(&mut self, tick: u64) -> Option<NetVehicleState> {
+        let mut confirmed = None;
+        while self.states.front().map_or(false, |(predicted_tick, _)| *predicted_tick <= tick) {
+            let (predicted_tick, state) = self.states.pop_front()?;
+            if predicted_tick == tick {
+                confirmed = Some(state);
+            }
+        }
+        confirmed
+    }
+
+    /// Moves the predictions not yet confirmed by `error`, so the next snapshot isn't corrected twice
+    pub fn correct(&mut self, error: &StateError) {
+        for (_, state) in self.states.iter_mut() {
+            *state = state.corrected(error);
+        }
+    }
+}
+
+/// Error still being blended into a client's own vehicle
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct PendingCorrection {
+    pub remaining: StateError,
+    /// Seconds left to blend it in
+    pub time_left: f32,
+}
+
+/// Snapshots of another player's vehicle waiting to be played back
+#[derive(Component, Debug, Clone, Default)]
+pub struct SnapshotBuffer {
+    pub states: VecDeque<(u64, NetVehicleState)>,
+}
+
+impl SnapshotBuffer {
+    /// Snapshots older than this many are dropped
+    const CAPACITY: usize = 32;
+
+    pub fn push(&mut self, tick: u64, state: NetVehicleState) {
+        // Snapshots can arrive out of order, keep the buffer sorted by tick
+        let index = self.states.partition_point(|(buffered, _)| *buffered < tick);
+        if self.states.get(index).map_or(false, |(buffered, _)| *buffered == tick) {
+            return;
+        }
+        self.states.insert(index, (tick, state));
+        while self.states.len() > Self::CAPACITY {
+            self.states.pop_front();
+        }
+    }
+
+    /// State at fractional server tick `tick`, held at the ends of the buffer rather than extrapolated
+    pub fn sample(&self, tick: f32) -> Option<NetVehicleState> {
+        let after = self.states.partition_point(|(buffered, _)| (*buffered as f32) <= tick);
+        match (after.checked_sub(1).and_then(|before| self.states.get(before)), self.states.get(after)) {
+            (Some((from_tick, from)), Some((to_tick, to))) => {
+                let t = (tick - *from_tick as f32) / (*to_tick - *from_tick) as f32;
+                Some(from.lerp(to, t.clamp(0.0, 1.0)))
+            }
+            (Some((_, state)), None) | (None, Some((_, state))) => Some(*state),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Inputs the server has received from clients, by peer and tick
+#[derive(Resource, Debug, Clone, Default)]
+pub struct ServerInputQueue {
+    inputs: HashMap<u32, BTreeMap<u64, RecordedInput>>,
+    /// Last input applied for each peer, repeated when the next one is late
+    last: HashMap<u32, RecordedInput>,
+}
+
+impl ServerInputQueue {
+    /// Queues a client's inputs, ignoring ones for ticks already simulated or too far ahead
+    pub fn receive(&mut self, message: &InputMessage, server_tick: u64, max_lead: u64) {
+        let queue = self.inputs.entry(message.peer).or_default();
+        for input in &message.inputs {
+            if input.tick >= server_tick && input.tick <= server_tick + max_lead {
+                queue.insert(input.tick, *input);
+            }
+        }
+    }
+
+    /// Input of `peer` for `tick`, the last known one if it hasn't arrived
+    pub fn take(&mut self, peer: u32, tick: u64) -> Option<RecordedInput> {
+        let queue = self.inputs.entry(peer).or_default();
+        // Anything older than this tick can never be used now
+        *queue = queue.split_off(&tick);
+        if let Some(input) = queue.remove(&tick) {
+            self.last.insert(peer, input);
+            return Some(input);
+        }
+        self.last.get(&peer).copied()
+    }
+}
+
+/// Server tick the client has heard of most recently, and how long ago
+#[derive(Resource, Debug, Clone, Default)]
+pub struct NetClock {
+    pub latest_tick: Option<u64>,
+    pub since_snapshot: f32,
+}
+
+impl NetClock {
+    /// Fractional server tick other vehicles are shown at
+    pub fn render_tick(&self, settings: &NetcodeSettings) -> Option<f32> {
+        let latest = self.latest_tick? as f32;
+        Some(latest + self.since_snapshot * settings.tick_rate as f32 - settings.interpolation_ticks())
+    }
+}
+
+fn netcode_active(settings: Res<NetcodeSettings>) -> bool {
+    settings.role != NetRole::Offline
+}
+
+fn is_client(settings: Res<NetcodeSettings>) -> bool {
+    settings.role == NetRole::Client
+}
+
+fn is_server(settings: Res<NetcodeSettings>) -> bool {
+    settings.role == NetRole::Server
+}
+
+/// Online play runs the deterministic simulation at the session's tick rate
+fn apply_netcode_settings(settings: Res<NetcodeSettings>, mut determinism: ResMut<DeterminismSettings>) {
+    if !settings.is_changed() || settings.role == NetRole::Offline {
+        return;
+    }
+    if !determinism.enabled || determinism.tick_rate != settings.tick_rate {
+        determinism.enabled = true;
+        determinism.tick_rate = settings.tick_rate;
+    }
+}
+
+/// Tags player one's vehicle as this peer's, and sets up prediction or playback on networked vehicles
+fn prepare_networked_vehicles(
+    mut commands: Commands,
+    settings: Res<NetcodeSettings>,
+    untagged: Query<(Entity, &PlayerId), (With<Vehicle>, Without<NetworkedVehicle>)>,
+    added: Query<(Entity, &NetworkedVehicle), Added<NetworkedVehicle>>,
+) {
+    for (entity, player) in untagged.iter() {
+        if player.0 == 0 {
+            commands.entity(entity).insert(NetworkedVehicle { owner: settings.local_peer });
+        }
+    }
+    for (entity, networked) in added.iter() {
+        if settings.role == NetRole::Server {
+            // Driven by the inputs the owner sends
+            if networked.owner != settings.local_peer {
+                commands.entity(entity).insert(PlayerInput::default());
+            }
+        } else if networked.owner == settings.local_peer {
+            commands.entity(entity).insert(PredictionHistory::default());
+        } else {
+            // Other players move only by snapshot on a client
+            commands
+                .entity(entity)
+                .insert((SnapshotBuffer::default(), RigidBody::KinematicPositionBased));
+        }
+    }
+}
+
+/// Sends the local player's inputs for this tick, with the last few repeated
+fn send_client_inputs(
+    settings: Res<NetcodeSettings>,
+    session: Res<DeterminismSession>,
+    players: Query<(&PlayerId, &PlayerInput)>,
+    mut recent: Local<VecDeque<RecordedInput>>,
+    mut messages: EventWriter<SendInputMessage>,
+) {
+    let Some((player, input)) = players.iter().find(|(player, _)| player.0 == 0) else {
+        return;
+    };
+    recent.push_back(RecordedInput::new(session.tick, player.0, input));
+    while recent.len() > settings.input_redundancy.max(1) {
+        recent.pop_front();
+    }
+    messages.send(SendInputMessage(InputMessage {
+        peer: settings.local_peer,
+        inputs: recent.iter().copied().collect(),
+    }));
+}
+
+/// Drives clients' vehicles on the server with the inputs they sent for this tick
+fn apply_client_inputs(
+    settings: Res<NetcodeSettings>,
+    session: Res<DeterminismSession>,
+    mut queue: ResMut<ServerInputQueue>,
+    mut received: EventReader<ReceivedInputMessage>,
+    mut vehicles: Query<(&NetworkedVehicle, &mut PlayerInput), Without<PlayerId>>,
+) {
+    for ReceivedInputMessage(message) in received.read() {
+        queue.receive(message, session.tick, settings.max_input_lead);
+    }
+    for (networked, mut input) in vehicles.iter_mut() {
+        if let Some(recorded) = queue.take(networked.owner, session.tick) {
+            recorded.apply(&mut input);
+            // One-shot presses only count on the tick they were made
+            if recorded.tick != session.tick {
+                input.ignition = false;
+                input.shift_transfer = false;
+                input.recover = false;
+            }
+        }
+    }
+}
+
+/// Sends the state of every networked vehicle at the snapshot rate
+fn send_snapshots(
+    settings: Res<NetcodeSettings>,
+    session: Res<DeterminismSession>,
+    origin: Res<WorldOrigin>,
+    vehicles: Query<(&NetworkedVehicle, &Transform, &Velocity)>,
+    mut snapshots: EventWriter<SendSnapshot>,
+) {
+    if session.tick % settings.snapshot_interval() != 0 {
+        return;
+    }
+    snapshots.send(SendSnapshot(StateSnapshot {
+        tick: session.tick,
+        vehicles: vehicles
+            .iter()
+            .map(|(networked, transform, velocity)| {
+                NetVehicleState::new(networked.owner, transform, velocity).to_world(&origin)
+            })
+            .collect(),
+    }));
+}
+
+/// Remembers what the client predicted for its own vehicle this tick
+fn record_prediction(
+    settings: Res<NetcodeSettings>,
+    session: Res<DeterminismSession>,
+    mut vehicles: Query<(&NetworkedVehicle, &Transform, &Velocity, &mut PredictionHistory, Option<&PendingCorrection>)>,
+) {
+    for (networked, transform, velocity, mut history, pending) in vehicles.iter_mut() {
+        let state = NetVehicleState::new(networked.owner, transform, velocity);
+        // Counts a correction still being blended in as already made
+        let state = pending.map_or(state, |pending| state.corrected(&pending.remaining));
+        history.push(session.tick, state, settings.prediction_history);
+    }
+}
+
+/// Checks snapshots against the prediction for the local vehicle and buffers the other vehicles
+#[allow(clippy::type_complexity)]
+fn receive_snapshots(
+    mut commands: Commands,
+    settings: Res<NetcodeSettings>,
+    origin: Res<WorldOrigin>,
+    mut clock: ResMut<NetClock>,
+    mut received: EventReader<ReceivedSnapshot>,
+    mut predicted: Query<(
+        Entity,
+        &NetworkedVehicle,
+        &mut Transform,
+        &mut Velocity,
+        &mut PredictionHistory,
+        Option<&PendingCorrection>,
+    )>,
+    mut remote: Query<(&NetworkedVehicle, &mut SnapshotBuffer)>,
+) {
+    for ReceivedSnapshot(snapshot) in received.read() {
+        if clock.latest_tick.map_or(true, |latest| snapshot.tick > latest) {
+            clock.latest_tick = Some(snapshot.tick);
+            clock.since_snapshot = 0.0;
+        }
+        for state in &snapshot.vehicles {
+            let state = state.to_local(&origin);
+            if let Some((_, mut buffer)) = remote.iter_mut().find(|(networked, _)| networked.owner == state.owner) {
+                buffer.push(snapshot.tick, state);
+                continue;
+            }
+            let Some((entity, _, mut transform, mut velocity, mut history, pending)) =
+                predicted.iter_mut().find(|(_, networked, ..)| networked.owner == state.owner)
+            else {
+                continue;
+            };
+            let Some(prediction) = history.confirm(snapshot.tick) else {
+                continue;
+            };
+            // Predictions after the snapshot tick followed the same inputs from the wrong
+            // start, so the error at the snapshot carries over to the current state
+            match reconcile(&prediction, &state, &settings) {
+                Correction::None => {}
+                Correction::Smooth(error) => {
+                    history.correct(&error);
+                    let remaining = pending.map_or(error, |pending| pending.remaining.then(&error));
+                    commands.entity(entity).insert(PendingCorrection {
+                        remaining,
+                        time_left: settings.correction_time,
+                    });
+                }
+                Correction::Snap(error) => {
+                    history.correct(&error);
+                    let error = pending.map_or(error, |pending| pending.remaining.then(&error));
+                    error.apply(&mut transform, &mut velocity);
+                    commands.entity(entity).remove::<PendingCorrection>();
+                }
+            }
+        }
+    }
+}
+
+/// Blends pending corrections into the client's own vehicle
+fn smooth_corrections(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut vehicles: Query<(Entity, &mut Transform, &mut Velocity, &mut PendingCorrection)>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut transform, mut velocity, mut correction) in vehicles.iter_mut() {
+        let fraction = if correction.time_left > dt { dt / correction.time_left } else { 1.0 };
+        let step = correction.remaining.scaled(fraction);
+        step.apply(&mut transform, &mut velocity);
+        correction.remaining = correction.remaining.scaled(1.0 - fraction);
+        correction.time_left -= dt;
+        if fraction >= 1.0 {
+            commands.entity(entity).remove::<PendingCorrection>();
+        }
+    }
+}
+
+/// Moves other players' vehicles along their buffered snapshots
+fn interpolate_remote_vehicles(
+    time: Res<Time>,
+    settings: Res<NetcodeSettings>,
+    mut clock: ResMut<NetClock>,
+    mut vehicles: Query<(&SnapshotBuffer, &mut Transform, &mut Velocity)>,
+) {
+    clock.since_snapshot += time.delta_seconds();
+    let Some(render_tick) = clock.render_tick(&settings) else {
+        return;
+    };
+    for (buffer, mut transform, mut velocity) in vehicles.iter_mut() {
+        let Some(state) = buffer.sample(render_tick) else {
+            continue;
+        };
+        transform.translation = state.position();
+        transform.rotation = state.rotation();
+        // Kept so wheels, audio and effects read the right speed
+        velocity.linvel = Vec3::from(state.linear_velocity);
+        velocity.angvel = Vec3::from(state.angular_velocity);
+    }
+}
+
+/// Moves the predictions and snapshots waiting on a client along with the world origin
+fn rebase_net_states(
+    mut shifts: EventReader<OriginShiftEvent>,
+    mut histories: Query<&mut PredictionHistory>,
+    mut buffers: Query<&mut SnapshotBuffer>,
+) {
+    for shift in shifts.read() {
+        for mut history in histories.iter_mut() {
+            for (_, state) in history.states.iter_mut() {
+                *state = state.rebased(shift);
+            }
+        }
+        for mut buffer in buffers.iter_mut() {
+            for (_, state) in buffer.states.iter_mut() {
+                *state = state.rebased(shift);
+            }
+        }
+    }
+}
+
+/// Plugin for server-authoritative online play with client prediction
+pub struct NetcodePlugin;
+
+impl Plugin for NetcodePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NetcodeSettings>()
+            .init_resource::<ServerInputQueue>()
+            .init_resource::<NetClock>()
+            .init_resource::<WorldOrigin>()
+            .add_event::<SendInputMessage>()
+            .add_event::<ReceivedInputMessage>()
+            .add_event::<SendSnapshot>()
+            .add_event::<ReceivedSnapshot>()
+            .add_event::<OriginShiftEvent>()
+            .add_systems(Update, (
+                apply_netcode_settings,
+                prepare_networked_vehicles.run_if(netcode_active),
+                send_client_inputs
+                    .after(record_player_inputs)
+                    .before(apply_player_input)
+                    .run_if(is_client),
+                apply_client_inputs
+                    .after(read_player_input)
+                    .before(apply_player_input)
+                    .run_if(is_server),
+                (receive_snapshots, smooth_corrections, interpolate_remote_vehicles)
+                    .chain()
+                    .run_if(is_client),
+            ))
+            .add_systems(
+                PostUpdate,
+                (
+                    record_prediction.run_if(is_client),
+                    send_snapshots.run_if(is_server),
+                )
+                    .after(PhysicsSet::Writeback)
+                    // Recorded before a shift this frame, which then rebases them with the rest
+                    .before(ShiftOrigin)
+                    .before(record_state_checksum),
+            )
+            .add_systems(PostUpdate, rebase_net_states.after(ShiftOrigin).run_if(is_client));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::plugins::{FloatingOriginPlugin, FloatingOriginSettings};
+
+    fn state(x: f32) -> NetVehicleState {
+        NetVehicleState::new(1, &Transform::from_xyz(x, 0.0, 0.0), &Velocity::linear(Vec3::X * x))
+    }
+
+    fn input(tick: u64, throttle: i16) -> RecordedInput {
+        let mut input = RecordedInput::new(tick, 0, &PlayerInput::default());
+        input.throttle = throttle;
+        input
+    }
+
+    #[test]
+    fn test_reconcile_ignores_smooths_and_snaps() {
+        let settings = NetcodeSettings::default();
+        assert_eq!(reconcile(&state(1.0), &state(1.01), &settings), Correction::None);
+        let Correction::Smooth(error) = reconcile(&state(1.0), &state(1.5), &settings) else {
+            panic!("expected a smoothed correction");
+        };
+        assert!((error.position.x - 0.5).abs() < 1e-5);
+        assert!(matches!(reconcile(&state(1.0), &state(10.0), &settings), Correction::Snap(_)));
+    }
+
+    #[test]
+    fn test_history_confirms_and_drops_older_ticks() {
+        let mut history = PredictionHistory::default();
+        for tick in 0..5 {
+            history.push(tick, state(tick as f32), 4);
+        }
+        assert_eq!(history.states.len(), 4);
+        assert_eq!(history.confirm(0), None);
+        assert_eq!(history.confirm(2), Some(state(2.0)));
+        assert_eq!(history.states.front().map(|(tick, _)| *tick), Some(3));
+    }
+
+    #[test]
+    fn test_snapshot_buffer_interpolates_and_holds_at_the_ends() {
+        let mut buffer = SnapshotBuffer::default();
+        buffer.push(20, state(2.0));
+        buffer.push(10, state(1.0));
+        buffer.push(20, state(99.0));
+        assert_eq!(buffer.states.len(), 2);
+
+        let middle = buffer.sample(15.0).unwrap();
+        assert!((middle.position[0] - 1.5).abs() < 1e-5);
+        assert_eq!(buffer.sample(5.0).unwrap().position[0], 1.0);
+        assert_eq!(buffer.sample(25.0).unwrap().position[0], 2.0);
+        assert_eq!(SnapshotBuffer::default().sample(0.0), None);
+    }
+
+    #[test]
+    fn test_server_queue_orders_bounds_and_repeats_inputs() {
+        let mut queue = ServerInputQueue::default();
+        let message = InputMessage { peer: 3, inputs: vec![input(9, 1), input(10, 2), input(11, 3), input(80, 4)] };
+        queue.receive(&message, 10, 30);
+
+        assert_eq!(queue.take(3, 10).map(|input| input.throttle), Some(2));
+        assert_eq!(queue.take(3, 11).map(|input| input.throttle), Some(3));
+        // Late input falls back to the last one applied
+        assert_eq!(queue.take(3, 12).map(|input| input.tick), Some(11));
+        assert_eq!(queue.take(4, 12), None);
+    }
+
+    #[test]
+    fn test_states_convert_between_origins() {
+        let server_origin = WorldOrigin::default();
+        let on_wire = state(5.0).to_world(&server_origin);
+
+        // The client's origin has moved since, the state lands where the server had it in the level
+        let mut app = App::new();
+        app.add_plugins(FloatingOriginPlugin);
+        app.world.resource_mut::<FloatingOriginSettings>().threshold = 1.0;
+        let far = Transform::from_xyz(2000.0, 0.0, 0.0);
+        app.world.spawn((PlayerId(0), far, GlobalTransform::from(far)));
+        app.world.run_schedule(PostUpdate);
+        let client_origin = *app.world.resource::<WorldOrigin>();
+        assert_ne!(client_origin, server_origin);
+
+        let local = on_wire.to_local(&client_origin);
+        assert_eq!(client_origin.to_world(local.position()), Vec3::new(5.0, 0.0, 0.0));
+        assert_eq!(local.rotation, on_wire.rotation);
+    }
+
+    #[test]
+    fn test_origin_shift_rebases_buffered_states() {
+        let mut app = App::new();
+        app.add_event::<OriginShiftEvent>().add_systems(Update, rebase_net_states);
+        let mut history = PredictionHistory::default();
+        history.push(1, state(100.0), 4);
+        let mut buffer = SnapshotBuffer::default();
+        buffer.push(1, state(50.0));
+        let entity = app.world.spawn((history, buffer)).id();
+
+        let shift = OriginShiftEvent { chunks: IVec2::new(1, 0) };
+        app.world.send_event(shift);
+        app.update();
+
+        let moved = 100.0 - shift.translation().x;
+        let history = app.world.get::<PredictionHistory>(entity).unwrap();
+        assert!((history.states[0].1.position[0] - moved).abs() < 1e-4);
+        let buffer = app.world.get::<SnapshotBuffer>(entity).unwrap();
+        assert!((buffer.states[0].1.position[0] - (50.0 - shift.translation().x)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_render_tick_trails_the_latest_snapshot() {
+        let settings = NetcodeSettings::default();
+        let mut clock = NetClock::default();
+        assert_eq!(clock.render_tick(&settings), None);
+        clock.latest_tick = Some(100);
+        clock.since_snapshot = 0.05;
+        let expected = 100.0 + 0.05 * 60.0 - 0.1 * 60.0;
+        assert!((clock.render_tick(&settings).unwrap() - expected).abs() < 1e-4);
+        assert_eq!(settings.snapshot_interval(), 3);
+    }
+}
@@ -1,12 +1,13 @@
 use bevy::{
     prelude::*,
     render::{
-        render_resource::{Buffer, BufferUsages, ShaderType},
-        renderer::RenderDevice,
+        render_resource::{Buffer, BufferUsages, CommandEncoderDescriptor, ShaderType},
+        renderer::{RenderDevice, RenderQueue},
     },
 };
 
 use crate::game::plugins::particle_system::particle::Particle;
+use crate::game::plugins::OriginShiftEvent;
 
 /// Manages double buffering for particle data
 #[derive(Component)]
@@ -74,6 +75,11 @@ impl ParticleBufferManager {
         self.max_particles
     }
 
+    /// Total size of the particle and draw command buffers in bytes
+    pub fn size_bytes(&self) -> u64 {
+        self.read_buffer.size() + self.write_buffer.size() + self.draw_commands.size()
+    }
+
     /// Swap the read and write buffers
     pub fn swap_buffers(&mut self) {
         std::mem::swap(&mut self.read_buffer, &mut self.write_buffer);
@@ -84,10 +90,22 @@ impl ParticleBufferManager {
     pub fn frame_index(&self) -> u32 {
         self.frame_index
     }
+
+    /// Clear the particle buffers so a pooled manager can be handed to a new effect
+    /// without its previous particles showing up for a frame
+    pub fn reset(&mut self, device: &RenderDevice, queue: &RenderQueue) {
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("particle_buffer_reset"),
+        });
+        encoder.clear_buffer(&self.read_buffer, 0, None);
+        encoder.clear_buffer(&self.write_buffer, 0, None);
+        queue.submit([encoder.finish()]);
+        self.frame_index = 0;
+    }
 }
 
-/// Parameters for the particle simulation compute shader
-#[derive(Clone, ShaderType)]
+/// Parameters for the particle simulation compute shader, one per emitter
+#[derive(Component, Clone, ShaderType)]
 pub struct SimulationParams {
     /// Time since last frame
     pub delta_time: f32,
@@ -123,8 +141,9 @@ pub struct SimulationParams {
     pub active_particles: u32,
     /// Maximum number of particles
     pub max_particles: u32,
-    /// Padding to ensure 16-byte alignment
-    pub _padding: Vec2,
+    /// How far the world origin moved this frame on XZ, taken off every live particle so they
+    /// stay put in the level. Zero on every other frame.
+    pub origin_shift: Vec2,
 }
 
 impl Default for SimulationParams {
@@ -147,7 +166,43 @@ impl Default for SimulationParams {
             anim_fps: 30.0,
             active_particles: 0,
             max_particles: 10000,
-            _padding: Vec2::ZERO,
+            origin_shift: Vec2::ZERO,
+        }
+    }
+}
+
+/// Hands the origin shift to every emitter's simulation for the one frame it happened in, so live
+/// particles move with the world rather than jumping a chunk. Runs after the shift, the next
+/// simulation step takes it off and the one after that gets zero again.
+pub fn apply_particle_origin_shift(
+    mut shifts: EventReader<OriginShiftEvent>,
+    mut emitters: Query<&mut SimulationParams>,
+) {
+    let shift = shifts.read().map(OriginShiftEvent::translation).sum::<Vec3>().xz();
+    for mut params in emitters.iter_mut() {
+        // Only frames that start or end a shift touch the params
+        if params.origin_shift != shift {
+            params.origin_shift = shift;
         }
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_origin_shift_lasts_one_frame() {
+        let mut app = App::new();
+        app.add_event::<OriginShiftEvent>().add_systems(Update, apply_particle_origin_shift);
+        let emitter = app.world.spawn(SimulationParams::default()).id();
+
+        app.world.send_event(OriginShiftEvent { chunks: IVec2::new(1, -2) });
+        app.update();
+        let expected = OriginShiftEvent { chunks: IVec2::new(1, -2) }.translation().xz();
+        assert_eq!(app.world.get::<SimulationParams>(emitter).unwrap().origin_shift, expected);
+
+        app.update();
+        assert_eq!(app.world.get::<SimulationParams>(emitter).unwrap().origin_shift, Vec2::ZERO);
+    }
+}
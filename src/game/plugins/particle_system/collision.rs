@@ -7,7 +7,13 @@ use bevy::{
 };
 use bytemuck::{Pod, Zeroable};
 
-use super::ParticleSystem;
+use crate::terrain::{TerrainChunk, TerrainQuery, CHUNK_SIZE};
+
+/// Heightfield samples along each side of a terrain chunk
+const HEIGHTFIELD_SAMPLES_PER_CHUNK: u32 = 16;
+
+/// Height of samples where no chunk is loaded, far below anything a particle reaches
+const NO_GROUND: f32 = -1.0e4;
 
 #[repr(C)]
 #[derive(Copy, Clone, Pod, Zeroable)]
@@ -300,4 +306,264 @@ pub fn spawn_collision_box(
             },
         ))
         .id()
-} 
\ No newline at end of file
+} 
+/// Which scene representation a particle effect collides against
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ParticleCollisionMode {
+    /// Particles pass through the scene
+    #[default]
+    None,
+    /// Screen-space collision against the camera depth buffer.
+    /// Cheap and precise for camera-facing effects, but misses geometry that is off screen.
+    Depth,
+    /// Collision against the coarse terrain heightfield, works for world-space effects anywhere
+    Terrain,
+    /// Depth buffer first, terrain heightfield when the particle is off screen
+    DepthAndTerrain,
+}
+
+/// What happens to a particle when it hits the scene
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ParticleCollisionResponse {
+    /// Reflect the velocity using restitution and friction
+    #[default]
+    Bounce,
+    /// End the particle's life on contact (rain hitting the ground)
+    Kill,
+    /// Stop the particle on the surface (mud sticking to rocks)
+    Stick,
+}
+
+/// Scene collision settings for a particle material
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParticleCollisionSettings {
+    pub mode: ParticleCollisionMode,
+    pub response: ParticleCollisionResponse,
+    /// Fraction of the normal velocity kept after a bounce (0.0 - 1.0)
+    pub restitution: f32,
+    /// Fraction of the tangential velocity removed on contact (0.0 - 1.0)
+    pub friction: f32,
+    /// Particle collision radius in meters
+    pub radius: f32,
+    /// Depth behind the depth buffer surface that still counts as a hit, in meters.
+    /// Anything further behind is assumed to be occluded rather than colliding.
+    pub depth_thickness: f32,
+}
+
+impl Default for ParticleCollisionSettings {
+    fn default() -> Self {
+        Self {
+            mode: ParticleCollisionMode::None,
+            response: ParticleCollisionResponse::Bounce,
+            restitution: 0.3,
+            friction: 0.5,
+            radius: 0.05,
+            depth_thickness: 0.5,
+        }
+    }
+}
+
+impl ParticleCollisionSettings {
+    /// Dust settles on the terrain with a small skid
+    pub fn dust() -> Self {
+        Self {
+            mode: ParticleCollisionMode::Terrain,
+            response: ParticleCollisionResponse::Bounce,
+            restitution: 0.05,
+            friction: 0.8,
+            radius: 0.1,
+            ..Default::default()
+        }
+    }
+
+    /// Mud clumps bounce off nearby geometry and stick to the ground
+    pub fn mud() -> Self {
+        Self {
+            mode: ParticleCollisionMode::DepthAndTerrain,
+            response: ParticleCollisionResponse::Stick,
+            restitution: 0.0,
+            friction: 1.0,
+            radius: 0.05,
+            ..Default::default()
+        }
+    }
+
+    /// Rain splashes die where they land
+    pub fn rain_splash() -> Self {
+        Self {
+            mode: ParticleCollisionMode::Depth,
+            response: ParticleCollisionResponse::Kill,
+            radius: 0.01,
+            depth_thickness: 0.25,
+            ..Default::default()
+        }
+    }
+
+    /// Applies the collision response to a velocity hitting a surface with `normal`.
+    /// Returns `None` when the particle should be killed.
+    /// Mirrors `apply_response` in `particle_scene_collision.wgsl`.
+    pub fn respond(&self, velocity: Vec3, normal: Vec3) -> Option<Vec3> {
+        let normal_speed = velocity.dot(normal);
+        match self.response {
+            ParticleCollisionResponse::Kill => None,
+            ParticleCollisionResponse::Stick => Some(Vec3::ZERO),
+            // Already moving away from the surface
+            ParticleCollisionResponse::Bounce if normal_speed >= 0.0 => Some(velocity),
+            ParticleCollisionResponse::Bounce => {
+                let normal_velocity = normal * normal_speed;
+                let tangent_velocity = velocity - normal_velocity;
+                Some(tangent_velocity * (1.0 - self.friction) - normal_velocity * self.restitution)
+            }
+        }
+    }
+}
+
+/// Coarse terrain heights for world-space particle collision, uploaded as an R32Float texture
+#[derive(Resource, Clone, Debug)]
+pub struct ParticleTerrainHeightfield {
+    /// World-space XZ of the first sample
+    pub origin: Vec2,
+    /// World-space XZ extent covered by the samples
+    pub size: Vec2,
+    /// Number of samples along X and Z
+    pub resolution: UVec2,
+    /// Row-major heights, Z rows of X samples
+    pub heights: Vec<f32>,
+}
+
+impl ParticleTerrainHeightfield {
+    /// Samples `height` on a regular grid over the given area
+    pub fn from_fn(origin: Vec2, size: Vec2, resolution: UVec2, height: impl Fn(f32, f32) -> f32) -> Self {
+        let resolution = resolution.max(UVec2::splat(2));
+        let step = size / (resolution - 1).as_vec2();
+        let mut heights = Vec::with_capacity((resolution.x * resolution.y) as usize);
+        for z in 0..resolution.y {
+            for x in 0..resolution.x {
+                let position = origin + step * Vec2::new(x as f32, z as f32);
+                heights.push(height(position.x, position.y));
+            }
+        }
+        Self { origin, size, resolution, heights }
+    }
+
+    fn sample(&self, x: u32, z: u32) -> f32 {
+        let x = x.min(self.resolution.x - 1);
+        let z = z.min(self.resolution.y - 1);
+        self.heights[(z * self.resolution.x + x) as usize]
+    }
+
+    /// Bilinear height at a world XZ position, `None` outside the covered area
+    pub fn height_at(&self, position: Vec2) -> Option<f32> {
+        let uv = (position - self.origin) / self.size;
+        if uv.cmplt(Vec2::ZERO).any() || uv.cmpgt(Vec2::ONE).any() {
+            return None;
+        }
+        let texel = uv * (self.resolution - 1).as_vec2();
+        let base = texel.floor();
+        let t = texel - base;
+        let (x, z) = (base.x as u32, base.y as u32);
+        let top = self.sample(x, z) + (self.sample(x + 1, z) - self.sample(x, z)) * t.x;
+        let bottom = self.sample(x, z + 1) + (self.sample(x + 1, z + 1) - self.sample(x, z + 1)) * t.x;
+        Some(top + (bottom - top) * t.y)
+    }
+}
+
+/// Resamples [`ParticleTerrainHeightfield`] over the loaded terrain chunks whenever one is loaded,
+/// unloaded or moved by an origin shift, and drops it while no terrain is loaded
+pub fn update_particle_heightfield(
+    mut commands: Commands,
+    terrain: Option<Res<TerrainQuery>>,
+    chunks: Query<&TerrainChunk>,
+    changed: Query<(), Changed<TerrainChunk>>,
+    mut removed: RemovedComponents<TerrainChunk>,
+) {
+    let unloaded = removed.read().count() > 0;
+    if changed.is_empty() && !unloaded {
+        return;
+    }
+    let Some(terrain) = terrain else {
+        return;
+    };
+    let Some((min, max)) = chunks.iter().fold(None, |bounds: Option<(IVec2, IVec2)>, chunk| {
+        Some(bounds.map_or((chunk.coord, chunk.coord), |(min, max)| (min.min(chunk.coord), max.max(chunk.coord))))
+    }) else {
+        commands.remove_resource::<ParticleTerrainHeightfield>();
+        return;
+    };
+
+    let chunks_across = (max - min + IVec2::ONE).as_uvec2();
+    let origin = min.as_vec2() * CHUNK_SIZE - Vec2::splat(CHUNK_SIZE * 0.5);
+    let size = chunks_across.as_vec2() * CHUNK_SIZE;
+    // The far edge belongs to the next chunk over, sample just inside it instead
+    let last = origin + size - Vec2::splat(1.0e-3);
+    let heightfield = ParticleTerrainHeightfield::from_fn(
+        origin,
+        size,
+        chunks_across * HEIGHTFIELD_SAMPLES_PER_CHUNK + UVec2::ONE,
+        |x, z| terrain.height(x.min(last.x), z.min(last.y)).unwrap_or(NO_GROUND),
+    );
+    commands.insert_resource(heightfield);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::ChunkHeights;
+
+    #[test]
+    fn test_bounce_reflects_and_damps() {
+        let settings = ParticleCollisionSettings {
+            restitution: 0.5,
+            friction: 0.0,
+            ..Default::default()
+        };
+        let velocity = settings.respond(Vec3::new(1.0, -2.0, 0.0), Vec3::Y).unwrap();
+        assert!((velocity - Vec3::new(1.0, 1.0, 0.0)).length() < 1e-5);
+
+        // Moving away from the surface is left alone
+        let leaving = Vec3::new(0.0, 3.0, 0.0);
+        assert_eq!(settings.respond(leaving, Vec3::Y), Some(leaving));
+    }
+
+    #[test]
+    fn test_kill_and_stick_responses() {
+        assert_eq!(ParticleCollisionSettings::rain_splash().respond(Vec3::NEG_Y, Vec3::Y), None);
+        assert_eq!(ParticleCollisionSettings::mud().respond(Vec3::NEG_Y, Vec3::Y), Some(Vec3::ZERO));
+    }
+
+    #[test]
+    fn test_heightfield_sampling() {
+        let heightfield = ParticleTerrainHeightfield::from_fn(
+            Vec2::ZERO,
+            Vec2::splat(10.0),
+            UVec2::splat(11),
+            |x, z| x + z,
+        );
+        assert!((heightfield.height_at(Vec2::new(2.5, 4.0)).unwrap() - 6.5).abs() < 1e-4);
+        assert_eq!(heightfield.height_at(Vec2::new(-1.0, 5.0)), None);
+    }
+
+    #[test]
+    fn test_heightfield_follows_loaded_chunks() {
+        let mut app = App::new();
+        app.add_systems(Update, update_particle_heightfield);
+        let mut terrain = TerrainQuery::default();
+        terrain.insert_chunk(ChunkHeights::from_fn(IVec2::ZERO, 8, |x, _| x * 0.1));
+        app.insert_resource(terrain);
+
+        app.update();
+        assert!(app.world.get_resource::<ParticleTerrainHeightfield>().is_none());
+
+        let chunk = app.world.spawn(TerrainChunk { coord: IVec2::ZERO }).id();
+        app.update();
+        let heightfield = app.world.resource::<ParticleTerrainHeightfield>();
+        assert_eq!(heightfield.origin, Vec2::splat(-CHUNK_SIZE * 0.5));
+        assert!((heightfield.height_at(Vec2::new(20.0, 5.0)).unwrap() - 2.0).abs() < 1e-3);
+        // The edge samples the chunk rather than the unloaded ground past it
+        assert!(heightfield.height_at(Vec2::splat(CHUNK_SIZE * 0.5)).unwrap() > 0.0);
+
+        app.world.despawn(chunk);
+        app.update();
+        assert!(app.world.get_resource::<ParticleTerrainHeightfield>().is_none());
+    }
+}
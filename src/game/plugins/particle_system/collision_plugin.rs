@@ -1,27 +1,33 @@
 use bevy::prelude::*;
 use bevy::render::renderer::RenderDevice;
 
-use super::collision::{ParticleCollisionBuffers, update_collision_objects};
+use super::collision::{ParticleCollisionBuffers, update_collision_objects, update_particle_heightfield};
+use super::scene_collision::SceneCollisionPlugin;
 
 pub struct ParticleCollisionPlugin;
 
 impl Plugin for ParticleCollisionPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, update_collision_objects);
-
-        // Initialize collision buffers when render device is available
-        app.add_systems(Startup, |render_device: Res<RenderDevice>| {
-            let collision_buffers = ParticleCollisionBuffers::new(
-                &render_device,
-                32,  // max_planes
-                64,  // max_spheres
-                32,  // max_boxes
-            );
-            app.world.insert_resource(collision_buffers);
-        });
+        app.add_plugins(SceneCollisionPlugin)
+            .add_systems(Startup, init_collision_buffers)
+            .add_systems(Update, (
+                update_collision_objects.run_if(resource_exists::<ParticleCollisionBuffers>()),
+                update_particle_heightfield,
+            ));
     }
 }
 
+/// Initialize collision buffers once the render device is available
+fn init_collision_buffers(mut commands: Commands, render_device: Res<RenderDevice>) {
+    let collision_buffers = ParticleCollisionBuffers::new(
+        &render_device,
+        32,  // max_planes
+        64,  // max_spheres
+        32,  // max_boxes
+    );
+    commands.insert_resource(collision_buffers);
+}
+
 // Re-export commonly used types
 pub use super::collision::{
     CollisionBoxComponent,
@@ -30,4 +36,8 @@ pub use super::collision::{
     spawn_collision_box,
     spawn_collision_plane,
     spawn_collision_sphere,
+    ParticleCollisionMode,
+    ParticleCollisionResponse,
+    ParticleCollisionSettings,
+    ParticleTerrainHeightfield,
 }; 
\ No newline at end of file
@@ -14,6 +14,7 @@ use super::{
     buffer::ParticleBufferManager,
     particle::{ParticleSystem, SimulationParams},
 };
+use crate::game::{GpuPass, GpuTimer};
 
 /// Resource for managing the particle compute pipeline
 #[derive(Resource)]
@@ -86,8 +87,9 @@ pub fn dispatch_particle_compute(
     mut particles: Query<(&mut ParticleSystem, &mut ParticleBufferManager)>,
     compute_pipeline: Res<ParticleComputePipeline>,
     render_device: Res<RenderDevice>,
+    gpu_timer: Option<Res<GpuTimer>>,
 ) {
-    for (particle_system, mut buffer_manager) in particles.iter_mut() {
+    for (index, (particle_system, mut buffer_manager)) in particles.iter_mut().enumerate() {
         // Create bind group for compute shader
         let bind_group = render_device.create_bind_group(&BindGroupDescriptor {
             label: Some("particle_compute_bind_group"),
@@ -110,6 +112,12 @@ pub fn dispatch_particle_compute(
 
         // Create compute pass
         let mut compute_pass = render_device.create_command_encoder("particle_compute_pass");
+        // Timed from the first system's dispatch to the last one's, each later end replaces the previous
+        if let Some(timer) = gpu_timer.as_deref() {
+            if index == 0 {
+                timer.begin(&mut compute_pass, GpuPass::ParticleCompute);
+            }
+        }
         {
             let mut pass = compute_pass.begin_compute_pass();
             pass.set_pipeline(&compute_pipeline.pipeline);
@@ -119,6 +127,9 @@ pub fn dispatch_particle_compute(
             let workgroup_count = (particle_system.particle_count + 63) / 64;
             pass.dispatch_workgroups(workgroup_count, 1, 1);
         }
+        if let Some(timer) = gpu_timer.as_deref() {
+            timer.end(&mut compute_pass, GpuPass::ParticleCompute);
+        }
 
         // Submit compute pass
         render_device.queue().submit(Some(compute_pass.finish()));
@@ -0,0 +1,405 @@
+use bevy::{
+    ecs::system::Command,
+    prelude::*,
+    render::renderer::{RenderDevice, RenderQueue},
+    utils::HashMap,
+};
+
+use crate::game::plugins::particle_system::{buffer::ParticleBufferManager, particle::ParticleSystem};
+use crate::game::plugins::relevance::{track_relevance, Relevance};
+use crate::utils::EntityPool;
+
+/// Identifies a kind of effect ("crash_sparks", "mud_splash", ...) for buffer pooling
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParticleEffectType(pub &'static str);
+
+/// Playback state of a particle effect
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParticleEffectState {
+    /// Emitting at the full spawn rate
+    Playing,
+    /// Spawn rate ramping down to zero
+    FadingOut { remaining: f32, duration: f32 },
+    /// No longer emitting, waiting for live particles to expire
+    Stopping { remaining: f32 },
+    /// Not emitting and no particles left alive
+    Finished,
+}
+
+/// Lifecycle of a pooled particle effect.
+/// Added by [`spawn_pooled_effect`], driven by [`ParticleEffectHandle`].
+#[derive(Component, Debug, Clone)]
+pub struct ParticleEffectLifecycle {
+    pub effect_type: ParticleEffectType,
+    pub state: ParticleEffectState,
+    /// Spawn rate the effect was authored with
+    pub base_spawn_rate: f32,
+    /// Longest particle lifetime, used to know when the last particle is gone
+    pub max_lifetime: f32,
+    /// Despawn the entity and recycle its buffers once finished
+    pub despawn_when_finished: bool,
+    /// Stop emitting after this many seconds, for one-shot effects like crash sparks
+    pub duration: Option<f32>,
+    elapsed: f32,
+}
+
+impl ParticleEffectLifecycle {
+    pub fn new(effect_type: ParticleEffectType, base_spawn_rate: f32, max_lifetime: f32) -> Self {
+        Self {
+            effect_type,
+            state: ParticleEffectState::Playing,
+            base_spawn_rate,
+            max_lifetime,
+            despawn_when_finished: true,
+            duration: None,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Stops emitting after `seconds`, then soft shuts down
+    pub fn with_duration(mut self, seconds: f32) -> Self {
+        self.duration = Some(seconds);
+        self
+    }
+
+    /// Keeps the entity around when finished so it can be replayed
+    pub fn keep_alive(mut self) -> Self {
+        self.despawn_when_finished = false;
+        self
+    }
+
+    /// Restarts emission at the full spawn rate
+    pub fn play(&mut self) {
+        self.state = ParticleEffectState::Playing;
+        self.elapsed = 0.0;
+    }
+
+    /// Stops emitting immediately; live particles finish their lifetime
+    pub fn stop(&mut self) {
+        if !matches!(self.state, ParticleEffectState::Stopping { .. } | ParticleEffectState::Finished) {
+            self.state = ParticleEffectState::Stopping { remaining: self.max_lifetime };
+        }
+    }
+
+    /// Ramps the spawn rate down to zero over `duration` seconds before stopping
+    pub fn fade_out(&mut self, duration: f32) {
+        match self.state {
+            ParticleEffectState::Playing if duration > 0.0 => {
+                self.state = ParticleEffectState::FadingOut { remaining: duration, duration };
+            }
+            ParticleEffectState::Playing => self.stop(),
+            _ => {}
+        }
+    }
+
+    /// Fraction of the base spawn rate to emit at
+    pub fn spawn_scale(&self) -> f32 {
+        match self.state {
+            ParticleEffectState::Playing => 1.0,
+            ParticleEffectState::FadingOut { remaining, duration } => (remaining / duration).clamp(0.0, 1.0),
+            ParticleEffectState::Stopping { .. } | ParticleEffectState::Finished => 0.0,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state == ParticleEffectState::Finished
+    }
+
+    /// Advances the state machine by `dt` seconds
+    pub fn advance(&mut self, dt: f32) {
+        self.state = match self.state {
+            ParticleEffectState::Playing => {
+                self.elapsed += dt;
+                match self.duration {
+                    Some(duration) if self.elapsed >= duration => {
+                        ParticleEffectState::Stopping { remaining: self.max_lifetime }
+                    }
+                    _ => ParticleEffectState::Playing,
+                }
+            }
+            ParticleEffectState::FadingOut { remaining, duration } => {
+                let remaining = remaining - dt;
+                if remaining <= 0.0 {
+                    ParticleEffectState::Stopping { remaining: self.max_lifetime }
+                } else {
+                    ParticleEffectState::FadingOut { remaining, duration }
+                }
+            }
+            ParticleEffectState::Stopping { remaining } => {
+                let remaining = remaining - dt;
+                if remaining <= 0.0 {
+                    ParticleEffectState::Finished
+                } else {
+                    ParticleEffectState::Stopping { remaining }
+                }
+            }
+            ParticleEffectState::Finished => ParticleEffectState::Finished,
+        };
+    }
+}
+
+/// Lightweight handle to a spawned effect, safe to keep after the effect finishes. Finished effects
+/// are recycled, so the entity may later carry another effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParticleEffectHandle(pub Entity);
+
+impl ParticleEffectHandle {
+    pub fn entity(&self) -> Entity {
+        self.0
+    }
+
+    /// Restarts the effect if it still exists
+    pub fn play(&self, commands: &mut Commands) {
+        commands.add(ControlParticleEffect { entity: self.0, action: EffectAction::Play });
+    }
+
+    /// Stops emitting; particles already alive finish their lifetime
+    pub fn stop(&self, commands: &mut Commands) {
+        commands.add(ControlParticleEffect { entity: self.0, action: EffectAction::Stop });
+    }
+
+    /// Ramps emission down over `duration` seconds, then stops
+    pub fn fade_out(&self, commands: &mut Commands, duration: f32) {
+        commands.add(ControlParticleEffect { entity: self.0, action: EffectAction::FadeOut(duration) });
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum EffectAction {
+    Play,
+    Stop,
+    FadeOut(f32),
+}
+
+struct ControlParticleEffect {
+    entity: Entity,
+    action: EffectAction,
+}
+
+impl Command for ControlParticleEffect {
+    fn apply(self, world: &mut World) {
+        // The effect may already have finished and been recycled
+        let Some(mut lifecycle) = world.get_mut::<ParticleEffectLifecycle>(self.entity) else {
+            return;
+        };
+        match self.action {
+            EffectAction::Play => lifecycle.play(),
+            EffectAction::Stop => lifecycle.stop(),
+            EffectAction::FadeOut(duration) => lifecycle.fade_out(duration),
+        }
+    }
+}
+
+/// Marks the recycled entities pooled effects are spawned on
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct PooledParticleEffect;
+
+/// Recycled GPU particle buffers, keyed by effect type so a burst of identical effects
+/// during a crash reuses allocations instead of creating new buffers mid-frame
+#[derive(Resource)]
+pub struct ParticleBufferPool {
+    free: HashMap<ParticleEffectType, Vec<ParticleBufferManager>>,
+    /// Buffers kept per effect type; extras are dropped on release
+    pub max_pooled_per_type: usize,
+    /// Entities the effects live on, recycled along with their buffers
+    pub entities: EntityPool<PooledParticleEffect>,
+}
+
+impl Default for ParticleBufferPool {
+    fn default() -> Self {
+        Self {
+            free: HashMap::default(),
+            max_pooled_per_type: 16,
+            entities: EntityPool::new(8, 32),
+        }
+    }
+}
+
+impl ParticleBufferPool {
+    /// Takes a pooled buffer for `effect_type` that fits `max_particles`, or allocates one
+    pub fn acquire(
+        &mut self,
+        device: &RenderDevice,
+        queue: &RenderQueue,
+        effect_type: ParticleEffectType,
+        max_particles: u32,
+    ) -> ParticleBufferManager {
+        if let Some(free) = self.free.get_mut(&effect_type) {
+            if let Some(index) = free.iter().position(|buffers| buffers.max_particles() >= max_particles) {
+                let mut buffers = free.swap_remove(index);
+                buffers.reset(device, queue);
+                return buffers;
+            }
+        }
+        ParticleBufferManager::new(device, max_particles)
+    }
+
+    /// Returns buffers to the pool
+    pub fn release(&mut self, effect_type: ParticleEffectType, buffers: ParticleBufferManager) {
+        let free = self.free.entry(effect_type).or_default();
+        if free.len() < self.max_pooled_per_type {
+            free.push(buffers);
+        }
+    }
+
+    /// Allocates buffers ahead of time, e.g. during level load
+    pub fn prewarm(&mut self, device: &RenderDevice, effect_type: ParticleEffectType, count: usize, max_particles: u32) {
+        let free = self.free.entry(effect_type).or_default();
+        let target = count.min(self.max_pooled_per_type);
+        while free.len() < target {
+            free.push(ParticleBufferManager::new(device, max_particles));
+        }
+    }
+
+    /// Number of idle buffers pooled for `effect_type`
+    pub fn available(&self, effect_type: ParticleEffectType) -> usize {
+        self.free.get(&effect_type).map_or(0, Vec::len)
+    }
+}
+
+/// Spawns an effect with pooled buffers and a lifecycle, returning a handle to control it
+pub fn spawn_pooled_effect(
+    commands: &mut Commands,
+    pool: &mut ParticleBufferPool,
+    device: &RenderDevice,
+    queue: &RenderQueue,
+    lifecycle: ParticleEffectLifecycle,
+    max_particles: u32,
+    bundle: impl Bundle,
+) -> ParticleEffectHandle {
+    let buffers = pool.acquire(device, queue, lifecycle.effect_type, max_particles);
+    let entity = pool.entities.acquire(commands);
+    // Recycled entities are hidden, the bundle may bring its own visibility
+    commands.entity(entity).insert(Visibility::Inherited).insert((bundle, buffers, lifecycle));
+    ParticleEffectHandle(entity)
+}
+
+/// Returns an effect's buffers and entity to the pools, or despawns the entity when the pool is full
+struct RecycleParticleEffect {
+    entity: Entity,
+    effect_type: ParticleEffectType,
+}
+
+impl Command for RecycleParticleEffect {
+    fn apply(self, world: &mut World) {
+        let Some(mut entity) = world.get_entity_mut(self.entity) else {
+            return;
+        };
+        let buffers = entity.take::<ParticleBufferManager>();
+        // Without a lifecycle an idle effect isn't advanced or recycled again
+        entity.remove::<ParticleEffectLifecycle>();
+
+        let mut pool = world.resource_mut::<ParticleBufferPool>();
+        if let Some(buffers) = buffers {
+            pool.release(self.effect_type, buffers);
+        }
+        let pooled = pool.entities.release(self.entity);
+        let mut entity = world.entity_mut(self.entity);
+        if pooled {
+            entity.despawn_descendants().insert(Visibility::Hidden);
+        } else {
+            entity.despawn_recursive();
+        }
+    }
+}
+
+/// Advances effect lifecycles, scales emission and recycles finished effects
+pub fn update_particle_lifecycles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut effects: Query<(Entity, &mut ParticleEffectLifecycle, &mut ParticleSystem, Option<&Relevance>)>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut lifecycle, mut system, relevance) in effects.iter_mut() {
+        lifecycle.advance(dt);
+        // Far away and off screen effects emit less, culled ones not at all
+        let relevance_scale = relevance.map_or(1.0, |relevance| relevance.visual_bucket().particle_spawn_scale());
+        system.params.spawn_rate = lifecycle.base_spawn_rate * lifecycle.spawn_scale() * relevance_scale;
+
+        if lifecycle.is_finished() && lifecycle.despawn_when_finished {
+            commands.add(RecycleParticleEffect {
+                entity,
+                effect_type: lifecycle.effect_type,
+            });
+        }
+    }
+}
+
+fn warm_effect_entities(mut commands: Commands, mut pool: ResMut<ParticleBufferPool>) {
+    let warm_up = pool.entities.warm_up;
+    pool.entities.warm(&mut commands, warm_up);
+}
+
+/// Plugin for effect playback control and GPU buffer pooling
+pub struct ParticleLifecyclePlugin;
+
+impl Plugin for ParticleLifecyclePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ParticleBufferPool>()
+            .add_systems(Startup, warm_effect_entities)
+            .add_systems(Update, (
+                track_relevance::<ParticleEffectLifecycle>,
+                update_particle_lifecycles,
+            ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPARKS: ParticleEffectType = ParticleEffectType("sparks");
+
+    #[test]
+    fn test_fade_out_ramps_spawn_rate() {
+        let mut lifecycle = ParticleEffectLifecycle::new(SPARKS, 100.0, 1.0);
+        lifecycle.fade_out(2.0);
+        assert_eq!(lifecycle.spawn_scale(), 1.0);
+
+        lifecycle.advance(1.0);
+        assert!((lifecycle.spawn_scale() - 0.5).abs() < 1e-5);
+
+        lifecycle.advance(1.0);
+        assert_eq!(lifecycle.spawn_scale(), 0.0);
+        assert!(matches!(lifecycle.state, ParticleEffectState::Stopping { .. }));
+    }
+
+    #[test]
+    fn test_stop_waits_for_live_particles() {
+        let mut lifecycle = ParticleEffectLifecycle::new(SPARKS, 100.0, 1.5);
+        lifecycle.stop();
+        lifecycle.advance(1.0);
+        assert!(!lifecycle.is_finished());
+        lifecycle.advance(0.6);
+        assert!(lifecycle.is_finished());
+    }
+
+    #[test]
+    fn test_one_shot_duration() {
+        let mut lifecycle = ParticleEffectLifecycle::new(SPARKS, 100.0, 0.5).with_duration(0.25);
+        lifecycle.advance(0.3);
+        assert_eq!(lifecycle.spawn_scale(), 0.0);
+        lifecycle.advance(0.5);
+        assert!(lifecycle.is_finished());
+    }
+
+    #[test]
+    fn test_play_restarts() {
+        let mut lifecycle = ParticleEffectLifecycle::new(SPARKS, 100.0, 0.5).keep_alive();
+        lifecycle.stop();
+        lifecycle.advance(1.0);
+        assert!(lifecycle.is_finished());
+
+        lifecycle.play();
+        assert_eq!(lifecycle.state, ParticleEffectState::Playing);
+        assert_eq!(lifecycle.spawn_scale(), 1.0);
+    }
+
+    #[test]
+    fn test_fade_after_stop_is_ignored() {
+        let mut lifecycle = ParticleEffectLifecycle::new(SPARKS, 100.0, 1.0);
+        lifecycle.stop();
+        lifecycle.fade_out(3.0);
+        assert!(matches!(lifecycle.state, ParticleEffectState::Stopping { .. }));
+    }
+}
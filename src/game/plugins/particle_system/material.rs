@@ -91,6 +91,7 @@ use bevy::{
 
 use super::{
     buffer::ParticleBufferManager,
+    collision::ParticleCollisionSettings,
     particle::ParticleSystem,
 };
 
@@ -219,6 +220,8 @@ pub struct ParticleMaterial {
     pub lod_bias: f32,
     /// Quality level
     pub quality_level: f32,
+    /// Collision against the depth buffer and terrain
+    pub collision: ParticleCollisionSettings,
     /// GPU buffer for material parameters
     #[cfg(not(test))]
     pub(crate) params_buffer: Option<Buffer>,
@@ -658,11 +661,18 @@ impl ParticleMaterial {
             max_visible_particles: 1000.0,
             lod_bias: 1.0,
             quality_level: 1.0,
+            collision: ParticleCollisionSettings::default(),
             params_buffer: None,
             bind_group: None,
         }
     }
 
+    /// Set how particles collide with the depth buffer and terrain
+    pub fn with_collision(mut self, collision: ParticleCollisionSettings) -> Self {
+        self.collision = collision;
+        self
+    }
+
     /// Set the texture atlas dimensions
     pub fn with_atlas(mut self, size: UVec2, frames: u32) -> Self {
         self.atlas_size = size;
@@ -996,6 +1006,23 @@ impl ParticleMaterial {
             })
     }
 
+    /// Create a preset for heat haze: nearly invisible particles that only bend what's behind them
+    pub fn preset_heat_haze(texture: Handle<Image>) -> Self {
+        Self::new(texture)
+            .with_blend_mode(BlendMode::Alpha)
+            .with_color_tint(Vec4::new(1.0, 1.0, 1.0, 0.04))  // Barely tints the scene
+            .with_emission(0.0)
+            .with_soft_particles(true, 0.5)
+            .with_noise(6.0, 1.5)               // Fast shimmer
+            .with_distortion(0.0)               // Driven by engine heat
+            .with_lod_settings(LodSettings {
+                fade_start: 10.0,
+                fade_end: 30.0,
+                min_size: 0.5,
+                auto_lod: true,
+            })
+    }
+
     /// Create a preset for magic/spell effects
     pub fn preset_magic(texture: Handle<Image>) -> Self {
         Self::new(texture)
@@ -1036,6 +1063,23 @@ impl ParticleMaterial {
                 min_size: 0.5,
                 auto_lod: true,
             })
+            .with_collision(ParticleCollisionSettings::dust())
+    }
+
+    /// Create a preset for mud clumps thrown off tyres
+    pub fn preset_mud(texture: Handle<Image>) -> Self {
+        Self::new(texture)
+            .with_blend_mode(BlendMode::Alpha)
+            .with_color_tint(Vec4::new(0.22, 0.16, 0.1, 1.0))  // Wet brown
+            .with_emission(0.0)
+            .with_soft_particles(false, 0.0)
+            .with_lod_settings(LodSettings {
+                fade_start: 20.0,
+                fade_end: 50.0,
+                min_size: 0.3,
+                auto_lod: true,
+            })
+            .with_collision(ParticleCollisionSettings::mud())
     }
 
     /// Create a preset for water/splash effects
@@ -1057,6 +1101,7 @@ impl ParticleMaterial {
                 min_size: 0.4,
                 auto_lod: true,
             })
+            .with_collision(ParticleCollisionSettings::rain_splash())
     }
 
     /// Create a preset for an energy beam effect with color gradient
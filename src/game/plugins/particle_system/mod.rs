@@ -1,6 +1,9 @@
 pub mod buffer;
+pub mod collision;
+pub mod collision_plugin;
 pub mod compute;
 pub mod emitter;
+pub mod lifecycle;
 pub mod material;
 pub mod particle;
 pub mod plugin;
@@ -14,6 +17,7 @@ mod gradient;
 mod special_effects;
 mod basic_particle;
 mod examples;
+mod scene_collision;
 
 mod prelude {
     pub use super::buffer::*;
@@ -27,11 +31,17 @@ mod prelude {
 
 pub use prelude::*;
 pub use animation::{AtlasAnimation, ParticleAnimationPlugin};
+pub use collision::{ParticleCollisionMode, ParticleCollisionResponse, ParticleCollisionSettings, ParticleTerrainHeightfield};
+pub use collision_plugin::ParticleCollisionPlugin;
 pub use compute::ParticleComputePipeline;
+pub use lifecycle::{
+    spawn_pooled_effect, ParticleBufferPool, ParticleEffectHandle, ParticleEffectLifecycle, ParticleEffectState,
+    ParticleEffectType, ParticleLifecyclePlugin, PooledParticleEffect,
+};
 pub use emitter::{BoxEmitter, PointEmitter, SphereEmitter};
 pub use material::{BlendMode, ParticleMaterial};
 pub use particle::{ParticleSystem, SimulationParams};
-pub use presets::{ParticlePresets, spawn_example_effects};
+pub use presets::{ParticlePresets, PresetConfig, spawn_example_effects};
 pub use texture_gen::ParticleTextureGenPlugin;
 pub use gradient::*;
 pub use special_effects::*;
@@ -45,6 +55,8 @@ pub use examples::basic_particles::BasicParticleExamplePlugin;
 
 use bevy::prelude::*;
 
+use super::{OriginShiftEvent, ShiftOrigin};
+
 /// Plugin that sets up the particle system
 pub struct ParticleSystemPlugin;
 
@@ -56,6 +68,8 @@ impl Plugin for ParticleSystemPlugin {
                 ParticleAnimationPlugin,
                 ParticleTextureGenPlugin,
                 material::ParticleMaterialPlugin,
+                ParticleCollisionPlugin,
+                ParticleLifecyclePlugin,
             ))
             // Add our resources
             .init_resource::<ParticleComputePipeline>()
@@ -64,6 +78,8 @@ impl Plugin for ParticleSystemPlugin {
                 particle::update_particle_params,
                 compute::dispatch_particle_compute,
             ))
+            .add_event::<OriginShiftEvent>()
+            .add_systems(PostUpdate, buffer::apply_particle_origin_shift.after(ShiftOrigin))
             .add_systems(Startup, (
                 presets::spawn_example_effects,
                 special_effects::spawn_special_effects_demo,
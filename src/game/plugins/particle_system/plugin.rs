@@ -8,6 +8,7 @@ use super::{
     particle::{ParticleSystem, ParticleSystemSettings},
     sorting::{ParticleSortPipeline, dispatch_particle_sort, init_particle_indices},
 };
+use crate::game::ProfileGroup;
 
 /// Plugin for managing particle systems
 pub struct ParticleSystemPlugin;
@@ -27,7 +28,7 @@ impl Plugin for ParticleSystemPlugin {
             init_particle_indices,
             update_material_params,
             create_material_bind_groups,
-        ));
+        ).in_set(ProfileGroup::ParticlesPrepare));
 
         // Add render systems
         app.add_systems(Render, (
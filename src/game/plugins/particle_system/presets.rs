@@ -147,6 +147,37 @@ impl ParticlePresets {
                 },
                 ..default()
             }),
+            // Settles on the terrain instead of sinking through it
+            ParticleMaterial::preset_dust(Handle::default()),
+            transform,
+        )).id()
+    }
+
+    /// Create a mud splash effect, clumps thrown up that stick where they land
+    pub fn mud_splash(commands: &mut Commands, transform: Transform, config: Option<PresetConfig>) -> Entity {
+        let config = config.unwrap_or_default();
+        let mut params = SimulationParams::default();
+        params.colors = ParticleColors {
+            albedo: ParticleColors::smoke(),
+            emission: ParticleColors::smoke(),
+            emission_strength: 0.0,
+            ease_function: EaseFunction::QuadOut,
+        };
+        params.lifetime = 1.5 * config.lifetime;
+        params.spawn_rate = 40.0 * config.intensity;
+        params.initial_velocity = Vec3::new(0.0, 3.0, 0.0) * config.speed;
+        params.velocity_randomness = 0.6;
+        params.size_begin = 0.08 * config.scale;
+        params.size_end = 0.06 * config.scale;
+        params.gravity = config.gravity;
+
+        commands.spawn((
+            ParticleSystem::new(params),
+            Emitter::new(EmitterConfig {
+                shape: EmitterShape::Sphere { radius: 0.2 * config.scale },
+                ..default()
+            }),
+            ParticleMaterial::preset_mud(Handle::default()),
             transform,
         )).id()
     }
@@ -365,6 +396,36 @@ impl ParticlePresets {
         )).id()
     }
 
+    /// Create a heat haze effect, slow rising shimmer over a hot engine or exhaust
+    pub fn heat_haze(commands: &mut Commands, transform: Transform, config: Option<PresetConfig>) -> Entity {
+        let config = config.unwrap_or_default();
+        let mut params = SimulationParams::default();
+        params.colors = ParticleColors {
+            albedo: ParticleColors::smoke(),
+            emission: ParticleColors::smoke(),
+            emission_strength: 0.0,
+            ease_function: EaseFunction::QuadOut,
+        };
+        params.lifetime = 0.8 * config.lifetime;
+        params.spawn_rate = 30.0 * config.intensity;
+        params.initial_velocity = Vec3::new(0.0, 0.8, 0.0) * config.speed;
+        params.velocity_randomness = 0.15;
+        params.size_begin = 0.3 * config.scale;
+        params.size_end = 0.6 * config.scale;
+        params.gravity = config.gravity;
+
+        commands.spawn((
+            ParticleSystem::new(params),
+            Emitter::new(EmitterConfig {
+                shape: EmitterShape::Box {
+                    size: Vec3::new(0.6, 0.05, 0.6) * config.scale
+                },
+                ..default()
+            }),
+            transform,
+        )).id()
+    }
+
     /// Create a sparkle effect
     pub fn sparkle(commands: &mut Commands, transform: Transform, config: Option<PresetConfig>) -> Entity {
         let config = config.unwrap_or_default();
@@ -418,7 +479,8 @@ impl ParticlePresets {
         emitter.size_randomness = 0.2;
         emitter.gravity = config.gravity;
 
-        commands.spawn(emitter);
+        // Rain drops die where they hit the ground or anything else in view
+        commands.spawn((emitter, ParticleMaterial::preset_water(Handle::default())));
     }
 
     pub fn snow(commands: &mut Commands, transform: Transform, config: Option<PresetConfig>) {
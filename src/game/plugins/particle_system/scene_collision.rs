@@ -0,0 +1,430 @@
+use bevy::{
+    asset::embedded_asset,
+    core_pipeline::prepass::ViewPrepassTextures,
+    prelude::*,
+    render::{
+        render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext},
+        render_resource::*,
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        view::{ViewUniformOffset, ViewUniforms},
+        Extract, Render, RenderApp, RenderSet,
+    },
+};
+
+use super::{
+    buffer::ParticleBufferManager,
+    collision::{ParticleCollisionMode, ParticleCollisionResponse, ParticleCollisionSettings, ParticleTerrainHeightfield},
+    material::ParticleMaterial,
+    particle::ParticleSystem,
+};
+use crate::rendering::{compute_pipeline, scene_depth_view, track_compute_pipeline, SceneDepthPlugin, SceneDepthUsers};
+
+/// Workgroup size used by the scene collision shader
+const WORKGROUP_SIZE: u32 = 64;
+
+/// GPU-side collision parameters for one particle effect
+#[derive(ShaderType, Clone, Copy, Debug, Default)]
+pub struct SceneCollisionUniform {
+    pub use_depth: u32,
+    pub use_terrain: u32,
+    pub response: u32,
+    pub particle_count: u32,
+    pub restitution: f32,
+    pub friction: f32,
+    pub radius: f32,
+    pub depth_thickness: f32,
+    pub terrain_origin: Vec2,
+    pub terrain_size: Vec2,
+}
+
+/// Whether the effect collides against the depth buffer
+fn uses_depth(settings: &ParticleCollisionSettings) -> bool {
+    matches!(
+        settings.mode,
+        ParticleCollisionMode::Depth | ParticleCollisionMode::DepthAndTerrain
+    )
+}
+
+impl SceneCollisionUniform {
+    fn new(settings: &ParticleCollisionSettings, particle_count: u32) -> Self {
+        let use_depth = uses_depth(settings);
+        let use_terrain = matches!(
+            settings.mode,
+            ParticleCollisionMode::Terrain | ParticleCollisionMode::DepthAndTerrain
+        );
+        Self {
+            use_depth: use_depth as u32,
+            use_terrain: use_terrain as u32,
+            response: match settings.response {
+                ParticleCollisionResponse::Bounce => 0,
+                ParticleCollisionResponse::Kill => 1,
+                ParticleCollisionResponse::Stick => 2,
+            },
+            particle_count,
+            restitution: settings.restitution,
+            friction: settings.friction,
+            radius: settings.radius,
+            depth_thickness: settings.depth_thickness,
+            ..default()
+        }
+    }
+}
+
+/// A particle effect that collides with the scene this frame
+struct ExtractedCollider {
+    particles: Buffer,
+    uniform: SceneCollisionUniform,
+}
+
+/// Particle effects extracted for scene collision
+#[derive(Resource, Default)]
+struct ExtractedParticleColliders(Vec<ExtractedCollider>);
+
+/// Heightfield texture uploaded for terrain collision
+#[derive(Resource)]
+struct GpuParticleHeightfield {
+    view: TextureView,
+    origin: Vec2,
+    size: Vec2,
+}
+
+/// Uniform offsets for the colliders, in the same order as [`ExtractedParticleColliders`]
+#[derive(Resource, Default)]
+struct SceneCollisionUniforms {
+    buffer: DynamicUniformBuffer<SceneCollisionUniform>,
+    offsets: Vec<u32>,
+}
+
+/// Registers particle collision as a scene depth user while an effect collides against depth
+fn request_particle_depth(materials: Query<&ParticleMaterial>, mut users: ResMut<SceneDepthUsers>) {
+    let active = materials.iter().any(|material| uses_depth(&material.collision));
+    users.set("particle_collision", active);
+}
+
+fn extract_particle_colliders(
+    mut commands: Commands,
+    particles: Extract<Query<(&ParticleBufferManager, &ParticleSystem, &ParticleMaterial)>>,
+) {
+    let colliders = particles
+        .iter()
+        .filter(|(_, _, material)| material.collision.mode != ParticleCollisionMode::None)
+        .map(|(buffers, system, material)| ExtractedCollider {
+            // The write buffer holds this frame's simulated particles
+            particles: buffers.write_buffer().clone(),
+            uniform: SceneCollisionUniform::new(&material.collision, system.particle_count),
+        })
+        .collect();
+    commands.insert_resource(ExtractedParticleColliders(colliders));
+}
+
+/// Re-uploads the terrain heightfield only when it changes
+fn extract_heightfield(
+    mut commands: Commands,
+    heightfield: Extract<Option<Res<ParticleTerrainHeightfield>>>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    let Some(heightfield) = heightfield.as_ref() else {
+        return;
+    };
+    if !heightfield.is_changed() {
+        return;
+    }
+
+    let texture = render_device.create_texture_with_data(
+        &render_queue,
+        &TextureDescriptor {
+            label: Some("particle_terrain_heightfield"),
+            size: Extent3d {
+                width: heightfield.resolution.x,
+                height: heightfield.resolution.y,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::R32Float,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        },
+        bytemuck::cast_slice(&heightfield.heights),
+    );
+    commands.insert_resource(GpuParticleHeightfield {
+        view: texture.create_view(&TextureViewDescriptor::default()),
+        origin: heightfield.origin,
+        size: heightfield.size,
+    });
+}
+
+fn prepare_scene_collision_uniforms(
+    mut uniforms: ResMut<SceneCollisionUniforms>,
+    colliders: Res<ExtractedParticleColliders>,
+    heightfield: Option<Res<GpuParticleHeightfield>>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    let uniforms = uniforms.as_mut();
+    uniforms.buffer.clear();
+    uniforms.offsets.clear();
+
+    for collider in colliders.0.iter() {
+        let mut uniform = collider.uniform;
+        match heightfield.as_ref() {
+            Some(heightfield) => {
+                uniform.terrain_origin = heightfield.origin;
+                uniform.terrain_size = heightfield.size;
+            }
+            // No terrain yet, fall back to depth-only collision
+            None => uniform.use_terrain = 0,
+        }
+        uniforms.offsets.push(uniforms.buffer.push(uniform));
+    }
+    uniforms.buffer.write_buffer(&render_device, &render_queue);
+}
+
+/// Pipeline for resolving particle collisions against depth and terrain
+#[derive(Resource)]
+pub struct SceneCollisionPipeline {
+    layout: BindGroupLayout,
+    /// Bound when no heightfield has been provided yet
+    fallback_heightfield: TextureView,
+    /// Bound on views without a single-sampled depth prepass
+    fallback_depth: TextureView,
+    pipeline_id: CachedComputePipelineId,
+}
+
+impl FromWorld for SceneCollisionPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let shader = world
+            .resource::<AssetServer>()
+            .load("embedded://sandk_offroad/game/plugins/particle_system/shaders/particle_scene_collision.wgsl");
+
+        let render_device = world.resource::<RenderDevice>();
+        let render_queue = world.resource::<RenderQueue>();
+
+        let layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("particle_scene_collision_bind_group_layout"),
+            entries: &[
+                // View uniform for projecting particles into the depth buffer
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Particle buffer, updated in place
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Per-effect collision settings
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: Some(SceneCollisionUniform::min_size()),
+                    },
+                    count: None,
+                },
+                // Scene depth
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Depth,
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // Terrain heightfield
+                BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let fallback = render_device.create_texture_with_data(
+            render_queue,
+            &TextureDescriptor {
+                label: Some("particle_terrain_heightfield_fallback"),
+                size: Extent3d::default(),
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::R32Float,
+                usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+                view_formats: &[],
+            },
+            bytemuck::bytes_of(&f32::MIN),
+        );
+
+        // Zero initialized, the far plane in reverse-Z, so nothing collides with it
+        let fallback_depth = render_device.create_texture(&TextureDescriptor {
+            label: Some("particle_scene_depth_fallback"),
+            size: Extent3d::default(),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Depth32Float,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+
+        let pipeline_id = world.resource::<PipelineCache>().queue_compute_pipeline(ComputePipelineDescriptor {
+            label: Some("particle_scene_collision_pipeline".into()),
+            layout: vec![layout.clone()],
+            push_constant_ranges: vec![],
+            shader,
+            shader_defs: vec![],
+            entry_point: "collide".into(),
+        });
+
+        track_compute_pipeline(world, pipeline_id);
+
+        Self {
+            layout,
+            fallback_heightfield: fallback.create_view(&TextureViewDescriptor::default()),
+            fallback_depth: fallback_depth.create_view(&TextureViewDescriptor::default()),
+            pipeline_id,
+        }
+    }
+}
+
+/// Node that pushes particles out of the depth buffer and terrain after the main pass
+pub struct SceneCollisionNode {
+    query: QueryState<(&'static ViewUniformOffset, Option<&'static ViewPrepassTextures>)>,
+}
+
+impl SceneCollisionNode {
+    /// Name of the node in the render graph
+    pub const NAME: &'static str = "particle_scene_collision";
+}
+
+impl FromWorld for SceneCollisionNode {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for SceneCollisionNode {
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let colliders = world.resource::<ExtractedParticleColliders>();
+        if colliders.0.is_empty() {
+            return Ok(());
+        }
+        let Ok((view_uniform, prepass)) = self.query.get_manual(world, graph.view_entity()) else {
+            return Ok(());
+        };
+        let uniforms = world.resource::<SceneCollisionUniforms>();
+        let (Some(view_binding), Some(settings_binding)) = (
+            world.resource::<ViewUniforms>().uniforms.binding(),
+            uniforms.buffer.binding(),
+        ) else {
+            return Ok(());
+        };
+
+        let pipeline = world.resource::<SceneCollisionPipeline>();
+        let Some(compute_pipeline) = compute_pipeline(world, pipeline.pipeline_id) else {
+            return Ok(());
+        };
+        // Terrain collision keeps working on views without a single-sampled depth prepass yet
+        let depth = prepass.and_then(scene_depth_view).unwrap_or(&pipeline.fallback_depth);
+        let heightfield = world
+            .get_resource::<GpuParticleHeightfield>()
+            .map_or(&pipeline.fallback_heightfield, |heightfield| &heightfield.view);
+
+        let render_device = render_context.render_device().clone();
+        let mut compute_pass = render_context
+            .command_encoder()
+            .begin_compute_pass(&ComputePassDescriptor {
+                label: Some("particle_scene_collision_pass"),
+            });
+        compute_pass.set_pipeline(compute_pipeline);
+
+        for (collider, offset) in colliders.0.iter().zip(uniforms.offsets.iter()) {
+            let bind_group = render_device.create_bind_group(
+                "particle_scene_collision_bind_group",
+                &pipeline.layout,
+                &BindGroupEntries::sequential((
+                    view_binding.clone(),
+                    collider.particles.as_entire_binding(),
+                    settings_binding.clone(),
+                    depth,
+                    heightfield,
+                )),
+            );
+            compute_pass.set_bind_group(0, &bind_group, &[view_uniform.offset, *offset]);
+            compute_pass.dispatch_workgroups(collider.uniform.particle_count.div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+
+        Ok(())
+    }
+}
+
+/// Plugin that resolves GPU particle collisions against the depth buffer and terrain
+pub struct SceneCollisionPlugin;
+
+impl Plugin for SceneCollisionPlugin {
+    fn build(&self, app: &mut App) {
+        if app.get_sub_app(RenderApp).is_err() {
+            return;
+        }
+        // Reloads on save with the `shader-hot-reload` feature
+        embedded_asset!(app, "shaders/particle_scene_collision.wgsl");
+
+        if !app.is_plugin_added::<SceneDepthPlugin>() {
+            app.add_plugins(SceneDepthPlugin);
+        }
+        app.add_systems(Update, request_particle_depth);
+
+        app.sub_app_mut(RenderApp)
+            .init_resource::<ExtractedParticleColliders>()
+            .init_resource::<SceneCollisionUniforms>()
+            .add_systems(ExtractSchedule, (extract_particle_colliders, extract_heightfield))
+            .add_systems(Render, prepare_scene_collision_uniforms.in_set(RenderSet::PrepareResources));
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<SceneCollisionPipeline>();
+
+        // Depth is complete once the main pass has run
+        let node = SceneCollisionNode::from_world(&mut render_app.world);
+        let mut render_graph = render_app.world.resource_mut::<RenderGraph>();
+        render_graph.add_node(SceneCollisionNode::NAME, node);
+        render_graph.add_node_edge("main_pass", SceneCollisionNode::NAME);
+    }
+}
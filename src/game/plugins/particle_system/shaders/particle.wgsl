@@ -31,7 +31,7 @@ struct SimulationParams {
     anim_fps: f32,
     active_particles: u32,
     max_particles: u32,
-    _padding: vec2<f32>,
+    origin_shift: vec2<f32>,
 }
 
 struct VertexInput {
@@ -0,0 +1,176 @@
+// Particle collision against the scene
+//
+// Runs after the main pass, in place on each effect's particle buffer:
+// - Depth: project the particle into the depth buffer, treat it as hit when it sits
+//   within `depth_thickness` behind the visible surface, and rebuild the surface normal
+//   from neighbouring depth samples.
+// - Terrain: compare against a coarse heightfield so world-space effects still collide
+//   when they are off screen.
+
+#import bevy_render::view::View
+
+struct Particle {
+    position: vec3<f32>,
+    velocity: vec3<f32>,
+    color: vec4<f32>,
+    size_and_rot: vec3<f32>,
+    lifetime: vec2<f32>,    // x = age, y = total lifetime
+    random_seed: f32,
+    tex_params: vec2<f32>,
+    _padding: vec2<f32>,
+}
+
+struct SceneCollision {
+    use_depth: u32,
+    use_terrain: u32,
+    response: u32,          // 0 = bounce, 1 = kill, 2 = stick
+    particle_count: u32,
+    restitution: f32,
+    friction: f32,
+    radius: f32,
+    depth_thickness: f32,
+    terrain_origin: vec2<f32>,
+    terrain_size: vec2<f32>,
+}
+
+const RESPONSE_BOUNCE: u32 = 0u;
+const RESPONSE_KILL: u32 = 1u;
+
+@group(0) @binding(0) var<uniform> view: View;
+@group(0) @binding(1) var<storage, read_write> particles: array<Particle>;
+@group(0) @binding(2) var<uniform> settings: SceneCollision;
+@group(0) @binding(3) var depth_texture: texture_depth_2d;
+@group(0) @binding(4) var heightfield: texture_2d<f32>;
+
+struct Hit {
+    hit: bool,
+    position: vec3<f32>,
+    normal: vec3<f32>,
+}
+
+fn no_hit() -> Hit {
+    return Hit(false, vec3(0.0), vec3(0.0, 1.0, 0.0));
+}
+
+fn world_from_depth(coord: vec2<i32>, size: vec2<i32>) -> vec3<f32> {
+    let clamped = clamp(coord, vec2(0), size - 1);
+    let depth = textureLoad(depth_texture, clamped, 0);
+    let uv = (vec2<f32>(clamped) + 0.5) / vec2<f32>(size);
+    let ndc = vec4(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    let world = view.inverse_view_proj * ndc;
+    return world.xyz / world.w;
+}
+
+fn depth_collision(position: vec3<f32>) -> Hit {
+    let clip = view.view_proj * vec4(position, 1.0);
+    if (clip.w <= 0.0) {
+        return no_hit();
+    }
+    let ndc = clip.xy / clip.w;
+    if (any(abs(ndc) > vec2(1.0))) {
+        // Off screen, the depth buffer knows nothing here
+        return no_hit();
+    }
+
+    let size = vec2<i32>(textureDimensions(depth_texture));
+    let uv = vec2(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+    let coord = vec2<i32>(uv * vec2<f32>(size));
+
+    // Reverse-Z infinite perspective: view z = near / depth
+    let scene_depth = view.projection[3][2] / max(textureLoad(depth_texture, coord, 0), 0.000001);
+    let penetration = clip.w - scene_depth;
+    if (penetration < -settings.radius || penetration > settings.depth_thickness) {
+        return no_hit();
+    }
+
+    let center = world_from_depth(coord, size);
+    let dx = world_from_depth(coord + vec2(1, 0), size) - center;
+    let dy = world_from_depth(coord + vec2(0, 1), size) - center;
+    var normal = normalize(cross(dy, dx));
+    if (dot(normal, view.world_position - center) < 0.0) {
+        normal = -normal;
+    }
+    return Hit(true, center + normal * settings.radius, normal);
+}
+
+fn terrain_height(texel: vec2<i32>) -> f32 {
+    let size = vec2<i32>(textureDimensions(heightfield));
+    return textureLoad(heightfield, clamp(texel, vec2(0), size - 1), 0).r;
+}
+
+fn terrain_collision(position: vec3<f32>) -> Hit {
+    let uv = (position.xz - settings.terrain_origin) / settings.terrain_size;
+    if (any(uv < vec2(0.0)) || any(uv > vec2(1.0))) {
+        return no_hit();
+    }
+
+    // Manual bilinear filtering, R32Float is not filterable everywhere
+    let size = vec2<f32>(textureDimensions(heightfield));
+    let texel = uv * (size - 1.0);
+    let base = vec2<i32>(floor(texel));
+    let t = fract(texel);
+    let h00 = terrain_height(base);
+    let h10 = terrain_height(base + vec2(1, 0));
+    let h01 = terrain_height(base + vec2(0, 1));
+    let h11 = terrain_height(base + vec2(1, 1));
+    let height = mix(mix(h00, h10, t.x), mix(h01, h11, t.x), t.y);
+
+    if (position.y - settings.radius > height) {
+        return no_hit();
+    }
+
+    let spacing = settings.terrain_size / (size - 1.0);
+    let normal = normalize(vec3(
+        (h00 - h10) / spacing.x,
+        1.0,
+        (h00 - h01) / spacing.y,
+    ));
+    return Hit(true, vec3(position.x, height + settings.radius, position.z), normal);
+}
+
+// Mirrors `ParticleCollisionSettings::respond`
+fn apply_response(particle: ptr<function, Particle>, hit: Hit) {
+    if (settings.response == RESPONSE_KILL) {
+        (*particle).lifetime.x = (*particle).lifetime.y;
+        return;
+    }
+
+    (*particle).position = hit.position;
+    if (settings.response == RESPONSE_BOUNCE) {
+        let normal_speed = dot((*particle).velocity, hit.normal);
+        if (normal_speed < 0.0) {
+            let normal_velocity = hit.normal * normal_speed;
+            let tangent_velocity = (*particle).velocity - normal_velocity;
+            (*particle).velocity = tangent_velocity * (1.0 - settings.friction) - normal_velocity * settings.restitution;
+        }
+    } else {
+        (*particle).velocity = vec3(0.0);
+    }
+}
+
+@compute @workgroup_size(64)
+fn collide(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= settings.particle_count) {
+        return;
+    }
+
+    var particle = particles[id.x];
+    // Dead particles are left for the emitter to recycle
+    if (particle.lifetime.x >= particle.lifetime.y) {
+        return;
+    }
+
+    var hit = no_hit();
+    if (settings.use_depth != 0u) {
+        hit = depth_collision(particle.position);
+    }
+    if (!hit.hit && settings.use_terrain != 0u) {
+        hit = terrain_collision(particle.position);
+    }
+    if (!hit.hit) {
+        return;
+    }
+
+    apply_response(&particle, hit);
+    particles[id.x] = particle;
+}
@@ -29,7 +29,7 @@ struct SimParams {
     anim_fps: f32,
     active_particles: u32,
     max_particles: u32,
-    _padding: vec2<f32>,
+    origin_shift: vec2<f32>,
 }
 
 // Buffers
@@ -113,8 +113,9 @@ fn init_particle(index: u32, seed_base: u32) -> Particle {
 fn update_particle(particle: Particle, dt: f32) -> Particle {
     var updated = particle;
     
-    // Update position based on velocity
-    updated.position = particle.position + particle.velocity * dt;
+    // Update position based on velocity, following the world origin if it moved
+    let origin_shift = vec3<f32>(params.origin_shift.x, 0.0, params.origin_shift.y);
+    updated.position = particle.position - origin_shift + particle.velocity * dt;
     
     // Apply forces
     updated.velocity = particle.velocity + params.forces * dt;
@@ -0,0 +1,403 @@
+use bevy::diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin};
+use bevy::prelude::*;
+
+use crate::game::{GameSettings, MirrorQuality, ShadowQuality};
+
+use super::memory::{MemoryBudgetEvent, MemoryCategory, MemoryPressure, MemoryUsage};
+use super::particle_system::ParticleMaterial;
+use super::post_process::PostProcessSettings;
+
+/// Quality steps the performance budget moves between, `Full` leaves the player's settings alone
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum QualityLevel {
+    Minimum,
+    Low,
+    Medium,
+    #[default]
+    Full,
+}
+
+impl QualityLevel {
+    pub fn lower(self) -> Option<Self> {
+        match self {
+            QualityLevel::Minimum => None,
+            QualityLevel::Low => Some(QualityLevel::Minimum),
+            QualityLevel::Medium => Some(QualityLevel::Low),
+            QualityLevel::Full => Some(QualityLevel::Medium),
+        }
+    }
+
+    pub fn higher(self) -> Option<Self> {
+        match self {
+            QualityLevel::Minimum => Some(QualityLevel::Low),
+            QualityLevel::Low => Some(QualityLevel::Medium),
+            QualityLevel::Medium => Some(QualityLevel::Full),
+            QualityLevel::Full => None,
+        }
+    }
+
+    /// Particle material quality (0.0 - 1.0)
+    pub fn particle_quality(self) -> f32 {
+        match self {
+            QualityLevel::Minimum => 0.25,
+            QualityLevel::Low => 0.5,
+            QualityLevel::Medium => 0.75,
+            QualityLevel::Full => 1.0,
+        }
+    }
+
+    /// Highest shadow quality allowed at this level
+    pub fn shadow_cap(self) -> ShadowQuality {
+        match self {
+            QualityLevel::Minimum | QualityLevel::Low => ShadowQuality::Low,
+            QualityLevel::Medium => ShadowQuality::Medium,
+            QualityLevel::Full => ShadowQuality::High,
+        }
+    }
+
+    /// Highest rear-view mirror quality allowed at this level
+    pub fn mirror_cap(self) -> MirrorQuality {
+        match self {
+            QualityLevel::Minimum | QualityLevel::Low => MirrorQuality::Low,
+            QualityLevel::Medium => MirrorQuality::Medium,
+            QualityLevel::Full => MirrorQuality::High,
+        }
+    }
+
+    /// Multiplier on terrain LOD switch distances
+    pub fn terrain_lod_scale(self) -> f32 {
+        match self {
+            QualityLevel::Minimum => 0.4,
+            QualityLevel::Low => 0.6,
+            QualityLevel::Medium => 0.8,
+            QualityLevel::Full => 1.0,
+        }
+    }
+
+    pub fn allows_depth_of_field(self) -> bool {
+        self == QualityLevel::Full
+    }
+
+    pub fn allows_ssao(self) -> bool {
+        self >= QualityLevel::Medium
+    }
+}
+
+/// Tuning for when quality steps down and back up
+#[derive(Resource, Debug, Clone)]
+pub struct PerformanceBudgetConfig {
+    /// Frame time over budget by this fraction counts as slow
+    pub slow_tolerance: f32,
+    /// Frame time under budget by this fraction counts as headroom
+    pub headroom: f32,
+    /// Seconds of slow frames before stepping down
+    pub downgrade_delay: f32,
+    /// Seconds of headroom before stepping back up
+    pub upgrade_delay: f32,
+    /// Seconds after any change before the next one, lets the new level settle
+    pub cooldown: f32,
+}
+
+impl Default for PerformanceBudgetConfig {
+    fn default() -> Self {
+        Self {
+            slow_tolerance: 0.1,
+            headroom: 0.25,
+            downgrade_delay: 1.0,
+            upgrade_delay: 5.0,
+            cooldown: 2.0,
+        }
+    }
+}
+
+/// Current quality level and how long frames have been slow or fast
+#[derive(Resource, Debug, Clone, Default)]
+pub struct PerformanceBudget {
+    pub level: QualityLevel,
+    /// Smoothed frame time in milliseconds
+    pub frame_time_ms: f32,
+    /// Memory that quality affects is near its budget, quality doesn't step back up meanwhile
+    pub memory_pressure: bool,
+    slow_time: f32,
+    fast_time: f32,
+    cooldown: f32,
+    /// Post-process toggles from before the budget started switching them off
+    saved_post_process: Option<(bool, bool)>,
+}
+
+impl PerformanceBudget {
+    /// Feeds one frame's smoothed frame time, returns the new level if it changed
+    pub fn update(
+        &mut self,
+        config: &PerformanceBudgetConfig,
+        frame_time_ms: f32,
+        target_fps: u32,
+        delta: f32,
+    ) -> Option<QualityLevel> {
+        self.frame_time_ms = frame_time_ms;
+        if self.cooldown > 0.0 {
+            self.cooldown -= delta;
+            return None;
+        }
+
+        let budget_ms = 1000.0 / target_fps.max(1) as f32;
+        if frame_time_ms > budget_ms * (1.0 + config.slow_tolerance) {
+            self.slow_time += delta;
+            self.fast_time = 0.0;
+        } else if frame_time_ms < budget_ms * (1.0 - config.headroom) {
+            self.fast_time += delta;
+            self.slow_time = 0.0;
+        } else {
+            self.slow_time = 0.0;
+            self.fast_time = 0.0;
+        }
+
+        let next = if self.slow_time >= config.downgrade_delay {
+            self.level.lower()
+        } else if self.fast_time >= config.upgrade_delay && !self.memory_pressure {
+            self.level.higher()
+        } else {
+            None
+        }?;
+
+        self.level = next;
+        self.slow_time = 0.0;
+        self.fast_time = 0.0;
+        self.cooldown = config.cooldown;
+        Some(next)
+    }
+
+    /// Steps down a level to free memory unless a change is still settling, returns the new level
+    pub fn relieve_memory(&mut self, config: &PerformanceBudgetConfig) -> Option<QualityLevel> {
+        if self.cooldown > 0.0 {
+            return None;
+        }
+        let next = self.level.lower()?;
+        self.level = next;
+        self.slow_time = 0.0;
+        self.fast_time = 0.0;
+        self.cooldown = config.cooldown;
+        Some(next)
+    }
+
+    /// Shadow quality to render with given the player's choice
+    pub fn shadow_quality(&self, requested: ShadowQuality) -> ShadowQuality {
+        requested.min(self.level.shadow_cap())
+    }
+
+    /// Mirror quality to render with given the player's choice
+    pub fn mirror_quality(&self, requested: MirrorQuality) -> MirrorQuality {
+        requested.min(self.level.mirror_cap())
+    }
+
+    /// Terrain LOD switch distance scaled for the current level
+    pub fn terrain_lod_distance(&self, base: f32) -> f32 {
+        base * self.level.terrain_lod_scale()
+    }
+}
+
+/// Watches frame time and moves the quality level up or down
+fn update_performance_budget(
+    time: Res<Time>,
+    diagnostics: Res<Diagnostics>,
+    game_settings: Res<GameSettings>,
+    config: Res<PerformanceBudgetConfig>,
+    mut budget: ResMut<PerformanceBudget>,
+) {
+    let graphics = &game_settings.graphics;
+    if !graphics.dynamic_quality {
+        if budget.level != QualityLevel::Full {
+            budget.level = QualityLevel::Full;
+            info!("Dynamic quality off, restoring full quality");
+        }
+        return;
+    }
+
+    let Some(frame_time) = diagnostics
+        .get(FrameTimeDiagnosticsPlugin::FRAME_TIME)
+        .and_then(|diagnostic| diagnostic.smoothed())
+    else {
+        return;
+    };
+
+    if let Some(level) = budget.update(&config, frame_time as f32, graphics.target_fps, time.delta_seconds()) {
+        info!(
+            "Frame time {:.1}ms against a {} FPS target, quality now {:?}",
+            frame_time, graphics.target_fps, level
+        );
+    }
+}
+
+/// Steps quality down when memory warnings come in, and again after each cooldown while still over budget
+fn relieve_memory_pressure(
+    game_settings: Res<GameSettings>,
+    config: Res<PerformanceBudgetConfig>,
+    usage: Res<MemoryUsage>,
+    mut warnings: EventReader<MemoryBudgetEvent>,
+    mut budget: ResMut<PerformanceBudget>,
+) {
+    let warned = warnings
+        .read()
+        .any(|warning| warning.category.scales_with_quality() && warning.pressure != MemoryPressure::Normal);
+    let over = MemoryCategory::ALL
+        .into_iter()
+        .any(|category| category.scales_with_quality() && usage.pressure(category) == MemoryPressure::Over);
+    let pressure = usage.under_pressure();
+    if budget.memory_pressure != pressure {
+        budget.memory_pressure = pressure;
+    }
+    if !game_settings.graphics.dynamic_quality || !(warned || over) {
+        return;
+    }
+    if let Some(level) = budget.relieve_memory(&config) {
+        info!("Memory near its budget, quality now {:?}", level);
+    }
+}
+
+/// Scales particle materials to the current level
+fn apply_particle_quality(
+    budget: Res<PerformanceBudget>,
+    mut applied: Local<Option<QualityLevel>>,
+    mut materials: Query<&mut ParticleMaterial>,
+    added: Query<(), Added<ParticleMaterial>>,
+) {
+    // The budget resource changes every frame with the frame time, only react to level changes
+    if *applied == Some(budget.level) && added.is_empty() {
+        return;
+    }
+    *applied = Some(budget.level);
+    let quality = budget.level.particle_quality();
+    for mut material in materials.iter_mut() {
+        if material.quality_level != quality {
+            material.quality_level = quality;
+        }
+    }
+}
+
+/// Switches off the expensive post-process passes at low levels and restores them afterwards
+fn apply_post_process_quality(
+    mut budget: ResMut<PerformanceBudget>,
+    mut applied: Local<Option<QualityLevel>>,
+    mut settings: ResMut<PostProcessSettings>,
+) {
+    let level = budget.level;
+    if *applied == Some(level) {
+        return;
+    }
+    *applied = Some(level);
+    if level == QualityLevel::Full {
+        if let Some((ssao, dof)) = budget.saved_post_process.take() {
+            settings.ssao_enabled = ssao;
+            settings.dof_enabled = dof;
+        }
+        return;
+    }
+
+    let (ssao, dof) = *budget
+        .saved_post_process
+        .get_or_insert((settings.ssao_enabled, settings.dof_enabled));
+    let ssao = ssao && level.allows_ssao();
+    let dof = dof && level.allows_depth_of_field();
+    if settings.ssao_enabled != ssao || settings.dof_enabled != dof {
+        settings.ssao_enabled = ssao;
+        settings.dof_enabled = dof;
+    }
+}
+
+/// Plugin that holds the frame rate target by scaling rendering quality
+pub struct PerformanceBudgetPlugin;
+
+impl Plugin for PerformanceBudgetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PerformanceBudgetConfig>()
+            .init_resource::<PerformanceBudget>()
+            .add_systems(Update, (
+                update_performance_budget.run_if(resource_exists::<GameSettings>()),
+                relieve_memory_pressure
+                    .run_if(resource_exists::<GameSettings>().and_then(resource_exists::<MemoryUsage>())),
+                apply_particle_quality,
+                apply_post_process_quality.run_if(resource_exists::<PostProcessSettings>()),
+            ).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: f32 = 1.0 / 60.0;
+
+    fn run(budget: &mut PerformanceBudget, frame_time_ms: f32, seconds: f32) -> Vec<QualityLevel> {
+        let config = PerformanceBudgetConfig::default();
+        let frames = (seconds / FRAME) as usize;
+        (0..frames)
+            .filter_map(|_| budget.update(&config, frame_time_ms, 60, FRAME))
+            .collect()
+    }
+
+    #[test]
+    fn test_steps_down_when_slow() {
+        let mut budget = PerformanceBudget::default();
+        // 30 FPS for a moment isn't enough
+        assert!(run(&mut budget, 33.0, 0.5).is_empty());
+        assert_eq!(run(&mut budget, 33.0, 0.6), vec![QualityLevel::Medium]);
+    }
+
+    #[test]
+    fn test_cooldown_spaces_out_steps() {
+        let mut budget = PerformanceBudget::default();
+        let steps = run(&mut budget, 40.0, 4.5);
+        assert_eq!(steps, vec![QualityLevel::Medium, QualityLevel::Low]);
+        run(&mut budget, 40.0, 10.0);
+        assert_eq!(budget.level, QualityLevel::Minimum);
+    }
+
+    #[test]
+    fn test_steps_up_with_headroom() {
+        let mut budget = PerformanceBudget {
+            level: QualityLevel::Low,
+            ..default()
+        };
+        // On budget but without headroom stays put
+        assert!(run(&mut budget, 16.0, 10.0).is_empty());
+        assert_eq!(run(&mut budget, 8.0, 5.5), vec![QualityLevel::Medium]);
+    }
+
+    #[test]
+    fn test_memory_pressure_steps_down_and_holds() {
+        let config = PerformanceBudgetConfig::default();
+        let mut budget = PerformanceBudget {
+            memory_pressure: true,
+            ..default()
+        };
+        assert_eq!(budget.relieve_memory(&config), Some(QualityLevel::Medium));
+        // Settling first, then no stepping back up while memory is tight, however fast frames are
+        assert_eq!(budget.relieve_memory(&config), None);
+        assert!(run(&mut budget, 8.0, 10.0).is_empty());
+        budget.memory_pressure = false;
+        assert_eq!(run(&mut budget, 8.0, 5.5), vec![QualityLevel::Full]);
+    }
+
+    #[test]
+    fn test_jitter_resets_timers() {
+        let mut budget = PerformanceBudget::default();
+        let config = PerformanceBudgetConfig::default();
+        for frame in 0..600 {
+            let frame_time = if frame % 30 == 0 { 16.0 } else { 30.0 };
+            assert_eq!(budget.update(&config, frame_time, 60, FRAME), None);
+        }
+    }
+
+    #[test]
+    fn test_shadow_cap_never_raises_quality() {
+        let budget = PerformanceBudget {
+            level: QualityLevel::Medium,
+            ..default()
+        };
+        assert_eq!(budget.shadow_quality(ShadowQuality::High), ShadowQuality::Medium);
+        assert_eq!(budget.shadow_quality(ShadowQuality::Low), ShadowQuality::Low);
+        assert_eq!(budget.terrain_lod_distance(100.0), 80.0);
+        assert_eq!(budget.mirror_quality(MirrorQuality::High), MirrorQuality::Medium);
+        assert_eq!(budget.mirror_quality(MirrorQuality::Low), MirrorQuality::Low);
+    }
+}
@@ -0,0 +1,381 @@
+//! Physics stress testing with extreme input sequences.
+//!
+//! Each case runs the headless simulation from one seed: the seed fixes the
+//! terrain and the random inputs, so a failing case reruns identically from
+//! the seed alone. Inputs are stored as an [`InputRecording`], the same format
+//! determinism mode records, which lets a failure be saved and replayed, and
+//! lets recordings of real play be fed in too. After every tick the vehicle
+//! is checked for non-finite state, falling through the terrain and energy
+//! growing without bound. The timestep is stretched by a timescale to push
+//! the solver harder than normal play does.
+
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use thiserror::Error;
+
+use super::determinism::{DeterminismSettings, InputRecording, RecordedInput};
+use super::split_screen::{apply_player_input, PlayerId, PlayerInput};
+use crate::game::vehicle::{PlayerOrAi, SpawnVehicleEvent, Vehicle, VehicleDefinition};
+use crate::game::{HeadlessPlugin, HeadlessSimulationPlugins};
+use crate::terrain::{sample_height, terrain_noise, TerrainQuery, TerrainSeed, TerrainSettings};
+
+const GRAVITY: f32 = 9.81;
+/// Lever arm turning angular velocity into an energy, roughly half the vehicle's length
+const ROTATIONAL_RADIUS: f32 = 2.0;
+/// Spawn height above the terrain, the vehicle drops onto its wheels
+const SPAWN_CLEARANCE: f32 = 2.0;
+
+/// Limits and length of fuzz cases
+#[derive(Debug, Clone)]
+pub struct FuzzConfig {
+    /// Ticks of input per case
+    pub ticks: u64,
+    /// Ticks the vehicle gets to settle before inputs start
+    pub settle_ticks: u64,
+    /// Seconds per tick at normal speed, also the recording's tick rate
+    pub timestep: f32,
+    /// How much longer each tick is than normal
+    pub timescale: f32,
+    /// How far the vehicle's centre may sink below the terrain surface, in meters
+    pub terrain_margin: f32,
+    /// Largest mechanical energy per kg above the spawn point, in J/kg
+    pub max_specific_energy: f32,
+}
+
+impl Default for FuzzConfig {
+    fn default() -> Self {
+        Self {
+            ticks: 600,
+            settle_ticks: 30,
+            timestep: 1.0 / 60.0,
+            timescale: 2.0,
+            terrain_margin: 1.0,
+            // Moving at 60 m/s
+            max_specific_energy: 1800.0,
+        }
+    }
+}
+
+impl FuzzConfig {
+    /// Defaults overridden by `SANDK_FUZZ_TICKS` and `SANDK_FUZZ_TIMESCALE`
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Some(ticks) = std::env::var("SANDK_FUZZ_TICKS").ok().and_then(|ticks| ticks.parse().ok()) {
+            config.ticks = ticks;
+        }
+        if let Some(timescale) = std::env::var("SANDK_FUZZ_TIMESCALE").ok().and_then(|scale| scale.parse().ok()) {
+            config.timescale = timescale;
+        }
+        config
+    }
+
+    fn tick_rate(&self) -> u32 {
+        (1.0 / self.timestep).round() as u32
+    }
+}
+
+/// Parses a seed as printed in failure reports, hex with `0x` or decimal
+pub fn parse_seed(text: &str) -> Option<u64> {
+    let text = text.trim();
+    match text.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(&hex.replace('_', ""), 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+/// Seeds for `cases` fuzz cases starting from `base`
+pub fn fuzz_seeds(base: u64, cases: usize) -> Vec<u64> {
+    let mut rng = FuzzRng(base);
+    (0..cases).map(|_| rng.next_u64()).collect()
+}
+
+/// splitmix64, good enough for inputs and stable across platforms
+struct FuzzRng(u64);
+
+impl FuzzRng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in 0.0..1.0
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound.max(1)
+    }
+
+    fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+}
+
+/// Random input of `ticks` ticks from `seed`, in runs of the extremes that break solvers: full
+/// throttle against full brake, steering slammed side to side, gear changes at speed, reverse
+/// at full throttle and recoveries mid-air
+pub fn random_inputs(seed: u64, ticks: u64, config: &FuzzConfig) -> InputRecording {
+    let mut recording = InputRecording::new(&DeterminismSettings {
+        seed,
+        tick_rate: config.tick_rate(),
+        ..default()
+    });
+    let mut rng = FuzzRng(seed);
+    let mut input = PlayerInput::default();
+    let mut tick = 0;
+
+    while tick < ticks {
+        let run = 1 + rng.below(45);
+        let pattern = rng.below(7);
+        input.diff_lock ^= rng.chance(0.2);
+        input.gear = rng.chance(0.3).then(|| rng.below(8) as i32 - 1);
+
+        for step in 0..run.min(ticks - tick) {
+            let flip = if step % 2 == 0 { 1.0 } else { -1.0 };
+            match pattern {
+                0 => {
+                    input.throttle = 1.0;
+                    input.brake = 0.0;
+                }
+                1 => {
+                    input.throttle = 0.0;
+                    input.brake = 1.0;
+                }
+                2 => {
+                    input.throttle = 1.0;
+                    input.steering = flip;
+                }
+                3 => {
+                    input.throttle = 1.0;
+                    input.brake = 1.0;
+                    input.handbrake = true;
+                }
+                4 => {
+                    input.throttle = rng.next_f32();
+                    input.brake = rng.next_f32();
+                    input.steering = rng.next_f32() * 2.0 - 1.0;
+                    input.handbrake = rng.chance(0.5);
+                }
+                5 => {
+                    input.throttle = 1.0;
+                    input.gear = Some(if step % 2 == 0 { -1 } else { 5 });
+                }
+                _ => input = PlayerInput { diff_lock: input.diff_lock, ..default() },
+            }
+            input.shift_transfer = rng.chance(0.01);
+            input.ignition = rng.chance(0.002);
+            input.recover = rng.chance(0.002);
+            recording.inputs.push(RecordedInput::new(tick, 0, &input));
+            tick += 1;
+        }
+    }
+    recording
+}
+
+/// An invariant the simulation broke
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum Violation {
+    #[error("vehicle {0} is not finite")]
+    NonFinite(&'static str),
+    #[error("vehicle is {depth:.2} m below the terrain")]
+    Tunneled { depth: f32 },
+    #[error("vehicle energy reached {energy:.0} J/kg, limit is {limit:.0} J/kg")]
+    EnergyBlowup { energy: f32, limit: f32 },
+    #[error("vehicle was never spawned or disappeared")]
+    Missing,
+}
+
+/// Mechanical energy per kg relative to a point at `reference_height`
+pub fn specific_energy(transform: &Transform, velocity: &Velocity, reference_height: f32) -> f32 {
+    0.5 * velocity.linvel.length_squared()
+        + 0.5 * (velocity.angvel * ROTATIONAL_RADIUS).length_squared()
+        + GRAVITY * (transform.translation.y - reference_height)
+}
+
+/// Checks one vehicle, `ground` is the terrain height under it where the terrain is loaded.
+/// Returns the vehicle's specific energy.
+pub fn check_invariants(
+    transform: &Transform,
+    velocity: &Velocity,
+    ground: Option<f32>,
+    reference_height: f32,
+    config: &FuzzConfig,
+) -> Result<f32, Violation> {
+    if !transform.translation.is_finite() {
+        return Err(Violation::NonFinite("position"));
+    }
+    if !transform.rotation.is_finite() {
+        return Err(Violation::NonFinite("rotation"));
+    }
+    if !velocity.linvel.is_finite() || !velocity.angvel.is_finite() {
+        return Err(Violation::NonFinite("velocity"));
+    }
+    if let Some(ground) = ground {
+        let depth = ground - transform.translation.y;
+        if depth > config.terrain_margin {
+            return Err(Violation::Tunneled { depth });
+        }
+    }
+    let energy = specific_energy(transform, velocity, reference_height);
+    if energy > config.max_specific_energy {
+        return Err(Violation::EnergyBlowup { energy, limit: config.max_specific_energy });
+    }
+    Ok(energy)
+}
+
+/// A case that passed
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzReport {
+    pub seed: u64,
+    pub ticks: u64,
+    /// Highest specific energy seen, J/kg
+    pub max_energy: f32,
+}
+
+/// A case that broke an invariant, with everything needed to reproduce it
+#[derive(Error, Debug, Clone)]
+#[error("physics fuzz case {seed:#x} failed at tick {tick}: {violation}, reproduce with SANDK_FUZZ_SEED={seed:#x}")]
+pub struct FuzzFailure {
+    pub seed: u64,
+    pub tick: u64,
+    pub violation: Violation,
+    pub recording: InputRecording,
+}
+
+impl FuzzFailure {
+    /// Writes the inputs to `directory` for replaying with `SANDK_FUZZ_RECORDING`
+    pub fn save(&self, directory: &Path) -> std::io::Result<PathBuf> {
+        std::fs::create_dir_all(directory)?;
+        let path = directory.join(format!("fuzz-{:016x}.json", self.seed));
+        let json = self.recording.to_json().map_err(std::io::Error::other)?;
+        std::fs::write(&path, json)?;
+        Ok(path)
+    }
+}
+
+/// Recording being fed to player one, after the settle ticks
+#[derive(Resource)]
+struct FuzzPlayback {
+    recording: InputRecording,
+    tick: u64,
+    settle_ticks: u64,
+}
+
+fn play_fuzz_inputs(mut playback: ResMut<FuzzPlayback>, mut players: Query<(&PlayerId, &mut PlayerInput)>) {
+    let Some(tick) = playback.tick.checked_sub(playback.settle_ticks) else {
+        playback.tick += 1;
+        return;
+    };
+    for (player, mut input) in players.iter_mut() {
+        match playback.recording.inputs_at(tick).iter().find(|recorded| recorded.player == player.0) {
+            Some(recorded) => recorded.apply(&mut input),
+            None => *input = PlayerInput::default(),
+        }
+    }
+    playback.tick += 1;
+}
+
+/// Runs `recording` through the headless simulation with the terrain of its seed
+pub fn run_fuzz_case(recording: &InputRecording, config: &FuzzConfig) -> Result<FuzzReport, FuzzFailure> {
+    let seed = recording.seed;
+    let settings = DeterminismSettings { seed, ..default() };
+    let terrain_seed = settings.stream_seed("terrain");
+    let ticks = recording.inputs.last().map_or(0, |input| input.tick + 1);
+
+    let mut app = App::new();
+    app.add_plugins(HeadlessSimulationPlugins.set(HeadlessPlugin {
+        timestep: config.timestep * config.timescale,
+        real_time: false,
+    }))
+    .insert_resource(TerrainSeed(terrain_seed))
+    .insert_resource(FuzzPlayback { recording: recording.clone(), tick: 0, settle_ticks: config.settle_ticks })
+    .add_systems(Update, (play_fuzz_inputs, apply_player_input).chain());
+
+    // No chunk is loaded before the first update, the spawn point is worked out from the noise
+    let terrain = app.world.resource::<TerrainSettings>().clone();
+    let noise = terrain_noise(&terrain, terrain_seed);
+    let spawn_height = terrain.base_height + sample_height(&noise, &terrain, 0.0, 0.0) + SPAWN_CLEARANCE;
+    app.world.send_event(SpawnVehicleEvent {
+        definition: VehicleDefinition { engine_sound: None, headlights: false, ..default() },
+        transform: Transform::from_xyz(0.0, spawn_height, 0.0),
+        driver: PlayerOrAi::Player(0),
+    });
+
+    let fail = |tick, violation| FuzzFailure { seed, tick, violation, recording: recording.clone() };
+    let mut max_energy = f32::MIN;
+    let mut vehicles = app.world.query_filtered::<(&Transform, &Velocity), With<Vehicle>>();
+    for tick in 0..config.settle_ticks + ticks {
+        app.update();
+        let Some((transform, velocity)) = vehicles.iter(&app.world).next() else {
+            // The spawn event is handled during the first update
+            return Err(fail(tick, Violation::Missing));
+        };
+        let position = transform.translation;
+        let ground = app.world.resource::<TerrainQuery>().height(position.x, position.z);
+        let energy =
+            check_invariants(transform, velocity, ground, spawn_height, config).map_err(|violation| fail(tick, violation))?;
+        max_energy = max_energy.max(energy);
+    }
+
+    Ok(FuzzReport { seed, ticks, max_energy })
+}
+
+/// Runs a random case for each seed, stopping at the first failure
+pub fn fuzz(seeds: impl IntoIterator<Item = u64>, config: &FuzzConfig) -> Result<Vec<FuzzReport>, FuzzFailure> {
+    seeds
+        .into_iter()
+        .map(|seed| run_fuzz_case(&random_inputs(seed, config.ticks, config), config))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inputs_repeat_for_a_seed() {
+        let config = FuzzConfig::default();
+        let inputs = random_inputs(7, 300, &config);
+        assert_eq!(inputs, random_inputs(7, 300, &config));
+        assert_ne!(inputs.inputs, random_inputs(8, 300, &config).inputs);
+        assert_eq!(inputs.inputs.len(), 300);
+        assert_eq!(inputs.inputs_at(299).len(), 1);
+        assert_eq!(inputs.tick_rate, 60);
+    }
+
+    #[test]
+    fn test_seeds_print_and_parse_back() {
+        let seed = fuzz_seeds(1, 3)[2];
+        assert_eq!(parse_seed(&format!("{seed:#x}")), Some(seed));
+        assert_eq!(parse_seed("42"), Some(42));
+        assert_eq!(parse_seed("0x5a4e_4b5f"), Some(0x5a4e_4b5f));
+    }
+
+    #[test]
+    fn test_invariants() {
+        let config = FuzzConfig::default();
+        let resting = Transform::from_xyz(0.0, 1.0, 0.0);
+        let still = Velocity::zero();
+        assert!(check_invariants(&resting, &still, Some(0.5), 1.0, &config).is_ok());
+
+        let nan = Transform::from_xyz(f32::NAN, 1.0, 0.0);
+        assert_eq!(check_invariants(&nan, &still, None, 1.0, &config), Err(Violation::NonFinite("position")));
+
+        let sunk = Transform::from_xyz(0.0, -2.0, 0.0);
+        assert!(matches!(check_invariants(&sunk, &still, Some(0.0), 1.0, &config), Err(Violation::Tunneled { .. })));
+        // Nothing to fall through where the terrain isn't loaded
+        assert!(check_invariants(&sunk, &still, None, 1.0, &config).is_ok());
+
+        let launched = Velocity::linear(Vec3::new(0.0, 200.0, 0.0));
+        assert!(matches!(
+            check_invariants(&resting, &launched, None, 1.0, &config),
+            Err(Violation::EnergyBlowup { .. })
+        ));
+    }
+}
@@ -0,0 +1,437 @@
+use bevy::{
+    core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+    prelude::*,
+    render::{
+        render_graph::{Node, NodeRunError, RenderGraphContext},
+        render_resource::*,
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        view::ViewTarget,
+        Extract, RenderApp,
+    },
+};
+use bytemuck::{Pod, Zeroable};
+
+use super::{load_shader, PostProcessChain, PostProcessEffect, PostProcessEffectNodes};
+use crate::game::plugins::weather::{TimeManager, TimeOfDay};
+use crate::game::vehicle::{EngineTemperature, VehicleBodyMaterial, VehicleDirtMaterials};
+use crate::rendering::{render_pipeline, track_render_pipeline};
+
+/// Alternative view modes for exploring at night
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CameraFilterMode {
+    #[default]
+    Off,
+    /// Amplified green phosphor image with grain
+    NightVision,
+    /// False color by temperature, engines and animals glow against the cold ground
+    Thermal,
+}
+
+impl CameraFilterMode {
+    /// Mode the toggle key switches to from this one
+    pub fn next(self) -> Self {
+        match self {
+            CameraFilterMode::Off => CameraFilterMode::NightVision,
+            CameraFilterMode::NightVision => CameraFilterMode::Thermal,
+            CameraFilterMode::Thermal => CameraFilterMode::Off,
+        }
+    }
+
+    fn shader_mode(self) -> u32 {
+        match self {
+            CameraFilterMode::Off => 0,
+            CameraFilterMode::NightVision => 1,
+            CameraFilterMode::Thermal => 2,
+        }
+    }
+}
+
+/// Camera filter mode and tuning
+#[derive(Resource, Clone, Debug)]
+pub struct CameraFilterSettings {
+    pub mode: CameraFilterMode,
+    /// Key cycling off, night vision and thermal
+    pub toggle_key: KeyCode,
+    /// Filters can only be switched on at night and switch off at dawn
+    pub night_only: bool,
+    /// Night vision amplification
+    pub gain: f32,
+    /// Night vision grain strength
+    pub noise: f32,
+    /// Brightest value night vision passes on to tone mapping and bloom
+    pub bloom_clamp: f32,
+    /// Darkening towards the edge of the night vision tube (0.0 - 1.0)
+    pub vignette: f32,
+    /// Temperature in °C that shows coldest in thermal
+    pub thermal_cold: f32,
+    /// Temperature in °C that shows hottest in thermal
+    pub thermal_hot: f32,
+    /// Emissive strength of the hottest heat source, unlit scene brightness stays far below it
+    pub heat_emissive: f32,
+}
+
+impl Default for CameraFilterSettings {
+    fn default() -> Self {
+        Self {
+            mode: CameraFilterMode::Off,
+            toggle_key: KeyCode::N,
+            night_only: true,
+            gain: 12.0,
+            noise: 0.15,
+            bloom_clamp: 2.0,
+            vignette: 0.8,
+            thermal_cold: 0.0,
+            thermal_hot: 110.0,
+            heat_emissive: 8.0,
+        }
+    }
+}
+
+impl CameraFilterSettings {
+    /// Heat of a temperature on the thermal scale (0.0 - 1.0)
+    pub fn heat(&self, temperature: f32) -> f32 {
+        ((temperature - self.thermal_cold) / (self.thermal_hot - self.thermal_cold).max(f32::EPSILON)).clamp(0.0, 1.0)
+    }
+
+    /// Emissive that makes a surface at `temperature` read as such in thermal
+    pub fn heat_color(&self, temperature: f32) -> Color {
+        let emissive = self.heat(temperature) * self.heat_emissive;
+        Color::rgb_linear(emissive, emissive, emissive)
+    }
+}
+
+/// Something warm that shows up in thermal, like an animal. Vehicles use their engine temperature.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct HeatSource {
+    /// Surface temperature in °C
+    pub temperature: f32,
+}
+
+/// Cycles the filter mode on the toggle key, and switches it off when night ends
+fn toggle_camera_filter(
+    keyboard: Res<Input<KeyCode>>,
+    time: Option<Res<TimeManager>>,
+    mut settings: ResMut<CameraFilterSettings>,
+) {
+    let night = !settings.night_only || time.map_or(true, |time| time.time_of_day() == TimeOfDay::Night);
+    if !night {
+        if settings.mode != CameraFilterMode::Off {
+            settings.mode = CameraFilterMode::Off;
+        }
+        return;
+    }
+    if keyboard.just_pressed(settings.toggle_key) {
+        settings.mode = settings.mode.next();
+    }
+}
+
+/// Enables the filter's chain slot only while a mode is on
+fn sync_camera_filter_chain(settings: Res<CameraFilterSettings>, mut chain: ResMut<PostProcessChain>) {
+    if !settings.is_changed() {
+        return;
+    }
+    chain.set_enabled(PostProcessEffect::CameraFilter, settings.mode != CameraFilterMode::Off);
+}
+
+/// Drives the emissive of heat sources from their temperature in thermal, and clears it otherwise
+fn update_heat_emissive(
+    settings: Res<CameraFilterSettings>,
+    vehicles: Query<(&EngineTemperature, &VehicleDirtMaterials)>,
+    sources: Query<(&HeatSource, &Handle<StandardMaterial>)>,
+    mut body_materials: ResMut<Assets<VehicleBodyMaterial>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let thermal = settings.mode == CameraFilterMode::Thermal;
+    // Outside thermal only the frame the mode changed needs to clear the glow
+    if !thermal && !settings.is_changed() {
+        return;
+    }
+
+    for (engine, handles) in vehicles.iter() {
+        let emissive = if thermal { settings.heat_color(engine.temperature) } else { Color::BLACK };
+        for handle in handles.0.iter() {
+            if let Some(material) = body_materials.get_mut(handle) {
+                material.base.emissive = emissive;
+            }
+        }
+    }
+    for (source, handle) in sources.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.emissive = if thermal { settings.heat_color(source.temperature) } else { Color::BLACK };
+        }
+    }
+}
+
+/// GPU-side filter parameters
+#[derive(Resource, ShaderType, Clone, Copy, Debug, Default, Pod, Zeroable)]
+#[repr(C)]
+pub struct CameraFilterUniform {
+    /// 0 off, 1 night vision, 2 thermal
+    pub mode: u32,
+    /// Seconds since startup, animates the grain
+    pub time: f32,
+    pub gain: f32,
+    pub noise: f32,
+    pub bloom_clamp: f32,
+    /// Brightness that reads as the hottest color
+    pub thermal_range: f32,
+    pub vignette: f32,
+    _padding: f32,
+}
+
+/// Copies the filter settings into the render world
+fn extract_camera_filter(
+    mut commands: Commands,
+    settings: Extract<Res<CameraFilterSettings>>,
+    time: Extract<Res<Time>>,
+) {
+    commands.insert_resource(CameraFilterUniform {
+        mode: settings.mode.shader_mode(),
+        time: time.elapsed_seconds_wrapped(),
+        gain: settings.gain,
+        noise: settings.noise,
+        bloom_clamp: settings.bloom_clamp,
+        thermal_range: settings.heat_emissive.max(0.01),
+        vignette: settings.vignette,
+        _padding: 0.0,
+    });
+}
+
+/// Pipeline for the full-screen filter pass
+#[derive(Resource)]
+pub struct CameraFilterPipeline {
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    settings_buffer: Buffer,
+    pipeline_id: CachedRenderPipelineId,
+}
+
+impl FromWorld for CameraFilterPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let shader = load_shader(world, "camera_filter.wgsl");
+
+        let render_device = world.resource::<RenderDevice>();
+
+        let layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("camera_filter_bind_group_layout"),
+            entries: &[
+                // Scene texture
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // Sampler
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                // Filter settings
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: Some(CameraFilterUniform::min_size()),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let sampler = render_device.create_sampler(&SamplerDescriptor::default());
+
+        let settings_buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("camera_filter_settings_buffer"),
+            size: CameraFilterUniform::min_size().get(),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let pipeline_id = world.resource::<PipelineCache>().queue_render_pipeline(RenderPipelineDescriptor {
+            label: Some("camera_filter_pipeline".into()),
+            layout: vec![layout.clone()],
+            push_constant_ranges: vec![],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader,
+                shader_defs: vec![],
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format: ViewTarget::TEXTURE_FORMAT_HDR,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+        });
+
+        track_render_pipeline(world, pipeline_id);
+
+        Self {
+            layout,
+            sampler,
+            settings_buffer,
+            pipeline_id,
+        }
+    }
+}
+
+/// Node in the render graph that applies the active camera filter before tone mapping
+pub struct CameraFilterNode {
+    query: QueryState<&'static ViewTarget>,
+}
+
+impl CameraFilterNode {
+    /// Name of the node in the render graph
+    pub const NAME: &'static str = "camera_filter";
+}
+
+impl FromWorld for CameraFilterNode {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for CameraFilterNode {
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let Ok(view_target) = self.query.get_manual(world, graph.view_entity()) else {
+            return Ok(());
+        };
+        let (Some(pipeline), Some(uniform)) = (
+            world.get_resource::<CameraFilterPipeline>(),
+            world.get_resource::<CameraFilterUniform>(),
+        ) else {
+            return Ok(());
+        };
+        if uniform.mode == 0 {
+            return Ok(());
+        }
+        let Some(render_pipeline) = render_pipeline(world, pipeline.pipeline_id) else {
+            return Ok(());
+        };
+
+        world
+            .resource::<RenderQueue>()
+            .write_buffer(&pipeline.settings_buffer, 0, bytemuck::bytes_of(uniform));
+
+        let post_process = view_target.post_process_write();
+        let bind_group = render_context.render_device().create_bind_group(
+            "camera_filter_bind_group",
+            &pipeline.layout,
+            &BindGroupEntries::sequential((
+                post_process.source,
+                &pipeline.sampler,
+                pipeline.settings_buffer.as_entire_binding(),
+            )),
+        );
+
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("camera_filter_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: post_process.destination,
+                resolve_target: None,
+                ops: Operations::default(),
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_render_pipeline(render_pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
+
+/// Plugin that adds night vision and thermal camera filters
+pub struct CameraFilterPlugin;
+
+impl Plugin for CameraFilterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraFilterSettings>().add_systems(
+            Update,
+            (toggle_camera_filter, sync_camera_filter_chain, update_heat_emissive).chain(),
+        );
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.add_systems(ExtractSchedule, extract_camera_filter);
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<CameraFilterPipeline>();
+
+        let node = CameraFilterNode::from_world(&mut render_app.world);
+        render_app
+            .world
+            .resource_mut::<PostProcessEffectNodes>()
+            .insert(PostProcessEffect::CameraFilter, node);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heat_scale() {
+        let settings = CameraFilterSettings::default();
+        assert_eq!(settings.heat(-20.0), 0.0);
+        assert_eq!(settings.heat(200.0), 1.0);
+        assert!(settings.heat(38.0) < settings.heat(90.0));
+        assert_eq!(settings.heat_color(110.0), Color::rgb_linear(8.0, 8.0, 8.0));
+    }
+
+    #[test]
+    fn test_filters_only_at_night() {
+        let mut app = App::new();
+        let mut time = TimeManager::default();
+        time.set_time(12.0);
+        app.init_resource::<Input<KeyCode>>()
+            .init_resource::<CameraFilterSettings>()
+            .insert_resource(time)
+            .add_systems(Update, toggle_camera_filter);
+
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::N);
+        app.update();
+        assert_eq!(app.world.resource::<CameraFilterSettings>().mode, CameraFilterMode::Off);
+
+        app.world.resource_mut::<TimeManager>().set_time(23.0);
+        app.world.resource_mut::<Input<KeyCode>>().clear();
+        app.world.resource_mut::<Input<KeyCode>>().release(KeyCode::N);
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::N);
+        app.update();
+        assert_eq!(app.world.resource::<CameraFilterSettings>().mode, CameraFilterMode::NightVision);
+
+        // Dawn switches it back off
+        app.world.resource_mut::<TimeManager>().set_time(7.0);
+        app.update();
+        assert_eq!(app.world.resource::<CameraFilterSettings>().mode, CameraFilterMode::Off);
+    }
+}
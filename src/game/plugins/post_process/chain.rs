@@ -0,0 +1,307 @@
+use bevy::{
+    prelude::*,
+    render::{
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext},
+        renderer::RenderContext,
+        RenderApp,
+    },
+    utils::HashMap,
+};
+
+use super::{GpuPass, GpuTimer, PostProcessSettings};
+
+/// Effects that can be placed in the post-process chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PostProcessEffect {
+    /// Screen space ambient occlusion
+    Ssao,
+    /// Temporal anti-aliasing resolve
+    Taa,
+    /// Bokeh depth of field
+    DepthOfField,
+    /// Night vision and thermal view modes
+    CameraFilter,
+    /// Rain droplets and mud on the chase camera's lens
+    Lens,
+    /// Tone mapping, bloom and color adjustments
+    ToneMapping,
+    /// LUT color grading
+    ColorGrading,
+}
+
+impl PostProcessEffect {
+    /// Human readable name, used by the debug UI and profiling labels
+    pub fn label(&self) -> &'static str {
+        match self {
+            PostProcessEffect::Ssao => "SSAO",
+            PostProcessEffect::Taa => "TAA",
+            PostProcessEffect::DepthOfField => "Depth of Field",
+            PostProcessEffect::CameraFilter => "Camera Filter",
+            PostProcessEffect::Lens => "Lens Droplets & Dirt",
+            PostProcessEffect::ToneMapping => "Tone Mapping",
+            PostProcessEffect::ColorGrading => "Color Grading",
+        }
+    }
+}
+
+/// One entry of the post-process chain
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EffectSlot {
+    pub effect: PostProcessEffect,
+    pub enabled: bool,
+}
+
+/// Ordered list of post-process effects and their toggles.
+/// Disabled effects are skipped entirely; their render passes are never recorded.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct PostProcessChain {
+    slots: Vec<EffectSlot>,
+}
+
+impl Default for PostProcessChain {
+    fn default() -> Self {
+        // Occlusion first so TAA smooths it, DOF on the resolved image, grading after tone mapping.
+        // Camera filters work on HDR so tone mapping and bloom see the filtered image, the lens sits
+        // in front of the camera and its filter.
+        let mut chain = Self::new(&[
+            PostProcessEffect::Ssao,
+            PostProcessEffect::Taa,
+            PostProcessEffect::DepthOfField,
+            PostProcessEffect::CameraFilter,
+            PostProcessEffect::Lens,
+            PostProcessEffect::ToneMapping,
+            PostProcessEffect::ColorGrading,
+        ]);
+        // Off until a filter mode is picked, and while the lens is clean
+        chain.set_enabled(PostProcessEffect::CameraFilter, false);
+        chain.set_enabled(PostProcessEffect::Lens, false);
+        chain
+    }
+}
+
+impl PostProcessChain {
+    /// Creates a chain with the given effects in order, all enabled
+    pub fn new(effects: &[PostProcessEffect]) -> Self {
+        let mut chain = Self { slots: Vec::with_capacity(effects.len()) };
+        for effect in effects {
+            if chain.position(*effect).is_none() {
+                chain.slots.push(EffectSlot { effect: *effect, enabled: true });
+            }
+        }
+        chain
+    }
+
+    /// All slots in execution order
+    pub fn slots(&self) -> &[EffectSlot] {
+        &self.slots
+    }
+
+    /// Enabled effects in execution order
+    pub fn enabled_effects(&self) -> impl Iterator<Item = PostProcessEffect> + '_ {
+        self.slots.iter().filter(|slot| slot.enabled).map(|slot| slot.effect)
+    }
+
+    /// Index of an effect in the chain
+    pub fn position(&self, effect: PostProcessEffect) -> Option<usize> {
+        self.slots.iter().position(|slot| slot.effect == effect)
+    }
+
+    /// Whether an effect is present and enabled
+    pub fn is_enabled(&self, effect: PostProcessEffect) -> bool {
+        self.slots.iter().any(|slot| slot.effect == effect && slot.enabled)
+    }
+
+    /// Enables or disables an effect, returns false if it is not in the chain
+    pub fn set_enabled(&mut self, effect: PostProcessEffect, enabled: bool) -> bool {
+        match self.slots.iter_mut().find(|slot| slot.effect == effect) {
+            Some(slot) => {
+                slot.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves an effect to `index` (clamped to the chain length), keeping the order of the others
+    pub fn move_to(&mut self, effect: PostProcessEffect, index: usize) -> bool {
+        let Some(from) = self.position(effect) else {
+            return false;
+        };
+        let slot = self.slots.remove(from);
+        self.slots.insert(index.min(self.slots.len()), slot);
+        true
+    }
+
+    /// Appends an effect at the end of the chain if it isn't already present
+    pub fn push(&mut self, effect: PostProcessEffect, enabled: bool) {
+        if self.position(effect).is_none() {
+            self.slots.push(EffectSlot { effect, enabled });
+        }
+    }
+}
+
+impl ExtractResource for PostProcessChain {
+    type Source = PostProcessChain;
+
+    fn extract_resource(source: &Self::Source) -> Self {
+        source.clone()
+    }
+}
+
+/// Render graph nodes for each effect, run by [`PostProcessChainNode`] in chain order.
+/// Effect plugins register their node here instead of adding it to the render graph.
+#[derive(Resource, Default)]
+pub struct PostProcessEffectNodes {
+    nodes: HashMap<PostProcessEffect, Box<dyn Node>>,
+}
+
+impl PostProcessEffectNodes {
+    /// Registers the node that renders `effect`, replacing any previous one
+    pub fn insert(&mut self, effect: PostProcessEffect, node: impl Node) {
+        self.nodes.insert(effect, Box::new(node));
+    }
+
+    /// Whether a node is registered for `effect`
+    pub fn contains(&self, effect: PostProcessEffect) -> bool {
+        self.nodes.contains_key(&effect)
+    }
+}
+
+/// Mirrors the per-effect toggles in [`PostProcessSettings`] into the chain when they change
+fn sync_chain_with_settings(settings: Res<PostProcessSettings>, mut chain: ResMut<PostProcessChain>) {
+    if !settings.is_changed() {
+        return;
+    }
+    chain.set_enabled(PostProcessEffect::Ssao, settings.ssao_enabled);
+    chain.set_enabled(PostProcessEffect::DepthOfField, settings.dof_enabled);
+    chain.set_enabled(PostProcessEffect::ColorGrading, settings.lut_intensity > 0.0);
+}
+
+/// Single render graph node that runs the enabled effects in the configured order
+pub struct PostProcessChainNode;
+
+impl PostProcessChainNode {
+    /// Name of the node in the render graph
+    pub const NAME: &'static str = "post_process_chain";
+}
+
+impl Node for PostProcessChainNode {
+    fn update(&mut self, world: &mut World) {
+        world.resource_scope(|world, mut nodes: Mut<PostProcessEffectNodes>| {
+            for node in nodes.nodes.values_mut() {
+                node.update(world);
+            }
+        });
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let Some(chain) = world.get_resource::<PostProcessChain>() else {
+            return Ok(());
+        };
+        let nodes = world.resource::<PostProcessEffectNodes>();
+        let timer = world.get_resource::<GpuTimer>();
+
+        if let Some(timer) = timer {
+            timer.begin(render_context.command_encoder(), GpuPass::PostProcess);
+        }
+        for effect in chain.enabled_effects() {
+            if let Some(node) = nodes.nodes.get(&effect) {
+                node.run(graph, render_context, world)?;
+            }
+        }
+        if let Some(timer) = timer {
+            timer.end(render_context.command_encoder(), GpuPass::PostProcess);
+        }
+
+        Ok(())
+    }
+}
+
+/// Plugin that owns the effect chain and its render graph node
+pub struct PostProcessChainPlugin;
+
+impl Plugin for PostProcessChainPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PostProcessChain>()
+            .add_plugins(ExtractResourcePlugin::<PostProcessChain>::default())
+            .add_systems(PostUpdate, sync_chain_with_settings);
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<PostProcessEffectNodes>();
+
+        let mut render_graph = render_app.world.resource_mut::<RenderGraph>();
+        render_graph.add_node(PostProcessChainNode::NAME, PostProcessChainNode);
+        render_graph.add_node_edge("main_pass", PostProcessChainNode::NAME);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_order() {
+        let chain = PostProcessChain::default();
+        let order: Vec<_> = chain.enabled_effects().collect();
+        assert_eq!(order.first(), Some(&PostProcessEffect::Ssao));
+        assert_eq!(order.last(), Some(&PostProcessEffect::ColorGrading));
+        assert!(chain.position(PostProcessEffect::Taa) < chain.position(PostProcessEffect::DepthOfField));
+    }
+
+    #[test]
+    fn test_disabled_effects_are_skipped() {
+        let mut chain = PostProcessChain::default();
+        assert!(chain.set_enabled(PostProcessEffect::DepthOfField, false));
+        assert!(!chain.is_enabled(PostProcessEffect::DepthOfField));
+        assert!(chain.enabled_effects().all(|effect| effect != PostProcessEffect::DepthOfField));
+        assert_eq!(chain.slots().len(), 7);
+        assert!(!chain.is_enabled(PostProcessEffect::CameraFilter));
+        assert!(!chain.is_enabled(PostProcessEffect::Lens));
+    }
+
+    #[test]
+    fn test_reorder() {
+        let mut chain = PostProcessChain::default();
+        assert!(chain.move_to(PostProcessEffect::ColorGrading, 0));
+        assert_eq!(chain.position(PostProcessEffect::ColorGrading), Some(0));
+        assert_eq!(chain.position(PostProcessEffect::Ssao), Some(1));
+
+        // Out of range indices move to the end
+        chain.move_to(PostProcessEffect::ColorGrading, 100);
+        assert_eq!(chain.position(PostProcessEffect::ColorGrading), Some(6));
+    }
+
+    #[test]
+    fn test_duplicates_are_ignored() {
+        let mut chain = PostProcessChain::new(&[PostProcessEffect::Taa, PostProcessEffect::Taa]);
+        chain.push(PostProcessEffect::Taa, false);
+        assert_eq!(chain.slots().len(), 1);
+        assert!(chain.is_enabled(PostProcessEffect::Taa));
+    }
+
+    #[test]
+    fn test_settings_toggles_are_mirrored() {
+        let mut app = App::new();
+        app.init_resource::<PostProcessChain>()
+            .insert_resource(PostProcessSettings {
+                ssao_enabled: false,
+                dof_enabled: true,
+                ..default()
+            })
+            .add_systems(Update, sync_chain_with_settings);
+
+        app.update();
+
+        let chain = app.world.resource::<PostProcessChain>();
+        assert!(!chain.is_enabled(PostProcessEffect::Ssao));
+        assert!(chain.is_enabled(PostProcessEffect::DepthOfField));
+    }
+}
@@ -0,0 +1,832 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+    prelude::*,
+    render::{
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        render_graph::{Node, NodeRunError, RenderGraphContext},
+        render_resource::*,
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        view::ViewTarget,
+        Extract, RenderApp,
+    },
+    utils::{BoxedFuture, HashMap},
+};
+use bytemuck::{Pod, Zeroable};
+
+use super::{load_shader, PostProcessEffect, PostProcessEffectNodes, PostProcessSettings};
+use crate::game::plugins::camera::GameCamera;
+use crate::game::plugins::weather::{Weather, WeatherManager};
+use crate::rendering::{render_pipeline, track_render_pipeline};
+use crate::terrain::{Biome, BiomeField, TerrainChunk, CHUNK_SIZE};
+
+/// Grade of each biome, alpine keeps the preset's grade
+const BIOME_GRADES: [(Biome, &str); 2] = [(Biome::Desert, "luts/cinematic.cube"), (Biome::Forest, "luts/vibrant.cube")];
+
+/// Weather that overrides the biome's grade while it lasts
+const WEATHER_GRADES: [(Weather, &str); 3] = [
+    (Weather::Rain, "luts/moody.cube"),
+    (Weather::Storm, "luts/moody.cube"),
+    (Weather::Fog, "luts/moody.cube"),
+];
+
+/// A 3D color lookup table, stored red-fastest then green then blue
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct ColorLut {
+    /// Number of entries along each axis
+    pub size: u32,
+    /// `size^3` RGBA entries
+    pub data: Vec<[f32; 4]>,
+}
+
+/// Errors produced while decoding LUT files
+#[derive(Debug, thiserror::Error)]
+pub enum LutError {
+    #[error("missing LUT_3D_SIZE in .cube file")]
+    MissingSize,
+    #[error("invalid .cube line {line}: {content}")]
+    InvalidLine { line: usize, content: String },
+    #[error("expected {expected} LUT entries, found {found}")]
+    WrongEntryCount { expected: usize, found: usize },
+    #[error("strip LUT must be size^2 x size pixels, got {width}x{height}")]
+    InvalidStripDimensions { width: u32, height: u32 },
+    #[error(transparent)]
+    Image(#[from] image::ImageError),
+    #[error(transparent)]
+    Utf8(#[from] std::str::Utf8Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl ColorLut {
+    /// Identity LUT that leaves colors untouched
+    pub fn identity(size: u32) -> Self {
+        let max = (size - 1) as f32;
+        let mut data = Vec::with_capacity((size * size * size) as usize);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    data.push([r as f32 / max, g as f32 / max, b as f32 / max, 1.0]);
+                }
+            }
+        }
+        Self { size, data }
+    }
+
+    /// Entry by entry blend towards `other`, `None` when the sizes differ
+    pub fn lerp(&self, other: &ColorLut, t: f32) -> Option<Self> {
+        if self.size != other.size {
+            return None;
+        }
+        let data = self
+            .data
+            .iter()
+            .zip(&other.data)
+            .map(|(a, b)| std::array::from_fn(|channel| a[channel] + (b[channel] - a[channel]) * t))
+            .collect();
+        Some(Self { size: self.size, data })
+    }
+
+    /// Parses an Adobe/Resolve `.cube` file
+    pub fn from_cube(text: &str) -> Result<Self, LutError> {
+        let mut size = None;
+        let mut data = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.split_whitespace();
+            let first = parts.next().unwrap_or_default();
+            match first {
+                "LUT_3D_SIZE" => {
+                    size = parts.next().and_then(|s| s.parse::<u32>().ok());
+                    if size.is_none() {
+                        return Err(LutError::InvalidLine { line: index + 1, content: line.to_string() });
+                    }
+                }
+                // Metadata we don't need; inputs are assumed to be in [0, 1]
+                "TITLE" | "DOMAIN_MIN" | "DOMAIN_MAX" | "LUT_1D_SIZE" => {}
+                _ => {
+                    let values: Result<Vec<f32>, _> = line.split_whitespace().map(str::parse).collect();
+                    match values.as_deref() {
+                        Ok([r, g, b]) => data.push([*r, *g, *b, 1.0]),
+                        _ => return Err(LutError::InvalidLine { line: index + 1, content: line.to_string() }),
+                    }
+                }
+            }
+        }
+
+        let size = size.ok_or(LutError::MissingSize)?;
+        let expected = (size * size * size) as usize;
+        if data.len() != expected {
+            return Err(LutError::WrongEntryCount { expected, found: data.len() });
+        }
+        Ok(Self { size, data })
+    }
+
+    /// Decodes a horizontal strip image: `size` slices of `size x size`, blue increasing per slice
+    pub fn from_strip_image(bytes: &[u8]) -> Result<Self, LutError> {
+        let image = image::load_from_memory(bytes)?.to_rgba32f();
+        let (width, height) = image.dimensions();
+        if height == 0 || width != height * height {
+            return Err(LutError::InvalidStripDimensions { width, height });
+        }
+
+        let size = height;
+        let mut data = Vec::with_capacity((size * size * size) as usize);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    let pixel = image.get_pixel(b * size + r, g);
+                    data.push([pixel[0], pixel[1], pixel[2], 1.0]);
+                }
+            }
+        }
+        Ok(Self { size, data })
+    }
+}
+
+/// Asset loader for `.cube` and `.lut.png` strip LUTs
+#[derive(Default)]
+pub struct ColorLutLoader;
+
+impl AssetLoader for ColorLutLoader {
+    type Asset = ColorLut;
+    type Settings = ();
+    type Error = LutError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<ColorLut, LutError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let is_cube = load_context
+                .path()
+                .extension()
+                .map_or(false, |ext| ext.eq_ignore_ascii_case("cube"));
+            if is_cube {
+                ColorLut::from_cube(std::str::from_utf8(&bytes)?)
+            } else {
+                ColorLut::from_strip_image(&bytes)
+            }
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["cube", "lut.png"]
+    }
+}
+
+/// Marks an area of the world that uses a biome's color grade
+/// The camera picks the closest region it is inside of
+#[derive(Component, Clone, Debug)]
+pub struct ColorGradeRegion {
+    /// Biome name, looked up in [`ColorGradeLibrary::biomes`]
+    pub biome: String,
+    /// Radius of the region around its transform in meters
+    pub radius: f32,
+}
+
+/// LUTs available for biome and weather grading
+#[derive(Resource, Default, Clone, Debug)]
+pub struct ColorGradeLibrary {
+    /// Grades keyed by biome name
+    pub biomes: HashMap<String, Handle<ColorLut>>,
+    /// Grades that override the biome while a given weather is active
+    pub weather: Vec<(Weather, Handle<ColorLut>)>,
+    /// LUT selected by the active [`PostProcessSettings`] preset, used when nothing else applies
+    pub preset: Option<Handle<ColorLut>>,
+}
+
+impl ColorGradeLibrary {
+    /// Picks the grade for the given biome and weather, weather overrides take priority
+    pub fn select(&self, biome: Option<&str>, weather: Option<Weather>) -> Option<Handle<ColorLut>> {
+        weather
+            .and_then(|weather| self.weather.iter().find(|(w, _)| *w == weather))
+            .map(|(_, lut)| lut.clone())
+            .or_else(|| biome.and_then(|biome| self.biomes.get(biome).cloned()))
+            .or_else(|| self.preset.clone())
+    }
+}
+
+/// Current color grade and the cross-fade towards the next one
+#[derive(Resource, Clone, Debug)]
+pub struct ColorGrading {
+    /// LUT currently on screen, `None` means identity
+    pub current: Option<Handle<ColorLut>>,
+    /// LUT being blended towards
+    pub next: Option<Handle<ColorLut>>,
+    /// Blend factor between `current` and `next` (0.0 - 1.0)
+    pub blend: f32,
+    /// Duration of a full cross-fade in seconds
+    pub transition_seconds: f32,
+    fading: bool,
+}
+
+impl Default for ColorGrading {
+    fn default() -> Self {
+        Self {
+            current: None,
+            next: None,
+            blend: 0.0,
+            transition_seconds: 3.0,
+            fading: false,
+        }
+    }
+}
+
+impl ColorGrading {
+    /// Whether a cross-fade is in progress
+    pub fn is_fading(&self) -> bool {
+        self.fading
+    }
+
+    /// Starts blending towards `lut` unless it is already the target. A fade interrupted on the
+    /// way carries on from the grade on screen, so changing target never pops.
+    pub fn transition_to(&mut self, lut: Option<Handle<ColorLut>>, luts: &mut Assets<ColorLut>) {
+        let target = if self.fading { &self.next } else { &self.current };
+        if *target == lut {
+            return;
+        }
+        if self.fading {
+            if self.current == lut {
+                // Heading back, run the same fade in reverse from where it got to
+                std::mem::swap(&mut self.current, &mut self.next);
+                self.blend = 1.0 - self.blend;
+                return;
+            }
+            self.current = self.blended(luts);
+        }
+        self.fading = self.current != lut;
+        self.next = lut;
+        self.blend = 0.0;
+    }
+
+    /// A LUT of the mid-fade grade on screen. LUTs that haven't loaded grade with identity, like
+    /// they do on the GPU. Falls back to the grade that dominates when the two can't be blended.
+    fn blended(&self, luts: &mut Assets<ColorLut>) -> Option<Handle<ColorLut>> {
+        let current = self.current.as_ref().and_then(|handle| luts.get(handle));
+        let next = self.next.as_ref().and_then(|handle| luts.get(handle));
+        let blended = match (current, next) {
+            (None, None) => return None,
+            (Some(current), None) => current.lerp(&ColorLut::identity(current.size), self.blend),
+            (None, Some(next)) => ColorLut::identity(next.size).lerp(next, self.blend),
+            (Some(current), Some(next)) => current.lerp(next, self.blend),
+        };
+        match blended {
+            Some(lut) => Some(luts.add(lut)),
+            None if self.blend >= 0.5 => self.next.clone(),
+            None => self.current.clone(),
+        }
+    }
+
+    /// Advances the cross-fade, promoting `next` to `current` when it finishes
+    pub fn advance(&mut self, delta_seconds: f32) {
+        if !self.fading {
+            return;
+        }
+        self.blend += delta_seconds / self.transition_seconds.max(0.001);
+        if self.blend >= 1.0 {
+            self.current = self.next.take();
+            self.blend = 0.0;
+            self.fading = false;
+        }
+    }
+}
+
+/// Loads the LUT named by the post-process preset when the settings change
+fn load_preset_lut(
+    settings: Res<PostProcessSettings>,
+    asset_server: Res<AssetServer>,
+    mut library: ResMut<ColorGradeLibrary>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    library.preset = settings.color_lut.as_ref().map(|path| asset_server.load(path.as_str()));
+}
+
+/// Registers the biome and weather grades
+fn register_color_grades(asset_server: Res<AssetServer>, mut library: ResMut<ColorGradeLibrary>) {
+    for (biome, path) in BIOME_GRADES {
+        library.biomes.insert(biome.name().to_string(), asset_server.load(path));
+    }
+    for (weather, path) in WEATHER_GRADES {
+        library.weather.push((weather, asset_server.load(path)));
+    }
+}
+
+/// Makes every loaded terrain chunk a region graded for the biome at its middle. Chunks are
+/// centered on their transform, so the closest region is always the chunk the camera is over.
+fn add_chunk_grade_regions(
+    mut commands: Commands,
+    biomes: Option<Res<BiomeField>>,
+    chunks: Query<(Entity, &Transform), Added<TerrainChunk>>,
+) {
+    let Some(biomes) = biomes else {
+        return;
+    };
+    for (entity, transform) in chunks.iter() {
+        let center = transform.translation;
+        commands.entity(entity).insert(ColorGradeRegion {
+            biome: biomes.sample(center.x, center.z).biome.name().to_string(),
+            radius: CHUNK_SIZE * std::f32::consts::FRAC_1_SQRT_2,
+        });
+    }
+}
+
+/// Chooses the grade from the camera's biome region and the weather, and advances the fade
+fn update_color_grading(
+    mut grading: ResMut<ColorGrading>,
+    mut luts: ResMut<Assets<ColorLut>>,
+    library: Res<ColorGradeLibrary>,
+    weather: Option<Res<WeatherManager>>,
+    cameras: Query<&GlobalTransform, With<GameCamera>>,
+    regions: Query<(&GlobalTransform, &ColorGradeRegion)>,
+    time: Res<Time>,
+) {
+    let biome = cameras.iter().next().and_then(|camera| {
+        let position = camera.translation();
+        regions
+            .iter()
+            .map(|(transform, region)| (transform.translation().distance(position), region))
+            .filter(|(distance, region)| *distance <= region.radius)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, region)| region.biome.as_str())
+    });
+
+    // Follow the weather we're heading into so the grade fades with the weather itself
+    let weather = weather.map(|manager| {
+        let state = manager.current_state();
+        state.transitioning_to().unwrap_or_else(|| state.weather())
+    });
+
+    grading.transition_to(library.select(biome, weather), &mut luts);
+    grading.advance(time.delta_seconds());
+}
+
+/// GPU-side grading parameters
+#[derive(Resource, ShaderType, Clone, Copy, Debug, Default, Pod, Zeroable)]
+#[repr(C)]
+pub struct ColorGradingUniform {
+    /// Blend factor between the current and next LUT
+    pub blend: f32,
+    /// Mix between the ungraded and graded image
+    pub intensity: f32,
+    /// Size of the current LUT
+    pub current_size: f32,
+    /// Size of the next LUT
+    pub next_size: f32,
+}
+
+/// Whether the grading pass should run this frame
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct ColorGradingEnabled(pub bool);
+
+impl ExtractResource for ColorGradingEnabled {
+    type Source = PostProcessSettings;
+
+    fn extract_resource(settings: &Self::Source) -> Self {
+        Self(settings.lut_intensity > 0.0)
+    }
+}
+
+/// LUT textures uploaded to the GPU, keyed by asset id
+#[derive(Resource, Default)]
+pub struct GpuColorLuts {
+    luts: HashMap<AssetId<ColorLut>, (Texture, TextureView, u32)>,
+    /// Textures bound this frame: (current, next)
+    active: Option<[(TextureView, u32); 2]>,
+}
+
+fn create_lut_texture(device: &RenderDevice, queue: &RenderQueue, lut: &ColorLut) -> (Texture, TextureView) {
+    let data: Vec<u16> = lut
+        .data
+        .iter()
+        .flat_map(|c| c.map(|v| half_from_f32(v.clamp(0.0, 1.0))))
+        .collect();
+    let texture = device.create_texture_with_data(
+        queue,
+        &TextureDescriptor {
+            label: Some("color_grading_lut"),
+            size: Extent3d {
+                width: lut.size,
+                height: lut.size,
+                depth_or_array_layers: lut.size,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D3,
+            format: TextureFormat::Rgba16Float,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        },
+        bytemuck::cast_slice(&data),
+    );
+    let view = texture.create_view(&TextureViewDescriptor::default());
+    (texture, view)
+}
+
+/// Converts a value in [0, 1] to IEEE half precision bits
+fn half_from_f32(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent <= 0 {
+        // Too small for a normal half, flush to zero
+        0
+    } else {
+        ((exponent as u16) << 10) | (mantissa >> 13) as u16
+    }
+}
+
+/// Uploads newly loaded LUTs and records which ones are blended this frame
+fn extract_color_grading(
+    mut commands: Commands,
+    grading: Extract<Res<ColorGrading>>,
+    settings: Extract<Res<PostProcessSettings>>,
+    luts: Extract<Res<Assets<ColorLut>>>,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+    mut gpu_luts: ResMut<GpuColorLuts>,
+    pipeline: Option<Res<ColorGradingPipeline>>,
+) {
+    let Some(pipeline) = pipeline else {
+        return;
+    };
+
+    let mut resolve = |handle: &Option<Handle<ColorLut>>| -> (TextureView, u32) {
+        let Some(handle) = handle else {
+            return (pipeline.identity_view.clone(), pipeline.identity_size);
+        };
+        let id = handle.id();
+        if !gpu_luts.luts.contains_key(&id) {
+            let Some(lut) = luts.get(handle) else {
+                // Still loading, grade with identity until it arrives
+                return (pipeline.identity_view.clone(), pipeline.identity_size);
+            };
+            let (texture, view) = create_lut_texture(&device, &queue, lut);
+            gpu_luts.luts.insert(id, (texture, view, lut.size));
+        }
+        let (_, view, size) = &gpu_luts.luts[&id];
+        (view.clone(), *size)
+    };
+
+    let current = resolve(&grading.current);
+    let next = if grading.is_fading() { resolve(&grading.next) } else { current.clone() };
+
+    commands.insert_resource(ColorGradingUniform {
+        blend: grading.blend,
+        intensity: settings.lut_intensity,
+        current_size: current.1 as f32,
+        next_size: next.1 as f32,
+    });
+    gpu_luts.active = Some([current, next]);
+}
+
+/// Pipeline for the full-screen grading pass
+#[derive(Resource)]
+pub struct ColorGradingPipeline {
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    settings_buffer: Buffer,
+    identity_view: TextureView,
+    identity_size: u32,
+    pipeline_id: CachedRenderPipelineId,
+}
+
+impl FromWorld for ColorGradingPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let shader = load_shader(world, "color_grading.wgsl");
+
+        let render_device = world.resource::<RenderDevice>();
+        let render_queue = world.resource::<RenderQueue>();
+
+        let lut_entry = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                sample_type: TextureSampleType::Float { filterable: true },
+                view_dimension: TextureViewDimension::D3,
+                multisampled: false,
+            },
+            count: None,
+        };
+
+        let layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("color_grading_bind_group_layout"),
+            entries: &[
+                // Scene texture
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // Current and next LUT
+                lut_entry(1),
+                lut_entry(2),
+                // Sampler
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                // Grading settings
+                BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: Some(ColorGradingUniform::min_size()),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        // Clamp so the edge texels of the LUT are never blended with the opposite side
+        let sampler = render_device.create_sampler(&SamplerDescriptor {
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..default()
+        });
+
+        let settings_buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("color_grading_settings_buffer"),
+            size: ColorGradingUniform::min_size().get(),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let identity = ColorLut::identity(2);
+        let (_, identity_view) = create_lut_texture(render_device, render_queue, &identity);
+
+        let pipeline_id = world.resource::<PipelineCache>().queue_render_pipeline(RenderPipelineDescriptor {
+            label: Some("color_grading_pipeline".into()),
+            layout: vec![layout.clone()],
+            push_constant_ranges: vec![],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader,
+                shader_defs: vec![],
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format: ViewTarget::TEXTURE_FORMAT_HDR,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+        });
+
+        track_render_pipeline(world, pipeline_id);
+
+        Self {
+            layout,
+            sampler,
+            settings_buffer,
+            identity_view,
+            identity_size: identity.size,
+            pipeline_id,
+        }
+    }
+}
+
+/// Node in the render graph that applies the blended LUTs after tone mapping
+pub struct ColorGradingNode {
+    query: QueryState<&'static ViewTarget>,
+}
+
+impl ColorGradingNode {
+    /// Name of the node in the render graph
+    pub const NAME: &'static str = "color_grading";
+}
+
+impl FromWorld for ColorGradingNode {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for ColorGradingNode {
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        if !world.resource::<ColorGradingEnabled>().0 {
+            return Ok(());
+        }
+        let Ok(view_target) = self.query.get_manual(world, graph.view_entity()) else {
+            return Ok(());
+        };
+        let Some([current, next]) = &world.resource::<GpuColorLuts>().active else {
+            return Ok(());
+        };
+        let (Some(pipeline), Some(uniform)) = (
+            world.get_resource::<ColorGradingPipeline>(),
+            world.get_resource::<ColorGradingUniform>(),
+        ) else {
+            return Ok(());
+        };
+        let Some(render_pipeline) = render_pipeline(world, pipeline.pipeline_id) else {
+            return Ok(());
+        };
+
+        world
+            .resource::<RenderQueue>()
+            .write_buffer(&pipeline.settings_buffer, 0, bytemuck::bytes_of(uniform));
+
+        let post_process = view_target.post_process_write();
+        let bind_group = render_context.render_device().create_bind_group(
+            "color_grading_bind_group",
+            &pipeline.layout,
+            &BindGroupEntries::sequential((
+                post_process.source,
+                &current.0,
+                &next.0,
+                &pipeline.sampler,
+                pipeline.settings_buffer.as_entire_binding(),
+            )),
+        );
+
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("color_grading_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: post_process.destination,
+                resolve_target: None,
+                ops: Operations::default(),
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_render_pipeline(render_pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
+
+/// Plugin that adds LUT color grading with biome and weather cross-fades
+pub struct ColorGradingPlugin;
+
+impl Plugin for ColorGradingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<ColorLut>()
+            .init_asset_loader::<ColorLutLoader>()
+            .init_resource::<ColorGrading>()
+            .init_resource::<ColorGradeLibrary>()
+            .add_plugins(ExtractResourcePlugin::<ColorGradingEnabled>::default())
+            .add_systems(Startup, register_color_grades)
+            .add_systems(Update, (load_preset_lut, add_chunk_grade_regions, update_color_grading).chain());
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .init_resource::<GpuColorLuts>()
+            .add_systems(ExtractSchedule, extract_color_grading);
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<ColorGradingPipeline>();
+
+        // LUTs are authored for display-referred color, so the chain grades after tone mapping
+        let node = ColorGradingNode::from_world(&mut render_app.world);
+        render_app
+            .world
+            .resource_mut::<PostProcessEffectNodes>()
+            .insert(PostProcessEffect::ColorGrading, node);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TINY_CUBE: &str = "TITLE \"test\"\n# comment\nLUT_3D_SIZE 2\n\
+        0 0 0\n1 0 0\n0 1 0\n1 1 0\n0 0 1\n1 0 1\n0 1 1\n1 1 1\n";
+
+    #[test]
+    fn test_parse_cube() {
+        let lut = ColorLut::from_cube(TINY_CUBE).unwrap();
+        assert_eq!(lut.size, 2);
+        assert_eq!(lut.data, ColorLut::identity(2).data);
+    }
+
+    #[test]
+    fn test_cube_entry_count_is_validated() {
+        let truncated = TINY_CUBE.rsplit_once("1 1 1").unwrap().0;
+        assert!(matches!(
+            ColorLut::from_cube(truncated),
+            Err(LutError::WrongEntryCount { expected: 8, found: 7 })
+        ));
+    }
+
+    #[test]
+    fn test_weather_overrides_biome() {
+        let desert = Handle::<ColorLut>::weak(AssetId::Uuid { uuid: bevy::utils::Uuid::from_u128(1) });
+        let storm = Handle::<ColorLut>::weak(AssetId::Uuid { uuid: bevy::utils::Uuid::from_u128(2) });
+        let mut library = ColorGradeLibrary::default();
+        library.biomes.insert("desert".to_string(), desert.clone());
+        library.weather.push((Weather::Storm, storm.clone()));
+
+        assert_eq!(library.select(Some("desert"), Some(Weather::Clear)), Some(desert));
+        assert_eq!(library.select(Some("desert"), Some(Weather::Storm)), Some(storm));
+        assert_eq!(library.select(Some("forest"), None), None);
+    }
+
+    #[test]
+    fn test_transition_blends_then_promotes() {
+        let lut = Handle::<ColorLut>::weak(AssetId::Uuid { uuid: bevy::utils::Uuid::from_u128(3) });
+        let mut grading = ColorGrading {
+            transition_seconds: 2.0,
+            ..default()
+        };
+
+        grading.transition_to(Some(lut.clone()), &mut Assets::default());
+        grading.advance(1.0);
+        assert!((grading.blend - 0.5).abs() < 1e-5);
+        assert_eq!(grading.current, None);
+
+        grading.advance(1.0);
+        assert_eq!(grading.current, Some(lut));
+        assert_eq!(grading.next, None);
+        assert_eq!(grading.blend, 0.0);
+        assert!(!grading.is_fading());
+    }
+
+    #[test]
+    fn test_retarget_continues_from_the_blend() {
+        let mut luts = Assets::<ColorLut>::default();
+        let mut warm = ColorLut::identity(2);
+        warm.data[0] = [0.4, 0.2, 0.0, 1.0];
+        let warm = luts.add(warm);
+        let cold = Handle::<ColorLut>::weak(AssetId::Uuid { uuid: bevy::utils::Uuid::from_u128(4) });
+        let mut grading = ColorGrading {
+            transition_seconds: 2.0,
+            ..default()
+        };
+
+        grading.transition_to(Some(warm.clone()), &mut luts);
+        grading.advance(0.5);
+        grading.transition_to(Some(cold.clone()), &mut luts);
+
+        // A quarter of the way from identity to warm, baked into the grade the new fade starts from
+        let current = luts.get(grading.current.as_ref().unwrap()).unwrap();
+        assert!((current.data[0][0] - 0.1).abs() < 1e-5);
+        assert_eq!(current.data[1], ColorLut::identity(2).data[1]);
+        assert_eq!(grading.next, Some(cold));
+        assert_eq!(grading.blend, 0.0);
+        assert!(grading.is_fading());
+    }
+
+    #[test]
+    fn test_retarget_back_reverses_the_fade() {
+        let mut luts = Assets::<ColorLut>::default();
+        let lut = Handle::<ColorLut>::weak(AssetId::Uuid { uuid: bevy::utils::Uuid::from_u128(5) });
+        let mut grading = ColorGrading {
+            transition_seconds: 2.0,
+            ..default()
+        };
+
+        grading.transition_to(Some(lut.clone()), &mut luts);
+        grading.advance(0.5);
+        grading.transition_to(None, &mut luts);
+
+        assert_eq!(grading.current, Some(lut));
+        assert_eq!(grading.next, None);
+        assert!((grading.blend - 0.75).abs() < 1e-5);
+        assert!(grading.is_fading());
+    }
+}
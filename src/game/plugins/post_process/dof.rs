@@ -0,0 +1,581 @@
+use bevy::{
+    core_pipeline::{fullscreen_vertex_shader::fullscreen_shader_vertex_state, prepass::ViewPrepassTextures},
+    prelude::*,
+    render::{
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        render_graph::{Node, NodeRunError, RenderGraphContext},
+        render_resource::*,
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        texture::{CachedTexture, TextureCache},
+        view::{ViewTarget, ViewUniformOffset, ViewUniforms},
+        Render, RenderApp, RenderSet,
+    },
+};
+use bytemuck::{Pod, Zeroable};
+
+use super::{load_shader, PostProcessEffect, PostProcessEffectNodes, PostProcessSettings};
+use crate::game::plugins::camera::GameCamera;
+use crate::rendering::{
+    compute_pipeline, render_pipeline, scene_depth_view, track_compute_pipeline, track_render_pipeline,
+    SceneDepthPlugin, SceneDepthUsers,
+};
+
+/// Workgroup size used by the bokeh compute shader (8x8 threads)
+const WORKGROUP_SIZE: u32 = 8;
+
+/// Height of a full frame sensor in meters, used to map the CoC to screen space
+const SENSOR_HEIGHT: f32 = 0.024;
+
+/// Quality tiers for the bokeh gather
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DofQuality {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl DofQuality {
+    /// Number of gather samples per pixel
+    pub fn sample_count(&self) -> u32 {
+        match self {
+            DofQuality::Low => 8,
+            DofQuality::Medium => 16,
+            DofQuality::High => 32,
+        }
+    }
+
+    /// Largest blur radius in pixels
+    /// Reason: a sparse gather over a large radius shows visible sample patterns
+    pub fn max_coc_pixels(&self) -> f32 {
+        match self {
+            DofQuality::Low => 6.0,
+            DofQuality::Medium => 12.0,
+            DofQuality::High => 20.0,
+        }
+    }
+}
+
+/// How the focal distance is chosen
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DofFocusMode {
+    /// Keep the vehicle followed by the game camera in focus
+    #[default]
+    FollowVehicle,
+    /// Fixed focal distance in meters, used by photo mode
+    Manual { distance: f32 },
+}
+
+/// Resource tracking the current focal plane
+#[derive(Resource, Clone, Debug)]
+pub struct DofFocus {
+    /// How the focal distance is chosen
+    pub mode: DofFocusMode,
+    /// Current (smoothed) focal distance in meters
+    pub distance: f32,
+    /// How quickly the focal distance follows its target (per second)
+    pub adaptation_speed: f32,
+}
+
+impl Default for DofFocus {
+    fn default() -> Self {
+        Self {
+            mode: DofFocusMode::default(),
+            distance: 10.0,
+            adaptation_speed: 4.0,
+        }
+    }
+}
+
+/// Circle of confusion diameter in meters on the sensor for an object at `distance`
+/// Uses the thin lens model with the focal length in millimeters
+pub fn circle_of_confusion(distance: f32, focus_distance: f32, focal_length_mm: f32, f_stop: f32) -> f32 {
+    let f = focal_length_mm / 1000.0;
+    let aperture = f / f_stop.max(0.1);
+    let denominator = distance.max(0.001) * (focus_distance - f).max(0.0001);
+    (aperture * f * (distance - focus_distance) / denominator).abs()
+}
+
+/// GPU-side depth of field parameters
+#[derive(Resource, ShaderType, Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+pub struct DofUniform {
+    pub focus_distance: f32,
+    pub focal_length: f32,
+    pub aperture: f32,
+    pub sensor_height: f32,
+    pub max_coc_pixels: f32,
+    pub sample_count: u32,
+    _padding: [u32; 2],
+}
+
+/// Whether the DOF pass should run this frame
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct DofEnabled(pub bool);
+
+impl ExtractResource for DofEnabled {
+    type Source = PostProcessSettings;
+
+    fn extract_resource(settings: &Self::Source) -> Self {
+        Self(settings.dof_enabled)
+    }
+}
+
+impl DofUniform {
+    /// Builds the uniform from the post-process settings and current focal distance
+    pub fn new(settings: &PostProcessSettings, focus: &DofFocus) -> Self {
+        let focal_length = settings.dof_focal_length / 1000.0;
+        Self {
+            focus_distance: focus.distance.max(focal_length + 0.01),
+            focal_length,
+            aperture: focal_length / settings.dof_aperture.max(0.1),
+            sensor_height: SENSOR_HEIGHT,
+            max_coc_pixels: settings.dof_quality.max_coc_pixels(),
+            sample_count: settings.dof_quality.sample_count(),
+            _padding: [0; 2],
+        }
+    }
+}
+
+/// Moves the focal plane towards the followed vehicle, or the manual photo mode distance
+fn update_dof_focus(
+    mut focus: ResMut<DofFocus>,
+    cameras: Query<(&GlobalTransform, &GameCamera)>,
+    targets: Query<&GlobalTransform>,
+    time: Res<Time>,
+) {
+    let target_distance = match focus.mode {
+        DofFocusMode::Manual { distance } => distance,
+        DofFocusMode::FollowVehicle => {
+            let followed = cameras.iter().find_map(|(camera, game_camera)| {
+                let target = targets.get(game_camera.target?).ok()?;
+                Some(camera.translation().distance(target.translation()))
+            });
+            match followed {
+                Some(distance) => distance,
+                // Nothing to follow, keep the current focus
+                None => return,
+            }
+        }
+    };
+
+    // Exponential approach so focus pulls feel like a camera operator, not a snap
+    let t = 1.0 - (-focus.adaptation_speed * time.delta_seconds()).exp();
+    focus.distance += (target_distance - focus.distance) * t;
+}
+
+/// Copies the focus and lens settings into the render world
+fn extract_dof_uniform(
+    mut commands: Commands,
+    settings: bevy::render::Extract<Res<PostProcessSettings>>,
+    focus: bevy::render::Extract<Res<DofFocus>>,
+) {
+    commands.insert_resource(DofUniform::new(&settings, &focus));
+}
+
+/// Pipelines for the bokeh gather and composite passes
+#[derive(Resource)]
+pub struct DofPipeline {
+    compute_layout: BindGroupLayout,
+    apply_layout: BindGroupLayout,
+    sampler: Sampler,
+    settings_buffer: Buffer,
+    bokeh_pipeline_id: CachedComputePipelineId,
+    apply_pipeline_id: CachedRenderPipelineId,
+}
+
+impl FromWorld for DofPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let shader = load_shader(world, "dof.wgsl");
+
+        let render_device = world.resource::<RenderDevice>();
+
+        // Create bind group layout for the bokeh gather:
+        // - View uniform for the projection
+        // - DOF settings
+        // - Scene color and depth
+        // - Storage output at full resolution
+        let compute_layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("dof_compute_bind_group_layout"),
+            entries: &[
+                // View uniform
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // DOF settings
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: Some(DofUniform::min_size()),
+                    },
+                    count: None,
+                },
+                // Scene texture
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // Depth texture
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Depth,
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // Blurred output
+                BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::StorageTexture {
+                        access: StorageTextureAccess::WriteOnly,
+                        format: TextureFormat::Rgba16Float,
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let apply_layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("dof_apply_bind_group_layout"),
+            entries: &[
+                // Blurred texture
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // Sampler
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let sampler = render_device.create_sampler(&SamplerDescriptor {
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..default()
+        });
+
+        let settings_buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("dof_settings_buffer"),
+            size: DofUniform::min_size().get(),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let bokeh_pipeline_id = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            label: Some("dof_bokeh_pipeline".into()),
+            layout: vec![compute_layout.clone()],
+            push_constant_ranges: vec![],
+            shader: shader.clone(),
+            shader_defs: vec![],
+            entry_point: "bokeh".into(),
+        });
+
+        let apply_pipeline_id = pipeline_cache.queue_render_pipeline(RenderPipelineDescriptor {
+            label: Some("dof_apply_pipeline".into()),
+            layout: vec![apply_layout.clone()],
+            push_constant_ranges: vec![],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader,
+                shader_defs: vec![],
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format: ViewTarget::TEXTURE_FORMAT_HDR,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,  // No depth testing needed for post-process
+            multisample: MultisampleState::default(),
+        });
+
+        track_compute_pipeline(world, bokeh_pipeline_id);
+        track_render_pipeline(world, apply_pipeline_id);
+
+        Self {
+            compute_layout,
+            apply_layout,
+            sampler,
+            settings_buffer,
+            bokeh_pipeline_id,
+            apply_pipeline_id,
+        }
+    }
+}
+
+/// Per-view storage target for the blurred image
+#[derive(Component)]
+pub struct DofTexture(pub CachedTexture);
+
+/// Allocates the per-view blur target and uploads the DOF settings
+fn prepare_dof_resources(
+    mut commands: Commands,
+    mut texture_cache: ResMut<TextureCache>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    pipeline: Res<DofPipeline>,
+    enabled: Res<DofEnabled>,
+    uniform: Option<Res<DofUniform>>,
+    views: Query<(Entity, &ViewTarget)>,
+) {
+    let Some(uniform) = uniform else {
+        return;
+    };
+    if !enabled.0 {
+        return;
+    }
+
+    render_queue.write_buffer(&pipeline.settings_buffer, 0, bytemuck::bytes_of(uniform.as_ref()));
+
+    for (entity, view_target) in views.iter() {
+        let texture = texture_cache.get(
+            &render_device,
+            TextureDescriptor {
+                label: Some("dof_blur_texture"),
+                size: view_target.main_texture().size(),
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Rgba16Float,
+                usage: TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+        );
+        commands.entity(entity).insert(DofTexture(texture));
+    }
+}
+
+/// Node in the render graph that applies the bokeh depth of field
+pub struct DofNode {
+    query: QueryState<(
+        &'static ViewTarget,
+        &'static ViewUniformOffset,
+        &'static ViewPrepassTextures,
+        &'static DofTexture,
+    )>,
+}
+
+impl DofNode {
+    /// Name of the node in the render graph
+    pub const NAME: &'static str = "depth_of_field";
+}
+
+impl FromWorld for DofNode {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for DofNode {
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        if !world.resource::<DofEnabled>().0 {
+            return Ok(());
+        }
+        let Ok((view_target, view_uniform, prepass, blur)) = self.query.get_manual(world, graph.view_entity()) else {
+            return Ok(());
+        };
+        // Views without a single-sampled prepass yet skip DoF until SceneDepthPlugin sets them up
+        let Some(depth) = scene_depth_view(prepass) else {
+            return Ok(());
+        };
+        let Some(view_binding) = world.resource::<ViewUniforms>().uniforms.binding() else {
+            return Ok(());
+        };
+
+        let pipeline = world.resource::<DofPipeline>();
+        let (Some(bokeh), Some(apply)) = (
+            compute_pipeline(world, pipeline.bokeh_pipeline_id),
+            render_pipeline(world, pipeline.apply_pipeline_id),
+        ) else {
+            // Pipelines are still compiling
+            return Ok(());
+        };
+
+        let post_process = view_target.post_process_write();
+        let render_device = render_context.render_device().clone();
+
+        let compute_bind_group = render_device.create_bind_group(
+            "dof_compute_bind_group",
+            &pipeline.compute_layout,
+            &BindGroupEntries::sequential((
+                view_binding,
+                pipeline.settings_buffer.as_entire_binding(),
+                post_process.source,
+                depth,
+                &blur.0.default_view,
+            )),
+        );
+
+        {
+            let size = blur.0.texture.size();
+            let mut compute_pass = render_context
+                .command_encoder()
+                .begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("dof_bokeh_pass"),
+                });
+            compute_pass.set_pipeline(bokeh);
+            compute_pass.set_bind_group(0, &compute_bind_group, &[view_uniform.offset]);
+            compute_pass.dispatch_workgroups(
+                size.width.div_ceil(WORKGROUP_SIZE),
+                size.height.div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+        }
+
+        let apply_bind_group = render_device.create_bind_group(
+            "dof_apply_bind_group",
+            &pipeline.apply_layout,
+            &BindGroupEntries::sequential((&blur.0.default_view, &pipeline.sampler)),
+        );
+
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("dof_apply_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: post_process.destination,
+                resolve_target: None,
+                ops: Operations::default(),
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_render_pipeline(apply);
+        render_pass.set_bind_group(0, &apply_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
+
+/// Registers depth of field as a scene depth user while it's enabled
+fn request_dof_depth(settings: Res<PostProcessSettings>, mut users: ResMut<SceneDepthUsers>) {
+    if settings.is_changed() {
+        users.set("dof", DofEnabled::extract_resource(&settings).0);
+    }
+}
+
+/// Plugin that adds the bokeh depth of field pass
+pub struct DofPlugin;
+
+impl Plugin for DofPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<SceneDepthPlugin>() {
+            app.add_plugins(SceneDepthPlugin);
+        }
+        app.init_resource::<DofFocus>()
+            .add_plugins(ExtractResourcePlugin::<DofEnabled>::default())
+            .add_systems(Update, (update_dof_focus, request_dof_depth));
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .add_systems(ExtractSchedule, extract_dof_uniform)
+            .add_systems(Render, prepare_dof_resources.in_set(RenderSet::PrepareResources));
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<DofPipeline>();
+
+        let node = DofNode::from_world(&mut render_app.world);
+        render_app
+            .world
+            .resource_mut::<PostProcessEffectNodes>()
+            .insert(PostProcessEffect::DepthOfField, node);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_focal_plane_is_sharp() {
+        assert_eq!(circle_of_confusion(10.0, 10.0, 50.0, 2.8), 0.0);
+        assert!(circle_of_confusion(30.0, 10.0, 50.0, 2.8) > 0.0);
+        assert!(circle_of_confusion(3.0, 10.0, 50.0, 2.8) > 0.0);
+    }
+
+    #[test]
+    fn test_wider_aperture_blurs_more() {
+        let wide = circle_of_confusion(30.0, 10.0, 50.0, 1.4);
+        let narrow = circle_of_confusion(30.0, 10.0, 50.0, 16.0);
+        assert!(wide > narrow);
+    }
+
+    #[test]
+    fn test_quality_tiers() {
+        assert!(DofQuality::Low.sample_count() < DofQuality::High.sample_count());
+        assert!(DofQuality::Low.max_coc_pixels() < DofQuality::High.max_coc_pixels());
+    }
+
+    #[test]
+    fn test_manual_focus_is_followed() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .insert_resource(DofFocus {
+                mode: DofFocusMode::Manual { distance: 25.0 },
+                distance: 5.0,
+                adaptation_speed: 4.0,
+            })
+            .add_systems(Update, update_dof_focus);
+
+        // A quarter second at 4 per second closes 1 - 1/e of the gap
+        app.world.resource_mut::<Time>().advance_by(Duration::from_secs_f32(0.25));
+        app.update();
+        let expected = 5.0 + 20.0 * (1.0 - (-1.0f32).exp());
+        assert!((app.world.resource::<DofFocus>().distance - expected).abs() < 1e-4);
+
+        app.world.resource_mut::<Time>().advance_by(Duration::from_secs_f32(2.0));
+        app.update();
+        assert!((app.world.resource::<DofFocus>().distance - 25.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,621 @@
+//! Golden image tests for the post-process chain and particle rendering.
+//!
+//! Each [`GoldenScene`] is built from the post-process test patterns, with its own post-process
+//! settings and a fixed camera, and rendered offscreen by a windowless app into an image target
+//! that is read back from the GPU. Noise patterns are generated from the scene's seed. Time only
+//! starts once every pipeline has compiled, and then advances by a fixed step, so TAA history,
+//! depth of field focus and particles are at the same point in every run.
+//!
+//! The result is compared with `tests/golden/<scene>.png` using CIE76 colour differences. A
+//! pixel differs when its ΔE is above the tolerance, and the scene fails when too many pixels
+//! differ, which lets GPU and driver rounding through while catching changed passes. On failure
+//! the render and a diff image are written next to each other for review. A missing golden image
+//! fails the scene, only `SANDK_GOLDEN_UPDATE` records the images, all of them, from this render.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bevy::{
+    app::PluginsState,
+    core::FrameCount,
+    log::LogPlugin,
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        pipelined_rendering::PipelinedRenderingPlugin,
+        render_asset::RenderAssets,
+        render_resource::{
+            BufferDescriptor, BufferUsages, CachedPipelineState, CommandEncoderDescriptor, Extent3d,
+            ImageCopyBuffer, ImageDataLayout, Maintain, MapMode, PipelineCache, TextureDescriptor,
+            TextureDimension, TextureFormat, TextureUsages,
+        },
+        renderer::{RenderDevice, RenderQueue},
+        Render, RenderApp, RenderSet,
+    },
+    tasks::tick_global_task_pools_on_main_thread,
+    time::TimeUpdateStrategy,
+    window::ExitCondition,
+    winit::WinitPlugin,
+};
+use image::{Rgba, RgbaImage};
+use thiserror::Error;
+
+use super::test_scene::{
+    create_depth_test_pattern, create_hdr_test_patterns, create_noise_test_pattern, create_specular_test_pattern,
+    create_test_patterns,
+};
+use super::{DofFocus, DofFocusMode, PostProcessPlugin, PostProcessSettings};
+use crate::game::ParticleSystemPlugin;
+use crate::rendering::{ShaderErrors, ShaderReloadPlugin};
+
+/// Seconds per frame once the scene is rendering
+const GOLDEN_TIMESTEP: f32 = 1.0 / 60.0;
+
+/// Test patterns from the post-process test scene
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GoldenPattern {
+    /// Checkerboard, colour wheel, gradient and radial patterns
+    TestPatterns,
+    /// HDR intensity strips and emissive dots for tone mapping and bloom
+    HdrStrips,
+    /// Random heights and greys from the scene's seed
+    Noise,
+    /// Overlapping transparent planes
+    Depth,
+    /// Spheres across roughness and metallic values
+    Specular,
+}
+
+/// A deterministic scene rendered and compared against its golden image
+#[derive(Resource, Debug, Clone)]
+pub struct GoldenScene {
+    /// Name of the golden image, `tests/golden/<name>.png`
+    pub name: &'static str,
+    /// Seed of the noise pattern
+    pub seed: u64,
+    pub patterns: Vec<GoldenPattern>,
+    /// Also runs the particle system with its example effects
+    pub particles: bool,
+    pub settings: PostProcessSettings,
+    pub camera: Transform,
+    /// Manual focal distance in meters for depth of field
+    pub focus_distance: f32,
+}
+
+/// The scenes covered by the golden image tests
+pub fn golden_scenes() -> Vec<GoldenScene> {
+    // LUTs load asynchronously and aren't covered, the grade would depend on when they arrive
+    let base = PostProcessSettings { color_lut: None, ssao_enabled: false, ..default() };
+    vec![
+        GoldenScene {
+            name: "test_patterns",
+            seed: 42,
+            patterns: vec![GoldenPattern::TestPatterns, GoldenPattern::HdrStrips],
+            particles: false,
+            settings: PostProcessSettings { chromatic_aberration: 0.3, vignette: 0.4, ..base.clone() },
+            camera: Transform::from_xyz(0.0, 14.0, 12.0).looking_at(Vec3::ZERO, Vec3::Y),
+            focus_distance: 18.0,
+        },
+        GoldenScene {
+            name: "hdr_bloom",
+            seed: 42,
+            patterns: vec![GoldenPattern::HdrStrips],
+            particles: false,
+            settings: PostProcessSettings { exposure: 1.5, bloom_intensity: 1.2, bloom_threshold: 0.8, ..base.clone() },
+            camera: Transform::from_xyz(-6.0, 5.0, 11.0).looking_at(Vec3::new(-6.0, 0.0, 6.0), Vec3::Y),
+            focus_distance: 7.0,
+        },
+        GoldenScene {
+            name: "noise_ssao",
+            seed: 7,
+            patterns: vec![GoldenPattern::Noise, GoldenPattern::Depth, GoldenPattern::Specular],
+            particles: false,
+            settings: PostProcessSettings { ssao_enabled: true, ssao_intensity: 1.5, ..base.clone() },
+            camera: Transform::from_xyz(2.0, 6.0, 8.0).looking_at(Vec3::new(2.0, 0.0, 1.0), Vec3::Y),
+            focus_distance: 9.0,
+        },
+        GoldenScene {
+            name: "specular_dof",
+            seed: 42,
+            patterns: vec![GoldenPattern::Specular],
+            particles: false,
+            settings: PostProcessSettings { dof_enabled: true, dof_aperture: 1.4, ..base.clone() },
+            camera: Transform::from_xyz(3.0, 1.5, 7.0).looking_at(Vec3::new(3.0, 0.3, 3.0), Vec3::Y),
+            focus_distance: 4.0,
+        },
+        GoldenScene {
+            name: "particles",
+            seed: 42,
+            patterns: vec![],
+            particles: true,
+            settings: base,
+            camera: Transform::from_xyz(0.0, 6.0, 16.0).looking_at(Vec3::new(0.0, 1.0, 0.0), Vec3::Y),
+            focus_distance: 16.0,
+        },
+    ]
+}
+
+/// How far a render may drift from its golden image
+#[derive(Debug, Clone, Copy)]
+pub struct GoldenTolerance {
+    /// CIE76 ΔE above which a pixel differs, 2.3 is about the smallest visible difference
+    pub delta_e: f32,
+    /// Fraction of pixels that may differ
+    pub mismatched_fraction: f32,
+}
+
+impl Default for GoldenTolerance {
+    fn default() -> Self {
+        Self {
+            delta_e: 2.3,
+            mismatched_fraction: 0.002,
+        }
+    }
+}
+
+/// Resolution, timing and paths of golden image runs
+#[derive(Debug, Clone)]
+pub struct GoldenConfig {
+    pub width: u32,
+    pub height: u32,
+    /// Frames rendered after every pipeline has compiled, before the image is read back
+    pub settle_frames: u32,
+    /// Frames to wait for shaders to load and pipelines to compile
+    pub max_frames: u32,
+    pub tolerance: GoldenTolerance,
+    /// Directory of the golden images
+    pub golden_dir: PathBuf,
+    /// Where renders and diff images of failed scenes are written
+    pub failure_dir: PathBuf,
+    /// Re-record every golden image instead of comparing
+    pub update: bool,
+}
+
+impl Default for GoldenConfig {
+    fn default() -> Self {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        Self {
+            width: 320,
+            height: 180,
+            // Two periods of the TAA jitter
+            settle_frames: 16,
+            max_frames: 2000,
+            tolerance: GoldenTolerance::default(),
+            golden_dir: root.join("tests/golden"),
+            failure_dir: root.join("target/golden-failures"),
+            update: false,
+        }
+    }
+}
+
+impl GoldenConfig {
+    /// Defaults overridden by `SANDK_GOLDEN_UPDATE`, `SANDK_GOLDEN_DELTA_E` and `SANDK_GOLDEN_MISMATCH`
+    pub fn from_env() -> Self {
+        let mut config = Self { update: std::env::var_os("SANDK_GOLDEN_UPDATE").is_some(), ..Self::default() };
+        if let Some(delta_e) = std::env::var("SANDK_GOLDEN_DELTA_E").ok().and_then(|delta_e| delta_e.parse().ok()) {
+            config.tolerance.delta_e = delta_e;
+        }
+        if let Some(fraction) = std::env::var("SANDK_GOLDEN_MISMATCH").ok().and_then(|fraction| fraction.parse().ok()) {
+            config.tolerance.mismatched_fraction = fraction;
+        }
+        config
+    }
+}
+
+/// Errors of a golden image run
+#[derive(Error, Debug)]
+pub enum GoldenError {
+    #[error("{scene}: pipelines still compiling after {frames} frames")]
+    Timeout { scene: String, frames: u32 },
+    #[error("{scene}: shaders failed to compile: {errors}")]
+    Shader { scene: String, errors: String },
+    #[error("{scene}: rendered {actual:?}, golden image is {expected:?}")]
+    SizeMismatch { scene: String, actual: (u32, u32), expected: (u32, u32) },
+    #[error("{scene}: no golden image at {}, record it with SANDK_GOLDEN_UPDATE and commit it", path.display())]
+    Missing { scene: String, path: PathBuf },
+    #[error(
+        "{scene}: {mismatched} of {pixels} pixels differ (max ΔE {max_delta_e:.1}), render and diff saved to {}",
+        saved.display()
+    )]
+    Mismatch { scene: String, mismatched: usize, pixels: usize, max_delta_e: f32, saved: PathBuf },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Image(#[from] image::ImageError),
+}
+
+/// How a render compares with its golden image
+#[derive(Debug, Clone)]
+pub struct GoldenDiff {
+    /// Pixels with a ΔE above the tolerance
+    pub mismatched: usize,
+    pub pixels: usize,
+    pub mean_delta_e: f32,
+    pub max_delta_e: f32,
+    /// The golden image dimmed to grey, with differing pixels in red by how far off they are
+    pub image: RgbaImage,
+}
+
+impl GoldenDiff {
+    pub fn mismatched_fraction(&self) -> f32 {
+        self.mismatched as f32 / self.pixels.max(1) as f32
+    }
+
+    pub fn passes(&self, tolerance: &GoldenTolerance) -> bool {
+        self.mismatched_fraction() <= tolerance.mismatched_fraction
+    }
+}
+
+/// Outcome of a scene that passed or was recorded
+#[derive(Debug, Clone)]
+pub struct GoldenReport {
+    pub scene: &'static str,
+    pub mismatched: usize,
+    pub max_delta_e: f32,
+    /// The golden image was re-recorded rather than compared
+    pub updated: bool,
+}
+
+fn srgb_to_linear(channel: u8) -> f32 {
+    let c = channel as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// CIELAB coordinates of an sRGB colour under the D65 white point
+pub fn srgb_to_lab(pixel: [u8; 3]) -> Vec3 {
+    let [r, g, b] = pixel.map(srgb_to_linear);
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+    let f = |t: f32| if t > 0.008856 { t.cbrt() } else { 7.787 * t + 16.0 / 116.0 };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    Vec3::new(116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+}
+
+/// CIE76 colour difference, the distance between two colours in CIELAB
+pub fn delta_e(a: [u8; 3], b: [u8; 3]) -> f32 {
+    srgb_to_lab(a).distance(srgb_to_lab(b))
+}
+
+/// Compares a render against its golden image pixel by pixel, ignoring alpha
+pub fn perceptual_diff(
+    scene: &str,
+    expected: &RgbaImage,
+    actual: &RgbaImage,
+    tolerance: &GoldenTolerance,
+) -> Result<GoldenDiff, GoldenError> {
+    if expected.dimensions() != actual.dimensions() {
+        return Err(GoldenError::SizeMismatch {
+            scene: scene.to_string(),
+            actual: actual.dimensions(),
+            expected: expected.dimensions(),
+        });
+    }
+
+    let mut image = RgbaImage::new(expected.width(), expected.height());
+    let mut mismatched = 0;
+    let mut total_delta_e = 0.0;
+    let mut max_delta_e: f32 = 0.0;
+    for ((expected, actual), diff) in expected.pixels().zip(actual.pixels()).zip(image.pixels_mut()) {
+        let rgb = |pixel: &Rgba<u8>| [pixel[0], pixel[1], pixel[2]];
+        let difference = delta_e(rgb(expected), rgb(actual));
+        total_delta_e += difference;
+        max_delta_e = max_delta_e.max(difference);
+        *diff = if difference > tolerance.delta_e {
+            mismatched += 1;
+            Rgba([(128.0 + difference * 4.0).min(255.0) as u8, 0, 0, 255])
+        } else {
+            let grey = (srgb_to_lab(rgb(expected)).x * 0.6) as u8;
+            Rgba([grey, grey, grey, 255])
+        };
+    }
+
+    let pixels = (expected.width() * expected.height()) as usize;
+    Ok(GoldenDiff {
+        mismatched,
+        pixels,
+        mean_delta_e: total_delta_e / pixels.max(1) as f32,
+        max_delta_e,
+        image,
+    })
+}
+
+/// Progress of the offscreen render, shared between the main and render worlds
+#[derive(Default)]
+struct CaptureState {
+    /// Every queued pipeline has finished compiling
+    pipelines_ready: bool,
+    /// Read the target back at the end of the next frame
+    armed: bool,
+    /// Tightly packed RGBA8 rows of the target
+    pixels: Option<Vec<u8>>,
+}
+
+#[derive(Resource, Clone)]
+struct GoldenCapture {
+    target: Handle<Image>,
+    state: Arc<Mutex<CaptureState>>,
+}
+
+/// Offscreen colour target the scene camera renders into and the readback copies from
+fn golden_target(width: u32, height: u32) -> Image {
+    let size = Extent3d { width, height, depth_or_array_layers: 1 };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("golden_target"),
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        },
+        ..default()
+    };
+    image.resize(size);
+    image
+}
+
+fn spawn_golden_scene(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    scene: Res<GoldenScene>,
+    capture: Res<GoldenCapture>,
+) {
+    for pattern in &scene.patterns {
+        match pattern {
+            GoldenPattern::TestPatterns => create_test_patterns(&mut commands, &mut meshes, &mut materials),
+            GoldenPattern::HdrStrips => create_hdr_test_patterns(&mut commands, &mut meshes, &mut materials),
+            GoldenPattern::Noise => create_noise_test_pattern(&mut commands, &mut meshes, &mut materials, scene.seed),
+            GoldenPattern::Depth => create_depth_test_pattern(&mut commands, &mut meshes, &mut materials),
+            GoldenPattern::Specular => create_specular_test_pattern(&mut commands, &mut meshes, &mut materials),
+        }
+    }
+
+    commands.spawn(PbrBundle {
+        mesh: meshes.add(Mesh::from(shape::Plane { size: 30.0, subdivisions: 0 })),
+        material: materials.add(StandardMaterial {
+            base_color: Color::rgb(0.3, 0.3, 0.32),
+            perceptual_roughness: 0.9,
+            ..default()
+        }),
+        ..default()
+    });
+    commands.spawn(DirectionalLightBundle {
+        directional_light: DirectionalLight { illuminance: 20000.0, ..default() },
+        transform: Transform::from_xyz(4.0, 10.0, 6.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+    commands.spawn(Camera3dBundle {
+        camera: Camera { target: RenderTarget::Image(capture.target.clone()), hdr: true, ..default() },
+        transform: scene.camera,
+        ..default()
+    });
+}
+
+/// Tracks pipeline compilation and copies the target into the capture once armed
+fn read_back_golden_target(
+    capture: Res<GoldenCapture>,
+    cache: Res<PipelineCache>,
+    images: Res<RenderAssets<Image>>,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+) {
+    let Ok(mut state) = capture.state.lock() else {
+        return;
+    };
+    state.pipelines_ready = cache
+        .pipelines()
+        .all(|pipeline| matches!(pipeline.state, CachedPipelineState::Ok(_) | CachedPipelineState::Err(_)));
+    if !state.armed {
+        return;
+    }
+    let Some(target) = images.get(&capture.target) else {
+        return;
+    };
+
+    let (width, height) = (target.size.x as u32, target.size.y as u32);
+    let row_bytes = width as usize * 4;
+    let padded_row_bytes = RenderDevice::align_copy_bytes_per_row(row_bytes);
+    let buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("golden_readback_buffer"),
+        size: (padded_row_bytes * height as usize) as u64,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor { label: Some("golden_readback") });
+    encoder.copy_texture_to_buffer(
+        target.texture.as_image_copy(),
+        ImageCopyBuffer {
+            buffer: &buffer,
+            layout: ImageDataLayout { offset: 0, bytes_per_row: Some(padded_row_bytes as u32), rows_per_image: None },
+        },
+        Extent3d { width, height, depth_or_array_layers: 1 },
+    );
+    queue.submit([encoder.finish()]);
+
+    let slice = buffer.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(Maintain::Wait);
+    if let Ok(Ok(())) = receiver.recv() {
+        let data = slice.get_mapped_range();
+        // Rows are padded to the copy alignment
+        let pixels = data.chunks(padded_row_bytes).flat_map(|row| &row[..row_bytes]).copied().collect();
+        state.pixels = Some(pixels);
+    }
+    buffer.unmap();
+}
+
+/// Renders a scene offscreen and reads it back once it has settled
+pub fn render_golden_scene(scene: &GoldenScene, config: &GoldenConfig) -> Result<RgbaImage, GoldenError> {
+    let mut app = App::new();
+    app.add_plugins(
+        DefaultPlugins
+            .set(WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                close_when_requested: false,
+            })
+            .disable::<WinitPlugin>()
+            .disable::<LogPlugin>()
+            // The readback has to see the frame the main world just finished
+            .disable::<PipelinedRenderingPlugin>(),
+    )
+    // Frozen until the pipelines are ready, however long they take to compile
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO))
+    .insert_resource(scene.settings.clone())
+    .insert_resource(DofFocus {
+        mode: DofFocusMode::Manual { distance: scene.focus_distance },
+        distance: scene.focus_distance,
+        ..default()
+    })
+    .add_plugins((ShaderReloadPlugin, PostProcessPlugin));
+    if scene.particles {
+        app.add_plugins(ParticleSystemPlugin);
+    }
+
+    while app.plugins_state() != PluginsState::Ready {
+        tick_global_task_pools_on_main_thread();
+    }
+    app.finish();
+    app.cleanup();
+
+    let target = app.world.resource_mut::<Assets<Image>>().add(golden_target(config.width, config.height));
+    let capture = GoldenCapture { target, state: default() };
+    app.insert_resource(scene.clone())
+        .insert_resource(capture.clone())
+        .add_systems(Startup, spawn_golden_scene);
+    app.sub_app_mut(RenderApp)
+        .insert_resource(capture.clone())
+        .add_systems(Render, read_back_golden_target.in_set(RenderSet::Cleanup));
+
+    let mut settled = None;
+    for _ in 0..config.max_frames {
+        app.update();
+
+        let Ok(mut state) = capture.state.lock() else {
+            break;
+        };
+        if let Some(pixels) = state.pixels.take() {
+            let errors = app.world.resource::<ShaderErrors>().list();
+            if !errors.is_empty() {
+                let errors: Vec<_> = errors.into_iter().map(|(label, error)| format!("{label}: {error}")).collect();
+                return Err(GoldenError::Shader { scene: scene.name.to_string(), errors: errors.join("\n") });
+            }
+            return RgbaImage::from_raw(config.width, config.height, pixels).ok_or_else(|| {
+                GoldenError::SizeMismatch {
+                    scene: scene.name.to_string(),
+                    actual: (0, 0),
+                    expected: (config.width, config.height),
+                }
+            });
+        }
+
+        match settled.as_mut() {
+            None if state.pipelines_ready => {
+                // Start the clock, and the TAA jitter sequence, from the same point every run
+                settled = Some(0);
+                app.world.resource_mut::<FrameCount>().0 = 0;
+                let timestep = Duration::from_secs_f32(GOLDEN_TIMESTEP);
+                app.world.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+            }
+            None => {}
+            Some(frames) => {
+                *frames += 1;
+                state.armed = *frames >= config.settle_frames;
+            }
+        }
+    }
+
+    Err(GoldenError::Timeout { scene: scene.name.to_string(), frames: config.max_frames })
+}
+
+/// Renders a scene and compares it with its golden image, or records the image in update mode
+pub fn check_golden_scene(scene: &GoldenScene, config: &GoldenConfig) -> Result<GoldenReport, GoldenError> {
+    let path = config.golden_dir.join(format!("{}.png", scene.name));
+    // Checked before rendering, a missing image fails the same with or without a GPU
+    if !config.update && !path.exists() {
+        return Err(GoldenError::Missing { scene: scene.name.to_string(), path });
+    }
+
+    let actual = render_golden_scene(scene, config)?;
+    if config.update {
+        std::fs::create_dir_all(&config.golden_dir)?;
+        actual.save(&path)?;
+        return Ok(GoldenReport { scene: scene.name, mismatched: 0, max_delta_e: 0.0, updated: true });
+    }
+
+    let expected = image::open(&path)?.into_rgba8();
+    let diff = perceptual_diff(scene.name, &expected, &actual, &config.tolerance)?;
+    if !diff.passes(&config.tolerance) {
+        std::fs::create_dir_all(&config.failure_dir)?;
+        actual.save(config.failure_dir.join(format!("{}.actual.png", scene.name)))?;
+        diff.image.save(config.failure_dir.join(format!("{}.diff.png", scene.name)))?;
+        return Err(GoldenError::Mismatch {
+            scene: scene.name.to_string(),
+            mismatched: diff.mismatched,
+            pixels: diff.pixels,
+            max_delta_e: diff.max_delta_e,
+            saved: config.failure_dir.clone(),
+        });
+    }
+
+    Ok(GoldenReport { scene: scene.name, mismatched: diff.mismatched, max_delta_e: diff.max_delta_e, updated: false })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delta_e() {
+        assert_eq!(delta_e([40, 120, 200], [40, 120, 200]), 0.0);
+        // Black to white is the full lightness range
+        assert!((delta_e([0, 0, 0], [255, 255, 255]) - 100.0).abs() < 0.5);
+        // One step of 8-bit rounding is well below a visible difference
+        assert!(delta_e([128, 64, 32], [129, 64, 32]) < GoldenTolerance::default().delta_e);
+    }
+
+    #[test]
+    fn test_perceptual_diff() {
+        let tolerance = GoldenTolerance::default();
+        let expected = RgbaImage::from_pixel(10, 10, Rgba([100, 150, 200, 255]));
+        let mut actual = expected.clone();
+        actual.put_pixel(0, 0, Rgba([101, 150, 199, 255]));
+        actual.put_pixel(5, 5, Rgba([255, 0, 0, 255]));
+
+        let diff = perceptual_diff("test", &expected, &actual, &tolerance).unwrap();
+        assert_eq!(diff.mismatched, 1);
+        assert_eq!(diff.image.get_pixel(0, 0)[1], diff.image.get_pixel(0, 0)[0]);
+        assert!(!diff.passes(&tolerance));
+        assert!(diff.passes(&GoldenTolerance { mismatched_fraction: 0.01, ..tolerance }));
+
+        let smaller = RgbaImage::new(5, 5);
+        assert!(matches!(
+            perceptual_diff("test", &expected, &smaller, &tolerance),
+            Err(GoldenError::SizeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_missing_golden_fails() {
+        let golden_dir = tempfile::tempdir().unwrap();
+        let config = GoldenConfig { golden_dir: golden_dir.path().to_path_buf(), update: false, ..default() };
+        let scene = &golden_scenes()[0];
+
+        let result = check_golden_scene(scene, &config);
+        assert!(matches!(result, Err(GoldenError::Missing { .. })));
+        // Nothing is recorded outside update mode
+        assert!(!golden_dir.path().join(format!("{}.png", scene.name)).exists());
+    }
+}
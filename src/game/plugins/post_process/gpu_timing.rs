@@ -0,0 +1,313 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext},
+        render_resource::{Buffer, BufferDescriptor, BufferUsages, MapMode, WgpuFeatures},
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        Render, RenderApp, RenderSet,
+    },
+};
+
+use super::PostProcessChainNode;
+
+/// Stretches of GPU work timed with timestamp queries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GpuPass {
+    MainPass,
+    ParticleCompute,
+    PostProcess,
+}
+
+impl GpuPass {
+    pub const ALL: [Self; 3] = [Self::MainPass, Self::ParticleCompute, Self::PostProcess];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::MainPass => "Main pass",
+            Self::ParticleCompute => "Particle compute",
+            Self::PostProcess => "Post-process",
+        }
+    }
+
+    /// Index of the timestamp written before the pass, the one after follows it
+    fn begin_query(self) -> u32 {
+        self as u32 * 2
+    }
+
+    fn end_query(self) -> u32 {
+        self.begin_query() + 1
+    }
+}
+
+/// Timestamps in the query set, a begin and an end per pass
+const QUERY_COUNT: u32 = GpuPass::ALL.len() as u32 * 2;
+/// Bytes of resolved timestamps
+const RESOLVE_SIZE: u64 = QUERY_COUNT as u64 * std::mem::size_of::<u64>() as u64;
+
+/// Milliseconds each pass took on the GPU, in [`GpuPass::ALL`] order, `None` for passes that didn't run.
+/// `period_ns` is the length of one timestamp tick.
+pub fn pass_durations(timestamps: &[u64], period_ns: f32) -> [Option<f32>; GpuPass::ALL.len()] {
+    GpuPass::ALL.map(|pass| {
+        let begin = *timestamps.get(pass.begin_query() as usize)?;
+        let end = *timestamps.get(pass.end_query() as usize)?;
+        // Unwritten queries resolve to zero, and a pass the GPU reordered gives nonsense
+        (begin > 0 && end > begin).then(|| (end - begin) as f32 * period_ns / 1_000_000.0)
+    })
+}
+
+/// Latest GPU pass times
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GpuTimingResults {
+    /// The device supports timestamp queries, without them there are never any times
+    pub supported: bool,
+    pub passes: [Option<f32>; GpuPass::ALL.len()],
+}
+
+impl GpuTimingResults {
+    pub fn pass(&self, pass: GpuPass) -> Option<f32> {
+        self.passes[pass as usize]
+    }
+
+    /// Sum of the passes that ran
+    pub fn total(&self) -> Option<f32> {
+        self.passes.iter().flatten().copied().reduce(|a, b| a + b)
+    }
+}
+
+/// GPU pass times, shared between the main and render worlds since the readback finishes on the render side
+#[derive(Resource, Debug, Clone, Default)]
+pub struct GpuTimings(Arc<Mutex<GpuTimingResults>>);
+
+impl GpuTimings {
+    pub fn get(&self) -> GpuTimingResults {
+        *self.0.lock().unwrap()
+    }
+
+    fn set(&self, results: GpuTimingResults) {
+        *self.0.lock().unwrap() = results;
+    }
+}
+
+/// Where the readback of the resolved timestamps is
+const IDLE: u8 = 0;
+const COPIED: u8 = 1;
+const MAPPING: u8 = 2;
+const MAPPED: u8 = 3;
+
+/// Timestamp queries and their readback buffers in the render world, only there when the device
+/// supports timestamp queries. Passes write into it with [`GpuTimer::begin`] and [`GpuTimer::end`].
+#[derive(Resource)]
+pub struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve: Buffer,
+    readback: Buffer,
+    /// Nanoseconds per timestamp tick
+    period_ns: f32,
+    state: Arc<AtomicU8>,
+}
+
+impl GpuTimer {
+    pub fn new(device: &RenderDevice, queue: &RenderQueue) -> Option<Self> {
+        if !device.features().contains(WgpuFeatures::TIMESTAMP_QUERY) {
+            return None;
+        }
+        let query_set = device.wgpu_device().create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("gpu_timing_queries"),
+            ty: wgpu::QueryType::Timestamp,
+            count: QUERY_COUNT,
+        });
+        let resolve = device.create_buffer(&BufferDescriptor {
+            label: Some("gpu_timing_resolve"),
+            size: RESOLVE_SIZE,
+            usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = device.create_buffer(&BufferDescriptor {
+            label: Some("gpu_timing_readback"),
+            size: RESOLVE_SIZE,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        Some(Self {
+            query_set,
+            resolve,
+            readback,
+            period_ns: queue.get_timestamp_period(),
+            state: Arc::new(AtomicU8::new(IDLE)),
+        })
+    }
+
+    pub fn begin(&self, encoder: &mut wgpu::CommandEncoder, pass: GpuPass) {
+        encoder.write_timestamp(&self.query_set, pass.begin_query());
+    }
+
+    pub fn end(&self, encoder: &mut wgpu::CommandEncoder, pass: GpuPass) {
+        encoder.write_timestamp(&self.query_set, pass.end_query());
+    }
+
+    /// Copies this frame's timestamps out for reading, unless the last copy is still being read
+    fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        if self.state.load(Ordering::Acquire) != IDLE {
+            return;
+        }
+        encoder.resolve_query_set(&self.query_set, 0..QUERY_COUNT, &self.resolve, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve, 0, &self.readback, 0, RESOLVE_SIZE);
+        self.state.store(COPIED, Ordering::Release);
+    }
+}
+
+/// Writes one of the timestamps around a pass in the render graph
+struct GpuTimestampNode {
+    pass: GpuPass,
+    end: bool,
+}
+
+impl Node for GpuTimestampNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        if let Some(timer) = world.get_resource::<GpuTimer>() {
+            let encoder = render_context.command_encoder();
+            if self.end {
+                timer.end(encoder, self.pass);
+            } else {
+                timer.begin(encoder, self.pass);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Resolves the frame's timestamps once every timed pass is recorded
+struct GpuTimingResolveNode;
+
+impl Node for GpuTimingResolveNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        if let Some(timer) = world.get_resource::<GpuTimer>() {
+            timer.resolve(render_context.command_encoder());
+        }
+        Ok(())
+    }
+}
+
+/// Starts reading the timestamps copied this frame, and hands over the last ones once they're readable
+fn read_back_timestamps(timer: Option<Res<GpuTimer>>, device: Res<RenderDevice>, timings: Res<GpuTimings>) {
+    let Some(timer) = timer else {
+        return;
+    };
+    match timer.state.load(Ordering::Acquire) {
+        COPIED => {
+            timer.state.store(MAPPING, Ordering::Release);
+            let state = timer.state.clone();
+            device.map_buffer(&timer.readback.slice(..), MapMode::Read, move |result| {
+                state.store(if result.is_ok() { MAPPED } else { IDLE }, Ordering::Release);
+            });
+        }
+        MAPPED => {
+            let timestamps: Vec<u64> = {
+                let view = timer.readback.slice(..).get_mapped_range();
+                view.chunks_exact(8).map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap())).collect()
+            };
+            timer.readback.unmap();
+            timer.state.store(IDLE, Ordering::Release);
+            timings.set(GpuTimingResults {
+                supported: true,
+                passes: pass_durations(&timestamps, timer.period_ns),
+            });
+        }
+        _ => {}
+    }
+    device.wgpu_device().poll(wgpu::Maintain::Poll);
+}
+
+/// Plugin that times the main pass, particle compute and post-process chain on the GPU
+pub struct GpuTimingPlugin;
+
+impl GpuTimingPlugin {
+    const MAIN_PASS_BEGIN: &'static str = "gpu_timing_main_pass_begin";
+    const MAIN_PASS_END: &'static str = "gpu_timing_main_pass_end";
+    const RESOLVE: &'static str = "gpu_timing_resolve";
+}
+
+impl Plugin for GpuTimingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GpuTimings>();
+        let timings = app.world.resource::<GpuTimings>().clone();
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .insert_resource(timings)
+            .add_systems(Render, read_back_timestamps.in_set(RenderSet::Cleanup));
+
+        let mut render_graph = render_app.world.resource_mut::<RenderGraph>();
+        render_graph.add_node(Self::MAIN_PASS_BEGIN, GpuTimestampNode { pass: GpuPass::MainPass, end: false });
+        render_graph.add_node(Self::MAIN_PASS_END, GpuTimestampNode { pass: GpuPass::MainPass, end: true });
+        render_graph.add_node(Self::RESOLVE, GpuTimingResolveNode);
+        render_graph.add_node_edge(Self::MAIN_PASS_BEGIN, "main_pass");
+        render_graph.add_node_edge("main_pass", Self::MAIN_PASS_END);
+        render_graph.add_node_edge(Self::MAIN_PASS_END, PostProcessChainNode::NAME);
+        render_graph.add_node_edge(PostProcessChainNode::NAME, Self::RESOLVE);
+    }
+
+    fn finish(&self, app: &mut App) {
+        // The device only exists once the renderer is up, which is after every plugin has been built
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        let (Some(device), Some(queue)) =
+            (render_app.world.get_resource::<RenderDevice>(), render_app.world.get_resource::<RenderQueue>())
+        else {
+            return;
+        };
+        let timer = GpuTimer::new(device, queue);
+        let supported = timer.is_some();
+        if let Some(timer) = timer {
+            render_app.insert_resource(timer);
+        } else {
+            info!("GPU timing unavailable, the device doesn't support timestamp queries");
+        }
+        app.world.resource::<GpuTimings>().set(GpuTimingResults { supported, ..default() });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pass_durations_from_timestamps() {
+        // Main pass 2 ms, particles never ran, post-process 0.5 ms at 1 ns per tick
+        let timestamps = [1_000_000, 3_000_000, 0, 0, 3_000_000, 3_500_000];
+        let durations = pass_durations(&timestamps, 1.0);
+        assert!((durations[0].unwrap() - 2.0).abs() < 1e-4);
+        assert_eq!(durations[1], None);
+        assert!((durations[2].unwrap() - 0.5).abs() < 1e-4);
+        // Ticks longer than a nanosecond scale up
+        assert!((pass_durations(&timestamps, 2.0)[0].unwrap() - 4.0).abs() < 1e-4);
+        assert_eq!(pass_durations(&[], 1.0), [None; 3]);
+    }
+
+    #[test]
+    fn test_total_skips_passes_that_did_not_run() {
+        let results = GpuTimingResults {
+            supported: true,
+            passes: [Some(2.0), None, Some(0.5)],
+        };
+        assert_eq!(results.total(), Some(2.5));
+        assert_eq!(results.pass(GpuPass::ParticleCompute), None);
+        assert_eq!(GpuTimingResults::default().total(), None);
+    }
+}
@@ -0,0 +1,420 @@
+use bevy::{
+    core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+    prelude::*,
+    render::{
+        render_graph::{Node, NodeRunError, RenderGraphContext},
+        render_resource::*,
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        view::ViewTarget,
+        Extract, RenderApp,
+    },
+};
+use bevy_rapier3d::prelude::Velocity;
+use bytemuck::{Pod, Zeroable};
+
+use super::{load_shader, PostProcessChain, PostProcessEffect, PostProcessEffectNodes};
+use crate::game::plugins::camera::{CockpitView, GameCamera};
+use crate::game::plugins::weather::WeatherManager;
+use crate::game::vehicle::DirtState;
+use crate::rendering::{render_pipeline, track_render_pipeline};
+
+/// Below this much water or mud the lens counts as clean and the pass is skipped
+const CLEAN: f32 = 0.005;
+
+/// How fast the chase camera's lens gets wet and muddy, and how it clears
+#[derive(Resource, Clone, Debug)]
+pub struct LensSettings {
+    pub enabled: bool,
+    /// Droplet coverage gained per second in the heaviest rain
+    pub rain_rate: f32,
+    /// Droplet coverage lost per second out of the rain, airflow at speed blows them off faster
+    pub drying_rate: f32,
+    /// Speed in m/s at which droplets run into full streaks
+    pub streak_speed: f32,
+    /// Droplet coverage per unit of wetness a water splash leaves on the vehicle
+    pub water_per_splash: f32,
+    /// Mud coverage per unit the mud line climbs in a splash
+    pub mud_per_splash: f32,
+    /// Mud lost per second as it dries and flakes off
+    pub mud_drying_rate: f32,
+    /// Key wiping the lens clean
+    pub wipe_key: KeyCode,
+    /// Seconds a wipe takes
+    pub wipe_time: f32,
+}
+
+impl Default for LensSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rain_rate: 0.08,
+            drying_rate: 0.05,
+            streak_speed: 20.0,
+            water_per_splash: 0.6,
+            mud_per_splash: 4.0,
+            mud_drying_rate: 0.01,
+            wipe_key: KeyCode::U,
+            wipe_time: 0.6,
+        }
+    }
+}
+
+/// Water and mud on the chase camera's lens
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct LensDirt {
+    /// Rain droplet coverage (0.0 - 1.0)
+    pub droplets: f32,
+    /// How far the droplets run, beading at 0 and streaking at 1
+    pub streak: f32,
+    /// Mud splatter coverage (0.0 - 1.0)
+    pub mud: f32,
+    /// Seconds left of a running wipe
+    wiping: f32,
+    /// Target vehicle and its mud line and wetness last frame, to spot splashes by
+    last_splash: Option<(Entity, f32, f32)>,
+}
+
+impl LensDirt {
+    pub fn is_clean(&self) -> bool {
+        self.droplets < CLEAN && self.mud < CLEAN
+    }
+
+    /// Gathers rain falling at `rain` (0.0 - 1.0) while the camera's target moves at `speed` in m/s
+    pub fn update(&mut self, settings: &LensSettings, rain: f32, speed: f32, dt: f32) {
+        let airflow = (speed / settings.streak_speed.max(0.1)).min(1.0);
+        if rain > 0.0 {
+            self.droplets += settings.rain_rate * rain * dt;
+        } else {
+            self.droplets -= settings.drying_rate * (1.0 + airflow) * dt;
+        }
+        // Droplets start running as soon as the air pushes them, and bead up again slowly
+        let rate = if airflow > self.streak { 4.0 } else { 0.5 };
+        self.streak += (airflow - self.streak) * (rate * dt).min(1.0);
+        self.mud -= settings.mud_drying_rate * dt;
+
+        if self.wiping > 0.0 {
+            let wiped = (dt / self.wiping).min(1.0);
+            self.droplets *= 1.0 - wiped;
+            self.mud *= 1.0 - wiped;
+            self.wiping -= dt;
+        }
+        self.droplets = self.droplets.clamp(0.0, 1.0);
+        self.mud = self.mud.clamp(0.0, 1.0);
+    }
+
+    /// Throws water and mud onto the lens
+    pub fn splash(&mut self, water: f32, mud: f32) {
+        self.droplets = (self.droplets + water).min(1.0);
+        self.mud = (self.mud + mud).min(1.0);
+    }
+
+    pub fn wipe(&mut self, settings: &LensSettings) {
+        self.wiping = settings.wipe_time.max(f32::EPSILON);
+    }
+}
+
+/// Wets and muddies the lens of the first chase camera from the weather and its target's splashes.
+/// The cockpit view looks through the windshield and keeps its lens clean.
+fn update_lens_dirt(
+    time: Res<Time>,
+    keyboard: Res<Input<KeyCode>>,
+    settings: Res<LensSettings>,
+    weather: Option<Res<WeatherManager>>,
+    mut lens: ResMut<LensDirt>,
+    cameras: Query<&GameCamera, Without<CockpitView>>,
+    targets: Query<(Option<&Velocity>, Option<&DirtState>)>,
+) {
+    if keyboard.just_pressed(settings.wipe_key) {
+        lens.wipe(&settings);
+    }
+
+    let target = cameras.iter().next().and_then(|camera| camera.target);
+    let (velocity, dirt) = target.and_then(|target| targets.get(target).ok()).unwrap_or_default();
+    let speed = velocity.map_or(0.0, |velocity| velocity.linvel.length());
+    let rain = weather
+        .as_deref()
+        .map(WeatherManager::current_state)
+        .filter(|state| state.weather().is_rain())
+        .map_or(0.0, |state| state.precipitation());
+    lens.update(&settings, rain, speed, time.delta_seconds());
+
+    // A splash shows up as the target's mud line jumping or its body getting wetter
+    let current = target.zip(dirt).map(|(target, dirt)| (target, dirt.mud_line, dirt.wetness));
+    if let Some((now, before)) = current.zip(lens.last_splash).filter(|(now, before)| now.0 == before.0) {
+        let water = (now.2 - before.2).max(0.0) * settings.water_per_splash;
+        let mud = (now.1 - before.1).max(0.0) * settings.mud_per_splash;
+        lens.splash(water, mud);
+    }
+    lens.last_splash = current;
+}
+
+/// Runs the lens pass only while there's something on the lens
+fn sync_lens_chain(
+    settings: Res<LensSettings>,
+    lens: Res<LensDirt>,
+    cameras: Query<(), (With<GameCamera>, Without<CockpitView>)>,
+    mut chain: ResMut<PostProcessChain>,
+) {
+    let visible = settings.enabled && !lens.is_clean() && !cameras.is_empty();
+    if chain.is_enabled(PostProcessEffect::Lens) != visible {
+        chain.set_enabled(PostProcessEffect::Lens, visible);
+    }
+}
+
+/// GPU-side lens parameters
+#[derive(Resource, ShaderType, Clone, Copy, Debug, Default, Pod, Zeroable)]
+#[repr(C)]
+pub struct LensUniform {
+    pub droplets: f32,
+    pub streak: f32,
+    pub mud: f32,
+    /// Seconds since startup, runs the streaks down the lens
+    pub time: f32,
+}
+
+/// Copies the lens state into the render world
+fn extract_lens(mut commands: Commands, lens: Extract<Res<LensDirt>>, time: Extract<Res<Time>>) {
+    commands.insert_resource(LensUniform {
+        droplets: lens.droplets,
+        streak: lens.streak,
+        mud: lens.mud,
+        time: time.elapsed_seconds_wrapped(),
+    });
+}
+
+/// Pipeline for the full-screen lens pass
+#[derive(Resource)]
+pub struct LensPipeline {
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    settings_buffer: Buffer,
+    pipeline_id: CachedRenderPipelineId,
+}
+
+impl FromWorld for LensPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let shader = load_shader(world, "lens.wgsl");
+
+        let render_device = world.resource::<RenderDevice>();
+
+        let layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("lens_bind_group_layout"),
+            entries: &[
+                // Scene texture
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // Sampler
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                // Lens settings
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: Some(LensUniform::min_size()),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let sampler = render_device.create_sampler(&SamplerDescriptor {
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..default()
+        });
+
+        let settings_buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("lens_settings_buffer"),
+            size: LensUniform::min_size().get(),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let pipeline_id = world.resource::<PipelineCache>().queue_render_pipeline(RenderPipelineDescriptor {
+            label: Some("lens_pipeline".into()),
+            layout: vec![layout.clone()],
+            push_constant_ranges: vec![],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader,
+                shader_defs: vec![],
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format: ViewTarget::TEXTURE_FORMAT_HDR,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+        });
+
+        track_render_pipeline(world, pipeline_id);
+
+        Self {
+            layout,
+            sampler,
+            settings_buffer,
+            pipeline_id,
+        }
+    }
+}
+
+/// Node in the render graph that draws the droplets and mud over the scene before tone mapping
+pub struct LensNode {
+    query: QueryState<&'static ViewTarget>,
+}
+
+impl LensNode {
+    /// Name of the node in the render graph
+    pub const NAME: &'static str = "lens";
+}
+
+impl FromWorld for LensNode {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for LensNode {
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let Ok(view_target) = self.query.get_manual(world, graph.view_entity()) else {
+            return Ok(());
+        };
+        let (Some(pipeline), Some(uniform)) = (
+            world.get_resource::<LensPipeline>(),
+            world.get_resource::<LensUniform>(),
+        ) else {
+            return Ok(());
+        };
+        let Some(render_pipeline) = render_pipeline(world, pipeline.pipeline_id) else {
+            return Ok(());
+        };
+
+        world
+            .resource::<RenderQueue>()
+            .write_buffer(&pipeline.settings_buffer, 0, bytemuck::bytes_of(uniform));
+
+        let post_process = view_target.post_process_write();
+        let bind_group = render_context.render_device().create_bind_group(
+            "lens_bind_group",
+            &pipeline.layout,
+            &BindGroupEntries::sequential((
+                post_process.source,
+                &pipeline.sampler,
+                pipeline.settings_buffer.as_entire_binding(),
+            )),
+        );
+
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("lens_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: post_process.destination,
+                resolve_target: None,
+                ops: Operations::default(),
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_render_pipeline(render_pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
+
+/// Plugin that adds rain droplets and mud splatter on the chase camera's lens
+pub struct LensPlugin;
+
+impl Plugin for LensPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LensSettings>()
+            .init_resource::<LensDirt>()
+            .add_systems(Update, (update_lens_dirt, sync_lens_chain).chain());
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.add_systems(ExtractSchedule, extract_lens);
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<LensPipeline>();
+
+        let node = LensNode::from_world(&mut render_app.world);
+        render_app.world.resource_mut::<PostProcessEffectNodes>().insert(PostProcessEffect::Lens, node);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rain_wets_and_speed_streaks() {
+        let settings = LensSettings::default();
+        let mut lens = LensDirt::default();
+        for _ in 0..60 {
+            lens.update(&settings, 1.0, settings.streak_speed, 0.1);
+        }
+        assert!(lens.droplets > 0.4);
+        assert!(lens.streak > 0.9);
+
+        // Out of the rain it dries again, quicker when driving
+        let mut parked = lens.clone();
+        for _ in 0..20 {
+            lens.update(&settings, 0.0, settings.streak_speed, 0.1);
+            parked.update(&settings, 0.0, 0.0, 0.1);
+        }
+        assert!(lens.droplets < parked.droplets);
+        assert!(parked.streak < lens.streak);
+    }
+
+    #[test]
+    fn test_wipe_clears_splashes() {
+        let settings = LensSettings::default();
+        let mut lens = LensDirt::default();
+        lens.splash(0.3, 0.8);
+        assert_eq!(lens.mud, 0.8);
+        assert!(!lens.is_clean());
+
+        lens.wipe(&settings);
+        for _ in 0..10 {
+            lens.update(&settings, 0.0, 0.0, settings.wipe_time / 10.0);
+        }
+        assert!(lens.is_clean());
+    }
+}
@@ -4,7 +4,7 @@
 /// including tone mapping, bloom, ambient occlusion, depth of field, and color grading.
 /// 
 /// The post-processing system uses a chain of effects that can be enabled/disabled and reordered
-/// at runtime. Each effect is implemented as a compute shader for optimal performance.
+/// at runtime through the `PostProcessChain` resource. Disabled effects are skipped entirely.
 /// 
 /// # Features
 /// - HDR tone mapping (ACES, Reinhard, etc.)
@@ -13,6 +13,7 @@
 /// - Depth of Field with bokeh simulation
 /// - Color grading with LUT support
 /// - Temporal Anti-Aliasing (TAA)
+/// - Night vision and thermal camera filters
 /// - Motion blur using velocity vectors
 /// 
 /// # Example
@@ -42,6 +43,7 @@
 /// ```
 
 use bevy::{
+    asset::embedded_asset,
     prelude::*,
     render::{
         render_asset::RenderAssets,
@@ -62,12 +64,37 @@ mod pipeline;
 mod settings;
 mod ui;
 mod node;
+mod camera_filter;
+mod chain;
+mod color_grading;
+mod dof;
+mod gpu_timing;
+#[cfg(feature = "golden-images")]
+mod golden;
+mod lens;
+mod scene_color;
+mod ssao;
+mod taa;
 mod test_scene;
 
 pub use effects::*;
 pub use pipeline::*;
 pub use settings::*;
 pub use ui::PerformanceDisplayPlugin;
+pub use camera_filter::{CameraFilterMode, CameraFilterPlugin, CameraFilterSettings, CameraFilterUniform, HeatSource};
+pub use chain::{EffectSlot, PostProcessChain, PostProcessChainNode, PostProcessChainPlugin, PostProcessEffect, PostProcessEffectNodes};
+pub use color_grading::{ColorGradeLibrary, ColorGradeRegion, ColorGrading, ColorGradingPlugin, ColorLut, LutError};
+pub use dof::{circle_of_confusion, DofFocus, DofFocusMode, DofPlugin, DofQuality};
+pub use gpu_timing::{pass_durations, GpuPass, GpuTimer, GpuTimingPlugin, GpuTimingResults, GpuTimings};
+#[cfg(feature = "golden-images")]
+pub use golden::{
+    check_golden_scene, delta_e, golden_scenes, perceptual_diff, render_golden_scene, srgb_to_lab, GoldenConfig,
+    GoldenDiff, GoldenError, GoldenPattern, GoldenReport, GoldenScene, GoldenTolerance,
+};
+pub use lens::{LensDirt, LensPlugin, LensSettings, LensUniform};
+pub use scene_color::{SceneColor, SceneColorNode, SceneColorPlugin, SceneColorSource};
+pub use ssao::{SsaoPlugin, SsaoUniform};
+pub use taa::{AntiAliasingMode, JitterSequence, MotionVectorSupport, TaaPlugin, TaaSettings};
 use node::PostProcessNode;
 
 /// Post-processing settings that control various visual effects in the rendering pipeline.
@@ -115,6 +142,48 @@ pub struct PostProcessSettings {
     /// Chromatic aberration strength. 0.0 is off.
     /// Range: [0.0, 1.0]
     pub chromatic_aberration: f32,
+
+    /// Whether screen space ambient occlusion is rendered.
+    /// Driven by the graphics settings toggle when `GameSettings` is present.
+    pub ssao_enabled: bool,
+
+    /// World-space sampling radius for SSAO in meters.
+    /// Range: [0.05, 2.0]
+    pub ssao_radius: f32,
+
+    /// Depth bias used to avoid self-occlusion on flat surfaces.
+    /// Range: [0.0, 0.1]
+    pub ssao_bias: f32,
+
+    /// Strength of the occlusion darkening. 0.0 is off.
+    /// Range: [0.0, 2.0]
+    pub ssao_intensity: f32,
+
+    /// Depth downsample factor for the SSAO pass (1, 2 or 4).
+    /// Higher values trade detail for performance.
+    pub ssao_downsample: u32,
+
+    /// Whether the bokeh depth of field pass is rendered.
+    pub dof_enabled: bool,
+
+    /// Lens focal length in millimeters. Longer lenses give a shallower focus.
+    /// Range: [10.0, 300.0]
+    pub dof_focal_length: f32,
+
+    /// Aperture as an f-stop. Lower values blur more.
+    /// Range: [1.0, 22.0]
+    pub dof_aperture: f32,
+
+    /// Sample count and maximum blur radius tier for the bokeh gather.
+    pub dof_quality: DofQuality,
+
+    /// Asset path of the color grading LUT (`.cube` or `.lut.png` strip).
+    /// Used when no biome or weather grade applies. `None` means no grade.
+    pub color_lut: Option<String>,
+
+    /// Mix between the ungraded and LUT graded image. 0.0 skips the pass.
+    /// Range: [0.0, 1.0]
+    pub lut_intensity: f32,
 }
 
 impl Default for PostProcessSettings {
@@ -130,6 +199,17 @@ impl Default for PostProcessSettings {
             contrast: 1.0,
             vignette: 0.2,
             chromatic_aberration: 0.0,
+            ssao_enabled: true,
+            ssao_radius: 0.5,
+            ssao_bias: 0.025,
+            ssao_intensity: 1.0,
+            ssao_downsample: 2,
+            dof_enabled: false,
+            dof_focal_length: 50.0,
+            dof_aperture: 5.6,
+            dof_quality: DofQuality::Medium,
+            color_lut: None,
+            lut_intensity: 1.0,
         }
     }
 }
@@ -148,6 +228,11 @@ impl PostProcessSettings {
             contrast: 1.2,
             vignette: 0.3,
             chromatic_aberration: 0.1,
+            ssao_intensity: 1.2,
+            dof_enabled: true,
+            dof_aperture: 2.8,
+            color_lut: Some("luts/cinematic.cube".to_string()),
+            ..Default::default()
         }
     }
 
@@ -164,6 +249,9 @@ impl PostProcessSettings {
             contrast: 1.1,
             vignette: 0.1,
             chromatic_aberration: 0.0,
+            ssao_intensity: 0.8,
+            color_lut: Some("luts/vibrant.cube".to_string()),
+            ..Default::default()
         }
     }
 
@@ -180,6 +268,10 @@ impl PostProcessSettings {
             contrast: 1.3,
             vignette: 0.4,
             chromatic_aberration: 0.05,
+            ssao_radius: 0.75,
+            ssao_intensity: 1.4,
+            color_lut: Some("luts/moody.cube".to_string()),
+            ..Default::default()
         }
     }
 }
@@ -193,12 +285,37 @@ impl PostProcessSettings {
 /// - Chromatic aberration
 /// - Vignette
 /// - Color grading (saturation, contrast, brightness)
+/// - Screen space ambient occlusion
+/// - Temporal anti-aliasing (with FXAA fallback)
+/// - Bokeh depth of field focused on the followed vehicle
+/// - LUT color grading blended across biomes and weather
+/// - Night vision and thermal view modes, toggled at night
+/// - Rain droplets and mud splatter on the chase camera's lens
 pub struct PostProcessPlugin;
 
 impl Plugin for PostProcessPlugin {
     fn build(&self, app: &mut App) {
+        // Compiled in, and watched at their source path with the `shader-hot-reload` feature
+        embedded_asset!(app, "shaders/camera_filter.wgsl");
+        embedded_asset!(app, "shaders/color_grading.wgsl");
+        embedded_asset!(app, "shaders/dof.wgsl");
+        embedded_asset!(app, "shaders/lens.wgsl");
+        embedded_asset!(app, "shaders/ssao.wgsl");
+        embedded_asset!(app, "shaders/taa.wgsl");
+
         // Add settings resource
-        app.init_resource::<PostProcessSettings>();
+        app.init_resource::<PostProcessSettings>()
+            .add_plugins((
+                PostProcessChainPlugin,
+                SsaoPlugin,
+                TaaPlugin,
+                DofPlugin,
+                ColorGradingPlugin,
+                CameraFilterPlugin,
+                LensPlugin,
+                GpuTimingPlugin,
+                SceneColorPlugin,
+            ));
 
         // Add systems to the render app
         let render_app = app.sub_app_mut(RenderApp);
@@ -208,16 +325,18 @@ impl Plugin for PostProcessPlugin {
     }
 }
 
+/// Loads one of the shaders [`PostProcessPlugin`] embeds from `shaders/`
+fn load_shader(world: &World, file: &str) -> Handle<Shader> {
+    world.resource::<AssetServer>().load(format!("embedded://sandk_offroad/game/plugins/post_process/shaders/{file}"))
+}
+
 fn setup_post_process_node(
-    mut render_graph: ResMut<RenderGraph>,
+    mut effect_nodes: ResMut<PostProcessEffectNodes>,
     device: Res<RenderDevice>,
 ) {
-    // Create and add the post-process node
+    // Tone mapping runs as one effect of the chain, ordered by `PostProcessChain`
     let node = PostProcessNode::new(&device);
-    render_graph.add_node("post_process", node);
-
-    // Add edge from camera node to post-process node
-    render_graph.add_node_edge("camera", "post_process");
+    effect_nodes.insert(PostProcessEffect::ToneMapping, node);
 }
 
 /// Enum defining available tone mapping operators
@@ -255,8 +374,11 @@ mod tests {
         // Verify settings resource was added
         assert!(app.world.contains_resource::<PostProcessSettings>());
 
+        assert!(app.world.contains_resource::<PostProcessChain>());
+
         // Verify render app setup
         let render_app = app.sub_app(RenderApp);
         assert!(render_app.world.contains_resource::<PostProcessPipeline>());
+        assert!(render_app.world.contains_resource::<PostProcessEffectNodes>());
     }
 } 
\ No newline at end of file
@@ -0,0 +1,218 @@
+use bevy::{
+    core_pipeline::blit::{BlitPipeline, BlitPipelineKey},
+    prelude::*,
+    render::{
+        extract_component::{ExtractComponent, ExtractComponentPlugin},
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        render_asset::RenderAssets,
+        render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext},
+        render_resource::*,
+        renderer::RenderContext,
+        view::ViewTarget,
+        Render, RenderApp, RenderSet,
+    },
+};
+
+use super::PostProcessChainNode;
+use crate::game::plugins::camera::GameCamera;
+use crate::rendering::render_pipeline;
+
+/// Format of the copy, HDR so bright highlights survive into reflections
+const SCENE_COLOR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// Copy of the game camera's lit scene, taken after the main pass and before post-processing.
+/// Materials drawn in the main pass read the previous frame's copy, for screen-space effects like
+/// reflections that need the color of what's around them.
+#[derive(Resource, Clone, ExtractResource)]
+pub struct SceneColor {
+    pub image: Handle<Image>,
+}
+
+/// Camera whose main pass is copied into [`SceneColor`]
+#[derive(Component, Clone, Copy, ExtractComponent)]
+pub struct SceneColorSource;
+
+/// Blit pipeline copying into the scene color, per view
+#[derive(Component)]
+pub struct SceneColorPipeline(CachedRenderPipelineId);
+
+fn scene_color_image(size: UVec2) -> Image {
+    let size = Extent3d { width: size.x.max(1), height: size.y.max(1), depth_or_array_layers: 1 };
+    let mut image = Image {
+        data: vec![0; (size.width * size.height * 8) as usize],
+        ..default()
+    };
+    image.texture_descriptor.size = size;
+    image.texture_descriptor.dimension = TextureDimension::D2;
+    image.texture_descriptor.format = SCENE_COLOR_FORMAT;
+    image.texture_descriptor.usage =
+        TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::RENDER_ATTACHMENT;
+    image
+}
+
+fn setup_scene_color(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let image = images.add(scene_color_image(UVec2::ONE));
+    commands.insert_resource(SceneColor { image });
+}
+
+/// Copies the first game camera, split screen players beyond it only get what its view shows,
+/// and keeps the copy the size of its viewport
+fn update_scene_color_source(
+    mut commands: Commands,
+    scene_color: Res<SceneColor>,
+    mut images: ResMut<Assets<Image>>,
+    cameras: Query<(Entity, &Camera, Has<SceneColorSource>), With<GameCamera>>,
+) {
+    let source = cameras.iter().find(|(_, camera, _)| camera.is_active);
+    for (entity, _, marked) in cameras.iter() {
+        let is_source = source.is_some_and(|(source, _, _)| source == entity);
+        if marked && !is_source {
+            commands.entity(entity).remove::<SceneColorSource>();
+        } else if is_source && !marked {
+            commands.entity(entity).insert(SceneColorSource);
+        }
+    }
+
+    let Some(size) = source.and_then(|(_, camera, _)| camera.physical_target_size()) else {
+        return;
+    };
+    let current = images.get(&scene_color.image).map(|image| image.size());
+    if current != Some(size) {
+        if let Some(image) = images.get_mut(&scene_color.image) {
+            image.resize(Extent3d { width: size.x.max(1), height: size.y.max(1), depth_or_array_layers: 1 });
+        }
+    }
+}
+
+fn prepare_scene_color_pipelines(
+    mut commands: Commands,
+    pipeline_cache: Res<PipelineCache>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<BlitPipeline>>,
+    blit_pipeline: Res<BlitPipeline>,
+    views: Query<Entity, With<SceneColorSource>>,
+) {
+    for entity in views.iter() {
+        let key = BlitPipelineKey {
+            texture_format: SCENE_COLOR_FORMAT,
+            blend_state: None,
+            samples: 1,
+        };
+        let id = pipelines.specialize(&pipeline_cache, &blit_pipeline, key);
+        commands.entity(entity).insert(SceneColorPipeline(id));
+    }
+}
+
+/// Node in the render graph that copies the main pass of [`SceneColorSource`] views
+pub struct SceneColorNode {
+    query: QueryState<(&'static ViewTarget, &'static SceneColorPipeline), With<SceneColorSource>>,
+}
+
+impl SceneColorNode {
+    /// Name of the node in the render graph
+    pub const NAME: &'static str = "scene_color";
+}
+
+impl FromWorld for SceneColorNode {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for SceneColorNode {
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let Ok((view_target, pipeline)) = self.query.get_manual(world, graph.view_entity()) else {
+            return Ok(());
+        };
+        let Some(scene_color) = world.get_resource::<SceneColor>() else {
+            return Ok(());
+        };
+        let Some(destination) = world.resource::<RenderAssets<Image>>().get(&scene_color.image) else {
+            return Ok(());
+        };
+        let Some(render_pipeline) = render_pipeline(world, pipeline.0) else {
+            return Ok(());
+        };
+
+        let blit_pipeline = world.resource::<BlitPipeline>();
+        let bind_group = render_context.render_device().create_bind_group(
+            "scene_color_bind_group",
+            &blit_pipeline.texture_bind_group,
+            &BindGroupEntries::sequential((view_target.main_texture_view(), &blit_pipeline.sampler)),
+        );
+
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("scene_color_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &destination.texture_view,
+                resolve_target: None,
+                ops: Operations::default(),
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_render_pipeline(render_pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
+
+/// Plugin that keeps a copy of the game camera's scene for materials to sample
+pub struct SceneColorPlugin;
+
+impl Plugin for SceneColorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            ExtractResourcePlugin::<SceneColor>::default(),
+            ExtractComponentPlugin::<SceneColorSource>::default(),
+        ))
+        .add_systems(Startup, setup_scene_color)
+        .add_systems(Update, update_scene_color_source);
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .init_resource::<SpecializedRenderPipelines<BlitPipeline>>()
+            .add_systems(Render, prepare_scene_color_pipelines.in_set(RenderSet::Prepare));
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        let node = SceneColorNode::from_world(&mut render_app.world);
+        let mut render_graph = render_app.world.resource_mut::<RenderGraph>();
+        render_graph.add_node(SceneColorNode::NAME, node);
+        // Before post-processing tonemaps and grades the main texture in place
+        render_graph.add_node_edge("main_pass", SceneColorNode::NAME);
+        render_graph.add_node_edge(SceneColorNode::NAME, PostProcessChainNode::NAME);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scene_color_image_is_renderable_and_sampleable() {
+        let image = scene_color_image(UVec2::new(320, 180));
+        assert_eq!(image.size(), UVec2::new(320, 180));
+        assert_eq!(image.texture_descriptor.format, SCENE_COLOR_FORMAT);
+        let usage = image.texture_descriptor.usage;
+        assert!(usage.contains(TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING));
+        // A zero sized viewport still gets a texture
+        assert_eq!(scene_color_image(UVec2::ZERO).size(), UVec2::ONE);
+    }
+}
@@ -0,0 +1,62 @@
+// Night vision and thermal camera filters
+//
+// Runs on the HDR scene before tone mapping. Night vision amplifies the scene into green
+// phosphor with grain, clamping highlights so amplified lights don't flood the bloom. Thermal
+// maps brightness onto a false color palette; heat sources glow through their emissive, which
+// is driven from their temperature while the mode is on, so they read hot against a cold world.
+
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+struct CameraFilterSettings {
+    mode: u32,              // 0 = off, 1 = night vision, 2 = thermal
+    time: f32,              // Seconds, animates the grain
+    gain: f32,              // Night vision amplification
+    noise: f32,             // Night vision grain strength
+    bloom_clamp: f32,       // Brightest value night vision passes on
+    thermal_range: f32,     // Brightness that reads as the hottest color
+    vignette: f32,          // Darkening towards the edge of the night vision tube
+    _padding: f32,
+}
+
+@group(0) @binding(0) var scene_texture: texture_2d<f32>;
+@group(0) @binding(1) var scene_sampler: sampler;
+@group(0) @binding(2) var<uniform> settings: CameraFilterSettings;
+
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+}
+
+// Black through blue, magenta, red and yellow to white
+fn ironbow(heat: f32) -> vec3<f32> {
+    let t = saturate(heat);
+    let cold = mix(vec3<f32>(0.0, 0.0, 0.05), vec3<f32>(0.25, 0.0, 0.55), saturate(t * 3.0));
+    let warm = mix(cold, vec3<f32>(0.9, 0.1, 0.15), saturate(t * 3.0 - 1.0));
+    let hot = mix(warm, vec3<f32>(1.0, 0.85, 0.1), saturate(t * 3.0 - 2.0));
+    return mix(hot, vec3<f32>(1.0), smoothstep(0.9, 1.0, t));
+}
+
+fn night_vision(color: vec3<f32>, uv: vec2<f32>, pixel: vec2<f32>) -> vec3<f32> {
+    let level = luminance(min(color, vec3<f32>(settings.bloom_clamp))) * settings.gain;
+    let grain = (hash(pixel + fract(settings.time) * 113.0) - 0.5) * settings.noise;
+    let tube = 1.0 - settings.vignette * smoothstep(0.35, 0.75, length(uv - 0.5));
+    let phosphor = vec3<f32>(0.15, 1.0, 0.25) * max(level + grain, 0.0) * tube;
+    return min(phosphor, vec3<f32>(settings.bloom_clamp));
+}
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let scene = textureSample(scene_texture, scene_sampler, in.uv);
+    let pixel = in.position.xy;
+
+    var color = scene.rgb;
+    if settings.mode == 1u {
+        color = night_vision(scene.rgb, in.uv, pixel);
+    } else if settings.mode == 2u {
+        color = ironbow(luminance(scene.rgb) / settings.thermal_range);
+    }
+    return vec4(color, scene.a);
+}
@@ -0,0 +1,35 @@
+// LUT color grading
+//
+// Looks the tone mapped color up in two 3D LUTs and cross-fades between them, so grades
+// change smoothly when the camera crosses a biome boundary or the weather turns.
+
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+struct ColorGradingSettings {
+    blend: f32,         // 0.0 = current LUT, 1.0 = next LUT
+    intensity: f32,     // 0.0 = ungraded, 1.0 = fully graded
+    current_size: f32,  // Entries per axis of the current LUT
+    next_size: f32,     // Entries per axis of the next LUT
+}
+
+@group(0) @binding(0) var scene_texture: texture_2d<f32>;
+@group(0) @binding(1) var current_lut: texture_3d<f32>;
+@group(0) @binding(2) var next_lut: texture_3d<f32>;
+@group(0) @binding(3) var lut_sampler: sampler;
+@group(0) @binding(4) var<uniform> settings: ColorGradingSettings;
+
+// Remap [0, 1] onto texel centers so the end points hit the first and last entries
+fn lut_coord(color: vec3<f32>, size: f32) -> vec3<f32> {
+    return saturate(color) * ((size - 1.0) / size) + 0.5 / size;
+}
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let scene = textureSample(scene_texture, lut_sampler, in.uv);
+
+    let current = textureSampleLevel(current_lut, lut_sampler, lut_coord(scene.rgb, settings.current_size), 0.0).rgb;
+    let next = textureSampleLevel(next_lut, lut_sampler, lut_coord(scene.rgb, settings.next_size), 0.0).rgb;
+    let graded = mix(current, next, settings.blend);
+
+    return vec4(mix(scene.rgb, graded, settings.intensity), scene.a);
+}
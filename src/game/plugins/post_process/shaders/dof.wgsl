@@ -0,0 +1,83 @@
+// Bokeh depth of field
+//
+// 1. bokeh (compute): computes the circle of confusion per pixel from linear depth and
+//    gathers samples on a golden-angle disc scaled by that CoC. Samples are weighted by
+//    their own CoC so in-focus foreground does not bleed onto blurred background.
+// 2. fragment: copies the blurred result back into the view target.
+
+#import bevy_render::view::View
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+struct DofSettings {
+    focus_distance: f32,    // Distance to the focal plane in meters
+    focal_length: f32,      // Lens focal length in meters
+    aperture: f32,          // Aperture diameter in meters (focal length / f-stop)
+    sensor_height: f32,     // Sensor height in meters, maps CoC to screen space
+    max_coc_pixels: f32,    // Clamp for the blur radius in pixels
+    sample_count: u32,      // Gather samples, set by the quality tier
+    _padding: vec2<u32>,
+}
+
+const GOLDEN_ANGLE: f32 = 2.39996323;
+
+@group(0) @binding(0) var<uniform> view: View;
+@group(0) @binding(1) var<uniform> settings: DofSettings;
+@group(0) @binding(2) var scene_texture: texture_2d<f32>;
+@group(0) @binding(3) var depth_texture: texture_depth_2d;
+@group(0) @binding(4) var output_texture: texture_storage_2d<rgba16float, write>;
+
+@group(1) @binding(0) var blurred_texture: texture_2d<f32>;
+@group(1) @binding(1) var linear_sampler: sampler;
+
+fn linear_depth(coord: vec2<u32>) -> f32 {
+    let depth = textureLoad(depth_texture, coord, 0);
+    // Reverse-Z infinite perspective: view z = near / depth
+    return view.projection[3][2] / max(depth, 0.000001);
+}
+
+fn coc_pixels(distance: f32, screen_height: f32) -> f32 {
+    let f = settings.focal_length;
+    let s = settings.focus_distance;
+    let coc = abs(settings.aperture * f * (distance - s) / (distance * max(s - f, 0.0001)));
+    return min(coc / settings.sensor_height * screen_height, settings.max_coc_pixels);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn bokeh(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(output_texture);
+    if (id.x >= size.x || id.y >= size.y) {
+        return;
+    }
+
+    let screen_height = f32(size.y);
+    let center_coc = coc_pixels(linear_depth(id.xy), screen_height);
+    var color = textureLoad(scene_texture, id.xy, 0);
+
+    // In focus, nothing to gather
+    if (center_coc < 0.5) {
+        textureStore(output_texture, id.xy, color);
+        return;
+    }
+
+    var total = color.rgb;
+    var weight = 1.0;
+    for (var i = 0u; i < settings.sample_count; i++) {
+        let t = (f32(i) + 0.5) / f32(settings.sample_count);
+        let angle = f32(i) * GOLDEN_ANGLE;
+        let offset = vec2(cos(angle), sin(angle)) * sqrt(t) * center_coc;
+        let coord = vec2<u32>(clamp(vec2<f32>(id.xy) + offset, vec2(0.0), vec2<f32>(size - 1u)));
+
+        // Only accept samples whose own blur reaches this pixel
+        let sample_coc = coc_pixels(linear_depth(coord), screen_height);
+        let w = saturate(sample_coc - length(offset) + 1.0);
+        total += textureLoad(scene_texture, coord, 0).rgb * w;
+        weight += w;
+    }
+
+    textureStore(output_texture, id.xy, vec4(total / weight, color.a));
+}
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(blurred_texture, linear_sampler, in.uv);
+}
@@ -0,0 +1,102 @@
+// Rain droplets and mud splatter on the chase camera's lens
+//
+// Runs on the HDR scene before tone mapping so highlights seen through a droplet still bloom.
+// Droplets are scattered over a jittered grid, one per cell where the cell's hash falls under the
+// coverage; each refracts an upside-down patch of the scene behind it. With speed they stretch
+// and run down the lens. Mud is value noise thresholded by its coverage, thickest at the bottom
+// edge where the splashes land.
+
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+struct LensSettings {
+    droplets: f32,          // Droplet coverage, 0 - 1
+    streak: f32,            // 0 beading, 1 running in streaks
+    mud: f32,               // Mud coverage, 0 - 1
+    time: f32,              // Seconds, runs the streaks down
+}
+
+@group(0) @binding(0) var scene_texture: texture_2d<f32>;
+@group(0) @binding(1) var scene_sampler: sampler;
+@group(0) @binding(2) var<uniform> settings: LensSettings;
+
+const MUD_COLOR: vec3<f32> = vec3<f32>(0.09, 0.06, 0.035);
+
+fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+}
+
+fn hash2(p: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(hash(p), hash(p + vec2<f32>(17.31, 5.79)));
+}
+
+fn value_noise(p: vec2<f32>) -> f32 {
+    let cell = floor(p);
+    let t = smoothstep(vec2<f32>(0.0), vec2<f32>(1.0), fract(p));
+    let a = hash(cell);
+    let b = hash(cell + vec2<f32>(1.0, 0.0));
+    let c = hash(cell + vec2<f32>(0.0, 1.0));
+    let d = hash(cell + vec2<f32>(1.0, 1.0));
+    return mix(mix(a, b, t.x), mix(c, d, t.x), t.y);
+}
+
+// Offset to sample the scene at and how much of a droplet covers `p`, for one layer of droplets
+// `cells` across the screen height. `p` is in screen heights, y growing downwards.
+fn droplet_layer(p: vec2<f32>, cells: f32, seed: f32) -> vec3<f32> {
+    // Running droplets slide down, each column at its own pace
+    let column = floor(p.x * cells);
+    let slide = settings.time * settings.streak * (0.3 + hash(vec2<f32>(column, seed)) * 0.5);
+    let q = (p + vec2<f32>(0.0, -slide)) * cells;
+    let id = floor(q) + vec2<f32>(seed * 31.0, 0.0);
+
+    if hash(id + vec2<f32>(3.7, seed)) > settings.droplets {
+        return vec3<f32>(0.0);
+    }
+    let center = vec2<f32>(0.5) + (hash2(id) - 0.5) * 0.5;
+    let radius = 0.12 + hash(id + vec2<f32>(9.1, 2.3)) * 0.18;
+    // Streaks trail upwards behind the running droplet
+    let stretch = 1.0 + settings.streak * 3.0;
+    var local = (fract(q) - center) / radius;
+    local.y = select(local.y, local.y / stretch, local.y < 0.0);
+    let distance = length(local);
+    if distance > 1.0 {
+        return vec3<f32>(0.0);
+    }
+
+    // A droplet is a small lens, it shows the scene around it flipped
+    let bulge = sqrt(1.0 - distance * distance);
+    let offset = -local * radius / cells * 1.5 * bulge;
+    let edge = 1.0 - smoothstep(0.75, 1.0, distance);
+    return vec3<f32>(offset, edge);
+}
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(scene_texture));
+    let aspect = size.x / size.y;
+    let p = vec2<f32>(in.uv.x * aspect, in.uv.y);
+
+    var uv = in.uv;
+    var coverage = 0.0;
+    if settings.droplets > 0.0 {
+        let big = droplet_layer(p, 7.0, 1.0);
+        let small = droplet_layer(p, 16.0, 2.0);
+        let drop = select(small, big, big.z > 0.0);
+        uv = clamp(in.uv + vec2<f32>(drop.x / aspect, drop.y), vec2<f32>(0.0), vec2<f32>(1.0));
+        coverage = drop.z;
+    }
+
+    let scene = textureSample(scene_texture, scene_sampler, in.uv);
+    let refracted = textureSample(scene_texture, scene_sampler, uv).rgb;
+    // Water scatters a little light, droplets read slightly brighter and flatter than the scene
+    var color = mix(scene.rgb, refracted * 0.95 + vec3<f32>(0.02), coverage);
+
+    if settings.mud > 0.0 {
+        let splatter = value_noise(p * 9.0) * 0.6 + value_noise(p * 23.0) * 0.4;
+        // Splashes come up from below, the bottom edge gets the most
+        let rise = mix(0.35, 1.0, smoothstep(0.3, 1.0, in.uv.y));
+        let threshold = 1.0 - settings.mud;
+        let mask = smoothstep(threshold, threshold + 0.08, splatter * rise);
+        color = mix(color, MUD_COLOR * (0.7 + splatter * 0.6), mask);
+    }
+    return vec4(color, scene.a);
+}
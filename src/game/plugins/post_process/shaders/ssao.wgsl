@@ -0,0 +1,118 @@
+// Screen-space ambient occlusion
+//
+// Runs in three steps:
+// 1. downsample_depth: reduce the full resolution depth buffer by `downsample`
+//    (keeping the farthest sample so silhouettes don't grow halos)
+// 2. ssao: hemisphere sampling against the downsampled depth
+// 3. fragment: upsample of the occlusion term multiplied into the scene color
+
+#import bevy_render::view::View
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+struct SsaoSettings {
+    radius: f32,        // World-space sampling radius
+    bias: f32,          // Depth bias to avoid self-occlusion
+    intensity: f32,     // Occlusion strength multiplier
+    downsample: u32,    // Depth downsample factor (1, 2 or 4)
+}
+
+const SAMPLE_COUNT: u32 = 12u;
+const GOLDEN_ANGLE: f32 = 2.39996323;
+
+@group(0) @binding(0) var<uniform> view: View;
+@group(0) @binding(1) var<uniform> settings: SsaoSettings;
+@group(0) @binding(2) var depth_texture: texture_depth_2d;
+@group(0) @binding(3) var downsampled_depth: texture_storage_2d<r32float, read_write>;
+@group(0) @binding(4) var ao_texture: texture_storage_2d<r32float, read_write>;
+
+@group(1) @binding(0) var scene_texture: texture_2d<f32>;
+@group(1) @binding(1) var ao_sample_texture: texture_2d<f32>;
+@group(1) @binding(2) var linear_sampler: sampler;
+
+fn view_position(uv: vec2<f32>, depth: f32) -> vec3<f32> {
+    let ndc = vec4(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    let position = view.inverse_projection * ndc;
+    return position.xyz / position.w;
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn downsample_depth(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(downsampled_depth);
+    if (id.x >= size.x || id.y >= size.y) {
+        return;
+    }
+
+    // Reverse-Z: the farthest sample has the smallest depth value
+    var depth = 1.0;
+    let base = id.xy * settings.downsample;
+    for (var y = 0u; y < settings.downsample; y++) {
+        for (var x = 0u; x < settings.downsample; x++) {
+            depth = min(depth, textureLoad(depth_texture, base + vec2(x, y), 0));
+        }
+    }
+    textureStore(downsampled_depth, id.xy, vec4(depth, 0.0, 0.0, 0.0));
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn ssao(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(ao_texture);
+    if (id.x >= size.x || id.y >= size.y) {
+        return;
+    }
+
+    let depth = textureLoad(downsampled_depth, id.xy).r;
+    // Sky pixels are never occluded
+    if (depth <= 0.0) {
+        textureStore(ao_texture, id.xy, vec4(1.0, 0.0, 0.0, 0.0));
+        return;
+    }
+
+    let uv = (vec2<f32>(id.xy) + 0.5) / vec2<f32>(size);
+    let center = view_position(uv, depth);
+    let normal = normalize(cross(dpdx_approx(id.xy, size, center), dpdy_approx(id.xy, size, center)));
+
+    // Project the world radius into screen space so the kernel shrinks with distance
+    let screen_radius = settings.radius * view.projection[1][1] / max(-center.z, 0.001) * 0.5;
+
+    var occlusion = 0.0;
+    for (var i = 0u; i < SAMPLE_COUNT; i++) {
+        let t = (f32(i) + 0.5) / f32(SAMPLE_COUNT);
+        let angle = f32(i) * GOLDEN_ANGLE;
+        let offset = vec2(cos(angle), sin(angle)) * sqrt(t) * screen_radius;
+        let sample_uv = clamp(uv + offset, vec2(0.0), vec2(1.0));
+        let sample_coord = vec2<u32>(sample_uv * vec2<f32>(size));
+        let sample_depth = textureLoad(downsampled_depth, sample_coord).r;
+        let sample_position = view_position(sample_uv, sample_depth);
+
+        let delta = sample_position - center;
+        let distance = length(delta);
+        let facing = max(dot(normal, delta / max(distance, 0.0001)) - settings.bias, 0.0);
+        let range_check = smoothstep(0.0, 1.0, settings.radius / max(distance, 0.0001));
+        occlusion += facing * range_check;
+    }
+
+    let ao = clamp(1.0 - occlusion / f32(SAMPLE_COUNT) * settings.intensity, 0.0, 1.0);
+    textureStore(ao_texture, id.xy, vec4(ao, 0.0, 0.0, 0.0));
+}
+
+fn dpdx_approx(coord: vec2<u32>, size: vec2<u32>, center: vec3<f32>) -> vec3<f32> {
+    let next = min(coord + vec2(1u, 0u), size - 1u);
+    let uv = (vec2<f32>(next) + 0.5) / vec2<f32>(size);
+    return view_position(uv, textureLoad(downsampled_depth, next).r) - center;
+}
+
+fn dpdy_approx(coord: vec2<u32>, size: vec2<u32>, center: vec3<f32>) -> vec3<f32> {
+    let next = min(coord + vec2(0u, 1u), size - 1u);
+    let uv = (vec2<f32>(next) + 0.5) / vec2<f32>(size);
+    return view_position(uv, textureLoad(downsampled_depth, next).r) - center;
+}
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(scene_texture, linear_sampler, in.uv);
+    // r32float is not filterable, so upsample with a plain load
+    let ao_size = textureDimensions(ao_sample_texture);
+    let ao_coord = min(vec2<u32>(in.uv * vec2<f32>(ao_size)), ao_size - 1u);
+    let ao = textureLoad(ao_sample_texture, ao_coord, 0).r;
+    return vec4(color.rgb * ao, color.a);
+}
@@ -0,0 +1,70 @@
+// Temporal anti-aliasing resolve
+//
+// Reprojects the previous frame using the motion vector prepass and blends it with the
+// current (jittered) frame. History is clamped to the 3x3 neighbourhood of the current
+// pixel to reject disoccluded samples, and the blend weight drops for fast moving pixels
+// (vehicle wheels, props) to limit ghosting.
+
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+struct TaaSettings {
+    history_blend: f32,        // Weight of the history sample (0.0 - 1.0)
+    velocity_rejection: f32,   // Screen-space velocity at which history is mostly rejected
+    reset: u32,                // 1 when history is invalid (first frame, camera cut)
+    _padding: u32,
+}
+
+@group(0) @binding(0) var scene_texture: texture_2d<f32>;
+@group(0) @binding(1) var history_texture: texture_2d<f32>;
+@group(0) @binding(2) var motion_vectors: texture_2d<f32>;
+@group(0) @binding(3) var linear_sampler: sampler;
+@group(0) @binding(4) var<uniform> settings: TaaSettings;
+
+struct Output {
+    @location(0) view_target: vec4<f32>,
+    @location(1) history: vec4<f32>,
+}
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> Output {
+    let size = vec2<f32>(textureDimensions(scene_texture));
+    let texel = 1.0 / size;
+    let current = textureSample(scene_texture, linear_sampler, in.uv);
+
+    var out: Output;
+    if (settings.reset == 1u) {
+        out.view_target = current;
+        out.history = current;
+        return out;
+    }
+
+    // Neighbourhood min/max for history clamping
+    var color_min = current.rgb;
+    var color_max = current.rgb;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let neighbour = textureSample(scene_texture, linear_sampler, in.uv + vec2(f32(x), f32(y)) * texel).rgb;
+            color_min = min(color_min, neighbour);
+            color_max = max(color_max, neighbour);
+        }
+    }
+
+    let velocity = textureSample(motion_vectors, linear_sampler, in.uv).rg;
+    let history_uv = in.uv - velocity;
+    let history = clamp(
+        textureSample(history_texture, linear_sampler, history_uv).rgb,
+        color_min,
+        color_max,
+    );
+
+    // Off-screen history and fast motion fall back to the current frame
+    let on_screen = all(history_uv >= vec2(0.0)) && all(history_uv <= vec2(1.0));
+    let speed = length(velocity * size);
+    let motion_weight = saturate(1.0 - speed / max(settings.velocity_rejection, 0.001));
+    let blend = select(0.0, settings.history_blend * motion_weight, on_screen);
+
+    let resolved = mix(current.rgb, history, blend);
+    out.view_target = vec4(resolved, current.a);
+    out.history = vec4(resolved, current.a);
+    return out;
+}
@@ -0,0 +1,549 @@
+use bevy::{
+    core_pipeline::{fullscreen_vertex_shader::fullscreen_shader_vertex_state, prepass::ViewPrepassTextures},
+    prelude::*,
+    render::{
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        render_graph::{Node, NodeRunError, RenderGraphContext},
+        render_resource::*,
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        texture::{CachedTexture, TextureCache},
+        view::{ViewTarget, ViewUniformOffset, ViewUniforms},
+        Render, RenderApp, RenderSet,
+    },
+};
+use bytemuck::{Pod, Zeroable};
+
+use super::{load_shader, PostProcessEffect, PostProcessEffectNodes, PostProcessSettings};
+use crate::game::GameSettings;
+use crate::rendering::{
+    compute_pipeline, render_pipeline, scene_depth_view, track_compute_pipeline, track_render_pipeline,
+    SceneDepthPlugin, SceneDepthUsers,
+};
+
+/// Workgroup size used by the SSAO compute shaders (8x8 threads)
+const WORKGROUP_SIZE: u32 = 8;
+
+/// GPU-side SSAO parameters, extracted from [`PostProcessSettings`] every frame
+#[derive(Resource, ShaderType, Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct SsaoUniform {
+    /// World-space sampling radius
+    pub radius: f32,
+    /// Depth bias to avoid self-occlusion
+    pub bias: f32,
+    /// Occlusion strength multiplier
+    pub intensity: f32,
+    /// Depth downsample factor (1, 2 or 4)
+    pub downsample: u32,
+}
+
+/// Whether SSAO should run this frame, extracted alongside [`SsaoUniform`]
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct SsaoEnabled(pub bool);
+
+impl ExtractResource for SsaoUniform {
+    type Source = PostProcessSettings;
+
+    fn extract_resource(settings: &Self::Source) -> Self {
+        Self {
+            radius: settings.ssao_radius.max(0.01),
+            bias: settings.ssao_bias.max(0.0),
+            intensity: settings.ssao_intensity.max(0.0),
+            downsample: sanitize_downsample(settings.ssao_downsample),
+        }
+    }
+}
+
+impl ExtractResource for SsaoEnabled {
+    type Source = PostProcessSettings;
+
+    fn extract_resource(settings: &Self::Source) -> Self {
+        Self(settings.ssao_enabled && settings.ssao_intensity > 0.0)
+    }
+}
+
+/// Clamps the downsample factor to the supported power-of-two values
+pub fn sanitize_downsample(factor: u32) -> u32 {
+    match factor {
+        0 | 1 => 1,
+        2 | 3 => 2,
+        _ => 4,
+    }
+}
+
+/// Size of the downsampled AO targets for a given view size
+pub fn downsampled_size(width: u32, height: u32, downsample: u32) -> (u32, u32) {
+    let factor = sanitize_downsample(downsample);
+    ((width / factor).max(1), (height / factor).max(1))
+}
+
+/// Pipelines and layouts for the SSAO passes
+#[derive(Resource)]
+pub struct SsaoPipeline {
+    /// Layout for the depth downsample and occlusion compute passes
+    compute_layout: BindGroupLayout,
+    /// Layout for the composite pass that multiplies AO into the scene
+    apply_layout: BindGroupLayout,
+    /// Sampler used for reading the scene texture
+    sampler: Sampler,
+    /// Buffer holding the current [`SsaoUniform`]
+    settings_buffer: Buffer,
+    downsample_pipeline_id: CachedComputePipelineId,
+    ssao_pipeline_id: CachedComputePipelineId,
+    apply_pipeline_id: CachedRenderPipelineId,
+}
+
+impl FromWorld for SsaoPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let shader = load_shader(world, "ssao.wgsl");
+
+        let render_device = world.resource::<RenderDevice>();
+
+        // Create bind group layout for the compute passes:
+        // - View uniform for projection data
+        // - SSAO settings
+        // - Full resolution depth, from the single-sampled depth prepass
+        // - Downsampled depth and AO storage targets
+        let storage_entry = |binding: u32| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::StorageTexture {
+                access: StorageTextureAccess::ReadWrite,
+                format: TextureFormat::R32Float,
+                view_dimension: TextureViewDimension::D2,
+            },
+            count: None,
+        };
+        let compute_layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("ssao_compute_bind_group_layout"),
+            entries: &[
+                // View uniform
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // SSAO settings
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: Some(SsaoUniform::min_size()),
+                    },
+                    count: None,
+                },
+                // Depth texture
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Depth,
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                storage_entry(3),
+                storage_entry(4),
+            ],
+        });
+
+        let apply_layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("ssao_apply_bind_group_layout"),
+            entries: &[
+                // Scene texture
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // AO texture
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // Scene sampler
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let sampler = render_device.create_sampler(&SamplerDescriptor {
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..default()
+        });
+
+        let settings_buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("ssao_settings_buffer"),
+            size: SsaoUniform::min_size().get(),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let compute_pipeline = |label: &'static str, entry_point: &'static str| {
+            pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+                label: Some(label.into()),
+                layout: vec![compute_layout.clone()],
+                push_constant_ranges: vec![],
+                shader: shader.clone(),
+                shader_defs: vec![],
+                entry_point: entry_point.into(),
+            })
+        };
+        let downsample_pipeline_id = compute_pipeline("ssao_downsample_depth_pipeline", "downsample_depth");
+        let ssao_pipeline_id = compute_pipeline("ssao_pipeline", "ssao");
+
+        let apply_pipeline_id = pipeline_cache.queue_render_pipeline(RenderPipelineDescriptor {
+            label: Some("ssao_apply_pipeline".into()),
+            layout: vec![apply_layout.clone()],
+            push_constant_ranges: vec![],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader,
+                shader_defs: vec![],
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format: ViewTarget::TEXTURE_FORMAT_HDR,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,  // No depth testing needed for post-process
+            multisample: MultisampleState::default(),
+        });
+
+        track_compute_pipeline(world, downsample_pipeline_id);
+        track_compute_pipeline(world, ssao_pipeline_id);
+        track_render_pipeline(world, apply_pipeline_id);
+
+        Self {
+            compute_layout,
+            apply_layout,
+            sampler,
+            settings_buffer,
+            downsample_pipeline_id,
+            ssao_pipeline_id,
+            apply_pipeline_id,
+        }
+    }
+}
+
+/// Per-view downsampled depth and AO targets
+#[derive(Component)]
+pub struct SsaoTextures {
+    pub downsampled_depth: CachedTexture,
+    pub ambient_occlusion: CachedTexture,
+}
+
+/// Per-view bind group for the SSAO compute passes
+#[derive(Component)]
+pub struct SsaoBindGroup {
+    value: BindGroup,
+}
+
+/// Allocates the per-view SSAO targets at the downsampled resolution
+fn prepare_ssao_textures(
+    mut commands: Commands,
+    mut texture_cache: ResMut<TextureCache>,
+    render_device: Res<RenderDevice>,
+    enabled: Res<SsaoEnabled>,
+    uniform: Res<SsaoUniform>,
+    views: Query<(Entity, &ViewTarget)>,
+) {
+    if !enabled.0 {
+        return;
+    }
+
+    for (entity, view_target) in views.iter() {
+        let size = view_target.main_texture().size();
+        let (width, height) = downsampled_size(size.width, size.height, uniform.downsample);
+        let mut create = |label: &'static str| {
+            texture_cache.get(
+                &render_device,
+                TextureDescriptor {
+                    label: Some(label),
+                    size: Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format: TextureFormat::R32Float,
+                    usage: TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                },
+            )
+        };
+
+        commands.entity(entity).insert(SsaoTextures {
+            downsampled_depth: create("ssao_downsampled_depth"),
+            ambient_occlusion: create("ssao_ambient_occlusion"),
+        });
+    }
+}
+
+/// Uploads the SSAO settings and creates the per-view compute bind groups
+fn prepare_ssao_bind_groups(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    pipeline: Res<SsaoPipeline>,
+    view_uniforms: Res<ViewUniforms>,
+    enabled: Res<SsaoEnabled>,
+    uniform: Res<SsaoUniform>,
+    views: Query<(Entity, &ViewPrepassTextures, &SsaoTextures)>,
+) {
+    if !enabled.0 {
+        return;
+    }
+    let Some(view_binding) = view_uniforms.uniforms.binding() else {
+        return;
+    };
+
+    // Update the settings buffer with current values
+    render_queue.write_buffer(&pipeline.settings_buffer, 0, bytemuck::bytes_of(uniform.as_ref()));
+
+    for (entity, prepass, textures) in views.iter() {
+        // Views without a single-sampled prepass yet skip SSAO until SceneDepthPlugin sets them up
+        let Some(depth) = scene_depth_view(prepass) else {
+            continue;
+        };
+        let bind_group = render_device.create_bind_group(
+            "ssao_compute_bind_group",
+            &pipeline.compute_layout,
+            &BindGroupEntries::sequential((
+                view_binding.clone(),
+                pipeline.settings_buffer.as_entire_binding(),
+                depth,
+                &textures.downsampled_depth.default_view,
+                &textures.ambient_occlusion.default_view,
+            )),
+        );
+
+        commands.entity(entity).insert(SsaoBindGroup { value: bind_group });
+    }
+}
+
+/// Node in the render graph that computes SSAO and composites it into the scene
+/// Skips all work when SSAO is disabled in [`PostProcessSettings`]
+pub struct SsaoNode {
+    query: QueryState<(
+        &'static ViewTarget,
+        &'static ViewUniformOffset,
+        &'static SsaoTextures,
+        &'static SsaoBindGroup,
+    )>,
+}
+
+impl SsaoNode {
+    /// Name of the node in the render graph
+    pub const NAME: &'static str = "ssao";
+}
+
+impl FromWorld for SsaoNode {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for SsaoNode {
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        if !world.resource::<SsaoEnabled>().0 {
+            return Ok(());
+        }
+
+        let Ok((view_target, view_uniform, textures, bind_group)) =
+            self.query.get_manual(world, graph.view_entity())
+        else {
+            return Ok(());
+        };
+
+        let pipeline = world.resource::<SsaoPipeline>();
+        let (Some(downsample), Some(ssao), Some(apply)) = (
+            compute_pipeline(world, pipeline.downsample_pipeline_id),
+            compute_pipeline(world, pipeline.ssao_pipeline_id),
+            render_pipeline(world, pipeline.apply_pipeline_id),
+        ) else {
+            // Pipelines are still compiling
+            return Ok(());
+        };
+
+        let size = textures.ambient_occlusion.texture.size();
+        let workgroups = (
+            size.width.div_ceil(WORKGROUP_SIZE),
+            size.height.div_ceil(WORKGROUP_SIZE),
+        );
+
+        {
+            let mut compute_pass = render_context
+                .command_encoder()
+                .begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("ssao_compute_pass"),
+                });
+            compute_pass.set_bind_group(0, &bind_group.value, &[view_uniform.offset]);
+
+            compute_pass.set_pipeline(downsample);
+            compute_pass.dispatch_workgroups(workgroups.0, workgroups.1, 1);
+
+            compute_pass.set_pipeline(ssao);
+            compute_pass.dispatch_workgroups(workgroups.0, workgroups.1, 1);
+        }
+
+        // Composite the occlusion term into the scene
+        let post_process = view_target.post_process_write();
+        let apply_bind_group = render_context.render_device().create_bind_group(
+            "ssao_apply_bind_group",
+            &pipeline.apply_layout,
+            &BindGroupEntries::sequential((
+                post_process.source,
+                &textures.ambient_occlusion.default_view,
+                &pipeline.sampler,
+            )),
+        );
+
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("ssao_apply_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: post_process.destination,
+                resolve_target: None,
+                ops: Operations::default(),
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_render_pipeline(apply);
+        render_pass.set_bind_group(0, &apply_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
+
+/// Keeps [`PostProcessSettings::ssao_enabled`] in sync with the graphics settings toggle
+fn sync_ssao_toggle(
+    game_settings: Res<GameSettings>,
+    mut settings: ResMut<PostProcessSettings>,
+) {
+    if game_settings.is_changed() && settings.ssao_enabled != game_settings.graphics.ambient_occlusion {
+        settings.ssao_enabled = game_settings.graphics.ambient_occlusion;
+    }
+}
+
+/// Registers SSAO as a scene depth user while it's enabled
+fn request_ssao_depth(settings: Res<PostProcessSettings>, mut users: ResMut<SceneDepthUsers>) {
+    if settings.is_changed() {
+        users.set("ssao", SsaoEnabled::extract_resource(&settings).0);
+    }
+}
+
+/// Plugin that adds the SSAO pass between the main pass and the post-process pass
+pub struct SsaoPlugin;
+
+impl Plugin for SsaoPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<SceneDepthPlugin>() {
+            app.add_plugins(SceneDepthPlugin);
+        }
+        app.add_plugins((
+                ExtractResourcePlugin::<SsaoUniform>::default(),
+                ExtractResourcePlugin::<SsaoEnabled>::default(),
+            ))
+            .add_systems(Update, (
+                sync_ssao_toggle.run_if(resource_exists::<GameSettings>()),
+                request_ssao_depth.after(sync_ssao_toggle),
+            ));
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.add_systems(Render, (
+            prepare_ssao_textures.in_set(RenderSet::PrepareResources),
+            prepare_ssao_bind_groups.in_set(RenderSet::PrepareBindGroups),
+        ));
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<SsaoPipeline>();
+
+        // Ordered by the post-process chain, which runs it before tone mapping by default
+        let node = SsaoNode::from_world(&mut render_app.world);
+        render_app
+            .world
+            .resource_mut::<PostProcessEffectNodes>()
+            .insert(PostProcessEffect::Ssao, node);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_downsample_factor_is_sanitized() {
+        assert_eq!(sanitize_downsample(0), 1);
+        assert_eq!(sanitize_downsample(2), 2);
+        assert_eq!(sanitize_downsample(3), 2);
+        assert_eq!(sanitize_downsample(16), 4);
+    }
+
+    #[test]
+    fn test_downsampled_size() {
+        assert_eq!(downsampled_size(1920, 1080, 2), (960, 540));
+        assert_eq!(downsampled_size(1, 1, 4), (1, 1));
+    }
+
+    #[test]
+    fn test_extract_from_settings() {
+        let mut settings = PostProcessSettings::default();
+        settings.ssao_radius = 0.75;
+        settings.ssao_downsample = 3;
+
+        let uniform = SsaoUniform::extract_resource(&settings);
+        assert_eq!(uniform.radius, 0.75);
+        assert_eq!(uniform.downsample, 2);
+
+        settings.ssao_enabled = false;
+        assert!(!SsaoEnabled::extract_resource(&settings).0);
+    }
+}
@@ -0,0 +1,596 @@
+use bevy::{
+    core::FrameCount,
+    core_pipeline::{
+        fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+        fxaa::Fxaa,
+        prepass::{DepthPrepass, MotionVectorPrepass, ViewPrepassTextures},
+    },
+    prelude::*,
+    render::{
+        camera::TemporalJitter,
+        extract_component::{ExtractComponent, ExtractComponentPlugin},
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        render_graph::{Node, NodeRunError, RenderGraphContext},
+        render_resource::*,
+        renderer::{RenderAdapter, RenderContext, RenderDevice, RenderQueue},
+        texture::CachedTexture,
+        view::{ExtractedView, ViewTarget},
+        Render, RenderApp, RenderSet,
+    },
+    utils::HashMap,
+};
+use bytemuck::{Pod, Zeroable};
+
+use super::{load_shader, PostProcessEffect, PostProcessEffectNodes};
+use crate::rendering::{render_pipeline, track_render_pipeline};
+
+/// Anti-aliasing technique applied to 3D cameras
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AntiAliasingMode {
+    /// No post-process anti-aliasing
+    Off,
+    /// Fast approximate anti-aliasing, used as the fallback without motion vectors
+    Fxaa,
+    /// Temporal anti-aliasing using the motion vector prepass
+    #[default]
+    Taa,
+}
+
+/// Sub-pixel jitter pattern applied to the projection while TAA is active
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JitterSequence {
+    /// Halton (2, 3) low discrepancy sequence with the given period
+    Halton23 { length: u32 },
+    /// Fixed 4-sample rotated grid, cheaper to converge but more prone to crawling
+    RotatedGrid4,
+}
+
+impl Default for JitterSequence {
+    fn default() -> Self {
+        JitterSequence::Halton23 { length: 8 }
+    }
+}
+
+impl JitterSequence {
+    /// Jitter offset for a frame, in pixels within [-0.5, 0.5]
+    pub fn offset(&self, frame: u32) -> Vec2 {
+        match *self {
+            JitterSequence::Halton23 { length } => {
+                // Halton index 0 is (0, 0), so start from 1 to avoid a repeated centre sample
+                let index = frame % length.max(1) + 1;
+                Vec2::new(halton(index, 2), halton(index, 3)) - Vec2::splat(0.5)
+            }
+            JitterSequence::RotatedGrid4 => {
+                const GRID: [Vec2; 4] = [
+                    Vec2::new(-0.125, -0.375),
+                    Vec2::new(0.375, -0.125),
+                    Vec2::new(0.125, 0.375),
+                    Vec2::new(-0.375, 0.125),
+                ];
+                GRID[(frame % 4) as usize]
+            }
+        }
+    }
+}
+
+/// Radical inverse of `index` in the given base
+pub fn halton(mut index: u32, base: u32) -> f32 {
+    let mut fraction = 1.0;
+    let mut result = 0.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+/// Settings for temporal anti-aliasing
+#[derive(Resource, Clone, Debug)]
+pub struct TaaSettings {
+    /// Requested anti-aliasing mode, TAA falls back to FXAA when unsupported
+    pub mode: AntiAliasingMode,
+    /// Jitter pattern applied to the camera projection
+    pub jitter_sequence: JitterSequence,
+    /// Scale applied to the jitter offsets (1.0 is one full pixel footprint)
+    pub jitter_scale: f32,
+    /// Weight of the reprojected history (0.0 - 1.0). Higher is smoother but ghosts more
+    pub history_blend: f32,
+    /// Screen-space speed in pixels per frame at which history is rejected
+    pub velocity_rejection: f32,
+}
+
+impl Default for TaaSettings {
+    fn default() -> Self {
+        Self {
+            mode: AntiAliasingMode::default(),
+            jitter_sequence: JitterSequence::default(),
+            jitter_scale: 1.0,
+            history_blend: 0.9,
+            velocity_rejection: 40.0,
+        }
+    }
+}
+
+/// Whether the current adapter can render the motion vector prepass
+/// Detected once the renderer is initialized
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MotionVectorSupport(pub bool);
+
+impl Default for MotionVectorSupport {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+impl TaaSettings {
+    /// Anti-aliasing mode that will actually be used on this hardware
+    pub fn effective_mode(&self, support: MotionVectorSupport) -> AntiAliasingMode {
+        match self.mode {
+            AntiAliasingMode::Taa if !support.0 => AntiAliasingMode::Fxaa,
+            mode => mode,
+        }
+    }
+}
+
+/// Marker added to cameras that are currently resolved with TAA
+#[derive(Component, Clone, Copy, Default, ExtractComponent)]
+pub struct TaaCamera;
+
+/// GPU-side TAA parameters
+#[derive(Resource, ShaderType, Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+pub struct TaaUniform {
+    pub history_blend: f32,
+    pub velocity_rejection: f32,
+    pub reset: u32,
+    _padding: u32,
+}
+
+impl ExtractResource for TaaUniform {
+    type Source = TaaSettings;
+
+    fn extract_resource(settings: &Self::Source) -> Self {
+        Self {
+            history_blend: settings.history_blend.clamp(0.0, 1.0),
+            velocity_rejection: settings.velocity_rejection.max(0.0),
+            reset: 0,
+            _padding: 0,
+        }
+    }
+}
+
+/// Adds or removes the prepasses, jitter and FXAA on cameras to match the effective mode
+fn configure_camera_antialiasing(
+    mut commands: Commands,
+    settings: Res<TaaSettings>,
+    support: Res<MotionVectorSupport>,
+    cameras: Query<Entity, With<Camera3d>>,
+    added: Query<(), Added<Camera3d>>,
+) {
+    if !settings.is_changed() && !support.is_changed() && added.is_empty() {
+        return;
+    }
+
+    let mode = settings.effective_mode(*support);
+    for entity in cameras.iter() {
+        let mut camera = commands.entity(entity);
+        match mode {
+            AntiAliasingMode::Taa => {
+                // Motion vectors come from the prepass for every moving mesh, including
+                // the vehicle body, wheels and props, via their previous global transform
+                camera
+                    .insert((DepthPrepass, MotionVectorPrepass, TemporalJitter::default(), TaaCamera))
+                    .remove::<Fxaa>();
+            }
+            // Features that need depth without TAA, like water, add the depth prepass back themselves
+            AntiAliasingMode::Fxaa => {
+                camera
+                    .insert(Fxaa::default())
+                    .remove::<(DepthPrepass, MotionVectorPrepass, TemporalJitter, TaaCamera)>();
+            }
+            AntiAliasingMode::Off => {
+                camera.remove::<(Fxaa, DepthPrepass, MotionVectorPrepass, TemporalJitter, TaaCamera)>();
+            }
+        }
+    }
+}
+
+/// Advances the sub-pixel jitter on TAA cameras
+fn update_temporal_jitter(
+    settings: Res<TaaSettings>,
+    frame_count: Res<FrameCount>,
+    mut cameras: Query<&mut TemporalJitter, With<TaaCamera>>,
+) {
+    let offset = settings.jitter_sequence.offset(frame_count.0) * settings.jitter_scale;
+    for mut jitter in cameras.iter_mut() {
+        jitter.offset = offset;
+    }
+}
+
+/// Render pipeline for the TAA resolve pass
+#[derive(Resource)]
+pub struct TaaPipeline {
+    bind_group_layout: BindGroupLayout,
+    sampler: Sampler,
+    settings_buffer: Buffer,
+    pipeline_id: CachedRenderPipelineId,
+}
+
+impl FromWorld for TaaPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let shader = load_shader(world, "taa.wgsl");
+
+        let render_device = world.resource::<RenderDevice>();
+        let texture_entry = |binding: u32, filterable: bool| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                sample_type: TextureSampleType::Float { filterable },
+                view_dimension: TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+
+        // Scene, history and motion vectors, plus a sampler and the settings uniform
+        let bind_group_layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("taa_bind_group_layout"),
+            entries: &[
+                texture_entry(0, true),
+                texture_entry(1, true),
+                texture_entry(2, true),
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: Some(TaaUniform::min_size()),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let sampler = render_device.create_sampler(&SamplerDescriptor {
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..default()
+        });
+
+        let settings_buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("taa_settings_buffer"),
+            size: TaaUniform::min_size().get(),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let target = Some(ColorTargetState {
+            format: ViewTarget::TEXTURE_FORMAT_HDR,
+            blend: None,
+            write_mask: ColorWrites::ALL,
+        });
+        let pipeline_id = world.resource::<PipelineCache>().queue_render_pipeline(RenderPipelineDescriptor {
+            label: Some("taa_pipeline".into()),
+            layout: vec![bind_group_layout.clone()],
+            push_constant_ranges: vec![],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader,
+                shader_defs: vec![],
+                entry_point: "fragment".into(),
+                // View target and history are written in the same pass
+                targets: vec![target.clone(), target],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,  // No depth testing needed for post-process
+            multisample: MultisampleState::default(),
+        });
+
+        track_render_pipeline(world, pipeline_id);
+
+        Self {
+            bind_group_layout,
+            sampler,
+            settings_buffer,
+            pipeline_id,
+        }
+    }
+}
+
+/// Ping-pong history targets for a TAA view
+#[derive(Component)]
+pub struct TaaHistoryTextures {
+    /// History written this frame
+    pub write: CachedTexture,
+    /// History from the previous frame
+    pub read: CachedTexture,
+    /// Whether the read texture holds valid history
+    pub valid: bool,
+}
+
+/// History targets of one TAA camera, kept across frames
+pub struct TaaHistory {
+    textures: [CachedTexture; 2],
+    size: Extent3d,
+    /// Frame the history was last written in
+    last_frame: Option<u32>,
+}
+
+impl TaaHistory {
+    fn new(render_device: &RenderDevice, size: Extent3d) -> Self {
+        let texture = |label: &'static str| {
+            let texture = render_device.create_texture(&TextureDescriptor {
+                label: Some(label),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: ViewTarget::TEXTURE_FORMAT_HDR,
+                usage: TextureUsages::TEXTURE_BINDING | TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            });
+            let default_view = texture.create_view(&TextureViewDescriptor::default());
+            CachedTexture { texture, default_view }
+        };
+        Self {
+            textures: [texture("taa_history_a"), texture("taa_history_b")],
+            size,
+            last_frame: None,
+        }
+    }
+}
+
+/// TAA history of every camera, by camera entity. Render world view entities are cleared every
+/// frame, so history kept on them would be gone by the next one. Views share the entity id of the
+/// camera they were extracted from.
+#[derive(Resource, Default)]
+pub struct TaaHistories(HashMap<Entity, TaaHistory>);
+
+/// History written in `last_frame` can be reprojected into `frame` only if no frame was skipped
+pub fn history_is_valid(last_frame: Option<u32>, frame: u32) -> bool {
+    last_frame.is_some_and(|last| last.wrapping_add(1) == frame)
+}
+
+/// Hands each view its camera's history textures, swapping read and write every frame
+fn prepare_taa_history_textures(
+    mut commands: Commands,
+    mut histories: ResMut<TaaHistories>,
+    render_device: Res<RenderDevice>,
+    frame_count: Res<FrameCount>,
+    views: Query<(Entity, &ExtractedView), With<TaaCamera>>,
+) {
+    // Cameras that were removed or stopped using TAA free their history
+    histories.0.retain(|entity, _| views.contains(*entity));
+
+    for (entity, view) in views.iter() {
+        let size = Extent3d {
+            width: view.viewport.z,
+            height: view.viewport.w,
+            depth_or_array_layers: 1,
+        };
+        let history = histories
+            .0
+            .entry(entity)
+            .or_insert_with(|| TaaHistory::new(&render_device, size));
+        // A resized view starts over with fresh history
+        if history.size != size {
+            *history = TaaHistory::new(&render_device, size);
+        }
+
+        let valid = history_is_valid(history.last_frame, frame_count.0);
+        history.last_frame = Some(frame_count.0);
+        let [a, b] = history.textures.clone();
+        let (write, read) = if frame_count.0 % 2 == 0 { (a, b) } else { (b, a) };
+        commands.entity(entity).insert(TaaHistoryTextures { write, read, valid });
+    }
+}
+
+/// Uploads the TAA settings for this frame
+fn prepare_taa_settings(
+    render_queue: Res<RenderQueue>,
+    pipeline: Res<TaaPipeline>,
+    uniform: Res<TaaUniform>,
+) {
+    render_queue.write_buffer(&pipeline.settings_buffer, 0, bytemuck::bytes_of(uniform.as_ref()));
+}
+
+/// Node in the render graph that resolves TAA before the post-process pass
+pub struct TaaNode {
+    query: QueryState<(
+        &'static ViewTarget,
+        &'static TaaHistoryTextures,
+        &'static ViewPrepassTextures,
+    ), With<TaaCamera>>,
+}
+
+impl TaaNode {
+    /// Name of the node in the render graph
+    pub const NAME: &'static str = "taa";
+}
+
+impl FromWorld for TaaNode {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for TaaNode {
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let Ok((view_target, history, prepass)) = self.query.get_manual(world, graph.view_entity()) else {
+            // Not a TAA camera (FXAA fallback or anti-aliasing disabled)
+            return Ok(());
+        };
+        let Some(motion_vectors) = &prepass.motion_vectors else {
+            return Ok(());
+        };
+
+        let pipeline = world.resource::<TaaPipeline>();
+        let Some(render_pipeline) = render_pipeline(world, pipeline.pipeline_id) else {
+            return Ok(());
+        };
+
+        // Invalid history is handled by a reset pass that just copies the current frame
+        if !history.valid {
+            let mut uniform = *world.resource::<TaaUniform>();
+            uniform.reset = 1;
+            world.resource::<RenderQueue>().write_buffer(
+                &pipeline.settings_buffer,
+                0,
+                bytemuck::bytes_of(&uniform),
+            );
+        }
+
+        let post_process = view_target.post_process_write();
+        let bind_group = render_context.render_device().create_bind_group(
+            "taa_bind_group",
+            &pipeline.bind_group_layout,
+            &BindGroupEntries::sequential((
+                post_process.source,
+                &history.read.default_view,
+                &motion_vectors.default_view,
+                &pipeline.sampler,
+                pipeline.settings_buffer.as_entire_binding(),
+            )),
+        );
+
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("taa_pass"),
+            color_attachments: &[
+                Some(RenderPassColorAttachment {
+                    view: post_process.destination,
+                    resolve_target: None,
+                    ops: Operations::default(),
+                }),
+                Some(RenderPassColorAttachment {
+                    view: &history.write.default_view,
+                    resolve_target: None,
+                    ops: Operations::default(),
+                }),
+            ],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_render_pipeline(render_pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
+
+/// Plugin that adds the TAA resolve pass, camera jitter and the FXAA fallback
+pub struct TaaPlugin;
+
+impl Plugin for TaaPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TaaSettings>()
+            .init_resource::<MotionVectorSupport>()
+            .add_plugins((
+                ExtractResourcePlugin::<TaaUniform>::default(),
+                ExtractComponentPlugin::<TaaCamera>::default(),
+            ))
+            .add_systems(Update, (
+                configure_camera_antialiasing,
+                update_temporal_jitter,
+            ).chain());
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<TaaHistories>().add_systems(Render, (
+            prepare_taa_history_textures.in_set(RenderSet::PrepareResources),
+            prepare_taa_settings.in_set(RenderSet::PrepareResources),
+        ));
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        // The motion vector prepass needs a second color attachment and compute-capable
+        // hardware, which downlevel backends such as WebGL2 do not provide
+        let downlevel = render_app.world.resource::<RenderAdapter>().get_downlevel_capabilities();
+        let supported = downlevel.flags.contains(DownlevelFlags::COMPUTE_SHADERS)
+            && render_app.world.resource::<RenderDevice>().limits().max_color_attachments >= 2;
+        if !supported {
+            warn!("Motion vectors are unsupported on this adapter, falling back to FXAA");
+        }
+
+        render_app.init_resource::<TaaPipeline>();
+        let node = TaaNode::from_world(&mut render_app.world);
+        render_app
+            .world
+            .resource_mut::<PostProcessEffectNodes>()
+            .insert(PostProcessEffect::Taa, node);
+
+        app.insert_resource(MotionVectorSupport(supported));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_halton_sequence() {
+        assert_eq!(halton(1, 2), 0.5);
+        assert_eq!(halton(2, 2), 0.25);
+        assert!((halton(1, 3) - 1.0 / 3.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn test_jitter_offsets_stay_within_pixel() {
+        for sequence in [JitterSequence::default(), JitterSequence::RotatedGrid4] {
+            for frame in 0..32 {
+                let offset = sequence.offset(frame);
+                assert!(offset.x.abs() <= 0.5 && offset.y.abs() <= 0.5);
+            }
+        }
+    }
+
+    #[test]
+    fn test_jitter_sequence_repeats() {
+        let sequence = JitterSequence::Halton23 { length: 8 };
+        assert_eq!(sequence.offset(3), sequence.offset(11));
+        assert_ne!(sequence.offset(3), sequence.offset(4));
+    }
+
+    #[test]
+    fn test_history_needs_the_previous_frame() {
+        assert!(!history_is_valid(None, 10));
+        assert!(history_is_valid(Some(9), 10));
+        // A camera that skipped frames, like one toggled off and on, doesn't reproject stale history
+        assert!(!history_is_valid(Some(7), 10));
+        assert!(history_is_valid(Some(u32::MAX), 0));
+    }
+
+    #[test]
+    fn test_fxaa_fallback_without_motion_vectors() {
+        let settings = TaaSettings::default();
+        assert_eq!(settings.effective_mode(MotionVectorSupport(true)), AntiAliasingMode::Taa);
+        assert_eq!(settings.effective_mode(MotionVectorSupport(false)), AntiAliasingMode::Fxaa);
+
+        let off = TaaSettings { mode: AntiAliasingMode::Off, ..Default::default() };
+        assert_eq!(off.effective_mode(MotionVectorSupport(false)), AntiAliasingMode::Off);
+    }
+}
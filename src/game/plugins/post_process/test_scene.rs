@@ -6,7 +6,6 @@ use bevy::{
         render_resource::PrimitiveTopology,
         settings::WgpuSettings,
         render_resource::WgpuFeatures,
-        renderer::RenderDevice,
     },
     input::mouse::MouseMotion,
     text::{Text2dBundle, TextAlignment, TextStyle},
@@ -25,6 +24,7 @@ use std::{
 use serde::{Deserialize, Serialize};
 
 use super::settings::PostProcessSettings;
+use super::GpuTimings;
 
 /// Plugin that sets up a test scene for demonstrating post-processing effects
 pub struct PostProcessTestPlugin;
@@ -508,7 +508,7 @@ fn setup_test_scene(
     spawn_test_objects(&mut commands, &mut meshes, &mut materials);
 }
 
-fn create_test_patterns(
+pub(super) fn create_test_patterns(
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
@@ -697,7 +697,7 @@ fn create_test_patterns(
 }
 
 /// Creates additional test patterns for HDR and bloom testing
-fn create_hdr_test_patterns(
+pub(super) fn create_hdr_test_patterns(
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
@@ -842,7 +842,7 @@ fn spawn_test_objects(
     
     // Add new test patterns
     create_resolution_test_pattern(commands, meshes, materials);
-    create_noise_test_pattern(commands, meshes, materials);
+    create_noise_test_pattern(commands, meshes, materials, 42);
     create_depth_test_pattern(commands, meshes, materials);
     create_motion_blur_test_pattern(commands, meshes, materials);
     create_specular_test_pattern(commands, meshes, materials);
@@ -1725,7 +1725,7 @@ fn lerp(a: f32, b: f32, t: f32) -> f32 {
 
 fn update_performance_metrics(
     time: Res<Time>,
-    render_device: Res<RenderDevice>,
+    gpu_timings: Option<Res<GpuTimings>>,
     mut metrics: ResMut<PerformanceMetrics>,
     diagnostics: Res<Diagnostics>,
 ) {
@@ -1733,9 +1733,9 @@ fn update_performance_metrics(
     if time.elapsed_seconds() - metrics.last_update >= 0.5 {
         metrics.last_update = time.elapsed_seconds();
 
-        // Get GPU time (if available)
-        if let Some(gpu_time) = render_device.features().contains(WgpuFeatures::TIMESTAMP_QUERY) {
-            metrics.gpu_time = time.delta_seconds() * 1000.0; // Approximate for now
+        // GPU time from the timestamp queries, where the device supports them
+        if let Some(total) = gpu_timings.and_then(|timings| timings.get().total()) {
+            metrics.gpu_time = total;
         }
 
         // Get memory usage from system information diagnostics
@@ -1868,11 +1868,12 @@ fn create_resolution_test_pattern(
     ));
 }
 
-/// Creates a noise pattern for testing temporal effects
-fn create_noise_test_pattern(
+/// Creates a noise pattern for testing temporal effects, the same for the same seed
+pub(super) fn create_noise_test_pattern(
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
+    seed: u64,
 ) {
     let mut noise_test = Mesh::new(PrimitiveTopology::TriangleList);
     let size = 4.0;
@@ -1885,7 +1886,7 @@ fn create_noise_test_pattern(
     let mut indices = Vec::new();
 
     use rand::{Rng, SeedableRng};
-    let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+    let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
 
     for z in 0..=segments {
         for x in 0..=segments {
@@ -1939,7 +1940,7 @@ fn create_noise_test_pattern(
 }
 
 /// Creates a depth complexity test with overlapping transparent objects
-fn create_depth_test_pattern(
+pub(super) fn create_depth_test_pattern(
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
@@ -2001,7 +2002,7 @@ fn create_motion_blur_test_pattern(
 }
 
 /// Creates a specular highlight test pattern with different roughness values
-fn create_specular_test_pattern(
+pub(super) fn create_specular_test_pattern(
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
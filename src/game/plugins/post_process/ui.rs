@@ -1,6 +1,8 @@
 use bevy::prelude::*;
 use bevy::diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin};
 
+use super::{GpuPass, GpuTimings};
+
 /// Component that marks an entity as part of the performance display UI
 #[derive(Component)]
 pub struct PerformanceDisplay;
@@ -21,6 +23,7 @@ fn update_performance_display(
     diagnostics: Res<Diagnostics>,
     query: Query<Entity, With<PerformanceDisplay>>,
     asset_server: Res<AssetServer>,
+    gpu_timings: Option<Res<GpuTimings>>,
 ) {
     // Remove existing display
     for entity in query.iter() {
@@ -99,49 +102,43 @@ fn update_performance_display(
                 ),
             ]));
 
-            // Effect Overheads
-            parent.spawn(TextBundle::from_sections([
-                TextSection::new(
-                    "\nPost-Process Effects:\n",
-                    TextStyle {
-                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                        font_size: 16.0,
-                        color: Color::WHITE,
-                    },
-                ),
-                TextSection::new(
-                    "• Bloom: 0.5ms\n",
-                    TextStyle {
-                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                        font_size: 14.0,
-                        color: Color::CYAN,
-                    },
-                ),
-                TextSection::new(
-                    "• Chromatic Aberration: 0.2ms\n",
-                    TextStyle {
-                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                        font_size: 14.0,
-                        color: Color::CYAN,
-                    },
-                ),
-                TextSection::new(
-                    "• Vignette: 0.1ms\n",
-                    TextStyle {
-                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                        font_size: 14.0,
-                        color: Color::CYAN,
-                    },
-                ),
-                TextSection::new(
-                    "Total Overhead: 0.8ms",
-                    TextStyle {
-                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                        font_size: 14.0,
-                        color: Color::ORANGE,
-                    },
-                ),
-            ]));
+            // GPU time per pass, from timestamp queries where the device has them
+            let gpu = gpu_timings.map(|timings| timings.get()).unwrap_or_default();
+            let mut sections = vec![TextSection::new(
+                "\nGPU Time:\n",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                },
+            )];
+            if gpu.supported {
+                for pass in GpuPass::ALL {
+                    let time = gpu.pass(pass).map_or_else(|| "-".to_string(), |ms| format!("{ms:.2}ms"));
+                    sections.push(TextSection::new(
+                        format!("• {}: {}\n", pass.label(), time),
+                        TextStyle {
+                            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                            font_size: 14.0,
+                            color: Color::CYAN,
+                        },
+                    ));
+                }
+            }
+            let total = match (gpu.supported, gpu.total()) {
+                (false, _) => "Unavailable on this GPU".to_string(),
+                (true, Some(total)) => format!("Total: {total:.2}ms"),
+                (true, None) => "Total: -".to_string(),
+            };
+            sections.push(TextSection::new(
+                total,
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 14.0,
+                    color: Color::ORANGE,
+                },
+            ));
+            parent.spawn(TextBundle::from_sections(sections));
         });
 }
 
@@ -0,0 +1,192 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::game::vehicle::{Bumper, LiftKit, MaterialOverride, Part, TireType, VehicleConfig};
+
+/// Body paint a vehicle can be sprayed in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Paint {
+    #[default]
+    Red,
+    Sand,
+    Olive,
+    Blue,
+    Orange,
+    Black,
+}
+
+impl Paint {
+    pub const ALL: [Paint; 6] = [Paint::Red, Paint::Sand, Paint::Olive, Paint::Blue, Paint::Orange, Paint::Black];
+
+    pub fn color(self) -> Color {
+        match self {
+            Self::Red => Color::rgb(0.55, 0.1, 0.1),
+            Self::Sand => Color::rgb(0.76, 0.66, 0.48),
+            Self::Olive => Color::rgb(0.33, 0.36, 0.18),
+            Self::Blue => Color::rgb(0.1, 0.22, 0.5),
+            Self::Orange => Color::rgb(0.9, 0.42, 0.08),
+            Self::Black => Color::rgb(0.04, 0.04, 0.05),
+        }
+    }
+
+    /// Body material of the paint, flat colors are matte and dark ones glossy
+    pub fn finish(self) -> MaterialOverride {
+        let (perceptual_roughness, metallic) = match self {
+            Self::Sand | Self::Olive => (0.85, 0.0),
+            Self::Red | Self::Orange => (0.5, 0.1),
+            Self::Blue | Self::Black => (0.3, 0.5),
+        };
+        MaterialOverride { base_color: self.color(), perceptual_roughness, metallic }
+    }
+
+    /// Localization key of the paint's name
+    pub fn name_key(self) -> &'static str {
+        match self {
+            Self::Red => "paint.red",
+            Self::Sand => "paint.sand",
+            Self::Olive => "paint.olive",
+            Self::Blue => "paint.blue",
+            Self::Orange => "paint.orange",
+            Self::Black => "paint.black",
+        }
+    }
+}
+
+/// Bolt-on part fitted to the player's vehicle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Accessory {
+    RoofRack,
+    LightBar,
+    Snorkel,
+}
+
+impl Accessory {
+    pub const ALL: [Accessory; 3] = [Accessory::RoofRack, Accessory::LightBar, Accessory::Snorkel];
+
+    /// Localization key of the accessory's name
+    pub fn name_key(self) -> &'static str {
+        match self {
+            Self::RoofRack => "accessory.roof_rack",
+            Self::LightBar => "accessory.light_bar",
+            Self::Snorkel => "accessory.snorkel",
+        }
+    }
+
+    /// Size and position of the part on a body of `dimensions`, centered on the chassis
+    pub fn fitment(self, dimensions: Vec3) -> (Vec3, Vec3) {
+        let (width, height, length) = (dimensions.x, dimensions.y, dimensions.z);
+        match self {
+            Self::RoofRack => (
+                Vec3::new(width * 0.9, 0.08, length * 0.45),
+                Vec3::new(0.0, height * 0.5 + 0.1, -length * 0.1),
+            ),
+            Self::LightBar => (
+                Vec3::new(width * 0.8, 0.1, 0.1),
+                Vec3::new(0.0, height * 0.5 + 0.08, length * 0.15),
+            ),
+            Self::Snorkel => (
+                Vec3::new(0.1, height * 0.9, 0.1),
+                Vec3::new(width * 0.5 + 0.05, height * 0.2, length * 0.25),
+            ),
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Self::LightBar => Color::rgb(0.9, 0.9, 0.85),
+            _ => Color::rgb(0.08, 0.08, 0.08),
+        }
+    }
+}
+
+/// Something the garage offers once the player has earned it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Unlock {
+    /// A vehicle by name, see [`catalog_vehicles`]
+    Vehicle(String),
+    Paint(Paint),
+    Accessory(Accessory),
+    /// Tires, lift kit or bumper
+    Part(Part),
+}
+
+/// An unlock and the player level it comes with, level 1 content is there from the start
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnlockEntry {
+    pub level: u32,
+    pub unlock: Unlock,
+}
+
+impl UnlockEntry {
+    fn new(level: u32, unlock: Unlock) -> Self {
+        Self { level, unlock }
+    }
+}
+
+/// Name of the vehicle every profile starts with
+pub const STARTER_VEHICLE: &str = "Jeep TJ";
+
+/// The vehicles the garage offers, the starter vehicle first
+pub fn catalog_vehicles() -> Vec<VehicleConfig> {
+    let starter = VehicleConfig::default();
+    vec![
+        VehicleConfig { name: STARTER_VEHICLE.to_string(), ..starter.clone() },
+        VehicleConfig {
+            name: "Ford Raptor".to_string(),
+            mass: 2600.0,
+            dimensions: starter.dimensions * Vec3::new(1.15, 1.05, 1.4),
+            wheelbase: starter.wheelbase * 1.4,
+            track_width: starter.track_width * 1.15,
+            ..starter.clone()
+        },
+        VehicleConfig {
+            name: "Rock Crawler".to_string(),
+            mass: 1900.0,
+            dimensions: starter.dimensions * Vec3::new(1.0, 0.9, 0.9),
+            wheel_radius: starter.wheel_radius * 1.3,
+            wheelbase: starter.wheelbase * 0.9,
+            max_steering_angle: starter.max_steering_angle * 1.2,
+            ..starter.clone()
+        },
+        VehicleConfig {
+            name: "Trophy Truck".to_string(),
+            mass: 2800.0,
+            dimensions: starter.dimensions * Vec3::new(1.25, 1.0, 1.5),
+            wheel_radius: starter.wheel_radius * 1.2,
+            wheelbase: starter.wheelbase * 1.5,
+            track_width: starter.track_width * 1.3,
+            ..starter
+        },
+    ]
+}
+
+/// What each player level unlocks
+pub fn default_unlocks() -> Vec<UnlockEntry> {
+    vec![
+        UnlockEntry::new(1, Unlock::Vehicle(STARTER_VEHICLE.to_string())),
+        UnlockEntry::new(1, Unlock::Paint(Paint::Red)),
+        UnlockEntry::new(1, Unlock::Part(Part::Tires(TireType::AllTerrain))),
+        UnlockEntry::new(1, Unlock::Part(Part::LiftKit(LiftKit::Stock))),
+        UnlockEntry::new(1, Unlock::Part(Part::Bumper(Bumper::Stock))),
+        UnlockEntry::new(2, Unlock::Paint(Paint::Sand)),
+        UnlockEntry::new(2, Unlock::Part(Part::Bumper(Bumper::Steel))),
+        UnlockEntry::new(3, Unlock::Paint(Paint::Olive)),
+        UnlockEntry::new(3, Unlock::Accessory(Accessory::RoofRack)),
+        UnlockEntry::new(3, Unlock::Part(Part::Tires(TireType::MudTerrain))),
+        UnlockEntry::new(4, Unlock::Vehicle("Rock Crawler".to_string())),
+        UnlockEntry::new(4, Unlock::Part(Part::Bumper(Bumper::Winch))),
+        UnlockEntry::new(5, Unlock::Paint(Paint::Blue)),
+        UnlockEntry::new(5, Unlock::Accessory(Accessory::LightBar)),
+        UnlockEntry::new(5, Unlock::Part(Part::LiftKit(LiftKit::TwoInch))),
+        UnlockEntry::new(6, Unlock::Vehicle("Ford Raptor".to_string())),
+        UnlockEntry::new(7, Unlock::Paint(Paint::Orange)),
+        UnlockEntry::new(7, Unlock::Accessory(Accessory::Snorkel)),
+        UnlockEntry::new(7, Unlock::Part(Part::Tires(TireType::Crawler))),
+        UnlockEntry::new(8, Unlock::Part(Part::LiftKit(LiftKit::FourInch))),
+        UnlockEntry::new(9, Unlock::Paint(Paint::Black)),
+        UnlockEntry::new(10, Unlock::Vehicle("Trophy Truck".to_string())),
+    ]
+}
@@ -0,0 +1,544 @@
+/// Player progression: experience, levels and the garage content they unlock
+///
+/// Challenges (deliveries and points of interest), races and distance driven all pay out through
+/// [`AwardXpEvent`]. Reaching a level unlocks the vehicles, paints, accessories and parts listed for it,
+/// which the garage only offers once unlocked. XP, unlocks and the garage loadout are kept in the
+/// [`GameProgress`] save, written to the profile file whenever XP is awarded and on exit.
+mod catalog;
+
+use std::path::{Path, PathBuf};
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+pub use catalog::{catalog_vehicles, default_unlocks, Accessory, Paint, Unlock, UnlockEntry, STARTER_VEHICLE};
+
+use super::scripting::VehicleUnlockedEvent;
+use super::split_screen::PlayerId;
+use super::trails::PoiReachedEvent;
+use crate::game::render_available;
+use crate::game::states::GameProgress;
+use crate::game::vehicle::{
+    CargoDeliveredEvent, Part, PlayerOrAi, Vehicle, VehicleConfig, VehicleCustomization, VehicleDefinition,
+    VehicleSpawnedEvent,
+};
+
+/// How much more XP each level takes than the one before it
+const LEVEL_XP_STEP: u32 = 500;
+
+/// Total XP needed to reach `level`, level 1 needs none
+pub fn xp_for_level(level: u32) -> u32 {
+    LEVEL_XP_STEP * level.saturating_sub(1) * level / 2
+}
+
+/// Level a player with `xp` has reached
+pub fn level_for_xp(xp: u32) -> u32 {
+    let mut level = 1;
+    while xp_for_level(level + 1) <= xp {
+        level += 1;
+    }
+    level
+}
+
+/// How far `xp` is through its level (0.0 - 1.0)
+pub fn level_progress(xp: u32) -> f32 {
+    let level = level_for_xp(xp);
+    let start = xp_for_level(level);
+    (xp - start) as f32 / (xp_for_level(level + 1) - start) as f32
+}
+
+/// What XP was earned for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XpSource {
+    Challenge,
+    Race,
+    Distance,
+}
+
+/// Gives the player XP
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct AwardXpEvent {
+    pub amount: u32,
+    pub source: XpSource,
+}
+
+/// A vehicle crossed the finish line, races report their results through this
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RaceFinishedEvent {
+    pub vehicle: Entity,
+    /// Finishing position, 1 is the winner
+    pub position: u32,
+    pub racers: u32,
+}
+
+/// A vehicle's place in a running race changed, races report the standings through this
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct RacePositionEvent {
+    pub vehicle: Entity,
+    /// Current position, 1 is leading
+    pub position: u32,
+    pub racers: u32,
+}
+
+/// The player reached a new level
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct LevelUpEvent {
+    pub level: u32,
+}
+
+/// A paint, accessory or part was unlocked, vehicles are announced with [`VehicleUnlockedEvent`]
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct ItemUnlockedEvent {
+    pub unlock: Unlock,
+}
+
+/// Vehicle, paint, accessories and parts picked in the garage
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Loadout {
+    /// `None` for the starter vehicle
+    pub vehicle: Option<String>,
+    pub paint: Paint,
+    pub accessories: Vec<Accessory>,
+    /// Tires, lift kit and bumper, fitted to whichever vehicle is picked
+    pub customization: VehicleCustomization,
+}
+
+/// Marks the accessory parts fitted to a vehicle
+#[derive(Component, Debug, Clone, Copy)]
+pub struct FittedAccessory(pub Accessory);
+
+/// XP rewards and the unlocks of each level
+#[derive(Resource, Debug, Clone)]
+pub struct ProgressionConfig {
+    /// XP for delivering cargo intact
+    pub delivery_xp: u32,
+    /// Share of the delivery XP paid for damaged cargo
+    pub damaged_delivery_share: f32,
+    /// XP for reaching a point of interest
+    pub discovery_xp: u32,
+    /// XP for finishing a race, each place ahead of last adds the same again
+    pub race_xp: u32,
+    pub xp_per_km: u32,
+    pub unlocks: Vec<UnlockEntry>,
+    pub vehicles: Vec<VehicleConfig>,
+    /// Where the profile is saved, `None` keeps it in memory only
+    pub profile_path: Option<PathBuf>,
+}
+
+impl Default for ProgressionConfig {
+    fn default() -> Self {
+        Self {
+            delivery_xp: 150,
+            damaged_delivery_share: 0.4,
+            discovery_xp: 50,
+            race_xp: 100,
+            xp_per_km: 20,
+            unlocks: default_unlocks(),
+            vehicles: catalog_vehicles(),
+            profile_path: Some(PathBuf::from("saves/profile.json")),
+        }
+    }
+}
+
+impl ProgressionConfig {
+    /// Level `unlock` comes with, `None` for content no level gives
+    pub fn required_level(&self, unlock: &Unlock) -> Option<u32> {
+        self.unlocks.iter().find(|entry| entry.unlock == *unlock).map(|entry| entry.level)
+    }
+
+    /// Starting content and anything the profile has unlocked since
+    pub fn is_unlocked(&self, progress: &GameProgress, unlock: &Unlock) -> bool {
+        let recorded = match unlock {
+            Unlock::Vehicle(name) => progress.unlocked_vehicles.contains(name),
+            Unlock::Paint(paint) => progress.unlocked_paints.contains(paint),
+            Unlock::Accessory(accessory) => progress.unlocked_accessories.contains(accessory),
+            Unlock::Part(part) => progress.unlocked_parts.contains(part),
+        };
+        recorded || self.required_level(unlock) == Some(1)
+    }
+
+    /// Unlocks up to `level` the profile doesn't have yet, lowest level first
+    pub fn due_unlocks(&self, progress: &GameProgress, level: u32) -> Vec<Unlock> {
+        let mut due: Vec<&UnlockEntry> = self
+            .unlocks
+            .iter()
+            .filter(|entry| entry.level <= level && !self.is_unlocked(progress, &entry.unlock))
+            .collect();
+        due.sort_by_key(|entry| entry.level);
+        due.into_iter().map(|entry| entry.unlock.clone()).collect()
+    }
+
+    /// XP for finishing a race in `position` of `racers`
+    pub fn race_reward(&self, position: u32, racers: u32) -> u32 {
+        let ahead_of = racers.saturating_sub(position.max(1));
+        self.race_xp * (ahead_of + 1)
+    }
+
+    /// What the player's loadout spawns as, anything locked falls back to the starter vehicle, paint and parts
+    pub fn vehicle_definition(&self, progress: &GameProgress) -> VehicleDefinition {
+        let loadout = &progress.loadout;
+        let config = loadout
+            .vehicle
+            .as_ref()
+            .filter(|name| self.is_unlocked(progress, &Unlock::Vehicle(name.to_string())))
+            .and_then(|name| self.vehicles.iter().find(|config| config.name == *name))
+            .or_else(|| self.vehicles.first())
+            .cloned()
+            .unwrap_or_default();
+        let paint = if self.is_unlocked(progress, &Unlock::Paint(loadout.paint)) {
+            loadout.paint
+        } else {
+            Paint::default()
+        };
+        let stock = VehicleCustomization::default();
+        let picked = loadout.customization;
+        let unlocked = |part| self.is_unlocked(progress, &Unlock::Part(part));
+        let customization = VehicleCustomization {
+            tires: if unlocked(Part::Tires(picked.tires)) { picked.tires } else { stock.tires },
+            lift: if unlocked(Part::LiftKit(picked.lift)) { picked.lift } else { stock.lift },
+            bumper: if unlocked(Part::Bumper(picked.bumper)) { picked.bumper } else { stock.bumper },
+        };
+        VehicleDefinition {
+            config,
+            body_color: paint.color(),
+            customization,
+            material_override: Some(paint.finish()),
+            ..default()
+        }
+    }
+}
+
+/// Adds `unlock` to the lists of the save
+fn record_unlock(progress: &mut GameProgress, unlock: &Unlock) {
+    match unlock {
+        Unlock::Vehicle(name) => progress.unlocked_vehicles.push(name.clone()),
+        Unlock::Paint(paint) => progress.unlocked_paints.push(*paint),
+        Unlock::Accessory(accessory) => progress.unlocked_accessories.push(*accessory),
+        Unlock::Part(part) => progress.unlocked_parts.push(*part),
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ProfileError {
+    #[error("profile file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("profile format: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Reads a saved profile
+pub fn load_profile(path: &Path) -> Result<GameProgress, ProfileError> {
+    Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+}
+
+/// Writes the profile, creating its directory if needed
+pub fn save_profile(progress: &GameProgress, path: &Path) -> Result<(), ProfileError> {
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+    std::fs::write(path, serde_json::to_vec_pretty(progress)?)?;
+    Ok(())
+}
+
+pub(super) fn write_profile(config: &ProgressionConfig, progress: &GameProgress) {
+    if let Some(path) = &config.profile_path {
+        if let Err(error) = save_profile(progress, path) {
+            warn!("Couldn't save the profile to {}: {error}", path.display());
+        }
+    }
+}
+
+/// Inserts the saved profile, if there is one
+fn restore_profile(mut commands: Commands, config: Res<ProgressionConfig>) {
+    let Some(path) = config.profile_path.as_ref().filter(|path| path.exists()) else {
+        return;
+    };
+    match load_profile(path) {
+        Ok(progress) => commands.insert_resource(progress),
+        Err(error) => warn!("Couldn't load the profile from {}: {error}", path.display()),
+    }
+}
+
+/// XP for deliveries, points of interest and races
+fn award_challenge_xp(
+    config: Res<ProgressionConfig>,
+    players: Query<(), With<PlayerId>>,
+    mut deliveries: EventReader<CargoDeliveredEvent>,
+    mut discoveries: EventReader<PoiReachedEvent>,
+    mut races: EventReader<RaceFinishedEvent>,
+    mut awards: EventWriter<AwardXpEvent>,
+) {
+    for delivery in deliveries.read() {
+        let share = if delivery.intact { 1.0 } else { config.damaged_delivery_share };
+        let amount = (config.delivery_xp as f32 * share).round() as u32;
+        awards.send(AwardXpEvent { amount, source: XpSource::Challenge });
+    }
+    for discovery in discoveries.read().filter(|discovery| players.contains(discovery.vehicle)) {
+        awards.send(AwardXpEvent { amount: config.discovery_xp, source: XpSource::Challenge });
+    }
+    for race in races.read().filter(|race| players.contains(race.vehicle)) {
+        awards.send(AwardXpEvent {
+            amount: config.race_reward(race.position, race.racers),
+            source: XpSource::Race,
+        });
+    }
+}
+
+/// Adds up the distance player vehicles drive, paying XP for each whole kilometer
+fn award_distance_xp(
+    time: Res<Time>,
+    config: Res<ProgressionConfig>,
+    progress: Option<ResMut<GameProgress>>,
+    vehicles: Query<&Vehicle, With<PlayerId>>,
+    mut awards: EventWriter<AwardXpEvent>,
+) {
+    let Some(mut progress) = progress else {
+        return;
+    };
+    let driven: f32 = vehicles.iter().map(|vehicle| vehicle.vehicle_speed.abs() * time.delta_seconds()).sum();
+    if driven <= 0.0 {
+        return;
+    }
+    let before = (progress.distance_driven / 1000.0) as u32;
+    progress.distance_driven += driven;
+    let kilometers = (progress.distance_driven / 1000.0) as u32 - before;
+    if kilometers > 0 {
+        awards.send(AwardXpEvent { amount: kilometers * config.xp_per_km, source: XpSource::Distance });
+    }
+}
+
+/// Adds awarded XP to the save, announcing new levels and what they unlock
+fn apply_xp(
+    config: Res<ProgressionConfig>,
+    progress: Option<ResMut<GameProgress>>,
+    mut awards: EventReader<AwardXpEvent>,
+    mut level_ups: EventWriter<LevelUpEvent>,
+    mut item_unlocks: EventWriter<ItemUnlockedEvent>,
+    mut vehicle_unlocks: EventWriter<VehicleUnlockedEvent>,
+) {
+    let total: u32 = awards.read().map(|award| award.amount).sum();
+    let Some(mut progress) = progress.filter(|_| total > 0) else {
+        return;
+    };
+    let before = level_for_xp(progress.xp);
+    progress.xp = progress.xp.saturating_add(total);
+    let level = level_for_xp(progress.xp);
+    for reached in before + 1..=level {
+        level_ups.send(LevelUpEvent { level: reached });
+    }
+
+    for unlock in config.due_unlocks(&progress, level) {
+        record_unlock(&mut progress, &unlock);
+        match unlock {
+            Unlock::Vehicle(name) => vehicle_unlocks.send(VehicleUnlockedEvent { name }),
+            unlock => item_unlocks.send(ItemUnlockedEvent { unlock }),
+        }
+    }
+    write_profile(&config, &progress);
+}
+
+/// Keeps the distance driven since the last award
+fn save_profile_on_exit(
+    config: Res<ProgressionConfig>,
+    progress: Option<Res<GameProgress>>,
+    mut exits: EventReader<AppExit>,
+) {
+    if exits.read().count() > 0 {
+        if let Some(progress) = progress {
+            write_profile(&config, &progress);
+        }
+    }
+}
+
+/// Bolts the loadout's unlocked accessories onto player vehicles as they spawn
+fn fit_accessories(
+    mut commands: Commands,
+    config: Res<ProgressionConfig>,
+    progress: Option<Res<GameProgress>>,
+    mut spawned: EventReader<VehicleSpawnedEvent>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let Some(progress) = progress else {
+        spawned.clear();
+        return;
+    };
+    // Player vehicles are spawned from the loadout, so its body is the one being fitted
+    let dimensions = config.vehicle_definition(&progress).config.dimensions;
+    for event in spawned.read() {
+        if !matches!(event.driver, PlayerOrAi::Player(_)) {
+            continue;
+        }
+        let accessories = progress
+            .loadout
+            .accessories
+            .iter()
+            .filter(|accessory| config.is_unlocked(&progress, &Unlock::Accessory(**accessory)));
+        for accessory in accessories {
+            let (size, offset) = accessory.fitment(dimensions);
+            let part = commands
+                .spawn((
+                    PbrBundle {
+                        mesh: meshes.add(Mesh::from(shape::Box::new(size.x, size.y, size.z))),
+                        material: materials.add(StandardMaterial {
+                            base_color: accessory.color(),
+                            perceptual_roughness: 0.5,
+                            ..default()
+                        }),
+                        transform: Transform::from_translation(offset),
+                        ..default()
+                    },
+                    FittedAccessory(*accessory),
+                    Name::new(format!("{accessory:?}")),
+                ))
+                .id();
+            commands.entity(event.vehicle).add_child(part);
+        }
+    }
+}
+
+/// Plugin for XP, levels and unlocks
+pub struct ProgressionPlugin;
+
+impl Plugin for ProgressionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ProgressionConfig>()
+            .add_event::<AwardXpEvent>()
+            .add_event::<RaceFinishedEvent>()
+            .add_event::<RacePositionEvent>()
+            .add_event::<LevelUpEvent>()
+            .add_event::<ItemUnlockedEvent>()
+            .add_event::<VehicleUnlockedEvent>()
+            .add_event::<CargoDeliveredEvent>()
+            .add_event::<PoiReachedEvent>()
+            .add_systems(PreStartup, restore_profile)
+            .add_systems(Update, (
+                award_challenge_xp,
+                award_distance_xp,
+                apply_xp,
+            ).chain())
+            .add_systems(Last, save_profile_on_exit);
+
+        // Accessory parts need meshes
+        if render_available(app) {
+            app.add_systems(Update, fit_accessories);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::vehicle::{Bumper, LiftKit};
+
+    fn test_app() -> App {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .insert_resource(ProgressionConfig { profile_path: None, ..default() })
+            .init_resource::<GameProgress>()
+            .add_event::<AwardXpEvent>()
+            .add_event::<RaceFinishedEvent>()
+            .add_event::<LevelUpEvent>()
+            .add_event::<ItemUnlockedEvent>()
+            .add_event::<VehicleUnlockedEvent>()
+            .add_event::<CargoDeliveredEvent>()
+            .add_event::<PoiReachedEvent>()
+            .add_systems(Update, (award_challenge_xp, apply_xp).chain());
+        app
+    }
+
+    #[test]
+    fn test_level_curve() {
+        assert_eq!(level_for_xp(0), 1);
+        assert_eq!(level_for_xp(499), 1);
+        assert_eq!(level_for_xp(500), 2);
+        assert_eq!(level_for_xp(1500), 3);
+        assert_eq!(xp_for_level(4), 3000);
+        assert_eq!(level_progress(1000), 0.5);
+    }
+
+    #[test]
+    fn test_levels_unlock_content_once() {
+        let mut app = test_app();
+        let progress = app.world.resource::<GameProgress>();
+        let config = app.world.resource::<ProgressionConfig>();
+        assert!(config.is_unlocked(progress, &Unlock::Vehicle(STARTER_VEHICLE.to_string())));
+        assert!(!config.is_unlocked(progress, &Unlock::Paint(Paint::Sand)));
+
+        // A race win among four pays 400, a delivery 150, enough for level 2
+        let player = app.world.spawn(PlayerId(0)).id();
+        app.world.send_event(RaceFinishedEvent { vehicle: player, position: 1, racers: 4 });
+        app.world.send_event(CargoDeliveredEvent {
+            cargo: Entity::PLACEHOLDER,
+            zone: Entity::PLACEHOLDER,
+            integrity: 1.0,
+            intact: true,
+        });
+        app.update();
+
+        let progress = app.world.resource::<GameProgress>();
+        assert_eq!(progress.xp, 550);
+        assert_eq!(progress.unlocked_paints, [Paint::Sand]);
+        assert_eq!(progress.unlocked_parts, [Part::Bumper(Bumper::Steel)]);
+        let level_ups: Vec<LevelUpEvent> = app.world.resource_mut::<Events<LevelUpEvent>>().drain().collect();
+        assert_eq!(level_ups, [LevelUpEvent { level: 2 }]);
+        assert_eq!(app.world.resource::<Events<ItemUnlockedEvent>>().len(), 2);
+
+        // Another vehicle's result pays nothing, and nothing is unlocked twice
+        let rival = app.world.spawn_empty().id();
+        app.world.send_event(RaceFinishedEvent { vehicle: rival, position: 1, racers: 4 });
+        app.world.send_event(AwardXpEvent { amount: 100, source: XpSource::Challenge });
+        app.update();
+        let progress = app.world.resource::<GameProgress>();
+        assert_eq!(progress.xp, 650);
+        assert_eq!(progress.unlocked_paints, [Paint::Sand]);
+    }
+
+    #[test]
+    fn test_locked_loadout_spawns_starter() {
+        let config = ProgressionConfig::default();
+        let mut progress = GameProgress {
+            loadout: Loadout {
+                vehicle: Some("Trophy Truck".to_string()),
+                paint: Paint::Black,
+                accessories: Vec::new(),
+                customization: VehicleCustomization { lift: LiftKit::FourInch, ..default() },
+            },
+            ..default()
+        };
+        let definition = config.vehicle_definition(&progress);
+        assert_eq!(definition.config.name, STARTER_VEHICLE);
+        assert_eq!(definition.body_color, Paint::Red.color());
+        assert_eq!(definition.customization.lift, LiftKit::Stock);
+
+        // Mission unlocks count as well as level unlocks
+        progress.unlocked_vehicles.push("Trophy Truck".to_string());
+        progress.unlocked_paints.push(Paint::Black);
+        progress.unlocked_parts.push(Part::LiftKit(LiftKit::FourInch));
+        let definition = config.vehicle_definition(&progress);
+        assert_eq!(definition.config.name, "Trophy Truck");
+        assert_eq!(definition.body_color, Paint::Black.color());
+        assert_eq!(definition.material_override, Some(Paint::Black.finish()));
+        assert_eq!(definition.customization.lift, LiftKit::FourInch);
+    }
+
+    #[test]
+    fn test_profile_round_trip() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("saves/profile.json");
+        let progress = GameProgress {
+            xp: 1234,
+            unlocked_paints: vec![Paint::Olive],
+            loadout: Loadout { paint: Paint::Olive, ..default() },
+            ..default()
+        };
+        save_profile(&progress, &path).unwrap();
+        let loaded = load_profile(&path).unwrap();
+        assert_eq!(loaded.xp, 1234);
+        assert_eq!(loaded.loadout.paint, Paint::Olive);
+        assert_eq!(loaded.unlocked_paints, [Paint::Olive]);
+    }
+}
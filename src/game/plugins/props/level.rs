@@ -0,0 +1,112 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::assets::{check_schema_version, read_schema_version, SchemaVersionError};
+
+/// What a destructible prop is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PropKind {
+    /// A run of posts and rails
+    Fence,
+    /// A swing gate of planks across a track
+    Gate,
+    /// A young tree, snapped at the trunk
+    Sapling,
+}
+
+fn default_length() -> f32 {
+    4.0
+}
+
+/// A destructible prop as written in a level file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PropDesc {
+    /// Unique within the level, free roam saves remember broken props by it
+    pub id: String,
+    pub kind: PropKind,
+    /// World position of the prop's foot, the middle of a fence run or gate
+    pub position: [f32; 3],
+    /// Rotation around the vertical axis in degrees, fences and gates run along their local X
+    #[serde(default)]
+    pub rotation: f32,
+    /// Length of a fence run or width of a gate in meters, saplings ignore it
+    #[serde(default = "default_length")]
+    pub length: f32,
+}
+
+/// Destructible props of a level, loaded from `*.props.json`
+#[derive(Asset, TypePath, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LevelProps {
+    pub props: Vec<PropDesc>,
+}
+
+/// Errors produced while loading level prop files
+#[derive(Debug, thiserror::Error)]
+pub enum LevelPropsError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Schema(#[from] SchemaVersionError),
+}
+
+/// Asset loader for level prop files
+#[derive(Default)]
+pub struct LevelPropsLoader;
+
+impl AssetLoader for LevelPropsLoader {
+    type Asset = LevelProps;
+    type Settings = ();
+    type Error = LevelPropsError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<LevelProps, LevelPropsError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            check_schema_version(read_schema_version(&bytes)?)?;
+            Ok(serde_json::from_slice(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["props.json"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_level_props() {
+        let json = r#"{
+            "props": [
+                {
+                    "id": "ranch_fence_1",
+                    "kind": "fence",
+                    "position": [10.0, 0.0, -4.0],
+                    "rotation": 30.0,
+                    "length": 12.0
+                },
+                { "id": "ranch_gate", "kind": "gate", "position": [16.0, 0.0, 0.0] },
+                { "id": "sapling_3", "kind": "sapling", "position": [-8.0, 0.5, 20.0] }
+            ]
+        }"#;
+        let level: LevelProps = serde_json::from_str(json).unwrap();
+        assert_eq!(level.props.len(), 3);
+        assert_eq!(level.props[0].kind, PropKind::Fence);
+        assert_eq!(level.props[1].length, default_length());
+        assert_eq!(level.props[2].kind, PropKind::Sapling);
+    }
+}
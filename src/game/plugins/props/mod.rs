@@ -0,0 +1,425 @@
+/// Destructible props: fences, gates and saplings
+///
+/// Props come from a level's `*.props.json` file and stand as fixed bodies until a vehicle hits
+/// one hard enough, when it breaks into its pieces as loose debris. Debris entities are pooled,
+/// a pileup through a fence line reuses the same few dozen rather than spawning a burst of new ones.
+/// In free roam the ids of broken props go into the save, so they stay broken next session.
+mod level;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+pub use level::{LevelProps, LevelPropsError, LevelPropsLoader, PropDesc, PropKind};
+
+use super::impacts::{ImpactEvent, SurfaceMaterial};
+use crate::game::states::{GameMode, GameProgress};
+use crate::utils::{warm_pool, EntityPool};
+
+/// Spacing of fence posts in meters
+const POST_SPACING: f32 = 2.0;
+
+/// One piece of a prop, a box in the prop's local space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PropPiece {
+    pub offset: Vec3,
+    pub half_size: Vec3,
+    /// Foliage rather than wood
+    pub leaves: bool,
+}
+
+impl PropPiece {
+    fn wood(offset: Vec3, half_size: Vec3) -> Self {
+        Self { offset, half_size, leaves: false }
+    }
+}
+
+/// Pieces a prop is built from and breaks into. Fence rails and gate planks are split between
+/// posts and in half, so a broken fence scatters into short lengths rather than one long pole.
+pub fn prop_pieces(kind: PropKind, length: f32) -> Vec<PropPiece> {
+    let length = length.max(0.5);
+    match kind {
+        PropKind::Fence => {
+            let spans = (length / POST_SPACING).ceil().max(1.0) as usize;
+            let span = length / spans as f32;
+            let mut pieces = Vec::with_capacity(spans * 3 + 1);
+            for i in 0..=spans {
+                let x = -length * 0.5 + i as f32 * span;
+                pieces.push(PropPiece::wood(Vec3::new(x, 0.6, 0.0), Vec3::new(0.06, 0.6, 0.06)));
+            }
+            for i in 0..spans {
+                let x = -length * 0.5 + (i as f32 + 0.5) * span;
+                for height in [0.45, 0.95] {
+                    pieces.push(PropPiece::wood(Vec3::new(x, height, 0.0), Vec3::new(span * 0.5, 0.05, 0.025)));
+                }
+            }
+            pieces
+        }
+        PropKind::Gate => {
+            let mut pieces = Vec::with_capacity(10);
+            for side in [-1.0, 1.0] {
+                pieces.push(PropPiece::wood(Vec3::new(side * length * 0.5, 0.7, 0.0), Vec3::new(0.08, 0.7, 0.08)));
+                for height in [0.3, 0.65, 1.0, 1.3] {
+                    let offset = Vec3::new(side * length * 0.25, height, 0.0);
+                    pieces.push(PropPiece::wood(offset, Vec3::new(length * 0.25 - 0.1, 0.07, 0.025)));
+                }
+            }
+            pieces
+        }
+        PropKind::Sapling => vec![
+            PropPiece::wood(Vec3::new(0.0, 0.5, 0.0), Vec3::new(0.06, 0.5, 0.06)),
+            PropPiece::wood(Vec3::new(0.0, 1.5, 0.0), Vec3::new(0.05, 0.5, 0.05)),
+            PropPiece { offset: Vec3::new(0.0, 2.2, 0.0), half_size: Vec3::new(0.6, 0.55, 0.6), leaves: true },
+        ],
+    }
+}
+
+/// Smallest box around `pieces`, as its center and half size
+fn bounds(pieces: &[PropPiece]) -> (Vec3, Vec3) {
+    let (min, max) = pieces.iter().fold((Vec3::MAX, Vec3::MIN), |(min, max), piece| {
+        (min.min(piece.offset - piece.half_size), max.max(piece.offset + piece.half_size))
+    });
+    ((min + max) * 0.5, (max - min) * 0.5)
+}
+
+/// How hard props are to break and how their debris behaves
+#[derive(Resource, Debug, Clone)]
+pub struct PropSettings {
+    /// Impact impulse in N·s that breaks a fence
+    pub fence_break_impulse: f32,
+    pub gate_break_impulse: f32,
+    pub sapling_break_impulse: f32,
+    /// Speed in m/s debris is knocked away at by a full intensity impact
+    pub debris_speed: f32,
+    /// Seconds debris lies around before it's recycled
+    pub debris_lifetime: f32,
+    /// Broken props stay broken in free roam saves
+    pub persist_in_free_roam: bool,
+}
+
+impl Default for PropSettings {
+    fn default() -> Self {
+        Self {
+            fence_break_impulse: 1500.0,
+            gate_break_impulse: 2500.0,
+            sapling_break_impulse: 600.0,
+            debris_speed: 8.0,
+            debris_lifetime: 8.0,
+            persist_in_free_roam: true,
+        }
+    }
+}
+
+impl PropSettings {
+    pub fn break_impulse(&self, kind: PropKind) -> f32 {
+        match kind {
+            PropKind::Fence => self.fence_break_impulse,
+            PropKind::Gate => self.gate_break_impulse,
+            PropKind::Sapling => self.sapling_break_impulse,
+        }
+    }
+}
+
+/// A standing prop that breaks when hit
+#[derive(Component, Debug, Clone)]
+pub struct DestructibleProp {
+    pub id: String,
+    pub kind: PropKind,
+    pub length: f32,
+}
+
+/// A loose piece of a broken prop
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PropDebris {
+    /// Seconds until it's recycled
+    pub remaining: f32,
+}
+
+/// Marks the recycled entities debris is spawned on
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct PooledPropDebris;
+
+/// Marks level entities whose props have been spawned
+#[derive(Component)]
+pub struct LevelPropsSpawned;
+
+/// Sent when a prop breaks, for sound and scoring
+#[derive(Event, Debug, Clone)]
+pub struct PropBrokenEvent {
+    pub id: String,
+    pub kind: PropKind,
+    pub position: Vec3,
+    /// Intensity of the impact that broke it (0.0 - 1.0)
+    pub intensity: f32,
+}
+
+/// Shared piece mesh and prop materials
+#[derive(Resource, Default)]
+struct PropAssets {
+    /// Unit cube, scaled to each piece
+    cube: Handle<Mesh>,
+    wood: Handle<StandardMaterial>,
+    leaves: Handle<StandardMaterial>,
+}
+
+impl PropAssets {
+    fn material(&self, piece: &PropPiece) -> Handle<StandardMaterial> {
+        if piece.leaves {
+            self.leaves.clone()
+        } else {
+            self.wood.clone()
+        }
+    }
+}
+
+fn piece_transform(piece: &PropPiece) -> Transform {
+    Transform::from_translation(piece.offset).with_scale(piece.half_size * 2.0)
+}
+
+fn setup_prop_assets(
+    mut assets: ResMut<PropAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    assets.cube = meshes.add(Mesh::from(shape::Cube { size: 1.0 }));
+    assets.wood = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.45, 0.32, 0.2),
+        perceptual_roughness: 0.9,
+        ..default()
+    });
+    assets.leaves = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.25, 0.45, 0.15),
+        perceptual_roughness: 0.8,
+        ..default()
+    });
+}
+
+/// Whether broken props are kept in the save this session
+fn persisting(settings: &PropSettings, mode: Option<&State<GameMode>>) -> bool {
+    settings.persist_in_free_roam && mode.is_some_and(|mode| *mode.get() == GameMode::FreeRoam)
+}
+
+/// Spawns the props of loaded level prop assets as children of the level entity, leaving out the
+/// ones the free roam save remembers as broken
+fn spawn_level_props(
+    mut commands: Commands,
+    levels: Query<(Entity, &Handle<LevelProps>), Without<LevelPropsSpawned>>,
+    level_props: Res<Assets<LevelProps>>,
+    assets: Res<PropAssets>,
+    settings: Res<PropSettings>,
+    mode: Option<Res<State<GameMode>>>,
+    progress: Option<Res<GameProgress>>,
+) {
+    let destroyed = progress
+        .filter(|_| persisting(&settings, mode.as_deref()))
+        .map(|progress| progress.destroyed_props.clone())
+        .unwrap_or_default();
+    for (level, handle) in levels.iter() {
+        let Some(props) = level_props.get(handle) else {
+            continue;
+        };
+
+        let mut children = Vec::new();
+        for desc in props.props.iter().filter(|desc| !destroyed.contains(&desc.id)) {
+            let pieces = prop_pieces(desc.kind, desc.length);
+            let (center, half_size) = bounds(&pieces);
+            let shape = Collider::cuboid(half_size.x, half_size.y, half_size.z);
+            let transform = Transform::from_translation(Vec3::from(desc.position))
+                .with_rotation(Quat::from_rotation_y(desc.rotation.to_radians()));
+            let prop = commands
+                .spawn((
+                    SpatialBundle::from_transform(transform),
+                    RigidBody::Fixed,
+                    Collider::compound(vec![(center, Quat::IDENTITY, shape)]),
+                    SurfaceMaterial::Wood,
+                    DestructibleProp { id: desc.id.clone(), kind: desc.kind, length: desc.length },
+                    Name::new(desc.id.clone()),
+                ))
+                .with_children(|parent| {
+                    for piece in &pieces {
+                        parent.spawn(PbrBundle {
+                            mesh: assets.cube.clone(),
+                            material: assets.material(piece),
+                            transform: piece_transform(piece),
+                            ..default()
+                        });
+                    }
+                })
+                .id();
+            children.push(prop);
+        }
+        commands.entity(level).push_children(&children).insert(LevelPropsSpawned);
+    }
+}
+
+/// Breaks props hit harder than their threshold into pooled debris, knocked away from the impact
+fn break_props(
+    mut commands: Commands,
+    mut impacts: EventReader<ImpactEvent>,
+    settings: Res<PropSettings>,
+    assets: Res<PropAssets>,
+    mut pool: ResMut<EntityPool<PooledPropDebris>>,
+    props: Query<(&DestructibleProp, &GlobalTransform)>,
+    mut broken: EventWriter<PropBrokenEvent>,
+) {
+    let mut broken_now = Vec::new();
+    for impact in impacts.read() {
+        // The normal is the force on entity1, debris goes the way the prop was pushed
+        let hits = [(impact.entity1, impact.normal), (impact.entity2, -impact.normal)];
+        for (entity, push) in hits {
+            let Ok((prop, transform)) = props.get(entity) else {
+                continue;
+            };
+            if impact.impulse < settings.break_impulse(prop.kind) || broken_now.contains(&entity) {
+                continue;
+            }
+            broken_now.push(entity);
+
+            let (_, rotation, translation) = transform.to_scale_rotation_translation();
+            let speed = settings.debris_speed * (0.5 + impact.intensity * 0.5);
+            for piece in prop_pieces(prop.kind, prop.length) {
+                let position = translation + rotation * piece.offset;
+                // Pieces near the hit fly furthest, a little upward so they tumble
+                let falloff = 1.0 / (1.0 + position.distance(impact.position));
+                let velocity = (push + Vec3::Y * 0.3).normalize_or_zero() * speed * (0.3 + falloff * 0.7);
+                let debris = pool.acquire(&mut commands);
+                commands.entity(debris).insert((
+                    PbrBundle {
+                        mesh: assets.cube.clone(),
+                        material: assets.material(&piece),
+                        // Rapier scales the unit collider along with the mesh
+                        transform: Transform::from_translation(position)
+                            .with_rotation(rotation)
+                            .with_scale(piece.half_size * 2.0),
+                        ..default()
+                    },
+                    RigidBody::Dynamic,
+                    Collider::cuboid(0.5, 0.5, 0.5),
+                    ColliderMassProperties::Density(600.0),
+                    Velocity::linear(velocity),
+                    SurfaceMaterial::Wood,
+                    PropDebris { remaining: settings.debris_lifetime },
+                ));
+            }
+
+            broken.send(PropBrokenEvent {
+                id: prop.id.clone(),
+                kind: prop.kind,
+                position: impact.position,
+                intensity: impact.intensity,
+            });
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Recycles debris that has lain around long enough, or despawns it when the pool is full
+fn expire_debris(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut pool: ResMut<EntityPool<PooledPropDebris>>,
+    mut debris: Query<(Entity, &mut PropDebris)>,
+) {
+    for (entity, mut piece) in debris.iter_mut() {
+        piece.remaining -= time.delta_seconds();
+        if piece.remaining > 0.0 {
+            continue;
+        }
+        if pool.release(entity) {
+            commands
+                .entity(entity)
+                .remove::<(PropDebris, RigidBody, Collider, ColliderMassProperties, Velocity)>()
+                .insert(Visibility::Hidden);
+        } else {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Remembers broken props in the save while in free roam
+fn record_broken_props(
+    mut broken: EventReader<PropBrokenEvent>,
+    settings: Res<PropSettings>,
+    mode: Option<Res<State<GameMode>>>,
+    progress: Option<ResMut<GameProgress>>,
+) {
+    let Some(mut progress) = progress.filter(|_| persisting(&settings, mode.as_deref())) else {
+        broken.clear();
+        return;
+    };
+    for event in broken.read() {
+        if !progress.destroyed_props.contains(&event.id) {
+            progress.destroyed_props.push(event.id.clone());
+        }
+    }
+}
+
+/// Plugin for destructible level props
+pub struct PropPlugin;
+
+impl Plugin for PropPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PropAssets>()
+            .init_resource::<PropSettings>()
+            .insert_resource(EntityPool::<PooledPropDebris>::new(16, 64))
+            .init_asset::<LevelProps>()
+            .init_asset_loader::<LevelPropsLoader>()
+            .add_event::<ImpactEvent>()
+            .add_event::<PropBrokenEvent>()
+            .add_systems(Startup, (setup_prop_assets, warm_pool::<PooledPropDebris>))
+            .add_systems(Update, (spawn_level_props, break_props, expire_debris, record_broken_props).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fence_pieces_span_its_length() {
+        let pieces = prop_pieces(PropKind::Fence, 7.0);
+        // 4 spans of 1.75 m, 5 posts and 2 rails per span
+        assert_eq!(pieces.len(), 5 + 4 * 2);
+        let (center, half_size) = bounds(&pieces);
+        assert!((half_size.x - (3.5 + 0.06)).abs() < 1e-4);
+        assert!(center.x.abs() < 1e-4);
+        assert!(pieces.iter().all(|piece| piece.offset.y - piece.half_size.y >= -1e-4));
+        assert!(pieces.iter().all(|piece| !piece.leaves));
+
+        let sapling = prop_pieces(PropKind::Sapling, 4.0);
+        assert_eq!(sapling.iter().filter(|piece| piece.leaves).count(), 1);
+    }
+
+    #[test]
+    fn test_gates_are_tougher_than_fences() {
+        let settings = PropSettings::default();
+        assert!(settings.break_impulse(PropKind::Sapling) < settings.break_impulse(PropKind::Fence));
+        assert!(settings.break_impulse(PropKind::Fence) < settings.break_impulse(PropKind::Gate));
+    }
+
+    #[test]
+    fn test_broken_props_persist_only_in_free_roam() {
+        let mut app = App::new();
+        app.init_resource::<PropSettings>()
+            .init_resource::<GameProgress>()
+            .add_state::<GameMode>()
+            .add_event::<PropBrokenEvent>()
+            .add_systems(Update, record_broken_props);
+        let broken = PropBrokenEvent {
+            id: "ranch_gate".to_string(),
+            kind: PropKind::Gate,
+            position: Vec3::ZERO,
+            intensity: 1.0,
+        };
+
+        app.world.send_event(broken.clone());
+        app.update();
+        assert!(app.world.resource::<GameProgress>().destroyed_props.is_empty());
+
+        app.world.resource_mut::<NextState<GameMode>>().set(GameMode::FreeRoam);
+        app.update();
+        app.world.send_event(broken.clone());
+        app.world.send_event(broken);
+        app.update();
+        assert_eq!(app.world.resource::<GameProgress>().destroyed_props, vec!["ranch_gate".to_string()]);
+    }
+}
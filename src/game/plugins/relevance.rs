@@ -0,0 +1,265 @@
+use bevy::audio::{AudioSinkPlayback, SpatialAudioSink};
+use bevy::prelude::*;
+use bevy::render::primitives::{Frustum, Sphere};
+use bevy::render::view::VisibilitySystems;
+use bevy::transform::TransformSystem;
+
+/// How much an entity matters to the players this frame, from its distance to the nearest camera
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum RelevanceBucket {
+    #[default]
+    Near,
+    Medium,
+    Far,
+    /// Too far away for anyone to notice, skip it entirely
+    Culled,
+}
+
+impl RelevanceBucket {
+    /// Run every n-th frame, `None` when culled
+    pub fn update_stride(self) -> Option<u32> {
+        match self {
+            RelevanceBucket::Near => Some(1),
+            RelevanceBucket::Medium => Some(2),
+            RelevanceBucket::Far => Some(4),
+            RelevanceBucket::Culled => None,
+        }
+    }
+
+    /// Multiplier on particle spawn rates
+    pub fn particle_spawn_scale(self) -> f32 {
+        match self {
+            RelevanceBucket::Near => 1.0,
+            RelevanceBucket::Medium => 0.5,
+            RelevanceBucket::Far => 0.2,
+            RelevanceBucket::Culled => 0.0,
+        }
+    }
+
+    fn further(self) -> Self {
+        match self {
+            RelevanceBucket::Near => RelevanceBucket::Medium,
+            RelevanceBucket::Medium => RelevanceBucket::Far,
+            RelevanceBucket::Far | RelevanceBucket::Culled => RelevanceBucket::Culled,
+        }
+    }
+}
+
+/// Distance bucket and visibility of an entity, refreshed once per frame
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Relevance {
+    pub bucket: RelevanceBucket,
+    /// Distance to the nearest active camera
+    pub distance: f32,
+    /// Inside at least one camera's frustum
+    pub in_view: bool,
+    /// Bounding radius used for the frustum test
+    pub radius: f32,
+}
+
+impl Default for Relevance {
+    fn default() -> Self {
+        // Fully relevant until the first update so nothing is skipped on spawn
+        Self {
+            bucket: RelevanceBucket::Near,
+            distance: 0.0,
+            in_view: true,
+            radius: 1.0,
+        }
+    }
+}
+
+impl Relevance {
+    pub fn with_radius(radius: f32) -> Self {
+        Self { radius, ..default() }
+    }
+
+    /// Bucket for things that only matter when seen, off screen counts one bucket further away
+    pub fn visual_bucket(&self) -> RelevanceBucket {
+        if self.in_view {
+            self.bucket
+        } else {
+            self.bucket.further()
+        }
+    }
+
+    /// Whether a throttled system should process this entity on `frame`,
+    /// returns the number of frames of time to simulate
+    pub fn stride_on(&self, frame: u32, entity: Entity) -> Option<u32> {
+        let stride = self.visual_bucket().update_stride()?;
+        // Offset by entity so throttled work spreads across frames instead of spiking
+        ((frame + entity.index()) % stride == 0).then_some(stride)
+    }
+}
+
+/// Distance thresholds between buckets in meters
+#[derive(Resource, Debug, Clone)]
+pub struct RelevanceSettings {
+    pub near_distance: f32,
+    pub medium_distance: f32,
+    pub far_distance: f32,
+}
+
+impl Default for RelevanceSettings {
+    fn default() -> Self {
+        Self {
+            near_distance: 40.0,
+            medium_distance: 100.0,
+            far_distance: 250.0,
+        }
+    }
+}
+
+impl RelevanceSettings {
+    pub fn bucket_for(&self, distance: f32) -> RelevanceBucket {
+        if distance <= self.near_distance {
+            RelevanceBucket::Near
+        } else if distance <= self.medium_distance {
+            RelevanceBucket::Medium
+        } else if distance <= self.far_distance {
+            RelevanceBucket::Far
+        } else {
+            RelevanceBucket::Culled
+        }
+    }
+}
+
+/// Gives new entities with `T` a [`Relevance`] so gameplay systems can filter on it
+pub fn track_relevance<T: Component>(
+    mut commands: Commands,
+    added: Query<Entity, (Added<T>, Without<Relevance>)>,
+) {
+    for entity in added.iter() {
+        commands.entity(entity).insert(Relevance::default());
+    }
+}
+
+/// Buckets every tracked entity against the active cameras, split screen uses the closest player
+fn update_relevance(
+    settings: Res<RelevanceSettings>,
+    cameras: Query<(&Camera, &GlobalTransform, &Frustum)>,
+    mut entities: Query<(&mut Relevance, &GlobalTransform)>,
+) {
+    let cameras: Vec<_> = cameras
+        .iter()
+        .filter(|(camera, _, _)| camera.is_active)
+        .map(|(_, transform, frustum)| (transform.translation(), frustum))
+        .collect();
+    if cameras.is_empty() {
+        return;
+    }
+
+    entities.par_iter_mut().for_each(|(mut relevance, transform)| {
+        let position = transform.translation();
+        let distance = cameras
+            .iter()
+            .map(|(camera, _)| camera.distance(position))
+            .fold(f32::INFINITY, f32::min);
+        let sphere = Sphere {
+            center: position.into(),
+            radius: relevance.radius,
+        };
+        let in_view = cameras
+            .iter()
+            .any(|(_, frustum)| frustum.intersects_sphere(&sphere, true));
+        let bucket = settings.bucket_for(distance);
+
+        // Distance changes every frame, only touch the component when a consumer would notice
+        if relevance.bucket != bucket || relevance.in_view != in_view {
+            relevance.bucket = bucket;
+            relevance.in_view = in_view;
+        }
+        relevance.bypass_change_detection().distance = distance;
+    });
+}
+
+/// Marks a sound paused by culling so it isn't confused with one paused by gameplay
+#[derive(Component)]
+pub struct AudioCulled;
+
+/// Pauses spatial sounds nobody is close enough to hear and resumes them on approach
+fn cull_spatial_audio(
+    mut commands: Commands,
+    sounds: Query<(Entity, &Relevance, &SpatialAudioSink, Has<AudioCulled>), Changed<Relevance>>,
+) {
+    // Sound carries behind the camera, so audio goes by distance alone
+    for (entity, relevance, sink, culled) in sounds.iter() {
+        let audible = relevance.bucket != RelevanceBucket::Culled;
+        if !audible && !culled && !sink.is_paused() {
+            sink.pause();
+            commands.entity(entity).insert(AudioCulled);
+        } else if audible && culled {
+            sink.play();
+            commands.entity(entity).remove::<AudioCulled>();
+        }
+    }
+}
+
+/// Plugin that keeps [`Relevance`] up to date and culls far away audio
+pub struct RelevancePlugin;
+
+impl Plugin for RelevancePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RelevanceSettings>()
+            .add_systems(Update, (
+                track_relevance::<SpatialAudioSink>,
+                cull_spatial_audio,
+            ))
+            .add_systems(
+                PostUpdate,
+                update_relevance
+                    .after(TransformSystem::TransformPropagate)
+                    .after(VisibilitySystems::UpdatePerspectiveFrusta),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buckets_by_distance() {
+        let settings = RelevanceSettings::default();
+        assert_eq!(settings.bucket_for(10.0), RelevanceBucket::Near);
+        assert_eq!(settings.bucket_for(80.0), RelevanceBucket::Medium);
+        assert_eq!(settings.bucket_for(200.0), RelevanceBucket::Far);
+        assert_eq!(settings.bucket_for(1000.0), RelevanceBucket::Culled);
+    }
+
+    #[test]
+    fn test_off_screen_counts_further_away() {
+        let relevance = Relevance {
+            bucket: RelevanceBucket::Near,
+            in_view: false,
+            ..default()
+        };
+        assert_eq!(relevance.visual_bucket(), RelevanceBucket::Medium);
+        let far_behind = Relevance {
+            bucket: RelevanceBucket::Far,
+            in_view: false,
+            ..default()
+        };
+        assert_eq!(far_behind.visual_bucket(), RelevanceBucket::Culled);
+    }
+
+    #[test]
+    fn test_stride_spreads_work_and_skips_culled() {
+        let relevance = Relevance {
+            bucket: RelevanceBucket::Far,
+            ..default()
+        };
+        let entity = Entity::from_raw(3);
+        let runs: Vec<_> = (0..8).filter_map(|frame| relevance.stride_on(frame, entity)).collect();
+        assert_eq!(runs, vec![4, 4]);
+        assert_eq!(relevance.stride_on(1, entity), Some(4));
+
+        let culled = Relevance {
+            bucket: RelevanceBucket::Culled,
+            ..default()
+        };
+        assert!((0..8).all(|frame| culled.stride_on(frame, entity).is_none()));
+        // Near entities run every frame
+        assert!((0..8).all(|frame| Relevance::default().stride_on(frame, entity) == Some(1)));
+    }
+}
@@ -0,0 +1,223 @@
+//! Waypoint routing over the terrain's drivability grid
+//!
+//! A [`RouteToEvent`] plans a route for a vehicle with [`DrivabilityMap::route`] and gives it a
+//! [`VehicleRoute`]. Waypoints are ticked off as the vehicle reaches them, and vehicles with an
+//! [`AiDriver`] steer along theirs, easing off over steep cells.
+
+use bevy::prelude::*;
+
+use super::floating_origin::{OriginShiftEvent, ShiftOrigin};
+use crate::game::vehicle::{AiDriver, Brakes, Engine, Steering, Vehicle};
+use crate::terrain::{Drivability, DrivabilityMap, DrivabilitySettings};
+
+/// How AI drivers follow their routes
+#[derive(Resource, Debug, Clone)]
+pub struct RoutingSettings {
+    /// A waypoint counts as reached this close, in meters across the ground
+    pub arrive_distance: f32,
+    /// Throttle on drivable ground
+    pub cruise_throttle: f32,
+    /// Throttle over steep ground and water
+    pub crawl_throttle: f32,
+    /// Speed in meters per second AI drivers brake down to on steep ground
+    pub crawl_speed: f32,
+}
+
+impl Default for RoutingSettings {
+    fn default() -> Self {
+        Self {
+            arrive_distance: 4.0,
+            cruise_throttle: 0.6,
+            crawl_throttle: 0.3,
+            crawl_speed: 4.0,
+        }
+    }
+}
+
+/// Plans a route for `vehicle` to `destination`, replacing any route it has
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RouteToEvent {
+    pub vehicle: Entity,
+    pub destination: Vec3,
+}
+
+/// Sent when there's no way to a destination over the loaded terrain
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RouteFailedEvent {
+    pub vehicle: Entity,
+    pub destination: Vec3,
+}
+
+/// Sent when a vehicle reaches the end of its route, which is then taken off it
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RouteFinishedEvent {
+    pub vehicle: Entity,
+}
+
+/// Waypoints a vehicle is following
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct VehicleRoute {
+    pub destination: Vec3,
+    pub waypoints: Vec<Vec3>,
+    /// Index of the waypoint being driven to
+    pub next: usize,
+}
+
+impl VehicleRoute {
+    pub fn next_waypoint(&self) -> Option<Vec3> {
+        self.waypoints.get(self.next).copied()
+    }
+
+    /// Moves on past every waypoint within `distance` of `position`, true once the last one is reached
+    pub fn advance(&mut self, position: Vec3, distance: f32) -> bool {
+        while let Some(waypoint) = self.next_waypoint() {
+            if waypoint.xz().distance(position.xz()) > distance {
+                return false;
+            }
+            self.next += 1;
+        }
+        true
+    }
+}
+
+/// Steering (-1.0 right to 1.0 left) turning a vehicle at `transform` towards `target`,
+/// at full lock once the target is `max_angle` radians or more off the nose
+pub fn steer_towards(transform: &GlobalTransform, target: Vec3, max_angle: f32) -> f32 {
+    let local = transform.affine().inverse().transform_point3(target);
+    // Forward is -Z and left is -X
+    let angle = (-local.x).atan2(-local.z);
+    (angle / max_angle.max(1e-3)).clamp(-1.0, 1.0)
+}
+
+fn plan_routes(
+    mut commands: Commands,
+    mut requests: EventReader<RouteToEvent>,
+    mut failed: EventWriter<RouteFailedEvent>,
+    map: Res<DrivabilityMap>,
+    settings: Res<DrivabilitySettings>,
+    vehicles: Query<&GlobalTransform, With<Vehicle>>,
+) {
+    for request in requests.read() {
+        let Ok(transform) = vehicles.get(request.vehicle) else {
+            continue;
+        };
+        match map.route(transform.translation(), request.destination, &settings) {
+            Some(waypoints) => {
+                commands.entity(request.vehicle).insert(VehicleRoute {
+                    destination: request.destination,
+                    waypoints,
+                    next: 0,
+                });
+            }
+            None => {
+                commands.entity(request.vehicle).remove::<VehicleRoute>();
+                failed.send(RouteFailedEvent { vehicle: request.vehicle, destination: request.destination });
+            }
+        }
+    }
+}
+
+fn advance_routes(
+    mut commands: Commands,
+    settings: Res<RoutingSettings>,
+    mut vehicles: Query<(Entity, &GlobalTransform, &mut VehicleRoute)>,
+    mut finished: EventWriter<RouteFinishedEvent>,
+) {
+    for (entity, transform, mut route) in vehicles.iter_mut() {
+        if route.advance(transform.translation(), settings.arrive_distance) {
+            commands.entity(entity).remove::<VehicleRoute>();
+            finished.send(RouteFinishedEvent { vehicle: entity });
+        }
+    }
+}
+
+/// Drives AI vehicles along their routes, they stop where they have none
+fn drive_ai_routes(
+    settings: Res<RoutingSettings>,
+    map: Res<DrivabilityMap>,
+    drivability: Res<DrivabilitySettings>,
+    mut vehicles: Query<
+        (&GlobalTransform, &Vehicle, &mut Engine, &mut Steering, &mut Brakes, Option<&VehicleRoute>),
+        With<AiDriver>,
+    >,
+) {
+    for (transform, vehicle, mut engine, mut steering, mut brakes, route) in vehicles.iter_mut() {
+        let Some(waypoint) = route.and_then(VehicleRoute::next_waypoint) else {
+            engine.throttle = 0.0;
+            brakes.brake = 1.0;
+            steering.angle = 0.0;
+            continue;
+        };
+
+        let max_angle = vehicle.config.max_steering_angle;
+        steering.angle = steer_towards(transform, waypoint, max_angle) * max_angle;
+        let crawling = map.drivability_at(transform.translation(), &drivability) != Some(Drivability::Drivable);
+        if crawling && vehicle.vehicle_speed.abs() > settings.crawl_speed {
+            engine.throttle = 0.0;
+            brakes.brake = 0.5;
+        } else {
+            engine.throttle = if crawling { settings.crawl_throttle } else { settings.cruise_throttle };
+            brakes.brake = 0.0;
+        }
+    }
+}
+
+/// Moves routes being driven along with the world origin
+fn rebase_routes(mut shifts: EventReader<OriginShiftEvent>, mut routes: Query<&mut VehicleRoute>) {
+    for shift in shifts.read() {
+        for mut route in routes.iter_mut() {
+            route.destination -= shift.translation();
+            for waypoint in &mut route.waypoints {
+                *waypoint -= shift.translation();
+            }
+        }
+    }
+}
+
+/// Plugin for routing vehicles over the terrain
+pub struct RoutingPlugin;
+
+impl Plugin for RoutingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RoutingSettings>()
+            .init_resource::<DrivabilityMap>()
+            .init_resource::<DrivabilitySettings>()
+            .add_event::<RouteToEvent>()
+            .add_event::<RouteFailedEvent>()
+            .add_event::<RouteFinishedEvent>()
+            .add_event::<OriginShiftEvent>()
+            .add_systems(Update, (plan_routes, advance_routes, drive_ai_routes).chain())
+            .add_systems(PostUpdate, rebase_routes.after(ShiftOrigin));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_advances_past_reached_waypoints() {
+        let mut route = VehicleRoute {
+            destination: Vec3::new(30.0, 0.0, 0.0),
+            waypoints: vec![Vec3::new(10.0, 0.0, 0.0), Vec3::new(20.0, 0.0, 0.0), Vec3::new(30.0, 0.0, 0.0)],
+            next: 0,
+        };
+        assert!(!route.advance(Vec3::ZERO, 4.0));
+        assert_eq!(route.next, 0);
+        // Height doesn't count
+        assert!(!route.advance(Vec3::new(9.0, 5.0, 0.0), 4.0));
+        assert_eq!(route.next_waypoint(), Some(Vec3::new(20.0, 0.0, 0.0)));
+        assert!(route.advance(Vec3::new(30.0, 0.0, 1.0), 4.0));
+        assert_eq!(route.next_waypoint(), None);
+    }
+
+    #[test]
+    fn test_steering_turns_towards_the_target() {
+        let transform = GlobalTransform::IDENTITY;
+        assert_eq!(steer_towards(&transform, Vec3::new(0.0, 0.0, -10.0), 0.6), 0.0);
+        assert!(steer_towards(&transform, Vec3::new(-10.0, 0.0, -10.0), 0.6) > 0.0);
+        assert!(steer_towards(&transform, Vec3::new(10.0, 0.0, -10.0), 0.6) < 0.0);
+        // Behind is full lock
+        assert_eq!(steer_towards(&transform, Vec3::new(1.0, 0.0, 10.0), 0.6), -1.0);
+    }
+}
@@ -0,0 +1,288 @@
+//! Gamepad rumble, the output side of controller input
+//!
+//! Players driving with a controller feel their vehicle through the pad's two motors: rough ground at
+//! speed, jolts from impacts, the engine idling and the winch straining against a stuck vehicle. Each
+//! source is a layer, and layers are mixed by priority so a jolt cuts through the idle buzz instead of
+//! stacking on top of it. Steering wheels play their own force feedback and are left alone here.
+
+use std::time::Duration;
+
+use bevy::input::gamepad::{GamepadRumbleIntensity, GamepadRumbleRequest};
+use bevy::prelude::*;
+
+use super::impacts::ImpactEvent;
+use super::split_screen::{PlayerInput, PlayerInputDevice};
+use super::steering_wheel::{surface_rumble, SteeringWheelDevice};
+use crate::game::vehicle::{Drivetrain, Engine, RecoveryGear, Vehicle, WheelHub, Winch};
+use crate::game::GameSettings;
+
+/// Rumble requests are refreshed this often, each lasting until the next
+const RUMBLE_INTERVAL: f32 = 0.1;
+
+/// Where a rumble layer comes from, lowest priority first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RumbleSource {
+    Engine,
+    Surface,
+    Winch,
+    Impact,
+}
+
+/// Strength of the strong (low frequency) and weak (high frequency) motors, 0.0 - 1.0 each
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RumbleMotors {
+    pub strong: f32,
+    pub weak: f32,
+}
+
+impl RumbleMotors {
+    pub const OFF: Self = Self { strong: 0.0, weak: 0.0 };
+
+    pub fn new(strong: f32, weak: f32) -> Self {
+        Self {
+            strong: strong.clamp(0.0, 1.0),
+            weak: weak.clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn scaled(self, scale: f32) -> Self {
+        Self::new(self.strong * scale, self.weak * scale)
+    }
+
+    pub fn is_off(&self) -> bool {
+        self.strong <= 0.0 && self.weak <= 0.0
+    }
+}
+
+/// Mixes rumble layers into one output. Higher priority layers go first and duck the ones below them,
+/// a layer only adds to the headroom left over and less the more is already playing.
+pub fn mix_rumble(layers: &[(RumbleSource, RumbleMotors)]) -> RumbleMotors {
+    let mut layers = layers.to_vec();
+    layers.sort_by(|a, b| b.0.cmp(&a.0));
+    layers.iter().fold(RumbleMotors::OFF, |mixed, (_, layer)| {
+        let layer = RumbleMotors::new(layer.strong, layer.weak);
+        RumbleMotors {
+            strong: mixed.strong + layer.strong * (1.0 - mixed.strong).powi(2),
+            weak: mixed.weak + layer.weak * (1.0 - mixed.weak).powi(2),
+        }
+    })
+}
+
+/// Strength of the winch pulse (0.0 - 1.0) at a point in its cycle, a short thump each turn of the drum
+pub fn winch_pulse(phase: f32) -> f32 {
+    let phase = phase.rem_euclid(1.0);
+    if phase < 0.25 {
+        (phase * 4.0 * std::f32::consts::PI).sin()
+    } else {
+        0.0
+    }
+}
+
+/// Idle vibration (0.0 - 1.0) of a running engine, strongest at idle and gone once the engine revs
+/// or the vehicle is moving and the ground takes over
+pub fn engine_idle(rpm: f32, idle_rpm: f32, speed: f32) -> f32 {
+    if idle_rpm <= 0.0 || rpm <= 0.0 {
+        return 0.0;
+    }
+    let revving = ((rpm - idle_rpm) / idle_rpm).clamp(0.0, 1.0);
+    (1.0 - revving) * (1.0 - speed.abs() / 3.0).clamp(0.0, 1.0)
+}
+
+/// Gains per rumble source, on top of the player's rumble intensity setting
+#[derive(Resource, Debug, Clone)]
+pub struct RumbleSettings {
+    pub surface_gain: f32,
+    pub impact_gain: f32,
+    pub engine_gain: f32,
+    pub winch_gain: f32,
+    /// Winch pulses per second while reeling in
+    pub winch_pulse_rate: f32,
+    /// Jolt lost per second
+    pub jolt_decay: f32,
+}
+
+impl Default for RumbleSettings {
+    fn default() -> Self {
+        Self {
+            surface_gain: 0.6,
+            impact_gain: 1.0,
+            engine_gain: 0.1,
+            winch_gain: 0.5,
+            winch_pulse_rate: 2.5,
+            jolt_decay: 4.0,
+        }
+    }
+}
+
+/// Rumble of a controller player's vehicle
+#[derive(Component, Debug, Clone, Default)]
+pub struct Rumble {
+    /// Kick from the last impact (0.0 - 1.0), decays quickly
+    pub jolt: f32,
+    /// Mixed output sent to the pad
+    pub motors: RumbleMotors,
+    winch_phase: f32,
+}
+
+/// Works out each controller player's rumble layers and mixes them
+#[allow(clippy::type_complexity)]
+fn update_rumble(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<RumbleSettings>,
+    mut impacts: EventReader<ImpactEvent>,
+    hubs: Query<&WheelHub>,
+    mut vehicles: Query<(
+        Entity,
+        &Vehicle,
+        &PlayerInputDevice,
+        &PlayerInput,
+        Option<&Engine>,
+        Option<&Drivetrain>,
+        Option<(&Winch, &RecoveryGear)>,
+        Option<&mut Rumble>,
+    )>,
+) {
+    let dt = time.delta_seconds();
+    let impacts: Vec<_> = impacts.read().collect();
+    for (entity, vehicle, device, input, engine, drivetrain, winch, rumble) in vehicles.iter_mut() {
+        if *device != PlayerInputDevice::Gamepad {
+            continue;
+        }
+        let Some(mut rumble) = rumble else {
+            commands.entity(entity).insert(Rumble::default());
+            continue;
+        };
+
+        rumble.jolt = (rumble.jolt - settings.jolt_decay * dt).max(0.0);
+        for impact in impacts.iter().filter(|impact| impact.involves(entity)) {
+            rumble.jolt = rumble.jolt.max(impact.intensity);
+        }
+
+        let suspension: Vec<_> = hubs
+            .iter_many(vehicle.wheel_entities)
+            .filter(|hub| hub.state.ground_contact)
+            .map(|hub| hub.state.velocity)
+            .collect();
+        let surface = surface_rumble(&suspension, vehicle.vehicle_speed) * settings.surface_gain;
+
+        let running = drivetrain.map_or(true, |drivetrain| drivetrain.running);
+        let idle = match engine {
+            Some(engine) if running => {
+                engine_idle(engine.rpm, vehicle.config.engine_config.idle_rpm, vehicle.vehicle_speed)
+            }
+            _ => 0.0,
+        };
+
+        let straining = input.winch && winch.map_or(false, |(_, gear)| gear.strapped_to.is_some());
+        let strain = if straining {
+            rumble.winch_phase = (rumble.winch_phase + settings.winch_pulse_rate * dt).fract();
+            winch_pulse(rumble.winch_phase) * settings.winch_gain
+        } else {
+            rumble.winch_phase = 0.0;
+            0.0
+        };
+
+        let jolt = rumble.jolt * settings.impact_gain;
+        rumble.motors = mix_rumble(&[
+            (RumbleSource::Impact, RumbleMotors::new(jolt, jolt * 0.5)),
+            (RumbleSource::Winch, RumbleMotors::new(strain, 0.0)),
+            (RumbleSource::Surface, RumbleMotors::new(surface * 0.3, surface)),
+            (RumbleSource::Engine, RumbleMotors::new(0.0, idle * settings.engine_gain)),
+        ]);
+    }
+}
+
+/// Plays the mixed rumble on the controller, scaled by the player's rumble intensity
+fn send_rumble(
+    time: Res<Time>,
+    game_settings: Option<Res<GameSettings>>,
+    gamepads: Res<Gamepads>,
+    wheel: Res<SteeringWheelDevice>,
+    rumbles: Query<&Rumble>,
+    mut since_last: Local<f32>,
+    mut requests: EventWriter<GamepadRumbleRequest>,
+) {
+    *since_last += time.delta_seconds();
+    if *since_last < RUMBLE_INTERVAL {
+        return;
+    }
+    *since_last = 0.0;
+
+    // Controller players share the first pad that isn't the wheel, like their input
+    let Some(gamepad) = gamepads.iter().find(|gamepad| Some(*gamepad) != wheel.0) else {
+        return;
+    };
+    let intensity = game_settings.map_or(1.0, |settings| settings.accessibility.rumble_intensity);
+    let motors = rumbles
+        .iter()
+        .map(|rumble| rumble.motors)
+        .fold(RumbleMotors::OFF, |a, b| RumbleMotors::new(a.strong.max(b.strong), a.weak.max(b.weak)))
+        .scaled(intensity);
+    if motors.is_off() {
+        return;
+    }
+    requests.send(GamepadRumbleRequest::Add {
+        gamepad,
+        duration: Duration::from_secs_f32(RUMBLE_INTERVAL),
+        intensity: GamepadRumbleIntensity {
+            strong_motor: motors.strong,
+            weak_motor: motors.weak,
+        },
+    });
+}
+
+/// Plugin for controller rumble
+pub struct RumblePlugin;
+
+impl Plugin for RumblePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RumbleSettings>()
+            .add_systems(Update, (update_rumble, send_rumble).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_higher_priority_layers_take_headroom_first() {
+        assert_eq!(mix_rumble(&[]), RumbleMotors::OFF);
+        let full = mix_rumble(&[
+            (RumbleSource::Engine, RumbleMotors::new(0.5, 0.5)),
+            (RumbleSource::Impact, RumbleMotors::new(1.0, 1.0)),
+        ]);
+        assert_eq!(full, RumbleMotors::new(1.0, 1.0));
+
+        // A light jolt ducks heavy ground rumble, heavy ground rumble doesn't swallow a light winch pulse
+        let jolt = mix_rumble(&[
+            (RumbleSource::Surface, RumbleMotors::new(1.0, 0.0)),
+            (RumbleSource::Impact, RumbleMotors::new(0.2, 0.0)),
+        ]);
+        assert!((jolt.strong - 0.84).abs() < 1e-5);
+        assert_eq!(jolt.weak, 0.0);
+        let strain = mix_rumble(&[
+            (RumbleSource::Winch, RumbleMotors::new(0.2, 0.0)),
+            (RumbleSource::Engine, RumbleMotors::new(1.0, 0.0)),
+        ]);
+        assert!((strain.strong - 0.84).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_idle_fades_with_revs_and_speed() {
+        assert_eq!(engine_idle(800.0, 800.0, 0.0), 1.0);
+        assert_eq!(engine_idle(0.0, 800.0, 0.0), 0.0);
+        assert_eq!(engine_idle(1600.0, 800.0, 0.0), 0.0);
+        assert_eq!(engine_idle(800.0, 800.0, 10.0), 0.0);
+        assert!(engine_idle(1000.0, 800.0, 1.0) < 1.0);
+    }
+
+    #[test]
+    fn test_winch_pulses() {
+        assert_eq!(winch_pulse(0.0), 0.0);
+        assert!((winch_pulse(0.125) - 1.0).abs() < 1e-5);
+        assert_eq!(winch_pulse(0.5), 0.0);
+        assert_eq!(winch_pulse(1.125), winch_pulse(0.125));
+    }
+}
@@ -0,0 +1,204 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::*;
+use rhai::{Dynamic, Engine, EvalAltResult, FLOAT};
+
+use super::super::weather::Weather;
+use super::super::world_clock::OpenHours;
+
+/// What a script asked the game to do. Scripts never touch the world, the API only queues these.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptCommand {
+    AddZone { name: String, center: Vec3, radius: f32 },
+    SpawnEvent(String),
+    SetWeather(Weather),
+    UnlockVehicle(String),
+    ShowMessage { title: Option<String>, message: String },
+}
+
+/// Weather by the name scripts use for it
+pub fn weather_by_name(name: &str) -> Option<Weather> {
+    match name.to_ascii_lowercase().as_str() {
+        "clear" => Some(Weather::Clear),
+        "cloudy" => Some(Weather::Cloudy),
+        "rain" => Some(Weather::Rain),
+        "storm" => Some(Weather::Storm),
+        "fog" => Some(Weather::Fog),
+        "snow" => Some(Weather::Snow),
+        _ => None,
+    }
+}
+
+/// Commands queued by the API, the operation budget of the call in progress and the world hour
+#[derive(Debug, Clone, Default)]
+pub struct ScriptContext {
+    commands: Arc<Mutex<Vec<ScriptCommand>>>,
+    /// Operations the running call may still use
+    limit: Arc<AtomicU64>,
+    /// Operations the running call has used so far
+    used: Arc<AtomicU64>,
+    /// Hour of the day scripts see, as `f32` bits
+    hour: Arc<AtomicU32>,
+}
+
+impl ScriptContext {
+    fn push(&self, command: ScriptCommand) {
+        self.commands.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(command);
+    }
+
+    /// Takes the commands queued since the last call
+    pub fn take_commands(&self) -> Vec<ScriptCommand> {
+        std::mem::take(&mut *self.commands.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+
+    /// Lets the next call run for at most `operations`
+    pub fn start_call(&self, operations: u64) {
+        self.limit.store(operations, Ordering::Relaxed);
+        self.used.store(0, Ordering::Relaxed);
+    }
+
+    /// Operations the last call used
+    pub fn operations_used(&self) -> u64 {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// Hour of the day `world_hour()` and `is_night()` report to the next calls
+    pub fn set_world_hour(&self, hour: f32) {
+        self.hour.store(hour.to_bits(), Ordering::Relaxed);
+    }
+
+    fn world_hour(&self) -> f32 {
+        f32::from_bits(self.hour.load(Ordering::Relaxed))
+    }
+}
+
+/// Engine with the language features scripts don't get taken away and hard limits set. Used to
+/// compile level scripts as well as to run them, so banned syntax fails when the level loads.
+pub fn restricted_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .disable_symbol("eval")
+        .set_max_modules(0)
+        .set_max_call_levels(32)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(4096)
+        .set_max_array_size(1024)
+        .set_max_map_size(256);
+    engine.on_print(|text| info!("[script] {text}"));
+    engine.on_debug(|text, _, position| debug!("[script] {position:?} {text}"));
+    engine
+}
+
+/// The engine level scripts run in, with the mission API registered and every call held to the
+/// operation budget set through `context`
+pub fn mission_engine(context: &ScriptContext) -> Engine {
+    let mut engine = restricted_engine();
+
+    let limit = context.limit.clone();
+    let used = context.used.clone();
+    engine.on_progress(move |operations| {
+        used.store(operations, Ordering::Relaxed);
+        (operations > limit.load(Ordering::Relaxed)).then_some(Dynamic::UNIT)
+    });
+
+    let api = context.clone();
+    engine.register_fn("add_zone", move |name: &str, x: FLOAT, y: FLOAT, z: FLOAT, radius: FLOAT| {
+        api.push(ScriptCommand::AddZone {
+            name: name.to_string(),
+            center: Vec3::new(x as f32, y as f32, z as f32),
+            radius: radius as f32,
+        });
+    });
+    let api = context.clone();
+    engine.register_fn("spawn_event", move |name: &str| api.push(ScriptCommand::SpawnEvent(name.to_string())));
+    let api = context.clone();
+    engine.register_fn("set_weather", move |name: &str| -> Result<(), Box<EvalAltResult>> {
+        let weather = weather_by_name(name).ok_or_else(|| format!("unknown weather '{name}'"))?;
+        api.push(ScriptCommand::SetWeather(weather));
+        Ok(())
+    });
+    let api = context.clone();
+    engine.register_fn("unlock_vehicle", move |name: &str| api.push(ScriptCommand::UnlockVehicle(name.to_string())));
+    let api = context.clone();
+    engine.register_fn("show_message", move |message: &str| {
+        api.push(ScriptCommand::ShowMessage { title: None, message: message.to_string() });
+    });
+    let api = context.clone();
+    engine.register_fn("show_message", move |title: &str, message: &str| {
+        api.push(ScriptCommand::ShowMessage { title: Some(title.to_string()), message: message.to_string() });
+    });
+    let api = context.clone();
+    engine.register_fn("world_hour", move || api.world_hour() as FLOAT);
+    let api = context.clone();
+    engine.register_fn("is_night", move || OpenHours::NIGHT.contains(api.world_hour()));
+
+    engine
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rhai::EvalAltResult;
+
+    fn run(script: &str, operations: u64) -> (Result<(), Box<EvalAltResult>>, Vec<ScriptCommand>) {
+        let context = ScriptContext::default();
+        let engine = mission_engine(&context);
+        context.start_call(operations);
+        let result = engine.run(script);
+        (result, context.take_commands())
+    }
+
+    #[test]
+    fn test_api_queues_commands() {
+        let (result, commands) = run(
+            r#"
+                add_zone("ford", 12.0, 0.0, 40.0, 8.0);
+                set_weather("Storm");
+                unlock_vehicle("Ford Raptor");
+                show_message("Checkpoint", "Cross the ford");
+                spawn_event("Ridge Rockslide");
+            "#,
+            10_000,
+        );
+        assert!(result.is_ok());
+        assert_eq!(commands.len(), 5);
+        assert_eq!(commands[1], ScriptCommand::SetWeather(Weather::Storm));
+        assert_eq!(
+            commands[3],
+            ScriptCommand::ShowMessage { title: Some("Checkpoint".into()), message: "Cross the ford".into() }
+        );
+    }
+
+    #[test]
+    fn test_scripts_read_the_world_clock() {
+        let context = ScriptContext::default();
+        let engine = mission_engine(&context);
+        context.start_call(10_000);
+        context.set_world_hour(22.5);
+        assert_eq!(engine.eval::<FLOAT>("world_hour()").unwrap(), 22.5);
+        assert!(engine.eval::<bool>("is_night()").unwrap());
+        context.set_world_hour(13.0);
+        assert!(!engine.eval::<bool>("is_night()").unwrap());
+    }
+
+    #[test]
+    fn test_unknown_weather_is_a_script_error() {
+        let (result, commands) = run(r#"set_weather("hail");"#, 10_000);
+        assert!(result.is_err());
+        assert!(commands.is_empty());
+    }
+
+    #[test]
+    fn test_runaway_scripts_are_stopped() {
+        let (result, _) = run("loop { }", 1_000);
+        assert!(matches!(*result.unwrap_err(), EvalAltResult::ErrorTerminated(..)));
+    }
+
+    #[test]
+    fn test_sandbox_has_no_eval_or_imports() {
+        let engine = restricted_engine();
+        assert!(engine.compile(r#"eval("1 + 1")"#).is_err());
+        assert!(engine.run(r#"import "std" as std;"#).is_err());
+    }
+}
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use rhai::AST;
+
+use super::api::restricted_engine;
+
+/// Compiled mission script of a level, loaded from `*.mission.rhai`
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct LevelScript {
+    pub ast: Arc<AST>,
+}
+
+impl LevelScript {
+    pub fn compile(source: &str) -> Result<Self, LevelScriptError> {
+        Ok(Self {
+            ast: Arc::new(restricted_engine().compile(source)?),
+        })
+    }
+}
+
+/// Errors produced while loading level scripts
+#[derive(Debug, thiserror::Error)]
+pub enum LevelScriptError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Utf8(#[from] std::string::FromUtf8Error),
+    #[error(transparent)]
+    Parse(#[from] rhai::ParseError),
+}
+
+/// Asset loader for level scripts
+#[derive(Default)]
+pub struct LevelScriptLoader;
+
+impl AssetLoader for LevelScriptLoader {
+    type Asset = LevelScript;
+    type Settings = ();
+    type Error = LevelScriptError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<LevelScript, LevelScriptError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            LevelScript::compile(&String::from_utf8(bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["mission.rhai"]
+    }
+}
@@ -0,0 +1,407 @@
+/// Mission scripting with Rhai
+///
+/// A level's `*.mission.rhai` file holds its trigger logic. The top level of the script runs once
+/// when the level starts and sets up zones with `add_zone(name, x, y, z, radius)`. After that the
+/// game calls the functions the script defines:
+/// - `on_enter(zone)` and `on_exit(zone)` when the first vehicle enters or the last one leaves a zone
+/// - `on_update(dt)` every frame
+///
+/// Inside them `this` is a map that keeps its contents between calls, for mission progress.
+/// Scripts act on the game only through `spawn_event(name)`, `set_weather(name)`,
+/// `unlock_vehicle(name)` and `show_message([title,] text)`, which queue commands the game
+/// applies after the scripts have run. `world_hour()` and `is_night()` read the world clock, for
+/// challenges that only run at night. `eval`, imports and runaway loops are ruled out: all
+/// scripts share an operation budget per frame, and callbacks that don't fit wait for the next one.
+mod api;
+mod level;
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope, AST, FLOAT};
+
+pub use api::{mission_engine, restricted_engine, weather_by_name, ScriptCommand, ScriptContext};
+pub use level::{LevelScript, LevelScriptError, LevelScriptLoader};
+
+use super::weather::WeatherManager;
+use super::world_clock::WorldClock;
+use crate::game::states::GameProgress;
+use crate::game::vehicle::Vehicle;
+
+/// A script asked for an event by name. Level hazards with a `script` trigger fire on the event
+/// with their name.
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct ScriptEvent {
+    pub name: String,
+}
+
+/// A script asked for a message to be shown to the player
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct ScriptMessageEvent {
+    pub title: Option<String>,
+    pub message: String,
+}
+
+/// A vehicle was unlocked by a mission
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct VehicleUnlockedEvent {
+    pub name: String,
+}
+
+/// Limits on mission scripts
+#[derive(Resource, Debug, Clone)]
+pub struct ScriptSettings {
+    /// Rhai operations all scripts together may run per frame
+    pub operations_per_frame: u64,
+}
+
+impl Default for ScriptSettings {
+    fn default() -> Self {
+        Self {
+            operations_per_frame: 20_000,
+        }
+    }
+}
+
+/// The engine scripts run in, with the mission API registered
+#[derive(Resource)]
+pub struct ScriptRuntime {
+    pub engine: Engine,
+    pub context: ScriptContext,
+}
+
+impl Default for ScriptRuntime {
+    fn default() -> Self {
+        let context = ScriptContext::default();
+        Self {
+            engine: mission_engine(&context),
+            context,
+        }
+    }
+}
+
+/// A zone a script watches for vehicles
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptZone {
+    pub name: String,
+    pub center: Vec3,
+    pub radius: f32,
+    /// A vehicle is inside
+    pub occupied: bool,
+}
+
+/// Script code waiting to run
+#[derive(Debug, Clone, PartialEq)]
+enum ScriptCallback {
+    /// The top level of the script
+    Start,
+    Enter(String),
+    Exit(String),
+    /// Seconds since the last update that ran
+    Update(f32),
+}
+
+/// A running mission script
+#[derive(Component)]
+pub struct MissionScript {
+    pub ast: Arc<AST>,
+    pub zones: Vec<ScriptZone>,
+    scope: Scope<'static>,
+    /// `this` inside callbacks
+    state: Dynamic,
+    pending: VecDeque<ScriptCallback>,
+}
+
+impl MissionScript {
+    pub fn new(ast: Arc<AST>) -> Self {
+        Self {
+            ast,
+            zones: Vec::new(),
+            scope: Scope::new(),
+            state: Map::new().into(),
+            pending: VecDeque::from([ScriptCallback::Start]),
+        }
+    }
+
+    /// The mission progress scripts keep in `this`
+    pub fn state(&self) -> &Dynamic {
+        &self.state
+    }
+
+    fn defines(&self, name: &str, arity: usize) -> bool {
+        self.ast
+            .iter_functions()
+            .any(|function| function.name == name && function.params.len() == arity)
+    }
+
+    fn watch_zones(&mut self, vehicles: &[Vec3]) {
+        let (on_enter, on_exit) = (self.defines("on_enter", 1), self.defines("on_exit", 1));
+        for zone in &mut self.zones {
+            let occupied = vehicles.iter().any(|position| position.distance(zone.center) <= zone.radius);
+            if occupied == zone.occupied {
+                continue;
+            }
+            zone.occupied = occupied;
+            if occupied && on_enter {
+                self.pending.push_back(ScriptCallback::Enter(zone.name.clone()));
+            } else if !occupied && on_exit {
+                self.pending.push_back(ScriptCallback::Exit(zone.name.clone()));
+            }
+        }
+    }
+
+    fn queue_update(&mut self, dt: f32) {
+        if !self.defines("on_update", 1) {
+            return;
+        }
+        // Updates the budget held back are folded into one
+        match self.pending.iter_mut().find_map(|callback| match callback {
+            ScriptCallback::Update(pending) => Some(pending),
+            _ => None,
+        }) {
+            Some(pending) => *pending += dt,
+            None => self.pending.push_back(ScriptCallback::Update(dt)),
+        }
+    }
+
+    fn run(&mut self, engine: &Engine, callback: &ScriptCallback) -> Result<(), Box<EvalAltResult>> {
+        let options = || CallFnOptions::new().eval_ast(false);
+        match callback {
+            ScriptCallback::Start => engine.run_ast_with_scope(&mut self.scope, &self.ast),
+            ScriptCallback::Enter(zone) => engine
+                .call_fn_with_options::<Dynamic>(
+                    options().bind_this_ptr(&mut self.state),
+                    &mut self.scope,
+                    &self.ast,
+                    "on_enter",
+                    (zone.clone(),),
+                )
+                .map(drop),
+            ScriptCallback::Exit(zone) => engine
+                .call_fn_with_options::<Dynamic>(
+                    options().bind_this_ptr(&mut self.state),
+                    &mut self.scope,
+                    &self.ast,
+                    "on_exit",
+                    (zone.clone(),),
+                )
+                .map(drop),
+            ScriptCallback::Update(dt) => engine
+                .call_fn_with_options::<Dynamic>(
+                    options().bind_this_ptr(&mut self.state),
+                    &mut self.scope,
+                    &self.ast,
+                    "on_update",
+                    (*dt as FLOAT,),
+                )
+                .map(drop),
+        }
+    }
+}
+
+/// Marks level entities whose script has been started
+#[derive(Component)]
+pub struct LevelScriptSpawned;
+
+/// Starts the scripts of loaded levels as children of the level entity
+fn spawn_level_scripts(
+    mut commands: Commands,
+    levels: Query<(Entity, &Handle<LevelScript>), Without<LevelScriptSpawned>>,
+    scripts: Res<Assets<LevelScript>>,
+) {
+    for (level, handle) in levels.iter() {
+        let Some(script) = scripts.get(handle) else {
+            continue;
+        };
+        let mission = commands
+            .spawn((MissionScript::new(script.ast.clone()), Name::new("Mission script")))
+            .id();
+        commands.entity(level).add_child(mission).insert(LevelScriptSpawned);
+    }
+}
+
+/// Runs script callbacks within the frame budget and applies what they asked for
+#[allow(clippy::too_many_arguments)]
+fn run_mission_scripts(
+    time: Res<Time>,
+    settings: Res<ScriptSettings>,
+    runtime: Res<ScriptRuntime>,
+    clock: Option<Res<WorldClock>>,
+    vehicles: Query<&GlobalTransform, With<Vehicle>>,
+    mut scripts: Query<(&mut MissionScript, Option<&Name>)>,
+    mut weather: Option<ResMut<WeatherManager>>,
+    mut progress: Option<ResMut<GameProgress>>,
+    mut script_events: EventWriter<ScriptEvent>,
+    mut messages: EventWriter<ScriptMessageEvent>,
+    mut unlocks: EventWriter<VehicleUnlockedEvent>,
+) {
+    let positions: Vec<Vec3> = vehicles.iter().map(|transform| transform.translation()).collect();
+    let mut budget = settings.operations_per_frame;
+    let mut queued = Vec::new();
+    if let Some(clock) = clock {
+        runtime.context.set_world_hour(clock.hour);
+    }
+
+    for (mut script, name) in scripts.iter_mut() {
+        let label = name.map_or("mission script", |name| name.as_str());
+        script.watch_zones(&positions);
+        script.queue_update(time.delta_seconds());
+
+        while budget > 0 {
+            let Some(callback) = script.pending.pop_front() else {
+                break;
+            };
+            runtime.context.start_call(budget);
+            let result = script.run(&runtime.engine, &callback);
+            budget = budget.saturating_sub(runtime.context.operations_used());
+
+            for command in runtime.context.take_commands() {
+                match command {
+                    ScriptCommand::AddZone { name, center, radius } => script.zones.push(ScriptZone {
+                        name,
+                        center,
+                        radius,
+                        occupied: false,
+                    }),
+                    command => queued.push(command),
+                }
+            }
+            match result {
+                Ok(()) => {}
+                Err(error) if matches!(*error, EvalAltResult::ErrorTerminated(..)) => {
+                    warn!("{label} ran out of its frame budget in {callback:?} and was stopped");
+                    budget = 0;
+                }
+                Err(error) => warn!("{label} failed in {callback:?}: {error}"),
+            }
+        }
+    }
+
+    for command in queued {
+        match command {
+            ScriptCommand::SpawnEvent(name) => script_events.send(ScriptEvent { name }),
+            ScriptCommand::SetWeather(target) => {
+                if let Some(weather) = weather.as_mut() {
+                    weather.change_weather(target);
+                }
+            }
+            ScriptCommand::UnlockVehicle(name) => {
+                if let Some(progress) = progress.as_mut() {
+                    if progress.unlocked_vehicles.contains(&name) {
+                        continue;
+                    }
+                    progress.unlocked_vehicles.push(name.clone());
+                }
+                unlocks.send(VehicleUnlockedEvent { name });
+            }
+            ScriptCommand::ShowMessage { title, message } => messages.send(ScriptMessageEvent { title, message }),
+            ScriptCommand::AddZone { .. } => {}
+        }
+    }
+}
+
+/// Plugin for level mission scripts
+pub struct ScriptingPlugin;
+
+impl Plugin for ScriptingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<LevelScript>()
+            .init_asset_loader::<LevelScriptLoader>()
+            .init_resource::<ScriptSettings>()
+            .init_resource::<ScriptRuntime>()
+            .add_event::<ScriptEvent>()
+            .add_event::<ScriptMessageEvent>()
+            .add_event::<VehicleUnlockedEvent>()
+            .add_systems(Update, (spawn_level_scripts, run_mission_scripts).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_app(operations_per_frame: u64) -> App {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .insert_resource(ScriptSettings { operations_per_frame })
+            .init_resource::<ScriptRuntime>()
+            .init_resource::<GameProgress>()
+            .add_event::<ScriptEvent>()
+            .add_event::<ScriptMessageEvent>()
+            .add_event::<VehicleUnlockedEvent>()
+            .add_systems(Update, run_mission_scripts);
+        app
+    }
+
+    fn spawn_script(app: &mut App, source: &str) -> Entity {
+        let script = LevelScript::compile(source).unwrap();
+        app.world.spawn(MissionScript::new(script.ast)).id()
+    }
+
+    const FORD: &str = r#"
+        add_zone("ford", 0.0, 0.0, 20.0, 5.0);
+
+        fn on_enter(zone) {
+            this.crossings = if "crossings" in this { this.crossings + 1 } else { 1 };
+            show_message("Entered " + zone);
+            if this.crossings == 2 {
+                unlock_vehicle("Ford Raptor");
+            }
+        }
+
+        fn on_exit(zone) {
+            spawn_event("Creek flood");
+        }
+    "#;
+
+    #[test]
+    fn test_zones_call_back_with_persistent_state() {
+        let mut app = test_app(20_000);
+        let script = spawn_script(&mut app, FORD);
+        let vehicle = app.world.spawn((Vehicle::default(), GlobalTransform::default())).id();
+        app.update();
+        assert_eq!(app.world.get::<MissionScript>(script).unwrap().zones.len(), 1);
+
+        for _ in 0..2 {
+            *app.world.get_mut::<GlobalTransform>(vehicle).unwrap() = GlobalTransform::from_xyz(0.0, 0.0, 20.0);
+            app.update();
+            *app.world.get_mut::<GlobalTransform>(vehicle).unwrap() = GlobalTransform::default();
+            app.update();
+        }
+
+        let messages: Vec<String> = app
+            .world
+            .resource_mut::<Events<ScriptMessageEvent>>()
+            .drain()
+            .map(|event| event.message)
+            .collect();
+        assert_eq!(messages, ["Entered ford"; 2]);
+        assert_eq!(app.world.resource::<Events<ScriptEvent>>().len(), 2);
+        assert_eq!(app.world.resource::<GameProgress>().unlocked_vehicles, ["Ford Raptor"]);
+        let state = app.world.get::<MissionScript>(script).unwrap().state().clone_cast::<Map>();
+        assert_eq!(state["crossings"].as_int().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_budget_defers_callbacks_to_the_next_frame() {
+        let mut app = test_app(400);
+        let script = spawn_script(
+            &mut app,
+            r#"
+                fn on_update(dt) {
+                    let count = 0;
+                    while count < 50 { count += 1; }
+                    show_message("tick");
+                }
+            "#,
+        );
+        let runaway = spawn_script(&mut app, "fn on_update(dt) { loop { } }");
+        for _ in 0..4 {
+            app.update();
+        }
+        // The runaway script is stopped each time instead of hanging the frame
+        assert!(app.world.get::<MissionScript>(runaway).is_some());
+        assert!(app.world.get::<MissionScript>(script).unwrap().pending.len() <= 2);
+    }
+}
@@ -0,0 +1,98 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{Season, VegetationKind};
+use crate::assets::{check_schema_version, read_schema_version, SchemaVersionError};
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+/// A tree, bush or patch of grass as written in a level file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VegetationDesc {
+    pub kind: VegetationKind,
+    pub position: [f32; 3],
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+}
+
+/// Season and vegetation of a level, loaded from `*.season.json`
+#[derive(Asset, TypePath, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LevelSeason {
+    pub season: Season,
+    /// Snow cover the level starts with (0.0 - 1.0), before any snow falls
+    #[serde(default)]
+    pub initial_snow: f32,
+    #[serde(default)]
+    pub vegetation: Vec<VegetationDesc>,
+}
+
+/// Errors produced while loading level season files
+#[derive(Debug, thiserror::Error)]
+pub enum LevelSeasonError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Schema(#[from] SchemaVersionError),
+}
+
+/// Asset loader for level season files
+#[derive(Default)]
+pub struct LevelSeasonLoader;
+
+impl AssetLoader for LevelSeasonLoader {
+    type Asset = LevelSeason;
+    type Settings = ();
+    type Error = LevelSeasonError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<LevelSeason, LevelSeasonError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            check_schema_version(read_schema_version(&bytes)?)?;
+            Ok(serde_json::from_slice(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["season.json"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_level_season() {
+        let json = r#"{
+            "season": "winter",
+            "initial_snow": 0.6,
+            "vegetation": [
+                { "kind": "conifer", "position": [12.0, 0.0, -8.0], "scale": 1.5 },
+                { "kind": "grass", "position": [-4.0, 0.0, 2.0] }
+            ]
+        }"#;
+        let level: LevelSeason = serde_json::from_str(json).unwrap();
+        assert_eq!(level.season, Season::Winter);
+        assert_eq!(level.initial_snow, 0.6);
+        assert_eq!(level.vegetation[0].kind, VegetationKind::Conifer);
+        assert_eq!(level.vegetation[1].scale, 1.0);
+
+        let summer: LevelSeason = serde_json::from_str(r#"{ "season": "summer" }"#).unwrap();
+        assert_eq!(summer.initial_snow, 0.0);
+        assert!(summer.vegetation.is_empty());
+    }
+}
@@ -0,0 +1,396 @@
+/// Seasonal level variants: vegetation, snow cover and frozen water
+///
+/// A level's `*.season.json` file picks summer, autumn or winter and places its vegetation. The
+/// season swaps vegetation and ground colors and sets how fast snow melts, while the snow itself
+/// settles in [`WeatherManager`] during snowfall and is splatted over the terrain by its material.
+/// In winter water volumes freeze over into drivable ice with very little grip.
+mod level;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub use level::{LevelSeason, LevelSeasonError, LevelSeasonLoader, VegetationDesc};
+
+use super::impacts::SurfaceMaterial;
+use super::water::{FluidKind, FluidVolume};
+use super::weather::WeatherManager;
+use crate::terrain::{TerrainChunkManager, TerrainLayer, TerrainMaterial};
+
+/// Ice sits this far above the water it covers, so the surface doesn't show through
+const ICE_LIFT: f32 = 0.02;
+
+/// Season a level is set in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Season {
+    #[default]
+    Summer,
+    Autumn,
+    Winter,
+}
+
+impl Season {
+    pub const ALL: [Season; 3] = [Season::Summer, Season::Autumn, Season::Winter];
+
+    /// Snow cover lost per second while it isn't snowing, winter snow stays put
+    pub fn snow_melt_rate(self) -> f32 {
+        match self {
+            Season::Summer => 1.0 / 60.0,
+            Season::Autumn => 1.0 / 300.0,
+            Season::Winter => 0.0,
+        }
+    }
+
+    /// Whether still water freezes over
+    pub fn freezes_water(self) -> bool {
+        self == Season::Winter
+    }
+
+    /// Color of the grass layer, the ground under any snow
+    pub fn ground_color(self) -> Color {
+        match self {
+            Season::Summer => Color::rgb(0.3, 0.5, 0.3),
+            Season::Autumn => Color::rgb(0.42, 0.4, 0.26),
+            Season::Winter => Color::rgb(0.4, 0.39, 0.34),
+        }
+    }
+}
+
+/// Which season a level is in and how its ice behaves
+#[derive(Resource, Debug, Clone)]
+pub struct SeasonSettings {
+    pub season: Season,
+    /// Friction coefficient of frozen water
+    pub ice_friction: f32,
+    /// Thickness of the ice on frozen water in meters
+    pub ice_thickness: f32,
+}
+
+impl Default for SeasonSettings {
+    fn default() -> Self {
+        Self {
+            season: Season::Summer,
+            ice_friction: 0.05,
+            ice_thickness: 0.25,
+        }
+    }
+}
+
+/// Kinds of vegetation, each with its own look per season
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VegetationKind {
+    /// Broadleaf trees and bushes, green in summer, turning in autumn and bare in winter
+    Foliage,
+    Grass,
+    /// Evergreens, frosted in winter
+    Conifer,
+}
+
+impl VegetationKind {
+    pub const ALL: [VegetationKind; 3] = [VegetationKind::Foliage, VegetationKind::Grass, VegetationKind::Conifer];
+
+    /// Color of the vegetation in a season
+    pub fn color(self, season: Season) -> Color {
+        match (self, season) {
+            (VegetationKind::Foliage, Season::Summer) => Color::rgb(0.24, 0.45, 0.17),
+            (VegetationKind::Foliage, Season::Autumn) => Color::rgb(0.75, 0.4, 0.12),
+            (VegetationKind::Foliage, Season::Winter) => Color::rgb(0.35, 0.28, 0.2),
+            (VegetationKind::Grass, Season::Summer) => Color::rgb(0.35, 0.55, 0.2),
+            (VegetationKind::Grass, Season::Autumn) => Color::rgb(0.6, 0.55, 0.3),
+            (VegetationKind::Grass, Season::Winter) => Color::rgb(0.55, 0.52, 0.42),
+            (VegetationKind::Conifer, Season::Winter) => Color::rgb(0.32, 0.42, 0.37),
+            (VegetationKind::Conifer, _) => Color::rgb(0.12, 0.3, 0.15),
+        }
+    }
+
+    /// Vehicles go through grass but not trees
+    pub fn solid(self) -> bool {
+        self != VegetationKind::Grass
+    }
+
+    /// Mesh at scale 1, resting on the origin
+    fn mesh(self) -> (Mesh, f32) {
+        match self {
+            VegetationKind::Foliage => (shape::UVSphere { radius: 1.5, sectors: 12, stacks: 8 }.into(), 1.5),
+            VegetationKind::Grass => (shape::Box::new(1.5, 0.3, 1.5).into(), 0.15),
+            VegetationKind::Conifer => {
+                (shape::Cylinder { radius: 0.8, height: 5.0, resolution: 10, segments: 1 }.into(), 2.5)
+            }
+        }
+    }
+}
+
+/// A tree, bush or patch of grass that changes with the season
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Vegetation {
+    pub kind: VegetationKind,
+}
+
+/// Ice covering a frozen water volume, a child of the water body
+#[derive(Component, Debug, Clone, Copy)]
+pub struct IceSheet;
+
+/// Marks level entities whose season has been applied and vegetation spawned
+#[derive(Component)]
+pub struct LevelSeasonSpawned;
+
+/// Shared vegetation meshes, and materials for every kind in every season
+#[derive(Resource, Default)]
+pub struct SeasonalVegetation {
+    meshes: HashMap<VegetationKind, (Handle<Mesh>, f32)>,
+    materials: HashMap<(VegetationKind, Season), Handle<StandardMaterial>>,
+}
+
+impl SeasonalVegetation {
+    /// Spawns a plant dressed for `season` standing at `ground`, `None` until the meshes are set up
+    pub fn spawn(
+        &self,
+        commands: &mut Commands,
+        kind: VegetationKind,
+        season: Season,
+        ground: Vec3,
+        scale: f32,
+    ) -> Option<Entity> {
+        let (mesh, rest_height) = self.meshes.get(&kind)?;
+        let material = self.materials.get(&(kind, season))?;
+        let mut plant = commands.spawn((
+            PbrBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                transform: Transform::from_translation(ground + Vec3::Y * rest_height * scale)
+                    .with_scale(Vec3::splat(scale)),
+                ..default()
+            },
+            Vegetation { kind },
+            Name::new("Vegetation"),
+        ));
+        if kind.solid() {
+            plant.insert((RigidBody::Fixed, Collider::cylinder(*rest_height, 0.3)));
+        }
+        Some(plant.id())
+    }
+}
+
+fn setup_seasonal_vegetation(
+    mut vegetation: ResMut<SeasonalVegetation>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for kind in VegetationKind::ALL {
+        let (mesh, rest_height) = kind.mesh();
+        vegetation.meshes.insert(kind, (meshes.add(mesh), rest_height));
+        for season in Season::ALL {
+            let material = materials.add(StandardMaterial {
+                base_color: kind.color(season),
+                perceptual_roughness: 0.85,
+                ..default()
+            });
+            vegetation.materials.insert((kind, season), material);
+        }
+    }
+}
+
+/// Applies the season of loaded level season assets and spawns their vegetation as children of the level entity
+fn spawn_level_season(
+    mut commands: Commands,
+    levels: Query<(Entity, &Handle<LevelSeason>), Without<LevelSeasonSpawned>>,
+    level_seasons: Res<Assets<LevelSeason>>,
+    vegetation: Res<SeasonalVegetation>,
+    mut settings: ResMut<SeasonSettings>,
+    mut weather: ResMut<WeatherManager>,
+) {
+    for (level, handle) in levels.iter() {
+        let Some(level_season) = level_seasons.get(handle) else {
+            continue;
+        };
+
+        settings.season = level_season.season;
+        weather.set_snow_cover(level_season.initial_snow);
+
+        let plants: Vec<Entity> = level_season
+            .vegetation
+            .iter()
+            .filter_map(|desc| {
+                let ground = Vec3::from(desc.position);
+                vegetation.spawn(&mut commands, desc.kind, level_season.season, ground, desc.scale)
+            })
+            .collect();
+        commands.entity(level).push_children(&plants).insert(LevelSeasonSpawned);
+    }
+}
+
+/// Swaps vegetation materials, ground color and snow melt rate over to the current season
+fn apply_season(
+    settings: Res<SeasonSettings>,
+    seasonal: Res<SeasonalVegetation>,
+    terrain: Option<Res<TerrainChunkManager>>,
+    mut terrain_materials: ResMut<Assets<TerrainMaterial>>,
+    mut weather: ResMut<WeatherManager>,
+    mut vegetation: Query<(&Vegetation, &mut Handle<StandardMaterial>)>,
+) {
+    let season = settings.season;
+    weather.set_snow_melt_rate(season.snow_melt_rate());
+
+    if let Some(terrain) = terrain {
+        for handle in terrain.materials() {
+            if let Some(material) = terrain_materials.get_mut(handle) {
+                let grass = material.extension.splat.layer_mut(TerrainLayer::Grass);
+                grass.color = Vec4::from(season.ground_color().as_rgba_f32());
+            }
+        }
+    }
+
+    for (plant, mut material) in vegetation.iter_mut() {
+        if let Some(seasonal) = seasonal.materials.get(&(plant.kind, season)) {
+            *material = seasonal.clone();
+        }
+    }
+}
+
+/// Hands the settled snow over to the terrain's snow layer
+fn update_terrain_snow(
+    weather: Res<WeatherManager>,
+    terrain: Option<Res<TerrainChunkManager>>,
+    mut terrain_materials: ResMut<Assets<TerrainMaterial>>,
+    mut shown: Local<Option<f32>>,
+) {
+    let coverage = weather.snow_cover();
+    // Only touch the material when the change is visible, every write re-uploads it
+    if shown.is_some_and(|shown| (shown - coverage).abs() < 0.002) {
+        return;
+    }
+    let Some(terrain) = terrain else {
+        return;
+    };
+    for handle in terrain.materials() {
+        if let Some(material) = terrain_materials.get_mut(handle) {
+            material.extension.snow.coverage = coverage;
+        }
+    }
+    *shown = Some(coverage);
+}
+
+/// Freezes water volumes over in winter and thaws them again, mud never freezes
+fn freeze_water(
+    mut commands: Commands,
+    settings: Res<SeasonSettings>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut volumes: Query<(Entity, &mut FluidVolume, Option<&Children>)>,
+    ice: Query<(), With<IceSheet>>,
+) {
+    let freeze = settings.season.freezes_water();
+    for (entity, mut volume, children) in volumes.iter_mut() {
+        if volume.kind != FluidKind::Water || volume.frozen == freeze {
+            continue;
+        }
+        volume.frozen = freeze;
+
+        if !freeze {
+            for child in children.into_iter().flatten().filter(|child| ice.contains(**child)) {
+                commands.entity(*child).despawn_recursive();
+            }
+            continue;
+        }
+
+        let half_size = volume.half_size;
+        let thickness = settings.ice_thickness;
+        let sheet = commands
+            .spawn((
+                PbrBundle {
+                    mesh: meshes.add(shape::Box::new(half_size.x * 2.0, thickness, half_size.y * 2.0).into()),
+                    material: materials.add(StandardMaterial {
+                        base_color: Color::rgb(0.78, 0.88, 0.95),
+                        perceptual_roughness: 0.1,
+                        reflectance: 0.6,
+                        ..default()
+                    }),
+                    transform: Transform::from_xyz(0.0, ICE_LIFT - thickness * 0.5, 0.0),
+                    ..default()
+                },
+                RigidBody::Fixed,
+                Collider::cuboid(half_size.x, thickness * 0.5, half_size.y),
+                // Min so tires with more grip don't get it back on the ice
+                Friction {
+                    coefficient: settings.ice_friction,
+                    combine_rule: CoefficientCombineRule::Min,
+                },
+                SurfaceMaterial::Ice,
+                IceSheet,
+                Name::new("Ice"),
+            ))
+            .id();
+        commands.entity(entity).add_child(sheet);
+    }
+}
+
+/// Plugin for seasonal level variants, snow cover on the terrain and frozen water
+pub struct SeasonsPlugin;
+
+impl Plugin for SeasonsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SeasonSettings>()
+            .init_resource::<SeasonalVegetation>()
+            .init_asset::<LevelSeason>()
+            .init_asset_loader::<LevelSeasonLoader>()
+            .add_systems(Startup, setup_seasonal_vegetation)
+            .add_systems(Update, (
+                spawn_level_season,
+                apply_season.run_if(resource_changed::<SeasonSettings>()),
+                update_terrain_snow,
+                freeze_water,
+            ).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_winter_freezes_and_keeps_snow() {
+        assert!(Season::Winter.freezes_water());
+        assert!(!Season::Autumn.freezes_water());
+        assert_eq!(Season::Winter.snow_melt_rate(), 0.0);
+        assert!(Season::Summer.snow_melt_rate() > Season::Autumn.snow_melt_rate());
+        assert_ne!(VegetationKind::Foliage.color(Season::Summer), VegetationKind::Foliage.color(Season::Autumn));
+        assert_eq!(VegetationKind::Conifer.color(Season::Summer), VegetationKind::Conifer.color(Season::Autumn));
+    }
+
+    #[test]
+    fn test_winter_freezes_water_into_ice() {
+        let mut app = App::new();
+        app.init_resource::<SeasonSettings>()
+            .init_resource::<Assets<Mesh>>()
+            .init_resource::<Assets<StandardMaterial>>()
+            .add_systems(Update, freeze_water);
+        let volume = FluidVolume {
+            kind: FluidKind::Water,
+            half_size: Vec2::new(4.0, 10.0),
+            depth: 1.0,
+            flow: Vec2::ZERO,
+            frozen: false,
+        };
+        let water = app.world.spawn(volume.clone()).id();
+        let mud = app.world.spawn(FluidVolume { kind: FluidKind::Mud, ..volume }).id();
+
+        app.world.resource_mut::<SeasonSettings>().season = Season::Winter;
+        app.update();
+        assert!(app.world.get::<FluidVolume>(water).unwrap().frozen);
+        assert!(!app.world.get::<FluidVolume>(mud).unwrap().frozen);
+        let children = app.world.get::<Children>(water).unwrap();
+        assert_eq!(children.len(), 1);
+        let friction = app.world.get::<Friction>(children[0]).unwrap();
+        assert_eq!(friction.coefficient, 0.05);
+        assert_eq!(friction.combine_rule, CoefficientCombineRule::Min);
+        assert_eq!(app.world.get::<SurfaceMaterial>(children[0]), Some(&SurfaceMaterial::Ice));
+
+        app.world.resource_mut::<SeasonSettings>().season = Season::Summer;
+        app.update();
+        assert!(!app.world.get::<FluidVolume>(water).unwrap().frozen);
+        assert_eq!(app.world.query::<&IceSheet>().iter(&app.world).count(), 0);
+    }
+}
@@ -0,0 +1,574 @@
+//! Event log of a run and the summary shown once it's over
+//!
+//! While playing, the [`SessionLog`] stamps the notable moments of the player vehicles with the run
+//! clock: checkpoints ticked off their route, points of interest found, damage taken, recovery gear
+//! used and jumps. A race finish or an [`EndSessionEvent`] closes the run into a [`SessionSummary`]
+//! with its stats, score and medal and moves to the game over screen. From there the input recording
+//! can be saved as a replay with [`SaveReplayEvent`] and the score sent to the leaderboard endpoint of
+//! the backend with [`SubmitScoreEvent`], on a background thread like matchmaking. Scores only go out
+//! while the leaderboard is synced, and not for runs whose money failed the economy audit.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::chat::ChatSettings;
+use super::determinism::{DeterminismSession, RecordingError};
+use super::economy::Ledger;
+use super::progression::RaceFinishedEvent;
+use super::routing::VehicleRoute;
+use super::split_screen::PlayerId;
+use super::trails::PoiReachedEvent;
+use crate::core::GameState;
+use crate::game::vehicle::{RecoveryTool, RecoveryToolUsedEvent, Vehicle, VehicleCollisionEvent, Wheel};
+use crate::game::{configure_game_sets, GameSet};
+
+/// Leaderboard endpoint of a locally running backend
+pub const DEFAULT_LEADERBOARD_URL: &str = "http://localhost:3000/leaderboard";
+
+/// What is logged, how a run is scored and where replays and scores go
+#[derive(Resource, Debug, Clone)]
+pub struct SessionLogSettings {
+    /// Seconds all wheels have to be off the ground for a jump to be logged
+    pub min_airtime: f32,
+    pub checkpoint_points: f32,
+    pub discovery_points: f32,
+    /// Points per second in the air
+    pub airtime_points: f32,
+    /// Points for winning a race, lower places get a share by position
+    pub win_points: f32,
+    /// Points lost per unit of zone damage
+    pub damage_penalty: f32,
+    /// Points lost per recovery tool used
+    pub recovery_penalty: f32,
+    /// Scores needed for gold, silver and bronze
+    pub medal_scores: [u32; 3],
+    /// Saved replays get numbered files in here
+    pub replay_directory: PathBuf,
+    pub leaderboard_url: String,
+    /// Scores are sent to the leaderboard, which has the economy audited for tampering
+    pub leaderboard_sync: bool,
+}
+
+impl Default for SessionLogSettings {
+    fn default() -> Self {
+        Self {
+            min_airtime: 0.5,
+            checkpoint_points: 100.0,
+            discovery_points: 150.0,
+            airtime_points: 50.0,
+            win_points: 1000.0,
+            damage_penalty: 200.0,
+            recovery_penalty: 25.0,
+            medal_scores: [2000, 1000, 400],
+            replay_directory: std::env::var("SANDK_REPLAY_PATH")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| PathBuf::from("replays")),
+            leaderboard_url: std::env::var("SANDK_LEADERBOARD_URL")
+                .unwrap_or_else(|_| DEFAULT_LEADERBOARD_URL.to_string()),
+            leaderboard_sync: true,
+        }
+    }
+}
+
+/// Something worth remembering that happened during a run
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionEventKind {
+    /// Index of the route waypoint reached
+    Checkpoint(usize),
+    PoiReached(String),
+    Damage(f32),
+    Recovery(RecoveryTool),
+    /// Seconds with every wheel off the ground
+    Airtime(f32),
+    /// Place in a race and how many raced
+    Finished { position: u32, racers: u32 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionEvent {
+    /// Seconds into the run, time spent paused doesn't count
+    pub time: f32,
+    pub vehicle: Entity,
+    pub kind: SessionEventKind,
+}
+
+/// Totals of a run
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SessionStats {
+    /// Seconds played
+    pub duration: f32,
+    /// Meters driven by all player vehicles
+    pub distance: f32,
+    /// Meters per second
+    pub top_speed: f32,
+    pub checkpoints: u32,
+    pub discoveries: u32,
+    pub damage: f32,
+    pub recoveries: u32,
+    pub longest_airtime: f32,
+    pub total_airtime: f32,
+    /// Best race place, 1 is the winner
+    pub position: Option<u32>,
+    pub racers: Option<u32>,
+}
+
+impl SessionStats {
+    fn add(&mut self, kind: &SessionEventKind) {
+        match kind {
+            SessionEventKind::Checkpoint(_) => self.checkpoints += 1,
+            SessionEventKind::PoiReached(_) => self.discoveries += 1,
+            SessionEventKind::Damage(amount) => self.damage += amount,
+            SessionEventKind::Recovery(_) => self.recoveries += 1,
+            SessionEventKind::Airtime(seconds) => {
+                self.longest_airtime = self.longest_airtime.max(*seconds);
+                self.total_airtime += seconds;
+            }
+            SessionEventKind::Finished { position, racers } => {
+                if self.position.map_or(true, |best| *position < best) {
+                    self.position = Some(*position);
+                    self.racers = Some(*racers);
+                }
+            }
+        }
+    }
+
+    /// Points the run is worth, never below zero
+    pub fn score(&self, settings: &SessionLogSettings) -> u32 {
+        let race = match (self.position, self.racers) {
+            // The winner gets all the race points, last place none
+            (Some(position), Some(racers)) if racers > 1 => {
+                settings.win_points * (racers - position.min(racers)) as f32 / (racers - 1) as f32
+            }
+            (Some(_), _) => settings.win_points,
+            _ => 0.0,
+        };
+        let score = self.checkpoints as f32 * settings.checkpoint_points
+            + self.discoveries as f32 * settings.discovery_points
+            + self.total_airtime * settings.airtime_points
+            + race
+            - self.damage * settings.damage_penalty
+            - self.recoveries as f32 * settings.recovery_penalty;
+        score.max(0.0).round() as u32
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Medal {
+    Gold,
+    Silver,
+    Bronze,
+}
+
+impl Medal {
+    pub const ALL: [Medal; 3] = [Medal::Gold, Medal::Silver, Medal::Bronze];
+
+    /// Best medal `score` earns against the gold, silver and bronze `thresholds`
+    pub fn for_score(score: u32, thresholds: [u32; 3]) -> Option<Self> {
+        Self::ALL.into_iter().zip(thresholds).find(|(_, needed)| score >= *needed).map(|(medal, _)| medal)
+    }
+
+    /// Localization key of the medal's name
+    pub fn name_key(self) -> &'static str {
+        match self {
+            Self::Gold => "summary.medal_gold",
+            Self::Silver => "summary.medal_silver",
+            Self::Bronze => "summary.medal_bronze",
+        }
+    }
+}
+
+/// How a finished run went
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionSummary {
+    pub stats: SessionStats,
+    pub score: u32,
+    pub medal: Option<Medal>,
+    pub events: Vec<SessionEvent>,
+    /// Where the replay was saved, or why it wasn't
+    pub replay: Option<Result<PathBuf, String>>,
+    pub leaderboard: LeaderboardStatus,
+}
+
+/// Where the run's score is with the leaderboard
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum LeaderboardStatus {
+    #[default]
+    NotSubmitted,
+    Submitting,
+    Ranked(LeaderboardRank),
+    Failed(String),
+}
+
+/// The run being played and the summary of the last one
+#[derive(Resource, Debug, Default)]
+pub struct SessionLog {
+    /// A run is being logged
+    pub active: bool,
+    /// Run clock in seconds
+    pub elapsed: f32,
+    pub events: Vec<SessionEvent>,
+    pub stats: SessionStats,
+    /// Summary of the run that just ended, cleared once it's been dismissed
+    pub summary: Option<SessionSummary>,
+}
+
+impl SessionLog {
+    /// Starts logging a fresh run, the last run's summary goes
+    pub fn start(&mut self) {
+        *self = Self { active: true, ..default() };
+    }
+
+    pub fn record(&mut self, vehicle: Entity, kind: SessionEventKind) {
+        self.stats.add(&kind);
+        self.events.push(SessionEvent { time: self.elapsed, vehicle, kind });
+    }
+
+    /// Closes the run into its summary
+    pub fn finish(&mut self, settings: &SessionLogSettings) -> &SessionSummary {
+        let mut stats = std::mem::take(&mut self.stats);
+        stats.duration = self.elapsed;
+        let score = stats.score(settings);
+        self.active = false;
+        self.summary.insert(SessionSummary {
+            medal: Medal::for_score(score, settings.medal_scores),
+            score,
+            stats,
+            events: std::mem::take(&mut self.events),
+            replay: None,
+            leaderboard: LeaderboardStatus::NotSubmitted,
+        })
+    }
+
+    /// Drops the summary of the last run
+    pub fn dismiss(&mut self) {
+        self.summary = None;
+    }
+}
+
+/// Ends the run being played, for game modes that finish without a race
+#[derive(Event, Debug, Clone, Copy, Default)]
+pub struct EndSessionEvent;
+
+/// Saves the input recording of the run just summarized
+#[derive(Event, Debug, Clone, Copy, Default)]
+pub struct SaveReplayEvent;
+
+/// Sends the summarized run's score to the leaderboard
+#[derive(Event, Debug, Clone, Copy, Default)]
+pub struct SubmitScoreEvent;
+
+/// Body of a leaderboard submission
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LeaderboardEntry {
+    pub player: String,
+    pub score: u32,
+    pub medal: Option<Medal>,
+    pub stats: SessionStats,
+}
+
+/// Answer of the leaderboard endpoint
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeaderboardRank {
+    /// Place of the submitted score, 1 is the top
+    pub rank: u32,
+    pub entries: u32,
+}
+
+#[derive(Error, Debug)]
+pub enum SessionLogError {
+    #[error("replay: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("replay: {0}")]
+    Recording(#[from] RecordingError),
+    #[error("leaderboard request: {0}")]
+    Request(#[from] Box<ureq::Error>),
+    #[error("leaderboard response: {0}")]
+    Response(std::io::Error),
+}
+
+/// Answers from the leaderboard, coming back from background threads
+#[derive(Resource)]
+struct LeaderboardChannel {
+    sender: Mutex<Sender<Result<LeaderboardRank, String>>>,
+    receiver: Mutex<Receiver<Result<LeaderboardRank, String>>>,
+}
+
+impl Default for LeaderboardChannel {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self { sender: Mutex::new(sender), receiver: Mutex::new(receiver) }
+    }
+}
+
+/// Runs the clock of the run being played, starting a new one when play resumes after the last one
+/// ended, and tracks distance, speed, route checkpoints and jumps of the player vehicles
+#[allow(clippy::too_many_arguments)]
+fn track_session(
+    time: Res<Time>,
+    settings: Res<SessionLogSettings>,
+    state: Option<Res<State<GameState>>>,
+    mut log: ResMut<SessionLog>,
+    vehicles: Query<(Entity, &Vehicle, Option<&VehicleRoute>), With<PlayerId>>,
+    wheels: Query<&Wheel>,
+    mut routes: Local<HashMap<Entity, usize>>,
+    mut airborne: Local<HashMap<Entity, f32>>,
+) {
+    if state.is_some_and(|state| state.get() != &GameState::Playing) {
+        return;
+    }
+    if !log.active {
+        log.start();
+        routes.clear();
+        airborne.clear();
+    }
+    let dt = time.delta_seconds();
+    log.elapsed += dt;
+
+    for (entity, vehicle, route) in vehicles.iter() {
+        let speed = vehicle.vehicle_speed.abs();
+        log.stats.distance += speed * dt;
+        log.stats.top_speed = log.stats.top_speed.max(speed);
+
+        // Every waypoint passed since last frame is a checkpoint, a new route starts over
+        let next = route.map_or(0, |route| route.next);
+        let passed = routes.insert(entity, next).unwrap_or(next);
+        for index in passed..next {
+            log.record(entity, SessionEventKind::Checkpoint(index));
+        }
+
+        let off_ground = |&wheel: &Entity| wheels.get(wheel).map_or(false, |wheel| !wheel.ground_contact);
+        let in_air = !vehicle.wheel_entities.is_empty() && vehicle.wheel_entities.iter().all(off_ground);
+        if in_air {
+            *airborne.entry(entity).or_default() += dt;
+        } else if let Some(seconds) = airborne.remove(&entity) {
+            if seconds >= settings.min_airtime {
+                log.record(entity, SessionEventKind::Airtime(seconds));
+            }
+        }
+    }
+}
+
+/// Logs damage, recovery gear and discoveries of the player vehicles
+fn record_session_events(
+    mut log: ResMut<SessionLog>,
+    players: Query<(), With<PlayerId>>,
+    mut collisions: EventReader<VehicleCollisionEvent>,
+    mut recoveries: EventReader<RecoveryToolUsedEvent>,
+    mut discoveries: EventReader<PoiReachedEvent>,
+) {
+    let events: Vec<_> = collisions
+        .read()
+        .filter(|collision| collision.damage > 0.0)
+        .map(|collision| (collision.vehicle, SessionEventKind::Damage(collision.damage)))
+        .chain(recoveries.read().map(|used| (used.vehicle, SessionEventKind::Recovery(used.action.tool()))))
+        .chain(discoveries.read().map(|found| (found.vehicle, SessionEventKind::PoiReached(found.name.clone()))))
+        .collect();
+    if !log.active {
+        return;
+    }
+    for (vehicle, kind) in events {
+        if players.contains(vehicle) {
+            log.record(vehicle, kind);
+        }
+    }
+}
+
+/// Closes the run when a player finishes a race or a game mode ends it, and shows the summary
+fn finish_session(
+    settings: Res<SessionLogSettings>,
+    mut log: ResMut<SessionLog>,
+    players: Query<(), With<PlayerId>>,
+    mut races: EventReader<RaceFinishedEvent>,
+    mut ends: EventReader<EndSessionEvent>,
+    next_state: Option<ResMut<NextState<GameState>>>,
+) {
+    let mut ended = ends.read().count() > 0;
+    for race in races.read() {
+        if log.active && players.contains(race.vehicle) {
+            let kind = SessionEventKind::Finished { position: race.position, racers: race.racers };
+            log.record(race.vehicle, kind);
+            ended = true;
+        }
+    }
+    if !ended || !log.active {
+        return;
+    }
+
+    let summary = log.finish(&settings);
+    info!(
+        "Run over after {:.1} s: {} points, {} events",
+        summary.stats.duration,
+        summary.score,
+        summary.events.len()
+    );
+    if let Some(mut next_state) = next_state {
+        next_state.set(GameState::GameOver);
+    }
+}
+
+/// Writes the run's input recording to the next free numbered file in `settings.replay_directory`
+fn write_replay(settings: &SessionLogSettings, session: &DeterminismSession) -> Result<PathBuf, SessionLogError> {
+    fs::create_dir_all(&settings.replay_directory)?;
+    let file = |index: u32| settings.replay_directory.join(format!("replay_{index:03}.json"));
+    let path = (1..).map(file).find(|path| !path.exists()).unwrap_or_else(|| file(1));
+    fs::write(&path, session.recording.to_json()?)?;
+    Ok(path)
+}
+
+/// Saves the replay of the summarized run
+fn save_replays(
+    settings: Res<SessionLogSettings>,
+    session: Option<Res<DeterminismSession>>,
+    mut log: ResMut<SessionLog>,
+    mut requests: EventReader<SaveReplayEvent>,
+) {
+    if requests.read().count() == 0 {
+        return;
+    }
+    let (Some(summary), Some(session)) = (log.summary.as_mut(), session) else {
+        return;
+    };
+    let saved = write_replay(&settings, &session);
+    match &saved {
+        Ok(path) => info!("Saved replay to {}", path.display()),
+        Err(error) => warn!("Failed to save replay: {error}"),
+    }
+    summary.replay = Some(saved.map_err(|error| error.to_string()));
+}
+
+/// Posts an entry to the leaderboard endpoint, blocking until it answers
+fn submit(url: &str, entry: &LeaderboardEntry) -> Result<LeaderboardRank, SessionLogError> {
+    let response = ureq::post(url).send_json(entry).map_err(Box::new)?;
+    response.into_json().map_err(SessionLogError::Response)
+}
+
+/// Sends the summarized run's score off on its own thread
+fn submit_scores(
+    settings: Res<SessionLogSettings>,
+    chat: Option<Res<ChatSettings>>,
+    ledger: Option<Res<Ledger>>,
+    channel: Res<LeaderboardChannel>,
+    mut log: ResMut<SessionLog>,
+    mut requests: EventReader<SubmitScoreEvent>,
+) {
+    if requests.read().count() == 0 || !settings.leaderboard_sync {
+        return;
+    }
+    let Some(summary) = log.summary.as_mut() else {
+        return;
+    };
+    if matches!(summary.leaderboard, LeaderboardStatus::Submitting | LeaderboardStatus::Ranked(_)) {
+        return;
+    }
+    if let Some(violation) = ledger.as_ref().and_then(|ledger| ledger.violation()) {
+        summary.leaderboard = LeaderboardStatus::Failed(violation.to_string());
+        return;
+    }
+    let Ok(sender) = channel.sender.lock().map(|sender| sender.clone()) else {
+        return;
+    };
+    let entry = LeaderboardEntry {
+        player: chat.map_or_else(String::new, |chat| chat.player_name.clone()),
+        score: summary.score,
+        medal: summary.medal,
+        stats: summary.stats.clone(),
+    };
+    let url = settings.leaderboard_url.clone();
+    summary.leaderboard = LeaderboardStatus::Submitting;
+    std::thread::spawn(move || {
+        let result = submit(&url, &entry).map_err(|error| {
+            warn!("{error}");
+            error.to_string()
+        });
+        // The receiver only goes away with the app
+        let _ = sender.send(result);
+    });
+}
+
+/// Puts leaderboard answers on the summary they were for
+fn receive_leaderboard_ranks(channel: Res<LeaderboardChannel>, mut log: ResMut<SessionLog>) {
+    let Ok(receiver) = channel.receiver.lock() else {
+        return;
+    };
+    for result in receiver.try_iter() {
+        if let Some(summary) = log.summary.as_mut() {
+            summary.leaderboard = match result {
+                Ok(rank) => LeaderboardStatus::Ranked(rank),
+                Err(error) => LeaderboardStatus::Failed(error),
+            };
+        }
+    }
+}
+
+/// Plugin for logging runs and summarizing them when they end
+pub struct SessionLogPlugin;
+
+impl Plugin for SessionLogPlugin {
+    fn build(&self, app: &mut App) {
+        configure_game_sets(app);
+        app.init_resource::<SessionLogSettings>()
+            .init_resource::<SessionLog>()
+            .init_resource::<LeaderboardChannel>()
+            .add_event::<EndSessionEvent>()
+            .add_event::<SaveReplayEvent>()
+            .add_event::<SubmitScoreEvent>()
+            .add_event::<VehicleCollisionEvent>()
+            .add_event::<RecoveryToolUsedEvent>()
+            .add_event::<PoiReachedEvent>()
+            .add_event::<RaceFinishedEvent>()
+            .add_systems(Update, (
+                track_session,
+                record_session_events,
+                finish_session,
+                save_replays,
+                submit_scores,
+                receive_leaderboard_ranks,
+            ).chain().in_set(GameSet::PostSim));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_summary() {
+        let settings = SessionLogSettings::default();
+        let mut log = SessionLog::default();
+        log.start();
+        let vehicle = Entity::from_raw(1);
+        log.elapsed = 12.0;
+        log.record(vehicle, SessionEventKind::Checkpoint(0));
+        log.record(vehicle, SessionEventKind::Airtime(1.5));
+        log.record(vehicle, SessionEventKind::Damage(0.5));
+        log.elapsed = 30.0;
+        log.record(vehicle, SessionEventKind::Recovery(RecoveryTool::TowStrap));
+        log.record(vehicle, SessionEventKind::Finished { position: 2, racers: 5 });
+
+        let summary = log.finish(&settings).clone();
+        assert!(!log.active);
+        assert!(log.events.is_empty());
+        assert_eq!(summary.events.len(), 5);
+        assert_eq!(summary.events[0].time, 12.0);
+        assert_eq!(summary.stats.duration, 30.0);
+        assert_eq!(summary.stats.longest_airtime, 1.5);
+        // 100 + 75 + 750 for second of five - 100 - 25
+        assert_eq!(summary.score, 800);
+        assert_eq!(summary.medal, Some(Medal::Bronze));
+    }
+
+    #[test]
+    fn test_medals() {
+        let thresholds = [2000, 1000, 400];
+        assert_eq!(Medal::for_score(2500, thresholds), Some(Medal::Gold));
+        assert_eq!(Medal::for_score(1000, thresholds), Some(Medal::Silver));
+        assert_eq!(Medal::for_score(399, thresholds), None);
+    }
+}
@@ -0,0 +1,651 @@
+use bevy::input::mouse::MouseMotion;
+use bevy::prelude::*;
+use bevy::render::camera::Viewport;
+use bevy::window::PrimaryWindow;
+
+use super::camera::{CameraSettings, GameCamera};
+use super::steering_wheel::{SteeringWheelDevice, SteeringWheelSettings};
+use crate::game::vehicle::{
+    Brakes, DespawnVehicleEvent, Engine, PlayerOrAi, RecoverVehicleEvent, ShiftTransferCaseEvent, SpawnVehicleEvent,
+    Steering, ToggleIgnitionEvent, Transmission, Vehicle,
+};
+use crate::game::{configure_game_sets, AccessibilitySettings, ControlPreset, GameSet, GameSettings};
+
+/// Local player a vehicle, camera or HUD belongs to, 0 is player one
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PlayerId(pub usize);
+
+/// Where a player's controls come from
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerInputDevice {
+    /// Keyboard and mouse
+    Keyboard,
+    /// The first connected gamepad
+    Gamepad,
+    /// The connected steering wheel, pedals and shifter
+    SteeringWheel,
+}
+
+/// Controls of one player for this frame, lives on the player's vehicle
+#[derive(Component, Debug, Clone, Default, PartialEq)]
+pub struct PlayerInput {
+    /// 0.0 - 1.0
+    pub throttle: f32,
+    /// 0.0 - 1.0
+    pub brake: f32,
+    /// -1.0 (right) - 1.0 (left)
+    pub steering: f32,
+    pub handbrake: bool,
+    /// Winch pulling in
+    pub winch: bool,
+    /// Ignition key turned this frame
+    pub ignition: bool,
+    /// Transfer case moved to the next range this frame
+    pub shift_transfer: bool,
+    /// Differentials locked, flips on each press
+    pub diff_lock: bool,
+    /// Recovery asked for this frame
+    pub recover: bool,
+    /// Recovery gear menu opened or closed this frame
+    pub recovery_menu: bool,
+    /// Refuel button held, fills from a pump or fuel can within reach
+    pub refuel: bool,
+    /// Page of the quick chat menu that's open, `None` while it's closed
+    pub quick_chat: Option<usize>,
+    /// Quick message picked this frame, as an index into all quick messages
+    pub quick_chat_pick: Option<usize>,
+    /// Gear picked on an H-shifter, -1 for reverse and 0 for neutral, `None` leaves the gear alone
+    pub gear: Option<i32>,
+    pub camera_rotate: Vec2,
+    pub camera_zoom: f32,
+}
+
+/// How the window is divided between players
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SplitLayout {
+    /// Side by side
+    #[default]
+    Vertical,
+    /// One above the other
+    Horizontal,
+}
+
+/// Local multiplayer configuration
+#[derive(Resource, Clone, Debug)]
+pub struct SplitScreenSettings {
+    pub enabled: bool,
+    pub layout: SplitLayout,
+    /// Stick values below this are ignored
+    pub gamepad_deadzone: f32,
+    /// Where player two's vehicle spawns, relative to player one
+    pub spawn_offset: Vec3,
+    /// Camera orbit speed from the right stick, radians per second at full tilt
+    pub gamepad_look_speed: f32,
+}
+
+impl Default for SplitScreenSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            layout: SplitLayout::Vertical,
+            gamepad_deadzone: 0.12,
+            spawn_offset: Vec3::new(4.0, 0.0, 0.0),
+            gamepad_look_speed: 2.5,
+        }
+    }
+}
+
+/// Viewport of `player` out of `players` in a window of `size` pixels, as (position, size)
+pub fn split_viewport(layout: SplitLayout, player: usize, players: usize, size: UVec2) -> (UVec2, UVec2) {
+    if players <= 1 {
+        return (UVec2::ZERO, size);
+    }
+    let players = players as u32;
+    let index = (player as u32).min(players - 1);
+    match layout {
+        SplitLayout::Vertical => {
+            let width = size.x / players;
+            (UVec2::new(width * index, 0), UVec2::new(width, size.y))
+        }
+        SplitLayout::Horizontal => {
+            let height = size.y / players;
+            (UVec2::new(0, height * index), UVec2::new(size.x, height))
+        }
+    }
+}
+
+/// Quick messages on one page of the quick chat menu, one per pick button
+pub const QUICK_CHAT_PAGE_SIZE: usize = 4;
+/// Pages the quick chat button cycles through before closing the menu
+pub const QUICK_CHAT_PAGES: usize = 2;
+/// Keys picking a quick message from the open page
+pub const QUICK_CHAT_KEYS: [KeyCode; QUICK_CHAT_PAGE_SIZE] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4];
+/// Gamepad buttons picking a quick message from the open page
+pub const QUICK_CHAT_BUTTONS: [GamepadButtonType; QUICK_CHAT_PAGE_SIZE] = [
+    GamepadButtonType::DPadUp,
+    GamepadButtonType::DPadRight,
+    GamepadButtonType::DPadDown,
+    GamepadButtonType::DPadLeft,
+];
+
+/// Keys for driving and the camera
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyboardLayout {
+    pub throttle: KeyCode,
+    pub brake: KeyCode,
+    pub steer_left: KeyCode,
+    pub steer_right: KeyCode,
+    pub handbrake: KeyCode,
+    pub winch: KeyCode,
+    pub ignition: KeyCode,
+    pub transfer_case: KeyCode,
+    pub diff_lock: KeyCode,
+    pub recover: KeyCode,
+    pub recovery_menu: KeyCode,
+    pub refuel: KeyCode,
+    pub quick_chat: KeyCode,
+    pub zoom_in: KeyCode,
+    pub zoom_out: KeyCode,
+    /// Keys turning the camera, for layouts that leave no hand for the mouse
+    pub look: Option<(KeyCode, KeyCode)>,
+}
+
+impl KeyboardLayout {
+    pub fn for_preset(preset: ControlPreset) -> Self {
+        match preset {
+            ControlPreset::Standard => Self {
+                throttle: KeyCode::W,
+                brake: KeyCode::S,
+                steer_left: KeyCode::A,
+                steer_right: KeyCode::D,
+                handbrake: KeyCode::Space,
+                winch: KeyCode::G,
+                ignition: KeyCode::I,
+                transfer_case: KeyCode::L,
+                diff_lock: KeyCode::K,
+                recover: KeyCode::R,
+                recovery_menu: KeyCode::T,
+                refuel: KeyCode::F,
+                quick_chat: KeyCode::Y,
+                zoom_in: KeyCode::Equals,
+                zoom_out: KeyCode::Minus,
+                look: None,
+            },
+            ControlPreset::OneHandedLeft => Self {
+                throttle: KeyCode::W,
+                brake: KeyCode::S,
+                steer_left: KeyCode::A,
+                steer_right: KeyCode::D,
+                handbrake: KeyCode::Space,
+                winch: KeyCode::ShiftLeft,
+                ignition: KeyCode::V,
+                transfer_case: KeyCode::Z,
+                diff_lock: KeyCode::X,
+                recover: KeyCode::C,
+                recovery_menu: KeyCode::T,
+                refuel: KeyCode::G,
+                quick_chat: KeyCode::B,
+                zoom_in: KeyCode::F,
+                zoom_out: KeyCode::R,
+                look: Some((KeyCode::Q, KeyCode::E)),
+            },
+            // The arrows with the Insert/Delete block right above them
+            ControlPreset::OneHandedRight => Self {
+                throttle: KeyCode::Up,
+                brake: KeyCode::Down,
+                steer_left: KeyCode::Left,
+                steer_right: KeyCode::Right,
+                handbrake: KeyCode::ShiftRight,
+                winch: KeyCode::ControlRight,
+                ignition: KeyCode::Back,
+                transfer_case: KeyCode::Home,
+                diff_lock: KeyCode::Insert,
+                recover: KeyCode::Return,
+                recovery_menu: KeyCode::Slash,
+                refuel: KeyCode::Comma,
+                quick_chat: KeyCode::Period,
+                zoom_in: KeyCode::PageUp,
+                zoom_out: KeyCode::PageDown,
+                look: Some((KeyCode::Delete, KeyCode::End)),
+            },
+        }
+    }
+}
+
+/// Gamepad buttons for driving, the sticks steer and look around
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GamepadLayout {
+    pub throttle: GamepadButtonType,
+    pub brake: GamepadButtonType,
+    pub handbrake: GamepadButtonType,
+    pub winch: GamepadButtonType,
+    pub ignition: GamepadButtonType,
+    pub transfer_case: GamepadButtonType,
+    pub diff_lock: GamepadButtonType,
+    pub recover: GamepadButtonType,
+    pub recovery_menu: GamepadButtonType,
+    pub refuel: GamepadButtonType,
+    pub quick_chat: GamepadButtonType,
+    pub zoom_in: GamepadButtonType,
+    pub zoom_out: GamepadButtonType,
+}
+
+impl Default for GamepadLayout {
+    fn default() -> Self {
+        Self {
+            throttle: GamepadButtonType::RightTrigger2,
+            brake: GamepadButtonType::LeftTrigger2,
+            handbrake: GamepadButtonType::South,
+            winch: GamepadButtonType::North,
+            ignition: GamepadButtonType::West,
+            transfer_case: GamepadButtonType::DPadLeft,
+            diff_lock: GamepadButtonType::DPadRight,
+            recover: GamepadButtonType::Select,
+            recovery_menu: GamepadButtonType::East,
+            refuel: GamepadButtonType::LeftTrigger,
+            quick_chat: GamepadButtonType::RightThumb,
+            zoom_in: GamepadButtonType::DPadUp,
+            zoom_out: GamepadButtonType::DPadDown,
+        }
+    }
+}
+
+fn apply_deadzone(value: f32, deadzone: f32) -> f32 {
+    if value.abs() < deadzone {
+        0.0
+    } else {
+        // Rescale so output starts at zero right past the deadzone
+        value.signum() * (value.abs() - deadzone) / (1.0 - deadzone)
+    }
+}
+
+/// Reads each player's device into their [`PlayerInput`]
+#[allow(clippy::too_many_arguments)]
+pub(super) fn read_player_input(
+    time: Res<Time>,
+    settings: Res<SplitScreenSettings>,
+    camera_settings: Res<CameraSettings>,
+    game_settings: Option<Res<GameSettings>>,
+    keyboard: Res<Input<KeyCode>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    gamepads: Res<Gamepads>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    wheel_settings: Res<SteeringWheelSettings>,
+    wheel: Res<SteeringWheelDevice>,
+    mut players: Query<(&PlayerInputDevice, &mut PlayerInput)>,
+) {
+    let mouse: Vec2 = mouse_motion.read().map(|motion| motion.delta).sum();
+    // The wheel registers as a gamepad too, controllers skip it
+    let gamepad = gamepads.iter().find(|gamepad| Some(*gamepad) != wheel.0);
+    let accessibility = game_settings.map_or_else(AccessibilitySettings::default, |settings| settings.accessibility.clone());
+    let layout = KeyboardLayout::for_preset(accessibility.control_preset);
+    let buttons = GamepadLayout::default();
+    // Sticks and look keys are a rate while the mouse is a distance, convert to the pixels the camera expects
+    let look_scale = settings.gamepad_look_speed * time.delta_seconds() / camera_settings.rotation_sensitivity;
+
+    for (device, mut input) in players.iter_mut() {
+        let previous = input.clone();
+        // Held state of the handbrake and winch buttons, and whether they were held last frame
+        let (handbrake, winch);
+        // Whether the ignition, transfer case, diff lock, recovery and recovery menu buttons went down this frame
+        let (ignition, shift_transfer, diff_lock, recover, recovery_menu);
+        // Whether the refuel button is held
+        let refuel;
+        // Whether the quick chat button went down, and which pick buttons did
+        let (quick_chat, quick_chat_picks): (bool, [bool; QUICK_CHAT_PAGE_SIZE]);
+        *input = match device {
+            PlayerInputDevice::Keyboard => {
+                let axis = |positive: KeyCode, negative: KeyCode| {
+                    keyboard.pressed(positive) as i32 as f32 - keyboard.pressed(negative) as i32 as f32
+                };
+                let look = layout.look.map_or(0.0, |(left, right)| axis(right, left));
+                handbrake = (keyboard.pressed(layout.handbrake), !keyboard.just_pressed(layout.handbrake));
+                winch = (keyboard.pressed(layout.winch), !keyboard.just_pressed(layout.winch));
+                ignition = keyboard.just_pressed(layout.ignition);
+                shift_transfer = keyboard.just_pressed(layout.transfer_case);
+                diff_lock = keyboard.just_pressed(layout.diff_lock);
+                recover = keyboard.just_pressed(layout.recover);
+                recovery_menu = keyboard.just_pressed(layout.recovery_menu);
+                refuel = keyboard.pressed(layout.refuel);
+                quick_chat = keyboard.just_pressed(layout.quick_chat);
+                quick_chat_picks = QUICK_CHAT_KEYS.map(|key| keyboard.just_pressed(key));
+                PlayerInput {
+                    throttle: keyboard.pressed(layout.throttle) as i32 as f32,
+                    brake: keyboard.pressed(layout.brake) as i32 as f32,
+                    steering: axis(layout.steer_left, layout.steer_right),
+                    camera_rotate: mouse + Vec2::X * look * look_scale,
+                    camera_zoom: axis(layout.zoom_out, layout.zoom_in),
+                    ..default()
+                }
+            }
+            PlayerInputDevice::Gamepad => {
+                let Some(gamepad) = gamepad else {
+                    *input = PlayerInput::default();
+                    continue;
+                };
+                let axis = |axis_type| {
+                    let value = gamepad_axes.get(GamepadAxis::new(gamepad, axis_type)).unwrap_or(0.0);
+                    apply_deadzone(value, settings.gamepad_deadzone)
+                };
+                let held = |button_type| {
+                    let button = GamepadButton::new(gamepad, button_type);
+                    (gamepad_buttons.pressed(button), !gamepad_buttons.just_pressed(button))
+                };
+                let button = |button_type| held(button_type).0;
+                let button_axis = |positive, negative| button(positive) as i32 as f32 - button(negative) as i32 as f32;
+
+                let look = Vec2::new(axis(GamepadAxisType::RightStickX), -axis(GamepadAxisType::RightStickY));
+                let just_pressed = |button_type| gamepad_buttons.just_pressed(GamepadButton::new(gamepad, button_type));
+                handbrake = held(buttons.handbrake);
+                winch = held(buttons.winch);
+                ignition = just_pressed(buttons.ignition);
+                shift_transfer = just_pressed(buttons.transfer_case);
+                diff_lock = just_pressed(buttons.diff_lock);
+                recover = just_pressed(buttons.recover);
+                recovery_menu = just_pressed(buttons.recovery_menu);
+                refuel = button(buttons.refuel);
+                quick_chat = just_pressed(buttons.quick_chat);
+                quick_chat_picks = QUICK_CHAT_BUTTONS.map(just_pressed);
+                PlayerInput {
+                    throttle: button(buttons.throttle) as i32 as f32,
+                    brake: button(buttons.brake) as i32 as f32,
+                    steering: -axis(GamepadAxisType::LeftStickX),
+                    camera_rotate: look * look_scale,
+                    camera_zoom: button_axis(buttons.zoom_out, buttons.zoom_in),
+                    ..default()
+                }
+            }
+            PlayerInputDevice::SteeringWheel => {
+                let Some(wheel) = wheel.0 else {
+                    *input = PlayerInput::default();
+                    continue;
+                };
+                let axis = |axis_type| gamepad_axes.get(GamepadAxis::new(wheel, axis_type)).unwrap_or(0.0);
+                let pressed = |button_type| gamepad_buttons.pressed(GamepadButton::new(wheel, button_type));
+                let just_pressed = |button_type| gamepad_buttons.just_pressed(GamepadButton::new(wheel, button_type));
+                handbrake = (
+                    pressed(wheel_settings.handbrake_button),
+                    !just_pressed(wheel_settings.handbrake_button),
+                );
+                // The winch and drivetrain controls share the keyboard's keys, wheels have few spare buttons
+                winch = (keyboard.pressed(layout.winch), !keyboard.just_pressed(layout.winch));
+                ignition = keyboard.just_pressed(layout.ignition);
+                shift_transfer = keyboard.just_pressed(layout.transfer_case);
+                diff_lock = keyboard.just_pressed(layout.diff_lock);
+                recover = keyboard.just_pressed(layout.recover);
+                recovery_menu = keyboard.just_pressed(layout.recovery_menu);
+                refuel = keyboard.pressed(layout.refuel);
+                quick_chat = keyboard.just_pressed(layout.quick_chat);
+                quick_chat_picks = QUICK_CHAT_KEYS.map(|key| keyboard.just_pressed(key));
+                // Camera stays on the mouse, wheels have nothing to look around with
+                PlayerInput {
+                    camera_rotate: mouse,
+                    ..wheel_settings.read(axis, pressed)
+                }
+            }
+        };
+        input.handbrake = accessibility.handbrake_mode.resolve(previous.handbrake, handbrake.0, handbrake.1);
+        input.winch = accessibility.winch_mode.resolve(previous.winch, winch.0, winch.1);
+        input.ignition = ignition;
+        input.recover = recover;
+        input.recovery_menu = recovery_menu;
+        input.refuel = refuel;
+
+        // The pick buttons share the D-pad with the drivetrain and zoom, which wait while the menu is open
+        let page = previous.quick_chat;
+        let pick = page.and_then(|_| quick_chat_picks.iter().position(|picked| *picked));
+        input.quick_chat_pick = page.zip(pick).map(|(page, pick)| page * QUICK_CHAT_PAGE_SIZE + pick);
+        input.quick_chat = match (pick, quick_chat, page) {
+            (Some(_), ..) => None,
+            (None, true, None) => Some(0),
+            (None, true, Some(page)) => Some(page + 1).filter(|next| *next < QUICK_CHAT_PAGES),
+            (None, false, page) => page,
+        };
+        let chatting = page.is_some();
+        input.diff_lock = previous.diff_lock != (diff_lock && !chatting);
+        input.shift_transfer = shift_transfer && !chatting;
+        if chatting {
+            input.camera_zoom = 0.0;
+        }
+    }
+}
+
+/// Drives each player's vehicle from their input
+pub(super) fn apply_player_input(
+    mut vehicles: Query<(
+        Entity,
+        &PlayerInput,
+        &mut Vehicle,
+        &mut Engine,
+        &mut Transmission,
+        &mut Steering,
+        &mut Brakes,
+    )>,
+    mut ignition: EventWriter<ToggleIgnitionEvent>,
+    mut shift_transfer: EventWriter<ShiftTransferCaseEvent>,
+    mut recover: EventWriter<RecoverVehicleEvent>,
+) {
+    for (entity, input, mut vehicle, mut engine, mut transmission, mut steering, mut brakes) in vehicles.iter_mut() {
+        engine.throttle = input.throttle.clamp(0.0, 1.0);
+        brakes.brake = input.brake.clamp(0.0, 1.0);
+        brakes.handbrake = input.handbrake;
+        vehicle.diff_locked = input.diff_lock;
+        if input.ignition {
+            ignition.send(ToggleIgnitionEvent { vehicle: entity });
+        }
+        if input.shift_transfer {
+            shift_transfer.send(ShiftTransferCaseEvent { vehicle: entity });
+        }
+        if input.recover {
+            recover.send(RecoverVehicleEvent { vehicle: entity });
+        }
+        if let Some(gear) = input.gear {
+            transmission.gear = gear;
+        }
+        steering.angle = input.steering.clamp(-1.0, 1.0) * vehicle.config.max_steering_angle;
+    }
+}
+
+/// Brings player two in when split screen is switched on and removes them when it's switched off
+fn manage_players(
+    mut commands: Commands,
+    settings: Res<SplitScreenSettings>,
+    mut pending: Local<bool>,
+    mut spawn_vehicles: EventWriter<SpawnVehicleEvent>,
+    mut despawn_vehicles: EventWriter<DespawnVehicleEvent>,
+    vehicles: Query<(Entity, &Transform, Option<&PlayerId>), With<Vehicle>>,
+    mut cameras: Query<(Entity, &mut GameCamera, &mut Camera, Option<&PlayerId>)>,
+) {
+    // Keep retrying after a change until player one's vehicle exists
+    *pending |= settings.is_changed();
+    if !*pending {
+        return;
+    }
+
+    // Player one is whoever already drives; claim the first vehicle and camera if nobody does yet
+    let player_one = vehicles
+        .iter()
+        .find(|(_, _, player)| *player == Some(&PlayerId(0)))
+        .or_else(|| vehicles.iter().find(|(_, _, player)| player.is_none()));
+    let Some((player_one, player_one_transform, claimed)) = player_one else {
+        return;
+    };
+    *pending = false;
+    if claimed.is_none() {
+        commands
+            .entity(player_one)
+            .insert((PlayerId(0), PlayerInputDevice::Keyboard, PlayerInput::default()));
+    }
+    if let Some((camera, mut game_camera, _, None)) = cameras.iter_mut().find(|(_, _, _, player)| player.is_none()) {
+        game_camera.target = Some(player_one);
+        commands.entity(camera).insert(PlayerId(0));
+    }
+
+    let player_two_vehicle = vehicles.iter().find(|(_, _, player)| *player == Some(&PlayerId(1)));
+    let player_two_camera = cameras.iter().find(|(_, _, _, player)| *player == Some(&PlayerId(1)));
+
+    match (settings.enabled, player_two_vehicle, player_two_camera) {
+        (true, None, _) => {
+            spawn_vehicles.send(SpawnVehicleEvent {
+                definition: default(),
+                transform: Transform::from_translation(player_one_transform.translation + settings.spawn_offset)
+                    .with_rotation(player_one_transform.rotation),
+                driver: PlayerOrAi::Player(1),
+            });
+            // The spawner points this camera at the vehicle once it exists
+            commands.spawn((
+                Camera3dBundle {
+                    camera: Camera { order: 1, ..default() },
+                    transform: Transform::from_translation(player_one_transform.translation + settings.spawn_offset + Vec3::new(0.0, 5.0, 10.0))
+                        .looking_at(player_one_transform.translation, Vec3::Y),
+                    ..default()
+                },
+                GameCamera::default(),
+                PlayerId(1),
+                Name::new("Camera P2"),
+            ));
+        }
+        (false, vehicle, camera) => {
+            if let Some((vehicle, _, _)) = vehicle {
+                despawn_vehicles.send(DespawnVehicleEvent { vehicle });
+            }
+            if let Some((camera, _, _, _)) = camera {
+                commands.entity(camera).despawn_recursive();
+            }
+            // Player one gets the whole window back
+            for (_, _, mut camera, player) in cameras.iter_mut() {
+                if player == Some(&PlayerId(0)) {
+                    camera.viewport = None;
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Lays player cameras out over the window
+fn update_split_viewports(
+    settings: Res<SplitScreenSettings>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut cameras: Query<(&mut Camera, &PlayerId), With<GameCamera>>,
+) {
+    if !settings.enabled {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let size = UVec2::new(window.physical_width(), window.physical_height());
+    let players = cameras.iter().count();
+
+    for (mut camera, player) in cameras.iter_mut() {
+        let (position, size) = split_viewport(settings.layout, player.0, players, size);
+        let viewport = Viewport { physical_position: position, physical_size: size, ..default() };
+        if camera.viewport.as_ref().map(|v| (v.physical_position, v.physical_size)) != Some((position, size)) {
+            camera.viewport = Some(viewport);
+        }
+    }
+}
+
+/// Plugin for two player split screen
+pub struct SplitScreenPlugin;
+
+impl Plugin for SplitScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SplitScreenSettings>()
+            .add_systems(Update, (
+                (manage_players, read_player_input, apply_player_input).chain().in_set(GameSet::Input),
+                update_split_viewports.in_set(GameSet::CameraUi),
+            ));
+        configure_game_sets(app);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::HoldMode;
+
+    #[test]
+    fn test_single_player_gets_whole_window() {
+        let size = UVec2::new(1920, 1080);
+        assert_eq!(split_viewport(SplitLayout::Vertical, 0, 1, size), (UVec2::ZERO, size));
+    }
+
+    #[test]
+    fn test_vertical_split() {
+        let size = UVec2::new(1920, 1080);
+        assert_eq!(split_viewport(SplitLayout::Vertical, 0, 2, size), (UVec2::ZERO, UVec2::new(960, 1080)));
+        assert_eq!(split_viewport(SplitLayout::Vertical, 1, 2, size), (UVec2::new(960, 0), UVec2::new(960, 1080)));
+    }
+
+    #[test]
+    fn test_horizontal_split() {
+        let size = UVec2::new(1920, 1080);
+        assert_eq!(split_viewport(SplitLayout::Horizontal, 1, 2, size), (UVec2::new(0, 540), UVec2::new(1920, 540)));
+    }
+
+    #[test]
+    fn test_deadzone() {
+        assert_eq!(apply_deadzone(0.05, 0.1), 0.0);
+        assert_eq!(apply_deadzone(1.0, 0.1), 1.0);
+        assert!((apply_deadzone(-0.55, 0.1) + 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_toggle_flips_on_press_only() {
+        assert!(HoldMode::Hold.resolve(false, true, true));
+        assert!(!HoldMode::Hold.resolve(true, false, true));
+        // Pressing switches it on, keeping the key down or letting go leaves it on
+        assert!(HoldMode::Toggle.resolve(false, true, false));
+        assert!(HoldMode::Toggle.resolve(true, true, true));
+        assert!(HoldMode::Toggle.resolve(true, false, true));
+        assert!(!HoldMode::Toggle.resolve(true, true, false));
+    }
+
+    #[test]
+    fn test_one_handed_layouts_stay_on_one_side() {
+        let right = KeyboardLayout::for_preset(ControlPreset::OneHandedRight);
+        assert_eq!(right.throttle, KeyCode::Up);
+        assert!(right.look.is_some());
+        let left = KeyboardLayout::for_preset(ControlPreset::OneHandedLeft);
+        assert_eq!(left.throttle, KeyCode::W);
+        assert!(KeyboardLayout::for_preset(ControlPreset::Standard).look.is_none());
+    }
+
+    #[test]
+    fn test_player_input_drives_vehicle() {
+        let mut app = App::new();
+        app.add_event::<RecoverVehicleEvent>()
+            .add_event::<ToggleIgnitionEvent>()
+            .add_event::<ShiftTransferCaseEvent>()
+            .add_systems(Update, apply_player_input);
+        let vehicle = app
+            .world
+            .spawn((
+                (Vehicle::default(), Engine::default(), Transmission::default()),
+                (Steering::default(), Brakes::default()),
+                PlayerInput {
+                    throttle: 0.8,
+                    steering: -1.0,
+                    handbrake: true,
+                    shift_transfer: true,
+                    recover: true,
+                    ..default()
+                },
+            ))
+            .id();
+        app.update();
+
+        assert_eq!(app.world.resource::<Events<RecoverVehicleEvent>>().len(), 1);
+        assert_eq!(app.world.resource::<Events<ShiftTransferCaseEvent>>().len(), 1);
+        assert!(app.world.resource::<Events<ToggleIgnitionEvent>>().is_empty());
+        let vehicle = app.world.entity(vehicle);
+        assert_eq!(vehicle.get::<Engine>().unwrap().throttle, 0.8);
+        assert!(vehicle.get::<Brakes>().unwrap().handbrake);
+        let max_steering_angle = vehicle.get::<Vehicle>().unwrap().config.max_steering_angle;
+        assert!(!vehicle.get::<Vehicle>().unwrap().diff_locked);
+        assert_eq!(vehicle.get::<Steering>().unwrap().angle, -max_steering_angle);
+    }
+}
@@ -0,0 +1,201 @@
+use std::time::Duration;
+
+use bevy::input::gamepad::{GamepadRumbleIntensity, GamepadRumbleRequest};
+use bevy::prelude::*;
+
+use super::{SteeringWheelDevice, SteeringWheelSettings};
+use crate::game::plugins::impacts::ImpactEvent;
+use crate::game::plugins::split_screen::PlayerId;
+use crate::game::vehicle::{Vehicle, Wheel, WheelHub};
+
+/// Slip angle in radians at which a tire's aligning torque peaks
+const PEAK_SLIP_ANGLE: f32 = 0.14;
+/// Suspension speed in m/s that counts as full rumble
+const FULL_RUMBLE_SUSPENSION_SPEED: f32 = 1.0;
+/// Jolt lost per second
+const JOLT_DECAY: f32 = 6.0;
+/// Rumble requests are refreshed this often, each lasting until the next
+const RUMBLE_INTERVAL: f32 = 0.1;
+
+/// Force feedback to play on the wheel this frame
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq)]
+pub struct ForceFeedback {
+    /// Self-aligning torque (-1.0 - 1.0), positive turns the wheel left like steering input
+    pub aligning: f32,
+    /// Vibration from the surface (0.0 - 1.0)
+    pub rumble: f32,
+    /// Kick from the last impact (-1.0 - 1.0), decays quickly
+    pub jolt: f32,
+}
+
+impl ForceFeedback {
+    /// Constant force for the wheel motor with the configured gains applied
+    pub fn constant_force(&self, settings: &SteeringWheelSettings) -> f32 {
+        ((self.aligning * settings.aligning_gain + self.jolt * settings.jolt_gain) * settings.ffb_gain).clamp(-1.0, 1.0)
+    }
+
+    /// Strong and weak rumble motor strengths with the configured gains applied
+    pub fn rumble_motors(&self, settings: &SteeringWheelSettings) -> (f32, f32) {
+        let strong = (self.jolt.abs() * settings.jolt_gain * settings.ffb_gain).clamp(0.0, 1.0);
+        let weak = (self.rumble * settings.rumble_gain * settings.ffb_gain).clamp(0.0, 1.0);
+        (strong, weak)
+    }
+}
+
+/// Self-aligning torque (-1.0 - 1.0) from the front tires' `(slip angle, load)`, pulling toward zero slip.
+/// The tire's pneumatic trail shrinks as it starts to slide, so the wheel goes light past the grip limit.
+pub fn self_aligning_torque(front: &[(f32, f32)], reference_load: f32) -> f32 {
+    if front.is_empty() || reference_load <= 0.0 {
+        return 0.0;
+    }
+    let torque: f32 = front
+        .iter()
+        .map(|&(slip_angle, load)| {
+            let x = slip_angle / PEAK_SLIP_ANGLE;
+            x * (1.0 - x.abs()).exp() * load / reference_load
+        })
+        .sum();
+    (-torque / front.len() as f32).clamp(-1.0, 1.0)
+}
+
+/// Rumble (0.0 - 1.0) from how fast the suspension is working, faded in with speed
+pub fn surface_rumble(suspension_velocities: &[f32], speed: f32) -> f32 {
+    if suspension_velocities.is_empty() {
+        return 0.0;
+    }
+    let mean_square =
+        suspension_velocities.iter().map(|velocity| velocity * velocity).sum::<f32>() / suspension_velocities.len() as f32;
+    let roughness = (mean_square.sqrt() / FULL_RUMBLE_SUSPENSION_SPEED).min(1.0);
+    roughness * (speed.abs() / 5.0).min(1.0)
+}
+
+/// Works out the aligning torque, rumble and jolts for the wheel's player
+#[allow(clippy::too_many_arguments)]
+fn update_force_feedback(
+    time: Res<Time>,
+    settings: Res<SteeringWheelSettings>,
+    device: Res<SteeringWheelDevice>,
+    mut impacts: EventReader<ImpactEvent>,
+    wheels: Query<&Wheel>,
+    hubs: Query<&WheelHub>,
+    vehicles: Query<(Entity, &Vehicle, &PlayerId, &GlobalTransform)>,
+    mut feedback: ResMut<ForceFeedback>,
+) {
+    let player_vehicle = vehicles.iter().find(|(_, _, player, _)| player.0 == settings.player);
+    let (Some(_), true, Some((entity, vehicle, _, transform))) = (device.0, settings.force_feedback, player_vehicle) else {
+        impacts.clear();
+        if *feedback != ForceFeedback::default() {
+            *feedback = ForceFeedback::default();
+        }
+        return;
+    };
+
+    let reference_load = vehicle.config.mass * 9.81 / 4.0;
+    let front: Vec<_> = wheels
+        .iter_many(&vehicle.wheel_entities[..2])
+        .filter(|wheel| wheel.ground_contact)
+        .map(|wheel| {
+            // Wheels without a load from the suspension yet carry their static share
+            let load = if wheel.normal_force > 0.0 { wheel.normal_force } else { reference_load };
+            (wheel.slip_angle, load)
+        })
+        .collect();
+    let suspension: Vec<_> = hubs
+        .iter_many(vehicle.wheel_entities)
+        .filter(|hub| hub.state.ground_contact)
+        .map(|hub| hub.state.velocity)
+        .collect();
+
+    let mut jolt = feedback.jolt;
+    jolt -= jolt.signum() * (JOLT_DECAY * time.delta_seconds()).min(jolt.abs());
+    for impact in impacts.read().filter(|impact| impact.involves(entity)) {
+        // Hits from the left kick the wheel right and the other way round
+        let side = (impact.position - transform.translation()).dot(transform.right());
+        let kick = -side.signum() * impact.intensity;
+        if kick.abs() > jolt.abs() {
+            jolt = kick;
+        }
+    }
+
+    *feedback = ForceFeedback {
+        aligning: self_aligning_torque(&front, reference_load),
+        rumble: surface_rumble(&suspension, vehicle.vehicle_speed),
+        jolt,
+    };
+}
+
+/// Plays rumble and jolts through gamepad rumble, which every backend supports
+fn send_rumble(
+    time: Res<Time>,
+    settings: Res<SteeringWheelSettings>,
+    device: Res<SteeringWheelDevice>,
+    feedback: Res<ForceFeedback>,
+    mut since_last: Local<f32>,
+    mut requests: EventWriter<GamepadRumbleRequest>,
+) {
+    let Some(gamepad) = device.0 else {
+        return;
+    };
+    *since_last += time.delta_seconds();
+    if *since_last < RUMBLE_INTERVAL {
+        return;
+    }
+    *since_last = 0.0;
+
+    let (strong_motor, weak_motor) = feedback.rumble_motors(&settings);
+    if strong_motor <= 0.0 && weak_motor <= 0.0 {
+        return;
+    }
+    requests.send(GamepadRumbleRequest::Add {
+        gamepad,
+        duration: Duration::from_secs_f32(RUMBLE_INTERVAL),
+        intensity: GamepadRumbleIntensity { strong_motor, weak_motor },
+    });
+}
+
+pub(super) fn build(app: &mut App) {
+    app.init_resource::<ForceFeedback>()
+        .add_systems(Update, (update_force_feedback, send_rumble).chain());
+
+    #[cfg(feature = "force-feedback")]
+    super::sdl_backend::build(app);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aligning_torque_opposes_slip_and_goes_light() {
+        let load = 3600.0;
+        assert_eq!(self_aligning_torque(&[], load), 0.0);
+        let grip = self_aligning_torque(&[(PEAK_SLIP_ANGLE, load), (PEAK_SLIP_ANGLE, load)], load);
+        assert!((grip + 1.0).abs() < 1e-5);
+        let sliding = self_aligning_torque(&[(PEAK_SLIP_ANGLE * 3.0, load)], load);
+        assert!(sliding < 0.0 && sliding > grip);
+        assert!(self_aligning_torque(&[(-0.05, load)], load) > 0.0);
+    }
+
+    #[test]
+    fn test_rumble_needs_rough_ground_and_speed() {
+        assert_eq!(surface_rumble(&[0.0; 4], 20.0), 0.0);
+        assert_eq!(surface_rumble(&[0.8; 4], 0.0), 0.0);
+        assert!((surface_rumble(&[0.5, -0.5], 10.0) - 0.5).abs() < 1e-5);
+        assert_eq!(surface_rumble(&[3.0], 10.0), 1.0);
+    }
+
+    #[test]
+    fn test_gains_scale_output() {
+        let settings = SteeringWheelSettings {
+            ffb_gain: 0.5,
+            ..default()
+        };
+        let feedback = ForceFeedback {
+            aligning: 0.4,
+            rumble: 1.0,
+            jolt: -1.0,
+        };
+        assert!((feedback.constant_force(&settings) + 0.3).abs() < 1e-5);
+        assert_eq!(feedback.rumble_motors(&settings), (0.5, 0.25));
+    }
+}
@@ -0,0 +1,243 @@
+/// Steering wheel input and force feedback
+///
+/// Wheels show up as gamepads, so the wheel, pedals and shifter are read through Bevy's gamepad
+/// input like any controller. The first gamepad whose name looks like a wheel is taken over for
+/// the configured player.
+///
+/// Force feedback combines self-aligning torque from the front tires, rumble from the surface
+/// under the suspension and jolts from impacts. Without the `force-feedback` feature only the
+/// rumble and jolts reach the device, through gamepad rumble. With it, an SDL2 haptic backend
+/// also plays the aligning torque as a constant force.
+mod force_feedback;
+#[cfg(feature = "force-feedback")]
+mod sdl_backend;
+
+use bevy::input::gamepad::{GamepadConnection, GamepadConnectionEvent};
+use bevy::prelude::*;
+
+use super::split_screen::{PlayerId, PlayerInput, PlayerInputDevice};
+
+pub use force_feedback::{self_aligning_torque, surface_rumble, ForceFeedback};
+
+/// Names of common wheels, matched case insensitively against the gamepad name
+const WHEEL_NAME_HINTS: &[&str] = &["wheel", "racing", "driving force", "g29", "g920", "g923", "t300", "t150", "fanatec", "moza"];
+
+/// A pedal on a gamepad axis
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PedalAxis {
+    pub axis: GamepadAxisType,
+    /// Axis reads highest when the pedal is released
+    pub inverted: bool,
+    /// Axis covers -1.0 - 1.0 rather than 0.0 - 1.0
+    pub full_range: bool,
+}
+
+impl PedalAxis {
+    /// Pedal travel (0.0 - 1.0) from a raw axis value
+    pub fn travel(&self, value: f32) -> f32 {
+        let value = if self.inverted { -value } else { value };
+        let travel = if self.full_range { (value + 1.0) * 0.5 } else { value };
+        travel.clamp(0.0, 1.0)
+    }
+}
+
+/// How a steering wheel maps onto vehicle controls and how strong its force feedback is
+#[derive(Resource, Debug, Clone)]
+pub struct SteeringWheelSettings {
+    /// Player the wheel is given to
+    pub player: usize,
+    pub steering_axis: GamepadAxisType,
+    /// Lock to lock rotation of the hardware in degrees
+    pub hardware_rotation: f32,
+    /// Lock to lock rotation that gives full steering lock in game, less for quicker steering
+    pub rotation_range: f32,
+    pub throttle: PedalAxis,
+    pub brake: PedalAxis,
+    pub handbrake_button: GamepadButtonType,
+    /// H-shifter buttons for gears 1 - 6
+    pub shifter_gears: [GamepadButtonType; 6],
+    pub shifter_reverse: GamepadButtonType,
+    pub force_feedback: bool,
+    /// Overall force feedback strength (0.0 - 1.0)
+    pub ffb_gain: f32,
+    /// Weight of the self-aligning torque
+    pub aligning_gain: f32,
+    /// Weight of the surface rumble
+    pub rumble_gain: f32,
+    /// Weight of impact jolts
+    pub jolt_gain: f32,
+}
+
+impl Default for SteeringWheelSettings {
+    fn default() -> Self {
+        // Logitech G29 layout with the pedals on their own axes
+        Self {
+            player: 0,
+            steering_axis: GamepadAxisType::LeftStickX,
+            hardware_rotation: 900.0,
+            rotation_range: 540.0,
+            throttle: PedalAxis {
+                axis: GamepadAxisType::RightZ,
+                inverted: true,
+                full_range: true,
+            },
+            brake: PedalAxis {
+                axis: GamepadAxisType::LeftZ,
+                inverted: true,
+                full_range: true,
+            },
+            handbrake_button: GamepadButtonType::South,
+            shifter_gears: [
+                GamepadButtonType::Other(12),
+                GamepadButtonType::Other(13),
+                GamepadButtonType::Other(14),
+                GamepadButtonType::Other(15),
+                GamepadButtonType::Other(16),
+                GamepadButtonType::Other(17),
+            ],
+            shifter_reverse: GamepadButtonType::Other(18),
+            force_feedback: true,
+            ffb_gain: 0.8,
+            aligning_gain: 1.0,
+            rumble_gain: 0.5,
+            jolt_gain: 1.0,
+        }
+    }
+}
+
+impl SteeringWheelSettings {
+    /// Steering input (-1.0 right - 1.0 left) from the raw wheel axis
+    pub fn steering(&self, axis: f32) -> f32 {
+        // Wheel axes grow to the right, steering grows to the left
+        let degrees = -axis * self.hardware_rotation * 0.5;
+        (degrees / (self.rotation_range * 0.5).max(1.0)).clamp(-1.0, 1.0)
+    }
+
+    /// Gear held on the H-shifter, -1 for reverse and 0 for neutral
+    pub fn shifter_gear(&self, pressed: impl Fn(GamepadButtonType) -> bool) -> i32 {
+        if pressed(self.shifter_reverse) {
+            return -1;
+        }
+        self.shifter_gears
+            .iter()
+            .position(|button| pressed(*button))
+            .map_or(0, |index| index as i32 + 1)
+    }
+
+    /// Controls for this frame from the wheel's axes and buttons
+    pub fn read(&self, axis: impl Fn(GamepadAxisType) -> f32, pressed: impl Fn(GamepadButtonType) -> bool) -> PlayerInput {
+        PlayerInput {
+            throttle: self.throttle.travel(axis(self.throttle.axis)),
+            brake: self.brake.travel(axis(self.brake.axis)),
+            steering: self.steering(axis(self.steering_axis)),
+            handbrake: pressed(self.handbrake_button),
+            gear: Some(self.shifter_gear(&pressed)),
+            ..default()
+        }
+    }
+}
+
+/// The gamepad recognised as a steering wheel, if one is connected
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SteeringWheelDevice(pub Option<Gamepad>);
+
+pub fn is_steering_wheel(name: &str) -> bool {
+    let name = name.to_lowercase();
+    WHEEL_NAME_HINTS.iter().any(|hint| name.contains(hint))
+}
+
+/// Picks up wheels as they connect and lets go of them when they disconnect
+fn detect_steering_wheel(
+    mut connections: EventReader<GamepadConnectionEvent>,
+    mut device: ResMut<SteeringWheelDevice>,
+) {
+    for event in connections.read() {
+        match &event.connection {
+            GamepadConnection::Connected(info) if device.0.is_none() && is_steering_wheel(&info.name) => {
+                device.0 = Some(event.gamepad);
+            }
+            GamepadConnection::Disconnected if device.0 == Some(event.gamepad) => {
+                device.0 = None;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Switches the wheel's player over to it, and back to the keyboard once it's gone
+fn assign_steering_wheel(
+    device: Res<SteeringWheelDevice>,
+    settings: Res<SteeringWheelSettings>,
+    mut players: Query<(&PlayerId, &mut PlayerInputDevice)>,
+) {
+    for (player, mut input_device) in players.iter_mut() {
+        if player.0 != settings.player {
+            continue;
+        }
+        let wanted = match (device.0, *input_device) {
+            (Some(_), _) => PlayerInputDevice::SteeringWheel,
+            (None, PlayerInputDevice::SteeringWheel) => PlayerInputDevice::Keyboard,
+            (None, current) => current,
+        };
+        if *input_device != wanted {
+            *input_device = wanted;
+        }
+    }
+}
+
+/// Plugin for steering wheel input and force feedback
+pub struct SteeringWheelPlugin;
+
+impl Plugin for SteeringWheelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SteeringWheelSettings>()
+            .init_resource::<SteeringWheelDevice>()
+            .add_systems(PreUpdate, (detect_steering_wheel, assign_steering_wheel).chain());
+        force_feedback::build(app);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation_range_scales_steering() {
+        let settings = SteeringWheelSettings::default();
+        assert_eq!(settings.steering(0.0), 0.0);
+        // 270 degrees to the right is full lock with a 540 degree range
+        assert_eq!(settings.steering(270.0 / 450.0), -1.0);
+        assert!((settings.steering(-0.3) - 0.5).abs() < 1e-5);
+        assert_eq!(settings.steering(1.0), -1.0);
+    }
+
+    #[test]
+    fn test_inverted_full_range_pedal() {
+        let pedal = SteeringWheelSettings::default().throttle;
+        assert_eq!(pedal.travel(1.0), 0.0);
+        assert_eq!(pedal.travel(-1.0), 1.0);
+        assert_eq!(pedal.travel(0.0), 0.5);
+        let half_axis = PedalAxis {
+            axis: GamepadAxisType::RightZ,
+            inverted: false,
+            full_range: false,
+        };
+        assert_eq!(half_axis.travel(-0.4), 0.0);
+        assert_eq!(half_axis.travel(0.4), 0.4);
+    }
+
+    #[test]
+    fn test_shifter_gears() {
+        let settings = SteeringWheelSettings::default();
+        assert_eq!(settings.shifter_gear(|_| false), 0);
+        assert_eq!(settings.shifter_gear(|button| button == GamepadButtonType::Other(14)), 3);
+        assert_eq!(settings.shifter_gear(|button| button == settings.shifter_reverse), -1);
+    }
+
+    #[test]
+    fn test_wheel_names() {
+        assert!(is_steering_wheel("Logitech G29 Driving Force Racing Wheel"));
+        assert!(is_steering_wheel("Thrustmaster T300RS"));
+        assert!(!is_steering_wheel("Xbox Wireless Controller"));
+    }
+}
@@ -0,0 +1,156 @@
+use std::ffi::CStr;
+
+use bevy::prelude::*;
+use sdl2::sys as sdl;
+use thiserror::Error;
+
+use super::force_feedback::ForceFeedback;
+use super::{is_steering_wheel, SteeringWheelDevice, SteeringWheelSettings};
+
+/// Constant force changes smaller than this aren't worth an effect update
+const FORCE_EPSILON: i16 = 64;
+
+#[derive(Error, Debug)]
+pub enum ForceFeedbackError {
+    #[error("failed to initialize SDL haptics: {0}")]
+    Init(String),
+    #[error("no haptic steering wheel found")]
+    NoDevice,
+    #[error("wheel doesn't support constant force effects")]
+    Unsupported,
+    #[error("haptic effect failed: {0}")]
+    Effect(String),
+}
+
+fn sdl_error() -> String {
+    // SAFETY: SDL_GetError always returns a valid, nul terminated string
+    unsafe { CStr::from_ptr(sdl::SDL_GetError()) }.to_string_lossy().into_owned()
+}
+
+/// Open SDL haptic device playing a single constant force effect
+pub struct SdlForceFeedback {
+    _sdl: sdl2::Sdl,
+    _haptic_subsystem: sdl2::HapticSubsystem,
+    haptic: *mut sdl::SDL_Haptic,
+    effect: sdl::SDL_HapticEffect,
+    effect_id: i32,
+    level: i16,
+}
+
+impl SdlForceFeedback {
+    /// Opens the first haptic device that looks like a steering wheel
+    pub fn open() -> Result<Self, ForceFeedbackError> {
+        let sdl = sdl2::init().map_err(ForceFeedbackError::Init)?;
+        let haptic_subsystem = sdl.haptic().map_err(ForceFeedbackError::Init)?;
+
+        // SAFETY: the haptic subsystem is initialized for as long as `haptic_subsystem` lives,
+        // which outlives the device since both are owned by `Self`
+        unsafe {
+            let index = (0..sdl::SDL_NumHaptics())
+                .find(|&index| {
+                    let name = sdl::SDL_HapticName(index);
+                    !name.is_null() && is_steering_wheel(&CStr::from_ptr(name).to_string_lossy())
+                })
+                .ok_or(ForceFeedbackError::NoDevice)?;
+            let haptic = sdl::SDL_HapticOpen(index);
+            if haptic.is_null() {
+                return Err(ForceFeedbackError::Init(sdl_error()));
+            }
+            if sdl::SDL_HapticQuery(haptic) & sdl::SDL_HAPTIC_CONSTANT == 0 {
+                sdl::SDL_HapticClose(haptic);
+                return Err(ForceFeedbackError::Unsupported);
+            }
+
+            let mut effect: sdl::SDL_HapticEffect = std::mem::zeroed();
+            effect.constant.type_ = sdl::SDL_HAPTIC_CONSTANT as u16;
+            effect.constant.direction.type_ = sdl::SDL_HAPTIC_CARTESIAN as u8;
+            effect.constant.direction.dir[0] = 1;
+            effect.constant.length = sdl::SDL_HAPTIC_INFINITY;
+            let effect_id = sdl::SDL_HapticNewEffect(haptic, &mut effect);
+            if effect_id < 0 || sdl::SDL_HapticRunEffect(haptic, effect_id, 1) < 0 {
+                let error = sdl_error();
+                sdl::SDL_HapticClose(haptic);
+                return Err(ForceFeedbackError::Effect(error));
+            }
+
+            Ok(Self {
+                _sdl: sdl,
+                _haptic_subsystem: haptic_subsystem,
+                haptic,
+                effect,
+                effect_id,
+                level: 0,
+            })
+        }
+    }
+
+    /// Sets the constant force (-1.0 - 1.0), positive turns the wheel left
+    pub fn set_force(&mut self, force: f32) -> Result<(), ForceFeedbackError> {
+        // SDL's cartesian x points right
+        let level = (-force.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        // Tiny changes aren't felt, but the force always gets back to exactly zero
+        if level == self.level || (level != 0 && (level as i32 - self.level as i32).abs() < FORCE_EPSILON as i32) {
+            return Ok(());
+        }
+        self.level = level;
+        // SAFETY: `haptic` and `effect_id` stay valid until drop
+        unsafe {
+            self.effect.constant.level = level;
+            if sdl::SDL_HapticUpdateEffect(self.haptic, self.effect_id, &mut self.effect) < 0 {
+                return Err(ForceFeedbackError::Effect(sdl_error()));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SdlForceFeedback {
+    fn drop(&mut self) {
+        // SAFETY: opened in `open` and not closed anywhere else
+        unsafe {
+            sdl::SDL_HapticDestroyEffect(self.haptic, self.effect_id);
+            sdl::SDL_HapticClose(self.haptic);
+        }
+    }
+}
+
+/// Opens the SDL device when a wheel connects and closes it when it goes away
+fn manage_sdl_device(world: &mut World) {
+    let connected = world.resource::<SteeringWheelDevice>().0.is_some();
+    let open = world.get_non_send_resource::<SdlForceFeedback>().is_some();
+    if connected && !open {
+        match SdlForceFeedback::open() {
+            Ok(device) => world.insert_non_send_resource(device),
+            // Rumble through the gamepad still works, only the aligning torque is lost.
+            // Runs on device changes only, so this is retried when the wheel reconnects.
+            Err(error) => warn!("Steering wheel force feedback unavailable: {error}"),
+        }
+    } else if !connected && open {
+        world.remove_non_send_resource::<SdlForceFeedback>();
+    }
+}
+
+/// Plays the aligning torque and jolts as a constant force
+fn play_constant_force(
+    settings: Res<SteeringWheelSettings>,
+    feedback: Res<ForceFeedback>,
+    device: Option<NonSendMut<SdlForceFeedback>>,
+) {
+    let Some(mut device) = device else {
+        return;
+    };
+    if let Err(error) = device.set_force(feedback.constant_force(&settings)) {
+        warn!("{error}");
+    }
+}
+
+pub(super) fn build(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            manage_sdl_device.run_if(resource_changed::<SteeringWheelDevice>()),
+            play_constant_force,
+        )
+            .chain(),
+    );
+}
@@ -0,0 +1,227 @@
+//! Ambient AI traffic on the trails
+//!
+//! Traffic vehicles spawn out of sight around the players and drive to an open point of interest,
+//! or somewhere nearby when none is open. They leave once they get there or give up on a route, out
+//! of the players' sight, or else stay parked until left far behind. How many are out follows the
+//! [`WorldClock`], busy in the day and quiet at night.
+
+use bevy::prelude::*;
+
+use super::routing::{RouteFailedEvent, RouteFinishedEvent, RouteToEvent};
+use super::trails::PointOfInterest;
+use super::wildlife::WildlifeRng;
+use super::world_clock::{is_open, ScheduleOpen, WorldClock};
+use crate::game::vehicle::{
+    AiDriver, PlayerOrAi, SpawnVehicleEvent, Vehicle, VehicleDefinition, VehicleSpawnedEvent, VehicleSpawner,
+};
+use crate::terrain::{Drivability, DrivabilityMap, DrivabilitySettings, TerrainQuery};
+
+/// Marks an AI vehicle that's part of the ambient traffic
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct TrafficVehicle;
+
+/// How much traffic there is and where it appears
+#[derive(Resource, Debug, Clone)]
+pub struct TrafficSettings {
+    pub enabled: bool,
+    /// Traffic vehicles out at the busiest hour, the world clock scales this down
+    pub max_vehicles: usize,
+    /// Seconds between spawns
+    pub spawn_interval: f32,
+    /// Ring around a player traffic spawns in, in meters
+    pub spawn_min_distance: f32,
+    pub spawn_max_distance: f32,
+    /// Traffic further than this from every player is removed
+    pub despawn_distance: f32,
+    /// Without an open point of interest traffic drives to a spot this far away
+    pub wander_distance: f32,
+}
+
+impl Default for TrafficSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_vehicles: 6,
+            spawn_interval: 4.0,
+            spawn_min_distance: 150.0,
+            spawn_max_distance: 300.0,
+            despawn_distance: 450.0,
+            wander_distance: 400.0,
+        }
+    }
+}
+
+impl TrafficSettings {
+    /// Traffic vehicles wanted out at `density` (0.0 - 1.0)
+    pub fn target_vehicles(&self, density: f32) -> usize {
+        (self.max_vehicles as f32 * density.clamp(0.0, 1.0)).round() as usize
+    }
+}
+
+/// Paint the traffic comes in
+const TRAFFIC_COLORS: [Color; 5] = [
+    Color::rgb(0.8, 0.8, 0.78),
+    Color::rgb(0.15, 0.2, 0.3),
+    Color::rgb(0.35, 0.4, 0.25),
+    Color::rgb(0.6, 0.45, 0.25),
+    Color::rgb(0.1, 0.1, 0.1),
+];
+
+/// Spawn countdown and random numbers for the traffic
+#[derive(Resource, Debug, Clone)]
+pub struct Traffic {
+    pub until_next_spawn: f32,
+    /// Its own stream, reseeded with the others in determinism mode
+    pub rng: WildlifeRng,
+}
+
+impl Default for Traffic {
+    fn default() -> Self {
+        Self { until_next_spawn: 0.0, rng: WildlifeRng::from_seed(0x7f4a_7c15) }
+    }
+}
+
+/// Spawns traffic around the players until there's as much as the hour calls for
+#[allow(clippy::too_many_arguments)]
+fn spawn_traffic(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<TrafficSettings>,
+    clock: Res<WorldClock>,
+    map: Res<DrivabilityMap>,
+    drivability: Res<DrivabilitySettings>,
+    terrain: Res<TerrainQuery>,
+    mut traffic: ResMut<Traffic>,
+    mut spawner: VehicleSpawner,
+    players: Query<&GlobalTransform, (With<Vehicle>, Without<AiDriver>)>,
+    vehicles: Query<(), With<TrafficVehicle>>,
+    points: Query<(&GlobalTransform, Option<&ScheduleOpen>), With<PointOfInterest>>,
+    mut spawned: EventWriter<VehicleSpawnedEvent>,
+    mut routes: EventWriter<RouteToEvent>,
+) {
+    traffic.until_next_spawn -= time.delta_seconds();
+    if !settings.enabled || traffic.until_next_spawn > 0.0 {
+        return;
+    }
+    traffic.until_next_spawn = settings.spawn_interval;
+    if vehicles.iter().count() >= settings.target_vehicles(clock.traffic_density) {
+        return;
+    }
+    let players: Vec<Vec3> = players.iter().map(|transform| transform.translation()).collect();
+    if players.is_empty() {
+        return;
+    }
+
+    let player = players[(traffic.rng.next_f32() * players.len() as f32) as usize % players.len()];
+    let mut position = traffic.rng.ring_point(player, settings.spawn_min_distance, settings.spawn_max_distance);
+    // Only on open ground, the next spawn tries somewhere else
+    if map.drivability_at(position, &drivability) != Some(Drivability::Drivable) {
+        return;
+    }
+    if players.iter().any(|player| player.distance(position) < settings.spawn_min_distance) {
+        return;
+    }
+    position.y = terrain.height(position.x, position.z).unwrap_or(player.y) + 1.0;
+
+    let open: Vec<Vec3> = points
+        .iter()
+        .filter(|(_, open)| is_open(*open))
+        .map(|(transform, _)| transform.translation())
+        .collect();
+    let destination = if open.is_empty() {
+        traffic.rng.ring_point(position, settings.wander_distance * 0.5, settings.wander_distance)
+    } else {
+        open[(traffic.rng.next_f32() * open.len() as f32) as usize % open.len()]
+    };
+
+    let color = TRAFFIC_COLORS[(traffic.rng.next_f32() * TRAFFIC_COLORS.len() as f32) as usize % TRAFFIC_COLORS.len()];
+    let heading = (destination - position).xz();
+    let request = SpawnVehicleEvent {
+        definition: VehicleDefinition { body_color: color, ..default() },
+        transform: Transform::from_translation(position).looking_to(Vec3::new(heading.x, 0.0, heading.y), Vec3::Y),
+        driver: PlayerOrAi::Ai,
+    };
+    let vehicle = spawner.spawn(&request);
+    commands.entity(vehicle).insert((TrafficVehicle, Name::new("Traffic")));
+    spawned.send(VehicleSpawnedEvent { vehicle, driver: PlayerOrAi::Ai });
+    routes.send(RouteToEvent { vehicle, destination });
+}
+
+/// Takes traffic off the trails once it has arrived, got stuck, been left behind or isn't wanted
+/// at this hour any more
+fn despawn_traffic(
+    settings: Res<TrafficSettings>,
+    clock: Res<WorldClock>,
+    mut spawner: VehicleSpawner,
+    players: Query<&GlobalTransform, (With<Vehicle>, Without<AiDriver>)>,
+    vehicles: Query<(Entity, &GlobalTransform), With<TrafficVehicle>>,
+    mut finished: EventReader<RouteFinishedEvent>,
+    mut failed: EventReader<RouteFailedEvent>,
+) {
+    let players: Vec<Vec3> = players.iter().map(|transform| transform.translation()).collect();
+    let out_of_sight = |position: Vec3| {
+        players.iter().all(|player| player.distance(position) > settings.spawn_min_distance)
+    };
+
+    let mut leaving: Vec<Entity> = finished
+        .read()
+        .map(|event| event.vehicle)
+        .chain(failed.read().map(|event| event.vehicle))
+        .filter(|vehicle| vehicles.get(*vehicle).is_ok_and(|(_, transform)| out_of_sight(transform.translation())))
+        .collect();
+    let mut remaining = Vec::new();
+    for (vehicle, transform) in vehicles.iter() {
+        if leaving.contains(&vehicle) {
+            continue;
+        }
+        let position = transform.translation();
+        if players.iter().all(|player| player.distance(position) > settings.despawn_distance) {
+            leaving.push(vehicle);
+        } else {
+            remaining.push((vehicle, position));
+        }
+    }
+    // Thin out to the hour's traffic, only where no player would see a car vanish
+    let target = if settings.enabled { settings.target_vehicles(clock.traffic_density) } else { 0 };
+    let excess = remaining.len().saturating_sub(target);
+    leaving.extend(
+        remaining
+            .into_iter()
+            .filter(|(_, position)| out_of_sight(*position))
+            .take(excess)
+            .map(|(vehicle, _)| vehicle),
+    );
+
+    for vehicle in leaving {
+        spawner.despawn(vehicle);
+    }
+}
+
+/// Plugin for the ambient traffic
+pub struct TrafficPlugin;
+
+impl Plugin for TrafficPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TrafficSettings>()
+            .init_resource::<Traffic>()
+            .init_resource::<WorldClock>()
+            .init_resource::<DrivabilityMap>()
+            .init_resource::<DrivabilitySettings>()
+            .init_resource::<TerrainQuery>()
+            .add_systems(Update, (spawn_traffic, despawn_traffic).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traffic_follows_density() {
+        let settings = TrafficSettings::default();
+        assert_eq!(settings.target_vehicles(1.0), 6);
+        assert_eq!(settings.target_vehicles(0.5), 3);
+        assert_eq!(settings.target_vehicles(0.05), 0);
+        assert_eq!(settings.target_vehicles(2.0), 6);
+    }
+}
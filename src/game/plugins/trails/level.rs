@@ -0,0 +1,225 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+
+use super::super::world_clock::OpenHours;
+use super::TrailCondition;
+use crate::assets::{check_schema_version, read_schema_version, SchemaVersionError};
+use crate::terrain::TerrainCarve;
+
+/// Difficulty rating of a trail, from graded dirt road to expert-only
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrailDifficulty {
+    #[default]
+    Easy,
+    Moderate,
+    Difficult,
+    Severe,
+    Extreme,
+}
+
+impl TrailDifficulty {
+    pub const ALL: [TrailDifficulty; 5] = [
+        TrailDifficulty::Easy,
+        TrailDifficulty::Moderate,
+        TrailDifficulty::Difficult,
+        TrailDifficulty::Severe,
+        TrailDifficulty::Extreme,
+    ];
+
+    /// This rating raised by `steps`, topping out at extreme
+    pub fn raised(self, steps: u8) -> Self {
+        let index = Self::ALL.iter().position(|difficulty| *difficulty == self).unwrap_or(0);
+        Self::ALL[(index + steps as usize).min(Self::ALL.len() - 1)]
+    }
+
+    /// Locale key of the rating's name
+    pub fn name_key(self) -> &'static str {
+        match self {
+            TrailDifficulty::Easy => "trail.difficulty.easy",
+            TrailDifficulty::Moderate => "trail.difficulty.moderate",
+            TrailDifficulty::Difficult => "trail.difficulty.difficult",
+            TrailDifficulty::Severe => "trail.difficulty.severe",
+            TrailDifficulty::Extreme => "trail.difficulty.extreme",
+        }
+    }
+}
+
+fn default_rain_steps() -> u8 {
+    2
+}
+
+fn default_poi_radius() -> f32 {
+    15.0
+}
+
+/// A trail as written in a level file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrailDesc {
+    pub name: String,
+    /// Rating in dry conditions
+    pub difficulty: TrailDifficulty,
+    /// Points along the trail, drawn on the map
+    pub path: Vec<[f32; 3]>,
+    /// How many steps a soaked trail climbs above its dry rating. Rocky trails that drain fast use 0.
+    #[serde(default = "default_rain_steps")]
+    pub rain_steps: u8,
+    /// Cuts the trail into the terrain along a spline through `path`, left as the ground lies when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub carve: Option<TerrainCarve>,
+    /// Surfaced road rather than dirt, some delivery jobs have to stay off it
+    #[serde(default)]
+    pub paved: bool,
+}
+
+/// A water or gully crossing that rain can make impassable
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrossingDesc {
+    pub name: String,
+    pub position: [f32; 3],
+    /// Half width of the crossing in meters, the barrier spans it when closed
+    pub width: f32,
+    /// Rotation around the vertical axis in degrees
+    #[serde(default)]
+    pub rotation: f32,
+    /// Closed once the trails are at least this wet, never closed when unset
+    #[serde(default)]
+    pub closes_when: Option<TrailCondition>,
+}
+
+/// A point of interest that greets vehicles passing by
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoiDesc {
+    pub name: String,
+    pub position: [f32; 3],
+    #[serde(default = "default_poi_radius")]
+    pub radius: f32,
+    pub message: String,
+    /// Shown instead of `message` while the trails are wet or soaked
+    #[serde(default)]
+    pub wet_message: Option<String>,
+    /// Camps, gas stations and the like only greet vehicles while open, `None` is always open
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open_hours: Option<OpenHours>,
+    /// Has pumps that refuel vehicles pulled up to them
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub gas_station: bool,
+}
+
+/// A trailer left parked in a level, ready to hitch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrailerDesc {
+    pub position: [f32; 3],
+    /// Rotation around the vertical axis in degrees
+    #[serde(default)]
+    pub rotation: f32,
+    /// Load already on the trailer in kg
+    #[serde(default)]
+    pub cargo_mass: f32,
+}
+
+/// Trails, crossings, points of interest and parked trailers of a level, loaded from `*.trails.json`
+#[derive(Asset, TypePath, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LevelTrails {
+    pub trails: Vec<TrailDesc>,
+    #[serde(default)]
+    pub crossings: Vec<CrossingDesc>,
+    #[serde(default)]
+    pub points_of_interest: Vec<PoiDesc>,
+    #[serde(default)]
+    pub trailers: Vec<TrailerDesc>,
+}
+
+/// Errors produced while loading level trail files
+#[derive(Debug, thiserror::Error)]
+pub enum LevelTrailsError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Schema(#[from] SchemaVersionError),
+}
+
+/// Asset loader for level trail files
+#[derive(Default)]
+pub struct LevelTrailsLoader;
+
+impl AssetLoader for LevelTrailsLoader {
+    type Asset = LevelTrails;
+    type Settings = ();
+    type Error = LevelTrailsError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<LevelTrails, LevelTrailsError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            check_schema_version(read_schema_version(&bytes)?)?;
+            Ok(serde_json::from_slice(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["trails.json"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::TerrainLayer;
+
+    #[test]
+    fn test_parse_level_trails() {
+        let json = r#"{
+            "trails": [
+                { "name": "Ridge Run", "difficulty": "difficult", "path": [[0.0, 0.0, 0.0], [10.0, 2.0, -40.0]] },
+                { "name": "Slickrock", "difficulty": "severe", "path": [], "rain_steps": 0 },
+                {
+                    "name": "Quarry Road",
+                    "difficulty": "easy",
+                    "path": [[0.0, 1.0, 0.0], [30.0, 2.0, 0.0]],
+                    "carve": { "width": 5.0, "surface": "sand" }
+                }
+            ],
+            "crossings": [
+                { "name": "Creek Ford", "position": [5.0, 0.0, -20.0], "width": 6.0, "closes_when": "soaked" }
+            ],
+            "points_of_interest": [
+                { "name": "Lookout", "position": [10.0, 2.0, -40.0], "message": "Great view", "wet_message": "Slippery up here" },
+                { "name": "Gas", "position": [0.0, 0.0, 0.0], "message": "Fill up", "gas_station": true }
+            ],
+            "trailers": [
+                { "position": [-10.0, 0.5, 6.0], "rotation": 90.0, "cargo_mass": 300.0 }
+            ]
+        }"#;
+        let level: LevelTrails = serde_json::from_str(json).unwrap();
+        assert_eq!(level.trails[0].difficulty, TrailDifficulty::Difficult);
+        assert_eq!(level.trails[0].rain_steps, 2);
+        assert_eq!(level.trails[1].rain_steps, 0);
+        assert_eq!(level.trails[0].carve, None);
+        let carve = level.trails[2].carve.unwrap();
+        assert_eq!((carve.width, carve.falloff, carve.surface), (5.0, 4.0, TerrainLayer::Sand));
+        assert_eq!(level.crossings[0].closes_when, Some(TrailCondition::Soaked));
+        assert_eq!(level.points_of_interest[0].radius, 15.0);
+        assert!(!level.points_of_interest[0].gas_station);
+        assert!(level.points_of_interest[1].gas_station);
+        assert_eq!(level.trailers[0].cargo_mass, 300.0);
+    }
+
+    #[test]
+    fn test_difficulty_tops_out_at_extreme() {
+        assert_eq!(TrailDifficulty::Easy.raised(0), TrailDifficulty::Easy);
+        assert_eq!(TrailDifficulty::Moderate.raised(2), TrailDifficulty::Severe);
+        assert_eq!(TrailDifficulty::Severe.raised(3), TrailDifficulty::Extreme);
+    }
+}
@@ -0,0 +1,476 @@
+/// Trail ratings and route conditions
+///
+/// Trails, crossings, points of interest and parked trailers come from a level's `*.trails.json`
+/// file. Rain builds up in [`WeatherHistory`], which is kept with the save so the ground stays wet
+/// between sessions.
+/// Wet trails rate harder, some crossings close and points of interest switch to their wet message.
+/// Points of interest with opening hours are only visited while the world clock has them open.
+/// Trails with a carve are cut into the terrain, and [`TrailTool`] lays out new ones in game.
+mod level;
+mod tool;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub use level::{
+    CrossingDesc, LevelTrails, LevelTrailsError, LevelTrailsLoader, PoiDesc, TrailDesc, TrailDifficulty, TrailerDesc,
+};
+pub use tool::{export_trails, merge_trails, CarveTrailEvent, TrailTool, TrailToolError};
+
+use super::weather::WeatherManager;
+use super::world_clock::{is_open, ScheduleOpen};
+use crate::game::states::GameProgress;
+use crate::game::vehicle::{spawn_trailer, GasStation, Trailer, Vehicle};
+use crate::terrain::TerrainCarves;
+
+/// How wet the trails are
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrailCondition {
+    #[default]
+    Dry,
+    Wet,
+    Soaked,
+}
+
+impl TrailCondition {
+    /// Difficulty steps a trail with `rain_steps` climbs in this condition
+    pub fn difficulty_steps(self, rain_steps: u8) -> u8 {
+        match self {
+            TrailCondition::Dry => 0,
+            TrailCondition::Wet => rain_steps.min(1),
+            TrailCondition::Soaked => rain_steps,
+        }
+    }
+
+    /// Locale key of the condition's name
+    pub fn name_key(self) -> &'static str {
+        match self {
+            TrailCondition::Dry => "trail.condition.dry",
+            TrailCondition::Wet => "trail.condition.wet",
+            TrailCondition::Soaked => "trail.condition.soaked",
+        }
+    }
+}
+
+/// How fast the ground soaks up rain and dries out
+#[derive(Resource, Debug, Clone)]
+pub struct TrailSettings {
+    /// Seconds of full rain to soak dry ground
+    pub soak_time: f32,
+    /// Seconds for soaked ground to dry out without rain
+    pub dry_time: f32,
+    /// Precipitation below this counts as no rain
+    pub rain_threshold: f32,
+    /// Wetness at which trails turn wet
+    pub wet_at: f32,
+    /// Wetness at which trails are soaked
+    pub soaked_at: f32,
+}
+
+impl Default for TrailSettings {
+    fn default() -> Self {
+        Self {
+            soak_time: 1200.0,
+            dry_time: 3600.0,
+            rain_threshold: 0.1,
+            wet_at: 0.3,
+            soaked_at: 0.7,
+        }
+    }
+}
+
+/// Recent rain, saved with the game progress
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WeatherHistory {
+    /// How wet the ground is (0.0 - 1.0)
+    pub wetness: f32,
+    /// Seconds since it last rained
+    pub since_rain: f32,
+}
+
+impl WeatherHistory {
+    /// Soaks up `precipitation` (0.0 - 1.0) falling for `dt` seconds, or dries out without it
+    pub fn record(&mut self, precipitation: f32, dt: f32, settings: &TrailSettings) {
+        if precipitation >= settings.rain_threshold {
+            self.wetness += precipitation * dt / settings.soak_time.max(f32::EPSILON);
+            self.since_rain = 0.0;
+        } else {
+            self.wetness -= dt / settings.dry_time.max(f32::EPSILON);
+            self.since_rain += dt;
+        }
+        self.wetness = self.wetness.clamp(0.0, 1.0);
+    }
+
+    pub fn condition(&self, settings: &TrailSettings) -> TrailCondition {
+        if self.wetness >= settings.soaked_at {
+            TrailCondition::Soaked
+        } else if self.wetness >= settings.wet_at {
+            TrailCondition::Wet
+        } else {
+            TrailCondition::Dry
+        }
+    }
+}
+
+/// Current trail condition, derived from [`WeatherHistory`]
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct TrailConditions {
+    pub condition: TrailCondition,
+}
+
+impl TrailConditions {
+    /// Rating of `trail` in the current condition
+    pub fn difficulty(&self, trail: &TrailDesc) -> TrailDifficulty {
+        trail.difficulty.raised(self.condition.difficulty_steps(trail.rain_steps))
+    }
+
+    pub fn crossing_closed(&self, crossing: &CrossingDesc) -> bool {
+        crossing.closes_when.map_or(false, |closes_when| self.condition >= closes_when)
+    }
+
+    /// Message `poi` shows in the current condition
+    pub fn poi_message<'a>(&self, poi: &'a PoiDesc) -> &'a str {
+        match (&poi.wet_message, self.condition) {
+            (Some(wet_message), TrailCondition::Wet | TrailCondition::Soaked) => wet_message,
+            _ => &poi.message,
+        }
+    }
+}
+
+/// A level trail
+#[derive(Component, Debug, Clone)]
+pub struct Trail(pub TrailDesc);
+
+/// A level crossing, blocked by a barrier while closed
+#[derive(Component, Debug, Clone)]
+pub struct TrailCrossing {
+    pub desc: CrossingDesc,
+    pub closed: bool,
+}
+
+/// A level point of interest
+#[derive(Component, Debug, Clone)]
+pub struct PointOfInterest {
+    pub desc: PoiDesc,
+    /// Vehicles inside the radius, so each visit shows the message once
+    visitors: Vec<Entity>,
+}
+
+impl PointOfInterest {
+    pub fn new(desc: PoiDesc) -> Self {
+        Self { desc, visitors: Vec::new() }
+    }
+}
+
+/// Marks level entities whose trails have been spawned
+#[derive(Component)]
+pub struct LevelTrailsSpawned;
+
+/// The trails turned wetter or dried out
+#[derive(Event, Debug, Clone, Copy)]
+pub struct TrailConditionChangedEvent {
+    pub condition: TrailCondition,
+}
+
+/// A crossing closed or reopened
+#[derive(Event, Debug, Clone)]
+pub struct CrossingStatusEvent {
+    pub crossing: Entity,
+    pub name: String,
+    pub closed: bool,
+}
+
+/// A vehicle reached a point of interest
+#[derive(Event, Debug, Clone)]
+pub struct PoiReachedEvent {
+    pub poi: Entity,
+    pub vehicle: Entity,
+    pub name: String,
+    pub message: String,
+}
+
+/// Spawns the trails, crossings and points of interest of loaded level trail assets as children of the level entity,
+/// and its parked trailers
+fn spawn_level_trails(
+    mut commands: Commands,
+    levels: Query<(Entity, &Handle<LevelTrails>), Without<LevelTrailsSpawned>>,
+    level_trails: Res<Assets<LevelTrails>>,
+    mut carves: Option<ResMut<TerrainCarves>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (level, handle) in levels.iter() {
+        let Some(trails) = level_trails.get(handle) else {
+            continue;
+        };
+
+        if let Some(carves) = carves.as_mut() {
+            for desc in &trails.trails {
+                if let Some(carve) = desc.carve {
+                    let path: Vec<Vec3> = desc.path.iter().map(|point| Vec3::from(*point)).collect();
+                    carves.add(&path, carve);
+                }
+            }
+        }
+
+        let mut children: Vec<Entity> = trails
+            .trails
+            .iter()
+            .map(|desc| commands.spawn((Trail(desc.clone()), Name::new(desc.name.clone()))).id())
+            .collect();
+        children.extend(trails.crossings.iter().map(|desc| {
+            let transform = Transform::from_translation(Vec3::from(desc.position))
+                .with_rotation(Quat::from_rotation_y(desc.rotation.to_radians()));
+            commands
+                .spawn((
+                    TrailCrossing { desc: desc.clone(), closed: false },
+                    TransformBundle::from_transform(transform),
+                    Name::new(desc.name.clone()),
+                ))
+                .id()
+        }));
+        children.extend(trails.points_of_interest.iter().map(|desc| {
+            let mut poi = commands.spawn((
+                PointOfInterest::new(desc.clone()),
+                TransformBundle::from_transform(Transform::from_translation(Vec3::from(desc.position))),
+                Name::new(desc.name.clone()),
+            ));
+            if let Some(hours) = desc.open_hours {
+                poi.insert(hours);
+            }
+            if desc.gas_station {
+                poi.insert(GasStation::default());
+            }
+            poi.id()
+        }));
+        commands.entity(level).push_children(&children).insert(LevelTrailsSpawned);
+
+        // Trailers are rigid bodies jointed to their wheels, they stay in the world rather than under the level
+        for desc in &trails.trailers {
+            let transform = Transform::from_translation(Vec3::from(desc.position))
+                .with_rotation(Quat::from_rotation_y(desc.rotation.to_radians()));
+            let trailer = Trailer { cargo_mass: desc.cargo_mass, ..default() };
+            spawn_trailer(&mut commands, &mut meshes, &mut materials, transform, trailer);
+        }
+    }
+}
+
+/// Loads the weather history of a save when its progress is inserted
+fn restore_weather_history(progress: Option<Res<GameProgress>>, mut history: ResMut<WeatherHistory>) {
+    if let Some(progress) = progress.filter(|progress| progress.is_added()) {
+        *history = progress.weather_history;
+    }
+}
+
+/// Soaks the ground while it rains and dries it out otherwise
+fn record_weather_history(
+    time: Res<Time>,
+    weather: Option<Res<WeatherManager>>,
+    settings: Res<TrailSettings>,
+    mut history: ResMut<WeatherHistory>,
+) {
+    let precipitation = weather.map_or(0.0, |weather| weather.current_state().precipitation());
+    history.record(precipitation, time.delta_seconds(), &settings);
+}
+
+/// Keeps the weather history in the save
+fn store_weather_history(progress: Option<ResMut<GameProgress>>, history: Res<WeatherHistory>) {
+    if let Some(mut progress) = progress {
+        if progress.weather_history != *history {
+            progress.weather_history = *history;
+        }
+    }
+}
+
+fn update_trail_conditions(
+    settings: Res<TrailSettings>,
+    history: Res<WeatherHistory>,
+    mut conditions: ResMut<TrailConditions>,
+    mut changed_events: EventWriter<TrailConditionChangedEvent>,
+) {
+    let condition = history.condition(&settings);
+    if conditions.condition != condition {
+        conditions.condition = condition;
+        changed_events.send(TrailConditionChangedEvent { condition });
+    }
+}
+
+/// Puts up or takes down the barriers of crossings the rain closes
+fn update_crossings(
+    mut commands: Commands,
+    conditions: Res<TrailConditions>,
+    mut crossings: Query<(Entity, &mut TrailCrossing)>,
+    mut status_events: EventWriter<CrossingStatusEvent>,
+) {
+    for (entity, mut crossing) in crossings.iter_mut() {
+        let closed = conditions.crossing_closed(&crossing.desc);
+        if crossing.closed == closed {
+            continue;
+        }
+        crossing.closed = closed;
+        if closed {
+            commands
+                .entity(entity)
+                .insert((RigidBody::Fixed, Collider::cuboid(crossing.desc.width, 1.0, 0.2)));
+        } else {
+            commands.entity(entity).remove::<(RigidBody, Collider)>();
+        }
+        status_events.send(CrossingStatusEvent {
+            crossing: entity,
+            name: crossing.desc.name.clone(),
+            closed,
+        });
+    }
+}
+
+/// Shows a point of interest's message to vehicles arriving at it while it's open
+fn check_points_of_interest(
+    conditions: Res<TrailConditions>,
+    vehicles: Query<(Entity, &GlobalTransform), With<Vehicle>>,
+    mut points: Query<(Entity, &mut PointOfInterest, Option<&ScheduleOpen>)>,
+    mut reached_events: EventWriter<PoiReachedEvent>,
+) {
+    for (entity, mut poi, open) in points.iter_mut() {
+        let center = Vec3::from(poi.desc.position);
+        let radius = poi.desc.radius;
+        let inside: Vec<Entity> = vehicles
+            .iter()
+            .filter(|(_, transform)| transform.translation().distance(center) <= radius)
+            .map(|(vehicle, _)| vehicle)
+            .collect();
+        // Vehicles waiting at a closed one count as visitors, so opening up doesn't greet them
+        let arrived = inside.iter().filter(|vehicle| is_open(open) && !poi.visitors.contains(vehicle));
+        for vehicle in arrived {
+            reached_events.send(PoiReachedEvent {
+                poi: entity,
+                vehicle: *vehicle,
+                name: poi.desc.name.clone(),
+                message: conditions.poi_message(&poi.desc).to_string(),
+            });
+        }
+        poi.visitors = inside;
+    }
+}
+
+/// Plugin for level trails and the rain conditions on them
+pub struct TrailPlugin;
+
+impl Plugin for TrailPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<LevelTrails>()
+            .init_asset_loader::<LevelTrailsLoader>()
+            .init_resource::<TrailSettings>()
+            .init_resource::<WeatherHistory>()
+            .init_resource::<TrailConditions>()
+            .add_event::<TrailConditionChangedEvent>()
+            .add_event::<CrossingStatusEvent>()
+            .add_event::<PoiReachedEvent>()
+            .init_resource::<TrailTool>()
+            .add_event::<CarveTrailEvent>()
+            .add_systems(Update, (
+                spawn_level_trails,
+                tool::carve_authored_trails,
+                restore_weather_history,
+                record_weather_history,
+                store_weather_history,
+                update_trail_conditions,
+                update_crossings,
+                check_points_of_interest,
+            ).chain());
+
+        // Gizmos need a renderer
+        if crate::game::render_available(app) {
+            app.add_systems(Update, tool::draw_trail_tool);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rain_soaks_and_sun_dries() {
+        let settings = TrailSettings::default();
+        let mut history = WeatherHistory::default();
+        history.record(1.0, settings.soak_time * 0.5, &settings);
+        assert_eq!(history.condition(&settings), TrailCondition::Wet);
+        assert_eq!(history.since_rain, 0.0);
+
+        history.record(1.0, settings.soak_time, &settings);
+        assert_eq!(history.wetness, 1.0);
+        assert_eq!(history.condition(&settings), TrailCondition::Soaked);
+
+        // Drizzle under the threshold doesn't count as rain
+        history.record(0.05, settings.dry_time * 0.5, &settings);
+        assert_eq!(history.condition(&settings), TrailCondition::Wet);
+        assert_eq!(history.since_rain, settings.dry_time * 0.5);
+
+        history.record(0.0, settings.dry_time, &settings);
+        assert_eq!(history.wetness, 0.0);
+        assert_eq!(history.condition(&settings), TrailCondition::Dry);
+    }
+
+    #[test]
+    fn test_conditions_rate_close_and_message() {
+        let trail = TrailDesc {
+            name: "Ridge Run".into(),
+            difficulty: TrailDifficulty::Moderate,
+            path: Vec::new(),
+            rain_steps: 2,
+            carve: None,
+            paved: false,
+        };
+        let ford = CrossingDesc {
+            name: "Creek Ford".into(),
+            position: [0.0; 3],
+            width: 5.0,
+            rotation: 0.0,
+            closes_when: Some(TrailCondition::Soaked),
+        };
+        let poi = PoiDesc {
+            name: "Lookout".into(),
+            position: [0.0; 3],
+            radius: 10.0,
+            message: "Great view".into(),
+            wet_message: Some("Slippery up here".into()),
+            open_hours: None,
+            gas_station: false,
+        };
+
+        let dry = TrailConditions { condition: TrailCondition::Dry };
+        assert_eq!(dry.difficulty(&trail), TrailDifficulty::Moderate);
+        assert!(!dry.crossing_closed(&ford));
+        assert_eq!(dry.poi_message(&poi), "Great view");
+
+        let wet = TrailConditions { condition: TrailCondition::Wet };
+        assert_eq!(wet.difficulty(&trail), TrailDifficulty::Difficult);
+        assert!(!wet.crossing_closed(&ford));
+        assert_eq!(wet.poi_message(&poi), "Slippery up here");
+
+        let soaked = TrailConditions { condition: TrailCondition::Soaked };
+        assert_eq!(soaked.difficulty(&trail), TrailDifficulty::Severe);
+        assert!(soaked.crossing_closed(&ford));
+    }
+
+    #[test]
+    fn test_weather_history_round_trips_through_the_save() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<TrailSettings>()
+            .init_resource::<WeatherHistory>()
+            .add_systems(Update, (restore_weather_history, record_weather_history, store_weather_history).chain());
+
+        let saved = WeatherHistory { wetness: 0.8, since_rain: 0.0 };
+        app.insert_resource(GameProgress { weather_history: saved, ..default() });
+        app.update();
+        assert_eq!(*app.world.resource::<WeatherHistory>(), saved);
+
+        app.world.resource_mut::<WeatherHistory>().wetness = 0.5;
+        app.update();
+        assert_eq!(app.world.resource::<GameProgress>().weather_history.wetness, 0.5);
+    }
+}
@@ -0,0 +1,205 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use thiserror::Error;
+
+use super::level::{LevelTrails, TrailDesc, TrailDifficulty};
+use super::Trail;
+use crate::assets::ASSET_SCHEMA_VERSION;
+use crate::terrain::{spline_points, TerrainCarve, TerrainCarves};
+
+#[derive(Error, Debug)]
+pub enum TrailToolError {
+    #[error("trail file: {0}")]
+    Io(#[from] io::Error),
+    #[error("could not read or write trails: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Spline tool for laying out trails over the procedural terrain, carving them in as they're finished
+/// and exporting them into a level's trail file
+#[derive(Resource, Debug, Clone)]
+pub struct TrailTool {
+    pub active: bool,
+    /// Control points of the trail being laid out
+    pub points: Vec<Vec3>,
+    pub name: String,
+    pub difficulty: TrailDifficulty,
+    pub carve: TerrainCarve,
+    /// Trails carved this session, written out on export
+    pub carved: Vec<TrailDesc>,
+    /// Level trail file the trails are exported into
+    pub export_path: PathBuf,
+}
+
+impl Default for TrailTool {
+    fn default() -> Self {
+        Self {
+            active: false,
+            points: Vec::new(),
+            name: String::new(),
+            difficulty: TrailDifficulty::Moderate,
+            carve: TerrainCarve::default(),
+            carved: Vec::new(),
+            export_path: std::env::var("SANDK_TRAIL_EXPORT_PATH")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| PathBuf::from("assets/levels/default.trails.json")),
+        }
+    }
+}
+
+impl TrailTool {
+    /// Finishes the trail being laid out, `None` until it has two points
+    pub fn finish(&mut self) -> Option<TrailDesc> {
+        if self.points.len() < 2 {
+            return None;
+        }
+        let name = match self.name.trim() {
+            "" => format!("Trail {}", self.carved.len() + 1),
+            name => name.to_string(),
+        };
+        let trail = TrailDesc {
+            name,
+            difficulty: self.difficulty,
+            path: self.points.drain(..).map(|point| point.to_array()).collect(),
+            rain_steps: 2,
+            carve: Some(self.carve),
+            paved: false,
+        };
+        self.name.clear();
+        self.carved.push(trail.clone());
+        Some(trail)
+    }
+
+    /// Writes the carved trails into the trail file, replacing trails of the same name and keeping
+    /// everything else in it. Returns the number of trails written.
+    pub fn export(&self) -> Result<usize, TrailToolError> {
+        export_trails(&self.export_path, &self.carved)
+    }
+}
+
+/// Adds `trails` to `level`, replacing the ones with the same name
+pub fn merge_trails(level: &mut LevelTrails, trails: &[TrailDesc]) {
+    for trail in trails {
+        match level.trails.iter_mut().find(|existing| existing.name == trail.name) {
+            Some(existing) => *existing = trail.clone(),
+            None => level.trails.push(trail.clone()),
+        }
+    }
+}
+
+pub fn export_trails(path: &Path, trails: &[TrailDesc]) -> Result<usize, TrailToolError> {
+    let mut level = match fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json)?,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => LevelTrails::default(),
+        Err(error) => return Err(error.into()),
+    };
+    merge_trails(&mut level, trails);
+
+    let mut json = serde_json::to_value(&level)?;
+    json["schema_version"] = ASSET_SCHEMA_VERSION.into();
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(&json)?)?;
+    Ok(trails.len())
+}
+
+/// Finishes the trail being laid out and carves it in
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CarveTrailEvent;
+
+/// Carves finished trails into the terrain and adds them to the level's trails
+pub(super) fn carve_authored_trails(
+    mut commands: Commands,
+    mut events: EventReader<CarveTrailEvent>,
+    mut tool: ResMut<TrailTool>,
+    mut carves: Option<ResMut<TerrainCarves>>,
+) {
+    for _ in events.read() {
+        let Some(trail) = tool.finish() else {
+            continue;
+        };
+        if let (Some(carves), Some(carve)) = (carves.as_mut(), trail.carve) {
+            let path: Vec<Vec3> = trail.path.iter().map(|point| Vec3::from(*point)).collect();
+            carves.add(&path, carve);
+        }
+        commands.spawn((Name::new(trail.name.clone()), Trail(trail)));
+    }
+}
+
+/// Spline of the trail being laid out, with the edges of its bed
+pub(super) fn draw_trail_tool(tool: Res<TrailTool>, mut gizmos: Gizmos) {
+    if !tool.active {
+        return;
+    }
+    for point in &tool.points {
+        gizmos.sphere(*point, Quat::IDENTITY, 0.4, Color::YELLOW);
+    }
+    let spline = spline_points(&tool.points, 1.0);
+    gizmos.linestrip(spline.iter().copied(), Color::YELLOW);
+
+    let half_width = tool.carve.width * 0.5;
+    let edges: Vec<(Vec3, Vec3)> = spline
+        .windows(2)
+        .map(|pair| {
+            let side = (pair[1] - pair[0]).cross(Vec3::Y).normalize_or_zero() * half_width;
+            (pair[0] - side, pair[0] + side)
+        })
+        .collect();
+    gizmos.linestrip(edges.iter().map(|edge| edge.0), Color::ORANGE);
+    gizmos.linestrip(edges.iter().map(|edge| edge.1), Color::ORANGE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finish_needs_two_points() {
+        let mut tool = TrailTool::default();
+        tool.points.push(Vec3::ZERO);
+        assert!(tool.finish().is_none());
+
+        tool.points.push(Vec3::new(10.0, 1.0, 0.0));
+        let trail = tool.finish().unwrap();
+        assert_eq!(trail.name, "Trail 1");
+        assert_eq!(trail.path.len(), 2);
+        assert_eq!(trail.carve, Some(tool.carve));
+        assert!(tool.points.is_empty());
+        assert_eq!(tool.carved.len(), 1);
+    }
+
+    #[test]
+    fn test_export_merges_into_the_level_file() {
+        let path = std::env::temp_dir().join(format!("sandk-trail-tool-{}.trails.json", std::process::id()));
+        fs::write(
+            &path,
+            r#"{
+                "trails": [
+                    { "name": "Ridge Run", "difficulty": "easy", "path": [] },
+                    { "name": "Old Road", "difficulty": "easy", "path": [] }
+                ],
+                "crossings": [{ "name": "Creek Ford", "position": [0.0, 0.0, 0.0], "width": 4.0 }]
+            }"#,
+        )
+        .unwrap();
+
+        let mut tool = TrailTool { export_path: path.clone(), ..default() };
+        tool.name = "Ridge Run".to_string();
+        tool.points = vec![Vec3::ZERO, Vec3::new(0.0, 0.0, -30.0)];
+        tool.finish();
+        assert_eq!(tool.export().unwrap(), 1);
+
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(crate::assets::read_schema_version(&bytes).unwrap(), ASSET_SCHEMA_VERSION);
+        let level: LevelTrails = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(level.trails.len(), 2);
+        assert_eq!(level.trails[0].path.len(), 2);
+        assert!(level.trails[0].carve.is_some());
+        assert_eq!(level.crossings.len(), 1);
+    }
+}
@@ -0,0 +1,342 @@
+/// Guided tutorial teaching the off-road controls one step at a time
+///
+/// The tutorial is anchored where player one's vehicle stands when it starts. Steps that ask the
+/// player to drive somewhere put a marker in the world relative to that spot, the rest wait for
+/// the control to be used. The UI shows the current step with the bindings of the player's device
+/// and lets them skip a step, skip the tutorial or replay it.
+use bevy::prelude::*;
+
+use super::split_screen::{PlayerId, PlayerInput};
+use crate::game::resources::{GameMode, GameState};
+use crate::game::render_available;
+use crate::game::states::GameProgress;
+use crate::game::vehicle::{Drivetrain, RecoverVehicleEvent, TransferCase, Vehicle};
+
+/// Distance from a marker's centre that counts as reaching it, in meters
+pub const MARKER_RADIUS: f32 = 4.0;
+/// Speed below which the vehicle counts as stopped, in m/s
+const STOPPED_SPEED: f32 = 0.5;
+/// Seconds the winch has to be pulled in
+const WINCH_HOLD: f32 = 3.0;
+
+/// A step of the tutorial, in the order they're taught
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TutorialStep {
+    /// Drive forward to the first marker
+    Throttle,
+    /// Come to a stop inside the next marker
+    Brake,
+    /// Shift into low range and crawl to the marker up the slope
+    LowRange,
+    /// Lock the differentials and get through to the marker
+    DiffLock,
+    /// Pull in the winch
+    Winch,
+    /// Put the vehicle back on its wheels
+    Recover,
+}
+
+/// A control the tutorial asks for, shown with its binding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TutorialAction {
+    Throttle,
+    Brake,
+    LowRange,
+    DiffLock,
+    Winch,
+    Recover,
+}
+
+impl TutorialStep {
+    pub const ALL: [Self; 6] = [Self::Throttle, Self::Brake, Self::LowRange, Self::DiffLock, Self::Winch, Self::Recover];
+
+    /// Position of the step's 0-based index in [`Self::ALL`]
+    pub fn index(self) -> usize {
+        Self::ALL.iter().position(|step| *step == self).unwrap_or(0)
+    }
+
+    pub fn next(self) -> Option<Self> {
+        Self::ALL.get(self.index() + 1).copied()
+    }
+
+    /// Locale key of the instructions, which take the binding as `{key}`
+    pub fn prompt_key(self) -> &'static str {
+        match self {
+            Self::Throttle => "tutorial.throttle",
+            Self::Brake => "tutorial.brake",
+            Self::LowRange => "tutorial.low_range",
+            Self::DiffLock => "tutorial.diff_lock",
+            Self::Winch => "tutorial.winch",
+            Self::Recover => "tutorial.recover",
+        }
+    }
+
+    /// The control the step teaches
+    pub fn action(self) -> TutorialAction {
+        match self {
+            Self::Throttle => TutorialAction::Throttle,
+            Self::Brake => TutorialAction::Brake,
+            Self::LowRange => TutorialAction::LowRange,
+            Self::DiffLock => TutorialAction::DiffLock,
+            Self::Winch => TutorialAction::Winch,
+            Self::Recover => TutorialAction::Recover,
+        }
+    }
+
+    /// Where the player has to drive, relative to the tutorial's origin. Forward is -Z.
+    pub fn marker(self) -> Option<Vec3> {
+        match self {
+            Self::Throttle => Some(Vec3::new(0.0, 0.0, -30.0)),
+            Self::Brake => Some(Vec3::new(0.0, 0.0, -45.0)),
+            Self::LowRange => Some(Vec3::new(12.0, 0.0, -70.0)),
+            Self::DiffLock => Some(Vec3::new(-8.0, 0.0, -95.0)),
+            Self::Winch | Self::Recover => None,
+        }
+    }
+
+    /// Whether the step is done, `held` counts seconds for steps that want a control held
+    pub fn is_complete(
+        self,
+        at_marker: bool,
+        vehicle: &Vehicle,
+        transfer_case: TransferCase,
+        input: &PlayerInput,
+        held: &mut f32,
+        dt: f32,
+    ) -> bool {
+        match self {
+            Self::Throttle => at_marker,
+            Self::Brake => at_marker && input.brake > 0.0 && vehicle.vehicle_speed.abs() < STOPPED_SPEED,
+            Self::LowRange => at_marker && transfer_case == TransferCase::FourLow,
+            Self::DiffLock => at_marker && vehicle.diff_locked,
+            Self::Winch => {
+                if input.winch {
+                    *held += dt;
+                }
+                *held >= WINCH_HOLD
+            }
+            Self::Recover => input.recover,
+        }
+    }
+}
+
+/// Progress through the tutorial
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Tutorial {
+    /// Step in progress, `None` when the tutorial isn't running
+    pub step: Option<TutorialStep>,
+    /// Where the tutorial started, markers are placed relative to it
+    pub origin: Transform,
+    /// Seconds the current step's control has been held
+    pub held: f32,
+    /// Steps finished in this run, skipped ones aren't
+    pub completed: Vec<TutorialStep>,
+    /// Ran to the end or was skipped, until it's replayed
+    pub finished: bool,
+    /// Start again from the first step once player one's vehicle is there
+    pub replay_requested: bool,
+}
+
+impl Tutorial {
+    pub fn is_active(&self) -> bool {
+        self.step.is_some()
+    }
+
+    /// Starts from the first step with markers laid out from `origin`
+    pub fn start(&mut self, origin: &Transform) {
+        let (yaw, _, _) = origin.rotation.to_euler(EulerRot::YXZ);
+        *self = Self {
+            step: Some(TutorialStep::Throttle),
+            origin: Transform::from_translation(origin.translation).with_rotation(Quat::from_rotation_y(yaw)),
+            ..default()
+        };
+    }
+
+    /// Moves on to the next step, finishing after the last
+    pub fn advance(&mut self) {
+        if let Some(step) = self.step {
+            self.completed.push(step);
+        }
+        self.move_on();
+    }
+
+    pub fn skip_step(&mut self) {
+        self.move_on();
+    }
+
+    /// Stops the tutorial for good
+    pub fn skip(&mut self) {
+        self.step = None;
+        self.finished = true;
+    }
+
+    /// Runs the tutorial again from the start
+    pub fn replay(&mut self) {
+        self.replay_requested = true;
+    }
+
+    /// World position of the current step's marker
+    pub fn marker_position(&self) -> Option<Vec3> {
+        self.step?.marker().map(|offset| self.origin.transform_point(offset))
+    }
+
+    fn move_on(&mut self) {
+        self.held = 0.0;
+        self.step = self.step.and_then(TutorialStep::next);
+        self.finished = self.step.is_none();
+    }
+}
+
+/// Starts the tutorial when the game switches into tutorial mode
+fn start_tutorial_for_mode(game_state: Option<Res<GameState>>, mut tutorial: ResMut<Tutorial>) {
+    let Some(game_state) = game_state else {
+        return;
+    };
+    if game_state.is_changed() && matches!(game_state.mode, GameMode::Tutorial) && !tutorial.is_active() {
+        tutorial.replay();
+    }
+}
+
+/// Steps the tutorial along as player one completes each step
+fn update_tutorial(
+    time: Res<Time>,
+    mut tutorial: ResMut<Tutorial>,
+    mut progress: Option<ResMut<GameProgress>>,
+    mut recovered: EventReader<RecoverVehicleEvent>,
+    players: Query<(Entity, &PlayerId, &Transform, &Vehicle, &PlayerInput, Option<&Drivetrain>)>,
+) {
+    let Some((entity, _, transform, vehicle, input, drivetrain)) = players.iter().find(|(_, player, ..)| player.0 == 0)
+    else {
+        return;
+    };
+    if tutorial.replay_requested {
+        tutorial.start(transform);
+    }
+    let recovered = recovered.read().any(|event| event.vehicle == entity);
+
+    if let Some(step) = tutorial.step {
+        let at_marker = tutorial
+            .marker_position()
+            .is_some_and(|marker| marker.distance(transform.translation) <= MARKER_RADIUS);
+        // Recovery can come from elsewhere than the player's input, e.g. the pause menu
+        let input = PlayerInput { recover: input.recover || recovered, ..input.clone() };
+        let mut held = tutorial.held;
+        let transfer_case = drivetrain.map_or(TransferCase::default(), |drivetrain| drivetrain.transfer_case);
+        let complete = step.is_complete(at_marker, vehicle, transfer_case, &input, &mut held, time.delta_seconds());
+        tutorial.held = held;
+        if complete {
+            tutorial.advance();
+        }
+    }
+    if let Some(progress) = progress.as_mut().filter(|progress| tutorial.finished && !progress.tutorial_completed) {
+        progress.tutorial_completed = true;
+    }
+}
+
+/// Draws the current step's marker in the world
+fn draw_tutorial_marker(tutorial: Res<Tutorial>, time: Res<Time>, mut gizmos: Gizmos) {
+    let Some(marker) = tutorial.marker_position() else {
+        return;
+    };
+    let pulse = 1.0 + (time.elapsed_seconds() * 3.0).sin() * 0.05;
+    let color = Color::rgb(1.0, 0.8, 0.1);
+    gizmos.circle(marker + Vec3::Y * 0.1, Vec3::Y, MARKER_RADIUS * pulse, color);
+    gizmos.line(marker, marker + Vec3::Y * 6.0, color);
+}
+
+/// Plugin for the guided tutorial
+pub struct TutorialPlugin;
+
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Tutorial>()
+            .add_event::<RecoverVehicleEvent>()
+            .add_systems(Update, (start_tutorial_for_mode, update_tutorial).chain());
+
+        // Gizmos need a renderer
+        if render_available(app) {
+            app.add_systems(Update, draw_tutorial_marker.after(update_tutorial));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_app() -> (App, Entity) {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<Tutorial>()
+            .init_resource::<GameProgress>()
+            .add_event::<RecoverVehicleEvent>()
+            .add_systems(Update, update_tutorial);
+        let player = app
+            .world
+            .spawn((PlayerId(0), Transform::default(), Vehicle::default(), Drivetrain::default(), PlayerInput::default()))
+            .id();
+        app.world.resource_mut::<Tutorial>().replay();
+        app.update();
+        (app, player)
+    }
+
+    fn drive_to(app: &mut App, player: Entity) {
+        let marker = app.world.resource::<Tutorial>().marker_position().unwrap();
+        app.world.get_mut::<Transform>(player).unwrap().translation = marker;
+    }
+
+    #[test]
+    fn test_steps_run_in_order() {
+        let (mut app, player) = test_app();
+        assert_eq!(app.world.resource::<Tutorial>().step, Some(TutorialStep::Throttle));
+
+        drive_to(&mut app, player);
+        app.update();
+        assert_eq!(app.world.resource::<Tutorial>().step, Some(TutorialStep::Brake));
+
+        // Low range has to be engaged on the way, getting there isn't enough
+        app.world.resource_mut::<Tutorial>().skip_step();
+        drive_to(&mut app, player);
+        app.update();
+        assert_eq!(app.world.resource::<Tutorial>().step, Some(TutorialStep::LowRange));
+        app.world.get_mut::<Drivetrain>(player).unwrap().transfer_case = TransferCase::FourLow;
+        app.update();
+        assert_eq!(app.world.resource::<Tutorial>().step, Some(TutorialStep::DiffLock));
+        assert_eq!(app.world.resource::<Tutorial>().completed, [TutorialStep::Throttle, TutorialStep::LowRange]);
+    }
+
+    #[test]
+    fn test_markers_follow_the_starting_heading() {
+        let mut tutorial = Tutorial::default();
+        tutorial.start(&Transform::from_xyz(10.0, 0.0, 0.0).with_rotation(Quat::from_rotation_y(std::f32::consts::FRAC_PI_2)));
+        // Facing -X, the first marker is 30 m ahead
+        assert!((tutorial.marker_position().unwrap() - Vec3::new(-20.0, 0.0, 0.0)).length() < 1e-3);
+    }
+
+    #[test]
+    fn test_finishing_or_skipping_marks_it_done_and_replay_restarts() {
+        let (mut app, player) = test_app();
+        app.world.resource_mut::<Tutorial>().skip();
+        app.update();
+        assert!(!app.world.resource::<Tutorial>().is_active());
+        assert!(app.world.resource::<GameProgress>().tutorial_completed);
+
+        app.world.get_mut::<Transform>(player).unwrap().translation = Vec3::new(5.0, 0.0, 5.0);
+        app.world.resource_mut::<Tutorial>().replay();
+        app.update();
+        let tutorial = app.world.resource::<Tutorial>();
+        assert_eq!(tutorial.step, Some(TutorialStep::Throttle));
+        assert_eq!(tutorial.origin.translation, Vec3::new(5.0, 0.0, 5.0));
+    }
+
+    #[test]
+    fn test_winch_has_to_be_held() {
+        let mut held = 0.0;
+        let vehicle = Vehicle::default();
+        let pulling = PlayerInput { winch: true, ..default() };
+        let range = TransferCase::default();
+        assert!(!TutorialStep::Winch.is_complete(false, &vehicle, range, &pulling, &mut held, 2.0));
+        assert!(!TutorialStep::Winch.is_complete(false, &vehicle, range, &PlayerInput::default(), &mut held, 2.0));
+        assert!(TutorialStep::Winch.is_complete(false, &vehicle, range, &pulling, &mut held, 1.0));
+    }
+}
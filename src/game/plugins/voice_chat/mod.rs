@@ -0,0 +1,248 @@
+/// Proximity voice chat for multiplayer sessions
+///
+/// Voice travels as [`VoicePacket`]s of Opus encoded 20 ms frames. The local microphone ends up in
+/// [`SendVoicePacket`] events for the network session to carry, and packets from peers come back
+/// in as [`ReceivedVoicePacket`] events. Each peer is heard from its [`VoicePeer`] entity through
+/// spatial audio, fading out with distance from the nearest local player, and can be muted.
+///
+/// Capture, encoding and decoding need the `voice-chat` feature, which pulls in cpal and Opus.
+/// Without it peers and mutes are still tracked, but no audio is sent or played.
+#[cfg(feature = "voice-chat")]
+mod opus_backend;
+
+use std::collections::{BTreeMap, HashSet};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::split_screen::PlayerId;
+
+/// Opus sample rate voice is captured and played at
+pub const VOICE_SAMPLE_RATE: u32 = 48_000;
+/// Samples in one 20 ms voice frame
+pub const VOICE_FRAME_SAMPLES: usize = 960;
+
+/// How voice is picked up and how far it carries
+#[derive(Resource, Debug, Clone)]
+pub struct VoiceSettings {
+    pub enabled: bool,
+    /// Peer id of this machine in the network session
+    pub local_peer: u32,
+    /// Only transmit while this key is held, voice activity detection when unset
+    pub push_to_talk: Option<KeyCode>,
+    /// Frame level (RMS, 0.0 - 1.0) that counts as speaking with voice activity detection
+    pub activity_threshold: f32,
+    /// Seconds transmission continues after speech drops below the threshold
+    pub activity_hold: f32,
+    /// Peers closer than this in meters are heard at full volume
+    pub near_distance: f32,
+    /// Peers further than this in meters can't be heard
+    pub max_distance: f32,
+    pub volume: f32,
+    /// Frames held back to smooth out network jitter before playback starts
+    pub jitter_frames: usize,
+}
+
+impl Default for VoiceSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            local_peer: 0,
+            push_to_talk: None,
+            activity_threshold: 0.02,
+            activity_hold: 0.4,
+            near_distance: 6.0,
+            max_distance: 60.0,
+            volume: 1.0,
+            jitter_frames: 3,
+        }
+    }
+}
+
+/// Volume (0.0 - 1.0) of a voice `distance` meters away, falling off smoothly past the near distance
+pub fn proximity_gain(distance: f32, settings: &VoiceSettings) -> f32 {
+    if distance <= settings.near_distance {
+        return 1.0;
+    }
+    let range = (settings.max_distance - settings.near_distance).max(f32::EPSILON);
+    let t = ((distance - settings.near_distance) / range).clamp(0.0, 1.0);
+    (1.0 - t) * (1.0 - t)
+}
+
+/// One encoded voice frame as sent over the network session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VoicePacket {
+    pub peer: u32,
+    /// Frame number, counting up from the start of the peer's capture
+    pub sequence: u32,
+    /// Opus encoded frame
+    pub frame: Vec<u8>,
+}
+
+/// A voice frame of the local player for the network session to send to its peers
+#[derive(Event, Debug, Clone)]
+pub struct SendVoicePacket(pub VoicePacket);
+
+/// A voice frame that arrived from a peer over the network session
+#[derive(Event, Debug, Clone)]
+pub struct ReceivedVoicePacket(pub VoicePacket);
+
+/// A remote player in the session, their voice plays from this entity
+#[derive(Component, Debug, Clone)]
+pub struct VoicePeer {
+    pub peer: u32,
+    pub name: String,
+}
+
+/// Child of a [`VoicePeer`] playing their voice
+#[derive(Component, Debug, Clone, Copy)]
+pub struct VoiceEmitter {
+    pub peer: u32,
+}
+
+/// Peers the local players muted
+#[derive(Resource, Debug, Clone, Default)]
+pub struct VoiceMutes(HashSet<u32>);
+
+impl VoiceMutes {
+    pub fn is_muted(&self, peer: u32) -> bool {
+        self.0.contains(&peer)
+    }
+
+    pub fn set_muted(&mut self, peer: u32, muted: bool) {
+        if muted {
+            self.0.insert(peer);
+        } else {
+            self.0.remove(&peer);
+        }
+    }
+}
+
+/// Puts a peer's frames back in order and hides late or lost ones from the decoder
+#[derive(Debug, Clone, Default)]
+pub struct JitterBuffer {
+    frames: BTreeMap<u32, Vec<u8>>,
+    /// Sequence of the next frame to play, `None` until playback starts
+    next: Option<u32>,
+}
+
+/// What to play for the next frame slot
+#[derive(Debug, Clone, PartialEq)]
+pub enum JitterFrame {
+    Frame(Vec<u8>),
+    /// The frame never arrived, the decoder should conceal the gap
+    Lost,
+}
+
+impl JitterBuffer {
+    pub fn push(&mut self, sequence: u32, frame: Vec<u8>) {
+        // Frames behind playback are too late to use
+        if self.next.map_or(true, |next| sequence >= next) {
+            self.frames.insert(sequence, frame);
+        }
+    }
+
+    pub fn buffered(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Next frame to play, waiting until `depth` frames are buffered before starting
+    pub fn pop(&mut self, depth: usize) -> Option<JitterFrame> {
+        let first = *self.frames.keys().next()?;
+        let next = match self.next {
+            Some(next) if first.wrapping_sub(next) as usize <= depth => next,
+            // Nothing played yet, or the peer paused between sentences and the
+            // sequence jumped ahead, so buffer up and start from the earliest frame
+            _ if self.frames.len() >= depth.max(1) => first,
+            _ => return None,
+        };
+        self.next = Some(next.wrapping_add(1));
+        Some(self.frames.remove(&next).map_or(JitterFrame::Lost, JitterFrame::Frame))
+    }
+}
+
+/// Sets each peer's voice volume from their distance to the nearest local player and the mutes
+fn update_voice_volumes(
+    settings: Res<VoiceSettings>,
+    mutes: Res<VoiceMutes>,
+    players: Query<&GlobalTransform, With<PlayerId>>,
+    emitters: Query<(&VoiceEmitter, &GlobalTransform, &SpatialAudioSink)>,
+) {
+    for (emitter, transform, sink) in emitters.iter() {
+        let gain = if mutes.is_muted(emitter.peer) {
+            0.0
+        } else {
+            players
+                .iter()
+                .map(|player| proximity_gain(player.translation().distance(transform.translation()), &settings))
+                .fold(0.0, f32::max)
+        };
+        sink.set_volume(gain * settings.volume);
+    }
+}
+
+/// Plugin for proximity voice chat
+pub struct VoiceChatPlugin;
+
+impl Plugin for VoiceChatPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VoiceSettings>()
+            .init_resource::<VoiceMutes>()
+            .add_event::<SendVoicePacket>()
+            .add_event::<ReceivedVoicePacket>()
+            .add_systems(Update, update_voice_volumes);
+
+        #[cfg(feature = "voice-chat")]
+        opus_backend::build(app);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_voice_fades_out_with_distance() {
+        let settings = VoiceSettings::default();
+        assert_eq!(proximity_gain(0.0, &settings), 1.0);
+        assert_eq!(proximity_gain(settings.near_distance, &settings), 1.0);
+        let mid = proximity_gain((settings.near_distance + settings.max_distance) * 0.5, &settings);
+        assert!(mid > 0.0 && mid < 0.5);
+        assert_eq!(proximity_gain(settings.max_distance, &settings), 0.0);
+        assert_eq!(proximity_gain(settings.max_distance * 2.0, &settings), 0.0);
+    }
+
+    #[test]
+    fn test_jitter_buffer_reorders_and_conceals_losses() {
+        let mut buffer = JitterBuffer::default();
+        buffer.push(11, vec![11]);
+        assert_eq!(buffer.pop(2), None);
+        buffer.push(10, vec![10]);
+        buffer.push(13, vec![13]);
+
+        assert_eq!(buffer.pop(2), Some(JitterFrame::Frame(vec![10])));
+        assert_eq!(buffer.pop(2), Some(JitterFrame::Frame(vec![11])));
+        assert_eq!(buffer.pop(2), Some(JitterFrame::Lost));
+        // Arrived after its slot was concealed
+        buffer.push(12, vec![12]);
+        assert_eq!(buffer.pop(2), Some(JitterFrame::Frame(vec![13])));
+        assert_eq!(buffer.buffered(), 0);
+        assert_eq!(buffer.pop(2), None);
+
+        // Picks up after a pause instead of concealing every skipped frame
+        buffer.push(40, vec![40]);
+        assert_eq!(buffer.pop(2), None);
+        buffer.push(41, vec![41]);
+        assert_eq!(buffer.pop(2), Some(JitterFrame::Frame(vec![40])));
+    }
+
+    #[test]
+    fn test_mutes() {
+        let mut mutes = VoiceMutes::default();
+        mutes.set_muted(3, true);
+        assert!(mutes.is_muted(3));
+        assert!(!mutes.is_muted(4));
+        mutes.set_muted(3, false);
+        assert!(!mutes.is_muted(3));
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use audiopus::coder::{Decoder, Encoder};
+use audiopus::{Application, Channels, SampleRate};
+use bevy::audio::{AddAudioSource, Decodable, Source};
+use bevy::prelude::*;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use thiserror::Error;
+
+use super::{
+    JitterBuffer, JitterFrame, ReceivedVoicePacket, SendVoicePacket, VoiceEmitter, VoicePacket, VoicePeer, VoiceSettings,
+    VOICE_FRAME_SAMPLES, VOICE_SAMPLE_RATE,
+};
+
+/// Largest encoded frame, Opus recommends this for a 20 ms voice frame buffer
+const MAX_PACKET_BYTES: usize = 4000;
+/// Decoded samples kept queued for playback before old ones are dropped, half a second
+const MAX_QUEUED_SAMPLES: usize = VOICE_SAMPLE_RATE as usize / 2;
+
+#[derive(Error, Debug)]
+pub enum VoiceCaptureError {
+    #[error("no microphone found")]
+    NoDevice,
+    #[error("microphone config: {0}")]
+    Config(#[from] cpal::DefaultStreamConfigError),
+    #[error("microphone stream: {0}")]
+    Stream(#[from] cpal::BuildStreamError),
+    #[error("microphone playback: {0}")]
+    Play(#[from] cpal::PlayStreamError),
+    #[error("opus: {0}")]
+    Opus(#[from] audiopus::Error),
+}
+
+/// RMS level of a frame (0.0 - 1.0)
+fn frame_level(frame: &[f32]) -> f32 {
+    if frame.is_empty() {
+        return 0.0;
+    }
+    (frame.iter().map(|sample| sample * sample).sum::<f32>() / frame.len() as f32).sqrt()
+}
+
+/// Averages interleaved channels down to mono and linearly resamples to the voice sample rate
+fn to_voice_rate(samples: &[f32], channels: usize, sample_rate: u32) -> Vec<f32> {
+    let mono: Vec<f32> = samples
+        .chunks(channels.max(1))
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect();
+    if sample_rate == VOICE_SAMPLE_RATE || mono.is_empty() {
+        return mono;
+    }
+    let step = sample_rate as f32 / VOICE_SAMPLE_RATE as f32;
+    let count = (mono.len() as f32 / step) as usize;
+    (0..count)
+        .map(|index| {
+            let position = index as f32 * step;
+            let before = position as usize;
+            let after = (before + 1).min(mono.len() - 1);
+            let t = position - before as f32;
+            mono[before] * (1.0 - t) + mono[after] * t
+        })
+        .collect()
+}
+
+/// Open microphone stream and the encoder its frames go through
+pub struct VoiceCapture {
+    _stream: cpal::Stream,
+    samples: Receiver<Vec<f32>>,
+    channels: usize,
+    sample_rate: u32,
+    pending: Vec<f32>,
+    encoder: Encoder,
+    sequence: u32,
+    /// Seconds left transmitting after speech stopped
+    hold: f32,
+}
+
+impl VoiceCapture {
+    /// Opens the default microphone
+    pub fn open() -> Result<Self, VoiceCaptureError> {
+        let device = cpal::default_host().default_input_device().ok_or(VoiceCaptureError::NoDevice)?;
+        let config: cpal::StreamConfig = device.default_input_config()?.into();
+        let (sender, samples) = mpsc::channel();
+        let stream = device.build_input_stream(
+            &config,
+            move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                // The receiver only goes away with the stream
+                let _ = sender.send(data.to_vec());
+            },
+            |error| warn!("Microphone error: {error}"),
+            None,
+        )?;
+        stream.play()?;
+
+        Ok(Self {
+            _stream: stream,
+            samples,
+            channels: config.channels as usize,
+            sample_rate: config.sample_rate.0,
+            pending: Vec::new(),
+            encoder: Encoder::new(SampleRate::Hz48000, Channels::Mono, Application::Voip)?,
+            sequence: 0,
+            hold: 0.0,
+        })
+    }
+}
+
+/// Decoded voice of one peer, played as an endless spatial source
+#[derive(Asset, TypePath, Clone, Default)]
+pub struct VoiceStream {
+    samples: Arc<Mutex<VecDeque<f32>>>,
+}
+
+/// Plays queued voice samples and silence while nothing is queued
+pub struct VoiceStreamDecoder {
+    samples: Arc<Mutex<VecDeque<f32>>>,
+}
+
+impl Iterator for VoiceStreamDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        Some(self.samples.lock().ok().and_then(|mut samples| samples.pop_front()).unwrap_or(0.0))
+    }
+}
+
+impl Source for VoiceStreamDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        VOICE_SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl Decodable for VoiceStream {
+    type DecoderItem = f32;
+    type Decoder = VoiceStreamDecoder;
+
+    fn decoder(&self) -> Self::Decoder {
+        VoiceStreamDecoder { samples: self.samples.clone() }
+    }
+}
+
+/// A peer's jitter buffer, decoder and the stream their voice plays through
+struct PeerVoice {
+    buffer: JitterBuffer,
+    decoder: Decoder,
+    stream: VoiceStream,
+}
+
+/// Playback state of every peer heard so far
+#[derive(Default)]
+struct PeerVoices(HashMap<u32, PeerVoice>);
+
+/// Opens or closes the microphone when voice chat is turned on or off
+fn manage_capture(world: &mut World) {
+    let enabled = world.resource::<VoiceSettings>().enabled;
+    let open = world.get_non_send_resource::<VoiceCapture>().is_some();
+    if enabled && !open {
+        match VoiceCapture::open() {
+            Ok(capture) => world.insert_non_send_resource(capture),
+            // Runs on settings changes only, so toggling voice chat retries
+            Err(error) => warn!("Voice chat capture unavailable: {error}"),
+        }
+    } else if !enabled && open {
+        world.remove_non_send_resource::<VoiceCapture>();
+    }
+}
+
+/// Cuts captured audio into frames and encodes the ones spoken into for the session to send
+fn encode_voice(
+    settings: Res<VoiceSettings>,
+    keyboard: Res<Input<KeyCode>>,
+    capture: Option<NonSendMut<VoiceCapture>>,
+    mut packets: EventWriter<SendVoicePacket>,
+) {
+    let Some(mut capture) = capture else {
+        return;
+    };
+    let captured: Vec<f32> = capture.samples.try_iter().flatten().collect();
+    let resampled = to_voice_rate(&captured, capture.channels, capture.sample_rate);
+    capture.pending.extend(resampled);
+
+    let frame_seconds = VOICE_FRAME_SAMPLES as f32 / VOICE_SAMPLE_RATE as f32;
+    let mut output = [0u8; MAX_PACKET_BYTES];
+    while capture.pending.len() >= VOICE_FRAME_SAMPLES {
+        let frame: Vec<f32> = capture.pending.drain(..VOICE_FRAME_SAMPLES).collect();
+        let talking = match settings.push_to_talk {
+            Some(key) => keyboard.pressed(key),
+            None => {
+                if frame_level(&frame) >= settings.activity_threshold {
+                    capture.hold = settings.activity_hold;
+                } else {
+                    capture.hold = (capture.hold - frame_seconds).max(0.0);
+                }
+                capture.hold > 0.0
+            }
+        };
+        let sequence = capture.sequence;
+        capture.sequence = capture.sequence.wrapping_add(1);
+        if !talking {
+            continue;
+        }
+
+        let pcm: Vec<i16> = frame.iter().map(|sample| (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).collect();
+        match capture.encoder.encode(&pcm, &mut output) {
+            Ok(length) => packets.send(SendVoicePacket(VoicePacket {
+                peer: settings.local_peer,
+                sequence,
+                frame: output[..length].to_vec(),
+            })),
+            Err(error) => warn!("Voice encoding failed: {error}"),
+        }
+    }
+}
+
+/// Buffers peers' frames, decodes them into their voice streams and starts playback on their entity
+fn decode_voice(
+    mut commands: Commands,
+    settings: Res<VoiceSettings>,
+    mut voices: NonSendMut<PeerVoices>,
+    mut streams: ResMut<Assets<VoiceStream>>,
+    peers: Query<(Entity, &VoicePeer)>,
+    mut received: EventReader<ReceivedVoicePacket>,
+) {
+    for ReceivedVoicePacket(packet) in received.read() {
+        if packet.peer == settings.local_peer {
+            continue;
+        }
+        if !voices.0.contains_key(&packet.peer) {
+            let Some((entity, _)) = peers.iter().find(|(_, peer)| peer.peer == packet.peer) else {
+                continue;
+            };
+            let decoder = match Decoder::new(SampleRate::Hz48000, Channels::Mono) {
+                Ok(decoder) => decoder,
+                Err(error) => {
+                    warn!("Voice decoder unavailable: {error}");
+                    continue;
+                }
+            };
+            let stream = VoiceStream::default();
+            commands.entity(entity).with_children(|parent| {
+                parent.spawn((
+                    AudioSourceBundle {
+                        source: streams.add(stream.clone()),
+                        settings: PlaybackSettings::LOOP.with_spatial(true),
+                    },
+                    SpatialBundle::default(),
+                    VoiceEmitter { peer: packet.peer },
+                    Name::new("Voice"),
+                ));
+            });
+            voices.0.insert(packet.peer, PeerVoice { buffer: JitterBuffer::default(), decoder, stream });
+        }
+        if let Some(voice) = voices.0.get_mut(&packet.peer) {
+            voice.buffer.push(packet.sequence, packet.frame.clone());
+        }
+    }
+
+    let mut pcm = [0i16; VOICE_FRAME_SAMPLES];
+    for voice in voices.0.values_mut() {
+        while let Some(frame) = voice.buffer.pop(settings.jitter_frames) {
+            let decoded = match &frame {
+                JitterFrame::Frame(bytes) => voice.decoder.decode(Some(bytes.as_slice()), &mut pcm[..], false),
+                JitterFrame::Lost => voice.decoder.decode(None::<&[u8]>, &mut pcm[..], false),
+            };
+            let length = match decoded {
+                Ok(length) => length,
+                Err(error) => {
+                    warn!("Voice decoding failed: {error}");
+                    continue;
+                }
+            };
+            let Ok(mut samples) = voice.stream.samples.lock() else {
+                continue;
+            };
+            samples.extend(pcm[..length].iter().map(|sample| *sample as f32 / i16::MAX as f32));
+            let overflow = samples.len().saturating_sub(MAX_QUEUED_SAMPLES);
+            samples.drain(..overflow);
+        }
+    }
+}
+
+/// Forgets peers that left the session
+fn remove_departed_peers(mut voices: NonSendMut<PeerVoices>, peers: Query<&VoicePeer>) {
+    voices.0.retain(|peer, _| peers.iter().any(|voice_peer| voice_peer.peer == *peer));
+}
+
+pub(super) fn build(app: &mut App) {
+    app.add_audio_source::<VoiceStream>()
+        .init_non_send_resource::<PeerVoices>()
+        .add_systems(
+            Update,
+            (
+                manage_capture.run_if(resource_changed::<VoiceSettings>()),
+                encode_voice,
+                decode_voice,
+                remove_departed_peers,
+            )
+                .chain(),
+        );
+}
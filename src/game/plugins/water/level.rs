@@ -0,0 +1,161 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{material::WaterParams, volume::FluidKind};
+use crate::assets::{check_schema_version, read_schema_version, SchemaVersionError};
+
+/// A stream, puddle or mud pit as written in a level file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WaterBodyDesc {
+    pub name: String,
+    #[serde(default)]
+    pub kind: FluidKind,
+    /// World position of the surface centre
+    pub surface: [f32; 3],
+    /// Surface size in meters along X and Z, before rotation
+    pub size: [f32; 2],
+    /// Depth below the surface in meters
+    pub depth: f32,
+    /// Rotation around the vertical axis in degrees
+    #[serde(default)]
+    pub rotation: f32,
+    /// Surface flow in meters per second, in world XZ
+    #[serde(default)]
+    pub flow: [f32; 2],
+    /// Overrides the default shallow water color
+    #[serde(default)]
+    pub shallow_color: Option<[f32; 4]>,
+    /// Overrides the default deep water color
+    #[serde(default)]
+    pub deep_color: Option<[f32; 4]>,
+}
+
+impl WaterBodyDesc {
+    /// Shader parameters for this body
+    pub fn params(&self) -> WaterParams {
+        let mut params = WaterParams::for_kind(self.kind);
+        // Small still bodies read better as puddles
+        if self.kind == FluidKind::Water && self.depth < 0.3 && self.flow == [0.0, 0.0] {
+            params = WaterParams::puddle();
+        }
+        params.flow = Vec2::from(self.flow);
+        if let Some(color) = self.shallow_color {
+            params.shallow_color = Vec4::from(color);
+        }
+        if let Some(color) = self.deep_color {
+            params.deep_color = Vec4::from(color);
+        }
+        params
+    }
+}
+
+fn default_dust_intensity() -> f32 {
+    1.0
+}
+
+/// A patch of loose dry ground as written in a level file, the dry counterpart of a mud pit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DustDesc {
+    pub name: String,
+    /// World position of the centre, at ground level
+    pub center: [f32; 3],
+    /// Size in meters along X and Z, before rotation
+    pub size: [f32; 2],
+    /// Rotation around the vertical axis in degrees
+    #[serde(default)]
+    pub rotation: f32,
+    /// How fast vehicles get dusty, 1.0 for ordinary dirt roads
+    #[serde(default = "default_dust_intensity")]
+    pub intensity: f32,
+}
+
+/// Water bodies and dusty ground of a level, loaded from `*.water.json`
+#[derive(Asset, TypePath, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LevelWater {
+    pub bodies: Vec<WaterBodyDesc>,
+    #[serde(default)]
+    pub dust: Vec<DustDesc>,
+}
+
+/// Errors produced while loading level water files
+#[derive(Debug, thiserror::Error)]
+pub enum LevelWaterError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Schema(#[from] SchemaVersionError),
+}
+
+/// Asset loader for level water files
+#[derive(Default)]
+pub struct LevelWaterLoader;
+
+impl AssetLoader for LevelWaterLoader {
+    type Asset = LevelWater;
+    type Settings = ();
+    type Error = LevelWaterError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<LevelWater, LevelWaterError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            check_schema_version(read_schema_version(&bytes)?)?;
+            Ok(serde_json::from_slice(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["water.json"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_level_water() {
+        let json = r#"{
+            "bodies": [
+                { "name": "Creek", "surface": [0.0, -1.0, 0.0], "size": [4.0, 60.0], "depth": 0.8, "flow": [0.0, 1.2] },
+                { "name": "Bog", "kind": "mud", "surface": [20.0, 0.0, 5.0], "size": [6.0, 6.0], "depth": 0.5 }
+            ],
+            "dust": [
+                { "name": "Wash", "center": [-40.0, 0.0, 10.0], "size": [30.0, 12.0], "rotation": 20.0 }
+            ]
+        }"#;
+        let level: LevelWater = serde_json::from_str(json).unwrap();
+        assert_eq!(level.bodies.len(), 2);
+        assert_eq!(level.bodies[0].kind, FluidKind::Water);
+        assert_eq!(level.bodies[1].kind, FluidKind::Mud);
+        assert_eq!(level.bodies[0].params().flow, Vec2::new(0.0, 1.2));
+        assert_eq!(level.dust[0].intensity, 1.0);
+    }
+
+    #[test]
+    fn test_shallow_still_water_is_a_puddle() {
+        let desc = WaterBodyDesc {
+            name: "Puddle".into(),
+            kind: FluidKind::Water,
+            surface: [0.0; 3],
+            size: [2.0, 3.0],
+            depth: 0.1,
+            rotation: 0.0,
+            flow: [0.0, 0.0],
+            shallow_color: None,
+            deep_color: None,
+        };
+        assert_eq!(desc.params().absorption_depth, WaterParams::puddle().absorption_depth);
+    }
+}
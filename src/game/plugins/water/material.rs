@@ -0,0 +1,231 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{AsBindGroup, Extent3d, ShaderRef, ShaderType, TextureDimension, TextureFormat},
+        texture::{ImageAddressMode, ImageSampler, ImageSamplerDescriptor},
+    },
+};
+use std::f32::consts::TAU;
+
+use super::volume::FluidKind;
+
+/// Shader parameters for a water surface
+#[derive(Clone, Copy, Debug, ShaderType)]
+pub struct WaterParams {
+    /// Color where the water is thin, alpha is the opacity at zero depth
+    pub shallow_color: Vec4,
+    /// Color where the water is deep
+    pub deep_color: Vec4,
+    /// Foam color, alpha is the foam strength
+    pub foam_color: Vec4,
+    /// Reflected color when no planar reflection is available
+    pub sky_color: Vec4,
+    /// Surface flow in world XZ, scrolls the normal maps
+    pub flow: Vec2,
+    /// World meters per normal map tile
+    pub normal_tiling: f32,
+    /// Strength of the normal map perturbation
+    pub normal_strength: f32,
+    /// Water thickness in meters at which the deep color is reached
+    pub absorption_depth: f32,
+    /// Water thickness in meters over which shoreline foam fades out
+    pub foam_width: f32,
+    /// Scale of the fresnel reflection
+    pub reflection_strength: f32,
+    /// Screen space offset applied to reflections by the normals
+    pub distortion: f32,
+    /// 1 when `reflection` holds a planar reflection for this surface
+    pub planar_reflection: u32,
+    pub _padding: Vec3,
+}
+
+impl Default for WaterParams {
+    fn default() -> Self {
+        Self::stream()
+    }
+}
+
+impl WaterParams {
+    /// Clear, flowing creek water
+    pub fn stream() -> Self {
+        Self {
+            shallow_color: Vec4::new(0.35, 0.45, 0.4, 0.15),
+            deep_color: Vec4::new(0.04, 0.12, 0.12, 0.95),
+            foam_color: Vec4::new(0.9, 0.92, 0.9, 0.8),
+            sky_color: Vec4::new(0.55, 0.65, 0.75, 1.0),
+            flow: Vec2::ZERO,
+            normal_tiling: 4.0,
+            normal_strength: 0.6,
+            absorption_depth: 1.5,
+            foam_width: 0.25,
+            reflection_strength: 1.0,
+            distortion: 0.02,
+            planar_reflection: 0,
+            _padding: Vec3::ZERO,
+        }
+    }
+
+    /// Still, shallow puddle that mostly mirrors the sky
+    pub fn puddle() -> Self {
+        Self {
+            shallow_color: Vec4::new(0.3, 0.27, 0.22, 0.05),
+            deep_color: Vec4::new(0.12, 0.1, 0.08, 0.8),
+            normal_tiling: 1.5,
+            normal_strength: 0.15,
+            absorption_depth: 0.4,
+            foam_width: 0.0,
+            distortion: 0.005,
+            ..Self::stream()
+        }
+    }
+
+    /// Thick opaque mud with dull reflections
+    pub fn mud() -> Self {
+        Self {
+            shallow_color: Vec4::new(0.3, 0.22, 0.14, 0.7),
+            deep_color: Vec4::new(0.18, 0.12, 0.07, 1.0),
+            foam_color: Vec4::new(0.35, 0.27, 0.18, 0.4),
+            normal_tiling: 2.0,
+            normal_strength: 0.25,
+            absorption_depth: 0.05,
+            foam_width: 0.1,
+            reflection_strength: 0.3,
+            distortion: 0.003,
+            ..Self::stream()
+        }
+    }
+
+    /// Default look for a fluid kind
+    pub fn for_kind(kind: FluidKind) -> Self {
+        match kind {
+            FluidKind::Water => Self::stream(),
+            FluidKind::Mud => Self::mud(),
+        }
+    }
+}
+
+/// Material for streams, puddles and mud pits
+#[derive(Asset, TypePath, AsBindGroup, Clone, Debug)]
+pub struct WaterMaterial {
+    #[uniform(0)]
+    pub params: WaterParams,
+
+    /// Tileable normal map, alpha holds foam noise
+    #[texture(1)]
+    #[sampler(2)]
+    pub normal_map: Handle<Image>,
+
+    /// Planar reflection of the scene, sampled in screen space
+    #[texture(3)]
+    #[sampler(4)]
+    pub reflection: Option<Handle<Image>>,
+}
+
+impl Material for WaterMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/water.wgsl".into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        AlphaMode::Blend
+    }
+}
+
+/// Wave vectors (cycles per tile) and amplitudes summed into the normal map.
+/// Integer frequencies keep the texture seamless.
+const NORMAL_WAVES: [(i32, i32, f32); 6] = [
+    (1, 2, 0.35),
+    (-2, 1, 0.3),
+    (3, -1, 0.18),
+    (-1, -4, 0.12),
+    (5, 3, 0.07),
+    (-6, 5, 0.05),
+];
+
+const FOAM_WAVES: [(i32, i32, f32); 4] = [(7, 3, 0.4), (-4, 9, 0.3), (11, -6, 0.2), (-13, -10, 0.1)];
+
+/// Height and slope of a sum of waves at `uv` in [0, 1)
+fn wave_sum(waves: &[(i32, i32, f32)], uv: Vec2) -> (f32, Vec2) {
+    let mut height = 0.0;
+    let mut slope = Vec2::ZERO;
+    for (index, &(fx, fy, amplitude)) in waves.iter().enumerate() {
+        let k = Vec2::new(fx as f32, fy as f32) * TAU;
+        let phase = k.dot(uv) + index as f32 * 1.7;
+        height += phase.sin() * amplitude;
+        slope += k * phase.cos() * amplitude;
+    }
+    (height, slope)
+}
+
+/// Tangent space normal at `uv`, z is up
+pub fn water_normal(uv: Vec2, strength: f32) -> Vec3 {
+    let (_, slope) = wave_sum(&NORMAL_WAVES, uv);
+    // Slope is per tile, scale it down to a sensible bump height
+    let slope = slope * strength * 0.02;
+    Vec3::new(-slope.x, -slope.y, 1.0).normalize()
+}
+
+/// Generates a seamless water normal map with foam noise in the alpha channel
+pub fn generate_water_normal_map(size: u32) -> Image {
+    let mut data = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let uv = Vec2::new(x as f32, y as f32) / size as f32;
+            let normal = water_normal(uv, 1.0);
+            let (foam, _) = wave_sum(&FOAM_WAVES, uv);
+            let encode = |v: f32| ((v * 0.5 + 0.5).clamp(0.0, 1.0) * 255.0).round() as u8;
+            data.extend_from_slice(&[encode(normal.x), encode(normal.y), encode(normal.z), encode(foam)]);
+        }
+    }
+
+    let mut image = Image::new(
+        Extent3d { width: size, height: size, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8Unorm,
+    );
+    image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+        address_mode_u: ImageAddressMode::Repeat,
+        address_mode_v: ImageAddressMode::Repeat,
+        ..ImageSamplerDescriptor::linear()
+    });
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normal_map_tiles_seamlessly() {
+        for t in [0.0, 0.25, 0.6] {
+            let left = water_normal(Vec2::new(0.0, t), 1.0);
+            let right = water_normal(Vec2::new(1.0, t), 1.0);
+            assert!((left - right).length() < 1e-4);
+
+            let bottom = water_normal(Vec2::new(t, 0.0), 1.0);
+            let top = water_normal(Vec2::new(t, 1.0), 1.0);
+            assert!((bottom - top).length() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_normals_point_up() {
+        for i in 0..16 {
+            let uv = Vec2::splat(i as f32 / 16.0);
+            assert!(water_normal(uv, 1.0).z > 0.5);
+        }
+    }
+
+    #[test]
+    fn test_generated_image_size() {
+        let image = generate_water_normal_map(32);
+        assert_eq!(image.data.len(), 32 * 32 * 4);
+    }
+
+    #[test]
+    fn test_mud_is_more_opaque_than_water() {
+        assert!(WaterParams::mud().shallow_color.w > WaterParams::stream().shallow_color.w);
+        assert!(WaterParams::mud().absorption_depth < WaterParams::stream().absorption_depth);
+    }
+}
@@ -0,0 +1,267 @@
+/// Water rendering for streams, puddles and mud pits
+///
+/// Water bodies come from a level's `*.water.json` file, and rivers carved into the terrain are
+/// filled stretch by stretch. Each body spawns a single entity holding
+/// both the rendered surface and the [`FluidVolume`] that physics queries, so what you see is
+/// exactly what the vehicle drives through. The same file lays out patches of dusty ground that
+/// coat vehicles passing over in dust.
+///
+/// The surface shader provides:
+/// - Planar reflections of the nearest surface, falling back to a sky color
+/// - Two scrolling normal maps that follow the surface flow
+/// - Depth-based transparency using the depth prepass
+/// - Foam where the water thins out against the shoreline
+mod level;
+mod material;
+mod reflection;
+mod volume;
+
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        view::RenderLayers,
+    },
+};
+
+pub use level::{DustDesc, LevelWater, LevelWaterError, LevelWaterLoader, WaterBodyDesc};
+pub use material::{generate_water_normal_map, water_normal, WaterMaterial, WaterParams};
+pub use reflection::{mirror_transform, WaterReflection, WaterReflectionCamera};
+pub use volume::{sample_fluid, FluidKind, FluidSample, FluidVolume};
+
+use reflection::{
+    configure_water_cameras, resize_water_reflection, setup_water_reflection, update_reflection_camera,
+    update_water_materials,
+};
+use crate::game::render_available;
+use crate::game::vehicle::DustVolume;
+use crate::terrain::{Drivability, DrivabilityBlocker, DrivabilitySettings, River, RiverWater, TerrainRivers};
+
+/// Render layer water surfaces live on, so the reflection camera can skip them
+pub const WATER_RENDER_LAYER: u8 = 1;
+
+/// How water surfaces reflect the scene
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WaterReflectionMode {
+    /// Mirror the scene across the nearest surface with a second camera
+    #[default]
+    Planar,
+    /// Reflect a flat sky color only, for low end hardware
+    SkyOnly,
+}
+
+/// Water rendering configuration
+#[derive(Resource, Clone, Debug)]
+pub struct WaterSettings {
+    pub reflections: WaterReflectionMode,
+    /// Reflection render target size relative to the window
+    pub reflection_resolution_scale: f32,
+    /// Surfaces further than this from the camera never get planar reflections
+    pub reflection_distance: f32,
+    /// Size of the generated normal map in pixels
+    pub normal_map_size: u32,
+}
+
+impl Default for WaterSettings {
+    fn default() -> Self {
+        Self {
+            reflections: WaterReflectionMode::Planar,
+            reflection_resolution_scale: 0.5,
+            reflection_distance: 150.0,
+            normal_map_size: 256,
+        }
+    }
+}
+
+/// Marks the rendered surface of a water body
+#[derive(Component)]
+pub struct WaterSurface;
+
+/// Shared normal map for all water surfaces
+#[derive(Resource)]
+pub struct WaterNormalMap(pub Handle<Image>);
+
+/// Marks level entities whose water bodies have been spawned
+#[derive(Component)]
+pub struct LevelWaterSpawned;
+
+/// Flat surface mesh in the XZ plane, facing up
+fn surface_mesh(size: Vec2) -> Mesh {
+    let half = size * 0.5;
+    let positions = vec![
+        [-half.x, 0.0, -half.y],
+        [half.x, 0.0, -half.y],
+        [half.x, 0.0, half.y],
+        [-half.x, 0.0, half.y],
+    ];
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 1.0, 0.0]; 4]);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]);
+    mesh.set_indices(Some(Indices::U32(vec![0, 2, 1, 0, 3, 2])));
+    mesh
+}
+
+/// Spawns the surface and fluid volume of a water body
+pub fn spawn_water_body(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<WaterMaterial>,
+    normal_map: &WaterNormalMap,
+    desc: &WaterBodyDesc,
+) -> Entity {
+    let size = Vec2::from(desc.size);
+    let transform = Transform::from_translation(Vec3::from(desc.surface))
+        .with_rotation(Quat::from_rotation_y(desc.rotation.to_radians()));
+
+    commands
+        .spawn((
+            MaterialMeshBundle {
+                mesh: meshes.add(surface_mesh(size)),
+                material: materials.add(WaterMaterial {
+                    params: desc.params(),
+                    normal_map: normal_map.0.clone(),
+                    reflection: None,
+                }),
+                transform,
+                ..default()
+            },
+            FluidVolume {
+                kind: desc.kind,
+                half_size: size * 0.5,
+                depth: desc.depth,
+                flow: Vec2::from(desc.flow),
+                frozen: false,
+            },
+            WaterSurface,
+            RenderLayers::layer(WATER_RENDER_LAYER),
+            Name::new(desc.name.clone()),
+        ))
+        .id()
+}
+
+fn setup_water_normal_map(mut commands: Commands, settings: Res<WaterSettings>, mut images: ResMut<Assets<Image>>) {
+    let image = images.add(generate_water_normal_map(settings.normal_map_size));
+    commands.insert_resource(WaterNormalMap(image));
+}
+
+/// Spawns the invisible volume of a dusty patch, reaching a little above the ground so the bottom of
+/// a vehicle driving over it is inside
+pub fn spawn_dust_volume(commands: &mut Commands, desc: &DustDesc) -> Entity {
+    let transform = Transform::from_translation(Vec3::from(desc.center))
+        .with_rotation(Quat::from_rotation_y(desc.rotation.to_radians()));
+    commands
+        .spawn((
+            DustVolume {
+                half_extents: Vec3::new(desc.size[0] * 0.5, 1.0, desc.size[1] * 0.5),
+                intensity: desc.intensity,
+            },
+            TransformBundle::from_transform(transform),
+            Name::new(desc.name.clone()),
+        ))
+        .id()
+}
+
+/// Spawns the water bodies and dusty patches of loaded level water assets as children of the level entity
+fn spawn_level_water(
+    mut commands: Commands,
+    levels: Query<(Entity, &Handle<LevelWater>), Without<LevelWaterSpawned>>,
+    level_water: Res<Assets<LevelWater>>,
+    normal_map: Res<WaterNormalMap>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<WaterMaterial>>,
+) {
+    for (level, handle) in levels.iter() {
+        let Some(water) = level_water.get(handle) else {
+            continue;
+        };
+
+        let mut children: Vec<Entity> = water
+            .bodies
+            .iter()
+            .map(|desc| spawn_water_body(&mut commands, &mut meshes, &mut materials, &normal_map, desc))
+            .collect();
+        children.extend(water.dust.iter().map(|desc| spawn_dust_volume(&mut commands, desc)));
+        commands.entity(level).push_children(&children).insert(LevelWaterSpawned);
+    }
+}
+
+/// Water body filling one stretch of a river
+fn river_body(river: &River, index: usize, stretch: &RiverWater) -> WaterBodyDesc {
+    WaterBodyDesc {
+        name: format!("{} {}", river.name, index + 1),
+        kind: FluidKind::Water,
+        surface: stretch.surface.to_array(),
+        size: stretch.size.to_array(),
+        depth: stretch.depth,
+        rotation: stretch.rotation,
+        flow: stretch.flow.to_array(),
+        shallow_color: None,
+        deep_color: None,
+    }
+}
+
+/// Fills rivers with water as the terrain carves them, `filled` counts the rivers already done
+fn fill_terrain_rivers(
+    mut commands: Commands,
+    rivers: Option<Res<TerrainRivers>>,
+    normal_map: Res<WaterNormalMap>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<WaterMaterial>>,
+    mut filled: Local<usize>,
+) {
+    let Some(rivers) = rivers else {
+        return;
+    };
+    for river in rivers.rivers.iter().skip(*filled) {
+        for (index, stretch) in river.water().iter().enumerate() {
+            let desc = river_body(river, index, stretch);
+            spawn_water_body(&mut commands, &mut meshes, &mut materials, &normal_map, &desc);
+        }
+    }
+    *filled = rivers.rivers.len();
+}
+
+/// Keeps routes out of water too deep to ford, frozen water is driven over like ground
+fn block_deep_water(
+    mut commands: Commands,
+    volumes: Query<(Entity, &FluidVolume), Changed<FluidVolume>>,
+    settings: Option<Res<DrivabilitySettings>>,
+) {
+    let wade_depth = settings.map_or(DrivabilitySettings::default().wade_depth, |settings| settings.wade_depth);
+    for (entity, volume) in volumes.iter() {
+        if volume.frozen {
+            commands.entity(entity).remove::<DrivabilityBlocker>();
+            continue;
+        }
+        let drivability = if volume.depth > wade_depth { Drivability::Impassable } else { Drivability::Steep };
+        commands.entity(entity).insert(DrivabilityBlocker { half_size: volume.half_size, drivability });
+    }
+}
+
+/// Plugin for water surfaces and the fluid volumes behind them
+pub struct WaterPlugin;
+
+impl Plugin for WaterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WaterSettings>()
+            .init_asset::<LevelWater>()
+            .init_asset_loader::<LevelWaterLoader>()
+            .add_systems(Startup, setup_water_normal_map)
+            .add_systems(Update, (spawn_level_water, fill_terrain_rivers, block_deep_water));
+
+        // Headless, the fluid volumes still work but nothing draws the surface
+        if !render_available(app) {
+            app.init_asset::<WaterMaterial>();
+            return;
+        }
+        app.add_plugins(MaterialPlugin::<WaterMaterial>::default())
+            .add_systems(Startup, setup_water_reflection)
+            .add_systems(Update, (configure_water_cameras, resize_water_reflection))
+            // Before propagation so the mirrored camera's global transform matches this frame's view
+            .add_systems(PostUpdate, (
+                update_reflection_camera,
+                update_water_materials,
+            ).chain().before(bevy::transform::TransformSystem::TransformPropagate));
+    }
+}
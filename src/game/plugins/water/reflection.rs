@@ -0,0 +1,219 @@
+use bevy::{
+    core_pipeline::prepass::DepthPrepass,
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+        view::RenderLayers,
+    },
+    window::{PrimaryWindow, WindowResized},
+};
+
+use super::{
+    material::WaterMaterial,
+    volume::FluidVolume,
+    WaterReflectionMode, WaterSettings, WaterSurface, WATER_RENDER_LAYER,
+};
+use crate::game::plugins::camera::GameCamera;
+
+/// Surfaces within this height of the reflection plane share its reflection
+const PLANE_TOLERANCE: f32 = 0.05;
+
+/// Render target and plane of the planar reflection
+#[derive(Resource)]
+pub struct WaterReflection {
+    pub image: Handle<Image>,
+    /// Height of the mirrored plane, `None` while no surface is reflected
+    pub plane_height: Option<f32>,
+}
+
+/// Camera rendering the mirrored scene for the nearest water surface
+#[derive(Component)]
+pub struct WaterReflectionCamera;
+
+/// Marks game cameras that have been set up to render water
+#[derive(Component)]
+pub struct WaterCameraReady;
+
+/// Mirrors a camera transform across a horizontal plane, keeping it upright
+pub fn mirror_transform(transform: &Transform, plane_height: f32) -> Transform {
+    let mut position = transform.translation;
+    position.y = 2.0 * plane_height - position.y;
+
+    let mut forward = transform.forward();
+    forward.y = -forward.y;
+
+    Transform::from_translation(position).looking_to(forward, Vec3::Y)
+}
+
+fn reflection_size(window: &Window, scale: f32) -> Extent3d {
+    Extent3d {
+        width: ((window.physical_width() as f32 * scale) as u32).max(1),
+        height: ((window.physical_height() as f32 * scale) as u32).max(1),
+        depth_or_array_layers: 1,
+    }
+}
+
+fn reflection_image(size: Extent3d) -> Image {
+    let mut image = Image {
+        data: vec![0; (size.width * size.height * 4) as usize],
+        ..default()
+    };
+    image.texture_descriptor.size = size;
+    image.texture_descriptor.dimension = TextureDimension::D2;
+    image.texture_descriptor.format = TextureFormat::Bgra8UnormSrgb;
+    image.texture_descriptor.usage =
+        TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::RENDER_ATTACHMENT;
+    image
+}
+
+pub fn setup_water_reflection(
+    mut commands: Commands,
+    settings: Res<WaterSettings>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let size = windows
+        .get_single()
+        .map(|window| reflection_size(window, settings.reflection_resolution_scale))
+        .unwrap_or(Extent3d { width: 512, height: 512, depth_or_array_layers: 1 });
+    let image = images.add(reflection_image(size));
+
+    commands.spawn((
+        Camera3dBundle {
+            camera: Camera {
+                // Render before the main camera so the reflection is ready when water draws
+                order: -1,
+                target: RenderTarget::Image(image.clone()),
+                is_active: false,
+                ..default()
+            },
+            ..default()
+        },
+        // Only the default layer, so water never reflects itself
+        RenderLayers::layer(0),
+        WaterReflectionCamera,
+        Name::new("Water Reflection Camera"),
+    ));
+
+    commands.insert_resource(WaterReflection { image, plane_height: None });
+}
+
+/// Lets game cameras see water and gives the water shader scene depth to fade against, again after
+/// anti-aliasing settings took the depth prepass away
+#[allow(clippy::type_complexity)]
+pub fn configure_water_cameras(
+    mut commands: Commands,
+    cameras: Query<Entity, (With<GameCamera>, Or<(Without<WaterCameraReady>, Without<DepthPrepass>)>)>,
+) {
+    for entity in cameras.iter() {
+        commands.entity(entity).insert((
+            DepthPrepass,
+            RenderLayers::from_layers(&[0, WATER_RENDER_LAYER]),
+            WaterCameraReady,
+        ));
+    }
+}
+
+pub fn resize_water_reflection(
+    mut resize_events: EventReader<WindowResized>,
+    settings: Res<WaterSettings>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    reflection: Res<WaterReflection>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    if resize_events.read().last().is_none() && !settings.is_changed() {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    if let Some(image) = images.get_mut(&reflection.image) {
+        image.resize(reflection_size(window, settings.reflection_resolution_scale));
+    }
+}
+
+/// Picks the nearest water surface and mirrors the game camera across it
+pub fn update_reflection_camera(
+    settings: Res<WaterSettings>,
+    mut reflection: ResMut<WaterReflection>,
+    game_cameras: Query<(&Transform, &Projection), (With<GameCamera>, Without<WaterReflectionCamera>)>,
+    surfaces: Query<(&FluidVolume, &GlobalTransform), With<WaterSurface>>,
+    mut reflection_cameras: Query<(&mut Camera, &mut Transform, &mut Projection), With<WaterReflectionCamera>>,
+) {
+    let Ok((mut camera, mut transform, mut projection)) = reflection_cameras.get_single_mut() else {
+        return;
+    };
+    let Some((game_transform, game_projection)) = game_cameras.iter().next() else {
+        camera.is_active = false;
+        return;
+    };
+
+    let camera_position = game_transform.translation;
+    let plane_height = match settings.reflections {
+        WaterReflectionMode::Planar => surfaces
+            .iter()
+            .map(|(volume, surface_transform)| {
+                let distance = surface_transform.translation().xz().distance(camera_position.xz());
+                (distance, volume.surface_height(surface_transform))
+            })
+            // Underwater cameras have nothing to mirror
+            .filter(|(distance, height)| *distance <= settings.reflection_distance && *height < camera_position.y)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, height)| height),
+        WaterReflectionMode::SkyOnly => None,
+    };
+
+    if reflection.plane_height != plane_height {
+        reflection.plane_height = plane_height;
+    }
+    camera.is_active = plane_height.is_some();
+
+    if let Some(height) = plane_height {
+        *transform = mirror_transform(game_transform, height);
+        *projection = game_projection.clone();
+    }
+}
+
+/// Points surfaces on the reflected plane at the reflection texture
+pub fn update_water_materials(
+    reflection: Res<WaterReflection>,
+    surfaces: Query<(&FluidVolume, &GlobalTransform, &Handle<WaterMaterial>), With<WaterSurface>>,
+    mut materials: ResMut<Assets<WaterMaterial>>,
+) {
+    for (volume, transform, handle) in surfaces.iter() {
+        let reflected = reflection
+            .plane_height
+            .map_or(false, |height| (volume.surface_height(transform) - height).abs() <= PLANE_TOLERANCE);
+        let planar_reflection = reflected as u32;
+
+        // Only touch the asset when the flag flips, mutating it re-uploads the bind group
+        let needs_update = materials
+            .get(handle)
+            .map_or(false, |material| material.params.planar_reflection != planar_reflection);
+        if needs_update {
+            if let Some(material) = materials.get_mut(handle) {
+                material.params.planar_reflection = planar_reflection;
+                material.reflection = reflected.then(|| reflection.image.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mirror_transform() {
+        let camera = Transform::from_xyz(0.0, 5.0, 10.0).looking_at(Vec3::new(0.0, 1.0, 0.0), Vec3::Y);
+        let mirrored = mirror_transform(&camera, 1.0);
+
+        assert!((mirrored.translation - Vec3::new(0.0, -3.0, 10.0)).length() < 1e-5);
+        // Looking down at the plane becomes looking up at it, toward the same point
+        let forward = mirrored.forward();
+        assert!(forward.y > 0.0);
+        assert!((forward.z - camera.forward().z).abs() < 1e-5);
+        assert!(mirrored.up().y > 0.0);
+    }
+}
@@ -0,0 +1,160 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// What a fluid volume is filled with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FluidKind {
+    #[default]
+    Water,
+    Mud,
+}
+
+impl FluidKind {
+    /// Linear drag coefficient applied to bodies moving through the fluid
+    pub fn drag(&self) -> f32 {
+        match self {
+            FluidKind::Water => 0.8,
+            FluidKind::Mud => 3.5,
+        }
+    }
+
+    /// Fluid density in kg/m³, used for buoyancy
+    pub fn density(&self) -> f32 {
+        match self {
+            FluidKind::Water => 1000.0,
+            FluidKind::Mud => 1600.0,
+        }
+    }
+}
+
+/// Physics side of a stream, puddle or mud pit.
+/// The entity's transform sits at the centre of the fluid surface and the volume extends `depth` below it.
+#[derive(Component, Clone, Debug)]
+pub struct FluidVolume {
+    pub kind: FluidKind,
+    /// Half size of the surface along local X and Z
+    pub half_size: Vec2,
+    /// Depth below the surface in meters
+    pub depth: f32,
+    /// Surface flow in meters per second, in world XZ
+    pub flow: Vec2,
+    /// Frozen over, vehicles drive on the ice instead of through the fluid
+    pub frozen: bool,
+}
+
+/// Fluid found at a point
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FluidSample {
+    pub kind: FluidKind,
+    /// How far below the surface the point is
+    pub depth: f32,
+    pub surface_height: f32,
+    pub flow: Vec2,
+}
+
+impl FluidVolume {
+    /// World height of the fluid surface
+    pub fn surface_height(&self, transform: &GlobalTransform) -> f32 {
+        transform.translation().y
+    }
+
+    /// Samples the volume at a world position, `None` if the point is outside it or it's frozen
+    pub fn sample(&self, transform: &GlobalTransform, point: Vec3) -> Option<FluidSample> {
+        if self.frozen {
+            return None;
+        }
+        let local = transform.affine().inverse().transform_point3(point);
+        let inside = local.x.abs() <= self.half_size.x
+            && local.z.abs() <= self.half_size.y
+            && local.y >= -self.depth
+            && local.y <= 0.0;
+        if !inside {
+            return None;
+        }
+
+        let surface_height = self.surface_height(transform);
+        Some(FluidSample {
+            kind: self.kind,
+            depth: surface_height - point.y,
+            surface_height,
+            flow: self.flow,
+        })
+    }
+}
+
+/// Deepest fluid at `point` among `volumes`
+pub fn sample_fluid<'a>(
+    volumes: impl IntoIterator<Item = (&'a FluidVolume, &'a GlobalTransform)>,
+    point: Vec3,
+) -> Option<FluidSample> {
+    volumes
+        .into_iter()
+        .filter_map(|(volume, transform)| volume.sample(transform, point))
+        .max_by(|a, b| a.depth.total_cmp(&b.depth))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream() -> (FluidVolume, GlobalTransform) {
+        (
+            FluidVolume {
+                kind: FluidKind::Water,
+                half_size: Vec2::new(2.0, 10.0),
+                depth: 1.0,
+                flow: Vec2::new(0.0, 1.0),
+                frozen: false,
+            },
+            GlobalTransform::IDENTITY,
+        )
+    }
+
+    #[test]
+    fn test_sample_inside_and_outside() {
+        let (volume, transform) = stream();
+        assert_eq!(volume.surface_height(&transform), 0.0);
+
+        let sample = volume.sample(&transform, Vec3::new(1.0, -0.3, 5.0)).unwrap();
+        assert!((sample.depth - 0.3).abs() < 1e-5);
+        assert_eq!(sample.kind, FluidKind::Water);
+
+        assert!(volume.sample(&transform, Vec3::new(3.0, -0.3, 0.0)).is_none());
+        assert!(volume.sample(&transform, Vec3::new(0.0, 0.2, 0.0)).is_none());
+
+        let frozen = FluidVolume { frozen: true, ..volume };
+        assert!(frozen.sample(&transform, Vec3::new(1.0, -0.3, 5.0)).is_none());
+    }
+
+    #[test]
+    fn test_rotated_volume() {
+        let volume = FluidVolume {
+            kind: FluidKind::Mud,
+            half_size: Vec2::new(1.0, 5.0),
+            depth: 0.5,
+            flow: Vec2::ZERO,
+            frozen: false,
+        };
+        let transform = GlobalTransform::from(Transform::from_rotation(Quat::from_rotation_y(std::f32::consts::FRAC_PI_2)));
+        // Long axis now runs along X
+        assert!(volume.sample(&transform, Vec3::new(4.0, -0.1, 0.0)).is_some());
+        assert!(volume.sample(&transform, Vec3::new(0.0, -0.1, 4.0)).is_none());
+    }
+
+    #[test]
+    fn test_deepest_volume_wins() {
+        let (stream, stream_transform) = stream();
+        let puddle = FluidVolume {
+            kind: FluidKind::Mud,
+            half_size: Vec2::new(1.0, 1.0),
+            depth: 0.2,
+            flow: Vec2::ZERO,
+            frozen: false,
+        };
+        let puddle_transform = GlobalTransform::from_translation(Vec3::new(0.0, -0.8, 0.0));
+
+        let sample = sample_fluid([(&puddle, &puddle_transform), (&stream, &stream_transform)], Vec3::new(0.0, -0.9, 0.0)).unwrap();
+        assert_eq!(sample.kind, FluidKind::Water);
+    }
+}
@@ -35,6 +35,8 @@ pub struct CloudParams {
     pub wind_speed: f32,
     pub precipitation_threshold: f32,
     pub time: f32,
+    /// How far the wind has carried the clouds, in world meters
+    pub scroll: Vec2,
 }
 
 impl Default for CloudParams {
@@ -48,6 +50,7 @@ impl Default for CloudParams {
             wind_speed: 10.0,
             precipitation_threshold: 0.5,
             time: 0.0,
+            scroll: Vec2::ZERO,
         }
     }
 }
@@ -0,0 +1,88 @@
+use bevy::prelude::*;
+
+use super::cloud_material::CloudMaterial;
+use super::weather_manager::{WeatherManager, WeatherState};
+use crate::terrain::{TerrainChunkManager, TerrainMaterial};
+
+/// Meters the cloud noise and the shadow texture take to repeat, the scroll wraps here without a jump
+pub const CLOUD_REPEAT: f32 = 1000.0;
+
+/// How far the wind has carried the cloud layer. Shared by the volumetric clouds and their shadows on
+/// the terrain, so the shadows stay under the clouds casting them.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq)]
+pub struct CloudScroll {
+    /// World meters along X and Z
+    pub offset: Vec2,
+    /// Seconds the clouds have been drifting
+    pub time: f32,
+}
+
+impl CloudScroll {
+    /// Drifts the clouds along the wind for `delta_seconds`
+    pub fn advance(&mut self, state: &WeatherState, delta_seconds: f32) {
+        let direction = state.wind_direction();
+        let wind = Vec2::new(direction.cos(), direction.sin()) * state.wind_speed();
+        self.offset = (self.offset + wind * delta_seconds).rem_euclid(Vec2::splat(CLOUD_REPEAT));
+        self.time += delta_seconds;
+    }
+}
+
+pub(super) fn update_cloud_scroll(time: Res<Time>, weather: Res<WeatherManager>, mut scroll: ResMut<CloudScroll>) {
+    scroll.advance(weather.current_state(), time.delta_seconds());
+}
+
+/// Moves the volumetric clouds with the wind and thickens them with the weather
+pub(super) fn update_cloud_materials(
+    scroll: Res<CloudScroll>,
+    weather: Res<WeatherManager>,
+    mut materials: ResMut<Assets<CloudMaterial>>,
+) {
+    let state = weather.current_state();
+    let direction = state.wind_direction();
+    for (_, material) in materials.iter_mut() {
+        material.params.coverage = state.cloud_coverage();
+        material.params.wind_direction = Vec2::new(direction.cos(), direction.sin());
+        material.params.wind_speed = state.wind_speed();
+        material.params.scroll = scroll.offset;
+        material.params.time = scroll.time;
+    }
+}
+
+/// Hands the cloud cover and its scroll to the terrain's cloud shadows
+pub(super) fn update_cloud_shadows(
+    scroll: Res<CloudScroll>,
+    weather: Res<WeatherManager>,
+    terrain: Option<Res<TerrainChunkManager>>,
+    mut terrain_materials: ResMut<Assets<TerrainMaterial>>,
+) {
+    let Some(terrain) = terrain else {
+        return;
+    };
+    let coverage = weather.current_state().cloud_coverage();
+    for handle in terrain.materials() {
+        if let Some(material) = terrain_materials.get_mut(handle) {
+            material.extension.cloud_shadow.scroll = scroll.offset;
+            material.extension.cloud_shadow.coverage = coverage;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Weather;
+    use super::*;
+
+    #[test]
+    fn test_clouds_drift_with_the_wind() {
+        // Storm wind blows 15 m/s along +X
+        let state = WeatherState::new(Weather::Storm);
+        let mut scroll = CloudScroll::default();
+        scroll.advance(&state, 2.0);
+        assert!((scroll.offset - Vec2::new(30.0, 0.0)).length() < 1e-3);
+
+        // Wraps where the clouds repeat
+        scroll.advance(&state, 70.0);
+        assert!((scroll.offset - Vec2::new(80.0, 0.0)).length() < 1e-2);
+        assert_eq!(scroll.time, 72.0);
+    }
+}
@@ -0,0 +1,115 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+
+use super::weather_manager::{ScheduledWeather, Weather, WeatherOverrides};
+use crate::assets::{check_schema_version, read_schema_version, SchemaVersionError};
+
+fn default_transition() -> f32 {
+    30.0
+}
+
+/// A weather change on a level's timeline, as written in a level file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeatherChangeDesc {
+    /// Minutes into the level the transition starts
+    pub minute: f32,
+    pub weather: Weather,
+    /// Seconds the transition takes
+    #[serde(default = "default_transition")]
+    pub transition: f32,
+    #[serde(default, flatten)]
+    pub overrides: WeatherOverrides,
+}
+
+impl WeatherChangeDesc {
+    /// The change on the weather clock, for a level that started at `start`
+    pub fn scheduled(&self, start: f32) -> ScheduledWeather {
+        ScheduledWeather {
+            at: start + self.minute * 60.0,
+            weather: self.weather,
+            transition: self.transition.max(0.0),
+            overrides: self.overrides,
+        }
+    }
+}
+
+/// Scripted weather of a level, loaded from `*.weather.json`
+#[derive(Asset, TypePath, Debug, Clone, Serialize, Deserialize)]
+pub struct LevelWeather {
+    /// Weather the level starts in
+    pub initial: Weather,
+    #[serde(default, flatten)]
+    pub initial_overrides: WeatherOverrides,
+    #[serde(default)]
+    pub timeline: Vec<WeatherChangeDesc>,
+}
+
+/// Errors produced while loading level weather files
+#[derive(Debug, thiserror::Error)]
+pub enum LevelWeatherError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Schema(#[from] SchemaVersionError),
+}
+
+/// Asset loader for level weather files
+#[derive(Default)]
+pub struct LevelWeatherLoader;
+
+impl AssetLoader for LevelWeatherLoader {
+    type Asset = LevelWeather;
+    type Settings = ();
+    type Error = LevelWeatherError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<LevelWeather, LevelWeatherError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            check_schema_version(read_schema_version(&bytes)?)?;
+            Ok(serde_json::from_slice(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["weather.json"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_level_weather() {
+        let json = r#"{
+            "initial": "cloudy",
+            "wind_speed": 3.0,
+            "timeline": [
+                { "minute": 10.0, "weather": "storm", "transition": 90.0, "wind_speed": 22.0 },
+                { "minute": 25.0, "weather": "clear" }
+            ]
+        }"#;
+        let level: LevelWeather = serde_json::from_str(json).unwrap();
+        assert_eq!(level.initial, Weather::Cloudy);
+        assert_eq!(level.initial_overrides.wind_speed, Some(3.0));
+
+        let storm = level.timeline[0].scheduled(100.0);
+        assert_eq!(storm.at, 700.0);
+        assert_eq!(storm.weather, Weather::Storm);
+        assert_eq!(storm.overrides.wind_speed, Some(22.0));
+        assert_eq!(level.timeline[1].transition, 30.0);
+        assert_eq!(level.timeline[1].overrides, WeatherOverrides::default());
+    }
+}
@@ -0,0 +1,254 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+use super::weather_manager::WeatherManager;
+use crate::game::plugins::wildlife::WildlifeRng;
+use crate::game::GameCamera;
+use crate::terrain::TerrainQuery;
+
+/// How often and how hard lightning strikes in a storm
+#[derive(Resource, Debug, Clone)]
+pub struct LightningSettings {
+    /// Seconds between strikes in a full storm, the gap grows as the storm eases off
+    pub min_interval: f32,
+    pub max_interval: f32,
+    /// Strikes land this far from the camera, in meters
+    pub min_distance: f32,
+    pub max_distance: f32,
+    /// Height above the ground bolts come down from
+    pub cloud_height: f32,
+    /// Seconds a flash and its bolt last
+    pub flash_duration: f32,
+    /// Illuminance added to the sun at the peak of a close flash
+    pub flash_illuminance: f32,
+    /// How far the sky color is pushed towards the flash color at the peak of a close flash
+    pub sky_flash: f32,
+    /// Storms weaker than this don't strike
+    pub min_storm_intensity: f32,
+}
+
+impl Default for LightningSettings {
+    fn default() -> Self {
+        Self {
+            min_interval: 6.0,
+            max_interval: 40.0,
+            min_distance: 150.0,
+            max_distance: 2500.0,
+            cloud_height: 600.0,
+            flash_duration: 0.35,
+            flash_illuminance: 60_000.0,
+            sky_flash: 0.7,
+            min_storm_intensity: 0.2,
+        }
+    }
+}
+
+/// Sent when lightning hits the ground, the thunder follows once its sound gets to the camera
+#[derive(Event, Debug, Clone, Copy)]
+pub struct LightningStrikeEvent {
+    pub position: Vec3,
+    /// Random 0.0 - 1.0, picks the thunder sample and its pitch
+    pub variant: f32,
+}
+
+/// A bolt being drawn, the main channel first and then its forks
+#[derive(Debug, Clone)]
+pub struct LightningBolt {
+    pub branches: Vec<Vec<Vec3>>,
+    pub age: f32,
+}
+
+/// Time to the next strike, the flash lighting the scene and the bolts on screen
+#[derive(Resource, Debug, Clone)]
+pub struct Lightning {
+    pub until_next_strike: f32,
+    /// Seconds since the last flash started and how bright it peaks, 0.0 - 1.0
+    pub flash_age: f32,
+    pub flash_strength: f32,
+    pub bolts: Vec<LightningBolt>,
+    /// Sky color from before the flash, put back once it's over
+    sky_color: Option<Color>,
+    /// Its own stream, reseeded with the others in determinism mode
+    pub rng: WildlifeRng,
+}
+
+impl Default for Lightning {
+    fn default() -> Self {
+        Self {
+            until_next_strike: 0.0,
+            flash_age: f32::INFINITY,
+            flash_strength: 0.0,
+            bolts: Vec::new(),
+            sky_color: None,
+            rng: WildlifeRng::from_seed(0x2545_f491),
+        }
+    }
+}
+
+impl Lightning {
+    /// Flash brightness right now, 0.0 - 1.0
+    pub fn flash(&self, duration: f32) -> f32 {
+        flash_brightness(self.flash_age, duration) * self.flash_strength
+    }
+}
+
+/// Brightness of a flash `age` seconds in, flickering with the return strokes as it fades
+pub fn flash_brightness(age: f32, duration: f32) -> f32 {
+    if age < 0.0 || age >= duration {
+        return 0.0;
+    }
+    let fade = 1.0 - age / duration;
+    fade * fade * (0.7 + 0.3 * (age * 45.0).cos())
+}
+
+/// Jagged bolt from `from` down to `to`, with shorter forks splitting off the main channel.
+/// `random` gives values in 0.0..1.0.
+pub fn fork_bolt(from: Vec3, to: Vec3, mut random: impl FnMut() -> f32) -> Vec<Vec<Vec3>> {
+    let mut branches = vec![jagged_line(from, to, 6, &mut random)];
+    let main = branches[0].clone();
+    let length = from.distance(to);
+    // Forks leave the upper two thirds of the channel, heading on down and away
+    for point in &main[1..main.len() * 2 / 3] {
+        if random() > 0.25 {
+            continue;
+        }
+        let angle = random() * TAU;
+        let reach = length * (0.15 + random() * 0.2);
+        let end = *point + Vec3::new(angle.cos() * reach * 0.6, -reach, angle.sin() * reach * 0.6);
+        branches.push(jagged_line(*point, end, 4, &mut random));
+    }
+    branches
+}
+
+/// Line from `from` to `to` split in half `depth` times, each midpoint knocked sideways
+fn jagged_line(from: Vec3, to: Vec3, depth: u32, random: &mut impl FnMut() -> f32) -> Vec<Vec3> {
+    let mut points = vec![from, to];
+    let mut offset = from.distance(to) * 0.12;
+    for _ in 0..depth {
+        let mut split = Vec::with_capacity(points.len() * 2 - 1);
+        for pair in points.windows(2) {
+            let sideways = Vec3::new(random() - 0.5, (random() - 0.5) * 0.3, random() - 0.5) * 2.0 * offset;
+            split.push(pair[0]);
+            split.push((pair[0] + pair[1]) * 0.5 + sideways);
+        }
+        split.push(to);
+        points = split;
+        offset *= 0.5;
+    }
+    points
+}
+
+/// Counts down to the next strike while there's a storm and sets it off around the camera
+pub(super) fn strike_lightning(
+    time: Res<Time>,
+    settings: Res<LightningSettings>,
+    weather: Res<WeatherManager>,
+    terrain: Option<Res<TerrainQuery>>,
+    cameras: Query<&GlobalTransform, With<GameCamera>>,
+    mut lightning: ResMut<Lightning>,
+    mut strikes: EventWriter<LightningStrikeEvent>,
+) {
+    let delta = time.delta_seconds();
+    lightning.flash_age += delta;
+    lightning.bolts.retain_mut(|bolt| {
+        bolt.age += delta;
+        bolt.age < settings.flash_duration
+    });
+
+    let intensity = weather.current_state().storm_intensity();
+    if intensity < settings.min_storm_intensity {
+        lightning.until_next_strike = lightning.until_next_strike.max(settings.min_interval);
+        return;
+    }
+    lightning.until_next_strike -= delta;
+    if lightning.until_next_strike > 0.0 {
+        return;
+    }
+    let interval = settings.max_interval.lerp(settings.min_interval, intensity);
+    lightning.until_next_strike = interval * lightning.rng.range(0.5, 1.5);
+
+    let Some(camera) = cameras.iter().next() else {
+        return;
+    };
+    let camera = camera.translation();
+    let angle = lightning.rng.range(0.0, TAU);
+    let distance = lightning.rng.range(settings.min_distance, settings.max_distance);
+    let mut ground = camera + Vec3::new(angle.cos(), 0.0, angle.sin()) * distance;
+    ground.y = terrain.as_ref().and_then(|terrain| terrain.height(ground.x, ground.z)).unwrap_or(camera.y);
+
+    let lean = Vec3::new(lightning.rng.range(-0.2, 0.2), 1.0, lightning.rng.range(-0.2, 0.2));
+    let top = ground + lean * settings.cloud_height;
+    let branches = fork_bolt(top, ground, || lightning.rng.next_f32());
+    lightning.bolts.push(LightningBolt { branches, age: 0.0 });
+
+    // Close strikes light the scene up more, and a bigger storm more again
+    let closeness = 1.0 - (distance - settings.min_distance) / (settings.max_distance - settings.min_distance).max(1.0);
+    lightning.flash_age = 0.0;
+    lightning.flash_strength = (0.3 + 0.7 * closeness) * intensity.sqrt();
+    let variant = lightning.rng.next_f32();
+    strikes.send(LightningStrikeEvent { position: ground, variant });
+}
+
+/// Spikes the sun and flashes the sky, after the environment lighting has set them for the frame
+pub(super) fn flash_lightning(
+    settings: Res<LightningSettings>,
+    mut lightning: ResMut<Lightning>,
+    sky: Option<ResMut<ClearColor>>,
+    mut lights: Query<&mut DirectionalLight>,
+) {
+    let flash = lightning.flash(settings.flash_duration);
+    if let Some(mut sky) = sky {
+        if flash > 0.0 {
+            let base = *lightning.sky_color.get_or_insert(sky.0);
+            sky.0 = base.lerp(Color::rgb(0.85, 0.88, 1.0), flash * settings.sky_flash);
+        } else if let Some(color) = lightning.sky_color.take() {
+            sky.0 = color;
+        }
+    }
+    if flash > 0.0 {
+        for mut light in lights.iter_mut() {
+            light.illuminance += settings.flash_illuminance * flash;
+        }
+    }
+}
+
+/// Draws bolts as glowing line strips, the forks fainter than the main channel
+pub(super) fn draw_lightning(settings: Res<LightningSettings>, lightning: Res<Lightning>, mut gizmos: Gizmos) {
+    for bolt in &lightning.bolts {
+        let brightness = flash_brightness(bolt.age, settings.flash_duration).max(0.2);
+        for (index, branch) in bolt.branches.iter().enumerate() {
+            let alpha = if index == 0 { brightness } else { brightness * 0.6 };
+            gizmos.linestrip(branch.iter().copied(), Color::rgba(0.85, 0.9, 1.0, alpha));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bolt_runs_from_cloud_to_ground() {
+        let mut lightning = Lightning::default();
+        let from = Vec3::new(0.0, 600.0, 0.0);
+        let branches = fork_bolt(from, Vec3::ZERO, || lightning.rng.next_f32());
+
+        let main = &branches[0];
+        assert_eq!(main.len(), 65);
+        assert_eq!(main[0], from);
+        assert_eq!(*main.last().unwrap(), Vec3::ZERO);
+        for fork in &branches[1..] {
+            assert!(main.contains(&fork[0]));
+            assert!(fork.last().unwrap().y < fork[0].y);
+        }
+    }
+
+    #[test]
+    fn test_flash_fades_out() {
+        assert!(flash_brightness(0.0, 0.35) > 0.9);
+        assert!(flash_brightness(0.3, 0.35) < flash_brightness(0.05, 0.35));
+        assert_eq!(flash_brightness(0.35, 0.35), 0.0);
+        assert_eq!(Lightning::default().flash(0.35), 0.0);
+    }
+}
@@ -1,13 +1,33 @@
+/// Weather, time of day and the weather forecast
+///
+/// A level's `*.weather.json` file sets the weather it starts in and a timeline of scripted
+/// changes, which [`WeatherManager`] schedules and blends smoothly into. Gameplay asks the
+/// manager what's coming, e.g. [`WeatherManager::will_rain_within`], and the map shows the forecast.
+/// Storms throw lightning around the camera, the thunder following at the speed of sound.
+/// The clouds drift with the wind and shade the terrain under them as they pass.
 mod cloud_material;
+mod clouds;
+mod level;
+mod lightning;
 mod noise_texture;
+mod precipitation;
 mod time_manager;
 mod weather_manager;
 mod weather_effects;
 
 pub use cloud_material::{CloudMaterial, CloudParams};
+pub use clouds::{CloudScroll, CLOUD_REPEAT};
+pub use level::{LevelWeather, LevelWeatherError, LevelWeatherLoader, WeatherChangeDesc};
+pub use lightning::{flash_brightness, fork_bolt, Lightning, LightningBolt, LightningSettings, LightningStrikeEvent};
 pub use noise_texture::{NoiseTexturePlugin, CloudNoiseTextureHandles};
+pub use precipitation::{
+    build_occlusion_map, Precipitation, PrecipitationKind, PrecipitationMaterial, PrecipitationOccluder,
+    PrecipitationPlugin, PrecipitationSettings,
+};
 pub use time_manager::{TimeOfDay, TimeManager};
-pub use weather_manager::{Weather, WeatherManager, WeatherState};
+pub use weather_manager::{
+    ForecastEntry, ScheduledWeather, Weather, WeatherManager, WeatherOverrides, WeatherParameters, WeatherState,
+};
 pub use weather_effects::{WeatherEffects, WeatherEffectType};
 
 use bevy::prelude::*;
@@ -20,16 +40,60 @@ impl Plugin for WeatherPlugin {
         app.add_plugins((
                 MaterialPlugin::<CloudMaterial>::default(),
                 NoiseTexturePlugin,
+                PrecipitationPlugin,
             ))
             .init_resource::<TimeManager>()
             .init_resource::<WeatherManager>()
             .init_resource::<WeatherEffects>()
+            .init_resource::<CloudScroll>()
+            .init_resource::<LightningSettings>()
+            .init_resource::<Lightning>()
+            .add_event::<LightningStrikeEvent>()
+            .init_asset::<LevelWeather>()
+            .init_asset_loader::<LevelWeatherLoader>()
             .add_systems(Update, (
+                apply_level_weather,
                 update_time_of_day,
                 update_weather_state,
                 update_weather_effects,
                 update_environment_lighting,
+                (clouds::update_cloud_scroll, clouds::update_cloud_materials, clouds::update_cloud_shadows)
+                    .chain()
+                    .after(update_weather_state),
+                lightning::strike_lightning,
+                lightning::flash_lightning.after(update_environment_lighting).after(lightning::strike_lightning),
             ));
+
+        // Gizmos need a renderer
+        if crate::game::render_available(app) {
+            app.add_systems(Update, lightning::draw_lightning.after(lightning::strike_lightning));
+        }
+    }
+}
+
+/// Marks level entities whose weather has been applied
+#[derive(Component)]
+pub struct LevelWeatherApplied;
+
+/// Starts loaded levels in their weather and schedules their timelines from now
+fn apply_level_weather(
+    mut commands: Commands,
+    levels: Query<(Entity, &Handle<LevelWeather>), Without<LevelWeatherApplied>>,
+    level_weather: Res<Assets<LevelWeather>>,
+    mut weather_manager: ResMut<WeatherManager>,
+) {
+    for (level, handle) in levels.iter() {
+        let Some(weather) = level_weather.get(handle) else {
+            continue;
+        };
+
+        weather_manager.set_weather(weather.initial, &weather.initial_overrides);
+        weather_manager.clear_schedule();
+        let start = weather_manager.elapsed();
+        for change in &weather.timeline {
+            weather_manager.schedule(change.scheduled(start));
+        }
+        commands.entity(level).insert(LevelWeatherApplied);
     }
 }
 
@@ -0,0 +1,432 @@
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_resource::{AsBindGroup, Extent3d, ShaderRef, ShaderType, TextureDimension, TextureFormat},
+        view::NoFrustumCulling,
+    },
+};
+
+use super::{Weather, WeatherManager, WeatherState};
+use crate::game::plugins::camera::GameCamera;
+use crate::terrain::TerrainQuery;
+
+/// Height used for occlusion cells with nothing above them
+const UNOCCLUDED: f32 = -1.0e6;
+
+/// Kind of precipitation falling around the camera
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrecipitationKind {
+    Rain,
+    Snow,
+}
+
+impl PrecipitationKind {
+    /// Precipitation produced by a weather type, if any
+    pub fn from_weather(weather: Weather) -> Option<Self> {
+        match weather {
+            Weather::Rain | Weather::Storm => Some(PrecipitationKind::Rain),
+            Weather::Snow => Some(PrecipitationKind::Snow),
+            _ => None,
+        }
+    }
+}
+
+/// What is currently falling, derived from the weather every frame
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct Precipitation {
+    /// Kind of precipitation, `None` when dry
+    pub kind: Option<PrecipitationKind>,
+    /// Fraction of the particle budget drawn (0.0 - 1.0)
+    pub intensity: f32,
+    /// Horizontal wind velocity in m/s
+    pub wind: Vec3,
+}
+
+impl Default for Precipitation {
+    fn default() -> Self {
+        Self {
+            kind: None,
+            intensity: 0.0,
+            wind: Vec3::ZERO,
+        }
+    }
+}
+
+impl Precipitation {
+    /// Derives precipitation from the weather state.
+    /// While transitioning into wet weather the incoming kind is used so it can fade in.
+    pub fn from_weather(state: &WeatherState) -> Self {
+        let kind = PrecipitationKind::from_weather(state.weather())
+            .or_else(|| state.transitioning_to().and_then(PrecipitationKind::from_weather));
+        let direction = state.wind_direction();
+        Self {
+            kind,
+            intensity: if kind.is_some() { state.precipitation().clamp(0.0, 1.0) } else { 0.0 },
+            wind: Vec3::new(direction.cos(), 0.0, direction.sin()) * state.wind_speed(),
+        }
+    }
+}
+
+/// Tuning for the camera-relative precipitation volumes
+#[derive(Resource, Debug, Clone)]
+pub struct PrecipitationSettings {
+    /// Size of the box of precipitation that follows the camera
+    pub volume_size: Vec3,
+    /// Rain drops at full intensity
+    pub rain_drops: u32,
+    /// Snow flakes at full intensity
+    pub snow_flakes: u32,
+    /// Terminal velocity of rain in m/s
+    pub rain_fall_speed: f32,
+    /// Terminal velocity of snow in m/s
+    pub snow_fall_speed: f32,
+    /// How much of each frame's motion a rain streak covers, in seconds
+    pub streak_length: f32,
+    /// Number of occlusion cells along each side of the volume
+    pub occlusion_resolution: u32,
+}
+
+impl Default for PrecipitationSettings {
+    fn default() -> Self {
+        Self {
+            volume_size: Vec3::new(40.0, 25.0, 40.0),
+            rain_drops: 20000,
+            snow_flakes: 12000,
+            rain_fall_speed: 9.0,
+            snow_fall_speed: 1.2,
+            streak_length: 0.03,
+            occlusion_resolution: 64,
+        }
+    }
+}
+
+/// Blocks precipitation below its top face, e.g. bridges and rock overhangs
+/// The box is axis aligned around the entity's translation
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PrecipitationOccluder {
+    pub half_extents: Vec3,
+}
+
+/// Marks the mesh entity drawing one kind of precipitation
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PrecipitationVolume(pub PrecipitationKind);
+
+/// Parameters for the precipitation vertex simulation
+#[derive(ShaderType, Debug, Clone, Copy, Default)]
+pub struct PrecipitationParams {
+    pub camera_position: Vec3,
+    pub time: f32,
+    pub wind: Vec3,
+    pub intensity: f32,
+    pub volume_size: Vec3,
+    pub fall_speed: f32,
+    pub color: Vec4,
+    pub occlusion_origin: Vec2,
+    pub occlusion_cell_size: f32,
+    pub streak_length: f32,
+    pub particle_size: f32,
+    /// 0 = rain streaks, 1 = snow flakes
+    pub kind: u32,
+}
+
+/// Material that simulates and draws precipitation entirely in the vertex shader.
+/// Each quad carries a random seed; position is a function of time, wind and camera,
+/// so no particle state has to live on the CPU.
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+pub struct PrecipitationMaterial {
+    #[uniform(0)]
+    pub params: PrecipitationParams,
+
+    /// Highest blocking surface per cell around the camera (R32Float)
+    #[texture(1, sample_type = "float", filterable = false)]
+    pub occlusion_map: Handle<Image>,
+}
+
+impl Material for PrecipitationMaterial {
+    fn vertex_shader() -> ShaderRef {
+        "shaders/precipitation.wgsl".into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        "shaders/precipitation.wgsl".into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        AlphaMode::Blend
+    }
+}
+
+/// Builds `count` quads whose vertex positions hold a random seed in [0, 1)^3
+fn build_precipitation_mesh(count: u32, seed: u32) -> Mesh {
+    let mut positions = Vec::with_capacity(count as usize * 4);
+    let mut uvs = Vec::with_capacity(count as usize * 4);
+    let mut indices = Vec::with_capacity(count as usize * 6);
+
+    let mut state = seed.max(1);
+    let mut next = || {
+        // xorshift32, deterministic so the pattern doesn't change between runs
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        (state >> 8) as f32 / (1u32 << 24) as f32
+    };
+
+    for i in 0..count {
+        let seed = [next(), next(), next()];
+        for corner in [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]] {
+            positions.push(seed);
+            uvs.push(corner);
+        }
+        let base = i * 4;
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+/// Fills an occlusion map: for each cell the highest surface that blocks precipitation.
+/// Cells are `cell_size` wide, starting at `origin` (world XZ), row-major in Z.
+pub fn build_occlusion_map(
+    origin: Vec2,
+    cell_size: f32,
+    resolution: u32,
+    terrain: Option<&TerrainQuery>,
+    occluders: &[(Vec3, Vec3)],
+) -> Vec<f32> {
+    let mut heights = vec![UNOCCLUDED; (resolution * resolution) as usize];
+    for z in 0..resolution {
+        for x in 0..resolution {
+            let center = origin + (Vec2::new(x as f32, z as f32) + 0.5) * cell_size;
+            let ground = terrain.and_then(|terrain| terrain.height(center.x, center.y)).unwrap_or(UNOCCLUDED);
+            let cover = occluders
+                .iter()
+                .filter(|(min, max)| {
+                    center.x >= min.x && center.x <= max.x && center.y >= min.z && center.y <= max.z
+                })
+                .map(|(_, max)| max.y)
+                .fold(ground, f32::max);
+            heights[(z * resolution + x) as usize] = cover;
+        }
+    }
+    heights
+}
+
+/// Spawns one hidden volume per precipitation kind
+fn spawn_precipitation_volumes(
+    mut commands: Commands,
+    settings: Res<PrecipitationSettings>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<PrecipitationMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let resolution = settings.occlusion_resolution;
+    let occlusion_map = images.add(Image::new_fill(
+        Extent3d { width: resolution, height: resolution, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        &UNOCCLUDED.to_le_bytes(),
+        TextureFormat::R32Float,
+    ));
+
+    let volumes = [
+        (PrecipitationKind::Rain, settings.rain_drops, Vec4::new(0.7, 0.75, 0.8, 0.35), 0.012),
+        (PrecipitationKind::Snow, settings.snow_flakes, Vec4::new(1.0, 1.0, 1.0, 0.9), 0.04),
+    ];
+    for (kind, count, color, particle_size) in volumes {
+        commands.spawn((
+            MaterialMeshBundle {
+                mesh: meshes.add(build_precipitation_mesh(count, count ^ (kind as u32 + 1))),
+                material: materials.add(PrecipitationMaterial {
+                    params: PrecipitationParams {
+                        color,
+                        particle_size,
+                        kind: kind as u32,
+                        ..default()
+                    },
+                    occlusion_map: occlusion_map.clone(),
+                }),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            // Vertices are placed by the shader, the mesh bounds mean nothing
+            NoFrustumCulling,
+            PrecipitationVolume(kind),
+            Name::new(format!("{kind:?} Precipitation")),
+        ));
+    }
+}
+
+/// Updates the precipitation resource from the weather
+fn update_precipitation(weather: Res<WeatherManager>, mut precipitation: ResMut<Precipitation>) {
+    let next = Precipitation::from_weather(weather.current_state());
+    if *precipitation != next {
+        *precipitation = next;
+    }
+}
+
+/// Moves the volumes with the camera and feeds the shader parameters
+fn update_precipitation_volumes(
+    precipitation: Res<Precipitation>,
+    settings: Res<PrecipitationSettings>,
+    time: Res<Time>,
+    cameras: Query<&GlobalTransform, With<GameCamera>>,
+    mut volumes: Query<(&PrecipitationVolume, &Handle<PrecipitationMaterial>, &mut Visibility)>,
+    mut materials: ResMut<Assets<PrecipitationMaterial>>,
+) {
+    let Some(camera) = cameras.iter().next() else {
+        return;
+    };
+    let camera_position = camera.translation();
+
+    // Cells snap to the grid so the occlusion map doesn't swim as the camera moves
+    let cell_size = settings.volume_size.x / settings.occlusion_resolution as f32;
+    let occlusion_origin = ((camera_position.xz() - settings.volume_size.xz() * 0.5) / cell_size).floor() * cell_size;
+
+    for (volume, handle, mut visibility) in volumes.iter_mut() {
+        let active = precipitation.kind == Some(volume.0) && precipitation.intensity > 0.0;
+        let target = if active { Visibility::Inherited } else { Visibility::Hidden };
+        if *visibility != target {
+            *visibility = target;
+        }
+        if !active {
+            continue;
+        }
+
+        let Some(material) = materials.get_mut(handle) else {
+            continue;
+        };
+        let params = &mut material.params;
+        params.camera_position = camera_position;
+        params.time = time.elapsed_seconds_wrapped();
+        params.wind = match volume.0 {
+            PrecipitationKind::Rain => precipitation.wind,
+            // Flakes are light and drift further with the wind
+            PrecipitationKind::Snow => precipitation.wind * 1.5,
+        };
+        params.intensity = precipitation.intensity;
+        params.volume_size = settings.volume_size;
+        params.fall_speed = match volume.0 {
+            PrecipitationKind::Rain => settings.rain_fall_speed,
+            PrecipitationKind::Snow => settings.snow_fall_speed,
+        };
+        params.streak_length = settings.streak_length;
+        params.occlusion_origin = occlusion_origin;
+        params.occlusion_cell_size = cell_size;
+    }
+}
+
+/// Rebuilds the occlusion map when the camera crosses a cell or an occluder moves
+fn update_occlusion_map(
+    settings: Res<PrecipitationSettings>,
+    precipitation: Res<Precipitation>,
+    terrain: Option<Res<TerrainQuery>>,
+    cameras: Query<&GlobalTransform, With<GameCamera>>,
+    occluders: Query<(&GlobalTransform, &PrecipitationOccluder)>,
+    changed_occluders: Query<(), Or<(Changed<GlobalTransform>, Changed<PrecipitationOccluder>)>>,
+    volumes: Query<&Handle<PrecipitationMaterial>, With<PrecipitationVolume>>,
+    materials: Res<Assets<PrecipitationMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    mut last_origin: Local<Option<Vec2>>,
+) {
+    if precipitation.kind.is_none() {
+        return;
+    }
+    let Some(camera) = cameras.iter().next() else {
+        return;
+    };
+
+    let resolution = settings.occlusion_resolution;
+    let cell_size = settings.volume_size.x / resolution as f32;
+    let origin = ((camera.translation().xz() - settings.volume_size.xz() * 0.5) / cell_size).floor() * cell_size;
+
+    let terrain_changed = terrain.as_ref().map_or(false, |terrain| terrain.is_changed());
+    if *last_origin == Some(origin) && changed_occluders.is_empty() && !terrain_changed {
+        return;
+    }
+    *last_origin = Some(origin);
+
+    let boxes: Vec<(Vec3, Vec3)> = occluders
+        .iter()
+        .map(|(transform, occluder)| {
+            let center = transform.translation();
+            (center - occluder.half_extents, center + occluder.half_extents)
+        })
+        .collect();
+    let heights = build_occlusion_map(origin, cell_size, resolution, terrain.as_deref(), &boxes);
+
+    // Both volumes share the same map
+    let Some(handle) = volumes.iter().next().and_then(|h| materials.get(h)).map(|m| m.occlusion_map.clone()) else {
+        return;
+    };
+    if let Some(image) = images.get_mut(&handle) {
+        image.data = bytemuck::cast_slice(&heights).to_vec();
+    }
+}
+
+/// Plugin that adds GPU rain and snow around the camera
+pub struct PrecipitationPlugin;
+
+impl Plugin for PrecipitationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MaterialPlugin::<PrecipitationMaterial>::default())
+            .init_resource::<PrecipitationSettings>()
+            .init_resource::<Precipitation>()
+            .add_systems(Startup, spawn_precipitation_volumes)
+            .add_systems(Update, (
+                update_precipitation,
+                update_precipitation_volumes,
+                update_occlusion_map,
+            ).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::ChunkHeights;
+
+    #[test]
+    fn test_precipitation_follows_weather() {
+        let rain = Precipitation::from_weather(&WeatherState::new(Weather::Storm));
+        assert_eq!(rain.kind, Some(PrecipitationKind::Rain));
+        assert!(rain.intensity > 0.9);
+        assert!(rain.wind.length() > 10.0);
+
+        let snow = Precipitation::from_weather(&WeatherState::new(Weather::Snow));
+        assert_eq!(snow.kind, Some(PrecipitationKind::Snow));
+
+        let clear = Precipitation::from_weather(&WeatherState::new(Weather::Clear));
+        assert_eq!(clear.kind, None);
+        assert_eq!(clear.intensity, 0.0);
+    }
+
+    #[test]
+    fn test_occluders_cover_cells_below_them() {
+        let bridge = (Vec3::new(2.0, 4.0, 0.0), Vec3::new(4.0, 5.0, 8.0));
+        let heights = build_occlusion_map(Vec2::ZERO, 1.0, 8, None, &[bridge]);
+
+        // Cell centered at (2.5, 0.5) sits under the bridge deck
+        assert_eq!(heights[2], 5.0);
+        // Open sky next to it
+        assert_eq!(heights[0], UNOCCLUDED);
+    }
+
+    #[test]
+    fn test_terrain_occludes_precipitation() {
+        let mut terrain = TerrainQuery::default();
+        terrain.insert_chunk(ChunkHeights::from_fn(IVec2::ZERO, 8, |_, _| 3.0));
+        let heights = build_occlusion_map(Vec2::ZERO, 1.0, 8, Some(&terrain), &[]);
+        assert!(heights.iter().all(|h| (*h - 3.0).abs() < 1e-5));
+    }
+
+    #[test]
+    fn test_mesh_has_one_quad_per_particle() {
+        let mesh = build_precipitation_mesh(10, 7);
+        assert_eq!(mesh.count_vertices(), 40);
+        assert_eq!(mesh.indices().map(|i| i.len()), Some(60));
+    }
+}
@@ -10,6 +10,7 @@ struct CloudParams {
     wind_speed: f32,
     precipitation_threshold: f32,
     time: f32,
+    scroll: vec2<f32>,
 }
 
 @group(1) @binding(0)
@@ -53,24 +54,22 @@ fn vertex(vertex: VertexInput) -> VertexOutput {
     return out;
 }
 
-// Helper function to sample 3D noise with wind offset
-fn sample_noise(pos: vec3<f32>, scale: f32, offset: vec2<f32>) -> f32 {
-    let wind_offset = vec3<f32>(offset * params.wind_speed * params.time, 0.0);
-    let sample_pos = (pos * scale + wind_offset) * 0.001;
+// Helper function to sample 3D noise, `pos` already carried along by the wind
+fn sample_noise(pos: vec3<f32>, scale: f32) -> f32 {
+    let sample_pos = pos * scale * 0.001;
     return textureSample(base_shape_texture, base_shape_sampler, sample_pos).r;
 }
 
 // Calculate cloud density at a point
 fn get_cloud_density(pos: vec3<f32>) -> f32 {
-    // Apply wind displacement
-    let wind_dir = vec3<f32>(params.wind_direction.x, 0.0, params.wind_direction.y);
-    let wind_offset = wind_dir * params.wind_speed * params.time;
+    // Apply wind displacement, the same scroll the terrain's cloud shadows use
+    let wind_offset = vec3<f32>(params.scroll.x, 0.0, params.scroll.y);
     
     // Sample base shape noise
-    var density = sample_noise(pos + wind_offset, 1.0, params.wind_direction);
+    var density = sample_noise(pos - wind_offset, 1.0);
     
-    // Add detail noise
-    let detail = sample_noise(pos + wind_offset, 4.0, params.wind_direction * 2.0);
+    // Add detail noise, churning a little faster than the clouds drift
+    let detail = sample_noise(pos - wind_offset * 2.0, 4.0);
     density = density + detail * 0.2;
     
     // Sample weather influence
@@ -99,6 +99,11 @@ impl TimeManager {
         self.current_time
     }
 
+    /// Get the normalized sun height (0.0 at the nadir, 0.5 on the horizon, 1.0 at the zenith)
+    pub fn sun_height(&self) -> f32 {
+        self.sun_height
+    }
+
     /// Set the game time speed (seconds per hour)
     pub fn set_time_speed(&mut self, seconds_per_hour: f32) {
         self.seconds_per_hour = seconds_per_hour.max(1.0);
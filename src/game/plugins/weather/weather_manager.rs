@@ -1,8 +1,10 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
 /// Different types of weather conditions
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Weather {
     Clear,
     Cloudy,
@@ -12,6 +14,122 @@ pub enum Weather {
     Snow,
 }
 
+impl Weather {
+    pub const ALL: [Weather; 6] =
+        [Weather::Clear, Weather::Cloudy, Weather::Rain, Weather::Storm, Weather::Fog, Weather::Snow];
+
+    /// Whether rain falls in this weather, snow doesn't count
+    pub fn is_rain(self) -> bool {
+        matches!(self, Weather::Rain | Weather::Storm)
+    }
+
+    /// Locale key of the weather's name
+    pub fn name_key(self) -> &'static str {
+        match self {
+            Weather::Clear => "weather.clear",
+            Weather::Cloudy => "weather.cloudy",
+            Weather::Rain => "weather.rain",
+            Weather::Storm => "weather.storm",
+            Weather::Fog => "weather.fog",
+            Weather::Snow => "weather.snow",
+        }
+    }
+}
+
+/// Weather parameters that blend during a transition
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeatherParameters {
+    /// 0.0 - 1.0
+    pub cloud_coverage: f32,
+    /// 0.0 - 1.0
+    pub precipitation: f32,
+    /// m/s
+    pub wind_speed: f32,
+    /// Radians
+    pub wind_direction: f32,
+    /// 0.0 - 1.0
+    pub fog_density: f32,
+}
+
+impl WeatherParameters {
+    /// Base parameters of a weather type, blowing from `wind_direction`
+    pub fn for_weather(weather: Weather, wind_direction: f32) -> Self {
+        let (cloud_coverage, precipitation, wind_speed, fog_density) = match weather {
+            Weather::Clear => (0.1, 0.0, 2.0, 0.0),
+            Weather::Cloudy => (0.7, 0.0, 5.0, 0.1),
+            Weather::Rain => (0.9, 0.6, 8.0, 0.3),
+            Weather::Storm => (1.0, 1.0, 15.0, 0.4),
+            Weather::Fog => (0.5, 0.0, 2.0, 0.8),
+            Weather::Snow => (0.8, 0.7, 4.0, 0.2),
+        };
+        Self { cloud_coverage, precipitation, wind_speed, wind_direction, fog_density }
+    }
+
+    /// Blend towards `other`, the wind turning the short way round
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let turn = (other.wind_direction - self.wind_direction + std::f32::consts::PI)
+            .rem_euclid(std::f32::consts::TAU)
+            - std::f32::consts::PI;
+        Self {
+            cloud_coverage: self.cloud_coverage.lerp(other.cloud_coverage, t),
+            precipitation: self.precipitation.lerp(other.precipitation, t),
+            wind_speed: self.wind_speed.lerp(other.wind_speed, t),
+            wind_direction: (self.wind_direction + turn * t).rem_euclid(std::f32::consts::TAU),
+            fog_density: self.fog_density.lerp(other.fog_density, t),
+        }
+    }
+}
+
+/// Parameters a scheduled change sets instead of its weather's defaults
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct WeatherOverrides {
+    #[serde(default)]
+    pub cloud_coverage: Option<f32>,
+    #[serde(default)]
+    pub precipitation: Option<f32>,
+    #[serde(default)]
+    pub wind_speed: Option<f32>,
+    /// Degrees
+    #[serde(default)]
+    pub wind_direction: Option<f32>,
+    #[serde(default)]
+    pub fog_density: Option<f32>,
+}
+
+impl WeatherOverrides {
+    fn apply(&self, mut parameters: WeatherParameters) -> WeatherParameters {
+        parameters.cloud_coverage = self.cloud_coverage.unwrap_or(parameters.cloud_coverage).clamp(0.0, 1.0);
+        parameters.precipitation = self.precipitation.unwrap_or(parameters.precipitation).clamp(0.0, 1.0);
+        parameters.wind_speed = self.wind_speed.unwrap_or(parameters.wind_speed).max(0.0);
+        if let Some(degrees) = self.wind_direction {
+            parameters.wind_direction = degrees.to_radians().rem_euclid(std::f32::consts::TAU);
+        }
+        parameters.fog_density = self.fog_density.unwrap_or(parameters.fog_density).clamp(0.0, 1.0);
+        parameters
+    }
+}
+
+/// A weather change on the schedule
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScheduledWeather {
+    /// When the transition starts, in seconds on the weather clock
+    pub at: f32,
+    pub weather: Weather,
+    /// Seconds the transition takes
+    pub transition: f32,
+    pub overrides: WeatherOverrides,
+}
+
+/// An upcoming change, as the forecast tells it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ForecastEntry {
+    /// Seconds from now until the transition starts
+    pub starts_in: f32,
+    pub weather: Weather,
+    pub precipitation: f32,
+    pub wind_speed: f32,
+}
+
 /// Represents the current state of the weather, including transition effects
 #[derive(Debug, Clone)]
 pub struct WeatherState {
@@ -31,11 +149,17 @@ pub struct WeatherState {
     wind_direction: f32,
     /// Current fog density (0.0 - 1.0)
     fog_density: f32,
+    /// Parameters the transition blends between
+    from: WeatherParameters,
+    to: WeatherParameters,
+    /// Seconds the transition in progress takes
+    transition_seconds: f32,
 }
 
 impl WeatherState {
     /// Create a new weather state with the given weather type
     pub fn new(weather: Weather) -> Self {
+        let parameters = WeatherParameters::for_weather(weather, 0.0);
         let mut state = Self {
             weather,
             transitioning_to: None,
@@ -45,51 +169,63 @@ impl WeatherState {
             wind_speed: 0.0,
             wind_direction: 0.0,
             fog_density: 0.0,
+            from: parameters,
+            to: parameters,
+            transition_seconds: 0.0,
         };
-        state.apply_weather_parameters(weather);
+        state.set_parameters(parameters);
         state
     }
 
-    /// Apply base parameters for a weather type
-    fn apply_weather_parameters(&mut self, weather: Weather) {
-        match weather {
-            Weather::Clear => {
-                self.cloud_coverage = 0.1;
-                self.precipitation = 0.0;
-                self.wind_speed = 2.0;
-                self.fog_density = 0.0;
-            }
-            Weather::Cloudy => {
-                self.cloud_coverage = 0.7;
-                self.precipitation = 0.0;
-                self.wind_speed = 5.0;
-                self.fog_density = 0.1;
-            }
-            Weather::Rain => {
-                self.cloud_coverage = 0.9;
-                self.precipitation = 0.6;
-                self.wind_speed = 8.0;
-                self.fog_density = 0.3;
-            }
-            Weather::Storm => {
-                self.cloud_coverage = 1.0;
-                self.precipitation = 1.0;
-                self.wind_speed = 15.0;
-                self.fog_density = 0.4;
-            }
-            Weather::Fog => {
-                self.cloud_coverage = 0.5;
-                self.precipitation = 0.0;
-                self.wind_speed = 2.0;
-                self.fog_density = 0.8;
-            }
-            Weather::Snow => {
-                self.cloud_coverage = 0.8;
-                self.precipitation = 0.7;
-                self.wind_speed = 4.0;
-                self.fog_density = 0.2;
+    /// Blended parameters right now
+    pub fn parameters(&self) -> WeatherParameters {
+        WeatherParameters {
+            cloud_coverage: self.cloud_coverage,
+            precipitation: self.precipitation,
+            wind_speed: self.wind_speed,
+            wind_direction: self.wind_direction,
+            fog_density: self.fog_density,
+        }
+    }
+
+    fn set_parameters(&mut self, parameters: WeatherParameters) {
+        self.cloud_coverage = parameters.cloud_coverage;
+        self.precipitation = parameters.precipitation;
+        self.wind_speed = parameters.wind_speed;
+        self.wind_direction = parameters.wind_direction;
+        self.fog_density = parameters.fog_density;
+    }
+
+    /// Starts blending from where the weather is now, even halfway through another transition
+    fn begin_transition(&mut self, weather: Weather, to: WeatherParameters, seconds: f32) {
+        if let Some(target) = self.transitioning_to {
+            // The half-finished weather counts as where it's coming from
+            if self.transition_progress >= 0.5 {
+                self.weather = target;
             }
         }
+        self.from = self.parameters();
+        self.to = to;
+        self.transitioning_to = Some(weather);
+        self.transition_progress = 0.0;
+        self.transition_seconds = seconds;
+    }
+
+    /// Moves the transition on, eased so the parameters don't jump at either end
+    fn advance(&mut self, delta_seconds: f32) {
+        let Some(target) = self.transitioning_to else {
+            return;
+        };
+        self.transition_progress += delta_seconds / self.transition_seconds.max(f32::EPSILON);
+        if self.transition_progress >= 1.0 {
+            self.weather = target;
+            self.transitioning_to = None;
+            self.transition_progress = 0.0;
+            self.set_parameters(self.to);
+        } else {
+            let t = self.transition_progress;
+            self.set_parameters(self.from.lerp(&self.to, t * t * (3.0 - 2.0 * t)));
+        }
     }
 
     /// Get the light intensity modifier for the current weather
@@ -148,6 +284,55 @@ impl WeatherState {
     pub fn ambient_intensity_modifier(&self) -> f32 {
         1.0 - (self.cloud_coverage * 0.5)
     }
+
+    /// Get the current primary weather type
+    pub fn weather(&self) -> Weather {
+        self.weather
+    }
+
+    /// Get the weather being transitioned to, if any
+    pub fn transitioning_to(&self) -> Option<Weather> {
+        self.transitioning_to
+    }
+
+    /// Get the transition progress (0.0 - 1.0)
+    pub fn transition_progress(&self) -> f32 {
+        self.transition_progress
+    }
+
+    /// Get the current cloud coverage (0.0 - 1.0)
+    pub fn cloud_coverage(&self) -> f32 {
+        self.cloud_coverage
+    }
+
+    /// Get the current precipitation intensity (0.0 - 1.0)
+    pub fn precipitation(&self) -> f32 {
+        self.precipitation
+    }
+
+    /// Get the current wind speed (m/s)
+    pub fn wind_speed(&self) -> f32 {
+        self.wind_speed
+    }
+
+    /// Get the current wind direction (radians)
+    pub fn wind_direction(&self) -> f32 {
+        self.wind_direction
+    }
+
+    /// Get the current fog density (0.0 - 1.0)
+    pub fn fog_density(&self) -> f32 {
+        self.fog_density
+    }
+
+    /// How much of a storm there is (0.0 - 1.0), rising and falling through transitions
+    pub fn storm_intensity(&self) -> f32 {
+        let storm = |weather: Weather| if weather == Weather::Storm { 1.0 } else { 0.0 };
+        match self.transitioning_to {
+            Some(target) => storm(self.weather).lerp(storm(target), self.transition_progress),
+            None => storm(self.weather),
+        }
+    }
 }
 
 /// Resource that manages weather transitions and state
@@ -161,6 +346,16 @@ pub struct WeatherManager {
     min_change_interval: Duration,
     /// Time since last weather change
     time_since_change: Duration,
+    /// Ground covered by settled snow (0.0 - 1.0)
+    snow_cover: f32,
+    /// Seconds of full intensity snowfall to cover the ground completely
+    snow_accumulation_time: f32,
+    /// Snow cover lost per second while it isn't snowing
+    snow_melt_rate: f32,
+    /// Seconds the weather clock has run, scheduled changes are timed on it
+    elapsed: f32,
+    /// Changes still to come, soonest first
+    schedule: Vec<ScheduledWeather>,
 }
 
 impl Default for WeatherManager {
@@ -170,6 +365,11 @@ impl Default for WeatherManager {
             transition_duration: Duration::from_secs(30),
             min_change_interval: Duration::from_secs(300),
             time_since_change: Duration::ZERO,
+            snow_cover: 0.0,
+            snow_accumulation_time: 600.0,
+            snow_melt_rate: 1.0 / 300.0,
+            elapsed: 0.0,
+            schedule: Vec::new(),
         }
     }
 }
@@ -179,20 +379,26 @@ impl WeatherManager {
     pub fn update(&mut self, delta_seconds: f32) {
         let delta = Duration::from_secs_f32(delta_seconds);
         self.time_since_change += delta;
+        self.elapsed += delta_seconds;
 
-        // Update transition if in progress
-        if let Some(target) = self.state.transitioning_to {
-            self.state.transition_progress += delta_seconds / self.transition_duration.as_secs_f32();
-            
-            if self.state.transition_progress >= 1.0 {
-                // Transition complete
-                self.state.weather = target;
-                self.state.transitioning_to = None;
-                self.state.transition_progress = 0.0;
-                self.state.apply_weather_parameters(target);
-            }
+        // Scheduled changes that came due, a late one still gets its full transition
+        while self.schedule.first().map_or(false, |change| change.at <= self.elapsed) {
+            let change = self.schedule.remove(0);
+            let to = change.overrides.apply(WeatherParameters::for_weather(change.weather, self.state.wind_direction));
+            self.state.begin_transition(change.weather, to, change.transition);
+            self.time_since_change = Duration::ZERO;
         }
 
+        self.state.advance(delta_seconds);
+
+        // Snow settles while it falls and melts away once it stops
+        if self.state.weather == Weather::Snow && self.state.precipitation > 0.0 {
+            self.snow_cover += self.state.precipitation * delta_seconds / self.snow_accumulation_time.max(f32::EPSILON);
+        } else {
+            self.snow_cover -= self.snow_melt_rate * delta_seconds;
+        }
+        self.snow_cover = self.snow_cover.clamp(0.0, 1.0);
+
         // Random weather changes (disabled for now, will be controlled by game logic)
         /*
         if self.time_since_change >= self.min_change_interval {
@@ -212,12 +418,113 @@ impl WeatherManager {
     /// Change to a new weather type with transition
     pub fn change_weather(&mut self, weather: Weather) {
         if weather != self.state.weather && self.state.transitioning_to.is_none() {
-            self.state.transitioning_to = Some(weather);
-            self.state.transition_progress = 0.0;
+            let to = WeatherParameters::for_weather(weather, self.state.wind_direction);
+            self.state.begin_transition(weather, to, self.transition_duration.as_secs_f32());
             self.time_since_change = Duration::ZERO;
         }
     }
 
+    /// Switches to `weather` at once, with no transition, e.g. for the start of a level
+    pub fn set_weather(&mut self, weather: Weather, overrides: &WeatherOverrides) {
+        let parameters = overrides.apply(WeatherParameters::for_weather(weather, self.state.wind_direction));
+        self.state.weather = weather;
+        self.state.transitioning_to = None;
+        self.state.transition_progress = 0.0;
+        self.state.from = parameters;
+        self.state.to = parameters;
+        self.state.set_parameters(parameters);
+        self.time_since_change = Duration::ZERO;
+    }
+
+    /// Seconds the weather clock has run
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    /// Adds a change to the schedule, `change.at` is on the weather clock
+    pub fn schedule(&mut self, change: ScheduledWeather) {
+        let index = self.schedule.partition_point(|scheduled| scheduled.at <= change.at);
+        self.schedule.insert(index, change);
+    }
+
+    /// Schedules `weather` to start moving in `seconds` from now, over the default transition
+    pub fn schedule_in(&mut self, seconds: f32, weather: Weather) {
+        self.schedule(ScheduledWeather {
+            at: self.elapsed + seconds.max(0.0),
+            weather,
+            transition: self.transition_duration.as_secs_f32(),
+            overrides: WeatherOverrides::default(),
+        });
+    }
+
+    /// Drops every change still to come
+    pub fn clear_schedule(&mut self) {
+        self.schedule.clear();
+    }
+
+    /// Changes still to come, soonest first
+    pub fn scheduled(&self) -> &[ScheduledWeather] {
+        &self.schedule
+    }
+
+    /// Changes starting within the next `horizon` seconds, soonest first
+    pub fn forecast(&self, horizon: f32) -> Vec<ForecastEntry> {
+        self.schedule
+            .iter()
+            .take_while(|change| change.at - self.elapsed <= horizon)
+            .map(|change| {
+                let parameters = change.overrides.apply(WeatherParameters::for_weather(change.weather, 0.0));
+                ForecastEntry {
+                    starts_in: (change.at - self.elapsed).max(0.0),
+                    weather: change.weather,
+                    precipitation: parameters.precipitation,
+                    wind_speed: parameters.wind_speed,
+                }
+            })
+            .collect()
+    }
+
+    /// Weather it will be in `seconds`, counting a transition as done once it's halfway
+    pub fn weather_in(&self, seconds: f32) -> Weather {
+        let at = self.elapsed + seconds;
+        let current = match self.state.transitioning_to {
+            Some(target) => {
+                let remaining = (1.0 - self.state.transition_progress) * self.state.transition_seconds;
+                if seconds >= remaining - self.state.transition_seconds * 0.5 { target } else { self.state.weather }
+            }
+            None => self.state.weather,
+        };
+        self.schedule
+            .iter()
+            .take_while(|change| change.at + change.transition * 0.5 <= at)
+            .last()
+            .map_or(current, |change| change.weather)
+    }
+
+    /// Whether rain is falling at any point in the next `seconds`, including now
+    pub fn will_rain_within(&self, seconds: f32) -> bool {
+        let raining_now = self.state.weather.is_rain() && self.state.precipitation > 0.0;
+        let rain_coming = self.state.transitioning_to.map_or(false, Weather::is_rain);
+        raining_now
+            || rain_coming
+            || self.forecast(seconds).iter().any(|entry| entry.weather.is_rain() && entry.precipitation > 0.0)
+    }
+
+    /// Get how much of the ground settled snow covers (0.0 - 1.0)
+    pub fn snow_cover(&self) -> f32 {
+        self.snow_cover
+    }
+
+    /// Set the settled snow cover, e.g. for a level that starts snowed in
+    pub fn set_snow_cover(&mut self, cover: f32) {
+        self.snow_cover = cover.clamp(0.0, 1.0);
+    }
+
+    /// Set how fast snow cover melts while it isn't snowing, per second
+    pub fn set_snow_melt_rate(&mut self, rate: f32) {
+        self.snow_melt_rate = rate.max(0.0);
+    }
+
     /// Set the weather transition duration
     pub fn set_transition_duration(&mut self, duration: Duration) {
         self.transition_duration = duration;
@@ -244,4 +551,83 @@ impl WeatherManager {
             }
         }
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snow_settles_and_melts() {
+        let mut manager = WeatherManager::default();
+        manager.set_transition_duration(Duration::from_secs(1));
+        manager.change_weather(Weather::Snow);
+        manager.update(1.0);
+        assert_eq!(manager.current_state().weather(), Weather::Snow);
+
+        manager.update(60.0);
+        let settled = manager.snow_cover();
+        assert!(settled > 0.0 && settled < 1.0);
+
+        manager.change_weather(Weather::Clear);
+        manager.update(1.0);
+        manager.set_snow_melt_rate(0.0);
+        manager.update(60.0);
+        assert!(manager.snow_cover() > 0.0);
+        manager.set_snow_melt_rate(1.0);
+        manager.update(1.0);
+        assert_eq!(manager.snow_cover(), 0.0);
+    }
+
+    #[test]
+    fn test_transition_blends_parameters() {
+        let mut manager = WeatherManager::default();
+        manager.set_transition_duration(Duration::from_secs(10));
+        manager.change_weather(Weather::Storm);
+        manager.update(5.0);
+        let state = manager.current_state();
+        let clear = WeatherParameters::for_weather(Weather::Clear, 0.0);
+        let storm = WeatherParameters::for_weather(Weather::Storm, 0.0);
+        assert!(state.wind_speed() > clear.wind_speed && state.wind_speed() < storm.wind_speed);
+        assert!(state.precipitation() > 0.0 && state.precipitation() < storm.precipitation);
+
+        manager.update(5.0);
+        assert_eq!(manager.current_state().weather(), Weather::Storm);
+        assert_eq!(manager.current_state().precipitation(), storm.precipitation);
+    }
+
+    #[test]
+    fn test_schedule_and_forecast() {
+        let mut manager = WeatherManager::default();
+        manager.schedule(ScheduledWeather {
+            at: 600.0,
+            weather: Weather::Storm,
+            transition: 60.0,
+            overrides: WeatherOverrides { wind_speed: Some(20.0), ..default() },
+        });
+        manager.schedule_in(300.0, Weather::Cloudy);
+        assert_eq!(manager.scheduled()[0].weather, Weather::Cloudy);
+
+        assert!(!manager.will_rain_within(5.0 * 60.0));
+        assert!(manager.will_rain_within(10.0 * 60.0));
+        assert_eq!(manager.forecast(400.0).len(), 1);
+        assert_eq!(manager.weather_in(400.0), Weather::Cloudy);
+        assert_eq!(manager.weather_in(700.0), Weather::Storm);
+
+        manager.update(599.0);
+        manager.update(1.0);
+        assert!(manager.forecast(f32::MAX).is_empty());
+        assert_eq!(manager.current_state().transitioning_to(), Some(Weather::Storm));
+        manager.update(60.0);
+        assert_eq!(manager.current_state().weather(), Weather::Storm);
+        assert_eq!(manager.current_state().wind_speed(), 20.0);
+    }
+
+    #[test]
+    fn test_wind_turns_the_short_way() {
+        let from = WeatherParameters { wind_direction: 0.1, ..WeatherParameters::for_weather(Weather::Clear, 0.1) };
+        let to = WeatherParameters { wind_direction: std::f32::consts::TAU - 0.1, ..from };
+        let halfway = from.lerp(&to, 0.5).wind_direction;
+        assert!(halfway < 1e-4 || halfway > std::f32::consts::TAU - 1e-4);
+    }
+}
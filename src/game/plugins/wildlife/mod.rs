@@ -0,0 +1,484 @@
+/// Ambient wildlife for free roam
+///
+/// Deer and birds spawn in a ring around the player's vehicle, in biomes that suit them, and
+/// despawn once left behind. They idle and wander around where they spawned and bolt when engine
+/// noise reaches them. There's no pathfinding: deer run straight away from the noise, birds take off.
+mod noise;
+
+use bevy::core::FrameCount;
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+
+pub use noise::{attenuate, engine_noise_db, noise_at, update_vehicle_noise, HeardNoise, NoiseEmitter};
+
+use super::floating_origin::{OriginShiftEvent, ShiftOrigin};
+use super::post_process::HeatSource;
+use super::relevance::{track_relevance, Relevance};
+use crate::game::vehicle::Vehicle;
+use crate::game::{GameState, StateScoped};
+use crate::terrain::{Biome, BiomeField, TerrainQuery};
+
+/// Kinds of ambient animals
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WildlifeSpecies {
+    Deer,
+    Bird,
+}
+
+impl WildlifeSpecies {
+    pub const ALL: [WildlifeSpecies; 2] = [WildlifeSpecies::Deer, WildlifeSpecies::Bird];
+
+    /// Biomes the species spawns in, empty means anywhere
+    pub fn biomes(&self) -> &'static [Biome] {
+        match self {
+            WildlifeSpecies::Deer => &[Biome::Forest, Biome::Alpine],
+            WildlifeSpecies::Bird => &[],
+        }
+    }
+
+    /// Noise in dB at the animal that makes it flee
+    pub fn flee_threshold_db(&self) -> f32 {
+        match self {
+            WildlifeSpecies::Deer => 55.0,
+            // Birds sit tighter, they can leave faster
+            WildlifeSpecies::Bird => 62.0,
+        }
+    }
+
+    /// Body temperature in °C, what shows in thermal
+    pub fn body_temperature(&self) -> f32 {
+        match self {
+            WildlifeSpecies::Deer => 38.5,
+            WildlifeSpecies::Bird => 41.0,
+        }
+    }
+
+    pub fn walk_speed(&self) -> f32 {
+        match self {
+            WildlifeSpecies::Deer => 1.2,
+            WildlifeSpecies::Bird => 0.6,
+        }
+    }
+
+    pub fn flee_speed(&self) -> f32 {
+        match self {
+            WildlifeSpecies::Deer => 12.0,
+            WildlifeSpecies::Bird => 9.0,
+        }
+    }
+
+    /// Animals spawned together
+    pub fn group_size(&self) -> std::ops::RangeInclusive<u32> {
+        match self {
+            WildlifeSpecies::Deer => 1..=3,
+            WildlifeSpecies::Bird => 3..=6,
+        }
+    }
+
+    pub fn max_population(&self) -> usize {
+        match self {
+            WildlifeSpecies::Deer => 8,
+            WildlifeSpecies::Bird => 18,
+        }
+    }
+}
+
+/// What an animal is doing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CritterState {
+    Idle { remaining: f32 },
+    Wander { target: Vec3 },
+    Flee { direction: Vec3, remaining: f32 },
+}
+
+/// An ambient animal
+#[derive(Component, Debug, Clone)]
+pub struct Critter {
+    pub species: WildlifeSpecies,
+    pub state: CritterState,
+    /// Where it spawned, wandering stays around here
+    pub home: Vec3,
+}
+
+/// Moves critters' homes and wander targets along with the world origin
+fn rebase_critters(mut shifts: EventReader<OriginShiftEvent>, mut critters: Query<&mut Critter>) {
+    for shift in shifts.read() {
+        for mut critter in critters.iter_mut() {
+            critter.home -= shift.translation();
+            if let CritterState::Wander { target } = &mut critter.state {
+                *target -= shift.translation();
+            }
+        }
+    }
+}
+
+/// Wildlife spawning configuration
+#[derive(Resource, Clone, Debug)]
+pub struct WildlifeSettings {
+    pub enabled: bool,
+    /// Animals spawn between these distances from the player, out of easy view
+    pub spawn_min_distance: f32,
+    pub spawn_max_distance: f32,
+    /// Animals further than this from every player despawn
+    pub despawn_distance: f32,
+    /// Seconds between spawn attempts
+    pub spawn_interval: f32,
+    /// How far from home an animal wanders
+    pub wander_radius: f32,
+    /// How long an animal keeps running once spooked, in seconds
+    pub flee_duration: f32,
+    /// How active animals are (0.0 - 1.0), the world clock raises it at dawn and dusk.
+    /// Fewer groups spawn and fewer of each species are about the lower it is.
+    pub activity: f32,
+}
+
+impl Default for WildlifeSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            spawn_min_distance: 60.0,
+            spawn_max_distance: 120.0,
+            despawn_distance: 180.0,
+            spawn_interval: 4.0,
+            wander_radius: 15.0,
+            flee_duration: 6.0,
+            activity: 1.0,
+        }
+    }
+}
+
+/// Cheap xorshift generator for spawn placement and idle timing
+#[derive(Resource, Debug, Clone)]
+pub struct WildlifeRng(u32);
+
+impl Default for WildlifeRng {
+    fn default() -> Self {
+        Self(0x9e37_79b9)
+    }
+}
+
+impl WildlifeRng {
+    pub fn from_seed(seed: u32) -> Self {
+        // xorshift gets stuck on zero
+        Self(seed.max(1))
+    }
+
+    /// Uniform in 0.0..1.0
+    pub fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    pub fn range_u32(&mut self, range: std::ops::RangeInclusive<u32>) -> u32 {
+        let span = range.end() - range.start() + 1;
+        range.start() + ((self.next_f32() * span as f32) as u32).min(span - 1)
+    }
+
+    /// Random point in the XZ ring between `min` and `max` meters around `center`
+    pub fn ring_point(&mut self, center: Vec3, min: f32, max: f32) -> Vec3 {
+        let angle = self.range(0.0, TAU);
+        // Square root keeps the density even across the ring
+        let radius = (self.range(min * min, max * max)).sqrt();
+        center + Vec3::new(angle.cos() * radius, 0.0, angle.sin() * radius)
+    }
+}
+
+/// Whether a species can live in `biome`. Without terrain biomes, `None`, anything goes.
+pub fn suits_species(species: WildlifeSpecies, biome: Option<Biome>) -> bool {
+    let allowed = species.biomes();
+    biome.map_or(true, |biome| allowed.is_empty() || allowed.contains(&biome))
+}
+
+/// Horizontal direction away from a noise source
+pub fn flee_direction(position: Vec3, source: Vec3) -> Vec3 {
+    let away = (position - source) * Vec3::new(1.0, 0.0, 1.0);
+    away.try_normalize().unwrap_or(Vec3::X)
+}
+
+/// Meshes and materials shared by every animal of a species
+#[derive(Resource)]
+pub struct WildlifeAssets {
+    pub deer_mesh: Handle<Mesh>,
+    pub deer_material: Handle<StandardMaterial>,
+    pub bird_mesh: Handle<Mesh>,
+    pub bird_material: Handle<StandardMaterial>,
+}
+
+#[derive(Resource, Default)]
+struct WildlifeSpawnTimer(f32);
+
+fn setup_wildlife_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(WildlifeAssets {
+        deer_mesh: meshes.add(shape::Box::new(0.45, 1.1, 1.5).into()),
+        deer_material: materials.add(Color::rgb(0.45, 0.3, 0.18).into()),
+        bird_mesh: meshes.add(shape::UVSphere { radius: 0.12, sectors: 8, stacks: 4 }.into()),
+        bird_material: materials.add(Color::rgb(0.15, 0.14, 0.13).into()),
+    });
+}
+
+fn ground_height(terrain: Option<&TerrainQuery>, point: Vec3) -> f32 {
+    terrain.and_then(|terrain| terrain.height(point.x, point.z)).unwrap_or(point.y)
+}
+
+/// Spawns groups of animals around players, up to each species' population cap
+#[allow(clippy::too_many_arguments)]
+fn spawn_wildlife(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<WildlifeSettings>,
+    assets: Res<WildlifeAssets>,
+    terrain: Option<Res<TerrainQuery>>,
+    mut rng: ResMut<WildlifeRng>,
+    mut timer: Local<WildlifeSpawnTimer>,
+    players: Query<&GlobalTransform, With<Vehicle>>,
+    biomes: Option<Res<BiomeField>>,
+    critters: Query<&Critter>,
+) {
+    if !settings.enabled {
+        return;
+    }
+    timer.0 += time.delta_seconds();
+    let activity = settings.activity.clamp(0.0, 1.0);
+    if timer.0 < settings.spawn_interval / activity.max(0.05) {
+        return;
+    }
+    timer.0 = 0.0;
+
+    for player in players.iter() {
+        for species in WildlifeSpecies::ALL {
+            let population = critters.iter().filter(|critter| critter.species == species).count();
+            let max_population = (species.max_population() as f32 * activity).ceil() as usize;
+            if population >= max_population {
+                continue;
+            }
+
+            let mut home = rng.ring_point(player.translation(), settings.spawn_min_distance, settings.spawn_max_distance);
+            let biome = biomes.as_ref().map(|biomes| biomes.sample(home.x, home.z).biome);
+            if !suits_species(species, biome) {
+                continue;
+            }
+            home.y = ground_height(terrain.as_deref(), home);
+
+            let group = rng.range_u32(species.group_size()) as usize;
+            for _ in 0..group.min(max_population - population) {
+                let mut position = rng.ring_point(home, 0.0, 4.0);
+                position.y = ground_height(terrain.as_deref(), position);
+                let (mesh, material, lift) = match species {
+                    WildlifeSpecies::Deer => (assets.deer_mesh.clone(), assets.deer_material.clone(), 0.55),
+                    WildlifeSpecies::Bird => (assets.bird_mesh.clone(), assets.bird_material.clone(), 0.12),
+                };
+                commands.spawn((
+                    PbrBundle {
+                        mesh,
+                        material,
+                        transform: Transform::from_translation(position + Vec3::Y * lift)
+                            .with_rotation(Quat::from_rotation_y(rng.range(0.0, TAU))),
+                        ..default()
+                    },
+                    Critter {
+                        species,
+                        state: CritterState::Idle { remaining: rng.range(1.0, 5.0) },
+                        home,
+                    },
+                    HeatSource { temperature: species.body_temperature() },
+                    Name::new(format!("{species:?}")),
+                    StateScoped(GameState::InGame),
+                ));
+            }
+        }
+    }
+}
+
+/// Idles, wanders and flees
+fn update_critters(
+    time: Res<Time>,
+    frame: Res<FrameCount>,
+    settings: Res<WildlifeSettings>,
+    terrain: Option<Res<TerrainQuery>>,
+    mut rng: ResMut<WildlifeRng>,
+    emitters: Query<(&NoiseEmitter, &GlobalTransform)>,
+    mut critters: Query<(Entity, &mut Critter, &mut Transform, Option<&Relevance>)>,
+) {
+    for (entity, mut critter, mut transform, relevance) in critters.iter_mut() {
+        // Distant animals think less often and catch up with a longer step, culled ones freeze
+        let stride = match relevance {
+            Some(relevance) => match relevance.stride_on(frame.0, entity) {
+                Some(stride) => stride,
+                None => continue,
+            },
+            None => 1,
+        };
+        let dt = time.delta_seconds() * stride as f32;
+        let species = critter.species;
+        let position = transform.translation;
+
+        if !matches!(critter.state, CritterState::Flee { .. }) {
+            if let Some(heard) = noise_at(emitters.iter(), position) {
+                if heard.level_db >= species.flee_threshold_db() {
+                    critter.state = CritterState::Flee {
+                        direction: flee_direction(position, heard.source),
+                        remaining: settings.flee_duration,
+                    };
+                }
+            }
+        }
+
+        let mut velocity = Vec3::ZERO;
+        let state = critter.state;
+        critter.state = match state {
+            CritterState::Idle { remaining } if remaining > dt => CritterState::Idle { remaining: remaining - dt },
+            CritterState::Idle { .. } => {
+                let mut target = rng.ring_point(critter.home, 0.0, settings.wander_radius);
+                target.y = critter.home.y;
+                CritterState::Wander { target }
+            }
+            CritterState::Wander { target } => {
+                let to_target = (target - position) * Vec3::new(1.0, 0.0, 1.0);
+                if to_target.length() < 0.5 {
+                    CritterState::Idle { remaining: rng.range(2.0, 8.0) }
+                } else {
+                    velocity = to_target.normalize() * species.walk_speed();
+                    state
+                }
+            }
+            CritterState::Flee { direction, remaining } => {
+                velocity = direction * species.flee_speed();
+                if species == WildlifeSpecies::Bird {
+                    velocity.y = species.flee_speed() * 0.5;
+                }
+                if remaining > dt {
+                    CritterState::Flee { direction, remaining: remaining - dt }
+                } else {
+                    // Settle wherever the run ended
+                    critter.home = position;
+                    CritterState::Idle { remaining: rng.range(3.0, 8.0) }
+                }
+            }
+        };
+
+        if velocity.length_squared() > 0.0 {
+            transform.translation += velocity * dt;
+            let heading = velocity * Vec3::new(1.0, 0.0, 1.0);
+            if heading.length_squared() > 1e-6 {
+                transform.look_to(-heading, Vec3::Y);
+            }
+        }
+        // Deer stay on the ground, airborne birds stay up until they land again
+        if species == WildlifeSpecies::Deer {
+            transform.translation.y = ground_height(terrain.as_deref(), transform.translation) + 0.55;
+        }
+    }
+}
+
+/// Removes animals that every player has left far behind
+fn despawn_far_wildlife(
+    mut commands: Commands,
+    settings: Res<WildlifeSettings>,
+    players: Query<&GlobalTransform, With<Vehicle>>,
+    critters: Query<(Entity, &Transform), With<Critter>>,
+) {
+    for (entity, transform) in critters.iter() {
+        let near_a_player = players
+            .iter()
+            .any(|player| player.translation().distance(transform.translation) <= settings.despawn_distance);
+        if !near_a_player || !settings.enabled {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Plugin for ambient wildlife
+pub struct WildlifePlugin;
+
+impl Plugin for WildlifePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WildlifeSettings>()
+            .init_resource::<WildlifeRng>()
+            .add_systems(Startup, setup_wildlife_assets)
+            .add_systems(Update, (
+                update_vehicle_noise,
+                spawn_wildlife,
+                track_relevance::<Critter>,
+                update_critters,
+                despawn_far_wildlife,
+            ).chain())
+            .add_event::<OriginShiftEvent>()
+            .add_systems(PostUpdate, rebase_critters.after(ShiftOrigin));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring_points_stay_in_ring() {
+        let mut rng = WildlifeRng::default();
+        let center = Vec3::new(10.0, 2.0, -5.0);
+        for _ in 0..200 {
+            let point = rng.ring_point(center, 60.0, 120.0);
+            let distance = point.xz().distance(center.xz());
+            assert!((60.0..=120.0).contains(&distance));
+            assert_eq!(point.y, center.y);
+        }
+    }
+
+    #[test]
+    fn test_group_sizes_in_range() {
+        let mut rng = WildlifeRng::default();
+        for _ in 0..100 {
+            assert!(WildlifeSpecies::Bird.group_size().contains(&rng.range_u32(WildlifeSpecies::Bird.group_size())));
+        }
+    }
+
+    #[test]
+    fn test_biomes() {
+        assert!(suits_species(WildlifeSpecies::Deer, Some(Biome::Forest)));
+        assert!(suits_species(WildlifeSpecies::Deer, Some(Biome::Alpine)));
+        assert!(!suits_species(WildlifeSpecies::Deer, Some(Biome::Desert)));
+        assert!(suits_species(WildlifeSpecies::Bird, Some(Biome::Desert)));
+        assert!(suits_species(WildlifeSpecies::Deer, None));
+    }
+
+    #[test]
+    fn test_loud_engine_scares_deer() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<WildlifeSettings>()
+            .init_resource::<WildlifeRng>()
+            .add_systems(Update, update_critters);
+
+        app.world.spawn((NoiseEmitter { level_db: 100.0 }, GlobalTransform::IDENTITY));
+        let near = app
+            .world
+            .spawn((
+                Critter { species: WildlifeSpecies::Deer, state: CritterState::Idle { remaining: 5.0 }, home: Vec3::ZERO },
+                Transform::from_xyz(30.0, 0.0, 0.0),
+            ))
+            .id();
+        let far = app
+            .world
+            .spawn((
+                Critter { species: WildlifeSpecies::Deer, state: CritterState::Idle { remaining: 5.0 }, home: Vec3::ZERO },
+                Transform::from_xyz(0.0, 0.0, 500.0),
+            ))
+            .id();
+
+        app.update();
+        match app.world.get::<Critter>(near).unwrap().state {
+            CritterState::Flee { direction, .. } => assert!(direction.x > 0.99),
+            state => panic!("deer should flee, was {state:?}"),
+        }
+        assert!(matches!(app.world.get::<Critter>(far).unwrap().state, CritterState::Idle { .. }));
+    }
+}
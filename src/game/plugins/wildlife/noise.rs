@@ -0,0 +1,89 @@
+use bevy::prelude::*;
+
+use crate::game::vehicle::{Engine, Vehicle};
+
+/// Something making noise that wildlife can hear
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct NoiseEmitter {
+    /// Sound level in dB measured one meter from the source
+    pub level_db: f32,
+}
+
+/// Loudest noise heard at a point and where it comes from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeardNoise {
+    pub level_db: f32,
+    pub source: Vec3,
+}
+
+/// Level of a source heard `distance` meters away, inverse square falloff of 6 dB per doubling
+pub fn attenuate(level_db: f32, distance: f32) -> f32 {
+    level_db - 20.0 * distance.max(1.0).log10()
+}
+
+/// Loudest noise heard at `point` from the given emitters
+pub fn noise_at<'a>(
+    emitters: impl IntoIterator<Item = (&'a NoiseEmitter, &'a GlobalTransform)>,
+    point: Vec3,
+) -> Option<HeardNoise> {
+    emitters
+        .into_iter()
+        .map(|(emitter, transform)| HeardNoise {
+            level_db: attenuate(emitter.level_db, transform.translation().distance(point)),
+            source: transform.translation(),
+        })
+        .max_by(|a, b| a.level_db.total_cmp(&b.level_db))
+}
+
+/// Engine noise at one meter from RPM and throttle (0.0 - 1.0)
+pub fn engine_noise_db(rpm: f32, throttle: f32) -> f32 {
+    // Idle sits around 70 dB, a revving V8 under load is near 105 dB
+    if rpm <= 0.0 {
+        return 0.0;
+    }
+    65.0 + 30.0 * (rpm / 6000.0).clamp(0.0, 1.0) + 10.0 * throttle.clamp(0.0, 1.0)
+}
+
+/// Keeps each vehicle's noise emitter in step with its engine
+pub fn update_vehicle_noise(
+    mut commands: Commands,
+    mut vehicles: Query<(Entity, &Engine, Option<&mut NoiseEmitter>), With<Vehicle>>,
+) {
+    for (entity, engine, emitter) in vehicles.iter_mut() {
+        let level_db = engine_noise_db(engine.rpm, engine.throttle);
+        match emitter {
+            Some(mut emitter) => emitter.level_db = level_db,
+            None => {
+                commands.entity(entity).insert(NoiseEmitter { level_db });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attenuation() {
+        assert_eq!(attenuate(90.0, 1.0), 90.0);
+        assert!((attenuate(90.0, 10.0) - 70.0).abs() < 1e-4);
+        assert!((attenuate(90.0, 100.0) - 50.0).abs() < 1e-4);
+        // No gain inside a meter
+        assert_eq!(attenuate(90.0, 0.1), 90.0);
+    }
+
+    #[test]
+    fn test_loudest_source_wins() {
+        let quiet = (NoiseEmitter { level_db: 70.0 }, GlobalTransform::from_translation(Vec3::new(5.0, 0.0, 0.0)));
+        let loud = (NoiseEmitter { level_db: 100.0 }, GlobalTransform::from_translation(Vec3::new(-50.0, 0.0, 0.0)));
+        let heard = noise_at([(&quiet.0, &quiet.1), (&loud.0, &loud.1)], Vec3::ZERO).unwrap();
+        assert_eq!(heard.source, Vec3::new(-50.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_engine_noise_rises_with_revs() {
+        assert_eq!(engine_noise_db(0.0, 1.0), 0.0);
+        assert!(engine_noise_db(800.0, 0.0) < engine_noise_db(5000.0, 1.0));
+    }
+}
@@ -0,0 +1,269 @@
+//! World clock schedule: what's open, how busy the trails are and what's out at each hour
+//!
+//! [`WorldClock`] follows the hour of the [`TimeManager`]. Entities with [`OpenHours`] open and
+//! close with it, points of interest like camps and gas stations as well as night-only delivery
+//! challenges, ambient traffic thins out overnight and wildlife comes out at dawn and dusk.
+//! The hour is kept in the [`GameProgress`] save, so the next session starts where this one ended.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::weather::TimeManager;
+use super::wildlife::WildlifeSettings;
+use crate::game::states::GameProgress;
+
+/// Hours of the day something is open, running overnight when `close` comes before `open`
+#[derive(Component, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OpenHours {
+    pub open: f32,
+    pub close: f32,
+}
+
+impl OpenHours {
+    /// Dusk to dawn, the hours the [`TimeManager`] calls night
+    pub const NIGHT: OpenHours = OpenHours { open: 18.0, close: 6.0 };
+    pub const DAY: OpenHours = OpenHours { open: 6.0, close: 18.0 };
+
+    pub fn contains(&self, hour: f32) -> bool {
+        let hour = hour.rem_euclid(24.0);
+        if self.open <= self.close {
+            hour >= self.open && hour < self.close
+        } else {
+            hour >= self.open || hour < self.close
+        }
+    }
+}
+
+/// Whether an entity with [`OpenHours`] is open right now
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduleOpen(pub bool);
+
+/// Entities without opening hours are always open
+pub fn is_open(open: Option<&ScheduleOpen>) -> bool {
+    open.map_or(true, |open| open.0)
+}
+
+/// Sent when an entity with [`OpenHours`] opens or closes
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct ScheduleChangedEvent {
+    pub entity: Entity,
+    pub open: bool,
+}
+
+/// Sent at dusk and at dawn
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct DayPhaseChangedEvent {
+    pub night: bool,
+}
+
+/// A value through the day, `(hour, value)` keys joined by straight lines that wrap round midnight
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyCurve(pub Vec<(f32, f32)>);
+
+impl DailyCurve {
+    /// Value at `hour`, 1.0 for a curve without keys. Keys have to be in hour order.
+    pub fn sample(&self, hour: f32) -> f32 {
+        let keys = &self.0;
+        let (Some(first), Some(last)) = (keys.first(), keys.last()) else {
+            return 1.0;
+        };
+        let hour = hour.rem_euclid(24.0);
+        let next = keys.iter().position(|(key_hour, _)| *key_hour > hour);
+        let (from, to) = match next {
+            Some(0) => ((last.0 - 24.0, last.1), *first),
+            Some(index) => (keys[index - 1], keys[index]),
+            None => (*last, (first.0 + 24.0, first.1)),
+        };
+        let span = (to.0 - from.0).max(1.0e-3);
+        from.1 + (to.1 - from.1) * ((hour - from.0) / span).clamp(0.0, 1.0)
+    }
+}
+
+/// How busy the world is through the day
+#[derive(Resource, Debug, Clone)]
+pub struct WorldScheduleSettings {
+    /// Share of the ambient traffic out on the trails (0.0 - 1.0)
+    pub traffic: DailyCurve,
+    /// How active the wildlife is (0.0 - 1.0)
+    pub wildlife: DailyCurve,
+}
+
+impl Default for WorldScheduleSettings {
+    fn default() -> Self {
+        Self {
+            // Busy mornings and evenings, nearly empty after midnight
+            traffic: DailyCurve(vec![
+                (0.0, 0.1),
+                (5.0, 0.1),
+                (8.0, 1.0),
+                (12.0, 0.7),
+                (17.0, 1.0),
+                (21.0, 0.4),
+            ]),
+            // Deer and birds are out most at dawn and dusk and lie low through the midday heat
+            wildlife: DailyCurve(vec![
+                (0.0, 0.4),
+                (5.5, 1.0),
+                (8.0, 0.6),
+                (13.0, 0.2),
+                (17.0, 0.6),
+                (19.5, 1.0),
+                (23.0, 0.5),
+            ]),
+        }
+    }
+}
+
+/// The hour and what the schedule makes of it, updated every frame from the [`TimeManager`]
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct WorldClock {
+    /// Hour of the day (0.0 - 24.0)
+    pub hour: f32,
+    pub night: bool,
+    pub traffic_density: f32,
+    pub wildlife_activity: f32,
+}
+
+impl Default for WorldClock {
+    fn default() -> Self {
+        Self {
+            hour: 12.0,
+            night: false,
+            traffic_density: 1.0,
+            wildlife_activity: 1.0,
+        }
+    }
+}
+
+impl WorldClock {
+    pub fn at(hour: f32, settings: &WorldScheduleSettings) -> Self {
+        Self {
+            hour,
+            night: OpenHours::NIGHT.contains(hour),
+            traffic_density: settings.traffic.sample(hour).clamp(0.0, 1.0),
+            wildlife_activity: settings.wildlife.sample(hour).clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// Puts the clock back to the hour the save was left at
+fn restore_world_time(progress: Option<Res<GameProgress>>, mut time: ResMut<TimeManager>) {
+    if let Some(hour) = progress.filter(|progress| progress.is_added()).and_then(|progress| progress.world_time) {
+        time.set_time(hour);
+    }
+}
+
+fn update_world_clock(
+    time: Res<TimeManager>,
+    settings: Res<WorldScheduleSettings>,
+    mut clock: ResMut<WorldClock>,
+    mut phase_events: EventWriter<DayPhaseChangedEvent>,
+) {
+    let now = WorldClock::at(time.current_time(), &settings);
+    if now.night != clock.night {
+        phase_events.send(DayPhaseChangedEvent { night: now.night });
+    }
+    if now != *clock {
+        *clock = now;
+    }
+}
+
+/// Opens and closes entities with opening hours
+fn update_open_hours(
+    mut commands: Commands,
+    clock: Res<WorldClock>,
+    mut scheduled: Query<(Entity, &OpenHours, Option<&mut ScheduleOpen>)>,
+    mut changed_events: EventWriter<ScheduleChangedEvent>,
+) {
+    for (entity, hours, state) in scheduled.iter_mut() {
+        let open = hours.contains(clock.hour);
+        match state {
+            Some(mut state) if state.0 != open => {
+                state.0 = open;
+                changed_events.send(ScheduleChangedEvent { entity, open });
+            }
+            Some(_) => {}
+            None => {
+                commands.entity(entity).insert(ScheduleOpen(open));
+            }
+        }
+    }
+}
+
+/// Hands the time of day's wildlife activity to the spawner
+fn apply_wildlife_activity(clock: Res<WorldClock>, wildlife: Option<ResMut<WildlifeSettings>>) {
+    if let Some(mut wildlife) = wildlife {
+        // Only on a visible change, so the settings don't read as changed every frame
+        if (wildlife.activity - clock.wildlife_activity).abs() > 0.01 {
+            wildlife.activity = clock.wildlife_activity;
+        }
+    }
+}
+
+/// Keeps the hour in the save, to the minute
+fn store_world_time(progress: Option<ResMut<GameProgress>>, clock: Res<WorldClock>) {
+    if let Some(mut progress) = progress {
+        if progress.world_time.map_or(true, |hour| (hour - clock.hour).abs() >= 1.0 / 60.0) {
+            progress.world_time = Some(clock.hour);
+        }
+    }
+}
+
+/// Plugin for the time of day schedule
+pub struct WorldClockPlugin;
+
+impl Plugin for WorldClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldScheduleSettings>()
+            .init_resource::<WorldClock>()
+            .init_resource::<TimeManager>()
+            .add_event::<ScheduleChangedEvent>()
+            .add_event::<DayPhaseChangedEvent>()
+            .add_systems(Update, (
+                restore_world_time,
+                update_world_clock,
+                update_open_hours,
+                apply_wildlife_activity,
+                store_world_time,
+            ).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_hours_run_overnight() {
+        let gas_station = OpenHours { open: 6.0, close: 22.0 };
+        assert!(gas_station.contains(12.0));
+        assert!(!gas_station.contains(23.0));
+        assert!(!gas_station.contains(22.0));
+
+        assert!(OpenHours::NIGHT.contains(23.5));
+        assert!(OpenHours::NIGHT.contains(2.0));
+        assert!(!OpenHours::NIGHT.contains(12.0));
+        assert!(OpenHours::NIGHT.contains(18.0));
+    }
+
+    #[test]
+    fn test_daily_curve_wraps_round_midnight() {
+        let curve = DailyCurve(vec![(6.0, 1.0), (18.0, 0.0)]);
+        assert_eq!(curve.sample(6.0), 1.0);
+        assert_eq!(curve.sample(12.0), 0.5);
+        // From 18:00 back round to 06:00 is twelve hours, so midnight is halfway
+        assert_eq!(curve.sample(0.0), 0.5);
+        assert_eq!(curve.sample(21.0), 0.25);
+        assert_eq!(DailyCurve(Vec::new()).sample(3.0), 1.0);
+    }
+
+    #[test]
+    fn test_quiet_at_night() {
+        let settings = WorldScheduleSettings::default();
+        let night = WorldClock::at(2.0, &settings);
+        let day = WorldClock::at(9.0, &settings);
+        assert!(night.night && !day.night);
+        assert!(night.traffic_density < day.traffic_density);
+        assert!(WorldClock::at(5.5, &settings).wildlife_activity > WorldClock::at(13.0, &settings).wildlife_activity);
+    }
+}
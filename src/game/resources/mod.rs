@@ -31,6 +31,8 @@ pub enum GameMode {
     Race,
     Challenge,
     Tutorial,
+    /// Delivery jobs taken from the job boards
+    Jobs,
 }
 
 /// Game settings that can be configured by the player
@@ -44,6 +46,22 @@ pub struct GameSettings {
     pub controls: ControlSettings,
     /// Physics settings
     pub physics: PhysicsSettings,
+    /// Gameplay rules
+    #[serde(default)]
+    pub gameplay: GameplaySettings,
+    /// Driver assists
+    #[serde(default)]
+    pub assists: DriverAssistSettings,
+    /// Accessibility options
+    #[serde(default)]
+    pub accessibility: AccessibilitySettings,
+    /// Language code of the UI text, such as `en`
+    #[serde(default = "default_language")]
+    pub language: String,
+}
+
+fn default_language() -> String {
+    "en".to_string()
 }
 
 impl Default for GameSettings {
@@ -53,6 +71,10 @@ impl Default for GameSettings {
             audio: AudioSettings::default(),
             controls: ControlSettings::default(),
             physics: PhysicsSettings::default(),
+            gameplay: GameplaySettings::default(),
+            assists: DriverAssistSettings::default(),
+            accessibility: AccessibilitySettings::default(),
+            language: default_language(),
         }
     }
 }
@@ -65,6 +87,35 @@ pub struct GraphicsSettings {
     pub vsync: bool,
     pub shadow_quality: ShadowQuality,
     pub texture_quality: TextureQuality,
+    /// Screen space ambient occlusion toggle
+    pub ambient_occlusion: bool,
+    /// Frame rate the automatic quality scaling tries to hold
+    #[serde(default = "default_target_fps")]
+    pub target_fps: u32,
+    /// Lower particle, shadow, terrain and post-process quality when below the target frame rate
+    #[serde(default = "default_dynamic_quality")]
+    pub dynamic_quality: bool,
+    /// Rear-view mirror in the HUD, off by default as it renders the scene a second time
+    #[serde(default)]
+    pub rear_view_mirror: bool,
+    /// Resolution and refresh rate of the mirror
+    #[serde(default)]
+    pub mirror_quality: MirrorQuality,
+    /// Animated driver in the vehicles' seats
+    #[serde(default = "default_show_driver")]
+    pub show_driver: bool,
+}
+
+fn default_target_fps() -> u32 {
+    60
+}
+
+fn default_dynamic_quality() -> bool {
+    true
+}
+
+fn default_show_driver() -> bool {
+    true
 }
 
 impl Default for GraphicsSettings {
@@ -75,18 +126,37 @@ impl Default for GraphicsSettings {
             vsync: true,
             shadow_quality: ShadowQuality::High,
             texture_quality: TextureQuality::High,
+            ambient_occlusion: true,
+            target_fps: default_target_fps(),
+            dynamic_quality: default_dynamic_quality(),
+            rear_view_mirror: false,
+            mirror_quality: MirrorQuality::default(),
+            show_driver: default_show_driver(),
         }
     }
 }
 
 /// Shadow quality levels
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum ShadowQuality {
     Low,
     Medium,
     High,
 }
 
+/// Rear-view mirror quality levels, higher ones render a bigger mirror more often
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum MirrorQuality {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl MirrorQuality {
+    pub const ALL: [MirrorQuality; 3] = [MirrorQuality::Low, MirrorQuality::Medium, MirrorQuality::High];
+}
+
 /// Texture quality levels
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum TextureQuality {
@@ -119,6 +189,8 @@ pub struct ControlSettings {
     pub mouse_sensitivity: f32,
     pub invert_y: bool,
     pub controller_deadzone: f32,
+    #[serde(default)]
+    pub transmission: TransmissionMode,
 }
 
 impl Default for ControlSettings {
@@ -127,14 +199,30 @@ impl Default for ControlSettings {
             mouse_sensitivity: 1.0,
             invert_y: false,
             controller_deadzone: 0.1,
+            transmission: TransmissionMode::default(),
         }
     }
 }
 
+/// How gears are changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TransmissionMode {
+    /// The engine can't stall, a torque converter takes up the slack
+    #[default]
+    Automatic,
+    /// Gears picked by the driver, lugging the engine in too high a gear stalls it
+    Manual,
+}
+
+impl TransmissionMode {
+    pub const ALL: [TransmissionMode; 2] = [TransmissionMode::Automatic, TransmissionMode::Manual];
+}
+
 /// Physics-related settings
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PhysicsSettings {
     pub gravity: f32,
+    /// Game speed against real time, 1 is normal and below it is slow motion
     pub time_scale: f32,
     pub simulation_rate: u32,
 }
@@ -149,6 +237,198 @@ impl Default for PhysicsSettings {
     }
 }
 
+/// Gameplay rule settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameplaySettings {
+    /// Casual mode turns off survival systems such as fuel
+    pub casual_mode: bool,
+    /// Whether vehicles burn fuel
+    pub fuel_consumption: bool,
+}
+
+impl Default for GameplaySettings {
+    fn default() -> Self {
+        Self {
+            casual_mode: false,
+            fuel_consumption: true,
+        }
+    }
+}
+
+impl GameplaySettings {
+    pub fn fuel_enabled(&self) -> bool {
+        self.fuel_consumption && !self.casual_mode
+    }
+}
+
+/// Driver assist toggles
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DriverAssistSettings {
+    /// Cut throttle when the driven wheels spin
+    pub traction_control: bool,
+    /// Release the brakes when wheels lock up
+    pub abs: bool,
+    /// Brake automatically to hold a crawl speed down steep slopes
+    pub hill_descent: bool,
+    /// Speed hill descent control holds in km/h
+    pub hill_descent_speed: f32,
+    /// Grip markers under the wheels and hints to lock the diffs or shift into low range when stuck
+    #[serde(default)]
+    pub traction_overlay: bool,
+}
+
+impl Default for DriverAssistSettings {
+    fn default() -> Self {
+        Self {
+            traction_control: true,
+            abs: true,
+            hill_descent: false,
+            hill_descent_speed: 6.0,
+            traction_overlay: false,
+        }
+    }
+}
+
+/// Accessibility options
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccessibilitySettings {
+    /// Multiplier on UI text and widget size
+    pub text_scale: f32,
+    pub hud_palette: HudPalette,
+    /// Multiplier on camera shake (0.0 - 1.0), 0.0 turns it off
+    pub camera_shake: f32,
+    /// Upper limit on motion blur strength (0.0 - 1.0)
+    pub motion_blur_limit: f32,
+    pub control_preset: ControlPreset,
+    /// Show subtitles for radio voice lines
+    pub subtitles: bool,
+    pub handbrake_mode: HoldMode,
+    pub winch_mode: HoldMode,
+    /// Multiplier on controller rumble (0.0 - 1.0), 0.0 turns it off
+    #[serde(default = "default_rumble_intensity")]
+    pub rumble_intensity: f32,
+}
+
+fn default_rumble_intensity() -> f32 {
+    1.0
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        Self {
+            text_scale: 1.0,
+            hud_palette: HudPalette::Standard,
+            camera_shake: 1.0,
+            motion_blur_limit: 1.0,
+            control_preset: ControlPreset::Standard,
+            subtitles: false,
+            handbrake_mode: HoldMode::Hold,
+            winch_mode: HoldMode::Hold,
+            rumble_intensity: 1.0,
+        }
+    }
+}
+
+/// Color sets for HUD gauges and indicators
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum HudPalette {
+    #[default]
+    Standard,
+    /// Red-green safe, for deuteranopia and protanopia
+    RedGreenSafe,
+    /// Blue-yellow safe, for tritanopia
+    BlueYellowSafe,
+    HighContrast,
+}
+
+/// RGB colors the HUD draws with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HudColors {
+    pub good: [u8; 3],
+    pub info: [u8; 3],
+    pub warning: [u8; 3],
+    pub danger: [u8; 3],
+    pub inactive: [u8; 3],
+}
+
+impl HudPalette {
+    pub const ALL: [HudPalette; 4] = [
+        HudPalette::Standard,
+        HudPalette::RedGreenSafe,
+        HudPalette::BlueYellowSafe,
+        HudPalette::HighContrast,
+    ];
+
+    pub fn colors(self) -> HudColors {
+        match self {
+            HudPalette::Standard => HudColors {
+                good: [60, 160, 60],
+                info: [60, 120, 200],
+                warning: [230, 170, 20],
+                danger: [200, 40, 30],
+                inactive: [90, 90, 90],
+            },
+            // Okabe-Ito colors, told apart by blue-orange contrast and brightness instead of red and green
+            HudPalette::RedGreenSafe => HudColors {
+                good: [0, 114, 178],
+                info: [86, 180, 233],
+                warning: [240, 228, 66],
+                danger: [213, 94, 0],
+                inactive: [90, 90, 90],
+            },
+            HudPalette::BlueYellowSafe => HudColors {
+                good: [0, 158, 115],
+                info: [0, 110, 110],
+                warning: [255, 130, 170],
+                danger: [220, 30, 40],
+                inactive: [90, 90, 90],
+            },
+            HudPalette::HighContrast => HudColors {
+                good: [255, 255, 255],
+                info: [0, 255, 255],
+                warning: [255, 255, 0],
+                danger: [255, 60, 60],
+                inactive: [60, 60, 60],
+            },
+        }
+    }
+}
+
+/// Keyboard layouts for driving
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ControlPreset {
+    /// WASD with the mouse for the camera
+    #[default]
+    Standard,
+    /// Everything on the left side of the keyboard, camera included
+    OneHandedLeft,
+    /// Everything on the arrow keys and the keys above them
+    OneHandedRight,
+}
+
+impl ControlPreset {
+    pub const ALL: [ControlPreset; 3] = [ControlPreset::Standard, ControlPreset::OneHandedLeft, ControlPreset::OneHandedRight];
+}
+
+/// Whether a button works while held or switches on and off with each press
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum HoldMode {
+    #[default]
+    Hold,
+    Toggle,
+}
+
+impl HoldMode {
+    /// New state of a control that was `active`, given whether its button is held now and was last frame
+    pub fn resolve(self, active: bool, held: bool, was_held: bool) -> bool {
+        match self {
+            HoldMode::Hold => held,
+            HoldMode::Toggle if held && !was_held => !active,
+            HoldMode::Toggle => active,
+        }
+    }
+}
+
 /// Resource for managing input state
 #[derive(Resource)]
 pub struct InputState {
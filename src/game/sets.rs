@@ -0,0 +1,66 @@
+/// Shared system sets ordering a frame's `Update` across the plugins
+///
+/// ```text
+/// PreUpdate   device detection (steering wheels, gamepads)
+/// Update      Input ─▶ Simulation ─▶ PostSim ─▶ CameraUi
+/// PostUpdate  rapier step and writeback ─▶ transform propagation
+/// ```
+///
+/// Rapier writes the vehicle transforms back in `PostUpdate`, so everything in `Update` reads the
+/// latest physics state. Putting a plugin's systems in a set is what keeps readers from seeing the
+/// state of the frame before: the camera follows the vehicle after the vehicle systems have moved
+/// it, and the UI shows the state after the frame's gameplay changes.
+use bevy::prelude::*;
+
+/// Stages of a frame's `Update`, run in declaration order
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameSet {
+    /// Device input read into each player's controls
+    Input,
+    /// Vehicles, wheels and suspension driven by the controls
+    Simulation,
+    /// Reactions to the simulation: damage, effects, debug drawing
+    PostSim,
+    /// Cameras follow their targets and the UI draws the result
+    CameraUi,
+}
+
+/// Orders the [`GameSet`]s. Every plugin using them calls this so it also works on its own, in
+/// tests or headless, configuring the sets again is harmless.
+pub fn configure_game_sets(app: &mut App) {
+    app.configure_sets(
+        Update,
+        (GameSet::Input, GameSet::Simulation, GameSet::PostSim, GameSet::CameraUi).chain(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Resource, Default)]
+    struct Ran(Vec<GameSet>);
+
+    fn record(set: GameSet) -> impl Fn(ResMut<Ran>) {
+        move |mut ran: ResMut<Ran>| ran.0.push(set)
+    }
+
+    #[test]
+    fn test_sets_run_in_order() {
+        let mut app = App::new();
+        configure_game_sets(&mut app);
+        // Added back to front, the sets still order them
+        app.init_resource::<Ran>().add_systems(Update, (
+            record(GameSet::CameraUi).in_set(GameSet::CameraUi),
+            record(GameSet::PostSim).in_set(GameSet::PostSim),
+            record(GameSet::Simulation).in_set(GameSet::Simulation),
+            record(GameSet::Input).in_set(GameSet::Input),
+        ));
+        configure_game_sets(&mut app);
+        app.update();
+        assert_eq!(
+            app.world.resource::<Ran>().0,
+            [GameSet::Input, GameSet::Simulation, GameSet::PostSim, GameSet::CameraUi]
+        );
+    }
+}
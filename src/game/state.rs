@@ -1,5 +1,9 @@
 use bevy::prelude::*;
 
+use crate::game::plugins::ProgressionConfig;
+use crate::game::states::GameProgress;
+use crate::game::vehicle::{PlayerOrAi, SpawnVehicleEvent, Vehicle};
+
 /// Core game states
 #[derive(States, Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
 pub enum GameState {
@@ -10,6 +14,38 @@ pub enum GameState {
     Paused,
 }
 
+impl GameState {
+    pub const ALL: [Self; 4] = [Self::Loading, Self::MainMenu, Self::InGame, Self::Paused];
+}
+
+/// Despawns an entity, with everything under it, once the game leaves the state it belongs to.
+/// Entities of [`GameState::InGame`] stay while paused, the pause menu sits on top of the running game.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateScoped(pub GameState);
+
+impl StateScoped {
+    /// Whether the entity still belongs in `state`
+    pub fn belongs_in(&self, state: GameState) -> bool {
+        self.0 == state || (self.0 == GameState::InGame && state == GameState::Paused)
+    }
+}
+
+/// Despawns scoped entities that don't belong in the state being entered. Runs on leaving any state,
+/// by then the state resource already holds the next one.
+fn despawn_state_scoped(mut commands: Commands, state: Res<State<GameState>>, scoped: Query<(Entity, &StateScoped)>) {
+    for (entity, scope) in scoped.iter() {
+        if !scope.belongs_in(*state.get()) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn add_state_scoped_cleanup(app: &mut App) {
+    for state in GameState::ALL {
+        app.add_systems(OnExit(state), despawn_state_scoped);
+    }
+}
+
 /// Plugin for managing game state transitions and updates
 pub struct StatePlugin;
 
@@ -27,6 +63,7 @@ impl Plugin for StatePlugin {
            
            .add_systems(OnEnter(GameState::Paused), setup_pause_menu)
            .add_systems(Update, update_pause_menu.run_if(in_state(GameState::Paused)));
+        add_state_scoped_cleanup(app);
     }
 }
 
@@ -58,9 +95,27 @@ fn update_main_menu(
 }
 
 // Game state systems
-fn setup_game(mut commands: Commands) {
+fn setup_game(
+    mut commands: Commands,
+    mut spawn_vehicles: EventWriter<SpawnVehicleEvent>,
+    vehicles: Query<(), With<Vehicle>>,
+    progression: Option<Res<ProgressionConfig>>,
+    progress: Option<Res<GameProgress>>,
+) {
     info!("Starting game");
-    // Initialize game world and entities
+    // Also entered when unpausing, the player's vehicle is already there then
+    if vehicles.is_empty() {
+        // The vehicle and paint picked in the garage, as far as they're unlocked
+        let definition = match (progression, progress) {
+            (Some(progression), Some(progress)) => progression.vehicle_definition(&progress),
+            _ => default(),
+        };
+        spawn_vehicles.send(SpawnVehicleEvent {
+            definition,
+            transform: Transform::from_xyz(0.0, 5.0, 0.0),
+            driver: PlayerOrAi::Player(0),
+        });
+    }
 }
 
 fn update_game(
@@ -81,4 +136,51 @@ fn update_pause_menu(
     // Add pause menu resources
 ) {
     // Handle pause menu interactions and state transitions
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scoped_app() -> App {
+        let mut app = App::new();
+        app.init_state::<GameState>();
+        add_state_scoped_cleanup(&mut app);
+        app
+    }
+
+    fn enter(app: &mut App, state: GameState) {
+        app.world.resource_mut::<NextState<GameState>>().set(state);
+        app.update();
+    }
+
+    #[test]
+    fn test_game_entities_survive_pause_but_not_the_menu() {
+        let mut app = scoped_app();
+        enter(&mut app, GameState::InGame);
+        let vehicle = app.world.spawn(StateScoped(GameState::InGame)).id();
+        let wheel = app.world.spawn_empty().id();
+        app.world.entity_mut(vehicle).add_child(wheel);
+        let unscoped = app.world.spawn_empty().id();
+
+        enter(&mut app, GameState::Paused);
+        let pause_menu = app.world.spawn(StateScoped(GameState::Paused)).id();
+        assert!(app.world.get_entity(vehicle).is_some());
+
+        enter(&mut app, GameState::MainMenu);
+        assert!(app.world.get_entity(vehicle).is_none());
+        assert!(app.world.get_entity(wheel).is_none());
+        assert!(app.world.get_entity(pause_menu).is_none());
+        assert!(app.world.get_entity(unscoped).is_some());
+    }
+
+    #[test]
+    fn test_scope_membership() {
+        let in_game = StateScoped(GameState::InGame);
+        assert!(in_game.belongs_in(GameState::InGame));
+        assert!(in_game.belongs_in(GameState::Paused));
+        assert!(!in_game.belongs_in(GameState::MainMenu));
+        assert!(!StateScoped(GameState::Paused).belongs_in(GameState::InGame));
+        assert!(!StateScoped(GameState::MainMenu).belongs_in(GameState::Loading));
+    }
+}
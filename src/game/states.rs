@@ -1,4 +1,8 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::plugins::{Accessory, Loadout, Paint, WeatherHistory};
+use super::vehicle::Part;
 
 #[derive(States, Default, Debug, Clone, Eq, PartialEq, Hash)]
 pub enum GameState {
@@ -23,12 +27,42 @@ pub struct GameTimer {
     pub best_time: Option<f32>,
 }
 
-#[derive(Resource)]
+/// Save data of a player's progress
+#[derive(Resource, Serialize, Deserialize)]
+#[serde(default)]
 pub struct GameProgress {
     pub current_level: u32,
     pub unlocked_levels: u32,
     pub best_times: Vec<f32>,
     pub total_score: u32,
+    /// Experience earned from challenges, races and driving
+    pub xp: u32,
+    /// Money earned from challenges, races and deliveries, spent on vehicles, parts, fuel and tows
+    pub cash: u32,
+    /// Meters driven in total
+    pub distance_driven: f32,
+    /// Vehicles unlocked by missions or levels
+    pub unlocked_vehicles: Vec<String>,
+    /// Unlocked vehicles that have been paid for, the free ones don't need to be
+    pub owned_vehicles: Vec<String>,
+    pub unlocked_paints: Vec<Paint>,
+    pub unlocked_accessories: Vec<Accessory>,
+    /// Tires, lift kits and bumpers unlocked
+    pub unlocked_parts: Vec<Part>,
+    /// Unlocked parts that have been paid for
+    pub owned_parts: Vec<Part>,
+    /// What the player picked in the garage
+    pub loadout: Loadout,
+    /// The tutorial was finished or skipped
+    pub tutorial_completed: bool,
+    /// Recent rain, so trails stay wet between sessions
+    pub weather_history: WeatherHistory,
+    /// Hour of the day the world clock was at, `None` before the first session
+    pub world_time: Option<f32>,
+    /// Ids of level props broken in free roam, they stay down between sessions
+    pub destroyed_props: Vec<String>,
+    /// Names of the points of interest reached, the tow service can take the player back to them
+    pub discovered_points: Vec<String>,
 }
 
 impl Default for GameProgress {
@@ -38,6 +72,21 @@ impl Default for GameProgress {
             unlocked_levels: 1,
             best_times: vec![0.0; 10], // Assuming 10 levels
             total_score: 0,
+            xp: 0,
+            cash: 0,
+            distance_driven: 0.0,
+            unlocked_vehicles: Vec::new(),
+            owned_vehicles: Vec::new(),
+            unlocked_paints: Vec::new(),
+            unlocked_accessories: Vec::new(),
+            unlocked_parts: Vec::new(),
+            owned_parts: Vec::new(),
+            loadout: Loadout::default(),
+            tutorial_completed: false,
+            weather_history: WeatherHistory::default(),
+            world_time: None,
+            destroyed_props: Vec::new(),
+            discovered_points: Vec::new(),
         }
     }
 }
@@ -468,7 +468,7 @@ fn setup_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
 }
 
 fn update_menu_transitions(
-    time: Res<Time>,
+    time: Res<Time<Real>>,
     mut commands: Commands,
     mut query: Query<(Entity, &mut MenuTransition, &mut Style, &mut BackgroundColor)>,
 ) {
@@ -1,6 +1,7 @@
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts};
 use crate::game::states::{GameState, GameTimer, GameProgress};
+use crate::tr;
 
 pub struct GameUIPlugin;
 
@@ -21,12 +22,12 @@ pub fn show_main_menu(
 ) {
     egui::CentralPanel::default().show(contexts.ctx_mut(), |ui| {
         ui.vertical_centered(|ui| {
-            ui.heading("Offroad Racing Game");
+            ui.heading(tr!("main_menu.title"));
             ui.add_space(20.0);
-            if ui.button("Start Game").clicked() {
+            if ui.button(tr!("main_menu.start")).clicked() {
                 commands.insert_resource(NextState(Some(GameState::Playing)));
             }
-            if ui.button("Quit").clicked() {
+            if ui.button(tr!("menu.quit")).clicked() {
                 std::process::exit(0);
             }
         });
@@ -38,10 +39,10 @@ pub fn show_game_ui(
     game_timer: Res<GameTimer>,
     game_progress: Res<GameProgress>,
 ) {
-    egui::Window::new("Game Stats").show(contexts.ctx_mut(), |ui| {
-        ui.label(format!("Time: {:.2}", game_timer.elapsed));
-        ui.label(format!("Level: {}", game_progress.current_level));
-        ui.label(format!("Score: {}", game_progress.total_score));
+    egui::Window::new(tr!("stats.title")).id(egui::Id::new("game_stats")).show(contexts.ctx_mut(), |ui| {
+        ui.label(tr!("stats.time", time = format!("{:.2}", game_timer.elapsed)));
+        ui.label(tr!("stats.level", level = game_progress.current_level));
+        ui.label(tr!("stats.score", score = game_progress.total_score));
     });
 }
 
@@ -51,12 +52,12 @@ pub fn show_pause_menu(
 ) {
     egui::CentralPanel::default().show(contexts.ctx_mut(), |ui| {
         ui.vertical_centered(|ui| {
-            ui.heading("Game Paused");
+            ui.heading(tr!("pause.title"));
             ui.add_space(20.0);
-            if ui.button("Resume").clicked() {
+            if ui.button(tr!("menu.resume")).clicked() {
                 commands.insert_resource(NextState(Some(GameState::Playing)));
             }
-            if ui.button("Main Menu").clicked() {
+            if ui.button(tr!("menu.main_menu")).clicked() {
                 commands.insert_resource(NextState(Some(GameState::MainMenu)));
             }
         });
@@ -70,13 +71,13 @@ pub fn show_game_over(
 ) {
     egui::CentralPanel::default().show(contexts.ctx_mut(), |ui| {
         ui.vertical_centered(|ui| {
-            ui.heading("Game Over");
+            ui.heading(tr!("game_over.title"));
             ui.add_space(20.0);
-            ui.label(format!("Time: {:.2}", game_timer.elapsed));
-            if ui.button("Restart").clicked() {
+            ui.label(tr!("stats.time", time = format!("{:.2}", game_timer.elapsed)));
+            if ui.button(tr!("menu.restart")).clicked() {
                 commands.insert_resource(NextState(Some(GameState::Playing)));
             }
-            if ui.button("Main Menu").clicked() {
+            if ui.button(tr!("menu.main_menu")).clicked() {
                 commands.insert_resource(NextState(Some(GameState::MainMenu)));
             }
         });
@@ -0,0 +1,224 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use super::{Brakes, DriveType, Engine, Vehicle, Wheel};
+use crate::game::GameSettings;
+
+/// Tuning for traction control, ABS and hill descent control
+#[derive(Resource, Clone, Debug)]
+pub struct DriverAssistConfig {
+    /// Toggles, follow the assist settings
+    pub traction_control: bool,
+    pub abs: bool,
+    pub hill_descent: bool,
+    /// Speed hill descent control holds in m/s
+    pub hill_descent_speed: f32,
+    /// Wheel spin traction control allows before cutting throttle
+    pub target_slip: f32,
+    /// Spin beyond the target at which throttle is cut completely
+    pub slip_window: f32,
+    /// Slip at which a braked wheel counts as locking up
+    pub lockup_slip: f32,
+    /// Fraction of the brake kept while ABS releases a locking wheel
+    pub abs_release: f32,
+    /// Brake per m/s over the hill descent speed
+    pub hill_descent_gain: f32,
+    /// Steepness of the descent in radians before hill descent engages
+    pub hill_descent_min_slope: f32,
+}
+
+impl Default for DriverAssistConfig {
+    fn default() -> Self {
+        Self {
+            traction_control: true,
+            abs: true,
+            hill_descent: false,
+            hill_descent_speed: 6.0 / 3.6,
+            target_slip: 0.15,
+            slip_window: 0.35,
+            lockup_slip: 0.2,
+            abs_release: 0.3,
+            hill_descent_gain: 0.4,
+            hill_descent_min_slope: 0.1,
+        }
+    }
+}
+
+impl DriverAssistConfig {
+    /// Throttle multiplier for the worst wheel spin among the driven wheels
+    pub fn traction_control_factor(&self, slip: f32) -> f32 {
+        1.0 - ((slip - self.target_slip) / self.slip_window.max(f32::EPSILON)).clamp(0.0, 1.0)
+    }
+
+    /// Brake to apply instead of `brake` while the worst wheel slips by `slip`, `None` if nothing locks
+    pub fn abs_brake(&self, brake: f32, slip: f32) -> Option<f32> {
+        (brake > 0.0 && slip < -self.lockup_slip).then_some(brake * self.abs_release)
+    }
+
+    /// Brake holding the descent speed, `None` when not going down a slope steep enough
+    pub fn hill_descent_brake(&self, speed: f32, descent_slope: f32) -> Option<f32> {
+        if descent_slope < self.hill_descent_min_slope {
+            return None;
+        }
+        Some(((speed - self.hill_descent_speed) * self.hill_descent_gain).clamp(0.0, 1.0))
+    }
+}
+
+/// Which assists are intervening on a vehicle this frame, shown on the HUD
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct DriverAssists {
+    pub traction_control_active: bool,
+    pub abs_active: bool,
+    pub hill_descent_active: bool,
+}
+
+/// Whether the wheel at `position` is driven for `drive_type`
+pub fn is_driven(drive_type: DriveType, position: usize) -> bool {
+    match drive_type {
+        DriveType::FrontWD => position <= 1,
+        DriveType::RearWD => position >= 2,
+        DriveType::FourWD => true,
+    }
+}
+
+/// Slip ratio relative to the direction of travel, positive when spinning and negative when locking
+pub fn directional_slip(slip_ratio: f32, speed: f32) -> f32 {
+    if speed < 0.0 {
+        -slip_ratio
+    } else {
+        slip_ratio
+    }
+}
+
+/// Keeps [`DriverAssistConfig`] toggles in sync with the assist settings
+fn sync_assist_settings(game_settings: Res<GameSettings>, mut config: ResMut<DriverAssistConfig>) {
+    if !game_settings.is_changed() {
+        return;
+    }
+    let assists = &game_settings.assists;
+    let speed = assists.hill_descent_speed / 3.6;
+    if config.traction_control != assists.traction_control
+        || config.abs != assists.abs
+        || config.hill_descent != assists.hill_descent
+        || config.hill_descent_speed != speed
+    {
+        config.traction_control = assists.traction_control;
+        config.abs = assists.abs;
+        config.hill_descent = assists.hill_descent;
+        config.hill_descent_speed = speed;
+    }
+}
+
+/// Adjusts throttle and brake from wheel slip and slope, on top of the driver's input
+pub fn apply_driver_assists(
+    config: Res<DriverAssistConfig>,
+    wheels: Query<&Wheel>,
+    mut vehicles: Query<(&Vehicle, &mut Engine, &mut Brakes, &mut DriverAssists, &GlobalTransform, Option<&Velocity>)>,
+) {
+    for (vehicle, mut engine, mut brakes, mut assists, transform, velocity) in vehicles.iter_mut() {
+        let drive_type = vehicle.config.drivetrain_config.drive_type;
+        let speed = vehicle.vehicle_speed;
+        let mut max_spin = 0.0_f32;
+        let mut max_lock = 0.0_f32;
+        for wheel in wheels.iter_many(vehicle.wheel_entities) {
+            if !wheel.ground_contact {
+                continue;
+            }
+            let slip = directional_slip(wheel.slip_ratio, speed);
+            if is_driven(drive_type, wheel.position) {
+                max_spin = max_spin.max(slip);
+            }
+            max_lock = max_lock.min(slip);
+        }
+
+        let mut state = DriverAssists::default();
+
+        if config.traction_control && engine.throttle > 0.0 {
+            let factor = config.traction_control_factor(max_spin);
+            if factor < 1.0 {
+                engine.throttle *= factor;
+                state.traction_control_active = true;
+            }
+        }
+
+        // Only when the driver is off the pedals, they can always override it
+        if config.hill_descent && engine.throttle <= 0.05 && brakes.brake <= 0.05 {
+            let travel = velocity.map_or(transform.forward() * speed, |velocity| velocity.linvel);
+            let descent_slope = (-travel.normalize_or_zero().y).asin();
+            if let Some(brake) = config.hill_descent_brake(travel.length(), descent_slope) {
+                brakes.brake = brake;
+                state.hill_descent_active = true;
+            }
+        }
+
+        if config.abs {
+            if let Some(brake) = config.abs_brake(brakes.brake, max_lock) {
+                brakes.brake = brake;
+                state.abs_active = true;
+            }
+        }
+
+        if assists.traction_control_active != state.traction_control_active
+            || assists.abs_active != state.abs_active
+            || assists.hill_descent_active != state.hill_descent_active
+        {
+            *assists = state;
+        }
+    }
+}
+
+/// Plugin for traction control, ABS and hill descent control
+pub struct DriverAssistPlugin;
+
+impl Plugin for DriverAssistPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DriverAssistConfig>()
+            .add_systems(Update, (
+                sync_assist_settings.run_if(resource_exists::<GameSettings>()),
+                apply_driver_assists,
+            ).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traction_control_cuts_throttle_with_spin() {
+        let config = DriverAssistConfig::default();
+        assert_eq!(config.traction_control_factor(0.1), 1.0);
+        let partial = config.traction_control_factor(config.target_slip + config.slip_window * 0.5);
+        assert!((partial - 0.5).abs() < 1e-5);
+        assert_eq!(config.traction_control_factor(2.0), 0.0);
+    }
+
+    #[test]
+    fn test_abs_releases_only_locking_wheels() {
+        let config = DriverAssistConfig::default();
+        assert_eq!(config.abs_brake(1.0, -0.1), None);
+        assert_eq!(config.abs_brake(0.0, -1.0), None);
+        assert_eq!(config.abs_brake(1.0, -1.0), Some(config.abs_release));
+    }
+
+    #[test]
+    fn test_hill_descent_holds_speed_on_slopes() {
+        let config = DriverAssistConfig::default();
+        // Flat ground or climbing
+        assert_eq!(config.hill_descent_brake(10.0, 0.0), None);
+        assert_eq!(config.hill_descent_brake(10.0, -0.3), None);
+        // Below the target speed it lets the vehicle roll
+        assert_eq!(config.hill_descent_brake(config.hill_descent_speed * 0.5, 0.3), Some(0.0));
+        let brake = config.hill_descent_brake(config.hill_descent_speed + 1.0, 0.3).unwrap();
+        assert!((brake - config.hill_descent_gain).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_slip_follows_direction_of_travel() {
+        // A wheel spinning backwards while reversing is spinning, not locking
+        assert_eq!(directional_slip(-0.5, -3.0), 0.5);
+        assert_eq!(directional_slip(-0.5, 3.0), -0.5);
+        assert!(is_driven(DriveType::RearWD, 3) && !is_driven(DriveType::RearWD, 0));
+        assert!(is_driven(DriveType::FrontWD, 1) && !is_driven(DriveType::FrontWD, 2));
+    }
+}
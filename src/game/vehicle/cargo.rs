@@ -0,0 +1,424 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use super::FuelCan;
+use crate::game::{is_open, ScheduleOpen};
+
+/// Kinds of loose cargo that can ride in a vehicle bed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CargoKind {
+    FuelCan,
+    SpareTire,
+    Cooler,
+}
+
+impl CargoKind {
+    pub const ALL: [CargoKind; 3] = [CargoKind::FuelCan, CargoKind::SpareTire, CargoKind::Cooler];
+
+    /// Localization key of the kind's name
+    pub fn name_key(&self) -> &'static str {
+        match self {
+            CargoKind::FuelCan => "cargo.fuel_can",
+            CargoKind::SpareTire => "cargo.spare_tire",
+            CargoKind::Cooler => "cargo.cooler",
+        }
+    }
+
+    /// Mass in kg
+    pub fn mass(&self) -> f32 {
+        match self {
+            CargoKind::FuelCan => 18.0,
+            CargoKind::SpareTire => 32.0,
+            CargoKind::Cooler => 25.0,
+        }
+    }
+
+    /// Half size of the collider box
+    pub fn half_extents(&self) -> Vec3 {
+        match self {
+            CargoKind::FuelCan => Vec3::new(0.09, 0.23, 0.17),
+            CargoKind::SpareTire => Vec3::new(0.4, 0.14, 0.4),
+            CargoKind::Cooler => Vec3::new(0.35, 0.22, 0.22),
+        }
+    }
+
+    /// Impact speed change in m/s above which the item starts taking damage
+    pub fn damage_threshold(&self) -> f32 {
+        match self {
+            CargoKind::FuelCan => 6.0,
+            CargoKind::SpareTire => 12.0,
+            CargoKind::Cooler => 4.0,
+        }
+    }
+
+    /// Integrity lost per m/s of impact above the threshold
+    pub fn fragility(&self) -> f32 {
+        match self {
+            CargoKind::FuelCan => 0.08,
+            CargoKind::SpareTire => 0.02,
+            CargoKind::Cooler => 0.15,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            CargoKind::FuelCan => Color::rgb(0.7, 0.1, 0.08),
+            CargoKind::SpareTire => Color::rgb(0.06, 0.06, 0.06),
+            CargoKind::Cooler => Color::rgb(0.1, 0.35, 0.75),
+        }
+    }
+}
+
+/// A loose cargo item, simulated as its own rigid body
+#[derive(Component, Debug, Clone)]
+pub struct CargoItem {
+    pub kind: CargoKind,
+    /// Remaining condition (0.0 - 1.0)
+    pub integrity: f32,
+    /// Vehicle whose bed the item is currently sitting in
+    pub in_bed_of: Option<Entity>,
+    /// Vehicle the item is strapped to
+    pub strapped_to: Option<Entity>,
+    last_velocity: Option<Vec3>,
+}
+
+impl CargoItem {
+    pub fn new(kind: CargoKind) -> Self {
+        Self {
+            kind,
+            integrity: 1.0,
+            in_bed_of: None,
+            strapped_to: None,
+            last_velocity: None,
+        }
+    }
+
+    /// Applies damage for an impact with the given change in velocity, returns the integrity lost
+    pub fn apply_impact(&mut self, delta_v: f32) -> f32 {
+        let excess = delta_v - self.kind.damage_threshold();
+        if excess <= 0.0 {
+            return 0.0;
+        }
+        let lost = (excess * self.kind.fragility()).min(self.integrity);
+        self.integrity -= lost;
+        lost
+    }
+
+    pub fn is_destroyed(&self) -> bool {
+        self.integrity <= 0.0
+    }
+}
+
+/// Load bed of a vehicle
+#[derive(Component, Debug, Clone)]
+pub struct CargoBed {
+    /// Bed centre in vehicle space
+    pub offset: Vec3,
+    /// Half size of the bed volume, items inside it count as loaded
+    pub half_extents: Vec3,
+    /// How far a strapped item can shift before the strap goes taut
+    pub strap_slack: f32,
+}
+
+impl Default for CargoBed {
+    fn default() -> Self {
+        Self {
+            offset: Vec3::new(0.0, 0.3, 1.2),
+            half_extents: Vec3::new(0.7, 0.4, 0.6),
+            strap_slack: 0.03,
+        }
+    }
+}
+
+impl CargoBed {
+    /// Whether a world position lies inside the bed of a vehicle at `vehicle_transform`
+    pub fn contains(&self, vehicle_transform: &GlobalTransform, point: Vec3) -> bool {
+        let local = vehicle_transform.affine().inverse().transform_point3(point) - self.offset;
+        local.abs().cmple(self.half_extents).all()
+    }
+}
+
+/// Area where cargo counts as delivered for a challenge
+#[derive(Component, Debug, Clone)]
+pub struct DeliveryZone {
+    pub radius: f32,
+    /// Minimum integrity for a delivery to count as intact
+    pub min_integrity: f32,
+}
+
+/// Request to strap an item down in a vehicle's bed
+#[derive(Event, Debug, Clone, Copy)]
+pub struct StrapCargoEvent {
+    pub cargo: Entity,
+    pub vehicle: Entity,
+}
+
+/// Request to release an item's straps
+#[derive(Event, Debug, Clone, Copy)]
+pub struct UnstrapCargoEvent {
+    pub cargo: Entity,
+}
+
+/// An item took damage
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CargoDamagedEvent {
+    pub cargo: Entity,
+    pub integrity_lost: f32,
+    pub destroyed: bool,
+}
+
+/// An item bounced out of a vehicle's bed
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CargoLostEvent {
+    pub cargo: Entity,
+    pub vehicle: Entity,
+}
+
+/// An item reached a delivery zone
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CargoDeliveredEvent {
+    pub cargo: Entity,
+    pub zone: Entity,
+    pub integrity: f32,
+    pub intact: bool,
+}
+
+/// Marks items that have already been delivered
+#[derive(Component)]
+pub struct Delivered;
+
+/// Spawns a cargo item as a free rigid body
+pub fn spawn_cargo(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    kind: CargoKind,
+    transform: Transform,
+) -> Entity {
+    let half = kind.half_extents();
+    let mut entity = commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(shape::Box::new(half.x * 2.0, half.y * 2.0, half.z * 2.0).into()),
+            material: materials.add(kind.color().into()),
+            transform,
+            ..default()
+        },
+        RigidBody::Dynamic,
+        Collider::cuboid(half.x, half.y, half.z),
+        ColliderMassProperties::Mass(kind.mass()),
+        Friction::coefficient(0.6),
+        Restitution::coefficient(0.15),
+        Velocity::default(),
+        // Small items at speed tunnel through thin bed walls without CCD
+        Ccd::enabled(),
+        CargoItem::new(kind),
+        Name::new(format!("{kind:?}")),
+    ));
+    if kind == CargoKind::FuelCan {
+        entity.insert(FuelCan::default());
+    }
+    entity.id()
+}
+
+/// Strap joint holding an item near where it sits in the bed, with a little slack
+pub fn strap_joint(anchor_in_vehicle: Vec3, slack: f32) -> GenericJoint {
+    GenericJointBuilder::new(JointAxesMask::empty())
+        .local_anchor1(anchor_in_vehicle)
+        .local_anchor2(Vec3::ZERO)
+        .limits(JointAxis::X, [-slack, slack])
+        .limits(JointAxis::Y, [-slack, slack])
+        .limits(JointAxis::Z, [-slack, slack])
+        .limits(JointAxis::AngX, [-0.1, 0.1])
+        .limits(JointAxis::AngY, [-0.1, 0.1])
+        .limits(JointAxis::AngZ, [-0.1, 0.1])
+        .build()
+}
+
+/// Tracks which bed each item sits in and reports items that fall out
+pub fn update_cargo_beds(
+    beds: Query<(Entity, &CargoBed, &GlobalTransform)>,
+    mut items: Query<(Entity, &mut CargoItem, &GlobalTransform)>,
+    mut lost_events: EventWriter<CargoLostEvent>,
+) {
+    for (entity, mut item, transform) in items.iter_mut() {
+        let position = transform.translation();
+        let bed = beds
+            .iter()
+            .find(|(_, bed, vehicle_transform)| bed.contains(vehicle_transform, position))
+            .map(|(vehicle, _, _)| vehicle);
+
+        if bed != item.in_bed_of {
+            if let (Some(vehicle), None) = (item.in_bed_of, bed) {
+                lost_events.send(CargoLostEvent { cargo: entity, vehicle });
+            }
+            item.in_bed_of = bed;
+        }
+    }
+}
+
+/// Damages items from sudden changes in velocity, which is what a hard landing or a fall does
+pub fn apply_cargo_impacts(
+    mut items: Query<(Entity, &mut CargoItem, &Velocity)>,
+    mut damaged_events: EventWriter<CargoDamagedEvent>,
+) {
+    for (entity, mut item, velocity) in items.iter_mut() {
+        let delta_v = item.last_velocity.map_or(0.0, |last| (velocity.linvel - last).length());
+        item.last_velocity = Some(velocity.linvel);
+
+        let lost = item.apply_impact(delta_v);
+        if lost > 0.0 {
+            damaged_events.send(CargoDamagedEvent {
+                cargo: entity,
+                integrity_lost: lost,
+                destroyed: item.is_destroyed(),
+            });
+        }
+    }
+}
+
+/// Creates and removes strap joints
+pub fn apply_strap_events(
+    mut commands: Commands,
+    mut strap_events: EventReader<StrapCargoEvent>,
+    mut unstrap_events: EventReader<UnstrapCargoEvent>,
+    beds: Query<(&CargoBed, &GlobalTransform)>,
+    mut items: Query<(&mut CargoItem, &GlobalTransform)>,
+) {
+    for event in strap_events.read() {
+        let (Ok((bed, vehicle_transform)), Ok((mut item, transform))) =
+            (beds.get(event.vehicle), items.get_mut(event.cargo))
+        else {
+            continue;
+        };
+        // Straps only reach items that are actually in the bed
+        if item.strapped_to.is_some() || !bed.contains(vehicle_transform, transform.translation()) {
+            continue;
+        }
+
+        let anchor = vehicle_transform.affine().inverse().transform_point3(transform.translation());
+        commands
+            .entity(event.cargo)
+            .insert(ImpulseJoint::new(event.vehicle, strap_joint(anchor, bed.strap_slack)));
+        item.strapped_to = Some(event.vehicle);
+    }
+
+    for event in unstrap_events.read() {
+        let Ok((mut item, _)) = items.get_mut(event.cargo) else {
+            continue;
+        };
+        if item.strapped_to.take().is_some() {
+            commands.entity(event.cargo).remove::<ImpulseJoint>();
+        }
+    }
+}
+
+/// Reports items that reach a delivery zone, once each. Zones with opening hours, like night-only
+/// challenges, only take deliveries while open.
+pub fn check_cargo_delivery(
+    mut commands: Commands,
+    zones: Query<(Entity, &DeliveryZone, &GlobalTransform, Option<&ScheduleOpen>)>,
+    items: Query<(Entity, &CargoItem, &GlobalTransform), Without<Delivered>>,
+    mut delivered_events: EventWriter<CargoDeliveredEvent>,
+) {
+    for (cargo, item, transform) in items.iter() {
+        let position = transform.translation();
+        let Some((zone, delivery)) = zones
+            .iter()
+            .filter(|(_, _, _, open)| is_open(*open))
+            .find(|(_, zone, zone_transform, _)| zone_transform.translation().distance(position) <= zone.radius)
+            .map(|(entity, zone, _, _)| (entity, zone))
+        else {
+            continue;
+        };
+
+        delivered_events.send(CargoDeliveredEvent {
+            cargo,
+            zone,
+            integrity: item.integrity,
+            intact: item.integrity >= delivery.min_integrity,
+        });
+        commands.entity(cargo).insert(Delivered);
+    }
+}
+
+/// Plugin for loose cargo, straps and delivery
+pub struct CargoPlugin;
+
+impl Plugin for CargoPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<StrapCargoEvent>()
+            .add_event::<UnstrapCargoEvent>()
+            .add_event::<CargoDamagedEvent>()
+            .add_event::<CargoLostEvent>()
+            .add_event::<CargoDeliveredEvent>()
+            .add_systems(Update, (
+                apply_strap_events,
+                update_cargo_beds,
+                apply_cargo_impacts,
+                check_cargo_delivery,
+            ).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_bumps_do_no_damage() {
+        let mut cooler = CargoItem::new(CargoKind::Cooler);
+        assert_eq!(cooler.apply_impact(3.0), 0.0);
+        assert_eq!(cooler.integrity, 1.0);
+    }
+
+    #[test]
+    fn test_fragile_items_break_first() {
+        let mut cooler = CargoItem::new(CargoKind::Cooler);
+        let mut tire = CargoItem::new(CargoKind::SpareTire);
+        cooler.apply_impact(10.0);
+        tire.apply_impact(10.0);
+        assert!(cooler.integrity < tire.integrity);
+    }
+
+    #[test]
+    fn test_integrity_never_goes_negative() {
+        let mut can = CargoItem::new(CargoKind::FuelCan);
+        can.apply_impact(100.0);
+        assert_eq!(can.integrity, 0.0);
+        assert!(can.is_destroyed());
+        assert_eq!(can.apply_impact(100.0), 0.0);
+    }
+
+    #[test]
+    fn test_bed_contains() {
+        let bed = CargoBed::default();
+        let transform = GlobalTransform::from_translation(Vec3::new(5.0, 0.0, 0.0));
+        assert!(bed.contains(&transform, Vec3::new(5.0, 0.3, 1.2)));
+        assert!(!bed.contains(&transform, Vec3::new(5.0, 0.3, -1.2)));
+        assert!(!bed.contains(&transform, Vec3::new(5.0, 1.5, 1.2)));
+    }
+
+    #[test]
+    fn test_lost_cargo_is_reported() {
+        let mut app = App::new();
+        app.add_event::<CargoLostEvent>()
+            .add_systems(Update, update_cargo_beds);
+
+        let vehicle = app.world.spawn((CargoBed::default(), GlobalTransform::IDENTITY)).id();
+        let cargo = app
+            .world
+            .spawn((CargoItem::new(CargoKind::Cooler), GlobalTransform::from_translation(Vec3::new(0.0, 0.3, 1.2))))
+            .id();
+
+        app.update();
+        assert_eq!(app.world.get::<CargoItem>(cargo).unwrap().in_bed_of, Some(vehicle));
+
+        *app.world.get_mut::<GlobalTransform>(cargo).unwrap() = GlobalTransform::from_translation(Vec3::new(3.0, 0.0, 4.0));
+        app.update();
+
+        let events = app.world.resource::<Events<CargoLostEvent>>();
+        let lost: Vec<_> = events.get_reader().read(events).copied().collect();
+        assert_eq!(lost.len(), 1);
+        assert_eq!(lost[0].vehicle, vehicle);
+    }
+}
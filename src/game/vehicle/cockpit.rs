@@ -0,0 +1,260 @@
+use bevy::prelude::*;
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use super::{Engine, Steering, Vehicle, VehicleConfig};
+use crate::game::plugins::PlayerId;
+
+/// Turns of the steering wheel from the centre to full lock
+pub const STEERING_WHEEL_TURNS: f32 = 1.5;
+/// Speed at the end of the speedometer scale, in meters per second (180 km/h)
+pub const SPEEDOMETER_FULL_SCALE: f32 = 50.0;
+/// RPM at the end of the tachometer scale
+pub const TACHOMETER_FULL_SCALE: f32 = 7000.0;
+/// Angle a needle sweeps from zero to full scale, in radians (240°)
+const GAUGE_SWEEP: f32 = 4.2;
+/// How fast needles swing over to their reading, per second
+const NEEDLE_RESPONSE: f32 = 12.0;
+/// Backwards tilt of the steering column, in radians
+const COLUMN_TILT: f32 = 0.35;
+
+/// Where the driver's head, the steering wheel and the gauges sit in chassis space, front is -Z and
+/// the driver sits on the left
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CockpitLayout {
+    pub eye: Vec3,
+    pub steering_wheel: Vec3,
+    pub speedometer: Vec3,
+    pub tachometer: Vec3,
+}
+
+impl CockpitLayout {
+    pub fn for_config(config: &VehicleConfig) -> Self {
+        let eye = Vec3::new(-config.dimensions.x * 0.2, config.dimensions.y * 0.35, -config.dimensions.z * 0.02);
+        let steering_wheel = eye + Vec3::new(0.0, -0.35, -0.45);
+        // The gauges sit in the dash, seen through the top of the wheel
+        let dash = steering_wheel + Vec3::new(0.0, 0.12, -0.2);
+        Self {
+            eye,
+            steering_wheel,
+            speedometer: dash - Vec3::X * 0.09,
+            tachometer: dash + Vec3::X * 0.09,
+        }
+    }
+}
+
+/// What a gauge needle reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gauge {
+    Speedometer,
+    Tachometer,
+}
+
+impl Gauge {
+    pub fn full_scale(self) -> f32 {
+        match self {
+            Gauge::Speedometer => SPEEDOMETER_FULL_SCALE,
+            Gauge::Tachometer => TACHOMETER_FULL_SCALE,
+        }
+    }
+
+    fn reading(self, vehicle: &Vehicle, engine: &Engine) -> f32 {
+        match self {
+            Gauge::Speedometer => vehicle.vehicle_speed.abs(),
+            Gauge::Tachometer => engine.rpm,
+        }
+    }
+}
+
+/// Pivot of a gauge needle in the dash, the needle mesh hangs off it pointing up
+#[derive(Component, Debug, Clone)]
+pub struct GaugeNeedle {
+    pub gauge: Gauge,
+    /// Current needle angle in radians, lagging behind the reading like a real needle
+    angle: f32,
+}
+
+impl GaugeNeedle {
+    pub fn new(gauge: Gauge) -> Self {
+        Self { gauge, angle: needle_angle(0.0, gauge.full_scale()) }
+    }
+
+    pub fn angle(&self) -> f32 {
+        self.angle
+    }
+}
+
+/// The cab's steering wheel, turned with the front wheels
+#[derive(Component, Debug, Clone, Copy)]
+pub struct SteeringWheel;
+
+/// Marks vehicles whose cockpit interior has been built
+#[derive(Component)]
+pub struct CockpitAttached;
+
+/// Needle angle about the gauge face for `value`, zero at the lower left and sweeping clockwise
+/// as seen from the driver's seat
+pub fn needle_angle(value: f32, full_scale: f32) -> f32 {
+    let fraction = (value / full_scale.max(1e-3)).clamp(0.0, 1.0);
+    GAUGE_SWEEP * (0.5 - fraction)
+}
+
+/// Steering wheel rotation about its column for a road wheel `steering_angle`, positive to the left
+pub fn steering_wheel_angle(steering_angle: f32, max_steering_angle: f32) -> f32 {
+    (steering_angle / max_steering_angle.max(1e-3)).clamp(-1.0, 1.0) * STEERING_WHEEL_TURNS * TAU
+}
+
+/// Chassis space rotation of the steering wheel turned by `angle`, the torus mesh is modelled flat on Y
+fn steering_wheel_rotation(angle: f32) -> Quat {
+    Quat::from_rotation_x(COLUMN_TILT) * Quat::from_rotation_z(angle) * Quat::from_rotation_x(FRAC_PI_2)
+}
+
+/// Builds a steering wheel and the speedometer and tachometer in the cab of each player's vehicle
+pub fn attach_cockpits(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    vehicles: Query<(Entity, &Vehicle), (With<PlayerId>, Without<CockpitAttached>)>,
+) {
+    for (vehicle_entity, vehicle) in vehicles.iter() {
+        let layout = CockpitLayout::for_config(&vehicle.config);
+        let trim = materials.add(StandardMaterial {
+            base_color: Color::rgb(0.06, 0.06, 0.06),
+            perceptual_roughness: 0.7,
+            ..default()
+        });
+        let dial = materials.add(StandardMaterial {
+            base_color: Color::rgb(0.9, 0.9, 0.88),
+            unlit: true,
+            ..default()
+        });
+        let needle = materials.add(StandardMaterial {
+            base_color: Color::rgb(1.0, 0.35, 0.1),
+            unlit: true,
+            ..default()
+        });
+
+        let wheel = commands
+            .spawn((
+                PbrBundle {
+                    mesh: meshes.add(Mesh::from(shape::Torus { radius: 0.19, ring_radius: 0.018, ..default() })),
+                    material: trim.clone(),
+                    transform: Transform::from_translation(layout.steering_wheel)
+                        .with_rotation(steering_wheel_rotation(0.0)),
+                    ..default()
+                },
+                SteeringWheel,
+                Name::new("Steering Wheel"),
+            ))
+            .with_children(|parent| {
+                // A spoke across the rim, so turning shows
+                parent.spawn(PbrBundle {
+                    mesh: meshes.add(Mesh::from(shape::Box::new(0.36, 0.02, 0.03))),
+                    material: trim.clone(),
+                    ..default()
+                });
+            })
+            .id();
+
+        let mut parts = vec![wheel];
+        for (gauge, position) in [(Gauge::Speedometer, layout.speedometer), (Gauge::Tachometer, layout.tachometer)] {
+            parts.push(
+                commands
+                    .spawn((
+                        PbrBundle {
+                            mesh: meshes.add(Mesh::from(shape::Circle::new(0.07))),
+                            material: dial.clone(),
+                            transform: Transform::from_translation(position).with_rotation(Quat::from_rotation_x(0.3)),
+                            ..default()
+                        },
+                        Name::new(format!("{gauge:?}")),
+                    ))
+                    .with_children(|parent| {
+                        parent
+                            .spawn((
+                                SpatialBundle::from_transform(Transform::from_xyz(0.0, 0.0, 0.005)),
+                                GaugeNeedle::new(gauge),
+                            ))
+                            .with_children(|pivot| {
+                                pivot.spawn(PbrBundle {
+                                    mesh: meshes.add(Mesh::from(shape::Box::new(0.006, 0.06, 0.002))),
+                                    material: needle.clone(),
+                                    transform: Transform::from_xyz(0.0, 0.03, 0.0),
+                                    ..default()
+                                });
+                            });
+                    })
+                    .id(),
+            );
+        }
+
+        commands.entity(vehicle_entity).push_children(&parts).insert(CockpitAttached);
+    }
+}
+
+/// Turns the steering wheel with the front wheels and swings the needles to their readings
+pub fn update_cockpits(
+    time: Res<Time>,
+    vehicles: Query<(&Vehicle, &Engine, &Steering)>,
+    parents: Query<&Parent>,
+    mut wheels: Query<(&mut Transform, &Parent), With<SteeringWheel>>,
+    mut needles: Query<(&mut GaugeNeedle, &mut Transform, &Parent), Without<SteeringWheel>>,
+) {
+    for (mut transform, parent) in wheels.iter_mut() {
+        if let Ok((vehicle, _, steering)) = vehicles.get(parent.get()) {
+            let angle = steering_wheel_angle(steering.angle, vehicle.config.max_steering_angle);
+            transform.rotation = steering_wheel_rotation(angle);
+        }
+    }
+
+    let blend = 1.0 - (-NEEDLE_RESPONSE * time.delta_seconds()).exp();
+    for (mut needle, mut transform, parent) in needles.iter_mut() {
+        // Needles hang off their gauge face, which hangs off the vehicle
+        let Some((vehicle, engine, _)) = parents.get(parent.get()).ok().and_then(|face| vehicles.get(face.get()).ok())
+        else {
+            continue;
+        };
+        let target = needle_angle(needle.gauge.reading(vehicle, engine), needle.gauge.full_scale());
+        needle.angle += (target - needle.angle) * blend;
+        transform.rotation = Quat::from_rotation_z(needle.angle);
+    }
+}
+
+/// Plugin for the cab interior seen from the cockpit camera
+pub struct CockpitPlugin;
+
+impl Plugin for CockpitPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (attach_cockpits, update_cockpits).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_needles_sweep_clockwise() {
+        let zero = needle_angle(0.0, SPEEDOMETER_FULL_SCALE);
+        let half = needle_angle(SPEEDOMETER_FULL_SCALE * 0.5, SPEEDOMETER_FULL_SCALE);
+        let full = needle_angle(SPEEDOMETER_FULL_SCALE * 2.0, SPEEDOMETER_FULL_SCALE);
+        // Positive is anticlockwise about +Z, which faces the driver
+        assert!(zero > 0.0 && full < 0.0);
+        assert_eq!(half, 0.0);
+        assert_eq!(zero, -full);
+    }
+
+    #[test]
+    fn test_steering_wheel_turns_to_lock() {
+        assert_eq!(steering_wheel_angle(0.0, 0.6), 0.0);
+        assert_eq!(steering_wheel_angle(0.6, 0.6), STEERING_WHEEL_TURNS * TAU);
+        assert_eq!(steering_wheel_angle(-0.9, 0.6), -STEERING_WHEEL_TURNS * TAU);
+    }
+
+    #[test]
+    fn test_driver_sits_left_behind_the_wheel() {
+        let layout = CockpitLayout::for_config(&VehicleConfig::default());
+        assert!(layout.eye.x < 0.0);
+        assert!(layout.steering_wheel.z < layout.eye.z && layout.steering_wheel.y < layout.eye.y);
+        assert!(layout.speedometer.x < layout.tachometer.x);
+    }
+}
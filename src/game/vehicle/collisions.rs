@@ -0,0 +1,368 @@
+use bevy::asset::UntypedAssetId;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_rapier3d::prelude::*;
+
+use super::{Vehicle, VehicleBodyMaterial, Wheel};
+use crate::game::plugins::ImpactEvent;
+
+/// Side of a vehicle's body a hit lands on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DamageZone {
+    Front,
+    Rear,
+    Left,
+    Right,
+}
+
+impl DamageZone {
+    /// Zone of a point in the vehicle's local space, vehicles face -Z. Measured against the body's
+    /// half `dimensions`, so a hit on a corner goes to whichever side it's further out on.
+    pub fn at(local: Vec3, dimensions: Vec3) -> Self {
+        let across = local.x.abs() / (dimensions.x * 0.5).max(f32::EPSILON);
+        let along = local.z.abs() / (dimensions.z * 0.5).max(f32::EPSILON);
+        match (along >= across, local.z < 0.0, local.x < 0.0) {
+            (true, true, _) => Self::Front,
+            (true, false, _) => Self::Rear,
+            (false, _, true) => Self::Left,
+            (false, _, false) => Self::Right,
+        }
+    }
+}
+
+/// Body damage of a vehicle per zone, each from 0.0 (straight) to 1.0 (caved in)
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq)]
+pub struct BodyDamage {
+    pub front: f32,
+    pub rear: f32,
+    pub left: f32,
+    pub right: f32,
+}
+
+impl BodyDamage {
+    pub fn zone(&self, zone: DamageZone) -> f32 {
+        match zone {
+            DamageZone::Front => self.front,
+            DamageZone::Rear => self.rear,
+            DamageZone::Left => self.left,
+            DamageZone::Right => self.right,
+        }
+    }
+
+    /// Adds `amount` to a zone, returning how much it actually took before caving in fully
+    pub fn add(&mut self, zone: DamageZone, amount: f32) -> f32 {
+        let value = match zone {
+            DamageZone::Front => &mut self.front,
+            DamageZone::Rear => &mut self.rear,
+            DamageZone::Left => &mut self.left,
+            DamageZone::Right => &mut self.right,
+        };
+        let taken = amount.clamp(0.0, 1.0 - *value);
+        *value += taken;
+        taken
+    }
+
+    /// Average over the zones
+    pub fn total(&self) -> f32 {
+        (self.front + self.rear + self.left + self.right) * 0.25
+    }
+}
+
+/// Tuning for vehicle on vehicle collisions
+#[derive(Resource, Debug, Clone)]
+pub struct VehicleCollisionSettings {
+    /// Closing speed in m/s below which a contact is a nudge and does no damage
+    pub min_closing_speed: f32,
+    /// Zone damage per kilojoule of collision energy a vehicle absorbs
+    pub damage_per_kilojoule: f32,
+    /// Size of a paint scrape in meters, stretched along the slide
+    pub scrape_size: f32,
+    /// Scrapes kept per vehicle, the oldest go first
+    pub max_scrapes: usize,
+}
+
+impl Default for VehicleCollisionSettings {
+    fn default() -> Self {
+        Self {
+            min_closing_speed: 1.5,
+            damage_per_kilojoule: 0.015,
+            scrape_size: 0.3,
+            max_scrapes: 16,
+        }
+    }
+}
+
+/// What a collision did to one of the two vehicles in it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CollisionShare {
+    /// Kinetic energy lost to the collision in joules, shared by both vehicles
+    pub energy: f32,
+    /// Speed the vehicles were closing at along the contact normal in m/s
+    pub closing_speed: f32,
+    /// Share of the energy absorbed by the first and second vehicle
+    pub absorbed: (f32, f32),
+}
+
+/// Splits a collision of two bodies of `mass1` and `mass2` kg by its impulse in N·s. The closing
+/// speed follows from the impulse through the reduced mass and restitution, and each vehicle absorbs
+/// the energy in proportion to the other's mass, so a light buggy hitting a truck takes the worst of it.
+pub fn split_collision(impulse: f32, mass1: f32, mass2: f32, restitution: f32) -> CollisionShare {
+    let total = (mass1 + mass2).max(f32::EPSILON);
+    let reduced = mass1 * mass2 / total;
+    if reduced <= 0.0 {
+        return CollisionShare { energy: 0.0, closing_speed: 0.0, absorbed: (0.0, 0.0) };
+    }
+    let restitution = restitution.clamp(0.0, 1.0);
+    let closing_speed = impulse / ((1.0 + restitution) * reduced);
+    let energy = 0.5 * reduced * closing_speed * closing_speed * (1.0 - restitution * restitution);
+    CollisionShare {
+        energy,
+        closing_speed,
+        absorbed: (energy * mass2 / total, energy * mass1 / total),
+    }
+}
+
+/// A vehicle hit by another vehicle, sent once for each of the two
+#[derive(Event, Debug, Clone, Copy)]
+pub struct VehicleCollisionEvent {
+    pub vehicle: Entity,
+    pub other: Entity,
+    /// Contact point in world space
+    pub position: Vec3,
+    pub zone: DamageZone,
+    pub closing_speed: f32,
+    /// Zone damage taken
+    pub damage: f32,
+}
+
+/// Paint scrape left on a vehicle by another one, a child of the scraped vehicle
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PaintScrape {
+    /// Vehicle whose paint it is
+    pub from: Entity,
+}
+
+/// Scrapes on a vehicle, oldest first
+#[derive(Component, Debug, Clone, Default)]
+pub struct PaintScrapes(pub Vec<Entity>);
+
+/// Shared scrape quad, and one scrape material per body material it was taken from
+#[derive(Resource, Default)]
+struct PaintScrapeAssets {
+    quad: Option<Handle<Mesh>>,
+    materials: HashMap<UntypedAssetId, Handle<StandardMaterial>>,
+}
+
+/// Mass of a vehicle's body, the wheels are colliders on the same rigid body
+fn vehicle_mass(vehicle: &Vehicle, wheels: &Query<&Wheel>) -> f32 {
+    vehicle.config.mass
+        + vehicle.wheel_entities.iter().filter_map(|wheel| wheels.get(*wheel).ok()).map(|wheel| wheel.mass).sum::<f32>()
+}
+
+/// The vehicle a collider belongs to, the vehicle itself or one of its wheels or underbody parts
+fn owning_vehicle(entity: Entity, vehicles: &Query<&Vehicle>, parents: &Query<&Parent>) -> Option<Entity> {
+    if vehicles.contains(entity) {
+        return Some(entity);
+    }
+    parents.get(entity).ok().map(Parent::get).filter(|parent| vehicles.contains(*parent))
+}
+
+fn add_body_damage(mut commands: Commands, vehicles: Query<Entity, (Added<Vehicle>, Without<BodyDamage>)>) {
+    for vehicle in vehicles.iter() {
+        commands.entity(vehicle).insert((BodyDamage::default(), PaintScrapes::default()));
+    }
+}
+
+/// Exchanges damage between vehicles that hit each other, by mass and closing speed
+fn exchange_collision_damage(
+    settings: Res<VehicleCollisionSettings>,
+    mut impacts: EventReader<ImpactEvent>,
+    vehicles: Query<&Vehicle>,
+    wheels: Query<&Wheel>,
+    parents: Query<&Parent>,
+    restitutions: Query<&Restitution>,
+    mut bodies: Query<(&GlobalTransform, &mut BodyDamage)>,
+    mut collisions: EventWriter<VehicleCollisionEvent>,
+) {
+    for impact in impacts.read() {
+        let (Some(first), Some(second)) = (
+            owning_vehicle(impact.entity1, &vehicles, &parents),
+            owning_vehicle(impact.entity2, &vehicles, &parents),
+        ) else {
+            continue;
+        };
+        if first == second {
+            continue;
+        }
+        let (Ok(vehicle1), Ok(vehicle2)) = (vehicles.get(first), vehicles.get(second)) else {
+            continue;
+        };
+
+        // Rapier averages the two coefficients by default
+        let restitution = |entity| restitutions.get(entity).map_or(0.0, |restitution| restitution.coefficient);
+        let restitution = (restitution(impact.entity1) + restitution(impact.entity2)) * 0.5;
+        let share = split_collision(
+            impact.impulse,
+            vehicle_mass(vehicle1, &wheels),
+            vehicle_mass(vehicle2, &wheels),
+            restitution,
+        );
+        if share.closing_speed < settings.min_closing_speed {
+            continue;
+        }
+
+        let sides = [(first, second, vehicle1, share.absorbed.0), (second, first, vehicle2, share.absorbed.1)];
+        for (vehicle, other, config, absorbed) in sides {
+            let Ok((transform, mut damage)) = bodies.get_mut(vehicle) else {
+                continue;
+            };
+            let local = transform.affine().inverse().transform_point3(impact.position);
+            let zone = DamageZone::at(local, config.config.dimensions);
+            let taken = damage.add(zone, absorbed / 1000.0 * settings.damage_per_kilojoule);
+            collisions.send(VehicleCollisionEvent {
+                vehicle,
+                other,
+                position: impact.position,
+                zone,
+                closing_speed: share.closing_speed,
+                damage: taken,
+            });
+        }
+    }
+}
+
+/// Leaves a scrape of the other vehicle's paint where vehicles touched, stretched along the slide.
+/// Scrapes are children of the vehicle in the world, so spectators and other players see them too.
+#[allow(clippy::too_many_arguments)]
+fn spawn_paint_scrapes(
+    mut commands: Commands,
+    settings: Res<VehicleCollisionSettings>,
+    mut assets: ResMut<PaintScrapeAssets>,
+    meshes: Option<ResMut<Assets<Mesh>>>,
+    materials: Option<ResMut<Assets<StandardMaterial>>>,
+    body_materials: Option<Res<Assets<VehicleBodyMaterial>>>,
+    mut collisions: EventReader<VehicleCollisionEvent>,
+    mut vehicles: Query<(&GlobalTransform, &mut PaintScrapes)>,
+    paints: Query<AnyOf<(&Handle<StandardMaterial>, &Handle<VehicleBodyMaterial>)>>,
+    velocities: Query<&Velocity>,
+) {
+    let (Some(mut meshes), Some(mut materials)) = (meshes, materials) else {
+        collisions.clear();
+        return;
+    };
+    let quad = assets.quad.get_or_insert_with(|| meshes.add(Mesh::from(shape::Quad::new(Vec2::ONE)))).clone();
+
+    for collision in collisions.read() {
+        let (Ok(paint), Ok((other_transform, _))) = (paints.get(collision.other), vehicles.get(collision.other)) else {
+            continue;
+        };
+        let other_center = other_transform.translation();
+        // Dirt materials carry the paint in their base material
+        let (paint, color) = match paint {
+            (Some(plain), _) => (plain.id().untyped(), materials.get(plain).map(|material| material.base_color)),
+            (None, Some(body)) => (
+                body.id().untyped(),
+                body_materials.as_ref().and_then(|assets| assets.get(body)).map(|material| material.base.base_color),
+            ),
+            (None, None) => continue,
+        };
+        let material = assets
+            .materials
+            .entry(paint)
+            .or_insert_with(|| {
+                let color = color.unwrap_or(Color::GRAY);
+                materials.add(StandardMaterial {
+                    base_color: color.with_a(0.85),
+                    perceptual_roughness: 0.95,
+                    alpha_mode: AlphaMode::Blend,
+                    depth_bias: 1.0,
+                    ..default()
+                })
+            })
+            .clone();
+
+        let Ok((transform, mut scrapes)) = vehicles.get_mut(collision.vehicle) else {
+            continue;
+        };
+        // Facing out of this vehicle's body towards the other one
+        let outward = (other_center - collision.position)
+            .try_normalize()
+            .or_else(|| (other_center - transform.translation()).try_normalize())
+            .unwrap_or(Vec3::Y);
+        let velocity = |entity| velocities.get(entity).map_or(Vec3::ZERO, |velocity| velocity.linvel);
+        let relative = velocity(collision.other) - velocity(collision.vehicle);
+        let slide = relative - outward * relative.dot(outward);
+        let along = slide.try_normalize().unwrap_or_else(|| outward.any_orthonormal_vector());
+        let rotation = Quat::from_mat3(&Mat3::from_cols(along, outward.cross(along), outward));
+        let stretch = 1.0 + (slide.length() * 0.25).min(3.0);
+
+        let (_, vehicle_rotation, _) = transform.to_scale_rotation_translation();
+        let local = Transform::from_translation(transform.affine().inverse().transform_point3(collision.position))
+            .with_rotation(vehicle_rotation.inverse() * rotation)
+            .with_scale(Vec3::new(settings.scrape_size * stretch, settings.scrape_size, 1.0));
+        let scrape = commands
+            .spawn((
+                PbrBundle { mesh: quad.clone(), material, transform: local, ..default() },
+                PaintScrape { from: collision.other },
+                Name::new("Paint Scrape"),
+            ))
+            .set_parent(collision.vehicle)
+            .id();
+
+        scrapes.0.push(scrape);
+        while scrapes.0.len() > settings.max_scrapes {
+            let oldest = scrapes.0.remove(0);
+            commands.entity(oldest).despawn_recursive();
+        }
+    }
+}
+
+/// Plugin for vehicle on vehicle collisions, damage exchange and paint scrapes
+pub struct VehicleCollisionPlugin;
+
+impl Plugin for VehicleCollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VehicleCollisionSettings>()
+            .init_resource::<PaintScrapeAssets>()
+            .add_event::<ImpactEvent>()
+            .add_event::<VehicleCollisionEvent>()
+            .add_systems(Update, (add_body_damage, exchange_collision_damage, spawn_paint_scrapes).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lighter_vehicle_absorbs_more() {
+        // A buggy into a truck at 10 m/s, no bounce
+        let (buggy, truck) = (800.0, 3200.0);
+        let reduced = buggy * truck / (buggy + truck);
+        let share = split_collision(reduced * 10.0, buggy, truck, 0.0);
+        assert!((share.closing_speed - 10.0).abs() < 1e-3);
+        assert!((share.energy - 0.5 * reduced * 100.0).abs() < 1e-1);
+        assert!((share.absorbed.0 / share.absorbed.1 - 4.0).abs() < 1e-3);
+        assert!((share.absorbed.0 + share.absorbed.1 - share.energy).abs() < 1e-1);
+
+        // Same impulse with some bounce, the vehicles were closing slower and lost less
+        let bouncy = split_collision(reduced * 10.0, buggy, truck, 0.5);
+        assert!(bouncy.closing_speed < share.closing_speed);
+        assert!(bouncy.energy < share.energy);
+    }
+
+    #[test]
+    fn test_damage_zones() {
+        let dimensions = Vec3::new(1.8, 1.8, 4.2);
+        assert_eq!(DamageZone::at(Vec3::new(0.2, 0.0, -2.1), dimensions), DamageZone::Front);
+        assert_eq!(DamageZone::at(Vec3::new(-0.3, 0.5, 2.0), dimensions), DamageZone::Rear);
+        assert_eq!(DamageZone::at(Vec3::new(-0.9, 0.0, 1.0), dimensions), DamageZone::Left);
+        assert_eq!(DamageZone::at(Vec3::new(0.9, 0.0, -1.0), dimensions), DamageZone::Right);
+
+        let mut damage = BodyDamage::default();
+        assert_eq!(damage.add(DamageZone::Left, 0.7), 0.7);
+        assert!((damage.add(DamageZone::Left, 0.7) - 0.3).abs() < 1e-6);
+        assert_eq!(damage.zone(DamageZone::Left), 1.0);
+        assert_eq!(damage.total(), 0.25);
+    }
+}
@@ -0,0 +1,232 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::VehicleConfig;
+
+/// Tire model parameters of a tire type
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TireSpec {
+    /// Friction coefficient of the tire collider
+    pub grip: f32,
+    /// Share of the wheel load resisting rolling
+    pub rolling_resistance: f32,
+    /// Tire width in meters
+    pub width: f32,
+    /// Wheel and tire mass in kg
+    pub mass: f32,
+    /// Tire diameter relative to the vehicle's stock tires
+    pub radius_scale: f32,
+}
+
+/// Tires fitted to all four wheels
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TireType {
+    #[default]
+    AllTerrain,
+    MudTerrain,
+    Crawler,
+}
+
+impl TireType {
+    pub const ALL: [TireType; 3] = [TireType::AllTerrain, TireType::MudTerrain, TireType::Crawler];
+
+    pub fn spec(self) -> TireSpec {
+        match self {
+            Self::AllTerrain => TireSpec {
+                grip: 1.0,
+                rolling_resistance: 0.02,
+                width: 0.25,
+                mass: 20.0,
+                radius_scale: 1.0,
+            },
+            // Open tread bites into loose ground but drags on the road
+            Self::MudTerrain => TireSpec {
+                grip: 1.15,
+                rolling_resistance: 0.035,
+                width: 0.3,
+                mass: 26.0,
+                radius_scale: 1.05,
+            },
+            // Soft compound aired down for rock, grips best and rolls worst
+            Self::Crawler => TireSpec {
+                grip: 1.3,
+                rolling_resistance: 0.05,
+                width: 0.33,
+                mass: 30.0,
+                radius_scale: 1.1,
+            },
+        }
+    }
+
+    /// Localization key of the tire's name
+    pub fn name_key(self) -> &'static str {
+        match self {
+            Self::AllTerrain => "tires.all_terrain",
+            Self::MudTerrain => "tires.mud_terrain",
+            Self::Crawler => "tires.crawler",
+        }
+    }
+}
+
+/// Suspension lift, raising the body on longer springs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LiftKit {
+    #[default]
+    Stock,
+    TwoInch,
+    FourInch,
+}
+
+impl LiftKit {
+    pub const ALL: [LiftKit; 3] = [LiftKit::Stock, LiftKit::TwoInch, LiftKit::FourInch];
+
+    /// Extra suspension travel in meters
+    pub fn lift(self) -> f32 {
+        match self {
+            Self::Stock => 0.0,
+            Self::TwoInch => 0.05,
+            Self::FourInch => 0.1,
+        }
+    }
+
+    /// Localization key of the kit's name
+    pub fn name_key(self) -> &'static str {
+        match self {
+            Self::Stock => "lift.stock",
+            Self::TwoInch => "lift.two_inch",
+            Self::FourInch => "lift.four_inch",
+        }
+    }
+}
+
+/// Front bumper, the winch bumper lets the tow strap be reeled in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Bumper {
+    #[default]
+    Stock,
+    Steel,
+    Winch,
+}
+
+impl Bumper {
+    pub const ALL: [Bumper; 3] = [Bumper::Stock, Bumper::Steel, Bumper::Winch];
+
+    /// Localization key of the bumper's name
+    pub fn name_key(self) -> &'static str {
+        match self {
+            Self::Stock => "bumper.stock",
+            Self::Steel => "bumper.steel",
+            Self::Winch => "bumper.winch",
+        }
+    }
+
+    /// Size and position of the bumper on a body of `dimensions`, `None` for the stock bumper
+    pub fn fitment(self, dimensions: Vec3) -> Option<(Vec3, Vec3)> {
+        let depth = match self {
+            Self::Stock => return None,
+            Self::Steel => 0.2,
+            Self::Winch => 0.3,
+        };
+        Some((
+            Vec3::new(dimensions.x * 1.05, 0.25, depth),
+            Vec3::new(0.0, -dimensions.y * 0.3, -dimensions.z * 0.5 - depth * 0.5),
+        ))
+    }
+}
+
+/// A garage part, unlocked and saved like paints and accessories
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Part {
+    Tires(TireType),
+    LiftKit(LiftKit),
+    Bumper(Bumper),
+}
+
+impl Part {
+    pub fn name_key(self) -> &'static str {
+        match self {
+            Self::Tires(tires) => tires.name_key(),
+            Self::LiftKit(lift) => lift.name_key(),
+            Self::Bumper(bumper) => bumper.name_key(),
+        }
+    }
+}
+
+/// Tires, lift and bumper a vehicle is built with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VehicleCustomization {
+    pub tires: TireType,
+    pub lift: LiftKit,
+    pub bumper: Bumper,
+}
+
+impl VehicleCustomization {
+    /// Fits the tires and lift to `config`. The lift lengthens the suspension and carries the body,
+    /// and so the center of mass, higher above the axles.
+    pub fn apply(&self, config: &mut VehicleConfig) {
+        config.wheel_radius *= self.tires.spec().radius_scale;
+        let lift = self.lift.lift();
+        config.suspension_config.rest_length += lift;
+        config.suspension_config.max_length += lift;
+        config.center_of_mass.y += lift * 0.5;
+    }
+
+    pub fn parts(&self) -> [Part; 3] {
+        [Part::Tires(self.tires), Part::LiftKit(self.lift), Part::Bumper(self.bumper)]
+    }
+}
+
+/// Body material replacing the plain `body_color` one, for paint finishes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaterialOverride {
+    pub base_color: Color,
+    pub perceptual_roughness: f32,
+    pub metallic: f32,
+}
+
+impl MaterialOverride {
+    pub fn material(&self) -> StandardMaterial {
+        StandardMaterial {
+            base_color: self.base_color,
+            perceptual_roughness: self.perceptual_roughness,
+            metallic: self.metallic,
+            ..default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lift_raises_suspension_and_center_of_mass() {
+        let stock = VehicleConfig::default();
+        let mut lifted = stock.clone();
+        VehicleCustomization { lift: LiftKit::FourInch, tires: TireType::Crawler, ..default() }.apply(&mut lifted);
+        let suspension = &lifted.suspension_config;
+        assert!((suspension.rest_length - stock.suspension_config.rest_length - 0.1).abs() < 1e-5);
+        assert!((suspension.max_length - stock.suspension_config.max_length - 0.1).abs() < 1e-5);
+        assert!(lifted.center_of_mass.y > stock.center_of_mass.y);
+        assert!(lifted.wheel_radius > stock.wheel_radius);
+
+        let mut untouched = stock.clone();
+        VehicleCustomization::default().apply(&mut untouched);
+        assert_eq!(untouched.suspension_config.rest_length, stock.suspension_config.rest_length);
+        assert_eq!(untouched.wheel_radius, stock.wheel_radius);
+    }
+
+    #[test]
+    fn test_customization_round_trips_through_json() {
+        let customization =
+            VehicleCustomization { tires: TireType::MudTerrain, lift: LiftKit::TwoInch, bumper: Bumper::Winch };
+        let json = serde_json::to_string(&customization).unwrap();
+        assert_eq!(serde_json::from_str::<VehicleCustomization>(&json).unwrap(), customization);
+        assert_eq!(serde_json::from_str::<VehicleCustomization>("{}").unwrap(), VehicleCustomization::default());
+    }
+}
@@ -0,0 +1,361 @@
+use bevy::{
+    pbr::{ExtendedMaterial, MaterialExtension},
+    prelude::*,
+    render::render_resource::{AsBindGroup, ShaderRef, ShaderType},
+};
+
+use super::paint_reflections::PaintReflectionUniform;
+use crate::game::constants::JEEP_HEIGHT;
+use crate::game::plugins::{sample_fluid, FluidKind, FluidVolume, OriginShiftEvent, ShiftOrigin};
+use crate::game::render_available;
+
+/// Vehicle body material: standard PBR with a procedural dirt layer and reflections on top
+pub type VehicleBodyMaterial = ExtendedMaterial<StandardMaterial, VehicleDirtExtension>;
+
+/// How much dirt a vehicle has picked up, drives the [`VehicleDirtExtension`] of its body materials
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct DirtState {
+    /// Mud coverage below the mud line (0.0 - 1.0)
+    pub mud: f32,
+    /// Dust film over the whole body (0.0 - 1.0)
+    pub dust: f32,
+    /// How far up the body mud has splashed, as a fraction of body height
+    pub mud_line: f32,
+    /// Wet sheen left behind by water, dries over time (0.0 - 1.0)
+    pub wetness: f32,
+    last_position: Option<Vec3>,
+}
+
+impl Default for DirtState {
+    fn default() -> Self {
+        Self {
+            mud: 0.0,
+            dust: 0.0,
+            // Even clean trucks get mud on the rockers first
+            mud_line: 0.15,
+            wetness: 0.0,
+            last_position: None,
+        }
+    }
+}
+
+/// Tuning for dirt accumulation and washing
+#[derive(Resource, Clone, Debug)]
+pub struct DirtConfig {
+    /// Mud coverage gained per meter driven through mud
+    pub mud_per_meter: f32,
+    /// Dust coverage gained per meter driven through dust
+    pub dust_per_meter: f32,
+    /// Fraction of dirt washed off per meter driven through water at full depth
+    pub wash_per_meter: f32,
+    /// Wetness lost per second
+    pub drying_rate: f32,
+    /// Mud line rise per meter at high speed, splashing climbs the body
+    pub splash_per_meter: f32,
+    /// Speed in m/s above which splashing reaches its full effect
+    pub splash_speed: f32,
+}
+
+impl Default for DirtConfig {
+    fn default() -> Self {
+        Self {
+            mud_per_meter: 0.02,
+            dust_per_meter: 0.002,
+            wash_per_meter: 0.04,
+            drying_rate: 0.02,
+            splash_per_meter: 0.01,
+            splash_speed: 10.0,
+        }
+    }
+}
+
+/// Area of loose dry ground that coats passing vehicles in dust
+#[derive(Component, Clone, Debug)]
+pub struct DustVolume {
+    /// Half size of the box centred on the entity's transform
+    pub half_extents: Vec3,
+    /// Multiplier on [`DirtConfig::dust_per_meter`]
+    pub intensity: f32,
+}
+
+impl DustVolume {
+    pub fn contains(&self, transform: &GlobalTransform, point: Vec3) -> bool {
+        let local = transform.affine().inverse().transform_point3(point);
+        local.abs().cmple(self.half_extents).all()
+    }
+}
+
+impl DirtState {
+    /// Adds mud after driving `distance` meters through mud `depth` deep at `speed`
+    pub fn drive_through_mud(&mut self, config: &DirtConfig, distance: f32, depth: f32, speed: f32, body_height: f32) {
+        self.mud = (self.mud + config.mud_per_meter * distance).min(1.0);
+
+        // Mud reaches at least the depth driven through, splashing carries it higher with speed
+        let submerged = (depth / body_height).clamp(0.0, 1.0);
+        let splash = config.splash_per_meter * distance * (speed / config.splash_speed).min(1.0);
+        self.mud_line = (self.mud_line + splash).max(submerged).min(1.0);
+    }
+
+    /// Adds dust after driving `distance` meters through a dust volume
+    pub fn drive_through_dust(&mut self, config: &DirtConfig, distance: f32, intensity: f32) {
+        // Dust doesn't stick to a wet body
+        let stick = 1.0 - self.wetness;
+        self.dust = (self.dust + config.dust_per_meter * intensity * stick * distance).min(1.0);
+    }
+
+    /// Washes dirt off after driving `distance` meters through water `depth` deep
+    pub fn drive_through_water(&mut self, config: &DirtConfig, distance: f32, depth: f32, body_height: f32) {
+        let waterline = (depth / body_height).clamp(0.0, 1.0);
+        let wash = (config.wash_per_meter * distance * waterline / self.mud_line.max(0.01)).min(1.0);
+
+        self.mud *= 1.0 - wash;
+        self.dust *= 1.0 - wash;
+        self.wetness = self.wetness.max(waterline.min(1.0) * 2.0).min(1.0);
+        // Fully washing out of the mud also resets how high it had splashed
+        if self.mud < 0.01 {
+            self.mud = 0.0;
+            self.mud_line = DirtState::default().mud_line;
+        }
+    }
+
+    pub fn dry(&mut self, config: &DirtConfig, dt: f32) {
+        self.wetness = (self.wetness - config.drying_rate * dt).max(0.0);
+    }
+
+    /// Removes all dirt, e.g. for a vehicle reset or the garage
+    pub fn clean(&mut self) {
+        *self = Self {
+            last_position: self.last_position,
+            ..default()
+        };
+    }
+}
+
+/// Body materials of a vehicle that show its [`DirtState`]
+#[derive(Component, Debug, Clone, Default)]
+pub struct VehicleDirtMaterials(pub Vec<Handle<VehicleBodyMaterial>>);
+
+/// Dirt layer parameters for the shader
+#[derive(Clone, Copy, Debug, ShaderType)]
+pub struct DirtUniform {
+    pub mud_color: Vec4,
+    pub dust_color: Vec4,
+    /// World position of the bottom of the body
+    pub body_origin: Vec3,
+    pub body_height: f32,
+    /// World up axis of the body
+    pub body_up: Vec3,
+    pub mud: f32,
+    pub dust: f32,
+    pub mud_line: f32,
+    pub wetness: f32,
+    /// Frequency of the breakup noise in UV space
+    pub noise_scale: f32,
+}
+
+impl Default for DirtUniform {
+    fn default() -> Self {
+        Self {
+            mud_color: Vec4::new(0.22, 0.16, 0.1, 1.0),
+            dust_color: Vec4::new(0.62, 0.54, 0.42, 1.0),
+            body_origin: Vec3::ZERO,
+            body_height: JEEP_HEIGHT,
+            body_up: Vec3::Y,
+            mud: 0.0,
+            dust: 0.0,
+            mud_line: 0.0,
+            wetness: 0.0,
+            noise_scale: 24.0,
+        }
+    }
+}
+
+/// Material extension layering mud and dust over the body's PBR material, and reflecting the
+/// surroundings off what paint still shows through
+#[derive(Asset, TypePath, AsBindGroup, Clone, Debug, Default)]
+pub struct VehicleDirtExtension {
+    // Binding 100 keeps clear of the standard material's bindings
+    #[uniform(100)]
+    pub dirt: DirtUniform,
+    #[uniform(101)]
+    pub reflection: PaintReflectionUniform,
+    /// Atlas of the reflection probe's faces, see [`probe_atlas_uv`](super::probe_atlas_uv)
+    #[texture(102)]
+    #[sampler(103)]
+    pub probe: Option<Handle<Image>>,
+    /// Last frame's scene for screen-space reflections
+    #[texture(104)]
+    #[sampler(105)]
+    pub scene_color: Option<Handle<Image>>,
+}
+
+impl MaterialExtension for VehicleDirtExtension {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/vehicle_dirt.wgsl".into()
+    }
+}
+
+/// Builds a body material from a standard PBR material with no dirt yet
+pub fn vehicle_body_material(base: StandardMaterial) -> VehicleBodyMaterial {
+    ExtendedMaterial {
+        base,
+        extension: VehicleDirtExtension::default(),
+    }
+}
+
+/// System that accumulates and washes dirt based on the volumes each vehicle drives through
+pub fn update_dirt_state(
+    time: Res<Time>,
+    config: Res<DirtConfig>,
+    fluids: Query<(&FluidVolume, &GlobalTransform)>,
+    dust: Query<(&DustVolume, &GlobalTransform)>,
+    mut vehicles: Query<(&mut DirtState, &GlobalTransform)>,
+) {
+    let dt = time.delta_seconds();
+    for (mut state, transform) in vehicles.iter_mut() {
+        let position = transform.translation();
+        let distance = state.last_position.map_or(0.0, |last| last.distance(position));
+        state.last_position = Some(position);
+        state.dry(&config, dt);
+
+        if distance <= 0.0 {
+            continue;
+        }
+        let speed = if dt > 0.0 { distance / dt } else { 0.0 };
+        let bottom = position - transform.up() * JEEP_HEIGHT * 0.5;
+
+        match sample_fluid(fluids.iter(), bottom) {
+            Some(sample) if sample.kind == FluidKind::Mud => {
+                state.drive_through_mud(&config, distance, sample.depth, speed, JEEP_HEIGHT);
+            }
+            Some(sample) => {
+                state.drive_through_water(&config, distance, sample.depth, JEEP_HEIGHT);
+            }
+            None => {
+                if let Some((volume, _)) = dust.iter().find(|(volume, t)| volume.contains(t, bottom)) {
+                    state.drive_through_dust(&config, distance, volume.intensity);
+                }
+            }
+        }
+    }
+}
+
+/// System that pushes dirt state and body placement into each vehicle's body materials
+pub fn sync_dirt_materials(
+    vehicles: Query<(&DirtState, &GlobalTransform, &VehicleDirtMaterials)>,
+    mut materials: ResMut<Assets<VehicleBodyMaterial>>,
+) {
+    for (state, transform, handles) in vehicles.iter() {
+        let up = transform.up();
+        let body_origin = transform.translation() - up * JEEP_HEIGHT * 0.5;
+
+        for handle in &handles.0 {
+            let Some(material) = materials.get_mut(handle) else {
+                continue;
+            };
+            let dirt = &mut material.extension.dirt;
+            dirt.body_origin = body_origin;
+            dirt.body_up = up;
+            dirt.body_height = JEEP_HEIGHT;
+            dirt.mud = state.mud;
+            dirt.dust = state.dust;
+            dirt.mud_line = state.mud_line;
+            dirt.wetness = state.wetness;
+        }
+    }
+}
+
+/// Keeps the distance driven continuous when the world origin moves under the vehicle
+fn rebase_dirt_state(mut shifts: EventReader<OriginShiftEvent>, mut vehicles: Query<&mut DirtState>) {
+    for shift in shifts.read() {
+        for mut state in vehicles.iter_mut() {
+            if let Some(last) = state.last_position.as_mut() {
+                *last -= shift.translation();
+            }
+        }
+    }
+}
+
+/// Plugin for the dirt accumulating vehicle body material
+pub struct VehicleDirtPlugin;
+
+impl Plugin for VehicleDirtPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DirtConfig>()
+            .add_event::<OriginShiftEvent>()
+            .add_systems(Update, update_dirt_state)
+            .add_systems(PostUpdate, rebase_dirt_state.after(ShiftOrigin));
+
+        if render_available(app) {
+            app.add_plugins(MaterialPlugin::<VehicleBodyMaterial>::default())
+                .add_systems(Update, sync_dirt_materials.after(update_dirt_state));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mud_accumulates_with_distance() {
+        let config = DirtConfig::default();
+        let mut state = DirtState::default();
+        state.drive_through_mud(&config, 10.0, 0.2, 2.0, 1.75);
+        assert!((state.mud - 0.2).abs() < 1e-5);
+        state.drive_through_mud(&config, 1000.0, 0.2, 2.0, 1.75);
+        assert_eq!(state.mud, 1.0);
+    }
+
+    #[test]
+    fn test_mud_line_follows_depth_and_speed() {
+        let config = DirtConfig::default();
+        let mut slow = DirtState::default();
+        slow.drive_through_mud(&config, 50.0, 0.7, 1.0, 1.75);
+        assert!((slow.mud_line - 0.4).abs() < 1e-5);
+
+        let mut fast = DirtState::default();
+        fast.drive_through_mud(&config, 50.0, 0.7, 20.0, 1.75);
+        assert!(fast.mud_line > slow.mud_line);
+    }
+
+    #[test]
+    fn test_water_washes_mud_off() {
+        let config = DirtConfig::default();
+        let mut state = DirtState::default();
+        state.drive_through_mud(&config, 50.0, 0.3, 5.0, 1.75);
+        let muddy = state.mud;
+
+        state.drive_through_water(&config, 5.0, 0.5, 1.75);
+        assert!(state.mud < muddy);
+        assert!(state.wetness > 0.0);
+
+        // Deep water washes faster than a shallow splash
+        let mut shallow = DirtState { mud: muddy, ..state.clone() };
+        let mut deep = shallow.clone();
+        shallow.drive_through_water(&config, 5.0, 0.1, 1.75);
+        deep.drive_through_water(&config, 5.0, 1.0, 1.75);
+        assert!(deep.mud < shallow.mud);
+    }
+
+    #[test]
+    fn test_wet_body_resists_dust() {
+        let config = DirtConfig::default();
+        let mut dry = DirtState::default();
+        let mut wet = DirtState { wetness: 0.8, ..default() };
+        dry.drive_through_dust(&config, 100.0, 1.0);
+        wet.drive_through_dust(&config, 100.0, 1.0);
+        assert!(wet.dust < dry.dust);
+
+        wet.dry(&config, 100.0);
+        assert_eq!(wet.wetness, 0.0);
+    }
+
+    #[test]
+    fn test_dust_volume_contains() {
+        let volume = DustVolume { half_extents: Vec3::new(5.0, 1.0, 5.0), intensity: 1.0 };
+        let transform = GlobalTransform::from_translation(Vec3::new(10.0, 0.0, 0.0));
+        assert!(volume.contains(&transform, Vec3::new(12.0, 0.5, -3.0)));
+        assert!(!volume.contains(&transform, Vec3::new(0.0, 0.0, 0.0)));
+    }
+}
@@ -0,0 +1,244 @@
+use bevy::prelude::*;
+use std::time::Duration;
+
+use super::{CockpitLayout, Steering, Vehicle};
+use crate::game::plugins::ImpactEvent;
+use crate::game::GameSettings;
+
+/// Driver model, with its animations in the order of [`DriverPose`]
+pub const DRIVER_MODEL: &str = "models/driver.glb";
+/// Head above the seat cushion, the model's origin sits on the cushion
+const SEATED_HEAD_HEIGHT: f32 = 0.75;
+/// Impacts at least this hard (0.0 - 1.0) make the driver brace
+const BRACE_INTENSITY: f32 = 0.4;
+/// Seconds the driver stays braced after an impact
+const BRACE_DURATION: f32 = 0.8;
+/// Steering below this share of full lock leaves the driver idle
+const STEER_DEAD_ZONE: f32 = 0.05;
+/// Crossfade between poses
+const POSE_TRANSITION: Duration = Duration::from_millis(250);
+
+/// What the driver is doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DriverPose {
+    #[default]
+    Idle,
+    /// Hands on the wheel, the clip is scrubbed from full left lock at its start to full right at its end
+    Steer,
+    /// Braced against the wheel after a hard hit
+    Brace,
+}
+
+impl DriverPose {
+    pub const ALL: [DriverPose; 3] = [DriverPose::Idle, DriverPose::Steer, DriverPose::Brace];
+}
+
+/// Driver pose for `steering` (-1.0 full right to 1.0 full left) with `brace` seconds of bracing left
+pub fn driver_pose(steering: f32, brace: f32) -> DriverPose {
+    if brace > 0.0 {
+        DriverPose::Brace
+    } else if steering.abs() > STEER_DEAD_ZONE {
+        DriverPose::Steer
+    } else {
+        DriverPose::Idle
+    }
+}
+
+/// Time in the steer clip matching `steering` (-1.0 full right to 1.0 full left)
+pub fn steer_clip_time(steering: f32, duration: f32) -> f32 {
+    (0.5 - steering.clamp(-1.0, 1.0) * 0.5) * duration
+}
+
+/// Driver scene and its animation clips
+#[derive(Resource, Debug, Clone)]
+pub struct DriverAssets {
+    pub scene: Handle<Scene>,
+    /// Indexed like [`DriverPose::ALL`]
+    pub clips: [Handle<AnimationClip>; 3],
+}
+
+impl DriverAssets {
+    pub fn clip(&self, pose: DriverPose) -> &Handle<AnimationClip> {
+        &self.clips[pose as usize]
+    }
+}
+
+/// The driver in a vehicle's seat
+#[derive(Component, Debug, Clone)]
+pub struct Driver {
+    pub vehicle: Entity,
+    pub pose: DriverPose,
+    /// Seconds of bracing left
+    pub brace: f32,
+}
+
+/// Links the animation player inside a driver's scene back to the driver
+#[derive(Component, Debug, Clone, Copy)]
+pub struct DriverAnimator {
+    pub driver: Entity,
+    /// Pose the player is showing
+    playing: Option<DriverPose>,
+}
+
+/// Marks vehicles that have been given a driver
+#[derive(Component)]
+pub struct DriverAttached;
+
+fn load_driver_assets(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(DriverAssets {
+        scene: asset_server.load(format!("{DRIVER_MODEL}#Scene0")),
+        clips: std::array::from_fn(|index| asset_server.load(format!("{DRIVER_MODEL}#Animation{index}"))),
+    });
+}
+
+/// Seats a driver in each vehicle, head at the cockpit camera's eye point so the cockpit view looks out
+/// over the driver's own arms
+pub fn attach_drivers(
+    mut commands: Commands,
+    assets: Res<DriverAssets>,
+    settings: Option<Res<GameSettings>>,
+    vehicles: Query<(Entity, &Vehicle), Without<DriverAttached>>,
+) {
+    let visibility = driver_visibility(settings.as_deref());
+    for (vehicle_entity, vehicle) in vehicles.iter() {
+        let seat = CockpitLayout::for_config(&vehicle.config).eye - Vec3::Y * SEATED_HEAD_HEIGHT;
+        let driver = commands
+            .spawn((
+                SceneBundle {
+                    scene: assets.scene.clone(),
+                    transform: Transform::from_translation(seat),
+                    visibility,
+                    ..default()
+                },
+                Driver { vehicle: vehicle_entity, pose: DriverPose::Idle, brace: 0.0 },
+                Name::new("Driver"),
+            ))
+            .id();
+        commands.entity(vehicle_entity).add_child(driver).insert(DriverAttached);
+    }
+}
+
+/// Finds the animation player once a driver's scene has been spawned
+pub fn find_driver_animators(
+    mut commands: Commands,
+    players: Query<Entity, Added<AnimationPlayer>>,
+    parents: Query<&Parent>,
+    drivers: Query<(), With<Driver>>,
+) {
+    for player in players.iter() {
+        if let Some(driver) = parents.iter_ancestors(player).find(|ancestor| drivers.contains(*ancestor)) {
+            commands.entity(player).insert(DriverAnimator { driver, playing: None });
+        }
+    }
+}
+
+/// Braces drivers whose vehicle is hit hard
+pub fn brace_drivers(time: Res<Time>, mut impacts: EventReader<ImpactEvent>, mut drivers: Query<&mut Driver>) {
+    let impacts: Vec<ImpactEvent> =
+        impacts.read().filter(|impact| impact.intensity >= BRACE_INTENSITY).copied().collect();
+    for mut driver in drivers.iter_mut() {
+        let vehicle = driver.vehicle;
+        if impacts.iter().any(|impact| impact.involves(vehicle)) {
+            driver.brace = BRACE_DURATION;
+        } else if driver.brace > 0.0 {
+            driver.brace = (driver.brace - time.delta_seconds()).max(0.0);
+        }
+    }
+}
+
+/// Picks each driver's pose and plays it, scrubbing the steer clip with the steering
+pub fn animate_drivers(
+    assets: Res<DriverAssets>,
+    clips: Res<Assets<AnimationClip>>,
+    vehicles: Query<(&Vehicle, &Steering)>,
+    mut drivers: Query<&mut Driver>,
+    mut players: Query<(&mut AnimationPlayer, &mut DriverAnimator)>,
+) {
+    for (mut player, mut animator) in players.iter_mut() {
+        let Ok(mut driver) = drivers.get_mut(animator.driver) else {
+            continue;
+        };
+        let Ok((vehicle, steering)) = vehicles.get(driver.vehicle) else {
+            continue;
+        };
+        let steering = steering.angle / vehicle.config.max_steering_angle.max(1e-3);
+        driver.pose = driver_pose(steering, driver.brace);
+
+        if animator.playing != Some(driver.pose) {
+            player.play_with_transition(assets.clip(driver.pose).clone(), POSE_TRANSITION);
+            match driver.pose {
+                // Held still and moved by hand below
+                DriverPose::Steer => player.set_speed(0.0),
+                DriverPose::Idle => player.set_speed(1.0).repeat(),
+                DriverPose::Brace => player.set_speed(1.0),
+            };
+            animator.playing = Some(driver.pose);
+        }
+        if driver.pose == DriverPose::Steer {
+            if let Some(clip) = clips.get(assets.clip(DriverPose::Steer)) {
+                player.seek_to(steer_clip_time(steering, clip.duration()));
+            }
+        }
+    }
+}
+
+fn driver_visibility(settings: Option<&GameSettings>) -> Visibility {
+    if settings.map_or(true, |settings| settings.graphics.show_driver) {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    }
+}
+
+/// Shows or hides the drivers when the setting changes
+pub fn apply_driver_visibility(settings: Option<Res<GameSettings>>, mut drivers: Query<&mut Visibility, With<Driver>>) {
+    let Some(settings) = settings.filter(|settings| settings.is_changed()) else {
+        return;
+    };
+    let visibility = driver_visibility(Some(&settings));
+    for mut driver in drivers.iter_mut() {
+        if *driver != visibility {
+            *driver = visibility;
+        }
+    }
+}
+
+/// Plugin for the animated driver in every vehicle
+pub struct DriverPlugin;
+
+impl Plugin for DriverPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ImpactEvent>()
+            .add_systems(Startup, load_driver_assets)
+            .add_systems(Update, (
+                attach_drivers,
+                find_driver_animators,
+                brace_drivers,
+                animate_drivers,
+                apply_driver_visibility,
+            ).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pose_follows_steering_and_impacts() {
+        assert_eq!(driver_pose(0.02, 0.0), DriverPose::Idle);
+        assert_eq!(driver_pose(-0.5, 0.0), DriverPose::Steer);
+        assert_eq!(driver_pose(0.0, 0.3), DriverPose::Brace);
+        // Clips are looked up by the pose's index
+        for (index, pose) in DriverPose::ALL.into_iter().enumerate() {
+            assert_eq!(pose as usize, index);
+        }
+    }
+
+    #[test]
+    fn test_steer_clip_scrubs_left_to_right() {
+        assert_eq!(steer_clip_time(1.0, 2.0), 0.0);
+        assert_eq!(steer_clip_time(0.0, 2.0), 1.0);
+        assert_eq!(steer_clip_time(-3.0, 2.0), 2.0);
+    }
+}
@@ -0,0 +1,423 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    apply_driver_assists, apply_engine_cutoff, apply_thermal_power_loss, is_driven, update_wheel_physics, Brakes,
+    DriveType, DrivetrainConfig, Engine, EngineStallReason, EngineStalledEvent, EngineTemperature,
+    EngineThermalConfig, FuelTank, Transmission, Vehicle, Wheel,
+};
+use crate::game::{GameSettings, TransmissionMode};
+
+/// Range selected on the transfer case
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TransferCase {
+    /// Rear wheels only
+    #[default]
+    TwoHigh,
+    /// All four wheels at road gearing
+    FourHigh,
+    /// All four wheels through the reduction gear, for crawling and steep climbs
+    FourLow,
+}
+
+impl TransferCase {
+    /// Next range on the lever, 2H → 4H → 4L → 2H
+    pub fn next(self) -> Self {
+        match self {
+            Self::TwoHigh => Self::FourHigh,
+            Self::FourHigh => Self::FourLow,
+            Self::FourLow => Self::TwoHigh,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::TwoHigh => "2H",
+            Self::FourHigh => "4H",
+            Self::FourLow => "4L",
+        }
+    }
+
+    pub fn four_wheel_drive(self) -> bool {
+        self != Self::TwoHigh
+    }
+}
+
+/// Ignition, engine and transfer case state of a vehicle
+#[derive(Component, Debug, Clone)]
+pub struct Drivetrain {
+    pub transfer_case: TransferCase,
+    /// Key turned on
+    pub ignition: bool,
+    /// Engine turning under its own power
+    pub running: bool,
+    /// Seconds of cranking left before the engine catches
+    pub cranking: Option<f32>,
+    /// Seconds the engine has been dragged below its stall speed
+    pub lugging: f32,
+}
+
+impl Default for Drivetrain {
+    fn default() -> Self {
+        Self {
+            transfer_case: TransferCase::default(),
+            ignition: true,
+            running: true,
+            cranking: None,
+            lugging: 0.0,
+        }
+    }
+}
+
+impl Drivetrain {
+    /// Switches the engine off, or cranks it when it is off and `can_start`
+    pub fn toggle_ignition(&mut self, config: &EngineConfig, can_start: bool) {
+        if self.running || self.cranking.is_some() {
+            self.ignition = false;
+            self.running = false;
+            self.cranking = None;
+            self.lugging = 0.0;
+        } else {
+            self.ignition = true;
+            if can_start {
+                self.cranking = Some(config.crank_time);
+            }
+        }
+    }
+
+    /// Stops the engine with the key left on, for overheating, a flooded intake or lugging
+    pub fn stall(&mut self) {
+        self.running = false;
+        self.cranking = None;
+        self.lugging = 0.0;
+    }
+
+    /// Moves the transfer case lever to the next range. Only four wheel drive vehicles have one,
+    /// and low range only engages or disengages near standstill. Returns whether it shifted.
+    pub fn shift_transfer_case(&mut self, config: &EngineConfig, drive_type: DriveType, speed: f32) -> bool {
+        if !matches!(drive_type, DriveType::FourWD) {
+            return false;
+        }
+        let next = self.transfer_case.next();
+        let low_range_change = self.transfer_case == TransferCase::FourLow || next == TransferCase::FourLow;
+        if low_range_change && speed.abs() > config.low_range_shift_speed {
+            return false;
+        }
+        self.transfer_case = next;
+        true
+    }
+
+    /// Advances cranking and lugging and returns the engine RPM, plus whether the engine stalled
+    /// this frame. `coupled_rpm` is the RPM the driven wheels would turn the engine at in `gear`.
+    pub fn update_engine(
+        &mut self,
+        config: &EngineConfig,
+        gear: i32,
+        coupled_rpm: f32,
+        throttle: f32,
+        dt: f32,
+    ) -> (f32, bool) {
+        if let Some(left) = self.cranking {
+            let left = left - dt;
+            if left > 0.0 {
+                self.cranking = Some(left);
+                return (config.cranking_rpm, false);
+            }
+            self.cranking = None;
+            self.running = true;
+        }
+        if !self.running {
+            self.lugging = 0.0;
+            return (0.0, false);
+        }
+
+        let free_rpm = config.idle_rpm + (config.launch_rpm - config.idle_rpm) * throttle.clamp(0.0, 1.0);
+        if gear == 0 {
+            self.lugging = 0.0;
+            return (free_rpm, false);
+        }
+
+        // A torque converter or a slipping clutch in first and reverse keep the engine turning,
+        // only a manual box in a higher gear can be dragged down to a stall
+        let slipping = config.transmission == TransmissionMode::Automatic || gear == -1 || gear == 1;
+        if !slipping && coupled_rpm < config.stall_rpm {
+            self.lugging += dt;
+            if self.lugging >= config.lug_time {
+                self.stall();
+                return (0.0, true);
+            }
+        } else {
+            self.lugging = 0.0;
+        }
+
+        let rpm = if slipping { coupled_rpm.max(free_rpm) } else { coupled_rpm.max(config.stall_rpm) };
+        (rpm.min(config.redline_rpm), false)
+    }
+}
+
+/// Tuning for the engine, ignition and transfer case
+#[derive(Resource, Clone, Debug)]
+pub struct EngineConfig {
+    /// Follows the control settings
+    pub transmission: TransmissionMode,
+    pub idle_rpm: f32,
+    /// RPM the engine spins at while cranking
+    pub cranking_rpm: f32,
+    /// RPM a slipping clutch or torque converter lets the engine rev to at full throttle
+    pub launch_rpm: f32,
+    /// RPM below which an engine lugging in gear dies
+    pub stall_rpm: f32,
+    pub redline_rpm: f32,
+    /// Seconds of lugging below the stall RPM before the engine dies
+    pub lug_time: f32,
+    /// Seconds of cranking before the engine catches
+    pub crank_time: f32,
+    /// Fastest speed low range engages or disengages at, in m/s
+    pub low_range_shift_speed: f32,
+    /// Share of torque an open differential still sends to an axle with a wheel in the air
+    pub open_diff_share: f32,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            transmission: TransmissionMode::default(),
+            idle_rpm: 800.0,
+            cranking_rpm: 250.0,
+            launch_rpm: 2500.0,
+            stall_rpm: 500.0,
+            redline_rpm: 6000.0,
+            lug_time: 0.6,
+            crank_time: 0.8,
+            low_range_shift_speed: 2.0,
+            open_diff_share: 0.15,
+        }
+    }
+}
+
+/// Ratio of `gear`, -1 for reverse and 0 for neutral
+pub fn gear_ratio(config: &DrivetrainConfig, gear: i32) -> f32 {
+    usize::try_from(gear + 1)
+        .ok()
+        .and_then(|index| config.gear_ratios.get(index))
+        .copied()
+        .unwrap_or(0.0)
+}
+
+/// Ratio from crankshaft to wheels through gearbox, transfer case and axles
+pub fn overall_ratio(config: &DrivetrainConfig, gear: i32, transfer_case: TransferCase) -> f32 {
+    let transfer = if transfer_case == TransferCase::FourLow { config.low_range_ratio } else { 1.0 };
+    gear_ratio(config, gear) * config.final_drive_ratio * transfer
+}
+
+/// Whether the wheel at `position` gets engine torque, the front axle of a four wheel drive
+/// vehicle only with the transfer case in 4H or 4L
+pub fn is_powered(drive_type: DriveType, transfer_case: TransferCase, position: usize) -> bool {
+    match drive_type {
+        DriveType::FourWD => position >= 2 || transfer_case.four_wheel_drive(),
+        _ => is_driven(drive_type, position),
+    }
+}
+
+/// Engine torque in Nm for `throttle` at `rpm`, none past the redline
+pub fn engine_torque(config: &DrivetrainConfig, engine: &EngineConfig, throttle: f32, rpm: f32) -> f32 {
+    if rpm >= engine.redline_rpm {
+        return 0.0;
+    }
+    config.max_engine_torque * throttle.clamp(0.0, 1.0)
+}
+
+/// Turns the ignition on or off
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ToggleIgnitionEvent {
+    pub vehicle: Entity,
+}
+
+/// Moves the transfer case lever to the next range
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ShiftTransferCaseEvent {
+    pub vehicle: Entity,
+}
+
+/// Keeps [`EngineConfig::transmission`] in sync with the control settings
+fn sync_transmission_setting(game_settings: Res<GameSettings>, mut config: ResMut<EngineConfig>) {
+    let transmission = game_settings.controls.transmission;
+    if game_settings.is_changed() && config.transmission != transmission {
+        config.transmission = transmission;
+    }
+}
+
+/// Applies ignition and transfer case requests
+pub fn handle_drivetrain_controls(
+    config: Res<EngineConfig>,
+    thermal_config: Res<EngineThermalConfig>,
+    mut ignition_events: EventReader<ToggleIgnitionEvent>,
+    mut shift_events: EventReader<ShiftTransferCaseEvent>,
+    mut vehicles: Query<(&Vehicle, &mut Drivetrain, Option<&EngineTemperature>, Option<&FuelTank>)>,
+) {
+    for event in ignition_events.read() {
+        if let Ok((_, mut drivetrain, engine, tank)) = vehicles.get_mut(event.vehicle) {
+            let can_start = engine.map_or(true, |engine| engine.can_start(&thermal_config))
+                && !tank.is_some_and(FuelTank::is_empty);
+            drivetrain.toggle_ignition(&config, can_start);
+        }
+    }
+    for event in shift_events.read() {
+        if let Ok((vehicle, mut drivetrain, _, _)) = vehicles.get_mut(event.vehicle) {
+            let drive_type = vehicle.config.drivetrain_config.drive_type;
+            drivetrain.shift_transfer_case(&config, drive_type, vehicle.vehicle_speed);
+        }
+    }
+}
+
+/// Runs the engine from the driven wheels and sends its torque through the gearbox, transfer case
+/// and differentials to the wheels
+pub fn update_drivetrain(
+    time: Res<Time>,
+    config: Res<EngineConfig>,
+    mut vehicles: Query<(Entity, &Vehicle, &mut Drivetrain, &mut Engine, &Transmission, &Brakes)>,
+    mut wheels: Query<&mut Wheel>,
+    mut stalled_events: EventWriter<EngineStalledEvent>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, vehicle, mut drivetrain, mut engine, transmission, brakes) in vehicles.iter_mut() {
+        let drivetrain_config = &vehicle.config.drivetrain_config;
+        let drive_type = drivetrain_config.drive_type;
+        let transfer_case = drivetrain.transfer_case;
+        let ratio = overall_ratio(drivetrain_config, transmission.gear, transfer_case);
+
+        let powered = |wheel: &Wheel| is_powered(drive_type, transfer_case, wheel.position);
+        let (spin, count) = wheels
+            .iter_many(vehicle.wheel_entities)
+            .filter(|wheel| powered(wheel))
+            .fold((0.0, 0), |(sum, count), wheel| (sum + wheel.angular_velocity, count + 1));
+        let coupled_rpm = if count > 0 { (spin / count as f32 * ratio).abs() * 60.0 / TAU } else { 0.0 };
+
+        if !drivetrain.running {
+            engine.throttle = 0.0;
+        }
+        let (rpm, stalled) = drivetrain.update_engine(&config, transmission.gear, coupled_rpm, engine.throttle, dt);
+        engine.rpm = rpm;
+        if stalled {
+            engine.throttle = 0.0;
+            stalled_events.send(EngineStalledEvent { vehicle: entity, reason: EngineStallReason::Lugged });
+        }
+
+        let torque = engine_torque(&vehicle.config.drivetrain_config, &config, engine.throttle, rpm) * ratio;
+        // An open differential sends the torque wherever turning is easiest, so a wheel in the air
+        // spins and its axle barely pulls. Locked, both wheels of an axle always get their share.
+        let axle_share = |axle: usize| {
+            let airborne = wheels
+                .iter_many(&vehicle.wheel_entities[axle * 2..axle * 2 + 2])
+                .any(|wheel| !wheel.ground_contact);
+            if airborne && !vehicle.diff_locked { config.open_diff_share } else { 1.0 }
+        };
+        let shares = [axle_share(0), axle_share(1)];
+
+        let max_brake = vehicle.config.drivetrain_config.max_brake_torque;
+        for &wheel_entity in &vehicle.wheel_entities {
+            let Ok(mut wheel) = wheels.get_mut(wheel_entity) else { continue };
+            wheel.drive_torque = if count > 0 && is_powered(drive_type, transfer_case, wheel.position) {
+                torque / count as f32 * shares[wheel.position / 2]
+            } else {
+                0.0
+            };
+            let handbrake = brakes.handbrake && wheel.position >= 2;
+            wheel.brake_torque = if handbrake { max_brake } else { brakes.brake.clamp(0.0, 1.0) * max_brake };
+        }
+    }
+}
+
+/// Plugin for ignition, engine stalls and the transfer case
+pub struct DrivetrainPlugin;
+
+impl Plugin for DrivetrainPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EngineConfig>()
+            .init_resource::<EngineThermalConfig>()
+            .add_event::<ToggleIgnitionEvent>()
+            .add_event::<ShiftTransferCaseEvent>()
+            .add_event::<EngineStalledEvent>()
+            .add_systems(Update, (
+                sync_transmission_setting.run_if(resource_exists::<GameSettings>()),
+                handle_drivetrain_controls,
+                update_drivetrain,
+            ).chain()
+                .after(apply_thermal_power_loss)
+                .after(apply_engine_cutoff)
+                .after(apply_driver_assists)
+                .before(update_wheel_physics));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manual() -> EngineConfig {
+        EngineConfig { transmission: TransmissionMode::Manual, ..default() }
+    }
+
+    #[test]
+    fn test_low_range_multiplies_ratio() {
+        let config = DrivetrainConfig::default();
+        let high = overall_ratio(&config, 1, TransferCase::FourHigh);
+        let low = overall_ratio(&config, 1, TransferCase::FourLow);
+        assert!((low / high - config.low_range_ratio).abs() < 1e-5);
+        assert_eq!(overall_ratio(&config, 0, TransferCase::FourLow), 0.0);
+        assert!(overall_ratio(&config, -1, TransferCase::TwoHigh) < 0.0);
+    }
+
+    #[test]
+    fn test_transfer_case_shifts_into_low_only_when_slow() {
+        let config = EngineConfig::default();
+        let mut drivetrain = Drivetrain { transfer_case: TransferCase::FourHigh, ..default() };
+        assert!(!drivetrain.shift_transfer_case(&config, DriveType::FourWD, 10.0));
+        assert_eq!(drivetrain.transfer_case, TransferCase::FourHigh);
+        assert!(drivetrain.shift_transfer_case(&config, DriveType::FourWD, 0.5));
+        assert_eq!(drivetrain.transfer_case, TransferCase::FourLow);
+
+        let mut two_wheel = Drivetrain::default();
+        assert!(!two_wheel.shift_transfer_case(&config, DriveType::RearWD, 0.0));
+        assert!(!is_powered(DriveType::FourWD, TransferCase::TwoHigh, 0));
+        assert!(is_powered(DriveType::FourWD, TransferCase::FourLow, 0));
+    }
+
+    #[test]
+    fn test_lugging_in_high_gear_stalls_manual() {
+        let config = manual();
+        let mut drivetrain = Drivetrain::default();
+        let (_, stalled) = drivetrain.update_engine(&config, 4, 200.0, 0.5, 0.5);
+        assert!(!stalled && drivetrain.running);
+        let (rpm, stalled) = drivetrain.update_engine(&config, 4, 200.0, 0.5, 0.5);
+        assert!(stalled && !drivetrain.running);
+        assert_eq!(rpm, 0.0);
+
+        // First gear slips the clutch, automatics never lug
+        let mut drivetrain = Drivetrain::default();
+        assert!(!drivetrain.update_engine(&config, 1, 0.0, 0.5, 1.0).1);
+        assert!(!drivetrain.update_engine(&EngineConfig::default(), 4, 0.0, 0.5, 1.0).1);
+        assert!(drivetrain.running);
+    }
+
+    #[test]
+    fn test_ignition_cranks_then_runs() {
+        let config = EngineConfig::default();
+        let mut drivetrain = Drivetrain::default();
+        drivetrain.toggle_ignition(&config, true);
+        assert!(!drivetrain.ignition && !drivetrain.running);
+        assert_eq!(drivetrain.update_engine(&config, 0, 0.0, 1.0, 0.1).0, 0.0);
+
+        drivetrain.toggle_ignition(&config, false);
+        assert!(drivetrain.ignition && drivetrain.cranking.is_none());
+        drivetrain.toggle_ignition(&config, true);
+        assert!(drivetrain.cranking.is_some());
+        drivetrain.update_engine(&config, 0, 0.0, 0.0, config.crank_time * 0.5);
+        assert!(!drivetrain.running);
+        let (rpm, _) = drivetrain.update_engine(&config, 0, 0.0, 0.0, config.crank_time);
+        assert!(drivetrain.running);
+        assert_eq!(rpm, config.idle_rpm);
+    }
+}
@@ -0,0 +1,349 @@
+use bevy::prelude::*;
+
+use super::{CargoItem, DriveType, Drivetrain, Engine, Vehicle};
+use crate::game::{is_open, GameSettings, PlayerId, PlayerInput, ScheduleOpen};
+
+/// Fuel tank of a vehicle
+#[derive(Component, Debug, Clone)]
+pub struct FuelTank {
+    /// Capacity in liters
+    pub capacity: f32,
+    /// Current contents in liters
+    pub level: f32,
+}
+
+impl Default for FuelTank {
+    fn default() -> Self {
+        // Jeep TJ tank
+        Self {
+            capacity: 72.0,
+            level: 72.0,
+        }
+    }
+}
+
+impl FuelTank {
+    pub fn fraction(&self) -> f32 {
+        if self.capacity > 0.0 {
+            (self.level / self.capacity).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.level <= 0.0
+    }
+
+    /// Burns up to `liters`, returns true if the tank ran dry
+    pub fn burn(&mut self, liters: f32) -> bool {
+        let was_empty = self.is_empty();
+        self.level = (self.level - liters).max(0.0);
+        !was_empty && self.is_empty()
+    }
+
+    /// Adds up to `liters`, returns the amount that actually fit
+    pub fn fill(&mut self, liters: f32) -> f32 {
+        let added = liters.min(self.capacity - self.level).max(0.0);
+        self.level += added;
+        added
+    }
+}
+
+/// Tuning for fuel consumption and refueling
+#[derive(Resource, Clone, Debug)]
+pub struct FuelConfig {
+    /// Whether vehicles burn fuel at all, follows the gameplay settings
+    pub enabled: bool,
+    /// Consumption at idle in liters per hour
+    pub idle_consumption: f32,
+    /// Consumption at full throttle and redline in liters per hour
+    pub full_load_consumption: f32,
+    /// RPM at which full load consumption is reached
+    pub redline_rpm: f32,
+    /// Pump flow at gas stations in liters per second
+    pub pump_rate: f32,
+    /// Pour rate from a fuel can in liters per second
+    pub can_pour_rate: f32,
+    /// How close a fuel can has to be to pour it, in meters
+    pub can_reach: f32,
+}
+
+impl Default for FuelConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            idle_consumption: 1.2,
+            full_load_consumption: 38.0,
+            redline_rpm: 6000.0,
+            pump_rate: 2.0,
+            can_pour_rate: 0.5,
+            can_reach: 2.5,
+        }
+    }
+}
+
+/// Drivetrain loss multiplier on consumption, more driven axles means more friction to turn
+pub fn drivetrain_loss(drive_type: DriveType) -> f32 {
+    match drive_type {
+        DriveType::RearWD | DriveType::FrontWD => 1.0,
+        DriveType::FourWD => 1.12,
+    }
+}
+
+/// Fuel burned in liters per second for the given throttle (0.0 - 1.0) and engine RPM
+pub fn fuel_consumption(config: &FuelConfig, throttle: f32, rpm: f32, drive_type: DriveType) -> f32 {
+    let load = throttle.clamp(0.0, 1.0) * (rpm / config.redline_rpm).clamp(0.0, 1.0);
+    let per_hour = config.idle_consumption + (config.full_load_consumption - config.idle_consumption) * load;
+    per_hour * drivetrain_loss(drive_type) / 3600.0
+}
+
+/// Gas station point of interest that refuels vehicles parked next to it
+#[derive(Component, Debug, Clone)]
+pub struct GasStation {
+    /// Radius around the pumps in meters
+    pub radius: f32,
+}
+
+impl Default for GasStation {
+    fn default() -> Self {
+        Self { radius: 5.0 }
+    }
+}
+
+/// Fuel carried in a jerry can, pair with a [`CargoItem`] of kind fuel can
+#[derive(Component, Debug, Clone)]
+pub struct FuelCan {
+    /// Contents in liters
+    pub liters: f32,
+}
+
+impl Default for FuelCan {
+    fn default() -> Self {
+        Self { liters: 20.0 }
+    }
+}
+
+/// A vehicle's tank ran dry and the engine cut out
+#[derive(Event, Debug, Clone, Copy)]
+pub struct OutOfFuelEvent {
+    pub vehicle: Entity,
+}
+
+/// Where fuel came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FuelSource {
+    Station(Entity),
+    Can(Entity),
+}
+
+/// Fuel was added to a vehicle's tank
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RefuelEvent {
+    pub vehicle: Entity,
+    pub source: FuelSource,
+    pub liters: f32,
+}
+
+/// Keeps [`FuelConfig::enabled`] in sync with the gameplay settings
+fn sync_fuel_setting(game_settings: Res<GameSettings>, mut config: ResMut<FuelConfig>) {
+    let enabled = game_settings.gameplay.fuel_enabled();
+    if game_settings.is_changed() && config.enabled != enabled {
+        config.enabled = enabled;
+    }
+}
+
+/// Burns fuel from throttle and RPM while the engine runs
+pub fn consume_fuel(
+    time: Res<Time>,
+    config: Res<FuelConfig>,
+    mut vehicles: Query<(Entity, &Vehicle, &Engine, &Drivetrain, &mut FuelTank)>,
+    mut out_of_fuel: EventWriter<OutOfFuelEvent>,
+) {
+    if !config.enabled {
+        return;
+    }
+    let dt = time.delta_seconds();
+    for (entity, vehicle, engine, drivetrain, mut tank) in vehicles.iter_mut() {
+        if !drivetrain.running {
+            continue;
+        }
+        let drive_type = vehicle.config.drivetrain_config.drive_type;
+        let burned = fuel_consumption(&config, engine.throttle, engine.rpm, drive_type) * dt;
+        if tank.burn(burned) {
+            out_of_fuel.send(OutOfFuelEvent { vehicle: entity });
+        }
+    }
+}
+
+/// Cuts the engine of vehicles with an empty tank
+pub fn apply_engine_cutoff(config: Res<FuelConfig>, mut engines: Query<(&mut Engine, &FuelTank)>) {
+    if !config.enabled {
+        return;
+    }
+    for (mut engine, tank) in engines.iter_mut() {
+        if tank.is_empty() {
+            engine.throttle = 0.0;
+            engine.rpm = 0.0;
+        }
+    }
+}
+
+/// Refuels each player's vehicle while they hold their refuel binding, from an open gas station or the nearest
+/// fuel can within reach
+pub fn handle_refueling(
+    time: Res<Time>,
+    config: Res<FuelConfig>,
+    stations: Query<(Entity, &GasStation, &GlobalTransform, Option<&ScheduleOpen>)>,
+    mut cans: Query<(Entity, &mut FuelCan, &GlobalTransform, Option<&CargoItem>)>,
+    mut vehicles: Query<(Entity, &mut FuelTank, &GlobalTransform, &PlayerInput), With<PlayerId>>,
+    mut refuel_events: EventWriter<RefuelEvent>,
+) {
+    let dt = time.delta_seconds();
+
+    for (vehicle, mut tank, transform, input) in vehicles.iter_mut() {
+        if !input.refuel {
+            continue;
+        }
+        let position = transform.translation();
+
+        let station = stations
+            .iter()
+            .filter(|(_, _, _, open)| is_open(*open))
+            .find(|(_, station, t, _)| t.translation().distance(position) <= station.radius)
+            .map(|(entity, _, _, _)| entity);
+        if let Some(station) = station {
+            let liters = tank.fill(config.pump_rate * dt);
+            if liters > 0.0 {
+                refuel_events.send(RefuelEvent { vehicle, source: FuelSource::Station(station), liters });
+            }
+            continue;
+        }
+
+        // Cans in this vehicle's bed come first, then anything lying within reach
+        let can = cans
+            .iter_mut()
+            .filter(|(_, can, t, item)| {
+                can.liters > 0.0
+                    && (item.is_some_and(|item| item.in_bed_of == Some(vehicle))
+                        || t.translation().distance(position) <= config.can_reach)
+            })
+            .min_by(|a, b| {
+                let outside_bed = |item: Option<&CargoItem>| !item.is_some_and(|item| item.in_bed_of == Some(vehicle));
+                outside_bed(a.3).cmp(&outside_bed(b.3)).then(
+                    a.2.translation().distance(position).total_cmp(&b.2.translation().distance(position)),
+                )
+            });
+        if let Some((entity, mut can, _, _)) = can {
+            let liters = tank.fill((config.can_pour_rate * dt).min(can.liters));
+            can.liters -= liters;
+            if liters > 0.0 {
+                refuel_events.send(RefuelEvent { vehicle, source: FuelSource::Can(entity), liters });
+            }
+        }
+    }
+}
+
+/// Plugin for fuel consumption and refueling
+pub struct FuelPlugin;
+
+impl Plugin for FuelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FuelConfig>()
+            .add_event::<OutOfFuelEvent>()
+            .add_event::<RefuelEvent>()
+            .add_systems(Update, (
+                sync_fuel_setting.run_if(resource_exists::<GameSettings>()),
+                handle_refueling,
+                consume_fuel,
+                apply_engine_cutoff,
+            ).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_consumption_scales_with_load() {
+        let config = FuelConfig::default();
+        let idle = fuel_consumption(&config, 0.0, 800.0, DriveType::RearWD);
+        let cruise = fuel_consumption(&config, 0.4, 2500.0, DriveType::RearWD);
+        let full = fuel_consumption(&config, 1.0, 6000.0, DriveType::RearWD);
+        assert!((idle * 3600.0 - config.idle_consumption).abs() < 1e-4);
+        assert!(idle < cruise && cruise < full);
+        assert!((full * 3600.0 - config.full_load_consumption).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_four_wheel_drive_burns_more() {
+        let config = FuelConfig::default();
+        let rwd = fuel_consumption(&config, 0.5, 3000.0, DriveType::RearWD);
+        let fwd4 = fuel_consumption(&config, 0.5, 3000.0, DriveType::FourWD);
+        assert!(fwd4 > rwd);
+    }
+
+    #[test]
+    fn test_tank_runs_dry_once() {
+        let mut tank = FuelTank { capacity: 10.0, level: 1.0 };
+        assert!(!tank.burn(0.5));
+        assert!(tank.burn(1.0));
+        assert_eq!(tank.level, 0.0);
+        assert!(!tank.burn(1.0));
+    }
+
+    #[test]
+    fn test_fill_stops_at_capacity() {
+        let mut tank = FuelTank { capacity: 10.0, level: 8.0 };
+        assert_eq!(tank.fill(5.0), 2.0);
+        assert_eq!(tank.fraction(), 1.0);
+    }
+
+    #[test]
+    fn test_engine_off_burns_no_fuel() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<FuelConfig>()
+            .add_event::<OutOfFuelEvent>()
+            .add_systems(Update, consume_fuel);
+        let engine = Engine { throttle: 1.0, rpm: 6000.0 };
+        let running = app.world.spawn((Vehicle::default(), engine, Drivetrain::default(), FuelTank::default())).id();
+        let off = Drivetrain { running: false, ..default() };
+        let parked = app.world.spawn((Vehicle::default(), engine, off, FuelTank::default())).id();
+
+        app.world.resource_mut::<Time>().advance_by(Duration::from_secs(60));
+        app.update();
+        assert!(app.world.get::<FuelTank>(running).unwrap().fraction() < 1.0);
+        assert_eq!(app.world.get::<FuelTank>(parked).unwrap().fraction(), 1.0);
+    }
+
+    #[test]
+    fn test_refuels_only_the_player_holding_refuel() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<FuelConfig>()
+            .add_event::<RefuelEvent>()
+            .add_systems(Update, handle_refueling);
+        app.world.spawn((GasStation::default(), TransformBundle::default()));
+        let empty = FuelTank { capacity: 72.0, level: 0.0 };
+        let holding = PlayerInput { refuel: true, ..default() };
+        let first = app.world.spawn((PlayerId(0), holding, empty.clone(), TransformBundle::default())).id();
+        let second = app.world.spawn((PlayerId(1), PlayerInput::default(), empty, TransformBundle::default())).id();
+
+        app.world.resource_mut::<Time>().advance_by(Duration::from_secs(1));
+        app.update();
+        assert!(app.world.get::<FuelTank>(first).unwrap().level > 0.0);
+        assert_eq!(app.world.get::<FuelTank>(second).unwrap().level, 0.0);
+    }
+
+    #[test]
+    fn test_casual_mode_disables_fuel() {
+        let mut settings = GameSettings::default();
+        assert!(settings.gameplay.fuel_enabled());
+        settings.gameplay.casual_mode = true;
+        assert!(!settings.gameplay.fuel_enabled());
+    }
+}
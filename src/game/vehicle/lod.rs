@@ -0,0 +1,411 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology, VertexAttributeValues};
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::utils::{HashMap, HashSet};
+
+use super::{wheel_rest_position, Vehicle, VehicleConfig, WheelVisual, WheelVisualsAttached};
+use crate::game::plugins::{NetcodeSettings, NetworkedVehicle, PlayerId, Relevance};
+
+/// Grid cell in meters vertices are merged within, per decimated level
+const DECIMATION_CELLS: [f32; 2] = [0.15, 0.4];
+/// Sides of the wheel cylinders per level down to [`VehicleLod::Low`]
+const WHEEL_RESOLUTIONS: [u32; 3] = [16, 10, 6];
+/// Pixels along the length of an impostor sprite
+const IMPOSTOR_RESOLUTION: u32 = 64;
+
+/// How much of a vehicle is drawn
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum VehicleLod {
+    #[default]
+    Full,
+    /// Decimated body, fewer sided wheels
+    Reduced,
+    /// Coarse body and wheels
+    Low,
+    /// A sprite standing in for the whole vehicle
+    Impostor,
+}
+
+impl VehicleLod {
+    pub const ALL: [Self; 4] = [Self::Full, Self::Reduced, Self::Low, Self::Impostor];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn coarser(self) -> Option<Self> {
+        Self::ALL.get(self.index() + 1).copied()
+    }
+
+    fn finer(self) -> Option<Self> {
+        self.index().checked_sub(1).map(|index| Self::ALL[index])
+    }
+}
+
+/// Distances vehicles other than the local players' switch detail at
+#[derive(Resource, Debug, Clone)]
+pub struct VehicleLodSettings {
+    /// Meters from the nearest camera at which [`VehicleLod::Reduced`], [`VehicleLod::Low`] and
+    /// [`VehicleLod::Impostor`] start
+    pub distances: [f32; 3],
+    /// Share of a distance a vehicle has to get past it before switching, so one driving along a
+    /// threshold doesn't pop back and forth
+    pub hysteresis: f32,
+}
+
+impl Default for VehicleLodSettings {
+    fn default() -> Self {
+        Self {
+            distances: [40.0, 90.0, 200.0],
+            hysteresis: 0.1,
+        }
+    }
+}
+
+impl VehicleLodSettings {
+    /// Distance `lod` starts at, zero for full detail
+    pub fn switch_distance(&self, lod: VehicleLod) -> f32 {
+        lod.index().checked_sub(1).map_or(0.0, |index| self.distances[index])
+    }
+
+    /// Level for a vehicle `distance` away that currently shows `current`
+    pub fn lod_for(&self, distance: f32, current: VehicleLod) -> VehicleLod {
+        let mut lod = current;
+        while let Some(coarser) = lod.coarser() {
+            if distance <= self.switch_distance(coarser) * (1.0 + self.hysteresis) {
+                break;
+            }
+            lod = coarser;
+        }
+        if lod != current {
+            return lod;
+        }
+        while let Some(finer) = lod.finer() {
+            if distance >= self.switch_distance(lod) * (1.0 - self.hysteresis) {
+                break;
+            }
+            lod = finer;
+        }
+        lod
+    }
+}
+
+/// Meshes of each level, built by the vehicle spawner
+#[derive(Component, Debug, Clone)]
+pub struct VehicleLodMeshes {
+    /// Body for each level down to [`VehicleLod::Low`]
+    pub body: [Handle<Mesh>; 3],
+    /// Wheels for each level down to [`VehicleLod::Low`]
+    pub wheel: [Handle<Mesh>; 3],
+    /// Sprite drawn instead of the vehicle at [`VehicleLod::Impostor`]
+    pub impostor: Entity,
+}
+
+impl VehicleLodMeshes {
+    /// Decimates `body` and rebuilds `wheel` with fewer sides for the lower levels
+    pub fn build(meshes: &mut Assets<Mesh>, body: Mesh, wheel: shape::Cylinder, impostor: Entity) -> Self {
+        let reduced = decimate_mesh(&body, DECIMATION_CELLS[0]);
+        let low = decimate_mesh(&body, DECIMATION_CELLS[1]);
+        Self {
+            body: [meshes.add(body), meshes.add(reduced), meshes.add(low)],
+            wheel: WHEEL_RESOLUTIONS.map(|resolution| meshes.add(Mesh::from(shape::Cylinder { resolution, ..wheel }))),
+            impostor,
+        }
+    }
+}
+
+/// Camera facing sprite of a vehicle, a root entity so hiding the vehicle doesn't hide it
+#[derive(Component, Debug, Clone)]
+pub struct VehicleImpostor {
+    pub vehicle: Entity,
+    /// Height of the sprite's centre above the vehicle's origin
+    pub offset: f32,
+    /// Vehicle width and length, the sprite narrows to the width seen head on
+    pub size: Vec2,
+}
+
+impl VehicleImpostor {
+    /// Width across the screen of the vehicle seen along `view` in its own space
+    pub fn extent(&self, view: Vec3) -> f32 {
+        let view = view.xz().normalize_or_zero();
+        // The screen's horizontal is perpendicular to the view in the ground plane
+        view.y.abs() * self.size.x + view.x.abs() * self.size.y
+    }
+}
+
+/// Simplifies a triangle mesh by merging the vertices that fall in the same `cell` meters across.
+/// Vertices facing different ways aren't merged, so hard edges stay hard.
+pub fn decimate_mesh(mesh: &Mesh, cell: f32) -> Mesh {
+    let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+        return mesh.clone();
+    };
+    let normals = match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+        Some(VertexAttributeValues::Float32x3(normals)) => Some(normals),
+        _ => None,
+    };
+    let uvs = match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+        Some(VertexAttributeValues::Float32x2(uvs)) => Some(uvs),
+        _ => None,
+    };
+    let indices: Vec<u32> = match mesh.indices() {
+        Some(Indices::U16(indices)) => indices.iter().map(|&index| index as u32).collect(),
+        Some(Indices::U32(indices)) => indices.clone(),
+        None => (0..positions.len() as u32).collect(),
+    };
+
+    // Sum of what's merged into each cluster and how many vertices that is
+    let mut clusters: HashMap<(IVec3, IVec3), u32> = HashMap::new();
+    let mut sums: Vec<(Vec3, Vec3, Vec2, f32)> = Vec::new();
+    let remap: Vec<u32> = positions
+        .iter()
+        .enumerate()
+        .map(|(index, &position)| {
+            let position = Vec3::from(position);
+            let normal = normals.map_or(Vec3::ZERO, |normals| Vec3::from(normals[index]));
+            let uv = uvs.map_or(Vec2::ZERO, |uvs| Vec2::from(uvs[index]));
+            let key = ((position / cell).floor().as_ivec3(), (normal * 2.0).round().as_ivec3());
+            let cluster = *clusters.entry(key).or_insert_with(|| {
+                sums.push((Vec3::ZERO, Vec3::ZERO, Vec2::ZERO, 0.0));
+                sums.len() as u32 - 1
+            });
+            let sum = &mut sums[cluster as usize];
+            sum.0 += position;
+            sum.1 += normal;
+            sum.2 += uv;
+            sum.3 += 1.0;
+            cluster
+        })
+        .collect();
+
+    // Triangles that collapsed or ended up on top of another one go
+    let mut seen = HashSet::new();
+    let mut triangles = Vec::with_capacity(indices.len());
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|corner| remap[triangle[corner] as usize]);
+        if a == b || b == c || a == c {
+            continue;
+        }
+        // Same corners in the same winding, whichever one it starts from
+        let first = a.min(b).min(c);
+        let key = if first == a { [a, b, c] } else if first == b { [b, c, a] } else { [c, a, b] };
+        if seen.insert(key) {
+            triangles.extend_from_slice(&[a, b, c]);
+        }
+    }
+
+    let mut decimated = Mesh::new(PrimitiveTopology::TriangleList);
+    let positions: Vec<[f32; 3]> = sums.iter().map(|sum| (sum.0 / sum.3).to_array()).collect();
+    decimated.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    if normals.is_some() {
+        let normals: Vec<[f32; 3]> = sums.iter().map(|sum| sum.1.normalize_or_zero().to_array()).collect();
+        decimated.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    }
+    if uvs.is_some() {
+        let uvs: Vec<[f32; 2]> = sums.iter().map(|sum| (sum.2 / sum.3).to_array()).collect();
+        decimated.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    }
+    decimated.set_indices(Some(Indices::U32(triangles)));
+    decimated
+}
+
+/// Bottom and top of a vehicle side on, relative to its origin, from the wheels to the roof
+pub fn impostor_bounds(config: &VehicleConfig) -> (f32, f32) {
+    let bottom = wheel_rest_position(config, 0).y - config.wheel_radius;
+    (bottom.min(-config.dimensions.y * 0.5), config.dimensions.y * 0.5)
+}
+
+/// Side on silhouette of a vehicle, body over wheels on a transparent background
+pub fn impostor_image(config: &VehicleConfig, body_color: Color, wheel_color: Color) -> Image {
+    let (bottom, top) = impostor_bounds(config);
+    let length = config.dimensions.z;
+    let width = IMPOSTOR_RESOLUTION;
+    let height = ((IMPOSTOR_RESOLUTION as f32 * (top - bottom) / length).ceil() as u32).max(1);
+    let wheel_height = wheel_rest_position(config, 0).y;
+
+    let mut data = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            // Pixel centre in meters, along the vehicle and up from its origin
+            let along = ((x as f32 + 0.5) / width as f32 - 0.5) * length;
+            let up = top - (y as f32 + 0.5) / height as f32 * (top - bottom);
+            let on_wheel = [-0.5, 0.5].into_iter().any(|axle: f32| {
+                Vec2::new(along - axle * config.wheelbase, up - wheel_height).length() <= config.wheel_radius
+            });
+            let color = if on_wheel {
+                wheel_color
+            } else if up >= -config.dimensions.y * 0.5 {
+                body_color
+            } else {
+                Color::NONE
+            };
+            data.extend_from_slice(&color.as_rgba_u8());
+        }
+    }
+
+    Image::new(
+        Extent3d { width, height, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+/// Picks each vehicle's level from its distance to the nearest camera, the local players' stay at full detail
+fn update_vehicle_lod(
+    settings: Res<VehicleLodSettings>,
+    netcode: Option<Res<NetcodeSettings>>,
+    mut vehicles: Query<(&mut VehicleLod, &Relevance, Has<PlayerId>, Option<&NetworkedVehicle>)>,
+) {
+    for (mut lod, relevance, player, networked) in vehicles.iter_mut() {
+        let remote = networked
+            .zip(netcode.as_deref())
+            .is_some_and(|(networked, netcode)| networked.owner != netcode.local_peer);
+        let target = if player && !remote {
+            VehicleLod::Full
+        } else {
+            settings.lod_for(relevance.distance, *lod)
+        };
+        // Only a real switch should trigger swapping meshes
+        if *lod != target {
+            *lod = target;
+        }
+    }
+}
+
+/// Swaps body and wheel meshes for each vehicle's level, and the vehicle for its impostor
+#[allow(clippy::type_complexity)]
+fn apply_vehicle_lod(
+    mut vehicles: Query<
+        (&VehicleLod, &VehicleLodMeshes, &mut Handle<Mesh>, &mut Visibility, Option<&Children>),
+        (With<Vehicle>, Or<(Changed<VehicleLod>, Added<WheelVisualsAttached>)>),
+    >,
+    mut wheels: Query<&mut Handle<Mesh>, (With<WheelVisual>, Without<Vehicle>)>,
+    mut impostors: Query<&mut Visibility, (With<VehicleImpostor>, Without<Vehicle>)>,
+) {
+    for (lod, lod_meshes, mut body, mut visibility, children) in vehicles.iter_mut() {
+        let impostor = *lod == VehicleLod::Impostor;
+        let level = lod.index().min(VehicleLod::Low.index());
+        *body = lod_meshes.body[level].clone();
+        for &child in children.into_iter().flatten() {
+            if let Ok(mut wheel) = wheels.get_mut(child) {
+                *wheel = lod_meshes.wheel[level].clone();
+            }
+        }
+        // Hiding the vehicle hides its wheels, fittings and lights with it
+        *visibility = if impostor { Visibility::Hidden } else { Visibility::Inherited };
+        if let Ok(mut impostor_visibility) = impostors.get_mut(lod_meshes.impostor) {
+            *impostor_visibility = if impostor { Visibility::Inherited } else { Visibility::Hidden };
+        }
+    }
+}
+
+/// Keeps shown impostors on their vehicles and turned to the nearest camera, and removes those whose
+/// vehicle is gone
+fn update_impostors(
+    mut commands: Commands,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    vehicles: Query<&GlobalTransform, With<Vehicle>>,
+    mut impostors: Query<(Entity, &VehicleImpostor, &Visibility, &mut Transform)>,
+) {
+    for (entity, impostor, visibility, mut transform) in impostors.iter_mut() {
+        let Ok(vehicle) = vehicles.get(impostor.vehicle) else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+        if *visibility == Visibility::Hidden {
+            continue;
+        }
+        let position = vehicle.translation() + Vec3::Y * impostor.offset;
+        let Some(camera) = cameras
+            .iter()
+            .filter(|(camera, _)| camera.is_active && camera.order >= 0)
+            .map(|(_, camera)| camera.translation())
+            .min_by(|a, b| a.distance_squared(position).total_cmp(&b.distance_squared(position)))
+        else {
+            continue;
+        };
+
+        let to_camera = camera - position;
+        let view = vehicle.affine().inverse().transform_vector3(to_camera);
+        transform.translation = position;
+        transform.rotation = Quat::from_rotation_y(to_camera.x.atan2(to_camera.z));
+        transform.scale.x = impostor.extent(view) / impostor.size.y;
+    }
+}
+
+/// Plugin switching AI and remote vehicles to lower detail and impostors with distance
+pub struct VehicleLodPlugin;
+
+impl Plugin for VehicleLodPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VehicleLodSettings>()
+            .add_systems(Update, (update_vehicle_lod, apply_vehicle_lod, update_impostors).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lod_switches_past_hysteresis_band() {
+        let settings = VehicleLodSettings::default();
+        assert_eq!(settings.lod_for(30.0, VehicleLod::Full), VehicleLod::Full);
+        // Just past the threshold isn't enough to step out
+        assert_eq!(settings.lod_for(42.0, VehicleLod::Full), VehicleLod::Full);
+        assert_eq!(settings.lod_for(45.0, VehicleLod::Full), VehicleLod::Reduced);
+        // Nor is just inside it enough to step back in
+        assert_eq!(settings.lod_for(38.0, VehicleLod::Reduced), VehicleLod::Reduced);
+        assert_eq!(settings.lod_for(35.0, VehicleLod::Reduced), VehicleLod::Full);
+        // Large jumps skip levels both ways
+        assert_eq!(settings.lod_for(500.0, VehicleLod::Full), VehicleLod::Impostor);
+        assert_eq!(settings.lod_for(10.0, VehicleLod::Impostor), VehicleLod::Full);
+    }
+
+    #[test]
+    fn test_decimation_merges_fine_detail() {
+        let plane = Mesh::from(shape::Plane { size: 4.0, subdivisions: 15 });
+        let decimated = decimate_mesh(&plane, 1.0);
+        assert!(decimated.count_vertices() < plane.count_vertices() / 4);
+        let triangles = |mesh: &Mesh| mesh.indices().map_or(0, |indices| indices.len() / 3);
+        assert!(triangles(&decimated) > 0 && triangles(&decimated) < triangles(&plane));
+
+        // A box's corners are farther apart than the cell, and its faces keep their own normals
+        let cube = Mesh::from(shape::Cube { size: 2.0 });
+        let decimated = decimate_mesh(&cube, 0.5);
+        assert_eq!(decimated.count_vertices(), cube.count_vertices());
+        assert_eq!(triangles(&decimated), 12);
+    }
+
+    #[test]
+    fn test_impostor_silhouette() {
+        let config = VehicleConfig::default();
+        let body = Color::RED;
+        let wheel = Color::BLACK;
+        let image = impostor_image(&config, body, wheel);
+        let size = image.size();
+        let pixel = |x: u32, y: u32| {
+            let start = ((y * size.x + x) * 4) as usize;
+            image.data[start..start + 4].to_vec()
+        };
+        // Roof is body colored, between the wheels under the body is see-through
+        assert_eq!(pixel(size.x / 2, 0), body.as_rgba_u8().to_vec());
+        assert_eq!(pixel(size.x / 2, size.y - 1)[3], 0);
+        // The wheels reach the bottom edge under the axles
+        let axle = ((0.5 - 0.5 * config.wheelbase / config.dimensions.z) * size.x as f32) as u32;
+        assert_eq!(pixel(axle, size.y - 1), wheel.as_rgba_u8().to_vec());
+    }
+
+    #[test]
+    fn test_impostor_narrows_head_on() {
+        let impostor = VehicleImpostor {
+            vehicle: Entity::PLACEHOLDER,
+            offset: 0.0,
+            size: Vec2::new(1.8, 4.2),
+        };
+        assert!((impostor.extent(Vec3::X) - 4.2).abs() < 1e-5);
+        assert!((impostor.extent(Vec3::NEG_Z) - 1.8).abs() < 1e-5);
+        let diagonal = impostor.extent(Vec3::new(1.0, 5.0, 1.0));
+        assert!(diagonal > 4.2 && diagonal < 1.8 + 4.2);
+    }
+}
@@ -2,14 +2,54 @@ use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 use serde::{Deserialize, Serialize};
 use crate::game::constants::*;
+use crate::game::{configure_game_sets, render_available, GameSet};
+use crate::physics::{suspension_rays_enabled, ForceAccumulator};
 
+mod assists;
+mod cargo;
 mod chassis;
+mod cockpit;
+mod collisions;
+mod customization;
+mod dirt;
+mod driver;
+mod drivetrain;
+mod fuel;
+mod lod;
+mod paint_reflections;
 mod wheel;
+mod wheel_visual;
+mod spawner;
 mod suspension;
+mod recovery;
+mod recovery_gear;
+mod thermal;
+mod towing;
+mod traction;
+mod underbody;
 
+pub use assists::*;
+pub use cargo::*;
 pub use chassis::*;
+pub use cockpit::*;
+pub use collisions::*;
+pub use customization::*;
+pub use dirt::*;
+pub use driver::*;
+pub use drivetrain::*;
+pub use fuel::*;
+pub use lod::*;
+pub use paint_reflections::*;
 pub use wheel::*;
+pub use wheel_visual::*;
+pub use spawner::*;
 pub use suspension::*;
+pub use recovery::*;
+pub use recovery_gear::*;
+pub use thermal::*;
+pub use towing::*;
+pub use traction::*;
+pub use underbody::*;
 
 /// Configuration for a vehicle, including all physical properties and component relationships
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +64,8 @@ pub struct VehicleConfig {
     pub max_steering_angle: f32,
     pub suspension_config: SuspensionConfig,
     pub drivetrain_config: DrivetrainConfig,
+    #[serde(default)]
+    pub underbody: UnderbodyConfig,
 }
 
 impl Default for VehicleConfig {
@@ -39,6 +81,7 @@ impl Default for VehicleConfig {
             max_steering_angle: MAX_STEERING_ANGLE,
             suspension_config: SuspensionConfig::default(),
             drivetrain_config: DrivetrainConfig::default(),
+            underbody: UnderbodyConfig::default(),
         }
     }
 }
@@ -74,9 +117,16 @@ pub struct DrivetrainConfig {
     pub max_brake_torque: f32,
     pub gear_ratios: Vec<f32>,
     pub final_drive_ratio: f32,
+    /// Transfer case reduction in low range
+    #[serde(default = "default_low_range_ratio")]
+    pub low_range_ratio: f32,
     pub drive_type: DriveType,
 }
 
+fn default_low_range_ratio() -> f32 {
+    2.72
+}
+
 impl Default for DrivetrainConfig {
     fn default() -> Self {
         Self {
@@ -84,6 +134,7 @@ impl Default for DrivetrainConfig {
             max_brake_torque: 1000.0,
             gear_ratios: vec![-2.72, 0.0, 3.59, 2.19, 1.41, 1.00, 0.83],
             final_drive_ratio: 3.73,
+            low_range_ratio: default_low_range_ratio(),
             drive_type: DriveType::FourWD,
         }
     }
@@ -97,18 +148,15 @@ pub enum DriveType {
     FourWD,
 }
 
-/// Main component for vehicles, containing all state and references to child components
+/// Main component for vehicles, containing the configuration, wheel references and chassis state.
+/// The driver's controls and the engine live in the [`Engine`], [`Transmission`], [`Steering`] and
+/// [`Brakes`] sub-components next to it, each wheel's suspension in the [`WheelHub`] on that wheel.
 #[derive(Component)]
 pub struct Vehicle {
     pub config: VehicleConfig,
     pub wheel_entities: [Entity; 4],
-    pub suspension_states: [SuspensionState; 4],
-    pub steering_angle: f32,
-    pub throttle: f32,
-    pub brake: f32,
-    pub handbrake: bool,
-    pub current_gear: i32,
-    pub engine_rpm: f32,
+    /// Differentials locked, wheels on an axle turn together instead of the unloaded one spinning
+    pub diff_locked: bool,
     pub vehicle_speed: f32,
 }
 
@@ -117,46 +165,55 @@ impl Default for Vehicle {
         Self {
             config: VehicleConfig::default(),
             wheel_entities: [Entity::PLACEHOLDER; 4],
-            suspension_states: [SuspensionState::default(); 4],
-            steering_angle: 0.0,
-            throttle: 0.0,
-            brake: 0.0,
-            handbrake: false,
-            current_gear: 1,
-            engine_rpm: 0.0,
+            diff_locked: false,
             vehicle_speed: 0.0,
         }
     }
 }
 
-/// Current state of a suspension unit
-#[derive(Debug, Clone, Copy)]
-pub struct SuspensionState {
-    pub compression: f32,
-    pub velocity: f32,
-    pub force: Vec3,
-    pub ground_contact: bool,
-    pub ground_normal: Vec3,
-    pub ground_point: Vec3,
+/// Throttle pedal and crank speed of a vehicle's engine
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct Engine {
+    /// Pedal travel, 0 to 1
+    pub throttle: f32,
+    pub rpm: f32,
+}
+
+/// Selected gear, -1 for reverse and 0 for neutral
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Transmission {
+    pub gear: i32,
 }
 
-impl Default for SuspensionState {
+impl Default for Transmission {
     fn default() -> Self {
-        Self {
-            compression: 0.0,
-            velocity: 0.0,
-            force: Vec3::ZERO,
-            ground_contact: false,
-            ground_normal: -Vec3::Y,
-            ground_point: Vec3::ZERO,
-        }
+        Self { gear: 1 }
     }
 }
 
+/// Front wheel steering angle in radians, positive to the left
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct Steering {
+    pub angle: f32,
+}
+
+/// Brake pedal and handbrake
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct Brakes {
+    /// Pedal travel, 0 to 1
+    pub brake: f32,
+    /// Locks the rear wheels
+    pub handbrake: bool,
+}
+
 /// Bundle for spawning a complete vehicle with all necessary components
 #[derive(Bundle)]
 pub struct VehicleBundle {
     pub vehicle: Vehicle,
+    pub engine: Engine,
+    pub transmission: Transmission,
+    pub steering: Steering,
+    pub brakes: Brakes,
     pub rigid_body: RigidBody,
     pub collider: Collider,
     pub mass_properties: ColliderMassProperties,
@@ -164,6 +221,7 @@ pub struct VehicleBundle {
     pub restitution: Restitution,
     pub damping: Damping,
     pub external_force: ExternalForce,
+    pub forces: ForceAccumulator,
     pub transform: Transform,
     pub global_transform: GlobalTransform,
     pub name: Name,
@@ -174,6 +232,10 @@ impl Default for VehicleBundle {
         let config = VehicleConfig::default();
         Self {
             vehicle: Vehicle::default(),
+            engine: Engine::default(),
+            transmission: Transmission::default(),
+            steering: Steering::default(),
+            brakes: Brakes::default(),
             rigid_body: RigidBody::Dynamic,
             collider: Collider::cuboid(
                 config.dimensions.x / 2.0,
@@ -188,9 +250,89 @@ impl Default for VehicleBundle {
                 angular_damping: 0.2,
             },
             external_force: ExternalForce::default(),
+            forces: ForceAccumulator::default(),
             transform: Transform::from_xyz(0.0, 5.0, 0.0),
             global_transform: GlobalTransform::default(),
             name: Name::new("Vehicle"),
         }
     }
-} 
\ No newline at end of file
+} 
+
+/// Plugin for the wheel, suspension and chassis simulation
+pub struct VehiclePlugin;
+
+impl Plugin for VehiclePlugin {
+    fn build(&self, app: &mut App) {
+        configure_game_sets(app);
+        app.init_resource::<RecoverySettings>()
+            .add_event::<RecoverVehicleEvent>()
+            .add_event::<RecoveryRejectedEvent>()
+            .add_systems(Update, (
+                recover_vehicles,
+                settle_recovered_vehicles,
+                update_wheel_physics,
+                update_suspension_physics,
+                apply_suspension_forces,
+                update_chassis_physics,
+            ).chain().in_set(GameSet::Simulation));
+
+        // Gizmos need a renderer, the physics debug panel turns them on
+        if render_available(app) {
+            app.init_resource::<SuspensionDebugConfig>().add_systems(
+                Update,
+                draw_suspension_debug.run_if(suspension_rays_enabled).in_set(GameSet::PostSim),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_starts_idle_in_first_with_the_brakes_off() {
+        let bundle = VehicleBundle::default();
+        assert_eq!(bundle.engine.throttle, 0.0);
+        assert_eq!(bundle.transmission.gear, 1);
+        assert_eq!(bundle.steering.angle, 0.0);
+        assert_eq!(bundle.brakes.brake, 0.0);
+        assert!(!bundle.brakes.handbrake);
+        assert!(bundle.vehicle.config.max_steering_angle > 0.0);
+    }
+
+    #[test]
+    fn test_positive_steering_turns_left() {
+        let config = VehicleConfig::default();
+        let steering = Steering { angle: config.max_steering_angle * 0.5 };
+        let (left, right) = ackermann_angles(steering.angle, config.wheelbase, config.track_width);
+        // The left wheel is on the inside of a left turn
+        assert!(left > right && right > 0.0);
+        assert!((Quat::from_rotation_y(left) * Vec3::NEG_Z).x < 0.0);
+    }
+
+    #[test]
+    fn test_drivetrain_reads_the_sub_components() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<EngineConfig>()
+            .add_event::<EngineStalledEvent>()
+            .add_systems(Update, update_drivetrain);
+        let revving = Engine { throttle: 1.0, ..default() };
+        let neutral = Transmission { gear: 0 };
+        let vehicle = app.world.spawn((VehicleBundle::default(), Drivetrain::default())).id();
+        app.world.entity_mut(vehicle).insert((revving, neutral, Steering { angle: 0.2 }));
+
+        app.update();
+        let config = app.world.resource::<EngineConfig>();
+        let engine = app.world.get::<Engine>(vehicle).unwrap();
+        assert!(engine.rpm > config.idle_rpm);
+        assert_eq!(app.world.get::<Steering>(vehicle).unwrap().angle, 0.2);
+
+        // A stopped engine drops the throttle without touching the other controls
+        app.world.get_mut::<Drivetrain>(vehicle).unwrap().stall();
+        app.update();
+        assert_eq!(app.world.get::<Engine>(vehicle).unwrap().throttle, 0.0);
+        assert_eq!(app.world.get::<Transmission>(vehicle).unwrap().gear, 0);
+    }
+}
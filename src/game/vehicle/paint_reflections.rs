@@ -0,0 +1,480 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{
+    core_pipeline::tonemapping::Tonemapping,
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        render_asset::RenderAssets,
+        render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext},
+        render_resource::{
+            Extent3d, ImageCopyTexture, Origin3d, ShaderType, TextureAspect, TextureDimension, TextureFormat,
+            TextureUsages,
+        },
+        renderer::RenderContext,
+        view::RenderLayers,
+        RenderApp,
+    },
+};
+
+use super::dirt::{sync_dirt_materials, VehicleBodyMaterial, VehicleDirtMaterials};
+use crate::game::plugins::camera::GameCamera;
+use crate::game::plugins::{OriginShiftEvent, SceneColor, ShiftOrigin};
+use crate::game::render_available;
+
+/// Faces of the reflection probe as the direction each looks in and its up, in the order they sit
+/// in the atlas: +X, -X and +Y on the top row, -Y, +Z and -Z below
+pub const PROBE_FACES: [(Vec3, Vec3); 6] = [
+    (Vec3::X, Vec3::Y),
+    (Vec3::NEG_X, Vec3::Y),
+    (Vec3::Y, Vec3::Z),
+    (Vec3::NEG_Y, Vec3::NEG_Z),
+    (Vec3::Z, Vec3::Y),
+    (Vec3::NEG_Z, Vec3::Y),
+];
+
+/// Columns of faces in the probe atlas, over two rows
+const ATLAS_COLUMNS: u32 = 3;
+/// Nothing closer than this reaches the probe, so the vehicle it sits in doesn't see its own insides
+const PROBE_NEAR: f32 = 3.0;
+
+/// Reflections on vehicle paint and chrome
+#[derive(Resource, Debug, Clone)]
+pub struct PaintReflectionSettings {
+    pub enabled: bool,
+    /// Pixels along each face of the probe
+    pub face_resolution: u32,
+    /// Frames between probe face renders, the whole probe refreshes every six renders
+    pub refresh_interval: u32,
+    /// Vehicles farther than this from the probe only get screen-space reflections
+    pub probe_radius: f32,
+    /// Trace reflections against the depth prepass where the camera has one, the probe fills in where rays miss
+    pub screen_space: bool,
+    pub ssr_steps: u32,
+    /// Meters a screen-space ray travels before giving up
+    pub ssr_max_distance: f32,
+    /// Meters behind the depth buffer a ray still counts as hitting it
+    pub ssr_thickness: f32,
+    pub strength: f32,
+}
+
+impl Default for PaintReflectionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            face_resolution: 128,
+            refresh_interval: 2,
+            probe_radius: 25.0,
+            screen_space: true,
+            ssr_steps: 24,
+            ssr_max_distance: 30.0,
+            ssr_thickness: 0.5,
+            strength: 1.0,
+        }
+    }
+}
+
+/// Reflection parameters for the body shader
+#[derive(Clone, Copy, Debug, ShaderType)]
+pub struct PaintReflectionUniform {
+    pub strength: f32,
+    pub ssr_max_distance: f32,
+    pub ssr_thickness: f32,
+    pub ssr_steps: u32,
+    /// 1 when the probe texture holds all six faces around this vehicle
+    pub probe_ready: u32,
+    /// 1 when the scene color texture can be traced against
+    pub screen_space: u32,
+}
+
+impl Default for PaintReflectionUniform {
+    fn default() -> Self {
+        let settings = PaintReflectionSettings::default();
+        Self {
+            strength: settings.strength,
+            ssr_max_distance: settings.ssr_max_distance,
+            ssr_thickness: settings.ssr_thickness,
+            ssr_steps: settings.ssr_steps,
+            probe_ready: 0,
+            screen_space: 0,
+        }
+    }
+}
+
+/// Low resolution cube of the surroundings following the player's vehicle. The faces are rendered
+/// one at a time, each into its own image since a camera clears all of its target, and copied into
+/// a 3x2 atlas for the body shader, see [`PROBE_FACES`].
+#[derive(Resource, Clone, ExtractResource)]
+pub struct ReflectionProbe {
+    /// Atlas of all six faces
+    pub image: Handle<Image>,
+    faces: Vec<Handle<Image>>,
+    /// Where the faces were rendered from, `None` until all six have been
+    pub center: Option<Vec3>,
+    face_resolution: u32,
+    target: Option<Entity>,
+    next_face: usize,
+    frame: u32,
+}
+
+/// Camera rendering one face of the [`ReflectionProbe`]
+#[derive(Component)]
+pub struct ReflectionProbeCamera {
+    pub face: usize,
+}
+
+/// Probe face camera at `center`
+pub fn probe_face_view(face: usize, center: Vec3) -> Transform {
+    let (forward, up) = PROBE_FACES[face];
+    Transform::from_translation(center).looking_to(forward, up)
+}
+
+/// Top left pixel of a face in the atlas
+pub fn probe_face_origin(face: usize, face_resolution: u32) -> UVec2 {
+    UVec2::new(face as u32 % ATLAS_COLUMNS, face as u32 / ATLAS_COLUMNS) * face_resolution
+}
+
+/// Atlas texture coordinates of what the probe sees in `direction`, the body shader does the same.
+/// `inset` keeps samples that far inside a face so filtering doesn't bleed in its neighbours.
+pub fn probe_atlas_uv(direction: Vec3, inset: f32) -> Vec2 {
+    let abs = direction.abs();
+    let face = if abs.x >= abs.y && abs.x >= abs.z {
+        if direction.x > 0.0 { 0 } else { 1 }
+    } else if abs.y >= abs.z {
+        if direction.y > 0.0 { 2 } else { 3 }
+    } else if direction.z > 0.0 {
+        4
+    } else {
+        5
+    };
+    let (forward, up) = PROBE_FACES[face];
+    let right = forward.cross(up);
+    let depth = direction.dot(forward);
+    let local = Vec2::new(direction.dot(right), direction.dot(up)) / depth;
+    let face_uv = Vec2::new(0.5 + 0.5 * local.x, 0.5 - 0.5 * local.y);
+    let face_uv = face_uv.clamp(Vec2::splat(inset), Vec2::splat(1.0 - inset));
+    let cell = Vec2::new((face as u32 % ATLAS_COLUMNS) as f32, (face as u32 / ATLAS_COLUMNS) as f32);
+    (cell + face_uv) / Vec2::new(ATLAS_COLUMNS as f32, 2.0)
+}
+
+fn probe_face_size(face_resolution: u32) -> Extent3d {
+    Extent3d { width: face_resolution, height: face_resolution, depth_or_array_layers: 1 }
+}
+
+fn probe_atlas_size(face_resolution: u32) -> Extent3d {
+    Extent3d { width: face_resolution * ATLAS_COLUMNS, height: face_resolution * 2, depth_or_array_layers: 1 }
+}
+
+fn probe_image(size: Extent3d) -> Image {
+    let mut image = Image {
+        data: vec![0; (size.width * size.height * 4) as usize],
+        ..default()
+    };
+    image.texture_descriptor.size = size;
+    image.texture_descriptor.dimension = TextureDimension::D2;
+    image.texture_descriptor.format = TextureFormat::Bgra8UnormSrgb;
+    image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+        | TextureUsages::COPY_SRC
+        | TextureUsages::COPY_DST
+        | TextureUsages::RENDER_ATTACHMENT;
+    image
+}
+
+fn setup_reflection_probe(
+    mut commands: Commands,
+    settings: Res<PaintReflectionSettings>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let face_resolution = settings.face_resolution.max(1);
+    let image = images.add(probe_image(probe_atlas_size(face_resolution)));
+    let faces: Vec<_> = (0..PROBE_FACES.len())
+        .map(|_| images.add(probe_image(probe_face_size(face_resolution))))
+        .collect();
+
+    for (face, face_image) in faces.iter().enumerate() {
+        commands.spawn((
+            Camera3dBundle {
+                camera: Camera {
+                    // Ahead of the rear-view mirror and the water reflection
+                    order: -3,
+                    target: RenderTarget::Image(face_image.clone()),
+                    is_active: false,
+                    ..default()
+                },
+                projection: Projection::Perspective(PerspectiveProjection {
+                    fov: FRAC_PI_2,
+                    aspect_ratio: 1.0,
+                    near: PROBE_NEAR,
+                    ..default()
+                }),
+                // The body shader adds the probe to lighting the main camera tonemaps afterwards
+                tonemapping: Tonemapping::None,
+                ..default()
+            },
+            // Water only draws for cameras with its prepass, keep it out like the water reflection does
+            RenderLayers::layer(0),
+            ReflectionProbeCamera { face },
+            Name::new("Reflection Probe Camera"),
+        ));
+    }
+
+    commands.insert_resource(ReflectionProbe {
+        image,
+        faces,
+        center: None,
+        face_resolution,
+        target: None,
+        next_face: 0,
+        frame: 0,
+    });
+}
+
+/// Moves the probe to the first game camera's vehicle and renders its next face when due
+fn update_reflection_probe(
+    settings: Res<PaintReflectionSettings>,
+    mut probe: ResMut<ReflectionProbe>,
+    mut images: ResMut<Assets<Image>>,
+    game_cameras: Query<&GameCamera>,
+    vehicles: Query<&GlobalTransform, With<VehicleDirtMaterials>>,
+    mut probe_cameras: Query<(&mut Camera, &mut Transform, &ReflectionProbeCamera)>,
+) {
+    let target = game_cameras
+        .iter()
+        .find_map(|game_camera| game_camera.target)
+        .filter(|target| vehicles.contains(*target));
+    if target != probe.target {
+        // A probe around another vehicle would reflect the wrong place until it's rendered again
+        probe.target = target;
+        probe.center = None;
+        probe.next_face = 0;
+    }
+
+    let face_resolution = settings.face_resolution.max(1);
+    if face_resolution != probe.face_resolution {
+        if let Some(image) = images.get_mut(&probe.image) {
+            image.resize(probe_atlas_size(face_resolution));
+        }
+        for face in &probe.faces {
+            if let Some(image) = images.get_mut(face) {
+                image.resize(probe_face_size(face_resolution));
+            }
+        }
+        probe.face_resolution = face_resolution;
+        probe.center = None;
+        probe.next_face = 0;
+    }
+
+    let due = probe.frame % settings.refresh_interval.max(1) == 0;
+    probe.frame = probe.frame.wrapping_add(1);
+    let position = target.and_then(|target| vehicles.get(target).ok()).map(GlobalTransform::translation);
+    let render_face = position.filter(|_| settings.enabled && due).map(|position| (probe.next_face, position));
+
+    for (mut camera, mut transform, probe_camera) in probe_cameras.iter_mut() {
+        camera.is_active = render_face.is_some_and(|(face, _)| face == probe_camera.face);
+        if let Some((_, position)) = render_face.filter(|_| camera.is_active) {
+            *transform = probe_face_view(probe_camera.face, position);
+        }
+    }
+
+    if let Some((face, position)) = render_face {
+        probe.next_face = (face + 1) % PROBE_FACES.len();
+        // The faces are rendered from where the vehicle was at the time, close enough at these rates
+        if probe.next_face == 0 {
+            probe.center = Some(position);
+        }
+    }
+}
+
+/// Points each vehicle's body materials at the probe and the scene color
+fn sync_reflection_materials(
+    settings: Res<PaintReflectionSettings>,
+    probe: Res<ReflectionProbe>,
+    scene_color: Option<Res<SceneColor>>,
+    vehicles: Query<(&GlobalTransform, &VehicleDirtMaterials)>,
+    mut materials: ResMut<Assets<VehicleBodyMaterial>>,
+) {
+    let scene_color = scene_color.filter(|_| settings.enabled && settings.screen_space);
+    for (transform, handles) in vehicles.iter() {
+        let near_probe = probe
+            .center
+            .is_some_and(|center| center.distance(transform.translation()) <= settings.probe_radius);
+        let probe_ready = settings.enabled && near_probe;
+
+        for handle in &handles.0 {
+            let Some(material) = materials.get_mut(handle) else {
+                continue;
+            };
+            let extension = &mut material.extension;
+            extension.reflection = PaintReflectionUniform {
+                strength: if settings.enabled { settings.strength } else { 0.0 },
+                ssr_max_distance: settings.ssr_max_distance,
+                ssr_thickness: settings.ssr_thickness,
+                ssr_steps: settings.ssr_steps,
+                probe_ready: probe_ready as u32,
+                screen_space: scene_color.is_some() as u32,
+            };
+            extension.probe = probe_ready.then(|| probe.image.clone());
+            extension.scene_color = scene_color.as_ref().map(|scene_color| scene_color.image.clone());
+        }
+    }
+}
+
+/// The probe cameras move with the world, where they rendered from has to as well
+fn rebase_reflection_probe(mut shifts: EventReader<OriginShiftEvent>, mut probe: ResMut<ReflectionProbe>) {
+    for shift in shifts.read() {
+        if let Some(center) = probe.center.as_mut() {
+            *center -= shift.translation();
+        }
+    }
+}
+
+/// Node in the render graph copying the probe faces into the atlas once every camera has rendered
+pub struct ProbeAtlasNode;
+
+impl ProbeAtlasNode {
+    /// Name of the node in the render graph
+    pub const NAME: &'static str = "reflection_probe_atlas";
+}
+
+impl Node for ProbeAtlasNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let Some(probe) = world.get_resource::<ReflectionProbe>() else {
+            return Ok(());
+        };
+        let images = world.resource::<RenderAssets<Image>>();
+        let Some(atlas) = images.get(&probe.image) else {
+            return Ok(());
+        };
+        let size = probe_face_size(probe.face_resolution);
+        // Until a resize has reached the GPU the faces don't fit their cells
+        if atlas.size != UVec2::new(size.width * ATLAS_COLUMNS, size.height * 2).as_vec2() {
+            return Ok(());
+        }
+
+        for (face, handle) in probe.faces.iter().enumerate() {
+            let Some(image) = images.get(handle).filter(|image| image.size == Vec2::splat(size.width as f32)) else {
+                continue;
+            };
+            let origin = probe_face_origin(face, probe.face_resolution);
+            render_context.command_encoder().copy_texture_to_texture(
+                image.texture.as_image_copy(),
+                ImageCopyTexture {
+                    texture: &atlas.texture,
+                    mip_level: 0,
+                    origin: Origin3d { x: origin.x, y: origin.y, z: 0 },
+                    aspect: TextureAspect::All,
+                },
+                size,
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Plugin for the reflection probe and screen-space reflections on vehicle bodies
+pub struct PaintReflectionPlugin;
+
+impl Plugin for PaintReflectionPlugin {
+    fn build(&self, app: &mut App) {
+        // Nothing to reflect without a renderer
+        if !render_available(app) {
+            return;
+        }
+        app.init_resource::<PaintReflectionSettings>()
+            .add_event::<OriginShiftEvent>()
+            .add_systems(Startup, setup_reflection_probe)
+            .add_systems(Update, (
+                update_reflection_probe,
+                sync_reflection_materials.after(sync_dirt_materials),
+            ).chain())
+            .add_systems(PostUpdate, rebase_reflection_probe.after(ShiftOrigin))
+            .add_plugins(ExtractResourcePlugin::<ReflectionProbe>::default());
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        let mut render_graph = render_app.world.resource_mut::<RenderGraph>();
+        render_graph.add_node(ProbeAtlasNode::NAME, ProbeAtlasNode);
+        render_graph.add_node_edge("camera_driver", ProbeAtlasNode::NAME);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::vehicle::{PlayerOrAi, SpawnVehicleEvent, VehicleDefinition, VehicleSpawnerPlugin};
+
+    #[test]
+    fn test_face_forward_hits_centre_of_its_cell() {
+        for (face, (forward, _)) in PROBE_FACES.into_iter().enumerate() {
+            let centre = (probe_face_origin(face, 64).as_vec2() + 32.0) / Vec2::new(192.0, 128.0);
+            assert!(probe_atlas_uv(forward, 0.0).abs_diff_eq(centre, 1e-5), "face {face}");
+            // The view from the face camera looks the same way
+            assert!(probe_face_view(face, Vec3::ONE).forward().abs_diff_eq(forward, 1e-5));
+        }
+    }
+
+    #[test]
+    fn test_atlas_uv_matches_camera_orientation() {
+        // Up and to the right in a face camera's view is up and to the right in its cell
+        for face in 0..PROBE_FACES.len() {
+            let view = probe_face_view(face, Vec3::ZERO);
+            let direction = view.forward() + view.right() * 0.5 + view.up() * 0.5;
+            let uv = probe_atlas_uv(direction, 0.0) * Vec2::new(3.0, 2.0);
+            let cell = Vec2::new((face % 3) as f32, (face / 3) as f32);
+            assert!((uv - cell).abs_diff_eq(Vec2::new(0.75, 0.25), 1e-5), "face {face}");
+        }
+    }
+
+    #[test]
+    fn test_inset_keeps_samples_inside_the_face() {
+        let corner = Vec3::new(1.0, 1.0, 0.999);
+        let uv = probe_atlas_uv(corner, 0.01) * Vec2::new(3.0, 2.0);
+        let cell = uv.floor();
+        assert!((uv - cell).min_element() >= 0.01 - 1e-5 && (uv - cell).max_element() <= 0.99 + 1e-5);
+    }
+
+    #[test]
+    fn test_spawned_vehicle_paint_reflects() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), VehicleSpawnerPlugin))
+            .init_asset::<Mesh>()
+            .init_asset::<StandardMaterial>()
+            .init_asset::<Image>()
+            .init_asset::<VehicleBodyMaterial>()
+            .init_resource::<PaintReflectionSettings>()
+            .add_systems(Update, sync_reflection_materials);
+        let image = app.world.resource_mut::<Assets<Image>>().add(Image::default());
+        app.world.insert_resource(ReflectionProbe {
+            image: image.clone(),
+            faces: Vec::new(),
+            center: Some(Vec3::ZERO),
+            face_resolution: 1,
+            target: None,
+            next_face: 0,
+            frame: 0,
+        });
+        app.world.send_event(SpawnVehicleEvent {
+            definition: VehicleDefinition { engine_sound: None, headlights: false, ..default() },
+            transform: Transform::from_xyz(0.0, 1.0, 0.0),
+            driver: PlayerOrAi::Ai,
+        });
+        // The vehicle exists after the first update, its materials are synced in the second
+        app.update();
+        app.update();
+
+        let handles = app.world.query::<&VehicleDirtMaterials>().single(&app.world).0.clone();
+        assert_eq!(handles.len(), 1);
+        let materials = app.world.resource::<Assets<VehicleBodyMaterial>>();
+        let extension = &materials.get(&handles[0]).unwrap().extension;
+        assert_eq!(extension.reflection.strength, PaintReflectionSettings::default().strength);
+        assert_eq!(extension.reflection.probe_ready, 1);
+        assert_eq!(extension.probe, Some(image));
+    }
+}
@@ -0,0 +1,308 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use super::Vehicle;
+use crate::game::constants::{JEEP_HEIGHT, JEEP_LENGTH, JEEP_WIDTH};
+use crate::game::plugins::{sample_fluid, FluidVolume};
+use crate::terrain::{
+    cell_center, world_to_cell, Drivability, DrivabilityMap, DrivabilitySettings, DRIVABILITY_CELL_SIZE,
+};
+
+/// How far above its current position a recovered vehicle is put down, in meters
+pub const RECOVERY_LIFT: f32 = 1.5;
+
+/// Puts a stuck or overturned vehicle back on its wheels
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RecoverVehicleEvent {
+    pub vehicle: Entity,
+}
+
+/// Where recovered vehicles may be put down and how often
+#[derive(Resource, Debug, Clone)]
+pub struct RecoverySettings {
+    /// How many drivability cells out from the vehicle a safe spot is looked for
+    pub search_radius: i32,
+    /// Half size of the box that has to be clear of colliders where the vehicle goes
+    pub clearance: Vec3,
+    /// Seconds between recoveries of the same vehicle
+    pub cooldown: f32,
+    /// Vehicles moving faster than this in m/s can't be recovered, it's not a way out of a crash
+    pub max_speed: f32,
+    /// Seconds a recovered vehicle takes to settle, its velocity is held down while it does
+    pub fade_in: f32,
+    /// Top speed in m/s a settling vehicle is allowed, let out to any speed as the fade-in ends
+    pub settle_speed: f32,
+}
+
+impl Default for RecoverySettings {
+    fn default() -> Self {
+        Self {
+            search_radius: 6,
+            clearance: Vec3::new(JEEP_WIDTH, JEEP_HEIGHT, JEEP_LENGTH) * 0.5,
+            cooldown: 10.0,
+            max_speed: 3.0,
+            fade_in: 1.5,
+            settle_speed: 2.0,
+        }
+    }
+}
+
+/// Why a vehicle wasn't recovered
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecoveryRejection {
+    CoolingDown { seconds: f32 },
+    Moving,
+    /// No flat spot nearby clear of obstacles and water
+    NoSafeSpot,
+}
+
+impl RecoveryRejection {
+    /// Localization key of the reason
+    pub fn reason_key(self) -> &'static str {
+        match self {
+            Self::CoolingDown { .. } => "recovery.rejected_cooldown",
+            Self::Moving => "recovery.rejected_moving",
+            Self::NoSafeSpot => "recovery.rejected_no_spot",
+        }
+    }
+}
+
+/// A recovery was asked for and refused
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RecoveryRejectedEvent {
+    pub vehicle: Entity,
+    pub reason: RecoveryRejection,
+}
+
+/// When a vehicle was last recovered, in seconds since startup
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Recovered {
+    pub at: f32,
+}
+
+impl Recovered {
+    /// How far the vehicle has faded back in, 0 right after the recovery and 1 once it's settled
+    pub fn fade(&self, settings: &RecoverySettings, now: f32) -> f32 {
+        ((now - self.at) / settings.fade_in.max(f32::EPSILON)).clamp(0.0, 1.0)
+    }
+
+    /// Seconds until the vehicle can be recovered again
+    pub fn cooldown(&self, settings: &RecoverySettings, now: f32) -> f32 {
+        (self.at + settings.cooldown - now).max(0.0)
+    }
+}
+
+/// Where a vehicle at `transform` is put down when recovered: upright, facing the way it was
+/// heading, lifted clear of whatever it was stuck on
+pub fn recovered_transform(transform: &Transform) -> Transform {
+    let (yaw, _, _) = transform.rotation.to_euler(EulerRot::YXZ);
+    Transform {
+        translation: transform.translation + Vec3::Y * RECOVERY_LIFT,
+        rotation: Quat::from_rotation_y(yaw),
+        scale: transform.scale,
+    }
+}
+
+/// Ground normal at a loaded cell from its neighbours' heights, `None` unless all four are loaded
+fn cell_normal(map: &DrivabilityMap, cell: IVec2) -> Option<Vec3> {
+    let height = |x: i32, z: i32| map.height(cell + IVec2::new(x, z));
+    let dx = (height(1, 0)? - height(-1, 0)?) / (2.0 * DRIVABILITY_CELL_SIZE);
+    let dz = (height(0, 1)? - height(0, -1)?) / (2.0 * DRIVABILITY_CELL_SIZE);
+    Some(Vec3::new(-dx, 1.0, -dz).normalize())
+}
+
+/// Nearest safe place within `radius` cells of `transform` for a recovered vehicle: on a drivable cell
+/// whose neighbours are all drivable too, so flat and away from water, lying along the ground's slope and
+/// lifted clear of it. `is_clear` gets the last say on each candidate, for colliders and fluid volumes.
+pub fn find_recovery_transform(
+    map: &DrivabilityMap,
+    settings: &DrivabilitySettings,
+    transform: &Transform,
+    radius: i32,
+    mut is_clear: impl FnMut(&Transform) -> bool,
+) -> Option<Transform> {
+    let (yaw, _, _) = transform.rotation.to_euler(EulerRot::YXZ);
+    let origin = world_to_cell(transform.translation);
+    let mut cells: Vec<IVec2> = (-radius..=radius)
+        .flat_map(|z| (-radius..=radius).map(move |x| origin + IVec2::new(x, z)))
+        .collect();
+    let position = transform.translation.xz();
+    cells.sort_by(|a, b| cell_center(*a).distance(position).total_cmp(&cell_center(*b).distance(position)));
+
+    let flat = |cell: IVec2| {
+        (-1..=1).all(|z| {
+            (-1..=1).all(|x| map.drivability(cell + IVec2::new(x, z), settings) == Some(Drivability::Drivable))
+        })
+    };
+    cells.into_iter().filter(|cell| flat(*cell)).find_map(|cell| {
+        let normal = cell_normal(map, cell)?;
+        let center = cell_center(cell);
+        let ground = Vec3::new(center.x, map.height(cell)?, center.y);
+        let candidate = Transform {
+            translation: ground + normal * RECOVERY_LIFT,
+            rotation: Quat::from_rotation_arc(Vec3::Y, normal) * Quat::from_rotation_y(yaw),
+            scale: transform.scale,
+        };
+        is_clear(&candidate).then_some(candidate)
+    })
+}
+
+/// Resets recovered vehicles and stops them moving. With the drivability grid loaded they're put down
+/// on the nearest safe spot, never into another collider or water, and refused when there isn't one,
+/// when they were only just recovered or while they're still moving.
+#[allow(clippy::too_many_arguments)]
+pub fn recover_vehicles(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<RecoverySettings>,
+    map: Option<Res<DrivabilityMap>>,
+    drivability: Option<Res<DrivabilitySettings>>,
+    rapier: Option<Res<RapierContext>>,
+    fluids: Query<(&FluidVolume, &GlobalTransform)>,
+    mut events: EventReader<RecoverVehicleEvent>,
+    mut vehicles: Query<(&mut Transform, Option<&mut Velocity>, Option<&Recovered>), With<Vehicle>>,
+    mut rejected: EventWriter<RecoveryRejectedEvent>,
+) {
+    let now = time.elapsed_seconds();
+    let drivability = drivability.map(|settings| settings.clone()).unwrap_or_default();
+    for event in events.read() {
+        let Ok((mut transform, velocity, recovered)) = vehicles.get_mut(event.vehicle) else {
+            continue;
+        };
+        let cooldown = recovered.map_or(0.0, |recovered| recovered.cooldown(&settings, now));
+        let speed = velocity.as_ref().map_or(0.0, |velocity| velocity.linvel.length());
+        let placed = if cooldown > 0.0 {
+            Err(RecoveryRejection::CoolingDown { seconds: cooldown })
+        } else if speed > settings.max_speed {
+            Err(RecoveryRejection::Moving)
+        } else if let Some(map) = map.as_deref() {
+            let shape = Collider::cuboid(settings.clearance.x, settings.clearance.y, settings.clearance.z);
+            let filter = QueryFilter::new().exclude_sensors().exclude_rigid_body(event.vehicle);
+            let is_clear = |candidate: &Transform| {
+                // Some fluid at the wheels is as bad as being under it
+                let wheels = candidate.translation - candidate.up() * RECOVERY_LIFT;
+                let dry = sample_fluid(&fluids, wheels + Vec3::Y * 0.1).is_none();
+                let blocked = rapier.as_deref().and_then(|rapier| {
+                    rapier.intersection_with_shape(candidate.translation, candidate.rotation, &shape, filter)
+                });
+                dry && blocked.is_none()
+            };
+            find_recovery_transform(map, &drivability, &transform, settings.search_radius, is_clear)
+                .ok_or(RecoveryRejection::NoSafeSpot)
+        } else {
+            // No terrain to search, put it down where it is
+            Ok(recovered_transform(&transform))
+        };
+
+        match placed {
+            Ok(placed) => {
+                *transform = placed;
+                if let Some(mut velocity) = velocity {
+                    *velocity = Velocity::zero();
+                }
+                commands.entity(event.vehicle).insert(Recovered { at: now });
+            }
+            Err(reason) => rejected.send(RecoveryRejectedEvent { vehicle: event.vehicle, reason }),
+        }
+    }
+}
+
+/// Holds recovered vehicles' velocity down while they fade back in, so a vehicle put down a little
+/// into the ground eases out of it instead of being launched
+pub fn settle_recovered_vehicles(
+    time: Res<Time>,
+    settings: Res<RecoverySettings>,
+    mut vehicles: Query<(&Recovered, &mut Velocity)>,
+) {
+    let now = time.elapsed_seconds();
+    for (recovered, mut velocity) in vehicles.iter_mut() {
+        let fade = recovered.fade(&settings, now);
+        if fade >= 1.0 {
+            continue;
+        }
+        let limit = settings.settle_speed * fade;
+        velocity.linvel = velocity.linvel.clamp_length_max(limit);
+        velocity.angvel = velocity.angvel.clamp_length_max(limit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::{ChunkDrivability, DrivabilityBlocker};
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    #[test]
+    fn test_recovery_rights_the_vehicle_and_keeps_its_heading() {
+        let overturned = Transform::from_xyz(3.0, 0.5, -2.0)
+            .with_rotation(Quat::from_rotation_y(FRAC_PI_2) * Quat::from_rotation_z(PI));
+        let recovered = recovered_transform(&overturned);
+
+        assert!((recovered.up() - Vec3::Y).length() < 1e-4);
+        assert!((recovered.forward() - overturned.forward().reject_from(Vec3::Y).normalize()).length() < 1e-4);
+        assert_eq!(recovered.translation, Vec3::new(3.0, 0.5 + RECOVERY_LIFT, -2.0));
+    }
+
+    #[test]
+    fn test_recovery_finds_flat_clear_ground_nearby() {
+        let settings = DrivabilitySettings::default();
+        let mut map = DrivabilityMap::default();
+        // A 45 degree bank east of x = 0, a gentle incline west of it
+        map.insert_chunk(ChunkDrivability::analyze(IVec2::ZERO, |x, _| if x < 0.0 { x * 0.1 } else { x }));
+        let stuck = Transform::from_xyz(12.0, 12.0, 2.5).with_rotation(Quat::from_rotation_z(PI));
+
+        let placed = find_recovery_transform(&map, &settings, &stuck, 6, |_| true).unwrap();
+        assert!(placed.translation.x < -DRIVABILITY_CELL_SIZE);
+        // Lies along the incline, lifted off it
+        let normal = Vec3::new(-0.1, 1.0, 0.0).normalize();
+        assert!((placed.up() - normal).length() < 1e-3);
+        let ground = placed.translation - normal * RECOVERY_LIFT;
+        assert!((ground.y - ground.x * 0.1).abs() < 1e-3);
+
+        // Water across the nearest flat ground pushes it further out
+        let water = DrivabilityBlocker { half_size: Vec2::new(10.0, 50.0), drivability: Drivability::Steep };
+        map.block(&water, &GlobalTransform::from_translation(Vec3::new(-10.0, 0.0, 0.0)));
+        assert!(find_recovery_transform(&map, &settings, &stuck, 6, |_| true).is_none());
+        let further = find_recovery_transform(&map, &settings, &stuck, 12, |_| true).unwrap();
+        assert!(further.translation.x < -20.0);
+
+        // As does anything in the way
+        let clear = |candidate: &Transform| candidate.translation.z.abs() > 10.0;
+        let around = find_recovery_transform(&map, &settings, &stuck, 12, clear).unwrap();
+        assert!(around.translation.z.abs() > 10.0);
+        assert!(find_recovery_transform(&map, &settings, &stuck, 2, |_| true).is_none());
+    }
+
+    #[test]
+    fn test_recovery_cooldown_and_moving_vehicles_are_refused() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<RecoverySettings>()
+            .add_event::<RecoverVehicleEvent>()
+            .add_event::<RecoveryRejectedEvent>()
+            .add_systems(Update, recover_vehicles);
+        let vehicle = app
+            .world
+            .spawn((Vehicle::default(), Transform::from_xyz(0.0, 1.0, 0.0), Velocity::zero()))
+            .id();
+        app.update();
+
+        app.world.send_event(RecoverVehicleEvent { vehicle });
+        app.update();
+        assert_eq!(app.world.get::<Transform>(vehicle).unwrap().translation.y, 1.0 + RECOVERY_LIFT);
+        assert!(app.world.get::<Recovered>(vehicle).is_some());
+
+        app.world.send_event(RecoverVehicleEvent { vehicle });
+        app.update();
+        app.world.get_mut::<Recovered>(vehicle).unwrap().at = -100.0;
+        app.world.get_mut::<Velocity>(vehicle).unwrap().linvel = Vec3::X * 10.0;
+        app.world.send_event(RecoverVehicleEvent { vehicle });
+        app.update();
+
+        let reasons: Vec<RecoveryRejection> =
+            app.world.resource_mut::<Events<RecoveryRejectedEvent>>().drain().map(|event| event.reason).collect();
+        assert!(matches!(reasons[0], RecoveryRejection::CoolingDown { .. }));
+        assert_eq!(reasons[1], RecoveryRejection::Moving);
+        assert_eq!(app.world.get::<Transform>(vehicle).unwrap().translation.y, 1.0 + RECOVERY_LIFT);
+    }
+}
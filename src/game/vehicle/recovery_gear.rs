@@ -0,0 +1,489 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use super::{Vehicle, Wheel};
+use crate::game::plugins::{PlayerInput, SurfaceMaterial};
+
+/// Recovery gear carried in a vehicle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RecoveryTool {
+    TractionBoards,
+    HiLiftJack,
+    TowStrap,
+}
+
+impl RecoveryTool {
+    pub const ALL: [RecoveryTool; 3] = [RecoveryTool::TractionBoards, RecoveryTool::HiLiftJack, RecoveryTool::TowStrap];
+
+    fn index(self) -> usize {
+        match self {
+            Self::TractionBoards => 0,
+            Self::HiLiftJack => 1,
+            Self::TowStrap => 2,
+        }
+    }
+
+    /// Localization key of the tool's name
+    pub fn name_key(self) -> &'static str {
+        match self {
+            Self::TractionBoards => "recovery.traction_boards",
+            Self::HiLiftJack => "recovery.hi_lift_jack",
+            Self::TowStrap => "recovery.tow_strap",
+        }
+    }
+}
+
+/// Side of the vehicle the jack goes under, the vehicle is pushed off towards the other side
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JackSide {
+    Left,
+    Right,
+}
+
+/// What to do with a piece of recovery gear
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryAction {
+    /// Wedge a traction board in front of the wheel at this position (FL: 0, FR: 1, RL: 2, RR: 3)
+    TractionBoard { wheel: usize },
+    /// Jack up one side and push the vehicle off the jack
+    Jack { side: JackSide },
+    /// Hook the strap to the nearest vehicle, or unhook it when already strapped
+    TowStrap,
+}
+
+impl RecoveryAction {
+    pub fn tool(self) -> RecoveryTool {
+        match self {
+            Self::TractionBoard { .. } => RecoveryTool::TractionBoards,
+            Self::Jack { .. } => RecoveryTool::HiLiftJack,
+            Self::TowStrap => RecoveryTool::TowStrap,
+        }
+    }
+}
+
+/// Recovery gear a vehicle carries and how long each tool needs before it can be used again
+#[derive(Component, Debug, Clone)]
+pub struct RecoveryGear {
+    /// Traction boards in the vehicle, boards on the ground come back when they expire
+    pub traction_boards: u32,
+    pub hi_lift_jack: bool,
+    pub tow_straps: u32,
+    /// Vehicle the strap is hooked to
+    pub strapped_to: Option<Entity>,
+    /// Seconds left per tool, indexed like [`RecoveryTool::ALL`]
+    cooldowns: [f32; 3],
+}
+
+impl Default for RecoveryGear {
+    fn default() -> Self {
+        Self {
+            traction_boards: 2,
+            hi_lift_jack: true,
+            tow_straps: 1,
+            strapped_to: None,
+            cooldowns: [0.0; 3],
+        }
+    }
+}
+
+impl RecoveryGear {
+    /// How many of `tool` are in the vehicle
+    pub fn count(&self, tool: RecoveryTool) -> u32 {
+        match tool {
+            RecoveryTool::TractionBoards => self.traction_boards,
+            RecoveryTool::HiLiftJack => self.hi_lift_jack as u32,
+            RecoveryTool::TowStrap => self.tow_straps,
+        }
+    }
+
+    /// Seconds before `tool` can be used again
+    pub fn cooldown(&self, tool: RecoveryTool) -> f32 {
+        self.cooldowns[tool.index()]
+    }
+
+    /// Whether `action` can be done right now. Unhooking a strap is always possible.
+    pub fn can_use(&self, action: RecoveryAction) -> bool {
+        let tool = action.tool();
+        if action == RecoveryAction::TowStrap && self.strapped_to.is_some() {
+            return true;
+        }
+        self.count(tool) > 0 && self.cooldown(tool) <= 0.0
+    }
+
+    pub fn start_cooldown(&mut self, tool: RecoveryTool, seconds: f32) {
+        self.cooldowns[tool.index()] = seconds;
+    }
+
+    pub fn tick(&mut self, dt: f32) {
+        for cooldown in &mut self.cooldowns {
+            *cooldown = (*cooldown - dt).max(0.0);
+        }
+    }
+}
+
+/// Tuning for the recovery gear
+#[derive(Resource, Debug, Clone)]
+pub struct RecoveryGearConfig {
+    /// Seconds before each tool can be used again, indexed like [`RecoveryTool::ALL`]
+    pub cooldowns: [f32; 3],
+    /// Seconds a traction board stays down before it is picked back up
+    pub board_lifetime: f32,
+    /// Size of a traction board in meters
+    pub board_size: Vec3,
+    pub board_friction: f32,
+    /// How far the jack lifts the vehicle, in meters
+    pub jack_lift: f32,
+    /// How far the vehicle moves sideways when pushed off the jack, in meters
+    pub jack_shift: f32,
+    /// Furthest another vehicle can be for the strap to reach it, in meters
+    pub strap_range: f32,
+    /// Length of the strap once hooked up, in meters
+    pub strap_length: f32,
+}
+
+impl Default for RecoveryGearConfig {
+    fn default() -> Self {
+        Self {
+            cooldowns: [5.0, 10.0, 2.0],
+            board_lifetime: 20.0,
+            board_size: Vec3::new(0.35, 0.05, 1.1),
+            board_friction: 1.8,
+            jack_lift: 0.4,
+            jack_shift: 0.3,
+            strap_range: 9.0,
+            strap_length: 6.0,
+        }
+    }
+}
+
+impl RecoveryGearConfig {
+    pub fn cooldown(&self, tool: RecoveryTool) -> f32 {
+        self.cooldowns[tool.index()]
+    }
+}
+
+/// Winch on the front bumper, reels the tow strap in while the winch input is held
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Winch {
+    /// Meters of strap reeled in per second
+    pub pull_speed: f32,
+    /// Shortest the strap gets, in meters
+    pub min_length: f32,
+}
+
+impl Default for Winch {
+    fn default() -> Self {
+        Self { pull_speed: 0.6, min_length: 1.0 }
+    }
+}
+
+impl Winch {
+    /// Strap length after reeling in for `dt` seconds
+    pub fn reel_in(&self, length: f32, dt: f32) -> f32 {
+        (length - self.pull_speed * dt).max(self.min_length).min(length)
+    }
+}
+
+/// A traction board on the ground, picked back up into its owner's vehicle when it expires
+#[derive(Component, Debug, Clone)]
+pub struct TractionBoard {
+    pub owner: Entity,
+    /// Seconds until it is picked up
+    pub remaining: f32,
+}
+
+/// Placeholder for the character animation of a tool being used. Nothing plays it yet, the
+/// tool's effect is applied right away.
+#[derive(Component, Debug, Clone)]
+pub struct RecoveryAnimation {
+    pub tool: RecoveryTool,
+    pub elapsed: f32,
+    pub duration: f32,
+}
+
+impl RecoveryAnimation {
+    pub fn new(tool: RecoveryTool) -> Self {
+        let duration = match tool {
+            RecoveryTool::TractionBoards => 1.5,
+            RecoveryTool::HiLiftJack => 3.0,
+            RecoveryTool::TowStrap => 2.0,
+        };
+        Self { tool, elapsed: 0.0, duration }
+    }
+
+    /// 0.0 - 1.0 through the animation
+    pub fn progress(&self) -> f32 {
+        (self.elapsed / self.duration).clamp(0.0, 1.0)
+    }
+}
+
+/// Request to use a piece of recovery gear
+#[derive(Event, Debug, Clone, Copy)]
+pub struct UseRecoveryToolEvent {
+    pub vehicle: Entity,
+    pub action: RecoveryAction,
+}
+
+/// A piece of recovery gear was used
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RecoveryToolUsedEvent {
+    pub vehicle: Entity,
+    pub action: RecoveryAction,
+}
+
+/// Where a traction board goes for a wheel at `wheel_position` touching the ground: flat on the
+/// ground, wedged just ahead of the tyre along `forward`
+pub fn board_placement(config: &RecoveryGearConfig, wheel_position: Vec3, radius: f32, up: Vec3, forward: Vec3) -> Transform {
+    let ground = wheel_position - up * (radius - config.board_size.y * 0.5);
+    let along = forward.reject_from_normalized(up).normalize_or_zero();
+    let translation = ground + along * config.board_size.z * 0.35;
+    Transform::from_translation(translation).looking_to(along, up)
+}
+
+/// Where a vehicle ends up pushed off a jack on `side`: lifted and moved towards the other side
+pub fn jacked_transform(config: &RecoveryGearConfig, transform: &Transform, side: JackSide) -> Transform {
+    let away = match side {
+        JackSide::Left => transform.right(),
+        JackSide::Right => transform.left(),
+    };
+    let sideways = away.reject_from_normalized(Vec3::Y).normalize_or_zero();
+    Transform {
+        translation: transform.translation + Vec3::Y * config.jack_lift + sideways * config.jack_shift,
+        ..*transform
+    }
+}
+
+/// Strap hook on the end of a vehicle facing `toward_local` (vehicle space), front is -Z
+pub fn recovery_point(half_length: f32, toward_local: Vec3) -> Vec3 {
+    let end = if toward_local.z <= 0.0 { -1.0 } else { 1.0 };
+    Vec3::new(0.0, -0.3, end * half_length)
+}
+
+/// Strap between two recovery points, slack up to `length` and free to swing
+pub fn tow_strap_joint(anchor1: Vec3, anchor2: Vec3, length: f32) -> GenericJoint {
+    GenericJointBuilder::new(JointAxesMask::empty())
+        .local_anchor1(anchor1)
+        .local_anchor2(anchor2)
+        .limits(JointAxis::X, [-length, length])
+        .limits(JointAxis::Y, [-length, length])
+        .limits(JointAxis::Z, [-length, length])
+        .build()
+}
+
+/// Counts down cooldowns, animations and boards on the ground, returning expired boards
+pub fn tick_recovery_gear(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut gear: Query<&mut RecoveryGear>,
+    mut animations: Query<(Entity, &mut RecoveryAnimation)>,
+    mut boards: Query<(Entity, &mut TractionBoard)>,
+) {
+    let dt = time.delta_seconds();
+    for mut gear in gear.iter_mut() {
+        if gear.cooldowns.iter().any(|cooldown| *cooldown > 0.0) {
+            gear.tick(dt);
+        }
+    }
+    for (entity, mut animation) in animations.iter_mut() {
+        animation.elapsed += dt;
+        if animation.elapsed >= animation.duration {
+            commands.entity(entity).remove::<RecoveryAnimation>();
+        }
+    }
+    for (entity, mut board) in boards.iter_mut() {
+        board.remaining -= dt;
+        if board.remaining <= 0.0 {
+            if let Ok(mut gear) = gear.get_mut(board.owner) {
+                gear.traction_boards += 1;
+            }
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Carries out recovery gear requests
+#[allow(clippy::too_many_arguments)]
+pub fn use_recovery_tools(
+    mut commands: Commands,
+    config: Res<RecoveryGearConfig>,
+    mut requests: EventReader<UseRecoveryToolEvent>,
+    mut used: EventWriter<RecoveryToolUsedEvent>,
+    mut vehicles: Query<(&Vehicle, &mut RecoveryGear, &mut Transform, Option<&mut Velocity>)>,
+    others: Query<(Entity, &Vehicle, &GlobalTransform, Option<&ImpulseJoint>)>,
+    wheels: Query<(&Wheel, &GlobalTransform)>,
+) {
+    for request in requests.read() {
+        let Ok((vehicle, mut gear, mut transform, velocity)) = vehicles.get_mut(request.vehicle) else {
+            continue;
+        };
+        if !gear.can_use(request.action) {
+            continue;
+        }
+        let tool = request.action.tool();
+
+        match request.action {
+            RecoveryAction::TractionBoard { wheel } => {
+                let Some((wheel, wheel_transform)) = vehicle
+                    .wheel_entities
+                    .get(wheel)
+                    .and_then(|&entity| wheels.get(entity).ok())
+                else {
+                    continue;
+                };
+                let placement = board_placement(
+                    &config,
+                    wheel_transform.translation(),
+                    wheel.radius,
+                    transform.up(),
+                    transform.forward(),
+                );
+                let half = config.board_size * 0.5;
+                commands.spawn((
+                    TransformBundle::from_transform(placement),
+                    RigidBody::Fixed,
+                    Collider::cuboid(half.x, half.y, half.z),
+                    Friction { coefficient: config.board_friction, combine_rule: CoefficientCombineRule::Max },
+                    SurfaceMaterial::Wood,
+                    TractionBoard { owner: request.vehicle, remaining: config.board_lifetime },
+                    Name::new("Traction Board"),
+                ));
+                gear.traction_boards -= 1;
+            }
+            RecoveryAction::Jack { side } => {
+                *transform = jacked_transform(&config, &transform, side);
+                if let Some(mut velocity) = velocity {
+                    *velocity = Velocity::zero();
+                }
+            }
+            RecoveryAction::TowStrap => {
+                if let Some(other) = gear.strapped_to.take() {
+                    commands.entity(other).remove::<ImpulseJoint>();
+                    gear.tow_straps += 1;
+                } else {
+                    // Anything already on a joint, a trailer or a strapped vehicle, can't take another
+                    let target = others
+                        .iter()
+                        .filter(|(entity, _, _, joint)| *entity != request.vehicle && joint.is_none())
+                        .map(|(entity, other, other_transform, _)| {
+                            (entity, other, other_transform, other_transform.translation().distance(transform.translation))
+                        })
+                        .filter(|(.., distance)| *distance <= config.strap_range)
+                        .min_by(|a, b| a.3.total_cmp(&b.3));
+                    let Some((target, other, other_transform, _)) = target else {
+                        continue;
+                    };
+                    let other_affine = other_transform.affine().inverse();
+                    let toward_other = transform.rotation.inverse() * (other_transform.translation() - transform.translation);
+                    let toward_us = other_affine.transform_vector3(transform.translation - other_transform.translation());
+                    let joint = tow_strap_joint(
+                        recovery_point(vehicle.config.dimensions.z * 0.5, toward_other),
+                        recovery_point(other.config.dimensions.z * 0.5, toward_us),
+                        config.strap_length,
+                    );
+                    commands.entity(target).insert(ImpulseJoint::new(request.vehicle, joint));
+                    gear.strapped_to = Some(target);
+                    gear.tow_straps -= 1;
+                }
+            }
+        }
+
+        gear.start_cooldown(tool, config.cooldown(tool));
+        commands.entity(request.vehicle).insert(RecoveryAnimation::new(tool));
+        used.send(RecoveryToolUsedEvent { vehicle: request.vehicle, action: request.action });
+    }
+}
+
+/// Shortens the strap of winch vehicles whose winch input is held
+pub fn reel_in_winches(
+    time: Res<Time>,
+    winches: Query<(&Winch, &RecoveryGear, &PlayerInput)>,
+    mut joints: Query<&mut ImpulseJoint>,
+) {
+    for (winch, gear, input) in winches.iter() {
+        if !input.winch {
+            continue;
+        }
+        let Some(mut joint) = gear.strapped_to.and_then(|other| joints.get_mut(other).ok()) else {
+            continue;
+        };
+        let Some(length) = joint.data.limits(JointAxis::X).map(|limits| limits.max) else {
+            continue;
+        };
+        let reeled = winch.reel_in(length, time.delta_seconds());
+        if reeled < length {
+            for axis in [JointAxis::X, JointAxis::Y, JointAxis::Z] {
+                joint.data.set_limits(axis, [-reeled, reeled]);
+            }
+        }
+    }
+}
+
+/// Plugin for traction boards, the hi-lift jack, tow straps and winches
+pub struct RecoveryGearPlugin;
+
+impl Plugin for RecoveryGearPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RecoveryGearConfig>()
+            .add_event::<UseRecoveryToolEvent>()
+            .add_event::<RecoveryToolUsedEvent>()
+            .add_systems(Update, (tick_recovery_gear, use_recovery_tools, reel_in_winches).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gear_needs_stock_and_cooldown() {
+        let mut gear = RecoveryGear { traction_boards: 1, ..default() };
+        let board = RecoveryAction::TractionBoard { wheel: 0 };
+        assert!(gear.can_use(board));
+        gear.start_cooldown(RecoveryTool::TractionBoards, 5.0);
+        assert!(!gear.can_use(board));
+        gear.tick(5.0);
+        assert!(gear.can_use(board));
+        gear.traction_boards = 0;
+        assert!(!gear.can_use(board));
+
+        // Unhooking works even with the only strap out and the cooldown running
+        gear.tow_straps = 0;
+        gear.strapped_to = Some(Entity::from_raw(7));
+        gear.start_cooldown(RecoveryTool::TowStrap, 2.0);
+        assert!(gear.can_use(RecoveryAction::TowStrap));
+    }
+
+    #[test]
+    fn test_jack_lifts_and_pushes_away_from_the_jack() {
+        let config = RecoveryGearConfig::default();
+        let transform = Transform::from_xyz(0.0, 1.0, 0.0);
+        let jacked = jacked_transform(&config, &transform, JackSide::Left);
+        assert!((jacked.translation.y - 1.0 - config.jack_lift).abs() < 1e-5);
+        assert!((jacked.translation.x - config.jack_shift).abs() < 1e-5);
+        assert_eq!(jacked.rotation, transform.rotation);
+    }
+
+    #[test]
+    fn test_board_goes_under_and_ahead_of_the_wheel() {
+        let config = RecoveryGearConfig::default();
+        let board = board_placement(&config, Vec3::new(1.0, 0.4, 0.0), 0.4, Vec3::Y, Vec3::NEG_Z);
+        assert!((board.translation.y - config.board_size.y * 0.5).abs() < 1e-5);
+        assert!(board.translation.z < 0.0);
+        assert!(board.forward().abs_diff_eq(Vec3::NEG_Z, 1e-5));
+    }
+
+    #[test]
+    fn test_winch_reels_in_down_to_its_minimum() {
+        let winch = Winch::default();
+        assert!((winch.reel_in(6.0, 1.0) - (6.0 - winch.pull_speed)).abs() < 1e-5);
+        assert_eq!(winch.reel_in(1.1, 1.0), winch.min_length);
+        // A strap already shorter than the minimum isn't let out
+        assert_eq!(winch.reel_in(0.5, 1.0), 0.5);
+    }
+
+    #[test]
+    fn test_strap_hooks_the_facing_ends() {
+        assert_eq!(recovery_point(2.0, Vec3::new(0.0, 0.0, -5.0)).z, -2.0);
+        assert_eq!(recovery_point(2.0, Vec3::new(1.0, 0.0, 5.0)).z, 2.0);
+    }
+}
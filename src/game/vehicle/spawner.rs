@@ -0,0 +1,496 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use std::f32::consts::FRAC_PI_2;
+
+use super::{
+    impostor_bounds, impostor_image, underbody_shapes, vehicle_body_material, wheel_mount, Bumper, CargoBed, Chassis,
+    CockpitLayout, DirtState, DriverAssists, Drivetrain, EngineTemperature, FuelTank, MaterialOverride, RecoveryGear,
+    Suspension, TowHitch, UnderbodyContact, Vehicle, VehicleBodyMaterial, VehicleBundle, VehicleConfig,
+    VehicleCustomization, VehicleDirtMaterials, VehicleImpostor, VehicleLod, VehicleLodMeshes, Wheel, WheelBundle,
+    WheelHub, Winch,
+};
+use crate::game::constants::JEEP_LENGTH;
+use crate::game::plugins::{
+    spawn_dashboard_gauges, spawn_vehicle_headlights, GameCamera, PlayerId, PlayerInput, PlayerInputDevice, Relevance,
+    SurfaceMaterial,
+};
+use crate::game::{GameState, StateScoped};
+
+/// Everything a vehicle is assembled from, the prefab for one kind of vehicle
+#[derive(Debug, Clone)]
+pub struct VehicleDefinition {
+    pub config: VehicleConfig,
+    pub body_color: Color,
+    pub wheel_color: Color,
+    pub headlights: bool,
+    /// Looping engine sound, `None` for a silent vehicle
+    pub engine_sound: Option<String>,
+    /// Tires, lift kit and bumper fitted on top of `config`
+    pub customization: VehicleCustomization,
+    /// Paint finish used for the body instead of a plain `body_color` material
+    pub material_override: Option<MaterialOverride>,
+}
+
+impl Default for VehicleDefinition {
+    fn default() -> Self {
+        Self {
+            config: VehicleConfig::default(),
+            body_color: Color::rgb(0.55, 0.1, 0.1),
+            wheel_color: Color::rgb(0.1, 0.1, 0.1),
+            headlights: true,
+            engine_sound: Some("sounds/engine.ogg".to_string()),
+            customization: VehicleCustomization::default(),
+            material_override: None,
+        }
+    }
+}
+
+/// Who controls a spawned vehicle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerOrAi {
+    Player(usize),
+    Ai,
+}
+
+/// Marks a vehicle driven by the AI rather than a player
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct AiDriver;
+
+/// Looping engine sound attached to a vehicle
+#[derive(Component)]
+pub struct VehicleEngineAudio;
+
+#[derive(Event, Debug, Clone)]
+pub struct SpawnVehicleEvent {
+    pub definition: VehicleDefinition,
+    pub transform: Transform,
+    pub driver: PlayerOrAi,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DespawnVehicleEvent {
+    pub vehicle: Entity,
+}
+
+/// Sent once a vehicle has been assembled so races, traffic and netcode can pick up its entity
+#[derive(Event, Debug, Clone, Copy)]
+pub struct VehicleSpawnedEvent {
+    pub vehicle: Entity,
+    pub driver: PlayerOrAi,
+}
+
+/// Wheel position in chassis space at the suspension's rest length
+pub fn wheel_rest_position(config: &VehicleConfig, index: usize) -> Vec3 {
+    let mount_height = -config.dimensions.y * 0.5 + config.suspension_config.max_length;
+    wheel_mount(config, index, mount_height) - Vec3::Y * config.suspension_config.rest_length
+}
+
+/// Spawns and removes complete vehicles, usable directly from systems that need the entity right away
+#[derive(SystemParam)]
+pub struct VehicleSpawner<'w, 's> {
+    commands: Commands<'w, 's>,
+    asset_server: Res<'w, AssetServer>,
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<StandardMaterial>>,
+    /// Missing without a renderer, bodies then get a plain material instead
+    body_materials: Option<ResMut<'w, Assets<VehicleBodyMaterial>>>,
+    images: ResMut<'w, Assets<Image>>,
+    joints: Query<'w, 's, (Entity, &'static ImpulseJoint)>,
+}
+
+impl<'w, 's> VehicleSpawner<'w, 's> {
+    /// Assembles chassis, wheels, suspension, engine audio, lights and driver components
+    pub fn spawn(&mut self, request: &SpawnVehicleEvent) -> Entity {
+        let definition = &request.definition;
+        let mut config = definition.config.clone();
+        definition.customization.apply(&mut config);
+        let config = &config;
+        let tires = definition.customization.tires.spec();
+        let stock_wheel = Wheel::default();
+
+        let body_material = definition.material_override.map_or_else(
+            || StandardMaterial {
+                base_color: definition.body_color,
+                perceptual_roughness: 0.6,
+                ..default()
+            },
+            |material| material.material(),
+        );
+        let wheel_material = self.materials.add(StandardMaterial {
+            base_color: definition.wheel_color,
+            perceptual_roughness: 0.9,
+            ..default()
+        });
+
+        // Lower detail meshes and the impostor for when the vehicle is far from every camera
+        let impostor = self.spawn_impostor(definition, config);
+        let lod_meshes = VehicleLodMeshes::build(
+            &mut self.meshes,
+            Mesh::from(shape::Box::new(config.dimensions.x, config.dimensions.y, config.dimensions.z)),
+            shape::Cylinder {
+                radius: config.wheel_radius,
+                height: tires.width,
+                ..default()
+            },
+            impostor,
+        );
+        let body_mesh = lod_meshes.body[0].clone();
+        let wheel_mesh = lod_meshes.wheel[0].clone();
+
+        let suspension_points: [Vec3; 4] = std::array::from_fn(|index| {
+            wheel_mount(config, index, -config.dimensions.y * 0.5 + config.suspension_config.max_length)
+        });
+        let wheels: [Entity; 4] = std::array::from_fn(|index| {
+            let wheel = Wheel {
+                position: index,
+                radius: config.wheel_radius,
+                width: tires.width,
+                mass: tires.mass,
+                // Heavier tires spin up slower, the stock tire keeps the stock inertia
+                inertia: stock_wheel.inertia * tires.mass / stock_wheel.mass,
+                rolling_resistance: tires.rolling_resistance,
+                ..default()
+            };
+            self.commands
+                .spawn((
+                    WheelBundle {
+                        collider: Collider::cylinder(wheel.width / 2.0, wheel.radius),
+                        mass_properties: ColliderMassProperties::Mass(wheel.mass),
+                        friction: Friction::coefficient(tires.grip),
+                        wheel,
+                        // Cylinder axis along the axle
+                        transform: Transform::from_translation(wheel_rest_position(config, index))
+                            .with_rotation(Quat::from_rotation_z(FRAC_PI_2)),
+                        ..default()
+                    },
+                    WheelHub::new(index, suspension_points[index]),
+                    Suspension {
+                        spring_stiffness: config.suspension_config.spring_strength,
+                        damping: config.suspension_config.damping,
+                        rest_length: config.suspension_config.rest_length,
+                        mount_point: suspension_points[index],
+                        wheel_point: wheel_rest_position(config, index),
+                        ..default()
+                    },
+                    Velocity::default(),
+                    wheel_mesh.clone(),
+                    wheel_material.clone(),
+                    VisibilityBundle::default(),
+                    Name::new(format!("Wheel {index}")),
+                ))
+                .id()
+        });
+
+        // Tow ball and load bed at the rear of the body, whatever its length
+        let hitch = TowHitch {
+            offset: Vec3::new(0.0, TowHitch::default().offset.y, config.dimensions.z * 0.5 + 0.25),
+            trailer: None,
+        };
+        let bed = CargoBed::default();
+        let bed = CargoBed {
+            offset: bed.offset + Vec3::Z * (config.dimensions.z - JEEP_LENGTH) * 0.5,
+            ..bed
+        };
+
+        let vehicle = self
+            .commands
+            .spawn((
+                VehicleBundle {
+                    vehicle: Vehicle {
+                        config: config.clone(),
+                        wheel_entities: wheels,
+                        ..default()
+                    },
+                    collider: Collider::cuboid(
+                        config.dimensions.x / 2.0,
+                        config.dimensions.y / 2.0,
+                        config.dimensions.z / 2.0,
+                    ),
+                    mass_properties: ColliderMassProperties::Mass(config.mass),
+                    transform: request.transform,
+                    name: Name::new(config.name.clone()),
+                    ..default()
+                },
+                Chassis {
+                    suspension_points,
+                    mass: config.mass,
+                    center_of_mass_offset: config.center_of_mass,
+                    ..default()
+                },
+                Velocity::default(),
+                DriverAssists::default(),
+                Drivetrain::default(),
+                UnderbodyContact::default(),
+                RecoveryGear::default(),
+                FuelTank::default(),
+                EngineTemperature::default(),
+                ExternalImpulse::default(),
+                SurfaceMaterial::Metal,
+                body_mesh,
+                DirtState::default(),
+                VisibilityBundle::default(),
+                // Wheels and fittings are children and go with it
+                StateScoped(GameState::InGame),
+            ))
+            .insert((VehicleLod::default(), lod_meshes, Relevance::with_radius(config.dimensions.length() * 0.5)))
+            .insert((hitch, bed))
+            .push_children(&wheels)
+            .with_children(|parent| {
+                // Underbody hardware rides on the chassis body so it can hang up on rocks
+                for shape in underbody_shapes(config) {
+                    parent.spawn((
+                        shape.collider,
+                        shape.part,
+                        SurfaceMaterial::Metal,
+                        Friction::coefficient(0.4),
+                        TransformBundle::from_transform(Transform::from_translation(shape.offset)),
+                        Name::new(format!("{:?}", shape.part)),
+                    ));
+                }
+            })
+            .id();
+
+        // The dirt material shows mud and dust on the paint and reflects the surroundings off it
+        match self.body_materials.as_mut() {
+            Some(body_materials) => {
+                let material = body_materials.add(vehicle_body_material(body_material));
+                self.commands.entity(vehicle).insert((material.clone(), VehicleDirtMaterials(vec![material])));
+            }
+            None => {
+                let material = self.materials.add(body_material);
+                self.commands.entity(vehicle).insert(material);
+            }
+        }
+
+        if let Some((size, offset)) = definition.customization.bumper.fitment(config.dimensions) {
+            let bumper = self
+                .commands
+                .spawn((
+                    PbrBundle {
+                        mesh: self.meshes.add(Mesh::from(shape::Box::new(size.x, size.y, size.z))),
+                        material: self.materials.add(StandardMaterial {
+                            base_color: Color::rgb(0.08, 0.08, 0.08),
+                            perceptual_roughness: 0.5,
+                            ..default()
+                        }),
+                        transform: Transform::from_translation(offset),
+                        ..default()
+                    },
+                    Name::new(format!("{:?} Bumper", definition.customization.bumper)),
+                ))
+                .id();
+            self.commands.entity(vehicle).add_child(bumper);
+        }
+        if definition.customization.bumper == Bumper::Winch {
+            self.commands.entity(vehicle).insert(Winch::default());
+        }
+
+        if let Some(sound) = &definition.engine_sound {
+            let audio = self
+                .commands
+                .spawn((
+                    AudioBundle {
+                        source: self.asset_server.load(sound.clone()),
+                        settings: PlaybackSettings::LOOP.with_spatial(true),
+                    },
+                    TransformBundle::default(),
+                    VehicleEngineAudio,
+                ))
+                .id();
+            self.commands.entity(vehicle).add_child(audio);
+        }
+
+        if definition.headlights {
+            let front = Vec3::new(0.0, 0.0, -config.dimensions.z * 0.5 - 0.05);
+            spawn_vehicle_headlights(&mut self.commands, vehicle, front, config.track_width * 0.8);
+        }
+        // Just behind the needles, lit up at night for the hood camera
+        let cockpit = CockpitLayout::for_config(&config);
+        let dash = (cockpit.speedometer + cockpit.tachometer) * 0.5 + Vec3::NEG_Z * 0.01;
+        spawn_dashboard_gauges(&mut self.commands, &mut self.meshes, &mut self.materials, vehicle, dash);
+
+        match request.driver {
+            PlayerOrAi::Player(id) => {
+                let device = if id == 0 {
+                    PlayerInputDevice::Keyboard
+                } else {
+                    PlayerInputDevice::Gamepad
+                };
+                self.commands
+                    .entity(vehicle)
+                    .insert((PlayerId(id), device, PlayerInput::default()));
+            }
+            PlayerOrAi::Ai => {
+                self.commands.entity(vehicle).insert(AiDriver);
+            }
+        }
+
+        let (bottom, top) = impostor_bounds(config);
+        self.commands.entity(impostor).insert(VehicleImpostor {
+            vehicle,
+            offset: (top + bottom) * 0.5,
+            size: config.dimensions.xz(),
+        });
+
+        vehicle
+    }
+
+    /// Hidden side on sprite of the vehicle, its vehicle is filled in once spawned
+    fn spawn_impostor(&mut self, definition: &VehicleDefinition, config: &VehicleConfig) -> Entity {
+        let (bottom, top) = impostor_bounds(config);
+        let image = self.images.add(impostor_image(config, definition.body_color, definition.wheel_color));
+        self.commands
+            .spawn((
+                PbrBundle {
+                    mesh: self.meshes.add(Mesh::from(shape::Quad::new(Vec2::new(config.dimensions.z, top - bottom)))),
+                    material: self.materials.add(StandardMaterial {
+                        base_color_texture: Some(image),
+                        alpha_mode: AlphaMode::Mask(0.5),
+                        unlit: true,
+                        double_sided: true,
+                        cull_mode: None,
+                        ..default()
+                    }),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                Name::new(format!("{} Impostor", config.name)),
+                StateScoped(GameState::InGame),
+            ))
+            .id()
+    }
+
+    /// Removes a vehicle with all its parts and detaches anything jointed to it
+    pub fn despawn(&mut self, vehicle: Entity) {
+        // Trailers and strapped cargo stay in the world, just no longer attached
+        for (entity, joint) in self.joints.iter() {
+            if joint.parent == vehicle {
+                self.commands.entity(entity).remove::<ImpulseJoint>();
+            }
+        }
+        if let Some(entity) = self.commands.get_entity(vehicle) {
+            entity.despawn_recursive();
+        }
+    }
+}
+
+fn handle_spawn_vehicle_events(
+    mut spawner: VehicleSpawner,
+    mut requests: EventReader<SpawnVehicleEvent>,
+    mut spawned: EventWriter<VehicleSpawnedEvent>,
+) {
+    for request in requests.read() {
+        let vehicle = spawner.spawn(request);
+        spawned.send(VehicleSpawnedEvent {
+            vehicle,
+            driver: request.driver,
+        });
+    }
+}
+
+/// Points each player's camera at the vehicle they were just given
+fn target_player_cameras(
+    mut spawned: EventReader<VehicleSpawnedEvent>,
+    mut cameras: Query<(Entity, &mut GameCamera, Option<&PlayerId>)>,
+) {
+    for event in spawned.read() {
+        let PlayerOrAi::Player(id) = event.driver else {
+            continue;
+        };
+        let owned = cameras
+            .iter()
+            .find(|(_, _, player)| player.map(|player| player.0) == Some(id))
+            .map(|(camera, _, _)| camera);
+        // Player one's camera may not have been claimed yet
+        let unclaimed = || {
+            cameras
+                .iter()
+                .find(|(_, _, player)| id == 0 && player.is_none())
+                .map(|(camera, _, _)| camera)
+        };
+        if let Some(camera) = owned.or_else(unclaimed) {
+            if let Ok((_, mut camera, _)) = cameras.get_mut(camera) {
+                camera.target = Some(event.vehicle);
+            }
+        }
+    }
+}
+
+fn handle_despawn_vehicle_events(
+    mut spawner: VehicleSpawner,
+    mut requests: EventReader<DespawnVehicleEvent>,
+    mut cameras: Query<&mut GameCamera>,
+) {
+    for request in requests.read() {
+        spawner.despawn(request.vehicle);
+        for mut camera in cameras.iter_mut() {
+            if camera.target == Some(request.vehicle) {
+                camera.target = None;
+            }
+        }
+    }
+}
+
+/// Plugin handling vehicle spawn and despawn requests
+pub struct VehicleSpawnerPlugin;
+
+impl Plugin for VehicleSpawnerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SpawnVehicleEvent>()
+            .add_event::<DespawnVehicleEvent>()
+            .add_event::<VehicleSpawnedEvent>()
+            .add_systems(Update, (
+                handle_despawn_vehicle_events,
+                handle_spawn_vehicle_events,
+                target_player_cameras,
+            ).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wheels_sit_at_the_corners() {
+        let config = VehicleConfig::default();
+        let front_left = wheel_rest_position(&config, 0);
+        let rear_right = wheel_rest_position(&config, 3);
+        assert!(front_left.x < 0.0 && front_left.z < 0.0);
+        assert!(rear_right.x > 0.0 && rear_right.z > 0.0);
+        assert!((rear_right.z - front_left.z - config.wheelbase).abs() < 1e-5);
+        assert!((rear_right.x - front_left.x - config.track_width).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_wheels_hang_below_the_body() {
+        let config = VehicleConfig::default();
+        for index in 0..4 {
+            assert!(wheel_rest_position(&config, index).y < config.dimensions.y * 0.5);
+        }
+    }
+
+    #[test]
+    fn test_spawned_vehicle_has_night_lights() {
+        use crate::game::plugins::{DashboardEmissive, VehicleHeadlight};
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), VehicleSpawnerPlugin))
+            .init_asset::<Mesh>()
+            .init_asset::<StandardMaterial>()
+            .init_asset::<Image>();
+        app.world.send_event(SpawnVehicleEvent {
+            definition: VehicleDefinition { engine_sound: None, ..default() },
+            transform: Transform::from_xyz(0.0, 1.0, 0.0),
+            driver: PlayerOrAi::Ai,
+        });
+        app.update();
+
+        let mut headlights = app.world.query_filtered::<&Transform, With<VehicleHeadlight>>();
+        let headlights: Vec<_> = headlights.iter(&app.world).collect();
+        assert_eq!(headlights.len(), 2);
+        assert!(headlights.iter().all(|light| light.translation.z < 0.0));
+        assert_eq!(app.world.query::<&DashboardEmissive>().iter(&app.world).count(), 2);
+    }
+}
@@ -2,6 +2,9 @@ use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use super::Chassis;
+use crate::physics::ForceAccumulator;
+
 /// Different types of suspension systems
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub enum SuspensionType {
@@ -269,6 +272,52 @@ impl Suspension {
     }
 }
 
+/// Current state of a suspension unit
+#[derive(Debug, Clone, Copy)]
+pub struct SuspensionState {
+    pub compression: f32,
+    pub velocity: f32,
+    pub force: Vec3,
+    pub ground_contact: bool,
+    pub ground_normal: Vec3,
+    pub ground_point: Vec3,
+}
+
+impl Default for SuspensionState {
+    fn default() -> Self {
+        Self {
+            compression: 0.0,
+            velocity: 0.0,
+            force: Vec3::ZERO,
+            ground_contact: false,
+            ground_normal: -Vec3::Y,
+            ground_point: Vec3::ZERO,
+        }
+    }
+}
+
+/// One corner of a vehicle, on each wheel entity parented to the chassis. Carries that wheel's
+/// suspension state, so systems query the wheels rather than index arrays on the vehicle.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct WheelHub {
+    /// Wheel index (FL: 0, FR: 1, RL: 2, RR: 3)
+    pub index: usize,
+    /// Upper suspension mount in chassis space
+    pub mount: Vec3,
+    /// Updated by the suspension every physics frame
+    pub state: SuspensionState,
+}
+
+impl WheelHub {
+    pub fn new(index: usize, mount: Vec3) -> Self {
+        Self {
+            index,
+            mount,
+            state: SuspensionState::default(),
+        }
+    }
+}
+
 /// Bundle for spawning a suspension with all necessary components
 #[derive(Bundle)]
 pub struct SuspensionBundle {
@@ -287,67 +336,72 @@ impl Default for SuspensionBundle {
     }
 }
 
-/// System to update suspension physics and handle damage
+/// Updates each wheel's suspension from where its wheel hangs below the mount, and handles damage
 pub fn update_suspension_physics(
-    mut suspension_query: Query<(&mut Suspension, &GlobalTransform)>,
-    wheel_query: Query<(&Transform, &Wheel)>,
-    chassis_query: Query<(&Transform, &Chassis)>,
-    rapier_context: Res<RapierContext>,
+    mut hubs: Query<(&mut WheelHub, &mut Suspension, &Transform, &Parent)>,
+    chassis_query: Query<&GlobalTransform, With<Chassis>>,
     time: Res<Time>,
 ) {
     let dt = time.delta_seconds();
+    if dt <= 0.0 {
+        return;
+    }
 
-    for (mut suspension, suspension_global) in suspension_query.iter_mut() {
+    for (mut hub, mut suspension, transform, parent) in hubs.iter_mut() {
         // Skip physics update if suspension is broken
         if suspension.is_broken {
             continue;
         }
+        let Ok(chassis_transform) = chassis_query.get(parent.get()) else {
+            continue;
+        };
 
-        // Get chassis transform
-        if let Ok((chassis_transform, chassis)) = chassis_query.get_single() {
-            // Transform suspension mount points to world space
-            let world_mount = chassis_transform.transform_point(suspension.mount_point);
-            let world_wheel = chassis_transform.transform_point(suspension.wheel_point);
-
-            // Calculate current length
-            let current_length = (world_mount - world_wheel).length();
-            
-            // Update compression
-            let prev_compression = suspension.compression;
-            suspension.compression = suspension.rest_length - current_length;
-            suspension.compression = suspension.compression.clamp(
-                -suspension.max_extension,
-                suspension.max_compression
-            );
-
-            // Calculate velocity
-            suspension.velocity = (suspension.compression - prev_compression) / dt;
-
-            // Calculate spring force
-            let spring_force = suspension.spring_stiffness * suspension.compression;
-            
-            // Calculate damping force
-            let damping_force = suspension.damping * suspension.velocity;
-            
-            // Total force
-            suspension.force = spring_force + damping_force;
-
-            // Handle damage
-            update_suspension_damage(&mut suspension, dt);
-
-            // Apply geometry correction from lift kit if present
-            if let Some(lift_kit) = &suspension.lift_kit {
-                suspension.force *= lift_kit.geometry_correction;
-            }
+        // The wheel is a child of the chassis, so both ends are already in chassis space
+        suspension.mount_point = hub.mount;
+        suspension.wheel_point = transform.translation;
+        let current_length = (suspension.mount_point - suspension.wheel_point).length();
+
+        // Update compression
+        let prev_compression = suspension.compression;
+        suspension.compression = suspension.rest_length - current_length;
+        suspension.compression = suspension.compression.clamp(
+            -suspension.max_extension,
+            suspension.max_compression
+        );
+
+        // Calculate velocity
+        suspension.velocity = (suspension.compression - prev_compression) / dt;
 
-            // Ensure force is within physical limits (adjusted by health)
-            let max_force = if suspension.health < 100.0 {
-                suspension.damage_threshold * (suspension.health / 100.0)
-            } else {
-                suspension.damage_threshold
-            };
-            suspension.force = suspension.force.clamp(-max_force, max_force);
+        // Calculate spring force
+        let spring_force = suspension.spring_stiffness * suspension.compression;
+
+        // Calculate damping force
+        let damping_force = suspension.damping * suspension.velocity;
+
+        // Total force
+        suspension.force = spring_force + damping_force;
+
+        // Handle damage
+        update_suspension_damage(&mut suspension, dt);
+
+        // Apply geometry correction from lift kit if present
+        if let Some(lift_kit) = &suspension.lift_kit {
+            suspension.force *= lift_kit.geometry_correction;
         }
+
+        // Ensure force is within physical limits (adjusted by health)
+        let max_force = if suspension.health < 100.0 {
+            suspension.damage_threshold * (suspension.health / 100.0)
+        } else {
+            suspension.damage_threshold
+        };
+        suspension.force = suspension.force.clamp(-max_force, max_force);
+
+        let axis = (suspension.mount_point - suspension.wheel_point).normalize_or_zero();
+        hub.state.compression = suspension.compression;
+        hub.state.velocity = suspension.velocity;
+        hub.state.force = chassis_transform.affine().transform_vector3(axis) * suspension.force;
+        hub.state.ground_contact = suspension.compression > 0.0;
     }
 }
 
@@ -392,36 +446,25 @@ fn update_suspension_damage(suspension: &mut Suspension, dt: f32) {
     }
 }
 
-/// System to apply suspension forces to chassis and wheels, accounting for damage
+/// Pushes each chassis up at its suspension mounts, accounting for damage
 pub fn apply_suspension_forces(
-    suspension_query: Query<(&Suspension, &GlobalTransform)>,
-    mut chassis_query: Query<(&mut ExternalForce, &Transform), With<Chassis>>,
-    mut wheel_query: Query<(&mut ExternalForce, &Transform), With<Wheel>>,
+    hubs: Query<(&WheelHub, &Suspension, &Parent)>,
+    mut chassis_query: Query<(&mut ForceAccumulator, &GlobalTransform), With<Chassis>>,
 ) {
-    if let Ok((mut chassis_force, chassis_transform)) = chassis_query.get_single_mut() {
-        for (suspension, suspension_global) in suspension_query.iter() {
-            // Skip if suspension is broken
-            if suspension.is_broken {
-                continue;
-            }
-
-            // Calculate force direction
-            let force_dir = (suspension.mount_point - suspension.wheel_point).normalize();
-            let force_vec = force_dir * suspension.force;
-
-            // Apply force to chassis (modified by health)
-            let health_factor = suspension.health / 100.0;
-            chassis_force.force += force_vec * health_factor;
-            
-            // Calculate torque on chassis
-            let r = suspension.mount_point - chassis_transform.translation;
-            chassis_force.torque += r.cross(force_vec) * health_factor;
-
-            // Apply opposite force to wheel
-            if let Ok((mut wheel_force, _)) = wheel_query.get_single_mut() {
-                wheel_force.force -= force_vec * health_factor;
-            }
+    for (hub, suspension, parent) in hubs.iter() {
+        // Skip if suspension is broken
+        if suspension.is_broken {
+            continue;
         }
+        let Ok((mut chassis_force, chassis_transform)) = chassis_query.get_mut(parent.get()) else {
+            continue;
+        };
+
+        // Apply force to chassis (modified by health)
+        let health_factor = suspension.health / 100.0;
+        let force_vec = hub.state.force * health_factor;
+        let mount = chassis_transform.transform_point(hub.mount);
+        chassis_force.add_force_at_point(force_vec, mount, chassis_transform.translation());
     }
 }
 
@@ -459,17 +502,17 @@ impl Default for SuspensionDebugConfig {
     }
 }
 
-/// System to draw debug visualizations for suspension
+/// System to draw debug visualizations for each wheel's suspension
 pub fn draw_suspension_debug(
     mut gizmos: Gizmos,
-    suspension_query: Query<(&Suspension, &GlobalTransform)>,
-    chassis_query: Query<&Transform, With<Chassis>>,
+    hubs: Query<(&WheelHub, &Suspension, &GlobalTransform, &Parent)>,
+    chassis_query: Query<&GlobalTransform, With<Chassis>>,
     debug_config: Res<SuspensionDebugConfig>,
 ) {
-    if let Ok(chassis_transform) = chassis_query.get_single() {
-        for (suspension, suspension_global) in suspension_query.iter() {
-            let world_mount = chassis_transform.transform_point(suspension.mount_point);
-            let world_wheel = chassis_transform.transform_point(suspension.wheel_point);
+    for (hub, suspension, wheel_transform, parent) in hubs.iter() {
+        if let Ok(chassis_transform) = chassis_query.get(parent.get()) {
+            let world_mount = chassis_transform.transform_point(hub.mount);
+            let world_wheel = wheel_transform.translation();
 
             // Draw mount points
             if debug_config.show_mount_points {
@@ -483,8 +526,7 @@ pub fn draw_suspension_debug(
 
             // Draw force vector
             if debug_config.show_forces {
-                let force_dir = (suspension.mount_point - suspension.wheel_point).normalize();
-                let force_vec = force_dir * suspension.force * debug_config.force_scale;
+                let force_vec = hub.state.force * debug_config.force_scale;
                 let force_color = if suspension.force > 0.0 {
                     Color::GREEN
                 } else {
@@ -650,6 +692,32 @@ pub fn draw_suspension_debug(
     }
 }
 
-// Add this to your plugin setup:
-// app.init_resource::<SuspensionDebugConfig>()
-//    .add_systems(Update, draw_suspension_debug); 
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_each_wheel_hub_tracks_its_own_suspension() {
+        let mut app = App::new();
+        app.init_resource::<Time>().add_systems(Update, update_suspension_physics);
+        let mount = Vec3::new(-0.75, -0.2, -1.19);
+        let chassis = app.world.spawn((Chassis::default(), GlobalTransform::IDENTITY)).id();
+        // One wheel pushed 0.1 m up into its travel, the other hanging at rest
+        let hubs = [0.1, 0.0].map(|compression| {
+            let rest = Suspension::default().rest_length;
+            let wheel = Transform::from_translation(mount - Vec3::Y * (rest - compression));
+            app.world.spawn((WheelHub::new(0, mount), Suspension::default(), wheel)).set_parent(chassis).id()
+        });
+        app.world.resource_mut::<Time>().advance_by(Duration::from_secs_f32(0.1));
+        app.update();
+
+        let compressed = app.world.get::<WheelHub>(hubs[0]).unwrap().state;
+        assert!((compressed.compression - 0.1).abs() < 1e-4);
+        assert!(compressed.ground_contact);
+        assert!(compressed.force.y > 0.0);
+        let resting = app.world.get::<WheelHub>(hubs[1]).unwrap().state;
+        assert!(resting.compression.abs() < 1e-4);
+        assert!(!resting.ground_contact);
+    }
+} 
\ No newline at end of file
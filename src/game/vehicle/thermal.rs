@@ -0,0 +1,293 @@
+use bevy::prelude::*;
+
+use super::{Drivetrain, Engine, Vehicle};
+use crate::game::constants::JEEP_HEIGHT;
+use crate::game::plugins::{sample_fluid, FluidVolume};
+
+/// Thermal state of a vehicle's engine
+#[derive(Component, Debug, Clone)]
+pub struct EngineTemperature {
+    /// Coolant temperature in °C
+    pub temperature: f32,
+    /// Radiator damage (0.0 intact - 1.0 destroyed), cuts cooling
+    pub radiator_damage: f32,
+    /// Water got into the intake; the engine won't start until it's pulled out of the water
+    pub intake_flooded: bool,
+}
+
+impl Default for EngineTemperature {
+    fn default() -> Self {
+        Self {
+            temperature: EngineThermalConfig::default().ambient,
+            radiator_damage: 0.0,
+            intake_flooded: false,
+        }
+    }
+}
+
+/// Tuning for the engine thermal model, temperatures in °C
+#[derive(Resource, Clone, Debug)]
+pub struct EngineThermalConfig {
+    pub ambient: f32,
+    /// Thermostat opening point, the radiator only starts working above it
+    pub operating: f32,
+    /// Power starts dropping above this
+    pub overheat: f32,
+    /// Engine stalls at this temperature
+    pub critical: f32,
+    /// Heat added per second at full throttle and redline
+    pub heat_rate: f32,
+    /// Heat added per second at idle
+    pub idle_heat_rate: f32,
+    /// Cooling per second per degree above ambient with the radiator fan alone
+    pub fan_cooling: f32,
+    /// Extra cooling per second per degree above ambient per m/s of airflow
+    pub airflow_cooling: f32,
+    /// Cooling per second per degree above ambient with the engine off
+    pub stalled_cooling: f32,
+    /// Sudden temperature jump when the radiator is holed, scaled by the damage taken
+    pub radiator_damage_spike: f32,
+    pub redline_rpm: f32,
+    /// Air intake height above the bottom of the body, with a snorkel this goes up to the roof
+    pub intake_height: f32,
+    /// Power multiplier right at the critical temperature
+    pub min_power_factor: f32,
+}
+
+impl Default for EngineThermalConfig {
+    fn default() -> Self {
+        Self {
+            ambient: 25.0,
+            operating: 90.0,
+            overheat: 110.0,
+            critical: 125.0,
+            heat_rate: 3.0,
+            idle_heat_rate: 0.6,
+            fan_cooling: 0.012,
+            airflow_cooling: 0.004,
+            stalled_cooling: 0.006,
+            radiator_damage_spike: 20.0,
+            redline_rpm: 6000.0,
+            intake_height: 0.9,
+            min_power_factor: 0.4,
+        }
+    }
+}
+
+impl EngineTemperature {
+    /// Advances the temperature by `dt` seconds at the given throttle, RPM and airflow speed in m/s.
+    /// Returns whether a running engine reached the critical temperature and has to stall.
+    pub fn step(
+        &mut self,
+        config: &EngineThermalConfig,
+        running: bool,
+        throttle: f32,
+        rpm: f32,
+        airflow: f32,
+        dt: f32,
+    ) -> bool {
+        let above_ambient = (self.temperature - config.ambient).max(0.0);
+
+        if !running {
+            self.temperature -= config.stalled_cooling * above_ambient * dt;
+            return false;
+        }
+
+        let load = throttle.clamp(0.0, 1.0) * (rpm / config.redline_rpm).clamp(0.0, 1.0);
+        let heat = config.idle_heat_rate + (config.heat_rate - config.idle_heat_rate) * load;
+
+        // The thermostat holds coolant in the block until it reaches operating temperature
+        let cooling = if self.temperature >= config.operating {
+            let radiator = 1.0 - self.radiator_damage.clamp(0.0, 1.0);
+            (config.fan_cooling + config.airflow_cooling * airflow.max(0.0)) * radiator * above_ambient
+        } else {
+            0.0
+        };
+
+        self.temperature += (heat - cooling) * dt;
+        self.temperature >= config.critical
+    }
+
+    /// Adds radiator damage and the heat spike that comes with losing coolant
+    pub fn damage_radiator(&mut self, config: &EngineThermalConfig, amount: f32) {
+        let taken = amount.clamp(0.0, 1.0 - self.radiator_damage);
+        self.radiator_damage += taken;
+        self.temperature += config.radiator_damage_spike * taken;
+    }
+
+    /// Power multiplier from heat, 1.0 below the overheat point
+    pub fn power_factor(&self, config: &EngineThermalConfig) -> f32 {
+        let t = ((self.temperature - config.overheat) / (config.critical - config.overheat)).clamp(0.0, 1.0);
+        1.0 - t * (1.0 - config.min_power_factor)
+    }
+
+    /// Whether the ignition can crank the engine, not with a flooded intake or before it has cooled off
+    pub fn can_start(&self, config: &EngineThermalConfig) -> bool {
+        !self.intake_flooded && self.temperature < config.operating
+    }
+}
+
+/// Damage to a vehicle's radiator, e.g. from a frontal hit
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RadiatorDamageEvent {
+    pub vehicle: Entity,
+    /// Damage to add (0.0 - 1.0)
+    pub amount: f32,
+}
+
+/// Why an engine stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineStallReason {
+    Overheated,
+    IntakeFlooded,
+    /// Dragged below its stall speed in too high a gear
+    Lugged,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct EngineStalledEvent {
+    pub vehicle: Entity,
+    pub reason: EngineStallReason,
+}
+
+/// Heats and cools engines, and stalls them when they overheat or water gets into the intake
+pub fn update_engine_temperature(
+    time: Res<Time>,
+    config: Res<EngineThermalConfig>,
+    fluids: Query<(&FluidVolume, &GlobalTransform)>,
+    mut vehicles: Query<(Entity, &Vehicle, &Engine, &mut Drivetrain, &mut EngineTemperature, &GlobalTransform)>,
+    mut stalled_events: EventWriter<EngineStalledEvent>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, vehicle, state, mut drivetrain, mut engine, transform) in vehicles.iter_mut() {
+        let up = transform.up();
+        let intake = transform.translation() + up * (config.intake_height - JEEP_HEIGHT * 0.5);
+        let intake_submerged = sample_fluid(fluids.iter(), intake).is_some();
+        if intake_submerged && drivetrain.running {
+            engine.intake_flooded = true;
+            drivetrain.stall();
+            stalled_events.send(EngineStalledEvent { vehicle: entity, reason: EngineStallReason::IntakeFlooded });
+        } else if !intake_submerged {
+            // Draining and drying out the intake once clear of the water
+            engine.intake_flooded = false;
+        }
+
+        let airflow = vehicle.vehicle_speed.abs();
+        if engine.step(&config, drivetrain.running, state.throttle, state.rpm, airflow, dt) {
+            drivetrain.stall();
+            stalled_events.send(EngineStalledEvent { vehicle: entity, reason: EngineStallReason::Overheated });
+        }
+    }
+}
+
+pub fn apply_radiator_damage(
+    config: Res<EngineThermalConfig>,
+    mut events: EventReader<RadiatorDamageEvent>,
+    mut engines: Query<&mut EngineTemperature>,
+) {
+    for event in events.read() {
+        if let Ok(mut engine) = engines.get_mut(event.vehicle) {
+            engine.damage_radiator(&config, event.amount);
+        }
+    }
+}
+
+/// Scales throttle down by the heat power factor
+pub fn apply_thermal_power_loss(
+    config: Res<EngineThermalConfig>,
+    mut engines: Query<(&mut Engine, &EngineTemperature)>,
+) {
+    for (mut state, engine) in engines.iter_mut() {
+        state.throttle *= engine.power_factor(&config);
+    }
+}
+
+/// Plugin for engine temperature and overheating
+pub struct EngineThermalPlugin;
+
+impl Plugin for EngineThermalPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EngineThermalConfig>()
+            .add_event::<RadiatorDamageEvent>()
+            .add_event::<EngineStalledEvent>()
+            .add_systems(Update, (
+                apply_radiator_damage,
+                update_engine_temperature,
+                apply_thermal_power_loss,
+            ).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the engine for `seconds`, returns whether it stalled
+    fn run(
+        engine: &mut EngineTemperature,
+        config: &EngineThermalConfig,
+        throttle: f32,
+        rpm: f32,
+        airflow: f32,
+        seconds: f32,
+    ) -> bool {
+        (0..(seconds * 10.0) as usize).any(|_| engine.step(config, true, throttle, rpm, airflow, 0.1))
+    }
+
+    #[test]
+    fn test_highway_driving_settles_at_operating_temperature() {
+        let config = EngineThermalConfig::default();
+        let mut engine = EngineTemperature::default();
+        assert!(!run(&mut engine, &config, 0.5, 3000.0, 25.0, 600.0));
+        assert!(engine.temperature > config.operating - 1.0);
+        assert!(engine.temperature < config.overheat);
+        assert_eq!(engine.power_factor(&config), 1.0);
+    }
+
+    #[test]
+    fn test_crawling_at_high_rpm_overheats() {
+        let config = EngineThermalConfig::default();
+        let mut engine = EngineTemperature::default();
+        assert!(run(&mut engine, &config, 1.0, 5500.0, 1.0, 600.0));
+        assert!((engine.power_factor(&config) - config.min_power_factor).abs() < 1e-5);
+
+        // Off, the engine only cools
+        let hot = engine.temperature;
+        assert!(!engine.step(&config, false, 1.0, 5500.0, 1.0, 10.0));
+        assert!(engine.temperature < hot);
+    }
+
+    #[test]
+    fn test_damaged_radiator_overheats() {
+        let config = EngineThermalConfig::default();
+        let mut healthy = EngineTemperature { temperature: config.operating, ..default() };
+        let mut damaged = healthy.clone();
+        damaged.damage_radiator(&config, 0.8);
+        assert!(damaged.temperature > healthy.temperature);
+
+        assert!(!run(&mut healthy, &config, 0.8, 4500.0, 15.0, 300.0));
+        assert!(run(&mut damaged, &config, 0.8, 4500.0, 15.0, 300.0));
+    }
+
+    #[test]
+    fn test_power_drops_between_overheat_and_critical() {
+        let config = EngineThermalConfig::default();
+        let midway = EngineTemperature {
+            temperature: (config.overheat + config.critical) * 0.5,
+            ..default()
+        };
+        let factor = midway.power_factor(&config);
+        assert!(factor < 1.0 && factor > config.min_power_factor);
+    }
+
+    #[test]
+    fn test_flooded_engine_cannot_start() {
+        let config = EngineThermalConfig::default();
+        let engine = EngineTemperature { intake_flooded: true, ..default() };
+        assert!(!engine.can_start(&config));
+        let drained = EngineTemperature { intake_flooded: false, ..engine };
+        assert!(drained.can_start(&config));
+        let hot = EngineTemperature { temperature: config.critical, ..drained };
+        assert!(!hot.can_start(&config));
+    }
+}
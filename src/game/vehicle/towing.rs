@@ -0,0 +1,456 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::game::constants::JEEP_LENGTH;
+use crate::game::{GameState, StateScoped};
+use crate::physics::ForceAccumulator;
+
+/// Tow ball on the back of a vehicle
+#[derive(Component, Debug, Clone)]
+pub struct TowHitch {
+    /// Hitch ball position in vehicle space, rear of the vehicle is +Z
+    pub offset: Vec3,
+    /// Trailer currently attached
+    pub trailer: Option<Entity>,
+}
+
+impl Default for TowHitch {
+    fn default() -> Self {
+        Self {
+            offset: Vec3::new(0.0, -0.45, JEEP_LENGTH * 0.5 + 0.25),
+            trailer: None,
+        }
+    }
+}
+
+/// Size of the default trailer bed
+const TRAILER_BODY_SIZE: Vec3 = Vec3::new(1.6, 0.5, 3.0);
+
+/// A towable trailer, its own rigid body running on its own wheels
+#[derive(Component, Debug, Clone)]
+pub struct Trailer {
+    /// Coupler position in trailer space, front of the trailer is -Z
+    pub coupler_offset: Vec3,
+    /// Axle position in trailer space
+    pub axle_offset: Vec3,
+    /// Mass of the empty trailer in kg
+    pub empty_mass: f32,
+    /// Mass of the load in kg
+    pub cargo_mass: f32,
+    /// Load centre along the trailer relative to the axle, negative is ahead of it.
+    /// Loads behind the axle take weight off the tongue and make the trailer sway.
+    pub cargo_offset: f32,
+    /// Yaw damping from the hitch friction and tyres
+    pub sway_damping: f32,
+    /// Maximum hitch angle before the coupler binds, radians
+    pub max_articulation: f32,
+    /// Vehicle this trailer is hitched to
+    pub hitched_to: Option<Entity>,
+}
+
+impl Default for Trailer {
+    fn default() -> Self {
+        Self {
+            coupler_offset: Vec3::new(0.0, -0.2, -2.2),
+            axle_offset: Vec3::new(0.0, -0.35, 0.3),
+            empty_mass: 450.0,
+            cargo_mass: 0.0,
+            cargo_offset: -0.4,
+            sway_damping: 1500.0,
+            max_articulation: 1.3,
+            hitched_to: None,
+        }
+    }
+}
+
+impl Trailer {
+    pub fn total_mass(&self) -> f32 {
+        self.empty_mass + self.cargo_mass
+    }
+
+    /// Centre of mass in trailer space, shifted along the trailer by the load
+    pub fn center_of_mass(&self) -> Vec3 {
+        let total = self.total_mass().max(1.0);
+        let load = self.axle_offset + Vec3::Z * self.cargo_offset;
+        // Empty trailers balance slightly ahead of the axle
+        let empty = self.axle_offset - Vec3::Z * 0.3;
+        (empty * self.empty_mass + load * self.cargo_mass) / total
+    }
+
+    /// Rigid body mass properties, inertia approximated by a solid box the size of the bed
+    pub fn mass_properties(&self) -> MassProperties {
+        let mass = self.total_mass();
+        let size = TRAILER_BODY_SIZE;
+        MassProperties {
+            local_center_of_mass: self.center_of_mass(),
+            mass,
+            principal_inertia: Vec3::new(
+                size.y * size.y + size.z * size.z,
+                size.x * size.x + size.z * size.z,
+                size.x * size.x + size.y * size.y,
+            ) * mass / 12.0,
+            ..default()
+        }
+    }
+
+    /// Sway stability margin: positive when the load keeps weight on the tongue
+    pub fn stability(&self) -> f32 {
+        self.axle_offset.z - self.center_of_mass().z
+    }
+}
+
+/// Configuration for hitching and trailer sway
+#[derive(Resource, Clone, Debug)]
+pub struct TowingConfig {
+    /// Distance between hitch ball and coupler within which hitching is offered
+    pub hitch_range: f32,
+    /// Key toggling the hitch
+    pub hitch_key: KeyCode,
+    /// How strongly a tail-heavy trailer amplifies its own sway at speed
+    pub sway_instability: f32,
+}
+
+impl Default for TowingConfig {
+    fn default() -> Self {
+        Self {
+            hitch_range: 1.5,
+            hitch_key: KeyCode::H,
+            sway_instability: 60.0,
+        }
+    }
+}
+
+/// Hitch action currently on offer to the player
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq)]
+pub enum HitchPrompt {
+    #[default]
+    None,
+    Hitch { vehicle: Entity, trailer: Entity },
+    Unhitch { vehicle: Entity, trailer: Entity },
+}
+
+/// Request to attach a trailer to a vehicle's hitch
+#[derive(Event, Debug, Clone, Copy)]
+pub struct HitchEvent {
+    pub vehicle: Entity,
+    pub trailer: Entity,
+}
+
+/// Request to detach a vehicle's trailer
+#[derive(Event, Debug, Clone, Copy)]
+pub struct UnhitchEvent {
+    pub vehicle: Entity,
+}
+
+/// Marks the text node showing the hitch prompt
+#[derive(Component)]
+pub struct HitchPromptText;
+
+/// Marks a trailer wheel
+#[derive(Component)]
+pub struct TrailerWheel;
+
+/// Hitch angle between vehicle and trailer around `up`, positive when the trailer points left of the vehicle
+pub fn articulation_angle(vehicle_forward: Vec3, trailer_forward: Vec3, up: Vec3) -> f32 {
+    let a = vehicle_forward.reject_from_normalized(up).normalize_or_zero();
+    let b = trailer_forward.reject_from_normalized(up).normalize_or_zero();
+    a.cross(b).dot(up).atan2(a.dot(b))
+}
+
+/// Yaw torque acting on a trailer around the hitch.
+/// Damping always opposes relative yaw; tail-heavy trailers also push further away from straight as speed rises.
+pub fn sway_torque(trailer: &Trailer, articulation: f32, relative_yaw_rate: f32, speed: f32, instability: f32) -> f32 {
+    let damping = -trailer.sway_damping * relative_yaw_rate;
+    let tail_heavy = (-trailer.stability()).max(0.0);
+    let divergence = instability * tail_heavy * trailer.total_mass() * 0.001 * articulation * speed * speed;
+    damping + divergence
+}
+
+/// Spawns a trailer body with two wheels on a revolute axle
+pub fn spawn_trailer(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    transform: Transform,
+    trailer: Trailer,
+) -> Entity {
+    let body_size = TRAILER_BODY_SIZE;
+    let wheel_radius = 0.35;
+    let track = 1.7;
+
+    let body = commands
+        .spawn((
+            PbrBundle {
+                mesh: meshes.add(shape::Box::new(body_size.x, body_size.y, body_size.z).into()),
+                material: materials.add(Color::rgb(0.3, 0.32, 0.3).into()),
+                transform,
+                ..default()
+            },
+            RigidBody::Dynamic,
+            Collider::cuboid(body_size.x * 0.5, body_size.y * 0.5, body_size.z * 0.5),
+            ColliderMassProperties::MassProperties(trailer.mass_properties()),
+            Velocity::default(),
+            ExternalForce::default(),
+            ForceAccumulator::default(),
+            Damping { linear_damping: 0.05, angular_damping: 0.3 },
+            Name::new("Trailer"),
+            StateScoped(GameState::InGame),
+        ))
+        .id();
+
+    let wheel_mesh = meshes.add(shape::UVSphere { radius: wheel_radius, ..default() }.into());
+    let wheel_material = materials.add(Color::rgb(0.08, 0.08, 0.08).into());
+    for side in [-0.5, 0.5] {
+        let anchor = trailer.axle_offset + Vec3::X * track * side;
+        commands.spawn((
+            PbrBundle {
+                mesh: wheel_mesh.clone(),
+                material: wheel_material.clone(),
+                transform: transform * Transform::from_translation(anchor),
+                ..default()
+            },
+            RigidBody::Dynamic,
+            Collider::ball(wheel_radius),
+            ColliderMassProperties::Mass(25.0),
+            Friction::coefficient(1.0),
+            ImpulseJoint::new(
+                body,
+                RevoluteJointBuilder::new(Vec3::X).local_anchor1(anchor).local_anchor2(Vec3::ZERO),
+            ),
+            TrailerWheel,
+            StateScoped(GameState::InGame),
+        ));
+    }
+
+    commands.entity(body).insert(trailer);
+    body
+}
+
+/// Keeps the trailer's mass and centre of mass in sync with its load
+pub fn apply_trailer_cargo(mut trailers: Query<(&Trailer, &mut ColliderMassProperties), Changed<Trailer>>) {
+    for (trailer, mut mass_properties) in trailers.iter_mut() {
+        *mass_properties = ColliderMassProperties::MassProperties(trailer.mass_properties());
+    }
+}
+
+/// Finds the hitch action to offer: unhitch when towing, hitch when a free coupler is in range
+pub fn update_hitch_prompt(
+    config: Res<TowingConfig>,
+    vehicles: Query<(Entity, &TowHitch, &GlobalTransform)>,
+    trailers: Query<(Entity, &Trailer, &GlobalTransform)>,
+    mut prompt: ResMut<HitchPrompt>,
+) {
+    let mut next = HitchPrompt::None;
+    for (vehicle, hitch, vehicle_transform) in vehicles.iter() {
+        if let Some(trailer) = hitch.trailer {
+            next = HitchPrompt::Unhitch { vehicle, trailer };
+            break;
+        }
+
+        let ball = vehicle_transform.transform_point(hitch.offset);
+        let nearest = trailers
+            .iter()
+            .filter(|(_, trailer, _)| trailer.hitched_to.is_none())
+            .map(|(entity, trailer, transform)| (entity, transform.transform_point(trailer.coupler_offset).distance(ball)))
+            .filter(|(_, distance)| *distance <= config.hitch_range)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((trailer, _)) = nearest {
+            next = HitchPrompt::Hitch { vehicle, trailer };
+            break;
+        }
+    }
+
+    if *prompt != next {
+        *prompt = next;
+    }
+}
+
+/// Turns the hitch key into hitch and unhitch requests for the prompted pair
+pub fn handle_hitch_input(
+    keyboard: Res<Input<KeyCode>>,
+    config: Res<TowingConfig>,
+    prompt: Res<HitchPrompt>,
+    mut hitch_events: EventWriter<HitchEvent>,
+    mut unhitch_events: EventWriter<UnhitchEvent>,
+) {
+    if !keyboard.just_pressed(config.hitch_key) {
+        return;
+    }
+    match *prompt {
+        HitchPrompt::Hitch { vehicle, trailer } => hitch_events.send(HitchEvent { vehicle, trailer }),
+        HitchPrompt::Unhitch { vehicle, .. } => unhitch_events.send(UnhitchEvent { vehicle }),
+        HitchPrompt::None => {}
+    }
+}
+
+/// Creates and removes the hitch joints
+pub fn apply_hitch_events(
+    mut commands: Commands,
+    mut hitch_events: EventReader<HitchEvent>,
+    mut unhitch_events: EventReader<UnhitchEvent>,
+    mut vehicles: Query<&mut TowHitch>,
+    mut trailers: Query<&mut Trailer>,
+) {
+    for event in hitch_events.read() {
+        let (Ok(mut hitch), Ok(mut trailer)) = (vehicles.get_mut(event.vehicle), trailers.get_mut(event.trailer)) else {
+            continue;
+        };
+        if hitch.trailer.is_some() || trailer.hitched_to.is_some() {
+            continue;
+        }
+
+        // Ball joint at the coupler; yaw is limited so the coupler binds rather than jackknifing through the bumper
+        let joint = SphericalJointBuilder::new()
+            .local_anchor1(hitch.offset)
+            .local_anchor2(trailer.coupler_offset)
+            .limits(JointAxis::AngY, [-trailer.max_articulation, trailer.max_articulation])
+            .limits(JointAxis::AngX, [-0.6, 0.6])
+            .limits(JointAxis::AngZ, [-0.4, 0.4]);
+        commands.entity(event.trailer).insert(ImpulseJoint::new(event.vehicle, joint));
+
+        hitch.trailer = Some(event.trailer);
+        trailer.hitched_to = Some(event.vehicle);
+    }
+
+    for event in unhitch_events.read() {
+        let Ok(mut hitch) = vehicles.get_mut(event.vehicle) else {
+            continue;
+        };
+        let Some(trailer_entity) = hitch.trailer.take() else {
+            continue;
+        };
+        if let Ok(mut trailer) = trailers.get_mut(trailer_entity) {
+            trailer.hitched_to = None;
+        }
+        commands.entity(trailer_entity).remove::<ImpulseJoint>();
+    }
+}
+
+/// Applies hitch damping and speed-dependent sway to towed trailers
+pub fn apply_trailer_sway(
+    config: Res<TowingConfig>,
+    vehicles: Query<(&GlobalTransform, Option<&Velocity>), With<TowHitch>>,
+    mut trailers: Query<(&Trailer, &GlobalTransform, &Velocity, &mut ForceAccumulator)>,
+) {
+    for (trailer, transform, velocity, mut force) in trailers.iter_mut() {
+        let Some((vehicle_transform, vehicle_velocity)) = trailer.hitched_to.and_then(|v| vehicles.get(v).ok()) else {
+            continue;
+        };
+
+        let up = vehicle_transform.up();
+        let articulation = articulation_angle(vehicle_transform.forward(), transform.forward(), up);
+        let vehicle_yaw_rate = vehicle_velocity.map_or(0.0, |v| v.angvel.dot(up));
+        let relative_yaw_rate = velocity.angvel.dot(up) - vehicle_yaw_rate;
+        let speed = velocity.linvel.length();
+
+        let torque = sway_torque(trailer, articulation, relative_yaw_rate, speed, config.sway_instability);
+        force.add_torque(up * torque);
+    }
+}
+
+pub fn spawn_hitch_prompt(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 28.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Percent(20.0),
+            left: Val::Percent(50.0),
+            margin: UiRect::left(Val::Px(-140.0)),
+            ..default()
+        }),
+        Visibility::Hidden,
+        HitchPromptText,
+    ));
+}
+
+pub fn update_hitch_prompt_text(
+    prompt: Res<HitchPrompt>,
+    config: Res<TowingConfig>,
+    mut texts: Query<(&mut Text, &mut Visibility), With<HitchPromptText>>,
+) {
+    if !prompt.is_changed() {
+        return;
+    }
+    for (mut text, mut visibility) in texts.iter_mut() {
+        let message = match *prompt {
+            HitchPrompt::Hitch { .. } => format!("Press {:?} to hitch trailer", config.hitch_key),
+            HitchPrompt::Unhitch { .. } => format!("Press {:?} to unhitch trailer", config.hitch_key),
+            HitchPrompt::None => String::new(),
+        };
+        *visibility = if message.is_empty() { Visibility::Hidden } else { Visibility::Inherited };
+        text.sections[0].value = message;
+    }
+}
+
+/// Plugin for trailers, hitching and sway
+pub struct TowingPlugin;
+
+impl Plugin for TowingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TowingConfig>()
+            .init_resource::<HitchPrompt>()
+            .add_event::<HitchEvent>()
+            .add_event::<UnhitchEvent>()
+            .add_systems(Startup, spawn_hitch_prompt)
+            .add_systems(Update, (
+                apply_trailer_cargo,
+                update_hitch_prompt,
+                handle_hitch_input,
+                apply_hitch_events,
+                apply_trailer_sway,
+                update_hitch_prompt_text,
+            ).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_articulation_angle() {
+        let forward = Vec3::NEG_Z;
+        assert!(articulation_angle(forward, forward, Vec3::Y).abs() < 1e-6);
+
+        // Trailer pointing 30 degrees to the left
+        let swung = Quat::from_rotation_y(30f32.to_radians()) * forward;
+        assert!((articulation_angle(forward, swung, Vec3::Y) - 30f32.to_radians()).abs() < 1e-5);
+
+        let swung_right = Quat::from_rotation_y(-30f32.to_radians()) * forward;
+        assert!((articulation_angle(forward, swung_right, Vec3::Y) + 30f32.to_radians()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_cargo_moves_center_of_mass() {
+        let empty = Trailer::default();
+        assert!(empty.stability() > 0.0);
+
+        let tail_heavy = Trailer { cargo_mass: 800.0, cargo_offset: 1.2, ..default() };
+        assert!(tail_heavy.stability() < 0.0);
+        assert_eq!(tail_heavy.total_mass(), 1250.0);
+    }
+
+    #[test]
+    fn test_sway_damps_when_balanced() {
+        let trailer = Trailer { cargo_mass: 500.0, cargo_offset: -0.5, ..default() };
+        // Swinging left: torque pushes back to the right
+        assert!(sway_torque(&trailer, 0.1, 0.5, 25.0, 60.0) < 0.0);
+        // No divergence for a nose-heavy load, even at speed
+        assert_eq!(sway_torque(&trailer, 0.2, 0.0, 30.0, 60.0), 0.0);
+    }
+
+    #[test]
+    fn test_tail_heavy_trailer_diverges_at_speed() {
+        let trailer = Trailer { cargo_mass: 800.0, cargo_offset: 1.2, ..default() };
+        let slow = sway_torque(&trailer, 0.1, 0.0, 5.0, 60.0);
+        let fast = sway_torque(&trailer, 0.1, 0.0, 30.0, 60.0);
+        assert!(fast > slow && slow > 0.0);
+    }
+}
@@ -0,0 +1,268 @@
+use bevy::prelude::*;
+
+use super::{directional_slip, is_powered, DriveType, Drivetrain, Engine, TransferCase, Vehicle, Wheel};
+use crate::game::plugins::PlayerId;
+use crate::game::{render_available, GameSettings};
+
+/// Tuning for the traction overlay and the diff lock and low range hints
+#[derive(Resource, Clone, Debug)]
+pub struct TractionAssistConfig {
+    /// Follows the traction assist setting
+    pub enabled: bool,
+    /// Slip up to which a wheel shows full grip
+    pub full_grip_slip: f32,
+    /// Slip at which a wheel shows no grip at all
+    pub no_grip_slip: f32,
+    /// Spin of a driven wheel that counts as struggling for a hint
+    pub hint_slip: f32,
+    /// Throttle the driver has to be giving it before a hint
+    pub hint_throttle: f32,
+    /// Speed in m/s below which the vehicle counts as stuck rather than just sliding around
+    pub hint_speed: f32,
+    /// Seconds of struggling before a hint
+    pub hint_delay: f32,
+    /// Seconds before the same hint is given again
+    pub hint_repeat: f32,
+}
+
+impl Default for TractionAssistConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            full_grip_slip: 0.1,
+            no_grip_slip: 0.6,
+            hint_slip: 0.4,
+            hint_throttle: 0.3,
+            hint_speed: 2.0,
+            hint_delay: 2.5,
+            hint_repeat: 45.0,
+        }
+    }
+}
+
+impl TractionAssistConfig {
+    /// Grip of a wheel (0.0 - 1.0) from its slip along and across its direction of roll,
+    /// `None` while it's in the air
+    pub fn wheel_grip(&self, wheel: &Wheel) -> Option<f32> {
+        if !wheel.ground_contact {
+            return None;
+        }
+        let slip = Vec2::new(wheel.slip_ratio, wheel.slip_angle.tan()).length();
+        let range = (self.no_grip_slip - self.full_grip_slip).max(f32::EPSILON);
+        Some(1.0 - ((slip - self.full_grip_slip) / range).clamp(0.0, 1.0))
+    }
+
+    /// Control that would get a struggling vehicle moving, `None` when it isn't struggling or
+    /// already has both. `slips` is the directional slip of each wheel in wheel order, `None` in the air.
+    pub fn suggest(
+        &self,
+        drive_type: DriveType,
+        transfer_case: TransferCase,
+        diff_locked: bool,
+        throttle: f32,
+        speed: f32,
+        slips: [Option<f32>; 4],
+    ) -> Option<TractionHint> {
+        if throttle < self.hint_throttle || speed.abs() > self.hint_speed {
+            return None;
+        }
+        let spinning = |position: usize| {
+            let slip = slips[position];
+            is_powered(drive_type, transfer_case, position) && slip.map_or(true, |slip| slip > self.hint_slip)
+        };
+        if !(0..4).any(spinning) {
+            return None;
+        }
+
+        // One wheel of an axle spinning or hanging while the other bites is what an open diff can't handle
+        let one_sided = [0, 2].into_iter().any(|left| spinning(left) != spinning(left + 1));
+        if !diff_locked && one_sided {
+            Some(TractionHint::LockDiffs)
+        } else if matches!(drive_type, DriveType::FourWD) && transfer_case != TransferCase::FourLow {
+            Some(TractionHint::LowRange)
+        } else if !diff_locked {
+            Some(TractionHint::LockDiffs)
+        } else {
+            None
+        }
+    }
+}
+
+/// Control suggested to a player whose wheels keep spinning
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TractionHint {
+    LockDiffs,
+    LowRange,
+}
+
+/// A player's vehicle has been struggling long enough for a hint
+#[derive(Event, Debug, Clone, Copy)]
+pub struct TractionHintEvent {
+    pub vehicle: Entity,
+    pub hint: TractionHint,
+}
+
+/// How long a vehicle has been struggling and since when each hint was given
+#[derive(Component, Debug, Clone, Default)]
+pub struct TractionHintTimer {
+    struggling: f32,
+    hint: Option<TractionHint>,
+    /// Seconds since each hint, in [`TractionHint`] order, `None` before the first
+    since_hint: [Option<f32>; 2],
+}
+
+/// Marker color for a wheel's grip, red through yellow to green and grey in the air
+pub fn grip_color(grip: Option<f32>) -> Color {
+    match grip {
+        None => Color::rgba(0.6, 0.6, 0.6, 0.6),
+        Some(grip) if grip < 0.5 => Color::rgb(1.0, grip * 2.0, 0.0),
+        Some(grip) => Color::rgb(2.0 - grip * 2.0, 1.0, 0.0),
+    }
+}
+
+fn sync_traction_settings(game_settings: Res<GameSettings>, mut config: ResMut<TractionAssistConfig>) {
+    let enabled = game_settings.assists.traction_overlay;
+    if game_settings.is_changed() && config.enabled != enabled {
+        config.enabled = enabled;
+    }
+}
+
+fn traction_assist_enabled(config: Res<TractionAssistConfig>) -> bool {
+    config.enabled
+}
+
+/// Hints at the diff locks or low range once a player's wheels have spun without getting anywhere for a while
+fn update_traction_hints(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<TractionAssistConfig>,
+    wheels: Query<&Wheel>,
+    mut vehicles: Query<(Entity, &Vehicle, &Engine, &Drivetrain, Option<&mut TractionHintTimer>), With<PlayerId>>,
+    mut hints: EventWriter<TractionHintEvent>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, vehicle, engine, drivetrain, timer) in vehicles.iter_mut() {
+        let Some(mut timer) = timer else {
+            commands.entity(entity).insert(TractionHintTimer::default());
+            continue;
+        };
+
+        let speed = vehicle.vehicle_speed;
+        let mut slips = [None; 4];
+        for wheel in wheels.iter_many(vehicle.wheel_entities) {
+            if wheel.ground_contact && wheel.position < 4 {
+                slips[wheel.position] = Some(directional_slip(wheel.slip_ratio, speed));
+            }
+        }
+        let drive_type = vehicle.config.drivetrain_config.drive_type;
+        let hint = config.suggest(
+            drive_type,
+            drivetrain.transfer_case,
+            vehicle.diff_locked,
+            engine.throttle,
+            speed,
+            slips,
+        );
+
+        for since in timer.since_hint.iter_mut().flatten() {
+            *since += dt;
+        }
+        // Struggling only counts while the same control would help
+        timer.struggling = if hint.is_some() && hint == timer.hint { timer.struggling + dt } else { 0.0 };
+        timer.hint = hint;
+
+        let Some(hint) = hint else {
+            continue;
+        };
+        let index = hint as usize;
+        let due = timer.since_hint[index].map_or(true, |since| since >= config.hint_repeat);
+        if timer.struggling >= config.hint_delay && due {
+            timer.since_hint[index] = Some(0.0);
+            timer.struggling = 0.0;
+            hints.send(TractionHintEvent { vehicle: entity, hint });
+        }
+    }
+}
+
+/// Colored markers under the players' wheels showing how much grip each has
+fn draw_traction_overlay(
+    mut gizmos: Gizmos,
+    config: Res<TractionAssistConfig>,
+    vehicles: Query<&Vehicle, With<PlayerId>>,
+    wheels: Query<(&Wheel, &GlobalTransform)>,
+) {
+    for vehicle in vehicles.iter() {
+        for (wheel, transform) in wheels.iter_many(vehicle.wheel_entities) {
+            let contact = transform.translation() - Vec3::Y * (wheel.radius - 0.02);
+            let color = grip_color(config.wheel_grip(wheel));
+            gizmos.circle(contact, Vec3::Y, wheel.width, color);
+            gizmos.circle(contact, Vec3::Y, wheel.width * 0.6, color);
+        }
+    }
+}
+
+/// Plugin for the traction overlay and the diff lock and low range hints
+pub struct TractionAssistPlugin;
+
+impl Plugin for TractionAssistPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TractionAssistConfig>()
+            .add_event::<TractionHintEvent>()
+            .add_systems(Update, (
+                sync_traction_settings.run_if(resource_exists::<GameSettings>()),
+                update_traction_hints.run_if(traction_assist_enabled),
+            ).chain());
+
+        // Gizmos need a renderer, the hints still reach the notifications headless
+        if render_available(app) {
+            app.add_systems(Update, draw_traction_overlay.run_if(traction_assist_enabled));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wheel(slip_ratio: f32, slip_angle: f32) -> Wheel {
+        Wheel { ground_contact: true, slip_ratio, slip_angle, ..default() }
+    }
+
+    #[test]
+    fn test_grip_falls_with_slip() {
+        let config = TractionAssistConfig::default();
+        assert_eq!(config.wheel_grip(&wheel(0.05, 0.0)), Some(1.0));
+        assert_eq!(config.wheel_grip(&wheel(1.0, 0.0)), Some(0.0));
+        let sliding = config.wheel_grip(&wheel(0.0, 0.35)).unwrap();
+        assert!(sliding > 0.0 && sliding < 1.0);
+        assert_eq!(config.wheel_grip(&Wheel::default()), None);
+    }
+
+    #[test]
+    fn test_hints_suggest_diff_lock_then_low_range() {
+        let config = TractionAssistConfig::default();
+        let suggest = |case, locked, slips| config.suggest(DriveType::FourWD, case, locked, 1.0, 0.2, slips);
+        let gripping = Some(0.0);
+        let spinning = Some(0.9);
+
+        // Front left in the air and the right one biting, the open diff sends everything to the air
+        let cross_axled = [None, gripping, gripping, gripping];
+        assert_eq!(suggest(TransferCase::FourHigh, false, cross_axled), Some(TractionHint::LockDiffs));
+        // Locked but still spinning everything, more torque at the wheels helps
+        let all_spinning = [spinning; 4];
+        assert_eq!(suggest(TransferCase::FourHigh, true, all_spinning), Some(TractionHint::LowRange));
+        assert_eq!(suggest(TransferCase::FourLow, true, all_spinning), None);
+        // Nothing to suggest while the wheels bite
+        assert_eq!(suggest(TransferCase::TwoHigh, false, [gripping; 4]), None);
+        // Nor while moving along or coasting
+        assert_eq!(config.suggest(DriveType::FourWD, TransferCase::FourHigh, false, 1.0, 8.0, all_spinning), None);
+        assert_eq!(config.suggest(DriveType::FourWD, TransferCase::FourHigh, false, 0.0, 0.2, all_spinning), None);
+    }
+
+    #[test]
+    fn test_grip_colors() {
+        assert_eq!(grip_color(Some(1.0)), Color::rgb(0.0, 1.0, 0.0));
+        assert_eq!(grip_color(Some(0.5)), Color::rgb(1.0, 1.0, 0.0));
+        assert_eq!(grip_color(Some(0.0)), Color::rgb(1.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,324 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{wheel_mount, wheel_rest_position, Vehicle, VehicleConfig, Wheel};
+use crate::game::plugins::SurfaceMaterial;
+
+/// Hardware hanging below the body that rocks catch on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnderbodyConfig {
+    /// Radius of the differential housings, in meters
+    pub diff_radius: f32,
+    /// Cross-section of the rock sliders, in meters
+    pub slider_size: f32,
+    /// How far the rock sliders hang below the body, in meters
+    pub slider_drop: f32,
+    /// Length of the skid plate under the engine and transfer case, in meters
+    pub skid_plate_length: f32,
+    pub skid_plate_thickness: f32,
+    /// How far the skid plate hangs below the body, in meters
+    pub skid_plate_drop: f32,
+}
+
+impl Default for UnderbodyConfig {
+    fn default() -> Self {
+        Self {
+            diff_radius: 0.14,
+            slider_size: 0.08,
+            slider_drop: 0.06,
+            skid_plate_length: 1.4,
+            skid_plate_thickness: 0.02,
+            skid_plate_drop: 0.08,
+        }
+    }
+}
+
+/// Underbody collider of a vehicle, lives on a child of the vehicle
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnderbodyPart {
+    FrontDifferential,
+    RearDifferential,
+    LeftRockSlider,
+    RightRockSlider,
+    SkidPlate,
+}
+
+/// Where an underbody part sits in chassis space and what shape it has
+#[derive(Debug, Clone)]
+pub struct UnderbodyShape {
+    pub part: UnderbodyPart,
+    pub offset: Vec3,
+    pub collider: Collider,
+    /// Height of the part's lowest point in chassis space
+    pub bottom: f32,
+}
+
+/// Underbody parts of a vehicle. The differentials sit on the axles at the suspension's rest
+/// length, the sliders run along the sills between the wheels and the skid plate covers the
+/// engine and transfer case behind the front axle.
+pub fn underbody_shapes(config: &VehicleConfig) -> [UnderbodyShape; 5] {
+    let underbody = &config.underbody;
+    let body_bottom = -config.dimensions.y * 0.5;
+
+    let differential = |part, index| {
+        let axle = wheel_rest_position(config, index) * Vec3::new(0.0, 1.0, 1.0);
+        UnderbodyShape {
+            part,
+            offset: axle,
+            collider: Collider::ball(underbody.diff_radius),
+            bottom: axle.y - underbody.diff_radius,
+        }
+    };
+
+    let slider_bottom = body_bottom - underbody.slider_drop;
+    let slider_half_length = (config.wheelbase * 0.5 - config.wheel_radius).max(0.1);
+    let slider = |part, side: f32| {
+        let half = underbody.slider_size * 0.5;
+        UnderbodyShape {
+            part,
+            offset: Vec3::new(side * (config.dimensions.x * 0.5 - half), slider_bottom + half, 0.0),
+            collider: Collider::cuboid(half, half, slider_half_length),
+            bottom: slider_bottom,
+        }
+    };
+
+    let plate_bottom = body_bottom - underbody.skid_plate_drop;
+    let plate_half = Vec3::new(
+        config.track_width * 0.25,
+        underbody.skid_plate_thickness * 0.5,
+        underbody.skid_plate_length * 0.5,
+    );
+    let front_axle = wheel_mount(config, 0, 0.0).z;
+
+    [
+        differential(UnderbodyPart::FrontDifferential, 0),
+        differential(UnderbodyPart::RearDifferential, 2),
+        slider(UnderbodyPart::LeftRockSlider, -1.0),
+        slider(UnderbodyPart::RightRockSlider, 1.0),
+        UnderbodyShape {
+            part: UnderbodyPart::SkidPlate,
+            offset: Vec3::new(0.0, plate_bottom + plate_half.y, front_axle + underbody.diff_radius + plate_half.z),
+            collider: Collider::cuboid(plate_half.x, plate_half.y, plate_half.z),
+            bottom: plate_bottom,
+        },
+    ]
+}
+
+/// Height of the lowest underbody part above flat ground with the suspension at rest
+pub fn ground_clearance(config: &VehicleConfig) -> f32 {
+    let ground = wheel_rest_position(config, 0).y - config.wheel_radius;
+    let lowest = underbody_shapes(config).iter().map(|shape| shape.bottom).fold(f32::INFINITY, f32::min);
+    lowest - ground
+}
+
+/// Tuning for underbody scraping
+#[derive(Resource, Debug, Clone)]
+pub struct UnderbodySettings {
+    /// Drag of each part dragging over the ground, in newtons
+    pub scrape_drag: f32,
+    /// Sliding speed below which parts rest instead of scraping, in m/s
+    pub min_scrape_speed: f32,
+    /// Sliding speed at which scraping is at full intensity, in m/s
+    pub full_scrape_speed: f32,
+    /// Seconds between scrape events of the same part
+    pub scrape_interval: f32,
+}
+
+impl Default for UnderbodySettings {
+    fn default() -> Self {
+        Self {
+            scrape_drag: 4000.0,
+            min_scrape_speed: 0.3,
+            full_scrape_speed: 5.0,
+            scrape_interval: 0.15,
+        }
+    }
+}
+
+impl UnderbodySettings {
+    /// Scrape intensity (0.0 - 1.0) at `speed`, `None` when too slow to scrape
+    pub fn intensity(&self, speed: f32) -> Option<f32> {
+        if speed < self.min_scrape_speed {
+            return None;
+        }
+        let range = (self.full_scrape_speed - self.min_scrape_speed).max(f32::EPSILON);
+        Some(((speed - self.min_scrape_speed) / range).clamp(0.0, 1.0))
+    }
+
+    /// Impulse slowing a vehicle of `mass` sliding at `velocity` on `parts` underbody parts over
+    /// `dt`. Never more than half the vehicle's momentum, so it can't push it backwards.
+    pub fn drag_impulse(&self, mass: f32, velocity: Vec3, parts: usize, dt: f32) -> Vec3 {
+        let sliding = Vec3::new(velocity.x, 0.0, velocity.z);
+        let speed = sliding.length();
+        if parts == 0 || speed < f32::EPSILON {
+            return Vec3::ZERO;
+        }
+        let impulse = (self.scrape_drag * parts as f32 * dt).min(mass * speed * 0.5);
+        -sliding / speed * impulse
+    }
+}
+
+/// Whether a vehicle touching the ground with `parts` underbody parts and `wheels_on_ground`
+/// wheels is hung up on its underbody
+pub fn is_high_centered(parts: usize, wheels_on_ground: usize) -> bool {
+    parts > 0 && wheels_on_ground <= 2
+}
+
+/// Underbody parts of a vehicle touching something this frame
+#[derive(Component, Debug, Clone, Default)]
+pub struct UnderbodyContact {
+    pub parts: Vec<UnderbodyPart>,
+    /// Resting on its underbody with too few wheels down to drive off
+    pub high_centered: bool,
+}
+
+/// An underbody part dragged over something
+#[derive(Event, Debug, Clone, Copy)]
+pub struct UnderbodyScrapeEvent {
+    pub vehicle: Entity,
+    pub part: UnderbodyPart,
+    /// Contact point in world space
+    pub position: Vec3,
+    /// What the part scraped over
+    pub surface: SurfaceMaterial,
+    /// 0.0 - 1.0, from the sliding speed
+    pub intensity: f32,
+}
+
+/// Finds underbody parts touching the world, drags the vehicle back and reports scrapes
+#[allow(clippy::too_many_arguments)]
+pub fn update_underbody_contacts(
+    time: Res<Time>,
+    settings: Res<UnderbodySettings>,
+    rapier_context: Res<RapierContext>,
+    parts: Query<(Entity, &UnderbodyPart, &Parent, &GlobalTransform)>,
+    surfaces: Query<&SurfaceMaterial>,
+    wheels: Query<&Wheel>,
+    mut vehicles: Query<(Entity, &Vehicle, &mut UnderbodyContact, Option<&Velocity>, Option<&mut ExternalImpulse>)>,
+    mut scrapes: EventWriter<UnderbodyScrapeEvent>,
+    mut last_scrape: Local<HashMap<Entity, f32>>,
+) {
+    let now = time.elapsed_seconds();
+    let dt = time.delta_seconds();
+    last_scrape.retain(|_, last| now - *last < settings.scrape_interval);
+
+    let mut touching: HashMap<Entity, Vec<(Entity, UnderbodyPart, Vec3, SurfaceMaterial)>> = HashMap::default();
+    for (entity, &part, parent, transform) in parts.iter() {
+        let vehicle = parent.get();
+        let Ok((_, vehicle_state, ..)) = vehicles.get(vehicle) else {
+            continue;
+        };
+        for pair in rapier_context.contact_pairs_with(entity) {
+            if !pair.has_any_active_contacts() {
+                continue;
+            }
+            let other = if pair.collider1() == entity { pair.collider2() } else { pair.collider1() };
+            // The vehicle's own wheels rub against its axles all the time
+            if vehicle_state.wheel_entities.contains(&other) || rapier_context.collider_parent(other) == Some(vehicle) {
+                continue;
+            }
+            let mut contact_sum = Vec3::ZERO;
+            let mut contact_count = 0;
+            for manifold in pair.manifolds() {
+                for contact in manifold.solver_contacts() {
+                    contact_sum += contact.point();
+                    contact_count += 1;
+                }
+            }
+            let position = if contact_count > 0 { contact_sum / contact_count as f32 } else { transform.translation() };
+            let surface = surfaces.get(other).copied().unwrap_or_default();
+            touching.entry(vehicle).or_default().push((entity, part, position, surface));
+            break;
+        }
+    }
+
+    for (vehicle_entity, contacts) in touching.iter() {
+        let Ok((_, vehicle, _, velocity, impulse)) = vehicles.get_mut(*vehicle_entity) else {
+            continue;
+        };
+        let velocity = velocity.map_or(Vec3::ZERO, |velocity| velocity.linvel);
+        if let Some(mut impulse) = impulse {
+            impulse.impulse += settings.drag_impulse(vehicle.config.mass, velocity, contacts.len(), dt);
+        }
+        let Some(intensity) = settings.intensity(Vec3::new(velocity.x, 0.0, velocity.z).length()) else {
+            continue;
+        };
+        for &(entity, part, position, surface) in contacts {
+            if last_scrape.contains_key(&entity) {
+                continue;
+            }
+            last_scrape.insert(entity, now);
+            scrapes.send(UnderbodyScrapeEvent { vehicle: *vehicle_entity, part, position, surface, intensity });
+        }
+    }
+
+    for (entity, vehicle, mut contact, ..) in vehicles.iter_mut() {
+        let parts: Vec<UnderbodyPart> = touching
+            .get(&entity)
+            .map(|contacts| contacts.iter().map(|(_, part, ..)| *part).collect())
+            .unwrap_or_default();
+        let wheels_on_ground = wheels.iter_many(vehicle.wheel_entities).filter(|wheel| wheel.ground_contact).count();
+        let high_centered = is_high_centered(parts.len(), wheels_on_ground);
+        if contact.parts != parts || contact.high_centered != high_centered {
+            contact.parts = parts;
+            contact.high_centered = high_centered;
+        }
+    }
+}
+
+/// Plugin for underbody contact, scraping and high-centering
+pub struct UnderbodyPlugin;
+
+impl Plugin for UnderbodyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UnderbodySettings>()
+            .add_event::<UnderbodyScrapeEvent>()
+            .add_systems(Update, update_underbody_contacts);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_differentials_sit_on_the_axles_and_set_clearance() {
+        let config = VehicleConfig::default();
+        let shapes = underbody_shapes(&config);
+        let front = wheel_rest_position(&config, 0);
+        assert_eq!(shapes[0].part, UnderbodyPart::FrontDifferential);
+        assert_eq!(shapes[0].offset, Vec3::new(0.0, front.y, front.z));
+        assert!(shapes[1].offset.z > 0.0 && shapes[4].offset.z < shapes[1].offset.z);
+
+        let clearance = ground_clearance(&config);
+        assert!(clearance <= config.wheel_radius - config.underbody.diff_radius + 1e-5);
+        let lowered = VehicleConfig {
+            underbody: UnderbodyConfig { diff_radius: config.wheel_radius, ..default() },
+            ..default()
+        };
+        assert!(ground_clearance(&lowered) <= 1e-5);
+    }
+
+    #[test]
+    fn test_scrape_drag_opposes_sliding_and_never_reverses() {
+        let settings = UnderbodySettings::default();
+        let impulse = settings.drag_impulse(1500.0, Vec3::new(0.0, -1.0, 4.0), 2, 1.0 / 60.0);
+        assert!(impulse.z < 0.0 && impulse.x == 0.0 && impulse.y == 0.0);
+        assert!((impulse.z + settings.scrape_drag * 2.0 / 60.0).abs() < 1e-3);
+
+        let slow = settings.drag_impulse(1500.0, Vec3::new(0.01, 0.0, 0.0), 5, 1.0);
+        assert!((slow.x + 1500.0 * 0.01 * 0.5).abs() < 1e-4);
+        assert_eq!(settings.drag_impulse(1500.0, Vec3::X, 0, 1.0), Vec3::ZERO);
+    }
+
+    #[test]
+    fn test_high_centered_needs_underbody_contact_and_wheels_off() {
+        assert!(is_high_centered(1, 2));
+        assert!(!is_high_centered(1, 3));
+        assert!(!is_high_centered(0, 0));
+        assert_eq!(UnderbodySettings::default().intensity(0.1), None);
+        assert_eq!(UnderbodySettings::default().intensity(10.0), Some(1.0));
+    }
+}
@@ -23,6 +23,8 @@ pub struct Wheel {
     pub mass: f32,
     /// Moment of inertia around rotation axis
     pub inertia: f32,
+    /// Share of the normal force resisting rolling, set by the tire type
+    pub rolling_resistance: f32,
     /// Whether the wheel is in contact with the ground
     pub ground_contact: bool,
     /// Normal force from ground contact
@@ -45,6 +47,7 @@ impl Default for Wheel {
             brake_torque: 0.0,
             mass: 20.0,
             inertia: 2.5, // Approximated as mr²/2 for a solid cylinder
+            rolling_resistance: 0.02,
             ground_contact: false,
             normal_force: 0.0,
             slip_angle: 0.0,
@@ -137,7 +140,7 @@ pub fn update_wheel_physics(
         wheel.angular_velocity += (total_torque / wheel.inertia) * dt;
 
         // Apply rolling resistance
-        let rolling_resistance = -0.02 * wheel.normal_force * wheel.angular_velocity.signum();
+        let rolling_resistance = -wheel.rolling_resistance * wheel.normal_force * wheel.angular_velocity.signum();
         wheel.angular_velocity += (rolling_resistance * wheel.radius / wheel.inertia) * dt;
     }
 } 
\ No newline at end of file
@@ -0,0 +1,223 @@
+use bevy::prelude::*;
+use std::f32::consts::FRAC_PI_2;
+
+use super::{Steering, Vehicle, VehicleConfig, Wheel, WheelHub};
+
+/// Visual wheel mesh parented to the chassis, posed from the physics wheel each frame
+#[derive(Component, Debug, Clone)]
+pub struct WheelVisual {
+    /// Wheel index (FL: 0, FR: 1, RL: 2, RR: 3)
+    pub index: usize,
+    /// Physics wheel entity driving this visual
+    pub wheel: Entity,
+    /// Suspension mount point in chassis space, the wheel hangs below it
+    pub mount: Vec3,
+    /// Rotation that lays the mesh on its axle, cylinders are modelled along Y
+    pub mesh_rotation: Quat,
+    /// Accumulated roll angle in radians
+    spin: f32,
+}
+
+impl WheelVisual {
+    pub fn new(index: usize, wheel: Entity, mount: Vec3) -> Self {
+        Self {
+            index,
+            wheel,
+            mount,
+            mesh_rotation: Quat::from_rotation_z(FRAC_PI_2),
+            spin: 0.0,
+        }
+    }
+
+    /// Accumulated roll angle in radians
+    pub fn spin(&self) -> f32 {
+        self.spin
+    }
+}
+
+/// Marks vehicles whose wheel meshes have been moved onto the chassis
+#[derive(Component)]
+pub struct WheelVisualsAttached;
+
+pub fn is_front_wheel(index: usize) -> bool {
+    index <= 1
+}
+
+pub fn is_left_wheel(index: usize) -> bool {
+    index % 2 == 0
+}
+
+/// Suspension mount of a wheel in chassis space, front of the vehicle is -Z
+pub fn wheel_mount(config: &VehicleConfig, index: usize, mount_height: f32) -> Vec3 {
+    let x = if is_left_wheel(index) { -0.5 } else { 0.5 } * config.track_width;
+    let z = if is_front_wheel(index) { -0.5 } else { 0.5 } * config.wheelbase;
+    Vec3::new(x, mount_height, z)
+}
+
+/// Ackermann steering angles (left, right) for a steering angle at the axle centre.
+/// Positive angles turn left; the inside wheel always turns tighter than the outside one.
+pub fn ackermann_angles(steering_angle: f32, wheelbase: f32, track_width: f32) -> (f32, f32) {
+    if steering_angle.abs() < 1e-4 {
+        return (steering_angle, steering_angle);
+    }
+    // Signed turn radius at the rear axle centre, positive to the left
+    let radius = wheelbase / steering_angle.tan();
+    let half_track = track_width * 0.5;
+    let left = (wheelbase / (radius - half_track)).atan();
+    let right = (wheelbase / (radius + half_track)).atan();
+    (left, right)
+}
+
+/// Chassis space transform of a wheel mesh
+pub fn wheel_local_transform(
+    mount: Vec3,
+    suspension_length: f32,
+    steer: f32,
+    spin: f32,
+    mesh_rotation: Quat,
+) -> Transform {
+    Transform::from_translation(mount - Vec3::Y * suspension_length)
+        .with_rotation(Quat::from_rotation_y(steer) * Quat::from_rotation_x(spin) * mesh_rotation)
+}
+
+/// Moves the meshes of each vehicle's physics wheels onto chassis children that can be posed freely
+pub fn attach_wheel_visuals(
+    mut commands: Commands,
+    vehicles: Query<(Entity, &Vehicle), Without<WheelVisualsAttached>>,
+    wheel_meshes: Query<(&Handle<Mesh>, &Handle<StandardMaterial>), With<Wheel>>,
+) {
+    for (vehicle_entity, vehicle) in vehicles.iter() {
+        // Wait until the wheels have been spawned
+        if vehicle.wheel_entities.contains(&Entity::PLACEHOLDER) {
+            continue;
+        }
+
+        let mount_height = -vehicle.config.dimensions.y * 0.5 + vehicle.config.suspension_config.max_length;
+        let mut visuals = Vec::with_capacity(4);
+        for (index, &wheel) in vehicle.wheel_entities.iter().enumerate() {
+            let Ok((mesh, material)) = wheel_meshes.get(wheel) else {
+                continue;
+            };
+            let visual = WheelVisual::new(index, wheel, wheel_mount(&vehicle.config, index, mount_height));
+            let transform = wheel_local_transform(
+                visual.mount,
+                vehicle.config.suspension_config.rest_length,
+                0.0,
+                0.0,
+                visual.mesh_rotation,
+            );
+
+            visuals.push(
+                commands
+                    .spawn((
+                        PbrBundle {
+                            mesh: mesh.clone(),
+                            material: material.clone(),
+                            transform,
+                            ..default()
+                        },
+                        visual,
+                        Name::new(format!("Wheel Visual {index}")),
+                    ))
+                    .id(),
+            );
+            // The physics wheel keeps its collider but no longer draws
+            commands.entity(wheel).remove::<(Handle<Mesh>, Handle<StandardMaterial>)>();
+        }
+
+        commands
+            .entity(vehicle_entity)
+            .push_children(&visuals)
+            .insert(WheelVisualsAttached);
+    }
+}
+
+/// Spins, steers and drops the wheel meshes from wheel speed, steering and suspension compression
+pub fn update_wheel_visuals(
+    time: Res<Time>,
+    vehicles: Query<(&Vehicle, &Steering)>,
+    wheels: Query<(&Wheel, Option<&WheelHub>)>,
+    mut visuals: Query<(&mut WheelVisual, &mut Transform, &Parent)>,
+) {
+    let dt = time.delta_seconds();
+    for (mut visual, mut transform, parent) in visuals.iter_mut() {
+        let Ok((vehicle, steering)) = vehicles.get(parent.get()) else {
+            continue;
+        };
+        let config = &vehicle.config;
+        let suspension = &config.suspension_config;
+
+        let (angular_velocity, compression) = match wheels.get(visual.wheel) {
+            Ok((wheel, hub)) => (wheel.angular_velocity, hub.map_or(0.0, |hub| hub.state.compression)),
+            Err(_) => (vehicle.vehicle_speed / config.wheel_radius, 0.0),
+        };
+        // Forward is -Z, so rolling forward turns the wheel top toward -Z: a negative X rotation
+        visual.spin = (visual.spin - angular_velocity * dt).rem_euclid(std::f32::consts::TAU);
+
+        let steer = if is_front_wheel(visual.index) {
+            let (left, right) = ackermann_angles(steering.angle, config.wheelbase, config.track_width);
+            if is_left_wheel(visual.index) { left } else { right }
+        } else {
+            0.0
+        };
+
+        let length = (suspension.rest_length - compression).clamp(suspension.min_length, suspension.max_length);
+
+        *transform = wheel_local_transform(visual.mount, length, steer, visual.spin, visual.mesh_rotation);
+    }
+}
+
+/// Plugin that poses wheel meshes from the vehicle simulation
+pub struct WheelVisualPlugin;
+
+impl Plugin for WheelVisualPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (attach_wheel_visuals, update_wheel_visuals).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ackermann_inner_wheel_turns_tighter() {
+        let (left, right) = ackermann_angles(0.3, 2.38, 1.5);
+        assert!(left > 0.3 && right < 0.3 && right > 0.0);
+
+        // Turning right mirrors it
+        let (left, right) = ackermann_angles(-0.3, 2.38, 1.5);
+        assert!(right < -0.3 && left > -0.3 && left < 0.0);
+    }
+
+    #[test]
+    fn test_ackermann_straight() {
+        assert_eq!(ackermann_angles(0.0, 2.38, 1.5), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_wheel_mounts() {
+        let config = VehicleConfig::default();
+        let front_left = wheel_mount(&config, 0, 0.0);
+        let rear_right = wheel_mount(&config, 3, 0.0);
+        assert!(front_left.x < 0.0 && front_left.z < 0.0);
+        assert!(rear_right.x > 0.0 && rear_right.z > 0.0);
+        assert!((rear_right.z - front_left.z - config.wheelbase).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_compression_raises_wheel() {
+        let mount = Vec3::new(-0.75, 0.0, -1.19);
+        let extended = wheel_local_transform(mount, 0.6, 0.0, 0.0, Quat::IDENTITY);
+        let compressed = wheel_local_transform(mount, 0.3, 0.0, 0.0, Quat::IDENTITY);
+        assert!(compressed.translation.y > extended.translation.y);
+        assert_eq!(compressed.translation.xz(), mount.xz());
+    }
+
+    #[test]
+    fn test_steer_yaws_axle() {
+        let transform = wheel_local_transform(Vec3::ZERO, 0.5, FRAC_PI_2, 0.0, Quat::IDENTITY);
+        // Axle (X) points along -Z after a quarter turn left
+        assert!((transform.rotation * Vec3::X - Vec3::NEG_Z).length() < 1e-5);
+    }
+}
@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use bevy::log::LogPlugin;
 use bevy::window::WindowMode;
 use crate::core::GameState;
 
@@ -15,6 +16,8 @@ fn main() {
     App::new()
         .add_state::<GameState>()
         .insert_resource(ClearColor(Color::rgb(0.5, 0.7, 1.0))) // Sky blue
+        // Data packs have to be layered in before the asset plugin is built
+        .add_plugins(assets::ModPlugin::default())
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
                 title: "SandK Offroad".into(),
@@ -24,6 +27,12 @@ fn main() {
                 ..default()
             }),
             ..default()
+        }).set(AssetPlugin {
+            watch_for_changes_override: cfg!(feature = "shader-hot-reload").then_some(true),
+            ..default()
+        }).set(LogPlugin {
+            update_subscriber: Some(game::capture_recent_logs),
+            ..default()
         }))
         .add_plugins((
             game::GamePlugin,
@@ -0,0 +1,112 @@
+//! Runtime toggles for the physics visualizations: rapier's collider wireframes and contact points,
+//! the suspension gizmos, center of mass markers and force vectors. The physics debug panel flips them.
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::game::{Chassis, WheelHub};
+
+/// Gizmo length per newton of force, in meters
+const FORCE_SCALE: f32 = 0.0001;
+/// Radius of the center of mass marker, in meters
+const CENTER_OF_MASS_RADIUS: f32 = 0.1;
+
+/// Which physics visualizations are drawn. Development builds start with the collider wireframes,
+/// release builds with everything off.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhysicsDebugSettings {
+    pub colliders: bool,
+    pub contacts: bool,
+    pub suspension_rays: bool,
+    pub center_of_mass: bool,
+    pub forces: bool,
+}
+
+impl Default for PhysicsDebugSettings {
+    fn default() -> Self {
+        Self {
+            colliders: cfg!(debug_assertions),
+            contacts: false,
+            suspension_rays: false,
+            center_of_mass: false,
+            forces: false,
+        }
+    }
+}
+
+impl PhysicsDebugSettings {
+    /// What rapier's debug renderer draws for these settings, empty when it has nothing to draw
+    pub fn render_mode(&self) -> DebugRenderMode {
+        let mut mode = DebugRenderMode::empty();
+        if self.colliders {
+            mode |= DebugRenderMode::COLLIDER_SHAPES | DebugRenderMode::JOINTS;
+        }
+        if self.contacts {
+            mode |= DebugRenderMode::CONTACTS;
+        }
+        mode
+    }
+}
+
+/// Run condition for the suspension gizmos
+pub fn suspension_rays_enabled(settings: Option<Res<PhysicsDebugSettings>>) -> bool {
+    settings.is_some_and(|settings| settings.suspension_rays)
+}
+
+/// Hands the collider and contact toggles to rapier's debug renderer
+pub(super) fn sync_debug_render(settings: Res<PhysicsDebugSettings>, mut context: ResMut<DebugRenderContext>) {
+    if !settings.is_changed() {
+        return;
+    }
+    let mode = settings.render_mode();
+    context.enabled = !mode.is_empty();
+    context.pipeline.mode = mode;
+}
+
+/// Marks each chassis' center of mass and draws the forces on the chassis and at each wheel
+pub(super) fn draw_physics_gizmos(
+    settings: Res<PhysicsDebugSettings>,
+    mut gizmos: Gizmos,
+    chassis: Query<(&Chassis, &GlobalTransform, Option<&ExternalForce>)>,
+    hubs: Query<(&WheelHub, &GlobalTransform)>,
+) {
+    if !settings.center_of_mass && !settings.forces {
+        return;
+    }
+    for (chassis, transform, force) in chassis.iter() {
+        let center = transform.transform_point(chassis.center_of_mass_offset);
+        if settings.center_of_mass {
+            gizmos.sphere(center, Quat::IDENTITY, CENTER_OF_MASS_RADIUS, Color::FUCHSIA);
+        }
+        if let Some(force) = force.filter(|_| settings.forces) {
+            gizmos.ray(center, force.force * FORCE_SCALE, Color::YELLOW);
+        }
+    }
+    if settings.forces {
+        for (hub, transform) in hubs.iter() {
+            gizmos.ray(transform.translation(), hub.state.force * FORCE_SCALE, Color::GREEN);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_mode_follows_toggles() {
+        let off = PhysicsDebugSettings {
+            colliders: false,
+            ..default()
+        };
+        assert!(off.render_mode().is_empty());
+
+        let contacts = PhysicsDebugSettings { contacts: true, ..off };
+        assert_eq!(contacts.render_mode(), DebugRenderMode::CONTACTS);
+
+        let both = PhysicsDebugSettings { colliders: true, ..contacts };
+        assert!(both.render_mode().contains(DebugRenderMode::COLLIDER_SHAPES | DebugRenderMode::CONTACTS));
+        // The gizmo toggles aren't rapier's business
+        let gizmos = PhysicsDebugSettings { suspension_rays: true, center_of_mass: true, forces: true, ..off };
+        assert!(gizmos.render_mode().is_empty());
+    }
+}
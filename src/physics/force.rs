@@ -0,0 +1,67 @@
+//! Forces gathered over a frame and handed to rapier once per step
+//!
+//! Systems never write [`ExternalForce`] themselves, they add into the body's [`ForceAccumulator`].
+//! [`finalize_forces`] then replaces the external force with the sum and clears the accumulator,
+//! so a force only acts for the step it was added in and the order of the contributors doesn't
+//! matter.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+/// Force and torque added to a rigid body during the current frame, in world space
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq)]
+pub struct ForceAccumulator {
+    pub force: Vec3,
+    pub torque: Vec3,
+}
+
+impl ForceAccumulator {
+    pub fn add_force(&mut self, force: Vec3) {
+        self.force += force;
+    }
+
+    pub fn add_torque(&mut self, torque: Vec3) {
+        self.torque += torque;
+    }
+
+    /// Adds a force acting at `point`, with the torque it exerts about `center_of_mass`
+    pub fn add_force_at_point(&mut self, force: Vec3, point: Vec3, center_of_mass: Vec3) {
+        self.force += force;
+        self.torque += (point - center_of_mass).cross(force);
+    }
+}
+
+/// Writes each body's accumulated force into its [`ExternalForce`] and starts the next step from zero
+pub(super) fn finalize_forces(mut bodies: Query<(&mut ForceAccumulator, &mut ExternalForce)>) {
+    for (mut accumulator, mut external) in bodies.iter_mut() {
+        external.force = accumulator.force;
+        external.torque = accumulator.torque;
+        *accumulator = ForceAccumulator::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forces_last_one_step() {
+        let mut app = App::new();
+        app.add_systems(Update, finalize_forces);
+        let body = app.world.spawn((ForceAccumulator::default(), ExternalForce::default())).id();
+
+        let mut accumulator = app.world.get_mut::<ForceAccumulator>(body).unwrap();
+        accumulator.add_force(Vec3::Y * 100.0);
+        accumulator.add_force_at_point(Vec3::Y * 50.0, Vec3::X, Vec3::ZERO);
+        app.update();
+
+        let external = *app.world.get::<ExternalForce>(body).unwrap();
+        assert_eq!(external.force, Vec3::Y * 150.0);
+        assert_eq!(external.torque, Vec3::Z * 50.0);
+        assert_eq!(*app.world.get::<ForceAccumulator>(body).unwrap(), ForceAccumulator::default());
+
+        // Nothing added, nothing left over from the step before
+        app.update();
+        assert_eq!(app.world.get::<ExternalForce>(body).unwrap().force, Vec3::ZERO);
+    }
+}
@@ -1,17 +1,52 @@
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 
+use crate::game::{configure_game_sets, render_available, GameSet};
+
+mod debug;
+mod force;
+mod time_scale;
+
+pub use debug::{suspension_rays_enabled, PhysicsDebugSettings};
+pub use force::ForceAccumulator;
+pub use time_scale::{SlowMotionEvent, TimeScale, TimeScaleKeys, MAX_TIME_SCALE, MIN_TIME_SCALE};
+
 pub struct PhysicsPlugin;
 
 impl Plugin for PhysicsPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
-            .add_plugins(RapierDebugRenderPlugin::default())
             .insert_resource(RapierConfiguration {
                 gravity: Vec3::new(0.0, -9.81, 0.0),
                 ..default()
             })
-            .add_systems(Startup, setup_physics);
+            .init_resource::<TimeScale>()
+            .init_resource::<TimeScaleKeys>()
+            .add_event::<SlowMotionEvent>()
+            .add_systems(Startup, setup_physics)
+            .add_systems(Update, (
+                time_scale::time_scale_keys.run_if(resource_exists::<Input<KeyCode>>()),
+                time_scale::apply_time_scale,
+            ).chain().in_set(GameSet::Input))
+            // Everything in Update has added its forces by now, rapier reads them in its next step
+            .add_systems(PostUpdate, force::finalize_forces.before(PhysicsSet::SyncBackend));
+        // Rapier steps in PostUpdate, after every GameSet
+        configure_game_sets(app);
+
+        // Collider outlines are drawn with gizmos, each visualization is toggled from the debug panel
+        if render_available(app) {
+            let settings = PhysicsDebugSettings::default();
+            app.insert_resource(settings)
+                .add_plugins(RapierDebugRenderPlugin {
+                    enabled: !settings.render_mode().is_empty(),
+                    mode: settings.render_mode(),
+                    ..default()
+                })
+                .add_systems(Update, (
+                    debug::sync_debug_render,
+                    debug::draw_physics_gizmos,
+                ).in_set(GameSet::PostSim));
+        }
     }
 }
 
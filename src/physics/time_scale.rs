@@ -0,0 +1,178 @@
+//! Game speed and slow motion
+//!
+//! Everything reading `Time` runs on bevy's virtual clock: rapier's step, the vehicle systems,
+//! animations and particles. Scaling that clock slows them all down together. UI, menus and audio
+//! read `Time<Real>` and keep running at full speed. Under a fixed physics timestep (headless runs
+//! and determinism mode) rapier steps the same `dt` every update, so the scale stays at 1 there.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::game::GameSettings;
+
+pub const MIN_TIME_SCALE: f32 = 0.05;
+pub const MAX_TIME_SCALE: f32 = 2.0;
+
+/// Keys halving, doubling and resetting the game speed
+#[derive(Resource, Debug, Clone)]
+pub struct TimeScaleKeys {
+    pub slower: KeyCode,
+    pub faster: KeyCode,
+    pub reset: KeyCode,
+}
+
+impl Default for TimeScaleKeys {
+    fn default() -> Self {
+        Self {
+            slower: KeyCode::BracketLeft,
+            faster: KeyCode::BracketRight,
+            reset: KeyCode::Backslash,
+        }
+    }
+}
+
+/// Slows the game down for a while on top of the player's game speed, for action replays and crash cams.
+/// A scale of 1 ends the slow motion early.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct SlowMotionEvent {
+    pub scale: f32,
+    /// Real seconds, not slowed down themselves
+    pub duration: f32,
+}
+
+/// How fast game time runs against real time
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct TimeScale {
+    /// Speed picked by the player, kept in [`crate::game::PhysicsSettings::time_scale`]
+    pub game_speed: f32,
+    /// Slow motion scale and the real seconds it has left
+    slow_motion: Option<(f32, f32)>,
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        Self { game_speed: 1.0, slow_motion: None }
+    }
+}
+
+impl TimeScale {
+    /// Scale the virtual clock runs at
+    pub fn effective(&self) -> f32 {
+        let slow_motion = self.slow_motion.map_or(1.0, |(scale, _)| scale);
+        (self.game_speed * slow_motion).clamp(MIN_TIME_SCALE, MAX_TIME_SCALE)
+    }
+
+    pub fn is_slow_motion(&self) -> bool {
+        self.slow_motion.is_some()
+    }
+
+    pub fn slow_motion(&mut self, scale: f32, duration: f32) {
+        self.slow_motion = (scale != 1.0 && duration > 0.0).then_some((scale, duration));
+    }
+
+    fn tick(&mut self, real_delta: f32) {
+        if let Some((_, remaining)) = &mut self.slow_motion {
+            *remaining -= real_delta;
+            if *remaining <= 0.0 {
+                self.slow_motion = None;
+            }
+        }
+    }
+}
+
+/// Changes the player's game speed, saved with the rest of the settings
+pub(super) fn time_scale_keys(
+    keys: Res<TimeScaleKeys>,
+    keyboard: Res<Input<KeyCode>>,
+    mut time_scale: ResMut<TimeScale>,
+    game_settings: Option<ResMut<GameSettings>>,
+) {
+    let speed = if keyboard.just_pressed(keys.reset) {
+        1.0
+    } else if keyboard.just_pressed(keys.slower) {
+        time_scale.game_speed * 0.5
+    } else if keyboard.just_pressed(keys.faster) {
+        time_scale.game_speed * 2.0
+    } else {
+        return;
+    };
+    time_scale.game_speed = speed.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+    if let Some(mut game_settings) = game_settings {
+        game_settings.physics.time_scale = time_scale.game_speed;
+    }
+}
+
+/// Scales the virtual clock by the game speed and any slow motion running
+pub(super) fn apply_time_scale(
+    real_time: Res<Time<Real>>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut time_scale: ResMut<TimeScale>,
+    mut slow_motion: EventReader<SlowMotionEvent>,
+    game_settings: Option<Res<GameSettings>>,
+    rapier_config: Option<Res<RapierConfiguration>>,
+) {
+    if let Some(game_settings) = game_settings.filter(|settings| settings.is_changed()) {
+        time_scale.game_speed = game_settings.physics.time_scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+    }
+    time_scale.tick(real_time.delta_seconds());
+    for event in slow_motion.read() {
+        time_scale.slow_motion(event.scale, event.duration);
+    }
+
+    let fixed_step = rapier_config.is_some_and(|config| matches!(config.timestep_mode, TimestepMode::Fixed { .. }));
+    let scale = if fixed_step { 1.0 } else { time_scale.effective() };
+    if virtual_time.relative_speed() != scale {
+        virtual_time.set_relative_speed(scale);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.init_resource::<Time<Real>>()
+            .init_resource::<Time<Virtual>>()
+            .init_resource::<TimeScale>()
+            .add_event::<SlowMotionEvent>()
+            .add_systems(Update, apply_time_scale);
+        app
+    }
+
+    #[test]
+    fn test_slow_motion_scales_the_game_speed() {
+        let mut app = app();
+        app.world.resource_mut::<TimeScale>().game_speed = 0.5;
+        app.world.send_event(SlowMotionEvent { scale: 0.25, duration: 2.0 });
+        app.update();
+        assert_eq!(app.world.resource::<Time<Virtual>>().relative_speed(), 0.125);
+
+        app.world.send_event(SlowMotionEvent { scale: 1.0, duration: 2.0 });
+        app.update();
+        assert!(!app.world.resource::<TimeScale>().is_slow_motion());
+        assert_eq!(app.world.resource::<Time<Virtual>>().relative_speed(), 0.5);
+    }
+
+    #[test]
+    fn test_slow_motion_runs_out_in_real_time() {
+        let mut scale = TimeScale::default();
+        scale.slow_motion(0.1, 1.0);
+        scale.tick(0.6);
+        assert_eq!(scale.effective(), 0.1);
+        scale.tick(0.6);
+        assert_eq!(scale.effective(), 1.0);
+    }
+
+    #[test]
+    fn test_fixed_timestep_keeps_real_time() {
+        let mut app = app();
+        app.insert_resource(RapierConfiguration {
+            timestep_mode: TimestepMode::Fixed { dt: 1.0 / 60.0, substeps: 1 },
+            ..default()
+        });
+        app.world.send_event(SlowMotionEvent { scale: 0.25, duration: 2.0 });
+        app.update();
+        assert_eq!(app.world.resource::<Time<Virtual>>().relative_speed(), 1.0);
+    }
+}
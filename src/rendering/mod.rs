@@ -1,10 +1,22 @@
 use bevy::prelude::*;
 use bevy::render::render_resource::*;
 
+mod scene_depth;
+mod shader_reload;
+mod shadow_manager;
+
+pub use scene_depth::{scene_depth_view, SceneDepthPlugin, SceneDepthUsers};
+pub use shader_reload::{
+    compute_pipeline, render_pipeline, track_compute_pipeline, track_render_pipeline, LastGoodPipelines, ShaderErrors,
+    ShaderReloadPlugin,
+};
+pub use shadow_manager::{ShadowCascadeSettings, ShadowManager, ShadowManagerPlugin, ShadowProp};
+
 pub struct RenderingPlugin;
 
 impl Plugin for RenderingPlugin {
     fn build(&self, app: &mut App) {
+        app.add_plugins((ShadowManagerPlugin, ShaderReloadPlugin));
         app.add_systems(Startup, setup_rendering);
         app.add_systems(Update, handle_particle_effects);
     }
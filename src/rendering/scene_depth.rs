@@ -0,0 +1,111 @@
+use bevy::{
+    core_pipeline::prepass::{DepthPrepass, ViewPrepassTextures},
+    prelude::*,
+    render::render_resource::TextureView,
+    utils::HashSet,
+};
+
+/// Features that sample scene depth in their own passes, like SSAO and depth of field.
+/// The main pass depth is multisampled and can't be bound as a texture, so while any of them is
+/// active the 3D cameras get a depth prepass and MSAA is turned off to keep that prepass single-sampled.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct SceneDepthUsers(HashSet<&'static str>);
+
+impl SceneDepthUsers {
+    /// Registers or unregisters a feature as sampling scene depth
+    pub fn set(&mut self, user: &'static str, active: bool) {
+        if active {
+            self.0.insert(user);
+        } else {
+            self.0.remove(user);
+        }
+    }
+
+    /// Whether any feature samples scene depth
+    pub fn any(&self) -> bool {
+        !self.0.is_empty()
+    }
+}
+
+/// Single-sampled depth of a view, for binding as `texture_depth_2d`.
+/// `None` until the view has a depth prepass and MSAA is off.
+pub fn scene_depth_view(prepass: &ViewPrepassTextures) -> Option<&TextureView> {
+    prepass
+        .depth
+        .as_ref()
+        .filter(|depth| depth.texture.sample_count() == 1)
+        .map(|depth| &depth.default_view)
+}
+
+/// Gives 3D cameras a single-sampled depth prepass while a feature samples scene depth, again after
+/// anti-aliasing settings took the prepass away
+fn configure_scene_depth(
+    mut commands: Commands,
+    users: Res<SceneDepthUsers>,
+    mut msaa: ResMut<Msaa>,
+    cameras: Query<Entity, (With<Camera3d>, Without<DepthPrepass>)>,
+) {
+    if !users.any() {
+        return;
+    }
+    if *msaa != Msaa::Off {
+        *msaa = Msaa::Off;
+    }
+    for entity in cameras.iter() {
+        commands.entity(entity).insert(DepthPrepass);
+    }
+}
+
+/// Keeps cameras set up for the features registered in [`SceneDepthUsers`].
+/// Added by each plugin that samples scene depth.
+pub struct SceneDepthPlugin;
+
+impl Plugin for SceneDepthPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SceneDepthUsers>()
+            .init_resource::<Msaa>()
+            .add_systems(PostUpdate, configure_scene_depth);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(SceneDepthPlugin);
+        app
+    }
+
+    #[test]
+    fn test_users_register_and_unregister() {
+        let mut users = SceneDepthUsers::default();
+        users.set("ssao", true);
+        users.set("dof", true);
+        users.set("ssao", false);
+        assert!(users.any());
+        users.set("dof", false);
+        assert!(!users.any());
+    }
+
+    #[test]
+    fn test_cameras_get_single_sampled_prepass_while_used() {
+        let mut app = app();
+        let camera = app.world.spawn(Camera3dBundle::default()).id();
+
+        app.update();
+        assert!(app.world.get::<DepthPrepass>(camera).is_none());
+        assert_eq!(*app.world.resource::<Msaa>(), Msaa::default());
+
+        app.world.resource_mut::<SceneDepthUsers>().set("ssao", true);
+        app.update();
+        assert!(app.world.get::<DepthPrepass>(camera).is_some());
+        assert_eq!(*app.world.resource::<Msaa>(), Msaa::Off);
+
+        // Anti-aliasing settings taking the prepass away doesn't stick
+        app.world.entity_mut(camera).remove::<DepthPrepass>();
+        app.update();
+        assert!(app.world.get::<DepthPrepass>(camera).is_some());
+    }
+}
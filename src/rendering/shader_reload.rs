@@ -0,0 +1,171 @@
+//! Shader hot reload that survives broken edits
+//!
+//! With the `shader-hot-reload` feature, shaders loaded from `assets/` and the ones embedded next to
+//! their passes with `embedded_asset!` are watched, and the pipeline cache requeues every pipeline
+//! using one when it changes. While a requeued pipeline compiles, or when the edit doesn't compile,
+//! passes keep drawing with the last pipeline that did through [`render_pipeline`] and
+//! [`compute_pipeline`]. Compile errors are listed in the shader error console instead.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::*;
+use bevy::render::render_resource::{
+    CachedComputePipelineId, CachedPipelineState, CachedRenderPipelineId, ComputePipeline, PipelineCache,
+    PipelineCacheError, PipelineDescriptor, RenderPipeline,
+};
+use bevy::render::{Render, RenderApp, RenderSet};
+use bevy::utils::HashMap;
+
+/// Pipelines that failed to compile, error messages by pipeline label. Shared between the main
+/// and render worlds, the render world fills it in and the console reads it.
+#[derive(Resource, Clone, Default)]
+pub struct ShaderErrors(Arc<Mutex<BTreeMap<String, String>>>);
+
+impl ShaderErrors {
+    pub fn list(&self) -> Vec<(String, String)> {
+        self.0.lock().map_or_else(|_| Vec::new(), |errors| errors.clone().into_iter().collect())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.lock().map_or(true, |errors| errors.is_empty())
+    }
+
+    fn replace(&self, errors: BTreeMap<String, String>) {
+        if let Ok(mut current) = self.0.lock() {
+            if *current != errors {
+                for (label, error) in errors.iter().filter(|(label, _)| !current.contains_key(*label)) {
+                    error!("Shader for {label} failed to compile, keeping the last good pipeline: {error}");
+                }
+                *current = errors;
+            }
+        }
+    }
+}
+
+/// Last compiled version of each tracked pipeline, kept in the render world
+#[derive(Resource, Default)]
+pub struct LastGoodPipelines {
+    render: HashMap<CachedRenderPipelineId, Option<RenderPipeline>>,
+    compute: HashMap<CachedComputePipelineId, Option<ComputePipeline>>,
+}
+
+impl LastGoodPipelines {
+    pub fn track_render(&mut self, id: CachedRenderPipelineId) {
+        self.render.entry(id).or_default();
+    }
+
+    pub fn track_compute(&mut self, id: CachedComputePipelineId) {
+        self.compute.entry(id).or_default();
+    }
+
+    /// Catches up with whatever compiled this frame
+    fn update(&mut self, cache: &PipelineCache) {
+        for (id, last) in self.render.iter_mut() {
+            if let Some(pipeline) = cache.get_render_pipeline(*id) {
+                if last.as_ref().map_or(true, |last| last.id() != pipeline.id()) {
+                    *last = Some(pipeline.clone());
+                }
+            }
+        }
+        for (id, last) in self.compute.iter_mut() {
+            if let Some(pipeline) = cache.get_compute_pipeline(*id) {
+                if last.as_ref().map_or(true, |last| last.id() != pipeline.id()) {
+                    *last = Some(pipeline.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Keeps the last good version of `id` around from now on, called where the pipeline is queued
+pub fn track_render_pipeline(world: &mut World, id: CachedRenderPipelineId) {
+    world.get_resource_or_insert_with(LastGoodPipelines::default).track_render(id);
+}
+
+pub fn track_compute_pipeline(world: &mut World, id: CachedComputePipelineId) {
+    world.get_resource_or_insert_with(LastGoodPipelines::default).track_compute(id);
+}
+
+/// The pipeline for `id`, or its last good version while it's recompiling or broken
+pub fn render_pipeline(world: &World, id: CachedRenderPipelineId) -> Option<&RenderPipeline> {
+    world.resource::<PipelineCache>().get_render_pipeline(id).or_else(|| {
+        world.get_resource::<LastGoodPipelines>()?.render.get(&id)?.as_ref()
+    })
+}
+
+pub fn compute_pipeline(world: &World, id: CachedComputePipelineId) -> Option<&ComputePipeline> {
+    world.resource::<PipelineCache>().get_compute_pipeline(id).or_else(|| {
+        world.get_resource::<LastGoodPipelines>()?.compute.get(&id)?.as_ref()
+    })
+}
+
+/// Whether an error only means a shader or one of its imports hasn't loaded yet
+fn still_loading(error: &PipelineCacheError) -> bool {
+    matches!(error, PipelineCacheError::ShaderNotLoaded(_) | PipelineCacheError::ShaderImportNotYetAvailable)
+}
+
+fn pipeline_label(descriptor: &PipelineDescriptor) -> String {
+    let label = match descriptor {
+        PipelineDescriptor::RenderPipelineDescriptor(descriptor) => descriptor.label.as_ref(),
+        PipelineDescriptor::ComputePipelineDescriptor(descriptor) => descriptor.label.as_ref(),
+    };
+    label.map_or_else(|| "unnamed pipeline".to_string(), |label| label.to_string())
+}
+
+/// Remembers the pipelines that compiled and collects the errors of the ones that didn't
+fn track_pipeline_states(
+    cache: Res<PipelineCache>,
+    mut last_good: ResMut<LastGoodPipelines>,
+    errors: Res<ShaderErrors>,
+) {
+    last_good.update(&cache);
+    let failed = cache
+        .pipelines()
+        .filter_map(|pipeline| match &pipeline.state {
+            CachedPipelineState::Err(error) if !still_loading(error) => {
+                Some((pipeline_label(&pipeline.descriptor), error.to_string()))
+            }
+            _ => None,
+        })
+        .collect();
+    errors.replace(failed);
+}
+
+/// Plugin for keeping passes drawing through broken shader edits and reporting the errors
+pub struct ShaderReloadPlugin;
+
+impl Plugin for ShaderReloadPlugin {
+    fn build(&self, app: &mut App) {
+        let errors = ShaderErrors::default();
+        app.insert_resource(errors.clone());
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .insert_resource(errors)
+            .init_resource::<LastGoodPipelines>()
+            .add_systems(Render, track_pipeline_states.in_set(RenderSet::Cleanup));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shader_errors_are_shared() {
+        let errors = ShaderErrors::default();
+        let console = errors.clone();
+        assert!(console.is_empty());
+
+        let failed = BTreeMap::from([("lens_pipeline".to_string(), "expected ';'".to_string())]);
+        errors.replace(failed);
+        assert_eq!(console.list(), vec![("lens_pipeline".to_string(), "expected ';'".to_string())]);
+
+        // Fixed shaders drop out on the next frame
+        errors.replace(BTreeMap::new());
+        assert!(console.is_empty());
+    }
+}
@@ -0,0 +1,273 @@
+use bevy::{
+    pbr::{CascadeShadowConfig, CascadeShadowConfigBuilder, DirectionalLightShadowMap, NotShadowCaster},
+    prelude::*,
+};
+
+use crate::game::{GameSettings, PerformanceBudget, ShadowQuality};
+
+/// Camera speed (m/s) at which cascades are stretched to their maximum
+const FAST_CAMERA_SPEED: f32 = 30.0;
+
+/// Relative change in cascade distances required before the cascade config is rebuilt
+/// Reason: rebuilding every frame as the camera speed jitters causes visible shadow shimmer
+const REBUILD_THRESHOLD: f32 = 0.05;
+
+/// Cascade layout for the main directional light
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowCascadeSettings {
+    /// Number of shadow cascades
+    pub num_cascades: usize,
+    /// Far bound of the first (highest detail) cascade in meters
+    pub first_cascade_far_bound: f32,
+    /// Maximum shadow distance from the camera in meters
+    pub maximum_distance: f32,
+    /// Overlap between neighbouring cascades (0.0 - 1.0)
+    pub overlap_proportion: f32,
+    /// Resolution of each cascade's shadow map
+    pub map_size: usize,
+    /// Distance beyond which small props stop casting shadows
+    pub prop_shadow_distance: f32,
+}
+
+impl ShadowCascadeSettings {
+    /// Base cascade layout for a shadow quality level
+    pub fn for_quality(quality: ShadowQuality) -> Self {
+        match quality {
+            ShadowQuality::Low => Self {
+                num_cascades: 1,
+                first_cascade_far_bound: 20.0,
+                maximum_distance: 60.0,
+                overlap_proportion: 0.2,
+                map_size: 1024,
+                prop_shadow_distance: 20.0,
+            },
+            ShadowQuality::Medium => Self {
+                num_cascades: 2,
+                first_cascade_far_bound: 15.0,
+                maximum_distance: 120.0,
+                overlap_proportion: 0.2,
+                map_size: 2048,
+                prop_shadow_distance: 40.0,
+            },
+            ShadowQuality::High => Self {
+                num_cascades: 4,
+                first_cascade_far_bound: 10.0,
+                maximum_distance: 200.0,
+                overlap_proportion: 0.2,
+                map_size: 4096,
+                prop_shadow_distance: 80.0,
+            },
+        }
+    }
+
+    /// Stretch the cascades for a fast moving camera
+    /// Pushes shadows further out so they don't pop in ahead of the vehicle
+    pub fn scaled_for_speed(mut self, camera_speed: f32) -> Self {
+        let factor = (camera_speed / FAST_CAMERA_SPEED).clamp(0.0, 1.0);
+        self.maximum_distance *= 1.0 + 0.5 * factor;
+        self.first_cascade_far_bound *= 1.0 + factor;
+        self
+    }
+
+    /// Far bound of each cascade, using the same geometric split as Bevy's cascade builder
+    pub fn split_distances(&self) -> Vec<f32> {
+        if self.num_cascades <= 1 {
+            return vec![self.maximum_distance];
+        }
+
+        let ratio = self.maximum_distance / self.first_cascade_far_bound;
+        let count = self.num_cascades as f32 - 1.0;
+        (0..self.num_cascades)
+            .map(|i| self.first_cascade_far_bound * ratio.powf(i as f32 / count))
+            .collect()
+    }
+
+    /// Whether the difference to `other` is large enough to warrant rebuilding the cascades
+    fn differs_significantly(&self, other: &Self) -> bool {
+        let relative = |a: f32, b: f32| (a - b).abs() / b.max(f32::EPSILON);
+        self.num_cascades != other.num_cascades
+            || self.map_size != other.map_size
+            || relative(self.maximum_distance, other.maximum_distance) > REBUILD_THRESHOLD
+            || relative(self.first_cascade_far_bound, other.first_cascade_far_bound) > REBUILD_THRESHOLD
+    }
+
+    fn build_cascade_config(&self) -> CascadeShadowConfig {
+        CascadeShadowConfigBuilder {
+            num_cascades: self.num_cascades,
+            minimum_distance: 0.1,
+            maximum_distance: self.maximum_distance,
+            first_cascade_far_bound: self.first_cascade_far_bound,
+            overlap_proportion: self.overlap_proportion,
+        }
+        .build()
+    }
+}
+
+impl Default for ShadowCascadeSettings {
+    fn default() -> Self {
+        Self::for_quality(ShadowQuality::High)
+    }
+}
+
+/// Resource tracking the currently applied shadow layout and camera motion
+#[derive(Resource, Default)]
+pub struct ShadowManager {
+    /// Layout currently applied to directional lights
+    pub active: ShadowCascadeSettings,
+    /// Smoothed camera speed in m/s
+    pub camera_speed: f32,
+    /// Camera position from the previous frame
+    last_camera_position: Option<Vec3>,
+    /// Whether the layout must be reapplied to lights this frame
+    dirty: bool,
+}
+
+/// Marker for small props whose shadows can be dropped at a distance
+#[derive(Component, Debug, Clone, Copy)]
+pub struct ShadowProp {
+    /// Approximate bounding radius of the prop in meters
+    pub radius: f32,
+}
+
+/// Plugin that scales directional light cascades with graphics settings and camera speed
+pub struct ShadowManagerPlugin;
+
+impl Plugin for ShadowManagerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShadowManager>()
+            .add_systems(Update, (
+                track_camera_speed,
+                update_shadow_settings.run_if(resource_exists::<GameSettings>()),
+                apply_cascade_config,
+                cull_prop_shadows,
+            ).chain());
+    }
+}
+
+/// Tracks a smoothed camera speed used to stretch cascades
+fn track_camera_speed(
+    mut manager: ResMut<ShadowManager>,
+    cameras: Query<&GlobalTransform, With<Camera3d>>,
+    time: Res<Time>,
+) {
+    let Some(camera) = cameras.iter().next() else {
+        return;
+    };
+    let position = camera.translation();
+    let dt = time.delta_seconds();
+
+    if let Some(last) = manager.last_camera_position {
+        if dt > 0.0 {
+            const SMOOTHING: f32 = 0.9; // Exponential moving average factor
+            let speed = position.distance(last) / dt;
+            manager.camera_speed = manager.camera_speed * SMOOTHING + speed * (1.0 - SMOOTHING);
+        }
+    }
+    manager.last_camera_position = Some(position);
+}
+
+/// Recomputes the target cascade layout from the graphics settings, performance budget and camera speed
+fn update_shadow_settings(
+    settings: Res<GameSettings>,
+    budget: Option<Res<PerformanceBudget>>,
+    mut manager: ResMut<ShadowManager>,
+) {
+    let quality = match budget {
+        Some(budget) => budget.shadow_quality(settings.graphics.shadow_quality),
+        None => settings.graphics.shadow_quality,
+    };
+    let target = ShadowCascadeSettings::for_quality(quality)
+        .scaled_for_speed(manager.camera_speed);
+
+    if target.differs_significantly(&manager.active) {
+        manager.active = target;
+        manager.dirty = true;
+    }
+}
+
+/// Applies the active cascade layout to all directional lights and the shadow map resource
+fn apply_cascade_config(
+    mut manager: ResMut<ShadowManager>,
+    mut shadow_map: ResMut<DirectionalLightShadowMap>,
+    mut lights: Query<&mut CascadeShadowConfig, With<DirectionalLight>>,
+    added_lights: Query<(), Added<DirectionalLight>>,
+) {
+    if !manager.dirty && added_lights.is_empty() {
+        return;
+    }
+
+    let active = manager.active;
+    if shadow_map.size != active.map_size {
+        shadow_map.size = active.map_size;
+    }
+
+    let config = active.build_cascade_config();
+    for mut cascades in lights.iter_mut() {
+        *cascades = config.clone();
+    }
+    manager.dirty = false;
+}
+
+/// Disables shadow casting for small props beyond the configured distance
+fn cull_prop_shadows(
+    mut commands: Commands,
+    manager: Res<ShadowManager>,
+    cameras: Query<&GlobalTransform, With<Camera3d>>,
+    props: Query<(Entity, &GlobalTransform, &ShadowProp, Has<NotShadowCaster>)>,
+) {
+    let Some(camera) = cameras.iter().next() else {
+        return;
+    };
+    let camera_position = camera.translation();
+    let max_distance = manager.active.prop_shadow_distance;
+
+    for (entity, transform, prop, culled) in props.iter() {
+        // Larger props keep their shadows a little further out
+        let distance = transform.translation().distance(camera_position) - prop.radius;
+        let should_cull = distance > max_distance;
+
+        if should_cull && !culled {
+            commands.entity(entity).insert(NotShadowCaster);
+        } else if !should_cull && culled {
+            commands.entity(entity).remove::<NotShadowCaster>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quality_levels_scale_cascades() {
+        let low = ShadowCascadeSettings::for_quality(ShadowQuality::Low);
+        let high = ShadowCascadeSettings::for_quality(ShadowQuality::High);
+        assert!(low.num_cascades < high.num_cascades);
+        assert!(low.map_size < high.map_size);
+        assert!(low.maximum_distance < high.maximum_distance);
+    }
+
+    #[test]
+    fn test_camera_speed_stretches_cascades() {
+        let base = ShadowCascadeSettings::for_quality(ShadowQuality::Medium);
+        let fast = base.scaled_for_speed(100.0);
+        assert_eq!(fast.maximum_distance, base.maximum_distance * 1.5);
+        assert_eq!(base.scaled_for_speed(0.0), base);
+    }
+
+    #[test]
+    fn test_split_distances_are_increasing() {
+        let settings = ShadowCascadeSettings::default();
+        let splits = settings.split_distances();
+        assert_eq!(splits.len(), settings.num_cascades);
+        assert!(splits.windows(2).all(|w| w[0] < w[1]));
+        assert!((splits.last().unwrap() - settings.maximum_distance).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_small_speed_changes_do_not_rebuild() {
+        let base = ShadowCascadeSettings::default();
+        assert!(!base.scaled_for_speed(0.5).differs_significantly(&base));
+        assert!(base.scaled_for_speed(20.0).differs_significantly(&base));
+    }
+}
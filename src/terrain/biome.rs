@@ -0,0 +1,449 @@
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::tasks::{futures_lite::future, AsyncComputeTaskPool, Task};
+use noise::{Fbm, MultiFractal, NoiseFn, Perlin};
+
+use super::generation::{sample_height, terrain_noise, world_pos_to_chunk, TerrainSettings, CHUNK_SIZE};
+use super::splat::{TerrainLayer, MAX_TERRAIN_LAYERS};
+use super::{TerrainChunkManager, TerrainFocus, TerrainMaterial, TerrainSeed};
+use crate::game::VegetationKind;
+
+/// Kinds of landscape the climate maps sort the terrain into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Biome {
+    Desert,
+    Forest,
+    Alpine,
+}
+
+impl Biome {
+    pub const ALL: [Biome; 3] = [Biome::Desert, Biome::Forest, Biome::Alpine];
+
+    /// Slot of the biome in weight arrays and the profile list
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Name the biome goes by in level data and color grades
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Desert => "desert",
+            Self::Forest => "forest",
+            Self::Alpine => "alpine",
+        }
+    }
+
+    /// Surface layers, plants and ambience the biome starts out with
+    pub fn default_profile(self) -> BiomeProfile {
+        let mut layers = [0.0; MAX_TERRAIN_LAYERS];
+        let affinity: [(TerrainLayer, f32); 5] = match self {
+            Self::Desert => [
+                (TerrainLayer::Grass, 0.05),
+                (TerrainLayer::Dirt, 0.4),
+                (TerrainLayer::Rock, 0.6),
+                (TerrainLayer::Sand, 1.0),
+                (TerrainLayer::Snow, 0.0),
+            ],
+            Self::Forest => [
+                (TerrainLayer::Grass, 1.0),
+                (TerrainLayer::Dirt, 0.8),
+                (TerrainLayer::Rock, 0.6),
+                (TerrainLayer::Sand, 0.2),
+                (TerrainLayer::Snow, 0.5),
+            ],
+            Self::Alpine => [
+                (TerrainLayer::Grass, 0.4),
+                (TerrainLayer::Dirt, 0.3),
+                (TerrainLayer::Rock, 1.0),
+                (TerrainLayer::Sand, 0.0),
+                (TerrainLayer::Snow, 1.0),
+            ],
+        };
+        for (layer, weight) in affinity {
+            layers[layer.index()] = weight;
+        }
+
+        let (vegetation, ambient) = match self {
+            Self::Desert => (vec![(VegetationKind::Grass, 0.6)], "sounds/weather/wind.ogg"),
+            Self::Forest => (
+                vec![(VegetationKind::Conifer, 2.0), (VegetationKind::Foliage, 1.5), (VegetationKind::Grass, 4.0)],
+                "sounds/ambient.ogg",
+            ),
+            Self::Alpine => (
+                vec![(VegetationKind::Conifer, 0.6), (VegetationKind::Grass, 1.0)],
+                "sounds/weather/strong_wind.ogg",
+            ),
+        };
+        BiomeProfile {
+            layers,
+            vegetation,
+            ambient: ambient.to_string(),
+        }
+    }
+}
+
+/// What grows and what's heard in a biome
+#[derive(Debug, Clone, PartialEq)]
+pub struct BiomeProfile {
+    /// How readily each splat layer grows in the biome (0.0 - 1.0), in layer order
+    pub layers: [f32; MAX_TERRAIN_LAYERS],
+    /// Plants per 1000 m² of open ground, by kind
+    pub vegetation: Vec<(VegetationKind, f32)>,
+    /// Looping ambient sound bed, relative to the assets directory
+    pub ambient: String,
+}
+
+/// Climate of a point, both 0.0 - 1.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Climate {
+    pub temperature: f32,
+    pub moisture: f32,
+}
+
+/// How the climate maps are laid out and turned into biomes
+#[derive(Resource, Debug, Clone)]
+pub struct BiomeSettings {
+    /// Frequency of the temperature and moisture noise, a climate zone spans a few hundred meters
+    pub climate_scale: f32,
+    /// Temperature lost per meter above the terrain's zero level
+    pub lapse_rate: f32,
+    /// Temperature below which the ground turns alpine
+    pub alpine_below: f32,
+    /// Moisture below which warmer ground turns to desert
+    pub desert_below: f32,
+    /// Climate either side of a threshold over which neighbouring biomes blend
+    pub blend: f32,
+    /// Share of growth that ignores the height and slope rules, so a biome's own layers show on any ground
+    pub cover: f32,
+    /// Texels along each side of the baked biome map
+    pub map_resolution: u32,
+    /// Meters the baked biome map covers along each side, centered on the camera
+    pub map_extent: f32,
+    /// Profile of each biome, in biome order
+    pub profiles: [BiomeProfile; 3],
+}
+
+impl Default for BiomeSettings {
+    fn default() -> Self {
+        Self {
+            climate_scale: 0.0025,
+            lapse_rate: 0.02,
+            alpine_below: 0.35,
+            desert_below: 0.4,
+            blend: 0.06,
+            cover: 0.25,
+            map_resolution: 128,
+            map_extent: 1600.0,
+            profiles: Biome::ALL.map(Biome::default_profile),
+        }
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+impl BiomeSettings {
+    pub fn profile(&self, biome: Biome) -> &BiomeProfile {
+        &self.profiles[biome.index()]
+    }
+
+    /// Share of each biome at a climate, in biome order and summing to one. Cold ground is alpine
+    /// whatever its moisture, the rest splits into desert and forest by moisture.
+    pub fn biome_weights(&self, climate: Climate) -> [f32; 3] {
+        let blend = self.blend.max(1.0e-4);
+        let alpine = 1.0 - smoothstep(self.alpine_below - blend, self.alpine_below + blend, climate.temperature);
+        let dry = 1.0 - smoothstep(self.desert_below - blend, self.desert_below + blend, climate.moisture);
+        [(1.0 - alpine) * dry, (1.0 - alpine) * (1.0 - dry), alpine]
+    }
+}
+
+/// Climate, biome mix and the strongest biome at a point
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BiomeSample {
+    pub climate: Climate,
+    /// Share of each biome, in biome order
+    pub weights: [f32; 3],
+    pub biome: Biome,
+}
+
+impl BiomeSample {
+    pub fn weight(&self, biome: Biome) -> f32 {
+        self.weights[biome.index()]
+    }
+}
+
+/// The terrain's climate maps and the biomes they make, queryable anywhere whether or not
+/// a chunk is loaded there
+#[derive(Resource, Clone)]
+pub struct BiomeField {
+    settings: BiomeSettings,
+    terrain: TerrainSettings,
+    height: Fbm<Perlin>,
+    temperature: Fbm<Perlin>,
+    moisture: Fbm<Perlin>,
+    /// Level XZ of the local origin, climate follows the level and not wherever the origin is
+    origin: Vec2,
+}
+
+impl Default for BiomeField {
+    fn default() -> Self {
+        Self::new(&BiomeSettings::default(), &TerrainSettings::default(), 0)
+    }
+}
+
+impl BiomeField {
+    pub fn new(settings: &BiomeSettings, terrain: &TerrainSettings, seed: u32) -> Self {
+        // Seeded apart from the height noise so climate zones don't follow the hills
+        let climate = |offset: u32| Fbm::<Perlin>::new(seed.wrapping_add(offset)).set_octaves(3);
+        Self {
+            settings: settings.clone(),
+            terrain: terrain.clone(),
+            height: terrain_noise(terrain, seed),
+            temperature: climate(101),
+            moisture: climate(202),
+            origin: Vec2::ZERO,
+        }
+    }
+
+    pub fn settings(&self) -> &BiomeSettings {
+        &self.settings
+    }
+
+    /// Keeps sampling the same level positions after the world origin moved by `shift`
+    pub fn rebase(&mut self, shift: Vec2) {
+        self.origin += shift;
+    }
+
+    /// Temperature and moisture at world `x`, `z`, colder the higher the ground
+    pub fn climate(&self, x: f32, z: f32) -> Climate {
+        let (x, z) = (x + self.origin.x, z + self.origin.y);
+        let point = [(x * self.settings.climate_scale) as f64, (z * self.settings.climate_scale) as f64];
+        let height = sample_height(&self.height, &self.terrain, x, z);
+        let temperature = self.temperature.get(point) as f32 * 0.5 + 0.5 - height.max(0.0) * self.settings.lapse_rate;
+        Climate {
+            temperature: temperature.clamp(0.0, 1.0),
+            moisture: (self.moisture.get(point) as f32 * 0.5 + 0.5).clamp(0.0, 1.0),
+        }
+    }
+
+    /// Biomes at world `x`, `z`
+    pub fn sample(&self, x: f32, z: f32) -> BiomeSample {
+        let climate = self.climate(x, z);
+        let weights = self.settings.biome_weights(climate);
+        let biome = Biome::ALL.into_iter().max_by(|a, b| weights[a.index()].total_cmp(&weights[b.index()]));
+        BiomeSample {
+            climate,
+            weights,
+            biome: biome.unwrap_or(Biome::Forest),
+        }
+    }
+
+    /// Splat layer affinity of the biome mix, what the terrain shader reads from the biome map
+    pub fn layer_affinity(&self, sample: &BiomeSample) -> [f32; MAX_TERRAIN_LAYERS] {
+        let mut affinity = [0.0; MAX_TERRAIN_LAYERS];
+        for biome in Biome::ALL {
+            for (total, layer) in affinity.iter_mut().zip(self.settings.profile(biome).layers) {
+                *total += layer * sample.weight(biome);
+            }
+        }
+        affinity
+    }
+
+    /// Plants of `kind` per 1000 m² in the biome mix
+    pub fn vegetation_density(&self, sample: &BiomeSample, kind: VegetationKind) -> f32 {
+        Biome::ALL
+            .into_iter()
+            .map(|biome| {
+                let profile = self.settings.profile(biome);
+                let density = profile.vegetation.iter().find(|(plant, _)| *plant == kind).map_or(0.0, |(_, d)| *d);
+                density * sample.weight(biome)
+            })
+            .sum()
+    }
+}
+
+/// Biome layer affinity baked into two RGBA images around the camera, layers 0-3 and 4-7
+struct BiomeBake {
+    origin: Vec2,
+    images: [Image; 2],
+}
+
+fn bake_biome_map(field: &BiomeField, origin: Vec2) -> BiomeBake {
+    let size = field.settings.map_resolution.max(1);
+    let texel = field.settings.map_extent / size as f32;
+    let affinity: Vec<[u8; MAX_TERRAIN_LAYERS]> = (0..size * size)
+        .map(|index| {
+            let world = origin + (Vec2::new((index % size) as f32, (index / size) as f32) + 0.5) * texel;
+            let sample = field.sample(world.x, world.y);
+            field.layer_affinity(&sample).map(|weight| (weight.clamp(0.0, 1.0) * 255.0).round() as u8)
+        })
+        .collect();
+    let images = std::array::from_fn(|half| {
+        let data = affinity.iter().flat_map(|weights| weights[half * 4..half * 4 + 4].iter().copied()).collect();
+        Image::new(
+            Extent3d { width: size, height: size, depth_or_array_layers: 1 },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8Unorm,
+        )
+    });
+    BiomeBake { origin, images }
+}
+
+/// The baked biome map the terrain materials read, rebaked on the task pool as the camera moves on
+#[derive(Resource, Default)]
+pub struct BiomeMap {
+    /// Chunk the current bake is centered on
+    center: Option<IVec2>,
+    images: Option<[Handle<Image>; 2]>,
+    pending: Option<(IVec2, Task<BiomeBake>)>,
+}
+
+impl BiomeMap {
+    /// Chunk the map is centered on, `None` until the first bake lands
+    pub fn center(&self) -> Option<IVec2> {
+        self.center
+    }
+
+    /// Follows the world origin moving by `chunks`. A bake still running was laid out around the old
+    /// origin and is dropped, the current map stays valid once the materials' origin is moved.
+    pub(super) fn rebase(&mut self, chunks: IVec2) {
+        self.center = self.center.map(|center| center - chunks);
+        self.pending = None;
+    }
+}
+
+/// Rebuilds the biome field when the settings or the seed change, and has the map rebaked with it
+pub(super) fn update_biome_field(
+    settings: Res<BiomeSettings>,
+    terrain: Res<TerrainSettings>,
+    seed: Option<Res<TerrainSeed>>,
+    mut field: ResMut<BiomeField>,
+    mut map: Option<ResMut<BiomeMap>>,
+) {
+    let seed_changed = seed.as_ref().is_some_and(|seed| seed.is_changed());
+    if !settings.is_changed() && !terrain.is_changed() && !seed_changed {
+        return;
+    }
+    let origin = field.origin;
+    *field = BiomeField::new(&settings, &terrain, seed.map_or(0, |seed| seed.0));
+    field.origin = origin;
+    if let Some(map) = map.as_mut() {
+        map.center = None;
+        map.pending = None;
+    }
+}
+
+/// Starts a bake once the camera strays a quarter of the map from its center and
+/// points the terrain materials at finished bakes
+#[allow(clippy::too_many_arguments)]
+pub(super) fn update_biome_map(
+    field: Res<BiomeField>,
+    mut map: ResMut<BiomeMap>,
+    manager: Res<TerrainChunkManager>,
+    mut materials: ResMut<Assets<TerrainMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    focuses: Query<&GlobalTransform, With<TerrainFocus>>,
+) {
+    if let Some((center, task)) = map.pending.as_mut() {
+        let center = *center;
+        let Some(bake) = future::block_on(future::poll_once(task)) else {
+            return;
+        };
+        map.pending = None;
+        map.center = Some(center);
+
+        let [first, second] = bake.images;
+        let handles = match map.images.clone() {
+            Some([first_handle, second_handle]) => {
+                images.insert(first_handle.id(), first);
+                images.insert(second_handle.id(), second);
+                [first_handle, second_handle]
+            }
+            None => [images.add(first), images.add(second)],
+        };
+        map.images = Some(handles.clone());
+
+        for handle in manager.materials() {
+            if let Some(material) = materials.get_mut(handle) {
+                let extension = &mut material.extension;
+                extension.splat.biome_origin = bake.origin;
+                extension.splat.biome_size = field.settings.map_extent;
+                extension.splat.biome_cover = field.settings.cover;
+                extension.biome_map_0 = Some(handles[0].clone());
+                extension.biome_map_1 = Some(handles[1].clone());
+            }
+        }
+        return;
+    }
+
+    let focus = cameras
+        .iter()
+        .filter(|(camera, _)| camera.is_active)
+        .map(|(_, transform)| transform)
+        .chain(focuses.iter())
+        .next();
+    let Some(focus) = focus else {
+        return;
+    };
+    let center = world_pos_to_chunk(focus.translation());
+    let recenter_chunks = (field.settings.map_extent * 0.25 / CHUNK_SIZE).max(1.0) as i32;
+    let near_center = map.center.is_some_and(|current| {
+        let offset = (center - current).abs();
+        offset.x.max(offset.y) < recenter_chunks
+    });
+    if near_center {
+        return;
+    }
+
+    let origin = center.as_vec2() * CHUNK_SIZE - Vec2::splat(field.settings.map_extent * 0.5);
+    let field = field.clone();
+    let task = AsyncComputeTaskPool::get().spawn(async move { bake_biome_map(&field, origin) });
+    map.pending = Some((center, task));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_climate_picks_biomes_and_blends_at_boundaries() {
+        let settings = BiomeSettings::default();
+        let dominant = |temperature: f32, moisture: f32| {
+            let weights = settings.biome_weights(Climate { temperature, moisture });
+            assert!((weights.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+            Biome::ALL.into_iter().max_by(|a, b| weights[a.index()].total_cmp(&weights[b.index()])).unwrap()
+        };
+        assert_eq!(dominant(0.8, 0.1), Biome::Desert);
+        assert_eq!(dominant(0.8, 0.8), Biome::Forest);
+        assert_eq!(dominant(0.1, 0.1), Biome::Alpine);
+        assert_eq!(dominant(0.1, 0.9), Biome::Alpine);
+
+        // Right on the desert edge it's half and half, not a hard line
+        let edge = settings.biome_weights(Climate { temperature: 0.8, moisture: settings.desert_below });
+        assert!((edge[Biome::Desert.index()] - 0.5).abs() < 1e-4);
+        assert!((edge[Biome::Forest.index()] - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_field_blends_profiles_by_weight() {
+        let field = BiomeField::default();
+        let sample = BiomeSample {
+            climate: Climate { temperature: 0.5, moisture: 0.5 },
+            weights: [0.5, 0.5, 0.0],
+            biome: Biome::Desert,
+        };
+        let affinity = field.layer_affinity(&sample);
+        let sand = TerrainLayer::Sand.index();
+        assert!((affinity[sand] - 0.6).abs() < 1e-4);
+        assert!((field.vegetation_density(&sample, VegetationKind::Conifer) - 1.0).abs() < 1e-4);
+
+        // The same point always lands in the same biome
+        assert_eq!(field.sample(812.0, -340.0), field.sample(812.0, -340.0));
+        let climate = field.climate(812.0, -340.0);
+        assert!((0.0..=1.0).contains(&climate.temperature) && (0.0..=1.0).contains(&climate.moisture));
+    }
+}
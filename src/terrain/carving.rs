@@ -0,0 +1,237 @@
+use std::sync::Arc;
+
+use bevy::math::Rect;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use serde::{Deserialize, Serialize};
+
+use super::erosion::TerrainErosion;
+use super::generation::{chunk_origin, CHUNK_SIZE};
+use super::holes::TerrainHoles;
+use super::splat::{TerrainLayer, TerrainSplatMap};
+use super::{queue_chunk_generation, TerrainChunkManager, TerrainSeed, TerrainSettings};
+
+/// Catmull-Rom spline through `p1` and `p2` at `t` (0.0 - 1.0)
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * (2.0 * p1 + (p2 - p0) * t + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2 + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+/// Points along the Catmull-Rom spline through `path` about `spacing` meters apart,
+/// the ends repeated so it doesn't overshoot them
+pub fn spline_points(path: &[Vec3], spacing: f32) -> Vec<Vec3> {
+    let Some(last) = path.len().checked_sub(1) else {
+        return Vec::new();
+    };
+    let mut points = vec![path[0]];
+    for i in 0..last {
+        let (p0, p1, p2, p3) = (path[i.saturating_sub(1)], path[i], path[i + 1], path[(i + 2).min(last)]);
+        let steps = (p1.distance(p2) / spacing.max(0.01)).ceil().max(1.0) as usize;
+        points.extend((1..=steps).map(|step| catmull_rom(p0, p1, p2, p3, step as f32 / steps as f32)));
+    }
+    points
+}
+
+fn default_falloff() -> f32 {
+    4.0
+}
+
+fn default_surface() -> TerrainLayer {
+    TerrainLayer::Dirt
+}
+
+/// How a trail cuts into the terrain, as written in a level file
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TerrainCarve {
+    /// Width of the flattened bed in meters
+    pub width: f32,
+    /// Meters either side over which the bed blends back into the natural ground
+    #[serde(default = "default_falloff")]
+    pub falloff: f32,
+    /// Layer painted along the bed
+    #[serde(default = "default_surface")]
+    pub surface: TerrainLayer,
+}
+
+impl Default for TerrainCarve {
+    fn default() -> Self {
+        Self {
+            width: 4.0,
+            falloff: default_falloff(),
+            surface: default_surface(),
+        }
+    }
+}
+
+/// A carved spline, sampled into straight pieces
+#[derive(Debug, Clone)]
+struct CarvedRoute {
+    points: Vec<Vec3>,
+    carve: TerrainCarve,
+    /// XZ area the carve reaches
+    bounds: Rect,
+}
+
+impl CarvedRoute {
+    /// How much of the bed applies at `point` (0.0 - 1.0) and the bed's height there
+    fn influence(&self, point: Vec2) -> Option<(f32, f32)> {
+        if !self.bounds.contains(point) {
+            return None;
+        }
+        let (distance, height) = self
+            .points
+            .windows(2)
+            .map(|pair| {
+                let (a, b) = (pair[0], pair[1]);
+                let along = b.xz() - a.xz();
+                let t = ((point - a.xz()).dot(along) / along.length_squared().max(1e-6)).clamp(0.0, 1.0);
+                let closest = a.lerp(b, t);
+                (closest.xz().distance(point), closest.y)
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))?;
+
+        let outside = distance - self.carve.width * 0.5;
+        let weight = if outside <= 0.0 {
+            1.0
+        } else {
+            let t = (1.0 - outside / self.carve.falloff.max(1e-3)).clamp(0.0, 1.0);
+            t * t * (3.0 - 2.0 * t)
+        };
+        (weight > 0.0).then_some((weight, height))
+    }
+}
+
+/// Trails carved into the terrain, flattened to their spline's height with the edges blended back.
+/// Cheap to clone into chunk generation tasks.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct TerrainCarves {
+    routes: Arc<Vec<CarvedRoute>>,
+    /// Routes added since the loaded chunks and the splat map last caught up
+    unapplied: Vec<usize>,
+}
+
+impl TerrainCarves {
+    /// Carves along the spline through `path`, later carves cut through earlier ones
+    pub fn add(&mut self, path: &[Vec3], carve: TerrainCarve) {
+        let points = spline_points(path, 1.0);
+        if points.len() < 2 {
+            return;
+        }
+        let reach = Vec2::splat(carve.width * 0.5 + carve.falloff);
+        let (min, max) = points.iter().fold((Vec2::MAX, Vec2::MIN), |(min, max), point| {
+            (min.min(point.xz()), max.max(point.xz()))
+        });
+        let bounds = Rect::from_corners(min - reach, max + reach);
+        Arc::make_mut(&mut self.routes).push(CarvedRoute { points, carve, bounds });
+        self.unapplied.push(self.routes.len() - 1);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    /// World height of ground that would naturally be at `natural` after carving
+    pub fn height(&self, x: f32, z: f32, natural: f32) -> f32 {
+        self.routes.iter().fold(natural, |height, route| match route.influence(Vec2::new(x, z)) {
+            Some((weight, bed)) => height + (bed - height) * weight,
+            None => height,
+        })
+    }
+
+    /// Surface of the trail bed at a world XZ position, `None` off the trails and on their blended edges
+    pub fn surface_at(&self, point: Vec2) -> Option<TerrainLayer> {
+        self.routes
+            .iter()
+            .rev()
+            .find(|route| route.influence(point).map_or(false, |(weight, _)| weight >= 1.0))
+            .map(|route| route.carve.surface)
+    }
+
+    /// Whether `route` reaches into chunk `coord`, counting the sample past the edge the normals look at
+    fn touches_chunk(&self, route: usize, coord: IVec2, settings: &TerrainSettings) -> bool {
+        let center = chunk_origin(coord, settings).xz();
+        let margin = CHUNK_SIZE / settings.resolution.max(1) as f32;
+        let chunk = Rect::from_center_half_size(center, Vec2::splat(CHUNK_SIZE * 0.5 + margin));
+        !self.routes[route].bounds.intersect(chunk).is_empty()
+    }
+}
+
+/// Regenerates loaded and generating chunks under newly carved routes and paints the routes' surface.
+/// The old chunk stays until its replacement is uploaded, so nothing falls through meanwhile.
+pub(super) fn apply_terrain_carves(
+    mut carves: ResMut<TerrainCarves>,
+    mut manager: ResMut<TerrainChunkManager>,
+    mut splat_map: ResMut<TerrainSplatMap>,
+    erosion: Res<TerrainErosion>,
+    holes: Res<TerrainHoles>,
+    settings: Res<TerrainSettings>,
+    seed: Option<Res<TerrainSeed>>,
+) {
+    if carves.unapplied.is_empty() {
+        return;
+    }
+    let unapplied = std::mem::take(&mut carves.bypass_change_detection().unapplied);
+
+    let seed = seed.map_or(0, |seed| seed.0);
+    // Chunks still generating were started without the carve too
+    let coords: HashSet<IVec2> = manager.chunks.keys().chain(manager.pending.keys()).copied().collect();
+    for coord in coords {
+        if unapplied.iter().any(|route| carves.touches_chunk(*route, manager.level_coord(coord), &settings)) {
+            // Replaces any task still building the chunk without the carve
+            queue_chunk_generation(&mut manager, coord, &settings, seed, &erosion, &carves, &holes);
+        }
+    }
+
+    // The splat map moves with the world origin, the routes stay in level coordinates
+    let offset = manager.origin().as_vec2() * CHUNK_SIZE;
+    for route in unapplied {
+        let route = &carves.routes[route];
+        let radius = route.carve.width * 0.5 + route.carve.falloff * 0.5;
+        for point in route.points.iter().step_by(((radius * 0.5) as usize).max(1)) {
+            splat_map.paint(point.xz() - offset, route.carve.surface, radius, 1.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_carve_flattens_the_bed_and_blends_the_edges() {
+        let mut carves = TerrainCarves::default();
+        let carve = TerrainCarve { width: 4.0, falloff: 4.0, surface: TerrainLayer::Sand };
+        carves.add(&[Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 2.0, -40.0)], carve);
+
+        // Bed at the spline's height across its width
+        assert!((carves.height(1.5, -20.0, 5.0) - 2.0).abs() < 1e-4);
+        // Halfway down the falloff, between bed and natural ground
+        let edge = carves.height(4.0, -20.0, 5.0);
+        assert!(edge > 2.0 && edge < 5.0);
+        // Untouched beyond it
+        assert_eq!(carves.height(7.0, -20.0, 5.0), 5.0);
+
+        assert_eq!(carves.surface_at(Vec2::new(0.0, -20.0)), Some(TerrainLayer::Sand));
+        assert_eq!(carves.surface_at(Vec2::new(4.0, -20.0)), None);
+    }
+
+    #[test]
+    fn test_only_chunks_under_a_route_are_touched() {
+        let settings = TerrainSettings::default();
+        let mut carves = TerrainCarves::default();
+        carves.add(&[Vec3::new(-10.0, 0.0, 0.0), Vec3::new(10.0, 0.0, 0.0)], TerrainCarve::default());
+        assert!(carves.touches_chunk(0, IVec2::ZERO, &settings));
+        assert!(!carves.touches_chunk(0, IVec2::new(2, 0), &settings));
+    }
+
+    #[test]
+    fn test_spline_passes_through_its_points() {
+        let path = [Vec3::ZERO, Vec3::new(10.0, 1.0, 0.0), Vec3::new(10.0, 2.0, 10.0)];
+        let points = spline_points(&path, 1.0);
+        assert_eq!(points[0], path[0]);
+        assert!(points.iter().any(|point| point.distance(path[1]) < 1e-4));
+        assert!(points.last().unwrap().distance(path[2]) < 1e-4);
+        assert!(points.windows(2).all(|pair| pair[0].distance(pair[1]) < 1.5));
+    }
+}
@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, ShaderType, TextureDimension, TextureFormat};
+
+use super::detail::repeating;
+use super::splat::tile_noise;
+use super::{TerrainChunkManager, TerrainMaterial};
+
+/// Cloud shadows moving over the ground, the weather keeps them in step with the clouds overhead
+#[derive(ShaderType, Clone, Copy, Debug, PartialEq)]
+pub struct CloudShadowUniform {
+    /// How far the wind has carried the cloud layer, in world meters
+    pub scroll: Vec2,
+    /// Coverage texture repeats per meter
+    pub scale: f32,
+    /// Sky covered by cloud (0.0 - 1.0), no shadows at 0.0
+    pub coverage: f32,
+    /// Width of the cloud edges in texture density, the shadows are softer the wider
+    pub softness: f32,
+    /// Share of the sunlight a thick cloud blocks
+    pub strength: f32,
+    /// Height of the cloud layer, shadows are cast from there along the sun
+    pub altitude: f32,
+}
+
+impl Default for CloudShadowUniform {
+    fn default() -> Self {
+        Self {
+            scroll: Vec2::ZERO,
+            scale: 0.001,
+            coverage: 0.0,
+            softness: 0.2,
+            strength: 0.65,
+            altitude: 1000.0,
+        }
+    }
+}
+
+impl CloudShadowUniform {
+    /// Sunlight blocked where the coverage texture reads `density`, mirrors `cloud_shadow` in `terrain.wgsl`.
+    /// Clouds form where the density clears `1 - coverage`, like the volumetric clouds.
+    pub fn shadow(&self, density: f32) -> f32 {
+        if self.coverage <= 0.0 {
+            return 0.0;
+        }
+        let start = 1.0 - self.coverage;
+        let t = ((density - start) / self.softness.max(1.0e-3)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t) * self.strength
+    }
+}
+
+/// Cloud density at `uv`, 0.0 - 1.0. Billowing fbm that tiles seamlessly across whole UV units.
+pub fn cloud_density(uv: Vec2) -> f32 {
+    let mut value = 0.0;
+    let mut amplitude = 0.5;
+    let mut cells = 4;
+    for octave in 0..5 {
+        value += tile_noise(uv, cells, 40 + octave) * amplitude;
+        amplitude *= 0.5;
+        cells *= 2;
+    }
+    // Five octaves add up to just under one
+    (value / 0.96875).clamp(0.0, 1.0)
+}
+
+/// Grayscale cloud density texture the terrain shader projects the shadows from
+pub fn generate_cloud_shadow_texture(size: u32) -> Image {
+    let mut data = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let value = (cloud_density(Vec2::new(x as f32, y as f32) / size as f32) * 255.0).round() as u8;
+            data.extend_from_slice(&[value, value, value, 255]);
+        }
+    }
+    repeating(Image::new(
+        Extent3d { width: size, height: size, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8Unorm,
+    ))
+}
+
+/// Gives the terrain materials their cloud density texture
+pub(super) fn setup_cloud_shadow_texture(
+    manager: Res<TerrainChunkManager>,
+    mut materials: ResMut<Assets<TerrainMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let texture = images.add(generate_cloud_shadow_texture(256));
+    for handle in manager.materials() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.extension.cloud_shadow_texture = Some(texture.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shadows_follow_coverage() {
+        let mut shadow = CloudShadowUniform::default();
+        assert_eq!(shadow.shadow(1.0), 0.0);
+
+        shadow.coverage = 0.5;
+        assert_eq!(shadow.shadow(0.3), 0.0);
+        assert!(shadow.shadow(0.6) > 0.0 && shadow.shadow(0.6) < shadow.strength);
+        assert_eq!(shadow.shadow(0.9), shadow.strength);
+
+        // Overcast shades everything
+        shadow.coverage = 1.0;
+        assert!(shadow.shadow(0.25) > 0.0);
+    }
+
+    #[test]
+    fn test_cloud_texture_tiles_seamlessly() {
+        for t in [0.0, 0.42, 0.77] {
+            assert!((cloud_density(Vec2::new(0.0, t)) - cloud_density(Vec2::new(1.0, t))).abs() < 1e-3);
+            assert!((cloud_density(Vec2::new(t, 0.0)) - cloud_density(Vec2::new(t, 1.0))).abs() < 1e-3);
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use bevy::pbr::ParallaxMappingMethod;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::texture::{ImageAddressMode, ImageSampler, ImageSamplerDescriptor};
+
+use super::{chunk_origin, TerrainChunk, TerrainChunkManager, TerrainMaterial, TerrainSettings, CHUNK_SIZE};
+use crate::game::{GameSettings, TextureQuality};
+
+/// Rock cells across one tile of the detail maps
+const ROCK_CELLS: i32 = 6;
+
+/// Parallax rock relief on the terrain close to the camera
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct TerrainDetailSettings {
+    pub enabled: bool,
+    /// Chunks closer than this to a camera, in meters, get the detail material
+    pub distance: f32,
+    /// Side of the generated detail maps in pixels
+    pub map_size: u32,
+    /// How deep the rock relief looks, in UV units
+    pub depth_scale: f32,
+    /// Most layers the parallax ray march takes at grazing angles
+    pub max_layers: f32,
+    /// Relief mapping refinement steps after the march, 0 uses plain occlusion mapping
+    pub relief_steps: u32,
+}
+
+impl Default for TerrainDetailSettings {
+    fn default() -> Self {
+        Self::for_quality(TextureQuality::High)
+    }
+}
+
+impl TerrainDetailSettings {
+    /// Detail matching a texture quality setting, low quality leaves it off
+    pub fn for_quality(quality: TextureQuality) -> Self {
+        let (enabled, map_size, max_layers, relief_steps) = match quality {
+            TextureQuality::Low => (false, 128, 8.0, 0),
+            TextureQuality::Medium => (true, 256, 12.0, 0),
+            TextureQuality::High => (true, 512, 24.0, 4),
+        };
+        Self {
+            enabled,
+            distance: CHUNK_SIZE * 0.6,
+            map_size,
+            depth_scale: 0.06,
+            max_layers,
+            relief_steps,
+        }
+    }
+
+    fn mapping_method(&self) -> ParallaxMappingMethod {
+        match self.relief_steps {
+            0 => ParallaxMappingMethod::Occlusion,
+            max_steps => ParallaxMappingMethod::Relief { max_steps },
+        }
+    }
+}
+
+/// Tileable hash of a rock cell, the cell's point within it
+fn cell_point(x: i32, y: i32) -> Vec2 {
+    let mut hash = (x.rem_euclid(ROCK_CELLS) as u32).wrapping_mul(0x8da6_b343)
+        ^ (y.rem_euclid(ROCK_CELLS) as u32).wrapping_mul(0xd816_3841);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0x5bd1_e995);
+    hash ^= hash >> 15;
+    Vec2::new((hash & 0xffff) as f32, (hash >> 16) as f32) / 65535.0
+}
+
+/// Height of the rock surface at `uv`, 0.0 in the cracks and 1.0 on top of the stones.
+/// Tiles seamlessly across whole UV units.
+pub fn rock_height(uv: Vec2) -> f32 {
+    let p = (uv - uv.floor()) * ROCK_CELLS as f32;
+    let cell = p.floor();
+    let (mut nearest, mut second) = (f32::MAX, f32::MAX);
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            let neighbour = cell + Vec2::new(dx as f32, dy as f32);
+            let point = neighbour + 0.15 + cell_point(neighbour.x as i32, neighbour.y as i32) * 0.7;
+            let distance = p.distance(point);
+            if distance < nearest {
+                second = nearest;
+                nearest = distance;
+            } else if distance < second {
+                second = distance;
+            }
+        }
+    }
+
+    // Rounded stones, cut by cracks where two cells meet
+    let dome = 1.0 - (nearest * 0.9).min(1.0).powi(2);
+    let crack = ((second - nearest) * 4.0).min(1.0);
+    (dome * crack).clamp(0.0, 1.0)
+}
+
+pub(super) fn repeating(mut image: Image) -> Image {
+    image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+        address_mode_u: ImageAddressMode::Repeat,
+        address_mode_v: ImageAddressMode::Repeat,
+        ..ImageSamplerDescriptor::linear()
+    });
+    image
+}
+
+/// Depth map of the rock relief, as the parallax mapping reads it: 0.0 on top, 1.0 deepest
+pub fn generate_rock_depth_map(size: u32) -> Image {
+    let mut data = Vec::with_capacity((size * size) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let uv = Vec2::new(x as f32, y as f32) / size as f32;
+            data.push(((1.0 - rock_height(uv)) * 255.0).round() as u8);
+        }
+    }
+    repeating(Image::new(
+        Extent3d { width: size, height: size, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        data,
+        TextureFormat::R8Unorm,
+    ))
+}
+
+/// Tangent space normal map of the rock relief, so the stones catch the light
+pub fn generate_rock_normal_map(size: u32) -> Image {
+    let texel = 1.0 / size as f32;
+    // Slopes are per UV unit, scaled down to how steep the relief looks
+    let strength = 0.04;
+    let mut data = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let uv = Vec2::new(x as f32, y as f32) * texel;
+            let dx = rock_height(uv + Vec2::X * texel) - rock_height(uv - Vec2::X * texel);
+            let dy = rock_height(uv + Vec2::Y * texel) - rock_height(uv - Vec2::Y * texel);
+            let normal = Vec3::new(-dx / (2.0 * texel) * strength, -dy / (2.0 * texel) * strength, 1.0).normalize();
+            let encode = |v: f32| ((v * 0.5 + 0.5).clamp(0.0, 1.0) * 255.0).round() as u8;
+            data.extend_from_slice(&[encode(normal.x), encode(normal.y), encode(normal.z), 255]);
+        }
+    }
+    repeating(Image::new(
+        Extent3d { width: size, height: size, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8Unorm,
+    ))
+}
+
+/// Follows the texture quality of the graphics settings
+pub(super) fn apply_texture_quality(game_settings: Res<GameSettings>, mut detail: ResMut<TerrainDetailSettings>) {
+    if !game_settings.is_changed() {
+        return;
+    }
+    let wanted = TerrainDetailSettings {
+        distance: detail.distance,
+        depth_scale: detail.depth_scale,
+        ..TerrainDetailSettings::for_quality(game_settings.graphics.texture_quality)
+    };
+    if *detail != wanted {
+        *detail = wanted;
+    }
+}
+
+/// Rebuilds the detail maps and parallax settings of the detail material
+pub(super) fn update_detail_material(
+    detail: Res<TerrainDetailSettings>,
+    manager: Res<TerrainChunkManager>,
+    mut materials: ResMut<Assets<TerrainMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let Some(material) = materials.get_mut(&manager.detail_material) else {
+        return;
+    };
+    let base = &mut material.base;
+    if !detail.enabled {
+        base.depth_map = None;
+        base.normal_map_texture = None;
+        return;
+    }
+    let size = detail.map_size.max(16);
+    base.depth_map = Some(images.add(generate_rock_depth_map(size)));
+    base.normal_map_texture = Some(images.add(generate_rock_normal_map(size)));
+    base.parallax_depth_scale = detail.depth_scale;
+    base.max_parallax_layer_count = detail.max_layers;
+    base.parallax_mapping_method = detail.mapping_method();
+}
+
+/// Gives chunks near a camera the detail material and the rest the plain one
+pub(super) fn assign_detail_materials(
+    detail: Res<TerrainDetailSettings>,
+    settings: Res<TerrainSettings>,
+    manager: Res<TerrainChunkManager>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    mut chunks: Query<(&TerrainChunk, &mut Handle<TerrainMaterial>)>,
+) {
+    let eyes: Vec<Vec2> = cameras
+        .iter()
+        .filter(|(camera, _)| camera.is_active)
+        .map(|(_, transform)| transform.translation().xz())
+        .collect();
+
+    for (chunk, mut material) in chunks.iter_mut() {
+        // Distance to the chunk's square, zero from inside it
+        let center = chunk_origin(chunk.coord, &settings).xz();
+        let near = detail.enabled
+            && eyes.iter().any(|eye| {
+                let outside = ((*eye - center).abs() - Vec2::splat(CHUNK_SIZE * 0.5)).max(Vec2::ZERO);
+                outside.length() <= detail.distance
+            });
+        let wanted = if near { &manager.detail_material } else { &manager.material };
+        if *material != *wanted {
+            *material = wanted.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rock_relief_tiles_seamlessly() {
+        for t in [0.0, 0.3, 0.71] {
+            assert!((rock_height(Vec2::new(0.0, t)) - rock_height(Vec2::new(1.0, t))).abs() < 1e-4);
+            assert!((rock_height(Vec2::new(t, 0.0)) - rock_height(Vec2::new(t, 1.0))).abs() < 1e-4);
+        }
+        let heights: Vec<f32> = (0..64).map(|i| rock_height(Vec2::splat(i as f32 / 64.0))).collect();
+        assert!(heights.iter().all(|height| (0.0..=1.0).contains(height)));
+        // Actual relief, not a flat map
+        assert!(heights.iter().any(|height| *height > 0.6));
+        assert!(heights.iter().any(|height| *height < 0.3));
+    }
+
+    #[test]
+    fn test_low_quality_turns_detail_off() {
+        assert!(!TerrainDetailSettings::for_quality(TextureQuality::Low).enabled);
+        let high = TerrainDetailSettings::for_quality(TextureQuality::High);
+        assert!(high.enabled);
+        assert_eq!(high.mapping_method(), ParallaxMappingMethod::Relief { max_steps: 4 });
+        let medium = TerrainDetailSettings::for_quality(TextureQuality::Medium);
+        assert_eq!(medium.mapping_method(), ParallaxMappingMethod::Occlusion);
+    }
+}
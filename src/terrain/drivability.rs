@@ -0,0 +1,414 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use super::generation::{world_pos_to_chunk, CHUNK_SIZE};
+
+/// Side of a drivability cell in meters, chunks divide into whole cells
+pub const DRIVABILITY_CELL_SIZE: f32 = 5.0;
+
+const CELLS_PER_CHUNK: i32 = (CHUNK_SIZE / DRIVABILITY_CELL_SIZE) as i32;
+
+/// How well a vehicle gets across a cell, worst last
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Drivability {
+    Drivable,
+    /// Slow going, steep ground or water shallow enough to ford
+    Steep,
+    /// Too steep, too deep or in the way
+    Impassable,
+}
+
+/// Slopes the drivability grid is classified by and what routing through it costs
+#[derive(Resource, Debug, Clone)]
+pub struct DrivabilitySettings {
+    /// Steepest slope in degrees that still counts as drivable
+    pub steep_slope: f32,
+    /// Slopes steeper than this in degrees are impassable
+    pub impassable_slope: f32,
+    /// Water up to this deep in meters can be forded, slowly
+    pub wade_depth: f32,
+    /// How many meters of drivable ground a meter of steep ground is worth to the router
+    pub steep_cost: f32,
+    /// Cells the router looks at before it gives up on a destination
+    pub max_search_cells: usize,
+}
+
+impl Default for DrivabilitySettings {
+    fn default() -> Self {
+        Self {
+            steep_slope: 18.0,
+            impassable_slope: 32.0,
+            wade_depth: 0.6,
+            steep_cost: 4.0,
+            max_search_cells: 20_000,
+        }
+    }
+}
+
+impl DrivabilitySettings {
+    pub fn classify(&self, slope: f32) -> Drivability {
+        if slope > self.impassable_slope {
+            Drivability::Impassable
+        } else if slope > self.steep_slope {
+            Drivability::Steep
+        } else {
+            Drivability::Drivable
+        }
+    }
+}
+
+/// Makes the cells under an entity at least as bad as `drivability`, for water and obstacles
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct DrivabilityBlocker {
+    /// Half size along the entity's local X and Z
+    pub half_size: Vec2,
+    pub drivability: Drivability,
+}
+
+/// Cell containing a world position
+pub fn world_to_cell(position: Vec3) -> IVec2 {
+    (position.xz() / DRIVABILITY_CELL_SIZE).floor().as_ivec2()
+}
+
+/// World XZ center of a cell
+pub fn cell_center(cell: IVec2) -> Vec2 {
+    (cell.as_vec2() + 0.5) * DRIVABILITY_CELL_SIZE
+}
+
+/// Slope and height of each cell of a chunk, worked out with the chunk's mesh
+#[derive(Debug, Clone)]
+pub struct ChunkDrivability {
+    coord: IVec2,
+    /// Steepest slope in degrees across each cell, row by row from -Z
+    slopes: Vec<f32>,
+    /// World height at each cell's center
+    heights: Vec<f32>,
+}
+
+impl ChunkDrivability {
+    /// Samples `height` (world X, Z to world height) around every cell of chunk `coord`
+    pub fn analyze(coord: IVec2, height: impl Fn(f32, f32) -> f32) -> Self {
+        let first = first_cell(coord);
+        let reach = DRIVABILITY_CELL_SIZE * 0.5;
+        let offsets = [
+            Vec2::new(reach, 0.0),
+            Vec2::new(-reach, 0.0),
+            Vec2::new(0.0, reach),
+            Vec2::new(0.0, -reach),
+            Vec2::new(reach, reach),
+            Vec2::new(-reach, reach),
+            Vec2::new(reach, -reach),
+            Vec2::new(-reach, -reach),
+        ];
+
+        let count = (CELLS_PER_CHUNK * CELLS_PER_CHUNK) as usize;
+        let mut slopes = Vec::with_capacity(count);
+        let mut heights = Vec::with_capacity(count);
+        for z in 0..CELLS_PER_CHUNK {
+            for x in 0..CELLS_PER_CHUNK {
+                let center = cell_center(first + IVec2::new(x, z));
+                let middle = height(center.x, center.y);
+                let rise = offsets
+                    .iter()
+                    .map(|offset| {
+                        let edge = center + *offset;
+                        (height(edge.x, edge.y) - middle).abs() / offset.length()
+                    })
+                    .fold(0.0, f32::max);
+                slopes.push(rise.atan().to_degrees());
+                heights.push(middle);
+            }
+        }
+        Self { coord, slopes, heights }
+    }
+
+    pub fn coord(&self) -> IVec2 {
+        self.coord
+    }
+
+    /// Moves the chunk onto the grid of a world origin moved by `chunks`
+    pub fn rebase(&mut self, chunks: IVec2) {
+        self.coord -= chunks;
+    }
+
+    fn index(&self, cell: IVec2) -> Option<usize> {
+        let local = cell - first_cell(self.coord);
+        let inside = (0..CELLS_PER_CHUNK).contains(&local.x) && (0..CELLS_PER_CHUNK).contains(&local.y);
+        inside.then(|| (local.y * CELLS_PER_CHUNK + local.x) as usize)
+    }
+}
+
+fn first_cell(coord: IVec2) -> IVec2 {
+    coord * CELLS_PER_CHUNK - IVec2::splat(CELLS_PER_CHUNK / 2)
+}
+
+/// Coarse drivability of the loaded terrain, for routing vehicles and the map overlay.
+/// Cells of chunks that aren't loaded are unknown and never routed through.
+#[derive(Resource, Debug, Default)]
+pub struct DrivabilityMap {
+    chunks: HashMap<IVec2, ChunkDrivability>,
+    /// Cells made worse than their slope by water and obstacles
+    blocked: HashMap<IVec2, Drivability>,
+}
+
+impl DrivabilityMap {
+    pub fn insert_chunk(&mut self, chunk: ChunkDrivability) {
+        self.chunks.insert(chunk.coord, chunk);
+    }
+
+    pub fn remove_chunk(&mut self, coord: IVec2) {
+        self.chunks.remove(&coord);
+    }
+
+    /// Follows the world origin moving by `chunks`
+    pub fn rebase(&mut self, chunks: IVec2) {
+        self.chunks = std::mem::take(&mut self.chunks)
+            .into_values()
+            .map(|mut chunk| {
+                chunk.rebase(chunks);
+                (chunk.coord, chunk)
+            })
+            .collect();
+        let cells = chunks * CELLS_PER_CHUNK;
+        self.blocked = std::mem::take(&mut self.blocked)
+            .into_iter()
+            .map(|(cell, drivability)| (cell - cells, drivability))
+            .collect();
+    }
+
+    /// Marks the cells under `blocker` at `transform`, keeping anything worse already there
+    pub fn block(&mut self, blocker: &DrivabilityBlocker, transform: &GlobalTransform) {
+        let affine = transform.affine();
+        let inverse = affine.inverse();
+        let half = blocker.half_size;
+        let corners = [Vec2::ONE, Vec2::new(1.0, -1.0), Vec2::NEG_ONE, Vec2::new(-1.0, 1.0)]
+            .map(|corner| affine.transform_point3(Vec3::new(corner.x * half.x, 0.0, corner.y * half.y)));
+        let (min, max) = corners.iter().fold((IVec2::MAX, IVec2::MIN), |(min, max), corner| {
+            (min.min(world_to_cell(*corner)), max.max(world_to_cell(*corner)))
+        });
+        // Any cell the footprint reaches into, not just the ones whose center it covers
+        let margin = Vec2::splat(DRIVABILITY_CELL_SIZE * 0.5);
+        for z in min.y..=max.y {
+            for x in min.x..=max.x {
+                let cell = IVec2::new(x, z);
+                let center = cell_center(cell);
+                let local = inverse.transform_point3(Vec3::new(center.x, transform.translation().y, center.y));
+                if local.xz().abs().cmple(blocker.half_size + margin).all() {
+                    let entry = self.blocked.entry(cell).or_insert(blocker.drivability);
+                    *entry = (*entry).max(blocker.drivability);
+                }
+            }
+        }
+    }
+
+    pub fn clear_blocked(&mut self) {
+        self.blocked.clear();
+    }
+
+    /// Loaded chunk holding a cell and the cell's index in it
+    fn chunk_of(&self, cell: IVec2) -> Option<(&ChunkDrivability, usize)> {
+        let center = cell_center(cell);
+        let chunk = self.chunks.get(&world_pos_to_chunk(Vec3::new(center.x, 0.0, center.y)))?;
+        Some((chunk, chunk.index(cell)?))
+    }
+
+    fn classify(&self, cell: IVec2, slope: f32, settings: &DrivabilitySettings) -> Drivability {
+        let natural = settings.classify(slope);
+        self.blocked.get(&cell).map_or(natural, |blocked| natural.max(*blocked))
+    }
+
+    /// Drivability of a cell, `None` while its chunk isn't loaded
+    pub fn drivability(&self, cell: IVec2, settings: &DrivabilitySettings) -> Option<Drivability> {
+        let (chunk, index) = self.chunk_of(cell)?;
+        Some(self.classify(cell, chunk.slopes[index], settings))
+    }
+
+    /// Drivability of the ground under a world position
+    pub fn drivability_at(&self, position: Vec3, settings: &DrivabilitySettings) -> Option<Drivability> {
+        self.drivability(world_to_cell(position), settings)
+    }
+
+    /// World height at the center of a loaded cell
+    pub fn height(&self, cell: IVec2) -> Option<f32> {
+        let (chunk, index) = self.chunk_of(cell)?;
+        Some(chunk.heights[index])
+    }
+
+    /// Every loaded cell with its drivability
+    pub fn cells<'a>(&'a self, settings: &'a DrivabilitySettings) -> impl Iterator<Item = (IVec2, Drivability)> + 'a {
+        self.chunks.values().flat_map(move |chunk| {
+            let first = first_cell(chunk.coord);
+            (0..chunk.slopes.len()).map(move |index| {
+                let cell = first + IVec2::new(index as i32 % CELLS_PER_CHUNK, index as i32 / CELLS_PER_CHUNK);
+                (cell, self.classify(cell, chunk.slopes[index], settings))
+            })
+        })
+    }
+
+    /// Waypoints from `from` to `to` over drivable ground where there is some, steep ground where there
+    /// isn't, and never through impassable or unloaded cells. Straight runs are merged into one waypoint
+    /// and the last one is `to` itself. `None` if there's no way there.
+    pub fn route(&self, from: Vec3, to: Vec3, settings: &DrivabilitySettings) -> Option<Vec<Vec3>> {
+        let (start, goal) = (world_to_cell(from), world_to_cell(to));
+        if self.drivability(goal, settings)? == Drivability::Impassable {
+            return None;
+        }
+        let passable = |cell: IVec2| {
+            self.drivability(cell, settings).filter(|drivability| *drivability != Drivability::Impassable)
+        };
+        let estimate = |cell: IVec2| (goal - cell).as_vec2().length() * DRIVABILITY_CELL_SIZE;
+        // Costs in centimeters so the heap can order them
+        let key = |cost: f32| (cost * 100.0) as u64;
+
+        let mut open = BinaryHeap::new();
+        let mut costs: HashMap<IVec2, f32> = HashMap::default();
+        let mut came_from: HashMap<IVec2, IVec2> = HashMap::default();
+        costs.insert(start, 0.0);
+        open.push(Reverse((key(estimate(start)), start.x, start.y)));
+
+        let mut searched = 0;
+        while let Some(Reverse((_, x, z))) = open.pop() {
+            let cell = IVec2::new(x, z);
+            if cell == goal {
+                return Some(self.waypoints(goal, &came_from, to));
+            }
+            searched += 1;
+            if searched > settings.max_search_cells {
+                return None;
+            }
+
+            let cost = costs[&cell];
+            for step in NEIGHBOURS {
+                let next = cell + step;
+                let Some(drivability) = passable(next) else {
+                    continue;
+                };
+                // No cutting the corner of an impassable cell
+                let corners = [cell + IVec2::new(step.x, 0), cell + IVec2::new(0, step.y)];
+                if step.x != 0 && step.y != 0 && corners.iter().any(|corner| passable(*corner).is_none()) {
+                    continue;
+                }
+                let weight = match drivability {
+                    Drivability::Steep => settings.steep_cost,
+                    _ => 1.0,
+                };
+                let next_cost = cost + step.as_vec2().length() * DRIVABILITY_CELL_SIZE * weight;
+                if costs.get(&next).map_or(true, |known| next_cost < *known) {
+                    costs.insert(next, next_cost);
+                    came_from.insert(next, cell);
+                    open.push(Reverse((key(next_cost + estimate(next)), next.x, next.y)));
+                }
+            }
+        }
+        None
+    }
+
+    fn waypoints(&self, goal: IVec2, came_from: &HashMap<IVec2, IVec2>, to: Vec3) -> Vec<Vec3> {
+        let mut cells = vec![goal];
+        while let Some(previous) = came_from.get(cells.last().unwrap()) {
+            cells.push(*previous);
+        }
+        cells.reverse();
+
+        let mut waypoints = Vec::new();
+        for (index, cell) in cells.iter().enumerate().skip(1) {
+            let Some(next) = cells.get(index + 1) else {
+                break;
+            };
+            // Only where the route turns
+            if *next - *cell != *cell - cells[index - 1] {
+                let center = cell_center(*cell);
+                waypoints.push(Vec3::new(center.x, self.height(*cell).unwrap_or(to.y), center.y));
+            }
+        }
+        waypoints.push(to);
+        waypoints
+    }
+}
+
+const NEIGHBOURS: [IVec2; 8] = [
+    IVec2::new(1, 0),
+    IVec2::new(-1, 0),
+    IVec2::new(0, 1),
+    IVec2::new(0, -1),
+    IVec2::new(1, 1),
+    IVec2::new(-1, 1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, -1),
+];
+
+/// Rebuilds the blocked cells when water or obstacles appear, move or go away
+pub(super) fn update_drivability_blockers(
+    mut map: ResMut<DrivabilityMap>,
+    blockers: Query<(&DrivabilityBlocker, &GlobalTransform)>,
+    changed: Query<(), (With<DrivabilityBlocker>, Or<(Changed<DrivabilityBlocker>, Changed<GlobalTransform>)>)>,
+    mut removed: RemovedComponents<DrivabilityBlocker>,
+) {
+    // Read either way so old removals don't pile up
+    let removed = removed.read().count() > 0;
+    if !removed && changed.is_empty() {
+        return;
+    }
+    map.clear_blocked();
+    for (blocker, transform) in blockers.iter() {
+        map.block(blocker, transform);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat_map() -> DrivabilityMap {
+        let mut map = DrivabilityMap::default();
+        for z in -1..=1 {
+            for x in -1..=1 {
+                map.insert_chunk(ChunkDrivability::analyze(IVec2::new(x, z), |_, _| 0.0));
+            }
+        }
+        map
+    }
+
+    #[test]
+    fn test_slopes_classify_cells() {
+        let settings = DrivabilitySettings::default();
+        let mut map = DrivabilityMap::default();
+        // Gentle ramp west of x = 0, a 45 degree wall east of it
+        map.insert_chunk(ChunkDrivability::analyze(IVec2::ZERO, |x, _| if x < 0.0 { x * 0.1 } else { x }));
+        assert_eq!(map.drivability_at(Vec3::new(-30.0, 0.0, 0.0), &settings), Some(Drivability::Drivable));
+        assert_eq!(map.drivability_at(Vec3::new(30.0, 0.0, 0.0), &settings), Some(Drivability::Impassable));
+        assert_eq!(map.drivability_at(Vec3::new(300.0, 0.0, 0.0), &settings), None);
+        assert_eq!(map.cells(&settings).count(), (CELLS_PER_CHUNK * CELLS_PER_CHUNK) as usize);
+    }
+
+    #[test]
+    fn test_route_goes_around_a_blocker() {
+        let settings = DrivabilitySettings::default();
+        let mut map = flat_map();
+        let from = Vec3::new(-40.0, 0.0, 2.5);
+        let to = Vec3::new(40.0, 0.0, 2.5);
+        assert_eq!(map.route(from, to, &settings), Some(vec![to]));
+
+        // A wall across the straight line
+        let wall = DrivabilityBlocker { half_size: Vec2::new(2.0, 40.0), drivability: Drivability::Impassable };
+        map.block(&wall, &GlobalTransform::IDENTITY);
+        let route = map.route(from, to, &settings).unwrap();
+        assert_eq!(*route.last().unwrap(), to);
+        assert!(route.len() > 1);
+        assert!(route.iter().all(|point| map.drivability_at(*point, &settings) != Some(Drivability::Impassable)));
+        assert!(route.iter().any(|point| point.z.abs() > 40.0));
+
+        map.clear_blocked();
+        assert_eq!(map.route(from, to, &settings).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_no_route_off_the_loaded_terrain() {
+        let settings = DrivabilitySettings::default();
+        let map = flat_map();
+        assert!(map.route(Vec3::ZERO, Vec3::new(500.0, 0.0, 0.0), &settings).is_none());
+    }
+}
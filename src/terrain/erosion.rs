@@ -0,0 +1,332 @@
+use std::sync::Arc;
+
+use bevy::math::Rect;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::generation::{chunk_origin, TerrainSettings, CHUNK_SIZE};
+
+fn default_extent() -> f32 {
+    400.0
+}
+
+fn default_cell_size() -> f32 {
+    1.0
+}
+
+fn default_droplets() -> u32 {
+    40_000
+}
+
+fn default_max_steps() -> u32 {
+    80
+}
+
+fn default_inertia() -> f32 {
+    0.05
+}
+
+fn default_capacity() -> f32 {
+    4.0
+}
+
+fn default_rate() -> f32 {
+    0.3
+}
+
+fn default_evaporation() -> f32 {
+    0.02
+}
+
+fn default_gravity() -> f32 {
+    4.0
+}
+
+fn default_radius() -> f32 {
+    2.5
+}
+
+/// How rain wears gullies and washes into a square of the level, as written in a level file
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ErosionSettings {
+    /// World XZ of the eroded square's center
+    #[serde(default)]
+    pub center: [f32; 2],
+    /// Meters the eroded square covers along each side, its edges blend back into the untouched ground
+    #[serde(default = "default_extent")]
+    pub extent: f32,
+    /// Spacing of the simulated height grid in meters
+    #[serde(default = "default_cell_size")]
+    pub cell_size: f32,
+    /// Raindrops simulated, more cut deeper and more connected channels
+    #[serde(default = "default_droplets")]
+    pub droplets: u32,
+    /// Steps a drop runs before it's dropped
+    #[serde(default = "default_max_steps")]
+    pub max_steps: u32,
+    /// How much a drop keeps its direction instead of following the slope (0.0 - 1.0)
+    #[serde(default = "default_inertia")]
+    pub inertia: f32,
+    /// Sediment a drop carries per unit of speed, water and drop in height
+    #[serde(default = "default_capacity")]
+    pub capacity: f32,
+    /// Share of the spare capacity picked up from the ground each step
+    #[serde(default = "default_rate")]
+    pub erode_rate: f32,
+    /// Share of the excess sediment dropped each step
+    #[serde(default = "default_rate")]
+    pub deposit_rate: f32,
+    /// Water lost per step
+    #[serde(default = "default_evaporation")]
+    pub evaporation: f32,
+    #[serde(default = "default_gravity")]
+    pub gravity: f32,
+    /// Radius in cells a drop wears the ground away over, wider makes smoother gullies
+    #[serde(default = "default_radius")]
+    pub radius: f32,
+}
+
+impl Default for ErosionSettings {
+    fn default() -> Self {
+        Self {
+            center: [0.0; 2],
+            extent: default_extent(),
+            cell_size: default_cell_size(),
+            droplets: default_droplets(),
+            max_steps: default_max_steps(),
+            inertia: default_inertia(),
+            capacity: default_capacity(),
+            erode_rate: default_rate(),
+            deposit_rate: default_rate(),
+            evaporation: default_evaporation(),
+            gravity: default_gravity(),
+            radius: default_radius(),
+        }
+    }
+}
+
+/// Height change erosion left on a grid over the eroded square
+#[derive(Debug, Clone)]
+struct ErosionMap {
+    /// World XZ of the grid's corner
+    origin: Vec2,
+    cell_size: f32,
+    /// Vertices along each side
+    size: usize,
+    delta: Vec<f32>,
+    /// Meters in from the edge over which the change fades in
+    fade: f32,
+}
+
+impl ErosionMap {
+    fn bounds(&self) -> Rect {
+        Rect::from_corners(self.origin, self.origin + Vec2::splat((self.size - 1) as f32 * self.cell_size))
+    }
+
+    fn delta_at(&self, point: Vec2) -> f32 {
+        let local = (point - self.origin) / self.cell_size;
+        let last = (self.size - 1) as f32;
+        if local.x < 0.0 || local.y < 0.0 || local.x >= last || local.y >= last {
+            return 0.0;
+        }
+        let (delta, _) = height_and_gradient(&self.delta, self.size, local);
+
+        let bounds = self.bounds();
+        let inset = (point - bounds.min).min(bounds.max - point).min_element();
+        let t = (inset / self.fade.max(1e-3)).clamp(0.0, 1.0);
+        delta * t * t * (3.0 - 2.0 * t)
+    }
+}
+
+/// Gullies and washes a level's rain wore into the terrain, baked once when the level loads.
+/// Cheap to clone into chunk generation tasks.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct TerrainErosion {
+    map: Option<Arc<ErosionMap>>,
+}
+
+impl TerrainErosion {
+    pub fn is_empty(&self) -> bool {
+        self.map.is_none()
+    }
+
+    /// XZ area the erosion reaches
+    pub fn bounds(&self) -> Option<Rect> {
+        self.map.as_ref().map(|map| map.bounds())
+    }
+
+    /// World height of ground that would naturally be at `natural` after erosion
+    pub fn height(&self, x: f32, z: f32, natural: f32) -> f32 {
+        natural + self.map.as_ref().map_or(0.0, |map| map.delta_at(Vec2::new(x, z)))
+    }
+
+    /// Whether the eroded square reaches into chunk `coord`
+    pub fn touches_chunk(&self, coord: IVec2, settings: &TerrainSettings) -> bool {
+        let center = chunk_origin(coord, settings).xz();
+        let margin = CHUNK_SIZE / settings.resolution.max(1) as f32;
+        let chunk = Rect::from_center_half_size(center, Vec2::splat(CHUNK_SIZE * 0.5 + margin));
+        self.bounds().is_some_and(|bounds| !bounds.intersect(chunk).is_empty())
+    }
+}
+
+/// Bilinear height and its slope per cell at grid position `position`, which has to be inside the grid
+fn height_and_gradient(heights: &[f32], size: usize, position: Vec2) -> (f32, Vec2) {
+    let cell = position.floor();
+    let (x, z) = (cell.x as usize, cell.y as usize);
+    let f = position - cell;
+    let h00 = heights[z * size + x];
+    let h10 = heights[z * size + x + 1];
+    let h01 = heights[(z + 1) * size + x];
+    let h11 = heights[(z + 1) * size + x + 1];
+    let gradient = Vec2::new(
+        (h10 - h00) * (1.0 - f.y) + (h11 - h01) * f.y,
+        (h01 - h00) * (1.0 - f.x) + (h11 - h10) * f.x,
+    );
+    let height = h00 * (1.0 - f.x) * (1.0 - f.y) + h10 * f.x * (1.0 - f.y) + h01 * (1.0 - f.x) * f.y + h11 * f.x * f.y;
+    (height, gradient)
+}
+
+/// Xorshift, so the same level erodes the same on every machine
+struct DropRandom(u32);
+
+impl DropRandom {
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 >> 8) as f32 / (1u32 << 24) as f32
+    }
+}
+
+/// Runs raindrops down the ground `height` gives for world `x`, `z` over the square of `settings`,
+/// each picking up sediment where it speeds downhill and dropping it where it slows or pools
+pub fn erode(settings: &ErosionSettings, seed: u32, height: impl Fn(f32, f32) -> f32) -> TerrainErosion {
+    let cell_size = settings.cell_size.max(0.1);
+    let size = ((settings.extent / cell_size).ceil() as usize).max(2) + 1;
+    let origin = Vec2::from(settings.center) - Vec2::splat((size - 1) as f32 * cell_size * 0.5);
+    let original: Vec<f32> = (0..size * size)
+        .map(|index| {
+            let world = origin + Vec2::new((index % size) as f32, (index / size) as f32) * cell_size;
+            height(world.x, world.y)
+        })
+        .collect();
+    let mut heights = original.clone();
+
+    let radius = settings.radius.max(0.5);
+    let reach = radius.ceil() as i32;
+    let last = (size - 1) as f32;
+    let mut random = DropRandom(seed.wrapping_mul(0x9e37_79b9) | 1);
+    for _ in 0..settings.droplets {
+        let mut position = Vec2::new(random.next(), random.next()) * last;
+        let mut direction = Vec2::ZERO;
+        let (mut speed, mut water, mut sediment) = (1.0_f32, 1.0_f32, 0.0_f32);
+
+        for _ in 0..settings.max_steps {
+            let (current, gradient) = height_and_gradient(&heights, size, position);
+            direction = direction * settings.inertia - gradient * (1.0 - settings.inertia);
+            if direction.length_squared() < 1e-12 {
+                break;
+            }
+            direction = direction.normalize();
+            let next = position + direction;
+            if next.x < 0.0 || next.y < 0.0 || next.x >= last || next.y >= last {
+                break;
+            }
+
+            let drop = height_and_gradient(&heights, size, next).0 - current;
+            let capacity = (-drop * speed * water * settings.capacity).max(0.01);
+            let cell = position.floor();
+            if sediment > capacity || drop > 0.0 {
+                // Uphill fills the hollow behind it, otherwise only the excess settles
+                let deposit = if drop > 0.0 {
+                    drop.min(sediment)
+                } else {
+                    (sediment - capacity) * settings.deposit_rate
+                };
+                sediment -= deposit;
+                let f = position - cell;
+                let index = cell.y as usize * size + cell.x as usize;
+                heights[index] += deposit * (1.0 - f.x) * (1.0 - f.y);
+                heights[index + 1] += deposit * f.x * (1.0 - f.y);
+                heights[index + size] += deposit * (1.0 - f.x) * f.y;
+                heights[index + size + 1] += deposit * f.x * f.y;
+            } else {
+                let amount = ((capacity - sediment) * settings.erode_rate).min(-drop);
+                let brush: Vec<(usize, f32)> = (-reach..=reach)
+                    .flat_map(|z| (-reach..=reach).map(move |x| IVec2::new(x, z)))
+                    .filter_map(|offset| {
+                        let corner = cell.as_ivec2() + offset;
+                        let inside = corner.cmpge(IVec2::ZERO).all() && corner.cmplt(IVec2::splat(size as i32)).all();
+                        let weight = radius - (corner.as_vec2() - position).length();
+                        (inside && weight > 0.0).then(|| (corner.y as usize * size + corner.x as usize, weight))
+                    })
+                    .collect();
+                let total: f32 = brush.iter().map(|(_, weight)| weight).sum();
+                for (index, weight) in brush {
+                    heights[index] -= amount * weight / total;
+                }
+                sediment += amount;
+            }
+
+            speed = (speed * speed - drop * settings.gravity).max(0.0).sqrt();
+            water *= 1.0 - settings.evaporation;
+            position = next;
+        }
+    }
+
+    let delta = heights.iter().zip(&original).map(|(eroded, original)| eroded - original).collect();
+    TerrainErosion {
+        map: Some(Arc::new(ErosionMap {
+            origin,
+            cell_size,
+            size,
+            delta,
+            fade: settings.extent * 0.1,
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hillside(x: f32, z: f32) -> f32 {
+        // Falls away to +X with a ripple across it for the water to gather in
+        -x * 0.15 + (z * 0.2).sin() * 0.5
+    }
+
+    #[test]
+    fn test_rain_cuts_gullies_and_leaves_the_edges_alone() {
+        let settings = ErosionSettings { extent: 60.0, droplets: 3000, ..default() };
+        let erosion = erode(&settings, 3, hillside);
+        let bounds = erosion.bounds().unwrap();
+
+        let mut lowest: f32 = 0.0;
+        let mut highest: f32 = 0.0;
+        for z in -25..25 {
+            for x in -25..25 {
+                let (x, z) = (x as f32, z as f32);
+                let change = erosion.height(x, z, hillside(x, z)) - hillside(x, z);
+                lowest = lowest.min(change);
+                highest = highest.max(change);
+            }
+        }
+        assert!(lowest < -0.01, "nothing was worn away");
+        assert!(highest > 0.0, "nothing settled");
+
+        // Blends back into the untouched ground at the edge and doesn't reach past it
+        assert_eq!(erosion.height(bounds.min.x, 0.0, 1.0), 1.0);
+        assert_eq!(erosion.height(100.0, 0.0, 1.0), 1.0);
+        assert!(TerrainErosion::default().is_empty());
+    }
+
+    #[test]
+    fn test_same_seed_same_erosion() {
+        let settings = ErosionSettings { extent: 30.0, droplets: 500, ..default() };
+        let first = erode(&settings, 9, hillside);
+        let second = erode(&settings, 9, hillside);
+        for x in [-7.5, 0.3, 6.1] {
+            assert_eq!(first.height(x, 2.0, 0.0), second.height(x, 2.0, 0.0));
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy_rapier3d::prelude::*;
+use noise::{Fbm, MultiFractal, NoiseFn, Perlin};
+
+use super::carving::TerrainCarves;
+use super::drivability::ChunkDrivability;
+use super::erosion::TerrainErosion;
+use super::holes::TerrainHoles;
+use super::query::ChunkHeights;
+
+/// Width of a terrain chunk in meters, chunk (0, 0) is centered on the origin
+pub const CHUNK_SIZE: f32 = 100.0;
+
+/// Times the detail maps repeat across a chunk, whole so they line up across chunk borders
+pub const DETAIL_TILES_PER_CHUNK: f32 = 40.0;
+
+/// Shape of the generated terrain and how it streams in
+#[derive(Resource, Debug, Clone)]
+pub struct TerrainSettings {
+    pub noise_scale: f32,
+    pub height_multiplier: f32,
+    /// Lacunarity is twice the roughness
+    pub roughness: f32,
+    pub persistence: f32,
+    pub octaves: usize,
+    /// Quads along each side of a chunk
+    pub resolution: u32,
+    /// Height of the terrain's zero level
+    pub base_height: f32,
+    /// Chunks kept loaded in each direction around a camera
+    pub view_distance: i32,
+    /// Chunk generation tasks allowed to run at once
+    pub max_pending_tasks: usize,
+    /// Finished chunks turned into entities per frame, uploading meshes and colliders costs time too
+    pub uploads_per_frame: usize,
+}
+
+impl Default for TerrainSettings {
+    fn default() -> Self {
+        Self {
+            noise_scale: 0.02,
+            height_multiplier: 5.0,
+            roughness: 1.0,
+            persistence: 0.5,
+            octaves: 4,
+            resolution: 64,
+            base_height: -2.0,
+            view_distance: 2,
+            max_pending_tasks: 4,
+            uploads_per_frame: 1,
+        }
+    }
+}
+
+/// Chunk containing a world position
+pub fn world_pos_to_chunk(position: Vec3) -> IVec2 {
+    IVec2::new(
+        (position.x / CHUNK_SIZE + 0.5).floor() as i32,
+        (position.z / CHUNK_SIZE + 0.5).floor() as i32,
+    )
+}
+
+/// World position of a chunk's center at the terrain's zero level
+pub fn chunk_origin(coord: IVec2, settings: &TerrainSettings) -> Vec3 {
+    Vec3::new(coord.x as f32 * CHUNK_SIZE, settings.base_height, coord.y as f32 * CHUNK_SIZE)
+}
+
+pub fn terrain_noise(settings: &TerrainSettings, seed: u32) -> Fbm<Perlin> {
+    Fbm::<Perlin>::new(seed)
+        .set_octaves(settings.octaves)
+        .set_persistence(settings.persistence as f64)
+        .set_lacunarity((settings.roughness * 2.0) as f64)
+}
+
+/// Terrain height relative to the base height at world `x`, `z`
+pub fn sample_height(noise: &Fbm<Perlin>, settings: &TerrainSettings, x: f32, z: f32) -> f32 {
+    let point = [(x * settings.noise_scale) as f64, (z * settings.noise_scale) as f64];
+    noise.get(point) as f32 * settings.height_multiplier
+}
+
+/// World height of the ground at `x`, `z`, eroded and with trails and rivers carved in
+pub fn ground_height(
+    noise: &Fbm<Perlin>,
+    settings: &TerrainSettings,
+    erosion: &TerrainErosion,
+    carves: &TerrainCarves,
+    x: f32,
+    z: f32,
+) -> f32 {
+    let natural = settings.base_height + sample_height(noise, settings, x, z);
+    carves.height(x, z, erosion.height(x, z, natural))
+}
+
+/// Everything needed to spawn a chunk, built off the main thread
+pub struct ChunkMeshData {
+    pub coord: IVec2,
+    pub mesh: Mesh,
+    /// `None` when holes cut away the whole chunk
+    pub collider: Option<Collider>,
+    pub drivability: ChunkDrivability,
+    pub heights: ChunkHeights,
+}
+
+impl ChunkMeshData {
+    /// Moves a chunk generated in level coordinates onto the grid of a world origin at chunk `chunks`,
+    /// the mesh and collider are relative to the chunk and stay as they are
+    pub fn rebase(&mut self, chunks: IVec2) {
+        self.coord -= chunks;
+        self.drivability.rebase(chunks);
+        self.heights.rebase(chunks);
+    }
+}
+
+/// Builds the mesh, trimesh collider and drivability of one chunk, pure so it can run on a task pool thread
+pub fn generate_chunk(
+    coord: IVec2,
+    settings: &TerrainSettings,
+    seed: u32,
+    erosion: &TerrainErosion,
+    carves: &TerrainCarves,
+    holes: &TerrainHoles,
+) -> ChunkMeshData {
+    let noise = terrain_noise(settings, seed);
+    let resolution = settings.resolution.max(1);
+    let step = CHUNK_SIZE / resolution as f32;
+    let origin = chunk_origin(coord, settings);
+    // Sampled in world space so neighbouring chunks share their edge heights
+    let height = |local_x: f32, local_z: f32| {
+        ground_height(&noise, settings, erosion, carves, origin.x + local_x, origin.z + local_z) - origin.y
+    };
+
+    let side = resolution as usize + 1;
+    let mut positions = Vec::with_capacity(side * side);
+    let mut normals = Vec::with_capacity(side * side);
+    let mut uvs = Vec::with_capacity(side * side);
+    for z in 0..=resolution {
+        for x in 0..=resolution {
+            let px = x as f32 * step - CHUNK_SIZE * 0.5;
+            let pz = z as f32 * step - CHUNK_SIZE * 0.5;
+            positions.push([px, height(px, pz), pz]);
+
+            // Central differences, also across the chunk border so lighting has no seams
+            let dx = height(px + step, pz) - height(px - step, pz);
+            let dz = height(px, pz + step) - height(px, pz - step);
+            normals.push(Vec3::new(-dx, 2.0 * step, -dz).normalize().to_array());
+            let uv = Vec2::new(x as f32, z as f32) / resolution as f32 * DETAIL_TILES_PER_CHUNK;
+            uvs.push(uv.to_array());
+        }
+    }
+
+    let mut indices = Vec::with_capacity(resolution as usize * resolution as usize * 6);
+    for z in 0..resolution {
+        for x in 0..resolution {
+            let center = Vec2::new(x as f32 + 0.5, z as f32 + 0.5) * step - Vec2::splat(CHUNK_SIZE * 0.5);
+            if holes.contains(origin.xz() + center) {
+                continue;
+            }
+            let top_left = z * (resolution + 1) + x;
+            let top_right = top_left + 1;
+            let bottom_left = (z + 1) * (resolution + 1) + x;
+            let bottom_right = bottom_left + 1;
+            indices.extend_from_slice(&[
+                top_left,
+                bottom_left,
+                top_right,
+                top_right,
+                bottom_left,
+                bottom_right,
+            ]);
+        }
+    }
+
+    // Kept for height queries, in world space like the drivability
+    let world_heights = positions.iter().map(|position| position[1] + origin.y).collect();
+    let heights = ChunkHeights::new(coord, resolution, world_heights);
+
+    // A trimesh needs at least one triangle
+    let collider = (!indices.is_empty()).then(|| {
+        Collider::trimesh(
+            positions.iter().map(|position| Vec3::from(*position)).collect(),
+            indices.chunks(3).map(|i| [i[0], i[1], i[2]]).collect(),
+        )
+    });
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    // Parallax and normal mapping of the detail material need tangents
+    if let Err(error) = mesh.generate_tangents() {
+        warn!("terrain chunk {coord} has no tangents: {error}");
+    }
+
+    let drivability =
+        ChunkDrivability::analyze(coord, |x, z| ground_height(&noise, settings, erosion, carves, x, z));
+
+    ChunkMeshData { coord, mesh, collider, drivability, heights }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_settings() -> TerrainSettings {
+        TerrainSettings {
+            resolution: 8,
+            ..default()
+        }
+    }
+
+    /// A chunk without carves or holes
+    fn plain_chunk(coord: IVec2, settings: &TerrainSettings, seed: u32) -> ChunkMeshData {
+        let erosion = TerrainErosion::default();
+        generate_chunk(coord, settings, seed, &erosion, &TerrainCarves::default(), &TerrainHoles::default())
+    }
+
+    fn positions(data: &ChunkMeshData) -> Vec<[f32; 3]> {
+        data.mesh
+            .attribute(Mesh::ATTRIBUTE_POSITION)
+            .and_then(|attribute| attribute.as_float3())
+            .unwrap()
+            .to_vec()
+    }
+
+    #[test]
+    fn test_world_pos_to_chunk() {
+        assert_eq!(world_pos_to_chunk(Vec3::new(25.0, 0.0, 25.0)), IVec2::new(0, 0));
+        assert_eq!(world_pos_to_chunk(Vec3::new(49.0, 0.0, -49.0)), IVec2::new(0, 0));
+        assert_eq!(world_pos_to_chunk(Vec3::new(51.0, 0.0, -51.0)), IVec2::new(1, -1));
+        assert_eq!(world_pos_to_chunk(Vec3::new(1000.0, 0.0, 1000.0)), IVec2::new(10, 10));
+    }
+
+    #[test]
+    fn test_chunk_vertex_layout() {
+        let settings = small_settings();
+        let data = plain_chunk(IVec2::ZERO, &settings, 0);
+        assert_eq!(data.mesh.count_vertices(), 81);
+        assert_eq!(data.mesh.indices().unwrap().len(), 8 * 8 * 6);
+    }
+
+    #[test]
+    fn test_neighbouring_chunks_share_edges() {
+        let settings = small_settings();
+        let left = positions(&plain_chunk(IVec2::new(0, 0), &settings, 7));
+        let right = positions(&plain_chunk(IVec2::new(1, 0), &settings, 7));
+        let side = settings.resolution as usize + 1;
+        for row in 0..side {
+            let left_edge = left[row * side + side - 1][1];
+            let right_edge = right[row * side][1];
+            assert!((left_edge - right_edge).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_same_seed_same_terrain() {
+        let settings = small_settings();
+        let coord = IVec2::new(3, -2);
+        assert_eq!(positions(&plain_chunk(coord, &settings, 11)), positions(&plain_chunk(coord, &settings, 11)));
+        assert_ne!(positions(&plain_chunk(coord, &settings, 11)), positions(&plain_chunk(coord, &settings, 12)));
+    }
+
+    #[test]
+    fn test_chunk_heights_match_the_ground() {
+        let settings = small_settings();
+        let carves = TerrainCarves::default();
+        let mut query = crate::terrain::TerrainQuery::default();
+        query.insert_chunk(plain_chunk(IVec2::new(1, 0), &settings, 5).heights);
+
+        let noise = terrain_noise(&settings, 5);
+        // A vertex of the chunk's mesh, where the sampled heights are exact
+        let (x, z) = (50.0 + CHUNK_SIZE / 8.0 * 3.0, CHUNK_SIZE / 8.0 * 2.0 - 50.0);
+        let expected = ground_height(&noise, &settings, &TerrainErosion::default(), &carves, x, z);
+        assert!((query.height(x, z).unwrap() - expected).abs() < 1e-4);
+    }
+}
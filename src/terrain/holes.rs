@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use bevy::math::Rect;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+use super::carving::TerrainCarves;
+use super::generation::{chunk_origin, CHUNK_SIZE};
+use super::erosion::TerrainErosion;
+use super::{queue_chunk_generation, TerrainChunkManager, TerrainSeed, TerrainSettings};
+
+/// An area cut out of the heightmap, a closed outline in world XZ
+#[derive(Debug, Clone)]
+struct TerrainHole {
+    outline: Vec<Vec2>,
+    bounds: Rect,
+}
+
+impl TerrainHole {
+    /// Even-odd test, points on the outline may land either side
+    fn contains(&self, point: Vec2) -> bool {
+        if !self.bounds.contains(point) {
+            return false;
+        }
+        let mut inside = false;
+        let mut previous = self.outline[self.outline.len() - 1];
+        for &current in &self.outline {
+            if (current.y > point.y) != (previous.y > point.y) {
+                let crossing = current.x + (point.y - current.y) / (previous.y - current.y) * (previous.x - current.x);
+                if point.x < crossing {
+                    inside = !inside;
+                }
+            }
+            previous = current;
+        }
+        inside
+    }
+}
+
+/// Holes punched into the terrain where authored meshes take over, the mouths of tunnels and
+/// caves and the ground under rock arches. Chunks drop the quads whose centers fall inside a hole,
+/// from their mesh and their collider. Height queries still report the heightmap across a hole.
+/// Cheap to clone into chunk generation tasks.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct TerrainHoles {
+    holes: Arc<Vec<TerrainHole>>,
+    /// Holes added since the loaded chunks last caught up
+    unapplied: Vec<usize>,
+}
+
+impl TerrainHoles {
+    /// Cuts out the area inside `outline`, world XZ points in order around it
+    pub fn add(&mut self, outline: &[Vec2]) {
+        if outline.len() < 3 {
+            return;
+        }
+        let (min, max) = outline.iter().fold((Vec2::MAX, Vec2::MIN), |(min, max), point| {
+            (min.min(*point), max.max(*point))
+        });
+        let hole = TerrainHole { outline: outline.to_vec(), bounds: Rect::from_corners(min, max) };
+        Arc::make_mut(&mut self.holes).push(hole);
+        self.unapplied.push(self.holes.len() - 1);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.holes.is_empty()
+    }
+
+    /// Whether the ground at world XZ `point` is cut away
+    pub fn contains(&self, point: Vec2) -> bool {
+        self.holes.iter().any(|hole| hole.contains(point))
+    }
+
+    /// Whether `hole` reaches into chunk `coord`
+    fn touches_chunk(&self, hole: usize, coord: IVec2, settings: &TerrainSettings) -> bool {
+        let chunk = Rect::from_center_half_size(chunk_origin(coord, settings).xz(), Vec2::splat(CHUNK_SIZE * 0.5));
+        !self.holes[hole].bounds.intersect(chunk).is_empty()
+    }
+}
+
+/// Regenerates loaded and generating chunks under newly added holes.
+/// The old chunk stays until its replacement is uploaded, like for carves.
+pub(super) fn apply_terrain_holes(
+    mut holes: ResMut<TerrainHoles>,
+    mut manager: ResMut<TerrainChunkManager>,
+    erosion: Res<TerrainErosion>,
+    carves: Res<TerrainCarves>,
+    settings: Res<TerrainSettings>,
+    seed: Option<Res<TerrainSeed>>,
+) {
+    if holes.unapplied.is_empty() {
+        return;
+    }
+    let unapplied = std::mem::take(&mut holes.bypass_change_detection().unapplied);
+
+    let seed = seed.map_or(0, |seed| seed.0);
+    // Holes are laid out in level coordinates, the loaded chunks around the world origin
+    let coords: HashSet<IVec2> = manager.chunks.keys().chain(manager.pending.keys()).copied().collect();
+    for coord in coords {
+        if unapplied.iter().any(|hole| holes.touches_chunk(*hole, manager.level_coord(coord), &settings)) {
+            queue_chunk_generation(&mut manager, coord, &settings, seed, &erosion, &carves, &holes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::generate_chunk;
+
+    #[test]
+    fn test_hole_outline_contains() {
+        let mut holes = TerrainHoles::default();
+        // An L shape
+        holes.add(&[
+            Vec2::new(0.0, 0.0),
+            Vec2::new(10.0, 0.0),
+            Vec2::new(10.0, 4.0),
+            Vec2::new(4.0, 4.0),
+            Vec2::new(4.0, 10.0),
+            Vec2::new(0.0, 10.0),
+        ]);
+        assert!(holes.contains(Vec2::new(2.0, 8.0)));
+        assert!(holes.contains(Vec2::new(8.0, 2.0)));
+        assert!(!holes.contains(Vec2::new(8.0, 8.0)));
+        assert!(!holes.contains(Vec2::new(-1.0, 2.0)));
+
+        let settings = TerrainSettings::default();
+        assert!(holes.touches_chunk(0, IVec2::ZERO, &settings));
+        assert!(!holes.touches_chunk(0, IVec2::new(1, 0), &settings));
+    }
+
+    #[test]
+    fn test_chunks_drop_the_quads_inside_a_hole() {
+        let settings = TerrainSettings { resolution: 10, ..default() };
+        let carves = TerrainCarves::default();
+        let mut holes = TerrainHoles::default();
+        // Covers the centers of a 2 by 2 block of 10 meter quads
+        holes.add(&[Vec2::new(-8.0, -8.0), Vec2::new(8.0, -8.0), Vec2::new(8.0, 8.0), Vec2::new(-8.0, 8.0)]);
+
+        let erosion = TerrainErosion::default();
+        let whole = generate_chunk(IVec2::ZERO, &settings, 0, &erosion, &carves, &TerrainHoles::default());
+        let holed = generate_chunk(IVec2::ZERO, &settings, 0, &erosion, &carves, &holes);
+        assert_eq!(whole.mesh.indices().unwrap().len(), 10 * 10 * 6);
+        assert_eq!(holed.mesh.indices().unwrap().len(), (10 * 10 - 4) * 6);
+        // Vertices stay so the edges around the hole line up with the patch
+        assert_eq!(holed.mesh.count_vertices(), whole.mesh.count_vertices());
+    }
+}
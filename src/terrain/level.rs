@@ -0,0 +1,203 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    tasks::{futures_lite::future, AsyncComputeTaskPool, Task},
+    utils::{BoxedFuture, HashSet},
+};
+use serde::{Deserialize, Serialize};
+
+use super::carving::TerrainCarves;
+use super::erosion::{erode, ErosionSettings, TerrainErosion};
+use super::generation::{sample_height, terrain_noise, TerrainSettings};
+use super::holes::TerrainHoles;
+use super::rivers::{River, RiverDesc, TerrainRivers};
+use super::{queue_chunk_generation, TerrainChunkManager, TerrainSeed};
+use crate::assets::{check_schema_version, read_schema_version, SchemaVersionError};
+
+/// Erosion and rivers of a level, loaded from `*.terrain.json`
+#[derive(Asset, TypePath, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LevelTerrain {
+    /// Left out for ground straight from the noise
+    #[serde(default)]
+    pub erosion: Option<ErosionSettings>,
+    #[serde(default)]
+    pub rivers: Vec<RiverDesc>,
+}
+
+/// Errors produced while loading level terrain files
+#[derive(Debug, thiserror::Error)]
+pub enum LevelTerrainError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Schema(#[from] SchemaVersionError),
+}
+
+/// Asset loader for level terrain files
+#[derive(Default)]
+pub struct LevelTerrainLoader;
+
+impl AssetLoader for LevelTerrainLoader {
+    type Asset = LevelTerrain;
+    type Settings = ();
+    type Error = LevelTerrainError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<LevelTerrain, LevelTerrainError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            check_schema_version(read_schema_version(&bytes)?)?;
+            Ok(serde_json::from_slice(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["terrain.json"]
+    }
+}
+
+/// Marks level entities whose terrain has been baked, or is being baked
+#[derive(Component, Debug, Default)]
+pub struct LevelTerrainBaked;
+
+/// A level's eroded ground and the rivers cut into it
+pub struct LevelTerrainBake {
+    pub erosion: TerrainErosion,
+    pub rivers: Vec<River>,
+}
+
+/// Erodes the level's ground and lays its rivers into the eroded ground, pure so it can run on a task pool thread
+pub fn bake_level_terrain(level: &LevelTerrain, settings: &TerrainSettings, seed: u32) -> LevelTerrainBake {
+    let noise = terrain_noise(settings, seed);
+    let natural = |x: f32, z: f32| settings.base_height + sample_height(&noise, settings, x, z);
+    let erosion = level.erosion.map_or_else(TerrainErosion::default, |erosion| erode(&erosion, seed, natural));
+    let rivers = level
+        .rivers
+        .iter()
+        .map(|river| River::carve(river, |x, z| erosion.height(x, z, natural(x, z))))
+        .collect();
+    LevelTerrainBake { erosion, rivers }
+}
+
+/// Level terrain still baking on the task pool
+#[derive(Resource, Default)]
+pub struct LevelTerrainBakes {
+    pending: Vec<Task<LevelTerrainBake>>,
+}
+
+impl LevelTerrainBakes {
+    pub fn is_baking(&self) -> bool {
+        !self.pending.is_empty()
+    }
+}
+
+/// Starts baking loaded level terrain assets, erosion takes a while so it runs off the main thread
+pub(super) fn start_level_terrain_bakes(
+    mut commands: Commands,
+    levels: Query<(Entity, &Handle<LevelTerrain>), Without<LevelTerrainBaked>>,
+    level_terrain: Res<Assets<LevelTerrain>>,
+    settings: Res<TerrainSettings>,
+    seed: Option<Res<TerrainSeed>>,
+    mut bakes: ResMut<LevelTerrainBakes>,
+) {
+    let seed = seed.map_or(0, |seed| seed.0);
+    for (level, handle) in levels.iter() {
+        let Some(terrain) = level_terrain.get(handle) else {
+            continue;
+        };
+        let (terrain, settings) = (terrain.clone(), settings.clone());
+        let task = AsyncComputeTaskPool::get().spawn(async move { bake_level_terrain(&terrain, &settings, seed) });
+        bakes.pending.push(task);
+        commands.entity(level).insert(LevelTerrainBaked);
+    }
+}
+
+/// Swaps in finished bakes: regenerates the chunks under the erosion and carves the rivers,
+/// which repaints their beds and regenerates the chunks under them in turn
+#[allow(clippy::too_many_arguments)]
+pub(super) fn apply_level_terrain_bakes(
+    mut bakes: ResMut<LevelTerrainBakes>,
+    mut erosion: ResMut<TerrainErosion>,
+    mut carves: ResMut<TerrainCarves>,
+    mut rivers: ResMut<TerrainRivers>,
+    mut manager: ResMut<TerrainChunkManager>,
+    holes: Res<TerrainHoles>,
+    settings: Res<TerrainSettings>,
+    seed: Option<Res<TerrainSeed>>,
+) {
+    let mut finished = Vec::new();
+    bakes.pending.retain_mut(|task| match future::block_on(future::poll_once(task)) {
+        Some(bake) => {
+            finished.push(bake);
+            false
+        }
+        None => true,
+    });
+
+    let seed = seed.map_or(0, |seed| seed.0);
+    for bake in finished {
+        let previous = std::mem::replace(&mut *erosion, bake.erosion);
+        for river in &bake.rivers {
+            carves.add(&river.bed, river.terrain_carve());
+        }
+        rivers.rivers.extend(bake.rivers);
+
+        // Both where the old erosion was and where the new one is
+        let coords: HashSet<IVec2> = manager.chunks.keys().chain(manager.pending.keys()).copied().collect();
+        for coord in coords {
+            let level_coord = manager.level_coord(coord);
+            if previous.touches_chunk(level_coord, &settings) || erosion.touches_chunk(level_coord, &settings) {
+                queue_chunk_generation(&mut manager, coord, &settings, seed, &erosion, &carves, &holes);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_level_terrain() {
+        let json = r#"{
+            "erosion": { "center": [0.0, 50.0], "extent": 200.0, "droplets": 10000 },
+            "rivers": [
+                { "name": "Mill Race", "path": [[-80.0, -60.0], [-20.0, 0.0], [40.0, 90.0]], "width": 8.0 }
+            ]
+        }"#;
+        let level: LevelTerrain = serde_json::from_str(json).unwrap();
+        let erosion = level.erosion.unwrap();
+        assert_eq!(erosion.extent, 200.0);
+        assert_eq!(erosion.inertia, ErosionSettings::default().inertia);
+        assert_eq!(level.rivers[0].width, 8.0);
+        assert_eq!(level.rivers[0].depth, 1.5);
+
+        let plain: LevelTerrain = serde_json::from_str("{}").unwrap();
+        assert!(plain.erosion.is_none() && plain.rivers.is_empty());
+    }
+
+    #[test]
+    fn test_rivers_lie_in_the_eroded_ground() {
+        let settings = TerrainSettings::default();
+        let level = LevelTerrain {
+            erosion: Some(ErosionSettings { extent: 40.0, droplets: 500, ..default() }),
+            rivers: vec![serde_json::from_str(r#"{ "name": "Creek", "path": [[-10.0, 0.0], [10.0, 0.0]] }"#).unwrap()],
+        };
+        let bake = bake_level_terrain(&level, &settings, 4);
+        assert!(!bake.erosion.is_empty());
+
+        let noise = terrain_noise(&settings, 4);
+        let river = &bake.rivers[0];
+        for point in &river.bed {
+            let natural = settings.base_height + sample_height(&noise, &settings, point.x, point.z);
+            assert!(point.y <= bake.erosion.height(point.x, point.z, natural) - 1.5 + 1e-4);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use bevy::{
+    pbr::{ExtendedMaterial, MaterialExtension},
+    prelude::*,
+    render::render_resource::{AsBindGroup, ShaderRef, ShaderType},
+};
+
+use super::cloud_shadow::CloudShadowUniform;
+use super::splat::SplatUniform;
+
+/// Terrain material: standard PBR ground splatted with the terrain layers, snow on top, shaded by passing clouds
+pub type TerrainMaterial = ExtendedMaterial<StandardMaterial, TerrainSplatExtension>;
+
+/// Snow layer parameters for the shader
+#[derive(ShaderType, Clone, Copy, Debug, PartialEq)]
+pub struct SnowUniform {
+    pub snow_color: Vec4,
+    /// Ground covered by snow (0.0 - 1.0)
+    pub coverage: f32,
+    /// Slope (1 - normal.y) above which snow starts sliding off
+    pub slope_start: f32,
+    /// Slope above which no snow holds at all
+    pub slope_end: f32,
+    /// Frequency of the drift noise in world space
+    pub noise_scale: f32,
+}
+
+impl Default for SnowUniform {
+    fn default() -> Self {
+        Self {
+            snow_color: Vec4::new(0.92, 0.94, 0.98, 1.0),
+            coverage: 0.0,
+            slope_start: 0.25,
+            slope_end: 0.5,
+            noise_scale: 0.15,
+        }
+    }
+}
+
+/// Material extension blending the terrain layers by splat map and height and slope rules shaped by the biome,
+/// then settling snow over them, flat ground first and steep slopes last, and shading the sunlit ground
+/// under the clouds. The base color tints the blended layers.
+#[derive(Asset, TypePath, AsBindGroup, Clone, Debug, Default)]
+pub struct TerrainSplatExtension {
+    // Binding 100 keeps clear of the standard material's bindings
+    #[uniform(100)]
+    pub snow: SnowUniform,
+    #[uniform(101)]
+    pub splat: SplatUniform,
+    /// Detail texture per layer, in layer order
+    #[texture(102, dimension = "2d_array")]
+    #[sampler(103)]
+    pub layer_textures: Option<Handle<Image>>,
+    /// Painted weights of layers 0-3
+    #[texture(104)]
+    #[sampler(105)]
+    pub splat_map_0: Option<Handle<Image>>,
+    /// Painted weights of layers 4-7
+    #[texture(106)]
+    #[sampler(107)]
+    pub splat_map_1: Option<Handle<Image>>,
+    #[uniform(108)]
+    pub cloud_shadow: CloudShadowUniform,
+    /// Cloud density the shadows are projected from
+    #[texture(109)]
+    #[sampler(110)]
+    pub cloud_shadow_texture: Option<Handle<Image>>,
+    /// Biome layer affinity of layers 0-3 around the camera
+    #[texture(111)]
+    #[sampler(112)]
+    pub biome_map_0: Option<Handle<Image>>,
+    /// Biome layer affinity of layers 4-7
+    #[texture(113)]
+    #[sampler(114)]
+    pub biome_map_1: Option<Handle<Image>>,
+}
+
+impl MaterialExtension for TerrainSplatExtension {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/terrain.wgsl".into()
+    }
+}
+
+/// Builds a terrain material from a standard PBR material with the default layers, nothing painted and no snow yet
+pub fn terrain_material(base: StandardMaterial) -> TerrainMaterial {
+    ExtendedMaterial {
+        base,
+        extension: TerrainSplatExtension::default(),
+    }
+}
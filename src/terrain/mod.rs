@@ -1,93 +1,425 @@
 use bevy::prelude::*;
-use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::tasks::{futures_lite::future, AsyncComputeTaskPool, Task};
+use bevy::utils::{HashMap, HashSet};
 use bevy_rapier3d::prelude::*;
-use noise::{NoiseFn, Perlin};
+
+mod biome;
+mod carving;
+mod cloud_shadow;
+mod detail;
+mod drivability;
+mod erosion;
+mod generation;
+mod holes;
+mod level;
+mod material;
+mod patches;
+mod query;
+mod rivers;
+mod splat;
+
+pub use biome::{Biome, BiomeField, BiomeMap, BiomeProfile, BiomeSample, BiomeSettings, Climate};
+pub use carving::{spline_points, TerrainCarve, TerrainCarves};
+pub use cloud_shadow::{cloud_density, generate_cloud_shadow_texture, CloudShadowUniform};
+pub use detail::{generate_rock_depth_map, generate_rock_normal_map, rock_height, TerrainDetailSettings};
+pub use drivability::{
+    cell_center, world_to_cell, ChunkDrivability, Drivability, DrivabilityBlocker, DrivabilityMap, DrivabilitySettings,
+    DRIVABILITY_CELL_SIZE,
+};
+pub use generation::{
+    chunk_origin, generate_chunk, ground_height, sample_height, terrain_noise, world_pos_to_chunk, ChunkMeshData,
+    TerrainSettings, CHUNK_SIZE, DETAIL_TILES_PER_CHUNK,
+};
+pub use erosion::{erode, ErosionSettings, TerrainErosion};
+pub use holes::TerrainHoles;
+pub use level::{
+    bake_level_terrain, LevelTerrain, LevelTerrainBake, LevelTerrainBaked, LevelTerrainBakes, LevelTerrainError,
+    LevelTerrainLoader,
+};
+pub use material::{terrain_material, SnowUniform, TerrainMaterial, TerrainSplatExtension};
+pub use patches::{
+    LevelTerrainPatches, LevelTerrainPatchesError, LevelTerrainPatchesLoader, LevelTerrainPatchesSpawned, TerrainPatch,
+    TerrainPatchDesc,
+};
+pub use query::{ChunkHeights, TerrainQuery, TerrainSample};
+pub use rivers::{River, RiverDesc, RiverWater, TerrainRivers};
+pub use splat::{
+    generate_layer_textures, layer_detail, PaintTerrainEvent, SplatUniform, TerrainLayer, TerrainLayerUniform,
+    TerrainSplatMap, MAX_TERRAIN_LAYERS,
+};
+
+use crate::game::{
+    render_available, GameSettings, GameState, OriginShiftEvent, ProfileGroup, ShiftOrigin, StateScoped,
+};
 
 pub struct TerrainPlugin;
 
 impl Plugin for TerrainPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup_terrain);
+        app.init_resource::<TerrainSettings>()
+            .init_resource::<BiomeSettings>()
+            .init_resource::<BiomeField>()
+            .init_resource::<TerrainChunkManager>()
+            .init_resource::<TerrainDetailSettings>()
+            .init_resource::<TerrainSplatMap>()
+            .init_resource::<TerrainCarves>()
+            .init_resource::<TerrainHoles>()
+            .init_resource::<TerrainErosion>()
+            .init_resource::<TerrainRivers>()
+            .init_resource::<LevelTerrainBakes>()
+            .init_resource::<DrivabilitySettings>()
+            .init_resource::<DrivabilityMap>()
+            .init_resource::<TerrainQuery>()
+            .init_asset::<LevelTerrainPatches>()
+            .init_asset_loader::<LevelTerrainPatchesLoader>()
+            .init_asset::<LevelTerrain>()
+            .init_asset_loader::<LevelTerrainLoader>()
+            .add_event::<PaintTerrainEvent>()
+            .add_event::<OriginShiftEvent>()
+            .add_systems(Startup, setup_terrain)
+            .add_systems(Update, (
+                // Without scenes a patch has no mesh to build its collider from, the heightmap stays whole
+                patches::spawn_level_patches.run_if(resource_exists::<Assets<Scene>>()),
+                level::start_level_terrain_bakes,
+                level::apply_level_terrain_bakes,
+                carving::apply_terrain_carves,
+                holes::apply_terrain_holes,
+                forget_despawned_chunks,
+                queue_terrain_chunks,
+                upload_terrain_chunks,
+                detail::apply_texture_quality.run_if(resource_exists::<GameSettings>()),
+                detail::update_detail_material.run_if(resource_changed::<TerrainDetailSettings>()),
+                detail::assign_detail_materials,
+            ).chain().in_set(ProfileGroup::TerrainStreaming))
+            .add_systems(PostUpdate, rebase_terrain.after(ShiftOrigin))
+            .add_systems(Update, (
+                splat::paint_terrain,
+                drivability::update_drivability_blockers,
+                biome::update_biome_field,
+            ));
+
+        // Headless, chunks still get their colliders and paint still lands in the splat map,
+        // but the material never reaches a GPU
+        if render_available(app) {
+            app.add_plugins(MaterialPlugin::<TerrainMaterial>::default())
+                .init_resource::<BiomeMap>()
+                .add_systems(Startup, (
+                    splat::setup_layer_textures.after(setup_terrain),
+                    cloud_shadow::setup_cloud_shadow_texture.after(setup_terrain),
+                ))
+                .add_systems(Update, (
+                    splat::upload_splat_maps.after(splat::paint_terrain),
+                    biome::update_biome_map.after(biome::update_biome_field),
+                ));
+        } else {
+            app.init_asset::<TerrainMaterial>();
+        }
     }
 }
 
 #[derive(Component)]
-pub struct TerrainChunk;
+pub struct TerrainChunk {
+    pub coord: IVec2,
+}
+
+/// Keeps terrain loaded around an entity, for when no camera is looking, like a headless simulation
+#[derive(Component, Debug, Default)]
+pub struct TerrainFocus;
+
+/// Seed for the terrain noise, fixed so every machine builds the same ground
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TerrainSeed(pub u32);
+
+/// Loaded chunks and the ones still being generated, on the grid around the world origin
+#[derive(Resource, Default)]
+pub struct TerrainChunkManager {
+    pub chunks: HashMap<IVec2, Entity>,
+    /// Generated in level coordinates, they're moved onto the grid when uploaded
+    pending: HashMap<IVec2, Task<ChunkMeshData>>,
+    /// Level chunk at the origin of the grid
+    origin: IVec2,
+    material: Handle<TerrainMaterial>,
+    /// Same ground with parallax rock relief, for chunks near a camera
+    detail_material: Handle<TerrainMaterial>,
+}
+
+impl TerrainChunkManager {
+    pub fn is_pending(&self, coord: IVec2) -> bool {
+        self.pending.contains_key(&coord)
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Plain and detail materials shared by the chunks, seasons and snow change both in place
+    pub fn materials(&self) -> [&Handle<TerrainMaterial>; 2] {
+        [&self.material, &self.detail_material]
+    }
+
+    /// Level chunk at grid coordinate zero, level data like carves and holes is placed in level coordinates
+    pub fn origin(&self) -> IVec2 {
+        self.origin
+    }
+
+    /// Level chunk of grid coordinate `coord`
+    pub fn level_coord(&self, coord: IVec2) -> IVec2 {
+        coord + self.origin
+    }
+
+    /// Follows the world origin moving by `chunks`
+    fn rebase(&mut self, chunks: IVec2) {
+        self.origin += chunks;
+        self.chunks = self.chunks.drain().map(|(coord, entity)| (coord - chunks, entity)).collect();
+        self.pending = self.pending.drain().map(|(coord, task)| (coord - chunks, task)).collect();
+    }
+}
+
+/// Chunks around `center` in load order, nearest first
+pub fn chunks_in_range(center: IVec2, view_distance: i32) -> Vec<IVec2> {
+    let mut coords: Vec<_> = (-view_distance..=view_distance)
+        .flat_map(|z| (-view_distance..=view_distance).map(move |x| center + IVec2::new(x, z)))
+        .collect();
+    coords.sort_by_key(|coord| (*coord - center).length_squared());
+    coords
+}
+
+fn spawn_chunk(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    manager: &mut TerrainChunkManager,
+    drivability: &mut DrivabilityMap,
+    terrain_query: &mut TerrainQuery,
+    settings: &TerrainSettings,
+    data: ChunkMeshData,
+) {
+    drivability.insert_chunk(data.drivability);
+    terrain_query.insert_chunk(data.heights);
+    let entity = commands
+        .spawn((
+            MaterialMeshBundle {
+                mesh: meshes.add(data.mesh),
+                material: manager.material.clone(),
+                transform: Transform::from_translation(chunk_origin(data.coord, settings)),
+                ..default()
+            },
+            TerrainChunk { coord: data.coord },
+            RigidBody::Fixed,
+            Friction::coefficient(0.3),
+            StateScoped(GameState::InGame),
+        ))
+        .id();
+    if let Some(collider) = data.collider {
+        commands.entity(entity).insert(collider);
+    }
+    // A chunk regenerated for a carve replaces the old one only now, so the ground never goes missing
+    if let Some(old) = manager.chunks.insert(data.coord, entity) {
+        commands.entity(old).despawn_recursive();
+    }
+}
+
+/// Starts generating chunk `coord` on the task pool, replacing any task already building it
+fn queue_chunk_generation(
+    manager: &mut TerrainChunkManager,
+    coord: IVec2,
+    settings: &TerrainSettings,
+    seed: u32,
+    erosion: &TerrainErosion,
+    carves: &TerrainCarves,
+    holes: &TerrainHoles,
+) {
+    let (settings, erosion, carves, holes) = (settings.clone(), erosion.clone(), carves.clone(), holes.clone());
+    let level_coord = manager.level_coord(coord);
+    let task_pool = AsyncComputeTaskPool::get();
+    let task = task_pool.spawn(async move { generate_chunk(level_coord, &settings, seed, &erosion, &carves, &holes) });
+    manager.pending.insert(coord, task);
+}
 
+/// Creates the terrain material and builds the chunk under the origin right away,
+/// vehicles spawn there and would fall through while it generates
+#[allow(clippy::too_many_arguments)]
 fn setup_terrain(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut materials: ResMut<Assets<TerrainMaterial>>,
+    mut manager: ResMut<TerrainChunkManager>,
+    mut drivability: ResMut<DrivabilityMap>,
+    mut terrain_query: ResMut<TerrainQuery>,
+    settings: Res<TerrainSettings>,
+    seed: Option<Res<TerrainSeed>>,
+    erosion: Res<TerrainErosion>,
+    carves: Res<TerrainCarves>,
+    holes: Res<TerrainHoles>,
 ) {
-    let chunk_size = 100.0;
-    let resolution = 100;
-    let height_scale = 5.0;
-    let noise = Perlin::new(0);
-
-    let mut vertices = Vec::new();
-    let mut indices = Vec::new();
-    let mut normals = Vec::new();
-    let mut uvs = Vec::new();
-
-    // Generate vertices
-    for z in 0..=resolution {
-        for x in 0..=resolution {
-            let px = (x as f32 / resolution as f32 - 0.5) * chunk_size;
-            let pz = (z as f32 / resolution as f32 - 0.5) * chunk_size;
-            
-            let noise_x = px * 0.02;
-            let noise_z = pz * 0.02;
-            let height = noise.get([noise_x as f64, noise_z as f64]) as f32 * height_scale;
-            
-            vertices.push([px, height, pz]);
-            normals.push([0.0, 1.0, 0.0]);
-            uvs.push([x as f32 / resolution as f32, z as f32 / resolution as f32]);
+    // Layer colors come from the splat rules, the base color only tints them
+    let ground = StandardMaterial {
+        base_color: Color::WHITE,
+        perceptual_roughness: 0.9,
+        ..default()
+    };
+    manager.material = materials.add(terrain_material(ground.clone()));
+    // Detail maps are added once the detail settings are known
+    manager.detail_material = materials.add(terrain_material(ground));
+
+    let seed = seed.map_or(0, |seed| seed.0);
+    let mut data = generate_chunk(manager.origin, &settings, seed, &erosion, &carves, &holes);
+    data.rebase(manager.origin);
+    spawn_chunk(&mut commands, &mut meshes, &mut manager, &mut drivability, &mut terrain_query, &settings, data);
+}
+
+/// Starts generation tasks for chunks coming into range and unloads the ones left behind
+#[allow(clippy::too_many_arguments)]
+fn queue_terrain_chunks(
+    mut commands: Commands,
+    settings: Res<TerrainSettings>,
+    seed: Option<Res<TerrainSeed>>,
+    erosion: Res<TerrainErosion>,
+    carves: Res<TerrainCarves>,
+    holes: Res<TerrainHoles>,
+    mut manager: ResMut<TerrainChunkManager>,
+    mut drivability: ResMut<DrivabilityMap>,
+    mut terrain_query: ResMut<TerrainQuery>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    focuses: Query<&GlobalTransform, With<TerrainFocus>>,
+) {
+    let centers: Vec<_> = cameras
+        .iter()
+        .filter(|(camera, _)| camera.is_active)
+        .map(|(_, transform)| transform)
+        .chain(focuses.iter())
+        .map(|transform| world_pos_to_chunk(transform.translation()))
+        .collect();
+    if centers.is_empty() {
+        return;
+    }
+
+    // One chunk of slack before unloading so driving along a border doesn't thrash
+    let keep_distance = settings.view_distance + 1;
+    let in_keep_range = |coord: IVec2| {
+        centers.iter().any(|center| {
+            let offset = (coord - *center).abs();
+            offset.x.max(offset.y) <= keep_distance
+        })
+    };
+
+    let unloaded: Vec<_> = manager.chunks.keys().copied().filter(|coord| !in_keep_range(*coord)).collect();
+    for coord in unloaded {
+        if let Some(entity) = manager.chunks.remove(&coord) {
+            commands.entity(entity).despawn_recursive();
         }
+        drivability.remove_chunk(coord);
+        terrain_query.remove_chunk(coord);
     }
+    // Dropping a task cancels it
+    manager.pending.retain(|coord, _| in_keep_range(*coord));
 
-    // Generate indices
-    for z in 0..resolution {
-        for x in 0..resolution {
-            let top_left = z * (resolution + 1) + x;
-            let top_right = top_left + 1;
-            let bottom_left = (z + 1) * (resolution + 1) + x;
-            let bottom_right = bottom_left + 1;
-
-            indices.extend_from_slice(&[
-                top_left as u32,
-                bottom_left as u32,
-                top_right as u32,
-                top_right as u32,
-                bottom_left as u32,
-                bottom_right as u32,
-            ]);
+    let seed = seed.map_or(0, |seed| seed.0);
+    let mut queued = HashSet::new();
+    for center in &centers {
+        for coord in chunks_in_range(*center, settings.view_distance) {
+            if manager.pending.len() >= settings.max_pending_tasks {
+                return;
+            }
+            if manager.chunks.contains_key(&coord) || manager.pending.contains_key(&coord) || !queued.insert(coord) {
+                continue;
+            }
+            queue_chunk_generation(&mut manager, coord, &settings, seed, &erosion, &carves, &holes);
         }
     }
+}
 
-    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
-    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vertices.clone());
-    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
-    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
-    mesh.set_indices(Some(Indices::U32(indices.clone())));
+/// Moves the chunk grid, painted and biome maps along with the world origin. Chunks keep being generated
+/// in level coordinates, so the ground stays where the level has it.
+#[allow(clippy::too_many_arguments)]
+fn rebase_terrain(
+    mut shifts: EventReader<OriginShiftEvent>,
+    mut manager: ResMut<TerrainChunkManager>,
+    mut drivability: ResMut<DrivabilityMap>,
+    mut terrain_query: ResMut<TerrainQuery>,
+    mut splat_map: ResMut<TerrainSplatMap>,
+    mut biome_field: ResMut<BiomeField>,
+    mut biome_map: Option<ResMut<BiomeMap>>,
+    mut materials: ResMut<Assets<TerrainMaterial>>,
+    mut chunks: Query<&mut TerrainChunk>,
+) {
+    for shift in shifts.read() {
+        let offset = shift.translation().xz();
+        manager.rebase(shift.chunks);
+        drivability.rebase(shift.chunks);
+        terrain_query.rebase(shift.chunks);
+        for mut chunk in chunks.iter_mut() {
+            chunk.coord -= shift.chunks;
+        }
 
-    // Create the terrain entity
-    commands.spawn((
-        PbrBundle {
-            mesh: meshes.add(mesh),
-            material: materials.add(StandardMaterial {
-                base_color: Color::rgb(0.3, 0.5, 0.3),
-                perceptual_roughness: 0.9,
-                ..default()
-            }),
-            transform: Transform::from_xyz(0.0, -2.0, 0.0),
-            ..default()
-        },
-        TerrainChunk,
-        RigidBody::Fixed,
-        Collider::trimesh(
-            vertices.into_iter().map(|v| Vec3::from(v)).collect(),
-            indices.chunks(3).map(|i| [i[0], i[1], i[2]]).collect(),
-        ),
-        Friction::coefficient(0.3),
-    ));
-} 
\ No newline at end of file
+        splat_map.origin -= offset;
+        biome_field.rebase(offset);
+        if let Some(biome_map) = biome_map.as_mut() {
+            biome_map.rebase(shift.chunks);
+        }
+        for handle in manager.materials() {
+            if let Some(material) = materials.get_mut(handle) {
+                material.extension.splat.splat_origin -= offset;
+                material.extension.splat.biome_origin -= offset;
+            }
+        }
+    }
+}
+
+/// Drops chunks despawned from outside, like on leaving the game, so streaming builds them again
+fn forget_despawned_chunks(
+    mut removed: RemovedComponents<TerrainChunk>,
+    mut manager: ResMut<TerrainChunkManager>,
+    mut drivability: ResMut<DrivabilityMap>,
+    mut terrain_query: ResMut<TerrainQuery>,
+) {
+    for entity in removed.read() {
+        // Unloaded and replaced chunks are already out of the manager, or point at their replacement
+        let Some(coord) = manager.chunks.iter().find(|(_, chunk)| **chunk == entity).map(|(coord, _)| *coord) else {
+            continue;
+        };
+        manager.chunks.remove(&coord);
+        drivability.remove_chunk(coord);
+        terrain_query.remove_chunk(coord);
+    }
+}
+
+/// Spawns finished chunks, a few per frame so a burst of completions doesn't hitch
+fn upload_terrain_chunks(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    settings: Res<TerrainSettings>,
+    mut manager: ResMut<TerrainChunkManager>,
+    mut drivability: ResMut<DrivabilityMap>,
+    mut terrain_query: ResMut<TerrainQuery>,
+) {
+    let mut finished = Vec::new();
+    for task in manager.pending.values_mut() {
+        if finished.len() >= settings.uploads_per_frame {
+            break;
+        }
+        if let Some(data) = future::block_on(future::poll_once(task)) {
+            finished.push(data);
+        }
+    }
+
+    for mut data in finished {
+        // Onto the grid as it is now, the origin may have moved while the chunk generated
+        data.rebase(manager.origin);
+        manager.pending.remove(&data.coord);
+        spawn_chunk(&mut commands, &mut meshes, &mut manager, &mut drivability, &mut terrain_query, &settings, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_in_range_nearest_first() {
+        let coords = chunks_in_range(IVec2::new(3, 3), 2);
+        assert_eq!(coords.len(), 25);
+        assert_eq!(coords[0], IVec2::new(3, 3));
+        // The four direct neighbours come before any diagonal
+        assert!(coords[1..5].iter().all(|coord| (*coord - IVec2::new(3, 3)).length_squared() == 1));
+    }
+}
@@ -0,0 +1,173 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::holes::TerrainHoles;
+use crate::assets::{check_schema_version, read_schema_version, SchemaVersionError};
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+/// A tunnel, cave or rock arch modelled as a mesh, as written in a level file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TerrainPatchDesc {
+    pub name: String,
+    /// glTF model of the patch, its first scene is spawned and its meshes become the collider
+    pub model: String,
+    pub position: [f32; 3],
+    /// Rotation around the vertical axis in degrees
+    #[serde(default)]
+    pub rotation: f32,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    /// Outline of the terrain the patch replaces in its local XZ, in order around it. Left empty
+    /// for patches that sit on top of the ground, like an arch.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hole: Vec<[f32; 2]>,
+}
+
+impl TerrainPatchDesc {
+    pub fn transform(&self) -> Transform {
+        Transform::from_translation(Vec3::from(self.position))
+            .with_rotation(Quat::from_rotation_y(self.rotation.to_radians()))
+            .with_scale(Vec3::splat(self.scale))
+    }
+
+    /// Hole outline in world XZ
+    pub fn world_hole(&self) -> Vec<Vec2> {
+        let transform = self.transform();
+        self.hole
+            .iter()
+            .map(|point| transform.transform_point(Vec3::new(point[0], 0.0, point[1])).xz())
+            .collect()
+    }
+}
+
+/// Mesh patches of a level, loaded from `*.patches.json`
+#[derive(Asset, TypePath, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LevelTerrainPatches {
+    pub patches: Vec<TerrainPatchDesc>,
+}
+
+/// Errors produced while loading level patch files
+#[derive(Debug, thiserror::Error)]
+pub enum LevelTerrainPatchesError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Schema(#[from] SchemaVersionError),
+}
+
+/// Asset loader for level patch files
+#[derive(Default)]
+pub struct LevelTerrainPatchesLoader;
+
+impl AssetLoader for LevelTerrainPatchesLoader {
+    type Asset = LevelTerrainPatches;
+    type Settings = ();
+    type Error = LevelTerrainPatchesError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<LevelTerrainPatches, LevelTerrainPatchesError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            check_schema_version(read_schema_version(&bytes)?)?;
+            Ok(serde_json::from_slice(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["patches.json"]
+    }
+}
+
+/// A spawned mesh patch
+#[derive(Component, Debug, Clone)]
+pub struct TerrainPatch(pub TerrainPatchDesc);
+
+/// Marks level entities whose patches have been spawned
+#[derive(Component, Debug, Default)]
+pub struct LevelTerrainPatchesSpawned;
+
+/// Punches the holes of loaded level patch assets into the terrain and spawns the patches as children of the level
+/// entity, with fixed trimesh colliders built from their meshes once the model has loaded
+pub(super) fn spawn_level_patches(
+    mut commands: Commands,
+    levels: Query<(Entity, &Handle<LevelTerrainPatches>), Without<LevelTerrainPatchesSpawned>>,
+    level_patches: Res<Assets<LevelTerrainPatches>>,
+    asset_server: Res<AssetServer>,
+    mut holes: ResMut<TerrainHoles>,
+) {
+    for (level, handle) in levels.iter() {
+        let Some(patches) = level_patches.get(handle) else {
+            continue;
+        };
+
+        let children: Vec<Entity> = patches
+            .patches
+            .iter()
+            .map(|desc| {
+                holes.add(&desc.world_hole());
+                commands
+                    .spawn((
+                        SceneBundle {
+                            scene: asset_server.load(format!("{}#Scene0", desc.model)),
+                            transform: desc.transform(),
+                            ..default()
+                        },
+                        AsyncSceneCollider {
+                            shape: Some(ComputedColliderShape::TriMesh),
+                            named_shapes: default(),
+                        },
+                        RigidBody::Fixed,
+                        Friction::coefficient(0.3),
+                        TerrainPatch(desc.clone()),
+                        Name::new(desc.name.clone()),
+                    ))
+                    .id()
+            })
+            .collect();
+        commands.entity(level).push_children(&children).insert(LevelTerrainPatchesSpawned);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_level_patches() {
+        let json = r#"{
+            "patches": [
+                {
+                    "name": "Ridge Tunnel",
+                    "model": "models/tunnel.glb",
+                    "position": [40.0, 2.0, -10.0],
+                    "rotation": 90.0,
+                    "hole": [[-3.0, -2.0], [3.0, -2.0], [3.0, 2.0], [-3.0, 2.0]]
+                },
+                { "name": "Arch", "model": "models/arch.glb", "position": [0.0, 0.0, 30.0], "scale": 2.0 }
+            ]
+        }"#;
+        let level: LevelTerrainPatches = serde_json::from_str(json).unwrap();
+        assert_eq!(level.patches.len(), 2);
+        assert!(level.patches[1].hole.is_empty());
+
+        // Turned a quarter, the long side of the hole runs along Z
+        let hole = level.patches[0].world_hole();
+        assert!(hole[0].abs_diff_eq(Vec2::new(38.0, -7.0), 1e-4));
+        assert!(hole[2].abs_diff_eq(Vec2::new(42.0, -13.0), 1e-4));
+    }
+}
@@ -0,0 +1,170 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use super::generation::{world_pos_to_chunk, CHUNK_SIZE};
+
+/// Height and normal of the ground at a point
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TerrainSample {
+    pub height: f32,
+    pub normal: Vec3,
+}
+
+/// World heights at the vertices of one chunk's mesh
+#[derive(Debug, Clone)]
+pub struct ChunkHeights {
+    coord: IVec2,
+    /// Quads along each side
+    resolution: u32,
+    /// Row by row from -Z, `resolution + 1` on a side
+    heights: Vec<f32>,
+}
+
+impl ChunkHeights {
+    /// Takes the vertex heights of chunk `coord`, row by row from its -X, -Z corner
+    pub fn new(coord: IVec2, resolution: u32, heights: Vec<f32>) -> Self {
+        let side = resolution as usize + 1;
+        assert_eq!(heights.len(), side * side, "chunk heights don't match the resolution");
+        Self { coord, resolution, heights }
+    }
+
+    /// Samples `height` (world X, Z to world height) at every vertex of chunk `coord`
+    pub fn from_fn(coord: IVec2, resolution: u32, height: impl Fn(f32, f32) -> f32) -> Self {
+        let resolution = resolution.max(1);
+        let step = CHUNK_SIZE / resolution as f32;
+        let corner = coord.as_vec2() * CHUNK_SIZE - Vec2::splat(CHUNK_SIZE * 0.5);
+        let heights = (0..=resolution)
+            .flat_map(|z| (0..=resolution).map(move |x| corner + Vec2::new(x as f32, z as f32) * step))
+            .map(|point| height(point.x, point.y))
+            .collect();
+        Self { coord, resolution, heights }
+    }
+
+    pub fn coord(&self) -> IVec2 {
+        self.coord
+    }
+
+    /// Moves the chunk onto the grid of a world origin moved by `chunks`, heights are relative to the chunk
+    pub fn rebase(&mut self, chunks: IVec2) {
+        self.coord -= chunks;
+    }
+
+    fn vertex(&self, x: u32, z: u32) -> f32 {
+        let x = x.min(self.resolution);
+        let z = z.min(self.resolution);
+        self.heights[(z * (self.resolution + 1) + x) as usize]
+    }
+
+    /// Bilinear height and the normal of that surface at world `x`, `z` inside the chunk
+    fn sample(&self, x: f32, z: f32) -> TerrainSample {
+        let step = CHUNK_SIZE / self.resolution as f32;
+        let corner = self.coord.as_vec2() * CHUNK_SIZE - Vec2::splat(CHUNK_SIZE * 0.5);
+        let grid = ((Vec2::new(x, z) - corner) / step).clamp(Vec2::ZERO, Vec2::splat(self.resolution as f32));
+        // A point on the far edge samples the last quad, not one past it
+        let base = grid.floor().min(Vec2::splat(self.resolution as f32 - 1.0));
+        let t = grid - base;
+        let (x, z) = (base.x as u32, base.y as u32);
+        let (h00, h10) = (self.vertex(x, z), self.vertex(x + 1, z));
+        let (h01, h11) = (self.vertex(x, z + 1), self.vertex(x + 1, z + 1));
+
+        let top = h00 + (h10 - h00) * t.x;
+        let bottom = h01 + (h11 - h01) * t.x;
+        let slope_x = ((h10 - h00) * (1.0 - t.y) + (h11 - h01) * t.y) / step;
+        let slope_z = (bottom - top) / step;
+        TerrainSample {
+            height: top + (bottom - top) * t.y,
+            normal: Vec3::new(-slope_x, 1.0, -slope_z).normalize(),
+        }
+    }
+}
+
+/// Height and normal of the loaded terrain anywhere on it, sampled from the chunks' own vertices
+/// so it's cheap enough to call per wheel or per animal every frame, no physics raycast needed.
+/// Ground under chunks that aren't loaded is unknown.
+#[derive(Resource, Debug, Default)]
+pub struct TerrainQuery {
+    chunks: HashMap<IVec2, ChunkHeights>,
+}
+
+impl TerrainQuery {
+    pub fn insert_chunk(&mut self, chunk: ChunkHeights) {
+        self.chunks.insert(chunk.coord, chunk);
+    }
+
+    pub fn remove_chunk(&mut self, coord: IVec2) {
+        self.chunks.remove(&coord);
+    }
+
+    pub fn is_loaded(&self, coord: IVec2) -> bool {
+        self.chunks.contains_key(&coord)
+    }
+
+    /// Follows the world origin moving by `chunks`
+    pub fn rebase(&mut self, chunks: IVec2) {
+        self.chunks = std::mem::take(&mut self.chunks)
+            .into_values()
+            .map(|mut chunk| {
+                chunk.rebase(chunks);
+                (chunk.coord, chunk)
+            })
+            .collect();
+    }
+
+    /// Bytes held by the heights of the loaded chunks
+    pub fn memory_bytes(&self) -> usize {
+        self.chunks.values().map(|chunk| chunk.heights.len() * std::mem::size_of::<f32>()).sum()
+    }
+
+    /// Ground height and normal at world `x`, `z`, `None` where no chunk is loaded
+    pub fn sample(&self, x: f32, z: f32) -> Option<TerrainSample> {
+        let chunk = self.chunks.get(&world_pos_to_chunk(Vec3::new(x, 0.0, z)))?;
+        Some(chunk.sample(x, z))
+    }
+
+    /// Ground height at world `x`, `z`
+    pub fn height(&self, x: f32, z: f32) -> Option<f32> {
+        self.sample(x, z).map(|sample| sample.height)
+    }
+
+    /// Upward ground normal at world `x`, `z`
+    pub fn normal(&self, x: f32, z: f32) -> Option<Vec3> {
+        self.sample(x, z).map(|sample| sample.normal)
+    }
+
+    /// `position` moved onto the ground straight below or above it
+    pub fn ground_point(&self, position: Vec3) -> Option<Vec3> {
+        let height = self.height(position.x, position.z)?;
+        Some(Vec3::new(position.x, height, position.z))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampling_interpolates_a_slope() {
+        let mut query = TerrainQuery::default();
+        // Rises a meter every two along X
+        query.insert_chunk(ChunkHeights::from_fn(IVec2::ZERO, 10, |x, _| x * 0.5));
+
+        let sample = query.sample(13.0, -7.0).unwrap();
+        assert!((sample.height - 6.5).abs() < 1e-4);
+        let expected = Vec3::new(-0.5, 1.0, 0.0).normalize();
+        assert!(sample.normal.abs_diff_eq(expected, 1e-5));
+        assert!((query.height(49.0, 49.0).unwrap() - 24.5).abs() < 1e-4);
+
+        assert_eq!(query.height(60.0, 0.0), None);
+        query.remove_chunk(IVec2::ZERO);
+        assert_eq!(query.height(13.0, -7.0), None);
+    }
+
+    #[test]
+    fn test_bilinear_between_vertices() {
+        let heights = vec![0.0, 1.0, 2.0, 3.0];
+        let mut query = TerrainQuery::default();
+        query.insert_chunk(ChunkHeights::new(IVec2::ZERO, 1, heights));
+        assert!((query.height(0.0, 0.0).unwrap() - 1.5).abs() < 1e-5);
+        assert!((query.height(-25.0, -50.0).unwrap() - 0.25).abs() < 1e-5);
+    }
+}
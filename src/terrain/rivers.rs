@@ -0,0 +1,191 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::carving::{spline_points, TerrainCarve};
+use super::splat::TerrainLayer;
+
+fn default_width() -> f32 {
+    6.0
+}
+
+fn default_depth() -> f32 {
+    1.5
+}
+
+fn default_bank() -> f32 {
+    6.0
+}
+
+fn default_water_depth() -> f32 {
+    0.8
+}
+
+fn default_flow_speed() -> f32 {
+    1.0
+}
+
+fn default_bed() -> TerrainLayer {
+    TerrainLayer::Sand
+}
+
+/// A river as written in a level file, flowing from the first point of its path to the last
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RiverDesc {
+    pub name: String,
+    /// World XZ points the river's spline runs through
+    pub path: Vec<[f32; 2]>,
+    /// Width of the river bed in meters
+    #[serde(default = "default_width")]
+    pub width: f32,
+    /// Meters the bed is cut below the ground around it
+    #[serde(default = "default_depth")]
+    pub depth: f32,
+    /// Meters either side over which the banks slope up to the natural ground
+    #[serde(default = "default_bank")]
+    pub bank: f32,
+    /// Depth of the water over the bed
+    #[serde(default = "default_water_depth")]
+    pub water_depth: f32,
+    /// Speed of the current in meters per second
+    #[serde(default = "default_flow_speed")]
+    pub flow_speed: f32,
+    /// Layer painted along the bed
+    #[serde(default = "default_bed")]
+    pub bed: TerrainLayer,
+}
+
+/// Length of the water volumes a river is filled with, short enough that the bed's fall
+/// along one stays under the water
+const WATER_SEGMENT: f32 = 8.0;
+
+/// A river carved into the terrain, its bed falling all the way from source to mouth
+#[derive(Debug, Clone, PartialEq)]
+pub struct River {
+    pub name: String,
+    /// World position of the bed along the river, about a meter apart
+    pub bed: Vec<Vec3>,
+    pub width: f32,
+    pub bank: f32,
+    pub water_depth: f32,
+    pub flow_speed: f32,
+    pub surface: TerrainLayer,
+}
+
+/// One stretch of a river's water, a box the water plugin fills
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RiverWater {
+    /// World position of the surface centre
+    pub surface: Vec3,
+    /// Surface size across and along the river
+    pub size: Vec2,
+    /// Rotation around the vertical axis in degrees, local Z runs downstream
+    pub rotation: f32,
+    /// Depth below the surface, down to the deepest point of the bed under it
+    pub depth: f32,
+    /// Surface flow in meters per second, in world XZ
+    pub flow: Vec2,
+}
+
+impl River {
+    /// Lays the river's bed `depth` under the ground `height` gives for world `x`, `z`,
+    /// never rising on its way down so the water always has somewhere to go
+    pub fn carve(desc: &RiverDesc, height: impl Fn(f32, f32) -> f32) -> Self {
+        let path: Vec<Vec3> = desc.path.iter().map(|point| Vec3::new(point[0], 0.0, point[1])).collect();
+        let mut lowest = f32::MAX;
+        let bed = spline_points(&path, 1.0)
+            .into_iter()
+            .map(|point| {
+                lowest = lowest.min(height(point.x, point.z) - desc.depth);
+                Vec3::new(point.x, lowest, point.z)
+            })
+            .collect();
+        Self {
+            name: desc.name.clone(),
+            bed,
+            width: desc.width,
+            bank: desc.bank,
+            water_depth: desc.water_depth,
+            flow_speed: desc.flow_speed,
+            surface: desc.bed,
+        }
+    }
+
+    /// How the river cuts into the terrain along its bed
+    pub fn terrain_carve(&self) -> TerrainCarve {
+        TerrainCarve {
+            width: self.width,
+            falloff: self.bank,
+            surface: self.surface,
+        }
+    }
+
+    /// Water volumes along the river, each over a short straight stretch of the bed
+    pub fn water(&self) -> Vec<RiverWater> {
+        let mut stretches = Vec::new();
+        let mut start = 0;
+        while start + 1 < self.bed.len() {
+            let mut end = start + 1;
+            while end + 1 < self.bed.len() && self.bed[start].xz().distance(self.bed[end].xz()) < WATER_SEGMENT {
+                end += 1;
+            }
+            let (from, to) = (self.bed[start], self.bed[end]);
+            let along = to.xz() - from.xz();
+            let length = along.length();
+            if length > 1e-3 {
+                let direction = along / length;
+                let fall = from.y - to.y;
+                let middle = from.lerp(to, 0.5);
+                stretches.push(RiverWater {
+                    surface: Vec3::new(middle.x, middle.y + self.water_depth, middle.z),
+                    // Overlapping a little so there are no dry seams at the bends
+                    size: Vec2::new(self.width, length + self.width * 0.25),
+                    rotation: direction.x.atan2(direction.y).to_degrees(),
+                    depth: self.water_depth + fall * 0.5,
+                    flow: direction * self.flow_speed,
+                });
+            }
+            start = end;
+        }
+        stretches
+    }
+}
+
+/// Rivers of the loaded level, for the water to be filled into
+#[derive(Resource, Debug, Clone, Default)]
+pub struct TerrainRivers {
+    pub rivers: Vec<River>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn desc(path: Vec<[f32; 2]>) -> RiverDesc {
+        serde_json::from_value(serde_json::json!({ "name": "Test", "path": path })).unwrap()
+    }
+
+    #[test]
+    fn test_bed_only_ever_falls() {
+        // Ground rises in the middle, the river has to cut through the rise
+        let hill = |x: f32, _: f32| 3.0 - (x - 20.0).abs() * 0.1;
+        let river = River::carve(&desc(vec![[0.0, 0.0], [20.0, 0.0], [40.0, 0.0]]), hill);
+        assert!(river.bed.windows(2).all(|pair| pair[1].y <= pair[0].y));
+        assert!((river.bed[0].y - (1.0 - 1.5)).abs() < 1e-4);
+        assert!(river.bed.iter().all(|point| point.y <= hill(point.x, point.z) - 1.5 + 1e-4));
+    }
+
+    #[test]
+    fn test_water_follows_the_bed_downstream() {
+        let slope = |_: f32, z: f32| -z * 0.02;
+        let river = River::carve(&desc(vec![[0.0, 0.0], [0.0, 40.0]]), slope);
+        let water = river.water();
+        assert_eq!(water.len(), 5);
+        for stretch in &water {
+            // Local Z runs along +Z, downstream
+            assert!(stretch.rotation.abs() < 1e-3);
+            assert!(stretch.flow.abs_diff_eq(Vec2::new(0.0, 1.0), 1e-4));
+            assert!(stretch.depth > river.water_depth);
+        }
+        assert!(water.windows(2).all(|pair| pair[1].surface.y < pair[0].surface.y));
+    }
+}
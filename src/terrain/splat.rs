@@ -0,0 +1,515 @@
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, ShaderType, TextureDimension, TextureFormat};
+use serde::{Deserialize, Serialize};
+
+use super::detail::{repeating, rock_height};
+use super::{TerrainChunkManager, TerrainMaterial};
+
+/// Most layers the splat shader blends, two RGBA splat maps hold one weight each
+pub const MAX_TERRAIN_LAYERS: usize = 8;
+
+/// Ground the terrain is splatted with, in layer order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TerrainLayer {
+    Grass,
+    Dirt,
+    Rock,
+    Sand,
+    Snow,
+}
+
+impl TerrainLayer {
+    pub const ALL: [TerrainLayer; 5] =
+        [TerrainLayer::Grass, TerrainLayer::Dirt, TerrainLayer::Rock, TerrainLayer::Sand, TerrainLayer::Snow];
+
+    /// Localization key of the layer's name
+    pub fn name_key(self) -> &'static str {
+        match self {
+            Self::Grass => "terrain_layer.grass",
+            Self::Dirt => "terrain_layer.dirt",
+            Self::Rock => "terrain_layer.rock",
+            Self::Sand => "terrain_layer.sand",
+            Self::Snow => "terrain_layer.snow",
+        }
+    }
+
+    /// Slot of the layer in the splat uniform, the splat maps and the layer textures
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Layer color and the height and slope it grows on when nothing is painted
+    pub fn default_rule(self) -> TerrainLayerUniform {
+        let (color, roughness) = match self {
+            Self::Grass => (Color::rgb(0.3, 0.5, 0.3), 0.9),
+            Self::Dirt => (Color::rgb(0.45, 0.35, 0.24), 0.95),
+            Self::Rock => (Color::rgb(0.42, 0.41, 0.39), 0.8),
+            Self::Sand => (Color::rgb(0.76, 0.68, 0.5), 0.95),
+            Self::Snow => (Color::rgb(0.9, 0.92, 0.96), 0.75),
+        };
+        // Heights are world space, the default terrain spans roughly -7 to 3 meters
+        let (height_min, height_max, slope_min, slope_max) = match self {
+            Self::Grass => (-4.5, 1.5, 0.0, 0.2),
+            Self::Dirt => (-4.5, NO_LIMIT, 0.15, 0.4),
+            Self::Rock => (-NO_LIMIT, NO_LIMIT, 0.4, NO_LIMIT),
+            Self::Sand => (-NO_LIMIT, -4.5, 0.0, 0.35),
+            Self::Snow => (1.5, NO_LIMIT, 0.0, 0.4),
+        };
+        let texture_scale = match self {
+            Self::Rock => 0.25,
+            Self::Snow => 0.1,
+            _ => 0.5,
+        };
+        TerrainLayerUniform {
+            color: Vec4::from(color.as_rgba_f32()),
+            height_min,
+            height_max,
+            slope_min,
+            slope_max,
+            height_blend: 0.5,
+            slope_blend: 0.05,
+            texture_scale,
+            roughness,
+            detail_strength: 0.35,
+        }
+    }
+}
+
+/// Height or slope bound that is never reached
+const NO_LIMIT: f32 = 1.0e4;
+
+/// One splat layer for the shader
+#[derive(ShaderType, Clone, Copy, Debug, Default, PartialEq)]
+pub struct TerrainLayerUniform {
+    pub color: Vec4,
+    /// World height band the layer grows in
+    pub height_min: f32,
+    pub height_max: f32,
+    /// Slope band (1 - normal.y) the layer grows on
+    pub slope_min: f32,
+    pub slope_max: f32,
+    /// Softness of the height band edges in meters
+    pub height_blend: f32,
+    /// Softness of the slope band edges
+    pub slope_blend: f32,
+    /// Texture repeats per meter
+    pub texture_scale: f32,
+    pub roughness: f32,
+    /// How strongly the layer texture varies the color (0.0 - 1.0)
+    pub detail_strength: f32,
+}
+
+/// Inside `[min, max]` with soft edges of `blend` either side
+fn band(value: f32, min: f32, max: f32, blend: f32) -> f32 {
+    let smoothstep = |edge0: f32, edge1: f32, x: f32| {
+        let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    };
+    let blend = blend.max(1.0e-4);
+    smoothstep(min - blend, min + blend, value) * (1.0 - smoothstep(max - blend, max + blend, value))
+}
+
+impl TerrainLayerUniform {
+    /// How much the height and slope rules want the layer at a point, mirrors `rule_weight` in `terrain.wgsl`
+    pub fn rule_weight(&self, height: f32, slope: f32) -> f32 {
+        band(height, self.height_min, self.height_max, self.height_blend)
+            * band(slope, self.slope_min, self.slope_max, self.slope_blend)
+    }
+}
+
+/// Splat layers and where the painted splat maps lie
+#[derive(ShaderType, Clone, Copy, Debug, PartialEq)]
+pub struct SplatUniform {
+    pub layers: [TerrainLayerUniform; MAX_TERRAIN_LAYERS],
+    pub layer_count: u32,
+    /// World XZ of the painted splat maps' corner
+    pub splat_origin: Vec2,
+    /// Meters the splat maps cover along each side, 0.0 while nothing is painted
+    pub splat_size: f32,
+    /// World XZ of the baked biome map's corner
+    pub biome_origin: Vec2,
+    /// Meters the biome map covers along each side, 0.0 until it's first baked
+    pub biome_size: f32,
+    /// Share of growth inside the biome map that ignores the height and slope rules
+    pub biome_cover: f32,
+    /// Slope (1 - normal.y) above which textures are projected from the side as well as from above
+    pub triplanar_start: f32,
+    /// How sharply the triplanar projections hand over, higher stretches less but seams more
+    pub triplanar_sharpness: f32,
+}
+
+impl Default for SplatUniform {
+    fn default() -> Self {
+        let mut layers = [TerrainLayerUniform::default(); MAX_TERRAIN_LAYERS];
+        for layer in TerrainLayer::ALL {
+            layers[layer.index()] = layer.default_rule();
+        }
+        Self {
+            layers,
+            layer_count: TerrainLayer::ALL.len() as u32,
+            splat_origin: Vec2::ZERO,
+            splat_size: 0.0,
+            biome_origin: Vec2::ZERO,
+            biome_size: 0.0,
+            biome_cover: 0.0,
+            triplanar_start: 0.3,
+            triplanar_sharpness: 4.0,
+        }
+    }
+}
+
+impl SplatUniform {
+    pub fn layer_mut(&mut self, layer: TerrainLayer) -> &mut TerrainLayerUniform {
+        &mut self.layers[layer.index()]
+    }
+
+    /// Layer weights summing to one, painted weights first and the rules filling what's left.
+    /// Mirrors `splat_weights` in `terrain.wgsl`.
+    pub fn weights(&self, height: f32, slope: f32, painted: [f32; MAX_TERRAIN_LAYERS]) -> [f32; MAX_TERRAIN_LAYERS] {
+        self.blend_weights(height, slope, painted, [1.0; MAX_TERRAIN_LAYERS], 0.0)
+    }
+
+    /// Layer weights where the biome map covers the point, the rules scaled by the biome's layer affinity
+    pub fn weights_in_biome(
+        &self,
+        height: f32,
+        slope: f32,
+        painted: [f32; MAX_TERRAIN_LAYERS],
+        affinity: [f32; MAX_TERRAIN_LAYERS],
+    ) -> [f32; MAX_TERRAIN_LAYERS] {
+        self.blend_weights(height, slope, painted, affinity, self.biome_cover)
+    }
+
+    fn blend_weights(
+        &self,
+        height: f32,
+        slope: f32,
+        painted: [f32; MAX_TERRAIN_LAYERS],
+        affinity: [f32; MAX_TERRAIN_LAYERS],
+        cover: f32,
+    ) -> [f32; MAX_TERRAIN_LAYERS] {
+        let count = (self.layer_count as usize).min(MAX_TERRAIN_LAYERS);
+        let mut rules = [0.0; MAX_TERRAIN_LAYERS];
+        for ((rule, layer), affinity) in rules.iter_mut().zip(&self.layers).zip(affinity).take(count) {
+            *rule = (layer.rule_weight(height, slope) + cover) * affinity;
+        }
+        let rule_total: f32 = rules.iter().sum();
+        if rule_total <= 1.0e-4 {
+            // Nothing matches, fall back to the first layer
+            rules[0] = 1.0;
+        } else {
+            rules.iter_mut().for_each(|rule| *rule /= rule_total);
+        }
+
+        let painted_total = painted.iter().take(count).sum::<f32>().min(1.0);
+        let mut weights = [0.0; MAX_TERRAIN_LAYERS];
+        for ((weight, painted), rule) in weights.iter_mut().zip(painted).zip(rules).take(count) {
+            *weight = painted + rule * (1.0 - painted_total);
+        }
+        let total: f32 = weights.iter().sum();
+        weights.iter_mut().for_each(|weight| *weight /= total);
+        weights
+    }
+}
+
+/// Paints a layer onto the terrain's splat maps
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PaintTerrainEvent {
+    pub position: Vec3,
+    pub layer: TerrainLayer,
+    /// Brush radius in meters
+    pub radius: f32,
+    /// Weight added at the brush center (0.0 - 1.0), falling off to nothing at the edge
+    pub strength: f32,
+}
+
+/// Hand painted layer weights over a square of the world, on top of the height and slope rules
+#[derive(Resource, Debug, Clone)]
+pub struct TerrainSplatMap {
+    /// Texels along each side
+    pub size: u32,
+    /// World XZ of the map's corner
+    pub origin: Vec2,
+    /// Meters covered along each side
+    pub extent: f32,
+    weights: Vec<[u8; MAX_TERRAIN_LAYERS]>,
+    images: Option<[Handle<Image>; 2]>,
+    painted: bool,
+    dirty: bool,
+}
+
+impl Default for TerrainSplatMap {
+    fn default() -> Self {
+        Self::new(512, Vec2::splat(-200.0), 400.0)
+    }
+}
+
+impl TerrainSplatMap {
+    pub fn new(size: u32, origin: Vec2, extent: f32) -> Self {
+        Self {
+            size,
+            origin,
+            extent,
+            weights: vec![[0; MAX_TERRAIN_LAYERS]; (size * size) as usize],
+            images: None,
+            painted: false,
+            dirty: false,
+        }
+    }
+
+    fn texel_size(&self) -> f32 {
+        self.extent / self.size as f32
+    }
+
+    fn texel(&self, world: Vec2) -> Option<usize> {
+        let local = (world - self.origin) / self.texel_size();
+        let inside = local.cmpge(Vec2::ZERO).all() && local.cmplt(Vec2::splat(self.size as f32)).all();
+        inside.then(|| local.y as usize * self.size as usize + local.x as usize)
+    }
+
+    /// Painted weights at a world XZ position, all zero outside the map
+    pub fn weights_at(&self, world: Vec2) -> [f32; MAX_TERRAIN_LAYERS] {
+        self.texel(world)
+            .map_or([0.0; MAX_TERRAIN_LAYERS], |texel| self.weights[texel].map(|weight| weight as f32 / 255.0))
+    }
+
+    /// Raises `layer` around `center`, taking the weight from the other layers so no texel goes over one
+    pub fn paint(&mut self, center: Vec2, layer: TerrainLayer, radius: f32, strength: f32) {
+        let texel_size = self.texel_size();
+        let reach = (radius / texel_size).ceil() as i32;
+        let center_texel = ((center - self.origin) / texel_size).floor().as_ivec2();
+        let index = layer.index();
+        for y in center_texel.y - reach..=center_texel.y + reach {
+            for x in center_texel.x - reach..=center_texel.x + reach {
+                if x < 0 || y < 0 || x >= self.size as i32 || y >= self.size as i32 {
+                    continue;
+                }
+                let world = self.origin + (Vec2::new(x as f32, y as f32) + 0.5) * texel_size;
+                let falloff = 1.0 - (world.distance(center) / radius.max(1.0e-3)).min(1.0);
+                let amount = strength.clamp(0.0, 1.0) * falloff * falloff;
+                if amount <= 0.0 {
+                    continue;
+                }
+
+                let texel = &mut self.weights[y as usize * self.size as usize + x as usize];
+                let mut weights = texel.map(|weight| weight as f32 / 255.0);
+                weights[index] = (weights[index] + amount).min(1.0);
+                let others: f32 = weights.iter().enumerate().filter(|(i, _)| *i != index).map(|(_, w)| *w).sum();
+                let room = 1.0 - weights[index];
+                if others > room {
+                    let scale = room / others;
+                    for (i, weight) in weights.iter_mut().enumerate() {
+                        if i != index {
+                            *weight *= scale;
+                        }
+                    }
+                }
+                *texel = weights.map(|weight| (weight * 255.0).round() as u8);
+            }
+        }
+        self.painted = true;
+        self.dirty = true;
+    }
+
+    /// The weights as two RGBA images, layers 0-3 and 4-7
+    fn to_images(&self) -> [Image; 2] {
+        std::array::from_fn(|half| {
+            let channels = half * 4..half * 4 + 4;
+            let data = self.weights.iter().flat_map(|weights| weights[channels.clone()].iter().copied()).collect();
+            Image::new(
+                Extent3d { width: self.size, height: self.size, depth_or_array_layers: 1 },
+                TextureDimension::D2,
+                data,
+                TextureFormat::Rgba8Unorm,
+            )
+        })
+    }
+}
+
+/// Tileable value noise over whole UV units, `cells` across
+pub(super) fn tile_noise(uv: Vec2, cells: u32, seed: u32) -> f32 {
+    let hash = |x: i32, y: i32| {
+        let mut h = (x.rem_euclid(cells as i32) as u32).wrapping_mul(0x27d4_eb2d)
+            ^ (y.rem_euclid(cells as i32) as u32).wrapping_mul(0x1656_67b1)
+            ^ seed.wrapping_mul(0x9e37_79b9);
+        h ^= h >> 15;
+        h = h.wrapping_mul(0x85eb_ca6b);
+        h ^= h >> 13;
+        (h & 0xffff) as f32 / 65535.0
+    };
+    let p = (uv - uv.floor()) * cells as f32;
+    let cell = p.floor().as_ivec2();
+    let f = p - p.floor();
+    let u = f * f * (Vec2::splat(3.0) - 2.0 * f);
+    let top = hash(cell.x, cell.y) + (hash(cell.x + 1, cell.y) - hash(cell.x, cell.y)) * u.x;
+    let bottom = hash(cell.x, cell.y + 1) + (hash(cell.x + 1, cell.y + 1) - hash(cell.x, cell.y + 1)) * u.x;
+    top + (bottom - top) * u.y
+}
+
+/// Brightness of a layer's texture at `uv`, around 0.5 on average. Tiles seamlessly across whole UV units.
+pub fn layer_detail(layer: TerrainLayer, uv: Vec2) -> f32 {
+    let seed = layer.index() as u32;
+    let value = match layer {
+        // Fine blades over larger patches of lush and dry grass
+        TerrainLayer::Grass => tile_noise(uv, 64, seed) * 0.5 + tile_noise(uv, 8, seed + 10) * 0.5,
+        TerrainLayer::Dirt => tile_noise(uv, 16, seed) * 0.6 + tile_noise(uv, 48, seed + 10) * 0.4,
+        TerrainLayer::Rock => rock_height(uv) * 0.7 + tile_noise(uv, 32, seed) * 0.3,
+        // Wind ripples running across the dunes
+        TerrainLayer::Sand => {
+            let ripple = ((uv.x * 12.0 + tile_noise(uv, 4, seed) * 2.0) * std::f32::consts::TAU).sin() * 0.5 + 0.5;
+            ripple * 0.4 + tile_noise(uv, 64, seed + 10) * 0.6
+        }
+        TerrainLayer::Snow => tile_noise(uv, 8, seed) * 0.7 + tile_noise(uv, 32, seed + 10) * 0.3,
+    };
+    value.clamp(0.0, 1.0)
+}
+
+/// Texture array with one grayscale detail texture per layer, in layer order
+pub fn generate_layer_textures(size: u32) -> Image {
+    let layers = TerrainLayer::ALL.len() as u32;
+    let mut data = Vec::with_capacity((size * size * 4 * layers) as usize);
+    for layer in TerrainLayer::ALL {
+        for y in 0..size {
+            for x in 0..size {
+                let value = (layer_detail(layer, Vec2::new(x as f32, y as f32) / size as f32) * 255.0).round() as u8;
+                data.extend_from_slice(&[value, value, value, 255]);
+            }
+        }
+    }
+    // Stacked vertically, then split into array layers
+    let mut image = Image::new(
+        Extent3d { width: size, height: size * layers, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8Unorm,
+    );
+    image.reinterpret_stacked_2d_as_array(layers);
+    repeating(image)
+}
+
+/// Gives the terrain materials their layer textures
+pub(super) fn setup_layer_textures(
+    manager: Res<TerrainChunkManager>,
+    mut materials: ResMut<Assets<TerrainMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let textures = images.add(generate_layer_textures(256));
+    for handle in manager.materials() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.extension.layer_textures = Some(textures.clone());
+        }
+    }
+}
+
+pub(super) fn paint_terrain(mut events: EventReader<PaintTerrainEvent>, mut splat_map: ResMut<TerrainSplatMap>) {
+    for event in events.read() {
+        splat_map.paint(event.position.xz(), event.layer, event.radius, event.strength);
+    }
+}
+
+/// Uploads painted splat maps and points the terrain materials at them
+pub(super) fn upload_splat_maps(
+    mut splat_map: ResMut<TerrainSplatMap>,
+    manager: Res<TerrainChunkManager>,
+    mut materials: ResMut<Assets<TerrainMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    if !splat_map.dirty {
+        return;
+    }
+    splat_map.dirty = false;
+
+    let [first, second] = splat_map.to_images();
+    let handles = match splat_map.images.clone() {
+        Some([first_handle, second_handle]) => {
+            images.insert(first_handle.id(), first);
+            images.insert(second_handle.id(), second);
+            [first_handle, second_handle]
+        }
+        None => [images.add(first), images.add(second)],
+    };
+    splat_map.images = Some(handles.clone());
+
+    let size = if splat_map.painted { splat_map.extent } else { 0.0 };
+    for handle in manager.materials() {
+        if let Some(material) = materials.get_mut(handle) {
+            let extension = &mut material.extension;
+            extension.splat.splat_origin = splat_map.origin;
+            extension.splat.splat_size = size;
+            extension.splat_map_0 = Some(handles[0].clone());
+            extension.splat_map_1 = Some(handles[1].clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules_pick_layers_by_height_and_slope() {
+        let splat = SplatUniform::default();
+        let strongest = |height: f32, slope: f32| {
+            let weights = splat.weights(height, slope, [0.0; MAX_TERRAIN_LAYERS]);
+            let best = (0..MAX_TERRAIN_LAYERS).max_by(|a, b| weights[*a].total_cmp(&weights[*b])).unwrap();
+            assert!((weights.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+            best
+        };
+        assert_eq!(strongest(-2.0, 0.05), TerrainLayer::Grass.index());
+        assert_eq!(strongest(-2.0, 0.8), TerrainLayer::Rock.index());
+        assert_eq!(strongest(-6.5, 0.05), TerrainLayer::Sand.index());
+        assert_eq!(strongest(3.0, 0.05), TerrainLayer::Snow.index());
+        assert_eq!(strongest(-2.0, 0.3), TerrainLayer::Dirt.index());
+    }
+
+    #[test]
+    fn test_painting_overrides_the_rules() {
+        let mut map = TerrainSplatMap::new(64, Vec2::splat(-32.0), 64.0);
+        map.paint(Vec2::ZERO, TerrainLayer::Sand, 4.0, 1.0);
+        map.paint(Vec2::ZERO, TerrainLayer::Dirt, 4.0, 0.5);
+        let painted = map.weights_at(Vec2::new(0.5, 0.5));
+        assert!(painted.iter().sum::<f32>() <= 1.0 + 1e-2);
+        assert!(painted[TerrainLayer::Dirt.index()] > 0.3);
+        assert!(painted[TerrainLayer::Sand.index()] > 0.3);
+        // Nothing outside the brush or the map
+        assert_eq!(map.weights_at(Vec2::new(20.0, 0.0)), [0.0; MAX_TERRAIN_LAYERS]);
+        assert_eq!(map.weights_at(Vec2::new(100.0, 0.0)), [0.0; MAX_TERRAIN_LAYERS]);
+
+        // Grass ground painted fully to sand is all sand
+        let mut full = [0.0; MAX_TERRAIN_LAYERS];
+        full[TerrainLayer::Sand.index()] = 1.0;
+        let weights = SplatUniform::default().weights(-2.0, 0.05, full);
+        assert!((weights[TerrainLayer::Sand.index()] - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_biome_affinity_reshapes_the_rules() {
+        let mut splat = SplatUniform::default();
+        splat.biome_cover = 0.25;
+        // Desert-like affinity, flat mid-height ground would be grass without it
+        let mut affinity = [0.0; MAX_TERRAIN_LAYERS];
+        affinity[TerrainLayer::Grass.index()] = 0.05;
+        affinity[TerrainLayer::Dirt.index()] = 0.4;
+        affinity[TerrainLayer::Sand.index()] = 1.0;
+        let weights = splat.weights_in_biome(-2.0, 0.05, [0.0; MAX_TERRAIN_LAYERS], affinity);
+        assert!((weights.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+        assert!(weights[TerrainLayer::Sand.index()] > weights[TerrainLayer::Grass.index()]);
+        assert_eq!(weights[TerrainLayer::Snow.index()], 0.0);
+
+        // Full affinity and no cover is the plain rules
+        let plain = splat.weights(-2.0, 0.05, [0.0; MAX_TERRAIN_LAYERS]);
+        splat.biome_cover = 0.0;
+        assert_eq!(splat.weights_in_biome(-2.0, 0.05, [0.0; MAX_TERRAIN_LAYERS], [1.0; MAX_TERRAIN_LAYERS]), plain);
+    }
+
+    #[test]
+    fn test_layer_textures_tile_seamlessly() {
+        for layer in TerrainLayer::ALL {
+            for t in [0.0, 0.37, 0.81] {
+                let edge = layer_detail(layer, Vec2::new(0.0, t)) - layer_detail(layer, Vec2::new(1.0, t));
+                assert!(edge.abs() < 1e-3, "{layer:?} has a seam");
+            }
+        }
+    }
+}
@@ -5,6 +5,8 @@ use bevy::log::LogPlugin;
 use tempfile::TempDir;
 use std::path::PathBuf;
 
+use crate::game::HeadlessSimulationPlugins;
+
 /// Test fixture for setting up a minimal Bevy app with required plugins
 pub struct TestApp {
     pub app: App,
@@ -14,16 +16,29 @@ pub struct TestApp {
 
 impl Default for TestApp {
     fn default() -> Self {
-        let temp_dir = TempDir::new().expect("Failed to create temp directory");
-        let asset_path = temp_dir.path().join("assets");
-        std::fs::create_dir_all(&asset_path).expect("Failed to create asset directory");
-
         let mut app = App::new();
         app.add_plugins((
             MinimalPlugins,
             AssetPlugin::default(),
             LogPlugin::default(),
         ));
+        Self::with_app(app)
+    }
+}
+
+impl TestApp {
+    /// Test app running the physics, vehicle and terrain simulation without a window or renderer,
+    /// each frame advancing one fixed timestep
+    pub fn headless() -> Self {
+        let mut app = App::new();
+        app.add_plugins((HeadlessSimulationPlugins, LogPlugin::default()));
+        Self::with_app(app)
+    }
+
+    fn with_app(app: App) -> Self {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let asset_path = temp_dir.path().join("assets");
+        std::fs::create_dir_all(&asset_path).expect("Failed to create asset directory");
 
         Self {
             app,
@@ -31,9 +46,7 @@ impl Default for TestApp {
             asset_path,
         }
     }
-}
 
-impl TestApp {
     /// Add a plugin to the test app
     pub fn add_plugin<T: Plugin>(&mut self, plugin: T) -> &mut Self {
         self.app.add_plugin(plugin);
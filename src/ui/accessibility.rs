@@ -0,0 +1,191 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiSettings};
+
+use super::UiState;
+use crate::audio::RadioMessageEvent;
+use crate::game::{ControlPreset, GameSettings, HoldMode, HudPalette};
+use crate::tr;
+
+/// Subtitles kept on screen at once, older lines make room for new ones
+const MAX_SUBTITLES: usize = 3;
+
+pub fn hud_color(rgb: [u8; 3]) -> egui::Color32 {
+    egui::Color32::from_rgb(rgb[0], rgb[1], rgb[2])
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subtitle {
+    pub speaker: String,
+    pub text: String,
+    /// Seconds left on screen
+    pub remaining: f32,
+}
+
+/// Radio lines currently shown as subtitles, oldest first
+#[derive(Resource, Debug, Default)]
+pub struct Subtitles {
+    pub lines: Vec<Subtitle>,
+}
+
+impl Subtitles {
+    pub fn push(&mut self, speaker: &str, text: &str, duration: f32) {
+        if self.lines.len() >= MAX_SUBTITLES {
+            self.lines.remove(0);
+        }
+        self.lines.push(Subtitle {
+            speaker: speaker.to_string(),
+            text: text.to_string(),
+            remaining: duration,
+        });
+    }
+
+    /// Counts lines down and drops the expired ones
+    pub fn tick(&mut self, dt: f32) {
+        for line in &mut self.lines {
+            line.remaining -= dt;
+        }
+        self.lines.retain(|line| line.remaining > 0.0);
+    }
+}
+
+/// Sizes all egui text and widgets by the accessibility text scale
+pub(super) fn apply_text_scale(game_settings: Res<GameSettings>, mut egui_settings: ResMut<EguiSettings>) {
+    if !game_settings.is_changed() {
+        return;
+    }
+    let scale = game_settings.accessibility.text_scale.clamp(0.75, 2.0) as f64;
+    if egui_settings.scale_factor != scale {
+        egui_settings.scale_factor = scale;
+    }
+}
+
+/// Queues radio lines as subtitles while subtitles are on
+pub(super) fn queue_subtitles(
+    game_settings: Option<Res<GameSettings>>,
+    mut messages: EventReader<RadioMessageEvent>,
+    mut subtitles: ResMut<Subtitles>,
+) {
+    let enabled = game_settings.map_or(false, |settings| settings.accessibility.subtitles);
+    for message in messages.read() {
+        if enabled {
+            subtitles.push(&message.speaker, &message.text, message.duration);
+        }
+    }
+}
+
+pub(super) fn show_subtitles(time: Res<Time<Real>>, mut contexts: EguiContexts, mut subtitles: ResMut<Subtitles>) {
+    subtitles.tick(time.delta_seconds());
+    if subtitles.lines.is_empty() {
+        return;
+    }
+
+    egui::Area::new("subtitles")
+        .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -40.0])
+        .show(contexts.ctx_mut(), |ui| {
+            egui::Frame::none()
+                .fill(egui::Color32::from_black_alpha(180))
+                .inner_margin(8.0)
+                .rounding(4.0)
+                .show(ui, |ui| {
+                    for line in &subtitles.lines {
+                        ui.label(
+                            egui::RichText::new(tr!("subtitle.line", speaker = line.speaker, text = line.text))
+                                .color(egui::Color32::WHITE)
+                                .size(18.0),
+                        );
+                    }
+                });
+        });
+}
+
+fn hold_mode_picker(ui: &mut egui::Ui, label: &str, mode: &mut HoldMode) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        ui.radio_value(mode, HoldMode::Hold, tr!("accessibility.hold"));
+        ui.radio_value(mode, HoldMode::Toggle, tr!("accessibility.toggle"));
+    });
+}
+
+/// Accessibility settings page, opened from the pause menu
+pub(super) fn accessibility_menu(
+    mut contexts: EguiContexts,
+    mut ui_state: ResMut<UiState>,
+    mut game_settings: ResMut<GameSettings>,
+) {
+    if !ui_state.show_accessibility {
+        return;
+    }
+
+    // Edit a copy so the settings only count as changed when something actually changed
+    let mut settings = game_settings.accessibility.clone();
+    let mut open = true;
+    egui::Window::new(tr!("accessibility.title"))
+        .id(egui::Id::new("accessibility"))
+        .open(&mut open)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(contexts.ctx_mut(), |ui| {
+            let palette_name = |palette: HudPalette| tr!(&format!("accessibility.palette.{palette:?}"));
+            let preset_name = |preset: ControlPreset| tr!(&format!("accessibility.preset.{preset:?}"));
+
+            ui.heading(tr!("accessibility.display"));
+            ui.add(egui::Slider::new(&mut settings.text_scale, 0.75..=2.0).text(tr!("accessibility.text_size")));
+            egui::ComboBox::from_label(tr!("accessibility.hud_colors"))
+                .selected_text(palette_name(settings.hud_palette))
+                .show_ui(ui, |ui| {
+                    for palette in HudPalette::ALL {
+                        ui.selectable_value(&mut settings.hud_palette, palette, palette_name(palette));
+                    }
+                });
+            ui.checkbox(&mut settings.subtitles, tr!("accessibility.subtitles"));
+
+            ui.separator();
+            ui.heading(tr!("accessibility.motion"));
+            ui.add(egui::Slider::new(&mut settings.camera_shake, 0.0..=1.0).text(tr!("accessibility.camera_shake")));
+            ui.add(egui::Slider::new(&mut settings.motion_blur_limit, 0.0..=1.0).text(tr!("accessibility.motion_blur_limit")));
+
+            ui.separator();
+            ui.heading(tr!("accessibility.controls"));
+            egui::ComboBox::from_label(tr!("accessibility.keyboard_layout"))
+                .selected_text(preset_name(settings.control_preset))
+                .show_ui(ui, |ui| {
+                    for preset in ControlPreset::ALL {
+                        ui.selectable_value(&mut settings.control_preset, preset, preset_name(preset));
+                    }
+                });
+            hold_mode_picker(ui, &tr!("accessibility.handbrake"), &mut settings.handbrake_mode);
+            hold_mode_picker(ui, &tr!("accessibility.winch"), &mut settings.winch_mode);
+            ui.add(egui::Slider::new(&mut settings.rumble_intensity, 0.0..=1.0).text(tr!("accessibility.rumble")));
+        });
+
+    if !open {
+        ui_state.show_accessibility = false;
+    }
+    if settings != game_settings.accessibility {
+        game_settings.accessibility = settings;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subtitles_expire() {
+        let mut subtitles = Subtitles::default();
+        subtitles.push("Spotter", "Rock on your left", 2.0);
+        subtitles.push("Spotter", "Keep coming", 4.0);
+        subtitles.tick(3.0);
+        assert_eq!(subtitles.lines.len(), 1);
+        assert_eq!(subtitles.lines[0].text, "Keep coming");
+    }
+
+    #[test]
+    fn test_oldest_subtitle_makes_room() {
+        let mut subtitles = Subtitles::default();
+        for index in 0..=MAX_SUBTITLES {
+            subtitles.push("Base", &index.to_string(), 5.0);
+        }
+        assert_eq!(subtitles.lines.len(), MAX_SUBTITLES);
+        assert_eq!(subtitles.lines[0].text, "1");
+    }
+}
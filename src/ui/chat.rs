@@ -0,0 +1,117 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::game::{ChatBody, ChatHistory, PlayerId, PlayerInput, QuickMessage, SubmitChatEvent};
+use crate::tr;
+
+/// Messages shown in the overlay while not typing
+const VISIBLE_MESSAGES: usize = 6;
+/// Seconds a message stays before fading
+const MESSAGE_HOLD: f32 = 8.0;
+/// Seconds a message takes to fade out
+const MESSAGE_FADE: f32 = 2.0;
+/// Pick buttons next to each quick message, keyboard key and D-pad direction
+const QUICK_CHAT_HINTS: [&str; 4] = ["1 ⏶", "2 ⏵", "3 ⏷", "4 ⏴"];
+
+/// Opacity (0.0 - 1.0) of a message `age` seconds old
+fn message_alpha(age: f32) -> f32 {
+    1.0 - ((age - MESSAGE_HOLD) / MESSAGE_FADE).clamp(0.0, 1.0)
+}
+
+/// Line being typed, `None` while the chat box is closed
+#[derive(Default)]
+pub(super) struct ChatInput(Option<String>);
+
+/// Chat overlay in the lower left, Enter opens the chat box and sends, Escape drops the line
+pub(super) fn chat_overlay(
+    mut contexts: EguiContexts,
+    time: Res<Time<Real>>,
+    keyboard: Res<Input<KeyCode>>,
+    history: Res<ChatHistory>,
+    mut submit: EventWriter<SubmitChatEvent>,
+    mut input: Local<ChatInput>,
+) {
+    let ctx = contexts.ctx_mut();
+    if input.0.is_none() && keyboard.just_pressed(KeyCode::Return) && !ctx.wants_keyboard_input() {
+        input.0 = Some(String::new());
+        return;
+    }
+
+    let typing = input.0.is_some();
+    let now = time.elapsed_seconds();
+    let shown: Vec<_> = if typing {
+        history.entries().collect()
+    } else {
+        let recent: Vec<_> = history.entries().rev().take(VISIBLE_MESSAGES).collect();
+        recent.into_iter().rev().filter(|entry| message_alpha(now - entry.received_at) > 0.0).collect()
+    };
+    if shown.is_empty() && !typing {
+        return;
+    }
+
+    egui::Area::new("chat")
+        .anchor(egui::Align2::LEFT_BOTTOM, [10.0, -120.0])
+        .interactable(typing)
+        .show(ctx, |ui| {
+            ui.set_max_width(360.0);
+            egui::ScrollArea::vertical().max_height(200.0).stick_to_bottom(true).show(ui, |ui| {
+                for entry in shown {
+                    let alpha = if typing { 1.0 } else { message_alpha(now - entry.received_at) };
+                    let text = match &entry.message.body {
+                        ChatBody::Text(text) => text.clone(),
+                        ChatBody::Quick(message) => tr!(message.text_key()),
+                    };
+                    let color = egui::Color32::WHITE.gamma_multiply(alpha);
+                    ui.colored_label(color, tr!("chat.line", name = entry.message.sender.clone(), text = text));
+                }
+            });
+
+            let Some(line) = input.0.as_mut() else {
+                return;
+            };
+            let response = ui.add(egui::TextEdit::singleline(line).hint_text(tr!("chat.hint")).desired_width(360.0));
+            response.request_focus();
+            if keyboard.just_pressed(KeyCode::Escape) {
+                input.0 = None;
+            } else if keyboard.just_pressed(KeyCode::Return) {
+                if let Some(line) = input.0.take().filter(|line| !line.trim().is_empty()) {
+                    submit.send(SubmitChatEvent(ChatBody::Text(line)));
+                }
+            }
+        });
+}
+
+/// Quick chat page the first player has open, picked with number keys or the D-pad
+pub(super) fn quick_chat_menu(mut contexts: EguiContexts, players: Query<(&PlayerId, &PlayerInput)>) {
+    let Some(page) = players.iter().find(|(player, _)| player.0 == 0).and_then(|(_, input)| input.quick_chat) else {
+        return;
+    };
+
+    egui::Window::new(tr!("chat.quick_title", page = page + 1))
+        .id(egui::Id::new("quick_chat"))
+        .anchor(egui::Align2::RIGHT_CENTER, [-10.0, 0.0])
+        .collapsible(false)
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            for (hint, message) in QUICK_CHAT_HINTS.iter().zip(QuickMessage::page(page)) {
+                ui.horizontal(|ui| {
+                    ui.weak(*hint);
+                    ui.label(tr!(message.text_key()));
+                });
+            }
+            ui.weak(tr!("chat.quick_next"));
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages_fade_after_hold() {
+        assert_eq!(message_alpha(0.0), 1.0);
+        assert_eq!(message_alpha(MESSAGE_HOLD), 1.0);
+        assert!((message_alpha(MESSAGE_HOLD + MESSAGE_FADE * 0.5) - 0.5).abs() < 1e-5);
+        assert_eq!(message_alpha(MESSAGE_HOLD + MESSAGE_FADE), 0.0);
+    }
+}
@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::game::{dismiss_crash_report, submit_crash_report, CrashReportSettings, PendingCrashReports};
+use crate::tr;
+
+/// Offers to send the crash reports earlier runs left behind, one at a time
+pub fn crash_report_dialog(
+    mut contexts: EguiContexts,
+    mut pending: ResMut<PendingCrashReports>,
+    settings: Res<CrashReportSettings>,
+) {
+    let Some((path, dump)) = pending.reports.first() else {
+        return;
+    };
+
+    let mut send = false;
+    let mut dismiss = false;
+    egui::Window::new(tr!("crash.title"))
+        .id(egui::Id::new("crash_report"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(tr!("crash.body"));
+            ui.add_space(8.0);
+            ui.monospace(dump.summary());
+            egui::CollapsingHeader::new(tr!("crash.details")).show(ui, |ui| {
+                egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                    ui.monospace(&dump.backtrace);
+                });
+            });
+            if pending.reports.len() > 1 {
+                ui.label(tr!("crash.more", count = pending.reports.len() - 1));
+            }
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                if settings.telemetry_url.is_some() && ui.button(tr!("crash.send")).clicked() {
+                    send = true;
+                }
+                if ui.button(tr!("crash.dont_send")).clicked() {
+                    dismiss = true;
+                }
+            });
+        });
+
+    if send {
+        let (path, dump) = pending.reports.remove(0);
+        if let Some(url) = &settings.telemetry_url {
+            submit_crash_report(url, path, dump);
+        }
+    } else if dismiss {
+        dismiss_crash_report(path);
+        pending.reports.remove(0);
+    }
+}
@@ -0,0 +1,192 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, EguiContexts};
+
+use super::UiState;
+use crate::game::{
+    CameraKeyframe, CameraPath, DirectorSettings, DirectorState, GameCamera, ReplayTimeline, StartExportEvent,
+    WorldOrigin, EXPORT_FRAME_RATES,
+};
+use crate::tr;
+
+/// Where the camera path is saved and loaded, editable in the window
+pub(super) struct CameraPathFile(String);
+
+impl Default for CameraPathFile {
+    fn default() -> Self {
+        Self("camera_paths/replay.camera.json".to_string())
+    }
+}
+
+/// Director window for keyframing the camera over a replay and exporting it, opened from the pause menu
+pub(super) fn director_window(
+    mut contexts: EguiContexts,
+    mut ui_state: ResMut<UiState>,
+    mut director: ResMut<DirectorState>,
+    mut path: ResMut<CameraPath>,
+    mut settings: ResMut<DirectorSettings>,
+    timeline: Res<ReplayTimeline>,
+    origin: Res<WorldOrigin>,
+    cameras: Query<(&Transform, &Projection), With<GameCamera>>,
+    mut exports: EventWriter<StartExportEvent>,
+    mut file: Local<CameraPathFile>,
+    mut status: Local<Option<String>>,
+) {
+    if !ui_state.show_director {
+        return;
+    }
+    // Nothing but the replay should end up in the exported frames
+    if director.export_progress.is_some() {
+        return;
+    }
+
+    let duration = timeline.duration();
+    let mut open = true;
+    egui::Window::new(tr!("director.title"))
+        .id(egui::Id::new("director"))
+        .open(&mut open)
+        .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -10.0])
+        .show(contexts.ctx_mut(), |ui| {
+            if timeline.frames.is_empty() {
+                ui.weak(tr!("director.no_replay"));
+                return;
+            }
+
+            let mut active = director.active;
+            if ui.checkbox(&mut active, tr!("director.active")).changed() {
+                director.active = active;
+                director.playing = false;
+            }
+            let mut preview_path = director.preview_path;
+            if ui.checkbox(&mut preview_path, tr!("director.preview_path")).changed() {
+                director.preview_path = preview_path;
+            }
+
+            ui.horizontal(|ui| {
+                let label = if director.playing { tr!("director.pause") } else { tr!("director.play") };
+                if ui.add_enabled(director.active, egui::Button::new(label)).clicked() {
+                    if director.time >= duration {
+                        director.time = 0.0;
+                    }
+                    director.playing = !director.playing;
+                }
+                let mut time = director.time;
+                let slider = egui::Slider::new(&mut time, 0.0..=duration).suffix(" s").text(tr!("director.time"));
+                if ui.add_enabled(director.active, slider).changed() {
+                    director.time = time;
+                    director.playing = false;
+                }
+            });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                // Keyframes are framed with the free follow camera
+                let framing = director.active && !director.preview_path;
+                if ui.add_enabled(framing, egui::Button::new(tr!("director.add_keyframe"))).clicked() {
+                    if let Ok((transform, projection)) = cameras.get_single() {
+                        let fov = match projection {
+                            Projection::Perspective(perspective) => perspective.fov,
+                            _ => PerspectiveProjection::default().fov,
+                        };
+                        path.insert(CameraKeyframe::from_transform(director.time, transform, fov, &origin));
+                    }
+                }
+                if ui.button(tr!("director.clear_keyframes")).clicked() {
+                    path.keyframes.clear();
+                }
+            });
+            let mut removed = None;
+            let mut jump = None;
+            egui::ScrollArea::vertical().max_height(120.0).show(ui, |ui| {
+                for (index, keyframe) in path.keyframes.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.button(format!("{:.2} s", keyframe.time)).clicked() {
+                            jump = Some(keyframe.time);
+                        }
+                        let mut degrees = keyframe.fov.to_degrees();
+                        if ui.add(egui::Slider::new(&mut degrees, 20.0..=100.0).text(tr!("director.fov"))).changed() {
+                            keyframe.fov = degrees.to_radians();
+                        }
+                        if ui.small_button("✖").clicked() {
+                            removed = Some(index);
+                        }
+                    });
+                }
+            });
+            if let Some(index) = removed {
+                path.remove(index);
+            }
+            if let Some(time) = jump {
+                director.time = time;
+                director.playing = false;
+            }
+
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut file.0);
+                if ui.button(tr!("director.save")).clicked() {
+                    *status = Some(match path.save(&PathBuf::from(&file.0)) {
+                        Ok(()) => tr!("director.saved"),
+                        Err(error) => error.to_string(),
+                    });
+                }
+                if ui.button(tr!("director.load")).clicked() {
+                    *status = Some(match CameraPath::load(&PathBuf::from(&file.0)) {
+                        Ok(loaded) => {
+                            *path = loaded;
+                            tr!("director.loaded")
+                        }
+                        Err(error) => error.to_string(),
+                    });
+                }
+            });
+            if let Some(status) = status.as_ref() {
+                ui.weak(status.as_str());
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label(tr!("director.frame_rate"));
+                for rate in EXPORT_FRAME_RATES {
+                    ui.selectable_value(&mut settings.frame_rate, rate, rate.to_string());
+                }
+            });
+            ui.weak(tr!("director.export_to", directory = settings.export_directory.display().to_string()));
+            let can_export = path.keyframes.len() >= 2;
+            if ui.add_enabled(can_export, egui::Button::new(tr!("director.export"))).clicked() {
+                exports.send(StartExportEvent);
+            }
+        });
+
+    if !open {
+        ui_state.show_director = false;
+        director.active = false;
+        director.playing = false;
+    }
+}
+
+/// Export progress in the window title, where it stays out of the exported frames
+pub(super) fn export_progress(
+    director: Res<DirectorState>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut title: Local<Option<String>>,
+) {
+    if !director.is_changed() {
+        return;
+    }
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+    match director.export_progress {
+        Some((frame, frames)) => {
+            let original = title.get_or_insert_with(|| window.title.clone()).clone();
+            window.title = format!("{original} - {}", tr!("director.exporting", frame = frame, frames = frames));
+        }
+        None => {
+            if let Some(original) = title.take() {
+                window.title = original;
+            }
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use super::UiState;
+use crate::game::states::{self, GameProgress};
+use crate::game::{
+    fast_travel_block, travel_destinations, CargoItem, DestinationKind, FastTravel, FastTravelConfig, PlayerId,
+    PointOfInterest, RequestFastTravelEvent, Trail, Vehicle,
+};
+use crate::tr;
+
+/// Tow service list next to the trail map, player one pays to be towed to a trailhead or a point of interest
+/// they've already found. Each destination shows why it can't be booked when it can't.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn fast_travel_window(
+    mut contexts: EguiContexts,
+    mut ui_state: ResMut<UiState>,
+    config: Res<FastTravelConfig>,
+    fast_travel: Res<FastTravel>,
+    progress: Option<Res<GameProgress>>,
+    mode: Option<Res<State<states::GameMode>>>,
+    session: Option<Res<crate::game::GameState>>,
+    trails: Query<&Trail>,
+    points: Query<&PointOfInterest>,
+    players: Query<(Entity, &PlayerId, &GlobalTransform), With<Vehicle>>,
+    cargo: Query<&CargoItem>,
+    mut requests: EventWriter<RequestFastTravelEvent>,
+) {
+    if !ui_state.show_trail_map {
+        return;
+    }
+    let (Some(progress), Some((vehicle, _, transform))) =
+        (progress, players.iter().find(|(_, player, _)| player.0 == 0))
+    else {
+        return;
+    };
+
+    let has_cargo = cargo.iter().any(|item| item.in_bed_of == Some(vehicle) || item.strapped_to == Some(vehicle));
+    let mode = mode.as_deref().map(State::get);
+    let session = session.as_ref().map(|session| session.mode);
+    let destinations = travel_destinations(trails.iter(), points.iter(), &progress.discovered_points);
+
+    egui::Window::new(tr!("travel.title"))
+        .id(egui::Id::new("fast_travel"))
+        .anchor(egui::Align2::LEFT_CENTER, [16.0, 0.0])
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(tr!("travel.cash", cash = progress.cash));
+            ui.separator();
+            if destinations.is_empty() {
+                ui.weak(tr!("travel.none"));
+            }
+            for destination in &destinations {
+                let fee = config.fee(transform.translation(), destination.position);
+                let block = fast_travel_block(&fast_travel, mode, session, has_cargo, progress.cash, fee);
+                ui.horizontal(|ui| {
+                    let icon = match destination.kind {
+                        DestinationKind::Trailhead => "⛺",
+                        DestinationKind::PointOfInterest => "★",
+                    };
+                    ui.label(format!("{icon} {}", destination.name));
+                    ui.weak(tr!("travel.fee", fee = fee));
+                    let button = ui.add_enabled(block.is_none(), egui::Button::new(tr!("travel.tow")));
+                    let button = match block {
+                        Some(block) => button.on_disabled_hover_text(tr!(block.reason_key())),
+                        None => button,
+                    };
+                    if button.clicked() {
+                        requests.send(RequestFastTravelEvent { destination: destination.clone() });
+                        ui_state.show_trail_map = false;
+                    }
+                });
+            }
+        });
+}
+
+/// Blacks out the screen while a vehicle is towed, fading out before it's moved and back in once it's set down
+pub(super) fn fast_travel_overlay(
+    mut contexts: EguiContexts,
+    config: Res<FastTravelConfig>,
+    fast_travel: Res<FastTravel>,
+) {
+    let Some(destination) = fast_travel.destination() else {
+        return;
+    };
+    let alpha = fast_travel.fade(&config).clamp(0.0, 1.0);
+    let ctx = contexts.ctx_mut();
+    let screen = ctx.screen_rect();
+    let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("fast_travel_fade")));
+    painter.rect_filled(screen, 0.0, egui::Color32::from_black_alpha((alpha * 255.0) as u8));
+    painter.text(
+        screen.center(),
+        egui::Align2::CENTER_CENTER,
+        tr!("travel.towing_to", name = destination.name.clone()),
+        egui::FontId::proportional(24.0),
+        egui::Color32::from_white_alpha((alpha * 255.0) as u8),
+    );
+}
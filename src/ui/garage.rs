@@ -0,0 +1,208 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use super::{hud_color, GaugeBar, UiState, UiTheme};
+use crate::game::states::GameProgress;
+use crate::game::{
+    level_for_xp, level_progress, xp_for_level, Accessory, Bumper, EconomyConfig, GameSettings, LiftKit, Paint, Part,
+    ProgressionConfig, PurchaseEvent, TireType, Unlock,
+};
+use crate::tr;
+
+const SWATCH_SIZE: egui::Vec2 = egui::vec2(28.0, 28.0);
+
+fn color32(color: Color) -> egui::Color32 {
+    let [r, g, b, _] = color.as_rgba_u8();
+    egui::Color32::from_rgb(r, g, b)
+}
+
+/// Level and progress towards the next one
+pub(super) fn xp_bar(ui: &mut egui::Ui, theme: &UiTheme, color: egui::Color32, xp: u32) {
+    let level = level_for_xp(xp);
+    ui.add(
+        GaugeBar::new(theme, level_progress(xp)).fill(color).text(tr!(
+            "progression.level",
+            level = level,
+            xp = xp - xp_for_level(level),
+            needed = xp_for_level(level + 1) - xp_for_level(level)
+        )),
+    );
+}
+
+/// Hint shown on content the profile hasn't unlocked yet
+fn locked_hint(config: &ProgressionConfig, unlock: &Unlock) -> String {
+    match config.required_level(unlock) {
+        Some(level) => tr!("garage.locked_level", level = level),
+        None => tr!("garage.locked"),
+    }
+}
+
+/// Button buying `unlock` for its price, greyed out while the balance doesn't cover it
+fn buy_button(ui: &mut egui::Ui, economy: &EconomyConfig, progress: &GameProgress, unlock: &Unlock) -> bool {
+    let price = economy.price(unlock);
+    let affordable = progress.cash >= price;
+    ui.add_enabled(affordable, egui::Button::new(tr!("garage.buy", price = price)).small())
+        .on_disabled_hover_text(tr!("garage.cant_afford"))
+        .clicked()
+}
+
+/// A row of the parts that fit one slot, returns the part clicked. Unlocked parts not paid for yet get
+/// a buy button, clicked ones are added to `purchases`.
+#[allow(clippy::too_many_arguments)]
+fn part_picker(
+    ui: &mut egui::Ui,
+    config: &ProgressionConfig,
+    economy: &EconomyConfig,
+    progress: &GameProgress,
+    label: String,
+    parts: [Part; 3],
+    picked: Part,
+    purchases: &mut Vec<Unlock>,
+) -> Option<Part> {
+    let mut clicked = None;
+    ui.label(egui::RichText::new(label).strong());
+    ui.horizontal_wrapped(|ui| {
+        for part in parts {
+            let unlock = Unlock::Part(part);
+            let unlocked = config.is_unlocked(progress, &unlock);
+            let owned = economy.is_owned(progress, &unlock);
+            let label = egui::SelectableLabel::new(part == picked, tr!(part.name_key()));
+            let button = ui.add_enabled(unlocked && owned, label);
+            if button.on_disabled_hover_text(locked_hint(config, &unlock)).clicked() {
+                clicked = Some(part);
+            }
+            if unlocked && !owned && buy_button(ui, economy, progress, &unlock) {
+                purchases.push(unlock);
+            }
+        }
+    });
+    clicked
+}
+
+/// Picks the vehicle, paint, accessories and parts the player drives with, only unlocked content can be picked
+/// and vehicles and parts with a price only once they're bought
+#[allow(clippy::too_many_arguments)]
+pub(super) fn garage_window(
+    mut contexts: EguiContexts,
+    mut ui_state: ResMut<UiState>,
+    config: Res<ProgressionConfig>,
+    economy: Res<EconomyConfig>,
+    progress: Option<ResMut<GameProgress>>,
+    game_settings: Option<Res<GameSettings>>,
+    theme: Res<UiTheme>,
+    mut purchase_events: EventWriter<PurchaseEvent>,
+) {
+    if !ui_state.show_garage {
+        return;
+    }
+    let Some(mut progress) = progress else {
+        ui_state.show_garage = false;
+        return;
+    };
+    let colors = game_settings.map(|settings| settings.accessibility.hud_palette).unwrap_or_default().colors();
+
+    let mut loadout = progress.loadout.clone();
+    let mut purchases = Vec::new();
+    let mut open = true;
+    egui::Window::new(tr!("garage.title"))
+        .id(egui::Id::new("garage"))
+        .open(&mut open)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(contexts.ctx_mut(), |ui| {
+            xp_bar(ui, &theme, hud_color(colors.info), progress.xp);
+            ui.label(tr!("garage.cash", cash = progress.cash));
+
+            ui.separator();
+            ui.label(egui::RichText::new(tr!("garage.vehicles")).strong());
+            let starter = config.vehicles.first().map(|vehicle| vehicle.name.clone());
+            let current = loadout.vehicle.clone().or(starter);
+            for vehicle in &config.vehicles {
+                let unlock = Unlock::Vehicle(vehicle.name.clone());
+                let unlocked = config.is_unlocked(&progress, &unlock);
+                let owned = economy.is_owned(&progress, &unlock);
+                let selected = current.as_ref() == Some(&vehicle.name);
+                ui.horizontal(|ui| {
+                    let label = egui::SelectableLabel::new(selected, &vehicle.name);
+                    if ui.add_enabled(unlocked && owned, label).clicked() {
+                        loadout.vehicle = Some(vehicle.name.clone());
+                    }
+                    if !unlocked {
+                        ui.label(egui::RichText::new(locked_hint(&config, &unlock)).color(hud_color(colors.inactive)));
+                    } else if !owned && buy_button(ui, &economy, &progress, &unlock) {
+                        purchases.push(unlock);
+                    }
+                });
+            }
+
+            ui.separator();
+            ui.label(egui::RichText::new(tr!("garage.paint")).strong());
+            ui.horizontal_wrapped(|ui| {
+                for paint in Paint::ALL {
+                    let unlock = Unlock::Paint(paint);
+                    let unlocked = config.is_unlocked(&progress, &unlock);
+                    let stroke = if loadout.paint == paint {
+                        egui::Stroke::new(2.0, egui::Color32::WHITE)
+                    } else {
+                        egui::Stroke::new(1.0, egui::Color32::DARK_GRAY)
+                    };
+                    let swatch =
+                        egui::Button::new("").fill(color32(paint.color())).stroke(stroke).min_size(SWATCH_SIZE);
+                    let hint = if unlocked { tr!(paint.name_key()) } else { locked_hint(&config, &unlock) };
+                    if ui.add_enabled(unlocked, swatch).on_hover_text(&hint).on_disabled_hover_text(&hint).clicked() {
+                        loadout.paint = paint;
+                    }
+                }
+            });
+
+            ui.separator();
+            ui.label(egui::RichText::new(tr!("garage.accessories")).strong());
+            for accessory in Accessory::ALL {
+                let unlock = Unlock::Accessory(accessory);
+                let unlocked = config.is_unlocked(&progress, &unlock);
+                let mut fitted = loadout.accessories.contains(&accessory);
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(unlocked, egui::Checkbox::new(&mut fitted, tr!(accessory.name_key()))).changed() {
+                        if fitted {
+                            loadout.accessories.push(accessory);
+                        } else {
+                            loadout.accessories.retain(|other| *other != accessory);
+                        }
+                    }
+                    if !unlocked {
+                        ui.label(egui::RichText::new(locked_hint(&config, &unlock)).color(hud_color(colors.inactive)));
+                    }
+                });
+            }
+
+            ui.separator();
+            let parts = &mut loadout.customization;
+            let mut pick = |ui: &mut egui::Ui, label, slot, picked| {
+                part_picker(ui, &config, &economy, &progress, label, slot, picked, &mut purchases)
+            };
+            let tires = TireType::ALL.map(Part::Tires);
+            if let Some(Part::Tires(picked)) = pick(ui, tr!("garage.tires"), tires, Part::Tires(parts.tires)) {
+                parts.tires = picked;
+            }
+            let lifts = LiftKit::ALL.map(Part::LiftKit);
+            if let Some(Part::LiftKit(picked)) = pick(ui, tr!("garage.lift"), lifts, Part::LiftKit(parts.lift)) {
+                parts.lift = picked;
+            }
+            let bumpers = Bumper::ALL.map(Part::Bumper);
+            if let Some(Part::Bumper(picked)) = pick(ui, tr!("garage.bumper"), bumpers, Part::Bumper(parts.bumper)) {
+                parts.bumper = picked;
+            }
+
+            ui.separator();
+            ui.label(egui::RichText::new(tr!("garage.applies_on_restart")).small());
+        });
+
+    if progress.loadout != loadout {
+        progress.loadout = loadout;
+    }
+    for unlock in purchases {
+        purchase_events.send(PurchaseEvent { unlock });
+    }
+    if !open {
+        ui_state.show_garage = false;
+    }
+}
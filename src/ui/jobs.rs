@@ -0,0 +1,121 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use super::{MarkerKind, WorldMarker};
+use crate::game::{
+    board_at, AbandonJobEvent, AcceptJobEvent, CargoItem, GameMode, GameState, Job, JobBoards, JobCondition, JobDropOff,
+    PlayerId, PointOfInterest, Vehicle,
+};
+use crate::tr;
+
+/// Description of a job's condition
+fn condition_text(condition: JobCondition) -> String {
+    match condition {
+        JobCondition::TimeLimit(seconds) => tr!(condition.name_key(), time = clock(seconds)),
+        JobCondition::MaxDamage(share) => tr!(condition.name_key(), percent = (share * 100.0).round() as u32),
+        JobCondition::AvoidPaved => tr!(condition.name_key()),
+    }
+}
+
+/// What goes where, as a job's heading
+fn job_heading(job: &Job) -> String {
+    tr!("jobs.deliver", cargo = tr!(job.cargo.name_key()), name = job.destination.clone())
+}
+
+/// Seconds as m:ss
+fn clock(seconds: f32) -> String {
+    let seconds = seconds.ceil() as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Job board of the point of interest player one is parked at, while they're free to take a job
+pub(super) fn job_board(
+    mut contexts: EguiContexts,
+    boards: Res<JobBoards>,
+    session: Option<Res<GameState>>,
+    points: Query<&PointOfInterest>,
+    players: Query<(&PlayerId, &GlobalTransform), With<Vehicle>>,
+    mut requests: EventWriter<AcceptJobEvent>,
+) {
+    let free_roaming = session.map_or(true, |session| matches!(session.mode, GameMode::FreeRoam | GameMode::Jobs));
+    if boards.active().is_some() || !free_roaming {
+        return;
+    }
+    let Some((_, transform)) = players.iter().find(|(player, _)| player.0 == 0) else {
+        return;
+    };
+    let position = transform.translation();
+    let Some(board) = board_at(&points, position) else {
+        return;
+    };
+
+    egui::Window::new(tr!("jobs.board", name = board.name.clone()))
+        .id(egui::Id::new("job_board"))
+        .anchor(egui::Align2::RIGHT_CENTER, [-16.0, 0.0])
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let jobs = boards.board(&board.name);
+            if jobs.is_empty() {
+                ui.weak(tr!("jobs.none"));
+            }
+            for job in jobs {
+                let distance = format!("{:.1}", job.destination_position.distance(position) / 1000.0);
+                ui.group(|ui| {
+                    ui.label(egui::RichText::new(job_heading(job)).strong());
+                    ui.label(tr!("jobs.distance", km = distance));
+                    match job.condition {
+                        Some(condition) => ui.label(condition_text(condition)),
+                        None => ui.weak(tr!("jobs.condition.none")),
+                    };
+                    ui.horizontal(|ui| {
+                        ui.label(tr!("jobs.payout", payout = job.payout));
+                        if ui.button(tr!("jobs.accept")).clicked() {
+                            requests.send(AcceptJobEvent { job: job.id });
+                        }
+                    });
+                });
+            }
+        });
+}
+
+/// The job being worked: where the cargo goes, how it's holding up and what's left of the condition
+pub(super) fn active_job_panel(
+    mut contexts: EguiContexts,
+    boards: Res<JobBoards>,
+    cargo: Query<&CargoItem>,
+    mut abandon: EventWriter<AbandonJobEvent>,
+) {
+    let Some(active) = boards.active() else {
+        return;
+    };
+    let integrity = cargo.get(active.cargo).map_or(0.0, |item| item.integrity);
+
+    egui::Window::new(tr!("jobs.active"))
+        .id(egui::Id::new("active_job"))
+        .anchor(egui::Align2::RIGHT_TOP, [-16.0, 120.0])
+        .resizable(false)
+        .collapsible(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(job_heading(&active.job));
+            ui.label(tr!("jobs.cargo_condition", percent = (integrity * 100.0).round() as u32));
+            match (active.time_left(), active.job.condition) {
+                (Some(left), _) => ui.label(tr!("jobs.time_left", time = clock(left))),
+                (None, Some(condition)) => ui.label(condition_text(condition)),
+                (None, None) => ui.label(tr!("jobs.payout", payout = active.job.payout)),
+            };
+            if ui.button(tr!("jobs.abandon")).clicked() {
+                abandon.send(AbandonJobEvent);
+            }
+        });
+}
+
+/// Floats a marker over new job drop-offs
+pub(super) fn mark_job_drop_offs(mut commands: Commands, drop_offs: Query<Entity, Added<JobDropOff>>) {
+    for entity in drop_offs.iter() {
+        commands.entity(entity).insert(WorldMarker {
+            kind: MarkerKind::Checkpoint,
+            label: tr!("jobs.drop_off"),
+            height: 3.0,
+        });
+    }
+}
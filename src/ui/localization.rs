@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+use bevy::asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::utils::BoxedFuture;
+use bevy_egui::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::assets::{check_schema_version, read_schema_version, SchemaVersionError};
+use crate::game::GameSettings;
+
+/// Languages shipped in `assets/locales`
+pub const LANGUAGES: &[&str] = &["en", "de", "ja"];
+/// Language used for anything a locale doesn't translate, built in so text shows before assets load
+const FALLBACK_LANGUAGE: &str = "en";
+const FALLBACK_LOCALE: &str = include_str!("../../assets/locales/en.locale.json");
+
+/// UI strings of one language, loaded from `locales/<code>.locale.json`
+#[derive(Asset, TypePath, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Locale {
+    /// Name of the language in that language, for the language picker
+    pub name: String,
+    /// Font files from [`GameAssets`](crate::assets::GameAssets) covering the script, added behind the default fonts
+    #[serde(default)]
+    pub fonts: Vec<String>,
+    pub strings: HashMap<String, String>,
+}
+
+/// Errors produced while loading locale files
+#[derive(Debug, thiserror::Error)]
+pub enum LocaleError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Schema(#[from] SchemaVersionError),
+}
+
+/// Asset loader for locale files
+#[derive(Default)]
+pub struct LocaleLoader;
+
+impl AssetLoader for LocaleLoader {
+    type Asset = Locale;
+    type Settings = ();
+    type Error = LocaleError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Locale, LocaleError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            check_schema_version(read_schema_version(&bytes)?)?;
+            Ok(serde_json::from_slice(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["locale.json"]
+    }
+}
+
+/// Locale assets by language code, and the language currently shown
+#[derive(Resource, Debug, Default)]
+pub struct Localization {
+    pub locales: HashMap<String, Handle<Locale>>,
+    pub active: Option<String>,
+    /// Fallback fonts the active language needs
+    pub fonts: Vec<String>,
+}
+
+/// Fills `{name}` placeholders in `template`
+pub fn format_template(template: &str, args: &[(&str, String)]) -> String {
+    args.iter()
+        .fold(template.to_string(), |text, (name, value)| text.replace(&format!("{{{name}}}"), value))
+}
+
+fn fallback_strings() -> &'static HashMap<String, String> {
+    static FALLBACK: OnceLock<HashMap<String, String>> = OnceLock::new();
+    FALLBACK.get_or_init(|| {
+        serde_json::from_str::<Locale>(FALLBACK_LOCALE)
+            .map(|locale| locale.strings)
+            .unwrap_or_default()
+    })
+}
+
+/// Strings of the active language. Global so `tr!` works anywhere without threading a resource through.
+fn active_strings() -> &'static RwLock<HashMap<String, String>> {
+    static ACTIVE: OnceLock<RwLock<HashMap<String, String>>> = OnceLock::new();
+    ACTIVE.get_or_init(Default::default)
+}
+
+fn set_active_strings(strings: HashMap<String, String>) {
+    *active_strings().write().unwrap_or_else(|poisoned| poisoned.into_inner()) = strings;
+}
+
+/// Text for `key` in the active language, falling back to English and then to the key itself
+pub fn translate(key: &str) -> String {
+    translate_with(key, &[])
+}
+
+/// [`translate`] with `{name}` placeholders filled from `args`
+pub fn translate_with(key: &str, args: &[(&str, String)]) -> String {
+    let active = active_strings().read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let template = active
+        .get(key)
+        .or_else(|| fallback_strings().get(key))
+        .map_or(key, String::as_str);
+    format_template(template, args)
+}
+
+/// Translated UI text, `tr!("hud.fuel", liters = level)` fills `{liters}` in the string
+#[macro_export]
+macro_rules! tr {
+    ($key:expr) => {
+        $crate::ui::translate($key)
+    };
+    ($key:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::ui::translate_with($key, &[$((stringify!($name), $value.to_string())),+])
+    };
+}
+
+fn load_locales(asset_server: Res<AssetServer>, mut localization: ResMut<Localization>) {
+    for language in LANGUAGES {
+        let handle = asset_server.load(format!("locales/{language}.locale.json"));
+        localization.locales.insert(language.to_string(), handle);
+    }
+}
+
+/// Switches the strings over when the language setting changes or its locale file is (re)loaded
+fn switch_language(
+    game_settings: Option<Res<GameSettings>>,
+    locales: Res<Assets<Locale>>,
+    mut locale_events: EventReader<AssetEvent<Locale>>,
+    mut localization: ResMut<Localization>,
+) {
+    let language = game_settings.map_or(FALLBACK_LANGUAGE.to_string(), |settings| settings.language.clone());
+    let handle = localization.locales.get(&language).cloned();
+    let reloaded = locale_events.read().any(|event| {
+        handle.as_ref().map_or(false, |handle| {
+            event.is_loaded_with_dependencies(handle.id()) || event.is_modified(handle.id())
+        })
+    });
+    if localization.active.as_deref() == Some(language.as_str()) && !reloaded {
+        return;
+    }
+
+    let Some(handle) = handle else {
+        warn!("No locale for language '{language}', showing {FALLBACK_LANGUAGE}");
+        set_active_strings(HashMap::new());
+        localization.fonts.clear();
+        localization.active = Some(language);
+        return;
+    };
+    // Try again once it has loaded
+    let Some(locale) = locales.get(&handle) else {
+        return;
+    };
+    set_active_strings(locale.strings.clone());
+    localization.fonts = locale.fonts.clone();
+    localization.active = Some(language);
+}
+
+/// Combo box choosing the UI language from the loaded locales
+pub fn language_picker(ui: &mut egui::Ui, localization: &Localization, locales: &Assets<Locale>, language: &mut String) {
+    let name = |code: &str| {
+        localization
+            .locales
+            .get(code)
+            .and_then(|handle| locales.get(handle))
+            .map_or(code.to_string(), |locale| locale.name.clone())
+    };
+    egui::ComboBox::from_label(crate::tr!("menu.language"))
+        .selected_text(name(language))
+        .show_ui(ui, |ui| {
+            for code in LANGUAGES {
+                ui.selectable_value(language, code.to_string(), name(code));
+            }
+        });
+}
+
+pub(super) fn build(app: &mut App) {
+    app.init_asset::<Locale>()
+        .init_asset_loader::<LocaleLoader>()
+        .init_resource::<Localization>()
+        .add_systems(Startup, load_locales)
+        .add_systems(Update, (switch_language, super::theme::apply_fonts).chain());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn shipped_locale(language: &str) -> Locale {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("assets/locales/{language}.locale.json"));
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_placeholders_are_filled() {
+        let text = format_template("{speaker}: {text}", &[("speaker", "Base".to_string()), ("text", "Copy".to_string())]);
+        assert_eq!(text, "Base: Copy");
+        assert_eq!(format_template("Fuel: {liters} L", &[]), "Fuel: {liters} L");
+    }
+
+    #[test]
+    fn test_missing_keys_fall_back() {
+        assert_eq!(translate_with("hud.fuel", &[("liters", "12.5".to_string())]), "Fuel: 12.5 L");
+        assert_eq!(translate("no.such.key"), "no.such.key");
+    }
+
+    #[test]
+    fn test_shipped_locales_are_complete() {
+        let english = shipped_locale(FALLBACK_LANGUAGE);
+        for language in LANGUAGES {
+            let locale = shipped_locale(language);
+            for (key, text) in &english.strings {
+                let translated = locale.strings.get(key).unwrap_or_else(|| panic!("{language} is missing {key}"));
+                // Placeholders have to survive translation
+                for placeholder in text.split('{').skip(1).filter_map(|part| part.split_once('}')) {
+                    assert!(translated.contains(&format!("{{{}}}", placeholder.0)), "{language} {key} lost {{{}}}", placeholder.0);
+                }
+            }
+        }
+    }
+}
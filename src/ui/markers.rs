@@ -0,0 +1,338 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use bevy_rapier3d::prelude::*;
+
+use super::{hud_color, UiState, UiTheme};
+use crate::core::GameState;
+use crate::game::{
+    ChatHistory, DirectorState, GameCamera, GameSettings, NetcodeSettings, NetworkedVehicle, PlayerId, PointOfInterest,
+    RecoveryGear, RecoveryGearConfig, Vehicle, VehicleRoute, Winch,
+};
+use crate::tr;
+
+/// Height above a vehicle's or point's origin the marker floats at
+const MARKER_HEIGHT: f32 = 2.0;
+/// Points of interest farther than this aren't marked
+const POI_RANGE: f32 = 400.0;
+/// Distance from the viewport edge, in points, that off-screen arrows are kept at
+const EDGE_MARGIN: f32 = 36.0;
+/// Hits this close in front of a marker don't hide it, so the ground a point sits on doesn't
+const OCCLUSION_SLACK: f32 = 1.0;
+
+/// What a world marker points at, deciding its icon and colour
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkerKind {
+    Checkpoint,
+    PointOfInterest,
+    WinchAnchor,
+    Teammate,
+}
+
+/// Marks an entity on screen for as long as it has this, for markers the built-in ones don't cover
+#[derive(Component, Debug, Clone)]
+pub struct WorldMarker {
+    pub kind: MarkerKind,
+    pub label: String,
+    /// Height above the entity's origin the marker floats at
+    pub height: f32,
+}
+
+/// Vehicles as seen by the markers: the followed one's route and recovery gear, everyone else's owner
+type MarkerVehicles<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static GlobalTransform,
+        Option<&'static VehicleRoute>,
+        Option<&'static Winch>,
+        Option<&'static RecoveryGear>,
+        Option<&'static ImpulseJoint>,
+        Option<&'static NetworkedVehicle>,
+        Option<&'static PlayerId>,
+    ),
+    With<Vehicle>,
+>;
+
+/// One marker to draw this frame
+#[derive(Debug, Clone)]
+struct MarkerTarget {
+    kind: MarkerKind,
+    label: String,
+    position: Vec3,
+    /// Entity the marker is anchored to, ignored by the occlusion check
+    entity: Option<Entity>,
+}
+
+/// Where a marker ends up on screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MarkerPlacement {
+    OnScreen(Vec2),
+    /// Off screen or behind the camera, an arrow on the edge pointing along `direction`
+    Edge { position: Vec2, direction: Vec2 },
+}
+
+/// Point where a ray from the centre of a `size` viewport along `direction` leaves it, kept `margin`
+/// inside the edge
+fn edge_position(size: Vec2, margin: f32, direction: Vec2) -> Vec2 {
+    let center = size * 0.5;
+    let half = (center - Vec2::splat(margin)).max(Vec2::ZERO);
+    let direction = direction.try_normalize().unwrap_or(Vec2::Y);
+    // Whichever edge the ray reaches first
+    let scale_x = if direction.x.abs() > f32::EPSILON { half.x / direction.x.abs() } else { f32::INFINITY };
+    let scale_y = if direction.y.abs() > f32::EPSILON { half.y / direction.y.abs() } else { f32::INFINITY };
+    center + direction * scale_x.min(scale_y)
+}
+
+/// Places a marker given where the camera projects it, `viewport`, and its position in view space
+fn place_marker(viewport: Option<Vec2>, view: Vec3, size: Vec2, margin: f32) -> MarkerPlacement {
+    let inside = Rect::from_corners(Vec2::splat(margin), size - Vec2::splat(margin));
+    match viewport {
+        // The camera looks down -Z, anything at or past its plane projects mirrored
+        Some(position) if view.z < 0.0 && inside.contains(position) => MarkerPlacement::OnScreen(position),
+        _ => {
+            // View space is y up, the screen is y down
+            let direction = Vec2::new(view.x, -view.y);
+            MarkerPlacement::Edge { position: edge_position(size, margin, direction), direction }
+        }
+    }
+}
+
+/// Display name of `peer`, the last one they chatted under if any
+fn peer_name(history: Option<&ChatHistory>, peer: u32) -> String {
+    history
+        .and_then(|history| history.entries().rev().find(|entry| entry.message.peer == peer))
+        .map_or_else(|| tr!("markers.teammate", peer = peer), |entry| entry.message.sender.clone())
+}
+
+/// Gathers this frame's markers: the next checkpoint of the followed vehicle's route, points of interest,
+/// vehicles its winch can reach, teammates and anything with a [`WorldMarker`]
+fn collect_markers(
+    followed: Entity,
+    vehicles: &MarkerVehicles,
+    pois: &Query<&PointOfInterest>,
+    custom: &Query<(Entity, &WorldMarker, &GlobalTransform)>,
+    recovery: Option<&RecoveryGearConfig>,
+    netcode: Option<&NetcodeSettings>,
+    chat: Option<&ChatHistory>,
+) -> Vec<MarkerTarget> {
+    let mut markers = Vec::new();
+    let Ok((_, transform, route, winch, gear, ..)) = vehicles.get(followed) else {
+        return markers;
+    };
+    let origin = transform.translation();
+
+    if let Some(waypoint) = route.and_then(VehicleRoute::next_waypoint) {
+        markers.push(MarkerTarget {
+            kind: MarkerKind::Checkpoint,
+            label: tr!("markers.checkpoint"),
+            position: waypoint + Vec3::Y * MARKER_HEIGHT,
+            entity: None,
+        });
+    }
+
+    for poi in pois.iter() {
+        let position = Vec3::from(poi.desc.position);
+        if position.distance(origin) <= POI_RANGE {
+            markers.push(MarkerTarget {
+                kind: MarkerKind::PointOfInterest,
+                label: poi.desc.name.clone(),
+                position: position + Vec3::Y * MARKER_HEIGHT,
+                entity: None,
+            });
+        }
+    }
+
+    // Same rules as hooking up the strap: a free strap, and targets not already on a joint
+    let can_strap = winch.is_some() && gear.is_some_and(|gear| gear.strapped_to.is_none() && gear.tow_straps > 0);
+    let strap_range = recovery.map_or(RecoveryGearConfig::default().strap_range, |config| config.strap_range);
+    for (entity, other, _, _, _, joint, networked, player) in vehicles.iter() {
+        if entity == followed {
+            continue;
+        }
+        let position = other.translation();
+        let teammate = match (networked, player) {
+            (Some(networked), _) if netcode.is_some_and(|netcode| networked.owner != netcode.local_peer) => {
+                Some(peer_name(chat, networked.owner))
+            }
+            (_, Some(player)) => Some(tr!("markers.teammate", peer = player.0 + 1)),
+            _ => None,
+        };
+        if let Some(label) = teammate {
+            markers.push(MarkerTarget {
+                kind: MarkerKind::Teammate,
+                label,
+                position: position + Vec3::Y * MARKER_HEIGHT,
+                entity: Some(entity),
+            });
+        }
+        if can_strap && joint.is_none() && position.distance(origin) <= strap_range {
+            markers.push(MarkerTarget {
+                kind: MarkerKind::WinchAnchor,
+                label: tr!("markers.winch_anchor"),
+                position: position + Vec3::Y * MARKER_HEIGHT * 0.5,
+                entity: Some(entity),
+            });
+        }
+    }
+
+    for (entity, marker, transform) in custom.iter() {
+        markers.push(MarkerTarget {
+            kind: marker.kind,
+            label: marker.label.clone(),
+            position: transform.translation() + Vec3::Y * marker.height,
+            entity: Some(entity),
+        });
+    }
+    markers
+}
+
+/// Whether terrain or other fixed colliders stand between the camera and `target`
+fn occluded(rapier_context: &RapierContext, camera: Vec3, target: &MarkerTarget) -> bool {
+    let offset = target.position - camera;
+    let distance = offset.length();
+    if distance <= OCCLUSION_SLACK {
+        return false;
+    }
+    let mut filter = QueryFilter::only_fixed();
+    if let Some(entity) = target.entity {
+        filter = filter.exclude_collider(entity);
+    }
+    rapier_context
+        .cast_ray(camera, offset / distance, distance - OCCLUSION_SLACK, true, filter)
+        .is_some()
+}
+
+/// Arrows, names and distances of checkpoints, points of interest, winch anchors and teammates, drawn
+/// over the 3D view of the main camera. Markers hidden behind terrain are left out while they're on
+/// screen, arrows at the edge for markers off screen always show.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn world_markers(
+    mut contexts: EguiContexts,
+    cameras: Query<(&Camera, &GlobalTransform, &GameCamera)>,
+    vehicles: MarkerVehicles,
+    pois: Query<&PointOfInterest>,
+    custom: Query<(Entity, &WorldMarker, &GlobalTransform)>,
+    recovery: Option<Res<RecoveryGearConfig>>,
+    netcode: Option<Res<NetcodeSettings>>,
+    chat: Option<Res<ChatHistory>>,
+    rapier_context: Option<Res<RapierContext>>,
+    state: Res<State<GameState>>,
+    ui_state: Res<UiState>,
+    director: Option<Res<DirectorState>>,
+    game_settings: Option<Res<GameSettings>>,
+    theme: Res<UiTheme>,
+) {
+    let directing = director.map_or(false, |director| director.active);
+    if state.get() != &GameState::Playing || ui_state.show_menu || directing {
+        return;
+    }
+    let active = cameras.iter().filter(|(camera, ..)| camera.is_active);
+    let Some((camera, camera_transform, game_camera)) = active.max_by_key(|(camera, ..)| camera.order) else {
+        return;
+    };
+    let (Some(followed), Some(viewport)) = (game_camera.target, camera.logical_viewport_rect()) else {
+        return;
+    };
+
+    let markers = collect_markers(
+        followed,
+        &vehicles,
+        &pois,
+        &custom,
+        recovery.as_deref(),
+        netcode.as_deref(),
+        chat.as_deref(),
+    );
+    if markers.is_empty() {
+        return;
+    }
+
+    let colors = game_settings.map(|settings| settings.accessibility.hud_palette).unwrap_or_default().colors();
+    let eye = camera_transform.translation();
+    let to_view = camera_transform.affine().inverse();
+    let painter = contexts.ctx_mut().layer_painter(egui::LayerId::background());
+    let font = egui::FontId::proportional(theme.text_size * 0.9);
+    let outline = egui::Stroke::new(1.5, hud_color(theme.panel));
+
+    for marker in &markers {
+        let placement = place_marker(
+            camera.world_to_viewport(camera_transform, marker.position),
+            to_view.transform_point3(marker.position),
+            viewport.size(),
+            EDGE_MARGIN,
+        );
+        if matches!(placement, MarkerPlacement::OnScreen(_))
+            && rapier_context.as_ref().is_some_and(|context| occluded(context, eye, marker))
+        {
+            continue;
+        }
+
+        let color = match marker.kind {
+            MarkerKind::Checkpoint => theme.accent(),
+            MarkerKind::PointOfInterest => hud_color(colors.info),
+            MarkerKind::WinchAnchor => hud_color(colors.good),
+            MarkerKind::Teammate => theme.text(),
+        };
+        let distance = tr!("markers.distance", distance = format!("{:.0}", marker.position.distance(eye)));
+        match placement {
+            MarkerPlacement::OnScreen(position) => {
+                let center = egui::pos2(viewport.min.x + position.x, viewport.min.y + position.y);
+                let icon = match marker.kind {
+                    // Diamonds for places, circles for vehicles
+                    MarkerKind::Checkpoint | MarkerKind::PointOfInterest => {
+                        let points = [(0.0, -8.0), (8.0, 0.0), (0.0, 8.0), (-8.0, 0.0)]
+                            .map(|(x, y)| center + egui::vec2(x, y))
+                            .to_vec();
+                        egui::Shape::convex_polygon(points, color, outline)
+                    }
+                    MarkerKind::WinchAnchor | MarkerKind::Teammate => egui::Shape::circle_filled(center, 6.0, color),
+                };
+                painter.add(icon);
+                let text = format!("{}\n{distance}", marker.label);
+                painter.text(center - egui::vec2(0.0, 12.0), egui::Align2::CENTER_BOTTOM, text, font.clone(), color);
+            }
+            MarkerPlacement::Edge { position, direction } => {
+                let center = egui::pos2(viewport.min.x + position.x, viewport.min.y + position.y);
+                let forward = direction.try_normalize().unwrap_or(Vec2::Y);
+                let (forward, side) = (egui::vec2(forward.x, forward.y), egui::vec2(-forward.y, forward.x));
+                let base = center - forward * 6.0;
+                let arrow = vec![center + forward * 12.0, base + side * 8.0, base - side * 8.0];
+                painter.add(egui::Shape::convex_polygon(arrow, color, outline));
+                // Label on the inward side of the arrow so it stays on screen
+                let anchor = center - forward * 18.0;
+                painter.text(anchor, egui::Align2::CENTER_CENTER, distance, font.clone(), color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edge_position() {
+        let size = Vec2::new(800.0, 600.0);
+        assert_eq!(edge_position(size, 20.0, Vec2::X), Vec2::new(780.0, 300.0));
+        assert_eq!(edge_position(size, 20.0, -Vec2::Y), Vec2::new(400.0, 20.0));
+        // Diagonals hit the nearer edge, the bottom on a wide screen
+        let corner = edge_position(size, 0.0, Vec2::ONE);
+        assert!((corner - Vec2::new(700.0, 600.0)).length() < 1e-3);
+    }
+
+    #[test]
+    fn test_place_marker() {
+        let size = Vec2::new(800.0, 600.0);
+        let ahead = place_marker(Some(Vec2::new(400.0, 300.0)), Vec3::new(0.0, 0.0, -10.0), size, 20.0);
+        assert_eq!(ahead, MarkerPlacement::OnScreen(Vec2::new(400.0, 300.0)));
+
+        // Projects to the left of the screen but is off it
+        let left = place_marker(Some(Vec2::new(-50.0, 300.0)), Vec3::new(-20.0, 0.0, -10.0), size, 20.0);
+        assert_eq!(left, MarkerPlacement::Edge { position: Vec2::new(20.0, 300.0), direction: Vec2::new(-20.0, 0.0) });
+
+        // Behind and to the right, the mirrored projection is ignored and the arrow points right
+        let behind = place_marker(Some(Vec2::new(300.0, 300.0)), Vec3::new(5.0, 0.0, 10.0), size, 20.0);
+        assert!(matches!(behind, MarkerPlacement::Edge { position, .. } if position == Vec2::new(780.0, 300.0)));
+    }
+}
@@ -0,0 +1,57 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::game::{DebugInfo, MemoryBudgetConfig, MemoryCategory, MemoryPressure, MemoryUsage};
+use crate::tr;
+
+const MEGABYTE: f32 = 1024.0 * 1024.0;
+
+fn pressure_color(pressure: MemoryPressure) -> egui::Color32 {
+    match pressure {
+        MemoryPressure::Normal => egui::Color32::from_rgb(80, 170, 90),
+        MemoryPressure::Nearing => egui::Color32::from_rgb(220, 170, 40),
+        MemoryPressure::Over => egui::Color32::from_rgb(210, 60, 50),
+    }
+}
+
+/// Memory per category against its budget, opened and closed with the memory key (F9)
+pub(super) fn memory_overlay(
+    mut contexts: EguiContexts,
+    mut debug_info: ResMut<DebugInfo>,
+    config: Res<MemoryBudgetConfig>,
+    usage: Res<MemoryUsage>,
+) {
+    if !debug_info.show_memory {
+        return;
+    }
+
+    let mut open = true;
+    egui::Window::new(tr!("memory.title"))
+        .id(egui::Id::new("memory"))
+        .open(&mut open)
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            for category in MemoryCategory::ALL {
+                let used = usage.bytes(category) as f32;
+                let budget = config.budget(category).max(1) as f32;
+                ui.label(tr!(category.name_key()));
+                let text = tr!(
+                    "memory.usage",
+                    used = format!("{:.1}", used / MEGABYTE),
+                    budget = format!("{:.0}", budget / MEGABYTE)
+                );
+                ui.add(
+                    egui::ProgressBar::new((used / budget).min(1.0))
+                        .text(text)
+                        .fill(pressure_color(usage.pressure(category))),
+                );
+            }
+            ui.separator();
+            ui.label(tr!("memory.total", used = format!("{:.1}", usage.total_bytes() as f32 / MEGABYTE)));
+        });
+
+    if !open {
+        debug_info.show_memory = false;
+    }
+}
@@ -1,7 +1,58 @@
 use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
 use bevy_egui::{egui, EguiContexts, EguiPlugin};
-use crate::game::Vehicle;
+use crate::game::{
+    split_viewport, DirectorState, DriverAssists, Drivetrain, EconomyConfig, EngineTemperature, EngineThermalConfig,
+    FastTravel, FuelConfig, FuelTank, GameSettings, HudColors, JobBoards, MemoryUsage, MirrorQuality,
+    PendingCrashReports, PlayerId, ProgressionConfig, RearViewMirror, SplitScreenSettings, TransferCase, Tutorial,
+    Vehicle, SPEEDOMETER_FULL_SCALE,
+};
+use crate::game::{configure_game_sets, DebugInfo, GameSet, ProfileGroup};
+use crate::audio::RadioMessageEvent;
+use crate::physics::PhysicsDebugSettings;
+use crate::rendering::ShaderErrors;
 use crate::core::GameState;
+use crate::game::states::GameProgress;
+use crate::tr;
+
+mod accessibility;
+mod chat;
+mod crash_dialog;
+mod director;
+mod fast_travel;
+mod garage;
+mod jobs;
+mod localization;
+mod markers;
+mod memory;
+mod notifications;
+mod physics_debug;
+mod profiler;
+mod recovery_menu;
+mod session_browser;
+mod session_summary;
+mod shader_errors;
+mod theme;
+mod trail_map;
+mod trail_tool;
+mod tutorial;
+mod voice_chat;
+mod widgets;
+
+pub use accessibility::{hud_color, Subtitle, Subtitles};
+pub use tutorial::binding_label;
+pub use notifications::{Notification, NotificationKind, NotificationPriority, Notifications, ShownNotification};
+pub use localization::{
+    format_template, language_picker, translate, translate_with, Locale, LocaleError, LocaleLoader, Localization, LANGUAGES,
+};
+pub use markers::{MarkerKind, WorldMarker};
+pub use theme::UiTheme;
+pub use widgets::{inclination, GaugeBar, Inclinometer, Speedometer};
+
+/// Tilt in radians past which the inclinometer warns, 25°
+const TILT_WARNING: f32 = 0.436;
+/// Tilt in radians past which the inclinometer shows danger, 35°
+const TILT_DANGER: f32 = 0.611;
 
 pub struct UiPlugin;
 
@@ -9,43 +60,285 @@ impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(EguiPlugin)
             .init_resource::<UiState>()
+            .init_resource::<UiTheme>()
+            .init_resource::<Subtitles>()
+            .init_resource::<Notifications>()
+            .add_event::<RadioMessageEvent>()
             .add_systems(Update, (
-                update_hud,
+                theme::apply_theme,
+                (update_hud, markers::world_markers),
+                show_rear_view_mirror.run_if(resource_exists::<RearViewMirror>()),
                 handle_menu_interactions,
-            ));
+                (accessibility::queue_subtitles, accessibility::show_subtitles).chain(),
+                (
+                    notifications::notify_connections,
+                    notifications::notify_damage,
+                    notifications::notify_challenges,
+                    notifications::notify_hazards,
+                    notifications::notify_trails,
+                    notifications::notify_traction_hints,
+                    notifications::notify_missions,
+                    notifications::notify_exports,
+                    notifications::notify_progression,
+                    notifications::notify_fast_travel,
+                    notifications::notify_transactions,
+                    notifications::notify_jobs,
+                    notifications::notify_recovery,
+                    notifications::show_notifications,
+                ).chain(),
+                (
+                    accessibility::apply_text_scale,
+                    accessibility::accessibility_menu,
+                ).run_if(resource_exists::<GameSettings>()),
+                crash_dialog::crash_report_dialog.run_if(resource_exists::<PendingCrashReports>()),
+                tutorial::tutorial_prompt.run_if(resource_exists::<Tutorial>()),
+                (recovery_menu::recovery_menu, recovery_menu::recovery_fade_overlay),
+                trail_map::trail_map,
+                (fast_travel::fast_travel_window, fast_travel::fast_travel_overlay)
+                    .run_if(resource_exists::<FastTravel>()),
+                garage::garage_window
+                    .run_if(resource_exists::<ProgressionConfig>().and_then(resource_exists::<EconomyConfig>())),
+                (jobs::job_board, jobs::active_job_panel, jobs::mark_job_drop_offs)
+                    .run_if(resource_exists::<JobBoards>()),
+                voice_chat::voice_chat_menu,
+                session_browser::session_browser,
+                (chat::chat_overlay, chat::quick_chat_menu),
+                (director::director_window, director::export_progress),
+                (trail_tool::trail_tool_window, trail_tool::pick_trail_points).chain(),
+                (
+                    physics_debug::physics_debug_panel.run_if(resource_exists::<PhysicsDebugSettings>()),
+                    profiler::profiler_overlay,
+                    memory::memory_overlay.run_if(resource_exists::<MemoryUsage>()),
+                ).run_if(resource_exists::<DebugInfo>()),
+                shader_errors::shader_error_console.run_if(resource_exists::<ShaderErrors>()),
+            ).in_set(GameSet::CameraUi).in_set(ProfileGroup::Ui))
+            .add_systems(
+                Update,
+                session_summary::session_summary.run_if(in_state(GameState::GameOver)).in_set(GameSet::CameraUi),
+            );
+        configure_game_sets(app);
+        localization::build(app);
     }
 }
 
 #[derive(Resource, Default)]
 pub struct UiState {
     pub show_menu: bool,
+    pub show_accessibility: bool,
+    pub show_recovery: bool,
+    pub show_trail_map: bool,
+    pub show_voice_chat: bool,
+    pub show_session_browser: bool,
+    pub show_director: bool,
+    pub show_garage: bool,
+    pub show_trail_tool: bool,
 }
 
+#[allow(clippy::too_many_arguments)]
 fn update_hud(
     mut contexts: EguiContexts,
-    vehicle_query: Query<&Vehicle>,
+    vehicle_query: Query<(
+        &Vehicle,
+        &GlobalTransform,
+        Option<&PlayerId>,
+        Option<&FuelTank>,
+        Option<&EngineTemperature>,
+        Option<&DriverAssists>,
+        Option<&Drivetrain>,
+    )>,
+    fuel_config: Option<Res<FuelConfig>>,
+    thermal_config: Option<Res<EngineThermalConfig>>,
+    split_screen: Option<Res<SplitScreenSettings>>,
+    game_settings: Option<Res<GameSettings>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
     state: Res<State<GameState>>,
     ui_state: Res<UiState>,
+    director: Option<Res<DirectorState>>,
+    progress: Option<Res<GameProgress>>,
+    theme: Res<UiTheme>,
 ) {
-    if state.get() != &GameState::Playing || ui_state.show_menu {
+    let directing = director.map_or(false, |director| director.active);
+    if state.get() != &GameState::Playing || ui_state.show_menu || directing {
         return;
     }
 
-    egui::Window::new("HUD")
-        .fixed_pos((10.0, 10.0))
-        .show(contexts.ctx_mut(), |ui| {
-            if let Ok(vehicle) = vehicle_query.get_single() {
-                let speed_percentage = (vehicle.speed / vehicle.max_speed).min(1.0);
-                ui.add(egui::ProgressBar::new(speed_percentage)
-                    .text(format!("Speed: {:.0} km/h", vehicle.speed * 3.6)));
+    let fuel_enabled = fuel_config.map_or(false, |config| config.enabled);
+    let split = split_screen.filter(|settings| settings.enabled);
+    let colors = game_settings.map(|settings| settings.accessibility.hud_palette).unwrap_or_default().colors();
+    let players = vehicle_query.iter().filter(|(_, player, ..)| player.is_some()).count();
+    let ctx = contexts.ctx_mut();
+
+    // One HUD per player, pinned to the corner of that player's viewport
+    for (vehicle, transform, player, tank, engine, assists, drivetrain) in vehicle_query.iter() {
+        let index = player.map_or(0, |player| player.0);
+        if (split.is_none() && index > 0) || (split.is_some() && player.is_none()) {
+            continue;
+        }
+        let origin = match (&split, windows.get_single()) {
+            (Some(settings), Ok(window)) => {
+                let size = UVec2::new(window.physical_width(), window.physical_height());
+                let (position, _) = split_viewport(settings.layout, index, players, size);
+                position.as_vec2() / window.scale_factor() as f32
             }
+            _ => Vec2::ZERO,
+        };
+        let title = if split.is_some() { tr!("hud.title_player", player = index + 1) } else { tr!("hud.title") };
+
+        egui::Window::new(title)
+            .id(egui::Id::new(("hud", index)))
+            .fixed_pos((origin.x + 10.0, origin.y + 10.0))
+            .show(ctx, |ui| {
+                vehicle_dials(ui, &theme, &colors, vehicle, transform.compute_transform().rotation);
+                let tank = fuel_enabled.then_some(tank).flatten();
+                let running = drivetrain.map_or(true, |drivetrain| drivetrain.running);
+                vehicle_hud(ui, &theme, &colors, tank, engine, running, thermal_config.as_deref());
+                if let Some(assists) = assists {
+                    assist_indicators(ui, &colors, assists);
+                }
+                if let Some(drivetrain) = drivetrain {
+                    drivetrain_indicators(ui, &colors, drivetrain);
+                }
+                // The profile is shared, so its XP shows with player one
+                if let (0, Some(progress)) = (index, &progress) {
+                    garage::xp_bar(ui, &theme, hud_color(colors.info), progress.xp);
+                }
+            });
+
+        if split.is_none() {
+            break;
+        }
+    }
+}
+
+/// On-screen size of the rear-view mirror, the texture behind it shrinks with the mirror quality
+const MIRROR_SIZE: egui::Vec2 = egui::vec2(384.0, 120.0);
+
+/// Rear-view mirror along the top of the screen, flipped like a real mirror
+fn show_rear_view_mirror(
+    mut contexts: EguiContexts,
+    mirror: Res<RearViewMirror>,
+    game_settings: Option<Res<GameSettings>>,
+    state: Res<State<GameState>>,
+    ui_state: Res<UiState>,
+    director: Option<Res<DirectorState>>,
+) {
+    let directing = director.map_or(false, |director| director.active);
+    let enabled = game_settings.map_or(false, |settings| settings.graphics.rear_view_mirror);
+    if !enabled || state.get() != &GameState::Playing || ui_state.show_menu || directing {
+        return;
+    }
+
+    let texture = contexts.add_image(mirror.image.clone_weak());
+    let flipped = egui::Rect::from_min_max(egui::pos2(1.0, 0.0), egui::pos2(0.0, 1.0));
+    egui::Area::new("rear_view_mirror")
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 10.0])
+        .interactable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.add(egui::Image::new(egui::load::SizedTexture::new(texture, MIRROR_SIZE)).uv(flipped));
         });
 }
 
+/// Rear-view mirror toggle and quality
+fn mirror_settings(ui: &mut egui::Ui, enabled: &mut bool, quality: &mut MirrorQuality) {
+    let quality_name = |quality: MirrorQuality| tr!(&format!("menu.mirror_quality.{quality:?}"));
+    ui.checkbox(enabled, tr!("menu.rear_view_mirror"));
+    ui.add_enabled_ui(*enabled, |ui| {
+        egui::ComboBox::from_label(tr!("menu.mirror_quality"))
+            .selected_text(quality_name(*quality))
+            .show_ui(ui, |ui| {
+                for option in MirrorQuality::ALL {
+                    ui.selectable_value(quality, option, quality_name(option));
+                }
+            });
+    });
+}
+
+/// Speedometer and inclinometer of one vehicle, side by side
+fn vehicle_dials(ui: &mut egui::Ui, theme: &UiTheme, colors: &HudColors, vehicle: &Vehicle, rotation: Quat) {
+    let (pitch, roll) = inclination(rotation);
+    ui.horizontal(|ui| {
+        ui.add(
+            Speedometer::new(theme, vehicle.vehicle_speed.abs() * 3.6, SPEEDOMETER_FULL_SCALE * 3.6)
+                .redline(0.85, hud_color(colors.danger))
+                .unit(tr!("hud.speed_unit")),
+        );
+        ui.add(
+            Inclinometer::new(theme, pitch, roll)
+                .warning(TILT_WARNING, hud_color(colors.warning))
+                .danger(TILT_DANGER, hud_color(colors.danger))
+                .labels(tr!("hud.pitch"), tr!("hud.roll")),
+        );
+    });
+}
+
+/// Fuel and temperature gauges of one vehicle
+fn vehicle_hud(
+    ui: &mut egui::Ui,
+    theme: &UiTheme,
+    colors: &HudColors,
+    tank: Option<&FuelTank>,
+    engine: Option<&EngineTemperature>,
+    running: bool,
+    thermal_config: Option<&EngineThermalConfig>,
+) {
+    if let Some(tank) = tank {
+        let fraction = tank.fraction();
+        let color = hud_color(if fraction < 0.15 { colors.danger } else { colors.good });
+        ui.add(GaugeBar::new(theme, fraction)
+            .fill(color)
+            .text(tr!("hud.fuel", liters = format!("{:.1}", tank.level))));
+    }
+    if let (Some(config), Some(engine)) = (thermal_config, engine) {
+        let fraction = ((engine.temperature - config.ambient) / (config.critical - config.ambient)).clamp(0.0, 1.0);
+        let color = hud_color(if engine.temperature >= config.overheat { colors.danger } else { colors.info });
+        let temperature = format!("{:.0}", engine.temperature);
+        let label = if engine.intake_flooded {
+            tr!("hud.intake_flooded")
+        } else if !running && engine.temperature >= config.overheat {
+            tr!("hud.temperature_overheated", temperature = temperature)
+        } else {
+            tr!("hud.temperature", temperature = temperature)
+        };
+        ui.add(GaugeBar::new(theme, fraction).fill(color).text(label));
+    }
+}
+
+/// TCS, ABS and HDC lamps, lit while the assist is intervening
+fn assist_indicators(ui: &mut egui::Ui, colors: &HudColors, assists: &DriverAssists) {
+    ui.horizontal(|ui| {
+        for (label, active) in [
+            ("hud.tcs", assists.traction_control_active),
+            ("hud.abs", assists.abs_active),
+            ("hud.hdc", assists.hill_descent_active),
+        ] {
+            let color = hud_color(if active { colors.warning } else { colors.inactive });
+            ui.label(egui::RichText::new(tr!(label)).color(color).strong());
+        }
+    });
+}
+
+/// Transfer case range with the selected one lit, and the engine state while it isn't running
+fn drivetrain_indicators(ui: &mut egui::Ui, colors: &HudColors, drivetrain: &Drivetrain) {
+    ui.horizontal(|ui| {
+        for range in [TransferCase::TwoHigh, TransferCase::FourHigh, TransferCase::FourLow] {
+            let color = hud_color(if drivetrain.transfer_case == range { colors.info } else { colors.inactive });
+            ui.label(egui::RichText::new(range.label()).color(color).strong());
+        }
+        if drivetrain.cranking.is_some() {
+            ui.label(egui::RichText::new(tr!("hud.cranking")).color(hud_color(colors.warning)).strong());
+        } else if !drivetrain.running {
+            ui.label(egui::RichText::new(tr!("hud.engine_off")).color(hud_color(colors.danger)).strong());
+        }
+    });
+}
+
 fn handle_menu_interactions(
     mut contexts: EguiContexts,
     mut next_state: ResMut<NextState<GameState>>,
     mut ui_state: ResMut<UiState>,
+    game_settings: Option<ResMut<GameSettings>>,
+    localization: Res<Localization>,
+    locales: Res<Assets<Locale>>,
     keyboard: Res<Input<KeyCode>>,
 ) {
     if keyboard.just_pressed(KeyCode::Escape) {
@@ -56,19 +349,79 @@ fn handle_menu_interactions(
         return;
     }
 
-    egui::Window::new("Menu")
+    let mut language = game_settings.as_ref().map(|settings| settings.language.clone());
+    let mut mirror =
+        game_settings.as_ref().map(|settings| (settings.graphics.rear_view_mirror, settings.graphics.mirror_quality));
+    let mut show_driver = game_settings.as_ref().map(|settings| settings.graphics.show_driver);
+    let mut traction_overlay = game_settings.as_ref().map(|settings| settings.assists.traction_overlay);
+    egui::Window::new(tr!("menu.title"))
+        .id(egui::Id::new("menu"))
         .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
         .show(contexts.ctx_mut(), |ui| {
-            if ui.button("Resume").clicked() {
+            if ui.button(tr!("menu.resume")).clicked() {
                 ui_state.show_menu = false;
             }
-            if ui.button("Restart").clicked() {
+            if ui.button(tr!("menu.trail_map")).clicked() {
+                ui_state.show_trail_map = true;
+            }
+            if ui.button(tr!("menu.garage")).clicked() {
+                ui_state.show_garage = true;
+            }
+            if ui.button(tr!("menu.voice_chat")).clicked() {
+                ui_state.show_voice_chat = true;
+            }
+            if ui.button(tr!("menu.multiplayer")).clicked() {
+                ui_state.show_session_browser = true;
+            }
+            if ui.button(tr!("menu.director")).clicked() {
+                ui_state.show_director = true;
+                ui_state.show_menu = false;
+            }
+            if ui.button(tr!("menu.trail_tool")).clicked() {
+                ui_state.show_trail_tool = true;
+                ui_state.show_menu = false;
+            }
+            if ui.button(tr!("menu.accessibility")).clicked() {
+                ui_state.show_accessibility = true;
+            }
+            if let Some(language) = language.as_mut() {
+                language_picker(ui, &localization, &locales, language);
+            }
+            if let Some((enabled, quality)) = mirror.as_mut() {
+                mirror_settings(ui, enabled, quality);
+            }
+            if let Some(show_driver) = show_driver.as_mut() {
+                ui.checkbox(show_driver, tr!("menu.show_driver"));
+            }
+            if let Some(traction_overlay) = traction_overlay.as_mut() {
+                ui.checkbox(traction_overlay, tr!("menu.traction_overlay"));
+            }
+            if ui.button(tr!("menu.restart")).clicked() {
                 next_state.set(GameState::Loading);
                 ui_state.show_menu = false;
             }
-            if ui.button("Quit").clicked() {
+            if ui.button(tr!("menu.quit")).clicked() {
                 next_state.set(GameState::MainMenu);
                 ui_state.show_menu = false;
             }
         });
+
+    let Some(mut settings) = game_settings else {
+        return;
+    };
+    if let Some(language) = language.filter(|language| settings.language != *language) {
+        settings.language = language;
+    }
+    if let Some((enabled, quality)) = mirror {
+        if (settings.graphics.rear_view_mirror, settings.graphics.mirror_quality) != (enabled, quality) {
+            settings.graphics.rear_view_mirror = enabled;
+            settings.graphics.mirror_quality = quality;
+        }
+    }
+    if let Some(show_driver) = show_driver.filter(|show_driver| settings.graphics.show_driver != *show_driver) {
+        settings.graphics.show_driver = show_driver;
+    }
+    if let Some(enabled) = traction_overlay.filter(|enabled| settings.assists.traction_overlay != *enabled) {
+        settings.assists.traction_overlay = enabled;
+    }
 } 
\ No newline at end of file
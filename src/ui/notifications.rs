@@ -0,0 +1,580 @@
+use std::collections::VecDeque;
+
+use bevy::audio::Volume;
+use bevy::input::gamepad::{GamepadConnection, GamepadConnectionEvent};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use super::hud_color;
+use crate::audio::{load_clip, AudioClipCache};
+use crate::game::{
+    CargoDeliveredEvent, CargoDamagedEvent, CargoLostEvent, CrossingStatusEvent, EngineStallReason, EngineStalledEvent,
+    ExportFinishedEvent, FastTravelFinishedEvent, GameSettings, HazardStartedEvent, HazardType, HudColors,
+    ItemUnlockedEvent, JobFinishedEvent, JobOutcome, LevelUpEvent, OutOfFuelEvent, PlayerId, PoiReachedEvent,
+    RadiatorDamageEvent, RecoveryRejectedEvent, RecoveryRejection, ScriptMessageEvent, SteeringWheelDevice,
+    TractionHint, TractionHintEvent, TrailCondition, TrailConditionChangedEvent, TransactionEvent, TransactionKind,
+    Unlock, VehicleUnlockedEvent,
+};
+use crate::tr;
+
+/// Notifications on screen at once, the rest wait their turn
+const MAX_VISIBLE: usize = 4;
+/// Seconds a notification takes to fade out at the end of its duration
+const FADE_TIME: f32 = 0.5;
+/// Radiator hits smaller than this aren't worth telling the player about
+const RADIATOR_WARNING_DAMAGE: f32 = 0.1;
+
+/// What a notification is about, which sets its color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationKind {
+    Discovery,
+    Challenge,
+    Warning,
+    Connection,
+}
+
+impl NotificationKind {
+    pub fn color(self, colors: &HudColors) -> [u8; 3] {
+        match self {
+            NotificationKind::Discovery => colors.info,
+            NotificationKind::Challenge => colors.good,
+            NotificationKind::Warning => colors.danger,
+            NotificationKind::Connection => colors.warning,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum NotificationPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    /// Pushes lower priority notifications off the screen when it's full
+    Critical,
+}
+
+/// A message popped up for the player
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub kind: NotificationKind,
+    pub priority: NotificationPriority,
+    pub title: String,
+    pub message: Option<String>,
+    /// Seconds on screen
+    pub duration: f32,
+    /// Sound played when it shows up, as an asset path
+    pub sound: Option<String>,
+}
+
+impl Notification {
+    pub fn new(kind: NotificationKind, title: impl Into<String>) -> Self {
+        Self {
+            kind,
+            priority: NotificationPriority::Normal,
+            title: title.into(),
+            message: None,
+            duration: 4.0,
+            sound: None,
+        }
+    }
+
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    pub fn with_priority(mut self, priority: NotificationPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_duration(mut self, duration: f32) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    pub fn with_sound(mut self, sound: impl Into<String>) -> Self {
+        self.sound = Some(sound.into());
+        self
+    }
+
+    fn same_as(&self, other: &Notification) -> bool {
+        self.kind == other.kind && self.title == other.title && self.message == other.message
+    }
+}
+
+/// A notification on screen
+#[derive(Debug, Clone)]
+pub struct ShownNotification {
+    pub notification: Notification,
+    /// Seconds left on screen
+    pub remaining: f32,
+}
+
+/// Notifications waiting to be shown and those on screen
+#[derive(Resource, Debug, Default)]
+pub struct Notifications {
+    /// Highest priority first, in arrival order within a priority
+    pending: VecDeque<Notification>,
+    visible: Vec<ShownNotification>,
+    /// Shown since the last [`Notifications::take_sounds`], for their sounds
+    just_shown: Vec<String>,
+}
+
+impl Notifications {
+    /// Queues a notification. Repeats of one already showing or waiting only restart its timer.
+    pub fn push(&mut self, notification: Notification) {
+        if let Some(shown) = self.visible.iter_mut().find(|shown| shown.notification.same_as(&notification)) {
+            shown.remaining = shown.remaining.max(notification.duration);
+            return;
+        }
+        if self.pending.iter().any(|pending| pending.same_as(&notification)) {
+            return;
+        }
+        let index = self
+            .pending
+            .iter()
+            .position(|pending| pending.priority < notification.priority)
+            .unwrap_or(self.pending.len());
+        self.pending.insert(index, notification);
+    }
+
+    pub fn visible(&self) -> &[ShownNotification] {
+        &self.visible
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Counts down the notifications on screen and brings up waiting ones in their place
+    pub fn tick(&mut self, dt: f32) {
+        for shown in &mut self.visible {
+            shown.remaining -= dt;
+        }
+        self.visible.retain(|shown| shown.remaining > 0.0);
+
+        while let Some(next) = self.pending.front() {
+            if self.visible.len() >= MAX_VISIBLE {
+                // A critical notification takes the place of the lowest priority one showing
+                let lowest = self
+                    .visible
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, shown)| shown.notification.priority)
+                    .map(|(index, shown)| (index, shown.notification.priority));
+                match lowest {
+                    Some((index, priority)) if next.priority == NotificationPriority::Critical && priority < next.priority => {
+                        self.visible.remove(index);
+                    }
+                    _ => break,
+                }
+            }
+            let notification = self.pending.pop_front().unwrap();
+            if let Some(sound) = &notification.sound {
+                self.just_shown.push(sound.clone());
+            }
+            self.visible.push(ShownNotification {
+                remaining: notification.duration,
+                notification,
+            });
+        }
+    }
+
+    /// Sounds of the notifications that came on screen since the last call
+    pub fn take_sounds(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.just_shown)
+    }
+}
+
+/// Draws notifications in the top right corner, fading each out at the end
+pub(super) fn show_notifications(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    asset_server: Res<AssetServer>,
+    mut clips: Option<ResMut<AudioClipCache>>,
+    game_settings: Option<Res<GameSettings>>,
+    mut contexts: EguiContexts,
+    mut notifications: ResMut<Notifications>,
+) {
+    notifications.tick(time.delta_seconds());
+
+    let volume = game_settings.as_ref().map_or(1.0, |settings| settings.audio.master_volume * settings.audio.sfx_volume);
+    for sound in notifications.take_sounds() {
+        commands.spawn(AudioBundle {
+            source: load_clip(clips.as_deref_mut(), &asset_server, &sound),
+            settings: PlaybackSettings::DESPAWN.with_volume(Volume::new_relative(volume)),
+        });
+    }
+
+    if notifications.visible().is_empty() {
+        return;
+    }
+    let colors = game_settings.map(|settings| settings.accessibility.hud_palette).unwrap_or_default().colors();
+
+    egui::Area::new("notifications")
+        .anchor(egui::Align2::RIGHT_TOP, [-10.0, 10.0])
+        .show(contexts.ctx_mut(), |ui| {
+            for shown in notifications.visible() {
+                let notification = &shown.notification;
+                let alpha = (shown.remaining / FADE_TIME).clamp(0.0, 1.0);
+                let accent = hud_color(notification.kind.color(&colors)).gamma_multiply(alpha);
+                egui::Frame::none()
+                    .fill(egui::Color32::from_black_alpha((200.0 * alpha) as u8))
+                    .stroke(egui::Stroke::new(2.0, accent))
+                    .inner_margin(8.0)
+                    .rounding(4.0)
+                    .show(ui, |ui| {
+                        ui.set_width(260.0);
+                        ui.label(egui::RichText::new(&notification.title).color(accent).strong());
+                        if let Some(message) = &notification.message {
+                            ui.label(egui::RichText::new(message).color(egui::Color32::WHITE.gamma_multiply(alpha)));
+                        }
+                    });
+                ui.add_space(6.0);
+            }
+        });
+}
+
+/// Controllers and the steering wheel coming and going
+pub(super) fn notify_connections(
+    wheel: Option<Res<SteeringWheelDevice>>,
+    mut connections: EventReader<GamepadConnectionEvent>,
+    mut last_wheel: Local<Option<Gamepad>>,
+    mut notifications: ResMut<Notifications>,
+) {
+    let wheel = wheel.and_then(|wheel| wheel.0);
+    for event in connections.read() {
+        // The wheel gets its own notification below
+        if Some(event.gamepad) == wheel || Some(event.gamepad) == *last_wheel {
+            continue;
+        }
+        let notification = match &event.connection {
+            GamepadConnection::Connected(info) => {
+                Notification::new(NotificationKind::Connection, tr!("notify.controller_connected")).with_message(info.name.clone())
+            }
+            GamepadConnection::Disconnected => Notification::new(NotificationKind::Connection, tr!("notify.controller_disconnected"))
+                .with_priority(NotificationPriority::High),
+        };
+        notifications.push(notification);
+    }
+
+    if wheel != *last_wheel {
+        let notification = if wheel.is_some() {
+            Notification::new(NotificationKind::Connection, tr!("notify.wheel_connected"))
+        } else {
+            Notification::new(NotificationKind::Connection, tr!("notify.wheel_disconnected"))
+                .with_message(tr!("notify.wheel_disconnected_message"))
+                .with_priority(NotificationPriority::High)
+        };
+        notifications.push(notification);
+        *last_wheel = wheel;
+    }
+}
+
+/// Engine, fuel and cargo trouble on the players' vehicles
+#[allow(clippy::too_many_arguments)]
+pub(super) fn notify_damage(
+    players: Query<(), With<PlayerId>>,
+    mut stalls: EventReader<EngineStalledEvent>,
+    mut radiator_hits: EventReader<RadiatorDamageEvent>,
+    mut out_of_fuel: EventReader<OutOfFuelEvent>,
+    mut cargo_lost: EventReader<CargoLostEvent>,
+    mut cargo_damage: EventReader<CargoDamagedEvent>,
+    mut notifications: ResMut<Notifications>,
+) {
+    let warning = |title: String, message: String| {
+        Notification::new(NotificationKind::Warning, title)
+            .with_message(message)
+            .with_priority(NotificationPriority::High)
+            .with_duration(6.0)
+    };
+
+    for stall in stalls.read().filter(|stall| players.contains(stall.vehicle)) {
+        notifications.push(match stall.reason {
+            EngineStallReason::Overheated => warning(tr!("notify.overheated"), tr!("notify.overheated_message")),
+            EngineStallReason::IntakeFlooded => {
+                warning(tr!("notify.intake_flooded"), tr!("notify.intake_flooded_message")).with_priority(NotificationPriority::Critical)
+            }
+            EngineStallReason::Lugged => warning(tr!("notify.lugged"), tr!("notify.lugged_message")),
+        });
+    }
+    for hit in radiator_hits.read() {
+        if players.contains(hit.vehicle) && hit.amount >= RADIATOR_WARNING_DAMAGE {
+            notifications.push(warning(tr!("notify.radiator_damaged"), tr!("notify.radiator_damaged_message")));
+        }
+    }
+    if out_of_fuel.read().any(|event| players.contains(event.vehicle)) {
+        notifications.push(
+            warning(tr!("notify.out_of_fuel"), tr!("notify.out_of_fuel_message")).with_priority(NotificationPriority::Critical),
+        );
+    }
+    if cargo_lost.read().any(|event| players.contains(event.vehicle)) {
+        notifications.push(warning(tr!("notify.cargo_lost"), tr!("notify.cargo_lost_message")));
+    }
+    if cargo_damage.read().any(|event| event.destroyed) {
+        notifications.push(warning(tr!("notify.cargo_destroyed"), tr!("notify.cargo_destroyed_message")));
+    }
+}
+
+/// Deliveries completing a cargo challenge
+pub(super) fn notify_challenges(mut deliveries: EventReader<CargoDeliveredEvent>, mut notifications: ResMut<Notifications>) {
+    for delivery in deliveries.read() {
+        let integrity = format!("{:.0}", delivery.integrity * 100.0);
+        let message = if delivery.intact {
+            tr!("notify.delivered_message", integrity = integrity)
+        } else {
+            tr!("notify.delivered_damaged_message", integrity = integrity)
+        };
+        notifications.push(
+            Notification::new(NotificationKind::Challenge, tr!("notify.delivered"))
+                .with_message(message)
+                .with_duration(5.0),
+        );
+    }
+}
+
+/// Hazards going off around the player
+pub(super) fn notify_hazards(mut hazards: EventReader<HazardStartedEvent>, mut notifications: ResMut<Notifications>) {
+    for hazard in hazards.read() {
+        let title = match hazard.hazard_type {
+            HazardType::Rockslide => tr!("notify.rockslide"),
+            HazardType::FallenTree => tr!("notify.fallen_tree"),
+            HazardType::FlashFlood => tr!("notify.flash_flood"),
+        };
+        notifications.push(Notification::new(NotificationKind::Warning, title).with_priority(NotificationPriority::High));
+    }
+}
+
+/// Trails turning wet or drying out, crossings closing and the players reaching points of interest
+pub(super) fn notify_trails(
+    players: Query<(), With<PlayerId>>,
+    mut condition_changes: EventReader<TrailConditionChangedEvent>,
+    mut crossings: EventReader<CrossingStatusEvent>,
+    mut points: EventReader<PoiReachedEvent>,
+    mut notifications: ResMut<Notifications>,
+) {
+    for change in condition_changes.read() {
+        let (title, message) = match change.condition {
+            TrailCondition::Dry => (tr!("notify.trails_dry"), tr!("notify.trails_dry_message")),
+            TrailCondition::Wet => (tr!("notify.trails_wet"), tr!("notify.trails_wet_message")),
+            TrailCondition::Soaked => (tr!("notify.trails_soaked"), tr!("notify.trails_soaked_message")),
+        };
+        notifications.push(Notification::new(NotificationKind::Warning, title).with_message(message));
+    }
+    for crossing in crossings.read() {
+        let notification = if crossing.closed {
+            Notification::new(NotificationKind::Warning, tr!("notify.crossing_closed")).with_priority(NotificationPriority::High)
+        } else {
+            Notification::new(NotificationKind::Discovery, tr!("notify.crossing_open"))
+        };
+        notifications.push(notification.with_message(crossing.name.clone()));
+    }
+    for poi in points.read().filter(|poi| players.contains(poi.vehicle)) {
+        notifications.push(
+            Notification::new(NotificationKind::Discovery, poi.name.clone())
+                .with_message(poi.message.clone())
+                .with_duration(5.0),
+        );
+    }
+}
+
+/// Diff lock and low range suggestions for players stuck spinning their wheels
+pub(super) fn notify_traction_hints(
+    mut hints: EventReader<TractionHintEvent>,
+    mut notifications: ResMut<Notifications>,
+) {
+    for hint in hints.read() {
+        let (title, message) = match hint.hint {
+            TractionHint::LockDiffs => (tr!("notify.hint_diff_lock"), tr!("notify.hint_diff_lock_message")),
+            TractionHint::LowRange => (tr!("notify.hint_low_range"), tr!("notify.hint_low_range_message")),
+        };
+        notifications.push(
+            Notification::new(NotificationKind::Discovery, title)
+                .with_message(message)
+                .with_priority(NotificationPriority::Low)
+                .with_duration(6.0),
+        );
+    }
+}
+
+/// Replay exports that wrote their last frame
+pub(super) fn notify_exports(mut exports: EventReader<ExportFinishedEvent>, mut notifications: ResMut<Notifications>) {
+    for export in exports.read() {
+        notifications.push(
+            Notification::new(NotificationKind::Discovery, tr!("notify.export_finished", frames = export.frames))
+                .with_message(export.directory.display().to_string()),
+        );
+    }
+}
+
+/// Messages and unlocks from level mission scripts
+pub(super) fn notify_missions(
+    mut messages: EventReader<ScriptMessageEvent>,
+    mut unlocks: EventReader<VehicleUnlockedEvent>,
+    mut notifications: ResMut<Notifications>,
+) {
+    for message in messages.read() {
+        let notification = match &message.title {
+            Some(title) => Notification::new(NotificationKind::Challenge, title.clone()).with_message(message.message.clone()),
+            None => Notification::new(NotificationKind::Challenge, message.message.clone()),
+        };
+        notifications.push(notification.with_duration(5.0));
+    }
+    for unlock in unlocks.read() {
+        notifications.push(
+            Notification::new(NotificationKind::Discovery, tr!("notify.vehicle_unlocked"))
+                .with_message(unlock.name.clone())
+                .with_priority(NotificationPriority::High),
+        );
+    }
+}
+
+/// New levels and the paints and accessories they unlock, vehicles come through [`notify_missions`]
+pub(super) fn notify_progression(
+    mut level_ups: EventReader<LevelUpEvent>,
+    mut unlocks: EventReader<ItemUnlockedEvent>,
+    mut notifications: ResMut<Notifications>,
+) {
+    for level_up in level_ups.read() {
+        notifications.push(
+            Notification::new(NotificationKind::Discovery, tr!("notify.level_up", level = level_up.level))
+                .with_priority(NotificationPriority::High),
+        );
+    }
+    for unlock in unlocks.read() {
+        let (title, name) = match &unlock.unlock {
+            Unlock::Paint(paint) => (tr!("notify.paint_unlocked"), tr!(paint.name_key())),
+            Unlock::Accessory(accessory) => (tr!("notify.accessory_unlocked"), tr!(accessory.name_key())),
+            Unlock::Part(part) => (tr!("notify.part_unlocked"), tr!(part.name_key())),
+            Unlock::Vehicle(name) => (tr!("notify.vehicle_unlocked"), name.clone()),
+        };
+        notifications.push(Notification::new(NotificationKind::Discovery, title).with_message(name));
+    }
+}
+
+pub(super) fn notify_fast_travel(
+    mut finished: EventReader<FastTravelFinishedEvent>,
+    mut notifications: ResMut<Notifications>,
+) {
+    for tow in finished.read() {
+        notifications.push(
+            Notification::new(NotificationKind::Discovery, tr!("notify.towed", name = tow.destination.clone()))
+                .with_message(tr!("notify.towed_message", fee = tow.fee)),
+        );
+    }
+}
+
+/// Cash earned and spent, tows and jobs are already announced with their destination
+pub(super) fn notify_transactions(
+    mut transactions: EventReader<TransactionEvent>,
+    mut notifications: ResMut<Notifications>,
+) {
+    let announced = |kind: &TransactionKind| matches!(kind, TransactionKind::Tow | TransactionKind::Job);
+    for transaction in transactions.read().filter(|transaction| !announced(&transaction.kind)) {
+        let (kind, title) = if transaction.kind.is_income() {
+            (NotificationKind::Challenge, tr!("notify.earned", amount = transaction.amount))
+        } else {
+            (NotificationKind::Discovery, tr!("notify.spent", amount = transaction.amount))
+        };
+        let reason = match &transaction.kind {
+            TransactionKind::Purchase(Unlock::Vehicle(name)) => name.clone(),
+            TransactionKind::Purchase(Unlock::Part(part)) => tr!(part.name_key()),
+            kind => tr!(kind.name_key()),
+        };
+        let message = tr!("notify.transaction_message", reason = reason, balance = transaction.balance);
+        notifications.push(Notification::new(kind, title).with_message(message));
+    }
+}
+
+/// Recoveries of a player's vehicle that were refused
+pub(super) fn notify_recovery(
+    players: Query<(), With<PlayerId>>,
+    mut rejected: EventReader<RecoveryRejectedEvent>,
+    mut notifications: ResMut<Notifications>,
+) {
+    for rejection in rejected.read().filter(|rejection| players.contains(rejection.vehicle)) {
+        let message = match rejection.reason {
+            RecoveryRejection::CoolingDown { seconds } => {
+                tr!(rejection.reason.reason_key(), seconds = format!("{seconds:.0}"))
+            }
+            reason => tr!(reason.reason_key()),
+        };
+        notifications.push(
+            Notification::new(NotificationKind::Warning, tr!("notify.recovery_refused"))
+                .with_message(message)
+                .with_duration(4.0),
+        );
+    }
+}
+
+/// Delivery jobs done, or failed and why
+pub(super) fn notify_jobs(mut finished: EventReader<JobFinishedEvent>, mut notifications: ResMut<Notifications>) {
+    for job in finished.read() {
+        let name = job.job.destination.clone();
+        let notification = match job.outcome {
+            JobOutcome::Delivered { payout } => Notification::new(NotificationKind::Challenge, tr!("notify.job_done"))
+                .with_message(tr!("notify.job_done_message", name = name, payout = payout)),
+            JobOutcome::Failed(failure) => Notification::new(NotificationKind::Warning, tr!("notify.job_failed"))
+                .with_message(tr!("notify.job_failed_message", name = name, reason = tr!(failure.reason_key()))),
+        };
+        notifications.push(notification);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(title: &str, priority: NotificationPriority) -> Notification {
+        Notification::new(NotificationKind::Discovery, title).with_priority(priority)
+    }
+
+    #[test]
+    fn test_higher_priority_shows_first() {
+        let mut notifications = Notifications::default();
+        for index in 0..MAX_VISIBLE {
+            notifications.push(note(&index.to_string(), NotificationPriority::Low));
+        }
+        notifications.push(note("important", NotificationPriority::High));
+        notifications.tick(0.0);
+        assert_eq!(notifications.visible()[0].notification.title, "important");
+        assert_eq!(notifications.visible().len(), MAX_VISIBLE);
+        assert_eq!(notifications.pending(), 1);
+    }
+
+    #[test]
+    fn test_critical_replaces_lowest_priority() {
+        let mut notifications = Notifications::default();
+        notifications.push(note("low", NotificationPriority::Low));
+        for index in 1..MAX_VISIBLE {
+            notifications.push(note(&index.to_string(), NotificationPriority::Normal));
+        }
+        notifications.tick(0.0);
+        notifications.push(note("critical", NotificationPriority::Critical));
+        notifications.tick(0.0);
+        let titles: Vec<_> = notifications.visible().iter().map(|shown| shown.notification.title.as_str()).collect();
+        assert!(titles.contains(&"critical") && !titles.contains(&"low"));
+    }
+
+    #[test]
+    fn test_repeats_refresh_instead_of_stacking() {
+        let mut notifications = Notifications::default();
+        notifications.push(note("overheated", NotificationPriority::High).with_duration(3.0));
+        notifications.tick(2.0);
+        notifications.push(note("overheated", NotificationPriority::High).with_duration(3.0));
+        notifications.tick(2.0);
+        assert_eq!(notifications.visible().len(), 1);
+        assert!((notifications.visible()[0].remaining - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_sounds_play_once_when_shown() {
+        let mut notifications = Notifications::default();
+        notifications.push(note("done", NotificationPriority::Normal).with_sound("sounds/challenge_complete.ogg"));
+        notifications.tick(0.0);
+        assert_eq!(notifications.take_sounds(), vec!["sounds/challenge_complete.ogg".to_string()]);
+        assert!(notifications.take_sounds().is_empty());
+    }
+}
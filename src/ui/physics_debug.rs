@@ -0,0 +1,39 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::game::DebugInfo;
+use crate::physics::PhysicsDebugSettings;
+use crate::tr;
+
+/// Toggles for each physics visualization, opened and closed with the physics debug key (F4)
+pub(super) fn physics_debug_panel(
+    mut contexts: EguiContexts,
+    mut debug_info: ResMut<DebugInfo>,
+    mut settings: ResMut<PhysicsDebugSettings>,
+) {
+    if !debug_info.show_physics_debug {
+        return;
+    }
+
+    let mut toggles = *settings;
+    let mut open = true;
+    egui::Window::new(tr!("physics_debug.title"))
+        .id(egui::Id::new("physics_debug"))
+        .open(&mut open)
+        .anchor(egui::Align2::LEFT_TOP, [10.0, 10.0])
+        .show(contexts.ctx_mut(), |ui| {
+            ui.checkbox(&mut toggles.colliders, tr!("physics_debug.colliders"));
+            ui.checkbox(&mut toggles.contacts, tr!("physics_debug.contacts"));
+            ui.checkbox(&mut toggles.suspension_rays, tr!("physics_debug.suspension"));
+            ui.checkbox(&mut toggles.center_of_mass, tr!("physics_debug.center_of_mass"));
+            ui.checkbox(&mut toggles.forces, tr!("physics_debug.forces"));
+        });
+
+    // Rapier's renderer is only reconfigured when the settings change
+    if toggles != *settings {
+        *settings = toggles;
+    }
+    if !open {
+        debug_info.show_physics_debug = false;
+    }
+}
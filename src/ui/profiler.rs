@@ -0,0 +1,42 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::game::DebugInfo;
+use crate::tr;
+
+/// Frame time per subsystem with the entities each works on, opened and closed with the profiler key (F7)
+pub(super) fn profiler_overlay(mut contexts: EguiContexts, mut debug_info: ResMut<DebugInfo>) {
+    if !debug_info.show_profiler {
+        return;
+    }
+
+    let frame_ms = debug_info.metrics.back().map_or(0.0, |metrics| metrics.frame_time_ms);
+    let mut open = true;
+    egui::Window::new(tr!("profiler.title"))
+        .id(egui::Id::new("profiler"))
+        .open(&mut open)
+        .anchor(egui::Align2::RIGHT_TOP, [-10.0, 10.0])
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::Grid::new("profiler_groups").num_columns(3).striped(true).show(ui, |ui| {
+                ui.strong(tr!("profiler.group"));
+                ui.strong(tr!("profiler.cpu_ms"));
+                ui.strong(tr!("profiler.entities"));
+                ui.end_row();
+                for profile in &debug_info.profile {
+                    ui.label(tr!(profile.group.name_key()));
+                    ui.monospace(format!("{:.2}", profile.cpu_ms));
+                    ui.monospace(profile.entities.to_string());
+                    ui.end_row();
+                }
+            });
+            ui.separator();
+            ui.label(tr!("profiler.frame", ms = format!("{frame_ms:.2}")));
+            ui.label(tr!("profiler.physics", ms = format!("{:.2}", debug_info.physics_time)));
+            ui.label(tr!("profiler.render", ms = format!("{:.2}", debug_info.render_time)));
+        });
+
+    if !open {
+        debug_info.show_profiler = false;
+    }
+}
@@ -0,0 +1,179 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use super::UiState;
+use crate::game::{
+    JackSide, PlayerId, PlayerInput, Recovered, RecoveryAction, RecoveryGear, RecoverySettings, RecoveryTool,
+    UseRecoveryToolEvent,
+};
+use crate::tr;
+
+/// Radius of the ring of buttons, in points
+const RING_RADIUS: f32 = 90.0;
+const BUTTON_SIZE: egui::Vec2 = egui::vec2(110.0, 36.0);
+
+/// What the radial menu shows: the tools, or the choices for the picked tool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(super) enum RecoveryMenuPage {
+    #[default]
+    Tools,
+    Wheels,
+    JackSide,
+}
+
+/// Offset of item `index` of `count` on a ring of `radius`, the first one at the top going clockwise
+pub fn radial_position(index: usize, count: usize, radius: f32) -> egui::Vec2 {
+    let angle = index as f32 / count.max(1) as f32 * std::f32::consts::TAU;
+    egui::vec2(angle.sin(), -angle.cos()) * radius
+}
+
+/// Label of a tool with what's left of it, or the seconds until it can be used again
+fn tool_label(gear: &RecoveryGear, tool: RecoveryTool) -> String {
+    if tool == RecoveryTool::TowStrap && gear.strapped_to.is_some() {
+        return tr!("recovery.unhook");
+    }
+    let cooldown = gear.cooldown(tool);
+    if cooldown > 0.0 {
+        tr!("recovery.cooling_down", tool = tr!(tool.name_key()), seconds = format!("{cooldown:.0}"))
+    } else {
+        tr!("recovery.count", tool = tr!(tool.name_key()), count = gear.count(tool))
+    }
+}
+
+/// Radial menu of player one's recovery gear, opened with the recovery menu binding
+pub(super) fn recovery_menu(
+    mut contexts: EguiContexts,
+    mut ui_state: ResMut<UiState>,
+    mut page: Local<RecoveryMenuPage>,
+    players: Query<(Entity, &PlayerId, &PlayerInput, &RecoveryGear)>,
+    mut requests: EventWriter<UseRecoveryToolEvent>,
+) {
+    let Some((vehicle, _, input, gear)) = players.iter().find(|(_, player, ..)| player.0 == 0) else {
+        ui_state.show_recovery = false;
+        return;
+    };
+    if input.recovery_menu {
+        ui_state.show_recovery = !ui_state.show_recovery;
+        *page = RecoveryMenuPage::Tools;
+    }
+    if !ui_state.show_recovery {
+        return;
+    }
+
+    let mut action = None;
+    let mut next_page = None;
+    let mut close = false;
+    egui::Area::new("recovery_menu")
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(contexts.ctx_mut(), |ui| {
+            let size = egui::Vec2::splat((RING_RADIUS + BUTTON_SIZE.x) * 2.0);
+            let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+            let center = rect.center();
+            ui.painter().circle_filled(center, RING_RADIUS + BUTTON_SIZE.y, egui::Color32::from_black_alpha(160));
+            let button_at = |ui: &mut egui::Ui, offset: egui::Vec2, label: String, enabled: bool| {
+                let button_rect = egui::Rect::from_center_size(center + offset, BUTTON_SIZE);
+                ui.allocate_ui_at_rect(button_rect, |ui| {
+                    ui.add_enabled(enabled, egui::Button::new(label).min_size(BUTTON_SIZE))
+                })
+                .inner
+                .clicked()
+            };
+
+            match *page {
+                RecoveryMenuPage::Tools => {
+                    let tools = RecoveryTool::ALL;
+                    for (index, tool) in tools.into_iter().enumerate() {
+                        let representative = match tool {
+                            RecoveryTool::TractionBoards => RecoveryAction::TractionBoard { wheel: 0 },
+                            RecoveryTool::HiLiftJack => RecoveryAction::Jack { side: JackSide::Left },
+                            RecoveryTool::TowStrap => RecoveryAction::TowStrap,
+                        };
+                        let offset = radial_position(index, tools.len(), RING_RADIUS);
+                        if button_at(ui, offset, tool_label(gear, tool), gear.can_use(representative)) {
+                            match tool {
+                                RecoveryTool::TractionBoards => next_page = Some(RecoveryMenuPage::Wheels),
+                                RecoveryTool::HiLiftJack => next_page = Some(RecoveryMenuPage::JackSide),
+                                RecoveryTool::TowStrap => action = Some(RecoveryAction::TowStrap),
+                            }
+                        }
+                    }
+                }
+                // Laid out like the wheels seen from above with the front at the top
+                RecoveryMenuPage::Wheels => {
+                    for (slot, wheel, key) in [
+                        (1, 1, "recovery.wheel_fr"),
+                        (3, 3, "recovery.wheel_rr"),
+                        (5, 2, "recovery.wheel_rl"),
+                        (7, 0, "recovery.wheel_fl"),
+                    ] {
+                        if button_at(ui, radial_position(slot, 8, RING_RADIUS), tr!(key), true) {
+                            action = Some(RecoveryAction::TractionBoard { wheel });
+                        }
+                    }
+                }
+                RecoveryMenuPage::JackSide => {
+                    if button_at(ui, radial_position(6, 8, RING_RADIUS), tr!("recovery.jack_left"), true) {
+                        action = Some(RecoveryAction::Jack { side: JackSide::Left });
+                    }
+                    if button_at(ui, radial_position(2, 8, RING_RADIUS), tr!("recovery.jack_right"), true) {
+                        action = Some(RecoveryAction::Jack { side: JackSide::Right });
+                    }
+                }
+            }
+
+            let back = if *page == RecoveryMenuPage::Tools { tr!("recovery.close") } else { tr!("recovery.back") };
+            if button_at(ui, egui::Vec2::ZERO, back, true) {
+                if *page == RecoveryMenuPage::Tools {
+                    close = true;
+                } else {
+                    next_page = Some(RecoveryMenuPage::Tools);
+                }
+            }
+        });
+
+    if let Some(action) = action {
+        requests.send(UseRecoveryToolEvent { vehicle, action });
+        close = true;
+    }
+    if close {
+        ui_state.show_recovery = false;
+        *page = RecoveryMenuPage::Tools;
+    } else if let Some(next_page) = next_page {
+        *page = next_page;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_radial_positions_start_at_the_top_and_go_clockwise() {
+        let top = radial_position(0, 4, 10.0);
+        assert!(top.x.abs() < 1e-4 && (top.y + 10.0).abs() < 1e-4);
+        let right = radial_position(1, 4, 10.0);
+        assert!((right.x - 10.0).abs() < 1e-4 && right.y.abs() < 1e-4);
+    }
+}
+
+/// Fades player one's view back in after their vehicle is recovered, over the time it takes to settle
+pub(super) fn recovery_fade_overlay(
+    mut contexts: EguiContexts,
+    time: Res<Time>,
+    settings: Option<Res<RecoverySettings>>,
+    players: Query<(&PlayerId, &Recovered)>,
+) {
+    let Some(settings) = settings else {
+        return;
+    };
+    let Some((_, recovered)) = players.iter().find(|(player, _)| player.0 == 0) else {
+        return;
+    };
+    let alpha = 1.0 - recovered.fade(&settings, time.elapsed_seconds());
+    if alpha <= 0.0 {
+        return;
+    }
+    let ctx = contexts.ctx_mut();
+    let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("recovery_fade")));
+    painter.rect_filled(ctx.screen_rect(), 0.0, egui::Color32::from_black_alpha((alpha * 255.0) as u8));
+}
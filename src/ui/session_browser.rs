@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use super::UiState;
+use crate::game::{MatchmakingRequest, SessionBrowser};
+use crate::tr;
+
+/// Session the player is about to host
+pub(super) struct CreateSessionForm {
+    name: String,
+    level: String,
+    max_players: u32,
+}
+
+impl Default for CreateSessionForm {
+    fn default() -> Self {
+        Self { name: String::new(), level: "default".to_string(), max_players: 4 }
+    }
+}
+
+/// Lists online sessions to join and hosts new ones, opened from the pause menu
+pub(super) fn session_browser(
+    mut contexts: EguiContexts,
+    mut ui_state: ResMut<UiState>,
+    browser: Res<SessionBrowser>,
+    mut requests: EventWriter<MatchmakingRequest>,
+    mut form: Local<CreateSessionForm>,
+    mut was_open: Local<bool>,
+) {
+    if !ui_state.show_session_browser {
+        *was_open = false;
+        return;
+    }
+    // Fresh list every time the browser opens
+    if !*was_open {
+        requests.send(MatchmakingRequest::Refresh);
+        *was_open = true;
+    }
+
+    let mut open = true;
+    egui::Window::new(tr!("session.title"))
+        .id(egui::Id::new("session_browser"))
+        .open(&mut open)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(contexts.ctx_mut(), |ui| {
+            if let Some(current) = &browser.current {
+                ui.label(tr!("session.current", name = current.name.clone()));
+                ui.separator();
+            }
+
+            ui.horizontal(|ui| {
+                if ui.add_enabled(!browser.loading, egui::Button::new(tr!("session.refresh"))).clicked() {
+                    requests.send(MatchmakingRequest::Refresh);
+                }
+                if browser.loading {
+                    ui.spinner();
+                }
+            });
+            if let Some(error) = &browser.error {
+                ui.colored_label(egui::Color32::RED, tr!("session.error", error = error.clone()));
+            }
+
+            if browser.sessions.is_empty() {
+                ui.weak(tr!("session.none"));
+            }
+            egui::Grid::new("sessions").striped(true).show(ui, |ui| {
+                for session in browser.sessions.iter() {
+                    ui.label(session.name.as_str());
+                    ui.label(session.host.as_str());
+                    ui.label(session.level.as_str());
+                    ui.label(format!("{}/{}", session.players, session.max_players));
+                    let joinable = !browser.loading && !session.is_full() && browser.current.is_none();
+                    if ui.add_enabled(joinable, egui::Button::new(tr!("session.join"))).clicked() {
+                        requests.send(MatchmakingRequest::Join { id: session.id.clone() });
+                    }
+                    ui.end_row();
+                }
+            });
+
+            ui.separator();
+            ui.heading(tr!("session.create_title"));
+            ui.horizontal(|ui| {
+                ui.label(tr!("session.name"));
+                ui.text_edit_singleline(&mut form.name);
+            });
+            ui.horizontal(|ui| {
+                ui.label(tr!("session.level"));
+                ui.text_edit_singleline(&mut form.level);
+            });
+            ui.add(egui::Slider::new(&mut form.max_players, 2..=8).text(tr!("session.max_players")));
+            let creatable = !browser.loading && browser.current.is_none() && !form.name.trim().is_empty();
+            if ui.add_enabled(creatable, egui::Button::new(tr!("session.create"))).clicked() {
+                requests.send(MatchmakingRequest::Create {
+                    name: form.name.trim().to_string(),
+                    level: form.level.trim().to_string(),
+                    max_players: form.max_players,
+                });
+            }
+        });
+
+    if !open {
+        ui_state.show_session_browser = false;
+    }
+}
@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use super::{hud_color, UiTheme};
+use crate::core::GameState;
+use crate::game::{
+    DeterminismSession, GameSettings, LeaderboardStatus, Medal, SaveReplayEvent, SessionEventKind, SessionLog,
+    SessionLogSettings, SubmitScoreEvent,
+};
+use crate::tr;
+
+/// Run clock as minutes and seconds, `2:05.3`
+fn format_run_time(seconds: f32) -> String {
+    let tenths = (seconds.max(0.0) * 10.0).round() as u32;
+    format!("{}:{:04.1}", tenths / 600, (tenths % 600) as f32 / 10.0)
+}
+
+fn medal_color(medal: Medal) -> [u8; 3] {
+    match medal {
+        Medal::Gold => [232, 186, 48],
+        Medal::Silver => [192, 196, 204],
+        Medal::Bronze => [196, 122, 64],
+    }
+}
+
+/// One line of the event log
+fn event_text(kind: &SessionEventKind) -> String {
+    match kind {
+        SessionEventKind::Checkpoint(index) => tr!("summary.event.checkpoint", index = index + 1),
+        SessionEventKind::PoiReached(name) => tr!("summary.event.poi", name = name),
+        SessionEventKind::Damage(amount) => tr!("summary.event.damage", damage = format!("{:.0}", amount * 100.0)),
+        SessionEventKind::Recovery(tool) => tr!("summary.event.recovery", tool = tr!(tool.name_key())),
+        SessionEventKind::Airtime(seconds) => tr!("summary.event.airtime", seconds = format!("{seconds:.1}")),
+        SessionEventKind::Finished { position, racers } => {
+            tr!("summary.event.finished", position = position, racers = racers)
+        }
+    }
+}
+
+/// Stats, medal and event log of the run that just ended, with its replay and leaderboard options
+#[allow(clippy::too_many_arguments)]
+pub(super) fn session_summary(
+    mut contexts: EguiContexts,
+    mut log: ResMut<SessionLog>,
+    theme: Res<UiTheme>,
+    game_settings: Option<Res<GameSettings>>,
+    determinism: Option<Res<DeterminismSession>>,
+    settings: Option<Res<SessionLogSettings>>,
+    mut save_replay: EventWriter<SaveReplayEvent>,
+    mut submit_score: EventWriter<SubmitScoreEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(summary) = &log.summary else {
+        return;
+    };
+    let colors = game_settings.map(|settings| settings.accessibility.hud_palette).unwrap_or_default().colors();
+    let stats = &summary.stats;
+
+    let mut leave = None;
+    egui::Window::new(tr!("summary.title"))
+        .id(egui::Id::new("session_summary"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.heading(tr!("stats.score", score = summary.score));
+                match summary.medal {
+                    Some(medal) => ui.colored_label(hud_color(medal_color(medal)), tr!(medal.name_key())),
+                    None => ui.colored_label(hud_color(colors.inactive), tr!("summary.no_medal")),
+                };
+            });
+            ui.add_space(6.0);
+
+            egui::Grid::new("session_stats").num_columns(2).spacing([24.0, 4.0]).show(ui, |ui| {
+                let rows = [
+                    (tr!("summary.time"), format_run_time(stats.duration)),
+                    (tr!("summary.distance"), format!("{:.2} km", stats.distance / 1000.0)),
+                    (tr!("summary.top_speed"), format!("{:.0} {}", stats.top_speed * 3.6, tr!("hud.speed_unit"))),
+                    (tr!("summary.checkpoints"), stats.checkpoints.to_string()),
+                    (tr!("summary.discoveries"), stats.discoveries.to_string()),
+                    (tr!("summary.longest_jump"), format!("{:.1} s", stats.longest_airtime)),
+                    (tr!("summary.damage"), format!("{:.0} %", stats.damage * 100.0)),
+                    (tr!("summary.recoveries"), stats.recoveries.to_string()),
+                ];
+                for (name, value) in rows {
+                    ui.label(name);
+                    ui.colored_label(theme.text(), value);
+                    ui.end_row();
+                }
+                if let (Some(position), Some(racers)) = (stats.position, stats.racers) {
+                    ui.label(tr!("summary.position"));
+                    ui.colored_label(theme.accent(), format!("{position} / {racers}"));
+                    ui.end_row();
+                }
+            });
+
+            egui::CollapsingHeader::new(tr!("summary.events", count = summary.events.len()))
+                .id_source("session_events")
+                .show(ui, |ui| {
+                    egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                        for event in &summary.events {
+                            ui.horizontal(|ui| {
+                                ui.monospace(format_run_time(event.time));
+                                ui.label(event_text(&event.kind));
+                            });
+                        }
+                    });
+                });
+            ui.add_space(8.0);
+
+            ui.horizontal(|ui| {
+                let can_save = determinism.is_some() && summary.replay.as_ref().map_or(true, |saved| saved.is_err());
+                if ui.add_enabled(can_save, egui::Button::new(tr!("summary.save_replay"))).clicked() {
+                    save_replay.send(SaveReplayEvent);
+                }
+                let synced = settings.as_ref().is_some_and(|settings| settings.leaderboard_sync);
+                let can_submit = synced
+                    && matches!(summary.leaderboard, LeaderboardStatus::NotSubmitted | LeaderboardStatus::Failed(_));
+                if ui.add_enabled(can_submit, egui::Button::new(tr!("summary.submit"))).clicked() {
+                    submit_score.send(SubmitScoreEvent);
+                }
+            });
+            match &summary.replay {
+                Some(Ok(path)) => {
+                    ui.colored_label(hud_color(colors.good), tr!("summary.replay_saved", path = path.display()));
+                }
+                Some(Err(error)) => {
+                    ui.colored_label(hud_color(colors.danger), tr!("summary.replay_failed", error = error));
+                }
+                None => {}
+            }
+            match &summary.leaderboard {
+                LeaderboardStatus::NotSubmitted => {}
+                LeaderboardStatus::Submitting => {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(tr!("summary.submitting"));
+                    });
+                }
+                LeaderboardStatus::Ranked(rank) => {
+                    let text = tr!("summary.ranked", rank = rank.rank, entries = rank.entries);
+                    ui.colored_label(hud_color(colors.good), text);
+                }
+                LeaderboardStatus::Failed(error) => {
+                    ui.colored_label(hud_color(colors.danger), tr!("summary.submit_failed", error = error));
+                }
+            }
+            ui.add_space(8.0);
+
+            ui.horizontal(|ui| {
+                if ui.button(tr!("menu.restart")).clicked() {
+                    leave = Some(GameState::Playing);
+                }
+                if ui.button(tr!("menu.main_menu")).clicked() {
+                    leave = Some(GameState::MainMenu);
+                }
+            });
+        });
+
+    if let Some(state) = leave {
+        log.dismiss();
+        next_state.set(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_run_time() {
+        assert_eq!(format_run_time(0.0), "0:00.0");
+        assert_eq!(format_run_time(125.34), "2:05.3");
+        assert_eq!(format_run_time(59.97), "1:00.0");
+        assert_eq!(format_run_time(-3.0), "0:00.0");
+    }
+}
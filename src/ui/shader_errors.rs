@@ -0,0 +1,27 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::rendering::ShaderErrors;
+use crate::tr;
+
+/// Debug console listing shaders that failed to compile, open while any of them is broken
+pub(super) fn shader_error_console(mut contexts: EguiContexts, errors: Res<ShaderErrors>) {
+    if errors.is_empty() {
+        return;
+    }
+
+    egui::Window::new(tr!("shader_errors.title"))
+        .id(egui::Id::new("shader_errors"))
+        .anchor(egui::Align2::LEFT_BOTTOM, [10.0, -10.0])
+        .default_width(480.0)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(tr!("shader_errors.hint"));
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                for (label, error) in errors.list() {
+                    ui.add_space(6.0);
+                    ui.strong(label);
+                    ui.monospace(error);
+                }
+            });
+        });
+}
@@ -0,0 +1,229 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use super::{hud_color, Localization};
+use crate::assets::{AssetCategory, GameAssets};
+
+/// Colours, rounding and font every egui window is drawn with, the HUD and menus included
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct UiTheme {
+    /// File name of a font in `assets/fonts`, used ahead of egui's own fonts
+    pub font: Option<String>,
+    /// Window and panel background
+    pub panel: [u8; 3],
+    /// Opacity of windows drawn over the 3D view
+    pub panel_alpha: u8,
+    /// Buttons, sliders and gauge tracks
+    pub widget: [u8; 3],
+    /// Selections, focus and gauge needles
+    pub accent: [u8; 3],
+    pub text: [u8; 3],
+    /// Corner radius of windows in points, widgets are half as round
+    pub rounding: f32,
+    /// Size of body text in points, headings and small text scale from it
+    pub text_size: f32,
+}
+
+impl Default for UiTheme {
+    fn default() -> Self {
+        Self {
+            font: Some("Rajdhani-SemiBold.ttf".to_string()),
+            panel: [30, 27, 23],
+            panel_alpha: 230,
+            widget: [66, 58, 48],
+            accent: [226, 122, 32],
+            text: [236, 228, 214],
+            rounding: 6.0,
+            text_size: 15.0,
+        }
+    }
+}
+
+/// `rgb` moved towards white by `amount`, 0.0 to 1.0
+fn lighten(rgb: [u8; 3], amount: f32) -> [u8; 3] {
+    rgb.map(|channel| (channel as f32 + (255.0 - channel as f32) * amount).round() as u8)
+}
+
+/// `rgb` moved towards black by `amount`, 0.0 to 1.0
+fn darken(rgb: [u8; 3], amount: f32) -> [u8; 3] {
+    rgb.map(|channel| (channel as f32 * (1.0 - amount)).round() as u8)
+}
+
+impl UiTheme {
+    pub fn accent(&self) -> egui::Color32 {
+        hud_color(self.accent)
+    }
+
+    pub fn text(&self) -> egui::Color32 {
+        hud_color(self.text)
+    }
+
+    pub fn panel_fill(&self) -> egui::Color32 {
+        let [r, g, b] = self.panel;
+        egui::Color32::from_rgba_unmultiplied(r, g, b, self.panel_alpha)
+    }
+
+    /// Background of gauge tracks and text fields, darker than the panel around them
+    pub fn track(&self) -> egui::Color32 {
+        hud_color(darken(self.panel, 0.4))
+    }
+
+    /// Outlines of widgets and gauge ticks
+    pub fn stroke(&self) -> egui::Color32 {
+        hud_color(lighten(self.widget, 0.25))
+    }
+
+    pub fn widget_rounding(&self) -> egui::Rounding {
+        egui::Rounding::same(self.rounding * 0.5)
+    }
+
+    /// `base` with this theme's visuals, text sizes and spacing
+    pub fn style(&self, mut style: egui::Style) -> egui::Style {
+        let mut visuals = egui::Visuals::dark();
+        visuals.window_fill = self.panel_fill();
+        visuals.panel_fill = self.panel_fill();
+        visuals.window_rounding = egui::Rounding::same(self.rounding);
+        visuals.window_stroke = egui::Stroke::new(1.0, self.accent().linear_multiply(0.5));
+        visuals.extreme_bg_color = self.track();
+        visuals.faint_bg_color = hud_color(lighten(self.panel, 0.05));
+        visuals.hyperlink_color = self.accent();
+        visuals.selection.bg_fill = self.accent().linear_multiply(0.6);
+        visuals.selection.stroke = egui::Stroke::new(1.0, self.text());
+
+        let widgets = &mut visuals.widgets;
+        widgets.noninteractive.bg_fill = self.panel_fill();
+        widgets.noninteractive.weak_bg_fill = self.panel_fill();
+        widgets.noninteractive.bg_stroke = egui::Stroke::new(1.0, hud_color(self.widget));
+        widgets.noninteractive.fg_stroke = egui::Stroke::new(1.0, self.text());
+        // Hovering and pressing light the widget up and outline it in the accent colour
+        for (widget, lift, outline) in [
+            (&mut widgets.inactive, 0.0, egui::Stroke::NONE),
+            (&mut widgets.hovered, 0.12, egui::Stroke::new(1.0, self.accent())),
+            (&mut widgets.active, 0.25, egui::Stroke::new(1.5, self.accent())),
+            (&mut widgets.open, 0.12, egui::Stroke::new(1.0, self.accent())),
+        ] {
+            widget.bg_fill = hud_color(lighten(self.widget, lift));
+            widget.weak_bg_fill = hud_color(lighten(self.widget, lift));
+            widget.bg_stroke = outline;
+            widget.fg_stroke = egui::Stroke::new(1.5, self.text());
+        }
+        for widget in [
+            &mut widgets.noninteractive,
+            &mut widgets.inactive,
+            &mut widgets.hovered,
+            &mut widgets.active,
+            &mut widgets.open,
+        ] {
+            widget.rounding = self.widget_rounding();
+        }
+        style.visuals = visuals;
+
+        let proportional = |scale: f32| egui::FontId::proportional(self.text_size * scale);
+        style.text_styles = [
+            (egui::TextStyle::Heading, proportional(1.5)),
+            (egui::TextStyle::Body, proportional(1.0)),
+            (egui::TextStyle::Button, proportional(1.0)),
+            (egui::TextStyle::Small, proportional(0.8)),
+            (egui::TextStyle::Monospace, egui::FontId::monospace(self.text_size * 0.9)),
+        ]
+        .into();
+        style.spacing.button_padding = egui::vec2(10.0, 4.0);
+        style.spacing.item_spacing = egui::vec2(8.0, 6.0);
+        style
+    }
+}
+
+/// Restyles egui whenever the theme changes
+pub(super) fn apply_theme(mut contexts: EguiContexts, theme: Res<UiTheme>) {
+    if !theme.is_changed() {
+        return;
+    }
+    let ctx = contexts.ctx_mut();
+    ctx.set_style(theme.style((*ctx.style()).clone()));
+}
+
+/// Reads the raw file of the font `name` found by the asset registry, egui can't use Bevy's parsed fonts
+fn read_font(game_assets: &GameAssets, name: &str) -> Option<std::io::Result<Vec<u8>>> {
+    let path = game_assets.category(AssetCategory::Font).find_map(|font| {
+        let path = Path::new(&font.path);
+        path.file_name().is_some_and(|file| file == name).then(|| path.to_path_buf())
+    })?;
+    Some(std::fs::read(Path::new("assets").join(path)))
+}
+
+/// Installs the theme's font ahead of egui's default fonts, and the active language's fonts behind
+/// them so scripts those lack still render
+pub(super) fn apply_fonts(
+    mut contexts: EguiContexts,
+    theme: Res<UiTheme>,
+    localization: Res<Localization>,
+    game_assets: Option<Res<GameAssets>>,
+    mut installed: Local<Option<(Option<String>, Vec<String>)>>,
+) {
+    let wanted = (theme.font.clone(), localization.fonts.clone());
+    if installed.as_ref() == Some(&wanted) {
+        return;
+    }
+    // Fonts are found by the registry, wait for it
+    let Some(game_assets) = game_assets else {
+        return;
+    };
+
+    // Language fonts haven't been found yet, wait for them
+    let mut language_fonts = Vec::new();
+    for name in &localization.fonts {
+        let Some(font) = read_font(&game_assets, name) else {
+            return;
+        };
+        language_fonts.push((name, font));
+    }
+
+    let mut fonts = egui::FontDefinitions::default();
+    if let Some(name) = &theme.font {
+        match read_font(&game_assets, name) {
+            Some(Ok(bytes)) => {
+                fonts.font_data.insert(name.clone(), egui::FontData::from_owned(bytes));
+                fonts.families.entry(egui::FontFamily::Proportional).or_default().insert(0, name.clone());
+            }
+            Some(Err(error)) => warn!("Failed to read theme font {name}: {error}"),
+            None => info!("Theme font {name} isn't in assets/fonts, using egui's default"),
+        }
+    }
+    for (name, font) in language_fonts {
+        match font {
+            Ok(bytes) => {
+                fonts.font_data.insert(name.clone(), egui::FontData::from_owned(bytes));
+                for family in [egui::FontFamily::Proportional, egui::FontFamily::Monospace] {
+                    fonts.families.entry(family).or_default().push(name.clone());
+                }
+            }
+            Err(error) => warn!("Failed to read font {name}: {error}"),
+        }
+    }
+    contexts.ctx_mut().set_fonts(fonts);
+    *installed = Some(wanted);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_style() {
+        let theme = UiTheme::default();
+        let style = theme.style(egui::Style::default());
+        assert_eq!(style.visuals.window_fill, theme.panel_fill());
+        assert_eq!(style.visuals.window_rounding, egui::Rounding::same(6.0));
+        assert_eq!(style.visuals.widgets.inactive.rounding, egui::Rounding::same(3.0));
+        assert_eq!(style.visuals.widgets.hovered.bg_stroke.color, theme.accent());
+        assert_eq!(style.text_styles[&egui::TextStyle::Heading].size, 22.5);
+    }
+
+    #[test]
+    fn test_shades() {
+        assert_eq!(lighten([100, 0, 255], 0.5), [178, 128, 255]);
+        assert_eq!(darken([100, 0, 255], 0.5), [50, 0, 128]);
+    }
+}
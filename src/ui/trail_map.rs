@@ -0,0 +1,211 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use super::UiState;
+use crate::game::{
+    is_open, OpenHours, PlayerId, PointOfInterest, ScheduleOpen, Trail, TrailConditions, TrailCrossing, TrailDifficulty,
+    VehicleRoute, WeatherManager,
+};
+use crate::terrain::{cell_center, Drivability, DrivabilityMap, DrivabilitySettings, DRIVABILITY_CELL_SIZE};
+use crate::tr;
+
+const MAP_SIZE: egui::Vec2 = egui::vec2(360.0, 360.0);
+/// How far ahead the forecast under the map looks, in seconds
+const FORECAST_HORIZON: f32 = 60.0 * 60.0;
+/// Empty space kept around the trails, in points
+const MAP_MARGIN: f32 = 16.0;
+
+/// Map color of a difficulty rating, following trail sign colors
+pub fn difficulty_color(difficulty: TrailDifficulty) -> egui::Color32 {
+    match difficulty {
+        TrailDifficulty::Easy => egui::Color32::from_rgb(80, 180, 90),
+        TrailDifficulty::Moderate => egui::Color32::from_rgb(70, 130, 220),
+        TrailDifficulty::Difficult => egui::Color32::from_rgb(230, 200, 60),
+        TrailDifficulty::Severe => egui::Color32::from_rgb(230, 120, 40),
+        TrailDifficulty::Extreme => egui::Color32::from_rgb(210, 50, 50),
+    }
+}
+
+/// Overlay tint of a drivability cell, see-through so the trails stay readable
+pub fn drivability_color(drivability: Drivability) -> egui::Color32 {
+    match drivability {
+        Drivability::Drivable => egui::Color32::from_rgba_unmultiplied(80, 180, 90, 40),
+        Drivability::Steep => egui::Color32::from_rgba_unmultiplied(230, 170, 50, 60),
+        Drivability::Impassable => egui::Color32::from_rgba_unmultiplied(210, 50, 50, 80),
+    }
+}
+
+/// Scales world XZ positions into `rect`, north (-Z) up, keeping the aspect ratio
+fn map_projection(points: &[Vec3], rect: egui::Rect) -> impl Fn(Vec3) -> egui::Pos2 {
+    let (min, max) = points.iter().fold((Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)), |(min, max), point| {
+        (min.min(point.xz()), max.max(point.xz()))
+    });
+    let center = if points.is_empty() { Vec2::ZERO } else { (min + max) * 0.5 };
+    let extent = if points.is_empty() { 1.0 } else { (max - min).max_element().max(1.0) };
+    let scale = (rect.width().min(rect.height()) - MAP_MARGIN * 2.0) / extent;
+    let origin = rect.center();
+    move |point: Vec3| {
+        let offset = (point.xz() - center) * scale;
+        origin + egui::vec2(offset.x, offset.y)
+    }
+}
+
+/// `hour` (0.0 - 24.0) as `HH:MM`
+fn clock_time(hour: f32) -> String {
+    let minutes = (hour.rem_euclid(24.0) * 60.0).round() as u32 % (24 * 60);
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// Trail map with difficulty ratings, closed crossings and points of interest, opened from the pause menu.
+/// Points of interest closed at this hour are greyed out and listed with when they open.
+/// The drivability overlay tints the loaded terrain by where vehicles can get through and shows their routes.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn trail_map(
+    mut contexts: EguiContexts,
+    mut ui_state: ResMut<UiState>,
+    conditions: Res<TrailConditions>,
+    trails: Query<&Trail>,
+    crossings: Query<&TrailCrossing>,
+    points: Query<(&PointOfInterest, Option<&ScheduleOpen>, Option<&OpenHours>)>,
+    players: Query<(&PlayerId, &GlobalTransform)>,
+    drivability_map: Option<Res<DrivabilityMap>>,
+    drivability_settings: Option<Res<DrivabilitySettings>>,
+    routes: Query<(&GlobalTransform, &VehicleRoute)>,
+    weather: Option<Res<WeatherManager>>,
+    mut show_drivability: Local<bool>,
+) {
+    if !ui_state.show_trail_map {
+        return;
+    }
+
+    let mut open = true;
+    egui::Window::new(tr!("trail.map_title"))
+        .id(egui::Id::new("trail_map"))
+        .open(&mut open)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(tr!("trail.conditions", condition = tr!(conditions.condition.name_key())));
+            let drivability = drivability_map.as_ref().zip(drivability_settings.as_ref());
+            if drivability.is_some() {
+                ui.checkbox(&mut show_drivability, tr!("trail.show_drivability"));
+            }
+
+            let (rect, _) = ui.allocate_exact_size(MAP_SIZE, egui::Sense::hover());
+            let painter = ui.painter_at(rect);
+            painter.rect_filled(rect, 4.0, egui::Color32::from_rgb(48, 44, 36));
+
+            let all_points: Vec<Vec3> = trails.iter().flat_map(|trail| trail.0.path.iter().copied().map(Vec3::from)).collect();
+            let project = map_projection(&all_points, rect);
+
+            if let (true, Some((map, settings))) = (*show_drivability, drivability) {
+                let half = Vec2::splat(DRIVABILITY_CELL_SIZE * 0.5);
+                for (cell, kind) in map.cells(settings) {
+                    let center = cell_center(cell);
+                    let min = project(Vec3::new(center.x - half.x, 0.0, center.y - half.y));
+                    let max = project(Vec3::new(center.x + half.x, 0.0, center.y + half.y));
+                    let cell_rect = egui::Rect::from_min_max(min, max);
+                    if rect.intersects(cell_rect) {
+                        painter.rect_filled(cell_rect, 0.0, drivability_color(kind));
+                    }
+                }
+                for (transform, route) in routes.iter() {
+                    let line: Vec<egui::Pos2> = std::iter::once(transform.translation())
+                        .chain(route.waypoints.iter().skip(route.next).copied())
+                        .map(&project)
+                        .collect();
+                    let stroke = egui::Stroke::new(2.0, egui::Color32::WHITE);
+                    painter.add(egui::Shape::dashed_line(&line, stroke, 6.0, 4.0));
+                }
+            }
+
+            for trail in trails.iter() {
+                let line: Vec<egui::Pos2> = trail.0.path.iter().map(|point| project(Vec3::from(*point))).collect();
+                let color = difficulty_color(conditions.difficulty(&trail.0));
+                painter.add(egui::Shape::line(line, egui::Stroke::new(3.0, color)));
+            }
+            for crossing in crossings.iter() {
+                let position = project(Vec3::from(crossing.desc.position));
+                if crossing.closed {
+                    let stroke = egui::Stroke::new(3.0, egui::Color32::RED);
+                    painter.line_segment([position - egui::vec2(6.0, 6.0), position + egui::vec2(6.0, 6.0)], stroke);
+                    painter.line_segment([position - egui::vec2(6.0, -6.0), position + egui::vec2(6.0, -6.0)], stroke);
+                } else {
+                    painter.circle_stroke(position, 5.0, egui::Stroke::new(2.0, egui::Color32::LIGHT_BLUE));
+                }
+            }
+            for (poi, open, _) in points.iter() {
+                let color = if is_open(open) { egui::Color32::WHITE } else { egui::Color32::DARK_GRAY };
+                painter.circle_filled(project(Vec3::from(poi.desc.position)), 4.0, color);
+            }
+            for (player, transform) in players.iter() {
+                let position = project(transform.translation());
+                painter.circle_filled(position, 5.0, egui::Color32::from_rgb(255, 90, 200));
+                painter.text(
+                    position + egui::vec2(8.0, 0.0),
+                    egui::Align2::LEFT_CENTER,
+                    format!("P{}", player.0 + 1),
+                    egui::FontId::proportional(12.0),
+                    egui::Color32::WHITE,
+                );
+            }
+
+            ui.separator();
+            for trail in trails.iter() {
+                let difficulty = conditions.difficulty(&trail.0);
+                ui.horizontal(|ui| {
+                    ui.colored_label(difficulty_color(difficulty), "■");
+                    ui.label(trail.0.name.as_str());
+                    ui.label(tr!(difficulty.name_key()));
+                    if difficulty != trail.0.difficulty {
+                        ui.weak(tr!("trail.raised_by_rain", dry = tr!(trail.0.difficulty.name_key())));
+                    }
+                });
+            }
+            for crossing in crossings.iter().filter(|crossing| crossing.closed) {
+                ui.colored_label(egui::Color32::RED, tr!("trail.crossing_closed", name = crossing.desc.name.clone()));
+            }
+            for (poi, _, hours) in points.iter().filter(|(_, open, _)| !is_open(*open)) {
+                let opens = hours.map_or(String::new(), |hours| clock_time(hours.open));
+                ui.weak(tr!("trail.poi_closed", name = poi.desc.name.clone(), time = opens));
+            }
+
+            if let Some(weather) = weather.as_ref() {
+                ui.separator();
+                ui.label(tr!("trail.forecast", weather = tr!(weather.current_state().weather().name_key())));
+                let forecast = weather.forecast(FORECAST_HORIZON);
+                if forecast.is_empty() {
+                    ui.weak(tr!("trail.forecast_steady"));
+                }
+                for entry in forecast {
+                    let minutes = (entry.starts_in / 60.0).ceil() as u32;
+                    let text = tr!("trail.forecast_entry", minutes = minutes, weather = tr!(entry.weather.name_key()));
+                    if entry.weather.is_rain() {
+                        ui.colored_label(egui::Color32::LIGHT_BLUE, text);
+                    } else {
+                        ui.label(text);
+                    }
+                }
+            }
+        });
+
+    if !open {
+        ui_state.show_trail_map = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_projection_fits_points_north_up() {
+        let rect = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(100.0, 100.0));
+        let points = [Vec3::new(-50.0, 0.0, -50.0), Vec3::new(50.0, 10.0, 50.0)];
+        let project = map_projection(&points, rect);
+        let north = project(points[0]);
+        let south = project(points[1]);
+        assert!(rect.contains(north) && rect.contains(south));
+        assert!(north.y < south.y);
+        assert_eq!(project(Vec3::ZERO), rect.center());
+    }
+}
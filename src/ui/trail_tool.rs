@@ -0,0 +1,133 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, EguiContexts};
+use bevy_rapier3d::prelude::*;
+
+use super::UiState;
+use crate::game::{CarveTrailEvent, GameCamera, TrailDifficulty, TrailTool};
+use crate::terrain::TerrainLayer;
+use crate::tr;
+
+/// Farthest a trail point can be placed from the camera
+const PICK_DISTANCE: f32 = 500.0;
+
+/// Trail tool window for laying out, carving and exporting trails, opened from the pause menu
+pub(super) fn trail_tool_window(
+    mut contexts: EguiContexts,
+    mut ui_state: ResMut<UiState>,
+    mut tool: ResMut<TrailTool>,
+    mut carve_events: EventWriter<CarveTrailEvent>,
+    mut status: Local<Option<String>>,
+) {
+    if !ui_state.show_trail_tool {
+        if tool.active {
+            tool.active = false;
+        }
+        return;
+    }
+    if !tool.active {
+        tool.active = true;
+    }
+
+    let mut open = true;
+    egui::Window::new(tr!("trail_tool.title"))
+        .id(egui::Id::new("trail_tool"))
+        .open(&mut open)
+        .anchor(egui::Align2::RIGHT_TOP, [-10.0, 10.0])
+        .show(contexts.ctx_mut(), |ui| {
+            ui.weak(tr!("trail_tool.hint"));
+            ui.horizontal(|ui| {
+                ui.label(tr!("trail_tool.name"));
+                ui.text_edit_singleline(&mut tool.name);
+            });
+            egui::ComboBox::from_label(tr!("trail_tool.difficulty"))
+                .selected_text(tr!(tool.difficulty.name_key()))
+                .show_ui(ui, |ui| {
+                    for difficulty in TrailDifficulty::ALL {
+                        ui.selectable_value(&mut tool.difficulty, difficulty, tr!(difficulty.name_key()));
+                    }
+                });
+            ui.add(egui::Slider::new(&mut tool.carve.width, 2.0..=16.0).suffix(" m").text(tr!("trail_tool.width")));
+            ui.add(egui::Slider::new(&mut tool.carve.falloff, 0.5..=12.0).suffix(" m").text(tr!("trail_tool.falloff")));
+            egui::ComboBox::from_label(tr!("trail_tool.surface"))
+                .selected_text(tr!(tool.carve.surface.name_key()))
+                .show_ui(ui, |ui| {
+                    for layer in TerrainLayer::ALL {
+                        ui.selectable_value(&mut tool.carve.surface, layer, tr!(layer.name_key()));
+                    }
+                });
+
+            ui.separator();
+            ui.label(tr!("trail_tool.points", count = tool.points.len()));
+            ui.horizontal(|ui| {
+                if ui.add_enabled(tool.points.len() >= 2, egui::Button::new(tr!("trail_tool.carve"))).clicked() {
+                    carve_events.send(CarveTrailEvent);
+                    *status = Some(tr!("trail_tool.carved"));
+                }
+                if ui.add_enabled(!tool.points.is_empty(), egui::Button::new(tr!("trail_tool.undo"))).clicked() {
+                    tool.points.pop();
+                }
+                if ui.add_enabled(!tool.points.is_empty(), egui::Button::new(tr!("trail_tool.clear"))).clicked() {
+                    tool.points.clear();
+                }
+            });
+
+            ui.separator();
+            ui.weak(tr!("trail_tool.export_to", path = tool.export_path.display().to_string()));
+            if ui.add_enabled(!tool.carved.is_empty(), egui::Button::new(tr!("trail_tool.export"))).clicked() {
+                *status = Some(match tool.export() {
+                    Ok(count) => tr!("trail_tool.exported", count = count),
+                    Err(error) => error.to_string(),
+                });
+            }
+            if let Some(status) = status.as_ref() {
+                ui.weak(status.as_str());
+            }
+        });
+
+    if !open {
+        ui_state.show_trail_tool = false;
+        tool.active = false;
+    }
+}
+
+/// Left click on the ground adds a point to the trail being laid out, backspace takes the last one off
+pub(super) fn pick_trail_points(
+    mouse_buttons: Res<Input<MouseButton>>,
+    keyboard: Res<Input<KeyCode>>,
+    mut tool: ResMut<TrailTool>,
+    mut contexts: EguiContexts,
+    rapier_context: Option<Res<RapierContext>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<GameCamera>>,
+) {
+    if !tool.active {
+        return;
+    }
+    let ctx = contexts.ctx_mut();
+    if keyboard.just_pressed(KeyCode::Back) && !ctx.wants_keyboard_input() {
+        tool.points.pop();
+    }
+    if !mouse_buttons.just_pressed(MouseButton::Left) || ctx.wants_pointer_input() {
+        return;
+    }
+    let (Some(rapier_context), Ok(window)) = (rapier_context, windows.get_single()) else {
+        return;
+    };
+    let Some(cursor) = window.cursor_position() else {
+        return;
+    };
+    let active = cameras.iter().filter(|(camera, _)| camera.is_active);
+    let Some((camera, transform)) = active.max_by_key(|(camera, _)| camera.order) else {
+        return;
+    };
+    let Some(ray) = camera.viewport_to_world(transform, cursor) else {
+        return;
+    };
+
+    // Only fixed colliders, so clicking through a vehicle still finds the ground
+    let filter = QueryFilter::only_fixed();
+    if let Some((_, distance)) = rapier_context.cast_ray(ray.origin, ray.direction, PICK_DISTANCE, true, filter) {
+        tool.points.push(ray.get_point(distance));
+    }
+}
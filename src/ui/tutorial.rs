@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::game::{
+    GameSettings, GamepadLayout, KeyboardLayout, PlayerId, PlayerInputDevice, Tutorial, TutorialAction, TutorialStep,
+};
+use crate::tr;
+
+/// Name of a key as shown in prompts
+fn key_name(key: KeyCode) -> String {
+    match key {
+        KeyCode::ShiftLeft => "Left Shift".into(),
+        KeyCode::ShiftRight => "Right Shift".into(),
+        KeyCode::ControlLeft => "Left Ctrl".into(),
+        KeyCode::ControlRight => "Right Ctrl".into(),
+        KeyCode::Return => "Enter".into(),
+        key => format!("{key:?}"),
+    }
+}
+
+/// Name of a gamepad button as shown in prompts, after the common Xbox-style labels
+fn button_name(button: GamepadButtonType) -> String {
+    match button {
+        GamepadButtonType::South => "A".into(),
+        GamepadButtonType::East => "B".into(),
+        GamepadButtonType::West => "X".into(),
+        GamepadButtonType::North => "Y".into(),
+        GamepadButtonType::LeftTrigger => "LB".into(),
+        GamepadButtonType::RightTrigger => "RB".into(),
+        GamepadButtonType::LeftTrigger2 => "LT".into(),
+        GamepadButtonType::RightTrigger2 => "RT".into(),
+        GamepadButtonType::Select => "View".into(),
+        GamepadButtonType::Start => "Menu".into(),
+        GamepadButtonType::DPadUp => tr!("tutorial.dpad_up"),
+        GamepadButtonType::DPadDown => tr!("tutorial.dpad_down"),
+        GamepadButtonType::DPadLeft => tr!("tutorial.dpad_left"),
+        GamepadButtonType::DPadRight => tr!("tutorial.dpad_right"),
+        button => format!("{button:?}"),
+    }
+}
+
+/// The binding of `action` on `device`, as shown in prompts
+pub fn binding_label(action: TutorialAction, device: PlayerInputDevice, keys: &KeyboardLayout, buttons: &GamepadLayout) -> String {
+    match device {
+        PlayerInputDevice::Keyboard => key_name(match action {
+            TutorialAction::Throttle => keys.throttle,
+            TutorialAction::Brake => keys.brake,
+            TutorialAction::LowRange => keys.transfer_case,
+            TutorialAction::DiffLock => keys.diff_lock,
+            TutorialAction::Winch => keys.winch,
+            TutorialAction::Recover => keys.recover,
+        }),
+        PlayerInputDevice::Gamepad => button_name(match action {
+            TutorialAction::Throttle => buttons.throttle,
+            TutorialAction::Brake => buttons.brake,
+            TutorialAction::LowRange => buttons.transfer_case,
+            TutorialAction::DiffLock => buttons.diff_lock,
+            TutorialAction::Winch => buttons.winch,
+            TutorialAction::Recover => buttons.recover,
+        }),
+        // Everything past the pedals stays on the keyboard with a wheel
+        PlayerInputDevice::SteeringWheel => match action {
+            TutorialAction::Throttle => tr!("tutorial.throttle_pedal"),
+            TutorialAction::Brake => tr!("tutorial.brake_pedal"),
+            action => binding_label(action, PlayerInputDevice::Keyboard, keys, buttons),
+        },
+    }
+}
+
+/// Shows the current tutorial step with player one's bindings, and the skip and replay buttons
+pub fn tutorial_prompt(
+    mut contexts: EguiContexts,
+    mut tutorial: ResMut<Tutorial>,
+    game_settings: Option<Res<GameSettings>>,
+    players: Query<(&PlayerId, &PlayerInputDevice)>,
+) {
+    let device = players
+        .iter()
+        .find(|(player, _)| player.0 == 0)
+        .map_or(PlayerInputDevice::Keyboard, |(_, device)| *device);
+    let preset = game_settings.map_or_else(default, |settings| settings.accessibility.control_preset);
+    let keys = KeyboardLayout::for_preset(preset);
+
+    if let Some(step) = tutorial.step {
+        let key = binding_label(step.action(), device, &keys, &GamepadLayout::default());
+        let mut skip_step = false;
+        let mut skip = false;
+        egui::Window::new(tr!("tutorial.title", step = step.index() + 1, count = TutorialStep::ALL.len()))
+            .id(egui::Id::new("tutorial"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 16.0])
+            .show(contexts.ctx_mut(), |ui| {
+                ui.label(egui::RichText::new(tr!(step.prompt_key(), key = key)).size(18.0));
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    skip_step = ui.button(tr!("tutorial.skip_step")).clicked();
+                    skip = ui.button(tr!("tutorial.skip")).clicked();
+                });
+            });
+        if skip {
+            tutorial.skip();
+        } else if skip_step {
+            tutorial.skip_step();
+        }
+    } else if tutorial.finished {
+        let mut replay = false;
+        let mut close = false;
+        egui::Window::new(tr!("tutorial.done"))
+            .id(egui::Id::new("tutorial"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 16.0])
+            .show(contexts.ctx_mut(), |ui| {
+                ui.label(tr!("tutorial.done_body", done = tutorial.completed.len(), count = TutorialStep::ALL.len()));
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    replay = ui.button(tr!("tutorial.replay")).clicked();
+                    close = ui.button(tr!("tutorial.close")).clicked();
+                });
+            });
+        if replay {
+            tutorial.replay();
+        } else if close {
+            tutorial.finished = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::ControlPreset;
+
+    #[test]
+    fn test_prompts_follow_device_and_preset() {
+        let keys = KeyboardLayout::for_preset(ControlPreset::Standard);
+        let buttons = GamepadLayout::default();
+        assert_eq!(binding_label(TutorialAction::Throttle, PlayerInputDevice::Keyboard, &keys, &buttons), "W");
+        assert_eq!(binding_label(TutorialAction::Throttle, PlayerInputDevice::Gamepad, &keys, &buttons), "RT");
+        assert_eq!(binding_label(TutorialAction::Winch, PlayerInputDevice::Gamepad, &keys, &buttons), "Y");
+
+        let right = KeyboardLayout::for_preset(ControlPreset::OneHandedRight);
+        assert_eq!(binding_label(TutorialAction::Recover, PlayerInputDevice::Keyboard, &right, &buttons), "Enter");
+        // Wheels fall back to the keyboard past the pedals
+        assert_eq!(binding_label(TutorialAction::DiffLock, PlayerInputDevice::SteeringWheel, &keys, &buttons), "K");
+    }
+}
@@ -0,0 +1,53 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use super::UiState;
+use crate::game::{VoiceMutes, VoicePeer, VoiceSettings};
+use crate::tr;
+
+/// Voice chat page with a mute toggle per player in the session, opened from the pause menu
+pub(super) fn voice_chat_menu(
+    mut contexts: EguiContexts,
+    mut ui_state: ResMut<UiState>,
+    mut settings: ResMut<VoiceSettings>,
+    mut mutes: ResMut<VoiceMutes>,
+    peers: Query<&VoicePeer>,
+) {
+    if !ui_state.show_voice_chat {
+        return;
+    }
+
+    let mut enabled = settings.enabled;
+    let mut volume = settings.volume;
+    let mut open = true;
+    egui::Window::new(tr!("voice.title"))
+        .id(egui::Id::new("voice_chat"))
+        .open(&mut open)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(contexts.ctx_mut(), |ui| {
+            ui.checkbox(&mut enabled, tr!("voice.enabled"));
+            ui.add(egui::Slider::new(&mut volume, 0.0..=1.0).text(tr!("voice.volume")));
+
+            ui.separator();
+            let mut peers: Vec<&VoicePeer> = peers.iter().collect();
+            if peers.is_empty() {
+                ui.weak(tr!("voice.no_players"));
+            }
+            peers.sort_by_key(|peer| peer.peer);
+            for peer in peers {
+                let mut muted = mutes.is_muted(peer.peer);
+                if ui.checkbox(&mut muted, tr!("voice.mute", name = peer.name.clone())).changed() {
+                    mutes.set_muted(peer.peer, muted);
+                }
+            }
+        });
+
+    if !open {
+        ui_state.show_voice_chat = false;
+    }
+    // Only touch the settings on a real change, turning voice chat on opens the microphone
+    if settings.enabled != enabled || settings.volume != volume {
+        settings.enabled = enabled;
+        settings.volume = volume;
+    }
+}
@@ -0,0 +1,366 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+
+use super::UiTheme;
+use crate::game::needle_angle;
+
+/// Points along each arc of the speedometer
+const ARC_SEGMENTS: usize = 48;
+
+/// Pitch and roll of a body in radians, from its rotation with the front at -Z. Pitch is positive nose
+/// up and roll positive with the right side down.
+pub fn inclination(rotation: Quat) -> (f32, f32) {
+    let forward = rotation * Vec3::NEG_Z;
+    let right = rotation * Vec3::X;
+    (forward.y.clamp(-1.0, 1.0).asin(), (-right.y).clamp(-1.0, 1.0).asin())
+}
+
+/// Screen direction of a needle `angle` radians anticlockwise from straight up
+fn dial_direction(angle: f32) -> egui::Vec2 {
+    egui::vec2(-angle.sin(), -angle.cos())
+}
+
+/// Spacing of speedometer ticks, a round number giving at most 12 of them
+fn tick_step(full_scale: f32) -> f32 {
+    [5.0, 10.0, 20.0, 25.0, 50.0, 100.0]
+        .into_iter()
+        .find(|step| full_scale / step <= 12.0)
+        .unwrap_or(full_scale / 10.0)
+}
+
+/// `offset` turned `angle` radians clockwise on screen
+fn rotate(offset: egui::Vec2, angle: f32) -> egui::Vec2 {
+    let (sin, cos) = angle.sin_cos();
+    egui::vec2(offset.x * cos - offset.y * sin, offset.x * sin + offset.y * cos)
+}
+
+/// Bar gauge with text over it, segmented like a dash display
+pub struct GaugeBar<'a> {
+    theme: &'a UiTheme,
+    fraction: f32,
+    fill: Option<egui::Color32>,
+    text: Option<String>,
+}
+
+impl<'a> GaugeBar<'a> {
+    pub fn new(theme: &'a UiTheme, fraction: f32) -> Self {
+        Self { theme, fraction, fill: None, text: None }
+    }
+
+    /// Colour of the filled part, the theme's accent by default
+    pub fn fill(mut self, color: egui::Color32) -> Self {
+        self.fill = Some(color);
+        self
+    }
+
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+}
+
+impl egui::Widget for GaugeBar<'_> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let size = egui::vec2(ui.available_size_before_wrap().x.max(96.0), ui.spacing().interact_size.y);
+        let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+        if !ui.is_rect_visible(rect) {
+            return response;
+        }
+
+        let theme = self.theme;
+        let painter = ui.painter();
+        let rounding = theme.widget_rounding();
+        painter.rect(rect, rounding, theme.track(), egui::Stroke::new(1.0, theme.stroke()));
+        let fraction = self.fraction.clamp(0.0, 1.0);
+        if fraction > 0.0 {
+            let inner = rect.shrink(2.0);
+            let filled = egui::Rect::from_min_size(inner.min, egui::vec2(inner.width() * fraction, inner.height()));
+            painter.rect_filled(filled, rounding, self.fill.unwrap_or_else(|| theme.accent()));
+        }
+        for notch in 1..10 {
+            let x = rect.left() + rect.width() * notch as f32 / 10.0;
+            let notch = [egui::pos2(x, rect.bottom() - rect.height() * 0.3), egui::pos2(x, rect.bottom())];
+            painter.line_segment(notch, egui::Stroke::new(1.0, theme.track()));
+        }
+        if let Some(text) = self.text {
+            let font = egui::TextStyle::Button.resolve(ui.style());
+            let position = rect.left_center() + egui::vec2(6.0, 0.0);
+            painter.text(position, egui::Align2::LEFT_CENTER, text, font, theme.text());
+        }
+        response
+    }
+}
+
+/// Round speedometer sweeping like the cockpit gauges, with the speed printed under the needle
+pub struct Speedometer<'a> {
+    theme: &'a UiTheme,
+    speed: f32,
+    full_scale: f32,
+    redline: Option<(f32, egui::Color32)>,
+    size: f32,
+    unit: String,
+}
+
+impl<'a> Speedometer<'a> {
+    /// Dial reading `speed` out of `full_scale`, in the unit shown under the readout
+    pub fn new(theme: &'a UiTheme, speed: f32, full_scale: f32) -> Self {
+        Self { theme, speed, full_scale, redline: None, size: 120.0, unit: String::new() }
+    }
+
+    /// Width of the dial in points
+    pub fn size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Marks the scale from `fraction` of full scale up in `color`, the needle arc turns that colour past it
+    pub fn redline(mut self, fraction: f32, color: egui::Color32) -> Self {
+        self.redline = Some((fraction, color));
+        self
+    }
+
+    pub fn unit(mut self, unit: impl Into<String>) -> Self {
+        self.unit = unit.into();
+        self
+    }
+}
+
+impl egui::Widget for Speedometer<'_> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let (rect, response) = ui.allocate_exact_size(egui::vec2(self.size, self.size * 0.9), egui::Sense::hover());
+        if !ui.is_rect_visible(rect) {
+            return response;
+        }
+
+        let theme = self.theme;
+        let painter = ui.painter();
+        let full_scale = self.full_scale.max(1.0);
+        let center = rect.center_top() + egui::vec2(0.0, self.size * 0.5);
+        let radius = self.size * 0.5 - 4.0;
+        let track_width = self.size * 0.06;
+        let track_radius = radius - track_width * 0.5;
+        let arc = |from: f32, to: f32, radius: f32| -> Vec<egui::Pos2> {
+            (0..=ARC_SEGMENTS)
+                .map(|i| from + (to - from) * i as f32 / ARC_SEGMENTS as f32)
+                .map(|value| center + dial_direction(needle_angle(value, full_scale)) * radius)
+                .collect()
+        };
+
+        let reading = self.speed.abs().min(full_scale);
+        let redlined = self.redline.filter(|(fraction, _)| reading >= fraction * full_scale);
+        let fill = redlined.map_or(theme.accent(), |(_, color)| color);
+        let track = egui::Stroke::new(track_width, theme.track());
+        painter.add(egui::Shape::line(arc(0.0, full_scale, track_radius), track));
+        painter.add(egui::Shape::line(arc(0.0, reading, track_radius), egui::Stroke::new(track_width, fill)));
+        if let Some((fraction, color)) = self.redline {
+            let line = arc(fraction * full_scale, full_scale, radius - track_width - 2.0);
+            painter.add(egui::Shape::line(line, egui::Stroke::new(2.0, color)));
+        }
+
+        // Ticks on every step, numbered on every other one
+        let step = tick_step(full_scale);
+        let label_font = egui::FontId::proportional(self.size * 0.08);
+        for tick in 0..=(full_scale / step + 1e-3) as u32 {
+            let value = tick as f32 * step;
+            let direction = dial_direction(needle_angle(value, full_scale));
+            let major = tick % 2 == 0;
+            let outer = radius - track_width - 3.0;
+            let inner = outer - if major { 7.0 } else { 4.0 };
+            let stroke = egui::Stroke::new(if major { 1.5 } else { 1.0 }, theme.stroke());
+            painter.line_segment([center + direction * inner, center + direction * outer], stroke);
+            if major {
+                let position = center + direction * (inner - self.size * 0.07);
+                let label = format!("{value:.0}");
+                painter.text(position, egui::Align2::CENTER_CENTER, label, label_font.clone(), theme.text());
+            }
+        }
+
+        let needle = dial_direction(needle_angle(reading, full_scale));
+        let needle_stroke = egui::Stroke::new(2.5, theme.accent());
+        let tip = center + needle * (radius - track_width - 2.0);
+        painter.line_segment([center - needle * radius * 0.12, tip], needle_stroke);
+        painter.circle(center, self.size * 0.05, theme.track(), egui::Stroke::new(1.5, theme.accent()));
+
+        let readout = egui::FontId::proportional(self.size * 0.2);
+        painter.text(
+            center + egui::vec2(0.0, radius * 0.45),
+            egui::Align2::CENTER_CENTER,
+            format!("{:.0}", self.speed.abs()),
+            readout,
+            theme.text(),
+        );
+        if !self.unit.is_empty() {
+            let unit_font = egui::FontId::proportional(self.size * 0.09);
+            let position = center + egui::vec2(0.0, radius * 0.72);
+            painter.text(position, egui::Align2::CENTER_CENTER, self.unit, unit_font, theme.stroke());
+        }
+        response
+    }
+}
+
+/// Outline of the vehicle drawn in an inclinometer dial, boxes and wheels in units of the dial's radius
+struct VehicleGlyph {
+    boxes: &'static [([f32; 2], [f32; 2])],
+    wheels: &'static [([f32; 2], f32)],
+}
+
+/// Seen from the left with the front to the right
+const SIDE_GLYPH: VehicleGlyph = VehicleGlyph {
+    boxes: &[([-0.62, -0.22], [0.62, 0.08]), ([-0.25, -0.48], [0.3, -0.22])],
+    wheels: &[([-0.38, 0.14], 0.17), ([0.38, 0.14], 0.17)],
+};
+
+/// Seen from behind
+const REAR_GLYPH: VehicleGlyph = VehicleGlyph {
+    boxes: &[
+        ([-0.48, -0.25], [0.48, 0.08]),
+        ([-0.32, -0.52], [0.32, -0.25]),
+        ([-0.58, 0.02], [-0.3, 0.34]),
+        ([0.3, 0.02], [0.58, 0.34]),
+    ],
+    wheels: &[],
+};
+
+/// Side and rear views of the vehicle tilted by its pitch and roll against a level line
+pub struct Inclinometer<'a> {
+    theme: &'a UiTheme,
+    pitch: f32,
+    roll: f32,
+    warning: Option<(f32, egui::Color32)>,
+    danger: Option<(f32, egui::Color32)>,
+    labels: [String; 2],
+    size: f32,
+}
+
+impl<'a> Inclinometer<'a> {
+    /// Pitch and roll in radians, as [`inclination`] gives them
+    pub fn new(theme: &'a UiTheme, pitch: f32, roll: f32) -> Self {
+        Self { theme, pitch, roll, warning: None, danger: None, labels: Default::default(), size: 64.0 }
+    }
+
+    /// Angle in radians past which a view turns `color`
+    pub fn warning(mut self, angle: f32, color: egui::Color32) -> Self {
+        self.warning = Some((angle, color));
+        self
+    }
+
+    /// Angle in radians past which a view turns `color`, over the warning
+    pub fn danger(mut self, angle: f32, color: egui::Color32) -> Self {
+        self.danger = Some((angle, color));
+        self
+    }
+
+    /// Captions under the side and rear views
+    pub fn labels(mut self, pitch: impl Into<String>, roll: impl Into<String>) -> Self {
+        self.labels = [pitch.into(), roll.into()];
+        self
+    }
+
+    /// Diameter of each view in points
+    pub fn size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    fn color(&self, angle: f32) -> egui::Color32 {
+        [self.danger, self.warning]
+            .into_iter()
+            .flatten()
+            .find(|(limit, _)| angle.abs() >= *limit)
+            .map_or(self.theme.text(), |(_, color)| color)
+    }
+
+    /// One view, `angle` is the reading and `tilt` how far the glyph turns clockwise on screen for it
+    fn view(
+        &self,
+        painter: &egui::Painter,
+        center: egui::Pos2,
+        glyph: &VehicleGlyph,
+        angle: f32,
+        tilt: f32,
+        label: &str,
+    ) {
+        let theme = self.theme;
+        let radius = self.size * 0.5;
+        let color = self.color(angle);
+        painter.circle(center, radius, theme.track(), egui::Stroke::new(1.0, theme.stroke()));
+        let level = [center - egui::vec2(radius, 0.0), center + egui::vec2(radius, 0.0)];
+        painter.line_segment(level, egui::Stroke::new(1.0, theme.stroke()));
+        if let Some((limit, warning)) = self.warning {
+            // Marks on the rim where the level line would be at the warning angle, either way
+            let stroke = egui::Stroke::new(1.5, warning);
+            for end in [-1.0, 1.0] {
+                for tilt in [-limit, limit] {
+                    let direction = rotate(egui::vec2(end, 0.0), tilt);
+                    painter.line_segment([center + direction * radius * 0.82, center + direction * radius], stroke);
+                }
+            }
+        }
+
+        let point = |[x, y]: [f32; 2]| center + rotate(egui::vec2(x, y) * radius, tilt);
+        for &(min, max) in glyph.boxes {
+            let corners = [min, [max[0], min[1]], max, [min[0], max[1]]].map(point).to_vec();
+            painter.add(egui::Shape::convex_polygon(corners, color, egui::Stroke::NONE));
+        }
+        for &(wheel, size) in glyph.wheels {
+            painter.circle(point(wheel), size * radius, theme.track(), egui::Stroke::new(1.5, color));
+        }
+
+        let caption = format!("{label} {:.0}°", angle.to_degrees());
+        let font = egui::FontId::proportional(self.size * 0.18);
+        painter.text(center + egui::vec2(0.0, radius + 2.0), egui::Align2::CENTER_TOP, caption, font, color);
+    }
+}
+
+impl egui::Widget for Inclinometer<'_> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let spacing = ui.spacing().item_spacing.x;
+        let size = egui::vec2(self.size * 2.0 + spacing, self.size * 1.3);
+        let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+        if !ui.is_rect_visible(rect) {
+            return response;
+        }
+
+        let painter = ui.painter();
+        let radius = self.size * 0.5;
+        let side = rect.left_top() + egui::vec2(radius, radius);
+        let rear = side + egui::vec2(self.size + spacing, 0.0);
+        // Nose up turns the side view anticlockwise, the right side down turns the rear view clockwise
+        self.view(painter, side, &SIDE_GLYPH, self.pitch, -self.pitch, &self.labels[0]);
+        self.view(painter, rear, &REAR_GLYPH, self.roll, self.roll, &self.labels[1]);
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inclination() {
+        let (pitch, roll) = inclination(Quat::from_rotation_x(0.3));
+        assert!((pitch - 0.3).abs() < 1e-5 && roll.abs() < 1e-5);
+
+        // Turning about Z clockwise seen from behind drops the right side
+        let (pitch, roll) = inclination(Quat::from_rotation_z(-0.2));
+        assert!(pitch.abs() < 1e-5 && (roll - 0.2).abs() < 1e-5);
+
+        // Heading doesn't matter
+        let (pitch, roll) = inclination(Quat::from_rotation_y(1.2) * Quat::from_rotation_x(-0.4));
+        assert!((pitch + 0.4).abs() < 1e-5 && roll.abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_dial_layout() {
+        assert_eq!(tick_step(180.0), 20.0);
+        assert_eq!(tick_step(60.0), 5.0);
+        assert_eq!(tick_step(1000.0), 100.0);
+
+        // Zero sits down and left of the hub, full scale mirrors it on the right
+        let zero = dial_direction(needle_angle(0.0, 180.0));
+        let full = dial_direction(needle_angle(180.0, 180.0));
+        assert!(zero.x < 0.0 && zero.y > 0.0);
+        assert!((zero.x + full.x).abs() < 1e-5 && (zero.y - full.y).abs() < 1e-5);
+        assert!(dial_direction(needle_angle(90.0, 180.0)).y < -0.99);
+    }
+}
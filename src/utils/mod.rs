@@ -1,7 +1,11 @@
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 use crate::physics::Terrain;
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+
+mod pool;
+
+pub use pool::{warm_pool, EntityPool, PoolMetrics};
 
 pub fn create_terrain_mesh(
     width: usize,
@@ -47,9 +51,10 @@ pub fn create_terrain_mesh(
     mesh
 }
 
-pub fn generate_height_map(width: usize, depth: usize, _seed: u32) -> Vec<f32> {
+pub fn generate_height_map(width: usize, depth: usize, seed: u32) -> Vec<f32> {
     let mut heights = vec![0.0; width * depth];
-    let mut rng = rand::thread_rng();
+    // Seeded so the same seed always gives the same terrain
+    let mut rng = rand::rngs::StdRng::seed_from_u64(seed as u64);
 
     // Simple random terrain generation
     for x in 0..width {
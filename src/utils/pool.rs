@@ -0,0 +1,177 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+
+/// How a pool has been used, for tuning warm-up counts
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PoolMetrics {
+    /// Entities spawned, on warm-up or because the pool ran dry
+    pub spawned: usize,
+    /// Acquires served by an idle entity
+    pub reused: usize,
+    pub released: usize,
+    /// Released while the pool was full, left for the caller to despawn
+    pub overflowed: usize,
+    pub active: usize,
+    pub peak_active: usize,
+}
+
+impl PoolMetrics {
+    /// Share of acquires that didn't have to spawn
+    pub fn reuse_rate(&self) -> f32 {
+        let acquired = self.reused + self.spawned;
+        if acquired == 0 {
+            1.0
+        } else {
+            self.reused as f32 / acquired as f32
+        }
+    }
+}
+
+impl fmt::Display for PoolMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} active (peak {}), {} spawned, {:.0}% reused",
+            self.active,
+            self.peak_active,
+            self.spawned,
+            self.reuse_rate() * 100.0
+        )
+    }
+}
+
+/// Recycled entities of one kind, tagged with the marker component `T`. Reusing an entity for the
+/// same kind of thing keeps it in the same archetype, rather than moving tables on every spawn and
+/// despawn during a burst of impacts.
+///
+/// The pool only tracks ids, callers insert their components on acquire and take off whatever must
+/// stop (an audio sink, child meshes) before they release.
+#[derive(Resource, Debug)]
+pub struct EntityPool<T: Component + Default> {
+    idle: Vec<Entity>,
+    /// Idle entities spawned up front by [`warm_pool`]
+    pub warm_up: usize,
+    /// Idle entities kept, releases beyond this are despawned by the caller
+    pub max_idle: usize,
+    metrics: PoolMetrics,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Component + Default> Default for EntityPool<T> {
+    fn default() -> Self {
+        Self::new(0, 64)
+    }
+}
+
+impl<T: Component + Default> EntityPool<T> {
+    pub fn new(warm_up: usize, max_idle: usize) -> Self {
+        Self {
+            idle: Vec::with_capacity(warm_up),
+            warm_up,
+            max_idle: max_idle.max(warm_up),
+            metrics: PoolMetrics::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Takes an idle entity, or spawns one when there's none left
+    pub fn acquire(&mut self, commands: &mut Commands) -> Entity {
+        let entity = match self.idle.pop() {
+            Some(entity) => {
+                self.metrics.reused += 1;
+                entity
+            }
+            None => {
+                self.metrics.spawned += 1;
+                commands.spawn(T::default()).id()
+            }
+        };
+        self.metrics.active += 1;
+        self.metrics.peak_active = self.metrics.peak_active.max(self.metrics.active);
+        entity
+    }
+
+    /// Hands `entity` back, false when the pool is full and the caller should despawn it instead
+    pub fn release(&mut self, entity: Entity) -> bool {
+        // Already back, despawning it now would leave a dead id in the pool
+        if self.idle.contains(&entity) {
+            return true;
+        }
+        self.metrics.active = self.metrics.active.saturating_sub(1);
+        if self.idle.len() >= self.max_idle {
+            self.metrics.overflowed += 1;
+            return false;
+        }
+        self.metrics.released += 1;
+        self.idle.push(entity);
+        true
+    }
+
+    /// Spawns idle entities until there are `count`
+    pub fn warm(&mut self, commands: &mut Commands, count: usize) {
+        let count = count.min(self.max_idle);
+        while self.idle.len() < count {
+            self.metrics.spawned += 1;
+            self.idle.push(commands.spawn(T::default()).id());
+        }
+    }
+
+    /// Idle entities ready to be acquired
+    pub fn available(&self) -> usize {
+        self.idle.len()
+    }
+
+    pub fn metrics(&self) -> &PoolMetrics {
+        &self.metrics
+    }
+}
+
+/// Spawns a pool's warm-up entities, run at startup or on level load
+pub fn warm_pool<T: Component + Default>(mut commands: Commands, mut pool: ResMut<EntityPool<T>>) {
+    let warm_up = pool.warm_up;
+    pool.warm(&mut commands, warm_up);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::CommandQueue;
+
+    use super::*;
+
+    #[derive(Component, Default)]
+    struct Spark;
+
+    #[test]
+    fn test_pool_reuses_released_entities() {
+        let mut world = World::new();
+        let mut queue = CommandQueue::default();
+        let mut pool = EntityPool::<Spark>::new(2, 3);
+
+        let mut commands = Commands::new(&mut queue, &world);
+        pool.warm(&mut commands, pool.warm_up);
+        let first = pool.acquire(&mut commands);
+        let second = pool.acquire(&mut commands);
+        let third = pool.acquire(&mut commands);
+        queue.apply(&mut world);
+        assert_eq!(world.query::<&Spark>().iter(&world).count(), 3);
+        assert_eq!(pool.metrics().reused, 2);
+        assert_eq!(pool.metrics().peak_active, 3);
+
+        assert!(pool.release(first));
+        assert!(pool.release(first));
+        assert!(pool.release(second));
+        assert_eq!((pool.available(), pool.metrics().active), (2, 1));
+        let mut commands = Commands::new(&mut queue, &world);
+        assert_eq!(pool.acquire(&mut commands), second);
+        queue.apply(&mut world);
+        assert_eq!(world.query::<&Spark>().iter(&world).count(), 3);
+
+        // Past the idle limit the caller despawns
+        let mut pool = EntityPool::<Spark>::new(0, 1);
+        assert!(pool.release(third));
+        assert!(!pool.release(first));
+        assert_eq!(pool.metrics().overflowed, 1);
+    }
+}
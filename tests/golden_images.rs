@@ -0,0 +1,38 @@
+//! Golden image tests, run with `cargo test --features golden-images --test golden_images`.
+//!
+//! Needs a GPU adapter. Each post-process and particle test scene is rendered offscreen and
+//! compared with `tests/golden/<scene>.png`.
+//!
+//! - `SANDK_GOLDEN_SCENE` runs only the scene with that name
+//! - `SANDK_GOLDEN_UPDATE` records the golden images, for new scenes and after an intended change
+//! - `SANDK_GOLDEN_DELTA_E` and `SANDK_GOLDEN_MISMATCH` loosen or tighten the tolerance
+//!
+//! Renders and diff images of failing scenes are saved under `target/golden-failures`.
+//! A scene without a committed golden image fails rather than recording one.
+#![cfg(feature = "golden-images")]
+
+use sandk_offroad::game::{check_golden_scene, golden_scenes, GoldenConfig};
+
+#[test]
+fn post_process_matches_golden_images() {
+    let config = GoldenConfig::from_env();
+    let only = std::env::var("SANDK_GOLDEN_SCENE").ok();
+    let scenes: Vec<_> = golden_scenes()
+        .into_iter()
+        .filter(|scene| only.as_deref().map_or(true, |name| name == scene.name))
+        .collect();
+    assert!(!scenes.is_empty(), "No golden scene named {}", only.unwrap_or_default());
+
+    // Every scene runs so one regression doesn't hide another
+    let mut failures = Vec::new();
+    for scene in &scenes {
+        match check_golden_scene(scene, &config) {
+            Ok(report) if report.updated => println!("{}: golden image recorded", report.scene),
+            Ok(report) => {
+                println!("{}: {} pixels differ, max ΔE {:.1}", report.scene, report.mismatched, report.max_delta_e)
+            }
+            Err(error) => failures.push(error.to_string()),
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
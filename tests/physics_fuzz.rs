@@ -0,0 +1,46 @@
+//! Physics stress test, run with `cargo test --features physics-fuzz --test physics_fuzz`.
+//!
+//! - `SANDK_FUZZ_CASES` sets the number of random cases (default 8)
+//! - `SANDK_FUZZ_SEED` reruns the one case a failure reported
+//! - `SANDK_FUZZ_RECORDING` replays a saved failure or a determinism mode recording
+//! - `SANDK_FUZZ_TICKS` and `SANDK_FUZZ_TIMESCALE` change the length and timestep of each case
+//!
+//! Failing inputs are saved under `target/fuzz-failures`.
+#![cfg(feature = "physics-fuzz")]
+
+use std::path::Path;
+
+use sandk_offroad::game::{fuzz_seeds, parse_seed, random_inputs, run_fuzz_case, FuzzConfig, InputRecording};
+
+const BASE_SEED: u64 = 0x5a4e_4b5f_f022_0001;
+
+#[test]
+fn vehicle_physics_survives_extreme_inputs() {
+    let config = FuzzConfig::from_env();
+    let recordings: Vec<InputRecording> = if let Ok(path) = std::env::var("SANDK_FUZZ_RECORDING") {
+        let json = std::fs::read_to_string(&path).unwrap_or_else(|error| panic!("Could not read {path}: {error}"));
+        vec![InputRecording::from_json(&json).unwrap_or_else(|error| panic!("{path}: {error}"))]
+    } else {
+        let seeds = match std::env::var("SANDK_FUZZ_SEED") {
+            Ok(seed) => vec![parse_seed(&seed).unwrap_or_else(|| panic!("SANDK_FUZZ_SEED {seed} is not a number"))],
+            Err(_) => {
+                let cases = std::env::var("SANDK_FUZZ_CASES").ok().and_then(|cases| cases.parse().ok()).unwrap_or(8);
+                fuzz_seeds(BASE_SEED, cases)
+            }
+        };
+        seeds.into_iter().map(|seed| random_inputs(seed, config.ticks, &config)).collect()
+    };
+
+    for recording in &recordings {
+        match run_fuzz_case(recording, &config) {
+            Ok(report) => println!("{:#x}: {} ticks, peak energy {:.0} J/kg", report.seed, report.ticks, report.max_energy),
+            Err(failure) => {
+                let saved = failure.save(&Path::new(env!("CARGO_TARGET_TMPDIR")).join("../fuzz-failures"));
+                match saved {
+                    Ok(path) => panic!("{failure}\ninputs saved to {}", path.display()),
+                    Err(error) => panic!("{failure}\ncould not save the inputs: {error}"),
+                }
+            }
+        }
+    }
+}